@@ -1,11 +1,79 @@
+use std::sync::Arc;
+
 use axum::{
+    extract::{RawQuery, State},
     response::{Html, IntoResponse},
     routing::get,
     Json, Router,
 };
+use rundler_paymaster_relay::{DashboardBalance, DashboardPolicies, DashboardTransactions};
+use serde::de::DeserializeOwned;
 use serde_json::json;
 use tokio::net::TcpListener;
 
+/// Swagger server of the paymaster relay serving the dashboard data
+const DEFAULT_UPSTREAM: &str = "http://localhost:9000";
+
+/// Client of the paymaster relay's dashboard data endpoints
+struct Upstream {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl Upstream {
+    /// GET `path` from the paymaster relay
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, String> {
+        let url = format!("{}{}", self.base_url, path);
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("paymaster relay unavailable: {}", e))?;
+        response
+            .json()
+            .await
+            .map_err(|e| format!("invalid response from {}: {}", url, e))
+    }
+}
+
+type AppState = Arc<Upstream>;
+
+async fn balance(State(upstream): State<AppState>) -> Json<DashboardBalance> {
+    Json(
+        upstream
+            .get("/dashboard/api/balance")
+            .await
+            .unwrap_or_else(DashboardBalance::unavailable),
+    )
+}
+
+async fn policies(State(upstream): State<AppState>) -> Json<DashboardPolicies> {
+    Json(
+        upstream
+            .get("/dashboard/api/policies")
+            .await
+            .unwrap_or_else(DashboardPolicies::unavailable),
+    )
+}
+
+async fn transactions(
+    State(upstream): State<AppState>,
+    RawQuery(query): RawQuery,
+) -> Json<DashboardTransactions> {
+    let path = match query {
+        Some(query) => format!("/dashboard/api/transactions?{}", query),
+        None => "/dashboard/api/transactions".to_string(),
+    };
+    Json(
+        upstream
+            .get(&path)
+            .await
+            .unwrap_or_else(DashboardTransactions::unavailable),
+    )
+}
+
 async fn dashboard_page() -> impl IntoResponse {
     let html = r#"
 <!DOCTYPE html>
@@ -73,7 +141,7 @@ async fn dashboard_page() -> impl IntoResponse {
                 </div>
                 <div class="status-item">
                     <div class="status-icon">💰</div>
-                    <div class="status-value">FUNDED</div>
+                    <div class="status-value" id="paymaster-balance">...</div>
                     <div class="status-label">Paymaster Balance</div>
                 </div>
                 <div class="status-item">
@@ -133,6 +201,16 @@ async fn dashboard_page() -> impl IntoResponse {
             document.getElementById(tabName).classList.add('active');
             event.target.classList.add('active');
         }
+
+        async function loadBalance() {
+            const balance = await (await fetch('/api/balance')).json();
+            document.getElementById('paymaster-balance').textContent =
+                balance.paymaster_balance_eth === null
+                    ? balance.status.toUpperCase()
+                    : `${balance.paymaster_balance_eth.toFixed(4)} ETH`;
+        }
+
+        document.addEventListener('DOMContentLoaded', loadBalance);
     </script>
 </body>
 </html>
@@ -153,9 +231,20 @@ async fn api_status() -> impl IntoResponse {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let upstream = Arc::new(Upstream {
+        client: reqwest::Client::new(),
+        base_url: std::env::var("SUPER_RELAY_DASHBOARD_UPSTREAM")
+            .unwrap_or_else(|_| DEFAULT_UPSTREAM.to_string()),
+    });
+    println!("🔗 数据来源: {}", upstream.base_url);
+
     let app = Router::new()
         .route("/", get(dashboard_page))
-        .route("/api/status", get(api_status));
+        .route("/api/status", get(api_status))
+        .route("/api/balance", get(balance))
+        .route("/api/policies", get(policies))
+        .route("/api/transactions", get(transactions))
+        .with_state(upstream);
 
     let listener = TcpListener::bind("0.0.0.0:8082").await?;
     println!("🌐 SuperPaymaster集成Dashboard启动成功!");
@@ -2,23 +2,39 @@
 //!
 //! 完整的端到端测试，验证SuperRelay的核心功能
 //! 包括API测试、签名测试、策略测试等
+//!
+//! 网关未运行 (或传入 `--localnet`) 时进入本地网络模式：先对Anvil执行
+//! `super-relay dev bootstrap`，再以生成的配置启动网关，裸Anvil即可直接运行
 
 use std::{
     error::Error,
+    fs::File,
     future::Future,
+    path::PathBuf,
     pin::Pin,
-    process::Command,
+    process::{Child, Command},
     time::{Duration, Instant},
 };
 
-use serde_json::{json, Value};
+use alloy_primitives::{address, bytes, Address, U256};
+use super_relay_client::{
+    ClientError, RpcErrorCode, SponsorOptions, SuperRelayClient, UserOperation, UserOperationV0_6,
+};
 use tokio::time::sleep;
 
 /// 测试配置
 const ANVIL_URL: &str = "http://localhost:8545";
 const RUNDLER_URL: &str = "http://localhost:3000";
 const DASHBOARD_URL: &str = "http://localhost:8082";
-const ENTRYPOINT_ADDRESS: &str = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
+const ENTRYPOINT_ADDRESS: Address = address!("5FF137D4b0FDCD49DcA30c7CF57E578a026d2789");
+
+/// 本地网络模式生成的配置与网关日志所在目录 (相对workspace根目录)
+const LOCALNET_DIR: &str = "target/localnet";
+/// Anvil账户#1，`super-relay dev bootstrap` 默认为其充值的Paymaster密钥
+const LOCALNET_PAYMASTER_KEY: &str =
+    "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c6a2440f60b6c4b9f78c2";
+/// 等待网关 /health 就绪的时间
+const LOCALNET_STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
 
 /// 测试结果统计
 #[derive(Debug, Default)]
@@ -29,57 +45,116 @@ struct TestStats {
 }
 
 /// 示例UserOperation
-fn create_test_user_operation() -> Value {
-    json!({
-        "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
-        "nonce": "0x0",
-        "callData": "0x",
-        "callGasLimit": "0x10000",
-        "verificationGasLimit": "0x10000",
-        "preVerificationGas": "0x5000",
-        "maxFeePerGas": "0x3b9aca00",
-        "maxPriorityFeePerGas": "0x3b9aca00",
-        "signature": "0x",
-        "initCode": "0x",
-        "paymasterAndData": "0x"
-    })
+fn create_test_user_operation() -> UserOperation {
+    UserOperationV0_6 {
+        sender: address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266"),
+        nonce: U256::ZERO,
+        init_code: bytes!(""),
+        call_data: bytes!(""),
+        call_gas_limit: U256::from(0x10000),
+        verification_gas_limit: U256::from(0x10000),
+        pre_verification_gas: U256::from(0x5000),
+        max_fee_per_gas: U256::from(0x3b9aca00),
+        max_priority_fee_per_gas: U256::from(0x3b9aca00),
+        paymaster_and_data: bytes!(""),
+        signature: bytes!(""),
+    }
+    .into()
 }
 
-/// RPC调用辅助函数
-async fn make_rpc_call(
-    url: &str,
-    method: &str,
-    params: Value,
-    id: u64,
-) -> Result<Value, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-    let payload = json!({
-        "jsonrpc": "2.0",
-        "method": method,
-        "params": params,
-        "id": id
-    });
-
-    let response = client
-        .post(url)
-        .header("Content-Type", "application/json")
-        .json(&payload)
-        .send()
-        .await?;
-
-    Ok(response.json().await?)
+type TestResult = Pin<Box<dyn Future<Output = Result<bool, Box<dyn Error>>> + Send>>;
+
+/// 本地网络模式启动的网关，drop时结束进程
+struct Localnet {
+    gateway: Child,
 }
 
-type TestResult = Pin<Box<dyn Future<Output = Result<bool, Box<dyn Error>>> + Send>>;
+impl Localnet {
+    /// 部署本地合约、写出配置并启动网关，直到 /health 就绪
+    async fn start() -> Result<Self, Box<dyn Error>> {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../..");
+        let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let built = Command::new(cargo)
+            .args(["build", "-p", "super-relay", "--bin", "super-relay"])
+            .current_dir(&root)
+            .status()?;
+        if !built.success() {
+            return Err("failed to build super-relay".into());
+        }
+        let target_dir = std::env::var("CARGO_TARGET_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| root.join("target"));
+        let super_relay = target_dir.join("debug").join("super-relay");
+
+        let config = format!("{}/config.toml", LOCALNET_DIR);
+        let bootstrapped = Command::new(&super_relay)
+            .args(["dev", "bootstrap", "--node-http", ANVIL_URL])
+            .args(["--config-out", &config])
+            .args(["--policy-out", &format!("{}/policies.toml", LOCALNET_DIR)])
+            .args(["--env-out", &format!("{}/.env", LOCALNET_DIR)])
+            .current_dir(&root)
+            .status()?;
+        if !bootstrapped.success() {
+            return Err(format!("super-relay dev bootstrap failed against {}", ANVIL_URL).into());
+        }
+
+        let log_path = root.join(LOCALNET_DIR).join("gateway.log");
+        let log = File::create(&log_path)?;
+        let gateway = Command::new(&super_relay)
+            .args(["gateway", "--config", &config, "--enable-paymaster"])
+            .env("PAYMASTER_PRIVATE_KEY", LOCALNET_PAYMASTER_KEY)
+            .current_dir(&root)
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .spawn()?;
+        let mut localnet = Self { gateway };
+
+        let deadline = Instant::now() + LOCALNET_STARTUP_TIMEOUT;
+        while !gateway_running().await {
+            if let Some(status) = localnet.gateway.try_wait()? {
+                return Err(
+                    format!("gateway exited with {}, see {}", status, log_path.display()).into(),
+                );
+            }
+            if Instant::now() > deadline {
+                return Err(format!(
+                    "gateway not healthy after {:?}, see {}",
+                    LOCALNET_STARTUP_TIMEOUT,
+                    log_path.display()
+                )
+                .into());
+            }
+            sleep(Duration::from_millis(500)).await;
+        }
+        println!(
+            "  ✅ Gateway started on the local node, logs in {}",
+            log_path.display()
+        );
+        Ok(localnet)
+    }
+}
+
+impl Drop for Localnet {
+    fn drop(&mut self) {
+        let _ = self.gateway.kill();
+        let _ = self.gateway.wait();
+    }
+}
+
+/// 网关 /health 是否可访问
+async fn gateway_running() -> bool {
+    reqwest::get(&format!("{}/health", RUNDLER_URL))
+        .await
+        .is_ok_and(|response| response.status().is_success())
+}
 
 /// 测试1: 基础连接性测试
-fn test_basic_connectivity() -> TestResult {
+fn test_basic_connectivity(with_dashboard: bool) -> TestResult {
     Box::pin(async move {
         println!("🔗 Testing basic connectivity...");
 
         // Test Anvil
-        let anvil_response = make_rpc_call(ANVIL_URL, "eth_chainId", json!([]), 1).await?;
-        assert!(anvil_response.get("result").is_some());
+        SuperRelayClient::new(ANVIL_URL).chain_id().await?;
         println!("  ✅ Anvil connection OK");
 
         // Test Rundler health
@@ -88,9 +163,11 @@ fn test_basic_connectivity() -> TestResult {
         println!("  ✅ Rundler health OK");
 
         // Test Dashboard
-        let dashboard_response = reqwest::get(DASHBOARD_URL).await?;
-        assert!(dashboard_response.status().is_success());
-        println!("  ✅ Dashboard accessibility OK");
+        if with_dashboard {
+            let dashboard_response = reqwest::get(DASHBOARD_URL).await?;
+            assert!(dashboard_response.status().is_success());
+            println!("  ✅ Dashboard accessibility OK");
+        }
 
         Ok(true)
     })
@@ -101,36 +178,35 @@ fn test_supported_rpc_methods() -> TestResult {
     Box::pin(async move {
         println!("📋 Testing supported RPC methods...");
 
-        let methods_to_test = vec![
-            ("eth_chainId", json!([])),
-            ("eth_supportedEntryPoints", json!([])),
-            (
-                "pm_sponsorUserOperation",
-                json!([create_test_user_operation(), ENTRYPOINT_ADDRESS]),
-            ),
-        ];
+        let client = SuperRelayClient::new(RUNDLER_URL);
 
-        for (method, params) in methods_to_test {
-            let response = make_rpc_call(RUNDLER_URL, method, params, 1).await?;
-
-            if response.get("error").is_some() {
-                if method == "pm_sponsorUserOperation" {
-                    // 这个方法预期会有业务逻辑错误，但不应该是"Method not found"
-                    let error_msg = response["error"]["message"].as_str().unwrap_or("");
-                    assert!(
-                        !error_msg.contains("Method not found"),
-                        "pm_sponsorUserOperation should not return 'Method not found'"
-                    );
-                    println!(
-                        "  ✅ {} - API registered (business logic error: {})",
-                        method, error_msg
-                    );
-                } else {
-                    return Err(format!("Method {} failed: {}", method, response["error"]).into());
-                }
-            } else {
-                println!("  ✅ {} - Success", method);
+        client.chain_id().await?;
+        println!("  ✅ eth_chainId - Success");
+        client.supported_entry_points().await?;
+        println!("  ✅ eth_supportedEntryPoints - Success");
+
+        match client
+            .sponsor_user_operation(
+                &create_test_user_operation(),
+                ENTRYPOINT_ADDRESS,
+                &SponsorOptions::default(),
+            )
+            .await
+        {
+            Ok(_) => println!("  ✅ pm_sponsorUserOperation - Success"),
+            // 这个方法预期会有业务逻辑错误，但不应该是"Method not found"
+            Err(ClientError::Rpc { code, message, .. }) => {
+                assert_ne!(
+                    code,
+                    RpcErrorCode::MethodNotFound,
+                    "pm_sponsorUserOperation should not return 'Method not found'"
+                );
+                println!(
+                    "  ✅ pm_sponsorUserOperation - API registered (business logic error: {})",
+                    message
+                );
             }
+            Err(e) => return Err(e.into()),
         }
 
         Ok(true)
@@ -142,11 +218,9 @@ fn test_entrypoint_configuration() -> TestResult {
     Box::pin(async move {
         println!("🎯 Testing EntryPoint configuration...");
 
-        let response = make_rpc_call(RUNDLER_URL, "eth_supportedEntryPoints", json!([]), 1).await?;
-
-        let supported_entries = response["result"]
-            .as_array()
-            .ok_or("Expected array of supported EntryPoints")?;
+        let supported_entries = SuperRelayClient::new(RUNDLER_URL)
+            .supported_entry_points()
+            .await?;
 
         // 验证至少有一个EntryPoint被支持
         assert!(
@@ -155,24 +229,17 @@ fn test_entrypoint_configuration() -> TestResult {
         );
 
         // 验证标准EntryPoint v0.6是否在支持列表中
-        let standard_v06 = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
-        let mut found_standard = false;
-        for entry in supported_entries {
-            if let Some(addr) = entry.as_str() {
-                println!("  📍 Supported EntryPoint: {}", addr);
-                if addr.to_lowercase() == standard_v06.to_lowercase() {
-                    found_standard = true;
-                }
-            }
+        for entry in &supported_entries {
+            println!("  📍 Supported EntryPoint: {}", entry);
         }
 
         assert!(
-            found_standard,
+            supported_entries.contains(&ENTRYPOINT_ADDRESS),
             "Standard EntryPoint v0.6 should be supported"
         );
         println!(
             "  ✅ Standard EntryPoint v0.6 {} is supported",
-            standard_v06
+            ENTRYPOINT_ADDRESS
         );
 
         Ok(true)
@@ -191,7 +258,7 @@ fn test_paymaster_balance_status() -> TestResult {
 
         // 验证关键信息是否存在
         assert!(status_output.contains("SuperPaymaster Financial Status Report"));
-        assert!(status_output.contains(ENTRYPOINT_ADDRESS));
+        assert!(status_output.contains(&ENTRYPOINT_ADDRESS.to_string()));
 
         // 检查是否有余额信息
         let has_balance_info = status_output.contains("Funder Account:")
@@ -212,17 +279,24 @@ fn test_api_performance() -> TestResult {
 
         let test_cases = 10;
         let mut response_times = Vec::new();
+        let client = SuperRelayClient::new(RUNDLER_URL);
+        let user_operation = create_test_user_operation();
 
-        for i in 0..test_cases {
+        for _ in 0..test_cases {
             let start = Instant::now();
 
-            let _response = make_rpc_call(
-                RUNDLER_URL,
-                "pm_sponsorUserOperation",
-                json!([create_test_user_operation(), ENTRYPOINT_ADDRESS]),
-                i + 1,
-            )
-            .await?;
+            // 业务逻辑错误同样计入响应时间，只有传输失败才中断
+            match client
+                .sponsor_user_operation(
+                    &user_operation,
+                    ENTRYPOINT_ADDRESS,
+                    &SponsorOptions::default(),
+                )
+                .await
+            {
+                Ok(_) | Err(ClientError::Rpc { .. }) => {}
+                Err(e) => return Err(e.into()),
+            }
 
             let duration = start.elapsed();
             response_times.push(duration);
@@ -301,15 +375,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut stats = TestStats::default();
 
-    // 定义测试用例
-    let test_cases: Vec<(&str, TestResult)> = vec![
-        ("Basic Connectivity", test_basic_connectivity()),
+    let localnet = if std::env::args().any(|arg| arg == "--localnet") || !gateway_running().await {
+        println!(
+            "\n🏗️  No gateway at {}, bootstrapping the local node at {}",
+            RUNDLER_URL, ANVIL_URL
+        );
+        Some(Localnet::start().await?)
+    } else {
+        None
+    };
+
+    // 定义测试用例；本地网络模式不启动Dashboard，也没有fund_paymaster脚本的环境
+    let mut test_cases: Vec<(&str, TestResult)> = vec![
+        (
+            "Basic Connectivity",
+            test_basic_connectivity(localnet.is_none()),
+        ),
         ("Supported RPC Methods", test_supported_rpc_methods()),
         ("EntryPoint Configuration", test_entrypoint_configuration()),
-        ("Paymaster Balance Status", test_paymaster_balance_status()),
         ("API Performance", test_api_performance()),
-        ("Dashboard Functionality", test_dashboard_functionality()),
     ];
+    if localnet.is_none() {
+        test_cases.push(("Paymaster Balance Status", test_paymaster_balance_status()));
+        test_cases.push(("Dashboard Functionality", test_dashboard_functionality()));
+    } else {
+        println!("⏭️  Skipping Paymaster Balance Status and Dashboard Functionality");
+        stats.skipped += 2;
+    }
 
     // 执行测试
     for (test_name, test_fn) in test_cases {
@@ -337,6 +429,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         (stats.passed as f64 / (stats.passed + stats.failed) as f64) * 100.0
     );
 
+    // 退出前结束本地网络模式启动的网关
+    drop(localnet);
     if stats.failed > 0 {
         std::process::exit(1);
     }
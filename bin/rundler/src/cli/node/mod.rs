@@ -200,13 +200,28 @@ senders = ["0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"]
             .parse::<SocketAddr>()
             .expect("Failed to parse Swagger UI address");
 
+        // Swagger UI follows the RPC server's allowed origins
+        let swagger_cors = rundler_paymaster_relay::CorsConfig {
+            allowed_origins: rpc_args
+                .corsdomain
+                .iter()
+                .flatten()
+                .filter_map(|origin| origin.to_str().ok().map(str::to_string))
+                .collect(),
+            ..Default::default()
+        };
+
         let service_clone = service.clone();
         task_spawner.spawn_critical(
             "swagger ui server",
             Box::pin(async move {
-                if let Err(e) =
-                    rundler_paymaster_relay::swagger::serve_swagger_ui(service_clone, swagger_addr)
-                        .await
+                if let Err(e) = rundler_paymaster_relay::swagger::serve_swagger_ui(
+                    service_clone,
+                    swagger_addr,
+                    &swagger_cors,
+                    None,
+                )
+                .await
                 {
                     tracing::error!("Swagger UI server error: {}", e);
                 }
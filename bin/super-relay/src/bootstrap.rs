@@ -0,0 +1,758 @@
+// super-relay dev bootstrap: 在裸Anvil节点上部署EntryPoint v0.6/v0.7、SimpleAccountFactory与测试账户，
+// 为Paymaster充值并写出可直接使用的配置；重复运行时检测已有部署并跳过
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use alloy_primitives::{address, Address, Bytes, B256, U256, U64};
+use alloy_sol_types::SolCall;
+use eyre::Result;
+use rundler_contracts::{v0_6, v0_7};
+use rundler_paymaster_relay::signer::SignerManager;
+use rundler_provider::EvmProvider;
+use rundler_types::chain::ChainSpec;
+use secrecy::SecretString;
+use serde_json::{json, Value};
+
+/// Deterministic deployment proxy, present on Anvil unless started with
+/// --disable-default-create2-deployer; called with salt ‖ initCode
+pub const CREATE2_DEPLOYER: Address = address!("4e59b44847b379578588920ca78fbf26c0b4956c");
+
+/// Runtime code of [`CREATE2_DEPLOYER`], installed when the node lacks it
+const CREATE2_DEPLOYER_CODE: &str = concat!(
+    "0x7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe036016000",
+    "81602082378035828234f58015156039578182fd5b8082525050506014600cf3"
+);
+
+/// Anvil account #0, unlocked on the node: sends every transaction and owns the test accounts
+pub const ANVIL_FUNDER: Address = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+
+const ANVIL_FUNDER_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+/// Anvil account #1, the paymaster signer unless another key is given
+pub const ANVIL_PAYMASTER_KEY: &str =
+    "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c6a2440f60b6c4b9f78c2";
+
+/// Anvil account #2, the bundler signer written to the .env stub
+const ANVIL_BUNDLER_KEY: &str =
+    "0x5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cccee3a6";
+
+/// First line of every file bootstrap writes; files without it are never overwritten
+const GENERATED_HEADER: &str = "# Generated by super-relay dev bootstrap";
+
+/// Time a transaction may take to be mined
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Settings of one bootstrap run
+#[derive(Debug, Clone)]
+pub struct BootstrapOptions {
+    pub node_http: String,
+    /// Configuration the generated config.toml is derived from
+    pub template: PathBuf,
+    pub config_out: PathBuf,
+    pub policy_out: PathBuf,
+    pub env_out: PathBuf,
+    pub paymaster_key: String,
+    /// ETH balance the paymaster address is topped up to, in wei
+    pub paymaster_balance_wei: U256,
+    /// Deposit of the paymaster on each EntryPoint, topped up to this, in wei
+    pub deposit_wei: U256,
+    /// Gateway URL used in the printed sample request
+    pub gateway_url: String,
+}
+
+/// Whether a step changed the node or found its work already done
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    Done,
+    Skipped,
+}
+
+/// One step of a bootstrap run
+#[derive(Debug, Clone)]
+pub struct BootstrapStep {
+    pub name: String,
+    pub outcome: StepOutcome,
+    pub detail: String,
+}
+
+/// Contracts and accounts on the local node after bootstrap
+#[derive(Debug, Clone)]
+pub struct LocalnetDeployment {
+    pub chain_id: u64,
+    pub node_http: String,
+    pub entry_point_v0_6: Address,
+    pub entry_point_v0_7: Address,
+    pub factory_v0_6: Address,
+    pub factory_v0_7: Address,
+    /// SimpleAccount of each factory, owned by [`ANVIL_FUNDER`] with salt 0
+    pub account_v0_6: Address,
+    pub account_v0_7: Address,
+    pub paymaster: Address,
+}
+
+impl LocalnetDeployment {
+    /// Sponsorship request for the v0.6 test account, allowed by the generated policy
+    pub fn sample_request(&self) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "pm_sponsorUserOperation",
+            "params": [
+                {
+                    "sender": self.account_v0_6,
+                    "nonce": "0x0",
+                    "initCode": "0x",
+                    "callData": "0x",
+                    "callGasLimit": "0x186a0",
+                    "verificationGasLimit": "0x186a0",
+                    "preVerificationGas": "0x5208",
+                    "maxFeePerGas": "0x2540be400",
+                    "maxPriorityFeePerGas": "0x3b9aca00",
+                    "paymasterAndData": "0x",
+                    "signature": "0x"
+                },
+                self.entry_point_v0_6
+            ]
+        })
+    }
+}
+
+/// Steps of one bootstrap run, in the order they ran
+#[derive(Debug)]
+pub struct BootstrapReport {
+    pub steps: Vec<BootstrapStep>,
+    pub deployment: LocalnetDeployment,
+}
+
+impl BootstrapReport {
+    /// Steps, addresses and how to use them, for the terminal
+    pub fn render(&self, options: &BootstrapOptions) -> String {
+        let mut out = String::new();
+        for step in &self.steps {
+            let mark = match step.outcome {
+                StepOutcome::Done => "✅",
+                StepOutcome::Skipped => "⏭️ ",
+            };
+            out.push_str(&format!("{} {:<28} {}\n", mark, step.name, step.detail));
+        }
+
+        let deployment = &self.deployment;
+        out.push_str(&format!(
+            "\nLocal node {} (chain id {})\n",
+            deployment.node_http, deployment.chain_id
+        ));
+        for (name, address) in [
+            ("EntryPoint v0.6", deployment.entry_point_v0_6),
+            ("EntryPoint v0.7", deployment.entry_point_v0_7),
+            ("SimpleAccountFactory v0.6", deployment.factory_v0_6),
+            ("SimpleAccountFactory v0.7", deployment.factory_v0_7),
+            ("Test account v0.6", deployment.account_v0_6),
+            ("Test account v0.7", deployment.account_v0_7),
+            ("Paymaster", deployment.paymaster),
+        ] {
+            out.push_str(&format!("  {:<26} {}\n", name, address));
+        }
+
+        out.push_str(&format!(
+            "\nStart the gateway:\n  super-relay gateway --config {} --enable-paymaster\n",
+            options.config_out.display()
+        ));
+        out.push_str(&format!(
+            "\nSponsor a UserOperation of the test account:\n  curl -s -X POST {} \
+             -H 'Content-Type: application/json' -d '{}'\n",
+            options.gateway_url,
+            deployment.sample_request()
+        ));
+        out
+    }
+}
+
+/// Deploy, fund and write the localnet configuration, skipping what is already in place
+pub async fn run(options: &BootstrapOptions) -> Result<BootstrapReport> {
+    let provider = rundler_provider::new_alloy_evm_provider(&options.node_http, 30)
+        .map_err(|e| eyre::eyre!("{}: {}", options.node_http, e))?;
+    let chain_id: U64 = provider
+        .request("eth_chainId", ())
+        .await
+        .map_err(|e| eyre::eyre!("{} is unreachable: {}", options.node_http, e))?;
+    let mut node = Localnet {
+        provider,
+        steps: Vec::new(),
+    };
+
+    node.ensure_create2_deployer().await?;
+
+    let chain_spec = ChainSpec::default();
+    let entry_point_v0_6 = chain_spec.entry_point_address_v0_6;
+    let entry_point_v0_7 = chain_spec.entry_point_address_v0_7;
+    node.ensure_entry_point(
+        "entry_point v0.6",
+        entry_point_v0_6,
+        &v0_6::ENTRY_POINT_V0_6_BYTECODE,
+    )
+    .await?;
+    node.ensure_entry_point(
+        "entry_point v0.7",
+        entry_point_v0_7,
+        &v0_7::EntryPoint::BYTECODE,
+    )
+    .await?;
+
+    let factory_v0_6 = node
+        .ensure_factory(
+            "account_factory v0.6",
+            &v0_6::SimpleAccountFactory::BYTECODE,
+            entry_point_v0_6,
+        )
+        .await?;
+    let factory_v0_7 = node
+        .ensure_factory(
+            "account_factory v0.7",
+            &v0_7::SimpleAccountFactory::BYTECODE,
+            entry_point_v0_7,
+        )
+        .await?;
+
+    let account_v0_6 = node
+        .ensure_account(
+            "test_account v0.6",
+            factory_v0_6,
+            v0_6::SimpleAccountFactory::getAddressCall {
+                owner: ANVIL_FUNDER,
+                salt: U256::ZERO,
+            }
+            .abi_encode(),
+            v0_6::SimpleAccountFactory::createAccountCall {
+                owner: ANVIL_FUNDER,
+                salt: U256::ZERO,
+            }
+            .abi_encode(),
+        )
+        .await?;
+    let account_v0_7 = node
+        .ensure_account(
+            "test_account v0.7",
+            factory_v0_7,
+            v0_7::SimpleAccountFactory::getAddressCall {
+                owner: ANVIL_FUNDER,
+                salt: U256::ZERO,
+            }
+            .abi_encode(),
+            v0_7::SimpleAccountFactory::createAccountCall {
+                owner: ANVIL_FUNDER,
+                salt: U256::ZERO,
+            }
+            .abi_encode(),
+        )
+        .await?;
+
+    let signer = SignerManager::new(SecretString::new(options.paymaster_key.clone().into()))
+        .map_err(|e| eyre::eyre!("Paymaster private key does not parse: {}", e))?;
+    let paymaster = Address::from_slice(signer.address().as_bytes());
+    node.ensure_balance(paymaster, options.paymaster_balance_wei)
+        .await?;
+    for (name, entry_point) in [
+        ("paymaster_deposit v0.6", entry_point_v0_6),
+        ("paymaster_deposit v0.7", entry_point_v0_7),
+    ] {
+        node.ensure_deposit(name, entry_point, paymaster, options.deposit_wei)
+            .await?;
+    }
+
+    let deployment = LocalnetDeployment {
+        chain_id: chain_id.to(),
+        node_http: options.node_http.clone(),
+        entry_point_v0_6,
+        entry_point_v0_7,
+        factory_v0_6,
+        factory_v0_7,
+        account_v0_6,
+        account_v0_7,
+        paymaster,
+    };
+
+    let policy = policy_file(&deployment);
+    node.steps.push(write_generated(
+        "policy_file",
+        &options.policy_out,
+        &policy,
+    )?);
+    let template = std::fs::read_to_string(&options.template)
+        .map_err(|e| eyre::eyre!("Failed to read {}: {}", options.template.display(), e))?;
+    let config = localnet_config(&template, &deployment, &options.policy_out)?;
+    node.steps
+        .push(write_generated("config", &options.config_out, &config)?);
+    let env = env_stub(&deployment, &options.paymaster_key);
+    node.steps
+        .push(write_generated("env", &options.env_out, &env)?);
+
+    Ok(BootstrapReport {
+        steps: node.steps,
+        deployment,
+    })
+}
+
+/// Local node reached through an unlocked Anvil account
+struct Localnet<P> {
+    provider: P,
+    steps: Vec<BootstrapStep>,
+}
+
+impl<P: EvmProvider> Localnet<P> {
+    fn done(&mut self, name: &str, detail: String) {
+        self.steps.push(BootstrapStep {
+            name: name.to_string(),
+            outcome: StepOutcome::Done,
+            detail,
+        });
+    }
+
+    fn skipped(&mut self, name: &str, detail: String) {
+        self.steps.push(BootstrapStep {
+            name: name.to_string(),
+            outcome: StepOutcome::Skipped,
+            detail,
+        });
+    }
+
+    async fn code(&self, address: Address) -> Result<Bytes> {
+        self.provider
+            .get_code(address, None)
+            .await
+            .map_err(|e| eyre::eyre!("eth_getCode of {} failed: {}", address, e))
+    }
+
+    async fn set_code(&self, address: Address, code: &Bytes) -> Result<()> {
+        self.provider
+            .request::<_, Value>("anvil_setCode", (address, code.clone()))
+            .await
+            .map_err(|e| eyre::eyre!("anvil_setCode failed, is the node Anvil? {}", e))?;
+        Ok(())
+    }
+
+    async fn call(&self, to: Address, data: Vec<u8>) -> Result<Bytes> {
+        self.provider
+            .request(
+                "eth_call",
+                (json!({ "to": to, "data": Bytes::from(data) }), "latest"),
+            )
+            .await
+            .map_err(|e| eyre::eyre!("eth_call to {} failed: {}", to, e))
+    }
+
+    /// Send a transaction from [`ANVIL_FUNDER`] and wait until it succeeds
+    async fn send(&self, to: Address, data: Vec<u8>, value: U256) -> Result<()> {
+        let hash: B256 = self
+            .provider
+            .request(
+                "eth_sendTransaction",
+                (json!({
+                    "from": ANVIL_FUNDER,
+                    "to": to,
+                    "data": Bytes::from(data),
+                    "value": value,
+                }),),
+            )
+            .await
+            .map_err(|e| eyre::eyre!("eth_sendTransaction to {} failed: {}", to, e))?;
+
+        let deadline = tokio::time::Instant::now() + RECEIPT_TIMEOUT;
+        loop {
+            let receipt: Option<Value> = self
+                .provider
+                .request("eth_getTransactionReceipt", (hash,))
+                .await
+                .map_err(|e| eyre::eyre!("eth_getTransactionReceipt failed: {}", e))?;
+            match receipt {
+                Some(receipt) if receipt["status"] == "0x1" => return Ok(()),
+                Some(_) => eyre::bail!("transaction {} to {} reverted", hash, to),
+                None if tokio::time::Instant::now() > deadline => {
+                    eyre::bail!("transaction {} was not mined in time", hash)
+                }
+                None => tokio::time::sleep(Duration::from_millis(200)).await,
+            }
+        }
+    }
+
+    /// Deploy `init_code` through [`CREATE2_DEPLOYER`] with salt 0, unless
+    /// its deterministic address already has code
+    async fn deploy(&self, init_code: &[u8]) -> Result<(Address, StepOutcome)> {
+        let deployed = CREATE2_DEPLOYER.create2_from_code(B256::ZERO, init_code);
+        if !self.code(deployed).await?.is_empty() {
+            return Ok((deployed, StepOutcome::Skipped));
+        }
+        self.send(
+            CREATE2_DEPLOYER,
+            [B256::ZERO.as_slice(), init_code].concat(),
+            U256::ZERO,
+        )
+        .await?;
+        if self.code(deployed).await?.is_empty() {
+            eyre::bail!("no code at {} after deployment", deployed);
+        }
+        Ok((deployed, StepOutcome::Done))
+    }
+
+    async fn ensure_create2_deployer(&mut self) -> Result<()> {
+        let name = "create2_deployer";
+        if !self.code(CREATE2_DEPLOYER).await?.is_empty() {
+            self.skipped(name, format!("{} present", CREATE2_DEPLOYER));
+            return Ok(());
+        }
+        let code = CREATE2_DEPLOYER_CODE.parse::<Bytes>()?;
+        self.set_code(CREATE2_DEPLOYER, &code).await?;
+        self.done(name, format!("installed at {}", CREATE2_DEPLOYER));
+        Ok(())
+    }
+
+    /// Place an EntryPoint at its canonical address
+    ///
+    /// The creation code is deployed through CREATE2 so its constructor
+    /// creates the SenderCreator, and the resulting runtime code is then set
+    /// at the canonical address.
+    async fn ensure_entry_point(
+        &mut self,
+        name: &str,
+        canonical: Address,
+        creation_code: &Bytes,
+    ) -> Result<()> {
+        if !self.code(canonical).await?.is_empty() {
+            self.skipped(name, format!("{} already deployed", canonical));
+            return Ok(());
+        }
+        let (deployed, _) = self.deploy(creation_code).await?;
+        let code = self.code(deployed).await?;
+        self.set_code(canonical, &code).await?;
+        self.done(name, format!("deployed at {}", canonical));
+        Ok(())
+    }
+
+    /// Deploy a SimpleAccountFactory of `entry_point`
+    async fn ensure_factory(
+        &mut self,
+        name: &str,
+        creation_code: &Bytes,
+        entry_point: Address,
+    ) -> Result<Address> {
+        let init_code = [creation_code.as_ref(), entry_point.into_word().as_slice()].concat();
+        let (factory, outcome) = self.deploy(&init_code).await?;
+        match outcome {
+            StepOutcome::Done => self.done(name, format!("deployed at {}", factory)),
+            StepOutcome::Skipped => self.skipped(name, format!("{} already deployed", factory)),
+        }
+        Ok(factory)
+    }
+
+    /// Create the account `get_address` of `factory` returns, unless it exists
+    async fn ensure_account(
+        &mut self,
+        name: &str,
+        factory: Address,
+        get_address: Vec<u8>,
+        create_account: Vec<u8>,
+    ) -> Result<Address> {
+        let result = self.call(factory, get_address).await?;
+        if result.len() != 32 {
+            eyre::bail!("unexpected getAddress result {}", result);
+        }
+        let account = Address::from_slice(&result[12..]);
+        if !self.code(account).await?.is_empty() {
+            self.skipped(name, format!("{} already created", account));
+            return Ok(account);
+        }
+        self.send(factory, create_account, U256::ZERO).await?;
+        self.done(
+            name,
+            format!("{} created, owned by {}", account, ANVIL_FUNDER),
+        );
+        Ok(account)
+    }
+
+    /// Top up the ETH balance of `paymaster` to `target`
+    async fn ensure_balance(&mut self, paymaster: Address, target: U256) -> Result<()> {
+        let name = "paymaster_balance";
+        let balance = self
+            .provider
+            .get_balance(paymaster, None)
+            .await
+            .map_err(|e| eyre::eyre!("eth_getBalance failed: {}", e))?;
+        if balance >= target {
+            self.skipped(name, format!("{} holds {} wei", paymaster, balance));
+            return Ok(());
+        }
+        self.send(paymaster, Vec::new(), target - balance).await?;
+        self.done(name, format!("{} topped up to {} wei", paymaster, target));
+        Ok(())
+    }
+
+    /// Top up the EntryPoint deposit of `paymaster` to `target`
+    async fn ensure_deposit(
+        &mut self,
+        name: &str,
+        entry_point: Address,
+        paymaster: Address,
+        target: U256,
+    ) -> Result<()> {
+        // balanceOf and depositTo have the same signature on both versions
+        let result = self
+            .call(
+                entry_point,
+                v0_6::IEntryPoint::balanceOfCall { account: paymaster }.abi_encode(),
+            )
+            .await?;
+        if result.len() != 32 {
+            eyre::bail!("unexpected balanceOf result {}", result);
+        }
+        let deposit = U256::from_be_slice(&result);
+        if deposit >= target {
+            self.skipped(name, format!("{} wei deposited", deposit));
+            return Ok(());
+        }
+        self.send(
+            entry_point,
+            v0_6::IEntryPoint::depositToCall { account: paymaster }.abi_encode(),
+            target - deposit,
+        )
+        .await?;
+        self.done(name, format!("topped up to {} wei", target));
+        Ok(())
+    }
+}
+
+/// Policy sponsoring the test accounts
+fn policy_file(deployment: &LocalnetDeployment) -> String {
+    format!(
+        "{}\n\n[default]\n# Test accounts of the local node\nsenders = [\"{}\", \"{}\"]\n",
+        GENERATED_HEADER, deployment.account_v0_6, deployment.account_v0_7
+    )
+}
+
+/// `template` pointed at the local node, its EntryPoints and the generated policy
+fn localnet_config(
+    template: &str,
+    deployment: &LocalnetDeployment,
+    policy_file: &Path,
+) -> Result<String> {
+    let mut config: toml::Table = toml::from_str(template)?;
+    let entry_points = toml::Value::Array(vec![
+        deployment.entry_point_v0_6.to_string().into(),
+        deployment.entry_point_v0_7.to_string().into(),
+    ]);
+
+    // The local node is the only chain
+    config.remove("chains");
+    set(&mut config, "node", "network", "dev".into())?;
+    set(
+        &mut config,
+        "node",
+        "node_http",
+        deployment.node_http.clone().into(),
+    )?;
+    set(
+        &mut config,
+        "gateway",
+        "chain_id",
+        (deployment.chain_id as i64).into(),
+    )?;
+    set(&mut config, "gateway", "entry_points", entry_points.clone())?;
+    set(&mut config, "paymaster_relay", "enabled", true.into())?;
+    set(
+        &mut config,
+        "paymaster_relay",
+        "private_key",
+        "${PAYMASTER_PRIVATE_KEY}".into(),
+    )?;
+    set(
+        &mut config,
+        "paymaster_relay",
+        "policy_file",
+        policy_file.display().to_string().into(),
+    )?;
+    set(&mut config, "paymaster_relay", "entry_points", entry_points)?;
+
+    Ok(format!(
+        "{} from {}\n\n{}",
+        GENERATED_HEADER,
+        deployment.node_http,
+        toml::to_string(&config)?
+    ))
+}
+
+fn set(config: &mut toml::Table, section: &str, key: &str, value: toml::Value) -> Result<()> {
+    config
+        .entry(section)
+        .or_insert_with(|| toml::Table::new().into())
+        .as_table_mut()
+        .ok_or_else(|| eyre::eyre!("[{}] is not a table", section))?
+        .insert(key.to_string(), value);
+    Ok(())
+}
+
+/// Keys and addresses of the local node, for .env
+fn env_stub(deployment: &LocalnetDeployment, paymaster_key: &str) -> String {
+    let mut env = format!("{} from {}\n", GENERATED_HEADER, deployment.node_http);
+    for (name, value) in [
+        ("NODE_HTTP", deployment.node_http.clone()),
+        ("PAYMASTER_PRIVATE_KEY", paymaster_key.to_string()),
+        ("SIGNER_PRIVATE_KEYS", ANVIL_BUNDLER_KEY.to_string()),
+        ("ENTRY_POINT_V0_6", deployment.entry_point_v0_6.to_string()),
+        ("ENTRY_POINT_V0_7", deployment.entry_point_v0_7.to_string()),
+        ("ACCOUNT_FACTORY_V0_6", deployment.factory_v0_6.to_string()),
+        ("ACCOUNT_FACTORY_V0_7", deployment.factory_v0_7.to_string()),
+        ("TEST_ACCOUNT_V0_6", deployment.account_v0_6.to_string()),
+        ("TEST_ACCOUNT_V0_7", deployment.account_v0_7.to_string()),
+        ("TEST_ACCOUNT_OWNER_KEY", ANVIL_FUNDER_KEY.to_string()),
+    ] {
+        env.push_str(&format!("{}={}\n", name, value));
+    }
+    env
+}
+
+/// Write `content` to `path`, leaving files bootstrap did not write untouched
+fn write_generated(name: &str, path: &Path, content: &str) -> Result<BootstrapStep> {
+    let step = |outcome, detail| BootstrapStep {
+        name: name.to_string(),
+        outcome,
+        detail,
+    };
+    match std::fs::read_to_string(path) {
+        Ok(existing) if existing == content => {
+            return Ok(step(
+                StepOutcome::Skipped,
+                format!("{} up to date", path.display()),
+            ))
+        }
+        Ok(existing) if !existing.starts_with(GENERATED_HEADER) => {
+            return Ok(step(
+                StepOutcome::Skipped,
+                format!(
+                    "{} exists and was not written by bootstrap, left unchanged",
+                    path.display()
+                ),
+            ))
+        }
+        _ => {}
+    }
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| eyre::eyre!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    std::fs::write(path, content)
+        .map_err(|e| eyre::eyre!("Failed to write {}: {}", path.display(), e))?;
+    Ok(step(StepOutcome::Done, format!("wrote {}", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn deployment() -> LocalnetDeployment {
+        LocalnetDeployment {
+            chain_id: 31337,
+            node_http: "http://localhost:8545".to_string(),
+            entry_point_v0_6: ChainSpec::default().entry_point_address_v0_6,
+            entry_point_v0_7: ChainSpec::default().entry_point_address_v0_7,
+            factory_v0_6: Address::repeat_byte(1),
+            factory_v0_7: Address::repeat_byte(2),
+            account_v0_6: Address::repeat_byte(3),
+            account_v0_7: Address::repeat_byte(4),
+            paymaster: Address::repeat_byte(5),
+        }
+    }
+
+    #[test]
+    fn test_config_points_at_local_node() {
+        let template = r#"
+[node]
+network = "ethereum_sepolia"
+node_http = "https://rpc.example"
+max_entries_per_chain = 100
+
+[paymaster_relay]
+enabled = false
+policy_file = "config/paymaster-policies.toml"
+
+[[chains]]
+chain_id = 10
+node_http = "https://optimism.example"
+entry_points = []
+"#;
+        let config =
+            localnet_config(template, &deployment(), Path::new("local/policies.toml")).unwrap();
+        assert!(config.starts_with(GENERATED_HEADER));
+
+        let config: toml::Table = toml::from_str(&config).unwrap();
+        assert!(!config.contains_key("chains"));
+        assert_eq!(config["node"]["network"].as_str(), Some("dev"));
+        assert_eq!(
+            config["node"]["node_http"].as_str(),
+            Some("http://localhost:8545")
+        );
+        // Settings the bootstrap does not manage are kept
+        assert_eq!(
+            config["node"]["max_entries_per_chain"].as_integer(),
+            Some(100)
+        );
+        assert_eq!(config["gateway"]["chain_id"].as_integer(), Some(31337));
+        assert_eq!(
+            config["gateway"]["entry_points"].as_array().unwrap().len(),
+            2
+        );
+        assert_eq!(config["paymaster_relay"]["enabled"].as_bool(), Some(true));
+        assert_eq!(
+            config["paymaster_relay"]["policy_file"].as_str(),
+            Some("local/policies.toml")
+        );
+    }
+
+    #[test]
+    fn test_foreign_files_not_overwritten() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(".env");
+        std::fs::write(&path, "PAYMASTER_PRIVATE_KEY=0xmine\n").unwrap();
+
+        let step = write_generated("env", &path, &env_stub(&deployment(), "0x01")).unwrap();
+        assert_eq!(step.outcome, StepOutcome::Skipped);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "PAYMASTER_PRIVATE_KEY=0xmine\n"
+        );
+    }
+
+    #[test]
+    fn test_generated_files_rewritten_only_when_changed() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("local").join("policies.toml");
+        let policy = policy_file(&deployment());
+
+        let first = write_generated("policy_file", &path, &policy).unwrap();
+        assert_eq!(first.outcome, StepOutcome::Done);
+        let second = write_generated("policy_file", &path, &policy).unwrap();
+        assert_eq!(second.outcome, StepOutcome::Skipped);
+
+        let mut moved = deployment();
+        moved.account_v0_6 = Address::repeat_byte(9);
+        let third = write_generated("policy_file", &path, &policy_file(&moved)).unwrap();
+        assert_eq!(third.outcome, StepOutcome::Done);
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains(&Address::repeat_byte(9).to_string()));
+    }
+
+    #[test]
+    fn test_sample_request_uses_test_account() {
+        let deployment = deployment();
+        let request = deployment.sample_request();
+        assert_eq!(
+            request["params"][0]["sender"],
+            json!(deployment.account_v0_6)
+        );
+        assert_eq!(request["params"][1], json!(deployment.entry_point_v0_6));
+    }
+}
@@ -0,0 +1,1161 @@
+// 统一配置: 所有服务模式、doctor 与 `config show` 从这里得到同一份生效配置
+// 优先级: CLI参数 > 密钥文件 > 环境变量 (进程环境优先于 .env) > 配置文件 > 默认值
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use rundler_paymaster_relay::{secrets::Secrets, signer::SignerBackendKind};
+use secrecy::{ExposeSecret, SecretString};
+use toml::{map::Map, Value};
+use tracing::warn;
+
+use crate::{default_chain_settings, SuperRelayConfig};
+
+/// Dotenv file read from the working directory
+pub const DOTENV_FILE: &str = ".env";
+
+const DEFAULT_NETWORK: &str = "dev";
+const DEFAULT_NODE_HTTP: &str = "http://localhost:8545";
+const DEFAULT_POLICY_FILE: &str = "config/paymaster-policies.toml";
+
+const NETWORK_VARS: &[&str] = &["NETWORK", "CHAIN_NETWORK"];
+const CHAIN_SPEC_VAR: &str = "CHAIN_SPEC";
+const NODE_HTTP_VARS: &[&str] = &["NODE_HTTP", "ETH_NODE_HTTP", "RPC_URL"];
+const PAYMASTER_KEY_VAR: &str = "PAYMASTER_PRIVATE_KEY";
+const POLICY_FILE_VAR: &str = "PAYMASTER_POLICY_FILE";
+const SIGNER_KEYS_VAR: &str = "SIGNER_PRIVATE_KEYS";
+
+const PAYMASTER_KEY_FIELD: &str = "paymaster_relay.private_key";
+const POLICY_FILE_FIELD: &str = "paymaster_relay.policy_file";
+
+/// Key fragments whose values `config show --redact-secrets` hides
+const SECRET_KEYS: &[&str] = &[
+    "private_key",
+    "secret",
+    "password",
+    "connection_string",
+    "credentials",
+];
+const REDACTED: &str = "<redacted>";
+
+/// Where an effective setting came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Command line flag
+    Cli(&'static str),
+    /// Environment variable, from the process or the dotenv file
+    Env(String),
+    /// Entry of the encrypted secrets file
+    Secrets(String),
+    /// Config file key
+    File(&'static str),
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Cli(flag) => write!(f, "cli {}", flag),
+            Source::Env(var) => write!(f, "env {}", var),
+            Source::Secrets(var) => write!(f, "secrets {}", var),
+            Source::File(key) => write!(f, "config {}", key),
+            Source::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// Effective value of one setting
+#[derive(Debug, Clone)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+impl<T> Setting<T> {
+    fn new(value: T, source: Source) -> Self {
+        Self { value, source }
+    }
+}
+
+/// Settings outside the config file sections, resolved once at startup
+#[derive(Debug)]
+pub struct ResolvedSettings {
+    /// Network of the default chain, one of the hardcoded chain specs (dev, ethereum, ...)
+    pub network: Setting<String>,
+    /// Chain spec file of the default chain, layered over the network's spec
+    pub chain_spec: Option<Setting<String>>,
+    /// Node of the default chain
+    pub node_http: Setting<String>,
+    /// Paymaster key of chains without their own `signer_key_env`
+    pub paymaster_private_key: Option<Setting<SecretString>>,
+    pub policy_file: Setting<PathBuf>,
+    /// Bundler signing keys, comma separated; passed to `rundler node` or
+    /// used by the in-process bundle builder
+    pub signer_private_keys: Option<Setting<SecretString>>,
+    /// Paymaster keys of [[chains]] entries, by `signer_key_env` name
+    pub chain_signer_keys: BTreeMap<String, SecretString>,
+}
+
+impl Default for ResolvedSettings {
+    fn default() -> Self {
+        Self {
+            network: Setting::new(DEFAULT_NETWORK.to_string(), Source::Default),
+            chain_spec: None,
+            node_http: Setting::new(DEFAULT_NODE_HTTP.to_string(), Source::Default),
+            paymaster_private_key: None,
+            policy_file: Setting::new(PathBuf::from(DEFAULT_POLICY_FILE), Source::Default),
+            signer_private_keys: None,
+            chain_signer_keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl ResolvedSettings {
+    /// Paymaster key of a chain: its `signer_key_env` variable, else the default key
+    pub fn paymaster_key(&self, signer_key_env: Option<&str>) -> eyre::Result<SecretString> {
+        let key = match signer_key_env {
+            Some(var) => self
+                .chain_signer_keys
+                .get(var)
+                .ok_or_else(|| eyre::eyre!("Paymaster key environment variable {} not set", var))?,
+            None => {
+                &self
+                    .paymaster_private_key
+                    .as_ref()
+                    .ok_or_else(|| eyre::eyre!("{}", missing_paymaster_key()))?
+                    .value
+            }
+        };
+        Ok(SecretString::new(key.expose_secret().into()))
+    }
+
+    /// Network whose spec the chain spec file is layered over; the default
+    /// network is left out when only a file is given, as in the rundler CLI
+    pub fn spec_network(&self) -> Option<&str> {
+        (self.chain_spec.is_none() || self.network.source != Source::Default)
+            .then_some(self.network.value.as_str())
+    }
+
+    /// Bundler signing keys of the in-process bundle builder, one per builder
+    pub fn bundler_keys(&self) -> eyre::Result<Vec<SecretString>> {
+        let keys = self.signer_private_keys.as_ref().ok_or_else(|| {
+            eyre::eyre!(
+                "Bundler signer keys are not set: set {} in the environment or {}",
+                SIGNER_KEYS_VAR,
+                DOTENV_FILE
+            )
+        })?;
+        Ok(split_keys(keys.value.expose_secret())
+            .map(|key| SecretString::new(key.into()))
+            .collect())
+    }
+}
+
+/// Overrides and requirements of the running command
+#[derive(Debug, Clone, Default)]
+pub struct CliOverrides {
+    pub network: Option<String>,
+    pub chain_spec: Option<String>,
+    pub node_http: Option<String>,
+    /// Paymaster key, or the name of the environment variable holding it
+    pub paymaster_private_key: Option<String>,
+    pub paymaster_policy_file: Option<String>,
+    /// Fail unless the paymaster keys and policy file resolve
+    pub require_paymaster: bool,
+    /// Fail unless the rundler signer keys resolve
+    pub require_signer_keys: bool,
+    /// Fail unless valid bundler keys resolve for the in-process bundle builder
+    pub require_builder_keys: bool,
+}
+
+/// Every missing or invalid setting found while resolving the configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigErrors(pub Vec<String>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration ({} problems):", self.0.len())?;
+        for error in &self.0 {
+            write!(f, "\n  • {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+/// `${VAR}` placeholder left unresolved, with the config key that held it
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MissingVar {
+    field: String,
+    var: String,
+}
+
+impl fmt::Display for MissingVar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: environment variable {} is not set",
+            self.field, self.var
+        )
+    }
+}
+
+/// Loads the config file and resolves it against the environment and CLI flags
+#[derive(Debug)]
+pub struct ConfigurationManager {
+    path: PathBuf,
+    env: BTreeMap<String, String>,
+    env_errors: Vec<String>,
+    /// Decrypted secrets file entries, looked up before the environment
+    secrets: BTreeMap<String, SecretString>,
+}
+
+impl ConfigurationManager {
+    /// Configuration at `path` with the process environment, backed by `.env`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let vars = std::env::vars_os().filter_map(|(name, value)| {
+            Some((name.into_string().ok()?, value.into_string().ok()?))
+        });
+        Self::with_env(path, vars).with_dotenv(DOTENV_FILE)
+    }
+
+    /// Configuration at `path` resolved against `vars` only
+    pub fn with_env(
+        path: impl Into<PathBuf>,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            env: vars.into_iter().collect(),
+            env_errors: Vec::new(),
+            secrets: BTreeMap::new(),
+        }
+    }
+
+    /// Fill variables not already set from a dotenv file, when it exists
+    pub fn with_dotenv(mut self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        match dotenvy::from_path_iter(path) {
+            Ok(entries) => {
+                for entry in entries {
+                    match entry {
+                        Ok((name, value)) => {
+                            self.env.entry(name).or_insert(value);
+                        }
+                        Err(e) => self.env_errors.push(format!("{}: {}", path.display(), e)),
+                    }
+                }
+            }
+            Err(e) if e.not_found() => {}
+            Err(e) => self.env_errors.push(format!("{}: {}", path.display(), e)),
+        }
+        self
+    }
+
+    /// Resolve variables held by a decrypted secrets file from it first
+    pub fn with_secrets(mut self, secrets: &Secrets) -> Self {
+        for name in secrets.names() {
+            if let Some(value) = secrets.get(name) {
+                self.secrets.insert(
+                    name.to_string(),
+                    SecretString::new(value.expose_secret().into()),
+                );
+            }
+        }
+        self
+    }
+
+    /// Value of an environment variable, taken from the secrets file when it
+    /// holds the variable
+    pub fn env(&self, name: &str) -> Option<&str> {
+        match self.secrets.get(name) {
+            Some(value) => Some(value.expose_secret()),
+            None => self.env.get(name).map(String::as_str),
+        }
+    }
+
+    /// Where the value of variable `name` comes from
+    fn var_source(&self, name: &str) -> Source {
+        if self.secrets.contains_key(name) {
+            Source::Secrets(name.to_string())
+        } else {
+            Source::Env(name.to_string())
+        }
+    }
+
+    /// Resolve the configuration, failing with every problem found
+    pub fn resolve(&self, cli: &CliOverrides) -> Result<SuperRelayConfig, ConfigErrors> {
+        let (config, errors) = self.resolve_all(cli)?;
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigErrors(errors))
+        }
+    }
+
+    /// Effective configuration as TOML: the expanded file plus an `[effective]`
+    /// table naming the source of each resolved setting
+    ///
+    /// Problems found while resolving are returned alongside, so the output
+    /// shows what does resolve.
+    pub fn show(
+        &self,
+        cli: &CliOverrides,
+        redact_secrets: bool,
+    ) -> Result<(String, Vec<String>), ConfigErrors> {
+        let (mut file, _) = self.expand()?;
+        let (config, errors) = self.resolve_all(cli)?;
+        let settings = &config.settings;
+
+        let secret = |value: &SecretString| {
+            if redact_secrets {
+                REDACTED.to_string()
+            } else {
+                value.expose_secret().to_string()
+            }
+        };
+        let mut effective = Map::new();
+        let mut add = |name: &str, value: String, source: &Source| {
+            let mut entry = Map::new();
+            entry.insert("value".to_string(), Value::String(value));
+            entry.insert("source".to_string(), Value::String(source.to_string()));
+            effective.insert(name.to_string(), Value::Table(entry));
+        };
+        add(
+            "network",
+            settings.network.value.clone(),
+            &settings.network.source,
+        );
+        if let Some(chain_spec) = &settings.chain_spec {
+            add("chain_spec", chain_spec.value.clone(), &chain_spec.source);
+        }
+        add(
+            "node_http",
+            settings.node_http.value.clone(),
+            &settings.node_http.source,
+        );
+        if let Some(key) = &settings.paymaster_private_key {
+            add("paymaster_private_key", secret(&key.value), &key.source);
+        }
+        add(
+            "policy_file",
+            settings.policy_file.value.display().to_string(),
+            &settings.policy_file.source,
+        );
+        if let Some(keys) = &settings.signer_private_keys {
+            add("signer_private_keys", secret(&keys.value), &keys.source);
+        }
+        for (var, key) in &settings.chain_signer_keys {
+            add(var, secret(key), &self.var_source(var));
+        }
+
+        if redact_secrets {
+            redact(&mut file);
+        }
+        if let Value::Table(table) = &mut file {
+            table.insert("effective".to_string(), Value::Table(effective));
+        }
+        let rendered = toml::to_string_pretty(&file)
+            .map_err(|e| ConfigErrors(vec![format!("Failed to render configuration: {}", e)]))?;
+        Ok((rendered, errors))
+    }
+
+    /// Parsed configuration and every invalid setting; fails only when the
+    /// file cannot be read or parsed at all
+    fn resolve_all(
+        &self,
+        cli: &CliOverrides,
+    ) -> Result<(SuperRelayConfig, Vec<String>), ConfigErrors> {
+        let (file, missing) = self.expand()?;
+        let mut errors = self.env_errors.clone();
+
+        let mut config: SuperRelayConfig = match file.try_into() {
+            Ok(config) => config,
+            Err(e) => {
+                errors.push(format!(
+                    "{}: {}",
+                    self.path.display(),
+                    e.to_string().trim_end()
+                ));
+                // A required field held by a missing variable shows up as a missing field
+                errors.extend(missing.iter().map(ToString::to_string));
+                return Err(ConfigErrors(errors));
+            }
+        };
+        for var in &missing {
+            if var.field != PAYMASTER_KEY_FIELD && var.field != POLICY_FILE_FIELD {
+                warn!("⚠️  {}, leaving it unset", var);
+            }
+        }
+
+        config.settings = self.settings(&config, cli, &mut errors);
+        self.warn_plaintext_keys(&config.settings);
+        self.validate(&config, cli, &missing, &mut errors);
+        Ok((config, errors))
+    }
+
+    /// Read the config file and expand `${VAR}` and `${VAR:-default}` in its
+    /// strings. Strings with a missing variable are removed, leaving the field
+    /// unset, and reported.
+    fn expand(&self) -> Result<(Value, Vec<MissingVar>), ConfigErrors> {
+        let content = fs::read_to_string(&self.path).map_err(|e| {
+            ConfigErrors(vec![format!(
+                "Failed to read config file '{}': {}",
+                self.path.display(),
+                e
+            )])
+        })?;
+        let mut file: Value = toml::from_str(&content).map_err(|e| {
+            ConfigErrors(vec![format!(
+                "Failed to parse config file '{}': {}",
+                self.path.display(),
+                e.to_string().trim_end()
+            )])
+        })?;
+
+        let mut missing = Vec::new();
+        self.expand_value(&mut file, "", &mut missing);
+        Ok((file, missing))
+    }
+
+    fn expand_value(&self, value: &mut Value, field: &str, missing: &mut Vec<MissingVar>) {
+        match value {
+            Value::Table(table) => {
+                let mut unset = Vec::new();
+                for (key, value) in table.iter_mut() {
+                    let field = join_field(field, key);
+                    match value {
+                        Value::String(s) => match self.expand_str(s) {
+                            Ok(expanded) => *s = expanded,
+                            Err(var) => {
+                                missing.push(MissingVar { field, var });
+                                unset.push(key.clone());
+                            }
+                        },
+                        value => self.expand_value(value, &field, missing),
+                    }
+                }
+                for key in unset {
+                    table.remove(&key);
+                }
+            }
+            Value::Array(values) => {
+                values.retain_mut(|value| match value {
+                    Value::String(s) => match self.expand_str(s) {
+                        Ok(expanded) => {
+                            *s = expanded;
+                            true
+                        }
+                        Err(var) => {
+                            missing.push(MissingVar {
+                                field: field.to_string(),
+                                var,
+                            });
+                            false
+                        }
+                    },
+                    value => {
+                        self.expand_value(value, field, missing);
+                        true
+                    }
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// `s` with its placeholders expanded, or the first variable that is not set
+    fn expand_str(&self, s: &str) -> Result<String, String> {
+        let mut result = String::with_capacity(s.len());
+        let mut rest = s;
+        while let Some(start) = rest.find("${") {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            let placeholder = &rest[start + 2..start + len];
+            let (var, default) = match placeholder.split_once(":-") {
+                Some((var, default)) => (var, Some(default)),
+                None => (placeholder, None),
+            };
+            let value = match (self.env(var), default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => default,
+                (None, None) => return Err(var.to_string()),
+            };
+            result.push_str(&rest[..start]);
+            result.push_str(value);
+            rest = &rest[start + len + 1..];
+        }
+        result.push_str(rest);
+        Ok(result)
+    }
+
+    /// First of `vars` that is set
+    fn env_first(&self, vars: &[&str]) -> Option<Setting<String>> {
+        vars.iter().find_map(|var| {
+            self.env(var)
+                .map(|value| Setting::new(value.to_string(), self.var_source(var)))
+        })
+    }
+
+    fn settings(
+        &self,
+        config: &SuperRelayConfig,
+        cli: &CliOverrides,
+        errors: &mut Vec<String>,
+    ) -> ResolvedSettings {
+        let defaults = ResolvedSettings::default();
+
+        let network = cli
+            .network
+            .clone()
+            .map(|network| Setting::new(network, Source::Cli("--network")))
+            .or_else(|| self.env_first(NETWORK_VARS))
+            .or_else(|| {
+                config
+                    .node
+                    .network
+                    .clone()
+                    .map(|network| Setting::new(network, Source::File("node.network")))
+            })
+            .unwrap_or(defaults.network);
+        let chain_spec = cli
+            .chain_spec
+            .clone()
+            .map(|path| Setting::new(path, Source::Cli("--chain_spec")))
+            .or_else(|| self.env_first(&[CHAIN_SPEC_VAR]))
+            .or_else(|| {
+                config
+                    .node
+                    .chain_spec
+                    .clone()
+                    .map(|path| Setting::new(path, Source::File("node.chain_spec")))
+            });
+        let node_http = cli
+            .node_http
+            .clone()
+            .map(|url| Setting::new(url, Source::Cli("--node-http")))
+            .or_else(|| self.env_first(NODE_HTTP_VARS))
+            .or_else(|| {
+                config
+                    .node
+                    .node_http
+                    .clone()
+                    .map(|url| Setting::new(url, Source::File("node.node_http")))
+            })
+            .unwrap_or(defaults.node_http);
+
+        // --paymaster-private-key takes the key itself or the variable holding it
+        let cli_key = cli.paymaster_private_key.as_ref().and_then(|key| {
+            if !is_env_var_name(key) {
+                return Some(Setting::new(
+                    key.clone(),
+                    Source::Cli("--paymaster-private-key"),
+                ));
+            }
+            match self.env(key) {
+                Some(value) => Some(Setting::new(value.to_string(), self.var_source(key))),
+                None => {
+                    errors.push(format!(
+                        "--paymaster-private-key: environment variable {} is not set",
+                        key
+                    ));
+                    None
+                }
+            }
+        });
+        let paymaster_private_key = cli_key
+            .or_else(|| self.env_first(&[PAYMASTER_KEY_VAR]))
+            .or_else(|| {
+                config
+                    .paymaster_relay
+                    .private_key
+                    .clone()
+                    .map(|key| Setting::new(key, Source::File(PAYMASTER_KEY_FIELD)))
+            })
+            .map(|key| Setting::new(SecretString::new(key.value.into()), key.source));
+
+        let policy_file = cli
+            .paymaster_policy_file
+            .clone()
+            .map(|path| Setting::new(path, Source::Cli("--paymaster-policy-file")))
+            .or_else(|| self.env_first(&[POLICY_FILE_VAR]))
+            .or_else(|| {
+                config
+                    .paymaster_relay
+                    .policy_file
+                    .clone()
+                    .map(|path| Setting::new(path, Source::File(POLICY_FILE_FIELD)))
+            })
+            .map(|path| Setting::new(PathBuf::from(path.value), path.source))
+            .unwrap_or(defaults.policy_file);
+
+        let signer_private_keys = self
+            .env_first(&[SIGNER_KEYS_VAR])
+            .map(|keys| Setting::new(SecretString::new(keys.value.into()), keys.source));
+
+        let chain_signer_keys = config
+            .chains
+            .iter()
+            .filter_map(|chain| chain.signer_key_env.as_deref())
+            .filter_map(|var| {
+                self.env(var)
+                    .map(|key| (var.to_string(), SecretString::new(key.into())))
+            })
+            .collect();
+
+        ResolvedSettings {
+            network,
+            chain_spec,
+            node_http,
+            paymaster_private_key,
+            policy_file,
+            signer_private_keys,
+            chain_signer_keys,
+        }
+    }
+
+    /// Warn about private keys resolved from outside the secrets file; they
+    /// still work, but sit in plaintext on disk or in the environment
+    fn warn_plaintext_keys(&self, settings: &ResolvedSettings) {
+        let mut sources = Vec::new();
+        if let Some(key) = &settings.paymaster_private_key {
+            sources.push(key.source.clone());
+        }
+        if let Some(keys) = &settings.signer_private_keys {
+            sources.push(keys.source.clone());
+        }
+        sources.extend(
+            settings
+                .chain_signer_keys
+                .keys()
+                .map(|var| self.var_source(var)),
+        );
+        for source in sources {
+            if !matches!(source, Source::Secrets(_)) {
+                warn!(
+                    "⚠️  Private key read in plaintext from {}; store it in an encrypted \
+                     secrets file (super-relay secrets encrypt)",
+                    source
+                );
+            }
+        }
+    }
+
+    /// Collect every invalid or, for this command, missing setting
+    fn validate(
+        &self,
+        config: &SuperRelayConfig,
+        cli: &CliOverrides,
+        missing: &[MissingVar],
+        errors: &mut Vec<String>,
+    ) {
+        let settings = &config.settings;
+        let missing_note = |field: &str| {
+            missing
+                .iter()
+                .find(|var| var.field == field)
+                .map(|var| format!(" ({})", var))
+                .unwrap_or_default()
+        };
+
+        // Chains and their gateway settings
+        if config.chains.is_empty() {
+            check_node_url("node_http", &settings.node_http.value, errors);
+            if let Err(e) = default_chain_settings(config) {
+                errors.push(format!("[gateway]: {}", e));
+            }
+        }
+        for chain in &config.chains {
+            let label = format!("[[chains]] {}", chain.label());
+            check_node_url(&label, &chain.node_http, errors);
+            if let Err(e) = chain.to_eth_api_config() {
+                errors.push(e.to_string());
+            }
+        }
+
+        // Paymaster keys and policy
+        let key_backend = config.signer.backend == SignerBackendKind::PrivateKey;
+        let paymaster_in_node =
+            cli.require_signer_keys && config.paymaster_relay.enabled.unwrap_or(false);
+        if (cli.require_paymaster && key_backend) || paymaster_in_node {
+            let default_key_used = config.chains.is_empty()
+                || config.chains.iter().any(|c| c.signer_key_env.is_none());
+            if default_key_used && settings.paymaster_private_key.is_none() {
+                errors.push(format!(
+                    "{}{}",
+                    missing_paymaster_key(),
+                    missing_note(PAYMASTER_KEY_FIELD)
+                ));
+            }
+            for var in config
+                .chains
+                .iter()
+                .filter_map(|c| c.signer_key_env.as_ref())
+            {
+                if !settings.chain_signer_keys.contains_key(var) {
+                    errors.push(format!(
+                        "[[chains]] signer_key_env: environment variable {} is not set",
+                        var
+                    ));
+                }
+            }
+        }
+        if let Some(key) = &settings.paymaster_private_key {
+            if !is_private_key(key.value.expose_secret()) {
+                errors.push(format!(
+                    "Paymaster private key from {} is not a 32 byte hex key",
+                    key.source
+                ));
+            }
+        }
+        for (var, key) in &settings.chain_signer_keys {
+            if !is_private_key(key.expose_secret()) {
+                errors.push(format!("{} is not a 32 byte hex key", var));
+            }
+        }
+        if cli.require_paymaster && !settings.policy_file.value.exists() {
+            errors.push(format!(
+                "Paymaster policy file {} (from {}) does not exist{}",
+                settings.policy_file.value.display(),
+                settings.policy_file.source,
+                missing_note(POLICY_FILE_FIELD)
+            ));
+        }
+
+        // Bundler keys of `node` or the in-process bundle builder
+        let signer_keys_required = cli.require_signer_keys || cli.require_builder_keys;
+        match &settings.signer_private_keys {
+            None if signer_keys_required => errors.push(format!(
+                "Bundler signer keys are not set: set {} in the environment or {}",
+                SIGNER_KEYS_VAR, DOTENV_FILE
+            )),
+            Some(keys) if cli.require_builder_keys => {
+                for (index, key) in split_keys(keys.value.expose_secret()).enumerate() {
+                    if !is_private_key(key) {
+                        errors.push(format!(
+                            "Bundler signer key {} from {} is not a 32 byte hex key",
+                            index + 1,
+                            keys.source
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn missing_paymaster_key() -> String {
+    format!(
+        "Paymaster private key is not set: pass --paymaster-private-key, set {} in the \
+         environment or {}, or set [paymaster_relay] private_key",
+        PAYMASTER_KEY_VAR, DOTENV_FILE
+    )
+}
+
+fn check_node_url(name: &str, url: &str, errors: &mut Vec<String>) {
+    if !["http://", "https://", "ws://", "wss://"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+    {
+        errors.push(format!(
+            "{}: '{}' is not an http(s) or ws(s) URL",
+            name, url
+        ));
+    }
+}
+
+fn join_field(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+/// Upper-case variable names, as opposed to keys themselves
+fn is_env_var_name(value: &str) -> bool {
+    value.starts_with(|c: char| c.is_ascii_uppercase())
+        && value
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Non-empty entries of a comma separated key list
+fn split_keys(keys: &str) -> impl Iterator<Item = &str> {
+    keys.split(',').map(str::trim).filter(|key| !key.is_empty())
+}
+
+fn is_private_key(key: &str) -> bool {
+    let hex = key.strip_prefix("0x").unwrap_or(key);
+    hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Hide string values under secret-looking keys
+fn redact(value: &mut Value) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                let key = key.to_ascii_lowercase();
+                match value {
+                    Value::String(s) if SECRET_KEYS.iter().any(|k| key.contains(k)) => {
+                        *s = REDACTED.to_string();
+                    }
+                    value => redact(value),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use rundler_paymaster_relay::secrets::KdfParams;
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    const KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const OTHER_KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+    const BASE_CONFIG: &str = r#"
+[node]
+[pool]
+[rpc]
+[mempool]
+"#;
+
+    fn config_file(paymaster_relay: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            "{}\n[paymaster_relay]\n{}\n",
+            BASE_CONFIG, paymaster_relay
+        )
+        .unwrap();
+        file
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn paymaster_key(config: &SuperRelayConfig) -> (String, Source) {
+        let key = config.settings.paymaster_private_key.as_ref().unwrap();
+        (key.value.expose_secret().to_string(), key.source.clone())
+    }
+
+    #[test]
+    fn test_precedence() {
+        let file = config_file(&format!("private_key = \"{}\"", KEY));
+
+        // Config file over default
+        let manager = ConfigurationManager::with_env(file.path(), env(&[]));
+        let config = manager.resolve(&CliOverrides::default()).unwrap();
+        assert_eq!(
+            paymaster_key(&config),
+            (KEY.to_string(), Source::File(PAYMASTER_KEY_FIELD))
+        );
+        assert_eq!(config.settings.network.source, Source::Default);
+        assert_eq!(config.settings.node_http.value, DEFAULT_NODE_HTTP);
+
+        // Environment over config file
+        let manager = ConfigurationManager::with_env(
+            file.path(),
+            env(&[
+                (PAYMASTER_KEY_VAR, OTHER_KEY),
+                ("ETH_NODE_HTTP", "http://node:8545"),
+            ]),
+        );
+        let config = manager.resolve(&CliOverrides::default()).unwrap();
+        assert_eq!(
+            paymaster_key(&config),
+            (
+                OTHER_KEY.to_string(),
+                Source::Env(PAYMASTER_KEY_VAR.to_string())
+            )
+        );
+        assert_eq!(config.settings.node_http.value, "http://node:8545");
+
+        // CLI flag over environment
+        let cli = CliOverrides {
+            paymaster_private_key: Some(KEY.to_string()),
+            node_http: Some("https://rpc.example".to_string()),
+            ..Default::default()
+        };
+        let config = manager.resolve(&cli).unwrap();
+        assert_eq!(
+            paymaster_key(&config),
+            (KEY.to_string(), Source::Cli("--paymaster-private-key"))
+        );
+        assert_eq!(config.settings.node_http.source, Source::Cli("--node-http"));
+    }
+
+    #[test]
+    fn test_dotenv_values_with_equals_signs() {
+        let file = config_file("");
+        let mut dotenv = NamedTempFile::new().unwrap();
+        write!(
+            dotenv,
+            "# development keys\nNODE_HTTP=\"http://node:8545/?token=a=b\"\n{}={}\n",
+            PAYMASTER_KEY_VAR, KEY
+        )
+        .unwrap();
+
+        let manager =
+            ConfigurationManager::with_env(file.path(), env(&[("NETWORK", "ethereum_sepolia")]))
+                .with_dotenv(dotenv.path());
+        assert_eq!(
+            manager.env("NODE_HTTP"),
+            Some("http://node:8545/?token=a=b")
+        );
+        let config = manager.resolve(&CliOverrides::default()).unwrap();
+        assert_eq!(paymaster_key(&config).0, KEY);
+        assert_eq!(config.settings.network.value, "ethereum_sepolia");
+
+        // The process environment wins over .env
+        let manager =
+            ConfigurationManager::with_env(file.path(), env(&[("NODE_HTTP", "http://other:8545")]))
+                .with_dotenv(dotenv.path());
+        assert_eq!(manager.env("NODE_HTTP"), Some("http://other:8545"));
+    }
+
+    #[test]
+    fn test_placeholder_expansion() {
+        let file = config_file(
+            "private_key = \"${PAYMASTER_PRIVATE_KEY}\"\npolicy_file = \"${POLICY_DIR:-config}/policies.toml\"",
+        );
+
+        let manager = ConfigurationManager::with_env(file.path(), env(&[]));
+        let config = manager.resolve(&CliOverrides::default()).unwrap();
+        // The placeholder is never taken as the key itself
+        assert!(config.paymaster_relay.private_key.is_none());
+        assert!(config.settings.paymaster_private_key.is_none());
+        assert_eq!(
+            config.settings.policy_file.value,
+            PathBuf::from("config/policies.toml")
+        );
+
+        let cli = CliOverrides {
+            require_paymaster: true,
+            ..Default::default()
+        };
+        let errors = manager.resolve(&cli).unwrap_err();
+        assert!(errors.0.iter().any(|e| e.contains(
+            "paymaster_relay.private_key: environment variable PAYMASTER_PRIVATE_KEY is not set"
+        )));
+    }
+
+    #[test]
+    fn test_all_problems_reported_together() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            "{}\n[paymaster_relay]\n[gateway]\nentry_points = [\"0xnot-an-address\"]\n",
+            BASE_CONFIG
+        )
+        .unwrap();
+        let manager = ConfigurationManager::with_env(
+            file.path(),
+            env(&[
+                ("NODE_HTTP", "localhost:8545"),
+                ("PAYMASTER_POLICY_FILE", "/nonexistent.toml"),
+            ]),
+        );
+        let cli = CliOverrides {
+            require_paymaster: true,
+            require_signer_keys: true,
+            ..Default::default()
+        };
+
+        let errors = manager.resolve(&cli).unwrap_err();
+        assert_eq!(errors.0.len(), 5, "{}", errors);
+        let message = errors.to_string();
+        for expected in [
+            "node_http",
+            "0xnot-an-address",
+            "Paymaster private key is not set",
+            "/nonexistent.toml",
+            SIGNER_KEYS_VAR,
+        ] {
+            assert!(
+                message.contains(expected),
+                "{} missing from {}",
+                expected,
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_builder_keys_split_and_checked() {
+        let file = config_file("");
+        let keys = format!("{}, {},", KEY, OTHER_KEY);
+        let cli = CliOverrides {
+            require_builder_keys: true,
+            ..Default::default()
+        };
+
+        let config = ConfigurationManager::with_env(file.path(), env(&[(SIGNER_KEYS_VAR, &keys)]))
+            .resolve(&cli)
+            .unwrap();
+        let bundler_keys = config.settings.bundler_keys().unwrap();
+        assert_eq!(bundler_keys.len(), 2);
+        assert_eq!(bundler_keys[1].expose_secret(), OTHER_KEY);
+
+        let keys = format!("{},0x1234", KEY);
+        let errors = ConfigurationManager::with_env(file.path(), env(&[(SIGNER_KEYS_VAR, &keys)]))
+            .resolve(&cli)
+            .unwrap_err();
+        assert_eq!(errors.0.len(), 1, "{}", errors);
+        assert!(errors.0[0].contains("Bundler signer key 2"));
+
+        let errors = ConfigurationManager::with_env(file.path(), env(&[]))
+            .resolve(&cli)
+            .unwrap_err();
+        assert!(errors.0[0].contains(SIGNER_KEYS_VAR));
+    }
+
+    #[test]
+    fn test_chain_spec_file_setting() {
+        let file = config_file("");
+        let mut spec = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        write!(
+            spec,
+            "base = \"optimism\"\nname = \"OP Testnet\"\nid = 11155420\n"
+        )
+        .unwrap();
+        let spec_path = spec.path().to_str().unwrap().to_string();
+
+        // A spec file alone is not layered over the default network
+        let manager =
+            ConfigurationManager::with_env(file.path(), env(&[(CHAIN_SPEC_VAR, &spec_path)]));
+        let config = manager.resolve(&CliOverrides::default()).unwrap();
+        let chain_spec = config.settings.chain_spec.as_ref().unwrap();
+        assert_eq!(chain_spec.value, spec_path);
+        assert_eq!(chain_spec.source, Source::Env(CHAIN_SPEC_VAR.to_string()));
+        assert_eq!(config.settings.spec_network(), None);
+
+        let cli = CliOverrides {
+            network: Some("optimism".to_string()),
+            chain_spec: Some(spec_path.clone()),
+            ..Default::default()
+        };
+        let config = manager.resolve(&cli).unwrap();
+        assert_eq!(
+            config.settings.chain_spec.as_ref().unwrap().source,
+            Source::Cli("--chain_spec")
+        );
+        assert_eq!(config.settings.spec_network(), Some("optimism"));
+
+        // Unknown networks fail startup instead of falling back to defaults
+        let cli = CliOverrides {
+            network: Some("sepolia".to_string()),
+            ..Default::default()
+        };
+        let errors = manager.resolve(&cli).unwrap_err();
+        assert!(errors
+            .0
+            .iter()
+            .any(|e| e.contains("Unknown network 'sepolia'")));
+    }
+
+    #[test]
+    fn test_missing_required_field_names_variable() {
+        let mut file = config_file("");
+        write!(
+            file,
+            "[[chains]]\nchain_id = 11155111\nnode_http = \"${{SEPOLIA_RPC}}\"\nentry_points = []\n"
+        )
+        .unwrap();
+
+        let errors = ConfigurationManager::with_env(file.path(), env(&[]))
+            .resolve(&CliOverrides::default())
+            .unwrap_err();
+        assert!(errors.0.iter().any(|e| e.contains("node_http")));
+        assert!(errors
+            .0
+            .iter()
+            .any(|e| e.contains("chains.node_http: environment variable SEPOLIA_RPC is not set")));
+    }
+
+    #[test]
+    fn test_show_redacts_secrets() {
+        let file = config_file(&format!("private_key = \"{}\"", KEY));
+        let manager = ConfigurationManager::with_env(file.path(), env(&[]));
+
+        let (shown, errors) = manager.show(&CliOverrides::default(), true).unwrap();
+        assert!(errors.is_empty());
+        assert!(!shown.contains(&KEY[2..]), "{}", shown);
+        assert!(shown.contains("[effective.paymaster_private_key]"));
+        assert!(shown.contains("source = \"config paymaster_relay.private_key\""));
+        assert!(shown.contains("source = \"default\""));
+
+        let (shown, _) = manager.show(&CliOverrides::default(), false).unwrap();
+        assert!(shown.contains(KEY));
+    }
+
+    #[test]
+    fn test_secrets_file_over_environment() {
+        let file = config_file("private_key = \"${PAYMASTER_PRIVATE_KEY}\"");
+        let passphrase = SecretString::new("correct horse".into());
+        let params = KdfParams::Argon2id {
+            memory_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let encrypted = Secrets::new(env(&[(PAYMASTER_KEY_VAR, KEY), (SIGNER_KEYS_VAR, KEY)]))
+            .encrypt(&passphrase, params)
+            .unwrap();
+        let mut secrets_file = NamedTempFile::new().unwrap();
+        write!(secrets_file, "{}", encrypted).unwrap();
+
+        let secrets = Secrets::load(secrets_file.path(), &passphrase).unwrap();
+        let manager = ConfigurationManager::with_env(
+            file.path(),
+            env(&[
+                (PAYMASTER_KEY_VAR, OTHER_KEY),
+                ("NETWORK", "ethereum_sepolia"),
+            ]),
+        )
+        .with_secrets(&secrets);
+        let cli = CliOverrides {
+            require_signer_keys: true,
+            ..Default::default()
+        };
+        let config = manager.resolve(&cli).unwrap();
+        assert_eq!(
+            paymaster_key(&config),
+            (
+                KEY.to_string(),
+                Source::Secrets(PAYMASTER_KEY_VAR.to_string())
+            )
+        );
+        let signer_keys = config.settings.signer_private_keys.as_ref().unwrap();
+        assert_eq!(signer_keys.value.expose_secret(), KEY);
+        assert_eq!(
+            signer_keys.source,
+            Source::Secrets(SIGNER_KEYS_VAR.to_string())
+        );
+        // Placeholders expand from the secrets file too; other variables still resolve
+        assert_eq!(config.paymaster_relay.private_key.as_deref(), Some(KEY));
+        assert_eq!(config.settings.network.value, "ethereum_sepolia");
+
+        // A modified file fails before any setting resolves
+        let tampered = encrypted.replacen("\"ciphertext\": \"", "\"ciphertext\": \"00", 1);
+        write!(secrets_file.reopen().unwrap(), "{}", tampered).unwrap();
+        let err = Secrets::load(secrets_file.path(), &passphrase).unwrap_err();
+        assert!(err.to_string().contains("authentication failed"), "{}", err);
+    }
+}
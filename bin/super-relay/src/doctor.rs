@@ -0,0 +1,549 @@
+// super-relay doctor: 加载与 gateway/dual-service 相同的配置并做真实检查
+// 每项检查单独超时，失效的RPC不会让命令卡住
+
+use std::{collections::HashMap, future::Future, net::TcpListener, time::Duration};
+
+use alloy_primitives::{Address, Bytes, U256};
+use eyre::Result;
+use rundler_paymaster_relay::{
+    policy::PolicyEngine,
+    signer::{SignerBackendKind, SignerManager},
+};
+use rundler_provider::EvmProvider;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{
+    config_system::ConfigurationManager, default_chain_settings, verify_node_chain_id, Cli,
+    SuperRelayConfig,
+};
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Result of one doctor check
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    /// A failed critical check makes `doctor` exit with a non-zero code
+    pub critical: bool,
+    pub detail: String,
+}
+
+/// All checks of one doctor run, in the order they ran
+#[derive(Debug, Default)]
+pub struct DoctorReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl DoctorReport {
+    fn push(&mut self, name: String, status: CheckStatus, critical: bool, detail: String) {
+        self.checks.push(CheckResult {
+            name,
+            status,
+            critical,
+            detail,
+        });
+    }
+
+    fn pass(&mut self, name: String, detail: String) {
+        self.push(name, CheckStatus::Pass, false, detail);
+    }
+
+    fn warn(&mut self, name: String, detail: String) {
+        self.push(name, CheckStatus::Warn, false, detail);
+    }
+
+    fn fail(&mut self, name: String, critical: bool, detail: String) {
+        self.push(name, CheckStatus::Fail, critical, detail);
+    }
+
+    fn count(&self, status: CheckStatus) -> usize {
+        self.checks.iter().filter(|c| c.status == status).count()
+    }
+
+    /// Whether any critical check failed
+    pub fn has_critical_failure(&self) -> bool {
+        self.checks
+            .iter()
+            .any(|c| c.critical && c.status == CheckStatus::Fail)
+    }
+
+    /// Report for `--json`
+    pub fn to_json(&self) -> Value {
+        json!({
+            "ok": !self.has_critical_failure(),
+            "passed": self.count(CheckStatus::Pass),
+            "warnings": self.count(CheckStatus::Warn),
+            "failed": self.count(CheckStatus::Fail),
+            "checks": self.checks,
+        })
+    }
+
+    /// Report as a STATUS / CHECK / DETAIL table with a summary line
+    pub fn render_table(&self) -> String {
+        let width = self
+            .checks
+            .iter()
+            .map(|c| c.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("CHECK".len());
+
+        let mut table = format!("{:<6}  {:<width$}  DETAIL\n", "STATUS", "CHECK");
+        for check in &self.checks {
+            let status = match check.status {
+                CheckStatus::Pass => "PASS",
+                CheckStatus::Warn => "WARN",
+                CheckStatus::Fail if check.critical => "FAIL",
+                CheckStatus::Fail => "fail",
+            };
+            table.push_str(&format!(
+                "{:<6}  {:<width$}  {}\n",
+                status, check.name, check.detail
+            ));
+        }
+        table.push_str(&format!(
+            "\n{} passed, {} warnings, {} failed{}\n",
+            self.count(CheckStatus::Pass),
+            self.count(CheckStatus::Warn),
+            self.count(CheckStatus::Fail),
+            if self.has_critical_failure() {
+                " (critical)"
+            } else {
+                ""
+            }
+        ));
+        table
+    }
+}
+
+/// Doctor settings from the command line
+#[derive(Debug, Clone)]
+pub struct DoctorOptions {
+    /// Time allowed for each network check
+    pub timeout: Duration,
+    /// The paymaster address should hold at least this much ETH
+    pub min_balance_wei: U256,
+}
+
+/// One chain served by the configuration
+struct ChainTarget {
+    label: Option<String>,
+    node_http: String,
+    chain_id: u64,
+    entry_points: Vec<Address>,
+    signer_key_env: Option<String>,
+}
+
+impl ChainTarget {
+    /// Check name, qualified by the chain label when [[chains]] are configured
+    fn check_name(&self, check: &str) -> String {
+        match &self.label {
+            Some(label) => format!("{}[{}]", check, label),
+            None => check.to_string(),
+        }
+    }
+}
+
+/// Run all checks against the configuration at `config_path`
+pub async fn run(cli: &Cli, config_path: &str, options: &DoctorOptions) -> DoctorReport {
+    let mut report = DoctorReport::default();
+
+    let config = match ConfigurationManager::new(config_path).resolve(&cli.overrides()) {
+        Ok(config) => {
+            report.pass("config".to_string(), format!("{} parsed", config_path));
+            config
+        }
+        Err(e) => {
+            report.fail("config".to_string(), true, e.to_string());
+            return report;
+        }
+    };
+
+    check_ports(&config, &mut report);
+
+    let paymaster_enabled = config.paymaster_relay.enabled.unwrap_or(false);
+    if paymaster_enabled {
+        check_policy(&config, &mut report);
+    } else {
+        report.warn(
+            "paymaster".to_string(),
+            "[paymaster_relay] enabled is not true, paymaster checks skipped".to_string(),
+        );
+    }
+
+    let targets = match chain_targets(&config) {
+        Ok(targets) => targets,
+        Err(e) => {
+            report.fail("chains".to_string(), true, e.to_string());
+            return report;
+        }
+    };
+
+    // 同一个密钥只检查一次 (KMS密钥或同一环境变量被多条链共用)
+    let mut paymasters: HashMap<Option<String>, Option<Address>> = HashMap::new();
+    for target in &targets {
+        let paymaster = if !paymaster_enabled {
+            None
+        } else {
+            let key_env = match config.signer.backend {
+                SignerBackendKind::AwsKms => None,
+                SignerBackendKind::PrivateKey => target.signer_key_env.clone(),
+            };
+            match paymasters.get(&key_env) {
+                Some(paymaster) => *paymaster,
+                None => {
+                    let paymaster =
+                        check_paymaster_key(&config, key_env.as_deref(), options, &mut report)
+                            .await;
+                    paymasters.insert(key_env, paymaster);
+                    paymaster
+                }
+            }
+        };
+        check_chain(&config, target, paymaster, options, &mut report).await;
+    }
+
+    report
+}
+
+/// Chains the gateway would serve: every [[chains]] entry, or the default chain
+fn chain_targets(config: &SuperRelayConfig) -> Result<Vec<ChainTarget>> {
+    if config.chains.is_empty() {
+        let (_, node_http, eth_config) = default_chain_settings(config)?;
+        return Ok(vec![ChainTarget {
+            label: None,
+            node_http,
+            chain_id: eth_config.chain_id,
+            entry_points: eth_config.entry_points,
+            signer_key_env: None,
+        }]);
+    }
+
+    config
+        .chains
+        .iter()
+        .map(|chain| {
+            let eth_config = chain.to_eth_api_config()?;
+            Ok(ChainTarget {
+                label: Some(chain.label()),
+                node_http: chain.node_http.clone(),
+                chain_id: eth_config.chain_id,
+                entry_points: eth_config.entry_points,
+                signer_key_env: chain.signer_key_env.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Gateway and rundler ports must not already be bound
+fn check_ports(config: &SuperRelayConfig, report: &mut DoctorReport) {
+    let mut ports = vec![("gateway_port", config.dual_service.gateway_port)];
+    if config.dual_service.enable_rundler_rpc {
+        ports.push(("rundler_port", config.dual_service.rundler_port));
+    }
+
+    for (name, port) in ports {
+        match port_available(port) {
+            Ok(()) => report.pass(name.to_string(), format!("port {} is free", port)),
+            Err(e) => report.fail(name.to_string(), true, e.to_string()),
+        }
+    }
+}
+
+fn port_available(port: u16) -> Result<()> {
+    TcpListener::bind(("0.0.0.0", port))
+        .map(drop)
+        .map_err(|e| eyre::eyre!("port {} is unavailable: {}", port, e))
+}
+
+/// The policy file the paymaster loads must parse
+fn check_policy(config: &SuperRelayConfig, report: &mut DoctorReport) {
+    let path = &config.settings.policy_file.value;
+    match PolicyEngine::new(&path) {
+        Ok(engine) if engine.policy_count() == 0 => report.warn(
+            "policy_file".to_string(),
+            format!(
+                "{} has no policies, every sponsorship is rejected",
+                path.display()
+            ),
+        ),
+        Ok(engine) => report.pass(
+            "policy_file".to_string(),
+            format!(
+                "{} parsed with {} policies",
+                path.display(),
+                engine.policy_count()
+            ),
+        ),
+        Err(e) => report.fail(
+            "policy_file".to_string(),
+            true,
+            format!("{}: {}", path.display(), e),
+        ),
+    }
+}
+
+/// Load the paymaster signer and return its address
+///
+/// With the aws-kms backend this also checks that KMS responds.
+async fn check_paymaster_key(
+    config: &SuperRelayConfig,
+    key_env: Option<&str>,
+    options: &DoctorOptions,
+    report: &mut DoctorReport,
+) -> Option<Address> {
+    let (name, signer) = match config.signer.backend {
+        SignerBackendKind::AwsKms => (
+            "kms".to_string(),
+            timed(options.timeout, async {
+                SignerManager::new_with_aws_kms(config.signer.to_kms_config())
+                    .await
+                    .map_err(|e| eyre::eyre!("AWS KMS signer unavailable: {}", e))
+            })
+            .await,
+        ),
+        SignerBackendKind::PrivateKey => {
+            let name = match key_env {
+                Some(var) => format!("paymaster_key[{}]", var),
+                None => "paymaster_key".to_string(),
+            };
+            let signer = config.settings.paymaster_key(key_env).and_then(|key| {
+                SignerManager::new(key)
+                    .map_err(|e| eyre::eyre!("Paymaster private key does not parse: {}", e))
+            });
+            (name, signer)
+        }
+    };
+
+    match signer {
+        Ok(signer) => {
+            let address = Address::from_slice(signer.address().as_bytes());
+            report.pass(name, format!("paymaster address {}", address));
+            Some(address)
+        }
+        Err(e) => {
+            report.fail(name, true, e.to_string());
+            None
+        }
+    }
+}
+
+/// Node, EntryPoint contracts and paymaster funds of one chain
+async fn check_chain(
+    config: &SuperRelayConfig,
+    target: &ChainTarget,
+    paymaster: Option<Address>,
+    options: &DoctorOptions,
+    report: &mut DoctorReport,
+) {
+    let node = target.check_name("node");
+    let provider = match rundler_provider::new_alloy_evm_provider(
+        &target.node_http,
+        options.timeout.as_secs().max(1),
+    ) {
+        Ok(provider) => provider,
+        Err(e) => {
+            report.fail(node, true, format!("{}: {}", target.node_http, e));
+            return;
+        }
+    };
+    match timed(
+        options.timeout,
+        verify_node_chain_id(&provider, target.chain_id),
+    )
+    .await
+    {
+        Ok(()) => report.pass(
+            node,
+            format!(
+                "{} reachable, chain id {}",
+                target.node_http, target.chain_id
+            ),
+        ),
+        Err(e) => {
+            // Nothing else on this chain can be checked without the node
+            report.fail(node, true, format!("{}: {}", target.node_http, e));
+            return;
+        }
+    }
+
+    for entry_point in &target.entry_points {
+        let name = target.check_name(&format!("entry_point {}", entry_point));
+        let code = timed(options.timeout, async {
+            provider
+                .get_code(*entry_point, None)
+                .await
+                .map_err(|e| eyre::eyre!("eth_getCode failed: {}", e))
+        })
+        .await;
+        match code {
+            Ok(code) if code.is_empty() => {
+                report.fail(name, true, "no contract code at this address".to_string())
+            }
+            Ok(code) => report.pass(name, format!("{} bytes of code", code.len())),
+            Err(e) => report.fail(name, true, e.to_string()),
+        }
+    }
+
+    let Some(paymaster) = paymaster else {
+        return;
+    };
+
+    let name = target.check_name("paymaster_balance");
+    let balance = timed(options.timeout, async {
+        provider
+            .get_balance(paymaster, None)
+            .await
+            .map_err(|e| eyre::eyre!("eth_getBalance failed: {}", e))
+    })
+    .await;
+    match balance {
+        Ok(balance) if balance < options.min_balance_wei => report.warn(
+            name,
+            format!(
+                "{} wei, below the minimum of {} wei",
+                balance, options.min_balance_wei
+            ),
+        ),
+        Ok(balance) => report.pass(name, format!("{} wei", balance)),
+        Err(e) => report.fail(name, false, e.to_string()),
+    }
+
+    let min_deposit = config.gateway.health.min_paymaster_deposit_wei;
+    for entry_point in &target.entry_points {
+        let name = target.check_name(&format!("paymaster_deposit {}", entry_point));
+        match timed(
+            options.timeout,
+            deposit_of(&provider, *entry_point, paymaster),
+        )
+        .await
+        {
+            Ok(deposit) if deposit < min_deposit => report.warn(
+                name,
+                format!("{} wei, below the minimum of {} wei", deposit, min_deposit),
+            ),
+            Ok(deposit) => report.pass(name, format!("{} wei", deposit)),
+            Err(e) => report.fail(name, false, e.to_string()),
+        }
+    }
+}
+
+/// Deposit of `account` on the EntryPoint, read with `balanceOf(address)`
+async fn deposit_of<P: EvmProvider>(
+    provider: &P,
+    entry_point: Address,
+    account: Address,
+) -> Result<U256> {
+    let mut data = vec![0x70, 0xa0, 0x82, 0x31];
+    data.extend_from_slice(account.into_word().as_slice());
+    let result: Bytes = provider
+        .request(
+            "eth_call",
+            (
+                json!({ "to": entry_point, "data": Bytes::from(data) }),
+                "latest",
+            ),
+        )
+        .await
+        .map_err(|e| eyre::eyre!("balanceOf call failed: {}", e))?;
+    if result.len() != 32 {
+        eyre::bail!("unexpected balanceOf result {}", result);
+    }
+    Ok(U256::from_be_slice(&result))
+}
+
+/// Run `check`, failing it once `timeout` elapses
+async fn timed<T>(timeout: Duration, check: impl Future<Output = Result<T>>) -> Result<T> {
+    tokio::time::timeout(timeout, check)
+        .await
+        .map_err(|_| eyre::eyre!("timed out after {}s", timeout.as_secs()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn test_only_critical_failures_fail_the_run() {
+        let mut report = DoctorReport::default();
+        report.pass("config".to_string(), "ok".to_string());
+        report.warn("paymaster_balance".to_string(), "low".to_string());
+        report.fail(
+            "paymaster_deposit".to_string(),
+            false,
+            "rpc error".to_string(),
+        );
+        assert!(!report.has_critical_failure());
+        assert_eq!(report.to_json()["ok"], true);
+
+        report.fail("node".to_string(), true, "unreachable".to_string());
+        assert!(report.has_critical_failure());
+
+        let json = report.to_json();
+        assert_eq!(json["ok"], false);
+        assert_eq!(json["failed"], 2);
+        assert_eq!(json["checks"][3]["status"], "fail");
+        assert_eq!(json["checks"][3]["critical"], true);
+    }
+
+    #[test]
+    fn test_table_lists_every_check() {
+        let mut report = DoctorReport::default();
+        report.pass(
+            "config".to_string(),
+            "config/config.toml parsed".to_string(),
+        );
+        report.fail("gateway_port".to_string(), true, "in use".to_string());
+
+        let table = report.render_table();
+        assert!(table.starts_with("STATUS"));
+        assert!(table.contains("PASS    config"));
+        assert!(table.contains("FAIL    gateway_port"));
+        assert!(table.contains("1 passed, 0 warnings, 1 failed (critical)"));
+    }
+
+    #[test]
+    fn test_bound_port_reported() {
+        let listener = TcpListener::bind(("0.0.0.0", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(port_available(port).is_err());
+
+        drop(listener);
+        assert!(port_available(port).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_check_times_out() {
+        let result = timed(Duration::from_millis(10), async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        })
+        .await;
+        assert!(result.unwrap_err().to_string().contains("timed out"));
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_config_is_critical() {
+        let cli = Cli::parse_from(["super-relay", "version"]);
+        let options = DoctorOptions {
+            timeout: Duration::from_secs(1),
+            min_balance_wei: U256::ZERO,
+        };
+
+        let report = run(&cli, "/nonexistent/config.toml", &options).await;
+        assert_eq!(report.checks.len(), 1);
+        assert_eq!(report.checks[0].name, "config");
+        assert!(report.has_critical_failure());
+    }
+}
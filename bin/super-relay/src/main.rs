@@ -3,36 +3,173 @@
 
 #![allow(unused_imports, unused_variables)]
 
-use std::{fs, path::Path, process::Command, sync::Arc};
+// Shared with the rundler CLI so both binaries resolve --network / --chain_spec alike
+#[path = "../../rundler/src/cli/chain_spec.rs"]
+mod bootstrap;
+mod chain_spec;
+mod config_system;
+mod doctor;
+mod rundler_service;
+mod secrets_file;
+mod status;
 
-use clap::{Parser, Subcommand};
+use std::{collections::HashMap, process::Command, sync::Arc, time::Duration};
+
+use alloy_primitives::{Address, U256};
+
+use clap::{builder::PossibleValuesParser, Parser, Subcommand};
 use eyre::Result;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{WithExportConfig, WithTonicConfig};
+use rundler_builder::{BuilderEvent, LocalBuilderHandle};
 use rundler_paymaster_relay::{
-    policy::PolicyEngine, service::PaymasterRelayService, signer::SignerManager, start_api_server,
+    attestation::{AttestationConfig, Attestor},
+    audit::{AuditLogConfig, AuditLogger},
+    balance_monitor::{BalanceMonitor, BalanceMonitorConfig},
+    clock_skew::{BlockTimestampSource, ClockGuard, ClockSkewConfig, ProviderBlockTimestamps},
+    cors::CorsConfig,
+    deposit::{DepositChain, DepositConfig, DepositManager, ProviderDepositChain},
+    entry_points::{
+        EntryPointRouting, PaymasterContractConfig, PaymasterContractReader, ProviderContractReader,
+    },
+    fees::FeeChecker,
+    idempotency::IdempotencyConfig,
+    kms::KmsConfig,
+    outbound_tx::{OutboundChain, OutboundTxConfig, OutboundTxManager},
+    policy::PolicyEngine,
+    policy_shadow::PolicyShadowConfig,
+    proxy_client::{ProxyClientConfig, SuperRelayProxyClient},
+    sbt::{ProviderTokenBalances, SBTValidator, SBTValidatorConfig, TokenBalanceReader},
+    sender_lock::SenderLockConfig,
+    service::{PaymasterGasConfig, PaymasterRelayService, ValidityConfig},
+    signature_cache::SignatureCacheConfig,
+    signer::{KeySource, SignerBackendKind, SignerManager},
+    simulation::{EntryPointSimulator, ValidationSimulator},
+    sponsorship_records::{SponsorshipRecordConfig, SponsorshipRecords},
+    start_api_server,
+    token_paymaster::{PriceOracle, ProviderPriceOracle, TokenPaymasterConfig, TokenPricing},
+    usage::{InMemoryUsageStore, UsageConfig, UsageStore},
+    verification_proof::{VerificationProofConfig, VerificationProofStore},
     PaymasterRelayApiServerImpl,
 };
-use rundler_pool::{LocalPoolBuilder, LocalPoolHandle};
+use rundler_pool::LocalPoolHandle;
 use rundler_provider::{
     new_alloy_da_gas_oracle, new_alloy_provider, new_fee_estimator, AlloyEntryPointV0_6,
-    AlloyEntryPointV0_7, AlloyEvmProvider,
+    AlloyEntryPointV0_7, AlloyEvmProvider, EntryPoint, EvmProvider, FeeEstimator,
 };
-use rundler_types::PriorityFeeMode;
-use secrecy::SecretString;
+use rundler_sim::{GasEstimatorV0_6, GasEstimatorV0_7};
+use rundler_types::{chain::ChainSpec, PriorityFeeMode};
+use rundler_utils::emit::WithEntryPoint;
+use secrecy::ExposeSecret;
 use serde::Deserialize;
-use super_relay_gateway::{router::EthApiConfig, GatewayConfig, PaymasterGateway};
-use tokio::task::JoinHandle;
-use tracing::{error, info};
+use super_relay_gateway::{
+    alerts::deliver_to_sinks, detect_entry_point_version, router::EthApiConfig, rpc_methods,
+    rpc_schema, AccessControlConfig, AdminAuthConfig, AdminListenerConfig, Alert, AlertBus,
+    AlertConfig, AlertKind, ApiKeyConfig, BatchSponsorshipConfig, BlockHeadSource,
+    BlockNumberSource, BundleSimulator, BundleSimulators, BundleTracker, BundleTrackerConfig,
+    ChainIdProbe, ChainRoute, CompressionConfig, ConditionalSendConfig, ConnectionLimitConfig,
+    ConnectionLimiter, ContractCodeReader, DepositProbe, DepositReader, EntryPointBundleSimulator,
+    EntryPointCheckConfig, EntryPointCodeProbe, EventStreamConfig, EvmBlockHeadSource,
+    EvmBlockNumberSource, EvmCodeReader, EvmContractSignatureReader, EvmNonceReader,
+    EvmReceiptProvider, GasEstimator, GasPriceConfig, GasPriceOracle, GatewayConfig, GatewayRouter,
+    HealthConfig, HealthProbe, HedgedEvmProvider, HedgedReadConfig, MetricsConfig, ModulePipeline,
+    NodeProbe, NonceReader, OpPermissionsConfig, PaymasterGateway, PipelineConfig,
+    PoolSnapshotConfig, PoolSnapshotter, PoolSupervisor, PoolSupervisorConfig, PreflightConfig,
+    PrometheusHandle, ReceiptEventSource, RequestLogConfig, ResponseCache, ResponseCacheConfig,
+    SenderAccessControl, ShutdownController, SignatureValidator, SimGasEstimator, SupervisedPool,
+    ThreatFeedConfig, ThreatIntelProbe, ThreatIntelStore, UpstreamProbe,
+    UserOperationReceiptProvider, ValidationConfig, VersionSelector,
+    MAX_BUNDLE_BLOCK_GAS_LIMIT_RATIO,
+};
+use tokio::{sync::broadcast, task::JoinHandle};
+use tracing::{error, info, warn};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use crate::{
+    config_system::{CliOverrides, ConfigurationManager, ResolvedSettings},
+    rundler_service::{
+        estimation_settings, BundleBuilderLauncher, EnabledEntryPoints, ProvidersBuilderLauncher,
+        ProvidersPoolLauncher, ProvidersRpcLauncher, RundlerRpcLauncher, SuperRelayProviders,
+    },
+};
+
+/// Builder事件广播通道容量
+const BUILDER_EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Pool事件广播通道容量
+const POOL_EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// 退出前等待Pool快照写入的最长时间
+const POOL_SNAPSHOT_WAIT: Duration = Duration::from_secs(10);
 
 /// 双服务共享组件架构
 /// 支持 Gateway(3000端口) + Rundler(3001端口) 双服务模式
 #[derive(Clone)]
 pub struct SharedRundlerComponents {
-    /// 共享的Pool组件句柄
-    pub pool: Arc<LocalPoolHandle>,
+    /// 共享的Pool句柄，Pool任务退出后由PoolSupervisor重启并切换到新句柄
+    pub pool: SupervisedPool,
     /// 共享的Provider配置
     pub provider_config: Arc<ProviderConfig>,
     /// 共享的配置信息
     pub rundler_config: Arc<RundlerServiceConfig>,
+    /// 链上UserOperation收据查询
+    pub receipt_provider: Arc<dyn UserOperationReceiptProvider>,
+    /// EntryPoint nonce查询 (eth_getUserOperationNonce)
+    pub nonce_reader: Arc<dyn NonceReader>,
+    /// 合约代码查询，EntryPoint能力检查确认已部署后才对外公布
+    pub code_reader: Arc<dyn ContractCodeReader>,
+    /// 最新区块号，作为Gas估算缓存键的一部分
+    pub block_source: Arc<dyn BlockNumberSource>,
+    /// 最新区块号与哈希，费用建议按区块缓存
+    pub block_head: Arc<dyn BlockHeadSource>,
+    /// 账户签名校验 (ECDSA / ERC-1271 / WebAuthn)
+    pub signature_validator: Arc<SignatureValidator>,
+    /// Gateway与rundler RPC共用的ChainSpec
+    pub chain_spec: Arc<ChainSpec>,
+    /// 支持的EntryPoint (v0.6在前, v0.7在后)
+    pub entry_points: Arc<Vec<Address>>,
+    /// 各EntryPoint的版本 (启动时查询一次)，用于解析UserOperation
+    pub version_selector: Arc<VersionSelector>,
+    /// Rundler RPC服务启动器 (3001端口)
+    pub rundler_rpc: Arc<dyn RundlerRpcLauncher>,
+    /// Bundle Builder启动器，签名密钥取自 SIGNER_PRIVATE_KEYS
+    pub bundle_builder: Arc<dyn BundleBuilderLauncher>,
+    /// 节点就绪探针 (可达且已同步、chain id一致、EntryPoint合约已部署)，启动前置检查同样使用
+    pub node_probes: Vec<Arc<dyn HealthProbe>>,
+    /// 已启用EntryPoint的存款查询，用于Paymaster存款就绪探针
+    pub entry_point_deposits: Vec<(&'static str, Arc<dyn DepositReader>)>,
+    /// Paymaster存款管理使用的链上接口
+    pub deposit_chain: Arc<dyn DepositChain>,
+    /// Paymaster账户发出交易 (存款、提款、自动补充存款) 使用的链上接口
+    pub outbound_chain: Arc<dyn OutboundChain>,
+    /// 共享的费用估算器，赞助前检查UserOperation费用
+    pub fee_estimator: Arc<dyn FeeEstimator>,
+    /// Gas估算 (eth_estimateUserOperationGas)，支持状态覆盖与EIP-7702委托
+    pub gas_estimator: Arc<dyn GasEstimator>,
+    /// Bundle模拟 (superRelay_simulateBundle)，按Builder的方式组装handleOps但不上链
+    pub bundle_simulator: Arc<dyn BundleSimulator>,
+    /// SBT/PNTs余额查询，用于赞助资格检查
+    pub token_balances: Arc<dyn TokenBalanceReader>,
+    /// Paymaster合约代码与签名者查询，用于启动时检查
+    pub paymaster_contracts: Arc<dyn PaymasterContractReader>,
+    /// Chainlink价格预言机查询，用于ERC-20代币支付报价
+    pub price_oracle: Arc<dyn PriceOracle>,
+    /// 最新区块时间戳，用于检测本地时钟偏差
+    pub block_timestamps: Arc<dyn BlockTimestampSource>,
+    /// EntryPoint验证模拟，用于赞助预览 (pm_simulateSponsorship)
+    pub simulator: Arc<dyn ValidationSimulator>,
+    /// [[chains]] 中的链名称，多链时用于区分健康检查项
+    pub chain_label: Option<String>,
+    /// Builder事件广播通道，Gateway据此跟踪Bundle状态
+    pub builder_events: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
+    /// Pool快照：关闭时写入待处理的UserOperation，启动时重放
+    pub pool_snapshot: Arc<PoolSnapshotter>,
+}
+
+/// 默认链以外的链，在Gateway上按chainId路由
+struct ChainServices {
+    components: SharedRundlerComponents,
+    paymaster_service: Option<Arc<PaymasterRelayService>>,
 }
 
 /// Provider配置信息
@@ -61,6 +198,23 @@ pub struct RundlerServiceConfig {
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Network of the default chain, overriding NETWORK / CHAIN_NETWORK
+    #[arg(
+        long,
+        global = true,
+        value_parser = PossibleValuesParser::new(chain_spec::HARDCODED_CHAIN_SPECS)
+    )]
+    network: Option<String>,
+
+    /// Chain spec file of the default chain, layered over the network's spec
+    /// and overriding CHAIN_SPEC
+    #[arg(long = "chain_spec", global = true)]
+    chain_spec: Option<String>,
+
+    /// Node URL of the default chain, overriding NODE_HTTP / ETH_NODE_HTTP / RPC_URL
+    #[arg(long, global = true)]
+    node_http: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -78,6 +232,10 @@ enum Commands {
         /// SuperRelay service URL to connect to
         #[arg(long, default_value = "http://localhost:3000")]
         super_relay_url: String,
+
+        /// Configuration file providing the [cors] policy
+        #[arg(long, default_value = "config/config.toml")]
+        config: String,
     },
     /// 双服务兼容模式 - 启动 Gateway(3000) + Rundler(3001) 双服务
     DualService {
@@ -97,6 +255,10 @@ enum Commands {
         #[arg(long, default_value = "true")]
         enable_rundler_rpc: bool,
 
+        /// Whether to run the bundle builder, signing bundles with SIGNER_PRIVATE_KEYS
+        #[arg(long, default_value = "true")]
+        enable_builder: bool,
+
         /// Enable paymaster service
         #[arg(long)]
         enable_paymaster: bool,
@@ -108,6 +270,14 @@ enum Commands {
         /// Paymaster policy file
         #[arg(long)]
         paymaster_policy_file: Option<String>,
+
+        /// Encrypted secrets file (super-relay secrets encrypt) holding the private keys
+        #[arg(long)]
+        secrets_file: Option<String>,
+
+        /// Bind the gateway port without waiting for the dependency probes (development)
+        #[arg(long)]
+        skip_preflight: bool,
     },
     /// Run the SuperRelay API Gateway (单服务模式，仅Gateway)
     Gateway {
@@ -123,6 +293,10 @@ enum Commands {
         #[arg(long, default_value = "3000")]
         port: u16,
 
+        /// Run the bundle builder, signing bundles with SIGNER_PRIVATE_KEYS
+        #[arg(long)]
+        enable_builder: bool,
+
         /// Enable paymaster service
         #[arg(long)]
         enable_paymaster: bool,
@@ -134,6 +308,14 @@ enum Commands {
         /// Paymaster policy file
         #[arg(long)]
         paymaster_policy_file: Option<String>,
+
+        /// Encrypted secrets file (super-relay secrets encrypt) holding the private keys
+        #[arg(long)]
+        secrets_file: Option<String>,
+
+        /// Bind the gateway port without waiting for the dependency probes (development)
+        #[arg(long)]
+        skip_preflight: bool,
     },
     /// Legacy: Run rundler node (compatibility mode)
     Node {
@@ -165,8 +347,155 @@ enum Commands {
     },
     /// Show version information
     Version,
-    /// Check service status
-    Status,
+    /// 探测配置文件中各服务的 /health 与 /metrics，任一关键服务不可用时退出码为1
+    Status {
+        /// Path to configuration file
+        #[arg(long, default_value = "config/config.toml")]
+        config: String,
+
+        /// Print the report as JSON (for scripts)
+        #[arg(long)]
+        json: bool,
+
+        /// Host the services are reached at
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Time allowed for each probe, in seconds
+        #[arg(long, default_value = "3")]
+        timeout_seconds: u64,
+
+        /// Time allowed for all probes together, in seconds
+        #[arg(long, default_value = "10")]
+        deadline_seconds: u64,
+    },
+    /// 深度诊断: 加载配置并检查节点、EntryPoint、Paymaster密钥与资金、策略文件、端口及KMS
+    Doctor {
+        /// Path to configuration file
+        #[arg(long, default_value = "config/config.toml")]
+        config: String,
+
+        /// Print the report as JSON (for CI)
+        #[arg(long)]
+        json: bool,
+
+        /// Time allowed for each network check, in seconds
+        #[arg(long, default_value = "5")]
+        timeout_seconds: u64,
+
+        /// Minimum ETH balance of the paymaster address, in wei
+        #[arg(long, default_value = "10000000000000000")]
+        min_balance_wei: u128,
+    },
+    /// 发送测试告警到 [gateway.alerts] 配置的所有Webhook，任一失败时退出码为1
+    AlertTest {
+        /// Path to configuration file
+        #[arg(long, default_value = "config/config.toml")]
+        config: String,
+
+        /// Message of the test alert
+        #[arg(long, default_value = "Test alert fired by super-relay alert-test")]
+        message: String,
+    },
+    /// 查看生效配置 (CLI参数 > 环境变量 > 配置文件 > 默认值)
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// 加密/解密密钥文件 (argon2id + AES-256-GCM)，口令取自 SUPER_RELAY_SECRETS_PASSPHRASE
+    Secrets {
+        #[command(subcommand)]
+        command: SecretsCommands,
+    },
+    /// 本地开发工具
+    Dev {
+        #[command(subcommand)]
+        command: DevCommands,
+    },
+    /// 导出网关JSON-RPC方法的OpenAPI文档 (与 GET /rpc-schema 相同)，供CI生成客户端
+    ExportSchema {
+        /// Output file, `-` for stdout
+        #[arg(long, default_value = "schema.json")]
+        out: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the fully resolved configuration and where each setting came from
+    Show {
+        /// Path to configuration file
+        #[arg(long, default_value = "config/config.toml")]
+        config: String,
+
+        /// Replace private keys and other secrets with <redacted>
+        #[arg(long)]
+        redact_secrets: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum DevCommands {
+    /// Deploy EntryPoint v0.6/v0.7, account factories and test accounts on a bare Anvil,
+    /// fund the paymaster and write a ready-to-use config; finished steps are skipped
+    Bootstrap {
+        /// Configuration the generated one is derived from
+        #[arg(long, default_value = "config/config.toml")]
+        template: String,
+
+        /// Generated configuration file
+        #[arg(long, default_value = "config/localnet.toml")]
+        config_out: String,
+
+        /// Generated policy file sponsoring the test accounts
+        #[arg(long, default_value = "config/localnet-policies.toml")]
+        policy_out: String,
+
+        /// Generated dotenv file with the keys and addresses; an existing file
+        /// not written by bootstrap is left unchanged
+        #[arg(long, default_value = ".env")]
+        env_out: String,
+
+        /// Paymaster private key (defaults to Anvil account #1)
+        #[arg(long)]
+        paymaster_private_key: Option<String>,
+
+        /// ETH balance the paymaster address is topped up to, in wei
+        #[arg(long, default_value = "10000000000000000000")]
+        paymaster_balance_wei: u128,
+
+        /// Paymaster deposit on each EntryPoint, topped up to this, in wei
+        #[arg(long, default_value = "10000000000000000000")]
+        deposit_wei: u128,
+
+        /// Gateway URL used in the printed sample request
+        #[arg(long, default_value = "http://localhost:3000")]
+        gateway_url: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SecretsCommands {
+    /// Encrypt a dotenv file of private keys into a secrets file
+    Encrypt {
+        /// Plaintext dotenv file to encrypt
+        #[arg(long, default_value = ".env")]
+        input: String,
+
+        /// Secrets file to write
+        #[arg(long, default_value = "superrelay.secrets")]
+        output: String,
+    },
+    /// Decrypt a secrets file back to dotenv lines
+    Decrypt {
+        /// Secrets file to decrypt
+        #[arg(long, default_value = "superrelay.secrets")]
+        input: String,
+
+        /// File to write the dotenv lines to, instead of stdout
+        #[arg(long)]
+        output: Option<String>,
+    },
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -181,6 +510,436 @@ struct SuperRelayConfig {
     /// 双服务配置 - 新增支持
     #[serde(default)]
     dual_service: DualServiceConfig,
+    /// Gateway链配置 (chain id 与 EntryPoint 列表)
+    #[serde(default)]
+    gateway: GatewaySectionConfig,
+    /// Paymaster签名后端配置
+    #[serde(default)]
+    signer: SignerSectionConfig,
+    /// 多链配置，一个进程按chainId服务多条链
+    #[serde(default)]
+    chains: Vec<ChainSectionConfig>,
+    /// Gateway、Swagger与API测试服务器共用的CORS策略
+    #[serde(default)]
+    cors: CorsConfig,
+    /// API测试服务器访问上游SuperRelay的连接池、超时与重试
+    #[serde(default)]
+    proxy_client: ProxyClientConfig,
+    /// 由CLI参数、环境变量与配置文件解析出的生效设置 (网络、节点、密钥、策略文件)
+    #[serde(skip)]
+    settings: ResolvedSettings,
+}
+
+/// 单条链配置 ([[chains]])
+#[derive(Debug, Clone, Deserialize)]
+struct ChainSectionConfig {
+    /// 链ID，需与节点 eth_chainId 一致
+    chain_id: u64,
+    /// 链名称，用于日志与健康检查，默认为链ID
+    name: Option<String>,
+    /// rundler内置的chain spec名称，与 chain_spec 均未设置时按链ID查找
+    network: Option<String>,
+    /// chain spec文件，叠加在 network 的chain spec之上
+    chain_spec: Option<String>,
+    /// 节点 HTTP RPC 地址
+    node_http: String,
+    /// 备用节点 HTTP RPC 地址，设置后只读请求按 [node.hedging] 对冲到两个节点
+    secondary_node_http: Option<String>,
+    /// 支持的EntryPoint地址列表
+    entry_points: Vec<String>,
+    /// 存放该链Paymaster私钥的环境变量名，默认 PAYMASTER_PRIVATE_KEY
+    signer_key_env: Option<String>,
+}
+
+impl ChainSectionConfig {
+    /// Name used in logs and health probe names
+    fn label(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| self.chain_id.to_string())
+    }
+
+    /// Chain spec of this chain: its network and spec file, else the
+    /// hardcoded spec with this chain id
+    fn chain_spec(&self) -> Result<ChainSpec> {
+        if self.network.is_some() || self.chain_spec.is_some() {
+            return resolve_chain_spec(self.network.as_deref(), self.chain_spec.as_deref());
+        }
+        chain_spec::HARDCODED_CHAIN_SPECS
+            .iter()
+            .copied()
+            .map(|network| resolve_chain_spec(Some(network), None))
+            .find(|spec| spec.as_ref().is_ok_and(|spec| spec.id == self.chain_id))
+            .unwrap_or_else(|| {
+                Err(eyre::eyre!(
+                    "[[chains]] {}: set network or chain_spec, no built-in spec has chain id {}",
+                    self.label(),
+                    self.chain_id
+                ))
+            })
+    }
+
+    /// Build the gateway ETH API config for this chain
+    fn to_eth_api_config(&self) -> Result<EthApiConfig> {
+        let entry_points = self
+            .entry_points
+            .iter()
+            .map(|ep| {
+                ep.parse().map_err(|e| {
+                    eyre::eyre!(
+                        "Invalid EntryPoint address '{}' in [[chains]] {}: {}",
+                        ep,
+                        self.label(),
+                        e
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(EthApiConfig {
+            chain_id: self.chain_id,
+            entry_points,
+            chain_spec: Arc::new(self.chain_spec()?),
+        })
+    }
+}
+
+/// Paymaster签名后端配置
+#[derive(Debug, Default, Deserialize)]
+struct SignerSectionConfig {
+    /// 签名后端: "private-key" (默认) 或 "aws-kms"
+    #[serde(default)]
+    backend: SignerBackendKind,
+    /// AWS KMS 密钥ARN (aws-kms 后端必填)
+    key_arn: Option<String>,
+    /// AWS 区域，未设置时使用默认凭证链的区域
+    region: Option<String>,
+    /// 额外的签名密钥池，赞助请求在各密钥间轮询签名
+    /// (每个密钥都须注册为Paymaster合约的签名者)，如 { env = "VAR" } 或 { kms = "ARN" }
+    #[serde(default)]
+    pool_keys: Vec<KeySource>,
+}
+
+impl SignerSectionConfig {
+    /// Build the KMS configuration for the aws-kms backend
+    fn to_kms_config(&self) -> KmsConfig {
+        let mut kms_config = KmsConfig {
+            primary_key_id: self.key_arn.clone().unwrap_or_default(),
+            backup_key_ids: vec![],
+            aws_key_arn: self.key_arn.clone(),
+            ..Default::default()
+        };
+        kms_config.credentials.region_or_tenant = self.region.clone().unwrap_or_default();
+        kms_config
+    }
+}
+
+/// Gateway链配置
+#[derive(Debug, Default, Deserialize)]
+struct GatewaySectionConfig {
+    /// 链ID，需与节点 eth_chainId 一致
+    chain_id: Option<u64>,
+    /// 支持的EntryPoint地址列表
+    entry_points: Option<Vec<String>>,
+    /// API key认证配置 (key以keccak256哈希存储)
+    #[serde(default)]
+    api_keys: ApiKeyConfig,
+    /// 管理方法的签名请求认证 (管理员ECDSA地址、时间窗口)
+    #[serde(default)]
+    admin_auth: AdminAuthConfig,
+    /// 管理监听端口：启用后管理方法 (admin_*、密钥轮换、黑名单、/admin/*) 只在此端口提供，公共端口返回 method-not-found
+    #[serde(default)]
+    admin: AdminListenerConfig,
+    /// 按API key信任级别授予提交操作的内存池权限 (trusted、每发送者上限、Bundler赞助)，匿名请求始终使用默认权限
+    #[serde(default)]
+    op_permissions: OpPermissionsConfig,
+    /// 条件提交 (superRelay_sendUserOperationConditional)：仅在Bundler以条件交易提交时启用，否则返回 -32004
+    #[serde(default)]
+    conditional_send: ConditionalSendConfig,
+    /// 批量赞助 (pm_sponsorUserOperationBatch)：每批最多操作数，及默认模式 atomic (任一失败即整批拒绝) 或 best_effort
+    #[serde(default)]
+    batch_sponsorship: BatchSponsorshipConfig,
+    /// 启动前置检查：绑定公共端口前重试就绪探针直至通过或超时，并预热缓存；失败时以非零状态退出
+    #[serde(default)]
+    preflight: PreflightConfig,
+    /// EntryPoint能力检查：启动时及按间隔确认合约代码、内存池与Gas估算器 (及Paymaster) 就绪，未通过的EntryPoint不出现在 eth_supportedEntryPoints 中
+    #[serde(default)]
+    entry_point_checks: EntryPointCheckConfig,
+    /// 单个请求的超时秒数 (默认30，0为不限)，超时返回 -32008 并注明执行中的阶段
+    request_timeout_seconds: Option<u64>,
+    /// 按方法覆盖请求超时秒数，如 eth_estimateUserOperationGas 需要更长时间
+    #[serde(default)]
+    method_timeouts: HashMap<String, u64>,
+    /// 关闭时等待进行中请求完成的秒数，默认等于请求超时
+    drain_timeout_seconds: Option<u64>,
+    /// 就绪探针配置 (超时、缓存、最低Paymaster存款)
+    #[serde(default)]
+    health: HealthConfig,
+    /// 请求未指定chainId时使用的链，默认为第一个 [[chains]]
+    default_chain_id: Option<u64>,
+    /// 威胁情报源 (文件/URL) 及刷新间隔
+    #[serde(default)]
+    threat_feeds: ThreatFeedConfig,
+    /// 发送者白名单/黑名单 (内联或文件，文件变更后自动重新加载)
+    #[serde(default)]
+    access_control: AccessControlConfig,
+    /// 赞助检查模块的顺序、开关及 fail_open
+    #[serde(default)]
+    pipeline: PipelineConfig,
+    /// 只读方法响应缓存 (chainId、supportedEntryPoints、Gas估算)
+    #[serde(default)]
+    cache: ResponseCacheConfig,
+    /// 启用 debug_bundler_* 方法 (ERC-4337 bundler-spec-tests) 以及 rundler_sendBundleNow、
+    /// rundler_setReputation，生产环境保持关闭
+    #[serde(default)]
+    enable_debug_api: bool,
+    /// Bundle跟踪 (保留的Bundle/UserOperation数量及统计窗口)
+    #[serde(default)]
+    bundle_tracking: BundleTrackerConfig,
+    /// 请求体大小、批量请求数及UserOperation字段的上限，在所有检查之前执行
+    #[serde(default)]
+    validation: ValidationConfig,
+    /// 请求日志：成功请求采样率、错误始终记录、签名/callData等字段以keccak256哈希脱敏
+    #[serde(default)]
+    logging: RequestLogConfig,
+    /// Prometheus指标：全局记录器、Gateway端口上的 /metrics 及独立监听地址
+    #[serde(default)]
+    metrics: MetricsConfig,
+    /// 响应压缩 (gzip/deflate，按Accept-Encoding协商) 及压缩请求体的解压
+    #[serde(default)]
+    compression: CompressionConfig,
+    /// 运维告警：Webhook地址、认证头、重试，按事件类型开关及冷却时间
+    #[serde(default)]
+    alerts: AlertConfig,
+    /// 费用建议 (superRelay_getUserOperationGasPrice) slow/standard/fast 三档相对最低费用的加价百分比
+    #[serde(default)]
+    gas_price: GasPriceConfig,
+    /// 连接数及高开销请求 (模拟、估算) 并发上限，超限返回503及Retry-After，修改配置文件后自动生效
+    #[serde(default)]
+    connection_limits: ConnectionLimitConfig,
+    /// 实时事件流 (GET /admin/events, SSE)：每个订阅者的缓冲事件数及保活间隔
+    #[serde(default)]
+    events: EventStreamConfig,
+}
+
+impl GatewaySectionConfig {
+    /// Build the gateway ETH API config, falling back to the chain spec's id
+    /// and v0.6 EntryPoint for unset values and rejecting EntryPoint addresses
+    /// that do not parse
+    fn to_eth_api_config(&self, chain_spec: ChainSpec) -> Result<EthApiConfig> {
+        let default_entry_point = chain_spec.entry_point_address_v0_6.to_string();
+        let entry_points = match &self.entry_points {
+            Some(entry_points) => entry_points.iter().map(String::as_str).collect(),
+            None => vec![default_entry_point.as_str()],
+        };
+
+        let entry_points = entry_points
+            .into_iter()
+            .map(|ep| {
+                ep.parse().map_err(|e| {
+                    eyre::eyre!("Invalid EntryPoint address '{}' in [gateway]: {}", ep, e)
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(EthApiConfig {
+            chain_id: self.chain_id.unwrap_or(chain_spec.id),
+            entry_points,
+            chain_spec: Arc::new(chain_spec),
+        })
+    }
+
+    /// [gateway.preflight], disabled by `--skip-preflight`
+    fn preflight(&self, skip_preflight: bool) -> PreflightConfig {
+        PreflightConfig {
+            enabled: self.preflight.enabled && !skip_preflight,
+            ..self.preflight.clone()
+        }
+    }
+
+    /// Threat intelligence store refreshed in the background, `None` without feeds
+    fn start_threat_intel(&self) -> Result<Option<Arc<ThreatIntelStore>>> {
+        let feeds = &self.threat_feeds;
+        if feeds.is_empty() {
+            return Ok(None);
+        }
+
+        let store = Arc::new(
+            ThreatIntelStore::from_config(feeds)
+                .map_err(|e| eyre::eyre!("Invalid [gateway.threat_feeds]: {}", e))?,
+        );
+        info!(
+            "🛡️ Threat intelligence: {} file(s), {} URL(s), refresh every {}s",
+            feeds.files.len(),
+            feeds.urls.len(),
+            feeds.refresh_interval_seconds
+        );
+        store.clone().spawn_refresh(
+            Duration::from_secs(feeds.refresh_interval_seconds),
+            Duration::from_secs(feeds.refresh_jitter_seconds),
+        );
+        Ok(Some(store))
+    }
+
+    /// Re-read [gateway.connection_limits] whenever the config file changes,
+    /// so the gateway's limits follow it without a restart
+    fn watch_connection_limits(
+        &self,
+        config_path: &str,
+        limiter: Arc<ConnectionLimiter>,
+    ) -> JoinHandle<()> {
+        let path = std::path::PathBuf::from(config_path);
+        let interval = Duration::from_secs(self.connection_limits.reload_interval_seconds.max(1));
+        tokio::spawn(async move {
+            let modified = |path: &std::path::Path| {
+                std::fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            };
+            let mut last_modified = modified(&path);
+            loop {
+                tokio::time::sleep(interval).await;
+                let current = modified(&path);
+                if current.is_none() || current == last_modified {
+                    continue;
+                }
+                last_modified = current;
+                let limits = std::fs::read_to_string(&path)
+                    .map_err(|e| eyre::eyre!("Failed to read {}: {}", path.display(), e))
+                    .and_then(|content| parse_connection_limits(&content));
+                match limits {
+                    Ok(limits) => {
+                        let summary = format!(
+                            "{} connections, {} expensive requests",
+                            limits.max_connections, limits.max_expensive_requests
+                        );
+                        if limiter.update(limits) {
+                            info!("🚦 Connection limits reloaded: {}", summary);
+                        }
+                    }
+                    Err(e) => warn!("Keeping previous connection limits: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Sender access lists with their files watched for changes, `None` when unconfigured
+    ///
+    /// Admin changes to the lists are journaled to the audit log.
+    fn start_access_control(
+        &self,
+        audit_log: &AuditLogConfig,
+    ) -> Result<Option<Arc<SenderAccessControl>>> {
+        let config = &self.access_control;
+        if !config.is_enabled() {
+            return Ok(None);
+        }
+
+        let access_control = Arc::new(
+            SenderAccessControl::from_config(config)
+                .map_err(|e| eyre::eyre!("Invalid [gateway.access_control]: {}", e))?
+                .with_audit_logger(AuditLogger::new(audit_log)),
+        );
+        let status = access_control.status();
+        info!(
+            "🚧 Sender access control: {} denylisted, {} allowlisted (allowlist mode {})",
+            status.denylist.len(),
+            status.allowlist.len(),
+            if status.allowlist_mode { "on" } else { "off" }
+        );
+        access_control
+            .clone()
+            .spawn_reload(Duration::from_secs(config.reload_interval_seconds));
+        Ok(Some(access_control))
+    }
+
+    /// Alert bus delivering to the configured webhooks, disabled unless enabled
+    fn start_alerts(&self) -> Result<AlertBus> {
+        let alerts = self
+            .alerts
+            .start()
+            .map_err(|e| eyre::eyre!("Invalid [gateway.alerts]: {}", e))?;
+        if alerts.is_enabled() {
+            info!(
+                "🚨 Alerting to {} webhook(s), cooldown {}s",
+                self.alerts.webhooks.len(),
+                self.alerts.cooldown_seconds
+            );
+        }
+        Ok(alerts)
+    }
+
+    /// Install the process-wide Prometheus recorder shared by all components
+    fn install_metrics(&self) -> Result<Option<PrometheusHandle>> {
+        if !self.metrics.enabled {
+            info!("📴 Prometheus metrics disabled");
+            return Ok(None);
+        }
+        let handle = super_relay_gateway::install_prometheus_recorder()
+            .map_err(|e| eyre::eyre!("Failed to set up metrics: {}", e))?;
+        Ok(Some(handle))
+    }
+
+    /// Serve /metrics on the dedicated listen address, if one is configured
+    fn serve_metrics(
+        &self,
+        prometheus: Option<&PrometheusHandle>,
+        shutdown: ShutdownController,
+    ) -> Option<JoinHandle<Result<()>>> {
+        let handle = prometheus?.clone();
+        let addr = self.metrics.listen_address.clone()?;
+        Some(tokio::spawn(async move {
+            super_relay_gateway::serve_metrics(&addr, handle, shutdown)
+                .await
+                .map_err(|e| eyre::eyre!("Metrics server error: {}", e))
+        }))
+    }
+
+    /// Sponsorship check pipeline; unknown or repeated module names fail startup
+    fn module_pipeline(&self) -> Result<Arc<ModulePipeline>> {
+        let pipeline = ModulePipeline::from_config(&self.pipeline)
+            .map_err(|e| eyre::eyre!("Invalid [gateway.pipeline]: {}", e))?;
+        info!(
+            "🧩 Sponsorship pipeline: {}",
+            pipeline.module_names().join(" → ")
+        );
+        Ok(Arc::new(pipeline))
+    }
+
+    /// Response cache of one chain, keying gas estimates by its latest block
+    fn response_cache(&self, block_source: Arc<dyn BlockNumberSource>) -> Arc<ResponseCache> {
+        Arc::new(ResponseCache::new(self.cache.clone()).with_block_source(block_source))
+    }
+
+    /// Fee suggestions from the shared fee estimator of one chain
+    fn gas_price_oracle(
+        &self,
+        components: &SharedRundlerComponents,
+    ) -> Result<Arc<GasPriceOracle>> {
+        let oracle = GasPriceOracle::new(
+            self.gas_price.clone(),
+            components.fee_estimator.clone(),
+            components.block_head.clone(),
+        )
+        .map_err(|e| eyre::eyre!("Invalid [gateway.gas_price]: {}", e))?;
+        Ok(Arc::new(oracle))
+    }
+
+    /// Bundle tracker of one chain, fed by its builder events and updating the
+    /// sponsorship records of its paymaster service
+    fn bundle_tracker(
+        &self,
+        builder_events: &broadcast::Sender<WithEntryPoint<BuilderEvent>>,
+        paymaster_service: Option<&Arc<PaymasterRelayService>>,
+    ) -> Arc<BundleTracker> {
+        let mut tracker = BundleTracker::new(self.bundle_tracking.clone());
+        if let Some(records) = paymaster_service.and_then(|service| service.sponsorship_records()) {
+            tracker = tracker.with_sponsorship_records(records.clone());
+        }
+        let tracker = Arc::new(tracker);
+        tracker.subscribe(builder_events.subscribe());
+        tracker
+    }
 }
 
 /// 双服务模式配置
@@ -222,6 +981,17 @@ fn default_gateway_port() -> u16 {
 #[allow(dead_code)]
 struct NodeConfig {
     http_api: Option<String>,
+    /// 默认链的网络名称 (rundler内置的chain spec之一)，可被 --network 与 NETWORK 覆盖
+    network: Option<String>,
+    /// 默认链的chain spec文件，叠加在网络的chain spec之上，可被 --chain_spec 与 CHAIN_SPEC 覆盖
+    chain_spec: Option<String>,
+    /// 默认链的节点地址，可被 --node-http 与 NODE_HTTP 覆盖
+    node_http: Option<String>,
+    /// 默认链的备用节点地址，设置后只读请求对冲到主备两个节点，交易只发往主节点
+    secondary_node_http: Option<String>,
+    /// 主备节点间的只读请求对冲 ([node.hedging])
+    #[serde(default)]
+    hedging: HedgedReadConfig,
     max_entries_per_chain: Option<u32>,
     max_mem_entries_per_chain: Option<u32>,
 }
@@ -232,6 +1002,12 @@ struct PoolConfig {
     max_expire_duration_seconds: Option<u64>,
     max_ops_per_unstaked_sender: Option<u32>,
     throttled_entity_mempool_count: Option<u32>,
+    /// 进程内Pool任务的重启退避 ([pool.supervisor])
+    #[serde(default)]
+    supervisor: PoolSupervisorConfig,
+    /// Pool快照 (pool_snapshot_path、max_snapshot_ops、max_snapshot_age_seconds)：正常关闭时写入，启动时校验后重放，损坏或过期的快照跳过
+    #[serde(flatten)]
+    snapshot: PoolSnapshotConfig,
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -240,6 +1016,21 @@ struct RpcConfig {
     max_verification_gas: Option<u64>,
     max_call_gas: Option<u64>,
     api: Option<Vec<String>>,
+    /// 查询UserOperation事件日志时距最新区块的最大区块数，未设置时从创世区块开始扫描
+    user_operation_event_block_distance: Option<u64>,
+}
+
+impl RpcConfig {
+    /// Namespaces served on the rundler port, defaulting to eth/rundler/debug
+    fn rundler_api(&self) -> Vec<String> {
+        match &self.api {
+            Some(api) => api.clone(),
+            None => rundler_service::DEFAULT_RUNDLER_API
+                .iter()
+                .map(|api| api.to_string())
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -249,6 +1040,137 @@ struct PaymasterRelayConfig {
     private_key: Option<String>,
     policy_file: Option<String>,
     entry_points: Option<Vec<String>>,
+    /// 赞助决策审计日志 ([paymaster_relay.audit_log])
+    #[serde(default)]
+    audit_log: AuditLogConfig,
+    /// EntryPoint存款管理 ([paymaster_relay.deposit])
+    #[serde(default)]
+    deposit: DepositConfig,
+    /// Paymaster账户发出的交易：顺序分配nonce、卡住时提高费用替换、重启后继续跟踪 ([paymaster_relay.outbound_tx])
+    #[serde(default)]
+    outbound_tx: OutboundTxConfig,
+    /// 费用低于最新区块要求时自动提高，而不是拒绝赞助
+    #[serde(default)]
+    auto_bump_fees: bool,
+    /// 密钥轮换后旧签名密钥仍被接受的秒数 (默认为最长赞助有效期)
+    key_rotation_grace_seconds: Option<u64>,
+    /// pm_rotatePaymasterKey 允许读取新密钥的环境变量 (默认不允许任何环境变量)
+    #[serde(default)]
+    key_rotation_env_vars: Vec<String>,
+    /// SBT/PNTs持有者赞助资格检查 ([paymaster_relay.sbt])
+    #[serde(default)]
+    sbt: SBTValidatorConfig,
+    /// 双重签名验证证明存储 ([paymaster_relay.verification_proofs])
+    #[serde(default)]
+    verification_proofs: VerificationProofConfig,
+    /// 幂等键缓存，重放请求返回相同的赞助结果 ([paymaster_relay.idempotency])
+    #[serde(default)]
+    idempotency: IdempotencyConfig,
+    /// 相同UserOperation重复请求的签名缓存，无幂等键时复用签名和有效期 ([paymaster_relay.signature_cache])
+    #[serde(default)]
+    signature_cache: SignatureCacheConfig,
+    /// 同一发送者和nonce的并发赞助串行执行，只签名和计费一次 ([paymaster_relay.sender_locks])
+    #[serde(default)]
+    sender_locks: SenderLockConfig,
+    /// 本地时钟与链上区块时间的偏差检查 ([paymaster_relay.clock_skew])
+    #[serde(default)]
+    clock_skew: ClockSkewConfig,
+    /// 候选策略影子评估，只统计与当前策略的分歧而不执行 ([paymaster_relay.policy_shadow])
+    #[serde(default)]
+    policy_shadow: PolicyShadowConfig,
+    /// 赞助有效期的默认值和客户端可请求的范围 ([paymaster_relay.validity])
+    #[serde(default)]
+    validity: ValidityConfig,
+    /// v0.7赞助的paymaster gas限制，也用于估算gas的dummy paymaster数据 ([paymaster_relay.paymaster_gas])
+    #[serde(default)]
+    paymaster_gas: PaymasterGasConfig,
+    /// 按API密钥统计赞助用量，用于向dApp计费 ([paymaster_relay.usage])
+    #[serde(default)]
+    usage: UsageConfig,
+    /// 用户以ERC-20代币支付Gas的代币Paymaster模式 ([paymaster_relay.token_paymaster])
+    #[serde(default)]
+    token_paymaster: TokenPaymasterConfig,
+    /// Paymaster账户余额与EntryPoint存款监控，可选自动补充存款 ([paymaster_relay.balance_monitor])
+    #[serde(default)]
+    balance_monitor: BalanceMonitorConfig,
+    /// 赞助响应签名证明，使用独立于Paymaster签名密钥的证明密钥 ([paymaster_relay.attestation])
+    #[serde(default)]
+    attestation: AttestationConfig,
+    /// 赞助记录及其链上结果 (已上链/已丢弃/已过期) 的持久化历史 ([paymaster_relay.sponsorship_records])
+    #[serde(default)]
+    sponsorship_records: SponsorshipRecordConfig,
+    /// 各EntryPoint版本部署的Paymaster合约 ([[paymaster_relay.paymasters]])，未配置时不限制EntryPoint
+    #[serde(default)]
+    paymasters: Vec<PaymasterContractConfig>,
+}
+
+impl PaymasterRelayConfig {
+    /// Proof store served by superRelay_getVerificationProof, restored from disk when persisted
+    fn verification_proof_store(&self) -> Result<Arc<VerificationProofStore>> {
+        VerificationProofStore::new(self.verification_proofs.clone())
+            .map(Arc::new)
+            .map_err(|e| eyre::eyre!("Failed to load verification proofs: {}", e))
+    }
+
+    /// Outbound transaction manager of one chain, restoring its pending
+    /// transactions; each chain keeps its own file, named after the chain id
+    fn outbound_txs(
+        &self,
+        chain: Arc<dyn OutboundChain>,
+        fee_estimator: Arc<dyn FeeEstimator>,
+        chain_id: u64,
+    ) -> Result<Arc<OutboundTxManager>> {
+        let mut config = self.outbound_tx.clone();
+        config.path = config.path.map(|path| {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let name = match path.extension() {
+                Some(ext) => format!("{}-{}.{}", stem, chain_id, ext.to_string_lossy()),
+                None => format!("{}-{}", stem, chain_id),
+            };
+            path.with_file_name(name)
+        });
+        info!(
+            "📤 Outbound transactions replaced after {} blocks (+{}% fees), ledger {:?}",
+            config.stuck_after_blocks, config.fee_bump_percent, config.path
+        );
+        OutboundTxManager::new(chain, fee_estimator, config)
+            .map(Arc::new)
+            .map_err(|e| eyre::eyre!("Failed to load outbound transactions: {}", e))
+    }
+
+    /// Usage store shared by the paymaster services of all chains, restored
+    /// from its snapshot and snapshotted in the background when persisted
+    fn usage_store(&self) -> Result<Option<Arc<dyn UsageStore>>> {
+        if !self.usage.enabled {
+            return Ok(None);
+        }
+        let store = Arc::new(
+            InMemoryUsageStore::new(self.usage.clone())
+                .map_err(|e| eyre::eyre!("Failed to load usage snapshot: {}", e))?,
+        );
+        store.spawn_snapshots();
+        info!(
+            "📊 Usage accounting enabled (retention {} days, snapshot {:?})",
+            self.usage.retention_days, self.usage.snapshot_path
+        );
+        Ok(Some(store))
+    }
+
+    /// Sponsorship records shared by the paymaster services of all chains,
+    /// replayed from their file when persisted
+    fn sponsorship_records(&self) -> Result<Option<Arc<SponsorshipRecords>>> {
+        let config = &self.sponsorship_records;
+        if !config.enabled {
+            return Ok(None);
+        }
+        let records = SponsorshipRecords::open(config.clone())
+            .map_err(|e| eyre::eyre!("Failed to load sponsorship records: {}", e))?;
+        info!(
+            "🧾 Sponsorship records enabled (file {:?}, reconcile every {}s)",
+            config.path, config.reconcile_interval_seconds
+        );
+        Ok(Some(Arc::new(records)))
+    }
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -266,25 +1188,66 @@ struct RateLimitingConfig {
     burst_capacity: Option<u32>,
     cleanup_interval_seconds: Option<u64>,
     entry_expiry_seconds: Option<u64>,
+    max_tracked_keys: Option<usize>,
+}
+
+/// 初始化日志；设置 LOG_OTLP_GRPC_ENDPOINT 时同时通过 OTLP 导出 span，
+/// span 字段 (如 request_id) 作为属性上报，便于在 Tempo/Jaeger 中关联
+fn init_tracing() -> Result<()> {
+    let otlp_layer = match std::env::var("LOG_OTLP_GRPC_ENDPOINT") {
+        Ok(endpoint) if !endpoint.is_empty() => {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .with_timeout(Duration::from_secs(3))
+                .build()?;
+            let tracer_provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+                .with_batch_exporter(exporter)
+                .with_resource(
+                    opentelemetry_sdk::Resource::builder()
+                        .with_service_name("super-relay")
+                        .build(),
+                )
+                .build();
+            opentelemetry::global::set_tracer_provider(tracer_provider.clone());
+            Some(tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("super-relay")))
+        }
+        _ => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::from_default_env())
+        .with(tracing_subscriber::fmt::layer())
+        .with(otlp_layer)
+        .try_init()?;
+    Ok(())
 }
 
 impl Cli {
     async fn run(self) -> Result<()> {
         // Initialize tracing
-        tracing_subscriber::fmt()
-            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-            .init();
-
-        // Show SuperRelay branding
-        self.show_banner();
+        init_tracing()?;
+
+        // Show SuperRelay branding (kept out of JSON and TOML output)
+        if !matches!(
+            self.command,
+            Commands::Doctor { json: true, .. }
+                | Commands::Status { json: true, .. }
+                | Commands::Config { .. }
+                | Commands::Secrets { .. }
+                | Commands::ExportSchema { .. }
+        ) {
+            self.show_banner();
+        }
 
         match self.command {
             Commands::ApiServer {
                 ref host,
                 port,
                 ref super_relay_url,
+                ref config,
             } => {
-                self.run_api_server(host.clone(), port, super_relay_url.clone())
+                self.run_api_server(host.clone(), port, super_relay_url.clone(), config)
                     .await?
             }
             Commands::DualService {
@@ -292,18 +1255,24 @@ impl Cli {
                 ref gateway_host,
                 gateway_port,
                 enable_rundler_rpc,
+                enable_builder,
                 enable_paymaster,
                 ref paymaster_private_key,
                 ref paymaster_policy_file,
+                ref secrets_file,
+                skip_preflight,
             } => {
                 self.run_dual_service(
                     config.clone(),
                     gateway_host.clone(),
                     gateway_port,
                     enable_rundler_rpc,
+                    enable_builder,
                     enable_paymaster,
                     paymaster_private_key.clone(),
                     paymaster_policy_file.clone(),
+                    secrets_file.as_deref(),
+                    skip_preflight,
                 )
                 .await?
             }
@@ -311,17 +1280,23 @@ impl Cli {
                 ref config,
                 ref host,
                 port,
+                enable_builder,
                 enable_paymaster,
                 ref paymaster_private_key,
                 ref paymaster_policy_file,
+                ref secrets_file,
+                skip_preflight,
             } => {
                 self.run_gateway(
                     config.clone(),
                     host.clone(),
                     port,
+                    enable_builder,
                     enable_paymaster,
                     paymaster_private_key.clone(),
                     paymaster_policy_file.clone(),
+                    secrets_file.as_deref(),
+                    skip_preflight,
                 )
                 .await?
             }
@@ -331,15 +1306,10 @@ impl Cli {
             } => {
                 println!("🚀 Starting SuperRelay Node...\n");
 
-                // Parse TOML configuration with environment variable expansion
-                let config_content = fs::read_to_string(config)
-                    .map_err(|e| eyre::eyre!("Failed to read config file '{}': {}", config, e))?;
-
-                // Expand environment variables in config content
-                let expanded_content = expand_env_vars(&config_content);
-
-                let _super_config: SuperRelayConfig = toml::from_str(&expanded_content)
-                    .map_err(|e| eyre::eyre!("Failed to parse config file: {}", e))?;
+                let _super_config = ConfigurationManager::new(config).resolve(&CliOverrides {
+                    require_signer_keys: true,
+                    ..self.overrides()
+                })?;
 
                 // Convert config to rundler arguments, avoiding duplicates
                 let config_args = self.config_to_rundler_args(&_super_config)?;
@@ -348,6 +1318,7 @@ impl Cli {
                 // Only add additional args that don't conflict with config args
                 for arg in rundler_args.iter() {
                     if !arg.starts_with("--network")
+                        && !arg.starts_with("--chain_spec")
                         && !arg.starts_with("--node_http")
                         && !arg.starts_with("--metrics.port")
                     {
@@ -438,16 +1409,194 @@ impl Cli {
             Commands::Version => {
                 self.show_version();
             }
-            Commands::Status => {
-                self.check_status().await?;
+            Commands::Status {
+                ref config,
+                json,
+                ref host,
+                timeout_seconds,
+                deadline_seconds,
+            } => {
+                let options = status::StatusOptions {
+                    host: host.clone(),
+                    timeout: Duration::from_secs(timeout_seconds),
+                    deadline: Duration::from_secs(deadline_seconds),
+                };
+                let report = status::run(&self, config, &options).await;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&report.to_json())?);
+                } else {
+                    print!("{}", report.render_table());
+                }
+                if report.has_critical_failure() {
+                    std::process::exit(1);
+                }
+            }
+            Commands::Doctor {
+                ref config,
+                json,
+                timeout_seconds,
+                min_balance_wei,
+            } => {
+                let options = doctor::DoctorOptions {
+                    timeout: Duration::from_secs(timeout_seconds),
+                    min_balance_wei: U256::from(min_balance_wei),
+                };
+                let report = doctor::run(&self, config, &options).await;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&report.to_json())?);
+                } else {
+                    print!("{}", report.render_table());
+                }
+                if report.has_critical_failure() {
+                    std::process::exit(1);
+                }
+            }
+            Commands::AlertTest {
+                ref config,
+                ref message,
+            } => {
+                self.run_alert_test(config, message).await?;
             }
+            Commands::Config {
+                command:
+                    ConfigCommands::Show {
+                        ref config,
+                        redact_secrets,
+                    },
+            } => {
+                let (shown, errors) =
+                    ConfigurationManager::new(config).show(&self.overrides(), redact_secrets)?;
+                print!("{}", shown);
+                if !errors.is_empty() {
+                    eprintln!("{}", config_system::ConfigErrors(errors));
+                    std::process::exit(1);
+                }
+            }
+            Commands::Secrets {
+                command:
+                    SecretsCommands::Encrypt {
+                        ref input,
+                        ref output,
+                    },
+            } => secrets_file::encrypt(input, output)?,
+            Commands::Secrets {
+                command:
+                    SecretsCommands::Decrypt {
+                        ref input,
+                        ref output,
+                    },
+            } => secrets_file::decrypt(input, output.as_deref())?,
+            Commands::Dev {
+                command:
+                    DevCommands::Bootstrap {
+                        ref template,
+                        ref config_out,
+                        ref policy_out,
+                        ref env_out,
+                        ref paymaster_private_key,
+                        paymaster_balance_wei,
+                        deposit_wei,
+                        ref gateway_url,
+                    },
+            } => {
+                let options = bootstrap::BootstrapOptions {
+                    node_http: self
+                        .node_http
+                        .clone()
+                        .unwrap_or_else(|| "http://localhost:8545".to_string()),
+                    template: template.into(),
+                    config_out: config_out.into(),
+                    policy_out: policy_out.into(),
+                    env_out: env_out.into(),
+                    paymaster_key: paymaster_private_key
+                        .clone()
+                        .unwrap_or_else(|| bootstrap::ANVIL_PAYMASTER_KEY.to_string()),
+                    paymaster_balance_wei: U256::from(paymaster_balance_wei),
+                    deposit_wei: U256::from(deposit_wei),
+                    gateway_url: gateway_url.clone(),
+                };
+                let report = bootstrap::run(&options).await?;
+                print!("{}", report.render(&options));
+            }
+            Commands::ExportSchema { ref out } => {
+                let schema = serde_json::to_string_pretty(&rpc_schema())?;
+                if out == "-" {
+                    println!("{}", schema);
+                } else {
+                    std::fs::write(out, schema + "\n")
+                        .map_err(|e| eyre::eyre!("Failed to write {}: {}", out, e))?;
+                    println!(
+                        "✅ Wrote the schema of {} JSON-RPC methods to {}",
+                        rpc_methods().len(),
+                        out
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Settings given on the command line, overriding environment and config file
+    fn overrides(&self) -> CliOverrides {
+        CliOverrides {
+            network: self.network.clone(),
+            chain_spec: self.chain_spec.clone(),
+            node_http: self.node_http.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// 配置文件与环境；给出密钥文件时先解密，其中的变量优先于环境变量
+    fn configuration(
+        &self,
+        config_path: &str,
+        secrets_file: Option<&str>,
+    ) -> Result<ConfigurationManager> {
+        let manager = ConfigurationManager::new(config_path);
+        Ok(match secrets_file {
+            Some(path) => manager.with_secrets(secrets_file::unlock(path)?),
+            None => manager,
+        })
+    }
+
+    /// 向所有配置的Webhook发送测试告警并打印每个Webhook的结果
+    async fn run_alert_test(&self, config_path: &str, message: &str) -> Result<()> {
+        let super_config = ConfigurationManager::new(config_path).resolve(&self.overrides())?;
+        let alerts = &super_config.gateway.alerts;
+        if alerts.webhooks.is_empty() {
+            eprintln!("❌ No webhooks configured in [gateway.alerts]");
+            std::process::exit(1);
+        }
+        if !alerts.enabled {
+            warn!("⚠️ [gateway.alerts] is disabled; sending the test alert anyway");
         }
 
+        let sinks = alerts
+            .sinks()
+            .map_err(|e| eyre::eyre!("Invalid [gateway.alerts]: {}", e))?;
+        let alert = Alert::new(AlertKind::Test, "super-relay", message);
+        let deliveries = deliver_to_sinks(&sinks, &alert).await;
+        for delivery in &deliveries {
+            match &delivery.error {
+                None => println!("✅ {}: delivered", delivery.sink),
+                Some(e) => println!("❌ {}: {}", delivery.sink, e),
+            }
+        }
+        if deliveries.iter().any(|delivery| !delivery.delivered) {
+            std::process::exit(1);
+        }
         Ok(())
     }
 
     /// 启动独立的 Swagger UI 测试服务器 (代理模式)
-    async fn run_api_server(&self, host: String, port: u16, super_relay_url: String) -> Result<()> {
+    async fn run_api_server(
+        &self,
+        host: String,
+        port: u16,
+        super_relay_url: String,
+        config_path: &str,
+    ) -> Result<()> {
         info!("🚀 Starting SuperRelay API Testing Server (Proxy Mode)");
         info!("📍 API Server will bind to {}:{}", host, port);
         info!("🔗 Connecting to SuperRelay service: {}", super_relay_url);
@@ -456,10 +1605,22 @@ impl Cli {
             host, port
         );
 
+        // CORS策略与上游连接设置来自配置文件的 [cors] 和 [proxy_client]，文件不存在时使用默认值
+        let (cors, proxy_client_config) = if std::path::Path::new(config_path).exists() {
+            let super_config = ConfigurationManager::new(config_path).resolve(&self.overrides())?;
+            (super_config.cors, super_config.proxy_client)
+        } else {
+            info!(
+                "📄 Config file {} not found, using default CORS policy and proxy settings",
+                config_path
+            );
+            (CorsConfig::default(), ProxyClientConfig::default())
+        };
+
         // Test connection to SuperRelay service
         info!("🔍 Testing connection to SuperRelay service...");
         let proxy_client =
-            rundler_paymaster_relay::proxy_client::SuperRelayProxyClient::new(&super_relay_url);
+            SuperRelayProxyClient::with_config(&super_relay_url, proxy_client_config);
 
         match proxy_client.health_check().await {
             Ok(_) => {
@@ -489,7 +1650,7 @@ impl Cli {
         );
 
         // Start the proxy API server
-        rundler_paymaster_relay::start_proxy_api_server(&bind_address, proxy_client)
+        rundler_paymaster_relay::start_proxy_api_server(&bind_address, proxy_client, &cors)
             .await
             .map_err(|e| eyre::eyre!("Proxy API server failed: {}", e))?;
 
@@ -504,109 +1665,513 @@ impl Cli {
         gateway_host: String,
         gateway_port: u16,
         enable_rundler_rpc: bool,
+        enable_builder: bool,
         enable_paymaster: bool,
-        _paymaster_private_key: Option<String>,
-        _paymaster_policy_file: Option<String>,
+        paymaster_private_key: Option<String>,
+        paymaster_policy_file: Option<String>,
+        secrets_file: Option<&str>,
+        skip_preflight: bool,
     ) -> Result<()> {
         info!("🚀 Starting SuperRelay Dual-Service Compatible Mode");
         info!("🌐 Gateway Service: {}:{}", gateway_host, gateway_port);
 
+        // 1. 解析配置文件，一次性报告所有缺失/无效的设置；密钥文件在任何服务启动前解密
+        let super_config =
+            self.configuration(&config_path, secrets_file)?
+                .resolve(&CliOverrides {
+                    paymaster_private_key,
+                    paymaster_policy_file,
+                    require_paymaster: enable_paymaster,
+                    require_builder_keys: enable_builder,
+                    ..self.overrides()
+                })?;
+
+        // 管理端口不能与Gateway端口或rundler RPC端口相同
+        let mut listeners = vec![("gateway", gateway_port)];
         if enable_rundler_rpc {
-            info!("🔄 Rundler Service: 127.0.0.1:3001 (enabled)");
+            listeners.push(("rundler RPC", super_config.dual_service.rundler_port));
+        }
+        super_config
+            .gateway
+            .admin
+            .validate_ports(&listeners)
+            .map_err(|e| eyre::eyre!("Invalid [gateway.admin]: {}", e))?;
+
+        // 在任何组件记录指标之前安装全局Prometheus记录器
+        let prometheus = super_config.gateway.install_metrics()?;
+
+        if enable_rundler_rpc {
+            info!(
+                "🔄 Rundler Service: 127.0.0.1:{} (enabled)",
+                super_config.dual_service.rundler_port
+            );
         } else {
             info!("📴 Rundler Service: disabled (Gateway-only mode)");
         }
 
-        // 1. 解析配置文件
-        let config_content = fs::read_to_string(&config_path)
-            .map_err(|e| eyre::eyre!("Failed to read config file '{}': {}", config_path, e))?;
-        let expanded_content = expand_env_vars(&config_content);
-        let super_config: SuperRelayConfig = toml::from_str(&expanded_content)
-            .map_err(|e| eyre::eyre!("Failed to parse config file: {}", e))?;
+        // 所有服务与Pool监管共享同一个关闭信号 (SIGTERM/SIGINT)
+        let shutdown = ShutdownController::new();
+        shutdown.listen_for_signals();
 
-        // 2. 初始化共享的rundler组件
+        // 2. 初始化共享的rundler组件；配置了 [[chains]] 时每条链一套组件
         info!("🔧 Initializing shared rundler components...");
-        let shared_components = self
-            .initialize_shared_rundler_components(&super_config)
-            .await?;
-        info!("✅ Shared rundler components initialized successfully");
+        let mut chain_components = Vec::new();
+        if super_config.chains.is_empty() {
+            let components = self
+                .initialize_shared_rundler_components(&super_config, &shutdown)
+                .await?;
+            chain_components.push((None, components));
+        } else {
+            for chain in &super_config.chains {
+                let components = self
+                    .initialize_chain_components(&super_config, chain, &shutdown)
+                    .await?;
+                chain_components.push((chain.signer_key_env.clone(), components));
+            }
+        }
 
-        // 3. 初始化PaymasterService (如果启用)
+        // 默认链服务未指定chainId的请求，以及3001端口的rundler RPC
+        let default_index = match super_config.gateway.default_chain_id {
+            Some(chain_id) => chain_components
+                .iter()
+                .position(|(_, components)| components.chain_spec.id == chain_id)
+                .ok_or_else(|| {
+                    eyre::eyre!(
+                        "[gateway] default_chain_id {} has no [[chains]] entry",
+                        chain_id
+                    )
+                })?,
+            None => 0,
+        };
+        let (default_signer_key_env, shared_components) = chain_components.remove(default_index);
+        info!(
+            "✅ Shared rundler components initialized successfully (default chain {})",
+            shared_components.chain_spec.id
+        );
+
+        // 3. 初始化PaymasterService (如果启用)，每条链使用各自的签名密钥
+        let usage_store = if enable_paymaster {
+            super_config.paymaster_relay.usage_store()?
+        } else {
+            None
+        };
+        let sponsorship_records = if enable_paymaster {
+            super_config.paymaster_relay.sponsorship_records()?
+        } else {
+            None
+        };
         let paymaster_service = if enable_paymaster {
             info!("🔐 Initializing PaymasterRelay service...");
-            match self
-                .initialize_paymaster_service(&shared_components.pool)
-                .await
-            {
-                Ok(service) => {
-                    info!("✅ PaymasterRelay service initialized successfully");
-                    Some(Arc::new(service))
-                }
-                Err(e) => {
-                    error!("❌ Failed to initialize PaymasterRelay service: {}", e);
-                    return Err(e);
-                }
-            }
+            Some(
+                self.build_paymaster_service(
+                    &super_config,
+                    &shared_components,
+                    default_signer_key_env.as_deref(),
+                    usage_store.clone(),
+                    sponsorship_records.clone(),
+                )
+                .await?,
+            )
         } else {
             info!("📴 PaymasterRelay service disabled");
             None
         };
 
+        let pool_snapshots: Vec<_> = std::iter::once(&shared_components)
+            .chain(chain_components.iter().map(|(_, components)| components))
+            .map(|components| components.pool_snapshot.clone())
+            .collect();
+
+        let mut extra_chains = Vec::new();
+        for (signer_key_env, components) in chain_components {
+            let paymaster_service = if enable_paymaster {
+                Some(
+                    self.build_paymaster_service(
+                        &super_config,
+                        &components,
+                        signer_key_env.as_deref(),
+                        usage_store.clone(),
+                        sponsorship_records.clone(),
+                    )
+                    .await?,
+                )
+            } else {
+                None
+            };
+            extra_chains.push(ChainServices {
+                components,
+                paymaster_service,
+            });
+        }
+
         // 4. 创建服务任务
-        let mut tasks: Vec<JoinHandle<Result<()>>> = Vec::new();
+        let mut tasks: Vec<(&'static str, JoinHandle<Result<()>>)> = Vec::new();
 
-        // 4a. 启动Gateway服务 (3000端口)
+        // 4a. 启动默认链的Bundle Builder (如果启用)，Gateway的rundler_sendBundleNow经由它打包
+        let builder = if enable_builder {
+            let (builder, builder_task) = self
+                .start_builder_service(&shared_components, &super_config.settings, shutdown.clone())
+                .await?;
+            tasks.push(("Bundle Builder", builder_task));
+            Some(builder)
+        } else {
+            info!("📴 Bundle builder disabled");
+            None
+        };
+
+        // 4b. 启动Gateway服务 (3000端口)
         let gateway_task = self
             .start_gateway_service(
                 gateway_host,
                 gateway_port,
                 shared_components.clone(),
                 paymaster_service.clone(),
+                builder,
+                extra_chains,
+                &config_path,
+                &super_config.gateway,
+                &super_config.cors,
+                &super_config.paymaster_relay.audit_log,
+                super_config.paymaster_relay.verification_proof_store()?,
+                prometheus.clone(),
+                shutdown.clone(),
+                skip_preflight,
             )
             .await?;
-        tasks.push(gateway_task);
+        tasks.push(("Gateway", gateway_task));
 
-        // 4b. 启动Rundler RPC服务 (3001端口，如果启用)
+        // 独立端口上的 /metrics (例如 8080，供 super-relay status 检查)
+        if let Some(metrics_task) = super_config
+            .gateway
+            .serve_metrics(prometheus.as_ref(), shutdown.clone())
+        {
+            tasks.push(("Metrics", metrics_task));
+        }
+
+        // 4c. 启动Rundler RPC服务 (3001端口，如果启用)
         if enable_rundler_rpc {
             let rundler_task = self
                 .start_rundler_rpc_service(
                     shared_components.clone(),
                     super_config.dual_service.rundler_port,
+                    &super_config.rpc.rundler_api(),
+                    shutdown.clone(),
                 )
                 .await?;
-            tasks.push(rundler_task);
+            tasks.push(("Rundler RPC", rundler_task));
         }
 
         // 5. 等待所有服务
         info!("✨ All services started successfully");
         info!("🚀 SuperRelay Dual-Service mode is now running...");
 
-        // 等待任何一个服务退出
-        let mut gateway_task = tasks.remove(0);
-        let rundler_task = if !tasks.is_empty() {
-            Some(tasks.remove(0))
-        } else {
-            None
+        // 任一服务退出都会通知其余服务开始排空
+        let supervised: Vec<_> = tasks
+            .into_iter()
+            .map(|(name, task)| {
+                let shutdown = shutdown.clone();
+                tokio::spawn(async move {
+                    let result = task.await;
+                    if !shutdown.is_draining() {
+                        error!(
+                            "{} service exited unexpectedly, stopping other services",
+                            name
+                        );
+                        shutdown.trigger();
+                    }
+                    (name, result)
+                })
+            })
+            .collect();
+
+        // 仅在所有服务排空后退出
+        let mut first_error = None;
+        for handle in supervised {
+            let (name, result) = handle.await?;
+            match result {
+                Ok(Ok(())) => info!("✅ {} service stopped", name),
+                Ok(Err(e)) => {
+                    error!("❌ {} service failed: {}", name, e);
+                    first_error.get_or_insert(e);
+                }
+                Err(e) => {
+                    error!("❌ {} service task panicked: {}", name, e);
+                    first_error.get_or_insert(eyre::eyre!("{} service task failed: {}", name, e));
+                }
+            }
+        }
+
+        wait_for_pool_snapshots(&pool_snapshots).await;
+
+        match first_error {
+            Some(e) => Err(e),
+            None => {
+                info!("👋 All services drained, exiting");
+                Ok(())
+            }
+        }
+    }
+
+    /// 创建一条链的PaymasterService，并接入该链的存款管理与费用检查
+    async fn build_paymaster_service(
+        &self,
+        super_config: &SuperRelayConfig,
+        components: &SharedRundlerComponents,
+        signer_key_env: Option<&str>,
+        usage_store: Option<Arc<dyn UsageStore>>,
+        sponsorship_records: Option<Arc<SponsorshipRecords>>,
+    ) -> Result<Arc<PaymasterRelayService>> {
+        let service = match self
+            .initialize_paymaster_service(
+                &components.pool.current()?,
+                &components.chain_spec,
+                &super_config.signer,
+                &super_config.paymaster_relay.audit_log,
+                &super_config.settings,
+                signer_key_env,
+            )
+            .await
+        {
+            Ok(service) => service,
+            Err(e) => {
+                error!(
+                    "❌ Failed to initialize PaymasterRelay service for chain {}: {}",
+                    components.chain_spec.id, e
+                );
+                return Err(e);
+            }
         };
+        info!(
+            "✅ PaymasterRelay service initialized successfully for chain {}",
+            components.chain_spec.id
+        );
+
+        // EntryPoint存款管理 (pm_getDepositInfo/pm_depositTo/pm_withdrawTo)
+        let deposit_config = super_config.paymaster_relay.deposit.clone();
+        info!(
+            "💰 Deposit management enabled (max deposit {} wei, withdraw {})",
+            deposit_config.max_deposit_wei,
+            if deposit_config.allow_withdraw {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+        // 签名前检查费用是否满足最新区块要求
+        let auto_bump_fees = super_config.paymaster_relay.auto_bump_fees;
+        info!(
+            "⛽ Fee check enabled ({})",
+            if auto_bump_fees {
+                "auto bump"
+            } else {
+                "reject underpriced"
+            }
+        );
 
-        tokio::select! {
-            result = &mut gateway_task => {
-                error!("Gateway service exited: {:?}", result);
-                result??;
+        // Paymaster账户发出的交易统一分配nonce并跟踪上链，卡住时提高费用替换
+        let outbound_txs = super_config.paymaster_relay.outbound_txs(
+            components.outbound_chain.clone(),
+            components.fee_estimator.clone(),
+            components.chain_spec.id,
+        )?;
+
+        let mut service = service
+            .with_deposit_manager(
+                DepositManager::new(components.deposit_chain.clone(), deposit_config)
+                    .with_entry_points(components.entry_points.to_vec())
+                    .with_outbound_txs(outbound_txs.clone()),
+            )
+            .with_outbound_txs(outbound_txs)
+            .with_fee_checker(FeeChecker::new(
+                components.fee_estimator.clone(),
+                auto_bump_fees,
+            ));
+
+        service = service.with_simulator(components.simulator.clone());
+
+        // 只赞助已部署Paymaster合约的EntryPoint版本，其余请求在签名前拒绝
+        let paymasters = &super_config.paymaster_relay.paymasters;
+        let check_paymasters = !paymasters.is_empty();
+        if check_paymasters {
+            let routing = EntryPointRouting::new(paymasters.clone())
+                .map_err(|e| eyre::eyre!("Invalid [[paymaster_relay.paymasters]]: {}", e))?;
+            for supported in routing.supported() {
+                info!("🧭 Sponsoring for entry point {}", supported);
             }
-            result = async {
-                if let Some(mut task) = rundler_task {
-                    (&mut task).await
+            service = service.with_entry_point_routing(routing);
+        }
+
+        // 客户端可通过validitySeconds请求的赞助有效期范围
+        service = service.with_validity(super_config.paymaster_relay.validity.clone());
+
+        // v0.7赞助及dummy paymaster数据的paymaster gas限制
+        service = service.with_paymaster_gas(super_config.paymaster_relay.paymaster_gas.clone());
+
+        // pm_rotatePaymasterKey 之后旧密钥的验证宽限期
+        if let Some(grace_seconds) = super_config.paymaster_relay.key_rotation_grace_seconds {
+            service = service.with_key_rotation_grace(Duration::from_secs(grace_seconds));
+        }
+        service = service
+            .with_key_rotation_env_vars(super_config.paymaster_relay.key_rotation_env_vars.clone());
+
+        // 相同幂等键的重放请求在TTL内返回缓存的赞助结果
+        service = service.with_idempotency(super_config.paymaster_relay.idempotency.clone());
+
+        // 无幂等键的重复请求 (赞助、重新估算Gas、再赞助) 在TTL内复用同一签名
+        service =
+            service.with_signature_cache(super_config.paymaster_relay.signature_cache.clone());
+
+        // 同一发送者和nonce的并发请求共享一次赞助，不同的UserOperation被拒绝
+        service = service.with_sender_locks(super_config.paymaster_relay.sender_locks.clone());
+
+        // 候选策略与当前策略并行评估，只记录分歧；pm_promoteCandidatePolicy 切换为当前策略
+        let shadow_config = super_config.paymaster_relay.policy_shadow.clone();
+        let candidate_file = shadow_config.candidate_file.clone();
+        service = service.with_policy_shadow(shadow_config);
+        if let Some(candidate_file) = candidate_file {
+            service
+                .load_candidate_policy(&candidate_file)
+                .map_err(|e| eyre::eyre!("Failed to load candidate policy: {}", e))?;
+        }
+
+        // 各链共享同一用量统计，按API密钥汇总
+        if let Some(usage_store) = usage_store {
+            service = service.with_usage_store(usage_store);
+        }
+
+        // 各链共享同一赞助记录，每条链按自己的UserOperationEvent日志对账
+        if let Some(records) = sponsorship_records {
+            records.spawn_reconciler(
+                components.chain_spec.id,
+                Arc::new(ReceiptEventSource::new(components.receipt_provider.clone())),
+            );
+            service = service.with_sponsorship_records(records);
+        }
+
+        // 只赞助持有SBT/PNTs的发送者
+        let sbt_config = super_config.paymaster_relay.sbt.clone();
+        if sbt_config.enabled {
+            info!(
+                "🎫 SBT check enabled (sbt {:?}, pnts {:?}, cache {}s, {})",
+                sbt_config.sbt_contract,
+                sbt_config.pnts_contract,
+                sbt_config.cache_ttl_seconds,
+                if sbt_config.fail_open {
+                    "fail open"
                 } else {
-                    std::future::pending().await
+                    "fail closed"
                 }
-            } => {
-                error!("Rundler RPC service exited: {:?}", result);
-                result??;
+            );
+            service = service.with_sbt_validator(SBTValidator::new(
+                components.token_balances.clone(),
+                sbt_config,
+            ));
+        }
+
+        // pm_sponsorUserOperationERC20: 按预言机汇率报价，用户以代币支付Gas
+        let token_config = super_config.paymaster_relay.token_paymaster.clone();
+        if token_config.enabled {
+            info!(
+                "🪙 ERC-20 gas payment enabled ({} tokens, markup {} bps, paymaster {:?})",
+                token_config.tokens.len(),
+                token_config.markup_bps,
+                token_config.paymaster
+            );
+            service = service.with_token_pricing(TokenPricing::new(
+                components.price_oracle.clone(),
+                token_config,
+            ));
+        }
+
+        // 后台监控Paymaster余额与存款，低于阈值时告警并按配置自动补充
+        let monitor_config = super_config.paymaster_relay.balance_monitor.clone();
+        if monitor_config.enabled {
+            info!(
+                "📉 Balance monitor enabled (every {}s, deposit low water {} wei, auto rebalance {})",
+                monitor_config.check_interval_seconds,
+                monitor_config.deposit_low_water_wei,
+                if monitor_config.auto_rebalance {
+                    "on"
+                } else {
+                    "off"
+                }
+            );
+            service = service.with_balance_monitor(BalanceMonitor::new(
+                monitor_config,
+                components.entry_points.as_ref().clone(),
+            ));
+        }
+
+        // 客户端传入"attest": true时对赞助响应签名，供日后证明响应来源
+        let attestation_config = &super_config.paymaster_relay.attestation;
+        if attestation_config.enabled {
+            let attestor = Attestor::from_config(attestation_config)
+                .map_err(|e| eyre::eyre!("Failed to load attestation key: {}", e))?;
+            if attestor.address() == service.signer_status().await.active_signer {
+                return Err(eyre::eyre!(
+                    "The attestation key must differ from the paymaster signing key"
+                ));
             }
+            info!(
+                "🔏 Response attestation enabled (key {})",
+                attestor.address()
+            );
+            service = service.with_attestor(attestor);
         }
 
-        Ok(())
+        // 启动时检查Paymaster合约已部署且接受我们的签名者，仅记录诊断信息
+        if check_paymasters {
+            service
+                .check_paymaster_contracts(components.paymaster_contracts.as_ref())
+                .await
+                .map_err(|e| eyre::eyre!("Failed to check paymaster contracts: {}", e))?;
+        }
+
+        // 按最新区块时间戳校正时钟：计算有效期时扣除偏差，偏差过大时拒绝签名
+        let clock_config = super_config.paymaster_relay.clock_skew.clone();
+        if clock_config.enabled {
+            let clock_guard = Arc::new(ClockGuard::new(
+                components.block_timestamps.clone(),
+                clock_config,
+            ));
+            clock_guard.spawn();
+            service = service.with_clock_guard(clock_guard);
+        }
+
+        let service = Arc::new(service);
+        service
+            .spawn_outbound_txs()
+            .map_err(|e| eyre::eyre!("Failed to start outbound transaction tracking: {}", e))?;
+        if super_config.paymaster_relay.balance_monitor.enabled {
+            service
+                .spawn_balance_monitor()
+                .map_err(|e| eyre::eyre!("Failed to start balance monitor: {}", e))?;
+        }
+        Ok(service)
+    }
+
+    /// 按 [[chains]] 条目初始化一条链的rundler组件
+    async fn initialize_chain_components(
+        &self,
+        config: &SuperRelayConfig,
+        chain: &ChainSectionConfig,
+        shutdown: &ShutdownController,
+    ) -> Result<SharedRundlerComponents> {
+        let label = chain.label();
+        info!(
+            "🔧 Setting up rundler components for chain {} ({})...",
+            label, chain.chain_id
+        );
+        self.build_rundler_components(
+            config,
+            label.clone(),
+            chain.node_http.clone(),
+            chain.secondary_node_http.clone(),
+            chain.to_eth_api_config()?,
+            Some(label),
+            shutdown,
+        )
+        .await
     }
 
     /// 初始化共享的rundler组件
@@ -614,29 +2179,74 @@ impl Cli {
     async fn initialize_shared_rundler_components(
         &self,
         config: &SuperRelayConfig,
+        shutdown: &ShutdownController,
     ) -> Result<SharedRundlerComponents> {
         info!("🔧 Setting up shared rundler components...");
 
-        // Provider配置
-        let network = std::env::var("NETWORK")
-            .or_else(|_| std::env::var("CHAIN_NETWORK"))
-            .unwrap_or_else(|_| "dev".to_string());
-        let node_http = std::env::var("NODE_HTTP")
-            .or_else(|_| std::env::var("ETH_NODE_HTTP"))
-            .unwrap_or_else(|_| "http://localhost:8545".to_string());
+        let (network, node_http, eth_config) = default_chain_settings(config)?;
 
+        self.build_rundler_components(
+            config,
+            network,
+            node_http,
+            config.node.secondary_node_http.clone(),
+            eth_config,
+            None,
+            shutdown,
+        )
+        .await
+    }
+
+    /// 为一条链创建Provider、Pool与EntryPoint等rundler组件
+    ///
+    /// Pool任务在 `shutdown` 触发前由PoolSupervisor监管，异常退出后按退避重启
+    #[allow(unused_imports, unused_variables, clippy::too_many_arguments)]
+    async fn build_rundler_components(
+        &self,
+        config: &SuperRelayConfig,
+        network: String,
+        node_http: String,
+        secondary_node_http: Option<String>,
+        eth_config: EthApiConfig,
+        chain_label: Option<String>,
+        shutdown: &ShutdownController,
+    ) -> Result<SharedRundlerComponents> {
         let provider_config = Arc::new(ProviderConfig {
             network: network.clone(),
             node_http: node_http.clone(),
-            chain_id: if network == "dev" { 31337 } else { 1 },
+            chain_id: eth_config.chain_id,
         });
 
+        // 2. ChainSpec按 --network / --chain_spec 解析，两个端口共用；
+        //    配置的chain id与 CHAIN_ID 一样覆盖其id
+        let chain_spec = if eth_config.chain_spec.id == provider_config.chain_id {
+            eth_config.chain_spec.clone()
+        } else {
+            warn!(
+                "⚠️ Chain id {} overrides the id {} of chain spec {}",
+                provider_config.chain_id, eth_config.chain_spec.id, eth_config.chain_spec.name
+            );
+            Arc::new(ChainSpec {
+                id: provider_config.chain_id,
+                ..(*eth_config.chain_spec).clone()
+            })
+        };
+        info!(
+            "⛓️  Chain spec {} (chain id {})",
+            chain_spec.name, chain_spec.id
+        );
+
+        // 按ChainSpec确定启用的EntryPoint版本，Gateway与rundler返回同一列表
+        let enabled_entry_points =
+            EnabledEntryPoints::from_configured(&chain_spec, &eth_config.entry_points)?;
+        let entry_points = Arc::new(enabled_entry_points.addresses(&chain_spec));
+
         // Rundler服务配置
         let rundler_config = Arc::new(RundlerServiceConfig {
             rpc_enabled: config.dual_service.enable_rundler_rpc,
             rpc_port: config.dual_service.rundler_port,
             chain_id: provider_config.chain_id,
-            entry_points: vec!["0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789".to_string()],
+            entry_points: entry_points.iter().map(|ep| ep.to_checksum(None)).collect(),
         });
 
         // 实现真实的rundler组件初始化
@@ -652,54 +2262,70 @@ impl Cli {
 
         info!("✅ Alloy provider connected to: {}", node_http);
 
-        // 2. 创建ChainSpec (使用默认值然后自定义)
-        let chain_spec = rundler_types::chain::ChainSpec {
-            name: if network == "dev" {
-                "Development".to_string()
-            } else {
-                "Mainnet".to_string()
-            },
-            id: provider_config.chain_id,
-            ..Default::default()
-        };
+        // 3. 创建EvmProvider，配置备用节点时只读请求在主备节点间对冲
+        let primary_evm = rundler_provider::AlloyEvmProvider::new(provider.clone());
+
+        // 校验配置的chain id与节点一致
+        verify_node_chain_id(&primary_evm, provider_config.chain_id).await?;
+
+        let mut hedged = HedgedEvmProvider::new(primary_evm, provider_config.chain_id);
+        if let Some(secondary_http) = &secondary_node_http {
+            let secondary_evm = rundler_provider::AlloyEvmProvider::new(Arc::new(
+                rundler_provider::new_alloy_provider(secondary_http, 30).map_err(|e| {
+                    eyre::eyre!("Failed to create Alloy provider for secondary node: {}", e)
+                })?,
+            ));
+            verify_node_chain_id(&secondary_evm, provider_config.chain_id).await?;
+            hedged = hedged.with_secondary(secondary_evm, config.node.hedging.clone());
+            info!(
+                "✅ Secondary node {} added, reads hedged after {}ms",
+                secondary_http, config.node.hedging.hedge_delay_ms
+            );
+        }
+        let evm_provider = Arc::new(hedged);
 
-        // 3. 创建EvmProvider
-        let evm_provider = rundler_provider::AlloyEvmProvider::new(provider.clone());
+        // 启动时查询一次各EntryPoint的版本，与ChainSpec地址对照
+        let version_selector =
+            entry_point_versions(&evm_provider, &chain_spec, &entry_points).await?;
 
         // 4. 创建DA Gas Oracle
-        let (da_gas_oracle, _da_gas_oracle_sync) =
+        let (da_gas_oracle, da_gas_oracle_sync) =
             rundler_provider::new_alloy_da_gas_oracle(&chain_spec, provider.clone());
 
-        // 5. 创建Entry Point providers
-        let max_verification_gas = 5_000_000u64;
+        // 5. 创建Entry Point providers (仅创建已启用的版本)
+        let max_verification_gas = config.rpc.max_verification_gas.unwrap_or(5_000_000);
         let max_bundle_execution_gas = chain_spec
-            .block_gas_limit_mult(0.9) // max_bundle_block_gas_limit_ratio
+            .block_gas_limit_mult(MAX_BUNDLE_BLOCK_GAS_LIMIT_RATIO)
             .try_into()
             .unwrap_or(30_000_000u64);
 
-        let _ep_v0_6 = Some(rundler_provider::AlloyEntryPointV0_6::new(
-            chain_spec.clone(),
-            max_verification_gas,
-            max_bundle_execution_gas,
-            max_bundle_execution_gas, // max_gas_estimation_gas
-            max_bundle_execution_gas,
-            provider.clone(),
-            da_gas_oracle.clone(),
-        ));
+        let ep_v0_6 = enabled_entry_points.v0_6.then(|| {
+            rundler_provider::AlloyEntryPointV0_6::new(
+                (*chain_spec).clone(),
+                max_verification_gas,
+                max_bundle_execution_gas,
+                max_bundle_execution_gas, // max_gas_estimation_gas
+                max_bundle_execution_gas,
+                provider.clone(),
+                da_gas_oracle.clone(),
+            )
+        });
 
-        let _ep_v0_7 = Some(rundler_provider::AlloyEntryPointV0_7::new(
-            chain_spec.clone(),
-            max_verification_gas,
-            max_bundle_execution_gas,
-            max_bundle_execution_gas, // max_gas_estimation_gas
-            max_bundle_execution_gas,
-            provider.clone(),
-            da_gas_oracle.clone(),
-        ));
+        let ep_v0_7 = enabled_entry_points.v0_7.then(|| {
+            rundler_provider::AlloyEntryPointV0_7::new(
+                (*chain_spec).clone(),
+                max_verification_gas,
+                max_bundle_execution_gas,
+                max_bundle_execution_gas, // max_gas_estimation_gas
+                max_bundle_execution_gas,
+                provider.clone(),
+                da_gas_oracle.clone(),
+            )
+        });
 
         // 6. 创建Fee Estimator
         let priority_fee_mode = PriorityFeeMode::BaseFeePercent(50); // 50% of base fee
-        let _fee_estimator = Arc::new(rundler_provider::new_fee_estimator(
+        let fee_estimator = Arc::new(rundler_provider::new_fee_estimator(
             &chain_spec,
             evm_provider.clone(),
             priority_fee_mode,
@@ -707,58 +2333,425 @@ impl Cli {
             0, // bundle_priority_fee_overhead_percent
         ));
 
+        let shared_fee_estimator: Arc<dyn FeeEstimator> = fee_estimator.clone();
+
+        // 7. 创建链上收据查询 (eth_getUserOperationReceipt)
+        let receipt_provider: Arc<dyn UserOperationReceiptProvider> =
+            Arc::new(EvmReceiptProvider::new(
+                evm_provider.clone(),
+                entry_points.to_vec(),
+                config.rpc.user_operation_event_block_distance,
+            ));
+        let nonce_reader: Arc<dyn NonceReader> =
+            Arc::new(EvmNonceReader::new(evm_provider.clone()));
+        let code_reader: Arc<dyn ContractCodeReader> =
+            Arc::new(EvmCodeReader::new(evm_provider.clone()));
+        let block_source: Arc<dyn BlockNumberSource> =
+            Arc::new(EvmBlockNumberSource::new(evm_provider.clone()));
+        let block_head: Arc<dyn BlockHeadSource> =
+            Arc::new(EvmBlockHeadSource::new(evm_provider.clone()));
+        let signature_validator = Arc::new(SignatureValidator::new().with_contract_reader(
+            Arc::new(EvmContractSignatureReader::new(evm_provider.clone())),
+        ));
+
         info!("✅ All rundler providers initialized successfully");
 
-        // 8. 创建真实的Pool组件
-        info!("🔧 Initializing Pool component with real providers...");
-        let pool_builder = LocalPoolBuilder::new(100); // BLOCK_CHANNEL_CAPACITY
-        let pool_handle = Arc::new(pool_builder.get_handle());
+        let providers = SuperRelayProviders {
+            evm: evm_provider.clone(),
+            ep_v0_6: ep_v0_6.clone(),
+            ep_v0_7: ep_v0_7.clone(),
+            da_gas_oracle,
+            da_gas_oracle_sync,
+            fee_estimator: fee_estimator.clone(),
+        };
+
+        // 8. 启动进程内Pool (mempool + 链上监听)，任务退出后自动重启
+        info!("🔧 Starting Pool task with real providers...");
+        let (pool_events, _) = broadcast::channel(POOL_EVENT_CHANNEL_CAPACITY);
+        // Pool快照，多链时文件名带chain id
+        let snapshot_config = match &chain_label {
+            Some(_) => config.pool.snapshot.for_chain(chain_spec.id),
+            None => config.pool.snapshot.clone(),
+        };
+        let pool_snapshot = Arc::new(
+            PoolSnapshotter::new(chain_spec.id, snapshot_config)
+                .with_nonce_reader(nonce_reader.clone()),
+        );
+        if pool_snapshot.is_enabled() {
+            pool_snapshot.subscribe(pool_events.subscribe());
+        }
+        let pool_launcher = Arc::new(ProvidersPoolLauncher {
+            chain_spec: chain_spec.clone(),
+            enabled: enabled_entry_points,
+            node_http: node_http.clone(),
+            providers: providers.clone(),
+            max_verification_gas,
+            same_sender_mempool_count: config.pool.max_ops_per_unstaked_sender.unwrap_or(4)
+                as usize,
+            throttled_entity_mempool_count: config.pool.throttled_entity_mempool_count.unwrap_or(4)
+                as u64,
+            events: pool_events,
+        });
+        let pool_handle =
+            PoolSupervisor::new(pool_launcher, config.pool.supervisor.clone(), chain_spec.id)
+                .with_snapshot(pool_snapshot.clone())
+                .start(shutdown.clone())
+                .await
+                .map_err(|e| eyre::eyre!("{}", e))?;
+
+        info!("✅ Pool task started successfully");
+
+        // 9. 就绪探针：节点同步状态、chain id、EntryPoint合约代码与各EntryPoint上的Paymaster存款
+        let mut node_probe = NodeProbe::new(
+            evm_provider.clone(),
+            Duration::from_secs(config.gateway.health.max_block_age_seconds),
+        );
+        let mut chain_id_probe = ChainIdProbe::new(evm_provider.clone(), chain_spec.id);
+        let mut entry_point_probe =
+            EntryPointCodeProbe::new(evm_provider.clone(), entry_points.to_vec());
+        if let Some(label) = &chain_label {
+            node_probe = node_probe.with_name(format!("node_{}", label));
+            chain_id_probe = chain_id_probe.with_name(format!("chain_id_{}", label));
+            entry_point_probe = entry_point_probe.with_name(format!("entry_point_code_{}", label));
+        }
+        let mut node_probes: Vec<Arc<dyn HealthProbe>> = vec![
+            Arc::new(node_probe),
+            Arc::new(chain_id_probe),
+            Arc::new(entry_point_probe),
+        ];
+        if secondary_node_http.is_some() {
+            let mut upstream_probe = UpstreamProbe::new(evm_provider.clone());
+            if let Some(label) = &chain_label {
+                upstream_probe = upstream_probe.with_name(format!("upstreams_{}", label));
+            }
+            node_probes.push(Arc::new(upstream_probe));
+        }
+        let mut entry_point_deposits: Vec<(&'static str, Arc<dyn DepositReader>)> = Vec::new();
+        if let Some(ep) = &ep_v0_6 {
+            entry_point_deposits.push(("paymaster_deposit_v0_6", Arc::new(ep.clone())));
+        }
+        if let Some(ep) = &ep_v0_7 {
+            entry_point_deposits.push(("paymaster_deposit_v0_7", Arc::new(ep.clone())));
+        }
+
+        // 10. Paymaster存款管理 (EntryPoint depositTo/withdrawTo)
+        let mut deposit_entry_points: Vec<Arc<dyn EntryPoint>> = Vec::new();
+        if let Some(ep) = &ep_v0_6 {
+            deposit_entry_points.push(Arc::new(ep.clone()));
+        }
+        if let Some(ep) = &ep_v0_7 {
+            deposit_entry_points.push(Arc::new(ep.clone()));
+        }
+        let provider_chain = Arc::new(ProviderDepositChain::new(
+            evm_provider.clone(),
+            chain_spec.id,
+            deposit_entry_points,
+        ));
+        let outbound_chain: Arc<dyn OutboundChain> = provider_chain.clone();
+        let deposit_chain: Arc<dyn DepositChain> = provider_chain;
+        let token_balances: Arc<dyn TokenBalanceReader> =
+            Arc::new(ProviderTokenBalances::new(evm_provider.clone()));
+        let paymaster_contracts: Arc<dyn PaymasterContractReader> =
+            Arc::new(ProviderContractReader::new(evm_provider.clone()));
+        let price_oracle: Arc<dyn PriceOracle> =
+            Arc::new(ProviderPriceOracle::new(evm_provider.clone()));
+        let block_timestamps: Arc<dyn BlockTimestampSource> =
+            Arc::new(ProviderBlockTimestamps::new(evm_provider.clone()));
+        let simulator: Arc<dyn ValidationSimulator> =
+            Arc::new(EntryPointSimulator::new(ep_v0_6.clone(), ep_v0_7.clone()));
+
+        // Gas估算，与rundler RPC使用相同的估算参数
+        let settings = estimation_settings(&chain_spec, max_verification_gas);
+        let mut gas_estimator = SimGasEstimator::new();
+        if let Some(ep) = &ep_v0_6 {
+            gas_estimator = gas_estimator.with_v0_6(
+                chain_spec.entry_point_address_v0_6,
+                Arc::new(GasEstimatorV0_6::new(
+                    (*chain_spec).clone(),
+                    evm_provider.clone(),
+                    ep.clone(),
+                    settings,
+                    fee_estimator.clone(),
+                )),
+            );
+        }
+        if let Some(ep) = &ep_v0_7 {
+            gas_estimator = gas_estimator.with_v0_7(
+                chain_spec.entry_point_address_v0_7,
+                Arc::new(GasEstimatorV0_7::new(
+                    (*chain_spec).clone(),
+                    evm_provider.clone(),
+                    ep.clone(),
+                    settings,
+                    fee_estimator.clone(),
+                )),
+            );
+        }
+        let gas_estimator: Arc<dyn GasEstimator> = Arc::new(gas_estimator);
+
+        // Bundle模拟，Gas上限与进程内Builder一致
+        let mut bundle_simulator = BundleSimulators::new();
+        if let Some(ep) = &ep_v0_6 {
+            bundle_simulator =
+                bundle_simulator.with_simulator(Arc::new(EntryPointBundleSimulator::new(
+                    (*chain_spec).clone(),
+                    chain_spec.entry_point_address_v0_6,
+                    evm_provider.clone(),
+                    ep.clone(),
+                )));
+        }
+        if let Some(ep) = &ep_v0_7 {
+            bundle_simulator =
+                bundle_simulator.with_simulator(Arc::new(EntryPointBundleSimulator::new(
+                    (*chain_spec).clone(),
+                    chain_spec.entry_point_address_v0_7,
+                    evm_provider.clone(),
+                    ep.clone(),
+                )));
+        }
+        let bundle_simulator: Arc<dyn BundleSimulator> = Arc::new(bundle_simulator);
+
+        // 11. Rundler RPC服务启动器，复用上面的Pool与Provider
+        let rundler_rpc: Arc<dyn RundlerRpcLauncher> = Arc::new(ProvidersRpcLauncher {
+            chain_spec: chain_spec.clone(),
+            enabled: enabled_entry_points,
+            node_http: node_http.clone(),
+            pool: pool_handle.clone(),
+            providers: providers.clone(),
+            max_verification_gas,
+            event_block_distance: config.rpc.user_operation_event_block_distance,
+        });
+
+        // 12. Builder事件通道，进程内Builder发送，Gateway的Bundle跟踪订阅
+        let (builder_events, _) = broadcast::channel(BUILDER_EVENT_CHANNEL_CAPACITY);
+
+        // 13. Bundle Builder启动器，从受监管的Pool取UserOperation，Pool重启后自动切换到新句柄
+        let bundle_builder: Arc<dyn BundleBuilderLauncher> = Arc::new(ProvidersBuilderLauncher {
+            chain_spec: chain_spec.clone(),
+            enabled: enabled_entry_points,
+            node_http: node_http.clone(),
+            pool: pool_handle.clone(),
+            providers,
+            events: builder_events.clone(),
+        });
 
-        info!("✅ Pool handle created successfully");
         info!("✅ Complete rundler component initialization finished");
 
         Ok(SharedRundlerComponents {
             pool: pool_handle,
             provider_config,
             rundler_config,
+            receipt_provider,
+            nonce_reader,
+            code_reader,
+            block_source,
+            block_head,
+            signature_validator,
+            chain_spec,
+            entry_points,
+            version_selector,
+            rundler_rpc,
+            bundle_builder,
+            node_probes,
+            entry_point_deposits,
+            deposit_chain,
+            outbound_chain,
+            fee_estimator: shared_fee_estimator,
+            gas_estimator,
+            bundle_simulator,
+            token_balances,
+            paymaster_contracts,
+            price_oracle,
+            block_timestamps,
+            simulator,
+            chain_label,
+            builder_events,
+            pool_snapshot,
         })
     }
 
     /// 启动Gateway服务
+    #[allow(clippy::too_many_arguments)]
     async fn start_gateway_service(
         &self,
         host: String,
         port: u16,
         shared_components: SharedRundlerComponents,
         paymaster_service: Option<Arc<PaymasterRelayService>>,
+        builder: Option<Arc<LocalBuilderHandle>>,
+        extra_chains: Vec<ChainServices>,
+        config_path: &str,
+        gateway_section: &GatewaySectionConfig,
+        cors: &CorsConfig,
+        audit_log: &AuditLogConfig,
+        verification_proofs: Arc<VerificationProofStore>,
+        prometheus: Option<PrometheusHandle>,
+        shutdown: ShutdownController,
+        skip_preflight: bool,
     ) -> Result<JoinHandle<Result<()>>> {
         info!("🌐 Starting Gateway service on {}:{}...", host, port);
 
         let gateway_config = GatewayConfig {
             host: host.clone(),
             port,
-            enable_logging: true,
-            enable_cors: true,
-            max_connections: 1000,
-            request_timeout: 30,
+            logging: gateway_section.logging.clone(),
+            cors: cors.clone(),
+            compression: gateway_section.compression.clone(),
+            connection_limits: gateway_section.connection_limits.clone(),
+            request_timeout: gateway_section.request_timeout_seconds.unwrap_or(30),
+            method_timeouts: gateway_section.method_timeouts.clone(),
+            drain_timeout: gateway_section.drain_timeout_seconds,
+            api_keys: gateway_section.api_keys.clone(),
+            health: gateway_section.health.clone(),
+            admin_auth: gateway_section.admin_auth.clone(),
+            validation: gateway_section.validation.clone(),
+            metrics: gateway_section.metrics.clone(),
+            events: gateway_section.events.clone(),
+            admin: gateway_section.admin.clone(),
+            preflight: gateway_section.preflight(skip_preflight),
+            entry_point_checks: gateway_section.entry_point_checks.clone(),
         };
 
         let eth_config = EthApiConfig {
-            chain_id: shared_components.rundler_config.chain_id,
-            entry_points: shared_components
-                .rundler_config
-                .entry_points
-                .iter()
-                .filter_map(|ep| ep.parse().ok())
-                .collect(),
+            chain_id: shared_components.chain_spec.id,
+            entry_points: shared_components.entry_points.to_vec(),
+            chain_spec: shared_components.chain_spec.clone(),
         };
 
-        let gateway = PaymasterGateway::with_rundler_components(
+        let deposit_probes = Self::deposit_probes(
+            &shared_components,
+            paymaster_service.as_ref(),
+            gateway_section,
+        );
+
+        let bundle_tracker = gateway_section.bundle_tracker(
+            &shared_components.builder_events,
+            paymaster_service.as_ref(),
+        );
+        let mut gateway = PaymasterGateway::with_rundler_components(
             gateway_config,
             paymaster_service,
             shared_components.pool.clone(),
             eth_config,
-        );
+        )
+        .with_receipt_provider(shared_components.receipt_provider.clone())
+        .with_nonce_reader(shared_components.nonce_reader.clone())
+        .with_code_reader(shared_components.code_reader.clone())
+        .with_gas_estimator(shared_components.gas_estimator.clone())
+        .with_bundle_simulator(shared_components.bundle_simulator.clone())
+        .with_gas_price_oracle(gateway_section.gas_price_oracle(&shared_components)?)
+        .with_signature_validator(shared_components.signature_validator.clone())
+        .with_signature_aggregators(shared_components.chain_spec.signature_aggregators.clone())
+        .with_version_selector(shared_components.version_selector.clone())
+        .with_bundle_tracker(bundle_tracker)
+        .with_response_cache(gateway_section.response_cache(shared_components.block_source.clone()))
+        .with_verification_proofs(verification_proofs)
+        .with_debug_api(gateway_section.enable_debug_api)
+        .with_op_permissions(gateway_section.op_permissions.clone())
+        .with_conditional_send(gateway_section.conditional_send.clone())
+        .with_batch_sponsorship(gateway_section.batch_sponsorship.clone())
+        .with_shutdown(shutdown);
+        if let Some(builder) = builder {
+            gateway = gateway.with_builder(builder);
+        }
+        gateway
+            .events()
+            .forward_builder_events(shared_components.builder_events.subscribe());
+        if let Some(handle) = prometheus {
+            gateway = gateway.with_prometheus(handle);
+        }
+        for probe in shared_components
+            .node_probes
+            .iter()
+            .cloned()
+            .chain(deposit_probes)
+        {
+            gateway = gateway.with_health_probe(probe);
+        }
+
+        // 所有链共享同一个检查流水线及统计
+        let pipeline = gateway_section.module_pipeline()?;
+        gateway = gateway.with_pipeline(pipeline.clone());
+
+        // 所有链共享同一份威胁情报
+        let threat_intel = gateway_section.start_threat_intel()?;
+        if let Some(store) = &threat_intel {
+            gateway = gateway
+                .with_threat_intel(store.clone())
+                .with_health_probe(Arc::new(ThreatIntelProbe::new(store.clone())));
+        }
+
+        // 发送者访问控制在所有链之前执行
+        if let Some(access_control) = gateway_section.start_access_control(audit_log)? {
+            gateway = gateway.with_access_control(access_control);
+        }
+
+        // 所有链共享同一条告警总线
+        let alerts = gateway_section.start_alerts()?;
+        gateway = gateway.with_alerts(alerts.clone());
+
+        // 其他链按请求中的chainId / X-Chain-Id路由
+        for chain in extra_chains {
+            let components = chain.components;
+            info!(
+                "⛓️  Gateway also serves chain {} ({} entry points)",
+                components.chain_spec.id,
+                components.entry_points.len()
+            );
+            let eth_config = EthApiConfig {
+                chain_id: components.chain_spec.id,
+                entry_points: components.entry_points.to_vec(),
+                chain_spec: components.chain_spec.clone(),
+            };
+            let mut router =
+                GatewayRouter::with_rundler_components(components.pool.clone(), eth_config)
+                    .with_receipt_provider(components.receipt_provider.clone())
+                    .with_nonce_reader(components.nonce_reader.clone())
+                    .with_code_reader(components.code_reader.clone())
+                    .with_gas_estimator(components.gas_estimator.clone())
+                    .with_bundle_simulator(components.bundle_simulator.clone())
+                    .with_gas_price_oracle(gateway_section.gas_price_oracle(&components)?)
+                    .with_signature_validator(components.signature_validator.clone())
+                    .with_signature_aggregators(components.chain_spec.signature_aggregators.clone())
+                    .with_version_selector(components.version_selector.clone())
+                    .with_bundle_tracker(gateway_section.bundle_tracker(
+                        &components.builder_events,
+                        chain.paymaster_service.as_ref(),
+                    ))
+                    .with_response_cache(
+                        gateway_section.response_cache(components.block_source.clone()),
+                    )
+                    .with_pipeline(pipeline.clone())
+                    .with_debug_api(gateway_section.enable_debug_api)
+                    .with_limits(gateway_section.validation.clone())
+                    .with_op_permissions(gateway_section.op_permissions.clone())
+                    .with_conditional_send(gateway_section.conditional_send.clone())
+                    .with_batch_sponsorship(gateway_section.batch_sponsorship.clone())
+                    .with_alerts(alerts.clone());
+            if let Some(service) = &chain.paymaster_service {
+                router = router.with_paymaster_service(service.clone());
+            }
+            if let Some(store) = &threat_intel {
+                router = router.with_threat_intel(store.clone());
+            }
+
+            for probe in Self::deposit_probes(
+                &components,
+                chain.paymaster_service.as_ref(),
+                gateway_section,
+            ) {
+                gateway = gateway.with_health_probe(probe);
+            }
+            for probe in &components.node_probes {
+                gateway = gateway.with_health_probe(probe.clone());
+            }
+            gateway
+                .events()
+                .forward_builder_events(components.builder_events.subscribe());
+            gateway = gateway.with_chain(ChainRoute::new(router, chain.paymaster_service));
+        }
+
+        gateway_section.watch_connection_limits(config_path, gateway.connection_limiter());
 
         // 在独立的tokio任务中启动Gateway
         let task = tokio::spawn(async move {
@@ -772,79 +2765,225 @@ impl Cli {
         Ok(task)
     }
 
+    /// 一条链上各EntryPoint的Paymaster存款就绪探针
+    fn deposit_probes(
+        components: &SharedRundlerComponents,
+        paymaster_service: Option<&Arc<PaymasterRelayService>>,
+        gateway_section: &GatewaySectionConfig,
+    ) -> Vec<Arc<dyn HealthProbe>> {
+        let Some(service) = paymaster_service else {
+            return Vec::new();
+        };
+
+        components
+            .entry_point_deposits
+            .iter()
+            .map(|(name, entry_point)| {
+                let name = match &components.chain_label {
+                    Some(label) => format!("{}_{}", name, label),
+                    None => name.to_string(),
+                };
+                Arc::new(DepositProbe::new(
+                    name,
+                    entry_point.clone(),
+                    service.clone(),
+                    gateway_section.health.min_paymaster_deposit_wei,
+                )) as Arc<dyn HealthProbe>
+            })
+            .collect()
+    }
+
+    /// 启动进程内Bundle Builder，返回其句柄与监管任务
+    ///
+    /// Builder任务退出视为服务异常退出；关闭信号触发后优雅停止Builder与签名者任务
+    async fn start_builder_service(
+        &self,
+        shared_components: &SharedRundlerComponents,
+        settings: &ResolvedSettings,
+        shutdown: ShutdownController,
+    ) -> Result<(Arc<LocalBuilderHandle>, JoinHandle<Result<()>>)> {
+        info!(
+            "📦 Starting bundle builder on chain {}...",
+            shared_components.chain_spec.id
+        );
+
+        // 签名者不足、节点不可达等启动错误直接返回
+        let (handle, mut task_manager) = shared_components
+            .bundle_builder
+            .start(settings.bundler_keys()?)
+            .await?;
+        info!("✅ Bundle builder started successfully");
+
+        let task = tokio::spawn(async move {
+            let result = tokio::select! {
+                _ = shutdown.wait() => Ok(()),
+                panicked = &mut task_manager => {
+                    Err(eyre::eyre!("Bundle builder task panicked: {:?}", panicked))
+                }
+            };
+
+            // TaskManager的关闭是阻塞调用，放到阻塞线程中执行
+            tokio::task::spawn_blocking(move || {
+                task_manager.graceful_shutdown_with_timeout(Duration::from_secs(10))
+            })
+            .await?;
+            info!("🛑 Bundle builder stopped");
+            result
+        });
+
+        Ok((Arc::new(handle), task))
+    }
+
     /// 启动Rundler RPC服务 (3001端口)
     async fn start_rundler_rpc_service(
         &self,
-        _shared_components: SharedRundlerComponents,
+        shared_components: SharedRundlerComponents,
         rundler_port: u16,
+        api_namespaces: &[String],
+        shutdown: ShutdownController,
     ) -> Result<JoinHandle<Result<()>>> {
         info!(
-            "🔄 Starting Rundler RPC service on 127.0.0.1:{}...",
-            rundler_port
+            "🔄 Starting Rundler RPC service on 127.0.0.1:{} (api: {})...",
+            rundler_port,
+            api_namespaces.join(",")
         );
 
-        // TODO: Task 11.4 - 实现真实的rundler RPC服务启动
-        // 当前为占位符实现
+        // 绑定端口失败等启动错误直接返回
+        let mut task_manager = shared_components
+            .rundler_rpc
+            .start(rundler_port, api_namespaces)
+            .await?;
+        info!("✅ Rundler RPC service started successfully");
+
         let task = tokio::spawn(async move {
-            info!("✅ Rundler RPC service started successfully (placeholder)");
-            // 占位符：保持服务运行
-            tokio::time::sleep(std::time::Duration::from_secs(u64::MAX)).await;
-            Ok(())
+            let result = tokio::select! {
+                _ = shutdown.wait() => Ok(()),
+                panicked = &mut task_manager => {
+                    Err(eyre::eyre!("Rundler RPC task panicked: {:?}", panicked))
+                }
+            };
+
+            // TaskManager的关闭是阻塞调用，放到阻塞线程中执行
+            tokio::task::spawn_blocking(move || {
+                task_manager.graceful_shutdown_with_timeout(Duration::from_secs(10))
+            })
+            .await?;
+            info!("🛑 Rundler RPC service stopped");
+            result
         });
 
         Ok(task)
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn run_gateway(
         &self,
         config_path: String,
         host: String,
         port: u16,
+        enable_builder: bool,
         enable_paymaster: bool,
-        _paymaster_private_key: Option<String>,
-        _paymaster_policy_file: Option<String>,
+        paymaster_private_key: Option<String>,
+        paymaster_policy_file: Option<String>,
+        secrets_file: Option<&str>,
+        skip_preflight: bool,
     ) -> Result<()> {
         info!("🌐 Starting SuperRelay Gateway Mode");
         info!("📍 Gateway will bind to {}:{}", host, port);
 
-        // Parse configuration file
-        let config_content = fs::read_to_string(&config_path)
-            .map_err(|e| eyre::eyre!("Failed to read config file '{}': {}", config_path, e))?;
+        // Parse configuration file, reporting every missing or invalid setting at once;
+        // the secrets file is decrypted before any service starts
+        let _super_config =
+            self.configuration(&config_path, secrets_file)?
+                .resolve(&CliOverrides {
+                    paymaster_private_key,
+                    paymaster_policy_file,
+                    require_paymaster: enable_paymaster,
+                    require_builder_keys: enable_builder,
+                    ..self.overrides()
+                })?;
+
+        if !_super_config.chains.is_empty() {
+            info!("⛓️  [[chains]] is only served in dual-service mode, using [gateway] chain");
+        }
+        _super_config
+            .gateway
+            .admin
+            .validate_ports(&[("gateway", port)])
+            .map_err(|e| eyre::eyre!("Invalid [gateway.admin]: {}", e))?;
+
+        // Install the Prometheus recorder before any component records metrics
+        let prometheus = _super_config.gateway.install_metrics()?;
 
-        let expanded_content = expand_env_vars(&config_content);
-        let _super_config: SuperRelayConfig = toml::from_str(&expanded_content)
-            .map_err(|e| eyre::eyre!("Failed to parse config file: {}", e))?;
+        // Resolve the chain spec, then chain id and EntryPoints from [gateway]
+        let (network, node_http, eth_config) = default_chain_settings(&_super_config)?;
+
+        // The gateway, the pool supervisor and /metrics stop on the same signal
+        let shutdown = ShutdownController::new();
+        shutdown.listen_for_signals();
 
         // Create gateway configuration
         let gateway_config = GatewayConfig {
             host,
             port,
-            enable_logging: true,
-            enable_cors: true,
-            max_connections: 1000,
-            request_timeout: 30,
+            logging: _super_config.gateway.logging.clone(),
+            cors: _super_config.cors.clone(),
+            compression: _super_config.gateway.compression.clone(),
+            connection_limits: _super_config.gateway.connection_limits.clone(),
+            request_timeout: _super_config.gateway.request_timeout_seconds.unwrap_or(30),
+            method_timeouts: _super_config.gateway.method_timeouts.clone(),
+            drain_timeout: _super_config.gateway.drain_timeout_seconds,
+            api_keys: _super_config.gateway.api_keys.clone(),
+            health: _super_config.gateway.health.clone(),
+            admin_auth: _super_config.gateway.admin_auth.clone(),
+            validation: _super_config.gateway.validation.clone(),
+            metrics: _super_config.gateway.metrics.clone(),
+            events: _super_config.gateway.events.clone(),
+            admin: _super_config.gateway.admin.clone(),
+            preflight: _super_config.gateway.preflight(skip_preflight),
+            entry_point_checks: _super_config.gateway.entry_point_checks.clone(),
         };
 
-        // In Gateway mode, we still need to create the full rundler infrastructure
-        // to provide real functionality. The Gateway will call these components directly.
+        // In Gateway mode the Gateway calls the rundler components directly, so the
+        // pool task runs in this process under the pool supervisor
         info!("🔧 Initializing rundler components for Gateway mode...");
-
-        // TODO: Initialize full rundler components (Pool, Builder, etc.)
-        // For now, create a minimal pool handle as placeholder
-        let pool_builder = LocalPoolBuilder::new(100);
-        let pool_handle = Arc::new(pool_builder.get_handle());
-
-        // TODO: In full implementation, we would:
-        // 1. Parse rundler configuration
-        // 2. Initialize Provider, Pool, Builder components
-        // 3. Start background tasks for these components
-        // 4. Pass the real component handles to Gateway
+        let components = self
+            .build_rundler_components(
+                &_super_config,
+                network,
+                node_http,
+                _super_config.node.secondary_node_http.clone(),
+                eth_config.clone(),
+                None,
+                &shutdown,
+            )
+            .await?;
+        info!(
+            "⚙️ Pool supervisor: backoff {}ms..{}ms, max restarts {}",
+            _super_config.pool.supervisor.initial_backoff_ms,
+            _super_config.pool.supervisor.max_backoff_ms,
+            _super_config
+                .pool
+                .supervisor
+                .max_restart_attempts
+                .map_or("unlimited".to_string(), |max| max.to_string())
+        );
 
         // Initialize paymaster service if enabled
         let paymaster_service = if enable_paymaster {
             info!("🔐 Initializing PaymasterRelay service");
 
-            match self.initialize_paymaster_service(&pool_handle).await {
+            match self
+                .initialize_paymaster_service(
+                    &components.pool.current()?,
+                    &components.chain_spec,
+                    &_super_config.signer,
+                    &_super_config.paymaster_relay.audit_log,
+                    &_super_config.settings,
+                    None,
+                )
+                .await
+            {
                 Ok(service) => {
                     info!("✅ PaymasterRelay service initialized successfully");
                     Some(Arc::new(service))
@@ -859,19 +2998,82 @@ impl Cli {
             None
         };
 
-        // Create ETH API configuration
-        let eth_config = EthApiConfig {
-            chain_id: 31337,      // Anvil default, can be configured later
-            entry_points: vec![], // Use defaults from router
-        };
-
         // Create and start gateway with rundler components
-        let gateway = PaymasterGateway::with_rundler_components(
+        let mut gateway = PaymasterGateway::with_rundler_components(
             gateway_config,
             paymaster_service,
-            pool_handle.clone(),
-            eth_config,
-        );
+            components.pool.clone(),
+            EthApiConfig {
+                chain_spec: components.chain_spec.clone(),
+                ..eth_config
+            },
+        )
+        .with_shutdown(shutdown.clone());
+        for probe in &components.node_probes {
+            gateway = gateway.with_health_probe(probe.clone());
+        }
+        gateway
+            .events()
+            .forward_builder_events(components.builder_events.subscribe());
+        gateway = gateway
+            .with_verification_proofs(_super_config.paymaster_relay.verification_proof_store()?)
+            .with_pipeline(_super_config.gateway.module_pipeline()?)
+            .with_debug_api(_super_config.gateway.enable_debug_api)
+            .with_op_permissions(_super_config.gateway.op_permissions.clone())
+            .with_conditional_send(_super_config.gateway.conditional_send.clone())
+            .with_batch_sponsorship(_super_config.gateway.batch_sponsorship.clone())
+            .with_version_selector(components.version_selector.clone())
+            .with_code_reader(components.code_reader.clone())
+            .with_gas_price_oracle(_super_config.gateway.gas_price_oracle(&components)?)
+            .with_response_cache(
+                _super_config
+                    .gateway
+                    .response_cache(components.block_source.clone()),
+            )
+            .with_signature_validator(components.signature_validator.clone());
+        if let Some(store) = _super_config.gateway.start_threat_intel()? {
+            gateway = gateway
+                .with_threat_intel(store.clone())
+                .with_health_probe(Arc::new(ThreatIntelProbe::new(store)));
+        }
+        // The builder stops the gateway when its task exits, like the other services
+        if enable_builder {
+            let (builder, builder_task) = self
+                .start_builder_service(&components, &_super_config.settings, shutdown.clone())
+                .await?;
+            gateway = gateway.with_builder(builder);
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                if let Ok(Err(e)) = builder_task.await {
+                    error!("❌ {}", e);
+                    shutdown.trigger();
+                }
+            });
+        }
+        if let Some(access_control) = _super_config
+            .gateway
+            .start_access_control(&_super_config.paymaster_relay.audit_log)?
+        {
+            gateway = gateway.with_access_control(access_control);
+        }
+        gateway = gateway.with_alerts(_super_config.gateway.start_alerts()?);
+        if let Some(handle) = &prometheus {
+            gateway = gateway.with_prometheus(handle.clone());
+        }
+        if let Some(metrics_task) = _super_config
+            .gateway
+            .serve_metrics(prometheus.as_ref(), shutdown.clone())
+        {
+            tokio::spawn(async move {
+                if let Ok(Err(e)) = metrics_task.await {
+                    error!("❌ {}", e);
+                }
+            });
+        }
+
+        _super_config
+            .gateway
+            .watch_connection_limits(&config_path, gateway.connection_limiter());
 
         info!("✨ Gateway initialization complete");
         info!("🚀 Starting SuperRelay Gateway server...");
@@ -880,6 +3082,7 @@ impl Cli {
             .start()
             .await
             .map_err(|e| eyre::eyre!("Gateway failed: {}", e))?;
+        wait_for_pool_snapshots(&[components.pool_snapshot.clone()]).await;
 
         Ok(())
     }
@@ -887,26 +3090,50 @@ impl Cli {
     async fn initialize_paymaster_service(
         &self,
         pool: &Arc<LocalPoolHandle>,
+        chain_spec: &Arc<ChainSpec>,
+        signer_config: &SignerSectionConfig,
+        audit_log: &AuditLogConfig,
+        settings: &ResolvedSettings,
+        signer_key_env: Option<&str>,
     ) -> Result<PaymasterRelayService> {
         info!("🔧 Setting up PaymasterRelay service components...");
 
-        // 1. Load private key from environment or config
-        let private_key = self.load_paymaster_private_key()?;
-        let secret_key = SecretString::new(private_key.into());
-
-        // 2. Initialize SignerManager
+        // 1-2. Initialize SignerManager with the configured backend
         info!("🔑 Initializing SignerManager...");
-        let signer_manager = SignerManager::new(secret_key)
-            .map_err(|e| eyre::eyre!("Failed to create SignerManager: {}", e))?;
+        let signer_manager = match signer_config.backend {
+            SignerBackendKind::PrivateKey => {
+                match (signer_key_env, &settings.paymaster_private_key) {
+                    (Some(var), _) => info!("🔐 Loading paymaster private key from {}", var),
+                    (None, Some(key)) => {
+                        info!("🔐 Loading paymaster private key from {}", key.source)
+                    }
+                    (None, None) => {}
+                }
+                SignerManager::new(settings.paymaster_key(signer_key_env)?)
+                    .map_err(|e| eyre::eyre!("Failed to create SignerManager: {}", e))?
+            }
+            SignerBackendKind::AwsKms => {
+                info!("🔐 Using AWS KMS signer backend");
+                SignerManager::new_with_aws_kms(signer_config.to_kms_config())
+                    .await
+                    .map_err(|e| eyre::eyre!("Failed to create AWS KMS SignerManager: {}", e))?
+            }
+        };
+
+        let signer_manager = signer_manager
+            .with_pool_keys(&signer_config.pool_keys)
+            .await
+            .map_err(|e| eyre::eyre!("Failed to load signer pool: {}", e))?;
 
         info!(
-            "✅ SignerManager initialized with address: {}",
-            signer_manager.address()
+            "✅ SignerManager initialized with address: {}, {} signing key(s)",
+            signer_manager.address(),
+            signer_manager.signer_keys().len()
         );
 
         // 3. Initialize PolicyEngine
         info!("📋 Loading policy configuration...");
-        let policy_file_path = self.get_policy_file_path();
+        let policy_file_path = &settings.policy_file.value;
         let policy_engine = PolicyEngine::new(&policy_file_path)
             .map_err(|e| eyre::eyre!("Failed to load policy engine: {}", e))?;
 
@@ -917,94 +3144,51 @@ impl Cli {
 
         // 4. Create PaymasterRelayService
         info!("🚀 Creating PaymasterRelayService...");
-        let service = PaymasterRelayService::new(signer_manager, policy_engine, pool.clone());
+        let service = PaymasterRelayService::new(signer_manager, policy_engine, pool.clone())
+            .with_chain_spec(chain_spec.clone())
+            .with_audit_logger(AuditLogger::new(audit_log));
+        match &audit_log.path {
+            Some(path) => info!("📝 Sponsorship audit log: {}", path.display()),
+            None => info!("📝 Sponsorship audit log: tracing target 'paymaster_audit'"),
+        }
 
         info!("✅ PaymasterRelayService created successfully");
         Ok(service)
     }
 
-    fn load_paymaster_private_key(&self) -> Result<String> {
-        // Priority order: Environment variable -> .env file -> error
-        if let Ok(key) = std::env::var("PAYMASTER_PRIVATE_KEY") {
-            info!("🔐 Loading paymaster private key from environment variable");
-            return Ok(key);
-        }
-
-        // Try loading from .env file for development
-        if let Ok(env_content) = std::fs::read_to_string(".env") {
-            for line in env_content.lines() {
-                if line.starts_with("PAYMASTER_PRIVATE_KEY=") {
-                    if let Some(key) = line.split('=').nth(1) {
-                        info!("🔐 Loading paymaster private key from .env file");
-                        return Ok(key.to_string());
-                    }
-                }
-            }
-        }
-
-        Err(eyre::eyre!(
-            "PAYMASTER_PRIVATE_KEY environment variable not found. \
-            Please set it or add it to .env file for development."
-        ))
-    }
-
-    fn get_policy_file_path(&self) -> std::path::PathBuf {
-        // Try environment variable first
-        if let Ok(path) = std::env::var("PAYMASTER_POLICY_FILE") {
-            return Path::new(&path).to_path_buf();
-        }
-
-        // Default to config/paymaster-policies.toml
-        Path::new("config/paymaster-policies.toml").to_path_buf()
-    }
-
     fn config_to_rundler_args(&self, config: &SuperRelayConfig) -> Result<Vec<String>> {
-        // Read network and node settings from environment variables or config file, supporting local development
-        let network = std::env::var("NETWORK")
-            .or_else(|_| std::env::var("CHAIN_NETWORK"))
-            .unwrap_or_else(|_| "dev".to_string());
-
-        let node_http = std::env::var("RPC_URL")
-            .or_else(|_| std::env::var("NODE_HTTP"))
-            .unwrap_or_else(|_| "http://localhost:8545".to_string());
-
-        // Smart private key management: prioritize environment variables, support .env files for testing
-        let signer_keys = std::env::var("SIGNER_PRIVATE_KEYS")
-            .or_else(|_| {
-                // Testing/development phase: try loading from .env file
-                if let Ok(env_content) = std::fs::read_to_string(".env") {
-                    for line in env_content.lines() {
-                        if line.starts_with("SIGNER_PRIVATE_KEYS=") {
-                            return Ok(line.split('=').nth(1).unwrap_or("").to_string());
-                        }
-                    }
-                }
-                Err(std::env::VarError::NotPresent)
-            })
-            .map_err(|_| {
-                eyre::eyre!(
-                    "🔐 Private key configuration required!\n\
-                \n\
-                🧪 For TESTING/DEVELOPMENT:\n\
-                   • Set SIGNER_PRIVATE_KEYS in .env file\n\
-                   • Or use: source ./scripts/load_dev_env.sh\n\
-                \n\
-                🏭 For PRODUCTION:\n\
-                   • Set SIGNER_PRIVATE_KEYS environment variable\n\
-                   • Future: Hardware wallet API support planned\n\
-                \n\
-                ⚠️  NEVER use test keys in production!"
-                )
-            })?;
+        let settings = &config.settings;
+
+        let signer_keys = settings.signer_private_keys.as_ref().ok_or_else(|| {
+            eyre::eyre!(
+                "🔐 Private key configuration required!\n\
+            \n\
+            🧪 For TESTING/DEVELOPMENT:\n\
+               • Set SIGNER_PRIVATE_KEYS in .env file\n\
+               • Or use: source ./scripts/load_dev_env.sh\n\
+            \n\
+            🏭 For PRODUCTION:\n\
+               • Set SIGNER_PRIVATE_KEYS environment variable\n\
+               • Future: Hardware wallet API support planned\n\
+            \n\
+            ⚠️  NEVER use test keys in production!"
+            )
+        })?;
 
         let mut args = vec![
-            "--network".to_string(),
-            network,
             "--node_http".to_string(),
-            node_http,
+            settings.node_http.value.clone(),
             "--signer.private_keys".to_string(),
-            signer_keys,
+            signer_keys.value.expose_secret().to_string(),
         ];
+        if let Some(network) = settings.spec_network() {
+            args.push("--network".to_string());
+            args.push(network.to_string());
+        }
+        if let Some(chain_spec) = &settings.chain_spec {
+            args.push("--chain_spec".to_string());
+            args.push(chain_spec.value.clone());
+        }
 
         // Node configuration
         if let Some(ref http_api) = config.node.http_api {
@@ -1033,35 +3217,23 @@ impl Cli {
             args.push("--max_verification_gas".to_string());
             args.push(max_verification_gas.to_string());
         }
+        if let Some(distance) = config.rpc.user_operation_event_block_distance {
+            args.push("--user_operation_event_block_distance".to_string());
+            args.push(distance.to_string());
+        }
 
         // Paymaster relay configuration
         if let Some(enabled) = config.paymaster_relay.enabled {
             if enabled {
                 args.push("--paymaster.enabled".to_string());
 
-                if let Some(ref private_key) = config.paymaster_relay.private_key {
+                if let Some(private_key) = &settings.paymaster_private_key {
                     args.push("--paymaster.private_key".to_string());
-                    // Handle both expanded and direct environment variable values
-                    if private_key.starts_with("${") && private_key.ends_with("}") {
-                        // If expansion failed, try direct environment variable lookup
-                        let var_name = &private_key[2..private_key.len() - 1];
-                        if let Ok(env_value) = std::env::var(var_name) {
-                            args.push(env_value);
-                        } else {
-                            eyre::bail!(
-                                "Environment variable {} not found for paymaster private key",
-                                var_name
-                            );
-                        }
-                    } else {
-                        args.push(private_key.clone());
-                    }
+                    args.push(private_key.value.expose_secret().to_string());
                 }
 
-                if let Some(ref policy_file) = config.paymaster_relay.policy_file {
-                    args.push("--paymaster.policy_file".to_string());
-                    args.push(policy_file.clone());
-                }
+                args.push("--paymaster.policy_file".to_string());
+                args.push(settings.policy_file.value.display().to_string());
             }
         }
 
@@ -1092,100 +3264,344 @@ impl Cli {
         println!("  - Real-time monitoring & metrics");
         println!("  - Swagger UI (separate deployment)");
     }
+}
 
-    async fn check_status(&self) -> Result<()> {
-        println!("🔍 Checking SuperRelay service status...\n");
+/// Network name, node URL and gateway chain config of the chain served
+/// when no [[chains]] are configured
+fn default_chain_settings(config: &SuperRelayConfig) -> Result<(String, String, EthApiConfig)> {
+    // Provider配置 (CLI参数 > 环境变量 > 默认值，见 config_system)
+    let settings = &config.settings;
+    let network = settings.network.value.clone();
+    let node_http = settings.node_http.value.clone();
+
+    let chain_spec = resolve_chain_spec(
+        settings.spec_network(),
+        settings.chain_spec.as_ref().map(|file| file.value.as_str()),
+    )?;
+
+    // Gateway链配置: [gateway] 未设置时回退到chain spec
+    let eth_config = config.gateway.to_eth_api_config(chain_spec)?;
+    Ok((network, node_http, eth_config))
+}
 
-        // Check main RPC service
-        match self
-            .check_endpoint("http://localhost:3000", "Main RPC Service")
-            .await
-        {
-            Ok(_) => println!("✅ Main RPC Service: RUNNING"),
-            Err(_) => println!("❌ Main RPC Service: NOT RUNNING"),
+/// Resolve a chain spec the way the rundler CLI does: defaults, the base and
+/// network specs, the spec file, then CHAIN_* environment variables
+fn resolve_chain_spec(network: Option<&str>, file: Option<&str>) -> Result<ChainSpec> {
+    if let Some(network) = network {
+        if !chain_spec::HARDCODED_CHAIN_SPECS.contains(&network.to_lowercase().as_str()) {
+            eyre::bail!(
+                "Unknown network '{}', expected one of: {}",
+                network,
+                chain_spec::HARDCODED_CHAIN_SPECS.join(", ")
+            );
         }
+    }
+    if let Some(file) = file {
+        config::Config::builder()
+            .add_source(config::File::with_name(file))
+            .build()
+            .map_err(|e| eyre::eyre!("Invalid chain spec file {}: {}", file, e))?;
+    }
+    Ok(chain_spec::resolve_chain_spec(
+        &network.map(str::to_string),
+        &file.map(str::to_string),
+    ))
+}
 
-        // Check Swagger UI
-        match self
-            .check_endpoint("http://localhost:9000/health", "Swagger UI & Monitoring")
+/// Wait for the pool supervisors to write their snapshots before exiting
+async fn wait_for_pool_snapshots(snapshots: &[Arc<PoolSnapshotter>]) {
+    for snapshot in snapshots.iter().filter(|snapshot| snapshot.is_enabled()) {
+        if tokio::time::timeout(POOL_SNAPSHOT_WAIT, snapshot.wait_finished())
             .await
+            .is_err()
         {
-            Ok(_) => println!("✅ Swagger UI & Monitoring: RUNNING"),
-            Err(_) => println!("❌ Swagger UI & Monitoring: NOT RUNNING"),
+            warn!(
+                "⚠️ Pool snapshot not written within {:?}, pending operations are lost",
+                POOL_SNAPSHOT_WAIT
+            );
         }
+    }
+}
 
-        // Check Prometheus metrics
-        match self
-            .check_endpoint("http://localhost:8080/metrics", "Prometheus Metrics")
-            .await
-        {
-            Ok(_) => println!("✅ Prometheus Metrics: RUNNING"),
-            Err(_) => println!("❌ Prometheus Metrics: NOT RUNNING"),
+/// Fail fast when the configured chain id differs from the one the node reports
+async fn verify_node_chain_id<P: EvmProvider>(provider: &P, configured: u64) -> Result<()> {
+    let reported: String = provider
+        .request("eth_chainId", ())
+        .await
+        .map_err(|e| eyre::eyre!("Failed to query eth_chainId from node: {}", e))?;
+    let reported = u64::from_str_radix(reported.trim_start_matches("0x"), 16)
+        .map_err(|e| eyre::eyre!("Invalid eth_chainId response '{}': {}", reported, e))?;
+
+    check_chain_id(configured, reported)?;
+    info!("✅ Node chain id {} matches configuration", reported);
+    Ok(())
+}
+
+/// EntryPoint versions of the chain spec's addresses, cross-checked once
+/// against each configured EntryPoint contract
+///
+/// Configured addresses outside the chain spec take the version the contract
+/// reports; when a contract cannot be queried the chain spec's version is kept.
+async fn entry_point_versions<P: EvmProvider>(
+    provider: &P,
+    chain_spec: &ChainSpec,
+    entry_points: &[Address],
+) -> Result<Arc<VersionSelector>> {
+    let mut selector = VersionSelector::from_chain_spec(chain_spec);
+    for entry_point in entry_points {
+        let declared = selector.declared_version(*entry_point);
+        match (
+            detect_entry_point_version(provider, *entry_point).await,
+            declared,
+        ) {
+            (Ok(detected), Some(declared)) if detected != declared => eyre::bail!(
+                "EntryPoint {} is configured as {:?} but the contract reports {:?}",
+                entry_point,
+                declared,
+                detected
+            ),
+            (Ok(detected), _) => {
+                info!("📜 EntryPoint {} is {:?}", entry_point, detected);
+                selector = selector.with_entry_point(*entry_point, detected);
+            }
+            (Err(e), Some(declared)) => {
+                warn!(
+                    "⚠️ Could not query EntryPoint {} version ({}), assuming {:?}",
+                    entry_point, e, declared
+                );
+            }
+            (Err(e), None) => {
+                warn!(
+                    "⚠️ EntryPoint {} version unknown ({}), operations are parsed by their fields",
+                    entry_point, e
+                );
+            }
         }
+    }
+    Ok(Arc::new(selector))
+}
 
-        println!("\n📋 Service URLs:");
-        println!("  🌐 Swagger UI: http://localhost:9000/swagger-ui/");
-        println!("  🏥 Health Check: http://localhost:9000/health");
-        println!("  📊 Metrics: http://localhost:9000/metrics");
-        println!("  📈 Prometheus: http://localhost:8080/metrics");
-        println!("  🔧 Main RPC: http://localhost:3000");
+/// Compare the chain spec's chain id against the node-reported one
+fn check_chain_id(configured: u64, reported: u64) -> Result<()> {
+    if configured != reported {
+        eyre::bail!(
+            "Chain id mismatch: the chain spec is for chain {} but the connected node reports {}",
+            configured,
+            reported
+        );
+    }
+    Ok(())
+}
 
-        Ok(())
+/// [gateway.connection_limits] of a config file, defaults when absent
+fn parse_connection_limits(content: &str) -> Result<ConnectionLimitConfig> {
+    let config: toml::Value = toml::from_str(content)?;
+    match config
+        .get("gateway")
+        .and_then(|gateway| gateway.get("connection_limits"))
+    {
+        Some(limits) => limits
+            .clone()
+            .try_into()
+            .map_err(|e| eyre::eyre!("Invalid [gateway.connection_limits]: {}", e)),
+        None => Ok(ConnectionLimitConfig::default()),
     }
+}
 
-    async fn check_endpoint(&self, url: &str, _service: &str) -> Result<()> {
-        // Simple TCP connection check (avoiding external dependencies)
-        use std::{net::TcpStream, time::Duration};
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    cli.run().await
+}
 
-        let url_parts: Vec<&str> = url.split("://").collect();
-        if url_parts.len() != 2 {
-            return Err(eyre::eyre!("Invalid URL format"));
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE_CONFIG: &str = r#"
+[node]
+[pool]
+[rpc]
+[paymaster_relay]
+[mempool]
+"#;
+
+    #[test]
+    fn test_gateway_section_parsing() {
+        let content = format!(
+            r#"{}
+[gateway]
+chain_id = 11155111
+entry_points = [
+    "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789",
+    "0x0000000071727De22E5E9d8BAf0edAc6f37da032",
+]
+"#,
+            BASE_CONFIG
+        );
+        let config: SuperRelayConfig = toml::from_str(&content).unwrap();
+
+        let eth_config = config
+            .gateway
+            .to_eth_api_config(ChainSpec::default())
+            .unwrap();
+        assert_eq!(eth_config.chain_id, 11155111);
+        assert_eq!(eth_config.entry_points.len(), 2);
+        assert_eq!(
+            format!("{:#x}", eth_config.entry_points[1]),
+            "0x0000000071727de22e5e9d8baf0edac6f37da032"
+        );
+    }
 
-        let host_port: Vec<&str> = url_parts[1].split('/').next().unwrap().split(':').collect();
-        if host_port.len() != 2 {
-            return Err(eyre::eyre!("Invalid host:port format"));
-        }
+    #[test]
+    fn test_gateway_section_defaults() {
+        let config: SuperRelayConfig = toml::from_str(BASE_CONFIG).unwrap();
 
-        let host = host_port[0];
-        let port: u16 = host_port[1].parse()?;
+        let chain_spec = resolve_chain_spec(Some("dev"), None).unwrap();
+        let eth_config = config.gateway.to_eth_api_config(chain_spec).unwrap();
+        assert_eq!(eth_config.chain_id, 1337);
+        assert_eq!(
+            eth_config.entry_points,
+            vec![eth_config.chain_spec.entry_point_address_v0_6]
+        );
+        assert!(!config.gateway.enable_debug_api);
+    }
 
-        match TcpStream::connect_timeout(
-            &format!("{}:{}", host, port).parse()?,
-            Duration::from_secs(3),
-        ) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(eyre::eyre!("Connection failed: {}", e)),
-        }
+    #[test]
+    fn test_gateway_section_invalid_entry_point() {
+        let content = format!(
+            r#"{}
+[gateway]
+entry_points = ["0xnot-an-address"]
+"#,
+            BASE_CONFIG
+        );
+        let config: SuperRelayConfig = toml::from_str(&content).unwrap();
+
+        let err = config
+            .gateway
+            .to_eth_api_config(ChainSpec::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("0xnot-an-address"));
     }
-}
 
-/// Expand environment variables in the form ${VAR_NAME} in the given string
-fn expand_env_vars(content: &str) -> String {
-    let mut result = content.to_string();
+    #[test]
+    fn test_connection_limits_parsing() {
+        let content = format!(
+            r#"{}
+[gateway.connection_limits]
+max_connections = 5
+expensive_methods = ["eth_estimateUserOperationGas"]
+"#,
+            BASE_CONFIG
+        );
+        let config: SuperRelayConfig = toml::from_str(&content).unwrap();
+        let limits = parse_connection_limits(&content).unwrap();
+        assert_eq!(config.gateway.connection_limits, limits);
+        assert_eq!(limits.max_connections, 5);
+        assert_eq!(limits.max_expensive_requests, 32);
+        assert!(limits.is_expensive("eth_estimateUserOperationGas"));
+        assert!(!limits.is_expensive("eth_sendUserOperation"));
+
+        assert_eq!(
+            parse_connection_limits(BASE_CONFIG).unwrap(),
+            ConnectionLimitConfig::default()
+        );
+        let err = parse_connection_limits("[gateway.connection_limits]\nmax_connections = -1")
+            .unwrap_err();
+        assert!(err.to_string().contains("[gateway.connection_limits]"));
+    }
+
+    #[test]
+    fn test_custom_chain_spec_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("op_testnet.toml");
+        std::fs::write(
+            &path,
+            r#"
+base = "optimism"
+name = "OP Testnet"
+id = 11155420
+entry_point_address_v0_6 = "0x6666666666666666666666666666666666666666"
+"#,
+        )
+        .unwrap();
+        let file = path.to_str().unwrap();
+
+        // The file's values over its base network's, the base's over the defaults
+        let chain_spec = resolve_chain_spec(None, Some(file)).unwrap();
+        assert_eq!(chain_spec.name, "OP Testnet");
+        assert_eq!(chain_spec.id, 11155420);
+        assert_eq!(
+            chain_spec.entry_point_address_v0_6,
+            Address::repeat_byte(0x66)
+        );
+        assert!(chain_spec.da_pre_verification_gas);
+        assert_eq!(
+            chain_spec.entry_point_address_v0_7,
+            ChainSpec::default().entry_point_address_v0_7
+        );
+
+        // Without [gateway] settings the gateway serves the spec's chain
+        let config: SuperRelayConfig = toml::from_str(BASE_CONFIG).unwrap();
+        let eth_config = config.gateway.to_eth_api_config(chain_spec).unwrap();
+        assert_eq!(eth_config.chain_id, 11155420);
+        assert_eq!(eth_config.entry_points, vec![Address::repeat_byte(0x66)]);
 
-    // Find all ${VAR_NAME} patterns and replace them
-    let re = regex::Regex::new(r"\$\{([^}]+)\}").unwrap();
+        let err = resolve_chain_spec(Some("sepolia"), None).unwrap_err();
+        assert!(err.to_string().contains("ethereum_sepolia"));
+        let missing = dir.path().join("missing.toml");
+        assert!(resolve_chain_spec(None, missing.to_str()).is_err());
+    }
 
-    for captures in re.captures_iter(content) {
-        let full_match = captures.get(0).unwrap().as_str();
-        let var_name = captures.get(1).unwrap().as_str();
+    #[test]
+    fn test_chain_id_mismatch_detection() {
+        assert!(check_chain_id(11155111, 11155111).is_ok());
 
-        if let Ok(var_value) = std::env::var(var_name) {
-            result = result.replace(full_match, &var_value);
-        } else {
-            eprintln!(
-                "⚠️  Environment variable {} not set, keeping original value",
-                var_name
-            );
-        }
+        let err = check_chain_id(11155111, 31337).unwrap_err();
+        assert!(err.to_string().contains("Chain id mismatch"));
     }
 
-    result
-}
+    #[test]
+    fn test_signer_section_parsing() {
+        let config: SuperRelayConfig = toml::from_str(BASE_CONFIG).unwrap();
+        assert_eq!(config.signer.backend, SignerBackendKind::PrivateKey);
+
+        let content = format!(
+            r#"{}
+[signer]
+backend = "aws-kms"
+key_arn = "arn:aws:kms:us-east-1:123456789012:key/test"
+region = "eu-west-1"
+"#,
+            BASE_CONFIG
+        );
+        let config: SuperRelayConfig = toml::from_str(&content).unwrap();
+        assert_eq!(config.signer.backend, SignerBackendKind::AwsKms);
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
-    cli.run().await
+        let kms_config = config.signer.to_kms_config();
+        assert_eq!(
+            kms_config.aws_key_arn.as_deref(),
+            Some("arn:aws:kms:us-east-1:123456789012:key/test")
+        );
+        assert_eq!(kms_config.credentials.region_or_tenant, "eu-west-1");
+        assert!(kms_config.backup_key_ids.is_empty());
+        assert!(config.signer.pool_keys.is_empty());
+
+        let content = format!(
+            r#"{}
+[signer]
+pool_keys = [{{ env = "PAYMASTER_POOL_KEY_1" }}, {{ kms = "paymaster-backup-key-1" }}]
+"#,
+            BASE_CONFIG
+        );
+        let config: SuperRelayConfig = toml::from_str(&content).unwrap();
+        assert_eq!(
+            config.signer.pool_keys,
+            vec![
+                KeySource::Env("PAYMASTER_POOL_KEY_1".to_string()),
+                KeySource::Kms("paymaster-backup-key-1".to_string()),
+            ]
+        );
+    }
 }
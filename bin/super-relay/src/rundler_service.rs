@@ -0,0 +1,618 @@
+// Rundler RPC service (3001端口) for dual-service mode
+// 与Gateway共享同一个Pool句柄、ChainSpec和Provider
+// 以及进程内Pool任务的启动器，由Gateway的PoolSupervisor在任务退出后重启
+// 以及进程内Bundle Builder的启动器，从共享Pool取UserOperation打包上链
+
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use alloy_primitives::{uint, Address, B256, U256};
+use async_trait::async_trait;
+use eyre::Result;
+use reth_tasks::TaskManager;
+use rundler_builder::{
+    BuilderEvent, BuilderSettings, BuilderTask, BuilderTaskArgs, EntryPointBuilderSettings,
+    LocalBuilderBuilder, LocalBuilderHandle, RawSenderArgs, TransactionSenderArgs,
+};
+use rundler_pool::{LocalPoolBuilder, PoolConfig, PoolEvent, PoolTask, PoolTaskArgs};
+use rundler_provider::{
+    DAGasOracle, DAGasOracleSync, EntryPointProvider, EvmProvider, FeeEstimator, Providers,
+};
+use rundler_rpc::{EthApiSettings, RpcTask, RpcTaskArgs};
+use rundler_signer::SigningScheme;
+use rundler_sim::{EstimationSettings, PrecheckSettings, SimulationSettings};
+use rundler_task::server::{HealthCheck, ServerStatus};
+use rundler_types::{
+    builder::{Builder, BuilderError, BuilderResult, BundlingMode},
+    chain::ChainSpec,
+    v0_6::UserOperation as UserOperationV0_6,
+    v0_7::UserOperation as UserOperationV0_7,
+    EntryPointVersion, PriorityFeeMode,
+};
+use rundler_utils::emit::WithEntryPoint;
+use secrecy::SecretString;
+use super_relay_gateway::{
+    LaunchedPool, PoolLauncher, SupervisedPool, MAX_BUNDLE_BLOCK_GAS_LIMIT_RATIO,
+    TARGET_BUNDLE_BLOCK_GAS_LIMIT_RATIO,
+};
+use tokio::sync::broadcast;
+
+/// Default namespaces served on the rundler port
+pub const DEFAULT_RUNDLER_API: &[&str] = &["eth", "rundler", "debug"];
+
+/// Entry point versions enabled for this process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnabledEntryPoints {
+    pub v0_6: bool,
+    pub v0_7: bool,
+}
+
+impl EnabledEntryPoints {
+    /// Match configured EntryPoint addresses against the chain spec,
+    /// rejecting addresses that are neither the v0.6 nor the v0.7 EntryPoint
+    pub fn from_configured(chain_spec: &ChainSpec, configured: &[Address]) -> Result<Self> {
+        let mut enabled = Self {
+            v0_6: false,
+            v0_7: false,
+        };
+
+        for entry_point in configured {
+            if *entry_point == chain_spec.entry_point_address_v0_6 {
+                enabled.v0_6 = true;
+            } else if *entry_point == chain_spec.entry_point_address_v0_7 {
+                enabled.v0_7 = true;
+            } else {
+                return Err(eyre::eyre!(
+                    "EntryPoint {} is not the v0.6 ({}) or v0.7 ({}) EntryPoint of chain {}",
+                    entry_point,
+                    chain_spec.entry_point_address_v0_6,
+                    chain_spec.entry_point_address_v0_7,
+                    chain_spec.id
+                ));
+            }
+        }
+
+        if !enabled.v0_6 && !enabled.v0_7 {
+            return Err(eyre::eyre!("At least one EntryPoint must be configured"));
+        }
+
+        Ok(enabled)
+    }
+
+    /// Enabled EntryPoint addresses in the order rundler reports them (v0.6, then v0.7)
+    pub fn addresses(&self, chain_spec: &ChainSpec) -> Vec<Address> {
+        let mut addresses = Vec::new();
+        if self.v0_6 {
+            addresses.push(chain_spec.entry_point_address_v0_6);
+        }
+        if self.v0_7 {
+            addresses.push(chain_spec.entry_point_address_v0_7);
+        }
+        addresses
+    }
+}
+
+/// Provider set handed to the rundler RPC task
+#[derive(Clone)]
+pub struct SuperRelayProviders<P, EP06, EP07, D, DS, F> {
+    pub evm: P,
+    pub ep_v0_6: Option<EP06>,
+    pub ep_v0_7: Option<EP07>,
+    pub da_gas_oracle: D,
+    pub da_gas_oracle_sync: Option<DS>,
+    pub fee_estimator: F,
+}
+
+impl<P, EP06, EP07, D, DS, F> Providers for SuperRelayProviders<P, EP06, EP07, D, DS, F>
+where
+    P: EvmProvider + Clone,
+    EP06: EntryPointProvider<UserOperationV0_6> + Clone,
+    EP07: EntryPointProvider<UserOperationV0_7> + Clone,
+    D: DAGasOracle + Clone,
+    DS: DAGasOracleSync + Clone,
+    F: FeeEstimator + Clone,
+{
+    type Evm = P;
+    type EntryPointV0_6 = EP06;
+    type EntryPointV0_7 = EP07;
+    type DAGasOracle = D;
+    type DAGasOracleSync = DS;
+    type FeeEstimator = F;
+
+    fn evm(&self) -> &Self::Evm {
+        &self.evm
+    }
+
+    fn ep_v0_6(&self) -> &Option<Self::EntryPointV0_6> {
+        &self.ep_v0_6
+    }
+
+    fn ep_v0_7(&self) -> &Option<Self::EntryPointV0_7> {
+        &self.ep_v0_7
+    }
+
+    fn da_gas_oracle(&self) -> &Self::DAGasOracle {
+        &self.da_gas_oracle
+    }
+
+    fn da_gas_oracle_sync(&self) -> &Option<Self::DAGasOracleSync> {
+        &self.da_gas_oracle_sync
+    }
+
+    fn fee_estimator(&self) -> &Self::FeeEstimator {
+        &self.fee_estimator
+    }
+}
+
+/// Builder handle used when no bundle builder runs in this process
+///
+/// Reports the shared EntryPoint list; debug bundling calls fail explicitly.
+#[derive(Debug, Clone)]
+struct DetachedBuilder {
+    entry_points: Vec<Address>,
+}
+
+const BUILDER_NOT_RUNNING: &str =
+    "bundle builder is not served on the rundler port, use rundler_sendBundleNow on the gateway";
+
+#[async_trait]
+impl Builder for DetachedBuilder {
+    async fn get_supported_entry_points(&self) -> BuilderResult<Vec<Address>> {
+        Ok(self.entry_points.clone())
+    }
+
+    async fn debug_send_bundle_now(&self) -> BuilderResult<(B256, u64)> {
+        Err(BuilderError::Other(anyhow::anyhow!(BUILDER_NOT_RUNNING)))
+    }
+
+    async fn debug_set_bundling_mode(&self, _mode: BundlingMode) -> BuilderResult<()> {
+        Err(BuilderError::Other(anyhow::anyhow!(BUILDER_NOT_RUNNING)))
+    }
+}
+
+#[async_trait]
+impl HealthCheck for DetachedBuilder {
+    fn name(&self) -> &'static str {
+        "DetachedBuilder"
+    }
+
+    async fn status(&self) -> ServerStatus {
+        ServerStatus::Serving
+    }
+}
+
+/// Starts the rundler RPC server without exposing the concrete provider types
+pub trait RundlerRpcLauncher: Send + Sync {
+    /// Bind 127.0.0.1:`port` and serve the given namespaces; the returned
+    /// task manager owns the server and stops it on shutdown
+    fn start<'a>(
+        &'a self,
+        port: u16,
+        api_namespaces: &'a [String],
+    ) -> Pin<Box<dyn Future<Output = Result<TaskManager>> + 'a>>;
+}
+
+/// Gas estimation settings shared by the rundler RPC and the gateway
+pub fn estimation_settings(
+    chain_spec: &ChainSpec,
+    max_verification_gas: u64,
+) -> EstimationSettings {
+    let max_bundle_execution_gas = chain_spec.block_gas_limit_mult(0.9);
+    let max_verification_gas = max_verification_gas as u128;
+    EstimationSettings {
+        max_verification_gas,
+        max_paymaster_verification_gas: max_verification_gas,
+        max_paymaster_post_op_gas: max_bundle_execution_gas,
+        max_bundle_execution_gas,
+        max_gas_estimation_gas: 550_000_000,
+        verification_estimation_gas_fee: 1_000_000_000_000,
+        verification_gas_limit_efficiency_reject_threshold: 0.0,
+        verification_gas_allowed_error_pct: 15,
+        call_gas_allowed_error_pct: 15,
+        max_gas_estimation_rounds: 3,
+    }
+}
+
+/// Precheck settings shared by the rundler RPC and the in-process pool
+fn precheck_settings(chain_spec: &ChainSpec, max_verification_gas: u64) -> PrecheckSettings {
+    PrecheckSettings {
+        max_verification_gas: max_verification_gas as u128,
+        max_bundle_execution_gas: chain_spec.block_gas_limit_mult(0.9),
+        max_uo_cost: U256::MAX,
+        bundle_priority_fee_overhead_percent: 0,
+        priority_fee_mode: PriorityFeeMode::BaseFeePercent(50),
+        base_fee_accept_percent: 50,
+        pre_verification_gas_accept_percent: 50,
+        verification_gas_limit_efficiency_reject_threshold: 0.0,
+    }
+}
+
+/// Simulation settings shared by the in-process pool and builder
+fn simulation_settings() -> SimulationSettings {
+    SimulationSettings {
+        min_unstake_delay: 84600,
+        min_stake_value: uint!(1_000_000_000_000_000_000_U256),
+        tracer_timeout: "10s".to_string(),
+        enable_unsafe_fallback: false,
+    }
+}
+
+/// Launcher over a concrete provider set
+pub struct ProvidersRpcLauncher<P> {
+    pub chain_spec: Arc<ChainSpec>,
+    pub enabled: EnabledEntryPoints,
+    pub node_http: String,
+    pub pool: SupervisedPool,
+    pub providers: P,
+    pub max_verification_gas: u64,
+    pub event_block_distance: Option<u64>,
+}
+
+impl<P: Providers + 'static> ProvidersRpcLauncher<P> {
+    fn task_args(&self, port: u16, api_namespaces: &[String]) -> Result<RpcTaskArgs> {
+        let chain_spec = (*self.chain_spec).clone();
+
+        let api_namespaces = api_namespaces
+            .iter()
+            .map(|api| {
+                api.parse()
+                    .map_err(|_| eyre::eyre!("Unknown rundler RPC namespace '{}'", api))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(RpcTaskArgs {
+            unsafe_mode: false,
+            port,
+            host: "127.0.0.1".to_string(),
+            api_namespaces,
+            rpc_url: self.node_http.clone(),
+            precheck_settings: precheck_settings(&chain_spec, self.max_verification_gas),
+            eth_api_settings: EthApiSettings {
+                user_operation_event_block_distance: self.event_block_distance,
+                user_operation_event_block_distance_fallback: None,
+                permissions_enabled: false,
+            },
+            estimation_settings: estimation_settings(&chain_spec, self.max_verification_gas),
+            rpc_timeout: Duration::from_secs(20),
+            max_connections: 100,
+            entry_point_v0_6_enabled: self.enabled.v0_6,
+            entry_point_v0_7_enabled: self.enabled.v0_7,
+            corsdomain: None,
+            rate_limit_config: None,
+            chain_spec,
+        })
+    }
+}
+
+impl<P: Providers + 'static> RundlerRpcLauncher for ProvidersRpcLauncher<P> {
+    fn start<'a>(
+        &'a self,
+        port: u16,
+        api_namespaces: &'a [String],
+    ) -> Pin<Box<dyn Future<Output = Result<TaskManager>> + 'a>> {
+        Box::pin(async move {
+            let args = self.task_args(port, api_namespaces)?;
+            let builder = DetachedBuilder {
+                entry_points: self.enabled.addresses(&self.chain_spec),
+            };
+
+            let task_manager = TaskManager::current();
+            RpcTask::new(
+                args,
+                self.pool.clone(),
+                builder,
+                self.providers.clone(),
+                None,
+            )
+            .spawn(task_manager.executor())
+            .await
+            .map_err(|e| eyre::eyre!("Failed to start rundler RPC server: {:#}", e))?;
+
+            Ok(task_manager)
+        })
+    }
+}
+
+/// Pool block channel capacity, as in rundler
+const BLOCK_CHANNEL_CAPACITY: usize = 1024;
+
+/// Starts the in-process mempool and its chain watcher over a concrete provider set
+///
+/// Every launch gets a fresh request channel and task manager, so a pool whose
+/// task panicked can be replaced without restarting the process.
+pub struct ProvidersPoolLauncher<P> {
+    pub chain_spec: Arc<ChainSpec>,
+    pub enabled: EnabledEntryPoints,
+    pub node_http: String,
+    pub providers: P,
+    pub max_verification_gas: u64,
+    /// [pool] max_ops_per_unstaked_sender
+    pub same_sender_mempool_count: usize,
+    /// [pool] throttled_entity_mempool_count
+    pub throttled_entity_mempool_count: u64,
+    pub events: broadcast::Sender<WithEntryPoint<PoolEvent>>,
+}
+
+impl<P> ProvidersPoolLauncher<P> {
+    fn task_args(&self) -> PoolTaskArgs {
+        let chain_spec = (*self.chain_spec).clone();
+        let base = PoolConfig {
+            chain_spec: chain_spec.clone(),
+            entry_point: Address::ZERO,
+            entry_point_version: EntryPointVersion::Unspecified,
+            same_sender_mempool_count: self.same_sender_mempool_count,
+            min_replacement_fee_increase_percentage: 10,
+            max_size_of_pool_bytes: 500_000_000,
+            blocklist: None,
+            allowlist: None,
+            precheck_settings: precheck_settings(&chain_spec, self.max_verification_gas),
+            sim_settings: simulation_settings(),
+            mempool_channel_configs: HashMap::new(),
+            throttled_entity_mempool_count: self.throttled_entity_mempool_count,
+            throttled_entity_live_blocks: 10,
+            paymaster_tracking_enabled: true,
+            paymaster_cache_length: 10_000,
+            reputation_tracking_enabled: true,
+            da_gas_tracking_enabled: false,
+            drop_min_num_blocks: 10,
+            execution_gas_limit_efficiency_reject_threshold: 0.0,
+            verification_gas_limit_efficiency_reject_threshold: 0.0,
+            max_time_in_pool: None,
+            max_expected_storage_slots: usize::MAX,
+        };
+
+        let mut pool_configs = Vec::new();
+        if self.enabled.v0_6 {
+            pool_configs.push(PoolConfig {
+                entry_point: chain_spec.entry_point_address_v0_6,
+                entry_point_version: EntryPointVersion::V0_6,
+                ..base.clone()
+            });
+        }
+        if self.enabled.v0_7 {
+            pool_configs.push(PoolConfig {
+                entry_point: chain_spec.entry_point_address_v0_7,
+                entry_point_version: EntryPointVersion::V0_7,
+                ..base
+            });
+        }
+
+        PoolTaskArgs {
+            chain_spec,
+            unsafe_mode: false,
+            http_url: self.node_http.clone(),
+            chain_poll_interval: Duration::from_millis(100),
+            chain_max_sync_retries: 5,
+            pool_configs,
+            remote_address: None,
+            chain_update_channel_capacity: 1024,
+        }
+    }
+}
+
+#[async_trait]
+impl<P: Providers + 'static> PoolLauncher for ProvidersPoolLauncher<P> {
+    async fn launch(&self) -> std::result::Result<LaunchedPool, String> {
+        let builder = LocalPoolBuilder::new(BLOCK_CHANNEL_CAPACITY);
+        let handle = builder.get_handle();
+
+        let mut task_manager = TaskManager::current();
+        PoolTask::new(
+            self.task_args(),
+            self.events.clone(),
+            builder,
+            self.providers.clone(),
+        )
+        .spawn(task_manager.executor())
+        .await
+        .map_err(|e| format!("{:#}", e))?;
+
+        let exited = Box::pin(async move {
+            let reason = match (&mut task_manager).await {
+                Err(panicked) => panicked.to_string(),
+                Ok(()) => "pool task manager stopped".to_string(),
+            };
+            // 停止旧Pool的链上监听等剩余任务，TaskManager的关闭是阻塞调用
+            let _ = tokio::task::spawn_blocking(move || {
+                task_manager.graceful_shutdown_with_timeout(Duration::from_secs(10))
+            })
+            .await;
+            reason
+        });
+
+        Ok(LaunchedPool { handle, exited })
+    }
+}
+
+/// Builder request channel capacity, as in rundler
+const REQUEST_CHANNEL_CAPACITY: usize = 1024;
+
+/// Starts the in-process bundle builder without exposing the concrete provider types
+pub trait BundleBuilderLauncher: Send + Sync {
+    /// Sign bundles with `private_keys`, one key per enabled EntryPoint, and
+    /// bundle the shared pool's operations; the returned task manager owns the
+    /// builder and signer tasks and stops them on shutdown
+    fn start(
+        &self,
+        private_keys: Vec<SecretString>,
+    ) -> Pin<Box<dyn Future<Output = Result<(LocalBuilderHandle, TaskManager)>> + '_>>;
+}
+
+/// Builder launcher over a concrete provider set, settings as in `rundler node`
+pub struct ProvidersBuilderLauncher<P> {
+    pub chain_spec: Arc<ChainSpec>,
+    pub enabled: EnabledEntryPoints,
+    pub node_http: String,
+    pub pool: SupervisedPool,
+    pub providers: P,
+    pub events: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
+}
+
+impl<P> ProvidersBuilderLauncher<P> {
+    fn task_args(&self, private_keys: Vec<SecretString>) -> BuilderTaskArgs {
+        let chain_spec = (*self.chain_spec).clone();
+        let entry_point = |address, version| EntryPointBuilderSettings {
+            address,
+            version,
+            mempool_configs: HashMap::new(),
+            builders: vec![BuilderSettings {
+                submission_proxy: None,
+                filter_id: None,
+            }],
+        };
+
+        let mut entry_points = Vec::new();
+        if self.enabled.v0_6 {
+            entry_points.push(entry_point(
+                chain_spec.entry_point_address_v0_6,
+                EntryPointVersion::V0_6,
+            ));
+        }
+        if self.enabled.v0_7 {
+            entry_points.push(entry_point(
+                chain_spec.entry_point_address_v0_7,
+                EntryPointVersion::V0_7,
+            ));
+        }
+
+        BuilderTaskArgs {
+            rpc_url: self.node_http.clone(),
+            unsafe_mode: false,
+            signing_scheme: SigningScheme::PrivateKeys { private_keys },
+            auto_fund: true,
+            max_bundle_size: 128,
+            target_bundle_gas: chain_spec.block_gas_limit_mult(TARGET_BUNDLE_BLOCK_GAS_LIMIT_RATIO),
+            max_bundle_gas: chain_spec.block_gas_limit_mult(MAX_BUNDLE_BLOCK_GAS_LIMIT_RATIO),
+            sender_args: TransactionSenderArgs::Raw(RawSenderArgs {
+                submit_url: self.node_http.clone(),
+                use_conditional_rpc: false,
+            }),
+            sim_settings: simulation_settings(),
+            max_blocks_to_wait_for_mine: 2,
+            replacement_fee_percent_increase: 10,
+            max_cancellation_fee_increases: 15,
+            max_replacement_underpriced_blocks: 20,
+            remote_address: None,
+            entry_points,
+            da_gas_tracking_enabled: false,
+            provider_client_timeout_seconds: 30,
+            max_expected_storage_slots: usize::MAX,
+            verification_gas_limit_efficiency_reject_threshold: 0.0,
+            chain_spec,
+        }
+    }
+}
+
+impl<P: Providers + 'static> BundleBuilderLauncher for ProvidersBuilderLauncher<P> {
+    fn start(
+        &self,
+        private_keys: Vec<SecretString>,
+    ) -> Pin<Box<dyn Future<Output = Result<(LocalBuilderHandle, TaskManager)>> + '_>> {
+        Box::pin(async move {
+            let required = self.enabled.addresses(&self.chain_spec).len();
+            if private_keys.len() < required {
+                eyre::bail!(
+                    "Found {} bundler keys (SIGNER_PRIVATE_KEYS), but the builder needs one \
+                     per enabled EntryPoint ({})",
+                    private_keys.len(),
+                    required
+                );
+            }
+            let args = self.task_args(private_keys);
+
+            let task_manager = TaskManager::current();
+            let signer_manager = rundler_signer::new_signer_manager(
+                &args.signing_scheme,
+                args.auto_fund,
+                &args.chain_spec,
+                self.providers.evm().clone(),
+                self.providers.da_gas_oracle().clone(),
+                &task_manager.executor(),
+            )
+            .await
+            .map_err(|e| eyre::eyre!("Failed to load bundler signers: {}", e))?;
+
+            let builder_builder = LocalBuilderBuilder::new(
+                REQUEST_CHANNEL_CAPACITY,
+                signer_manager.clone(),
+                Arc::new(self.pool.clone()),
+            );
+            let handle = builder_builder.get_handle();
+
+            BuilderTask::new(
+                args,
+                self.events.clone(),
+                builder_builder,
+                self.pool.clone(),
+                self.providers.clone(),
+                signer_manager,
+            )
+            .spawn(task_manager.executor())
+            .await
+            .map_err(|e| eyre::eyre!("Failed to start bundle builder: {:#}", e))?;
+
+            Ok((handle, task_manager))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enabled_entry_points_from_configured() {
+        let chain_spec = ChainSpec::default();
+
+        let enabled = EnabledEntryPoints::from_configured(
+            &chain_spec,
+            &[
+                chain_spec.entry_point_address_v0_7,
+                chain_spec.entry_point_address_v0_6,
+            ],
+        )
+        .unwrap();
+        assert!(enabled.v0_6 && enabled.v0_7);
+        // Always reported v0.6 first, regardless of configuration order
+        assert_eq!(
+            enabled.addresses(&chain_spec),
+            vec![
+                chain_spec.entry_point_address_v0_6,
+                chain_spec.entry_point_address_v0_7
+            ]
+        );
+
+        let unknown = Address::repeat_byte(0x11);
+        assert!(EnabledEntryPoints::from_configured(&chain_spec, &[unknown]).is_err());
+        assert!(EnabledEntryPoints::from_configured(&chain_spec, &[]).is_err());
+    }
+
+    #[test]
+    fn test_builder_args_one_builder_per_enabled_entry_point() {
+        let chain_spec = Arc::new(ChainSpec::default());
+        let (events, _) = broadcast::channel(1);
+        let launcher = ProvidersBuilderLauncher {
+            chain_spec: chain_spec.clone(),
+            enabled: EnabledEntryPoints::from_configured(
+                &chain_spec,
+                &[chain_spec.entry_point_address_v0_7],
+            )
+            .unwrap(),
+            node_http: "http://localhost:8545".to_string(),
+            pool: SupervisedPool::from(Arc::new(LocalPoolBuilder::new(1).get_handle())),
+            providers: (),
+            events,
+        };
+
+        let args = launcher.task_args(vec![SecretString::new("0x01".into())]);
+        assert_eq!(args.entry_points.len(), 1);
+        assert_eq!(
+            args.entry_points[0].address,
+            chain_spec.entry_point_address_v0_7
+        );
+        assert_eq!(args.entry_points[0].builders.len(), 1);
+        assert!(matches!(
+            &args.sender_args,
+            TransactionSenderArgs::Raw(raw) if raw.submit_url == "http://localhost:8545"
+        ));
+    }
+}
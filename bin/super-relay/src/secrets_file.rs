@@ -0,0 +1,115 @@
+// super-relay secrets encrypt/decrypt 与 --secrets-file: 私钥加密存放，启动时在内存中解密
+// 口令来自 SUPER_RELAY_SECRETS_PASSPHRASE，未设置时在终端提示输入
+
+use std::{
+    fs,
+    io::{self, BufRead, IsTerminal, Write},
+    path::Path,
+};
+
+use eyre::Result;
+use rundler_paymaster_relay::secrets::{self, KdfParams, Secrets, PASSPHRASE_VAR};
+use secrecy::{ExposeSecret, SecretString};
+use tracing::info;
+
+/// Passphrase from the environment, else prompted for on the terminal
+fn passphrase(confirm: bool) -> Result<SecretString> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_VAR) {
+        return Ok(SecretString::new(passphrase.into()));
+    }
+    if !io::stdin().is_terminal() {
+        return Err(eyre::eyre!(
+            "Secrets passphrase required: set {} or run on a terminal",
+            PASSPHRASE_VAR
+        ));
+    }
+    let passphrase = prompt("🔑 Secrets passphrase: ")?;
+    if confirm && prompt("🔑 Repeat passphrase: ")?.expose_secret() != passphrase.expose_secret()
+    {
+        return Err(eyre::eyre!("Passphrases do not match"));
+    }
+    Ok(passphrase)
+}
+
+/// Read one line from stdin; the input is echoed, prefer the environment
+/// variable where that matters
+fn prompt(message: &str) -> Result<SecretString> {
+    eprint!("{}", message);
+    io::stderr().flush()?;
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    line.truncate(line.trim_end_matches(['\r', '\n']).len());
+    Ok(SecretString::new(line.into()))
+}
+
+/// Decrypt the secrets file at `path` and install it for the process, failing
+/// before any service starts on a wrong passphrase or a corrupted file
+pub fn unlock(path: &str) -> Result<&'static Secrets> {
+    let secrets = Secrets::load(Path::new(path), &passphrase(false)?)
+        .map_err(|e| eyre::eyre!("Failed to unlock {}: {}", path, e))?;
+    info!("🔐 Unlocked {} secret(s) from {}", secrets.len(), path);
+    if !secrets::install(secrets) {
+        return Err(eyre::eyre!("A secrets file is already unlocked"));
+    }
+    Ok(secrets::installed().expect("secrets were just installed"))
+}
+
+/// Encrypt the dotenv file `input` into the secrets file `output`
+pub fn encrypt(input: &str, output: &str) -> Result<()> {
+    let mut entries = Vec::new();
+    for entry in dotenvy::from_path_iter(input)
+        .map_err(|e| eyre::eyre!("Failed to read {}: {}", input, e))?
+    {
+        entries.push(entry.map_err(|e| eyre::eyre!("{}: {}", input, e))?);
+    }
+    let secrets = Secrets::new(entries);
+    if secrets.is_empty() {
+        return Err(eyre::eyre!("{} holds no variables", input));
+    }
+
+    let content = secrets
+        .encrypt(&passphrase(true)?, KdfParams::default())
+        .map_err(|e| eyre::eyre!("Failed to encrypt {}: {}", input, e))?;
+    write_private(output, content.as_bytes())?;
+    println!(
+        "✅ Encrypted {} variable(s) from {} into {}",
+        secrets.len(),
+        input,
+        output
+    );
+    for name in secrets.names() {
+        println!("  • {}", name);
+    }
+    println!(
+        "Start with --secrets-file {} and remove the plaintext {}",
+        output, input
+    );
+    Ok(())
+}
+
+/// Decrypt the secrets file `input` to dotenv lines in `output`, or stdout
+pub fn decrypt(input: &str, output: Option<&str>) -> Result<()> {
+    let secrets = Secrets::load(Path::new(input), &passphrase(false)?)
+        .map_err(|e| eyre::eyre!("Failed to unlock {}: {}", input, e))?;
+    let dotenv = secrets.to_dotenv();
+    match output {
+        Some(output) => {
+            write_private(output, dotenv.expose_secret().as_bytes())?;
+            eprintln!("✅ Decrypted {} variable(s) into {}", secrets.len(), output);
+        }
+        None => print!("{}", dotenv.expose_secret()),
+    }
+    Ok(())
+}
+
+/// Write `content` to `path`, readable by the owner only
+fn write_private(path: &str, content: &[u8]) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content))
+        .map_err(|e| eyre::eyre!("Failed to write {}: {}", path, e))
+}
@@ -0,0 +1,604 @@
+// super-relay status: 从服务使用的同一配置文件发现端口，并发探测 /health 与 /metrics
+// 整体有截止时间，任一关键服务不可用时以非零退出码结束
+
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{config_system::ConfigurationManager, Cli, SuperRelayConfig};
+
+/// State of one probed service
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceState {
+    Up,
+    Degraded,
+    Down,
+}
+
+/// What a probed endpoint serves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeKind {
+    /// Health JSON, with overall and per-component status
+    Health,
+    /// Prometheus text exposition
+    Metrics,
+}
+
+/// Endpoint probed by `status`
+#[derive(Debug, Clone)]
+pub struct ServiceTarget {
+    pub name: String,
+    pub url: String,
+    /// A critical service being down makes `status` exit with a non-zero code
+    pub critical: bool,
+    pub kind: ProbeKind,
+}
+
+impl ServiceTarget {
+    fn new(name: &str, url: String, critical: bool, kind: ProbeKind) -> Self {
+        Self {
+            name: name.to_string(),
+            url,
+            critical,
+            kind,
+        }
+    }
+}
+
+/// Component of a service, as reported by its /health
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentState {
+    pub name: String,
+    pub status: String,
+    pub critical: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of probing one service
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceStatus {
+    pub name: String,
+    pub url: String,
+    pub status: ServiceState,
+    pub critical: bool,
+    /// Time until the response was read, unset when nothing answered
+    pub latency_ms: Option<u64>,
+    pub version: Option<String>,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub components: Vec<ComponentState>,
+}
+
+impl ServiceStatus {
+    fn new(target: &ServiceTarget, status: ServiceState, detail: String) -> Self {
+        Self {
+            name: target.name.clone(),
+            url: target.url.clone(),
+            status,
+            critical: target.critical,
+            latency_ms: None,
+            version: None,
+            detail,
+            components: Vec::new(),
+        }
+    }
+}
+
+/// All services of one status run, in discovery order
+#[derive(Debug, Default)]
+pub struct StatusReport {
+    pub services: Vec<ServiceStatus>,
+}
+
+impl StatusReport {
+    fn count(&self, status: ServiceState) -> usize {
+        self.services.iter().filter(|s| s.status == status).count()
+    }
+
+    /// Whether any critical service is down
+    pub fn has_critical_failure(&self) -> bool {
+        self.services
+            .iter()
+            .any(|s| s.critical && s.status == ServiceState::Down)
+    }
+
+    /// Report for `--json`
+    pub fn to_json(&self) -> Value {
+        json!({
+            "ok": !self.has_critical_failure(),
+            "up": self.count(ServiceState::Up),
+            "degraded": self.count(ServiceState::Degraded),
+            "down": self.count(ServiceState::Down),
+            "services": self.services,
+        })
+    }
+
+    /// Report as a STATUS / SERVICE / LATENCY / DETAIL table, components of
+    /// each service indented below it, with a summary line
+    pub fn render_table(&self) -> String {
+        let width = self
+            .services
+            .iter()
+            .map(|s| s.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("SERVICE".len());
+
+        let mut table = format!(
+            "{:<8}  {:<width$}  {:<7}  DETAIL\n",
+            "STATUS", "SERVICE", "LATENCY"
+        );
+        for service in &self.services {
+            let status = match service.status {
+                ServiceState::Up => "UP",
+                ServiceState::Degraded => "DEGRADED",
+                ServiceState::Down if service.critical => "DOWN",
+                ServiceState::Down => "down",
+            };
+            let latency = service
+                .latency_ms
+                .map_or("-".to_string(), |ms| format!("{}ms", ms));
+            table.push_str(&format!(
+                "{:<8}  {:<width$}  {:<7}  {} ({})\n",
+                status, service.name, latency, service.detail, service.url
+            ));
+            for component in &service.components {
+                table.push_str(&format!(
+                    "{:<8}  {:<width$}    {}: {}{}\n",
+                    "",
+                    "",
+                    component.name,
+                    component.status,
+                    component
+                        .error
+                        .as_ref()
+                        .map_or(String::new(), |e| format!(" ({})", e))
+                ));
+            }
+        }
+        table.push_str(&format!(
+            "\n{} up, {} degraded, {} down{}\n",
+            self.count(ServiceState::Up),
+            self.count(ServiceState::Degraded),
+            self.count(ServiceState::Down),
+            if self.has_critical_failure() {
+                " (critical)"
+            } else {
+                ""
+            }
+        ));
+        table
+    }
+}
+
+/// Status settings from the command line
+#[derive(Debug, Clone)]
+pub struct StatusOptions {
+    /// Host the services are reached at; wildcard listen addresses map to it
+    pub host: String,
+    /// Time allowed for each probe
+    pub timeout: Duration,
+    /// Time allowed for the whole run
+    pub deadline: Duration,
+}
+
+/// Services run by `config`, reached at `host`
+pub fn targets(config: &SuperRelayConfig, host: &str) -> Vec<ServiceTarget> {
+    let gateway = format!("http://{}:{}", host, config.dual_service.gateway_port);
+    let mut targets = vec![ServiceTarget::new(
+        "gateway",
+        format!("{}/health", gateway),
+        true,
+        ProbeKind::Health,
+    )];
+
+    let metrics = &config.gateway.metrics;
+    if metrics.enabled && metrics.serve_on_gateway {
+        targets.push(ServiceTarget::new(
+            "gateway_metrics",
+            format!("{}/metrics", gateway),
+            false,
+            ProbeKind::Metrics,
+        ));
+    }
+    if let Some(address) = metrics
+        .listen_address
+        .as_deref()
+        .filter(|_| metrics.enabled)
+    {
+        targets.push(ServiceTarget::new(
+            "metrics",
+            format!("http://{}/metrics", reachable_address(address, host)),
+            false,
+            ProbeKind::Metrics,
+        ));
+    }
+
+    if config.dual_service.enable_rundler_rpc {
+        targets.push(ServiceTarget::new(
+            "rundler_rpc",
+            format!(
+                "http://{}:{}/health",
+                host, config.dual_service.rundler_port
+            ),
+            true,
+            ProbeKind::Health,
+        ));
+    }
+
+    let admin = &config.gateway.admin;
+    if admin.enabled {
+        let address = format!("{}:{}", admin.host, admin.port);
+        targets.push(ServiceTarget::new(
+            "admin",
+            format!("http://{}/health", reachable_address(&address, host)),
+            false,
+            ProbeKind::Health,
+        ));
+    }
+    targets
+}
+
+/// `address` with a wildcard host replaced by `host`
+fn reachable_address(address: &str, host: &str) -> String {
+    match address.rsplit_once(':') {
+        Some(("0.0.0.0" | "[::]" | "::" | "", port)) => format!("{}:{}", host, port),
+        _ => address.to_string(),
+    }
+}
+
+/// Probe the services of the configuration at `config_path`
+pub async fn run(cli: &Cli, config_path: &str, options: &StatusOptions) -> StatusReport {
+    match ConfigurationManager::new(config_path).resolve(&cli.overrides()) {
+        Ok(config) => probe_all(targets(&config, &options.host), options).await,
+        Err(e) => {
+            let target =
+                ServiceTarget::new("config", config_path.to_string(), true, ProbeKind::Health);
+            StatusReport {
+                services: vec![ServiceStatus::new(
+                    &target,
+                    ServiceState::Down,
+                    e.to_string(),
+                )],
+            }
+        }
+    }
+}
+
+/// Probe `targets` concurrently; services not answered by the deadline are down
+pub async fn probe_all(targets: Vec<ServiceTarget>, options: &StatusOptions) -> StatusReport {
+    let client = reqwest::Client::new();
+    let deadline = tokio::time::Instant::now() + options.deadline;
+
+    let probes: Vec<_> = targets
+        .into_iter()
+        .map(|target| {
+            let client = client.clone();
+            let timeout = options.timeout;
+            let handle = tokio::spawn({
+                let target = target.clone();
+                async move {
+                    tokio::time::timeout_at(deadline, probe(&client, &target, timeout))
+                        .await
+                        .unwrap_or_else(|_| {
+                            ServiceStatus::new(
+                                &target,
+                                ServiceState::Down,
+                                "no answer before the status deadline".to_string(),
+                            )
+                        })
+                }
+            });
+            (target, handle)
+        })
+        .collect();
+
+    let mut report = StatusReport::default();
+    for (target, handle) in probes {
+        report.services.push(handle.await.unwrap_or_else(|e| {
+            ServiceStatus::new(&target, ServiceState::Down, format!("probe failed: {}", e))
+        }));
+    }
+    report
+}
+
+async fn probe(
+    client: &reqwest::Client,
+    target: &ServiceTarget,
+    timeout: Duration,
+) -> ServiceStatus {
+    let started = Instant::now();
+    let response = match client.get(&target.url).timeout(timeout).send().await {
+        Ok(response) => response,
+        Err(e) => return ServiceStatus::new(target, ServiceState::Down, e.to_string()),
+    };
+    let code = response.status();
+    let body = response.text().await;
+    let latency_ms = Some(started.elapsed().as_millis() as u64);
+
+    let mut status = match body {
+        Err(e) => ServiceStatus::new(target, ServiceState::Down, e.to_string()),
+        Ok(_) if !code.is_success() => {
+            ServiceStatus::new(target, ServiceState::Down, format!("HTTP {}", code))
+        }
+        Ok(body) => match target.kind {
+            ProbeKind::Health => health_status(target, &body),
+            ProbeKind::Metrics => metrics_status(target, &body),
+        },
+    };
+    status.latency_ms = latency_ms;
+    status
+}
+
+/// Status from a /health body
+///
+/// The gateway answers with its overall status, version and components;
+/// other services answering 200 with anything else count as up.
+fn health_status(target: &ServiceTarget, body: &str) -> ServiceStatus {
+    let health: Value = serde_json::from_str(body).unwrap_or(Value::Null);
+    let Some(overall) = health.get("status").and_then(Value::as_str) else {
+        return ServiceStatus::new(target, ServiceState::Up, "HTTP 200".to_string());
+    };
+
+    let components: Vec<ComponentState> = health
+        .get("components")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|component| ComponentState {
+            name: component["name"].as_str().unwrap_or_default().to_string(),
+            status: component["status"]
+                .as_str()
+                .unwrap_or("unknown")
+                .to_string(),
+            critical: component["critical"].as_bool().unwrap_or(false),
+            error: component["error"].as_str().map(str::to_string),
+        })
+        .collect();
+    let state = match overall {
+        "unhealthy" => ServiceState::Down,
+        "degraded" => ServiceState::Degraded,
+        _ => ServiceState::Up,
+    };
+    let failing: Vec<&str> = components
+        .iter()
+        .filter(|c| c.status != "healthy")
+        .map(|c| c.name.as_str())
+        .collect();
+    let detail = if failing.is_empty() {
+        format!("{}, {} components", overall, components.len())
+    } else {
+        format!("{}, failing: {}", overall, failing.join(", "))
+    };
+
+    let mut status = ServiceStatus::new(target, state, detail);
+    status.version = health["version"].as_str().map(str::to_string);
+    status.components = components;
+    status
+}
+
+/// Status from a /metrics body: up once any sample is exposed
+fn metrics_status(target: &ServiceTarget, body: &str) -> ServiceStatus {
+    let samples = body
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .count();
+    if samples == 0 {
+        ServiceStatus::new(
+            target,
+            ServiceState::Degraded,
+            "no samples exposed".to_string(),
+        )
+    } else {
+        ServiceStatus::new(target, ServiceState::Up, format!("{} samples", samples))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::get, Router};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Serve `router` on a free local port and return its address
+    async fn serve(router: Router) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        addr.to_string()
+    }
+
+    fn stub(health: Value) -> Router {
+        Router::new()
+            .route(
+                "/health",
+                get(move || {
+                    let health = health.clone();
+                    async move { axum::Json(health) }
+                }),
+            )
+            .route(
+                "/metrics",
+                get(|| async { "# TYPE requests counter\nrequests 3\n" }),
+            )
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    "late"
+                }),
+            )
+    }
+
+    fn options() -> StatusOptions {
+        StatusOptions {
+            host: "127.0.0.1".to_string(),
+            timeout: Duration::from_secs(2),
+            deadline: Duration::from_secs(5),
+        }
+    }
+
+    /// Address nothing listens on
+    async fn closed_address() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_targets_follow_config() {
+        let config: SuperRelayConfig = toml::from_str(
+            r#"
+[node]
+[pool]
+[rpc]
+[paymaster_relay]
+[mempool]
+
+[dual_service]
+gateway_port = 4000
+rundler_port = 4001
+
+[gateway.metrics]
+listen_address = "0.0.0.0:9100"
+
+[gateway.admin]
+enabled = true
+host = "10.0.0.5"
+port = 4002
+"#,
+        )
+        .unwrap();
+
+        let targets = targets(&config, "127.0.0.1");
+        let urls: Vec<(&str, &str, bool)> = targets
+            .iter()
+            .map(|t| (t.name.as_str(), t.url.as_str(), t.critical))
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                ("gateway", "http://127.0.0.1:4000/health", true),
+                ("gateway_metrics", "http://127.0.0.1:4000/metrics", false),
+                ("metrics", "http://127.0.0.1:9100/metrics", false),
+                ("rundler_rpc", "http://127.0.0.1:4001/health", true),
+                ("admin", "http://10.0.0.5:4002/health", false),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reports_components_and_critical_failures() {
+        let addr = serve(stub(json!({
+            "status": "degraded",
+            "version": "0.1.5",
+            "components": [
+                { "name": "node", "critical": true, "status": "healthy" },
+                { "name": "paymaster_deposit", "critical": false, "status": "warning",
+                  "error": "deposit below minimum" },
+            ],
+        })))
+        .await;
+        let gateway = ServiceTarget::new(
+            "gateway",
+            format!("http://{}/health", addr),
+            true,
+            ProbeKind::Health,
+        );
+        let metrics = ServiceTarget::new(
+            "gateway_metrics",
+            format!("http://{}/metrics", addr),
+            false,
+            ProbeKind::Metrics,
+        );
+
+        let report = probe_all(vec![gateway.clone(), metrics.clone()], &options()).await;
+        assert!(!report.has_critical_failure());
+        let json = report.to_json();
+        assert_eq!(json["ok"], true);
+        assert_eq!(json["services"][0]["status"], "degraded");
+        assert_eq!(json["services"][0]["version"], "0.1.5");
+        assert_eq!(
+            json["services"][0]["components"][1]["error"],
+            "deposit below minimum"
+        );
+        assert!(json["services"][0]["latency_ms"].is_u64());
+        assert_eq!(json["services"][1]["status"], "up");
+        assert_eq!(json["services"][1]["detail"], "1 samples");
+
+        let table = report.render_table();
+        assert!(table.starts_with("STATUS"));
+        assert!(table.contains("DEGRADED  gateway"));
+        assert!(table.contains("paymaster_deposit: warning (deposit below minimum)"));
+        assert!(table.contains("1 up, 1 degraded, 0 down\n"));
+
+        // A critical service that does not answer fails the run
+        let rundler = ServiceTarget::new(
+            "rundler_rpc",
+            format!("http://{}/health", closed_address().await),
+            true,
+            ProbeKind::Health,
+        );
+        let report = probe_all(vec![gateway, metrics, rundler], &options()).await;
+        assert!(report.has_critical_failure());
+        assert_eq!(report.to_json()["ok"], false);
+        assert_eq!(report.services[2].status, ServiceState::Down);
+        assert!(report
+            .render_table()
+            .contains("1 up, 1 degraded, 1 down (critical)"));
+    }
+
+    #[tokio::test]
+    async fn test_unhealthy_gateway_is_down() {
+        let addr = serve(stub(json!({
+            "status": "unhealthy",
+            "components": [{ "name": "node", "critical": true, "status": "error" }],
+        })))
+        .await;
+        let target = ServiceTarget::new(
+            "gateway",
+            format!("http://{}/health", addr),
+            true,
+            ProbeKind::Health,
+        );
+
+        let report = probe_all(vec![target], &options()).await;
+        assert!(report.has_critical_failure());
+        assert_eq!(report.services[0].detail, "unhealthy, failing: node");
+    }
+
+    #[tokio::test]
+    async fn test_deadline_bounds_the_run() {
+        let addr = serve(stub(json!({ "status": "healthy" }))).await;
+        let slow = ServiceTarget::new(
+            "gateway",
+            format!("http://{}/slow", addr),
+            true,
+            ProbeKind::Health,
+        );
+        let options = StatusOptions {
+            deadline: Duration::from_millis(200),
+            ..options()
+        };
+
+        let started = Instant::now();
+        let report = probe_all(vec![slow], &options).await;
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(report.services[0].status, ServiceState::Down);
+        assert!(report.services[0].detail.contains("deadline"));
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_config_is_critical() {
+        use clap::Parser;
+
+        let cli = Cli::parse_from(["super-relay", "version"]);
+        let report = run(&cli, "/nonexistent/config.toml", &options()).await;
+        assert_eq!(report.services.len(), 1);
+        assert_eq!(report.services[0].name, "config");
+        assert!(report.has_critical_failure());
+    }
+}
@@ -0,0 +1,252 @@
+//! Integration test for dual-service mode (Gateway on 3000, rundler RPC on 3001).
+//! The bundle builder signs with anvil's funded accounts.
+use std::{
+    net::TcpStream,
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use ethers::utils::{hex, Anvil, AnvilInstance};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+const GATEWAY_PORT: u16 = 3000;
+const RUNDLER_PORT: u16 = 3001;
+const ENTRY_POINT_V0_6: &str = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
+const ENTRY_POINT_V0_7: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+
+struct DualServiceContext {
+    anvil: AnvilInstance,
+    _config_dir: TempDir,
+    super_relay: Child,
+}
+
+impl Drop for DualServiceContext {
+    fn drop(&mut self) {
+        let _ = self.super_relay.kill();
+        let _ = self.super_relay.wait();
+    }
+}
+
+fn wait_for_port(port: u16, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if TcpStream::connect(("127.0.0.1", port)).is_ok() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    }
+    false
+}
+
+fn start_dual_service(entry_points: &[&str]) -> DualServiceContext {
+    let anvil = Anvil::new().spawn();
+    // One bundler key per EntryPoint, skipping the account anvil funds first
+    let bundler_keys: Vec<String> = anvil.keys()[1..=entry_points.len()]
+        .iter()
+        .map(|key| format!("0x{}", hex::encode(key.to_bytes())))
+        .collect();
+
+    let config_dir = tempfile::tempdir().unwrap();
+    let config_path = config_dir.path().join("config.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            r#"
+[node]
+[pool]
+[rpc]
+[paymaster_relay]
+[mempool]
+
+[dual_service]
+rundler_port = {RUNDLER_PORT}
+
+[gateway]
+chain_id = 31337
+entry_points = {entry_points:?}
+"#
+        ),
+    )
+    .unwrap();
+
+    let super_relay = Command::new(env!("CARGO_BIN_EXE_super-relay"))
+        .arg("dual-service")
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--gateway-port")
+        .arg(GATEWAY_PORT.to_string())
+        .env("NODE_HTTP", anvil.endpoint())
+        .env("NETWORK", "dev")
+        .env("SIGNER_PRIVATE_KEYS", bundler_keys.join(","))
+        .stdout(Stdio::null())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .expect("failed to start super-relay");
+
+    let context = DualServiceContext {
+        anvil,
+        _config_dir: config_dir,
+        super_relay,
+    };
+
+    assert!(
+        wait_for_port(GATEWAY_PORT, Duration::from_secs(30)),
+        "gateway did not start"
+    );
+    assert!(
+        wait_for_port(RUNDLER_PORT, Duration::from_secs(30)),
+        "rundler RPC did not start"
+    );
+
+    context
+}
+
+async fn call(client: &reqwest::Client, port: u16, method: &str) -> Value {
+    call_with(client, port, method, json!([])).await
+}
+
+async fn call_with(client: &reqwest::Client, port: u16, method: &str, params: Value) -> Value {
+    client
+        .post(format!("http://127.0.0.1:{}", port))
+        .json(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+async fn supported_entry_points(client: &reqwest::Client, port: u16) -> Value {
+    let response = call(client, port, "eth_supportedEntryPoints").await;
+
+    assert!(
+        response.get("error").is_none(),
+        "port {} returned error: {}",
+        port,
+        response
+    );
+    response["result"].clone()
+}
+
+#[tokio::test]
+#[ignore] // requires anvil
+async fn test_supported_entry_points_match_on_both_ports() {
+    let _context = start_dual_service(&[ENTRY_POINT_V0_6, ENTRY_POINT_V0_7]);
+    let client = reqwest::Client::new();
+
+    let gateway = supported_entry_points(&client, GATEWAY_PORT).await;
+    let rundler = supported_entry_points(&client, RUNDLER_PORT).await;
+
+    assert_eq!(gateway, json!([ENTRY_POINT_V0_6, ENTRY_POINT_V0_7]));
+    assert_eq!(gateway, rundler);
+}
+
+#[tokio::test]
+#[ignore] // requires anvil
+async fn test_send_bundle_now_reaches_builder() {
+    // rundler bundles on request only with a single builder
+    let _context = start_dual_service(&[ENTRY_POINT_V0_7]);
+    let client = reqwest::Client::new();
+
+    let health: Value = client
+        .get(format!("http://127.0.0.1:{}/health", GATEWAY_PORT))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let builder = health["components"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|component| component["name"] == "builder")
+        .unwrap_or_else(|| panic!("no builder in {}", health));
+    assert_eq!(builder["status"], "healthy");
+    assert_eq!(builder["details"]["available"], true);
+
+    // The builder answers; with an empty mempool there is nothing to bundle
+    let response = call(&client, GATEWAY_PORT, "rundler_sendBundleNow").await;
+    let message = response["error"]["message"].as_str().unwrap_or_default();
+    assert!(message.contains("no ops to send"), "{}", response);
+}
+
+/// Unsigned v0.6 operation with zero fees, so no deposit is needed
+fn v0_6_op(sender: &str, call_data: &str) -> Value {
+    json!({
+        "sender": sender,
+        "nonce": "0x0",
+        "initCode": "0x",
+        "callData": call_data,
+        "callGasLimit": "0x186a0",
+        "verificationGasLimit": "0x186a0",
+        "preVerificationGas": "0xc350",
+        "maxFeePerGas": "0x0",
+        "maxPriorityFeePerGas": "0x0",
+        "paymasterAndData": "0x",
+        "signature": "0x"
+    })
+}
+
+#[tokio::test]
+#[ignore] // requires anvil
+async fn test_simulate_bundle_reports_each_op() {
+    let context = start_dual_service(&[ENTRY_POINT_V0_6]);
+    let client = reqwest::Client::new();
+    let anvil_port = context.anvil.port();
+
+    // Accounts return 0 (valid) from any call; the second reverts anything
+    // but validateUserOp
+    let passing = "0x1111111111111111111111111111111111111111";
+    let reverting = "0x2222222222222222222222222222222222222222";
+    for (address, code) in [
+        (
+            ENTRY_POINT_V0_6.to_string(),
+            rundler_contracts::v0_6::ENTRY_POINT_V0_6_DEPLOYED_BYTECODE.to_string(),
+        ),
+        (passing.to_string(), "0x60206000f3".to_string()),
+        (
+            reverting.to_string(),
+            "0x60003560e01c633a871cdd1460145760206000fd5b60206000f3".to_string(),
+        ),
+    ] {
+        let response =
+            call_with(&client, anvil_port, "anvil_setCode", json!([address, code])).await;
+        assert!(response.get("error").is_none(), "{}", response);
+    }
+
+    let unknown = format!("0x{}", "33".repeat(32));
+    let response = call_with(
+        &client,
+        GATEWAY_PORT,
+        "superRelay_simulateBundle",
+        json!([
+            ENTRY_POINT_V0_6,
+            [
+                v0_6_op(passing, "0x"),
+                v0_6_op(reverting, "0xdeadbeef"),
+                unknown
+            ]
+        ]),
+    )
+    .await;
+
+    let ops = response["result"]["ops"]
+        .as_array()
+        .unwrap_or_else(|| panic!("{}", response));
+    let statuses: Vec<&str> = ops
+        .iter()
+        .map(|op| op["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(statuses, ["success", "reverted", "skipped"], "{}", response);
+    assert_eq!(ops[2]["userOpHash"], json!(unknown));
+    assert_eq!(ops[2]["reason"], "not in the pool");
+    assert!(response["result"]["gasUsed"].is_string(), "{}", response);
+}
@@ -564,7 +564,26 @@ where
             )
             .await;
         let result = match result {
-            Ok(success) => (op, Ok(success)),
+            Ok(mut success) => {
+                // Storage required by the submitter joins the storage validation read,
+                // so both are checked by the bundle's conditional transaction
+                if let Some(required) = &op.op.perms.expected_storage {
+                    let mut merged = BundleExpectedStorage::default();
+                    if let Err(e) = merged
+                        .add(&success.expected_storage)
+                        .and_then(|()| merged.add(required))
+                    {
+                        self.emit(BuilderEvent::skipped_op(
+                            self.builder_tag.clone(),
+                            op_hash,
+                            SkipReason::ExpectedStorageConflict(e.to_string()),
+                        ));
+                        return None;
+                    }
+                    success.expected_storage = merged.inner;
+                }
+                (op, Ok(success))
+            }
             Err(error) => match error {
                 SimulationError {
                     violation_error: ViolationError::Violations(_),
@@ -3231,6 +3250,95 @@ mod tests {
         assert_eq!(bundle.rejected_ops, vec![op]);
     }
 
+    #[tokio::test]
+    async fn test_submitter_expected_storage() {
+        let op = default_op();
+
+        let mut simulated_storage = ExpectedStorage::default();
+        simulated_storage.insert(address(1), U256::ZERO, U256::ZERO);
+        let mut required_storage = ExpectedStorage::default();
+        required_storage.insert(address(2), U256::from(1), U256::from(7));
+        let mut bundle_storage = simulated_storage.clone();
+        bundle_storage.insert(address(2), U256::from(1), U256::from(7));
+
+        let bundle = mock_make_bundle(
+            vec![MockOp {
+                op: op.clone(),
+                simulation_result: Box::new(move || {
+                    Ok(SimulationResult {
+                        expected_storage: simulated_storage.clone(),
+                        ..Default::default()
+                    })
+                }),
+                perms: UserOperationPermissions {
+                    expected_storage: Some(required_storage),
+                    ..Default::default()
+                },
+            }],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            vec![],
+            None,
+            U256::MAX,
+        )
+        .await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op],
+                ..Default::default()
+            }]
+        );
+        assert_eq!(bundle.expected_storage, bundle_storage);
+    }
+
+    #[tokio::test]
+    async fn test_submitter_expected_storage_conflict() {
+        let op = default_op();
+
+        let mut simulated_storage = ExpectedStorage::default();
+        simulated_storage.insert(address(1), U256::ZERO, U256::ZERO);
+        let mut required_storage = ExpectedStorage::default();
+        required_storage.insert(address(1), U256::ZERO, U256::from(1));
+
+        let bundle = mock_make_bundle(
+            vec![MockOp {
+                op,
+                simulation_result: Box::new(move || {
+                    Ok(SimulationResult {
+                        expected_storage: simulated_storage.clone(),
+                        ..Default::default()
+                    })
+                }),
+                perms: UserOperationPermissions {
+                    expected_storage: Some(required_storage),
+                    ..Default::default()
+                },
+            }],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            vec![],
+            None,
+            U256::MAX,
+        )
+        .await;
+
+        assert!(bundle.is_empty())
+    }
+
     #[tokio::test]
     async fn test_single_uo_max_expected_storage_slots() {
         let op = default_op();
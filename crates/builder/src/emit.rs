@@ -154,21 +154,35 @@ pub struct BundleTxDetails {
 #[derive(Clone, Debug)]
 pub enum SkipReason {
     /// Operation accessed another sender account included earlier in the bundle
-    AccessedOtherSender { other_sender: Address },
+    AccessedOtherSender {
+        /// Sender included earlier in the bundle
+        other_sender: Address,
+    },
     /// Operation did not bid high enough gas fees for inclusion in the bundle
     InsufficientFees {
+        /// Fees required for inclusion
         required_fees: GasFees,
+        /// Fees bid by the operation
         actual_fees: GasFees,
     },
     /// Insufficient pre-verification gas for the operation at the given base fee
     InsufficientPreVerificationGas {
+        /// Base fee the pre-verification gas was checked at
         base_fee: u128,
+        /// Fees bid by the operation
         op_fees: GasFees,
+        /// Pre-verification gas required
         required_pvg: u128,
+        /// Pre-verification gas of the operation
         actual_pvg: u128,
     },
     /// Cost of this operation is greater than the max cost of the bundler sponsorship
-    OverSponsorshipMaxCost { max_cost: U256, actual_cost: U256 },
+    OverSponsorshipMaxCost {
+        /// Max cost of the bundler sponsorship
+        max_cost: U256,
+        /// Cost of the operation
+        actual_cost: U256,
+    },
     /// Bundle ran out of space by simulation gas limit to include the operation
     SimulationGasLimit,
     /// Bundle ran out of space by target gas limit to include the operation
@@ -186,7 +200,10 @@ pub enum SkipReason {
     /// UO uses an unsupported aggregator
     UnsupportedAggregator(Address),
     /// Other reason, typically internal errors
-    Other { reason: Arc<String> },
+    Other {
+        /// Description of the reason
+        reason: Arc<String>,
+    },
 }
 
 /// Reason for rejecting an operation from a bundle
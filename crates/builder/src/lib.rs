@@ -24,7 +24,7 @@ mod bundle_proposer;
 mod bundle_sender;
 
 mod emit;
-pub use emit::{BuilderEvent, BuilderEventKind};
+pub use emit::{BuilderEvent, BuilderEventKind, BundleTxDetails, SkipReason};
 
 mod sender;
 pub use sender::{
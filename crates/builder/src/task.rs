@@ -228,6 +228,10 @@ where
                     bundle_sender_actions.extend(actions);
                     supported_entry_points.insert(self.args.chain_spec.entry_point_address_v0_7);
                 }
+                #[cfg(feature = "entrypoint-v0_8")]
+                EntryPointVersion::V0_8 => {
+                    return Err(anyhow::anyhow!("Entry point v0.8 has no bundle builder"));
+                }
                 EntryPointVersion::Unspecified => {
                     panic!("Unspecified entry point version")
                 }
@@ -0,0 +1,326 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use alloy_primitives::{Address, B256, U64};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+
+use crate::{
+    error::{ClientError, ClientResult, RpcErrorCode},
+    types::{
+        GasEstimate, GasPriceQuote, OpInclusionStatus, SponsorOptions, SponsorshipResult,
+        UserOperation, UserOperationByHash, UserOperationReceipt,
+    },
+};
+
+/// Header carrying the API key of a request
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Retries of idempotent calls
+///
+/// Reads, and sponsorships with an idempotency key, are retried on timeouts,
+/// transport errors, 429/502/503/504 answers and the gateway's limit exceeded
+/// error. eth_sendUserOperation is never retried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryConfig {
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before the first retry, growing linearly with each retry
+    pub backoff: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            backoff: Duration::from_millis(200),
+        }
+    }
+}
+
+/// Typed async client of the SuperRelay gateway JSON-RPC API
+#[derive(Clone)]
+pub struct SuperRelayClient {
+    http: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+    timeout: Option<Duration>,
+    retries: Option<RetryConfig>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl std::fmt::Debug for SuperRelayClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SuperRelayClient")
+            .field("url", &self.url)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("timeout", &self.timeout)
+            .field("retries", &self.retries)
+            .finish()
+    }
+}
+
+impl SuperRelayClient {
+    /// Client of the gateway JSON-RPC endpoint at `url`
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            url: url.into(),
+            api_key: None,
+            timeout: None,
+            retries: None,
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// Authenticate every request with `api_key`
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Fail each request, and each retry, after `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Retry idempotent calls as configured by `retries`
+    pub fn with_retries(mut self, retries: RetryConfig) -> Self {
+        self.retries = Some(retries);
+        self
+    }
+
+    /// Send requests through `http`, e.g. one with custom TLS settings
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// eth_chainId
+    pub async fn chain_id(&self) -> ClientResult<u64> {
+        let chain_id: U64 = self.request("eth_chainId", json!([]), true).await?;
+        Ok(chain_id.to())
+    }
+
+    /// eth_supportedEntryPoints
+    pub async fn supported_entry_points(&self) -> ClientResult<Vec<Address>> {
+        self.request("eth_supportedEntryPoints", json!([]), true)
+            .await
+    }
+
+    /// pm_sponsorUserOperation
+    ///
+    /// Retried like a read only when `options` carries an idempotency key.
+    pub async fn sponsor_user_operation(
+        &self,
+        user_operation: &UserOperation,
+        entry_point: Address,
+        options: &SponsorOptions,
+    ) -> ClientResult<SponsorshipResult> {
+        self.request(
+            "pm_sponsorUserOperation",
+            json!([user_operation, entry_point, options]),
+            options.idempotency_key.is_some(),
+        )
+        .await
+    }
+
+    /// eth_sendUserOperation, returning the operation's hash
+    pub async fn send_user_operation(
+        &self,
+        user_operation: &UserOperation,
+        entry_point: Address,
+    ) -> ClientResult<B256> {
+        self.request(
+            "eth_sendUserOperation",
+            json!([user_operation, entry_point]),
+            false,
+        )
+        .await
+    }
+
+    /// eth_estimateUserOperationGas
+    pub async fn estimate_user_operation_gas(
+        &self,
+        user_operation: &UserOperation,
+        entry_point: Address,
+    ) -> ClientResult<GasEstimate> {
+        self.request(
+            "eth_estimateUserOperationGas",
+            json!([user_operation, entry_point]),
+            true,
+        )
+        .await
+    }
+
+    /// eth_getUserOperationByHash, `None` for unknown operations
+    pub async fn get_user_operation_by_hash(
+        &self,
+        user_op_hash: B256,
+    ) -> ClientResult<Option<UserOperationByHash>> {
+        self.request("eth_getUserOperationByHash", json!([user_op_hash]), true)
+            .await
+    }
+
+    /// eth_getUserOperationReceipt, `None` until the operation is mined
+    pub async fn get_user_operation_receipt(
+        &self,
+        user_op_hash: B256,
+    ) -> ClientResult<Option<UserOperationReceipt>> {
+        self.request("eth_getUserOperationReceipt", json!([user_op_hash]), true)
+            .await
+    }
+
+    /// superRelay_getUserOperationGasPrice
+    pub async fn get_user_operation_gas_price(&self) -> ClientResult<GasPriceQuote> {
+        self.request("superRelay_getUserOperationGasPrice", json!([]), true)
+            .await
+    }
+
+    /// superRelay_getOpInclusionStatus, `None` for operations no builder saw
+    pub async fn get_op_inclusion_status(
+        &self,
+        user_op_hash: B256,
+    ) -> ClientResult<Option<OpInclusionStatus>> {
+        self.request(
+            "superRelay_getOpInclusionStatus",
+            json!([user_op_hash]),
+            true,
+        )
+        .await
+    }
+
+    /// superRelay_getVerificationProof, the stored dual-signature proof
+    pub async fn get_verification_proof(&self, user_op_hash: B256) -> ClientResult<Option<Value>> {
+        self.request(
+            "superRelay_getVerificationProof",
+            json!([user_op_hash]),
+            true,
+        )
+        .await
+    }
+
+    /// superRelay_getPipelineStats, per-module counters of the sponsorship checks
+    pub async fn get_pipeline_stats(&self) -> ClientResult<Value> {
+        self.request("superRelay_getPipelineStats", json!([]), true)
+            .await
+    }
+
+    /// superRelay_getErrorCatalog, the key, code and description of every error
+    pub async fn get_error_catalog(&self) -> ClientResult<Value> {
+        self.request("superRelay_getErrorCatalog", json!([]), true)
+            .await
+    }
+
+    /// superRelay_poolSummary
+    pub async fn pool_summary(&self) -> ClientResult<Value> {
+        self.request("superRelay_poolSummary", json!([]), true)
+            .await
+    }
+
+    /// Call `method` with `params` and read its result as `T`
+    ///
+    /// `idempotent` calls are retried when retries are configured.
+    pub async fn request<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+        idempotent: bool,
+    ) -> ClientResult<T> {
+        let max_retries = match &self.retries {
+            Some(retries) if idempotent => retries.max_retries,
+            _ => 0,
+        };
+        let mut attempt = 0;
+        let result = loop {
+            match self.send(method, &params).await {
+                Err(e) if e.is_retryable() && attempt < max_retries => {
+                    attempt += 1;
+                    let backoff = self
+                        .retries
+                        .as_ref()
+                        .map_or(Duration::ZERO, |retries| retries.backoff * attempt);
+                    warn!(
+                        "{} failed, retry {}/{} in {:?}: {}",
+                        method, attempt, max_retries, backoff, e
+                    );
+                    tokio::time::sleep(backoff).await;
+                }
+                result => break result?,
+            }
+        };
+        serde_json::from_value(result).map_err(|e| {
+            ClientError::InvalidResponse(format!("Unexpected result of {}: {}", method, e))
+        })
+    }
+
+    /// POST one JSON-RPC call and return its result
+    async fn send(&self, method: &str, params: &Value) -> ClientResult<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        debug!("Calling {} (id {})", method, id);
+        let body = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+
+        let mut request = self.http.post(&self.url).json(&body);
+        if let Some(api_key) = &self.api_key {
+            request = request.header(API_KEY_HEADER, api_key);
+        }
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send().await.map_err(|e| self.transport_error(e))?;
+        let status = response.status();
+        let text = response.text().await.map_err(|e| self.transport_error(e))?;
+
+        let mut envelope: Value = match serde_json::from_str(&text) {
+            Ok(envelope) => envelope,
+            Err(_) => {
+                return Err(ClientError::Http {
+                    status: status.as_u16(),
+                    body: text,
+                })
+            }
+        };
+        // Errors like the connection limit come with a non-200 status
+        if let Some(error) = envelope.get("error") {
+            return Err(ClientError::Rpc {
+                code: RpcErrorCode::from_code(
+                    error
+                        .get("code")
+                        .and_then(Value::as_i64)
+                        .and_then(|code| i32::try_from(code).ok())
+                        .unwrap_or_default(),
+                ),
+                message: error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                data: error.get("data").cloned(),
+            });
+        }
+        if !status.is_success() {
+            return Err(ClientError::Http {
+                status: status.as_u16(),
+                body: text,
+            });
+        }
+        envelope
+            .get_mut("result")
+            .map(Value::take)
+            .ok_or_else(|| ClientError::InvalidResponse(format!("{} returned no result", method)))
+    }
+
+    fn transport_error(&self, e: reqwest::Error) -> ClientError {
+        match self.timeout {
+            Some(timeout) if e.is_timeout() => ClientError::Timeout(timeout),
+            _ => ClientError::Transport(e),
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::time::Duration;
+
+use serde_json::Value;
+use thiserror::Error;
+
+/// JSON-RPC error codes the gateway answers with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RpcErrorCode {
+    /// -32700: the request is not valid JSON-RPC
+    ParseError,
+    /// -32600: empty or oversized batch, oversized body
+    InvalidRequest,
+    /// -32601: unknown method, or an admin method on the public listener
+    MethodNotFound,
+    /// -32602: invalid method parameters or chain
+    InvalidParams,
+    /// -32603: internal error, also used for most gateway failures
+    InternalError,
+    /// -32000: precheck of the operation failed before simulation
+    CallExecutionFailed,
+    /// -32001: missing or insufficient API key or admin signature
+    Unauthorized,
+    /// -32004: the gateway does not offer the requested capability
+    UnsupportedCapability,
+    /// -32005: a concurrency limit of the gateway is reached; retry later
+    LimitExceeded,
+    /// -32008: the request ran past its deadline
+    DeadlineExceeded,
+    /// -32500: rejected by the EntryPoint during wallet creation or validation
+    EntryPointValidationRejected,
+    /// -32501: rejected by the paymaster's validation
+    PaymasterValidationRejected,
+    /// -32502: banned opcode or storage access during validation
+    OpcodeViolation,
+    /// -32503: valid only in a different time range
+    OutOfTimeRange,
+    /// -32504: a paymaster, factory or aggregator is throttled or banned
+    ThrottledOrBanned,
+    /// -32505: an entity's stake or unstake delay is too low
+    StakeTooLow,
+    /// -32506: unsupported signature aggregator
+    UnsupportedAggregator,
+    /// -32507: invalid signature, or an idempotency key reused for another operation
+    SignatureCheckFailed,
+    /// -32508: paymaster deposit too low for the operation
+    PaymasterDepositTooLow,
+    /// -32509: the sender is refused by the gateway's access lists
+    SenderDenied,
+    /// -32521: the operation's call reverted during gas estimation
+    ExecutionReverted,
+    /// Any other code
+    Other(i32),
+}
+
+impl RpcErrorCode {
+    /// Code of a JSON-RPC error object
+    pub fn from_code(code: i32) -> Self {
+        match code {
+            -32700 => RpcErrorCode::ParseError,
+            -32600 => RpcErrorCode::InvalidRequest,
+            -32601 => RpcErrorCode::MethodNotFound,
+            -32602 => RpcErrorCode::InvalidParams,
+            -32603 => RpcErrorCode::InternalError,
+            -32000 => RpcErrorCode::CallExecutionFailed,
+            -32001 => RpcErrorCode::Unauthorized,
+            -32004 => RpcErrorCode::UnsupportedCapability,
+            -32005 => RpcErrorCode::LimitExceeded,
+            -32008 => RpcErrorCode::DeadlineExceeded,
+            -32500 => RpcErrorCode::EntryPointValidationRejected,
+            -32501 => RpcErrorCode::PaymasterValidationRejected,
+            -32502 => RpcErrorCode::OpcodeViolation,
+            -32503 => RpcErrorCode::OutOfTimeRange,
+            -32504 => RpcErrorCode::ThrottledOrBanned,
+            -32505 => RpcErrorCode::StakeTooLow,
+            -32506 => RpcErrorCode::UnsupportedAggregator,
+            -32507 => RpcErrorCode::SignatureCheckFailed,
+            -32508 => RpcErrorCode::PaymasterDepositTooLow,
+            -32509 => RpcErrorCode::SenderDenied,
+            -32521 => RpcErrorCode::ExecutionReverted,
+            other => RpcErrorCode::Other(other),
+        }
+    }
+
+    /// Numeric JSON-RPC code
+    pub fn code(&self) -> i32 {
+        match self {
+            RpcErrorCode::ParseError => -32700,
+            RpcErrorCode::InvalidRequest => -32600,
+            RpcErrorCode::MethodNotFound => -32601,
+            RpcErrorCode::InvalidParams => -32602,
+            RpcErrorCode::InternalError => -32603,
+            RpcErrorCode::CallExecutionFailed => -32000,
+            RpcErrorCode::Unauthorized => -32001,
+            RpcErrorCode::UnsupportedCapability => -32004,
+            RpcErrorCode::LimitExceeded => -32005,
+            RpcErrorCode::DeadlineExceeded => -32008,
+            RpcErrorCode::EntryPointValidationRejected => -32500,
+            RpcErrorCode::PaymasterValidationRejected => -32501,
+            RpcErrorCode::OpcodeViolation => -32502,
+            RpcErrorCode::OutOfTimeRange => -32503,
+            RpcErrorCode::ThrottledOrBanned => -32504,
+            RpcErrorCode::StakeTooLow => -32505,
+            RpcErrorCode::UnsupportedAggregator => -32506,
+            RpcErrorCode::SignatureCheckFailed => -32507,
+            RpcErrorCode::PaymasterDepositTooLow => -32508,
+            RpcErrorCode::SenderDenied => -32509,
+            RpcErrorCode::ExecutionReverted => -32521,
+            RpcErrorCode::Other(code) => *code,
+        }
+    }
+}
+
+/// Client error types
+#[derive(Error, Debug)]
+pub enum ClientError {
+    /// The gateway answered with a JSON-RPC error
+    #[error("JSON-RPC error {}: {message}", code.code())]
+    Rpc {
+        /// Error code
+        code: RpcErrorCode,
+        /// Error message
+        message: String,
+        /// Error data, such as revert data or the offending entity
+        data: Option<Value>,
+    },
+
+    /// No answer within the per-request timeout
+    #[error("No response within {0:?}")]
+    Timeout(Duration),
+
+    /// The request did not reach the gateway or the response was cut
+    #[error("HTTP request failed: {0}")]
+    Transport(#[source] reqwest::Error),
+
+    /// The gateway answered with an HTTP error and no JSON-RPC error
+    #[error("HTTP {status}: {body}")]
+    Http {
+        /// HTTP status code
+        status: u16,
+        /// Response body
+        body: String,
+    },
+
+    /// The response does not have the shape of the method's result
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+}
+
+impl ClientError {
+    /// JSON-RPC error code, if the gateway answered with one
+    pub fn rpc_code(&self) -> Option<RpcErrorCode> {
+        match self {
+            ClientError::Rpc { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// Error catalog key, such as `policy.sender_not_allowed`, to localize the message by
+    pub fn error_key(&self) -> Option<&str> {
+        self.rpc_data()?.get("key")?.as_str()
+    }
+
+    /// Values of the error catalog entry's placeholders
+    pub fn error_params(&self) -> Option<&Value> {
+        self.rpc_data()?.get("params")
+    }
+
+    fn rpc_data(&self) -> Option<&Value> {
+        match self {
+            ClientError::Rpc { data, .. } => data.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Whether retrying the same call may succeed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Rpc { code, .. } => *code == RpcErrorCode::LimitExceeded,
+            ClientError::Timeout(_) | ClientError::Transport(_) => true,
+            ClientError::Http { status, .. } => matches!(status, 429 | 502 | 503 | 504),
+            ClientError::InvalidResponse(_) => false,
+        }
+    }
+}
+
+/// Result type for client calls
+pub type ClientResult<T> = Result<T, ClientError>;
@@ -0,0 +1,24 @@
+// SuperRelay Client - Typed async client for the SuperRelay gateway
+//
+// Services calling the gateway use this crate instead of hand-rolled JSON-RPC,
+// so their requests and the responses they read follow the gateway's API.
+
+#![warn(missing_docs, unreachable_pub)]
+#![deny(unused_must_use, rust_2018_idioms)]
+
+//! SuperRelay Client - typed JSON-RPC client of the SuperRelay gateway
+
+/// JSON-RPC client with API key auth, timeouts and retries
+pub mod client;
+/// Client errors and the gateway's JSON-RPC error codes
+pub mod error;
+/// Serde mirrors of the gateway's request and response shapes
+pub mod types;
+
+pub use client::{RetryConfig, SuperRelayClient, API_KEY_HEADER};
+pub use error::{ClientError, ClientResult, RpcErrorCode};
+pub use types::{
+    GasEstimate, GasPriceQuote, GasPriceTier, OpInclusion, OpInclusionStatus, SponsorOptions,
+    SponsorshipResult, UserOperation, UserOperationByHash, UserOperationReceipt, UserOperationV0_6,
+    UserOperationV0_7,
+};
@@ -0,0 +1,305 @@
+use alloy_primitives::{Address, Bytes, B256, U256};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// UserOperation of EntryPoint v0.6 in its RPC shape
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationV0_6 {
+    /// Account sending the operation
+    pub sender: Address,
+    /// Anti-replay nonce
+    pub nonce: U256,
+    /// Factory and calldata deploying the account, empty once deployed
+    pub init_code: Bytes,
+    /// Call the account executes
+    pub call_data: Bytes,
+    /// Gas of the execution call
+    pub call_gas_limit: U256,
+    /// Gas of the validation step
+    pub verification_gas_limit: U256,
+    /// Gas paid for calldata and overhead
+    pub pre_verification_gas: U256,
+    /// EIP-1559 max fee per gas
+    pub max_fee_per_gas: U256,
+    /// EIP-1559 max priority fee per gas
+    pub max_priority_fee_per_gas: U256,
+    /// Paymaster address and data, empty when unsponsored
+    pub paymaster_and_data: Bytes,
+    /// Account signature
+    #[serde(default)]
+    pub signature: Bytes,
+}
+
+/// UserOperation of EntryPoint v0.7 in its RPC shape
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationV0_7 {
+    /// Account sending the operation
+    pub sender: Address,
+    /// Anti-replay nonce
+    pub nonce: U256,
+    /// Factory deploying the account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factory: Option<Address>,
+    /// Calldata of the factory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factory_data: Option<Bytes>,
+    /// Call the account executes
+    pub call_data: Bytes,
+    /// Gas of the execution call
+    pub call_gas_limit: U256,
+    /// Gas of the validation step
+    pub verification_gas_limit: U256,
+    /// Gas paid for calldata and overhead
+    pub pre_verification_gas: U256,
+    /// EIP-1559 max fee per gas
+    pub max_fee_per_gas: U256,
+    /// EIP-1559 max priority fee per gas
+    pub max_priority_fee_per_gas: U256,
+    /// Paymaster sponsoring the operation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster: Option<Address>,
+    /// Gas of the paymaster's validation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_verification_gas_limit: Option<U256>,
+    /// Gas of the paymaster's postOp
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_post_op_gas_limit: Option<U256>,
+    /// Data passed to the paymaster
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_data: Option<Bytes>,
+    /// Account signature
+    #[serde(default)]
+    pub signature: Bytes,
+}
+
+/// UserOperation of either EntryPoint version
+///
+/// v0.6 operations are told apart by their `initCode` and `paymasterAndData`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum UserOperation {
+    /// EntryPoint v0.6
+    V0_6(UserOperationV0_6),
+    /// EntryPoint v0.7
+    V0_7(UserOperationV0_7),
+}
+
+impl UserOperation {
+    /// Account sending the operation
+    pub fn sender(&self) -> Address {
+        match self {
+            UserOperation::V0_6(op) => op.sender,
+            UserOperation::V0_7(op) => op.sender,
+        }
+    }
+}
+
+impl From<UserOperationV0_6> for UserOperation {
+    fn from(op: UserOperationV0_6) -> Self {
+        UserOperation::V0_6(op)
+    }
+}
+
+impl From<UserOperationV0_7> for UserOperation {
+    fn from(op: UserOperationV0_7) -> Self {
+        UserOperation::V0_7(op)
+    }
+}
+
+/// Options of pm_sponsorUserOperation
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SponsorOptions {
+    /// Return the operation with the paymaster fields and gas limits filled in
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub return_full_operation: bool,
+    /// Key making retries of the same sponsorship return the same signature;
+    /// calls with a key are retried like reads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    /// Requested validity window, clamped by the gateway
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validity_seconds: Option<u64>,
+    /// Sign the response with the gateway's attestation key
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub attest: bool,
+}
+
+/// Result of pm_sponsorUserOperation
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SponsorshipResult {
+    /// Paymaster address, validity window and signature
+    pub paymaster_and_data: Bytes,
+    /// Unix time the sponsorship expires at
+    pub valid_until: u64,
+    /// Unix time the sponsorship becomes valid at
+    pub valid_after: u64,
+    /// Gas of the paymaster's validation (v0.7)
+    pub paymaster_verification_gas_limit: Option<U256>,
+    /// Gas of the paymaster's postOp (v0.7)
+    pub paymaster_post_op_gas_limit: Option<U256>,
+    /// Pre-verification gas the sponsorship was signed for
+    pub pre_verification_gas: Option<U256>,
+    /// Verification gas limit the sponsorship was signed for
+    pub verification_gas_limit: Option<U256>,
+    /// Call gas limit the sponsorship was signed for
+    pub call_gas_limit: Option<U256>,
+    /// Max fee per gas the sponsorship was signed for
+    pub max_fee_per_gas: Option<U256>,
+    /// Max priority fee per gas the sponsorship was signed for
+    pub max_priority_fee_per_gas: Option<U256>,
+    /// Sponsored operation, with `returnFullOperation`
+    pub user_operation: Option<UserOperation>,
+    /// Token quote of ERC-20 sponsorships
+    pub token_quote: Option<Value>,
+    /// Signature of the response, with `attest`
+    pub attestation: Option<Value>,
+    /// Reuses the signature of an identical request made moments ago
+    #[serde(default)]
+    pub cached: bool,
+}
+
+/// Result of eth_estimateUserOperationGas
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasEstimate {
+    /// Gas paid for calldata and overhead
+    pub pre_verification_gas: U256,
+    /// Gas of the validation step
+    pub verification_gas_limit: U256,
+    /// Gas of the execution call
+    pub call_gas_limit: U256,
+    /// Gas of the paymaster's validation (v0.7)
+    #[serde(default)]
+    pub paymaster_verification_gas_limit: Option<U256>,
+    /// Gas of the paymaster's postOp (v0.7)
+    #[serde(default)]
+    pub paymaster_post_op_gas_limit: Option<U256>,
+}
+
+/// Result of eth_getUserOperationByHash
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationByHash {
+    /// The operation
+    pub user_operation: UserOperation,
+    /// EntryPoint it was sent to
+    pub entry_point: Address,
+    /// Block that included it, `None` while pending
+    pub block_number: Option<U256>,
+    /// Hash of that block
+    pub block_hash: Option<B256>,
+    /// Bundle transaction that included it
+    pub transaction_hash: Option<B256>,
+}
+
+/// Result of eth_getUserOperationReceipt
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationReceipt {
+    /// Hash of the operation
+    pub user_op_hash: B256,
+    /// EntryPoint that executed it
+    pub entry_point: Address,
+    /// Account that sent it
+    pub sender: Address,
+    /// Its nonce
+    pub nonce: U256,
+    /// Paymaster that paid for it, zero when unsponsored
+    pub paymaster: Address,
+    /// Gas cost charged, in wei
+    pub actual_gas_cost: U256,
+    /// Gas used
+    pub actual_gas_used: U256,
+    /// Whether the execution call succeeded
+    pub success: bool,
+    /// Revert reason of a failed execution
+    #[serde(default)]
+    pub reason: String,
+    /// Logs the operation emitted
+    pub logs: Vec<Value>,
+    /// Receipt of the bundle transaction
+    pub receipt: Value,
+}
+
+/// Fees of one suggestion tier of superRelay_getUserOperationGasPrice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasPriceTier {
+    /// EIP-1559 max fee per gas, in wei
+    pub max_fee_per_gas: U256,
+    /// EIP-1559 max priority fee per gas, in wei
+    pub max_priority_fee_per_gas: U256,
+}
+
+/// Result of superRelay_getUserOperationGasPrice
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasPriceQuote {
+    /// Cheapest tier
+    pub slow: GasPriceTier,
+    /// Default tier
+    pub standard: GasPriceTier,
+    /// Tier with the most headroom for base fee increases
+    pub fast: GasPriceTier,
+    /// Pending base fee, in wei
+    pub base_fee: U256,
+    /// Latest block the quote is based on
+    pub block_number: U256,
+}
+
+/// What the builders did with a UserOperation
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum OpInclusion {
+    /// Put in a bundle that has not been mined yet
+    #[serde(rename_all = "camelCase")]
+    Bundled {
+        /// Latest bundle transaction carrying the operation
+        tx_hash: B256,
+    },
+    /// Put in a bundle that was mined
+    #[serde(rename_all = "camelCase")]
+    Included {
+        /// Bundle transaction carrying the operation
+        tx_hash: B256,
+        /// Block containing the transaction
+        block_number: u64,
+    },
+    /// Put in a bundle the builder abandoned; the operation stays in the pool
+    #[serde(rename_all = "camelCase")]
+    BundleDropped {
+        /// Abandoned bundle transaction
+        tx_hash: B256,
+        /// Why the bundle was abandoned
+        reason: String,
+    },
+    /// Left out of a bundle but kept in the pool
+    Skipped {
+        /// Why the builder left the operation out
+        reason: String,
+    },
+    /// Removed from the pool by the builder
+    Rejected {
+        /// Why the builder rejected the operation
+        reason: String,
+    },
+}
+
+/// Result of superRelay_getOpInclusionStatus
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpInclusionStatus {
+    /// Whether the operation was put in any bundle
+    pub attempted: bool,
+    /// Bundle transactions that carried the operation, oldest first
+    pub bundles: Vec<B256>,
+    /// Latest thing a builder did with the operation
+    #[serde(flatten)]
+    pub latest: OpInclusion,
+    /// Unix time of the latest builder event about the operation
+    pub updated_at: u64,
+}
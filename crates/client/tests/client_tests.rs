@@ -0,0 +1,369 @@
+//! Typed client against a served gateway: request and response serde, errors, retries
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use alloy_primitives::{address, b256, bytes, Address, U256};
+use axum::{http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+use rundler_paymaster_relay::{
+    policy::PolicyEngine, service::PaymasterRelayService, signer::SignerManager,
+};
+use rundler_pool::LocalPoolBuilder;
+use secrecy::SecretString;
+use serde_json::{json, Value};
+use super_relay_client::{
+    ClientError, GasPriceQuote, OpInclusion, OpInclusionStatus, RetryConfig, RpcErrorCode,
+    SponsorOptions, SuperRelayClient, UserOperation, UserOperationV0_6, UserOperationV0_7,
+};
+use super_relay_gateway::{
+    error, middleware::hash_api_key, ApiKeyConfig, ApiKeyEntry, ApiKeyScope, GatewayConfig,
+    PaymasterGateway,
+};
+use tempfile::tempdir;
+use tokio::net::TcpListener;
+
+const SENDER: Address = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+const SIGNER_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+const ENTRY_POINT_V06: Address = address!("5FF137D4b0FDCD49DcA30c7CF57E578a026d2789");
+const ENTRY_POINT_V07: Address = address!("0000000071727De22E5E9d8BAf0edAc6f37da032");
+const API_KEY: &str = "sk-client-test";
+
+fn paymaster_service() -> Arc<PaymasterRelayService> {
+    let dir = tempdir().unwrap();
+    let policy_path = dir.path().join("policy.toml");
+    std::fs::write(
+        &policy_path,
+        format!("[default]\nsenders = [\"{}\"]\n", SENDER),
+    )
+    .unwrap();
+    let signer_manager =
+        SignerManager::new(SecretString::new(SIGNER_KEY.to_string().into())).unwrap();
+    let policy_engine = PolicyEngine::new(&policy_path).unwrap();
+    let pool = Arc::new(LocalPoolBuilder::new(10).get_handle());
+    Arc::new(PaymasterRelayService::new(
+        signer_manager,
+        policy_engine,
+        pool,
+    ))
+}
+
+async fn serve(app: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{}", addr)
+}
+
+async fn serve_gateway(config: GatewayConfig) -> String {
+    serve(
+        PaymasterGateway::new(config, Some(paymaster_service()))
+            .app()
+            .unwrap(),
+    )
+    .await
+}
+
+fn user_op_v0_6() -> UserOperationV0_6 {
+    UserOperationV0_6 {
+        sender: SENDER,
+        nonce: U256::from(1),
+        init_code: bytes!(""),
+        call_data: bytes!("b61d27f6"),
+        call_gas_limit: U256::from(100_000),
+        verification_gas_limit: U256::from(100_000),
+        pre_verification_gas: U256::from(21_000),
+        max_fee_per_gas: U256::from(1_000_000_000),
+        max_priority_fee_per_gas: U256::from(1_000_000_000),
+        paymaster_and_data: bytes!(""),
+        signature: bytes!(""),
+    }
+}
+
+#[tokio::test]
+async fn test_sponsorship_round_trip() {
+    let client = SuperRelayClient::new(serve_gateway(GatewayConfig::default()).await);
+    let options = SponsorOptions {
+        return_full_operation: true,
+        ..Default::default()
+    };
+
+    let op = UserOperation::V0_6(user_op_v0_6());
+    let result = client
+        .sponsor_user_operation(&op, ENTRY_POINT_V06, &options)
+        .await
+        .unwrap();
+    assert!(result.paymaster_and_data.len() > 20);
+    assert!(result.valid_until > result.valid_after);
+    // The gateway parsed our operation and serialized it back unchanged,
+    // apart from the paymaster data and gas it signed
+    let op = user_op_v0_6();
+    let expected = UserOperation::V0_6(UserOperationV0_6 {
+        paymaster_and_data: result.paymaster_and_data.clone(),
+        call_gas_limit: result.call_gas_limit.unwrap_or(op.call_gas_limit),
+        verification_gas_limit: result
+            .verification_gas_limit
+            .unwrap_or(op.verification_gas_limit),
+        pre_verification_gas: result
+            .pre_verification_gas
+            .unwrap_or(op.pre_verification_gas),
+        max_fee_per_gas: result.max_fee_per_gas.unwrap_or(op.max_fee_per_gas),
+        max_priority_fee_per_gas: result
+            .max_priority_fee_per_gas
+            .unwrap_or(op.max_priority_fee_per_gas),
+        ..op
+    });
+    assert_eq!(result.user_operation, Some(expected));
+
+    let op = UserOperation::V0_7(UserOperationV0_7 {
+        sender: SENDER,
+        nonce: U256::from(2),
+        call_data: bytes!("b61d27f6"),
+        call_gas_limit: U256::from(100_000),
+        verification_gas_limit: U256::from(100_000),
+        pre_verification_gas: U256::from(21_000),
+        max_fee_per_gas: U256::from(1_000_000_000),
+        max_priority_fee_per_gas: U256::from(1_000_000_000),
+        ..Default::default()
+    });
+    let result = client
+        .sponsor_user_operation(&op, ENTRY_POINT_V07, &options)
+        .await
+        .unwrap();
+    let Some(UserOperation::V0_7(sponsored)) = result.user_operation else {
+        panic!("v0.7 operation expected, got {:?}", result.user_operation);
+    };
+    assert_eq!(sponsored.sender, SENDER);
+    assert_eq!(sponsored.nonce, U256::from(2));
+    assert!(sponsored.paymaster.is_some());
+}
+
+#[tokio::test]
+async fn test_reads() {
+    let client = SuperRelayClient::new(serve_gateway(GatewayConfig::default()).await);
+
+    assert!(client.chain_id().await.unwrap() > 0);
+    assert!(client
+        .supported_entry_points()
+        .await
+        .unwrap()
+        .contains(&ENTRY_POINT_V06));
+    let estimate = client
+        .estimate_user_operation_gas(&user_op_v0_6().into(), ENTRY_POINT_V06)
+        .await
+        .unwrap();
+    assert_eq!(estimate.pre_verification_gas, U256::from(21_000));
+    let hash = b256!("00000000000000000000000000000000000000000000000000000000000000aa");
+    assert_eq!(client.get_user_operation_by_hash(hash).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_error_codes() {
+    let config = GatewayConfig {
+        api_keys: ApiKeyConfig {
+            enabled: true,
+            allow_anonymous_reads: true,
+            keys: vec![ApiKeyEntry {
+                id: "client-test".to_string(),
+                key_hash: hash_api_key(API_KEY),
+                scopes: vec![ApiKeyScope::Sponsor, ApiKeyScope::Read],
+                trust_level: None,
+            }],
+        },
+        ..Default::default()
+    };
+    let url = serve_gateway(config).await;
+    let op = user_op_v0_6().into();
+
+    let err = SuperRelayClient::new(&url)
+        .sponsor_user_operation(&op, ENTRY_POINT_V06, &SponsorOptions::default())
+        .await
+        .unwrap_err();
+    assert_eq!(err.rpc_code(), Some(RpcErrorCode::Unauthorized), "{}", err);
+
+    let client = SuperRelayClient::new(&url).with_api_key(API_KEY);
+    client
+        .sponsor_user_operation(&op, ENTRY_POINT_V06, &SponsorOptions::default())
+        .await
+        .unwrap();
+    let err = client
+        .request::<Value>("foo_bar", json!([]), true)
+        .await
+        .unwrap_err();
+    assert_eq!(err.rpc_code(), Some(RpcErrorCode::MethodNotFound));
+    assert!(!err.is_retryable());
+}
+
+#[test]
+fn test_error_codes_match_gateway() {
+    for (code, expected) in [
+        (RpcErrorCode::InvalidParams, error::INVALID_PARAMS_CODE),
+        (RpcErrorCode::InternalError, error::INTERNAL_ERROR_CODE),
+        (
+            RpcErrorCode::CallExecutionFailed,
+            error::CALL_EXECUTION_FAILED_CODE,
+        ),
+        (
+            RpcErrorCode::EntryPointValidationRejected,
+            error::ENTRYPOINT_VALIDATION_REJECTED_CODE,
+        ),
+        (
+            RpcErrorCode::PaymasterValidationRejected,
+            error::PAYMASTER_VALIDATION_REJECTED_CODE,
+        ),
+        (RpcErrorCode::OpcodeViolation, error::OPCODE_VIOLATION_CODE),
+        (RpcErrorCode::OutOfTimeRange, error::OUT_OF_TIME_RANGE_CODE),
+        (
+            RpcErrorCode::ThrottledOrBanned,
+            error::THROTTLED_OR_BANNED_CODE,
+        ),
+        (RpcErrorCode::StakeTooLow, error::STAKE_TOO_LOW_CODE),
+        (
+            RpcErrorCode::UnsupportedAggregator,
+            error::UNSUPPORTED_AGGREGATOR_CODE,
+        ),
+        (
+            RpcErrorCode::SignatureCheckFailed,
+            error::SIGNATURE_CHECK_FAILED_CODE,
+        ),
+        (
+            RpcErrorCode::PaymasterDepositTooLow,
+            error::PAYMASTER_DEPOSIT_TOO_LOW_CODE,
+        ),
+        (
+            RpcErrorCode::ExecutionReverted,
+            error::EXECUTION_REVERTED_CODE,
+        ),
+        (RpcErrorCode::SenderDenied, error::SENDER_DENIED_CODE),
+        (
+            RpcErrorCode::DeadlineExceeded,
+            error::DEADLINE_EXCEEDED_CODE,
+        ),
+        (RpcErrorCode::LimitExceeded, error::LIMIT_EXCEEDED_CODE),
+        (
+            RpcErrorCode::UnsupportedCapability,
+            error::UNSUPPORTED_CAPABILITY_CODE,
+        ),
+    ] {
+        assert_eq!(code.code(), expected, "{:?}", code);
+        assert_eq!(RpcErrorCode::from_code(expected), code);
+    }
+    assert_eq!(RpcErrorCode::from_code(-1), RpcErrorCode::Other(-1));
+}
+
+#[test]
+fn test_superrelay_results_match_gateway_serde() {
+    let tier = |fee: u128| super_relay_gateway::GasPriceTier {
+        max_fee_per_gas: fee,
+        max_priority_fee_per_gas: fee / 10,
+    };
+    let quote = super_relay_gateway::GasPriceQuote {
+        slow: tier(1_000),
+        standard: tier(2_000),
+        fast: tier(3_000),
+        base_fee: 900,
+        block_number: 77,
+    };
+    let parsed: GasPriceQuote =
+        serde_json::from_value(serde_json::to_value(&quote).unwrap()).unwrap();
+    assert_eq!(parsed.fast.max_fee_per_gas, U256::from(3_000));
+    assert_eq!(parsed.standard.max_priority_fee_per_gas, U256::from(200));
+    assert_eq!(parsed.base_fee, U256::from(900));
+    assert_eq!(parsed.block_number, U256::from(77));
+
+    let tx_hash = b256!("00000000000000000000000000000000000000000000000000000000000000bb");
+    let status = super_relay_gateway::OpInclusionStatus {
+        attempted: true,
+        bundles: vec![tx_hash],
+        latest: super_relay_gateway::OpInclusion::Included {
+            tx_hash,
+            block_number: 12,
+        },
+        updated_at: 1_700_000_000,
+    };
+    let parsed: OpInclusionStatus =
+        serde_json::from_value(serde_json::to_value(&status).unwrap()).unwrap();
+    assert_eq!(
+        parsed,
+        OpInclusionStatus {
+            attempted: true,
+            bundles: vec![tx_hash],
+            latest: OpInclusion::Included {
+                tx_hash,
+                block_number: 12,
+            },
+            updated_at: 1_700_000_000,
+        }
+    );
+}
+
+/// Server answering 503 to the first `failures` calls, then eth_chainId's result
+async fn flaky_server(failures: usize) -> (String, Arc<AtomicUsize>) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let app = Router::new().route(
+        "/",
+        post(move |Json(request): Json<Value>| {
+            let counter = counter.clone();
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                    return (StatusCode::SERVICE_UNAVAILABLE, "overloaded").into_response();
+                }
+                Json(json!({"jsonrpc": "2.0", "id": request["id"], "result": "0x7a69"}))
+                    .into_response()
+            }
+        }),
+    );
+    (serve(app).await, calls)
+}
+
+#[tokio::test]
+async fn test_retries_of_idempotent_calls() {
+    let retries = RetryConfig {
+        max_retries: 2,
+        backoff: Duration::from_millis(10),
+    };
+
+    let (url, calls) = flaky_server(2).await;
+    let client = SuperRelayClient::new(url).with_retries(retries.clone());
+    assert_eq!(client.chain_id().await.unwrap(), 31337);
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    // Sends are never retried
+    let (url, calls) = flaky_server(2).await;
+    let client = SuperRelayClient::new(url).with_retries(retries.clone());
+    let err = client
+        .send_user_operation(&user_op_v0_6().into(), ENTRY_POINT_V06)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, ClientError::Http { status: 503, .. }),
+        "{}",
+        err
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    // Retries stop after max_retries
+    let (url, calls) = flaky_server(5).await;
+    let client = SuperRelayClient::new(url).with_retries(retries);
+    assert!(client.chain_id().await.is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_per_request_timeout() {
+    let app = Router::new().route(
+        "/",
+        post(|| async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            "late"
+        }),
+    );
+    let client = SuperRelayClient::new(serve(app).await).with_timeout(Duration::from_millis(200));
+    let err = client.chain_id().await.unwrap_err();
+    assert!(matches!(err, ClientError::Timeout(_)), "{}", err);
+}
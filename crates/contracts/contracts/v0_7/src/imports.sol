@@ -10,3 +10,5 @@ import "@account-abstraction/interfaces/IAggregator.sol";
 import "@account-abstraction/interfaces/IStakeManager.sol";
 import "@account-abstraction/interfaces/PackedUserOperation.sol";
 import "@account-abstraction/core/SenderCreator.sol";
+import "@account-abstraction/core/EntryPoint.sol";
+import "@account-abstraction/samples/SimpleAccountFactory.sol";
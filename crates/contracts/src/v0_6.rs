@@ -128,6 +128,8 @@ sol! {
 
         function balanceOf(address account) external view returns (uint256);
 
+        function depositTo(address account) external payable;
+
         function simulateValidation(UserOperation calldata userOp) external;
 
         function simulateHandleOp(UserOperation calldata op, address target, bytes calldata targetCallData) external;
@@ -188,6 +190,13 @@ sol!(
     "contracts/out/v0_6/GetBalances.sol/GetBalances.json"
 );
 
+// Deployed on local nodes by `super-relay dev bootstrap`
+sol!(
+    #[allow(missing_docs)]
+    SimpleAccountFactory,
+    "contracts/out/v0_6/SimpleAccountFactory.sol/SimpleAccountFactory.json"
+);
+
 // https://etherscan.io/address/0x5ff137d4b0fdcd49dca30c7cf57e578a026d2789#code
 static __ENTRY_POINT_V0_6_DEPLOYED_BYTECODE_HEX: &[u8] = include_bytes!(
     "../contracts/bytecode/entrypoint/0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789_deployed.txt"
@@ -201,6 +210,18 @@ static __ENTRY_POINT_V0_6_DEPLOYED_BYTECODE: [u8; 23689] = {
 pub static ENTRY_POINT_V0_6_DEPLOYED_BYTECODE: Bytes =
     Bytes::from_static(&__ENTRY_POINT_V0_6_DEPLOYED_BYTECODE);
 
+// Creation code of the above, deployed on local nodes by `super-relay dev bootstrap`
+static __ENTRY_POINT_V0_6_BYTECODE_HEX: &[u8] = include_bytes!(
+    "../contracts/bytecode/entrypoint/0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789.txt"
+);
+static __ENTRY_POINT_V0_6_BYTECODE: [u8; 24388] = {
+    match const_hex::const_decode_to_array(__ENTRY_POINT_V0_6_BYTECODE_HEX) {
+        Ok(a) => a,
+        Err(_) => panic!("Failed to decode entrypoint creation hex"),
+    }
+};
+pub static ENTRY_POINT_V0_6_BYTECODE: Bytes = Bytes::from_static(&__ENTRY_POINT_V0_6_BYTECODE);
+
 // CallGasEstimationProxy deployed bytecode
 static __CALL_GAS_ESTIMATION_PROXY_V0_6_DEPLOYED_BYTECODE_HEX: &[u8] = include_bytes!(
     "../contracts/out/v0_6/CallGasEstimationProxy.sol/CallGasEstimationProxy_deployedBytecode.txt"
@@ -159,6 +159,8 @@ sol!(
         ) external view returns (DepositInfo memory info);
 
         function balanceOf(address account) external view returns (uint256);
+
+        function depositTo(address account) external payable;
     }
 
     #[allow(missing_docs)]
@@ -265,6 +267,19 @@ sol!(
     "contracts/out/v0_7/GetBalances.sol/GetBalances.json"
 );
 
+// Deployed on local nodes by `super-relay dev bootstrap`
+sol!(
+    #[allow(missing_docs)]
+    EntryPoint,
+    "contracts/out/v0_7/EntryPoint.sol/EntryPoint.json"
+);
+
+sol!(
+    #[allow(missing_docs)]
+    SimpleAccountFactory,
+    "contracts/out/v0_7/SimpleAccountFactory.sol/SimpleAccountFactory.json"
+);
+
 // EntryPointSimulations deployed bytecode
 static __ENTRY_POINT_SIMULATIONS_V0_7_DEPLOYED_BYTECODE_HEX: &[u8] = include_bytes!(
     "../contracts/out/v0_7/EntryPointSimulations.sol/EntryPointSimulations_deployedBytecode.txt"
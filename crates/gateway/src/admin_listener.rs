@@ -0,0 +1,232 @@
+use serde::Deserialize;
+
+use crate::{
+    error::{GatewayError, GatewayResult},
+    middleware::ApiKeyScope,
+};
+
+/// Separate listener for admin-scoped routes (`[gateway.admin]`)
+///
+/// Admin-scoped routes are the JSON-RPC methods requiring the admin scope
+/// (policy reload, key rotation, access lists, ...) and the GET /admin/*
+/// endpoints. With the admin listener enabled the public listener refuses
+/// them; both listeners share the gateway's components and shutdown.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AdminListenerConfig {
+    /// Serve admin-scoped routes on `host`:`port`
+    pub enabled: bool,
+    /// Host the admin listener binds to
+    pub host: String,
+    /// Port the admin listener binds to
+    pub port: u16,
+    /// Serve admin-scoped routes on the public port while the admin listener
+    /// is disabled; ignored once it is enabled
+    pub allow_admin_on_public: bool,
+    /// Serve GET /metrics on the admin listener as well
+    pub serve_metrics: bool,
+}
+
+impl Default for AdminListenerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "127.0.0.1".to_string(),
+            port: 3002,
+            allow_admin_on_public: true,
+            serve_metrics: false,
+        }
+    }
+}
+
+impl AdminListenerConfig {
+    /// Whether the public listener serves admin-scoped routes
+    pub fn admin_on_public(&self) -> bool {
+        !self.enabled && self.allow_admin_on_public
+    }
+
+    /// Address of the admin listener
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// Check the enabled admin listener does not take the port of another
+    /// listener of the process, `listeners` naming each with its port
+    pub fn validate_ports(&self, listeners: &[(&str, u16)]) -> GatewayResult<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if let Some((name, _)) = listeners.iter().find(|(_, port)| *port == self.port) {
+            return Err(GatewayError::InvalidRequest(format!(
+                "admin port {} is also the {} port",
+                self.port, name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Whether JSON-RPC `method` is only served where admin routes are
+pub(crate) fn is_admin_method(method: &str) -> bool {
+    ApiKeyScope::required_for(method) == ApiKeyScope::Admin
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc};
+
+    use axum::Router;
+    use serde_json::{json, Value};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::{
+        test_utils, AccessControlConfig, GatewayConfig, PaymasterGateway, SenderAccessControl,
+    };
+
+    fn gateway(admin: AdminListenerConfig) -> PaymasterGateway {
+        let access_control =
+            SenderAccessControl::from_config(&AccessControlConfig::default()).unwrap();
+        PaymasterGateway::new(
+            GatewayConfig {
+                admin,
+                api_keys: test_utils::admin_api_keys(),
+                ..Default::default()
+            },
+            None,
+        )
+        .with_access_control(Arc::new(access_control))
+    }
+
+    async fn serve(app: Router) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    /// Send a raw HTTP request and return the raw response
+    async fn send(addr: SocketAddr, request: String) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    async fn get(addr: SocketAddr, path: &str) -> String {
+        send(
+            addr,
+            format!(
+                "GET {} HTTP/1.1\r\nHost: localhost\r\nx-api-key: {}\r\nConnection: close\r\n\r\n",
+                path,
+                test_utils::ADMIN_KEY
+            ),
+        )
+        .await
+    }
+
+    /// POST a JSON-RPC call and return the response body
+    async fn call(addr: SocketAddr, method: &str) -> Value {
+        let body = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": []}).to_string();
+        let response = send(
+            addr,
+            format!(
+                "POST / HTTP/1.1\r\nHost: localhost\r\nx-api-key: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                test_utils::ADMIN_KEY,
+                body.len(),
+                body
+            ),
+        )
+        .await;
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_admin_methods_only_on_admin_listener() {
+        let gateway = gateway(AdminListenerConfig {
+            enabled: true,
+            allow_admin_on_public: true,
+            ..Default::default()
+        });
+        let public = serve(gateway.app().unwrap()).await;
+        let admin = serve(gateway.admin_app().unwrap()).await;
+
+        let response = call(admin, "admin_getAccessLists").await;
+        assert_eq!(response["result"]["denylist"], json!([]), "{}", response);
+
+        // The public listener does not know admin-scoped methods, even with
+        // allow_admin_on_public as the admin listener is enabled
+        for method in ["admin_getAccessLists", "pm_rotatePaymasterKey"] {
+            let response = call(public, method).await;
+            assert_eq!(response["error"]["code"], json!(-32601), "{}", response);
+            assert_eq!(response["error"]["message"], "Method not found");
+        }
+        assert!(get(public, "/admin/events")
+            .await
+            .starts_with("HTTP/1.1 404"));
+        assert!(!get(admin, "/admin/events?types=bogus")
+            .await
+            .starts_with("HTTP/1.1 404"));
+
+        // User-facing methods keep working on the public listener
+        let response = call(public, "eth_chainId").await;
+        assert!(response.get("result").is_some(), "{}", response);
+
+        for addr in [public, admin] {
+            let response = get(addr, "/live").await;
+            assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+            assert!(!get(addr, "/health").await.starts_with("HTTP/1.1 404"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_admin_on_public_without_admin_listener() {
+        let allowed = gateway(AdminListenerConfig::default());
+        let addr = serve(allowed.app().unwrap()).await;
+        let response = call(addr, "admin_getAccessLists").await;
+        assert!(response.get("result").is_some(), "{}", response);
+
+        let refused = gateway(AdminListenerConfig {
+            allow_admin_on_public: false,
+            ..Default::default()
+        });
+        let addr = serve(refused.app().unwrap()).await;
+        let response = call(addr, "admin_getAccessLists").await;
+        assert_eq!(response["error"]["code"], json!(-32601), "{}", response);
+        assert!(get(addr, "/admin/pool/export")
+            .await
+            .starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_admin_port_must_differ_from_other_listeners() {
+        let listeners = [("gateway", 3000), ("rundler RPC", 3001)];
+        let admin = AdminListenerConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(admin.validate_ports(&listeners).is_ok());
+
+        for port in [3000, 3001] {
+            let admin = AdminListenerConfig {
+                enabled: true,
+                port,
+                ..Default::default()
+            };
+            let err = admin.validate_ports(&listeners).unwrap_err();
+            assert!(err.to_string().contains(&port.to_string()), "{}", err);
+        }
+
+        // A disabled listener binds nothing
+        let disabled = AdminListenerConfig {
+            port: 3001,
+            ..Default::default()
+        };
+        assert!(disabled.validate_ports(&listeners).is_ok());
+    }
+}
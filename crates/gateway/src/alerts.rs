@@ -0,0 +1,1065 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::Address;
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use metrics::counter;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use crate::{
+    error::{GatewayError, GatewayResult},
+    events::{EventBus, GatewayEvent},
+};
+
+/// Source named in every webhook payload
+const ALERT_SOURCE: &str = "super-relay";
+
+/// Senders tracked for repeated rejections before idle ones are forgotten
+const MAX_TRACKED_SENDERS: usize = 10_000;
+
+/// Critical condition an operator is alerted about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// Paymaster deposit on an EntryPoint is below the configured minimum
+    DepositLow,
+    /// The pool task gave up restarting; sends fail until the process restarts
+    PoolStopped,
+    /// The sponsorship policy is not loaded
+    PolicyFailure,
+    /// The paymaster signer is unusable or failed to sign
+    SignerError,
+    /// One sender was rejected by the sponsorship checks over and over
+    RepeatedRejections,
+    /// An EntryPoint was added to or removed from the advertised lists
+    EntryPointsChanged,
+    /// The pool started throttling or banning a paymaster we sponsor with
+    EntityThrottled,
+    /// Synthetic alert fired to test delivery
+    Test,
+}
+
+impl AlertKind {
+    /// Every kind, in declaration order
+    pub const ALL: [AlertKind; 8] = [
+        AlertKind::DepositLow,
+        AlertKind::PoolStopped,
+        AlertKind::PolicyFailure,
+        AlertKind::SignerError,
+        AlertKind::RepeatedRejections,
+        AlertKind::EntryPointsChanged,
+        AlertKind::EntityThrottled,
+        AlertKind::Test,
+    ];
+
+    /// Name used in configuration, payloads and metric labels
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertKind::DepositLow => "deposit_low",
+            AlertKind::PoolStopped => "pool_stopped",
+            AlertKind::PolicyFailure => "policy_failure",
+            AlertKind::SignerError => "signer_error",
+            AlertKind::RepeatedRejections => "repeated_rejections",
+            AlertKind::EntryPointsChanged => "entry_points_changed",
+            AlertKind::EntityThrottled => "entity_throttled",
+            AlertKind::Test => "test",
+        }
+    }
+
+    /// Severity of alerts of this kind
+    pub fn severity(&self) -> AlertSeverity {
+        match self {
+            AlertKind::RepeatedRejections | AlertKind::EntryPointsChanged => AlertSeverity::Warning,
+            AlertKind::Test => AlertSeverity::Info,
+            _ => AlertSeverity::Critical,
+        }
+    }
+}
+
+/// How urgently an alert needs attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertSeverity {
+    /// Informational, e.g. a test alert
+    Info,
+    /// Needs a look, service continues
+    Warning,
+    /// Sponsorship or sending is failing or about to
+    Critical,
+}
+
+/// One alert as sent to the sinks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Alert {
+    /// Condition alerted about
+    pub kind: AlertKind,
+    /// Severity of the kind
+    pub severity: AlertSeverity,
+    /// What the alert is about, such as a probe name or a sender; alerts are
+    /// rate limited per kind and subject
+    pub subject: String,
+    /// Human readable description
+    pub message: String,
+    /// Kind-specific data
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub details: Value,
+    /// Unix time the condition was observed, in seconds
+    pub timestamp: u64,
+}
+
+impl Alert {
+    /// Alert of `kind` about `subject`, observed now
+    pub fn new(kind: AlertKind, subject: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            severity: kind.severity(),
+            subject: subject.into(),
+            message: message.into(),
+            details: Value::Null,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+
+    /// Attach kind-specific data
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = details;
+        self
+    }
+
+    /// One line summary, e.g. for chat webhooks that display `text`
+    pub fn summary(&self) -> String {
+        format!(
+            "[{}] {} {}: {}",
+            serde_json::to_value(self.severity)
+                .ok()
+                .and_then(|severity| severity.as_str().map(str::to_uppercase))
+                .unwrap_or_default(),
+            self.kind.as_str(),
+            self.subject,
+            self.message
+        )
+    }
+}
+
+/// Destination alerts are delivered to
+#[async_trait]
+pub trait AlertSink: Send + Sync {
+    /// Sink name used in logs and delivery reports
+    fn name(&self) -> &str;
+
+    /// Deliver `alert`, retrying as the sink is configured to
+    async fn deliver(&self, alert: &Alert) -> Result<(), String>;
+}
+
+/// Generic HTTP webhook, e.g. a Slack incoming webhook or a PagerDuty relay
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    /// URL alerts are POSTed to as JSON
+    pub url: String,
+    /// Header carrying the credentials, sent when `auth_header_value` is set
+    pub auth_header_name: String,
+    /// Credentials, e.g. `Bearer <token>`
+    pub auth_header_value: Option<String>,
+    /// Time allowed for each delivery attempt
+    pub timeout_ms: u64,
+    /// Further attempts after a failed delivery; 4xx answers other than 429 are not retried
+    pub max_retries: u32,
+    /// Delay before the first retry, doubling for each further one
+    pub retry_backoff_ms: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            auth_header_name: "Authorization".to_string(),
+            auth_header_value: None,
+            timeout_ms: 5_000,
+            max_retries: 3,
+            retry_backoff_ms: 500,
+        }
+    }
+}
+
+/// Webhook payload: the alert with its source and a one line `text`
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    source: &'static str,
+    text: String,
+    #[serde(flatten)]
+    alert: &'a Alert,
+}
+
+/// Sink POSTing alerts to an HTTP webhook
+pub struct WebhookSink {
+    config: WebhookConfig,
+    name: String,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    /// Sink for the webhook of `config`
+    pub fn new(config: WebhookConfig) -> GatewayResult<Self> {
+        let url = reqwest::Url::parse(&config.url).map_err(|e| {
+            GatewayError::InvalidRequest(format!("Invalid alert webhook URL: {}", e))
+        })?;
+        // Webhook URLs often embed their secret in the path
+        let name = url.origin().ascii_serialization();
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .map_err(|e| GatewayError::InternalError(format!("HTTP client error: {}", e)))?;
+        Ok(Self {
+            config,
+            name,
+            client,
+        })
+    }
+
+    /// One delivery attempt, returning whether a failure is worth retrying
+    async fn attempt(&self, payload: &WebhookPayload<'_>) -> Result<(), (String, bool)> {
+        let mut request = self.client.post(&self.config.url).json(payload);
+        if let Some(value) = &self.config.auth_header_value {
+            request = request.header(self.config.auth_header_name.as_str(), value);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| (format!("request failed: {}", e), true))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let retryable = status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS;
+        Err((format!("webhook answered {}", status), retryable))
+    }
+}
+
+#[async_trait]
+impl AlertSink for WebhookSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn deliver(&self, alert: &Alert) -> Result<(), String> {
+        let payload = WebhookPayload {
+            source: ALERT_SOURCE,
+            text: alert.summary(),
+            alert,
+        };
+        let mut attempt = 0u32;
+        loop {
+            match self.attempt(&payload).await {
+                Ok(()) => return Ok(()),
+                Err((reason, retryable)) => {
+                    if !retryable || attempt >= self.config.max_retries {
+                        return Err(format!("{} after {} attempts", reason, attempt + 1));
+                    }
+                    let delay = self
+                        .config
+                        .retry_backoff_ms
+                        .saturating_mul(1u64 << attempt.min(16));
+                    debug!(
+                        "Alert webhook {} {}, retrying in {}ms",
+                        self.name, reason, delay
+                    );
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Per-kind alert settings (`[gateway.alerts.events.<kind>]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AlertEventConfig {
+    /// Send alerts of this kind
+    pub enabled: bool,
+    /// Overrides the global cooldown for this kind
+    pub cooldown_seconds: Option<u64>,
+}
+
+impl Default for AlertEventConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cooldown_seconds: None,
+        }
+    }
+}
+
+/// Operator alerting through webhooks (`[gateway.alerts]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AlertConfig {
+    /// Publish alerts at all
+    pub enabled: bool,
+    /// Webhooks every alert is delivered to
+    pub webhooks: Vec<WebhookConfig>,
+    /// The same kind and subject is alerted at most once per this many seconds
+    pub cooldown_seconds: u64,
+    /// Per-kind settings keyed by kind name, e.g. `deposit_low`
+    pub events: HashMap<String, AlertEventConfig>,
+    /// Seconds between health probe runs raising alerts, 0 to only probe on /health and /ready
+    pub probe_interval_seconds: u64,
+    /// Sponsorship check rejections of one sender that raise an alert
+    pub rejection_threshold: usize,
+    /// Window the rejections are counted in, in seconds
+    pub rejection_window_seconds: u64,
+    /// Alerts waiting for delivery; further alerts are dropped while full
+    pub queue_capacity: usize,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            webhooks: Vec::new(),
+            cooldown_seconds: 300,
+            events: HashMap::new(),
+            probe_interval_seconds: 60,
+            rejection_threshold: 20,
+            rejection_window_seconds: 60,
+            queue_capacity: 256,
+        }
+    }
+}
+
+impl AlertConfig {
+    /// Sinks for the configured webhooks
+    pub fn sinks(&self) -> GatewayResult<Vec<Arc<dyn AlertSink>>> {
+        self.webhooks
+            .iter()
+            .map(|webhook| {
+                WebhookSink::new(webhook.clone()).map(|sink| Arc::new(sink) as Arc<dyn AlertSink>)
+            })
+            .collect()
+    }
+
+    /// Alert bus delivering to the configured webhooks, disabled unless enabled
+    pub fn start(&self) -> GatewayResult<AlertBus> {
+        if !self.enabled {
+            return Ok(AlertBus::disabled());
+        }
+        AlertBus::start(self.clone(), self.sinks()?)
+    }
+
+    fn event(&self, kind: AlertKind) -> Option<&AlertEventConfig> {
+        self.events.get(kind.as_str())
+    }
+
+    fn cooldown(&self, kind: AlertKind) -> Duration {
+        let seconds = self
+            .event(kind)
+            .and_then(|event| event.cooldown_seconds)
+            .unwrap_or(self.cooldown_seconds);
+        Duration::from_secs(seconds)
+    }
+}
+
+/// Outcome of delivering an alert to one sink
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertDelivery {
+    /// Sink name
+    pub sink: String,
+    /// Whether the sink accepted the alert
+    pub delivered: bool,
+    /// Why delivery failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Deliver `alert` to every sink concurrently
+pub async fn deliver_to_sinks(sinks: &[Arc<dyn AlertSink>], alert: &Alert) -> Vec<AlertDelivery> {
+    join_all(sinks.iter().map(|sink| async move {
+        let result = sink.deliver(alert).await;
+        let outcome = if result.is_ok() {
+            "delivered"
+        } else {
+            "failed"
+        };
+        counter!("superrelay_alerts_total", "kind" => alert.kind.as_str(), "outcome" => outcome)
+            .increment(1);
+        match result {
+            Ok(()) => AlertDelivery {
+                sink: sink.name().to_string(),
+                delivered: true,
+                error: None,
+            },
+            Err(e) => {
+                warn!(
+                    "🚨 Alert {} not delivered to {}: {}",
+                    alert.kind.as_str(),
+                    sink.name(),
+                    e
+                );
+                AlertDelivery {
+                    sink: sink.name().to_string(),
+                    delivered: false,
+                    error: Some(e),
+                }
+            }
+        }
+    }))
+    .await
+}
+
+struct AlertBusInner {
+    config: AlertConfig,
+    sinks: Vec<Arc<dyn AlertSink>>,
+    queue: mpsc::Sender<Alert>,
+    last_sent: Mutex<HashMap<(AlertKind, String), Instant>>,
+    rejections: Mutex<HashMap<Address, VecDeque<Instant>>>,
+}
+
+/// Queue components publish alerts onto, delivered to the sinks in the background
+///
+/// Publishing never waits on delivery: alerts are filtered, rate limited and
+/// queued, and a full queue drops the alert. A disabled bus, the default,
+/// ignores everything published to it. Queued alerts are also streamed as
+/// gateway events when an event bus is attached.
+#[derive(Clone, Default)]
+pub struct AlertBus {
+    inner: Option<Arc<AlertBusInner>>,
+    events: Option<EventBus>,
+}
+
+impl AlertBus {
+    /// Bus that ignores every alert
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Bus delivering to `sinks`, with its delivery task spawned on the current runtime
+    pub fn start(config: AlertConfig, sinks: Vec<Arc<dyn AlertSink>>) -> GatewayResult<Self> {
+        for name in config.events.keys() {
+            if !AlertKind::ALL.iter().any(|kind| kind.as_str() == name) {
+                return Err(GatewayError::InvalidRequest(format!(
+                    "Unknown alert kind '{}' in [gateway.alerts.events]",
+                    name
+                )));
+            }
+        }
+
+        let (queue, mut queued) = mpsc::channel::<Alert>(config.queue_capacity.max(1));
+        let inner = Arc::new(AlertBusInner {
+            config,
+            sinks,
+            queue,
+            last_sent: Mutex::new(HashMap::new()),
+            rejections: Mutex::new(HashMap::new()),
+        });
+
+        let sinks = inner.sinks.clone();
+        tokio::spawn(async move {
+            while let Some(alert) = queued.recv().await {
+                deliver_to_sinks(&sinks, &alert).await;
+            }
+        });
+        Ok(Self {
+            inner: Some(inner),
+            events: None,
+        })
+    }
+
+    /// Stream queued alerts onto `events`
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Whether alerts are published at all
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Interval of the health probe runs raising alerts, `None` when disabled
+    pub fn probe_interval(&self) -> Option<Duration> {
+        let seconds = self.inner.as_ref()?.config.probe_interval_seconds;
+        (seconds > 0).then(|| Duration::from_secs(seconds))
+    }
+
+    /// Queue `alert` unless its kind is disabled or the same kind and subject
+    /// was alerted within the cooldown, returning whether it was queued
+    pub fn publish(&self, alert: Alert) -> bool {
+        let Some(inner) = &self.inner else {
+            return false;
+        };
+        let kind = alert.kind;
+        if inner.config.event(kind).is_some_and(|event| !event.enabled) {
+            return false;
+        }
+
+        {
+            let mut last_sent = inner.last_sent.lock().unwrap();
+            let key = (kind, alert.subject.clone());
+            let cooldown = inner.config.cooldown(kind);
+            if last_sent
+                .get(&key)
+                .is_some_and(|at| at.elapsed() < cooldown)
+            {
+                debug!(
+                    "Alert {} {} suppressed within cooldown",
+                    kind.as_str(),
+                    alert.subject
+                );
+                counter!("superrelay_alerts_total", "kind" => kind.as_str(), "outcome" => "suppressed")
+                    .increment(1);
+                return false;
+            }
+            last_sent.insert(key, Instant::now());
+        }
+
+        let streamed = self.events.as_ref().map(|events| (events, alert.clone()));
+        let queued = Self::enqueue(inner, alert);
+        if let (true, Some((events, alert))) = (queued, streamed) {
+            events.publish(GatewayEvent::Alert(alert));
+        }
+        queued
+    }
+
+    fn enqueue(inner: &AlertBusInner, alert: Alert) -> bool {
+        let kind = alert.kind;
+        match inner.queue.try_send(alert) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("🚨 Alert {} dropped: {}", kind.as_str(), e);
+                counter!("superrelay_alerts_total", "kind" => kind.as_str(), "outcome" => "dropped")
+                    .increment(1);
+                false
+            }
+        }
+    }
+
+    /// Count a sponsorship check rejection of `sender`, alerting once the
+    /// rejections within the window reach the threshold
+    pub fn record_rejection(&self, sender: Address, reason: &str) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        let window = Duration::from_secs(inner.config.rejection_window_seconds);
+        let count = {
+            let mut rejections = inner.rejections.lock().unwrap();
+            if rejections.len() >= MAX_TRACKED_SENDERS && !rejections.contains_key(&sender) {
+                rejections.retain(|_, times| times.back().is_some_and(|at| at.elapsed() < window));
+            }
+            let times = rejections.entry(sender).or_default();
+            times.push_back(Instant::now());
+            while times.front().is_some_and(|at| at.elapsed() >= window) {
+                times.pop_front();
+            }
+            times.len()
+        };
+
+        let threshold = inner.config.rejection_threshold;
+        if threshold > 0 && count >= threshold {
+            self.publish(
+                Alert::new(
+                    AlertKind::RepeatedRejections,
+                    sender.to_checksum(None),
+                    format!(
+                        "{} sponsorship rejections within {}s, last: {}",
+                        count,
+                        window.as_secs(),
+                        reason
+                    ),
+                )
+                .with_details(serde_json::json!({
+                    "rejections": count,
+                    "windowSeconds": window.as_secs(),
+                    "lastReason": reason,
+                })),
+            );
+        }
+    }
+
+    /// Deliver a synthetic alert to every sink now, bypassing kind settings and cooldown
+    pub async fn fire_test(&self, message: &str) -> GatewayResult<(Alert, Vec<AlertDelivery>)> {
+        let inner = self
+            .inner
+            .as_ref()
+            .ok_or_else(|| GatewayError::InvalidRequest("Alerting is not enabled".to_string()))?;
+        let alert = Alert::new(AlertKind::Test, ALERT_SOURCE, message);
+        let deliveries = deliver_to_sinks(&inner.sinks, &alert).await;
+        Ok((alert, deliveries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::{Duration, Instant},
+    };
+
+    use alloy_primitives::{Address, U256};
+    use async_trait::async_trait;
+    use axum::{
+        extract::State,
+        http::{HeaderMap, StatusCode},
+        routing::post,
+        Json, Router,
+    };
+    use serde_json::{json, Value};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::{test_utils, DepositProbe, DepositReader, HealthChecker, PaymasterGateway};
+
+    const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    /// Webhook recording what it receives, answering `fail_status` to the first `failures` calls
+    #[derive(Clone)]
+    struct MockWebhook {
+        received: Arc<Mutex<Vec<(HeaderMap, Value)>>>,
+        calls: Arc<AtomicUsize>,
+        failures: usize,
+        fail_status: StatusCode,
+        delay: Duration,
+    }
+
+    impl MockWebhook {
+        fn new() -> Self {
+            Self {
+                received: Arc::new(Mutex::new(Vec::new())),
+                calls: Arc::new(AtomicUsize::new(0)),
+                failures: 0,
+                fail_status: StatusCode::INTERNAL_SERVER_ERROR,
+                delay: Duration::ZERO,
+            }
+        }
+
+        fn failing(mut self, failures: usize, status: StatusCode) -> Self {
+            self.failures = failures;
+            self.fail_status = status;
+            self
+        }
+
+        fn slow(mut self, delay: Duration) -> Self {
+            self.delay = delay;
+            self
+        }
+
+        /// Serve on a random port, returning the webhook URL
+        async fn serve(&self) -> String {
+            async fn receive(
+                State(webhook): State<MockWebhook>,
+                headers: HeaderMap,
+                Json(body): Json<Value>,
+            ) -> StatusCode {
+                tokio::time::sleep(webhook.delay).await;
+                let call = webhook.calls.fetch_add(1, Ordering::SeqCst);
+                if call < webhook.failures {
+                    return webhook.fail_status;
+                }
+                webhook.received.lock().unwrap().push((headers, body));
+                StatusCode::OK
+            }
+
+            let app = Router::new()
+                .route("/hooks/secret-token", post(receive))
+                .with_state(self.clone());
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+            format!("http://{}/hooks/secret-token", addr)
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+
+        fn bodies(&self) -> Vec<Value> {
+            let received = self.received.lock().unwrap();
+            received.iter().map(|(_, body)| body.clone()).collect()
+        }
+
+        /// Wait until `count` alerts were received
+        async fn wait_for(&self, count: usize) -> Vec<Value> {
+            let start = Instant::now();
+            while self.bodies().len() < count {
+                assert!(
+                    start.elapsed() < Duration::from_secs(5),
+                    "received {:?}",
+                    self.bodies()
+                );
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            self.bodies()
+        }
+    }
+
+    fn webhook(url: String) -> WebhookConfig {
+        WebhookConfig {
+            url,
+            retry_backoff_ms: 10,
+            ..Default::default()
+        }
+    }
+
+    fn bus(url: String, config: AlertConfig) -> AlertBus {
+        AlertConfig {
+            enabled: true,
+            webhooks: vec![webhook(url)],
+            ..config
+        }
+        .start()
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_webhook_payload_schema_and_auth_header() {
+        let mock = MockWebhook::new();
+        let sink = WebhookSink::new(WebhookConfig {
+            auth_header_name: "X-Alert-Token".to_string(),
+            auth_header_value: Some("s3cret".to_string()),
+            ..webhook(mock.serve().await)
+        })
+        .unwrap();
+        // The path of webhook URLs carries their secret
+        assert!(!sink.name().contains("secret-token"), "{}", sink.name());
+
+        let alert = Alert::new(
+            AlertKind::DepositLow,
+            "deposit_v0_7",
+            "deposit 1 wei is below minimum 10 wei",
+        )
+        .with_details(json!({"minDepositWei": "10"}));
+        sink.deliver(&alert).await.unwrap();
+
+        let received = mock.received.lock().unwrap();
+        let (headers, body) = &received[0];
+        assert_eq!(headers["x-alert-token"], "s3cret");
+        assert_eq!(
+            *body,
+            json!({
+                "source": "super-relay",
+                "text": "[CRITICAL] deposit_low deposit_v0_7: deposit 1 wei is below minimum 10 wei",
+                "kind": "deposit_low",
+                "severity": "critical",
+                "subject": "deposit_v0_7",
+                "message": "deposit 1 wei is below minimum 10 wei",
+                "details": {"minDepositWei": "10"},
+                "timestamp": alert.timestamp,
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<Alert>(body.clone()).unwrap(),
+            alert
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cooldown_dedups_per_kind_and_subject() {
+        let mock = MockWebhook::new();
+        let events = HashMap::from([
+            (
+                "policy_failure".to_string(),
+                AlertEventConfig {
+                    enabled: false,
+                    cooldown_seconds: None,
+                },
+            ),
+            (
+                "signer_error".to_string(),
+                AlertEventConfig {
+                    enabled: true,
+                    cooldown_seconds: Some(0),
+                },
+            ),
+        ]);
+        let bus = bus(
+            mock.serve().await,
+            AlertConfig {
+                cooldown_seconds: 300,
+                events,
+                ..Default::default()
+            },
+        );
+
+        let deposit = |subject: &str| Alert::new(AlertKind::DepositLow, subject, "low");
+        assert!(bus.publish(deposit("deposit_v0_6")));
+        assert!(!bus.publish(deposit("deposit_v0_6")));
+        assert!(bus.publish(deposit("deposit_v0_7")));
+        assert!(bus.publish(Alert::new(
+            AlertKind::PoolStopped,
+            "deposit_v0_6",
+            "stopped"
+        )));
+        // Disabled kinds are never sent
+        assert!(!bus.publish(Alert::new(AlertKind::PolicyFailure, "policy", "missing")));
+        // A per-kind cooldown of 0 sends every alert
+        for _ in 0..2 {
+            assert!(bus.publish(Alert::new(AlertKind::SignerError, "signer", "failed")));
+        }
+
+        let bodies = mock.wait_for(5).await;
+        let sent: Vec<_> = bodies
+            .iter()
+            .map(|body| {
+                format!(
+                    "{} {}",
+                    body["kind"].as_str().unwrap(),
+                    body["subject"].as_str().unwrap()
+                )
+            })
+            .collect();
+        assert_eq!(
+            sent,
+            [
+                "deposit_low deposit_v0_6",
+                "deposit_low deposit_v0_7",
+                "pool_stopped deposit_v0_6",
+                "signer_error signer",
+                "signer_error signer",
+            ]
+        );
+
+        let unknown = AlertConfig {
+            enabled: true,
+            events: HashMap::from([("deposit".to_string(), AlertEventConfig::default())]),
+            ..Default::default()
+        };
+        assert!(unknown.start().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_webhook_retries_server_errors_only() {
+        let alert = Alert::new(AlertKind::Test, "super-relay", "retry");
+
+        // Two 500s then success, within three retries
+        let mock = MockWebhook::new().failing(2, StatusCode::INTERNAL_SERVER_ERROR);
+        let sink = WebhookSink::new(webhook(mock.serve().await)).unwrap();
+        sink.deliver(&alert).await.unwrap();
+        assert_eq!(mock.calls(), 3);
+        assert_eq!(mock.bodies().len(), 1);
+
+        // Rate limiting is retried too
+        let mock = MockWebhook::new().failing(1, StatusCode::TOO_MANY_REQUESTS);
+        let sink = WebhookSink::new(webhook(mock.serve().await)).unwrap();
+        sink.deliver(&alert).await.unwrap();
+        assert_eq!(mock.calls(), 2);
+
+        // A client error will not go away by retrying
+        let mock = MockWebhook::new().failing(1, StatusCode::BAD_REQUEST);
+        let sink = WebhookSink::new(webhook(mock.serve().await)).unwrap();
+        let err = sink.deliver(&alert).await.unwrap_err();
+        assert_eq!(err, "webhook answered 400 Bad Request after 1 attempts");
+        assert_eq!(mock.calls(), 1);
+
+        // Retries give up after max_retries
+        let mock = MockWebhook::new().failing(usize::MAX, StatusCode::SERVICE_UNAVAILABLE);
+        let sink = WebhookSink::new(WebhookConfig {
+            max_retries: 2,
+            ..webhook(mock.serve().await)
+        })
+        .unwrap();
+        let err = sink.deliver(&alert).await.unwrap_err();
+        assert_eq!(
+            err,
+            "webhook answered 503 Service Unavailable after 3 attempts"
+        );
+        assert_eq!(mock.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_rejections_alert_at_threshold() {
+        let mock = MockWebhook::new();
+        let bus = bus(
+            mock.serve().await,
+            AlertConfig {
+                rejection_threshold: 3,
+                rejection_window_seconds: 60,
+                ..Default::default()
+            },
+        );
+        let sender: Address = SENDER.parse().unwrap();
+        let other = Address::repeat_byte(0x42);
+
+        bus.record_rejection(sender, "sender is blocked");
+        bus.record_rejection(other, "sender is blocked");
+        bus.record_rejection(sender, "sender is blocked");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(mock.bodies().is_empty());
+
+        bus.record_rejection(sender, "gas limit too high");
+        // Further rejections fall in the cooldown
+        bus.record_rejection(sender, "gas limit too high");
+
+        let bodies = mock.wait_for(1).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(mock.bodies().len(), 1);
+        assert_eq!(bodies[0]["kind"], "repeated_rejections");
+        assert_eq!(bodies[0]["severity"], "warning");
+        assert_eq!(bodies[0]["subject"], SENDER);
+        assert_eq!(
+            bodies[0]["details"],
+            json!({"rejections": 3, "windowSeconds": 60, "lastReason": "gas limit too high"})
+        );
+    }
+
+    struct ZeroDeposit;
+
+    #[async_trait]
+    impl DepositReader for ZeroDeposit {
+        async fn deposit_of(&self, _account: Address) -> Result<U256, String> {
+            Ok(U256::ZERO)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failing_deposit_probe_publishes_alert() {
+        let mock = MockWebhook::new();
+        let health = HealthChecker::new()
+            .with_probe(Arc::new(DepositProbe::new(
+                "deposit_v0_7",
+                Arc::new(ZeroDeposit),
+                Arc::new(test_utils::service(&format!(
+                    "senders = [\"{}\"]\n",
+                    SENDER
+                ))),
+                U256::from(10),
+            )))
+            .with_alerts(bus(mock.serve().await, AlertConfig::default()));
+
+        let report = health.check_readiness().await;
+        assert!(!report.ready);
+        let bodies = mock.wait_for(1).await;
+        assert_eq!(bodies[0]["kind"], "deposit_low");
+        assert_eq!(bodies[0]["subject"], "deposit_v0_7");
+        assert_eq!(bodies[0]["details"]["minDepositWei"], "10");
+        assert!(
+            bodies[0]["message"]
+                .as_str()
+                .unwrap()
+                .contains("below minimum 10 wei"),
+            "{}",
+            bodies[0]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_publish_never_waits_on_delivery() {
+        let mock = MockWebhook::new().slow(Duration::from_secs(2));
+        let bus = bus(
+            mock.serve().await,
+            AlertConfig {
+                queue_capacity: 2,
+                ..Default::default()
+            },
+        );
+
+        let start = Instant::now();
+        let queued: Vec<bool> = (0..10)
+            .map(|i| {
+                bus.publish(Alert::new(
+                    AlertKind::SignerError,
+                    format!("signer-{}", i),
+                    "x",
+                ))
+            })
+            .collect();
+        assert!(start.elapsed() < Duration::from_millis(500));
+        // One alert in delivery and two queued at most; the rest are dropped
+        assert!(queued.iter().take(2).all(|queued| *queued), "{:?}", queued);
+        assert!(queued.iter().filter(|queued| **queued).count() <= 3);
+        assert!(!queued[9]);
+
+        // A disabled bus drops everything
+        let disabled = AlertBus::disabled();
+        assert!(!disabled.publish(Alert::new(AlertKind::Test, "test", "x")));
+        assert!(disabled.probe_interval().is_none());
+    }
+
+    /// POST a JSON-RPC call and return the response body
+    async fn call(addr: std::net::SocketAddr, method: &str, params: Value) -> Value {
+        let body =
+            json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params}).to_string();
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nx-api-key: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            test_utils::ADMIN_KEY,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        serde_json::from_str(body).unwrap()
+    }
+
+    async fn serve(gateway: PaymasterGateway) -> std::net::SocketAddr {
+        let app = gateway.app().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_admin_test_alert_reports_deliveries() {
+        let mock = MockWebhook::new();
+        let failing = MockWebhook::new().failing(usize::MAX, StatusCode::FORBIDDEN);
+        let alerts = AlertConfig {
+            enabled: true,
+            webhooks: vec![webhook(mock.serve().await), webhook(failing.serve().await)],
+            ..Default::default()
+        }
+        .start()
+        .unwrap();
+        let addr = serve(
+            PaymasterGateway::new(test_utils::admin_gateway_config(), None).with_alerts(alerts),
+        )
+        .await;
+
+        // Fired twice: test alerts bypass the cooldown
+        for _ in 0..2 {
+            let response = call(addr, "admin_testAlert", json!(["webhook check"])).await;
+            let result = &response["result"];
+            assert_eq!(result["alert"]["kind"], "test", "{}", response);
+            assert_eq!(result["alert"]["message"], "webhook check");
+            assert_eq!(result["deliveries"][0]["delivered"], true);
+            assert_eq!(result["deliveries"][1]["delivered"], false);
+            assert_eq!(
+                result["deliveries"][1]["error"],
+                "webhook answered 403 Forbidden after 1 attempts"
+            );
+        }
+        assert_eq!(mock.bodies().len(), 2);
+
+        let addr = serve(PaymasterGateway::new(
+            test_utils::admin_gateway_config(),
+            None,
+        ))
+        .await;
+        let response = call(addr, "admin_testAlert", json!([])).await;
+        assert_eq!(
+            response["error"]["message"],
+            "Invalid request: Alerting is not enabled"
+        );
+    }
+}
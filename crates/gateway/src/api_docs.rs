@@ -109,11 +109,12 @@ SuperRelay 是基于 Rundler 构建的企业级 Account Abstraction 解决方案
 ### 1️⃣ Paymaster API (1 method)
 - `pm_sponsorUserOperation` - UserOperation Gas费赞助
 
-### 2️⃣ ERC-4337 Core API (5 methods)  
+### 2️⃣ ERC-4337 Core API (6 methods)  
 - `eth_sendUserOperation` - 发送用户操作到内存池
 - `eth_estimateUserOperationGas` - 估算Gas费用
 - `eth_getUserOperationByHash` - 根据哈希查询用户操作
 - `eth_getUserOperationReceipt` - 获取用户操作收据
+- `eth_getUserOperationNonce` - 获取下一个可用nonce (含内存池中待处理的操作)
 - `eth_supportedEntryPoints` - 获取支持的入口点
 
 ### 3️⃣ Chain Information (2 methods)
@@ -147,6 +148,14 @@ SuperRelay 是基于 Rundler 构建的企业级 Account Abstraction 解决方案
 - `ready` - 就绪检查  
 - `metrics` - Prometheus指标
 
+### 9️⃣ SuperRelay API (6 methods)
+- `superRelay_getVerificationProof` - 按userOpHash查询双重签名验证证明 (KMS签名摘要、验证摘要、TEE设备ID)
+- `superRelay_getPipelineStats` - 赞助检查流水线的模块顺序及各模块通过/拒绝/错误/跳过次数与耗时
+- `superRelay_getUserOperationGasPrice` - 基于最新区块的 slow/standard/fast 三档 maxFeePerGas 与 maxPriorityFeePerGas 建议 (按区块缓存)
+- `superRelay_sendUserOperationConditional` - 带 expectedStorage ({地址: {槽: 值}}) 条件提交，仅在存储值仍匹配时打包 (需Bundler支持条件交易)
+- `superRelay_getEntryPointStatus` - 各EntryPoint的 send/estimate/sponsor 能力及缺失原因，未通过检查的EntryPoint不出现在 eth_supportedEntryPoints 中
+- `superRelay_getErrorCatalog` - 错误目录: 每个错误的稳定 key、错误码、英文描述与参数占位符 (亦可 GET /errors)，便于客户端本地化
+
 ## 📘 使用示例
 
 ```bash
@@ -282,6 +291,8 @@ pub struct HealthResponse {
     pub timestamp: u64,
     /// System uptime in seconds
     pub uptime_seconds: u64,
+    /// Gateway version
+    pub version: String,
     /// Component health status
     pub components: Value,
     /// System metrics
@@ -1197,6 +1208,8 @@ pub struct SecurityMetadata {
     pub contract_risk_score: u8,
     /// Pattern analysis results
     pub pattern_analysis: Vec<String>,
+    /// Age of the threat feed data in seconds
+    pub threat_intel_age_seconds: Option<u64>,
 }
 
 /// Data integrity validation result
@@ -1,12 +1,26 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, SystemTime},
+};
 
 use alloy_primitives::{Address, U256};
+use metrics::counter;
 use num_traits::ToPrimitive;
+use rundler_paymaster_relay::audit::AuditLogger;
 use rundler_types::{UserOperation, UserOperationVariant};
 use serde::{Deserialize, Serialize};
-use tracing::{debug, warn};
+use serde_json::{json, Value};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
 
-use crate::error::GatewayResult;
+use crate::{
+    error::{GatewayError, GatewayResult},
+    gateway::JsonRpcRequest,
+};
 
 /// Authorization check result for UserOperation
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -241,6 +255,8 @@ impl AuthorizationChecker {
         let sender = match user_op {
             UserOperationVariant::V0_6(op) => op.sender(),
             UserOperationVariant::V0_7(op) => op.sender(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.sender(),
         };
 
         debug!("Authorizing UserOperation for sender: {:?}", sender);
@@ -586,6 +602,8 @@ impl AuthorizationChecker {
         let nonce = match user_op {
             UserOperationVariant::V0_6(op) => op.nonce(),
             UserOperationVariant::V0_7(op) => op.nonce(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.nonce(),
         };
 
         // TODO: In production, verify nonce against actual on-chain state
@@ -628,6 +646,11 @@ impl AuthorizationChecker {
                 op.max_fee_per_gas().to_u64().unwrap_or(u64::MAX),
                 op.max_priority_fee_per_gas().to_u64().unwrap_or(u64::MAX),
             ),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => (
+                op.max_fee_per_gas().to_u64().unwrap_or(u64::MAX),
+                op.max_priority_fee_per_gas().to_u64().unwrap_or(u64::MAX),
+            ),
         };
 
         if max_fee_per_gas > self.config.max_gas_price {
@@ -727,6 +750,8 @@ impl AuthorizationChecker {
                 }
             }
             UserOperationVariant::V0_7(op) => op.paymaster(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.paymaster(),
         }
     }
 
@@ -782,9 +807,521 @@ impl Default for AuthorizationChecker {
     }
 }
 
+/// Methods gated by the allowlist in allowlist mode
+const ALLOWLIST_METHODS: &[&str] = &[
+    "eth_sendUserOperation",
+    "superRelay_sendUserOperationConditional",
+    "pm_sponsorUserOperation",
+    "pm_sponsorUserOperationERC20",
+    "pm_sponsorUserOperationBatch",
+];
+
+/// Methods whose first param is a UserOperation naming its sender
+const USER_OPERATION_METHODS: &[&str] = &[
+    "eth_sendUserOperation",
+    "superRelay_sendUserOperationConditional",
+    "eth_estimateUserOperationGas",
+    "pm_sponsorUserOperation",
+    "pm_sponsorUserOperationERC20",
+    "pm_simulateSponsorship",
+];
+
+/// Methods whose result names the sender of a stored operation
+const SENDER_RESULT_METHODS: &[&str] =
+    &["eth_getUserOperationByHash", "eth_getUserOperationReceipt"];
+
+/// Sender list checked at the gateway edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessList {
+    /// Senders allowed to send and sponsor operations in allowlist mode
+    Allowlist,
+    /// Senders refused on every method
+    Denylist,
+}
+
+impl AccessList {
+    /// Name used in config, admin RPC params and the metric label
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccessList::Allowlist => "allowlist",
+            AccessList::Denylist => "denylist",
+        }
+    }
+}
+
+impl fmt::Display for AccessList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AccessList {
+    type Err = GatewayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "allowlist" => Ok(AccessList::Allowlist),
+            "denylist" => Ok(AccessList::Denylist),
+            _ => Err(GatewayError::InvalidParams(format!(
+                "Unknown access list {:?}, expected \"allowlist\" or \"denylist\"",
+                s
+            ))),
+        }
+    }
+}
+
+/// Sender allowlist and denylist enforced at the gateway edge (`[gateway.access_control]`)
+///
+/// Denylisted senders are refused on every method that names them, including
+/// reads of their operations. In allowlist mode only allowlisted senders may
+/// call eth_sendUserOperation and pm_sponsorUserOperation.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AccessControlConfig {
+    /// Restrict sending and sponsoring to allowlisted senders
+    pub allowlist_mode: bool,
+    /// Allowlisted sender addresses
+    pub allowlist: Vec<String>,
+    /// File of allowlisted addresses, one per line, reloaded when it changes
+    pub allowlist_file: Option<PathBuf>,
+    /// Denylisted sender addresses
+    pub denylist: Vec<String>,
+    /// File of denylisted addresses, one per line, reloaded when it changes
+    pub denylist_file: Option<PathBuf>,
+    /// Seconds between checks of the list files for changes
+    pub reload_interval_seconds: u64,
+}
+
+impl Default for AccessControlConfig {
+    fn default() -> Self {
+        Self {
+            allowlist_mode: false,
+            allowlist: Vec::new(),
+            allowlist_file: None,
+            denylist: Vec::new(),
+            denylist_file: None,
+            reload_interval_seconds: 10,
+        }
+    }
+}
+
+impl AccessControlConfig {
+    /// Whether any list is configured or allowlist mode is on
+    pub fn is_enabled(&self) -> bool {
+        self.allowlist_mode
+            || !self.allowlist.is_empty()
+            || self.allowlist_file.is_some()
+            || !self.denylist.is_empty()
+            || self.denylist_file.is_some()
+    }
+}
+
+/// Parse a list entry in any hex case; mixed-case input must be a valid EIP-55 checksum
+pub fn parse_list_address(entry: &str) -> GatewayResult<Address> {
+    let entry = entry.trim();
+    let digits = entry
+        .strip_prefix("0x")
+        .or_else(|| entry.strip_prefix("0X"))
+        .unwrap_or(entry);
+    let address: Address = digits
+        .parse()
+        .map_err(|_| GatewayError::InvalidParams(format!("Invalid address: {}", entry)))?;
+    let mixed_case = digits.bytes().any(|b| b.is_ascii_lowercase())
+        && digits.bytes().any(|b| b.is_ascii_uppercase());
+    if mixed_case && address.to_checksum(None)[2..] != *digits {
+        return Err(GatewayError::InvalidParams(format!(
+            "Invalid address checksum: {}",
+            entry
+        )));
+    }
+    Ok(address)
+}
+
+/// Parse a list file: one address per line, empty lines and `#` comments skipped
+pub fn parse_list_file(data: &str) -> GatewayResult<HashSet<Address>> {
+    let mut addresses = HashSet::new();
+    for (index, line) in data.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let address = parse_list_address(line)
+            .map_err(|e| GatewayError::InvalidRequest(format!("Line {}: {}", index + 1, e)))?;
+        addresses.insert(address);
+    }
+    Ok(addresses)
+}
+
+/// Entries of one list from config, its file and admin changes
+#[derive(Debug, Default)]
+struct ListEntries {
+    inline: HashSet<Address>,
+    file: HashSet<Address>,
+    /// Added at runtime, kept across file reloads
+    added: HashSet<Address>,
+    /// Removed at runtime, kept across file reloads
+    removed: HashSet<Address>,
+}
+
+impl ListEntries {
+    fn contains(&self, address: &Address) -> bool {
+        !self.removed.contains(address)
+            && (self.inline.contains(address)
+                || self.file.contains(address)
+                || self.added.contains(address))
+    }
+
+    fn addresses(&self) -> Vec<String> {
+        let mut addresses: Vec<Address> = self
+            .inline
+            .iter()
+            .chain(&self.file)
+            .chain(&self.added)
+            .filter(|address| !self.removed.contains(*address))
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        addresses.sort();
+        addresses
+            .into_iter()
+            .map(|address| address.to_checksum(None))
+            .collect()
+    }
+}
+
+/// One list with the file it reloads from
+struct ListState {
+    entries: RwLock<ListEntries>,
+    file: Option<PathBuf>,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl ListState {
+    fn new(inline: &[String], file: Option<PathBuf>) -> GatewayResult<Self> {
+        let inline = inline
+            .iter()
+            .map(|entry| parse_list_address(entry))
+            .collect::<GatewayResult<_>>()?;
+        let state = Self {
+            entries: RwLock::new(ListEntries {
+                inline,
+                ..Default::default()
+            }),
+            file,
+            modified: Mutex::new(None),
+        };
+        state.reload()?;
+        Ok(state)
+    }
+
+    /// Reload the file if it changed since the last load
+    fn reload(&self) -> GatewayResult<bool> {
+        let Some(path) = &self.file else {
+            return Ok(false);
+        };
+        let read_error = |e: std::io::Error| {
+            GatewayError::InternalError(format!("Failed to read {}: {}", path.display(), e))
+        };
+        let modified = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(read_error)?;
+        let mut last_modified = self.modified.lock().unwrap();
+        if *last_modified == Some(modified) {
+            return Ok(false);
+        }
+
+        let data = std::fs::read_to_string(path).map_err(read_error)?;
+        let addresses = parse_list_file(&data).map_err(|e| {
+            GatewayError::InvalidRequest(format!("Invalid list {}: {}", path.display(), e))
+        })?;
+        self.entries.write().unwrap().file = addresses;
+        *last_modified = Some(modified);
+        Ok(true)
+    }
+}
+
+/// Admin change of an access list, journaled to the audit log
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListChange {
+    /// RFC 3339 time of the change
+    pub timestamp: String,
+    /// Always `access_list_change`
+    pub event: &'static str,
+    /// Admin API key id or signer that made the change
+    pub requester: Option<String>,
+    /// Correlation id of the admin request
+    pub request_id: Option<String>,
+    /// Changed list
+    pub list: AccessList,
+    /// `add` or `remove`
+    pub action: &'static str,
+    /// Checksummed sender address
+    pub address: String,
+}
+
+/// Current access lists, returned by admin_getAccessLists
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessControlStatus {
+    /// Whether sending and sponsoring is limited to the allowlist
+    pub allowlist_mode: bool,
+    /// Effective allowlist, checksummed and sorted
+    pub allowlist: Vec<String>,
+    /// Effective denylist, checksummed and sorted
+    pub denylist: Vec<String>,
+}
+
+/// Edge filter refusing denylisted senders and, in allowlist mode, unlisted ones
+///
+/// Runs before the policy engine and any pool or paymaster work. Admin changes
+/// apply immediately and survive file reloads but not restarts; edit the list
+/// files to make them permanent.
+pub struct SenderAccessControl {
+    allowlist_mode: bool,
+    allowlist: ListState,
+    denylist: ListState,
+    audit_logger: AuditLogger,
+}
+
+impl SenderAccessControl {
+    /// Access control for `config`, loading the list files once
+    pub fn from_config(config: &AccessControlConfig) -> GatewayResult<Self> {
+        Ok(Self {
+            allowlist_mode: config.allowlist_mode,
+            allowlist: ListState::new(&config.allowlist, config.allowlist_file.clone())?,
+            denylist: ListState::new(&config.denylist, config.denylist_file.clone())?,
+            audit_logger: AuditLogger::default(),
+        })
+    }
+
+    /// Journal admin changes to `audit_logger`
+    pub fn with_audit_logger(mut self, audit_logger: AuditLogger) -> Self {
+        self.audit_logger = audit_logger;
+        self
+    }
+
+    fn list(&self, list: AccessList) -> &ListState {
+        match list {
+            AccessList::Allowlist => &self.allowlist,
+            AccessList::Denylist => &self.denylist,
+        }
+    }
+
+    /// Whether `address` is on `list`
+    pub fn contains(&self, list: AccessList, address: &Address) -> bool {
+        self.list(list).entries.read().unwrap().contains(address)
+    }
+
+    /// Check `sender` may call `method`
+    pub fn check_sender(&self, method: &str, sender: &Address) -> GatewayResult<()> {
+        if self.contains(AccessList::Denylist, sender) {
+            return Err(Self::denied(
+                AccessList::Denylist,
+                method,
+                format!("{} is denylisted", sender.to_checksum(None)),
+            ));
+        }
+        if self.allowlist_mode
+            && ALLOWLIST_METHODS.contains(&method)
+            && !self.contains(AccessList::Allowlist, sender)
+        {
+            return Err(Self::denied(
+                AccessList::Allowlist,
+                method,
+                format!("{} is not allowlisted", sender.to_checksum(None)),
+            ));
+        }
+        Ok(())
+    }
+
+    fn denied(list: AccessList, method: &str, message: String) -> GatewayError {
+        warn!("Rejected {}: {}", method, message);
+        counter!("superrelay_gateway_access_denied_total", "list" => list.as_str()).increment(1);
+        GatewayError::SenderDenied(message)
+    }
+
+    /// Check the sender named in the request params, before routing
+    ///
+    /// Requests without a parseable sender pass; their handlers reject them.
+    pub fn check_request(&self, request: &JsonRpcRequest) -> GatewayResult<()> {
+        let method = request.method.as_str();
+        if method == "pm_sponsorUserOperationBatch" {
+            let user_ops = request.params.first().and_then(Value::as_array);
+            for (index, op) in user_ops.into_iter().flatten().enumerate() {
+                let sender = op.get("sender").and_then(Value::as_str);
+                if let Some(Ok(sender)) = sender.map(parse_list_address) {
+                    self.check_sender(method, &sender).map_err(|e| {
+                        GatewayError::BatchRejected {
+                            index,
+                            error: Box::new(e),
+                        }
+                    })?;
+                }
+            }
+            return Ok(());
+        }
+        let sender = if USER_OPERATION_METHODS.contains(&method) {
+            request.params.first().and_then(|op| op.get("sender"))
+        } else if method == "eth_getUserOperationNonce" {
+            request.params.first()
+        } else {
+            None
+        };
+        match sender.and_then(Value::as_str).map(parse_list_address) {
+            Some(Ok(sender)) => self.check_sender(method, &sender),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the sender of an operation returned by a lookup method
+    pub fn check_result(&self, method: &str, result: &Value) -> GatewayResult<()> {
+        if !SENDER_RESULT_METHODS.contains(&method) {
+            return Ok(());
+        }
+        let sender = result
+            .get("sender")
+            .or_else(|| result.get("userOperation").and_then(|op| op.get("sender")));
+        match sender.and_then(Value::as_str).map(parse_list_address) {
+            Some(Ok(sender)) if self.contains(AccessList::Denylist, &sender) => Err(Self::denied(
+                AccessList::Denylist,
+                method,
+                format!("{} is denylisted", sender.to_checksum(None)),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Add `address` to `list`, returning whether the list changed
+    pub fn add(&self, list: AccessList, address: Address, request: &JsonRpcRequest) -> bool {
+        let changed = {
+            let mut entries = self.list(list).entries.write().unwrap();
+            let listed = entries.contains(&address);
+            entries.removed.remove(&address);
+            entries.added.insert(address);
+            !listed
+        };
+        self.journal(list, "add", address, request);
+        changed
+    }
+
+    /// Remove `address` from `list`, returning whether the list changed
+    pub fn remove(&self, list: AccessList, address: Address, request: &JsonRpcRequest) -> bool {
+        let changed = {
+            let mut entries = self.list(list).entries.write().unwrap();
+            let listed = entries.contains(&address);
+            entries.added.remove(&address);
+            entries.removed.insert(address);
+            listed
+        };
+        self.journal(list, "remove", address, request);
+        changed
+    }
+
+    fn journal(
+        &self,
+        list: AccessList,
+        action: &'static str,
+        address: Address,
+        request: &JsonRpcRequest,
+    ) {
+        info!(
+            "🚧 Access list change: {} {} {} (by {:?})",
+            action,
+            address.to_checksum(None),
+            list,
+            request.api_key_id
+        );
+        self.audit_logger.log_event(&AccessListChange {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            event: "access_list_change",
+            requester: request.api_key_id.clone(),
+            request_id: request.request_id.clone(),
+            list,
+            action,
+            address: address.to_checksum(None),
+        });
+    }
+
+    /// Current effective lists
+    pub fn status(&self) -> AccessControlStatus {
+        AccessControlStatus {
+            allowlist_mode: self.allowlist_mode,
+            allowlist: self.allowlist.entries.read().unwrap().addresses(),
+            denylist: self.denylist.entries.read().unwrap().addresses(),
+        }
+    }
+
+    /// Reload list files that changed, returning whether any did
+    ///
+    /// A file that fails to load keeps its previous entries.
+    pub fn reload(&self) -> GatewayResult<bool> {
+        let allowlist = self.allowlist.reload();
+        let denylist = self.denylist.reload();
+        Ok(allowlist? | denylist?)
+    }
+
+    /// Check the list files for changes every `interval`, until aborted
+    pub fn spawn_reload(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                match self.reload() {
+                    Ok(true) => info!("🚧 Sender access lists reloaded"),
+                    Ok(false) => {}
+                    Err(e) => warn!("Keeping previous access lists: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Handle the admin_*AccessList* methods
+    pub fn handle_admin_request(&self, request: &JsonRpcRequest) -> GatewayResult<Value> {
+        match request.method.as_str() {
+            "admin_getAccessLists" => serde_json::to_value(self.status())
+                .map_err(|e| GatewayError::InternalError(e.to_string())),
+            "admin_addAccessListEntry" | "admin_removeAccessListEntry" => {
+                let (list, address) = match request.params.as_slice() {
+                    [Value::String(list), Value::String(address)] => {
+                        (list.parse::<AccessList>()?, parse_list_address(address)?)
+                    }
+                    _ => {
+                        return Err(GatewayError::InvalidParams(format!(
+                            "{} requires [list, address]",
+                            request.method
+                        )))
+                    }
+                };
+                let changed = if request.method == "admin_addAccessListEntry" {
+                    self.add(list, address, request)
+                } else {
+                    self.remove(list, address, request)
+                };
+                Ok(
+                    json!({ "list": list, "address": address.to_checksum(None), "changed": changed }),
+                )
+            }
+            _ => Err(GatewayError::UnsupportedMethod(request.method.clone())),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use alloy_primitives::{address, Address};
+    use serde_json::{json, Value};
+    use tempfile::tempdir;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
     use super::*;
+    use crate::{error::SENDER_DENIED_CODE, test_utils, PaymasterGateway};
 
     #[test]
     fn test_authorization_config_default() {
@@ -896,4 +1433,231 @@ mod tests {
         let result = checker.check_sender_reputation(&sender);
         assert!(result.passed);
     }
+
+    const ALLOWED: Address = address!("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    const DENIED: Address = address!("70997970c51812dc3a010c7d01b50e0d17dc79c8");
+    const UNLISTED: Address = address!("3c44cdddb6a900fa2b585dd299e03d12fa4293bc");
+
+    fn access_control(config: AccessControlConfig) -> SenderAccessControl {
+        SenderAccessControl::from_config(&config).unwrap()
+    }
+
+    fn assert_denied(result: Result<(), GatewayError>, reason: &str) {
+        let err = result.unwrap_err();
+        assert_eq!(err.code(), SENDER_DENIED_CODE);
+        assert!(err.to_string().contains(reason), "{}", err);
+    }
+
+    #[test]
+    fn test_addresses_parse_in_any_case() {
+        let lower = "0x70997970c51812dc3a010c7d01b50e0d17dc79c8";
+        for entry in [
+            lower,
+            "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+            "0X70997970C51812DC3A010C7D01B50E0D17DC79C8",
+            " 70997970c51812dc3a010c7d01b50e0d17dc79c8 ",
+        ] {
+            assert_eq!(parse_list_address(entry).unwrap(), DENIED, "{}", entry);
+        }
+        // Mixed case that is not the EIP-55 checksum is likely a typo
+        assert!(parse_list_address("0x70997970c51812DC3a010c7d01b50e0d17dc79c8").is_err());
+        assert!(parse_list_address("0x1234").is_err());
+    }
+
+    #[test]
+    fn test_denylist_hit() {
+        let control = access_control(AccessControlConfig {
+            denylist: vec!["0x70997970C51812dc3A010C7d01b50e0d17dc79C8".to_string()],
+            ..Default::default()
+        });
+
+        assert_denied(
+            control.check_sender("eth_sendUserOperation", &DENIED),
+            "denylisted",
+        );
+        // Reads about the sender's operations are refused too
+        assert_denied(
+            control.check_result(
+                "eth_getUserOperationByHash",
+                &json!({"userOperation": {"sender": format!("{:#x}", DENIED)}}),
+            ),
+            "denylisted",
+        );
+        assert_denied(
+            control.check_result(
+                "eth_getUserOperationReceipt",
+                &json!({"sender": DENIED.to_checksum(None)}),
+            ),
+            "denylisted",
+        );
+        control
+            .check_sender("eth_sendUserOperation", &UNLISTED)
+            .unwrap();
+        control
+            .check_result(
+                "eth_getUserOperationReceipt",
+                &json!({"sender": format!("{:#x}", UNLISTED)}),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_allowlist_mode() {
+        let control = access_control(AccessControlConfig {
+            allowlist_mode: true,
+            allowlist: vec![format!("{:#x}", ALLOWED)],
+            denylist: vec![format!("{:#x}", DENIED)],
+            ..Default::default()
+        });
+
+        control
+            .check_sender("eth_sendUserOperation", &ALLOWED)
+            .unwrap();
+        control
+            .check_sender("pm_sponsorUserOperation", &ALLOWED)
+            .unwrap();
+        assert_denied(
+            control.check_sender("eth_sendUserOperation", &UNLISTED),
+            "not allowlisted",
+        );
+        assert_denied(
+            control.check_sender("pm_sponsorUserOperation", &UNLISTED),
+            "not allowlisted",
+        );
+        // Only sending and sponsoring are gated by the allowlist
+        control
+            .check_sender("eth_estimateUserOperationGas", &UNLISTED)
+            .unwrap();
+        // The denylist applies to every method
+        assert_denied(
+            control.check_sender("eth_estimateUserOperationGas", &DENIED),
+            "denylisted",
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_file_hot_reload() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("denylist.txt");
+        std::fs::write(
+            &path,
+            format!("# compromised accounts\n{}\n", DENIED.to_checksum(None)),
+        )
+        .unwrap();
+        let control = Arc::new(access_control(AccessControlConfig {
+            denylist_file: Some(path.clone()),
+            ..Default::default()
+        }));
+        assert!(control.contains(AccessList::Denylist, &DENIED));
+        assert!(!control.contains(AccessList::Denylist, &UNLISTED));
+        assert!(!control.reload().unwrap());
+
+        // Make sure the modification time moves on coarse-grained filesystems
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        std::fs::write(&path, format!("{:#x} # moved\n", UNLISTED)).unwrap();
+        let reload = control.clone().spawn_reload(Duration::from_millis(20));
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !control.contains(AccessList::Denylist, &UNLISTED) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(!control.contains(AccessList::Denylist, &DENIED));
+
+        // A broken file keeps the previous entries
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        std::fs::write(&path, "not an address\n").unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(control.contains(AccessList::Denylist, &UNLISTED));
+        reload.abort();
+    }
+
+    async fn serve(control: SenderAccessControl) -> std::net::SocketAddr {
+        let app = PaymasterGateway::new(test_utils::admin_gateway_config(), None)
+            .with_access_control(Arc::new(control))
+            .app()
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    /// POST a JSON-RPC call and return the response body
+    async fn call(addr: std::net::SocketAddr, method: &str, params: Value) -> Value {
+        let body =
+            json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params}).to_string();
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nx-api-key: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            test_utils::ADMIN_KEY,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_denied_requests_and_admin_rpcs() {
+        let addr = serve(access_control(AccessControlConfig::default())).await;
+        let op = json!({"sender": format!("{:#x}", DENIED), "nonce": "0x0"});
+
+        // Not listed yet: the request reaches the router
+        let response = call(addr, "eth_sendUserOperation", json!([op, Address::ZERO])).await;
+        assert_ne!(response["error"]["code"], json!(SENDER_DENIED_CODE));
+
+        let response = call(
+            addr,
+            "admin_addAccessListEntry",
+            json!(["denylist", DENIED.to_checksum(None)]),
+        )
+        .await;
+        assert_eq!(response["result"]["changed"], json!(true));
+
+        for (method, params) in [
+            ("eth_sendUserOperation", json!([op, Address::ZERO])),
+            ("pm_sponsorUserOperation", json!([op, Address::ZERO])),
+            (
+                "eth_getUserOperationNonce",
+                json!([format!("{:#x}", DENIED), Address::ZERO]),
+            ),
+        ] {
+            let response = call(addr, method, params).await;
+            assert_eq!(
+                response["error"]["code"],
+                json!(SENDER_DENIED_CODE),
+                "{}",
+                method
+            );
+        }
+
+        let lists = call(addr, "admin_getAccessLists", json!([])).await;
+        assert_eq!(
+            lists["result"]["denylist"],
+            json!([DENIED.to_checksum(None)])
+        );
+
+        let response = call(
+            addr,
+            "admin_removeAccessListEntry",
+            json!(["denylist", format!("{:#x}", DENIED)]),
+        )
+        .await;
+        assert_eq!(response["result"]["changed"], json!(true));
+        let response = call(addr, "eth_sendUserOperation", json!([op, Address::ZERO])).await;
+        assert_ne!(response["error"]["code"], json!(SENDER_DENIED_CODE));
+
+        let response = call(
+            addr,
+            "admin_addAccessListEntry",
+            json!(["greylist", DENIED.to_checksum(None)]),
+        )
+        .await;
+        assert_eq!(response["error"]["code"], json!(-32602));
+    }
 }
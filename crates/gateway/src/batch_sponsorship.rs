@@ -0,0 +1,90 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::{GatewayError, GatewayResult};
+
+/// Batch sponsorship of pm_sponsorUserOperationBatch (`[gateway.batch_sponsorship]`)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct BatchSponsorshipConfig {
+    /// Most UserOperations one batch may carry
+    pub max_ops: usize,
+    /// Mode of batches whose options do not name one
+    pub mode: BatchSponsorshipMode,
+}
+
+impl Default for BatchSponsorshipConfig {
+    fn default() -> Self {
+        Self {
+            max_ops: 32,
+            mode: BatchSponsorshipMode::Atomic,
+        }
+    }
+}
+
+/// What a batch does with operations that fail parsing, the gateway checks
+/// or signing
+///
+/// The policy and deployment quotas are checked for the batch as a whole in
+/// either mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchSponsorshipMode {
+    /// The first failing operation rejects the batch, no sponsorship is returned
+    #[default]
+    Atomic,
+    /// Failing operations get an error in their place, the others are signed
+    BestEffort,
+}
+
+impl BatchSponsorshipConfig {
+    /// Operations of the `userOperations` param, at least one and at most `max_ops`
+    pub fn parse_operations<'a>(&self, value: &'a Value) -> GatewayResult<&'a [Value]> {
+        let user_operations = value.as_array().ok_or_else(|| {
+            GatewayError::InvalidRequest("userOperations must be an array".to_string())
+        })?;
+        if user_operations.is_empty() {
+            return Err(GatewayError::InvalidRequest(
+                "userOperations must not be empty".to_string(),
+            ));
+        }
+        if user_operations.len() > self.max_ops {
+            return Err(GatewayError::InvalidRequest(format!(
+                "Batch of {} UserOperations exceeds the limit of {}",
+                user_operations.len(),
+                self.max_ops
+            )));
+        }
+        Ok(user_operations)
+    }
+
+    /// Mode named by the `mode` option, `atomic` or `bestEffort`, or the configured one
+    pub fn parse_mode(&self, options: Option<&Value>) -> GatewayResult<BatchSponsorshipMode> {
+        match options.and_then(|options| options.get("mode")) {
+            None | Some(Value::Null) => Ok(self.mode),
+            Some(Value::String(mode)) if mode == "atomic" => Ok(BatchSponsorshipMode::Atomic),
+            Some(Value::String(mode)) if mode == "bestEffort" => {
+                Ok(BatchSponsorshipMode::BestEffort)
+            }
+            Some(_) => Err(GatewayError::InvalidRequest(
+                "mode must be \"atomic\" or \"bestEffort\"".to_string(),
+            )),
+        }
+    }
+}
+
+/// Entry of a pm_sponsorUserOperationBatch response: the sponsorship of the
+/// operation at `index`, or the JSON-RPC error it failed with
+pub fn batch_result(index: usize, result: GatewayResult<Value>) -> Value {
+    match result {
+        Ok(sponsorship) => json!({ "index": index, "result": sponsorship }),
+        Err(e) => json!({
+            "index": index,
+            "error": {
+                "code": e.code(),
+                "message": e.to_string(),
+                "data": e.rpc_data(),
+            },
+        }),
+    }
+}
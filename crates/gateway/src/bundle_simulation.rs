@@ -0,0 +1,941 @@
+use std::{collections::HashMap, sync::Arc};
+
+use alloy_primitives::{address, Address, Bytes, B256, U256};
+use alloy_sol_types::SolEvent;
+use async_trait::async_trait;
+use rundler_contracts::v0_7::IEntryPoint::{UserOperationEvent, UserOperationRevertReason};
+use rundler_provider::{
+    BlockId, BundleHandler, EvmProvider, GethDebugBuiltInTracerType, GethDebugTracerCallConfig,
+    GethDebugTracerCallFrame, GethDebugTracerType, GethDebugTracingCallOptions,
+    GethDebugTracingOptions, HandleOpsOut, TransactionRequest,
+};
+use rundler_types::{
+    chain::ChainSpec, GasFees, UserOperation, UserOperationVariant, UserOpsPerAggregator,
+};
+use rundler_utils::math;
+use serde::Serialize;
+use tracing::debug;
+
+use crate::error::{GatewayError, GatewayResult};
+
+/// Bundle gas the in-process builder stops adding operations at, as a share
+/// of the block gas limit
+pub const TARGET_BUNDLE_BLOCK_GAS_LIMIT_RATIO: f64 = 0.5;
+/// Bundle gas the in-process builder never exceeds, as a share of the block
+/// gas limit
+pub const MAX_BUNDLE_BLOCK_GAS_LIMIT_RATIO: f64 = 0.9;
+/// Overhead the builder adds to the summed gas limits of a bundle
+const BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT: u32 = 5;
+/// Beneficiary of simulated bundles; nothing is sent, so nothing is collected
+pub const SIMULATION_BENEFICIARY: Address = address!("0x000000000000000000000000000000000000dEaD");
+
+/// Computation gas caps a bundle is assembled under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BundleGasCaps {
+    /// Once reached, further operations are left for the next bundle
+    pub target: u128,
+    /// Operations that would take the bundle past it are left out
+    pub max: u128,
+}
+
+impl BundleGasCaps {
+    /// Caps of the in-process builder on `chain_spec`
+    pub fn builder(chain_spec: &ChainSpec) -> Self {
+        Self {
+            target: chain_spec.block_gas_limit_mult(TARGET_BUNDLE_BLOCK_GAS_LIMIT_RATIO),
+            max: chain_spec.block_gas_limit_mult(MAX_BUNDLE_BLOCK_GAS_LIMIT_RATIO),
+        }
+    }
+}
+
+/// What simulating a bundle did with one operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OpSimulationStatus {
+    /// Executed, and its call succeeded
+    Success,
+    /// Executed, but its call reverted; the operation still pays for its gas
+    Reverted,
+    /// Bundled without a trace of its execution
+    Included,
+    /// Rejected by the EntryPoint and dropped from the bundle, as the builder would
+    Failed,
+    /// Left out of the bundle before it was simulated
+    Skipped,
+}
+
+/// Outcome of one operation of a simulated bundle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpSimulation {
+    /// Hash of the operation
+    pub user_op_hash: B256,
+    /// What the bundle did with the operation
+    pub status: OpSimulationStatus,
+    /// Revert data, EntryPoint error or why the operation was skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Gas the EntryPoint charged the executed operation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_gas_used: Option<U256>,
+}
+
+impl OpSimulation {
+    /// An operation left out of the bundle for `reason`
+    pub fn skipped(user_op_hash: B256, reason: impl Into<String>) -> Self {
+        Self {
+            user_op_hash,
+            status: OpSimulationStatus::Skipped,
+            reason: Some(reason.into()),
+            actual_gas_used: None,
+        }
+    }
+
+    fn set(&mut self, status: OpSimulationStatus, reason: impl Into<String>) {
+        self.status = status;
+        self.reason = Some(reason.into());
+    }
+}
+
+/// Result of superRelay_simulateBundle
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleSimulation {
+    /// Every requested operation, in request order
+    pub ops: Vec<OpSimulation>,
+    /// Gas limit of the last handleOps transaction simulated, null when no
+    /// operation was bundled
+    pub gas_limit: Option<U256>,
+    /// Gas used by the final bundle, null when it was not traced or no
+    /// operation was left to bundle
+    pub gas_used: Option<U256>,
+}
+
+/// Backend of superRelay_simulateBundle
+#[async_trait]
+pub trait BundleSimulator: Send + Sync {
+    /// Run handleOps of `ops` on `entry_point` against `block`, latest when
+    /// unset, without submitting anything
+    async fn simulate_bundle(
+        &self,
+        entry_point: Address,
+        ops: Vec<UserOperationVariant>,
+        block: Option<BlockId>,
+    ) -> GatewayResult<BundleSimulation>;
+
+    /// Whether bundles on `entry_point` can be simulated at all
+    fn supports(&self, _entry_point: Address) -> bool {
+        true
+    }
+}
+
+/// [`BundleSimulator`] dispatching to the first simulator supporting an EntryPoint
+#[derive(Default)]
+pub struct BundleSimulators {
+    simulators: Vec<Arc<dyn BundleSimulator>>,
+}
+
+impl BundleSimulators {
+    /// Create a dispatcher without any simulator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Simulate bundles of the EntryPoints `simulator` supports with it
+    pub fn with_simulator(mut self, simulator: Arc<dyn BundleSimulator>) -> Self {
+        self.simulators.push(simulator);
+        self
+    }
+}
+
+#[async_trait]
+impl BundleSimulator for BundleSimulators {
+    async fn simulate_bundle(
+        &self,
+        entry_point: Address,
+        ops: Vec<UserOperationVariant>,
+        block: Option<BlockId>,
+    ) -> GatewayResult<BundleSimulation> {
+        match self.simulators.iter().find(|s| s.supports(entry_point)) {
+            Some(simulator) => simulator.simulate_bundle(entry_point, ops, block).await,
+            None => Err(unsupported(entry_point)),
+        }
+    }
+
+    fn supports(&self, entry_point: Address) -> bool {
+        self.simulators.iter().any(|s| s.supports(entry_point))
+    }
+}
+
+fn unsupported(entry_point: Address) -> GatewayError {
+    GatewayError::InvalidParams(format!(
+        "Bundle simulation is not available for entry point {:#x}",
+        entry_point
+    ))
+}
+
+/// [`BundleSimulator`] of one EntryPoint, assembling bundles like the builder
+///
+/// Operations are capped by the builder's gas caps and grouped by aggregator,
+/// then handleOps is traced with the call tracer, or called when the node
+/// does not trace. An operation the EntryPoint rejects is dropped and the
+/// rest simulated again, as the builder does before sending.
+pub struct EntryPointBundleSimulator<P, E> {
+    entry_point: Address,
+    chain_spec: ChainSpec,
+    provider: P,
+    handler: E,
+    caps: BundleGasCaps,
+    beneficiary: Address,
+}
+
+impl<P, E> EntryPointBundleSimulator<P, E> {
+    /// Simulate bundles sent to `entry_point` through `handler`, capped like
+    /// the in-process builder
+    pub fn new(chain_spec: ChainSpec, entry_point: Address, provider: P, handler: E) -> Self {
+        Self {
+            entry_point,
+            caps: BundleGasCaps::builder(&chain_spec),
+            chain_spec,
+            provider,
+            handler,
+            beneficiary: SIMULATION_BENEFICIARY,
+        }
+    }
+
+    /// Cap bundles by `caps` instead of the builder's
+    pub fn with_gas_caps(mut self, caps: BundleGasCaps) -> Self {
+        self.caps = caps;
+        self
+    }
+
+    /// Name `beneficiary` as the receiver of the bundle's fees
+    pub fn with_beneficiary(mut self, beneficiary: Address) -> Self {
+        self.beneficiary = beneficiary;
+        self
+    }
+
+    /// Gas of a bundle of `ops`: the shared transaction gas, the fixed gas of
+    /// each aggregator and the gas limits of the operations
+    fn bundle_gas<'a>(
+        &self,
+        ops: impl Iterator<Item = &'a UserOperationVariant>,
+        include_da_gas: bool,
+    ) -> u128 {
+        let mut gas = rundler_types::bundle_shared_gas(&self.chain_spec);
+        let mut aggregators = Vec::new();
+        for op in ops {
+            if let Some(aggregator) = op.aggregator() {
+                if !aggregators.contains(&aggregator) {
+                    aggregators.push(aggregator);
+                    gas += self
+                        .chain_spec
+                        .get_signature_aggregator(&aggregator)
+                        .map_or(0, |agg| agg.costs().execution_fixed_gas);
+                }
+            }
+            gas += if include_da_gas {
+                op.bundle_gas_limit(&self.chain_spec, None)
+            } else {
+                op.bundle_computation_gas_limit(&self.chain_spec, None)
+            };
+        }
+        gas
+    }
+
+    /// Indexes of the operations the builder would bundle, in order; the
+    /// others are marked skipped
+    fn select(
+        &self,
+        ops: &[UserOperationVariant],
+        results: &mut [OpSimulation],
+        version: rundler_types::EntryPointVersion,
+    ) -> Vec<usize> {
+        let mut selected: Vec<usize> = Vec::new();
+        let mut passed_target = false;
+        for (index, op) in ops.iter().enumerate() {
+            let skip = if op.uo_type() != version {
+                Some(format!("not a {:?} operation", version))
+            } else if op
+                .aggregator()
+                .is_some_and(|agg| self.chain_spec.get_signature_aggregator(&agg).is_none())
+            {
+                Some("unsupported aggregator".to_string())
+            } else if passed_target {
+                Some("bundle reached its target gas".to_string())
+            } else {
+                let gas = self.bundle_gas(selected.iter().chain([&index]).map(|&i| &ops[i]), false);
+                if gas > self.caps.max {
+                    Some(format!(
+                        "bundle would exceed its max gas of {}",
+                        self.caps.max
+                    ))
+                } else {
+                    passed_target = gas >= self.caps.target;
+                    None
+                }
+            };
+            match skip {
+                Some(reason) => results[index] = OpSimulation::skipped(op.hash(), reason),
+                None => selected.push(index),
+            }
+        }
+        selected
+    }
+}
+
+impl<P, E> EntryPointBundleSimulator<P, E>
+where
+    P: EvmProvider,
+    E: BundleHandler,
+    E::UO: From<UserOperationVariant>,
+{
+    /// Operations of `bundle` grouped by aggregator in order of first use,
+    /// with the flattened order the EntryPoint indexes failures by
+    async fn group(
+        &self,
+        ops: &[UserOperationVariant],
+        bundle: &[usize],
+        results: &mut [OpSimulation],
+    ) -> Option<(Vec<UserOpsPerAggregator<E::UO>>, Vec<usize>)> {
+        let mut groups: Vec<(Address, Vec<usize>)> = Vec::new();
+        for &index in bundle {
+            let aggregator = ops[index].aggregator().unwrap_or(Address::ZERO);
+            match groups.iter_mut().find(|(agg, _)| *agg == aggregator) {
+                Some((_, indexes)) => indexes.push(index),
+                None => groups.push((aggregator, vec![index])),
+            }
+        }
+
+        let mut ops_per_aggregator = Vec::new();
+        let mut order = Vec::new();
+        for (aggregator, indexes) in groups {
+            let signature = match self.chain_spec.get_signature_aggregator(&aggregator) {
+                Some(agg) if !aggregator.is_zero() => {
+                    let uos = indexes
+                        .iter()
+                        .map(|&i| ops[i].clone().with_original_signature())
+                        .collect();
+                    match agg.aggregate_signatures(uos).await {
+                        Ok(signature) => signature,
+                        Err(e) => {
+                            for &i in &indexes {
+                                results[i].set(
+                                    OpSimulationStatus::Failed,
+                                    format!("signature aggregation failed: {}", e),
+                                );
+                            }
+                            return None;
+                        }
+                    }
+                }
+                _ => Bytes::new(),
+            };
+            order.extend(&indexes);
+            ops_per_aggregator.push(UserOpsPerAggregator {
+                user_ops: indexes.iter().map(|&i| ops[i].clone().into()).collect(),
+                aggregator,
+                signature,
+            });
+        }
+        Some((ops_per_aggregator, order))
+    }
+
+    /// handleOps outcome of `tx`, and its call frame when the node traced it
+    async fn execute(
+        &self,
+        tx: TransactionRequest,
+        block: Option<BlockId>,
+    ) -> GatewayResult<(HandleOpsOut, Option<GethDebugTracerCallFrame>)> {
+        let options = GethDebugTracingCallOptions {
+            tracing_options: GethDebugTracingOptions::new_tracer(
+                GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::CallTracer),
+            )
+            .with_call_config(GethDebugTracerCallConfig::default().with_log()),
+            state_overrides: None,
+            block_overrides: None,
+        };
+        match self
+            .provider
+            .debug_trace_call(tx.clone(), block, options)
+            .await
+        {
+            Ok(trace) => {
+                let frame = trace.try_into_call_frame().map_err(|e| {
+                    GatewayError::RundlerError(format!("Unexpected bundle trace: {}", e))
+                })?;
+                let out = match &frame.error {
+                    None => HandleOpsOut::Success,
+                    Some(error) => E::decode_handle_ops_revert(error, &frame.output)
+                        .unwrap_or_else(|| {
+                            HandleOpsOut::Revert(frame.output.clone().unwrap_or_default())
+                        }),
+                };
+                Ok((out, Some(frame)))
+            }
+            Err(e) => {
+                debug!("Bundle not traced, falling back to eth_call: {}", e);
+                match self.provider.call(tx, block, None).await {
+                    Ok(_) => Ok((HandleOpsOut::Success, None)),
+                    Err(e) => E::decode_handle_ops_revert(&e.to_string(), &e.as_revert_data())
+                        .map(|out| (out, None))
+                        .ok_or_else(|| {
+                            GatewayError::RundlerError(format!("Bundle simulation failed: {}", e))
+                        }),
+                }
+            }
+        }
+    }
+
+    /// Outcome of each operation of the successful `bundle` from the
+    /// EntryPoint's events in `frame`
+    fn record_execution(
+        &self,
+        ops: &[UserOperationVariant],
+        bundle: &[usize],
+        frame: Option<&GethDebugTracerCallFrame>,
+        results: &mut [OpSimulation],
+    ) {
+        let Some(frame) = frame else {
+            return;
+        };
+        let mut events = HashMap::new();
+        let mut revert_reasons = HashMap::new();
+        let mut frames = vec![frame];
+        while let Some(frame) = frames.pop() {
+            frames.extend(&frame.calls);
+            for log in &frame.logs {
+                let (Some(topics), Some(data)) = (&log.topics, &log.data) else {
+                    continue;
+                };
+                if log.address != Some(self.entry_point) {
+                    continue;
+                }
+                let signature = topics.first().copied().unwrap_or_default();
+                if signature == UserOperationEvent::SIGNATURE_HASH {
+                    if let Ok(event) =
+                        UserOperationEvent::decode_raw_log(topics.iter().copied(), data)
+                    {
+                        events.insert(event.userOpHash, event);
+                    }
+                } else if signature == UserOperationRevertReason::SIGNATURE_HASH {
+                    if let Ok(event) =
+                        UserOperationRevertReason::decode_raw_log(topics.iter().copied(), data)
+                    {
+                        revert_reasons.insert(event.userOpHash, event.revertReason);
+                    }
+                }
+            }
+        }
+
+        for &index in bundle {
+            let result = &mut results[index];
+            let hash = ops[index].hash();
+            match events.get(&hash) {
+                Some(event) if event.success => {
+                    result.status = OpSimulationStatus::Success;
+                    result.actual_gas_used = Some(event.actualGasUsed);
+                }
+                Some(event) => {
+                    result.status = OpSimulationStatus::Reverted;
+                    result.reason = revert_reasons.get(&hash).map(ToString::to_string);
+                    result.actual_gas_used = Some(event.actualGasUsed);
+                }
+                None => result.reason = Some("no UserOperationEvent in the trace".to_string()),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<P, E> BundleSimulator for EntryPointBundleSimulator<P, E>
+where
+    P: EvmProvider,
+    E: BundleHandler,
+    E::UO: From<UserOperationVariant>,
+{
+    async fn simulate_bundle(
+        &self,
+        entry_point: Address,
+        ops: Vec<UserOperationVariant>,
+        block: Option<BlockId>,
+    ) -> GatewayResult<BundleSimulation> {
+        if entry_point != self.entry_point {
+            return Err(unsupported(entry_point));
+        }
+
+        let mut simulation = BundleSimulation {
+            ops: ops
+                .iter()
+                .map(|op| OpSimulation {
+                    user_op_hash: op.hash(),
+                    status: OpSimulationStatus::Included,
+                    reason: None,
+                    actual_gas_used: None,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let results = &mut simulation.ops;
+        let mut bundle = self.select(&ops, results, E::UO::entry_point_version());
+
+        while !bundle.is_empty() {
+            let Some((ops_per_aggregator, order)) = self.group(&ops, &bundle, results).await else {
+                bundle.retain(|&i| results[i].status != OpSimulationStatus::Failed);
+                continue;
+            };
+
+            let gas = math::increase_by_percent(
+                self.bundle_gas(
+                    bundle.iter().map(|&i| &ops[i]),
+                    self.chain_spec.include_da_gas_in_gas_limit,
+                ),
+                BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT,
+            );
+            let gas_limit: u64 = gas.try_into().map_err(|_| {
+                GatewayError::InvalidParams(format!("Bundle gas limit {} exceeds u64", gas))
+            })?;
+            simulation.gas_limit = Some(U256::from(gas_limit));
+
+            // Zero fees: calls against state skip the base fee check and no
+            // balance is needed for the bundle's gas
+            let tx = self.handler.get_send_bundle_transaction(
+                ops_per_aggregator,
+                self.beneficiary,
+                gas_limit,
+                GasFees::default(),
+                None,
+            );
+            let (out, frame) = self.execute(tx, block).await?;
+            debug!("Simulated bundle of {} ops: {:?}", order.len(), out);
+
+            match out {
+                HandleOpsOut::Success => {
+                    simulation.gas_used = frame.as_ref().map(|frame| frame.gas_used);
+                    self.record_execution(&ops, &bundle, frame.as_ref(), results);
+                    break;
+                }
+                HandleOpsOut::FailedOp(index, reason) => {
+                    let Some(&failed) = order.get(index) else {
+                        return Err(GatewayError::RundlerError(format!(
+                            "EntryPoint rejected operation {} of a {} operation bundle: {}",
+                            index,
+                            order.len(),
+                            reason
+                        )));
+                    };
+                    results[failed].set(OpSimulationStatus::Failed, reason);
+                    bundle.retain(|&i| i != failed);
+                }
+                HandleOpsOut::SignatureValidationFailed(aggregator) => {
+                    bundle.retain(|&i| {
+                        if ops[i].aggregator() != Some(aggregator) {
+                            return true;
+                        }
+                        results[i].set(
+                            OpSimulationStatus::Failed,
+                            format!(
+                                "signature validation failed for aggregator {:#x}",
+                                aggregator
+                            ),
+                        );
+                        false
+                    });
+                }
+                // The failing operation cannot be told apart, so the whole
+                // bundle is reported as failed
+                HandleOpsOut::PostOpRevert => {
+                    fail_all(&mut bundle, results, "postOp reverted".to_string())
+                }
+                HandleOpsOut::Revert(data) => fail_all(
+                    &mut bundle,
+                    results,
+                    format!("handleOps reverted: {}", data),
+                ),
+            }
+        }
+        Ok(simulation)
+    }
+
+    fn supports(&self, entry_point: Address) -> bool {
+        entry_point == self.entry_point
+    }
+}
+
+/// Mark every operation left in `bundle` failed for `reason`
+fn fail_all(bundle: &mut Vec<usize>, results: &mut [OpSimulation], reason: String) {
+    for i in bundle.drain(..) {
+        results[i].set(OpSimulationStatus::Failed, reason.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy_primitives::{Address, Bytes, LogData, B256, U256};
+    use alloy_sol_types::{SolError, SolEvent};
+    use rundler_contracts::v0_7::IEntryPoint::{
+        FailedOp, UserOperationEvent, UserOperationRevertReason,
+    };
+    use rundler_provider::{
+        new_alloy_provider, AlloyEntryPointV0_7, BundleHandler, GethDebugTracerCallFrame,
+        GethTrace, MockEvmProvider, ProviderError, ZeroDAGasOracle,
+    };
+    use rundler_types::{chain::ChainSpec, v0_7, UserOperation, UserOperationVariant};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{gateway::JsonRpcRequest, middleware::ApiKeyScope, test_utils, GatewayRouter};
+
+    const PASSING: Address = Address::repeat_byte(0x11);
+    const REVERTING: Address = Address::repeat_byte(0x22);
+
+    fn entry_point() -> Address {
+        ChainSpec::default().entry_point_address_v0_7
+    }
+
+    fn user_op(sender: Address) -> UserOperationVariant {
+        UserOperationVariant::V0_7(
+            v0_7::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_7::UserOperationRequiredFields {
+                    sender,
+                    nonce: U256::ZERO,
+                    call_data: Bytes::new(),
+                    call_gas_limit: 100_000,
+                    verification_gas_limit: 100_000,
+                    pre_verification_gas: 50_000,
+                    max_fee_per_gas: 0,
+                    max_priority_fee_per_gas: 0,
+                    signature: Bytes::new(),
+                },
+            )
+            .build(),
+        )
+    }
+
+    /// Simulator encoding handleOps with the real v0.7 EntryPoint provider; the
+    /// node answering the calls is `provider`
+    fn simulator(
+        provider: MockEvmProvider,
+    ) -> EntryPointBundleSimulator<MockEvmProvider, impl BundleHandler<UO = v0_7::UserOperation>>
+    {
+        let chain_spec = ChainSpec::default();
+        let handler = AlloyEntryPointV0_7::new(
+            chain_spec.clone(),
+            1_000_000,
+            1_000_000,
+            1_000_000,
+            1_000_000,
+            new_alloy_provider("http://127.0.0.1:1", 1).unwrap(),
+            ZeroDAGasOracle,
+        );
+        EntryPointBundleSimulator::new(chain_spec, entry_point(), provider, handler)
+    }
+
+    /// UserOperationEvent of `op`, as a call tracer log of the EntryPoint
+    fn op_event(op: &UserOperationVariant, success: bool, gas_used: u64) -> Value {
+        let event = UserOperationEvent {
+            userOpHash: op.hash(),
+            sender: op.sender(),
+            paymaster: Address::ZERO,
+            nonce: U256::ZERO,
+            success,
+            actualGasCost: U256::ZERO,
+            actualGasUsed: U256::from(gas_used),
+        };
+        log(event.encode_log_data())
+    }
+
+    fn revert_reason(op: &UserOperationVariant, reason: &[u8]) -> Value {
+        let event = UserOperationRevertReason {
+            userOpHash: op.hash(),
+            sender: op.sender(),
+            nonce: U256::ZERO,
+            revertReason: Bytes::copy_from_slice(reason),
+        };
+        log(event.encode_log_data())
+    }
+
+    fn log(data: LogData) -> Value {
+        json!({
+            "address": entry_point(),
+            "topics": data.topics(),
+            "data": data.data,
+        })
+    }
+
+    /// Top level handleOps frame with `logs`, reverted with `revert` when set
+    fn frame(gas_used: u64, logs: Vec<Value>, revert: Option<Bytes>) -> GethTrace {
+        let mut frame = json!({
+            "type": "CALL",
+            "from": Address::ZERO,
+            "to": entry_point(),
+            "gas": "0x1000000",
+            "gasUsed": format!("{:#x}", gas_used),
+            "input": "0x",
+            "logs": logs,
+        });
+        if let Some(revert) = revert {
+            frame["error"] = json!("execution reverted");
+            frame["output"] = json!(revert);
+        }
+        GethTrace::CallTracer(serde_json::from_value::<GethDebugTracerCallFrame>(frame).unwrap())
+    }
+
+    fn failed_op(index: u64, reason: &str) -> Bytes {
+        FailedOp {
+            opIndex: U256::from(index),
+            reason: reason.to_string(),
+        }
+        .abi_encode()
+        .into()
+    }
+
+    #[tokio::test]
+    async fn test_passing_and_reverting_op_breakdown() {
+        let passing = user_op(PASSING);
+        let reverting = user_op(REVERTING);
+        let trace = frame(
+            180_000,
+            vec![
+                op_event(&passing, true, 70_000),
+                revert_reason(&reverting, &[0xde, 0xad]),
+                op_event(&reverting, false, 90_000),
+            ],
+            None,
+        );
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_debug_trace_call()
+            .times(1)
+            .returning(move |_, _, _| Ok(trace.clone()));
+
+        let simulation = simulator(provider)
+            .simulate_bundle(
+                entry_point(),
+                vec![passing.clone(), reverting.clone()],
+                None,
+            )
+            .await
+            .unwrap();
+
+        let [passed, reverted] = simulation.ops.as_slice() else {
+            panic!("{:?}", simulation);
+        };
+        assert_eq!(passed.user_op_hash, passing.hash());
+        assert_eq!(passed.status, OpSimulationStatus::Success);
+        assert_eq!(passed.actual_gas_used, Some(U256::from(70_000)));
+        assert_eq!(reverted.user_op_hash, reverting.hash());
+        assert_eq!(reverted.status, OpSimulationStatus::Reverted);
+        assert_eq!(reverted.reason.as_deref(), Some("0xdead"));
+        assert_eq!(reverted.actual_gas_used, Some(U256::from(90_000)));
+        assert_eq!(simulation.gas_used, Some(U256::from(180_000)));
+        assert!(simulation.gas_limit.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_op_dropped_and_rest_simulated_again() {
+        let passing = user_op(PASSING);
+        let rejected = user_op(REVERTING);
+        let success = frame(80_000, vec![op_event(&passing, true, 70_000)], None);
+        let mut calls = 0;
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_debug_trace_call()
+            .times(2)
+            .returning(move |_, _, _| {
+                calls += 1;
+                Ok(match calls {
+                    1 => frame(0, vec![], Some(failed_op(1, "AA23 reverted"))),
+                    _ => success.clone(),
+                })
+            });
+
+        let simulation = simulator(provider)
+            .simulate_bundle(entry_point(), vec![passing, rejected], None)
+            .await
+            .unwrap();
+
+        assert_eq!(simulation.ops[0].status, OpSimulationStatus::Success);
+        assert_eq!(simulation.ops[1].status, OpSimulationStatus::Failed);
+        assert_eq!(simulation.ops[1].reason.as_deref(), Some("AA23 reverted"));
+        assert_eq!(simulation.gas_used, Some(U256::from(80_000)));
+    }
+
+    #[tokio::test]
+    async fn test_builder_gas_caps_skip_ops() {
+        let ops = vec![user_op(PASSING), user_op(REVERTING)];
+        let one_op_gas = {
+            let chain_spec = ChainSpec::default();
+            rundler_types::bundle_shared_gas(&chain_spec)
+                + ops[0].bundle_computation_gas_limit(&chain_spec, None)
+        };
+
+        for (caps, reason) in [
+            (
+                BundleGasCaps {
+                    target: u128::MAX,
+                    max: one_op_gas,
+                },
+                "max gas",
+            ),
+            (
+                BundleGasCaps {
+                    target: one_op_gas,
+                    max: u128::MAX,
+                },
+                "target gas",
+            ),
+        ] {
+            let mut provider = MockEvmProvider::new();
+            provider
+                .expect_debug_trace_call()
+                .times(1)
+                .returning(|_, _, _| Ok(frame(60_000, vec![], None)));
+
+            let simulation = simulator(provider)
+                .with_gas_caps(caps)
+                .simulate_bundle(entry_point(), ops.clone(), None)
+                .await
+                .unwrap();
+
+            assert_ne!(simulation.ops[0].status, OpSimulationStatus::Skipped);
+            assert_eq!(simulation.ops[1].status, OpSimulationStatus::Skipped);
+            let skipped = simulation.ops[1].reason.as_deref().unwrap();
+            assert!(skipped.contains(reason), "{}", skipped);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_untraced_bundle_falls_back_to_call() {
+        let mut provider = MockEvmProvider::new();
+        provider.expect_debug_trace_call().returning(|_, _, _| {
+            Err(ProviderError::Other(anyhow::anyhow!(
+                "the method debug_traceCall does not exist"
+            )))
+        });
+        provider
+            .expect_call()
+            .times(1)
+            .returning(|_, _, _| Ok(Bytes::new()));
+
+        let simulation = simulator(provider)
+            .simulate_bundle(entry_point(), vec![user_op(PASSING)], None)
+            .await
+            .unwrap();
+
+        assert_eq!(simulation.ops[0].status, OpSimulationStatus::Included);
+        assert!(simulation.gas_limit.is_some());
+        assert_eq!(simulation.gas_used, None);
+    }
+
+    #[tokio::test]
+    async fn test_wrong_version_op_skipped() {
+        let v0_6_op = UserOperationVariant::V0_6(
+            rundler_types::v0_6::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                rundler_types::v0_6::UserOperationRequiredFields {
+                    sender: PASSING,
+                    ..Default::default()
+                },
+            )
+            .build(),
+        );
+        let mut provider = MockEvmProvider::new();
+        provider.expect_debug_trace_call().never();
+
+        let simulation = simulator(provider)
+            .simulate_bundle(entry_point(), vec![v0_6_op], None)
+            .await
+            .unwrap();
+
+        assert_eq!(simulation.ops[0].status, OpSimulationStatus::Skipped);
+        assert_eq!(simulation.gas_limit, None);
+    }
+
+    fn request(params: Vec<Value>) -> JsonRpcRequest {
+        test_utils::request("superRelay_simulateBundle", params)
+    }
+
+    fn raw_op(sender: Address) -> Value {
+        json!({
+            "sender": sender,
+            "nonce": "0x0",
+            "callData": "0x",
+            "callGasLimit": "0x186a0",
+            "verificationGasLimit": "0x186a0",
+            "preVerificationGas": "0xc350",
+            "maxFeePerGas": "0x0",
+            "maxPriorityFeePerGas": "0x0",
+            "signature": "0x",
+        })
+    }
+
+    #[tokio::test]
+    async fn test_simulate_bundle_method() {
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_debug_trace_call()
+            .times(1)
+            .returning(|_, block, _| {
+                assert_eq!(block, Some(rundler_provider::BlockId::number(7)));
+                Ok(frame(60_000, vec![], None))
+            });
+        let router = GatewayRouter::new().with_bundle_simulator(Arc::new(simulator(provider)));
+
+        let result = router
+            .route_to_super_relay(&request(vec![
+                json!(entry_point()),
+                json!([raw_op(PASSING)]),
+                json!("0x7"),
+            ]))
+            .await
+            .unwrap();
+        assert_eq!(result["gasUsed"], json!("0xea60"));
+        assert_eq!(result["ops"].as_array().unwrap().len(), 1);
+        assert!(result["ops"][0]["userOpHash"].is_string());
+
+        // Pooled operations are looked up by hash, which needs the pool
+        let err = router
+            .route_to_super_relay(&request(vec![
+                json!(entry_point()),
+                json!([B256::repeat_byte(0x33)]),
+            ]))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Pool not available"), "{}", err);
+
+        assert_eq!(
+            ApiKeyScope::required_for("superRelay_simulateBundle"),
+            ApiKeyScope::Admin
+        );
+    }
+
+    #[tokio::test]
+    async fn test_simulate_bundle_needs_simulator_for_entry_point() {
+        let err = GatewayRouter::new()
+            .route_to_super_relay(&request(vec![
+                json!(entry_point()),
+                json!([raw_op(PASSING)]),
+            ]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidRequest(_)), "{:?}", err);
+
+        let router =
+            GatewayRouter::new().with_bundle_simulator(Arc::new(simulator(MockEvmProvider::new())));
+        for params in [
+            vec![json!(entry_point())],
+            vec![json!(entry_point()), json!([])],
+            vec![json!(entry_point()), json!(["0x1234"])],
+        ] {
+            let err = router
+                .route_to_super_relay(&request(params))
+                .await
+                .unwrap_err();
+            assert!(matches!(err, GatewayError::InvalidParams(_)), "{:?}", err);
+        }
+    }
+}
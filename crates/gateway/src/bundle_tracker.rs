@@ -0,0 +1,720 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::{Address, B256};
+use rundler_builder::{BuilderEvent, BuilderEventKind};
+use rundler_paymaster_relay::{MinedUserOperation, SponsorshipRecords, StatusUpdate};
+use rundler_utils::emit::{self, WithEntryPoint};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::broadcast, task::JoinHandle};
+
+/// Bundle tracking configuration (`[gateway.bundle_tracking]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BundleTrackerConfig {
+    /// Bundle attempts kept before the oldest is forgotten
+    pub max_bundles: usize,
+    /// UserOperations whose inclusion status is kept before the oldest is forgotten
+    pub max_ops: usize,
+    /// Sliding window of rundler_getBundleStats, in seconds
+    pub window_seconds: u64,
+}
+
+impl Default for BundleTrackerConfig {
+    fn default() -> Self {
+        Self {
+            max_bundles: 256,
+            max_ops: 4096,
+            window_seconds: 3600,
+        }
+    }
+}
+
+/// Where a bundle transaction stands
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum BundleStatus {
+    /// Sent and not yet mined
+    Submitted,
+    /// Mined in `block_number`
+    #[serde(rename_all = "camelCase")]
+    Landed {
+        /// Block containing the transaction
+        block_number: u64,
+    },
+    /// Abandoned by the builder
+    Dropped {
+        /// Why the transaction was abandoned
+        reason: String,
+    },
+}
+
+/// One bundle transaction sent by a builder, including its fee bumps
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleAttempt {
+    /// EntryPoint the bundle was sent to
+    pub entry_point: Address,
+    /// Tag of the builder that sent it
+    pub builder_tag: String,
+    /// Transaction nonce, shared by fee bumped replacements
+    pub nonce: u64,
+    /// Hash of the latest transaction sent for the bundle
+    pub tx_hash: B256,
+    /// Gas limit of the bundle transaction; the builder reports no gas used
+    pub gas_limit: Option<u64>,
+    /// Number of fee bumped replacements
+    pub fee_increase_count: u64,
+    /// Hashes of the bundled UserOperations
+    pub user_op_hashes: Vec<B256>,
+    /// Unix time the bundle was first sent
+    pub submitted_at: u64,
+    /// Current status
+    #[serde(flatten)]
+    pub status: BundleStatus,
+}
+
+/// Bundle counts over the sliding window, served by rundler_getBundleStats
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleStats {
+    /// Length of the window, in seconds
+    pub window_seconds: u64,
+    /// Bundles sent within the window
+    pub submitted: u64,
+    /// Of those, bundles mined
+    pub landed: u64,
+    /// Of those, bundles abandoned
+    pub dropped: u64,
+    /// Of those, bundles still waiting to be mined
+    pub pending: u64,
+    /// Operations skipped or rejected by a builder within the window
+    pub skipped_ops: u64,
+}
+
+/// What the builders did with a UserOperation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum OpInclusion {
+    /// Put in a bundle that has not been mined yet
+    #[serde(rename_all = "camelCase")]
+    Bundled {
+        /// Latest bundle transaction carrying the operation
+        tx_hash: B256,
+    },
+    /// Put in a bundle that was mined
+    #[serde(rename_all = "camelCase")]
+    Included {
+        /// Bundle transaction carrying the operation
+        tx_hash: B256,
+        /// Block containing the transaction
+        block_number: u64,
+    },
+    /// Put in a bundle the builder abandoned; the operation stays in the pool
+    #[serde(rename_all = "camelCase")]
+    BundleDropped {
+        /// Abandoned bundle transaction
+        tx_hash: B256,
+        /// Why the bundle was abandoned
+        reason: String,
+    },
+    /// Left out of a bundle but kept in the pool
+    Skipped {
+        /// Why the builder left the operation out
+        reason: String,
+    },
+    /// Removed from the pool by the builder
+    Rejected {
+        /// Why the builder rejected the operation
+        reason: String,
+    },
+}
+
+impl OpInclusion {
+    /// Serialized `status` of this outcome
+    pub fn status(&self) -> &'static str {
+        match self {
+            OpInclusion::Bundled { .. } => "bundled",
+            OpInclusion::Included { .. } => "included",
+            OpInclusion::BundleDropped { .. } => "bundleDropped",
+            OpInclusion::Skipped { .. } => "skipped",
+            OpInclusion::Rejected { .. } => "rejected",
+        }
+    }
+}
+
+/// Inclusion status of one UserOperation, served by superRelay_getOpInclusionStatus
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpInclusionStatus {
+    /// Whether the operation was put in any bundle
+    pub attempted: bool,
+    /// Bundle transactions that carried the operation, oldest first
+    pub bundles: Vec<B256>,
+    /// Latest thing a builder did with the operation
+    #[serde(flatten)]
+    pub latest: OpInclusion,
+    /// Unix time of the latest builder event about the operation
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Default)]
+struct TrackerState {
+    bundles: VecDeque<BundleAttempt>,
+    ops: HashMap<B256, OpInclusionStatus>,
+    op_order: VecDeque<B256>,
+    /// Unix times operations were skipped or rejected, oldest first
+    skips: VecDeque<u64>,
+}
+
+/// Recent bundle attempts and per-operation inclusion, fed by builder events
+///
+/// Memory is bounded: the oldest bundles and operations are forgotten once
+/// the configured limits are reached.
+#[derive(Debug)]
+pub struct BundleTracker {
+    config: BundleTrackerConfig,
+    state: Mutex<TrackerState>,
+    sponsorship_records: Option<Arc<SponsorshipRecords>>,
+}
+
+impl Default for BundleTracker {
+    fn default() -> Self {
+        Self::new(BundleTrackerConfig::default())
+    }
+}
+
+impl BundleTracker {
+    /// Create an empty tracker
+    pub fn new(config: BundleTrackerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(TrackerState::default()),
+            sponsorship_records: None,
+        }
+    }
+
+    /// Mark sponsorships mined when their bundle lands and dropped when the
+    /// builder rejects their operation
+    pub fn with_sponsorship_records(mut self, records: Arc<SponsorshipRecords>) -> Self {
+        self.sponsorship_records = Some(records);
+        self
+    }
+
+    fn update_sponsorship(&self, hash: B256, update: StatusUpdate) {
+        if let Some(records) = &self.sponsorship_records {
+            records.update_status(hash, update);
+        }
+    }
+
+    /// Record every builder event broadcast on `events` until the channel closes
+    pub fn subscribe(
+        self: &Arc<Self>,
+        events: broadcast::Receiver<WithEntryPoint<BuilderEvent>>,
+    ) -> JoinHandle<()> {
+        let tracker = self.clone();
+        tokio::spawn(emit::receive_events(
+            "bundle tracker",
+            events,
+            move |event| tracker.record(&event),
+        ))
+    }
+
+    /// Apply one builder event
+    pub fn record(&self, event: &WithEntryPoint<BuilderEvent>) {
+        self.record_at(event, unix_now());
+    }
+
+    fn record_at(&self, event: &WithEntryPoint<BuilderEvent>, now: u64) {
+        let mut state = self.state.lock().unwrap();
+        let tag = &event.event.tag;
+
+        match &event.event.kind {
+            BuilderEventKind::FormedBundle {
+                tx_details: Some(tx_details),
+                nonce,
+                fee_increase_count,
+                ..
+            } => {
+                let user_op_hashes: Vec<B256> =
+                    tx_details.ops.iter().map(|(_, hash)| *hash).collect();
+                match state.pending_bundle(event.entry_point, tag, *nonce) {
+                    // A fee bump replaces the pending transaction of the same nonce
+                    Some(attempt) => {
+                        attempt.tx_hash = tx_details.tx_hash;
+                        attempt.gas_limit = tx_details.tx.gas;
+                        attempt.fee_increase_count = *fee_increase_count;
+                        attempt.user_op_hashes = user_op_hashes.clone();
+                    }
+                    None => {
+                        if state.bundles.len() >= self.config.max_bundles.max(1) {
+                            state.bundles.pop_front();
+                        }
+                        state.bundles.push_back(BundleAttempt {
+                            entry_point: event.entry_point,
+                            builder_tag: tag.clone(),
+                            nonce: *nonce,
+                            tx_hash: tx_details.tx_hash,
+                            gas_limit: tx_details.tx.gas,
+                            fee_increase_count: *fee_increase_count,
+                            user_op_hashes: user_op_hashes.clone(),
+                            submitted_at: now,
+                            status: BundleStatus::Submitted,
+                        });
+                    }
+                }
+                for hash in user_op_hashes {
+                    let inclusion = OpInclusion::Bundled {
+                        tx_hash: tx_details.tx_hash,
+                    };
+                    self.update_op(&mut state, hash, inclusion, Some(tx_details.tx_hash), now);
+                }
+            }
+            BuilderEventKind::FormedBundle {
+                tx_details: None, ..
+            } => {}
+            BuilderEventKind::TransactionMined {
+                tx_hash,
+                nonce,
+                block_number,
+            } => {
+                let Some(attempt) = state.pending_bundle(event.entry_point, tag, *nonce) else {
+                    return;
+                };
+                attempt.tx_hash = *tx_hash;
+                attempt.status = BundleStatus::Landed {
+                    block_number: *block_number,
+                };
+                let user_op_hashes = attempt.user_op_hashes.clone();
+                for hash in user_op_hashes {
+                    let inclusion = OpInclusion::Included {
+                        tx_hash: *tx_hash,
+                        block_number: *block_number,
+                    };
+                    self.update_op(&mut state, hash, inclusion, None, now);
+                    let mined = MinedUserOperation {
+                        transaction_hash: *tx_hash,
+                        block_number: *block_number,
+                        success: None,
+                    };
+                    self.update_sponsorship(hash, StatusUpdate::Mined(mined));
+                }
+            }
+            BuilderEventKind::LatestTransactionDropped { nonce } => {
+                self.drop_bundle(
+                    &mut state,
+                    event,
+                    *nonce,
+                    "transaction dropped, higher fees are needed",
+                    now,
+                );
+            }
+            BuilderEventKind::NonceUsedForOtherTransaction { nonce } => {
+                self.drop_bundle(
+                    &mut state,
+                    event,
+                    *nonce,
+                    "nonce used by another transaction",
+                    now,
+                );
+            }
+            BuilderEventKind::SkippedOp { op_hash, reason } => {
+                let inclusion = OpInclusion::Skipped {
+                    reason: format!("{:?}", reason),
+                };
+                self.update_op(&mut state, *op_hash, inclusion, None, now);
+                state.skips.push_back(now);
+            }
+            BuilderEventKind::RejectedOp { op_hash, reason } => {
+                let reason = format!("{:?}", reason);
+                let inclusion = OpInclusion::Rejected {
+                    reason: reason.clone(),
+                };
+                self.update_op(&mut state, *op_hash, inclusion, None, now);
+                self.update_sponsorship(*op_hash, StatusUpdate::Dropped { reason });
+                state.skips.push_back(now);
+            }
+        }
+    }
+
+    fn drop_bundle(
+        &self,
+        state: &mut TrackerState,
+        event: &WithEntryPoint<BuilderEvent>,
+        nonce: u64,
+        reason: &str,
+        now: u64,
+    ) {
+        let Some(attempt) = state.pending_bundle(event.entry_point, &event.event.tag, nonce) else {
+            return;
+        };
+        attempt.status = BundleStatus::Dropped {
+            reason: reason.to_string(),
+        };
+        let tx_hash = attempt.tx_hash;
+        let user_op_hashes = attempt.user_op_hashes.clone();
+        for hash in user_op_hashes {
+            let inclusion = OpInclusion::BundleDropped {
+                tx_hash,
+                reason: reason.to_string(),
+            };
+            self.update_op(state, hash, inclusion, None, now);
+        }
+    }
+
+    fn update_op(
+        &self,
+        state: &mut TrackerState,
+        hash: B256,
+        latest: OpInclusion,
+        bundle: Option<B256>,
+        now: u64,
+    ) {
+        if !state.ops.contains_key(&hash) {
+            while state.ops.len() >= self.config.max_ops.max(1) {
+                let Some(oldest) = state.op_order.pop_front() else {
+                    break;
+                };
+                state.ops.remove(&oldest);
+            }
+            state.op_order.push_back(hash);
+        }
+
+        let status = state.ops.entry(hash).or_insert_with(|| OpInclusionStatus {
+            attempted: false,
+            bundles: Vec::new(),
+            latest: latest.clone(),
+            updated_at: now,
+        });
+        if let Some(tx_hash) = bundle {
+            status.attempted = true;
+            if !status.bundles.contains(&tx_hash) {
+                status.bundles.push(tx_hash);
+            }
+        }
+        status.latest = latest;
+        status.updated_at = now;
+    }
+
+    /// Bundle counts over the configured window
+    pub fn stats(&self) -> BundleStats {
+        self.stats_at(unix_now())
+    }
+
+    fn stats_at(&self, now: u64) -> BundleStats {
+        let mut state = self.state.lock().unwrap();
+        let since = now.saturating_sub(self.config.window_seconds);
+        while state.skips.front().is_some_and(|at| *at < since) {
+            state.skips.pop_front();
+        }
+
+        let mut stats = BundleStats {
+            window_seconds: self.config.window_seconds,
+            submitted: 0,
+            landed: 0,
+            dropped: 0,
+            pending: 0,
+            skipped_ops: state.skips.len() as u64,
+        };
+        for attempt in state.bundles.iter().filter(|a| a.submitted_at >= since) {
+            stats.submitted += 1;
+            match attempt.status {
+                BundleStatus::Submitted => stats.pending += 1,
+                BundleStatus::Landed { .. } => stats.landed += 1,
+                BundleStatus::Dropped { .. } => stats.dropped += 1,
+            }
+        }
+        stats
+    }
+
+    /// Most recent bundle attempts, newest first
+    pub fn recent_bundles(&self, limit: usize) -> Vec<BundleAttempt> {
+        let state = self.state.lock().unwrap();
+        state.bundles.iter().rev().take(limit).cloned().collect()
+    }
+
+    /// Bundle attempt whose latest transaction is `tx_hash`
+    pub fn bundle_by_hash(&self, tx_hash: B256) -> Option<BundleAttempt> {
+        let state = self.state.lock().unwrap();
+        state
+            .bundles
+            .iter()
+            .rev()
+            .find(|attempt| attempt.tx_hash == tx_hash)
+            .cloned()
+    }
+
+    /// Inclusion status of `user_op_hash`, `None` when no builder reported on it
+    pub fn op_status(&self, user_op_hash: B256) -> Option<OpInclusionStatus> {
+        self.state.lock().unwrap().ops.get(&user_op_hash).cloned()
+    }
+}
+
+impl TrackerState {
+    /// Latest unfinished bundle of `tag` with `nonce`
+    fn pending_bundle(
+        &mut self,
+        entry_point: Address,
+        tag: &str,
+        nonce: u64,
+    ) -> Option<&mut BundleAttempt> {
+        self.bundles.iter_mut().rev().find(|attempt| {
+            attempt.entry_point == entry_point
+                && attempt.builder_tag == tag
+                && attempt.nonce == nonce
+                && attempt.status == BundleStatus::Submitted
+        })
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use alloy_primitives::{Address, B256, U256};
+    use rundler_builder::{BuilderEvent, BuilderEventKind, BundleTxDetails, SkipReason};
+    use rundler_provider::TransactionRequest;
+    use rundler_utils::emit::WithEntryPoint;
+    use serde_json::{json, Value};
+    use tokio::sync::broadcast;
+
+    use super::*;
+    use crate::{gateway::JsonRpcRequest, test_utils, GatewayError, GatewayRouter};
+
+    const ENTRY_POINT: Address = Address::repeat_byte(0xe7);
+
+    fn event(kind: BuilderEventKind) -> WithEntryPoint<BuilderEvent> {
+        WithEntryPoint {
+            entry_point: ENTRY_POINT,
+            event: BuilderEvent {
+                tag: "builder-0".to_string(),
+                kind,
+            },
+        }
+    }
+
+    fn formed(nonce: u64, tx_hash: B256, ops: &[B256]) -> WithEntryPoint<BuilderEvent> {
+        event(BuilderEventKind::FormedBundle {
+            tx_details: Some(BundleTxDetails {
+                tx_hash,
+                tx: TransactionRequest::default(),
+                ops: Arc::new(ops.iter().map(|hash| (Address::ZERO, *hash)).collect()),
+            }),
+            nonce,
+            fee_increase_count: 0,
+            required_fees: None,
+        })
+    }
+
+    #[test]
+    fn test_stats_cover_window_only() {
+        let tracker = BundleTracker::new(BundleTrackerConfig {
+            window_seconds: 100,
+            ..Default::default()
+        });
+        tracker.record_at(&formed(0, B256::repeat_byte(1), &[]), 1_000);
+        tracker.record_at(&formed(1, B256::repeat_byte(2), &[]), 1_150);
+        tracker.record_at(
+            &event(BuilderEventKind::TransactionMined {
+                tx_hash: B256::repeat_byte(2),
+                nonce: 1,
+                block_number: 7,
+            }),
+            1_160,
+        );
+
+        let stats = tracker.stats_at(1_200);
+        assert_eq!(stats.submitted, 1);
+        assert_eq!(stats.landed, 1);
+        assert_eq!(stats.pending, 0);
+        // The older bundle is still listed, just not counted
+        assert_eq!(tracker.recent_bundles(10).len(), 2);
+    }
+
+    #[test]
+    fn test_memory_bounded() {
+        let tracker = BundleTracker::new(BundleTrackerConfig {
+            max_bundles: 2,
+            max_ops: 3,
+            ..Default::default()
+        });
+        for nonce in 0..4u8 {
+            let ops = [
+                B256::repeat_byte(0x10 + nonce),
+                B256::repeat_byte(0x20 + nonce),
+            ];
+            tracker.record_at(&formed(nonce.into(), B256::repeat_byte(nonce), &ops), 0);
+        }
+
+        let bundles = tracker.recent_bundles(10);
+        assert_eq!(bundles.len(), 2);
+        assert_eq!(bundles[0].nonce, 3);
+        assert!(tracker.bundle_by_hash(B256::repeat_byte(0)).is_none());
+
+        assert!(tracker.op_status(B256::repeat_byte(0x23)).is_some());
+        assert!(tracker.op_status(B256::repeat_byte(0x10)).is_none());
+        assert_eq!(tracker.state.lock().unwrap().ops.len(), 3);
+    }
+
+    /// Bundle of the single operation `B256::repeat_byte(op)`, sent as `B256::repeat_byte(tx)`
+    fn formed_bundle(
+        nonce: u64,
+        fee_increase_count: u64,
+        tx: u8,
+        op: u8,
+    ) -> WithEntryPoint<BuilderEvent> {
+        let request = TransactionRequest {
+            gas: Some(1_500_000),
+            ..Default::default()
+        };
+        event(BuilderEventKind::FormedBundle {
+            tx_details: Some(BundleTxDetails {
+                tx_hash: B256::repeat_byte(tx),
+                tx: request,
+                ops: Arc::new(vec![(Address::ZERO, B256::repeat_byte(op))]),
+            }),
+            nonce,
+            fee_increase_count,
+            required_fees: None,
+        })
+    }
+
+    fn mined(nonce: u64, tx_hash: B256) -> WithEntryPoint<BuilderEvent> {
+        event(BuilderEventKind::TransactionMined {
+            tx_hash,
+            nonce,
+            block_number: 42,
+        })
+    }
+
+    fn request(method: &str, params: Vec<Value>) -> JsonRpcRequest {
+        test_utils::request(method, params)
+    }
+
+    async fn inclusion(router: &GatewayRouter, hash: B256) -> Value {
+        router
+            .route_to_super_relay(&request(
+                "superRelay_getOpInclusionStatus",
+                vec![json!(format!("{:#x}", hash))],
+            ))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_bundle_stats_aggregated() {
+        let tracker = Arc::new(BundleTracker::default());
+        let router = GatewayRouter::new().with_bundle_tracker(tracker.clone());
+
+        // Nonce 0 lands after one fee bump, nonce 1 is dropped, nonce 2 is pending
+        tracker.record(&formed_bundle(0, 0, 0xa0, 1));
+        tracker.record(&formed_bundle(0, 1, 0xa1, 1));
+        tracker.record(&mined(0, B256::repeat_byte(0xa1)));
+        tracker.record(&formed_bundle(1, 0, 0xb0, 2));
+        tracker.record(&event(BuilderEventKind::LatestTransactionDropped {
+            nonce: 1,
+        }));
+        tracker.record(&formed_bundle(2, 0, 0xc0, 3));
+        tracker.record(&event(BuilderEventKind::SkippedOp {
+            op_hash: B256::repeat_byte(4),
+            reason: SkipReason::TargetGasLimit,
+        }));
+
+        let stats = router
+            .route_to_rundler(&request("rundler_getBundleStats", vec![]))
+            .await
+            .unwrap();
+        assert_eq!(stats["submitted"], 3);
+        assert_eq!(stats["landed"], 1);
+        assert_eq!(stats["dropped"], 1);
+        assert_eq!(stats["pending"], 1);
+        assert_eq!(stats["skippedOps"], 1);
+
+        let recent = stats["recentBundles"].as_array().unwrap();
+        assert_eq!(recent.len(), 3);
+        assert_eq!(recent[2]["txHash"], json!(B256::repeat_byte(0xa1)));
+        assert_eq!(recent[2]["feeIncreaseCount"], 1);
+        assert_eq!(recent[2]["status"], "landed");
+        assert_eq!(recent[2]["blockNumber"], 42);
+
+        let bundle = router
+            .route_to_rundler(&request(
+                "rundler_getBundleByHash",
+                vec![json!(B256::repeat_byte(0xb0))],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(bundle["status"], "dropped");
+        assert_eq!(bundle["gasLimit"], 1_500_000);
+        assert_eq!(bundle["userOpHashes"], json!([B256::repeat_byte(2)]));
+    }
+
+    #[tokio::test]
+    async fn test_op_inclusion_status() {
+        let tracker = Arc::new(BundleTracker::default());
+        let router = GatewayRouter::new().with_bundle_tracker(tracker.clone());
+
+        tracker.record(&formed_bundle(0, 0, 0xa0, 1));
+        tracker.record(&mined(0, B256::repeat_byte(0xa0)));
+        tracker.record(&event(BuilderEventKind::SkippedOp {
+            op_hash: B256::repeat_byte(2),
+            reason: SkipReason::OverSponsorshipMaxCost {
+                max_cost: U256::from(1),
+                actual_cost: U256::from(2),
+            },
+        }));
+
+        let included = inclusion(&router, B256::repeat_byte(1)).await;
+        assert_eq!(included["attempted"], true);
+        assert_eq!(included["status"], "included");
+        assert_eq!(included["blockNumber"], 42);
+        assert_eq!(included["bundles"], json!([B256::repeat_byte(0xa0)]));
+
+        let skipped = inclusion(&router, B256::repeat_byte(2)).await;
+        assert_eq!(skipped["attempted"], false);
+        assert_eq!(skipped["status"], "skipped");
+        assert!(skipped["reason"]
+            .as_str()
+            .unwrap()
+            .contains("OverSponsorshipMaxCost"));
+
+        assert!(inclusion(&router, B256::repeat_byte(3)).await.is_null());
+    }
+
+    #[tokio::test]
+    async fn test_tracker_subscribes_to_builder_events() {
+        let tracker = Arc::new(BundleTracker::default());
+        let (events, rx) = broadcast::channel(16);
+        let task = tracker.subscribe(rx);
+
+        events.send(formed_bundle(0, 0, 0xa0, 1)).unwrap();
+        drop(events);
+        tokio::time::timeout(Duration::from_secs(5), task)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(tracker.stats().pending, 1);
+        assert!(tracker.op_status(B256::repeat_byte(1)).unwrap().attempted);
+    }
+
+    #[tokio::test]
+    async fn test_tracking_disabled() {
+        let router = GatewayRouter::new();
+        let err = router
+            .route_to_rundler(&request("rundler_getBundleStats", vec![]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidRequest(_)));
+    }
+}
@@ -0,0 +1,488 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use alloy_primitives::{keccak256, Address, B256};
+use async_trait::async_trait;
+use rundler_paymaster_relay::canonical::canonical_json;
+use rundler_provider::EvmProvider;
+use rundler_utils::cache::LruMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::error::{GatewayError, GatewayResult};
+
+/// Header that makes the gateway skip cached responses for a request
+pub const NO_CACHE_HEADER: &str = "x-no-cache";
+
+/// Response cache configuration (`[gateway.cache]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ResponseCacheConfig {
+    /// Serve cached responses of read methods
+    pub enabled: bool,
+    /// Gas estimates kept before the least recently used one is evicted
+    pub max_entries: u32,
+    /// How long a gas estimate is reused, defaults to one mainnet block
+    pub estimate_ttl_ms: u64,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: 1024,
+            estimate_ttl_ms: 12_000,
+        }
+    }
+}
+
+/// Source of the latest block number, part of the gas estimate cache key
+#[async_trait]
+pub trait BlockNumberSource: Send + Sync {
+    /// Latest block number
+    async fn block_number(&self) -> GatewayResult<u64>;
+}
+
+/// [`BlockNumberSource`] backed by a node provider
+pub struct EvmBlockNumberSource<P> {
+    provider: P,
+}
+
+impl<P: EvmProvider> EvmBlockNumberSource<P> {
+    /// Read block numbers through `provider`
+    pub fn new(provider: P) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> BlockNumberSource for EvmBlockNumberSource<P> {
+    async fn block_number(&self) -> GatewayResult<u64> {
+        self.provider
+            .get_block_number()
+            .await
+            .map_err(|e| GatewayError::RundlerError(format!("Failed to get block number: {}", e)))
+    }
+}
+
+/// Hit and miss counts of one cached method
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CacheCounters {
+    /// JSON-RPC method
+    pub method: &'static str,
+    /// Requests answered from the cache
+    pub hits: u64,
+    /// Requests that had to be computed
+    pub misses: u64,
+}
+
+/// In-process cache of idempotent read responses
+///
+/// Values that cannot change for a running instance, such as the chain id,
+/// are kept forever. Gas estimates are kept in a bounded LRU keyed by the
+/// canonical operation, EntryPoint and latest block, and expire after the
+/// configured TTL.
+pub struct ResponseCache {
+    config: ResponseCacheConfig,
+    block_source: Option<Arc<dyn BlockNumberSource>>,
+    permanent: Mutex<HashMap<&'static str, Value>>,
+    estimates: Mutex<LruMap<B256, (Instant, Value)>>,
+    counters: Mutex<BTreeMap<&'static str, (u64, u64)>>,
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new(ResponseCacheConfig::default())
+    }
+}
+
+impl ResponseCache {
+    /// Create an empty cache
+    pub fn new(config: ResponseCacheConfig) -> Self {
+        let estimates = LruMap::new(config.max_entries.max(1));
+        Self {
+            config,
+            block_source: None,
+            permanent: Mutex::new(HashMap::new()),
+            estimates: Mutex::new(estimates),
+            counters: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Key gas estimates by the latest block of `block_source`
+    pub fn with_block_source(mut self, block_source: Arc<dyn BlockNumberSource>) -> Self {
+        self.block_source = Some(block_source);
+        self
+    }
+
+    /// Response of `method` computed once for the lifetime of the cache
+    pub fn permanent(
+        &self,
+        method: &'static str,
+        bypass: bool,
+        compute: impl FnOnce() -> GatewayResult<Value>,
+    ) -> GatewayResult<Value> {
+        if !self.config.enabled || bypass {
+            return compute();
+        }
+
+        if let Some(value) = self.permanent.lock().unwrap().get(method) {
+            self.record(method, true);
+            return Ok(value.clone());
+        }
+        self.record(method, false);
+
+        let value = compute()?;
+        self.permanent.lock().unwrap().insert(method, value.clone());
+        Ok(value)
+    }
+
+    /// Drop the stored response of `method`, computing it again on the next call
+    pub fn invalidate(&self, method: &'static str) {
+        self.permanent.lock().unwrap().remove(method);
+    }
+
+    /// Gas estimate for `user_op` on `entry_point`, reused within the TTL at
+    /// the same block
+    ///
+    /// Callers must not route estimates with state overrides through the cache.
+    pub async fn estimate<F>(
+        &self,
+        user_op: &Value,
+        entry_point: Address,
+        bypass: bool,
+        estimate: F,
+    ) -> GatewayResult<Value>
+    where
+        F: Future<Output = GatewayResult<Value>>,
+    {
+        const METHOD: &str = "eth_estimateUserOperationGas";
+
+        if !self.config.enabled || bypass {
+            return estimate.await;
+        }
+
+        let block_number = match &self.block_source {
+            Some(source) => match source.block_number().await {
+                Ok(block_number) => block_number,
+                Err(e) => {
+                    warn!("Gas estimate not cached: {}", e);
+                    return estimate.await;
+                }
+            },
+            None => 0,
+        };
+        let key = Self::estimate_key(user_op, entry_point, block_number);
+        let ttl = Duration::from_millis(self.config.estimate_ttl_ms);
+
+        if let Some((cached_at, value)) = self.estimates.lock().unwrap().get(&key) {
+            if cached_at.elapsed() < ttl {
+                debug!("Gas estimate cache hit at block {}", block_number);
+                self.record(METHOD, true);
+                return Ok(value.clone());
+            }
+        }
+        self.record(METHOD, false);
+
+        let value = estimate.await?;
+        self.estimates
+            .lock()
+            .unwrap()
+            .insert(key, (Instant::now(), value.clone()));
+        Ok(value)
+    }
+
+    /// Hit and miss counts per method, sorted by method
+    pub fn counters(&self) -> Vec<CacheCounters> {
+        self.counters
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(method, (hits, misses))| CacheCounters {
+                method: *method,
+                hits: *hits,
+                misses: *misses,
+            })
+            .collect()
+    }
+
+    fn record(&self, method: &'static str, hit: bool) {
+        let mut counters = self.counters.lock().unwrap();
+        let (hits, misses) = counters.entry(method).or_default();
+        if hit {
+            *hits += 1;
+        } else {
+            *misses += 1;
+        }
+    }
+
+    /// keccak256 of the canonical operation, EntryPoint and block number
+    fn estimate_key(user_op: &Value, entry_point: Address, block_number: u64) -> B256 {
+        let mut data = canonical_json(user_op).into_bytes();
+        data.extend_from_slice(entry_point.as_slice());
+        data.extend_from_slice(&block_number.to_be_bytes());
+        keccak256(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
+
+    use alloy_primitives::Address;
+    use async_trait::async_trait;
+    use rundler_provider::StateOverride;
+    use rundler_types::{GasEstimate, UserOperationOptionalGas};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{gateway::JsonRpcRequest, test_utils, GasEstimator, GatewayRouter};
+
+    const ENTRY_POINT: &str = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
+
+    #[derive(Default)]
+    struct CountingEstimator {
+        calls: AtomicU64,
+    }
+
+    #[async_trait]
+    impl GasEstimator for CountingEstimator {
+        async fn estimate_user_operation_gas(
+            &self,
+            _user_op: UserOperationOptionalGas,
+            _entry_point: Address,
+            _state_override: StateOverride,
+        ) -> GatewayResult<GasEstimate> {
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(GasEstimate {
+                pre_verification_gas: 21_000,
+                call_gas_limit: 100_000 + calls as u128,
+                verification_gas_limit: 100_000,
+                paymaster_verification_gas_limit: None,
+            })
+        }
+    }
+
+    #[derive(Default)]
+    struct TestBlocks {
+        number: AtomicU64,
+    }
+
+    #[async_trait]
+    impl BlockNumberSource for TestBlocks {
+        async fn block_number(&self) -> GatewayResult<u64> {
+            Ok(self.number.load(Ordering::SeqCst))
+        }
+    }
+
+    struct Harness {
+        router: GatewayRouter,
+        estimator: Arc<CountingEstimator>,
+        blocks: Arc<TestBlocks>,
+    }
+
+    impl Harness {
+        fn new(config: ResponseCacheConfig) -> Self {
+            let estimator = Arc::new(CountingEstimator::default());
+            let blocks = Arc::new(TestBlocks::default());
+            let cache = ResponseCache::new(config).with_block_source(blocks.clone());
+            let router = GatewayRouter::new()
+                .with_gas_estimator(estimator.clone())
+                .with_response_cache(Arc::new(cache));
+            Self {
+                router,
+                estimator,
+                blocks,
+            }
+        }
+
+        async fn call(&self, method: &str, params: Vec<Value>, no_cache: bool) -> Value {
+            let request = JsonRpcRequest {
+                no_cache,
+                ..test_utils::request(method, params)
+            };
+            self.router.route_to_rundler(&request).await.unwrap()
+        }
+
+        async fn estimate(&self, user_op: Value) -> Value {
+            self.call(
+                "eth_estimateUserOperationGas",
+                vec![user_op, json!(ENTRY_POINT)],
+                false,
+            )
+            .await
+        }
+
+        fn estimator_calls(&self) -> u64 {
+            self.estimator.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    fn user_op() -> Value {
+        json!({
+            "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "nonce": "0x1",
+            "callData": "0xABCD",
+        })
+    }
+
+    #[tokio::test]
+    async fn test_identical_estimate_within_ttl_not_reestimated() {
+        let harness = Harness::new(ResponseCacheConfig::default());
+
+        let first = harness.estimate(user_op()).await;
+        let second = harness.estimate(user_op()).await;
+        assert_eq!(first, second);
+        assert_eq!(harness.estimator_calls(), 1);
+
+        assert_eq!(
+            harness.router.cache_counters(),
+            vec![CacheCounters {
+                method: "eth_estimateUserOperationGas",
+                hits: 1,
+                misses: 1,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_equivalent_op_shares_cache_entry() {
+        let harness = Harness::new(ResponseCacheConfig::default());
+
+        harness.estimate(user_op()).await;
+        harness
+            .estimate(json!({
+                "callData": "0xabcd",
+                "nonce": "0x1",
+                "sender": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+            }))
+            .await;
+        // Decimal or zero-padded quantities and null optional fields
+        harness
+            .estimate(json!({
+                "nonce": "1",
+                "callData": "0xAbCd",
+                "factory": null,
+                "sender": "0xF39FD6E51AAD88F6F4CE6AB8827279CFFFB92266",
+            }))
+            .await;
+        harness
+            .estimate(json!({
+                "nonce": "0x0001",
+                "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "callData": "0xabcd",
+                "paymaster": null,
+            }))
+            .await;
+        assert_eq!(harness.estimator_calls(), 1);
+
+        harness
+            .estimate(json!({
+                "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "nonce": "0x2",
+                "callData": "0xABCD",
+            }))
+            .await;
+        assert_eq!(harness.estimator_calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_new_block_or_expired_ttl_reestimates() {
+        let harness = Harness::new(ResponseCacheConfig::default());
+        harness.estimate(user_op()).await;
+        harness.blocks.number.store(1, Ordering::SeqCst);
+        harness.estimate(user_op()).await;
+        assert_eq!(harness.estimator_calls(), 2);
+
+        let harness = Harness::new(ResponseCacheConfig {
+            estimate_ttl_ms: 0,
+            ..Default::default()
+        });
+        harness.estimate(user_op()).await;
+        harness.estimate(user_op()).await;
+        assert_eq!(harness.estimator_calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_state_override_never_cached() {
+        let harness = Harness::new(ResponseCacheConfig::default());
+        let params = vec![
+            user_op(),
+            json!(ENTRY_POINT),
+            json!({ "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266": { "balance": "0x1" } }),
+        ];
+
+        harness
+            .call("eth_estimateUserOperationGas", params.clone(), false)
+            .await;
+        harness
+            .call("eth_estimateUserOperationGas", params, false)
+            .await;
+        assert_eq!(harness.estimator_calls(), 2);
+        assert!(harness.router.cache_counters().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_no_cache_header_bypasses_cache() {
+        let harness = Harness::new(ResponseCacheConfig::default());
+        let params = vec![user_op(), json!(ENTRY_POINT)];
+
+        harness.estimate(user_op()).await;
+        harness
+            .call("eth_estimateUserOperationGas", params, true)
+            .await;
+        assert_eq!(harness.estimator_calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_lru_bounded() {
+        let harness = Harness::new(ResponseCacheConfig {
+            max_entries: 1,
+            ..Default::default()
+        });
+        let mut other = user_op();
+        other["nonce"] = json!("0x2");
+
+        harness.estimate(user_op()).await;
+        harness.estimate(other).await;
+        harness.estimate(user_op()).await;
+        assert_eq!(harness.estimator_calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_chain_id_and_entry_points_cached() {
+        let harness = Harness::new(ResponseCacheConfig::default());
+
+        for _ in 0..3 {
+            assert_eq!(harness.call("eth_chainId", vec![], false).await, "0x7a69");
+            harness
+                .call("eth_supportedEntryPoints", vec![], false)
+                .await;
+        }
+        harness.call("eth_chainId", vec![], true).await;
+
+        assert_eq!(
+            harness.router.cache_counters(),
+            vec![
+                CacheCounters {
+                    method: "eth_chainId",
+                    hits: 2,
+                    misses: 1,
+                },
+                CacheCounters {
+                    method: "eth_supportedEntryPoints",
+                    hits: 2,
+                    misses: 1,
+                },
+            ]
+        );
+    }
+}
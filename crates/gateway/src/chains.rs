@@ -0,0 +1,325 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use axum::http::HeaderMap;
+use rundler_paymaster_relay::PaymasterRelayService;
+use serde_json::Value;
+
+use crate::{
+    error::{GatewayError, GatewayResult},
+    gateway::JsonRpcRequest,
+    router::GatewayRouter,
+};
+
+/// Header selecting the chain a request is routed to
+pub const CHAIN_ID_HEADER: &str = "x-chain-id";
+
+/// Router and paymaster serving one chain
+#[derive(Clone)]
+pub struct ChainRoute {
+    /// Router bound to the chain's pool and EntryPoints
+    pub router: GatewayRouter,
+    /// Paymaster signing sponsorships on this chain
+    pub paymaster_service: Option<Arc<PaymasterRelayService>>,
+}
+
+impl ChainRoute {
+    /// Serve the chain of `router`
+    pub fn new(
+        router: GatewayRouter,
+        paymaster_service: Option<Arc<PaymasterRelayService>>,
+    ) -> Self {
+        Self {
+            router,
+            paymaster_service,
+        }
+    }
+
+    /// Chain id this route serves
+    pub fn chain_id(&self) -> u64 {
+        self.router.chain_id()
+    }
+}
+
+/// Chains served by one gateway, selected per request
+#[derive(Clone)]
+pub struct ChainRegistry {
+    default_chain_id: u64,
+    chains: BTreeMap<u64, ChainRoute>,
+}
+
+impl ChainRegistry {
+    /// Registry serving `default_route` to requests that do not name a chain
+    pub fn new(default_route: ChainRoute) -> Self {
+        let default_chain_id = default_route.chain_id();
+        Self {
+            default_chain_id,
+            chains: BTreeMap::from([(default_chain_id, default_route)]),
+        }
+    }
+
+    /// Serve another chain, replacing any route for the same chain id
+    pub fn with_chain(mut self, route: ChainRoute) -> Self {
+        self.chains.insert(route.chain_id(), route);
+        self
+    }
+
+    /// Route requests without a chain id to `chain_id`, which must be registered
+    pub fn with_default_chain(mut self, chain_id: u64) -> GatewayResult<Self> {
+        if !self.chains.contains_key(&chain_id) {
+            return Err(GatewayError::InvalidRequest(format!(
+                "Default chain {} is not configured",
+                chain_id
+            )));
+        }
+        self.default_chain_id = chain_id;
+        Ok(self)
+    }
+
+    /// Chain used when a request does not name one
+    pub fn default_chain_id(&self) -> u64 {
+        self.default_chain_id
+    }
+
+    /// All served chain ids, in ascending order
+    pub fn chain_ids(&self) -> Vec<u64> {
+        self.chains.keys().copied().collect()
+    }
+
+    /// Routes of every served chain, in ascending chain id order
+    pub fn routes(&self) -> impl Iterator<Item = &ChainRoute> {
+        self.chains.values()
+    }
+
+    /// Route for `chain_id`, or for the default chain when unset
+    pub fn select(&self, chain_id: Option<u64>) -> GatewayResult<&ChainRoute> {
+        let chain_id = chain_id.unwrap_or(self.default_chain_id);
+        self.chains.get(&chain_id).ok_or_else(|| {
+            GatewayError::InvalidRequest(format!(
+                "Unsupported chain id {}, served chains: {:?}",
+                chain_id,
+                self.chain_ids()
+            ))
+        })
+    }
+}
+
+/// Chain a request names via the `X-Chain-Id` header or its `chainId` field
+///
+/// Both may be given as long as they agree.
+pub fn requested_chain_id(
+    headers: &HeaderMap,
+    request: &JsonRpcRequest,
+) -> GatewayResult<Option<u64>> {
+    let from_header = match headers.get(CHAIN_ID_HEADER) {
+        Some(value) => Some(parse_chain_id(value.to_str().map_err(|_| {
+            GatewayError::InvalidRequest("X-Chain-Id header is not valid text".to_string())
+        })?)?),
+        None => None,
+    };
+
+    match (from_header, request.chain_id) {
+        (Some(header), Some(field)) if header != field => Err(GatewayError::InvalidRequest(
+            format!("X-Chain-Id {} does not match chainId {}", header, field),
+        )),
+        (header, field) => Ok(field.or(header)),
+    }
+}
+
+/// Explicit `chainId` of a JSON-RPC request
+///
+/// Read from the request object itself or, for the sponsorship methods, from
+/// their options object.
+pub(crate) fn chain_id_field(
+    payload: &Value,
+    request: &JsonRpcRequest,
+) -> GatewayResult<Option<u64>> {
+    let from_request = chain_id_value(payload.get("chainId"))?;
+    let options_index = match request.method.as_str() {
+        "pm_sponsorUserOperation" | "pm_sponsorUserOperationBatch" => Some(2),
+        "pm_sponsorUserOperationERC20" => Some(3),
+        _ => None,
+    };
+    let from_options = match options_index {
+        Some(index) => chain_id_value(
+            request
+                .params
+                .get(index)
+                .and_then(|options| options.get("chainId")),
+        )?,
+        None => None,
+    };
+
+    match (from_request, from_options) {
+        (Some(field), Some(options)) if field != options => {
+            Err(GatewayError::InvalidRequest(format!(
+                "chainId {} does not match options chainId {}",
+                field, options
+            )))
+        }
+        (field, options) => Ok(field.or(options)),
+    }
+}
+
+fn chain_id_value(value: Option<&Value>) -> GatewayResult<Option<u64>> {
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(number)) => number
+            .as_u64()
+            .map(Some)
+            .ok_or_else(|| GatewayError::InvalidRequest(format!("Invalid chainId: {}", number))),
+        Some(Value::String(value)) => parse_chain_id(value).map(Some),
+        Some(other) => Err(GatewayError::InvalidRequest(format!(
+            "Invalid chainId: {}",
+            other
+        ))),
+    }
+}
+
+/// Parse a chain id given as a decimal or 0x-hex string
+pub fn parse_chain_id(value: &str) -> GatewayResult<u64> {
+    let value = value.trim();
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| GatewayError::InvalidRequest(format!("Invalid chain id: {}", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::Address;
+    use axum::http::HeaderValue;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{router::EthApiConfig, test_utils};
+
+    const SEPOLIA: u64 = 11155111;
+    const BASE_SEPOLIA: u64 = 84532;
+    const ENTRY_POINT_V0_7: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+    const ENTRY_POINT_V0_6: &str = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
+
+    fn route(chain_id: u64, entry_points: &[&str]) -> ChainRoute {
+        let router = GatewayRouter::with_config(EthApiConfig {
+            chain_id,
+            entry_points: entry_points
+                .iter()
+                .map(|ep| ep.parse::<Address>().unwrap())
+                .collect(),
+            ..Default::default()
+        });
+        ChainRoute::new(router, None)
+    }
+
+    /// Sepolia serving both EntryPoints by default, Base Sepolia serving v0.7 only
+    fn create_registry() -> ChainRegistry {
+        ChainRegistry::new(route(SEPOLIA, &[ENTRY_POINT_V0_6, ENTRY_POINT_V0_7]))
+            .with_chain(route(BASE_SEPOLIA, &[ENTRY_POINT_V0_7]))
+    }
+
+    fn request(method: &str, chain_id: Option<u64>) -> JsonRpcRequest {
+        JsonRpcRequest {
+            chain_id,
+            ..test_utils::request(method, vec![])
+        }
+    }
+
+    fn chain_header(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CHAIN_ID_HEADER, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    async fn call(registry: &ChainRegistry, headers: &HeaderMap, request: JsonRpcRequest) -> Value {
+        let chain_id = requested_chain_id(headers, &request).unwrap();
+        registry
+            .select(chain_id)
+            .unwrap()
+            .router
+            .route_to_rundler(&request)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_requests_without_chain_use_default() {
+        let registry = create_registry();
+        assert_eq!(registry.default_chain_id(), SEPOLIA);
+        assert_eq!(registry.chain_ids(), vec![BASE_SEPOLIA, SEPOLIA]);
+
+        let chain_id = call(&registry, &HeaderMap::new(), request("eth_chainId", None)).await;
+        assert_eq!(chain_id, json!(format!("0x{:x}", SEPOLIA)));
+    }
+
+    #[tokio::test]
+    async fn test_chain_selected_by_header_or_field() {
+        let registry = create_registry();
+
+        let by_header = call(
+            &registry,
+            &chain_header("0x14a34"),
+            request("eth_chainId", None),
+        )
+        .await;
+        assert_eq!(by_header, json!(format!("0x{:x}", BASE_SEPOLIA)));
+
+        let by_field = call(
+            &registry,
+            &HeaderMap::new(),
+            request("eth_chainId", Some(BASE_SEPOLIA)),
+        )
+        .await;
+        assert_eq!(by_field, by_header);
+
+        // Each chain advertises its own EntryPoints
+        let entry_points = call(
+            &registry,
+            &chain_header(&BASE_SEPOLIA.to_string()),
+            request("eth_supportedEntryPoints", None),
+        )
+        .await;
+        assert_eq!(entry_points.as_array().unwrap().len(), 1);
+        let entry_points = call(
+            &registry,
+            &HeaderMap::new(),
+            request("eth_supportedEntryPoints", None),
+        )
+        .await;
+        assert_eq!(entry_points.as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_conflicting_chain_ids_rejected() {
+        let headers = chain_header(&SEPOLIA.to_string());
+
+        assert!(requested_chain_id(&headers, &request("eth_chainId", Some(BASE_SEPOLIA))).is_err());
+        assert_eq!(
+            requested_chain_id(&headers, &request("eth_chainId", Some(SEPOLIA))).unwrap(),
+            Some(SEPOLIA)
+        );
+        assert!(
+            requested_chain_id(&chain_header("sepolia"), &request("eth_chainId", None)).is_err()
+        );
+    }
+
+    #[test]
+    fn test_unknown_chain_rejected() {
+        let registry = create_registry();
+
+        assert!(registry.select(Some(1)).is_err());
+        assert!(create_registry().with_default_chain(1).is_err());
+
+        let registry = create_registry().with_default_chain(BASE_SEPOLIA).unwrap();
+        assert_eq!(registry.select(None).unwrap().chain_id(), BASE_SEPOLIA);
+    }
+
+    #[test]
+    fn test_parse_chain_id() {
+        assert_eq!(parse_chain_id("84532").unwrap(), BASE_SEPOLIA);
+        assert_eq!(parse_chain_id("0xaa36a7").unwrap(), SEPOLIA);
+        assert!(parse_chain_id("0xzz").is_err());
+    }
+}
@@ -0,0 +1,623 @@
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    sync::Arc,
+};
+
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::{Request, State},
+    http::{
+        header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, UPGRADE, VARY},
+        HeaderValue, Method, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Router,
+};
+use flate2::{
+    read::{GzDecoder, ZlibDecoder},
+    write::{GzEncoder, ZlibEncoder},
+    Compression,
+};
+use futures_util::{stream, StreamExt};
+use serde::Deserialize;
+use tracing::debug;
+
+/// Response compression configuration (`[gateway.compression]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CompressionConfig {
+    /// Compress responses for clients that accept brotli, gzip or deflate
+    pub enabled: bool,
+    /// Responses of known size below this many bytes are sent uncompressed
+    pub min_size_bytes: u64,
+    /// Compression level, 0 (none) to 9 (smallest), used as the brotli quality
+    pub level: u32,
+    /// Accept brotli, gzip and deflate encoded request bodies
+    pub decompress_requests: bool,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_size_bytes: 1024,
+            level: 6,
+            decompress_requests: true,
+        }
+    }
+}
+
+/// Content coding the gateway compresses with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentCoding {
+    /// `br`
+    Brotli,
+    /// `gzip`
+    Gzip,
+    /// `deflate`, zlib framed as HTTP requires
+    Deflate,
+}
+
+impl ContentCoding {
+    /// Token used in Accept-Encoding and Content-Encoding
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ContentCoding::Brotli => "br",
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Deflate => "deflate",
+        }
+    }
+
+    /// Coding named by a Content-Encoding header value
+    fn from_content_encoding(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "br" => Some(ContentCoding::Brotli),
+            "gzip" | "x-gzip" => Some(ContentCoding::Gzip),
+            "deflate" => Some(ContentCoding::Deflate),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ContentCoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Pick the response coding for an Accept-Encoding header value
+///
+/// The supported coding with the highest quality wins, brotli then gzip on
+/// ties; `*` stands for any coding not listed. `None` means identity.
+pub fn negotiate(accept_encoding: &str) -> Option<ContentCoding> {
+    let mut brotli = None;
+    let mut gzip = None;
+    let mut deflate = None;
+    let mut any = None;
+    for entry in accept_encoding.split(',') {
+        let mut parts = entry.split(';');
+        let coding = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        match coding.as_str() {
+            "br" => brotli = Some(quality),
+            "gzip" | "x-gzip" => gzip = Some(quality),
+            "deflate" => deflate = Some(quality),
+            "*" => any = Some(quality),
+            _ => {}
+        }
+    }
+
+    [
+        (ContentCoding::Brotli, brotli.or(any)),
+        (ContentCoding::Gzip, gzip.or(any)),
+        (ContentCoding::Deflate, deflate.or(any)),
+    ]
+    .into_iter()
+    .filter_map(|(coding, quality)| Some((coding, quality.filter(|q| *q > 0.0)?)))
+    // The first of equal qualities is kept
+    .reduce(|best, next| if next.1 > best.1 { next } else { best })
+    .map(|(coding, _)| coding)
+}
+
+/// Brotli window size, log2 of bytes, as browsers and common servers use
+const BROTLI_WINDOW_BITS: u32 = 22;
+
+/// Brotli encoder and decoder internal buffer size
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+enum Encoder {
+    Brotli(Box<brotli::CompressorWriter<Vec<u8>>>),
+    Gzip(GzEncoder<Vec<u8>>),
+    Deflate(ZlibEncoder<Vec<u8>>),
+}
+
+impl Encoder {
+    fn new(coding: ContentCoding, level: u32) -> Self {
+        let level = level.min(9);
+        match coding {
+            ContentCoding::Brotli => Encoder::Brotli(Box::new(brotli::CompressorWriter::new(
+                Vec::new(),
+                BROTLI_BUFFER_SIZE,
+                level,
+                BROTLI_WINDOW_BITS,
+            ))),
+            ContentCoding::Gzip => {
+                Encoder::Gzip(GzEncoder::new(Vec::new(), Compression::new(level)))
+            }
+            ContentCoding::Deflate => {
+                Encoder::Deflate(ZlibEncoder::new(Vec::new(), Compression::new(level)))
+            }
+        }
+    }
+
+    /// Compress `chunk`, returning the output produced so far
+    fn write(&mut self, chunk: &[u8]) -> io::Result<Bytes> {
+        let output = match self {
+            Encoder::Brotli(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.get_mut()
+            }
+            Encoder::Gzip(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.get_mut()
+            }
+            Encoder::Deflate(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.get_mut()
+            }
+        };
+        Ok(Bytes::from(std::mem::take(output)))
+    }
+
+    fn finish(self) -> io::Result<Bytes> {
+        match self {
+            Encoder::Brotli(encoder) => Ok(encoder.into_inner()),
+            Encoder::Gzip(encoder) => encoder.finish(),
+            Encoder::Deflate(encoder) => encoder.finish(),
+        }
+        .map(Bytes::from)
+    }
+}
+
+/// Compress `body` as it streams, so NDJSON exports are not buffered
+fn compress_body(body: Body, coding: ContentCoding, level: u32) -> Body {
+    let chunks = body.into_data_stream();
+    let compressed = stream::unfold(
+        (chunks, Some(Encoder::new(coding, level))),
+        |(mut chunks, encoder)| async move {
+            let mut encoder = encoder?;
+            loop {
+                match chunks.next().await {
+                    Some(Ok(chunk)) => match encoder.write(&chunk) {
+                        Ok(output) if output.is_empty() => continue,
+                        Ok(output) => return Some((Ok(output), (chunks, Some(encoder)))),
+                        Err(e) => return Some((Err(e), (chunks, None))),
+                    },
+                    Some(Err(e)) => {
+                        return Some((Err(io::Error::other(e)), (chunks, None)));
+                    }
+                    None => return Some((encoder.finish(), (chunks, None))),
+                }
+            }
+        },
+    );
+    Body::from_stream(compressed)
+}
+
+/// Whether the response is worth compressing
+fn compressible(response: &Response, min_size_bytes: u64) -> bool {
+    let status = response.status();
+    if status == StatusCode::SWITCHING_PROTOCOLS
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::NOT_MODIFIED
+    {
+        return false;
+    }
+    let headers = response.headers();
+    if headers.contains_key(CONTENT_ENCODING) {
+        return false;
+    }
+    // Server-sent events must reach the client as soon as they are written
+    let event_stream = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"));
+    if event_stream {
+        return false;
+    }
+    match response.body().size_hint().exact() {
+        Some(size) => size >= min_size_bytes.max(1),
+        // Streamed bodies of unknown size, such as the pool export
+        None => true,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CompressionState {
+    config: CompressionConfig,
+    max_request_body_bytes: usize,
+}
+
+impl CompressionConfig {
+    /// Add request decompression and negotiated response compression to `router`
+    ///
+    /// Apply before CORS: handlers see and log the uncompressed bodies.
+    /// Upgrade requests and server-sent events pass through uncompressed.
+    pub fn apply(&self, router: Router, max_request_body_bytes: usize) -> Router {
+        if !self.enabled && !self.decompress_requests {
+            return router;
+        }
+        let state = Arc::new(CompressionState {
+            config: self.clone(),
+            max_request_body_bytes,
+        });
+        router.layer(axum::middleware::from_fn_with_state(state, compression))
+    }
+}
+
+async fn compression(
+    State(state): State<Arc<CompressionState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let config = &state.config;
+    let request = if config.decompress_requests {
+        match decompress_request(request, state.max_request_body_bytes).await {
+            Ok(request) => request,
+            Err(rejection) => return rejection,
+        }
+    } else {
+        request
+    };
+
+    let coding = if !config.enabled
+        || request.method() == Method::HEAD
+        || request.headers().contains_key(UPGRADE)
+    {
+        None
+    } else {
+        request
+            .headers()
+            .get(ACCEPT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .and_then(negotiate)
+    };
+
+    let mut response = next.run(request).await;
+    if !config.enabled || !compressible(&response, config.min_size_bytes) {
+        return response;
+    }
+    response
+        .headers_mut()
+        .append(VARY, HeaderValue::from_static("accept-encoding"));
+    let Some(coding) = coding else {
+        return response;
+    };
+
+    debug!(
+        "Compressing {} response of {:?} bytes",
+        coding,
+        response.body().size_hint().exact()
+    );
+    let (mut parts, body) = response.into_parts();
+    parts.headers.remove(CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(CONTENT_ENCODING, HeaderValue::from_static(coding.as_str()));
+    Response::from_parts(parts, compress_body(body, coding, config.level))
+}
+
+/// Replace a brotli, gzip or deflate encoded request body with its decoded bytes
+///
+/// The decoded body is held to `max_bytes`, so small compressed payloads cannot
+/// expand without bound.
+async fn decompress_request(request: Request, max_bytes: usize) -> Result<Request, Response> {
+    let Some(encoding) = request.headers().get(CONTENT_ENCODING) else {
+        return Ok(request);
+    };
+    let encoding = encoding.to_str().unwrap_or_default();
+    if encoding.trim().eq_ignore_ascii_case("identity") {
+        return Ok(request);
+    }
+    let Some(coding) = ContentCoding::from_content_encoding(encoding) else {
+        return Err((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("Unsupported Content-Encoding {:?}", encoding),
+        )
+            .into_response());
+    };
+
+    let (mut parts, body) = request.into_parts();
+    let compressed = axum::body::to_bytes(body, max_bytes).await.map_err(|_| {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Request body too large".to_string(),
+        )
+            .into_response()
+    })?;
+
+    let mut decoded = Vec::new();
+    let limit = max_bytes as u64 + 1;
+    let result = match coding {
+        ContentCoding::Brotli => brotli::Decompressor::new(&compressed[..], BROTLI_BUFFER_SIZE)
+            .take(limit)
+            .read_to_end(&mut decoded),
+        ContentCoding::Gzip => GzDecoder::new(&compressed[..])
+            .take(limit)
+            .read_to_end(&mut decoded),
+        ContentCoding::Deflate => ZlibDecoder::new(&compressed[..])
+            .take(limit)
+            .read_to_end(&mut decoded),
+    };
+    if let Err(e) = result {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Invalid {} request body: {}", coding, e),
+        )
+            .into_response());
+    }
+    if decoded.len() > max_bytes {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Request body too large".to_string(),
+        )
+            .into_response());
+    }
+
+    parts.headers.remove(CONTENT_ENCODING);
+    parts.headers.insert(CONTENT_LENGTH, decoded.len().into());
+    Ok(Request::from_parts(parts, Body::from(decoded)))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::{GatewayConfig, PaymasterGateway};
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(negotiate("gzip"), Some(ContentCoding::Gzip));
+        assert_eq!(negotiate("br"), Some(ContentCoding::Brotli));
+        assert_eq!(negotiate("gzip, deflate, br"), Some(ContentCoding::Brotli));
+        assert_eq!(negotiate("gzip, deflate"), Some(ContentCoding::Gzip));
+        assert_eq!(
+            negotiate("deflate;q=1, gzip;q=0.5"),
+            Some(ContentCoding::Deflate)
+        );
+        assert_eq!(negotiate("br;q=0.5, gzip"), Some(ContentCoding::Gzip));
+        assert_eq!(negotiate("*"), Some(ContentCoding::Brotli));
+        assert_eq!(negotiate("br;q=0, *;q=0.1"), Some(ContentCoding::Gzip));
+        assert_eq!(negotiate("br;q=0"), None);
+        assert_eq!(negotiate("gzip;q=0"), None);
+        assert_eq!(negotiate("identity"), None);
+        assert_eq!(negotiate(""), None);
+    }
+
+    async fn serve(compression: CompressionConfig) -> std::net::SocketAddr {
+        let config = GatewayConfig {
+            compression,
+            ..Default::default()
+        };
+        let app = PaymasterGateway::new(config, None).app().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    struct HttpResponse {
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    }
+
+    impl HttpResponse {
+        fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        }
+    }
+
+    /// Send a raw request and parse the response, undoing chunked transfer encoding
+    async fn send(addr: std::net::SocketAddr, head: &str, body: &[u8]) -> HttpResponse {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream.write_all(body).await.unwrap();
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).await.unwrap();
+
+        let split = raw.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let head = String::from_utf8(raw[..split].to_vec()).unwrap();
+        let mut lines = head.split("\r\n");
+        let status = lines
+            .next()
+            .unwrap()
+            .split(' ')
+            .nth(1)
+            .unwrap()
+            .parse()
+            .unwrap();
+        let headers: Vec<(String, String)> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+        let mut rest = &raw[split + 4..];
+
+        let chunked = headers.iter().any(|(key, value)| {
+            key.eq_ignore_ascii_case("transfer-encoding") && value.eq_ignore_ascii_case("chunked")
+        });
+        let body = if chunked {
+            let mut body = Vec::new();
+            loop {
+                let end = rest.windows(2).position(|w| w == b"\r\n").unwrap();
+                let size = std::str::from_utf8(&rest[..end]).unwrap();
+                let size = usize::from_str_radix(size.split(';').next().unwrap(), 16).unwrap();
+                rest = &rest[end + 2..];
+                if size == 0 {
+                    break;
+                }
+                body.extend_from_slice(&rest[..size]);
+                rest = &rest[size + 2..];
+            }
+            body
+        } else {
+            rest.to_vec()
+        };
+        HttpResponse {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    async fn get_openapi(
+        addr: std::net::SocketAddr,
+        accept_encoding: Option<&str>,
+    ) -> HttpResponse {
+        let accept = accept_encoding
+            .map(|value| format!("Accept-Encoding: {}\r\n", value))
+            .unwrap_or_default();
+        let head = format!(
+            "GET /api-docs/openapi.json HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n",
+            accept
+        );
+        send(addr, &head, &[]).await
+    }
+
+    fn gunzip(bytes: &[u8]) -> Vec<u8> {
+        let mut decoded = Vec::new();
+        GzDecoder::new(bytes).read_to_end(&mut decoded).unwrap();
+        decoded
+    }
+
+    fn unbrotli(bytes: &[u8]) -> Vec<u8> {
+        let mut decoded = Vec::new();
+        brotli::Decompressor::new(bytes, 4096)
+            .read_to_end(&mut decoded)
+            .unwrap();
+        decoded
+    }
+
+    fn brotli(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 6, 22);
+        encoder.write_all(bytes).unwrap();
+        encoder.into_inner()
+    }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_large_response_is_compressed() {
+        let addr = serve(CompressionConfig::default()).await;
+
+        let identity = get_openapi(addr, None).await;
+        assert_eq!(identity.status, 200);
+        assert!(identity.header("content-encoding").is_none());
+        assert!(identity.body.len() > 1024);
+        assert_eq!(identity.header("vary"), Some("accept-encoding"));
+
+        let compressed = get_openapi(addr, Some("br;q=0.5, gzip;q=0.8")).await;
+        assert_eq!(compressed.status, 200);
+        assert_eq!(compressed.header("content-encoding"), Some("gzip"));
+        assert!(compressed.body.len() < identity.body.len());
+        assert_eq!(gunzip(&compressed.body), identity.body);
+
+        let compressed = get_openapi(addr, Some("gzip, deflate, br")).await;
+        assert_eq!(compressed.status, 200);
+        assert_eq!(compressed.header("content-encoding"), Some("br"));
+        assert!(compressed.body.len() < identity.body.len());
+        assert_eq!(unbrotli(&compressed.body), identity.body);
+
+        // Clients offering only unsupported codings get identity
+        let zstd_only = get_openapi(addr, Some("zstd")).await;
+        assert!(zstd_only.header("content-encoding").is_none());
+        assert_eq!(zstd_only.body, identity.body);
+    }
+
+    #[tokio::test]
+    async fn test_small_and_disabled_responses_are_identity() {
+        let addr = serve(CompressionConfig::default()).await;
+        let small = send(
+            addr,
+            "GET /health HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: gzip\r\nConnection: close\r\n\r\n",
+            &[],
+        )
+        .await;
+        assert!(small.body.len() < 1024);
+        assert!(small.header("content-encoding").is_none());
+
+        let addr = serve(CompressionConfig {
+            enabled: false,
+            ..Default::default()
+        })
+        .await;
+        let response = get_openapi(addr, Some("gzip")).await;
+        assert_eq!(response.status, 200);
+        assert!(response.header("content-encoding").is_none());
+    }
+
+    async fn post_encoded(
+        addr: std::net::SocketAddr,
+        content_encoding: &str,
+        body: &[u8],
+    ) -> HttpResponse {
+        let head = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Encoding: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            content_encoding,
+            body.len()
+        );
+        send(addr, &head, body).await
+    }
+
+    #[tokio::test]
+    async fn test_compressed_request_body() {
+        let addr = serve(CompressionConfig::default()).await;
+        let request =
+            json!({"jsonrpc": "2.0", "id": 7, "method": "web3_unknownMethod", "params": []});
+
+        let response = post_encoded(addr, "gzip", &gzip(request.to_string().as_bytes())).await;
+        assert_eq!(response.status, 200);
+        let body: Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(body["id"], json!(7));
+        assert_eq!(body["jsonrpc"], json!("2.0"));
+
+        let response = post_encoded(addr, "br", &brotli(request.to_string().as_bytes())).await;
+        assert_eq!(response.status, 200);
+        let body: Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(body["id"], json!(7));
+
+        let response = post_encoded(addr, "gzip", b"not gzip").await;
+        assert_eq!(response.status, 400);
+        let response = post_encoded(addr, "br", b"not brotli").await;
+        assert_eq!(response.status, 400);
+
+        let response = post_encoded(addr, "zstd", request.to_string().as_bytes()).await;
+        assert_eq!(response.status, 415);
+
+        // A small body that inflates past the request body limit
+        let bomb = gzip(&vec![b' '; 4 * 1024 * 1024]);
+        assert!(bomb.len() < 64 * 1024);
+        let response = post_encoded(addr, "gzip", &bomb).await;
+        assert_eq!(response.status, 413);
+        let bomb = brotli(&vec![b' '; 4 * 1024 * 1024]);
+        let response = post_encoded(addr, "br", &bomb).await;
+        assert_eq!(response.status, 413);
+    }
+}
@@ -0,0 +1,264 @@
+use rundler_types::ExpectedStorage;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::{GatewayError, GatewayResult};
+
+/// Conditional submission of superRelay_sendUserOperationConditional (`[gateway.conditional_send]`)
+///
+/// The conditions only hold if the builder submits bundles through the
+/// conditional transaction endpoint (`builder.use_conditional_rpc`) of a
+/// chain supporting it, so the method stays disabled until the operator
+/// declares that capability.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ConditionalSendConfig {
+    /// The builder submits bundles as conditional transactions
+    pub enabled: bool,
+    /// Most storage slots one operation may require
+    pub max_expected_storage_slots: usize,
+}
+
+impl Default for ConditionalSendConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_expected_storage_slots: 32,
+        }
+    }
+}
+
+impl ConditionalSendConfig {
+    /// Parse the `expectedStorage` param: `{address: {slot: value}}` with 32
+    /// byte slots and values, as in `knownAccounts` of eth_sendRawTransactionConditional
+    pub fn parse_expected_storage(&self, value: &Value) -> GatewayResult<ExpectedStorage> {
+        if !self.enabled {
+            return Err(GatewayError::UnsupportedCapability(
+                "conditional UserOperations need a builder submitting conditional transactions"
+                    .to_string(),
+            ));
+        }
+
+        let storage: ExpectedStorage = serde_json::from_value(value.clone()).map_err(|e| {
+            GatewayError::InvalidParams(format!(
+                "expectedStorage must map addresses to {{slot: value}} objects of 32 byte words: {}",
+                e
+            ))
+        })?;
+        if let Some((address, _)) = storage.0.iter().find(|(_, slots)| slots.is_empty()) {
+            return Err(GatewayError::InvalidParams(format!(
+                "expectedStorage of {:#x} has no slots",
+                address
+            )));
+        }
+
+        let slots = storage.num_slots();
+        if slots == 0 {
+            return Err(GatewayError::InvalidParams(
+                "expectedStorage has no slots, use eth_sendUserOperation".to_string(),
+            ));
+        }
+        if slots > self.max_expected_storage_slots {
+            return Err(GatewayError::InvalidParams(format!(
+                "expectedStorage has {} slots, at most {} are allowed",
+                slots, self.max_expected_storage_slots
+            )));
+        }
+        Ok(storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use alloy_primitives::{address, b256, B256, U256};
+    use serde_json::{json, Value};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::{
+        error::{INVALID_PARAMS_CODE, UNSUPPORTED_CAPABILITY_CODE},
+        GatewayConfig, PaymasterGateway,
+    };
+
+    const SLOT_ONE: &str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+    const SLOT_ONE_WORD: B256 =
+        b256!("0000000000000000000000000000000000000000000000000000000000000001");
+    const VALUE_SEVEN: &str = "0x0000000000000000000000000000000000000000000000000000000000000007";
+
+    fn enabled(max_expected_storage_slots: usize) -> ConditionalSendConfig {
+        ConditionalSendConfig {
+            enabled: true,
+            max_expected_storage_slots,
+        }
+    }
+
+    fn invalid_params(result: Result<impl std::fmt::Debug, GatewayError>) -> String {
+        match result {
+            Err(e @ GatewayError::InvalidParams(_)) => e.to_string(),
+            other => panic!("invalid params expected, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_expected_storage_structure() {
+        let config = enabled(8);
+
+        let storage = config
+            .parse_expected_storage(&json!({
+                "0x1111111111111111111111111111111111111111": { SLOT_ONE: VALUE_SEVEN },
+                "0x2222222222222222222222222222222222222222": {
+                    SLOT_ONE: VALUE_SEVEN,
+                    "0x0000000000000000000000000000000000000000000000000000000000000002": VALUE_SEVEN,
+                },
+            }))
+            .unwrap();
+        assert_eq!(storage.num_slots(), 3);
+        assert_eq!(
+            storage.0[&address!("1111111111111111111111111111111111111111")][&SLOT_ONE_WORD],
+            b256!("0000000000000000000000000000000000000000000000000000000000000007")
+        );
+
+        for malformed in [
+            json!(null),
+            json!([SLOT_ONE, VALUE_SEVEN]),
+            json!({ "0x1111": { SLOT_ONE: VALUE_SEVEN } }),
+            json!({ "0x1111111111111111111111111111111111111111": { "0x01": VALUE_SEVEN } }),
+            json!({ "0x1111111111111111111111111111111111111111": { SLOT_ONE: 7 } }),
+            json!({ "0x1111111111111111111111111111111111111111": [VALUE_SEVEN] }),
+        ] {
+            let message = invalid_params(config.parse_expected_storage(&malformed));
+            assert!(message.contains("32 byte words"), "{}", message);
+        }
+
+        let message = invalid_params(config.parse_expected_storage(&json!({})));
+        assert!(message.contains("no slots"), "{}", message);
+        let message =
+            invalid_params(config.parse_expected_storage(
+                &json!({ "0x1111111111111111111111111111111111111111": {} }),
+            ));
+        assert!(message.contains("no slots"), "{}", message);
+    }
+
+    #[test]
+    fn test_expected_storage_slot_cap() {
+        let storage = |slots: u64| {
+            let slots: serde_json::Map<String, Value> = (0..slots)
+                .map(|slot| (format!("0x{:064x}", U256::from(slot)), json!(VALUE_SEVEN)))
+                .collect();
+            json!({ "0x1111111111111111111111111111111111111111": slots })
+        };
+
+        let config = enabled(4);
+        assert_eq!(
+            config
+                .parse_expected_storage(&storage(4))
+                .unwrap()
+                .num_slots(),
+            4
+        );
+        let message = invalid_params(config.parse_expected_storage(&storage(5)));
+        assert!(message.contains("5 slots, at most 4"), "{}", message);
+    }
+
+    #[test]
+    fn test_disabled_is_a_capability_error() {
+        let config = ConditionalSendConfig::default();
+        assert!(!config.enabled);
+
+        let error = config
+            .parse_expected_storage(&json!({
+                "0x1111111111111111111111111111111111111111": { SLOT_ONE: VALUE_SEVEN },
+            }))
+            .unwrap_err();
+        assert!(matches!(error, GatewayError::UnsupportedCapability(_)));
+        assert_eq!(error.code(), UNSUPPORTED_CAPABILITY_CODE);
+    }
+
+    async fn serve(gateway: PaymasterGateway) -> SocketAddr {
+        let app = gateway.app().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    async fn send_conditional(addr: SocketAddr, expected_storage: Value) -> Value {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "superRelay_sendUserOperationConditional",
+            "params": [
+                {
+                    "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                    "nonce": "0x0",
+                    "initCode": "0x",
+                    "callData": "0x",
+                    "callGasLimit": "0x10000",
+                    "verificationGasLimit": "0x10000",
+                    "preVerificationGas": "0x5000",
+                    "maxFeePerGas": "0x3b9aca00",
+                    "maxPriorityFeePerGas": "0x3b9aca00",
+                    "paymasterAndData": "0x",
+                    "signature": "0x"
+                },
+                "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789",
+                expected_storage
+            ]
+        })
+        .to_string();
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                format!(
+                    "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_conditional_send_over_rpc() {
+        let conditions = json!({
+            "0x1111111111111111111111111111111111111111": { SLOT_ONE: VALUE_SEVEN },
+        });
+
+        // Without a conditional builder the conditions are never silently dropped
+        let addr = serve(PaymasterGateway::new(GatewayConfig::default(), None)).await;
+        let response = send_conditional(addr, conditions.clone()).await;
+        assert_eq!(response["error"]["code"], UNSUPPORTED_CAPABILITY_CODE);
+
+        let addr = serve(
+            PaymasterGateway::new(GatewayConfig::default(), None).with_conditional_send(enabled(1)),
+        )
+        .await;
+        let response = send_conditional(
+            addr,
+            json!({
+                "0x1111111111111111111111111111111111111111": {
+                    SLOT_ONE: VALUE_SEVEN,
+                    "0x0000000000000000000000000000000000000000000000000000000000000002": VALUE_SEVEN,
+                },
+            }),
+        )
+        .await;
+        assert_eq!(response["error"]["code"], INVALID_PARAMS_CODE);
+
+        // Valid conditions reach the pool submission, absent from this gateway
+        let response = send_conditional(addr, conditions).await;
+        let message = response["error"]["message"].as_str().unwrap();
+        assert!(message.contains("Pool not available"), "{}", response);
+    }
+}
@@ -0,0 +1,590 @@
+use std::{
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use arc_swap::ArcSwap;
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    serve::IncomingStream,
+    Json, Router,
+};
+use futures_util::future::BoxFuture;
+use metrics::{counter, gauge};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::Notify;
+use tower::Service;
+use tracing::debug;
+
+use crate::{
+    error::{GatewayError, GatewayResult},
+    method_registry,
+};
+
+/// Connection and expensive request limits (`[gateway.connection_limits]`)
+///
+/// A connection over `max_connections` waits up to `accept_timeout_ms` for a
+/// free slot, while no further connection is accepted, and is otherwise
+/// answered with 503 and `Retry-After`. Calls of the `expensive_methods` over
+/// `max_expensive_requests` are refused at once with JSON-RPC error -32005.
+/// The limits are reloaded from the config file without a restart.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ConnectionLimitConfig {
+    /// Open connections served at once, 0 for no limit
+    pub max_connections: usize,
+    /// Milliseconds a connection over the limit waits for a free slot
+    pub accept_timeout_ms: u64,
+    /// Concurrent calls of the expensive methods, 0 for no limit
+    pub max_expensive_requests: usize,
+    /// Simulation and estimation methods limited by `max_expensive_requests`
+    pub expensive_methods: Vec<String>,
+    /// Seconds sent in `Retry-After` with a rejection
+    pub retry_after_seconds: u64,
+    /// Seconds between checks of the config file for changed limits
+    pub reload_interval_seconds: u64,
+}
+
+impl Default for ConnectionLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 1000,
+            accept_timeout_ms: 100,
+            max_expensive_requests: 32,
+            expensive_methods: method_registry::expensive_methods()
+                .map(str::to_string)
+                .collect(),
+            retry_after_seconds: 1,
+            reload_interval_seconds: 10,
+        }
+    }
+}
+
+impl ConnectionLimitConfig {
+    /// Whether calls of `method` count against `max_expensive_requests`
+    pub fn is_expensive(&self, method: &str) -> bool {
+        self.expensive_methods.iter().any(|m| m == method)
+    }
+}
+
+/// Counting semaphore whose limit can change while permits are held
+///
+/// Lowering the limit below the permits in use refuses new permits until
+/// enough are released.
+#[derive(Debug)]
+struct ConcurrencyLimit {
+    /// Maximum permits, 0 for no limit
+    limit: AtomicUsize,
+    active: AtomicUsize,
+    /// Number of permits released so far
+    releases: AtomicU64,
+    released: Notify,
+    gauge: &'static str,
+}
+
+impl ConcurrencyLimit {
+    fn new(limit: usize, gauge: &'static str) -> Arc<Self> {
+        Arc::new(Self {
+            limit: AtomicUsize::new(limit),
+            active: AtomicUsize::new(0),
+            releases: AtomicU64::new(0),
+            released: Notify::new(),
+            gauge,
+        })
+    }
+
+    fn set_limit(&self, limit: usize) {
+        self.limit.store(limit, Ordering::Release);
+        // Waiters may fit under a raised limit
+        self.released.notify_waiters();
+    }
+
+    fn try_acquire(self: &Arc<Self>) -> Option<Permit> {
+        let limit = self.limit.load(Ordering::Acquire);
+        self.active
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |active| {
+                (limit == 0 || active < limit).then_some(active + 1)
+            })
+            .ok()?;
+        gauge!(self.gauge).increment(1.0);
+        Some(Permit {
+            limit: self.clone(),
+        })
+    }
+
+    async fn acquire(self: &Arc<Self>, timeout: Duration) -> Option<Permit> {
+        let wait = async {
+            loop {
+                // Register before trying, so a release in between is not missed
+                let released = self.released.notified();
+                tokio::pin!(released);
+                released.as_mut().enable();
+                if let Some(permit) = self.try_acquire() {
+                    return permit;
+                }
+                released.await;
+            }
+        };
+        tokio::time::timeout(timeout, wait).await.ok()
+    }
+}
+
+/// Slot of a connection or expensive request limit, freed on drop
+#[derive(Debug)]
+pub struct Permit {
+    limit: Arc<ConcurrencyLimit>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.limit.active.fetch_sub(1, Ordering::AcqRel);
+        self.limit.releases.fetch_add(1, Ordering::AcqRel);
+        gauge!(self.limit.gauge).decrement(1.0);
+        self.limit.released.notify_waiters();
+    }
+}
+
+/// Enforces the connection and expensive request limits of a gateway
+#[derive(Debug)]
+pub struct ConnectionLimiter {
+    config: ArcSwap<ConnectionLimitConfig>,
+    connections: Arc<ConcurrencyLimit>,
+    expensive: Arc<ConcurrencyLimit>,
+    /// Releases counted when a connection last timed out waiting for a slot
+    saturated_at: AtomicU64,
+}
+
+impl ConnectionLimiter {
+    /// Limiter enforcing `config`
+    pub fn new(config: ConnectionLimitConfig) -> Self {
+        Self {
+            connections: ConcurrencyLimit::new(
+                config.max_connections,
+                "superrelay_gateway_connections_active",
+            ),
+            expensive: ConcurrencyLimit::new(
+                config.max_expensive_requests,
+                "superrelay_gateway_expensive_requests_active",
+            ),
+            config: ArcSwap::from_pointee(config),
+            saturated_at: AtomicU64::new(u64::MAX),
+        }
+    }
+
+    /// Limiter that never refuses a connection or request
+    pub fn unlimited() -> Self {
+        Self::new(ConnectionLimitConfig {
+            max_connections: 0,
+            max_expensive_requests: 0,
+            ..Default::default()
+        })
+    }
+
+    /// Limits in force
+    pub fn config(&self) -> Arc<ConnectionLimitConfig> {
+        self.config.load_full()
+    }
+
+    /// Enforce `config` from now on; returns whether any limit changed
+    ///
+    /// Connections and requests already admitted are kept when a limit is
+    /// lowered below them.
+    pub fn update(&self, config: ConnectionLimitConfig) -> bool {
+        if *self.config.load_full() == config {
+            return false;
+        }
+        self.connections.set_limit(config.max_connections);
+        self.expensive.set_limit(config.max_expensive_requests);
+        self.config.store(Arc::new(config));
+        true
+    }
+
+    /// Connections currently served
+    pub fn active_connections(&self) -> usize {
+        self.connections.active.load(Ordering::Acquire)
+    }
+
+    /// Expensive requests currently executing
+    pub fn active_expensive_requests(&self) -> usize {
+        self.expensive.active.load(Ordering::Acquire)
+    }
+
+    /// Slot for a newly accepted connection, waiting up to `accept_timeout_ms`
+    ///
+    /// Once a wait times out, later connections are refused without waiting
+    /// until a connection closes.
+    pub async fn acquire_connection(&self) -> Option<Permit> {
+        if let Some(permit) = self.connections.try_acquire() {
+            return Some(permit);
+        }
+
+        let releases = self.connections.releases.load(Ordering::Acquire);
+        let permit = if self.saturated_at.load(Ordering::Acquire) == releases {
+            None
+        } else {
+            let timeout = Duration::from_millis(self.config.load().accept_timeout_ms);
+            let queued = gauge!("superrelay_gateway_connections_queued");
+            queued.increment(1.0);
+            let permit = self.connections.acquire(timeout).await;
+            queued.decrement(1.0);
+            if permit.is_none() {
+                self.saturated_at.store(
+                    self.connections.releases.load(Ordering::Acquire),
+                    Ordering::Release,
+                );
+            }
+            permit
+        };
+        if permit.is_none() {
+            counter!("superrelay_gateway_limit_rejections_total", "limit" => "connections")
+                .increment(1);
+        }
+        permit
+    }
+
+    /// Slot for a call of `method`, `None` when the method is not expensive
+    pub fn acquire_expensive(&self, method: &str) -> GatewayResult<Option<Permit>> {
+        let config = self.config.load();
+        if !config.is_expensive(method) {
+            return Ok(None);
+        }
+        match self.expensive.try_acquire() {
+            Some(permit) => Ok(Some(permit)),
+            None => {
+                counter!("superrelay_gateway_limit_rejections_total", "limit" => "expensive_requests")
+                    .increment(1);
+                Err(GatewayError::LimitExceeded {
+                    message: format!(
+                        "Too many concurrent simulation and estimation requests ({} max)",
+                        config.max_expensive_requests
+                    ),
+                    retry_after_seconds: config.retry_after_seconds,
+                })
+            }
+        }
+    }
+
+    /// Make-service serving `app` on connections admitted by this limiter
+    pub fn make_service(self: Arc<Self>, app: Router) -> LimitedMakeService {
+        LimitedMakeService { limiter: self, app }
+    }
+}
+
+/// 503 response with `Retry-After` carrying the JSON-RPC error `body`
+pub(crate) fn overloaded_response(body: Value, retry_after_seconds: u64) -> Response {
+    let mut response = (StatusCode::SERVICE_UNAVAILABLE, Json(body)).into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after_seconds));
+    response
+}
+
+/// Make-service giving each accepted connection a slot of the connection limit
+///
+/// `axum::serve` awaits it before accepting the next connection, so while the
+/// limit is reached new connections stay in the listen backlog.
+#[derive(Clone)]
+pub struct LimitedMakeService {
+    limiter: Arc<ConnectionLimiter>,
+    app: Router,
+}
+
+impl<'a> Service<IncomingStream<'a>> for LimitedMakeService {
+    type Response = LimitedConnection;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<LimitedConnection, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, stream: IncomingStream<'a>) -> Self::Future {
+        let remote_addr = stream.remote_addr();
+        let limiter = self.limiter.clone();
+        let app = self.app.clone();
+        Box::pin(async move {
+            let permit = limiter.acquire_connection().await;
+            if permit.is_none() {
+                debug!("Connection limit reached, refusing {}", remote_addr);
+            }
+            Ok(LimitedConnection {
+                app,
+                permit: permit.map(Arc::new),
+                retry_after_seconds: limiter.config().retry_after_seconds,
+            })
+        })
+    }
+}
+
+/// Service of one connection, holding its slot until the connection closes
+#[derive(Clone)]
+pub struct LimitedConnection {
+    app: Router,
+    /// `None` when the connection was refused
+    permit: Option<Arc<Permit>>,
+    retry_after_seconds: u64,
+}
+
+impl Service<Request> for LimitedConnection {
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Response, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        if self.permit.is_some() {
+            return Box::pin(Service::call(&mut self.app, request));
+        }
+        let error = GatewayError::LimitExceeded {
+            message: "Too many connections".to_string(),
+            retry_after_seconds: self.retry_after_seconds,
+        };
+        let body = json!({
+            "jsonrpc": "2.0",
+            "error": {
+                "code": error.code(),
+                "message": error.to_string(),
+                "data": error.rpc_data()
+            },
+            "id": null
+        });
+        let mut response = overloaded_response(body, self.retry_after_seconds);
+        response
+            .headers_mut()
+            .insert(header::CONNECTION, HeaderValue::from_static("close"));
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use axum::{routing::get, Router};
+    use futures_util::future::join_all;
+    use serde_json::{json, Value};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::{
+        serve_with_connection_limits, GatewayConfig, PaymasterGateway, ShutdownController,
+    };
+
+    fn limits(max_connections: usize) -> ConnectionLimitConfig {
+        ConnectionLimitConfig {
+            max_connections,
+            accept_timeout_ms: 20,
+            retry_after_seconds: 2,
+            ..Default::default()
+        }
+    }
+
+    /// Serve a /slow route through `limiter`, recording the highest concurrency seen
+    async fn spawn_server(limiter: Arc<ConnectionLimiter>, peak: Arc<AtomicUsize>) -> SocketAddr {
+        let active = Arc::new(AtomicUsize::new(0));
+        let app = Router::new().route(
+            "/slow",
+            get(move || {
+                let (active, peak) = (active.clone(), peak.clone());
+                async move {
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                    active.fetch_sub(1, Ordering::SeqCst);
+                    "slow-done"
+                }
+            }),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_with_connection_limits(
+            listener,
+            app,
+            limiter,
+            ShutdownController::new(),
+            Duration::from_secs(1),
+        ));
+        addr
+    }
+
+    /// Send a raw HTTP request and return the response head and body
+    async fn send(addr: SocketAddr, request: String) -> (String, String) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.to_lowercase(), body.to_string())
+    }
+
+    async fn get_slow(addr: SocketAddr) -> (String, String) {
+        send(
+            addr,
+            "GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n".to_string(),
+        )
+        .await
+    }
+
+    async fn post(addr: SocketAddr, body: &Value) -> (String, Value) {
+        let body = body.to_string();
+        let (head, body) = send(
+            addr,
+            format!(
+                "POST / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+        )
+        .await;
+        (head, serde_json::from_str(&body).unwrap())
+    }
+
+    fn assert_refused(head: &str, body: &str) {
+        assert!(head.starts_with("http/1.1 503"), "{}", head);
+        assert!(head.contains("retry-after: 2"), "{}", head);
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["error"]["code"], -32005);
+        assert_eq!(body["error"]["data"]["retryAfter"], 2);
+    }
+
+    /// Wait until every connection has released its slot
+    async fn wait_until_idle(limiter: &ConnectionLimiter) {
+        for _ in 0..50 {
+            if limiter.active_connections() == 0 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("{} connections still open", limiter.active_connections());
+    }
+
+    #[tokio::test]
+    async fn test_connection_cap_under_load() {
+        let limiter = Arc::new(ConnectionLimiter::new(limits(5)));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let addr = spawn_server(limiter.clone(), peak.clone()).await;
+
+        let responses = join_all((0..20).map(|_| get_slow(addr))).await;
+
+        let served: Vec<_> = responses
+            .iter()
+            .filter(|(head, _)| head.starts_with("http/1.1 200"))
+            .collect();
+        assert_eq!(served.len(), 5);
+        assert!(served.iter().all(|(_, body)| body == "slow-done"));
+        for (head, body) in responses
+            .iter()
+            .filter(|(head, _)| !head.starts_with("http/1.1 200"))
+        {
+            assert_refused(head, body);
+            assert!(head.contains("connection: close"));
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 5);
+
+        // Slots are freed once the connections close, and the next client is served
+        wait_until_idle(&limiter).await;
+        let (head, _) = get_slow(addr).await;
+        assert!(head.starts_with("http/1.1 200"));
+    }
+
+    #[tokio::test]
+    async fn test_limits_reload_without_restart() {
+        let limiter = Arc::new(ConnectionLimiter::new(limits(1)));
+        let addr = spawn_server(limiter.clone(), Arc::new(AtomicUsize::new(0))).await;
+
+        let held = tokio::spawn(get_slow(addr));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let (head, body) = get_slow(addr).await;
+        assert_refused(&head, &body);
+
+        // Raising the limit admits the next connection while the first is open
+        assert!(limiter.update(limits(2)));
+        assert!(!limiter.update(limits(2)));
+        let (head, _) = get_slow(addr).await;
+        assert!(head.starts_with("http/1.1 200"));
+        assert!(held.await.unwrap().0.starts_with("http/1.1 200"));
+
+        // Lowering it below the open connections refuses new ones until they close
+        wait_until_idle(&limiter).await;
+        let held = tokio::spawn(get_slow(addr));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(limiter.update(limits(1)));
+        assert_eq!(limiter.active_connections(), 1);
+        let (head, body) = get_slow(addr).await;
+        assert_refused(&head, &body);
+        held.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_expensive_requests_limited() {
+        let config = GatewayConfig {
+            connection_limits: ConnectionLimitConfig {
+                max_expensive_requests: 1,
+                retry_after_seconds: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let gateway = PaymasterGateway::new(config, None);
+        let limiter = gateway.connection_limiter();
+        let app = gateway.app().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let estimate = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_estimateUserOperationGas",
+            "params": []
+        });
+        let chain_id = json!({ "jsonrpc": "2.0", "id": 2, "method": "eth_chainId", "params": [] });
+
+        // Another estimation holds the only slot
+        let permit = limiter
+            .acquire_expensive("eth_estimateUserOperationGas")
+            .unwrap();
+        assert!(permit.is_some());
+        assert!(limiter.acquire_expensive("eth_chainId").unwrap().is_none());
+
+        let (head, body) = post(addr, &estimate).await;
+        assert_refused(&head, &body.to_string());
+        assert_eq!(body["id"], 1);
+
+        // Inside a batch only the expensive call is refused
+        let (head, body) = post(addr, &json!([estimate, chain_id])).await;
+        assert!(head.starts_with("http/1.1 200"));
+        assert_eq!(body[0]["error"]["code"], -32005);
+        assert_ne!(body[1]["error"]["code"], -32005);
+
+        drop(permit);
+        let (head, body) = post(addr, &estimate).await;
+        assert!(!head.starts_with("http/1.1 503"));
+        assert_ne!(body["error"]["code"], -32005);
+        assert_eq!(limiter.active_expensive_requests(), 0);
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    future::Future,
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use metrics::counter;
+use tracing::warn;
+
+use crate::error::{GatewayError, GatewayResult};
+
+/// Stage reported before any work has started
+const DISPATCH_STAGE: &str = "dispatch";
+
+/// Time budget of one JSON-RPC call, shared by every stage that handles it
+///
+/// Stages run their slow work through [`Deadline::run`], which aborts once the
+/// budget is spent and reports the stage in the timeout error. After a stage
+/// has changed state the caller must learn about, such as a signed and
+/// accounted sponsorship or an operation handed to the pool, it calls
+/// [`Deadline::commit`] and the call is left to finish.
+#[derive(Debug, Clone)]
+pub struct Deadline {
+    inner: Arc<DeadlineState>,
+}
+
+#[derive(Debug)]
+struct DeadlineState {
+    timeout: Option<Duration>,
+    expires_at: Option<Instant>,
+    stage: Mutex<&'static str>,
+    committed: AtomicBool,
+}
+
+impl Default for Deadline {
+    /// A deadline that never expires
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl Deadline {
+    /// Deadline `timeout` from now, or none when `timeout` is unset
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            inner: Arc::new(DeadlineState {
+                timeout,
+                expires_at: timeout.map(|timeout| Instant::now() + timeout),
+                stage: Mutex::new(DISPATCH_STAGE),
+                committed: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Deadline `timeout` from now
+    pub fn after(timeout: Duration) -> Self {
+        Self::new(Some(timeout))
+    }
+
+    /// Time left, `None` when the deadline never expires
+    pub fn remaining(&self) -> Option<Duration> {
+        self.inner
+            .expires_at
+            .map(|expires_at| expires_at.saturating_duration_since(Instant::now()))
+    }
+
+    /// Whether the time budget is spent
+    pub fn is_expired(&self) -> bool {
+        self.remaining()
+            .is_some_and(|remaining| remaining.is_zero())
+    }
+
+    /// Stage most recently entered
+    pub fn stage(&self) -> &'static str {
+        *self.inner.stage.lock().unwrap()
+    }
+
+    /// Record that `stage` is now executing
+    pub fn enter(&self, stage: &'static str) {
+        *self.inner.stage.lock().unwrap() = stage;
+    }
+
+    /// Enter `stage`, failing if the budget is already spent
+    pub fn check(&self, stage: &'static str) -> GatewayResult<()> {
+        self.enter(stage);
+        if self.is_expired() && !self.is_committed() {
+            return Err(self.exceeded());
+        }
+        Ok(())
+    }
+
+    /// Run `stage`, dropping its work if the deadline passes first
+    pub async fn run<T>(
+        &self,
+        stage: &'static str,
+        work: impl Future<Output = GatewayResult<T>>,
+    ) -> GatewayResult<T> {
+        self.check(stage)?;
+        match self.inner.expires_at {
+            Some(expires_at) if !self.is_committed() => {
+                tokio::time::timeout_at(expires_at.into(), work)
+                    .await
+                    .map_err(|_| self.exceeded())?
+            }
+            _ => work.await,
+        }
+    }
+
+    /// Let the call finish even if the deadline passes from here on
+    pub fn commit(&self) {
+        self.inner.committed.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the call has changed state that its response must report
+    pub fn is_committed(&self) -> bool {
+        self.inner.committed.load(Ordering::Relaxed)
+    }
+
+    /// Run the whole call, returning the timeout error if it is still running
+    /// at the deadline and has not committed
+    pub async fn enforce<T>(&self, work: impl Future<Output = T>) -> GatewayResult<T> {
+        let Some(expires_at) = self.inner.expires_at else {
+            return Ok(work.await);
+        };
+        let mut work = pin!(work);
+        match tokio::time::timeout_at(expires_at.into(), &mut work).await {
+            Ok(output) => Ok(output),
+            Err(_) if self.is_committed() => Ok(work.await),
+            Err(_) => Err(self.exceeded()),
+        }
+    }
+
+    /// Timeout error naming the executing stage
+    fn exceeded(&self) -> GatewayError {
+        let stage = self.stage();
+        let timeout = self.inner.timeout.unwrap_or_default();
+        warn!(
+            "⏱️ Request deadline of {}ms exceeded during {}",
+            timeout.as_millis(),
+            stage
+        );
+        counter!("superrelay_gateway_deadline_exceeded_total", "stage" => stage).increment(1);
+        GatewayError::DeadlineExceeded {
+            stage,
+            timeout_ms: timeout.as_millis() as u64,
+        }
+    }
+}
@@ -390,6 +390,8 @@ impl E2EValidator {
         match user_op {
             UserOperationVariant::V0_6(op) => format!("{:?}", op.sender()),
             UserOperationVariant::V0_7(op) => format!("{:?}", op.sender()),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => format!("{:?}", op.sender()),
         }
     }
 
@@ -397,6 +399,8 @@ impl E2EValidator {
         match user_op {
             UserOperationVariant::V0_6(op) => op.call_data().0.to_vec(),
             UserOperationVariant::V0_7(op) => op.call_data().0.to_vec(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.call_data().0.to_vec(),
         }
     }
 
@@ -404,6 +408,8 @@ impl E2EValidator {
         match user_op {
             UserOperationVariant::V0_6(op) => op.signature().0.to_vec(),
             UserOperationVariant::V0_7(op) => op.signature().0.to_vec(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.signature().0.to_vec(),
         }
     }
 
@@ -411,6 +417,8 @@ impl E2EValidator {
         match user_op {
             UserOperationVariant::V0_6(_) => "v0.6".to_string(),
             UserOperationVariant::V0_7(_) => "v0.7".to_string(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(_) => "v0.8".to_string(),
         }
     }
 }
@@ -464,14 +472,38 @@ pub async fn quick_e2e_health_check(state: &GatewayState) -> E2EValidationResult
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use super::*;
-    use crate::{router::GatewayRouter, GatewayConfig};
+    use crate::{
+        alerts::AlertBus,
+        chains::{ChainRegistry, ChainRoute},
+        connection_limit::ConnectionLimiter,
+        events::EventBus,
+        health::HealthChecker,
+        middleware::AuthMiddleware,
+        request_log::RequestLogger,
+        router::GatewayRouter,
+        shutdown::ShutdownController,
+        GatewayConfig,
+    };
 
     fn create_test_state() -> GatewayState {
         GatewayState {
             paymaster_service: None,
             router: GatewayRouter::new(),
             config: GatewayConfig::default(),
+            auth: AuthMiddleware::new(),
+            shutdown: ShutdownController::new(),
+            health: HealthChecker::new(),
+            chains: ChainRegistry::new(ChainRoute::new(GatewayRouter::new(), None)),
+            request_log: RequestLogger::default(),
+            prometheus: None,
+            access_control: None,
+            alerts: AlertBus::disabled(),
+            connection_limiter: Arc::new(ConnectionLimiter::unlimited()),
+            events: EventBus::new(Default::default()),
+            admin_methods: true,
         }
     }
 
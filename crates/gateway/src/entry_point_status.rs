@@ -0,0 +1,601 @@
+use std::{
+    collections::BTreeSet,
+    sync::RwLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::Address;
+use async_trait::async_trait;
+use metrics::{counter, gauge};
+use rundler_provider::EvmProvider;
+use rundler_types::EntryPointVersion;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::{
+    alerts::{Alert, AlertBus, AlertKind},
+    error::{GatewayError, GatewayResult},
+};
+
+/// Source of deployed contract code
+#[async_trait]
+pub trait ContractCodeReader: Send + Sync {
+    /// Whether `address` holds contract code at the latest block
+    async fn has_code(&self, address: Address) -> GatewayResult<bool>;
+}
+
+/// [`ContractCodeReader`] calling `eth_getCode` through a node provider
+pub struct EvmCodeReader<P> {
+    provider: P,
+}
+
+impl<P: EvmProvider> EvmCodeReader<P> {
+    /// Create a code reader using `provider`
+    pub fn new(provider: P) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> ContractCodeReader for EvmCodeReader<P> {
+    async fn has_code(&self, address: Address) -> GatewayResult<bool> {
+        let code = self.provider.get_code(address, None).await.map_err(|e| {
+            GatewayError::RundlerError(format!("getCode of {} failed: {}", address, e))
+        })?;
+        Ok(!code.is_empty())
+    }
+}
+
+/// Capability checks of the configured EntryPoints (`[gateway.entry_point_checks]`)
+///
+/// EntryPoints failing a check are left out of eth_supportedEntryPoints, or of
+/// pm_getSupportedEntryPoints when only sponsoring fails, until a later check
+/// passes again.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct EntryPointCheckConfig {
+    /// Check the EntryPoints before serving; when off every configured one is advertised
+    pub enabled: bool,
+    /// Seconds between re-checks after the startup check, 0 to check only at startup
+    pub interval_seconds: u64,
+}
+
+impl Default for EntryPointCheckConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_seconds: 60,
+        }
+    }
+}
+
+/// Why an EntryPoint lacks a capability
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryPointIssue {
+    /// No contract code at the address; nothing can be sent or estimated
+    NoCode,
+    /// The gateway has no mempool attached
+    NoPool,
+    /// The mempool does not serve the EntryPoint, e.g. its provider is not initialized
+    NotInPool,
+    /// The gas estimator has no estimator for the EntryPoint's version
+    NoEstimator,
+    /// The gateway has no paymaster service attached
+    NoPaymaster,
+    /// No paymaster contract is configured for the EntryPoint
+    NotSponsored,
+}
+
+impl EntryPointIssue {
+    /// Name used in status responses, logs and metric labels
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntryPointIssue::NoCode => "no_code",
+            EntryPointIssue::NoPool => "no_pool",
+            EntryPointIssue::NotInPool => "not_in_pool",
+            EntryPointIssue::NoEstimator => "no_estimator",
+            EntryPointIssue::NoPaymaster => "no_paymaster",
+            EntryPointIssue::NotSponsored => "not_sponsored",
+        }
+    }
+}
+
+/// What one configured EntryPoint can be used for, as of the latest check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryPointStatus {
+    /// EntryPoint address
+    pub entry_point: Address,
+    /// Declared version, e.g. `v0.7`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<&'static str>,
+    /// Operations can be sent to the mempool
+    pub send: bool,
+    /// Operations can be gas estimated
+    pub estimate: bool,
+    /// Operations can be sponsored by the paymaster
+    pub sponsor: bool,
+    /// Why capabilities are missing
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<EntryPointIssue>,
+}
+
+impl EntryPointStatus {
+    /// Status of `entry_point` with every capability, before issues are recorded
+    pub fn new(entry_point: Address, version: Option<EntryPointVersion>) -> Self {
+        Self {
+            entry_point,
+            version: version.and_then(version_name),
+            send: true,
+            estimate: true,
+            sponsor: true,
+            issues: Vec::new(),
+        }
+    }
+
+    /// Record `issue`, clearing the capabilities it takes away
+    pub fn with_issue(mut self, issue: EntryPointIssue) -> Self {
+        match issue {
+            EntryPointIssue::NoCode => {
+                self.send = false;
+                self.estimate = false;
+                self.sponsor = false;
+            }
+            EntryPointIssue::NoPool | EntryPointIssue::NotInPool => self.send = false,
+            EntryPointIssue::NoEstimator => self.estimate = false,
+            EntryPointIssue::NoPaymaster | EntryPointIssue::NotSponsored => self.sponsor = false,
+        }
+        if !self.issues.contains(&issue) {
+            self.issues.push(issue);
+        }
+        self
+    }
+
+    /// Listed by eth_supportedEntryPoints
+    pub fn advertised(&self) -> bool {
+        self.send && self.estimate
+    }
+
+    /// Listed by pm_getSupportedEntryPoints
+    pub fn sponsored(&self) -> bool {
+        self.estimate && self.sponsor
+    }
+}
+
+fn version_name(version: EntryPointVersion) -> Option<&'static str> {
+    match version {
+        EntryPointVersion::Unspecified => None,
+        EntryPointVersion::V0_6 => Some("v0.6"),
+        EntryPointVersion::V0_7 => Some("v0.7"),
+        #[cfg(feature = "entrypoint-v0_8")]
+        EntryPointVersion::V0_8 => Some("v0.8"),
+    }
+}
+
+/// Statuses of one check, with the time it ran
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryPointReport {
+    /// Unix time of the check, in seconds
+    pub checked_at: u64,
+    /// One status per configured EntryPoint, in configuration order
+    pub entry_points: Vec<EntryPointStatus>,
+}
+
+/// Latest EntryPoint statuses, shared by the clones of a router
+///
+/// Until the first check every configured EntryPoint is advertised.
+#[derive(Debug, Default)]
+pub struct EntryPointStatusBoard {
+    latest: RwLock<Option<EntryPointReport>>,
+}
+
+impl EntryPointStatusBoard {
+    /// Latest check, `None` before the first one
+    pub fn latest(&self) -> Option<EntryPointReport> {
+        self.latest.read().unwrap().clone()
+    }
+
+    /// Status of `entry_point` from the latest check
+    pub fn status(&self, entry_point: Address) -> Option<EntryPointStatus> {
+        let latest = self.latest.read().unwrap();
+        latest
+            .as_ref()?
+            .entry_points
+            .iter()
+            .find(|status| status.entry_point == entry_point)
+            .cloned()
+    }
+
+    /// Store the statuses of a check on `chain_id`, logging, counting and
+    /// alerting each EntryPoint added to or removed from either advertised
+    /// list, and returning whether eth_supportedEntryPoints changed
+    pub fn record(
+        &self,
+        chain_id: u64,
+        configured: &[Address],
+        statuses: Vec<EntryPointStatus>,
+        alerts: &AlertBus,
+    ) -> bool {
+        let chain = chain_id.to_string();
+        for status in &statuses {
+            let entry_point = status.entry_point.to_checksum(None);
+            for (capability, capable) in [
+                ("send", status.send),
+                ("estimate", status.estimate),
+                ("sponsor", status.sponsor),
+            ] {
+                gauge!(
+                    "superrelay_entry_point_capable",
+                    "chain_id" => chain.clone(),
+                    "entry_point" => entry_point.clone(),
+                    "capability" => capability
+                )
+                .set(if capable { 1.0 } else { 0.0 });
+            }
+        }
+
+        let report = EntryPointReport {
+            checked_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            entry_points: statuses,
+        };
+        let previous = self.latest.write().unwrap().replace(report.clone());
+
+        // Before the first check every configured EntryPoint counts as advertised
+        let before = |listed: fn(&EntryPointStatus) -> bool| -> BTreeSet<Address> {
+            match &previous {
+                Some(previous) => previous
+                    .entry_points
+                    .iter()
+                    .filter(|status| listed(status))
+                    .map(|status| status.entry_point)
+                    .collect(),
+                None => configured.iter().copied().collect(),
+            }
+        };
+        let after = |listed: fn(&EntryPointStatus) -> bool| -> BTreeSet<Address> {
+            report
+                .entry_points
+                .iter()
+                .filter(|status| listed(status))
+                .map(|status| status.entry_point)
+                .collect()
+        };
+
+        // pm_getSupportedEntryPoints is not served without a paymaster
+        let paymaster = !report
+            .entry_points
+            .iter()
+            .all(|status| status.issues.contains(&EntryPointIssue::NoPaymaster));
+        let mut lists = vec![(
+            "eth_supportedEntryPoints",
+            EntryPointStatus::advertised as fn(&EntryPointStatus) -> bool,
+        )];
+        if paymaster {
+            lists.push(("pm_getSupportedEntryPoints", EntryPointStatus::sponsored));
+        }
+
+        let mut changes = Vec::new();
+        for (list, listed) in lists {
+            let (before, after) = (before(listed), after(listed));
+            for entry_point in before.difference(&after) {
+                let issues: Vec<&str> = report
+                    .entry_points
+                    .iter()
+                    .find(|status| status.entry_point == *entry_point)
+                    .map(|status| status.issues.iter().map(EntryPointIssue::as_str).collect())
+                    .unwrap_or_default();
+                warn!(
+                    "⛔ EntryPoint {} removed from {} on chain {}: {}",
+                    entry_point,
+                    list,
+                    chain_id,
+                    issues.join(", ")
+                );
+                for issue in &issues {
+                    counter!(
+                        "superrelay_entry_point_exclusions_total",
+                        "chain_id" => chain.clone(),
+                        "list" => list,
+                        "reason" => *issue
+                    )
+                    .increment(1);
+                }
+                changes.push(json!({
+                    "list": list,
+                    "entryPoint": entry_point,
+                    "change": "removed",
+                    "issues": issues,
+                }));
+            }
+            for entry_point in after.difference(&before) {
+                info!(
+                    "✅ EntryPoint {} added to {} on chain {}",
+                    entry_point, list, chain_id
+                );
+                changes.push(json!({
+                    "list": list,
+                    "entryPoint": entry_point,
+                    "change": "added",
+                }));
+            }
+        }
+
+        if changes.is_empty() {
+            return false;
+        }
+        let advertised_changed = changes
+            .iter()
+            .any(|change| change["list"] == "eth_supportedEntryPoints");
+        alerts.publish(
+            Alert::new(
+                AlertKind::EntryPointsChanged,
+                format!("chain_{}", chain_id),
+                format!(
+                    "{} change(s) to the advertised EntryPoints on chain {}",
+                    changes.len(),
+                    chain_id
+                ),
+            )
+            .with_details(json!({ "chainId": chain_id, "changes": changes })),
+        );
+        advertised_changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use alloy_primitives::Address;
+    use async_trait::async_trait;
+    use rundler_pool::LocalPoolBuilder;
+    use rundler_provider::StateOverride;
+    use rundler_types::{GasEstimate, UserOperationOptionalGas};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        gateway::JsonRpcRequest, router::EthApiConfig, test_utils, AlertConfig, AlertSink,
+        GasEstimator, GatewayRouter,
+    };
+
+    const ENTRY_POINT_V0_6: &str = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
+    const ENTRY_POINT_V0_7: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+
+    fn v0_6() -> Address {
+        ENTRY_POINT_V0_6.parse().unwrap()
+    }
+
+    fn v0_7() -> Address {
+        ENTRY_POINT_V0_7.parse().unwrap()
+    }
+
+    /// Code reader whose deployed contracts can be changed, or which fails every lookup
+    #[derive(Default)]
+    struct MockCode {
+        deployed: Mutex<HashSet<Address>>,
+        unreachable: Mutex<bool>,
+    }
+
+    impl MockCode {
+        fn deployed(addresses: &[Address]) -> Arc<Self> {
+            let code = Self::default();
+            code.deployed.lock().unwrap().extend(addresses);
+            Arc::new(code)
+        }
+    }
+
+    #[async_trait]
+    impl ContractCodeReader for MockCode {
+        async fn has_code(&self, address: Address) -> GatewayResult<bool> {
+            if *self.unreachable.lock().unwrap() {
+                return Err(GatewayError::RundlerError("node unreachable".to_string()));
+            }
+            Ok(self.deployed.lock().unwrap().contains(&address))
+        }
+    }
+
+    /// Estimator with a v0.6 estimator only, as when the v0.7 provider is not initialized
+    struct V0_6OnlyEstimator;
+
+    #[async_trait]
+    impl GasEstimator for V0_6OnlyEstimator {
+        async fn estimate_user_operation_gas(
+            &self,
+            _user_op: UserOperationOptionalGas,
+            _entry_point: Address,
+            _state_override: StateOverride,
+        ) -> GatewayResult<GasEstimate> {
+            Err(GatewayError::RundlerError("not estimated".to_string()))
+        }
+
+        fn supports(&self, entry_point: Address) -> bool {
+            entry_point == v0_6()
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingSink {
+        alerts: Mutex<Vec<Alert>>,
+    }
+
+    #[async_trait]
+    impl AlertSink for RecordingSink {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        async fn deliver(&self, alert: &Alert) -> Result<(), String> {
+            self.alerts.lock().unwrap().push(alert.clone());
+            Ok(())
+        }
+    }
+
+    impl RecordingSink {
+        async fn wait_for(&self, count: usize) -> Vec<Alert> {
+            let start = Instant::now();
+            loop {
+                let alerts = self.alerts.lock().unwrap().clone();
+                if alerts.len() >= count {
+                    return alerts;
+                }
+                assert!(start.elapsed() < Duration::from_secs(5), "{:?}", alerts);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+    }
+
+    /// Router serving both EntryPoints through a pool that does not answer, so
+    /// only the code and estimator checks decide
+    fn router(code: Arc<MockCode>) -> GatewayRouter {
+        let pool = Arc::new(LocalPoolBuilder::new(10).get_handle());
+        GatewayRouter::with_rundler_components(
+            pool,
+            EthApiConfig {
+                chain_id: 31337,
+                entry_points: vec![v0_6(), v0_7()],
+                ..Default::default()
+            },
+        )
+        .with_code_reader(code)
+        .with_gas_estimator(Arc::new(V0_6OnlyEstimator))
+    }
+
+    fn alerting(sink: &Arc<RecordingSink>) -> AlertBus {
+        AlertBus::start(
+            AlertConfig {
+                enabled: true,
+                cooldown_seconds: 0,
+                ..Default::default()
+            },
+            vec![sink.clone()],
+        )
+        .unwrap()
+    }
+
+    fn request(method: &str) -> JsonRpcRequest {
+        test_utils::request(method, vec![])
+    }
+
+    async fn supported(router: &GatewayRouter) -> Value {
+        router
+            .route_to_rundler(&request("eth_supportedEntryPoints"))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_entry_point_without_estimator_is_not_advertised() {
+        let router = router(MockCode::deployed(&[v0_6(), v0_7()]));
+        // Cached before the check, refreshed when the list changes
+        assert_eq!(
+            supported(&router).await,
+            json!([ENTRY_POINT_V0_6, ENTRY_POINT_V0_7])
+        );
+
+        router.check_entry_points().await;
+        assert_eq!(supported(&router).await, json!([ENTRY_POINT_V0_6]));
+
+        let status = router
+            .route_to_super_relay(&request("superRelay_getEntryPointStatus"))
+            .await
+            .unwrap();
+        let entry_points = status["entryPoints"].as_array().unwrap();
+        assert_eq!(entry_points[0]["entryPoint"], ENTRY_POINT_V0_6);
+        assert_eq!(entry_points[0]["version"], "v0.6");
+        assert_eq!(entry_points[0]["send"], true);
+        assert_eq!(entry_points[0]["estimate"], true);
+        // No paymaster service is attached
+        assert_eq!(entry_points[0]["sponsor"], false);
+        assert_eq!(entry_points[0]["issues"], json!(["no_paymaster"]));
+
+        assert_eq!(entry_points[1]["entryPoint"], ENTRY_POINT_V0_7);
+        assert_eq!(entry_points[1]["send"], true);
+        assert_eq!(entry_points[1]["estimate"], false);
+        assert_eq!(
+            entry_points[1]["issues"],
+            json!(["no_estimator", "no_paymaster"])
+        );
+        assert!(status["checkedAt"].as_u64().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_status_checks_on_first_request() {
+        let router = router(MockCode::deployed(&[v0_6(), v0_7()]));
+
+        let status = router
+            .route_to_super_relay(&request("superRelay_getEntryPointStatus"))
+            .await
+            .unwrap();
+        assert_eq!(status["entryPoints"].as_array().unwrap().len(), 2);
+        assert_eq!(supported(&router).await, json!([ENTRY_POINT_V0_6]));
+    }
+
+    #[tokio::test]
+    async fn test_list_changes_raise_alerts() {
+        let sink = Arc::new(RecordingSink::default());
+        let code = MockCode::deployed(&[v0_6(), v0_7()]);
+        let router = router(code.clone()).with_alerts(alerting(&sink));
+
+        // v0.7 leaves eth_supportedEntryPoints at the first check
+        router.check_entry_points().await;
+        let alerts = sink.wait_for(1).await;
+        assert_eq!(alerts[0].kind.as_str(), "entry_points_changed");
+        assert_eq!(alerts[0].subject, "chain_31337");
+        let changes = &alerts[0].details["changes"];
+        assert_eq!(changes[0]["list"], "eth_supportedEntryPoints");
+        assert_eq!(changes[0]["change"], "removed");
+        assert_eq!(changes[0]["entryPoint"], ENTRY_POINT_V0_7);
+        assert_eq!(
+            changes[0]["issues"],
+            json!(["no_estimator", "no_paymaster"])
+        );
+
+        // Unchanged outcomes do not alert again
+        router.check_entry_points().await;
+        code.deployed.lock().unwrap().remove(&v0_6());
+        router.check_entry_points().await;
+        let alerts = sink.wait_for(2).await;
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(
+            alerts[1].details["changes"][0]["entryPoint"],
+            ENTRY_POINT_V0_6
+        );
+        assert_eq!(
+            alerts[1].details["changes"][0]["issues"],
+            json!(["no_code", "no_paymaster"])
+        );
+        assert_eq!(supported(&router).await, json!([]));
+
+        code.deployed.lock().unwrap().insert(v0_6());
+        router.check_entry_points().await;
+        let alerts = sink.wait_for(3).await;
+        assert_eq!(alerts[2].details["changes"][0]["change"], "added");
+        assert_eq!(supported(&router).await, json!([ENTRY_POINT_V0_6]));
+    }
+
+    #[tokio::test]
+    async fn test_failed_code_lookup_keeps_previous_outcome() {
+        let code = MockCode::deployed(&[v0_7()]);
+        let router = router(code.clone());
+
+        router.check_entry_points().await;
+        *code.unreachable.lock().unwrap() = true;
+        code.deployed.lock().unwrap().insert(v0_6());
+        let statuses = router.check_entry_points().await;
+
+        assert_eq!(statuses[0].issues.len(), 2, "{:?}", statuses[0]);
+        assert!(!statuses[0].advertised());
+        assert_eq!(supported(&router).await, json!([]));
+    }
+}
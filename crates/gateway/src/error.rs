@@ -1,5 +1,50 @@
+use alloy_primitives::Address;
+use rundler_paymaster_relay::{ErrorCatalogEntry, PaymasterError};
+use rundler_sim::GasEstimationError;
+use rundler_types::{
+    pool::{MempoolError, PoolError, SimulationViolation},
+    Entity, EntityType,
+};
+use serde_json::{json, Value};
 use thiserror::Error;
 
+use crate::{error_catalog as catalog, preflight::PreflightReport};
+
+/// Invalid params (JSON-RPC)
+pub const INVALID_PARAMS_CODE: i32 = -32602;
+/// Internal error (JSON-RPC)
+pub const INTERNAL_ERROR_CODE: i32 = -32603;
+/// Precheck of the operation failed before simulation
+pub const CALL_EXECUTION_FAILED_CODE: i32 = -32000;
+/// Rejected by the EntryPoint's simulateValidation, during wallet creation or validation
+pub const ENTRYPOINT_VALIDATION_REJECTED_CODE: i32 = -32500;
+/// Rejected by the paymaster's validatePaymasterUserOp
+pub const PAYMASTER_VALIDATION_REJECTED_CODE: i32 = -32501;
+/// Banned opcode or storage access during validation
+pub const OPCODE_VIOLATION_CODE: i32 = -32502;
+/// Valid only in a different time range
+pub const OUT_OF_TIME_RANGE_CODE: i32 = -32503;
+/// A paymaster, factory or aggregator is throttled or banned
+pub const THROTTLED_OR_BANNED_CODE: i32 = -32504;
+/// An entity's stake or unstake delay is too low
+pub const STAKE_TOO_LOW_CODE: i32 = -32505;
+/// Unsupported signature aggregator
+pub const UNSUPPORTED_AGGREGATOR_CODE: i32 = -32506;
+/// Invalid account, paymaster or aggregator signature
+pub const SIGNATURE_CHECK_FAILED_CODE: i32 = -32507;
+/// Paymaster deposit too low for the operation
+pub const PAYMASTER_DEPOSIT_TOO_LOW_CODE: i32 = -32508;
+/// The operation's call reverted during gas estimation
+pub const EXECUTION_REVERTED_CODE: i32 = -32521;
+/// The sender is refused by the gateway's access lists
+pub const SENDER_DENIED_CODE: i32 = -32509;
+/// The request ran past its deadline
+pub const DEADLINE_EXCEEDED_CODE: i32 = -32008;
+/// A concurrency limit of the gateway is reached (EIP-1474 limit exceeded)
+pub const LIMIT_EXCEEDED_CODE: i32 = -32005;
+/// The request needs a capability this deployment lacks (EIP-1474 method not supported)
+pub const UNSUPPORTED_CAPABILITY_CODE: i32 = -32004;
+
 /// Gateway error types
 #[derive(Error, Debug)]
 pub enum GatewayError {
@@ -31,6 +76,13 @@ pub enum GatewayError {
     #[error("Paymaster error: {0}")]
     PaymasterError(String),
 
+    /// The paymaster refused or failed to sponsor an operation
+    ///
+    /// Reported with the internal error code, and the paymaster error's
+    /// catalog key in the data.
+    #[error("Paymaster error: Sponsorship failed: {0}")]
+    SponsorshipFailed(PaymasterError),
+
     /// Pool operation error
     #[error("Pool error: {0}")]
     PoolError(String),
@@ -39,6 +91,10 @@ pub enum GatewayError {
     #[error("Server error: {0}")]
     ServerError(String),
 
+    /// A critical dependency probe still failed when the startup checks timed out
+    #[error("Preflight failed: {0}")]
+    PreflightFailed(Box<PreflightReport>),
+
     /// JSON-RPC error
     #[error("JSON-RPC error: {0}")]
     JsonRpcError(String),
@@ -47,6 +103,24 @@ pub enum GatewayError {
     #[error("Request timeout")]
     Timeout,
 
+    /// The request's deadline passed while `stage` was executing
+    #[error("Request deadline of {timeout_ms}ms exceeded during {stage}")]
+    DeadlineExceeded {
+        /// Stage executing when the deadline passed
+        stage: &'static str,
+        /// Time budget of the request in milliseconds
+        timeout_ms: u64,
+    },
+
+    /// A connection or expensive request limit is reached
+    #[error("{message}")]
+    LimitExceeded {
+        /// Which limit was reached
+        message: String,
+        /// Seconds after which the client may retry
+        retry_after_seconds: u64,
+    },
+
     /// Data validation error
     #[error("Validation error: {0}")]
     ValidationError(String),
@@ -54,6 +128,379 @@ pub enum GatewayError {
     /// Internal system error
     #[error("Internal error: {0}")]
     InternalError(String),
+
+    /// Our paymaster's signature on a UserOperation does not verify
+    #[error("Paymaster signature rejected: {0}")]
+    InvalidSponsorship(String),
+
+    /// Our paymaster's signature is valid but outside its validity window
+    #[error("Paymaster signature out of time range: {0}")]
+    SponsorshipOutOfTimeRange(String),
+
+    /// The UserOperation names a signature aggregator that is not enabled
+    #[error("Unsupported signature aggregator: {0}")]
+    UnsupportedAggregator(Address),
+
+    /// An idempotency key was reused for a different UserOperation
+    #[error("Idempotency key conflict: {0}")]
+    IdempotencyConflict(String),
+
+    /// Invalid JSON-RPC method parameters
+    #[error("Invalid params: {0}")]
+    InvalidParams(String),
+
+    /// The sender is denylisted, or not allowlisted in allowlist mode
+    #[error("Sender denied: {0}")]
+    SenderDenied(String),
+
+    /// The request needs a capability this deployment lacks
+    #[error("Unsupported capability: {0}")]
+    UnsupportedCapability(String),
+
+    /// Gas estimation failed in validation or in the operation's call
+    #[error("{message}")]
+    EstimationFailed {
+        /// ERC-4337 JSON-RPC error code
+        code: i32,
+        /// Reason the estimation failed
+        message: String,
+        /// Error data, such as the call's revert data
+        data: Option<Value>,
+    },
+
+    /// The mempool rejected the UserOperation
+    #[error("{message}")]
+    PoolRejected {
+        /// ERC-4337 JSON-RPC error code
+        code: i32,
+        /// Reason given by the mempool
+        message: String,
+        /// Error data, such as the offending entity
+        data: Option<Value>,
+    },
+
+    /// An operation of a sponsorship batch failed, rejecting the whole batch
+    #[error("UserOperation {index} of the batch: {error}")]
+    BatchRejected {
+        /// Position of the operation in the batch
+        index: usize,
+        /// Error of the operation
+        error: Box<GatewayError>,
+    },
+}
+
+impl GatewayError {
+    /// JSON-RPC error code (ERC-7769 codes where one applies)
+    pub fn code(&self) -> i32 {
+        match self {
+            GatewayError::InvalidSponsorship(_) => PAYMASTER_VALIDATION_REJECTED_CODE,
+            GatewayError::SponsorshipOutOfTimeRange(_) => OUT_OF_TIME_RANGE_CODE,
+            GatewayError::UnsupportedAggregator(_) => UNSUPPORTED_AGGREGATOR_CODE,
+            GatewayError::IdempotencyConflict(_) => -32507,
+            GatewayError::InvalidParams(_) => INVALID_PARAMS_CODE,
+            GatewayError::SenderDenied(_) => SENDER_DENIED_CODE,
+            GatewayError::UnsupportedCapability(_) => UNSUPPORTED_CAPABILITY_CODE,
+            GatewayError::DeadlineExceeded { .. } => DEADLINE_EXCEEDED_CODE,
+            GatewayError::LimitExceeded { .. } => LIMIT_EXCEEDED_CODE,
+            GatewayError::EstimationFailed { code, .. }
+            | GatewayError::PoolRejected { code, .. } => *code,
+            GatewayError::BatchRejected { error, .. } => error.code(),
+            _ => INTERNAL_ERROR_CODE,
+        }
+    }
+
+    /// JSON-RPC error data, if any
+    pub fn data(&self) -> Option<&Value> {
+        match self {
+            GatewayError::EstimationFailed { data, .. }
+            | GatewayError::PoolRejected { data, .. } => data.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Full JSON-RPC error data: [`Self::data`] or the variant's own fields,
+    /// with the catalog `key` and `params` clients localize the message from
+    pub fn rpc_data(&self) -> Value {
+        let mut data = match self {
+            GatewayError::DeadlineExceeded { stage, timeout_ms } => {
+                json!({ "stage": stage, "timeoutMs": timeout_ms })
+            }
+            GatewayError::LimitExceeded {
+                retry_after_seconds,
+                ..
+            } => json!({ "retryAfter": retry_after_seconds }),
+            GatewayError::BatchRejected { index, error } => {
+                let mut data = error.rpc_data();
+                data["index"] = json!(index);
+                data
+            }
+            _ => match self.data() {
+                Some(Value::Object(data)) => Value::Object(data.clone()),
+                // Keep non-object data next to the key
+                Some(data) => json!({ "value": data }),
+                None => json!({}),
+            },
+        };
+        data["key"] = json!(self.catalog_entry().key);
+        data["params"] = self.params();
+        data
+    }
+
+    /// Catalog entry of the error; every variant must have one
+    pub fn catalog_entry(&self) -> &'static ErrorCatalogEntry {
+        match self {
+            GatewayError::InvalidRequest(_) => &catalog::INVALID_REQUEST,
+            GatewayError::UnsupportedMethod(_) => &catalog::UNSUPPORTED_METHOD,
+            GatewayError::AuthenticationFailed(_) => &catalog::AUTHENTICATION_FAILED,
+            GatewayError::RateLimitExceeded => &catalog::RATE_LIMIT_EXCEEDED,
+            GatewayError::PolicyViolation(_) => &catalog::POLICY_VIOLATION,
+            GatewayError::RundlerError(_) => &catalog::BUNDLER_ERROR,
+            GatewayError::PaymasterError(_) => &catalog::PAYMASTER_ERROR,
+            GatewayError::SponsorshipFailed(e) => e.catalog_entry(),
+            GatewayError::PoolError(_) => &catalog::POOL_ERROR,
+            GatewayError::ServerError(_) => &catalog::SERVER_ERROR,
+            GatewayError::PreflightFailed(_) => &catalog::PREFLIGHT_FAILED,
+            GatewayError::JsonRpcError(_) => &catalog::JSON_RPC_ERROR,
+            GatewayError::Timeout => &catalog::TIMEOUT,
+            GatewayError::DeadlineExceeded { .. } => &catalog::DEADLINE_EXCEEDED,
+            GatewayError::LimitExceeded { .. } => &catalog::LIMIT_EXCEEDED,
+            GatewayError::ValidationError(_) => &catalog::VALIDATION_ERROR,
+            GatewayError::InternalError(_) => &catalog::INTERNAL_ERROR,
+            GatewayError::InvalidSponsorship(_) => &catalog::INVALID_SPONSORSHIP,
+            GatewayError::SponsorshipOutOfTimeRange(_) => &catalog::SPONSORSHIP_OUT_OF_TIME_RANGE,
+            GatewayError::UnsupportedAggregator(_) => &catalog::UNSUPPORTED_AGGREGATOR,
+            GatewayError::IdempotencyConflict(_) => &catalog::IDEMPOTENCY_CONFLICT,
+            GatewayError::InvalidParams(_) => &catalog::INVALID_PARAMS,
+            GatewayError::SenderDenied(_) => &catalog::SENDER_DENIED,
+            GatewayError::UnsupportedCapability(_) => &catalog::UNSUPPORTED_CAPABILITY,
+            GatewayError::EstimationFailed { code, .. } => catalog::estimation_entry(*code),
+            GatewayError::PoolRejected { code, .. } => catalog::pool_rejection_entry(*code),
+            GatewayError::BatchRejected { error, .. } => error.catalog_entry(),
+        }
+    }
+
+    /// Values of the catalog entry's placeholders
+    pub fn params(&self) -> Value {
+        match self {
+            GatewayError::InvalidRequest(reason)
+            | GatewayError::AuthenticationFailed(reason)
+            | GatewayError::PolicyViolation(reason)
+            | GatewayError::RundlerError(reason)
+            | GatewayError::PaymasterError(reason)
+            | GatewayError::PoolError(reason)
+            | GatewayError::ServerError(reason)
+            | GatewayError::JsonRpcError(reason)
+            | GatewayError::ValidationError(reason)
+            | GatewayError::InternalError(reason)
+            | GatewayError::InvalidSponsorship(reason)
+            | GatewayError::SponsorshipOutOfTimeRange(reason)
+            | GatewayError::IdempotencyConflict(reason)
+            | GatewayError::InvalidParams(reason)
+            | GatewayError::SenderDenied(reason)
+            | GatewayError::UnsupportedCapability(reason)
+            | GatewayError::EstimationFailed {
+                message: reason, ..
+            }
+            | GatewayError::PoolRejected {
+                message: reason, ..
+            } => json!({ "reason": reason }),
+            GatewayError::UnsupportedMethod(method) => json!({ "method": method }),
+            GatewayError::SponsorshipFailed(e) => e.params(),
+            GatewayError::BatchRejected { error, .. } => error.params(),
+            GatewayError::PreflightFailed(report) => json!({ "reason": report.to_string() }),
+            GatewayError::RateLimitExceeded | GatewayError::Timeout => json!({}),
+            GatewayError::DeadlineExceeded { stage, timeout_ms } => {
+                json!({ "stage": stage, "timeoutMs": timeout_ms })
+            }
+            GatewayError::LimitExceeded {
+                message,
+                retry_after_seconds,
+            } => json!({ "reason": message, "retryAfter": retry_after_seconds }),
+            GatewayError::UnsupportedAggregator(aggregator) => {
+                json!({ "aggregator": aggregator.to_checksum(None) })
+            }
+        }
+    }
+
+    fn pool_rejected(code: i32, message: impl ToString, data: Option<Value>) -> Self {
+        GatewayError::PoolRejected {
+            code,
+            message: message.to_string(),
+            data,
+        }
+    }
+}
+
+impl From<PoolError> for GatewayError {
+    fn from(err: PoolError) -> Self {
+        match err {
+            PoolError::MempoolError(e) => e.into(),
+            PoolError::UnexpectedResponse => {
+                GatewayError::PoolError("Unexpected response from pool".to_string())
+            }
+            PoolError::Other(e) => GatewayError::PoolError(e.to_string()),
+        }
+    }
+}
+
+// Exhaustive on purpose: a new mempool error must be given its ERC-4337 code
+impl From<MempoolError> for GatewayError {
+    fn from(err: MempoolError) -> Self {
+        let message = err.to_string();
+        match err {
+            MempoolError::Other(e) => GatewayError::PoolError(e.to_string()),
+            MempoolError::OperationAlreadyKnown => {
+                Self::pool_rejected(INVALID_PARAMS_CODE, "already known", None)
+            }
+            MempoolError::ReplacementUnderpriced(priority_fee, fee) => Self::pool_rejected(
+                INVALID_PARAMS_CODE,
+                "replacement underpriced",
+                Some(json!({
+                    "currentMaxPriorityFee": format!("0x{:x}", priority_fee),
+                    "currentMaxFee": format!("0x{:x}", fee),
+                })),
+            ),
+            MempoolError::MaxOperationsReached(_, entity) => {
+                Self::pool_rejected(STAKE_TOO_LOW_CODE, message, Some(json!(entity)))
+            }
+            MempoolError::EntityThrottled(entity) => {
+                Self::pool_rejected(THROTTLED_OR_BANNED_CODE, message, Some(json!(entity)))
+            }
+            MempoolError::MultipleRolesViolation(entity) => {
+                Self::pool_rejected(OPCODE_VIOLATION_CODE, message, Some(json!(entity)))
+            }
+            MempoolError::AssociatedStorageIsAlternateSender
+            | MempoolError::SenderAddressUsedAsAlternateEntity(_) => {
+                Self::pool_rejected(OPCODE_VIOLATION_CODE, message, None)
+            }
+            MempoolError::PaymasterBalanceTooLow(_, _) => {
+                Self::pool_rejected(PAYMASTER_DEPOSIT_TOO_LOW_CODE, message, None)
+            }
+            MempoolError::PrecheckViolation(_) => {
+                Self::pool_rejected(CALL_EXECUTION_FAILED_CODE, message, None)
+            }
+            MempoolError::SimulationViolation(violation) => violation.into(),
+            MempoolError::AggregatorError(_) => {
+                Self::pool_rejected(SIGNATURE_CHECK_FAILED_CODE, message, None)
+            }
+            MempoolError::UnknownEntryPoint(_) => {
+                Self::pool_rejected(ENTRYPOINT_VALIDATION_REJECTED_CODE, message, None)
+            }
+            MempoolError::DiscardedOnInsert
+            | MempoolError::OperationDropTooSoon(_, _, _)
+            | MempoolError::VerificationGasLimitEfficiencyTooLow(_, _)
+            | MempoolError::ExecutionGasLimitEfficiencyTooLow(_, _)
+            | MempoolError::TooManyExpectedStorageSlots(_, _)
+            | MempoolError::Invalid7702AuthSignature(_)
+            | MempoolError::EIPNotSupported(_) => {
+                Self::pool_rejected(INVALID_PARAMS_CODE, message, None)
+            }
+        }
+    }
+}
+
+impl From<GasEstimationError> for GatewayError {
+    fn from(err: GasEstimationError) -> Self {
+        let message = err.to_string();
+        match err {
+            GasEstimationError::RevertInValidation(_) | GasEstimationError::GasUsedTooLarge => {
+                Self::EstimationFailed {
+                    code: ENTRYPOINT_VALIDATION_REJECTED_CODE,
+                    message,
+                    data: None,
+                }
+            }
+            GasEstimationError::RevertInCallWithMessage(_) => Self::EstimationFailed {
+                code: EXECUTION_REVERTED_CODE,
+                message,
+                data: None,
+            },
+            GasEstimationError::RevertInCallWithBytes(revert_data) => Self::EstimationFailed {
+                code: EXECUTION_REVERTED_CODE,
+                message,
+                data: Some(json!({ "revertData": revert_data })),
+            },
+            GasEstimationError::GasFieldTooLarge(_, _)
+            | GasEstimationError::GasTotalTooLarge(_, _) => Self::InvalidParams(message),
+            GasEstimationError::UnsupportedAggregator(aggregator) => {
+                Self::UnsupportedAggregator(aggregator)
+            }
+            GasEstimationError::ProviderError(_) | GasEstimationError::Other(_) => {
+                Self::RundlerError(message)
+            }
+        }
+    }
+}
+
+impl From<SimulationViolation> for GatewayError {
+    fn from(violation: SimulationViolation) -> Self {
+        let message = format!("validation simulation failed: {}", violation);
+        match violation {
+            SimulationViolation::InvalidSignature
+            | SimulationViolation::InvalidAccountSignature
+            | SimulationViolation::InvalidPaymasterSignature
+            | SimulationViolation::AggregatorMismatch(_, _) => {
+                Self::pool_rejected(SIGNATURE_CHECK_FAILED_CODE, message, None)
+            }
+            SimulationViolation::InvalidTimeRange(valid_until, valid_after) => Self::pool_rejected(
+                OUT_OF_TIME_RANGE_CODE,
+                message,
+                Some(json!({
+                    "validUntil": valid_until.seconds_since_epoch(),
+                    "validAfter": valid_after.seconds_since_epoch(),
+                })),
+            ),
+            SimulationViolation::UnintendedRevertWithMessage(
+                EntityType::Paymaster,
+                _,
+                Some(paymaster),
+            )
+            | SimulationViolation::UnintendedRevert(EntityType::Paymaster, Some(paymaster)) => {
+                Self::pool_rejected(
+                    PAYMASTER_VALIDATION_REJECTED_CODE,
+                    message,
+                    Some(json!(Entity::paymaster(paymaster))),
+                )
+            }
+            SimulationViolation::UnintendedRevertWithMessage(_, _, _)
+            | SimulationViolation::UnintendedRevert(_, _)
+            | SimulationViolation::ValidationRevert(_)
+            | SimulationViolation::DidNotRevert
+            | SimulationViolation::WrongNumberOfPhases(_)
+            | SimulationViolation::CodeHashChanged
+            | SimulationViolation::VerificationGasLimitBufferTooLow(_, _) => {
+                Self::pool_rejected(ENTRYPOINT_VALIDATION_REJECTED_CODE, message, None)
+            }
+            SimulationViolation::UsedForbiddenOpcode(entity, _, _)
+            | SimulationViolation::UsedForbiddenPrecompile(entity, _, _)
+            | SimulationViolation::AccessedUndeployedContract(entity, _)
+            | SimulationViolation::InvalidStorageAccess(entity, _)
+            | SimulationViolation::CalledBannedEntryPointMethod(entity)
+            | SimulationViolation::CallHadValue(entity)
+            | SimulationViolation::OutOfGas(entity) => {
+                Self::pool_rejected(OPCODE_VIOLATION_CODE, message, Some(json!(entity)))
+            }
+            SimulationViolation::FactoryCalledCreate2Twice(factory) => Self::pool_rejected(
+                OPCODE_VIOLATION_CODE,
+                message,
+                Some(json!(Entity::factory(factory))),
+            ),
+            SimulationViolation::AssociatedStorageDuringDeploy(_, _)
+            | SimulationViolation::UnstakedPaymasterContext
+            | SimulationViolation::AccessedUnsupportedContractType(_, _) => {
+                Self::pool_rejected(OPCODE_VIOLATION_CODE, message, None)
+            }
+            SimulationViolation::NotStaked(stake) => Self::pool_rejected(
+                STAKE_TOO_LOW_CODE,
+                message,
+                Some(json!({
+                    "entity": stake.needs_stake,
+                    "minimumStake": format!("0x{:x}", stake.min_stake),
+                    "minimumUnstakeDelay": stake.min_unstake_delay,
+                })),
+            ),
+        }
+    }
 }
 
 impl From<anyhow::Error> for GatewayError {
@@ -71,3 +518,158 @@ impl From<anyhow::Error> for GatewayError {
 
 /// Result type for gateway operations
 pub type GatewayResult<T> = Result<T, GatewayError>;
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, Address, B256};
+    use rundler_types::{
+        chain::ChainSpec,
+        pool::{MempoolError, MockPool, PoolError, SimulationViolation},
+        v0_6, Entity, EntityType, UserOperationPermissions, UserOperationVariant,
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::router::add_op_to_pool;
+
+    const SENDER: Address = address!("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    const PAYMASTER: Address = address!("00000000000000000000000000000000000000aa");
+
+    fn user_op() -> UserOperationVariant {
+        UserOperationVariant::V0_6(
+            v0_6::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_6::UserOperationRequiredFields {
+                    sender: SENDER,
+                    ..Default::default()
+                },
+            )
+            .build(),
+        )
+    }
+
+    /// Pool rejecting every operation with `error`, unable to report reputations
+    fn rejecting_pool(error: fn() -> MempoolError) -> MockPool {
+        let mut pool = MockPool::new();
+        pool.expect_add_op()
+            .returning(move |_, _| Err(PoolError::MempoolError(error())));
+        pool.expect_get_reputation_status()
+            .returning(|_, _| Err(PoolError::UnexpectedResponse));
+        pool
+    }
+
+    async fn submit(pool: &MockPool) -> Result<B256, GatewayError> {
+        add_op_to_pool(pool, user_op(), UserOperationPermissions::default()).await
+    }
+
+    #[tokio::test]
+    async fn test_accepted_operation_returns_hash() {
+        let mut pool = MockPool::new();
+        pool.expect_add_op()
+            .returning(|_, _| Ok(B256::repeat_byte(0x11)));
+        assert_eq!(submit(&pool).await.unwrap(), B256::repeat_byte(0x11));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_operation() {
+        let err = submit(&rejecting_pool(|| MempoolError::OperationAlreadyKnown))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), INVALID_PARAMS_CODE);
+        assert_eq!(err.to_string(), "already known");
+        assert!(err.data().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_underpriced_replacement() {
+        let err = submit(&rejecting_pool(|| {
+            MempoolError::ReplacementUnderpriced(1_000, 2_000)
+        }))
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), INVALID_PARAMS_CODE);
+        assert_eq!(err.to_string(), "replacement underpriced");
+        assert_eq!(
+            err.data(),
+            Some(&json!({"currentMaxPriorityFee": "0x3e8", "currentMaxFee": "0x7d0"}))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_banned_entity_attached() {
+        let err = submit(&rejecting_pool(|| {
+            MempoolError::EntityThrottled(Entity::paymaster(PAYMASTER))
+        }))
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), THROTTLED_OR_BANNED_CODE);
+        assert_eq!(
+            err.data(),
+            Some(&json!({"paymaster": PAYMASTER.to_checksum(None)}))
+        );
+
+        let err = submit(&rejecting_pool(|| {
+            MempoolError::EntityThrottled(Entity::factory(Address::repeat_byte(0xfa)))
+        }))
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), THROTTLED_OR_BANNED_CODE);
+        assert!(err.data().unwrap().get("factory").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_sender_limit_reached() {
+        let err = submit(&rejecting_pool(|| {
+            MempoolError::MaxOperationsReached(4, Entity::account(SENDER))
+        }))
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), STAKE_TOO_LOW_CODE);
+        assert_eq!(
+            err.data(),
+            Some(&json!({"account": SENDER.to_checksum(None)}))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_simulation_failures() {
+        let err = submit(&rejecting_pool(|| {
+            MempoolError::SimulationViolation(SimulationViolation::UnintendedRevertWithMessage(
+                EntityType::Paymaster,
+                "AA33 reverted".to_string(),
+                Some(PAYMASTER),
+            ))
+        }))
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), PAYMASTER_VALIDATION_REJECTED_CODE);
+        assert_eq!(
+            err.data(),
+            Some(&json!({"paymaster": PAYMASTER.to_checksum(None)}))
+        );
+
+        let err = submit(&rejecting_pool(|| {
+            MempoolError::SimulationViolation(SimulationViolation::InvalidTimeRange(
+                100.into(),
+                50.into(),
+            ))
+        }))
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), OUT_OF_TIME_RANGE_CODE);
+        assert_eq!(
+            err.data(),
+            Some(&json!({"validUntil": 100, "validAfter": 50}))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_internal_pool_failure_stays_generic() {
+        let mut pool = MockPool::new();
+        pool.expect_add_op()
+            .returning(|_, _| Err(PoolError::Other(anyhow::anyhow!("pool server gone"))));
+        let err = submit(&pool).await.unwrap_err();
+        assert!(matches!(err, GatewayError::PoolError(_)), "{}", err);
+        assert_eq!(err.code(), -32603);
+    }
+}
@@ -0,0 +1,408 @@
+pub use rundler_paymaster_relay::ErrorCatalogEntry;
+use rundler_paymaster_relay::PAYMASTER_ERROR_CATALOG;
+use serde_json::{json, Value};
+
+use crate::error::{
+    CALL_EXECUTION_FAILED_CODE, DEADLINE_EXCEEDED_CODE, ENTRYPOINT_VALIDATION_REJECTED_CODE,
+    EXECUTION_REVERTED_CODE, INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE, LIMIT_EXCEEDED_CODE,
+    OPCODE_VIOLATION_CODE, OUT_OF_TIME_RANGE_CODE, PAYMASTER_DEPOSIT_TOO_LOW_CODE,
+    PAYMASTER_VALIDATION_REJECTED_CODE, SENDER_DENIED_CODE, SIGNATURE_CHECK_FAILED_CODE,
+    STAKE_TOO_LOW_CODE, THROTTLED_OR_BANNED_CODE, UNSUPPORTED_AGGREGATOR_CODE,
+    UNSUPPORTED_CAPABILITY_CODE,
+};
+
+const fn entry(
+    code: i32,
+    key: &'static str,
+    description: &'static str,
+    params: &'static [&'static str],
+) -> ErrorCatalogEntry {
+    ErrorCatalogEntry {
+        code,
+        key,
+        description,
+        params,
+    }
+}
+
+const REASON: &[&str] = &["reason"];
+
+// Errors of the JSON-RPC envelope, answered before a method runs
+
+/// The body is not a JSON-RPC request
+pub const PARSE_ERROR: ErrorCatalogEntry = entry(
+    -32700,
+    "request.parse_error",
+    "The body is not a valid JSON-RPC request",
+    &[],
+);
+/// Empty or oversized batch, or oversized body
+pub const INVALID_BATCH: ErrorCatalogEntry = entry(
+    -32600,
+    "request.invalid_batch",
+    "The request is not acceptable: {reason}",
+    REASON,
+);
+/// No such method on this listener
+pub const METHOD_NOT_FOUND: ErrorCatalogEntry = entry(
+    -32601,
+    "request.method_not_found",
+    "Method {method} is not available",
+    &["method"],
+);
+/// The API key or admin signature does not allow the method
+pub const UNAUTHORIZED: ErrorCatalogEntry = entry(
+    -32001,
+    "auth.unauthorized",
+    "The request is not authorized: {reason}",
+    REASON,
+);
+/// The requested chain id is malformed or not served
+pub const INVALID_CHAIN: ErrorCatalogEntry = entry(
+    -32602,
+    "request.invalid_chain",
+    "The requested chain is invalid or not served: {reason}",
+    REASON,
+);
+
+// GatewayError variants
+
+/// [`GatewayError::InvalidRequest`](crate::GatewayError::InvalidRequest)
+pub const INVALID_REQUEST: ErrorCatalogEntry = entry(
+    INTERNAL_ERROR_CODE,
+    "request.invalid",
+    "The request is malformed or refers to something unknown: {reason}",
+    REASON,
+);
+/// [`GatewayError::UnsupportedMethod`](crate::GatewayError::UnsupportedMethod)
+pub const UNSUPPORTED_METHOD: ErrorCatalogEntry = entry(
+    INTERNAL_ERROR_CODE,
+    "request.unsupported_method",
+    "Method {method} is not supported by this gateway",
+    &["method"],
+);
+/// [`GatewayError::AuthenticationFailed`](crate::GatewayError::AuthenticationFailed)
+pub const AUTHENTICATION_FAILED: ErrorCatalogEntry = entry(
+    INTERNAL_ERROR_CODE,
+    "auth.failed",
+    "Authentication failed: {reason}",
+    REASON,
+);
+/// [`GatewayError::RateLimitExceeded`](crate::GatewayError::RateLimitExceeded)
+pub const RATE_LIMIT_EXCEEDED: ErrorCatalogEntry = entry(
+    INTERNAL_ERROR_CODE,
+    "rate_limit.exceeded",
+    "Too many requests from this client",
+    &[],
+);
+/// [`GatewayError::PolicyViolation`](crate::GatewayError::PolicyViolation)
+pub const POLICY_VIOLATION: ErrorCatalogEntry = entry(
+    INTERNAL_ERROR_CODE,
+    "policy.violation",
+    "The operation violates a gateway policy: {reason}",
+    REASON,
+);
+/// [`GatewayError::RundlerError`](crate::GatewayError::RundlerError)
+pub const BUNDLER_ERROR: ErrorCatalogEntry = entry(
+    INTERNAL_ERROR_CODE,
+    "bundler.error",
+    "The bundler failed: {reason}",
+    REASON,
+);
+/// [`GatewayError::PaymasterError`](crate::GatewayError::PaymasterError)
+pub const PAYMASTER_ERROR: ErrorCatalogEntry = entry(
+    INTERNAL_ERROR_CODE,
+    "paymaster.error",
+    "The paymaster failed: {reason}",
+    REASON,
+);
+/// [`GatewayError::PoolError`](crate::GatewayError::PoolError)
+pub const POOL_ERROR: ErrorCatalogEntry = entry(
+    INTERNAL_ERROR_CODE,
+    "pool.error",
+    "The mempool could not be reached: {reason}",
+    REASON,
+);
+/// [`GatewayError::ServerError`](crate::GatewayError::ServerError)
+pub const SERVER_ERROR: ErrorCatalogEntry = entry(
+    INTERNAL_ERROR_CODE,
+    "server.error",
+    "The server failed: {reason}",
+    REASON,
+);
+/// [`GatewayError::PreflightFailed`](crate::GatewayError::PreflightFailed)
+pub const PREFLIGHT_FAILED: ErrorCatalogEntry = entry(
+    INTERNAL_ERROR_CODE,
+    "server.preflight_failed",
+    "Startup checks failed: {reason}",
+    REASON,
+);
+/// [`GatewayError::JsonRpcError`](crate::GatewayError::JsonRpcError)
+pub const JSON_RPC_ERROR: ErrorCatalogEntry = entry(
+    INTERNAL_ERROR_CODE,
+    "request.json_rpc",
+    "The JSON-RPC call failed: {reason}",
+    REASON,
+);
+/// [`GatewayError::Timeout`](crate::GatewayError::Timeout)
+pub const TIMEOUT: ErrorCatalogEntry = entry(
+    INTERNAL_ERROR_CODE,
+    "request.timeout",
+    "The request timed out",
+    &[],
+);
+/// [`GatewayError::DeadlineExceeded`](crate::GatewayError::DeadlineExceeded)
+pub const DEADLINE_EXCEEDED: ErrorCatalogEntry = entry(
+    DEADLINE_EXCEEDED_CODE,
+    "request.deadline_exceeded",
+    "The request deadline of {timeoutMs}ms passed during {stage}",
+    &["stage", "timeoutMs"],
+);
+/// [`GatewayError::LimitExceeded`](crate::GatewayError::LimitExceeded)
+pub const LIMIT_EXCEEDED: ErrorCatalogEntry = entry(
+    LIMIT_EXCEEDED_CODE,
+    "request.limit_exceeded",
+    "{reason}; retry after {retryAfter} seconds",
+    &["reason", "retryAfter"],
+);
+/// [`GatewayError::ValidationError`](crate::GatewayError::ValidationError)
+pub const VALIDATION_ERROR: ErrorCatalogEntry = entry(
+    INTERNAL_ERROR_CODE,
+    "validation.failed",
+    "The operation failed a gateway check: {reason}",
+    REASON,
+);
+/// [`GatewayError::InternalError`](crate::GatewayError::InternalError)
+pub const INTERNAL_ERROR: ErrorCatalogEntry = entry(
+    INTERNAL_ERROR_CODE,
+    "server.internal",
+    "Internal error: {reason}",
+    REASON,
+);
+/// [`GatewayError::InvalidSponsorship`](crate::GatewayError::InvalidSponsorship)
+pub const INVALID_SPONSORSHIP: ErrorCatalogEntry = entry(
+    PAYMASTER_VALIDATION_REJECTED_CODE,
+    "sponsorship.invalid_signature",
+    "The paymaster signature does not verify: {reason}",
+    REASON,
+);
+/// [`GatewayError::SponsorshipOutOfTimeRange`](crate::GatewayError::SponsorshipOutOfTimeRange)
+pub const SPONSORSHIP_OUT_OF_TIME_RANGE: ErrorCatalogEntry = entry(
+    OUT_OF_TIME_RANGE_CODE,
+    "sponsorship.out_of_time_range",
+    "The paymaster signature is outside its validity window: {reason}",
+    REASON,
+);
+/// [`GatewayError::UnsupportedAggregator`](crate::GatewayError::UnsupportedAggregator)
+pub const UNSUPPORTED_AGGREGATOR: ErrorCatalogEntry = entry(
+    UNSUPPORTED_AGGREGATOR_CODE,
+    "aggregator.unsupported",
+    "Signature aggregator {aggregator} is not supported",
+    &["aggregator"],
+);
+/// [`GatewayError::IdempotencyConflict`](crate::GatewayError::IdempotencyConflict)
+pub const IDEMPOTENCY_CONFLICT: ErrorCatalogEntry = entry(
+    -32507,
+    "idempotency.conflict",
+    "The idempotency key was already used for a different UserOperation: {reason}",
+    REASON,
+);
+/// [`GatewayError::InvalidParams`](crate::GatewayError::InvalidParams)
+pub const INVALID_PARAMS: ErrorCatalogEntry = entry(
+    INVALID_PARAMS_CODE,
+    "request.invalid_params",
+    "Invalid parameters: {reason}",
+    REASON,
+);
+/// [`GatewayError::SenderDenied`](crate::GatewayError::SenderDenied)
+pub const SENDER_DENIED: ErrorCatalogEntry = entry(
+    SENDER_DENIED_CODE,
+    "policy.sender_not_allowed",
+    "The sender is not allowed by this gateway: {reason}",
+    REASON,
+);
+/// [`GatewayError::UnsupportedCapability`](crate::GatewayError::UnsupportedCapability)
+pub const UNSUPPORTED_CAPABILITY: ErrorCatalogEntry = entry(
+    UNSUPPORTED_CAPABILITY_CODE,
+    "capability.unsupported",
+    "This deployment does not support the request: {reason}",
+    REASON,
+);
+
+// GatewayError::EstimationFailed, by code
+
+/// Validation reverted while estimating gas
+pub const ESTIMATION_VALIDATION_REVERTED: ErrorCatalogEntry = entry(
+    ENTRYPOINT_VALIDATION_REJECTED_CODE,
+    "estimation.validation_reverted",
+    "Validation reverted during gas estimation: {reason}",
+    REASON,
+);
+/// The operation's call reverted while estimating gas
+pub const ESTIMATION_EXECUTION_REVERTED: ErrorCatalogEntry = entry(
+    EXECUTION_REVERTED_CODE,
+    "estimation.execution_reverted",
+    "The operation's call reverted during gas estimation: {reason}",
+    REASON,
+);
+
+// GatewayError::PoolRejected, by code
+
+/// The mempool refused the operation's parameters
+pub const POOL_INVALID_OPERATION: ErrorCatalogEntry = entry(
+    INVALID_PARAMS_CODE,
+    "pool.invalid_operation",
+    "The mempool refused the operation: {reason}",
+    REASON,
+);
+/// A precheck of the operation failed
+pub const POOL_PRECHECK_FAILED: ErrorCatalogEntry = entry(
+    CALL_EXECUTION_FAILED_CODE,
+    "pool.precheck_failed",
+    "A precheck of the operation failed: {reason}",
+    REASON,
+);
+/// The EntryPoint rejected the operation during validation
+pub const POOL_ENTRY_POINT_REJECTED: ErrorCatalogEntry = entry(
+    ENTRYPOINT_VALIDATION_REJECTED_CODE,
+    "pool.entry_point_rejected",
+    "The EntryPoint rejected the operation: {reason}",
+    REASON,
+);
+/// The paymaster rejected the operation during validation
+pub const POOL_PAYMASTER_REJECTED: ErrorCatalogEntry = entry(
+    PAYMASTER_VALIDATION_REJECTED_CODE,
+    "pool.paymaster_rejected",
+    "The paymaster rejected the operation: {reason}",
+    REASON,
+);
+/// Validation used a banned opcode or storage access
+pub const POOL_OPCODE_VIOLATION: ErrorCatalogEntry = entry(
+    OPCODE_VIOLATION_CODE,
+    "pool.opcode_violation",
+    "Validation broke the opcode or storage rules: {reason}",
+    REASON,
+);
+/// The operation is valid only in a different time range
+pub const POOL_OUT_OF_TIME_RANGE: ErrorCatalogEntry = entry(
+    OUT_OF_TIME_RANGE_CODE,
+    "pool.out_of_time_range",
+    "The operation is not valid at this time: {reason}",
+    REASON,
+);
+/// An entity of the operation is throttled or banned
+pub const POOL_THROTTLED_OR_BANNED: ErrorCatalogEntry = entry(
+    THROTTLED_OR_BANNED_CODE,
+    "pool.throttled_or_banned",
+    "An entity of the operation is throttled or banned: {reason}",
+    REASON,
+);
+/// An entity's stake or unstake delay is too low
+pub const POOL_STAKE_TOO_LOW: ErrorCatalogEntry = entry(
+    STAKE_TOO_LOW_CODE,
+    "pool.stake_too_low",
+    "An entity's stake is too low: {reason}",
+    REASON,
+);
+/// A signature of the operation is invalid
+pub const POOL_SIGNATURE_FAILED: ErrorCatalogEntry = entry(
+    SIGNATURE_CHECK_FAILED_CODE,
+    "pool.signature_failed",
+    "A signature of the operation is invalid: {reason}",
+    REASON,
+);
+/// The paymaster's deposit does not cover the operation
+pub const POOL_PAYMASTER_DEPOSIT_TOO_LOW: ErrorCatalogEntry = entry(
+    PAYMASTER_DEPOSIT_TOO_LOW_CODE,
+    "pool.paymaster_deposit_too_low",
+    "The paymaster's deposit is too low: {reason}",
+    REASON,
+);
+
+/// Every error the gateway itself reports
+pub const GATEWAY_ERROR_CATALOG: &[ErrorCatalogEntry] = &[
+    PARSE_ERROR,
+    INVALID_BATCH,
+    METHOD_NOT_FOUND,
+    UNAUTHORIZED,
+    INVALID_CHAIN,
+    INVALID_REQUEST,
+    UNSUPPORTED_METHOD,
+    AUTHENTICATION_FAILED,
+    RATE_LIMIT_EXCEEDED,
+    POLICY_VIOLATION,
+    BUNDLER_ERROR,
+    PAYMASTER_ERROR,
+    POOL_ERROR,
+    SERVER_ERROR,
+    PREFLIGHT_FAILED,
+    JSON_RPC_ERROR,
+    TIMEOUT,
+    DEADLINE_EXCEEDED,
+    LIMIT_EXCEEDED,
+    VALIDATION_ERROR,
+    INTERNAL_ERROR,
+    INVALID_SPONSORSHIP,
+    SPONSORSHIP_OUT_OF_TIME_RANGE,
+    UNSUPPORTED_AGGREGATOR,
+    IDEMPOTENCY_CONFLICT,
+    INVALID_PARAMS,
+    SENDER_DENIED,
+    UNSUPPORTED_CAPABILITY,
+    ESTIMATION_VALIDATION_REVERTED,
+    ESTIMATION_EXECUTION_REVERTED,
+    POOL_INVALID_OPERATION,
+    POOL_PRECHECK_FAILED,
+    POOL_ENTRY_POINT_REJECTED,
+    POOL_PAYMASTER_REJECTED,
+    POOL_OPCODE_VIOLATION,
+    POOL_OUT_OF_TIME_RANGE,
+    POOL_THROTTLED_OR_BANNED,
+    POOL_STAKE_TOO_LOW,
+    POOL_SIGNATURE_FAILED,
+    POOL_PAYMASTER_DEPOSIT_TOO_LOW,
+];
+
+/// Catalog served by `GET /errors` and `superRelay_getErrorCatalog`
+///
+/// Paymaster errors carry the codes of the paymaster's own API; a failed
+/// pm_sponsorUserOperation on the gateway is reported with -32603 and the
+/// paymaster error's key.
+pub fn error_catalog() -> Value {
+    json!({
+        "gateway": GATEWAY_ERROR_CATALOG,
+        "paymaster": PAYMASTER_ERROR_CATALOG,
+    })
+}
+
+/// Entry a [`GatewayError::EstimationFailed`](crate::GatewayError::EstimationFailed) with `code` is reported as
+///
+/// Every code the conversions produce has its own entry, which the
+/// catalog tests check.
+pub(crate) fn estimation_entry(code: i32) -> &'static ErrorCatalogEntry {
+    match code {
+        ENTRYPOINT_VALIDATION_REJECTED_CODE => &ESTIMATION_VALIDATION_REVERTED,
+        EXECUTION_REVERTED_CODE => &ESTIMATION_EXECUTION_REVERTED,
+        _ => &ESTIMATION_VALIDATION_REVERTED,
+    }
+}
+
+/// Entry a [`GatewayError::PoolRejected`](crate::GatewayError::PoolRejected) with `code` is reported as
+///
+/// Every code the conversions produce has its own entry, which the
+/// catalog tests check.
+pub(crate) fn pool_rejection_entry(code: i32) -> &'static ErrorCatalogEntry {
+    match code {
+        INVALID_PARAMS_CODE => &POOL_INVALID_OPERATION,
+        CALL_EXECUTION_FAILED_CODE => &POOL_PRECHECK_FAILED,
+        ENTRYPOINT_VALIDATION_REJECTED_CODE => &POOL_ENTRY_POINT_REJECTED,
+        PAYMASTER_VALIDATION_REJECTED_CODE => &POOL_PAYMASTER_REJECTED,
+        OPCODE_VIOLATION_CODE => &POOL_OPCODE_VIOLATION,
+        OUT_OF_TIME_RANGE_CODE => &POOL_OUT_OF_TIME_RANGE,
+        THROTTLED_OR_BANNED_CODE => &POOL_THROTTLED_OR_BANNED,
+        STAKE_TOO_LOW_CODE => &POOL_STAKE_TOO_LOW,
+        SIGNATURE_CHECK_FAILED_CODE => &POOL_SIGNATURE_FAILED,
+        PAYMASTER_DEPOSIT_TOO_LOW_CODE => &POOL_PAYMASTER_DEPOSIT_TOO_LOW,
+        _ => &POOL_INVALID_OPERATION,
+    }
+}
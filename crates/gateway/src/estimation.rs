@@ -0,0 +1,388 @@
+use std::sync::Arc;
+
+use alloy_primitives::Address;
+use async_trait::async_trait;
+use rundler_provider::{AccountOverride, StateOverride};
+use rundler_types::{v0_6, v0_7, GasEstimate, UserOperationOptionalGas};
+use serde_json::{json, Value};
+
+use crate::error::{GatewayError, GatewayResult};
+
+/// Backend answering eth_estimateUserOperationGas
+#[async_trait]
+pub trait GasEstimator: Send + Sync {
+    /// Gas limits for `user_op` on `entry_point`, with `state_override` applied
+    /// on top of the latest block
+    async fn estimate_user_operation_gas(
+        &self,
+        user_op: UserOperationOptionalGas,
+        entry_point: Address,
+        state_override: StateOverride,
+    ) -> GatewayResult<GasEstimate>;
+
+    /// Whether operations on `entry_point` can be estimated at all
+    fn supports(&self, _entry_point: Address) -> bool {
+        true
+    }
+}
+
+/// rundler estimator of v0.6 UserOperations
+pub type GasEstimatorV0_6 =
+    dyn rundler_sim::GasEstimator<UserOperationOptionalGas = v0_6::UserOperationOptionalGas>;
+/// rundler estimator of v0.7 UserOperations
+pub type GasEstimatorV0_7 =
+    dyn rundler_sim::GasEstimator<UserOperationOptionalGas = v0_7::UserOperationOptionalGas>;
+
+/// [`GasEstimator`] running rundler's simulation-based estimation, one
+/// estimator per EntryPoint version
+///
+/// The pre-verification gas of an operation with an EIP-7702 auth address is
+/// estimated from rundler's max/random fills, which include the auth tuple and
+/// its gas cost.
+#[derive(Default)]
+pub struct SimGasEstimator {
+    v0_6: Option<(Address, Arc<GasEstimatorV0_6>)>,
+    v0_7: Option<(Address, Arc<GasEstimatorV0_7>)>,
+}
+
+impl SimGasEstimator {
+    /// Create an estimator without any EntryPoint
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Estimate v0.6 operations on `entry_point` with `estimator`
+    pub fn with_v0_6(mut self, entry_point: Address, estimator: Arc<GasEstimatorV0_6>) -> Self {
+        self.v0_6 = Some((entry_point, estimator));
+        self
+    }
+
+    /// Estimate v0.7 operations on `entry_point` with `estimator`
+    pub fn with_v0_7(mut self, entry_point: Address, estimator: Arc<GasEstimatorV0_7>) -> Self {
+        self.v0_7 = Some((entry_point, estimator));
+        self
+    }
+}
+
+#[async_trait]
+impl GasEstimator for SimGasEstimator {
+    async fn estimate_user_operation_gas(
+        &self,
+        user_op: UserOperationOptionalGas,
+        entry_point: Address,
+        state_override: StateOverride,
+    ) -> GatewayResult<GasEstimate> {
+        let unsupported = || {
+            GatewayError::InvalidParams(format!(
+                "Invalid user operation for entry point: {:#x}",
+                entry_point
+            ))
+        };
+        let estimate = match user_op {
+            UserOperationOptionalGas::V0_6(op) => match &self.v0_6 {
+                Some((address, estimator)) if *address == entry_point => {
+                    estimator.estimate_op_gas(op, state_override).await
+                }
+                _ => return Err(unsupported()),
+            },
+            UserOperationOptionalGas::V0_7(op) => match &self.v0_7 {
+                Some((address, estimator)) if *address == entry_point => {
+                    estimator.estimate_op_gas(op, state_override).await
+                }
+                _ => return Err(unsupported()),
+            },
+        };
+        Ok(estimate?)
+    }
+
+    fn supports(&self, entry_point: Address) -> bool {
+        self.v0_6
+            .as_ref()
+            .is_some_and(|(address, _)| *address == entry_point)
+            || self
+                .v0_7
+                .as_ref()
+                .is_some_and(|(address, _)| *address == entry_point)
+    }
+}
+
+/// JSON result of eth_estimateUserOperationGas, in the shape of `user_op`'s version
+pub(crate) fn gas_estimate_to_json(
+    user_op: &UserOperationOptionalGas,
+    estimate: &GasEstimate,
+) -> Value {
+    let mut result = json!({
+        "preVerificationGas": format!("0x{:x}", estimate.pre_verification_gas),
+        "verificationGasLimit": format!("0x{:x}", estimate.verification_gas_limit),
+        "callGasLimit": format!("0x{:x}", estimate.call_gas_limit),
+    });
+    if let UserOperationOptionalGas::V0_7(_) = user_op {
+        result["paymasterVerificationGasLimit"] = estimate
+            .paymaster_verification_gas_limit
+            .map_or(Value::Null, |limit| json!(format!("0x{:x}", limit)));
+    }
+    result
+}
+
+/// Parse the state override map at `params[index]`
+///
+/// Each key must be an account address and each value an account override;
+/// an account cannot override both its full `state` and a `stateDiff`.
+pub(crate) fn parse_state_override(value: &Value, index: usize) -> GatewayResult<StateOverride> {
+    let invalid =
+        |message: String| GatewayError::InvalidParams(format!("params[{}]: {}", index, message));
+
+    let accounts = value
+        .as_object()
+        .ok_or_else(|| invalid("state override must be an object".to_string()))?;
+
+    let mut state_override = StateOverride::default();
+    for (key, account) in accounts {
+        let address: Address = key
+            .parse()
+            .map_err(|_| invalid(format!("invalid account address {}", key)))?;
+        let account: AccountOverride = serde_json::from_value(account.clone())
+            .map_err(|e| invalid(format!("invalid override for {}: {}", key, e)))?;
+        if account.state.is_some() && account.state_diff.is_some() {
+            return Err(invalid(format!(
+                "override for {} sets both state and stateDiff",
+                key
+            )));
+        }
+        state_override.insert(address, account);
+    }
+    Ok(state_override)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy_primitives::{Address, Bytes, B256, U256};
+    use alloy_sol_types::SolValue;
+    use rundler_contracts::common::EstimationTypes::TestCallGasResult;
+    use rundler_provider::{
+        ExecutionResult, MockEntryPointV0_7, MockEvmProvider, MockFeeEstimator, StateOverride,
+    };
+    use rundler_sim::{EstimationSettings, GasEstimatorV0_7};
+    use rundler_types::{chain::ChainSpec, ValidationRevert};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{test_utils, GatewayRouter, VersionSelector};
+
+    #[test]
+    fn test_state_override_parsed() {
+        let state_override = parse_state_override(
+            &json!({
+                "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266": {
+                    "balance": "0xde0b6b3a7640000",
+                    "stateDiff": {
+                        "0x0000000000000000000000000000000000000000000000000000000000000001":
+                            "0x0000000000000000000000000000000000000000000000000000000000000002"
+                    }
+                }
+            }),
+            2,
+        )
+        .unwrap();
+        let sender: Address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+            .parse()
+            .unwrap();
+        let account = &state_override[&sender];
+        assert_eq!(account.balance.unwrap().to::<u128>(), 10u128.pow(18));
+        assert_eq!(account.state_diff.as_ref().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_state_override_names_index() {
+        for invalid in [
+            json!([]),
+            json!({ "0x1234": { "balance": "0x1" } }),
+            json!({ "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266": { "state": { "0x01": "0x02" } } }),
+            json!({ "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266": { "state": {}, "stateDiff": {} } }),
+        ] {
+            let err = parse_state_override(&invalid, 2).unwrap_err();
+            assert_eq!(err.code(), crate::error::INVALID_PARAMS_CODE);
+            assert!(err.to_string().contains("params[2]"), "{}", err);
+        }
+    }
+
+    const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    /// Router estimating v0.7 operations against a mocked EntryPoint whose
+    /// validation only passes when the sender's balance is overridden
+    fn router() -> GatewayRouter {
+        let chain_spec = ChainSpec::default();
+        let sender: Address = SENDER.parse().unwrap();
+
+        let mut entry_point = MockEntryPointV0_7::new();
+        entry_point
+            .expect_address()
+            .return_const(chain_spec.entry_point_address_v0_7);
+        entry_point
+            .expect_simulation_should_revert()
+            .return_const(true);
+        entry_point
+            .expect_simulate_handle_op_estimate_gas()
+            .returning(
+                move |_op, _target, _data, _block, state_override: StateOverride| {
+                    if state_override
+                        .get(&sender)
+                        .is_some_and(|account| account.balance.is_some())
+                    {
+                        Ok(Ok(ExecutionResult {
+                            target_result: TestCallGasResult {
+                                success: true,
+                                gasUsed: U256::ZERO,
+                                revertData: Bytes::new(),
+                            }
+                            .abi_encode()
+                            .into(),
+                            target_success: true,
+                            ..Default::default()
+                        }))
+                    } else {
+                        Ok(Err(ValidationRevert::EntryPoint(
+                            "AA21 didn't pay prefund".to_string(),
+                        )))
+                    }
+                },
+            );
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((B256::ZERO, 0)));
+
+        let settings = EstimationSettings {
+            max_verification_gas: 10_000_000,
+            max_paymaster_verification_gas: 10_000_000,
+            max_paymaster_post_op_gas: 10_000_000,
+            max_bundle_execution_gas: 25_000_000,
+            max_gas_estimation_gas: 25_000_000,
+            verification_estimation_gas_fee: 1_000_000_000_000,
+            verification_gas_limit_efficiency_reject_threshold: 0.5,
+            verification_gas_allowed_error_pct: 15,
+            call_gas_allowed_error_pct: 15,
+            max_gas_estimation_rounds: 3,
+        };
+        let estimator = GasEstimatorV0_7::new(
+            chain_spec.clone(),
+            Arc::new(provider),
+            Arc::new(entry_point),
+            settings,
+            MockFeeEstimator::new(),
+        );
+
+        GatewayRouter::new()
+            .with_version_selector(Arc::new(VersionSelector::from_chain_spec(&chain_spec)))
+            .with_gas_estimator(Arc::new(
+                SimGasEstimator::new()
+                    .with_v0_7(chain_spec.entry_point_address_v0_7, Arc::new(estimator)),
+            ))
+    }
+
+    /// v0.7 operation with its verification and call limits set, leaving the
+    /// pre-verification gas to be estimated
+    fn user_op() -> Value {
+        json!({
+            "sender": SENDER,
+            "nonce": "0x0",
+            "callData": "0xb61d27f6",
+            "callGasLimit": "0x186a0",
+            "verificationGasLimit": "0x186a0",
+            "maxFeePerGas": "0x3b9aca00",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "signature": "0x",
+        })
+    }
+
+    fn auth() -> Value {
+        json!({
+            "chainId": "0x0",
+            "address": "0x63c0c19a282a1B52b07dD5a65b58948A07DAE32B",
+            "nonce": "0x0",
+            "yParity": "0x0",
+            "r": "0x1",
+            "s": "0x1",
+        })
+    }
+
+    async fn estimate(router: &GatewayRouter, params: Vec<Value>) -> Result<Value, GatewayError> {
+        let request = test_utils::request("eth_estimateUserOperationGas", params);
+        router.route_to_rundler(&request).await
+    }
+
+    fn entry_point() -> Value {
+        json!(format!(
+            "{:#x}",
+            ChainSpec::default().entry_point_address_v0_7
+        ))
+    }
+
+    fn hex_u128(value: &Value) -> u128 {
+        u128::from_str_radix(value.as_str().unwrap().trim_start_matches("0x"), 16).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_balance_override_lets_estimation_succeed() {
+        let router = router();
+
+        let err = estimate(&router, vec![user_op(), entry_point()])
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), -32500);
+        assert!(err.to_string().contains("AA21"), "{}", err);
+
+        let estimate = estimate(
+            &router,
+            vec![
+                user_op(),
+                entry_point(),
+                json!({ SENDER: { "balance": "0xde0b6b3a7640000" } }),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(estimate["callGasLimit"], json!("0x186a0"));
+        assert!(hex_u128(&estimate["preVerificationGas"]) > 0);
+    }
+
+    #[tokio::test]
+    async fn test_eip7702_auth_raises_pre_verification_gas() {
+        let router = router();
+        let balance = json!({ SENDER: { "balance": "0xde0b6b3a7640000" } });
+
+        let plain = estimate(&router, vec![user_op(), entry_point(), balance.clone()])
+            .await
+            .unwrap();
+        let mut delegated_op = user_op();
+        delegated_op["eip7702Auth"] = auth();
+        let delegated = estimate(&router, vec![delegated_op, entry_point(), balance])
+            .await
+            .unwrap();
+
+        assert!(
+            hex_u128(&delegated["preVerificationGas"]) > hex_u128(&plain["preVerificationGas"]),
+            "{} <= {}",
+            delegated["preVerificationGas"],
+            plain["preVerificationGas"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_override_names_param_index() {
+        let router = router();
+
+        for state_override in [
+            json!({ "0xnot-an-address": { "balance": "0x1" } }),
+            json!({ SENDER: { "stateDiff": { "0x01": "0x02" } } }),
+        ] {
+            let err = estimate(&router, vec![user_op(), entry_point(), state_override])
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), -32602);
+            assert!(err.to_string().contains("params[2]"), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,573 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::{Address, B256};
+use axum::response::sse::Event;
+use metrics::counter;
+use rundler_builder::{BuilderEvent, BuilderEventKind};
+use rundler_utils::emit::{self, WithEntryPoint};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    task::JoinHandle,
+};
+
+use crate::{
+    alerts::Alert,
+    error::{GatewayError, GatewayResult},
+};
+
+/// Pseudo-method whose scope GET /admin/events requires
+pub const EVENT_STREAM_METHOD: &str = "admin_streamEvents";
+
+/// Live event stream configuration (`[gateway.events]`)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct EventStreamConfig {
+    /// Events buffered per subscriber; a subscriber falling further behind
+    /// loses the oldest
+    pub capacity: usize,
+    /// Seconds between keepalive comments on an idle stream
+    pub keepalive_seconds: u64,
+}
+
+impl Default for EventStreamConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            keepalive_seconds: 15,
+        }
+    }
+}
+
+/// How a JSON-RPC request ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestOutcome {
+    /// Answered with a result
+    Success,
+    /// Answered with a JSON-RPC error
+    Error,
+}
+
+/// Event streamed to GET /admin/events subscribers
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum GatewayEvent {
+    /// A JSON-RPC call was received
+    RequestStarted {
+        /// Correlation id of the request
+        request_id: String,
+        /// Called method
+        method: String,
+    },
+    /// A JSON-RPC call was answered
+    RequestFinished {
+        /// Correlation id of the request
+        request_id: String,
+        /// Called method
+        method: String,
+        /// Time to answer, in milliseconds
+        duration_ms: u64,
+        /// Whether the call succeeded
+        outcome: RequestOutcome,
+        /// JSON-RPC error code of a failed call
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error_code: Option<i64>,
+    },
+    /// A sponsorship check rejected an operation or could not decide
+    PipelineStageFailed {
+        /// Name of the pipeline module
+        stage: String,
+        /// Sender of the checked operation
+        sender: Address,
+        /// EntryPoint the operation targets
+        entry_point: Address,
+        /// Why the check failed
+        error: String,
+        /// The check could not decide and the operation went on
+        failed_open: bool,
+    },
+    /// An operation was added to the pool
+    PoolSubmission {
+        /// Hash of the operation
+        user_op_hash: B256,
+        /// Sender of the operation
+        sender: Address,
+        /// EntryPoint the operation targets
+        entry_point: Address,
+        /// Trust level of the submitting API key
+        #[serde(skip_serializing_if = "Option::is_none")]
+        trust_level: Option<String>,
+    },
+    /// The bundle builder reported progress
+    Bundle {
+        /// EntryPoint of the builder
+        entry_point: Address,
+        /// Builder tag
+        builder: String,
+        /// Builder event kind, e.g. formed_bundle or transaction_mined
+        kind: String,
+        /// Bundle transaction, when the event has one
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tx_hash: Option<B256>,
+        /// Human readable description of the event
+        description: String,
+    },
+    /// An operator alert was queued for delivery
+    Alert(Alert),
+}
+
+impl GatewayEvent {
+    /// Every event type, as named in the `types` filter
+    pub const TYPES: [&'static str; 6] = [
+        "request_started",
+        "request_finished",
+        "pipeline_stage_failed",
+        "pool_submission",
+        "bundle",
+        "alert",
+    ];
+
+    /// SSE event type of this event
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            GatewayEvent::RequestStarted { .. } => "request_started",
+            GatewayEvent::RequestFinished { .. } => "request_finished",
+            GatewayEvent::PipelineStageFailed { .. } => "pipeline_stage_failed",
+            GatewayEvent::PoolSubmission { .. } => "pool_submission",
+            GatewayEvent::Bundle { .. } => "bundle",
+            GatewayEvent::Alert(_) => "alert",
+        }
+    }
+
+    /// Event describing a builder event
+    pub fn from_builder_event(event: &WithEntryPoint<BuilderEvent>) -> Self {
+        let (kind, tx_hash) = match &event.event.kind {
+            BuilderEventKind::FormedBundle { tx_details, .. } => (
+                "formed_bundle",
+                tx_details.as_ref().map(|details| details.tx_hash),
+            ),
+            BuilderEventKind::TransactionMined { tx_hash, .. } => {
+                ("transaction_mined", Some(*tx_hash))
+            }
+            BuilderEventKind::LatestTransactionDropped { .. } => {
+                ("latest_transaction_dropped", None)
+            }
+            BuilderEventKind::NonceUsedForOtherTransaction { .. } => {
+                ("nonce_used_for_other_transaction", None)
+            }
+            BuilderEventKind::SkippedOp { .. } => ("skipped_op", None),
+            BuilderEventKind::RejectedOp { .. } => ("rejected_op", None),
+        };
+        GatewayEvent::Bundle {
+            entry_point: event.entry_point,
+            builder: event.event.tag.clone(),
+            kind: kind.to_string(),
+            tx_hash,
+            description: event.event.to_string(),
+        }
+    }
+}
+
+/// Event as delivered to subscribers, numbered in publication order
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamedEvent {
+    /// Sequence number; gaps mean the subscriber missed events
+    pub id: u64,
+    /// Unix time of publication, in milliseconds
+    pub timestamp_ms: u64,
+    /// The event, with its type in `type`
+    #[serde(flatten)]
+    pub event: GatewayEvent,
+}
+
+impl StreamedEvent {
+    /// SSE message carrying this event as JSON
+    pub fn to_sse(&self) -> Event {
+        Event::default()
+            .id(self.id.to_string())
+            .event(self.event.event_type())
+            .data(serde_json::to_string(self).unwrap_or_default())
+    }
+}
+
+/// Query parameters of GET /admin/events
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EventStreamQuery {
+    /// Comma separated event types to receive, every type when unset
+    pub types: Option<String>,
+}
+
+/// Event types one subscriber receives
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    /// `None` for every type
+    types: Option<HashSet<&'static str>>,
+}
+
+impl EventFilter {
+    /// Filter from the comma separated `types`, every type when unset or empty
+    pub fn parse(types: Option<&str>) -> GatewayResult<Self> {
+        let Some(types) = types.map(str::trim).filter(|types| !types.is_empty()) else {
+            return Ok(Self::default());
+        };
+        let types = types
+            .split(',')
+            .map(|name| {
+                let name = name.trim();
+                GatewayEvent::TYPES
+                    .into_iter()
+                    .find(|t| *t == name)
+                    .ok_or_else(|| {
+                        GatewayError::InvalidRequest(format!(
+                            "Unknown event type '{}', expected one of {}",
+                            name,
+                            GatewayEvent::TYPES.join(", ")
+                        ))
+                    })
+            })
+            .collect::<GatewayResult<_>>()?;
+        Ok(Self { types: Some(types) })
+    }
+
+    /// Whether `event` passes the filter
+    pub fn matches(&self, event: &GatewayEvent) -> bool {
+        self.types
+            .as_ref()
+            .is_none_or(|types| types.contains(event.event_type()))
+    }
+}
+
+struct EventBusInner {
+    config: EventStreamConfig,
+    sender: broadcast::Sender<Arc<StreamedEvent>>,
+    next_id: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// Bounded broadcast of gateway events to live subscribers
+///
+/// Publishing never waits: with no subscriber an event is discarded, and a
+/// subscriber more than `capacity` events behind loses the oldest ones, which
+/// are counted as dropped.
+#[derive(Clone)]
+pub struct EventBus {
+    inner: Arc<EventBusInner>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(EventStreamConfig::default())
+    }
+}
+
+impl EventBus {
+    /// Bus buffering `config.capacity` events per subscriber
+    pub fn new(config: EventStreamConfig) -> Self {
+        let (sender, _) = broadcast::channel(config.capacity.max(1));
+        Self {
+            inner: Arc::new(EventBusInner {
+                config,
+                sender,
+                next_id: AtomicU64::new(1),
+                dropped: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Configuration of this bus
+    pub fn config(&self) -> &EventStreamConfig {
+        &self.inner.config
+    }
+
+    /// Deliver `event` to the current subscribers
+    pub fn publish(&self, event: GatewayEvent) {
+        if self.inner.sender.receiver_count() == 0 {
+            return;
+        }
+        let streamed = StreamedEvent {
+            id: self.inner.next_id.fetch_add(1, Ordering::Relaxed),
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            event,
+        };
+        // Only fails when the last subscriber left in the meantime
+        let _ = self.inner.sender.send(Arc::new(streamed));
+    }
+
+    /// Receive events passing `filter` published from now on
+    pub fn subscribe(&self, filter: EventFilter) -> EventSubscription {
+        EventSubscription {
+            receiver: self.inner.sender.subscribe(),
+            filter,
+            bus: self.inner.clone(),
+        }
+    }
+
+    /// Live subscribers
+    pub fn subscribers(&self) -> usize {
+        self.inner.sender.receiver_count()
+    }
+
+    /// Events subscribers lost by falling behind, summed over subscribers
+    pub fn dropped(&self) -> u64 {
+        self.inner.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Publish every builder event broadcast on `events` until the channel closes
+    pub fn forward_builder_events(
+        &self,
+        events: broadcast::Receiver<WithEntryPoint<BuilderEvent>>,
+    ) -> JoinHandle<()> {
+        let bus = self.clone();
+        tokio::spawn(emit::receive_events("event stream", events, move |event| {
+            bus.publish(GatewayEvent::from_builder_event(&event))
+        }))
+    }
+}
+
+/// Events received by one subscriber
+pub struct EventSubscription {
+    receiver: broadcast::Receiver<Arc<StreamedEvent>>,
+    filter: EventFilter,
+    bus: Arc<EventBusInner>,
+}
+
+impl EventSubscription {
+    /// Next event passing the filter, `None` once the bus is gone
+    pub async fn next(&mut self) -> Option<Arc<StreamedEvent>> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) if self.filter.matches(&event.event) => return Some(event),
+                Ok(_) => {}
+                Err(RecvError::Lagged(missed)) => {
+                    self.bus.dropped.fetch_add(missed, Ordering::Relaxed);
+                    counter!("superrelay_gateway_events_dropped_total").increment(missed);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::Value;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+        net::{TcpListener, TcpStream},
+        time::timeout,
+    };
+
+    use super::*;
+    use crate::{test_utils, PaymasterGateway};
+
+    const UNKNOWN_METHOD: &str = r#"{"jsonrpc":"2.0","id":1,"method":"foo_bar","params":[]}"#;
+
+    async fn spawn_gateway() -> std::net::SocketAddr {
+        let app = PaymasterGateway::new(test_utils::admin_gateway_config(), None)
+            .app()
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    /// Open GET /admin/events with `query` and return the stream past the headers
+    async fn subscribe(addr: std::net::SocketAddr, query: &str) -> BufReader<TcpStream> {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                format!(
+                    "GET /admin/events{} HTTP/1.1\r\nHost: localhost\r\nx-api-key: {}\r\nAccept: text/event-stream\r\n\r\n",
+                    query,
+                    test_utils::ADMIN_KEY
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        assert!(line.starts_with("HTTP/1.1 200"), "{}", line);
+        loop {
+            line.clear();
+            reader.read_line(&mut line).await.unwrap();
+            if line == "\r\n" {
+                return reader;
+            }
+        }
+    }
+
+    /// Next SSE event as its `event:` name and parsed `data:`, skipping keepalives
+    async fn next_event(reader: &mut BufReader<TcpStream>) -> (String, Value) {
+        let (mut name, mut data) = (None, None);
+        loop {
+            let mut line = String::new();
+            timeout(Duration::from_secs(5), reader.read_line(&mut line))
+                .await
+                .expect("event within 5s")
+                .unwrap();
+            // Lines of the chunked transfer framing carry no SSE field
+            if let Some(value) = line.strip_prefix("event:") {
+                name = Some(value.trim().to_string());
+            } else if let Some(value) = line.strip_prefix("data:") {
+                data = Some(serde_json::from_str(value.trim()).unwrap());
+            } else if line.trim().is_empty() {
+                if let (Some(name), Some(data)) = (name.take(), data.take()) {
+                    return (name, data);
+                }
+            }
+        }
+    }
+
+    async fn post(addr: std::net::SocketAddr, body: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                format!(
+                    "POST / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+                 Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    /// Wait until the gateway has registered `count` subscribers on its bus
+    async fn wait_for_subscribers(bus: &EventBus, count: usize) {
+        for _ in 0..100 {
+            if bus.subscribers() >= count {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("{} subscribers expected, got {}", count, bus.subscribers());
+    }
+
+    #[tokio::test]
+    async fn test_request_events_streamed() {
+        let gateway = PaymasterGateway::new(test_utils::admin_gateway_config(), None);
+        let bus = gateway.events();
+        let app = gateway.app().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut all = subscribe(addr, "").await;
+        let mut finished = subscribe(addr, "?types=request_finished").await;
+        wait_for_subscribers(&bus, 2).await;
+
+        post(addr, UNKNOWN_METHOD).await;
+
+        let (name, started) = next_event(&mut all).await;
+        assert_eq!(name, "request_started");
+        assert_eq!(started["type"], "request_started");
+        assert_eq!(started["method"], "foo_bar");
+        let (name, done) = next_event(&mut all).await;
+        assert_eq!(name, "request_finished");
+        assert_eq!(done["requestId"], started["requestId"]);
+        assert_eq!(done["outcome"], "error");
+        assert_eq!(done["errorCode"], -32601);
+        assert!(done["id"].as_u64().unwrap() > started["id"].as_u64().unwrap());
+
+        // The filtered subscriber only sees the finished event
+        let (name, filtered) = next_event(&mut finished).await;
+        assert_eq!(name, "request_finished");
+        assert_eq!(filtered, done);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_event_type_rejected() {
+        let addr = spawn_gateway().await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                format!(
+                    "GET /admin/events?types=request_finished,bogus HTTP/1.1\r\nHost: localhost\r\nx-api-key: {}\r\nConnection: close\r\n\r\n",
+                    test_utils::ADMIN_KEY
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+        assert!(
+            response.contains("Unknown event type 'bogus'"),
+            "{}",
+            response
+        );
+    }
+
+    #[test]
+    fn test_event_filter() {
+        let finished = GatewayEvent::RequestFinished {
+            request_id: "r".to_string(),
+            method: "eth_chainId".to_string(),
+            duration_ms: 1,
+            outcome: RequestOutcome::Success,
+            error_code: None,
+        };
+        assert!(EventFilter::parse(None).unwrap().matches(&finished));
+        assert!(EventFilter::parse(Some(" ")).unwrap().matches(&finished));
+        let filter = EventFilter::parse(Some("bundle, request_finished")).unwrap();
+        assert!(filter.matches(&finished));
+        assert!(!EventFilter::parse(Some("alert"))
+            .unwrap()
+            .matches(&finished));
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_loses_oldest_events() {
+        let bus = EventBus::new(EventStreamConfig {
+            capacity: 4,
+            ..Default::default()
+        });
+        let event = |i: usize| GatewayEvent::RequestStarted {
+            request_id: i.to_string(),
+            method: "eth_chainId".to_string(),
+        };
+        // Without subscribers publishing is a no-op
+        bus.publish(event(0));
+
+        let mut subscription = bus.subscribe(EventFilter::default());
+        for i in 1..=10 {
+            bus.publish(event(i));
+        }
+        let first = subscription.next().await.unwrap();
+        assert_eq!(bus.dropped(), 6);
+        assert_eq!(first.event, event(7));
+        for i in 8..=10 {
+            assert_eq!(subscription.next().await.unwrap().event, event(i));
+        }
+        assert_eq!(bus.subscribers(), 1);
+        drop(subscription);
+        assert_eq!(bus.subscribers(), 0);
+    }
+}
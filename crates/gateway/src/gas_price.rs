@@ -0,0 +1,456 @@
+use std::sync::Arc;
+
+use alloy_primitives::B256;
+use async_trait::async_trait;
+use rundler_provider::{BlockId, EvmProvider, FeeEstimator};
+use rundler_types::GasFees;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::debug;
+
+use crate::error::{GatewayError, GatewayResult};
+
+/// Fee suggestion tiers of superRelay_getUserOperationGasPrice (`[gateway.gas_price]`)
+///
+/// Each tier is the fee the mempool requires of an operation, from the bundle
+/// fees of the latest block, increased by the tier's percentage.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GasPriceConfig {
+    /// Percentage added to the required fees for the slow tier
+    pub slow_increase_percent: u32,
+    /// Percentage added to the required fees for the standard tier
+    pub standard_increase_percent: u32,
+    /// Percentage added to the required fees for the fast tier
+    pub fast_increase_percent: u32,
+}
+
+impl Default for GasPriceConfig {
+    fn default() -> Self {
+        Self {
+            slow_increase_percent: 10,
+            standard_increase_percent: 25,
+            fast_increase_percent: 50,
+        }
+    }
+}
+
+impl GasPriceConfig {
+    /// Check the tiers are ordered slow, standard, fast
+    pub fn validate(&self) -> GatewayResult<()> {
+        if self.slow_increase_percent > self.standard_increase_percent
+            || self.standard_increase_percent > self.fast_increase_percent
+        {
+            return Err(GatewayError::InvalidRequest(format!(
+                "Gas price tiers must increase: slow {}%, standard {}%, fast {}%",
+                self.slow_increase_percent,
+                self.standard_increase_percent,
+                self.fast_increase_percent
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Source of the latest block the fee quote is based on
+#[async_trait]
+pub trait BlockHeadSource: Send + Sync {
+    /// Number and hash of the latest block
+    async fn latest_block(&self) -> GatewayResult<(u64, B256)>;
+}
+
+/// [`BlockHeadSource`] backed by a node provider
+pub struct EvmBlockHeadSource<P> {
+    provider: P,
+}
+
+impl<P: EvmProvider> EvmBlockHeadSource<P> {
+    /// Read the latest block through `provider`
+    pub fn new(provider: P) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> BlockHeadSource for EvmBlockHeadSource<P> {
+    async fn latest_block(&self) -> GatewayResult<(u64, B256)> {
+        let block = self
+            .provider
+            .get_block(BlockId::latest())
+            .await
+            .map_err(|e| GatewayError::RundlerError(format!("Failed to get latest block: {}", e)))?
+            .ok_or_else(|| {
+                GatewayError::RundlerError("Node returned no latest block".to_string())
+            })?;
+        Ok((block.header.number, block.header.hash))
+    }
+}
+
+/// Fees of one suggestion tier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasPriceTier {
+    /// EIP-1559 max fee per gas, in wei
+    #[serde(serialize_with = "hex_quantity")]
+    pub max_fee_per_gas: u128,
+    /// EIP-1559 max priority fee per gas, in wei
+    #[serde(serialize_with = "hex_quantity")]
+    pub max_priority_fee_per_gas: u128,
+}
+
+/// Fee suggestion of superRelay_getUserOperationGasPrice
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasPriceQuote {
+    /// Cheapest tier, included once the base fee stays put
+    pub slow: GasPriceTier,
+    /// Default tier
+    pub standard: GasPriceTier,
+    /// Tier with the most headroom for base fee increases
+    pub fast: GasPriceTier,
+    /// Pending base fee of the bundle, in wei
+    #[serde(serialize_with = "hex_quantity")]
+    pub base_fee: u128,
+    /// Latest block the quote is based on
+    #[serde(serialize_with = "hex_quantity")]
+    pub block_number: u64,
+}
+
+fn hex_quantity<S: serde::Serializer, T: std::fmt::LowerHex>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("0x{:x}", value))
+}
+
+/// Fee suggestions from the shared fee estimator, computed once per block
+pub struct GasPriceOracle {
+    config: GasPriceConfig,
+    fee_estimator: Arc<dyn FeeEstimator>,
+    blocks: Arc<dyn BlockHeadSource>,
+    cached: Mutex<Option<(B256, GasPriceQuote)>>,
+}
+
+impl GasPriceOracle {
+    /// Oracle quoting `fee_estimator`'s fees for the latest block of `blocks`
+    pub fn new(
+        config: GasPriceConfig,
+        fee_estimator: Arc<dyn FeeEstimator>,
+        blocks: Arc<dyn BlockHeadSource>,
+    ) -> GatewayResult<Self> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            fee_estimator,
+            blocks,
+            cached: Mutex::new(None),
+        })
+    }
+
+    /// Fee tiers for the latest block, reused until the next block
+    pub async fn quote(&self) -> GatewayResult<GasPriceQuote> {
+        let (block_number, block_hash) = self.blocks.latest_block().await?;
+
+        // Holding the lock while computing lets concurrent callers share one quote
+        let mut cached = self.cached.lock().await;
+        if let Some((hash, quote)) = cached.as_ref() {
+            if *hash == block_hash {
+                return Ok(quote.clone());
+            }
+        }
+
+        let (bundle_fees, base_fee) = self
+            .fee_estimator
+            .required_bundle_fees(block_hash, None)
+            .await
+            .map_err(|e| GatewayError::RundlerError(format!("Failed to estimate fees: {}", e)))?;
+        // The least the mempool accepts, per the chain's priority fee mode
+        let required = self.fee_estimator.required_op_fees(bundle_fees);
+        debug!(
+            "Gas price for block {}: base fee {}, required {:?}",
+            block_number, base_fee, required
+        );
+
+        let slow = tier(required, base_fee, self.config.slow_increase_percent, None);
+        let standard = tier(
+            required,
+            base_fee,
+            self.config.standard_increase_percent,
+            Some(slow),
+        );
+        let fast = tier(
+            required,
+            base_fee,
+            self.config.fast_increase_percent,
+            Some(standard),
+        );
+        let quote = GasPriceQuote {
+            slow,
+            standard,
+            fast,
+            base_fee,
+            block_number,
+        };
+        *cached = Some((block_hash, quote.clone()));
+        Ok(quote)
+    }
+}
+
+/// `required` increased by `percent`, covering the base fee and never below `previous`
+fn tier(
+    required: GasFees,
+    base_fee: u128,
+    percent: u32,
+    previous: Option<GasPriceTier>,
+) -> GasPriceTier {
+    let fees = required.increase_by_percent(percent);
+    let mut max_priority_fee_per_gas = fees.max_priority_fee_per_gas;
+    let mut max_fee_per_gas = fees
+        .max_fee_per_gas
+        .max(base_fee.saturating_add(max_priority_fee_per_gas));
+    // With base_fee_percent the required priority fee can round to the same
+    // value across tiers; a faster tier never pays less
+    if let Some(previous) = previous {
+        max_priority_fee_per_gas = max_priority_fee_per_gas.max(previous.max_priority_fee_per_gas);
+        max_fee_per_gas = max_fee_per_gas.max(previous.max_fee_per_gas);
+    }
+    GasPriceTier {
+        max_fee_per_gas,
+        max_priority_fee_per_gas,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    use alloy_primitives::B256;
+    use async_trait::async_trait;
+    use rundler_provider::FeeEstimator;
+    use rundler_types::{GasFees, PriorityFeeMode};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{test_utils, GatewayResult, GatewayRouter};
+
+    const GWEI: u128 = 1_000_000_000;
+
+    /// Fee estimator with settable network fees that counts its estimates
+    struct TestFeeEstimator {
+        mode: PriorityFeeMode,
+        /// Base fee and network priority fee
+        fees: Mutex<(u128, u128)>,
+        estimates: AtomicUsize,
+    }
+
+    impl TestFeeEstimator {
+        fn new(mode: PriorityFeeMode, base_fee: u128, priority_fee: u128) -> Self {
+            Self {
+                mode,
+                fees: Mutex::new((base_fee, priority_fee)),
+                estimates: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl FeeEstimator for TestFeeEstimator {
+        async fn required_bundle_fees(
+            &self,
+            _block_hash: B256,
+            min_fees: Option<GasFees>,
+        ) -> anyhow::Result<(GasFees, u128)> {
+            assert!(min_fees.is_none());
+            self.estimates.fetch_add(1, Ordering::SeqCst);
+            self.latest_bundle_fees().await
+        }
+
+        async fn latest_bundle_fees(&self) -> anyhow::Result<(GasFees, u128)> {
+            let (base_fee, priority_fee) = *self.fees.lock().unwrap();
+            Ok((
+                GasFees {
+                    max_fee_per_gas: base_fee + priority_fee,
+                    max_priority_fee_per_gas: priority_fee,
+                },
+                base_fee,
+            ))
+        }
+
+        fn required_op_fees(&self, bundle_fees: GasFees) -> GasFees {
+            self.mode.required_fees(bundle_fees)
+        }
+    }
+
+    /// Latest block, advanced by the tests
+    struct TestBlocks {
+        number: Mutex<u64>,
+    }
+
+    impl TestBlocks {
+        fn advance(&self) {
+            *self.number.lock().unwrap() += 1;
+        }
+    }
+
+    #[async_trait]
+    impl BlockHeadSource for TestBlocks {
+        async fn latest_block(&self) -> GatewayResult<(u64, B256)> {
+            let number = *self.number.lock().unwrap();
+            Ok((number, B256::left_padding_from(&number.to_be_bytes())))
+        }
+    }
+
+    fn oracle(estimator: Arc<TestFeeEstimator>) -> (GasPriceOracle, Arc<TestBlocks>) {
+        let blocks = Arc::new(TestBlocks {
+            number: Mutex::new(100),
+        });
+        let oracle =
+            GasPriceOracle::new(GasPriceConfig::default(), estimator, blocks.clone()).unwrap();
+        (oracle, blocks)
+    }
+
+    fn tier(max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> GasPriceTier {
+        GasPriceTier {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        }
+    }
+
+    fn assert_increasing(tiers: [GasPriceTier; 3]) {
+        for pair in tiers.windows(2) {
+            assert!(
+                pair[0].max_fee_per_gas < pair[1].max_fee_per_gas
+                    && pair[0].max_priority_fee_per_gas < pair[1].max_priority_fee_per_gas,
+                "{:?}",
+                tiers
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tiers_with_priority_fee_increase_mode() {
+        let estimator = Arc::new(TestFeeEstimator::new(
+            PriorityFeeMode::PriorityFeeIncreasePercent(0),
+            10 * GWEI,
+            GWEI,
+        ));
+        let (oracle, _) = oracle(estimator);
+
+        let quote = oracle.quote().await.unwrap();
+        // Required: 11 gwei max fee, 1 gwei priority fee, plus 10/25/50%
+        assert_eq!(quote.slow, tier(12_100_000_000, 1_100_000_000));
+        assert_eq!(quote.standard, tier(13_750_000_000, 1_250_000_000));
+        assert_eq!(quote.fast, tier(16_500_000_000, 1_500_000_000));
+        assert_eq!(quote.base_fee, 10 * GWEI);
+        assert_eq!(quote.block_number, 100);
+        assert_increasing([quote.slow, quote.standard, quote.fast]);
+    }
+
+    #[tokio::test]
+    async fn test_tiers_with_base_fee_percent_mode_stay_ordered() {
+        let estimator = Arc::new(TestFeeEstimator::new(
+            PriorityFeeMode::BaseFeePercent(50),
+            10 * GWEI,
+            GWEI,
+        ));
+        let (oracle, _) = oracle(estimator);
+
+        // The required priority fee follows the base fee, not the network priority fee
+        let quote = oracle.quote().await.unwrap();
+        assert_eq!(quote.slow, tier(16_500_000_000, 5_500_000_000));
+        assert_eq!(quote.standard, tier(18_750_000_000, 6_250_000_000));
+        assert_eq!(quote.fast, tier(22_500_000_000, 7_500_000_000));
+        assert_increasing([quote.slow, quote.standard, quote.fast]);
+
+        // Tiers never decrease, even when rounding leaves nothing to add
+        let estimator = Arc::new(TestFeeEstimator::new(
+            PriorityFeeMode::BaseFeePercent(1),
+            100,
+            0,
+        ));
+        let (oracle, _) = oracle(estimator);
+        let quote = oracle.quote().await.unwrap();
+        let tiers = [quote.slow, quote.standard, quote.fast];
+        for pair in tiers.windows(2) {
+            assert!(pair[0].max_fee_per_gas <= pair[1].max_fee_per_gas);
+            assert!(pair[0].max_priority_fee_per_gas <= pair[1].max_priority_fee_per_gas);
+        }
+        for tier in tiers {
+            assert!(tier.max_fee_per_gas >= quote.base_fee + tier.max_priority_fee_per_gas);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quote_cached_until_next_block() {
+        let estimator = Arc::new(TestFeeEstimator::new(
+            PriorityFeeMode::PriorityFeeIncreasePercent(0),
+            10 * GWEI,
+            GWEI,
+        ));
+        let (oracle, blocks) = oracle(estimator.clone());
+
+        let first = oracle.quote().await.unwrap();
+        // Network fees move within the block: the block's quote is kept
+        *estimator.fees.lock().unwrap() = (20 * GWEI, 2 * GWEI);
+        for _ in 0..3 {
+            assert_eq!(oracle.quote().await.unwrap(), first);
+        }
+        assert_eq!(estimator.estimates.load(Ordering::SeqCst), 1);
+
+        blocks.advance();
+        let next = oracle.quote().await.unwrap();
+        assert_eq!(estimator.estimates.load(Ordering::SeqCst), 2);
+        assert_eq!(next.block_number, 101);
+        assert_eq!(next.base_fee, 20 * GWEI);
+        assert_eq!(next.slow, tier(24_200_000_000, 2_200_000_000));
+        assert_eq!(oracle.quote().await.unwrap(), next);
+        assert_eq!(estimator.estimates.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_gas_price_rpc() {
+        let estimator = Arc::new(TestFeeEstimator::new(
+            PriorityFeeMode::PriorityFeeIncreasePercent(0),
+            10 * GWEI,
+            GWEI,
+        ));
+        let (oracle, _) = oracle(estimator);
+        let request = test_utils::request("superRelay_getUserOperationGasPrice", vec![]);
+
+        let router = GatewayRouter::new().with_gas_price_oracle(Arc::new(oracle));
+        let result = router.route_to_super_relay(&request).await.unwrap();
+        assert_eq!(
+            result,
+            json!({
+                "slow": {"maxFeePerGas": "0x2d1375900", "maxPriorityFeePerGas": "0x4190ab00"},
+                "standard": {"maxFeePerGas": "0x333905980", "maxPriorityFeePerGas": "0x4a817c80"},
+                "fast": {"maxFeePerGas": "0x3d77a0500", "maxPriorityFeePerGas": "0x59682f00"},
+                "baseFee": "0x2540be400",
+                "blockNumber": "0x64",
+            })
+        );
+
+        assert!(GatewayRouter::new()
+            .route_to_super_relay(&request)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_tiers_must_not_decrease() {
+        let config = GasPriceConfig {
+            slow_increase_percent: 30,
+            standard_increase_percent: 20,
+            fast_increase_percent: 50,
+        };
+        let err = config.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid request: Gas price tiers must increase: slow 30%, standard 20%, fast 50%"
+        );
+        assert!(GasPriceConfig::default().validate().is_ok());
+    }
+}
@@ -1,27 +1,76 @@
-use std::sync::Arc;
+use std::{
+    convert::Infallible,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::Json,
+    body::Body,
+    extract::{rejection::JsonRejection, DefaultBodyLimit, OriginalUri, Query, State},
+    http::{header::CONTENT_TYPE, HeaderMap, HeaderValue, StatusCode},
+    response::{
+        sse::{KeepAlive, Sse},
+        IntoResponse, Json, Response,
+    },
     routing::{get, post},
     Router,
 };
-use rundler_paymaster_relay::PaymasterRelayService;
-use rundler_pool::LocalPoolHandle;
+use futures_util::{future::join_all, stream, StreamExt};
+use metrics_exporter_prometheus::PrometheusHandle;
+use rundler_paymaster_relay::{ErrorCatalogEntry, PaymasterRelayService, VerificationProofStore};
+use rundler_types::{aggregator::SignatureAggregator, builder::Builder, chain::ContractRegistry};
 use serde_json::Value;
 use tokio::net::TcpListener;
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
-use tracing::{debug, error, info, warn};
+use tower_http::trace::TraceLayer;
+use tracing::{debug, error, field, info, info_span, warn, Instrument, Span};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
+    admin_listener::is_admin_method,
+    alerts::AlertBus,
     api_docs::CompleteApiDoc,
+    authorization::SenderAccessControl,
+    batch_sponsorship::BatchSponsorshipConfig,
+    bundle_simulation::BundleSimulator,
+    bundle_tracker::BundleTracker,
+    cache::{ResponseCache, NO_CACHE_HEADER},
+    chains::{chain_id_field, requested_chain_id, ChainRegistry, ChainRoute},
+    conditional::ConditionalSendConfig,
+    connection_limit::{overloaded_response, ConnectionLimiter},
+    deadline::Deadline,
     e2e_validator::quick_e2e_health_check,
-    error::{GatewayError, GatewayResult},
-    health::health_routes,
+    entry_point_status::ContractCodeReader,
+    error::{GatewayError, GatewayResult, LIMIT_EXCEEDED_CODE},
+    error_catalog::{self as catalog, error_catalog},
+    estimation::GasEstimator,
+    events::{
+        EventBus, EventFilter, EventStreamQuery, GatewayEvent, RequestOutcome, EVENT_STREAM_METHOD,
+    },
+    gas_price::GasPriceOracle,
+    health::{
+        health_routes, BuilderProbe, ClockSkewProbe, HealthChecker, HealthProbe, PolicyProbe,
+        PoolProbe, ReputationProbe, SignerProbe,
+    },
+    method_registry::{self, MethodHandler},
+    metrics::record_request,
+    middleware::{ApiKeyScope, AuthMiddleware},
+    nonce::NonceReader,
+    op_permissions::OpPermissionsConfig,
+    pipeline::ModulePipeline,
+    pool_export::{PoolExportQuery, POOL_EXPORT_METHOD},
+    pool_supervisor::SupervisedPool,
+    preflight::{run_preflight, PreflightReport},
+    receipt::UserOperationReceiptProvider,
+    request_id::{attach_to_error, request_id, REQUEST_ID_HEADER},
+    request_log::RequestLogger,
     router::{EthApiConfig, GatewayRouter},
+    rpc_schema::{rpc_schema, RPC_SCHEMA_PATH},
+    shutdown::{serve_with_connection_limits, serve_with_graceful_shutdown, ShutdownController},
+    signature::SignatureValidator,
+    threat_feed::ThreatIntelStore,
+    usage_export::{UsageExportQuery, USAGE_EXPORT_METHOD},
+    version_selector::VersionSelector,
     GatewayConfig,
 };
 
@@ -31,6 +80,15 @@ pub struct PaymasterGateway {
     config: GatewayConfig,
     paymaster_service: Option<Arc<PaymasterRelayService>>,
     router: GatewayRouter,
+    shutdown: ShutdownController,
+    health: HealthChecker,
+    extra_chains: Vec<ChainRoute>,
+    default_chain_id: Option<u64>,
+    prometheus: Option<PrometheusHandle>,
+    access_control: Option<Arc<SenderAccessControl>>,
+    alerts: AlertBus,
+    connection_limiter: Arc<ConnectionLimiter>,
+    events: EventBus,
 }
 
 /// Gateway state shared across requests
@@ -42,6 +100,28 @@ pub struct GatewayState {
     pub router: GatewayRouter,
     /// Gateway configuration
     pub config: GatewayConfig,
+    /// API key authentication
+    pub auth: AuthMiddleware,
+    /// Graceful shutdown state, used to fail health checks while draining
+    pub shutdown: ShutdownController,
+    /// Dependency probes backing /health and /ready
+    pub health: HealthChecker,
+    /// Per-chain routers and paymasters, selected by chain id
+    pub chains: ChainRegistry,
+    /// Sampled, redacted request and response logging
+    pub request_log: RequestLogger,
+    /// Process-wide Prometheus recorder rendered on /metrics
+    pub prometheus: Option<PrometheusHandle>,
+    /// Sender allowlist/denylist checked before routing
+    pub access_control: Option<Arc<SenderAccessControl>>,
+    /// Operator alerts, fired on demand by admin_testAlert
+    pub alerts: AlertBus,
+    /// Connection and expensive request limits
+    pub connection_limiter: Arc<ConnectionLimiter>,
+    /// Live events streamed on GET /admin/events
+    pub events: EventBus,
+    /// Whether this listener serves admin-scoped methods
+    pub admin_methods: bool,
 }
 
 impl PaymasterGateway {
@@ -50,12 +130,30 @@ impl PaymasterGateway {
         config: GatewayConfig,
         paymaster_service: Option<Arc<PaymasterRelayService>>,
     ) -> Self {
-        let router = GatewayRouter::new();
+        let events = EventBus::new(config.events.clone());
+        let mut router = GatewayRouter::new()
+            .with_limits(config.validation.clone())
+            .with_events(events.clone());
+        if let Some(service) = &paymaster_service {
+            router = router.with_paymaster_service(service.clone());
+        }
+        let health = Self::default_health_checker(&config, paymaster_service.as_ref(), None);
+
+        let connection_limiter = Arc::new(ConnectionLimiter::new(config.connection_limits.clone()));
 
         Self {
             config,
             paymaster_service,
             router,
+            shutdown: ShutdownController::new(),
+            health,
+            extra_chains: Vec::new(),
+            default_chain_id: None,
+            prometheus: None,
+            access_control: None,
+            alerts: AlertBus::disabled(),
+            connection_limiter,
+            events,
         }
     }
 
@@ -63,30 +161,421 @@ impl PaymasterGateway {
     pub fn with_rundler_components(
         config: GatewayConfig,
         paymaster_service: Option<Arc<PaymasterRelayService>>,
-        pool_handle: Arc<LocalPoolHandle>,
+        pool_handle: impl Into<SupervisedPool>,
         eth_config: EthApiConfig,
     ) -> Self {
-        let router = GatewayRouter::with_rundler_components(pool_handle, eth_config);
+        let pool_handle = pool_handle.into();
+        let health = Self::default_health_checker(
+            &config,
+            paymaster_service.as_ref(),
+            Some(pool_handle.clone()),
+        );
+        let events = EventBus::new(config.events.clone());
+        let mut router = GatewayRouter::with_rundler_components(pool_handle, eth_config)
+            .with_limits(config.validation.clone())
+            .with_events(events.clone());
+        if let Some(service) = &paymaster_service {
+            router = router.with_paymaster_service(service.clone());
+        }
+
+        let connection_limiter = Arc::new(ConnectionLimiter::new(config.connection_limits.clone()));
 
         Self {
             config,
             paymaster_service,
             router,
+            shutdown: ShutdownController::new(),
+            health,
+            extra_chains: Vec::new(),
+            default_chain_id: None,
+            prometheus: None,
+            access_control: None,
+            alerts: AlertBus::disabled(),
+            connection_limiter,
+            events,
+        }
+    }
+
+    /// Probes for the components the gateway holds itself
+    fn default_health_checker(
+        config: &GatewayConfig,
+        paymaster_service: Option<&Arc<PaymasterRelayService>>,
+        pool_handle: Option<SupervisedPool>,
+    ) -> HealthChecker {
+        let mut health = HealthChecker::with_config(config.health.clone());
+        if let Some(pool_handle) = &pool_handle {
+            health = health
+                .with_probe(Arc::new(PoolProbe::new(pool_handle.clone())))
+                .with_probe(Arc::new(BuilderProbe::new(None)));
+        }
+        if let Some(service) = paymaster_service {
+            health = health
+                .with_probe(Arc::new(SignerProbe::new(service.clone())))
+                .with_probe(Arc::new(PolicyProbe::new(service.clone())));
+            if let Some(clock_guard) = service.clock_guard() {
+                health = health.with_probe(Arc::new(ClockSkewProbe::new(clock_guard.clone())));
+            }
+            if let Some(pool_handle) = pool_handle {
+                health =
+                    health.with_probe(Arc::new(ReputationProbe::new(pool_handle, service.clone())));
+            }
         }
+        health
+    }
+
+    /// Add a readiness probe, e.g. for the node or EntryPoint deposits
+    pub fn with_health_probe(mut self, probe: Arc<dyn HealthProbe>) -> Self {
+        self.health = self.health.with_probe(probe);
+        self
+    }
+
+    /// Attach an on-chain receipt provider for eth_getUserOperationReceipt
+    pub fn with_receipt_provider(
+        mut self,
+        receipt_provider: Arc<dyn UserOperationReceiptProvider>,
+    ) -> Self {
+        self.router = self.router.with_receipt_provider(receipt_provider);
+        self
+    }
+
+    /// Attach an on-chain nonce reader for eth_getUserOperationNonce
+    pub fn with_nonce_reader(mut self, nonce_reader: Arc<dyn NonceReader>) -> Self {
+        self.router = self.router.with_nonce_reader(nonce_reader);
+        self
+    }
+
+    /// Check operations against feed-backed threat intelligence
+    pub fn with_threat_intel(mut self, threat_intel: Arc<ThreatIntelStore>) -> Self {
+        self.router = self.router.with_threat_intel(threat_intel);
+        self
+    }
+
+    /// Serve stored dual-signature verification proofs
+    pub fn with_verification_proofs(
+        mut self,
+        verification_proofs: Arc<VerificationProofStore>,
+    ) -> Self {
+        self.router = self.router.with_verification_proofs(verification_proofs);
+        self
+    }
+
+    /// Run sponsorship checks through `pipeline`
+    pub fn with_pipeline(mut self, pipeline: Arc<ModulePipeline>) -> Self {
+        self.router = self.router.with_pipeline(pipeline);
+        self
+    }
+
+    /// Serve idempotent read methods through `response_cache`
+    pub fn with_response_cache(mut self, response_cache: Arc<ResponseCache>) -> Self {
+        self.router = self.router.with_response_cache(response_cache);
+        self
+    }
+
+    /// Check account signatures with `signature_validator`
+    pub fn with_signature_validator(
+        mut self,
+        signature_validator: Arc<SignatureValidator>,
+    ) -> Self {
+        self.router = self.router.with_signature_validator(signature_validator);
+        self
+    }
+
+    /// Answer superRelay_simulateBundle with `simulator`
+    pub fn with_bundle_simulator(mut self, simulator: Arc<dyn BundleSimulator>) -> Self {
+        self.router = self.router.with_bundle_simulator(simulator);
+        self
+    }
+
+    /// Estimate gas with `gas_estimator`
+    pub fn with_gas_estimator(mut self, gas_estimator: Arc<dyn GasEstimator>) -> Self {
+        self.router = self.router.with_gas_estimator(gas_estimator);
+        self
+    }
+
+    /// Answer superRelay_getUserOperationGasPrice from `oracle`
+    pub fn with_gas_price_oracle(mut self, oracle: Arc<GasPriceOracle>) -> Self {
+        self.router = self.router.with_gas_price_oracle(oracle);
+        self
+    }
+
+    /// Drive rundler_sendBundleNow and debug_bundler_sendBundleNow/setBundlingMode
+    /// through `builder`, reporting it in /health
+    pub fn with_builder(mut self, builder: Arc<dyn Builder>) -> Self {
+        self.health = self
+            .health
+            .with_probe(Arc::new(BuilderProbe::new(Some(builder.clone()))));
+        self.router = self.router.with_builder(builder);
+        self
+    }
+
+    /// Grant submitted operations the pool permissions of their API key's trust level
+    pub fn with_op_permissions(mut self, op_permissions: OpPermissionsConfig) -> Self {
+        self.router = self.router.with_op_permissions(op_permissions);
+        self
+    }
+
+    /// Cap and default mode of pm_sponsorUserOperationBatch
+    pub fn with_batch_sponsorship(mut self, batch_sponsorship: BatchSponsorshipConfig) -> Self {
+        self.router = self.router.with_batch_sponsorship(batch_sponsorship);
+        self
+    }
+
+    /// Accept superRelay_sendUserOperationConditional as configured by `conditional_send`
+    pub fn with_conditional_send(mut self, conditional_send: ConditionalSendConfig) -> Self {
+        self.router = self.router.with_conditional_send(conditional_send);
+        self
+    }
+
+    /// Serve debug_bundler_* methods for the ERC-4337 bundler spec tests
+    pub fn with_debug_api(mut self, enabled: bool) -> Self {
+        self.router = self.router.with_debug_api(enabled);
+        self
+    }
+
+    /// Accept UserOperations aggregated by one of `signature_aggregators`
+    pub fn with_signature_aggregators(
+        mut self,
+        signature_aggregators: Arc<ContractRegistry<Arc<dyn SignatureAggregator>>>,
+    ) -> Self {
+        self.router = self
+            .router
+            .with_signature_aggregators(signature_aggregators);
+        self
+    }
+
+    /// Select EntryPoint versions with `version_selector`
+    pub fn with_version_selector(mut self, version_selector: Arc<VersionSelector>) -> Self {
+        self.router = self.router.with_version_selector(version_selector);
+        self
+    }
+
+    /// Serve bundle status and operation inclusion from `bundle_tracker`
+    pub fn with_bundle_tracker(mut self, bundle_tracker: Arc<BundleTracker>) -> Self {
+        self.router = self.router.with_bundle_tracker(bundle_tracker);
+        self
+    }
+
+    /// Check that EntryPoints have contract code through `code_reader` before advertising them
+    pub fn with_code_reader(mut self, code_reader: Arc<dyn ContractCodeReader>) -> Self {
+        self.router = self.router.with_code_reader(code_reader);
+        self
+    }
+
+    /// Serve another chain next to the one the gateway was created for
+    pub fn with_chain(mut self, mut route: ChainRoute) -> Self {
+        route.router = route.router.with_events(self.events.clone());
+        self.extra_chains.push(route);
+        self
+    }
+
+    /// Route requests that do not name a chain to `chain_id`
+    pub fn with_default_chain(mut self, chain_id: u64) -> Self {
+        self.default_chain_id = Some(chain_id);
+        self
+    }
+
+    /// Chains served by this gateway
+    pub fn chain_registry(&self) -> GatewayResult<ChainRegistry> {
+        let mut chains = ChainRegistry::new(ChainRoute::new(
+            self.router.clone(),
+            self.paymaster_service.clone(),
+        ));
+        for route in &self.extra_chains {
+            chains = chains.with_chain(route.clone());
+        }
+        match self.default_chain_id {
+            Some(chain_id) => chains.with_default_chain(chain_id),
+            None => Ok(chains),
+        }
+    }
+
+    /// Use a shared shutdown controller so the gateway drains with other services
+    pub fn with_shutdown(mut self, shutdown: ShutdownController) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Render the process-wide Prometheus recorder on /metrics
+    pub fn with_prometheus(mut self, handle: PrometheusHandle) -> Self {
+        self.prometheus = Some(handle);
+        self
+    }
+
+    /// Refuse denylisted (and in allowlist mode, unlisted) senders on every chain
+    pub fn with_access_control(mut self, access_control: Arc<SenderAccessControl>) -> Self {
+        self.access_control = Some(access_control);
+        self
+    }
+
+    /// Publish operator alerts from the health probes and the sponsorship path
+    pub fn with_alerts(mut self, alerts: AlertBus) -> Self {
+        let alerts = alerts.with_events(self.events.clone());
+        self.router = self.router.with_alerts(alerts.clone());
+        self.health = self.health.with_alerts(alerts.clone());
+        self.alerts = alerts;
+        self
+    }
+
+    /// Shutdown controller that stops this gateway when triggered
+    pub fn shutdown_handle(&self) -> ShutdownController {
+        self.shutdown.clone()
+    }
+
+    /// Live events of this gateway, for publishing events of other components
+    pub fn events(&self) -> EventBus {
+        self.events.clone()
+    }
+
+    /// Connection limiter of this gateway, for reloading its limits
+    pub fn connection_limiter(&self) -> Arc<ConnectionLimiter> {
+        self.connection_limiter.clone()
+    }
+
+    /// HTTP application of the public listener, serving JSON-RPC, health,
+    /// metrics and API docs
+    ///
+    /// Admin-scoped routes are only served here while the admin listener is
+    /// disabled and `allow_admin_on_public` is set.
+    pub fn app(&self) -> GatewayResult<Router> {
+        let chains = self.chain_registry()?;
+        info!(
+            "⛓️  Serving chains {:?} (default {})",
+            chains.chain_ids(),
+            chains.default_chain_id()
+        );
+
+        let admin_on_public = self.config.admin.admin_on_public();
+        let state = self.state(chains, admin_on_public);
+        let mut router = self.public_routes(state.clone());
+        if admin_on_public {
+            router = router.merge(admin_routes(state.clone()));
+        }
+        if self.config.metrics.serve_on_gateway {
+            router = router.merge(metrics_routes(state));
+        }
+        self.apply_layers(router)
+    }
+
+    /// HTTP application of the admin listener, serving JSON-RPC with the
+    /// admin-scoped methods, GET /admin/*, health and optionally metrics
+    pub fn admin_app(&self) -> GatewayResult<Router> {
+        let state = self.state(self.chain_registry()?, true);
+        let mut router = Router::new()
+            .route("/", post(handle_jsonrpc))
+            .merge(health_routes())
+            .with_state(state.clone())
+            .merge(admin_routes(state.clone()));
+        if self.config.admin.serve_metrics {
+            router = router.merge(metrics_routes(state));
+        }
+        self.apply_layers(router)
+    }
+
+    /// API key and admin signature checks, judging admin timestamps by the
+    /// skew-corrected clock when the paymaster checks clock skew
+    fn auth_middleware(&self) -> AuthMiddleware {
+        let auth = AuthMiddleware::with_config(self.config.api_keys.clone())
+            .with_admin_auth(self.config.admin_auth.clone());
+        match self
+            .paymaster_service
+            .as_ref()
+            .and_then(|service| service.clock_guard())
+        {
+            Some(clock_guard) => auth.with_clock_guard(clock_guard.clone()),
+            None => auth,
+        }
+    }
+
+    /// State shared by the handlers of one listener
+    fn state(&self, chains: ChainRegistry, admin_methods: bool) -> GatewayState {
+        GatewayState {
+            paymaster_service: self.paymaster_service.clone(),
+            router: self.router.clone(),
+            config: self.config.clone(),
+            auth: self.auth_middleware(),
+            shutdown: self.shutdown.clone(),
+            health: self.health.clone(),
+            chains,
+            request_log: RequestLogger::new(self.config.logging.clone()),
+            prometheus: self.prometheus.clone(),
+            access_control: self.access_control.clone(),
+            alerts: self.alerts.clone(),
+            connection_limiter: self.connection_limiter.clone(),
+            events: self.events.clone(),
+            admin_methods,
+        }
+    }
+
+    /// Probe the dependencies until they are usable and warm the caches of
+    /// every chain, as configured by `[gateway.preflight]`
+    pub async fn preflight(&self) -> GatewayResult<PreflightReport> {
+        let chains = self.chain_registry()?;
+        Ok(run_preflight(&self.config.preflight, &self.health, &chains).await)
+    }
+
+    /// Check the EntryPoints of every chain once, then every configured interval
+    async fn check_entry_points(&self) -> GatewayResult<()> {
+        let config = &self.config.entry_point_checks;
+        if !config.enabled {
+            return Ok(());
+        }
+        for route in self.chain_registry()?.routes() {
+            let statuses = route.router.check_entry_points().await;
+            info!(
+                "🔎 Chain {}: {} of {} EntryPoints advertised",
+                route.chain_id(),
+                statuses.iter().filter(|status| status.advertised()).count(),
+                statuses.len()
+            );
+            if config.interval_seconds > 0 {
+                route
+                    .router
+                    .spawn_entry_point_checks(Duration::from_secs(config.interval_seconds));
+            }
+        }
+        Ok(())
     }
 
     /// Start the gateway server
+    ///
+    /// The listeners bind only after the preflight passes, unless it is disabled.
     pub async fn start(self) -> GatewayResult<()> {
         let addr = format!("{}:{}", self.config.host, self.config.port);
         info!("🌐 Starting SuperRelay Gateway on {}", addr);
 
-        let state = GatewayState {
-            paymaster_service: self.paymaster_service.clone(),
-            router: self.router.clone(),
-            config: self.config.clone(),
-        };
+        if self.config.preflight.enabled {
+            info!(
+                "🩺 Running preflight checks (timeout {}s)...",
+                self.config.preflight.timeout_seconds
+            );
+            let report = self.preflight().await?;
+            if !report.passed {
+                error!(
+                    "❌ Preflight failed: {}",
+                    serde_json::to_string(&report).unwrap_or_default()
+                );
+                return Err(GatewayError::PreflightFailed(Box::new(report)));
+            }
+            info!(
+                "✅ Preflight passed: {} checks after {} attempts in {}ms",
+                report.checks.len(),
+                report.attempts,
+                report.elapsed_ms
+            );
+        } else {
+            warn!("⚠️ Preflight skipped, serving before dependencies are probed");
+        }
+        self.check_entry_points().await?;
 
-        let app = self.create_router(state);
+        let app = self.app()?;
+        let admin_app = if self.config.admin.enabled {
+            Some(self.admin_app()?)
+        } else {
+            None
+        };
+        if let Some(interval) = self.alerts.probe_interval() {
+            Arc::new(self.health.clone()).spawn_alert_probes(interval);
+        }
 
         let listener = TcpListener::bind(&addr)
             .await
@@ -96,94 +585,538 @@ impl PaymasterGateway {
         info!("📋 Available endpoints:");
         info!("  • POST /              - JSON-RPC API (25 methods)");
         info!("  • GET /swagger-ui     - Complete API Documentation");
+        info!("  • GET /rpc-schema     - JSON-RPC method schemas (OpenAPI)");
         info!("  • GET /health         - Comprehensive health check");
         info!("  • GET /ready          - Readiness check");
         info!("  • GET /live           - Liveness check");
         info!("  • GET /e2e            - End-to-end validation");
         info!("  • GET /metrics        - Prometheus metrics");
+        if self.config.admin.admin_on_public() {
+            info!("  • GET /admin/pool/export - NDJSON pool export (admin)");
+            info!("  • GET /admin/usage/export - CSV usage export (admin)");
+            info!("  • GET /admin/events   - Live event stream, SSE (admin)");
+        } else {
+            info!("  🔒 Admin-scoped methods and /admin/* are not served on this port");
+        }
         info!("");
         info!("🌐 Swagger UI: http://{}/swagger-ui/", addr);
         info!("🔥 Complete SuperRelay API Documentation Available!");
 
-        axum::serve(listener, app)
-            .await
-            .map_err(|e| GatewayError::ServerError(format!("Server error: {}", e)))?;
+        let drain_timeout = Duration::from_secs(
+            self.config
+                .drain_timeout
+                .unwrap_or(self.config.request_timeout),
+        );
+        let limits = self.connection_limiter.config();
+        info!(
+            "🚦 Connection limits: {} connections, {} expensive requests",
+            limits.max_connections, limits.max_expensive_requests
+        );
+        let public = serve_with_connection_limits(
+            listener,
+            app,
+            self.connection_limiter.clone(),
+            self.shutdown.clone(),
+            drain_timeout,
+        );
+        let Some(admin_app) = admin_app else {
+            return public.await;
+        };
 
-        Ok(())
+        // Admin traffic stays outside the public connection limits
+        let admin_addr = self.config.admin.address();
+        let admin_listener = TcpListener::bind(&admin_addr).await.map_err(|e| {
+            GatewayError::ServerError(format!("Failed to bind to {}: {}", admin_addr, e))
+        })?;
+        info!(
+            "🔒 Admin listener on {} (admin methods, /admin/*, health{})",
+            admin_addr,
+            if self.config.admin.serve_metrics {
+                ", metrics"
+            } else {
+                ""
+            }
+        );
+        let admin = serve_with_graceful_shutdown(
+            admin_listener,
+            admin_app,
+            self.shutdown.clone(),
+            drain_timeout,
+        );
+        tokio::try_join!(public, admin).map(|_| ())
     }
 
-    fn create_router(&self, state: GatewayState) -> Router {
-        let mut router = Router::new()
+    /// Routes of the public listener, without the admin-scoped ones
+    fn public_routes(&self, state: GatewayState) -> Router {
+        Router::new()
             // JSON-RPC API endpoint
             .route("/", post(handle_jsonrpc))
             // Monitoring and health endpoints
             .route("/e2e", get(handle_e2e_validation))
-            .route("/metrics", get(handle_metrics))
+            // Error catalog for documentation tooling and client-side localization
+            .route("/errors", get(handle_error_catalog))
             .merge(health_routes())
-            // Swagger UI integration - Complete API documentation
+            // Swagger UI integration - Complete API documentation, plus the
+            // JSON-RPC schema as a second page, also served at /rpc-schema
             .merge(
                 SwaggerUi::new("/swagger-ui")
-                    .url("/api-docs/openapi.json", CompleteApiDoc::openapi()),
+                    .url("/api-docs/openapi.json", CompleteApiDoc::openapi())
+                    .external_url_unchecked(RPC_SCHEMA_PATH, rpc_schema()),
             )
-            .with_state(state);
+            .with_state(state)
+    }
 
+    fn apply_layers(&self, mut router: Router) -> GatewayResult<Router> {
         // Add middleware layers
-        if self.config.enable_cors {
-            let cors = CorsLayer::permissive();
-            router = router.layer(cors);
-        }
+        router = self
+            .config
+            .compression
+            .apply(router, self.config.validation.max_request_body_bytes);
+        router = self
+            .config
+            .cors
+            .apply(router)
+            .map_err(|e| GatewayError::ServerError(e.to_string()))?;
 
-        router = router.layer(TraceLayer::new_for_http());
+        router = router
+            .layer(DefaultBodyLimit::max(
+                self.config.validation.max_request_body_bytes,
+            ))
+            .layer(TraceLayer::new_for_http());
 
-        router
+        Ok(router)
     }
 }
 
+/// Operational tooling routes requiring the admin scope
+fn admin_routes(state: GatewayState) -> Router {
+    Router::new()
+        .route("/admin/pool/export", get(handle_pool_export))
+        .route("/admin/usage/export", get(handle_usage_export))
+        .route("/admin/events", get(handle_event_stream))
+        .with_state(state)
+}
+
+fn metrics_routes(state: GatewayState) -> Router {
+    Router::new()
+        .route("/metrics", get(handle_metrics))
+        .with_state(state)
+}
+
 /// Handle JSON-RPC requests with enterprise features
+///
+/// Batches of up to `max_batch_size` requests are dispatched concurrently and
+/// answered with an array in request order.
 async fn handle_jsonrpc(
     State(state): State<GatewayState>,
-    Json(payload): Json<Value>,
-) -> Result<Json<Value>, StatusCode> {
+    headers: HeaderMap,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> Response {
+    let request_id = request_id(&headers);
+    let limits = &state.config.validation;
+    // Dispatch consumes the request, so keep a copy only when it may be logged
+    let logged_request = match &payload {
+        Ok(Json(request)) if state.request_log.enabled() => Some(request.clone()),
+        _ => None,
+    };
+
+    let (status, mut response) = match payload {
+        Ok(Json(Value::Array(batch))) if batch.is_empty() => (
+            StatusCode::OK,
+            jsonrpc_catalog_error(
+                &catalog::INVALID_BATCH,
+                "Empty batch",
+                serde_json::json!({ "reason": "Empty batch" }),
+                None,
+            ),
+        ),
+        Ok(Json(Value::Array(batch))) if batch.len() > limits.max_batch_size => {
+            warn!("Rejected batch of {} requests", batch.len());
+            let message = format!(
+                "Batch of {} requests exceeds the maximum of {}",
+                batch.len(),
+                limits.max_batch_size
+            );
+            (
+                StatusCode::OK,
+                jsonrpc_catalog_error(
+                    &catalog::INVALID_BATCH,
+                    &message,
+                    serde_json::json!({ "reason": message }),
+                    None,
+                ),
+            )
+        }
+        Ok(Json(Value::Array(batch))) => {
+            let responses = batch.into_iter().map(|payload| {
+                dispatch_one(state.clone(), headers.clone(), payload, request_id.clone())
+            });
+            (StatusCode::OK, Value::Array(join_all(responses).await))
+        }
+        Ok(Json(payload)) => (
+            StatusCode::OK,
+            dispatch_one(state.clone(), headers, payload, request_id.clone()).await,
+        ),
+        Err(rejection) if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+            warn!("Rejected request body: {}", rejection.body_text());
+            let message = format!(
+                "Request body exceeds the maximum of {} bytes",
+                limits.max_request_body_bytes
+            );
+            (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                jsonrpc_catalog_error(
+                    &catalog::INVALID_BATCH,
+                    &message,
+                    serde_json::json!({ "reason": message }),
+                    None,
+                ),
+            )
+        }
+        Err(rejection) => return rejection.into_response(),
+    };
+    attach_to_error(&mut response, &request_id);
+    if let Some(request) = &logged_request {
+        state.request_log.log(&request_id, request, &response);
+    }
+
+    let mut response = match limit_exceeded(&response) {
+        // A single refused call is answered like a refused connection
+        Some(retry_after) => overloaded_response(response, retry_after),
+        None => (status, Json(response)).into_response(),
+    };
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Retry-After seconds of a single call refused by a concurrency limit
+fn limit_exceeded(response: &Value) -> Option<u64> {
+    let error = response.get("error")?;
+    if error.get("code")?.as_i64()? != i64::from(LIMIT_EXCEEDED_CODE) {
+        return None;
+    }
+    error.get("data")?.get("retryAfter")?.as_u64()
+}
+
+/// Dispatch one JSON-RPC request of a call or batch in its own span
+async fn dispatch_one(
+    state: GatewayState,
+    headers: HeaderMap,
+    payload: Value,
+    request_id: String,
+) -> Value {
+    let span = info_span!("jsonrpc", request_id = %request_id, method = field::Empty);
+    let started = Instant::now();
+    let method = payload
+        .get("method")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let events = state.events.clone();
+    events.publish(GatewayEvent::RequestStarted {
+        request_id: request_id.clone(),
+        method: method.clone().unwrap_or_default(),
+    });
+    let mut response = dispatch_jsonrpc(state, headers, payload, request_id.clone())
+        .instrument(span)
+        .await;
+    record_request(method.as_deref(), &response, started);
+    let error_code = response.get("error").map(|error| {
+        error
+            .get("code")
+            .and_then(Value::as_i64)
+            .unwrap_or_default()
+    });
+    events.publish(GatewayEvent::RequestFinished {
+        request_id: request_id.clone(),
+        method: method.unwrap_or_default(),
+        duration_ms: started.elapsed().as_millis() as u64,
+        outcome: if error_code.is_some() {
+            RequestOutcome::Error
+        } else {
+            RequestOutcome::Success
+        },
+        error_code,
+    });
+    attach_to_error(&mut response, &request_id);
+    response
+}
+
+/// Stream the pool contents as NDJSON, one operation per line
+///
+/// Requires the admin scope. Signed admin requests sign the request path and
+/// query, as a JSON string, in place of a body.
+async fn handle_pool_export(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PoolExportQuery>,
+) -> Response {
+    let signed = Value::from(uri.path_and_query().map_or(uri.path(), |p| p.as_str()));
+    match state
+        .auth
+        .authorize_request(&headers, &signed, POOL_EXPORT_METHOD)
+    {
+        Ok(identity) => info!("Exporting pool for {:?}: {:?}", identity, query),
+        Err(e) => {
+            warn!("Rejected pool export: {}", e);
+            return (StatusCode::UNAUTHORIZED, e.to_string()).into_response();
+        }
+    }
+
+    match state.router.export_pool(&query).await {
+        Ok(stream) => (
+            [(CONTENT_TYPE, "application/x-ndjson")],
+            Body::from_stream(stream),
+        )
+            .into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+/// Export per API key sponsorship usage as CSV, for billing
+async fn handle_usage_export(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<UsageExportQuery>,
+) -> Response {
+    let signed = Value::from(uri.path_and_query().map_or(uri.path(), |p| p.as_str()));
+    match state
+        .auth
+        .authorize_request(&headers, &signed, USAGE_EXPORT_METHOD)
+    {
+        Ok(identity) => info!("Exporting usage for {:?}: {:?}", identity, query),
+        Err(e) => {
+            warn!("Rejected usage export: {}", e);
+            return (StatusCode::UNAUTHORIZED, e.to_string()).into_response();
+        }
+    }
+
+    match state.router.export_usage(&query) {
+        Ok(csv) => ([(CONTENT_TYPE, "text/csv")], csv).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+/// Stream gateway events as Server-Sent Events until the client leaves or the
+/// gateway shuts down
+///
+/// Requires the admin scope, signed like the pool export. `types` limits the
+/// stream to a comma separated list of event types; idle streams get keepalive
+/// comments.
+async fn handle_event_stream(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<EventStreamQuery>,
+) -> Response {
+    let signed = Value::from(uri.path_and_query().map_or(uri.path(), |p| p.as_str()));
+    match state
+        .auth
+        .authorize_request(&headers, &signed, EVENT_STREAM_METHOD)
+    {
+        Ok(identity) => info!("Streaming events to {:?}: {:?}", identity, query),
+        Err(e) => {
+            warn!("Rejected event stream: {}", e);
+            return (StatusCode::UNAUTHORIZED, e.to_string()).into_response();
+        }
+    }
+    let filter = match EventFilter::parse(query.types.as_deref()) {
+        Ok(filter) => filter,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    let keepalive = Duration::from_secs(state.events.config().keepalive_seconds.max(1));
+    let subscription = state.events.subscribe(filter);
+    let stream = stream::unfold(subscription, |mut subscription| async move {
+        let event = subscription.next().await?;
+        Some((Ok::<_, Infallible>(event.to_sse()), subscription))
+    });
+    let shutdown = state.shutdown.clone();
+    let stream = stream.take_until(async move { shutdown.wait().await });
+    Sse::new(stream)
+        .keep_alive(KeepAlive::new().interval(keepalive).text("keepalive"))
+        .into_response()
+}
+
+/// Authorize, select the chain and route one JSON-RPC request
+async fn dispatch_jsonrpc(
+    state: GatewayState,
+    headers: HeaderMap,
+    payload: Value,
+    request_id: String,
+) -> Value {
     // Parse JSON-RPC request
-    let request = match parse_jsonrpc_request(&payload) {
+    let mut request = match parse_jsonrpc_request(&payload) {
         Ok(req) => req,
         Err(e) => {
             warn!("Invalid JSON-RPC request: {}", e);
-            return Ok(Json(jsonrpc_error(-32700, "Parse error", None)));
+            return jsonrpc_catalog_error(
+                &catalog::PARSE_ERROR,
+                "Parse error",
+                serde_json::json!({}),
+                None,
+            );
         }
     };
+    Span::current().record("method", request.method.as_str());
+    request.request_id = Some(request_id);
+    request.deadline = state.config.deadline_for(&request.method);
+
+    // Only methods with registry metadata are dispatched
+    let Some(spec) = method_registry::lookup(&request.method) else {
+        warn!("Unknown method: {}", request.method);
+        return method_not_found(&request);
+    };
 
-    // Route request based on method
-    let response = match request.method.as_str() {
-        // Paymaster methods
-        "pm_sponsorUserOperation" => handle_paymaster_request(&state, &request).await,
+    // Admin-scoped methods do not exist on a listener that does not serve them
+    if !state.admin_methods && is_admin_method(&request.method) {
+        warn!(
+            "Refused admin method {} on the public listener",
+            request.method
+        );
+        return method_not_found(&request);
+    }
 
-        // Standard eth methods - forward to rundler
-        method if method.starts_with("eth_") => handle_rundler_request(&state, &request).await,
+    // Check the API key scope (or admin signature) required by the method before dispatch
+    match state
+        .auth
+        .authorize_request(&headers, &payload, &request.method)
+    {
+        Ok(api_key_id) => {
+            debug!("Authorized {} for API key {:?}", request.method, api_key_id);
+            request.trust_level = api_key_id
+                .as_deref()
+                .and_then(|key_id| state.auth.trust_level(key_id))
+                .map(str::to_string);
+            request.debug = api_key_id
+                .as_deref()
+                .is_some_and(|key_id| state.auth.has_scope(key_id, ApiKeyScope::Debug));
+            request.permissions = api_key_id
+                .as_deref()
+                .is_some_and(|key_id| state.auth.has_scope(key_id, ApiKeyScope::Permissions));
+            request.api_key_id = api_key_id;
+        }
+        Err(e) => {
+            warn!("Rejected {}: {}", request.method, e);
+            return jsonrpc_catalog_error(
+                &catalog::UNAUTHORIZED,
+                &e.to_string(),
+                serde_json::json!({ "reason": e.to_string() }),
+                Some(request.id),
+            );
+        }
+    }
 
-        // Rundler-specific methods
-        method if method.starts_with("rundler_") => handle_rundler_request(&state, &request).await,
+    request.no_cache = headers.contains_key(NO_CACHE_HEADER);
 
-        // Debug methods
-        method if method.starts_with("debug_") => handle_rundler_request(&state, &request).await,
+    // Pick the chain from the X-Chain-Id header or the request's chainId
+    let route = match chain_id_field(&payload, &request)
+        .and_then(|chain_id| {
+            request.chain_id = chain_id;
+            requested_chain_id(&headers, &request)
+        })
+        .and_then(|chain_id| state.chains.select(chain_id))
+    {
+        Ok(route) => route,
+        Err(e) => {
+            warn!("Rejected {}: {}", request.method, e);
+            return jsonrpc_catalog_error(
+                &catalog::INVALID_CHAIN,
+                &e.to_string(),
+                serde_json::json!({ "reason": e.to_string() }),
+                Some(request.id),
+            );
+        }
+    };
 
-        // Admin methods
-        method if method.starts_with("admin_") => handle_rundler_request(&state, &request).await,
+    // Edge filter on the sender, before any policy, pool or paymaster work
+    if let Some(access_control) = &state.access_control {
+        if let Err(e) = access_control.check_request(&request) {
+            return jsonrpc_gateway_error(&e, &e.to_string(), request.id);
+        }
+    }
 
-        _ => {
-            warn!("Unknown method: {}", request.method);
-            jsonrpc_error(-32601, "Method not found", Some(request.id))
+    // Simulation and estimation share a smaller concurrency limit
+    let _permit = match state.connection_limiter.acquire_expensive(&request.method) {
+        Ok(permit) => permit,
+        Err(e) => {
+            warn!("Rejected {}: {}", request.method, e);
+            return jsonrpc_gateway_error(&e, &e.to_string(), request.id);
         }
     };
 
-    Ok(Json(response))
+    // Dispatch to the handler of the method, within its deadline
+    let deadline = request.deadline.clone();
+    let routed = deadline
+        .enforce(async {
+            match spec.handler {
+                MethodHandler::AccessList => {
+                    handle_access_control_request(state.access_control.as_deref(), &request)
+                }
+                MethodHandler::TestAlert => handle_test_alert(&state.alerts, &request).await,
+                MethodHandler::Paymaster => handle_paymaster_request(route, &request).await,
+                MethodHandler::Rundler => handle_rundler_request(route, &request).await,
+                MethodHandler::SuperRelay => handle_super_relay_request(route, &request).await,
+            }
+        })
+        .await;
+    let response = match routed {
+        Ok(response) => response,
+        Err(e) => return jsonrpc_gateway_error(&e, &e.to_string(), request.id),
+    };
+
+    // Lookups return the sender only with the operation
+    if let (Some(access_control), Some(result)) = (&state.access_control, response.get("result")) {
+        if let Err(e) = access_control.check_result(&request.method, result) {
+            return jsonrpc_gateway_error(&e, &e.to_string(), request.id);
+        }
+    }
+    response
+}
+
+/// Handle the access list admin methods
+fn handle_access_control_request(
+    access_control: Option<&SenderAccessControl>,
+    request: &JsonRpcRequest,
+) -> Value {
+    let Some(access_control) = access_control else {
+        return jsonrpc_catalog_error(
+            &catalog::METHOD_NOT_FOUND,
+            "Access control is not configured",
+            serde_json::json!({ "method": request.method }),
+            Some(request.id.clone()),
+        );
+    };
+    match access_control.handle_admin_request(request) {
+        Ok(result) => jsonrpc_success(result, request.id.clone()),
+        Err(e) => jsonrpc_gateway_error(&e, &e.to_string(), request.id.clone()),
+    }
+}
+
+/// Deliver a synthetic alert to every webhook, reporting each delivery
+async fn handle_test_alert(alerts: &AlertBus, request: &JsonRpcRequest) -> Value {
+    let message = request
+        .params
+        .first()
+        .and_then(Value::as_str)
+        .unwrap_or("Test alert fired through admin_testAlert");
+    match alerts.fire_test(message).await {
+        Ok((alert, deliveries)) => jsonrpc_success(
+            serde_json::json!({ "alert": alert, "deliveries": deliveries }),
+            request.id.clone(),
+        ),
+        Err(e) => jsonrpc_gateway_error(&e, &e.to_string(), request.id.clone()),
+    }
 }
 
 /// Handle paymaster-specific requests
-async fn handle_paymaster_request(state: &GatewayState, request: &JsonRpcRequest) -> Value {
-    if let Some(ref paymaster_service) = state.paymaster_service {
+async fn handle_paymaster_request(route: &ChainRoute, request: &JsonRpcRequest) -> Value {
+    if let Some(ref paymaster_service) = route.paymaster_service {
         // Forward to paymaster service
-        match state
+        match route
             .router
             .route_to_paymaster(paymaster_service, request)
             .await
@@ -191,39 +1124,48 @@ async fn handle_paymaster_request(state: &GatewayState, request: &JsonRpcRequest
             Ok(result) => jsonrpc_success(result, request.id.clone()),
             Err(e) => {
                 warn!("Paymaster request failed: {}", e);
-                jsonrpc_error(
-                    -32603,
-                    &format!("Paymaster error: {}", e),
-                    Some(request.id.clone()),
-                )
+                jsonrpc_gateway_error(&e, &format!("Paymaster error: {}", e), request.id.clone())
             }
         }
     } else {
-        jsonrpc_error(
-            -32601,
+        jsonrpc_catalog_error(
+            &catalog::METHOD_NOT_FOUND,
             "Paymaster service not available",
+            serde_json::json!({ "method": request.method }),
             Some(request.id.clone()),
         )
     }
 }
 
 /// Handle rundler requests by forwarding to appropriate rundler components
-async fn handle_rundler_request(state: &GatewayState, request: &JsonRpcRequest) -> Value {
-    match state.router.route_to_rundler(request).await {
+async fn handle_rundler_request(route: &ChainRoute, request: &JsonRpcRequest) -> Value {
+    match route.router.route_to_rundler(request).await {
         Ok(result) => jsonrpc_success(result, request.id.clone()),
         Err(e) => {
             warn!("Rundler request failed: {}", e);
-            jsonrpc_error(
-                -32603,
-                &format!("Rundler error: {}", e),
-                Some(request.id.clone()),
-            )
+            jsonrpc_gateway_error(&e, &format!("Rundler error: {}", e), request.id.clone())
+        }
+    }
+}
+
+/// Handle SuperRelay-specific requests
+async fn handle_super_relay_request(route: &ChainRoute, request: &JsonRpcRequest) -> Value {
+    match route.router.route_to_super_relay(request).await {
+        Ok(result) => jsonrpc_success(result, request.id.clone()),
+        Err(e) => {
+            warn!("SuperRelay request failed: {}", e);
+            jsonrpc_gateway_error(&e, &e.to_string(), request.id.clone())
         }
     }
 }
 
 // Health check endpoints now handled by health module
 
+/// Key, code and description of every JSON-RPC error
+async fn handle_error_catalog() -> Json<Value> {
+    Json(error_catalog())
+}
+
 /// End-to-end validation endpoint
 async fn handle_e2e_validation(
     State(state): State<GatewayState>,
@@ -250,15 +1192,10 @@ async fn handle_e2e_validation(
     Ok(Json(result))
 }
 
-/// Metrics endpoint - integrate with rundler metrics
+/// Metrics endpoint - gateway status plus the shared Prometheus recorder
 async fn handle_metrics(State(state): State<GatewayState>) -> String {
     let mut metrics = String::new();
 
-    // Gateway-specific metrics (minimal additions)
-    metrics.push_str("# Gateway metrics\n");
-    metrics.push_str("superrelay_gateway_requests_total 0\n");
-    metrics.push_str("superrelay_gateway_active_connections 0\n");
-
     // If paymaster service exists, include its basic info
     if let Some(ref _paymaster_service) = state.paymaster_service {
         metrics.push_str("\n# Paymaster service status\n");
@@ -269,8 +1206,30 @@ async fn handle_metrics(State(state): State<GatewayState>) -> String {
         metrics.push_str("paymaster_service_available 0\n");
     }
 
-    // TODO: Proxy rundler metrics endpoint (/metrics) here for unified access
-    metrics.push_str("\n# Note: Rundler metrics available at original endpoints\n");
+    // Response cache hit/miss counters per chain and method
+    metrics.push_str("\n# TYPE superrelay_gateway_cache_hits_total counter\n");
+    metrics.push_str("# TYPE superrelay_gateway_cache_misses_total counter\n");
+    for chain_id in state.chains.chain_ids() {
+        let Ok(route) = state.chains.select(Some(chain_id)) else {
+            continue;
+        };
+        for counters in route.router.cache_counters() {
+            metrics.push_str(&format!(
+                "superrelay_gateway_cache_hits_total{{chain_id=\"{}\",method=\"{}\"}} {}\n",
+                chain_id, counters.method, counters.hits
+            ));
+            metrics.push_str(&format!(
+                "superrelay_gateway_cache_misses_total{{chain_id=\"{}\",method=\"{}\"}} {}\n",
+                chain_id, counters.method, counters.misses
+            ));
+        }
+    }
+
+    // Request counters and latencies, paymaster and rundler component metrics
+    if let Some(handle) = &state.prometheus {
+        metrics.push('\n');
+        metrics.push_str(&handle.render());
+    }
 
     metrics
 }
@@ -284,6 +1243,23 @@ pub struct JsonRpcRequest {
     pub method: String,
     /// Method parameters
     pub params: Vec<Value>,
+    /// Id of the API key that authorized this request, if any
+    pub api_key_id: Option<String>,
+    /// Trust level of that API key, never taken from the request itself
+    pub trust_level: Option<String>,
+    /// Chain named by the request's `chainId` field, if any
+    pub chain_id: Option<u64>,
+    /// Skip cached responses, set by the x-no-cache header
+    pub no_cache: bool,
+    /// Correlation id from the x-request-id header, or generated per request
+    pub request_id: Option<String>,
+    /// Time budget shared by every stage handling this request
+    pub deadline: Deadline,
+    /// The API key holds the debug scope, enabling opt-in debugging output
+    pub debug: bool,
+    /// The API key holds the permissions scope, allowing pool permissions
+    /// requested per operation
+    pub permissions: bool,
 }
 
 /// Parse JSON-RPC request
@@ -306,7 +1282,19 @@ fn parse_jsonrpc_request(payload: &Value) -> GatewayResult<JsonRpcRequest> {
         .cloned()
         .unwrap_or_default();
 
-    Ok(JsonRpcRequest { id, method, params })
+    Ok(JsonRpcRequest {
+        id,
+        method,
+        params,
+        api_key_id: None,
+        trust_level: None,
+        chain_id: None,
+        no_cache: false,
+        request_id: None,
+        deadline: Deadline::default(),
+        debug: false,
+        permissions: false,
+    })
 }
 
 /// Create JSON-RPC success response
@@ -329,3 +1317,32 @@ fn jsonrpc_error(code: i32, message: &str, id: Option<Value>) -> Value {
         "id": id.unwrap_or(Value::Null)
     })
 }
+
+/// Error response for a [`GatewayError`], carrying its code and data
+fn jsonrpc_gateway_error(error: &GatewayError, message: &str, id: Value) -> Value {
+    let mut response = jsonrpc_error(error.code(), message, Some(id));
+    response["error"]["data"] = error.rpc_data();
+    response
+}
+
+/// Error response for a catalogued error raised outside a [`GatewayError`]
+fn jsonrpc_catalog_error(
+    entry: &ErrorCatalogEntry,
+    message: &str,
+    params: Value,
+    id: Option<Value>,
+) -> Value {
+    let mut response = jsonrpc_error(entry.code, message, id);
+    response["error"]["data"] = entry.data(params);
+    response
+}
+
+/// `Method not found` response for `request`
+fn method_not_found(request: &JsonRpcRequest) -> Value {
+    jsonrpc_catalog_error(
+        &catalog::METHOD_NOT_FOUND,
+        "Method not found",
+        serde_json::json!({ "method": request.method }),
+        Some(request.id.clone()),
+    )
+}
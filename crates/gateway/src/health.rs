@@ -1,12 +1,64 @@
-use std::{sync::Arc, time::SystemTime};
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
+use alloy_primitives::{Address, U256, U64};
+use async_trait::async_trait;
 use axum::{extract::State, http::StatusCode, response::Json, routing::get, Router};
-use rundler_paymaster_relay::PaymasterRelayService;
+use rundler_paymaster_relay::{ClockGuard, ClockSkewState, PaymasterRelayService};
+use rundler_provider::{BlockId, EntryPoint, EvmProvider};
+use rundler_types::{
+    builder::Builder,
+    pool::{Pool, ReputationStatus},
+};
 use serde::{Deserialize, Serialize};
-use tokio::time::Instant;
+use serde_json::Value;
+use tokio::{
+    sync::Mutex,
+    task::{JoinHandle, JoinSet},
+    time::Instant,
+};
 use tracing::{debug, error, info, warn};
 
-use crate::{gateway::GatewayState, router::GatewayRouter};
+use crate::{
+    alerts::{Alert, AlertBus, AlertKind},
+    gateway::GatewayState,
+    hedged_provider::HedgedEvmProvider,
+    pool_supervisor::{PoolState, SupervisedPool},
+    reputation::{entity_reputation, EntityReputation},
+    threat_feed::ThreatIntelStore,
+};
+
+/// Start of the deposit probe failure when the deposit could not be read
+const DEPOSIT_READ_FAILED: &str = "failed to read deposit";
+
+/// Health probe configuration (`[gateway.health]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// Time allowed for each probe before it is reported as failed
+    pub probe_timeout_ms: u64,
+    /// How long probe results are reused before probing again
+    pub cache_ttl_seconds: u64,
+    /// The node counts as out of sync once its latest block is older than this
+    pub max_block_age_seconds: u64,
+    /// Minimum paymaster deposit on each EntryPoint, in wei (decimal or 0x-hex string)
+    pub min_paymaster_deposit_wei: U256,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            probe_timeout_ms: 2_000,
+            cache_ttl_seconds: 5,
+            max_block_age_seconds: 120,
+            // 0.01 ETH
+            min_paymaster_deposit_wei: U256::from(10_000_000_000_000_000u64),
+        }
+    }
+}
 
 /// Health check response structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,8 +69,10 @@ pub struct HealthStatus {
     pub timestamp: u64,
     /// Uptime in seconds
     pub uptime_seconds: u64,
-    /// Component status details
-    pub components: ComponentsStatus,
+    /// Version of the gateway answering the check
+    pub version: String,
+    /// Dependency probe results
+    pub components: Vec<ProbeReport>,
     /// System metrics
     pub metrics: SystemMetrics,
 }
@@ -35,19 +89,6 @@ pub enum SystemStatus {
     Unhealthy,
 }
 
-/// Component status details
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ComponentsStatus {
-    /// Gateway service status
-    pub gateway: ComponentHealth,
-    /// Paymaster service status
-    pub paymaster: ComponentHealth,
-    /// Pool service status
-    pub pool: ComponentHealth,
-    /// Router service status
-    pub router: ComponentHealth,
-}
-
 /// Individual component health
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComponentHealth {
@@ -59,6 +100,32 @@ pub struct ComponentHealth {
     pub response_time_ms: Option<u64>,
     /// Error message if unhealthy
     pub error: Option<String>,
+    /// Probe-specific state, such as pool restarts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+/// Health of one probed dependency
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProbeReport {
+    /// Component name
+    pub name: String,
+    /// Whether readiness requires this component
+    pub critical: bool,
+    /// Probe outcome
+    #[serde(flatten)]
+    pub health: ComponentHealth,
+}
+
+/// Readiness response body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadinessReport {
+    /// Whether every critical probe passed
+    pub ready: bool,
+    /// Timestamp of the check
+    pub timestamp: u64,
+    /// Dependency probe results
+    pub components: Vec<ProbeReport>,
 }
 
 /// Component status enum
@@ -88,12 +155,42 @@ pub struct SystemMetrics {
     pub error_rate: f64,
 }
 
+/// A dependency check contributing to readiness
+#[async_trait]
+pub trait HealthProbe: Send + Sync {
+    /// Component name used in health reports
+    fn name(&self) -> &str;
+
+    /// Whether the gateway is not ready while this probe fails
+    fn critical(&self) -> bool {
+        true
+    }
+
+    /// Run the check, returning the reason on failure
+    async fn check(&self) -> Result<(), String>;
+
+    /// State reported next to the outcome, whether the check passed or not
+    fn details(&self) -> Option<Value> {
+        None
+    }
+
+    /// Operator alert for a failed check with `reason`, `None` when the
+    /// failure is not worth alerting about
+    fn alert(&self, _reason: &str) -> Option<Alert> {
+        None
+    }
+}
+
 /// Health checker service
 #[derive(Clone)]
 pub struct HealthChecker {
     start_time: Instant,
     request_counter: Arc<std::sync::atomic::AtomicU64>,
     error_counter: Arc<std::sync::atomic::AtomicU64>,
+    config: HealthConfig,
+    probes: Vec<Arc<dyn HealthProbe>>,
+    cache: Arc<Mutex<Option<(Instant, Vec<ProbeReport>)>>>,
+    alerts: AlertBus,
 }
 
 impl Default for HealthChecker {
@@ -105,13 +202,41 @@ impl Default for HealthChecker {
 impl HealthChecker {
     /// Create a new health checker
     pub fn new() -> Self {
+        Self::with_config(HealthConfig::default())
+    }
+
+    /// Create a health checker with the given probe settings
+    pub fn with_config(config: HealthConfig) -> Self {
         Self {
             start_time: Instant::now(),
             request_counter: Arc::new(std::sync::atomic::AtomicU64::new(0)),
             error_counter: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            config,
+            probes: vec![],
+            cache: Arc::new(Mutex::new(None)),
+            alerts: AlertBus::disabled(),
         }
     }
 
+    /// Add a dependency probe, replacing the probe of the same name
+    pub fn with_probe(mut self, probe: Arc<dyn HealthProbe>) -> Self {
+        self.probes
+            .retain(|existing| existing.name() != probe.name());
+        self.probes.push(probe);
+        self
+    }
+
+    /// Publish alerts for failing probes onto `alerts`
+    pub fn with_alerts(mut self, alerts: AlertBus) -> Self {
+        self.alerts = alerts;
+        self
+    }
+
+    /// Probe settings
+    pub fn config(&self) -> &HealthConfig {
+        &self.config
+    }
+
     /// Register a request
     pub fn record_request(&self) {
         self.request_counter
@@ -124,145 +249,110 @@ impl HealthChecker {
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
     }
 
-    /// Perform comprehensive health check
-    pub async fn check_health(&self, state: &GatewayState) -> HealthStatus {
-        let now = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-
-        let uptime = self.start_time.elapsed().as_secs();
-
-        // Check individual components
-        let gateway_health = self.check_gateway_health().await;
-        let paymaster_health = self.check_paymaster_health(&state.paymaster_service).await;
-        let pool_health = self.check_pool_health().await;
-        let router_health = self.check_router_health(&state.router).await;
-
-        // Determine overall status
-        let overall_status = self.determine_overall_status(&[
-            &gateway_health,
-            &paymaster_health,
-            &pool_health,
-            &router_health,
-        ]);
-
-        // Collect system metrics
-        let metrics = self.collect_system_metrics().await;
-
-        HealthStatus {
-            status: overall_status,
-            timestamp: now,
-            uptime_seconds: uptime,
-            components: ComponentsStatus {
-                gateway: gateway_health,
-                paymaster: paymaster_health,
-                pool: pool_health,
-                router: router_health,
-            },
-            metrics,
+    /// Run all probes concurrently, reusing results younger than the cache TTL
+    pub async fn probe_components(&self) -> Vec<ProbeReport> {
+        // Holding the lock while probing lets concurrent callers share one run
+        let mut cache = self.cache.lock().await;
+        let ttl = Duration::from_secs(self.config.cache_ttl_seconds);
+        if let Some((probed_at, reports)) = cache.as_ref() {
+            if probed_at.elapsed() < ttl {
+                return reports.clone();
+            }
         }
+
+        let reports = self.run_probes().await;
+        *cache = Some((Instant::now(), reports.clone()));
+        reports
     }
 
-    /// Check gateway component health
-    async fn check_gateway_health(&self) -> ComponentHealth {
-        let start = Instant::now();
+    /// Run all probes concurrently, ignoring cached results
+    pub async fn probe_now(&self) -> Vec<ProbeReport> {
+        let mut cache = self.cache.lock().await;
+        let reports = self.run_probes().await;
+        *cache = Some((Instant::now(), reports.clone()));
+        reports
+    }
 
-        // Basic gateway health check - verify we can process requests
-        let status = ComponentStatus::Healthy;
-        let response_time = start.elapsed().as_millis() as u64;
+    async fn run_probes(&self) -> Vec<ProbeReport> {
+        let timeout = Duration::from_millis(self.config.probe_timeout_ms);
+        let mut tasks = JoinSet::new();
+        for (index, probe) in self.probes.iter().enumerate() {
+            let probe = probe.clone();
+            tasks.spawn(async move { (index, run_probe(probe.as_ref(), timeout).await) });
+        }
 
-        ComponentHealth {
-            status,
-            last_check: SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs(),
-            response_time_ms: Some(response_time),
-            error: None,
-        }
-    }
-
-    /// Check paymaster service health
-    async fn check_paymaster_health(
-        &self,
-        paymaster_service: &Option<Arc<PaymasterRelayService>>,
-    ) -> ComponentHealth {
-        let start = Instant::now();
-
-        let (status, error) = match paymaster_service {
-            Some(_service) => {
-                // TODO: Add actual paymaster health check logic
-                // For now, just check if service exists
-                (ComponentStatus::Healthy, None)
+        let mut reports = Vec::with_capacity(self.probes.len());
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok(report) => reports.push(report),
+                Err(e) => error!("Health probe task failed: {}", e),
+            }
+        }
+        reports.sort_by_key(|(index, _)| *index);
+        for (index, report) in &reports {
+            if let Some(reason) = &report.health.error {
+                if let Some(alert) = self.probes[*index].alert(reason) {
+                    self.alerts.publish(alert);
+                }
             }
-            None => (
-                ComponentStatus::Warning,
-                Some("Paymaster service not configured".to_string()),
-            ),
-        };
-
-        let response_time = start.elapsed().as_millis() as u64;
-
-        ComponentHealth {
-            status,
-            last_check: SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs(),
-            response_time_ms: Some(response_time),
-            error,
         }
+        reports.into_iter().map(|(_, report)| report).collect()
     }
 
-    /// Check pool service health
-    async fn check_pool_health(&self) -> ComponentHealth {
-        let start = Instant::now();
+    /// Probe every `interval` so failing probes raise alerts without /health traffic
+    pub fn spawn_alert_probes(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.probe_components().await;
+            }
+        })
+    }
 
-        // TODO: Add actual pool health check logic
-        // For now, assume healthy
-        let status = ComponentStatus::Healthy;
-        let response_time = start.elapsed().as_millis() as u64;
+    /// Perform comprehensive health check
+    pub async fn check_health(&self) -> HealthStatus {
+        let components = self.probe_components().await;
+        let overall_status = self.determine_overall_status(&components);
 
-        ComponentHealth {
-            status,
-            last_check: SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs(),
-            response_time_ms: Some(response_time),
-            error: None,
+        // Collect system metrics
+        let metrics = self.collect_system_metrics().await;
+
+        HealthStatus {
+            status: overall_status,
+            timestamp: unix_now(),
+            uptime_seconds: self.start_time.elapsed().as_secs(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            components,
+            metrics,
         }
     }
 
-    /// Check router service health
-    async fn check_router_health(&self, _router: &GatewayRouter) -> ComponentHealth {
-        let start = Instant::now();
+    /// Check whether every critical probe passes
+    pub async fn check_readiness(&self) -> ReadinessReport {
+        let components = self.probe_components().await;
+        let ready = components
+            .iter()
+            .all(|c| !c.critical || c.health.status != ComponentStatus::Error);
 
-        // Basic router health check
-        let status = ComponentStatus::Healthy;
-        let response_time = start.elapsed().as_millis() as u64;
-
-        ComponentHealth {
-            status,
-            last_check: SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs(),
-            response_time_ms: Some(response_time),
-            error: None,
+        ReadinessReport {
+            ready,
+            timestamp: unix_now(),
+            components,
         }
     }
 
-    /// Determine overall system status from component statuses
-    fn determine_overall_status(&self, components: &[&ComponentHealth]) -> SystemStatus {
+    /// Determine overall system status from probe results
+    ///
+    /// Failing critical probes make the system unhealthy; failing optional ones degrade it.
+    fn determine_overall_status(&self, components: &[ProbeReport]) -> SystemStatus {
         let mut has_error = false;
         let mut has_warning = false;
 
         for component in components {
-            match component.status {
-                ComponentStatus::Error => has_error = true,
-                ComponentStatus::Warning => has_warning = true,
+            match component.health.status {
+                ComponentStatus::Error if component.critical => has_error = true,
+                ComponentStatus::Error | ComponentStatus::Warning => has_warning = true,
                 ComponentStatus::Healthy | ComponentStatus::Unknown => {}
             }
         }
@@ -322,16 +412,55 @@ impl HealthChecker {
     }
 }
 
-/// Health check endpoint handler
+/// Run one probe with a timeout
+async fn run_probe(probe: &dyn HealthProbe, timeout: Duration) -> ProbeReport {
+    let start = Instant::now();
+    let result = match tokio::time::timeout(timeout, probe.check()).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("timed out after {}ms", timeout.as_millis())),
+    };
+
+    let (status, error) = match result {
+        Ok(()) => (ComponentStatus::Healthy, None),
+        Err(reason) => {
+            warn!("Health probe {} failed: {}", probe.name(), reason);
+            (ComponentStatus::Error, Some(reason))
+        }
+    };
+
+    ProbeReport {
+        name: probe.name().to_string(),
+        critical: probe.critical(),
+        health: ComponentHealth {
+            status,
+            last_check: unix_now(),
+            response_time_ms: Some(start.elapsed().as_millis() as u64),
+            error,
+            details: probe.details(),
+        },
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Health check endpoint handler (liveness: 200 while the process serves requests)
 pub async fn health_check(
     State(state): State<GatewayState>,
 ) -> Result<Json<HealthStatus>, StatusCode> {
     debug!("Processing health check request");
 
-    // For now, create a new health checker each time
-    // In production, you might want to maintain a singleton
-    let health_checker = HealthChecker::new();
-    let health_status = health_checker.check_health(&state).await;
+    // Tell load balancers to stop routing here once draining starts
+    if state.shutdown.is_draining() {
+        warn!("Health check failing: gateway is draining");
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    let health_status = state.health.check_health().await;
 
     match health_status.status {
         SystemStatus::Healthy => {
@@ -348,11 +477,24 @@ pub async fn health_check(
     Ok(Json(health_status))
 }
 
-/// Readiness check endpoint handler (simpler check for load balancers)
-pub async fn readiness_check(State(_state): State<GatewayState>) -> Result<StatusCode, StatusCode> {
-    // Simple readiness check - just return OK if service is running
+/// Readiness check endpoint handler (503 unless every critical probe passes)
+pub async fn readiness_check(
+    State(state): State<GatewayState>,
+) -> (StatusCode, Json<ReadinessReport>) {
     debug!("Processing readiness check request");
-    Ok(StatusCode::OK)
+
+    let mut report = state.health.check_readiness().await;
+    // Unavailable once draining for shutdown, whatever the probes say
+    if state.shutdown.is_draining() {
+        report.ready = false;
+    }
+
+    let status = if report.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
 }
 
 /// Liveness check endpoint handler (basic service alive check)
@@ -369,9 +511,796 @@ pub fn health_routes() -> Router<GatewayState> {
         .route("/live", get(liveness_check))
 }
 
+/// Node RPC is reachable and its latest block is recent
+pub struct NodeProbe<P> {
+    name: String,
+    provider: P,
+    max_block_age: Duration,
+}
+
+impl<P> NodeProbe<P> {
+    /// Probe `provider`, treating blocks older than `max_block_age` as out of sync
+    pub fn new(provider: P, max_block_age: Duration) -> Self {
+        Self {
+            name: "node".to_string(),
+            provider,
+            max_block_age,
+        }
+    }
+
+    /// Report under `name` instead of "node", e.g. when probing several chains
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> HealthProbe for NodeProbe<P> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let block = self
+            .provider
+            .get_block(BlockId::latest())
+            .await
+            .map_err(|e| format!("node RPC unreachable: {}", e))?
+            .ok_or_else(|| "node returned no latest block".to_string())?;
+
+        let age = unix_now().saturating_sub(block.header.timestamp);
+        if age > self.max_block_age.as_secs() {
+            return Err(format!(
+                "node not synced: latest block {} is {}s old",
+                block.header.number, age
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Node serves the chain the gateway is configured for
+pub struct ChainIdProbe<P> {
+    name: String,
+    provider: P,
+    chain_id: u64,
+}
+
+impl<P> ChainIdProbe<P> {
+    /// Probe that `provider` reports `chain_id`
+    pub fn new(provider: P, chain_id: u64) -> Self {
+        Self {
+            name: "chain_id".to_string(),
+            provider,
+            chain_id,
+        }
+    }
+
+    /// Report under `name` instead of "chain_id", e.g. when probing several chains
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> HealthProbe for ChainIdProbe<P> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let reported: U64 = self
+            .provider
+            .request("eth_chainId", ())
+            .await
+            .map_err(|e| format!("node RPC unreachable: {}", e))?;
+        if reported.to::<u64>() != self.chain_id {
+            return Err(format!(
+                "node reports chain id {} but {} is configured",
+                reported, self.chain_id
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Every served EntryPoint has contract code on the node's chain
+pub struct EntryPointCodeProbe<P> {
+    name: String,
+    provider: P,
+    entry_points: Vec<Address>,
+}
+
+impl<P> EntryPointCodeProbe<P> {
+    /// Probe the code of `entry_points` through `provider`
+    pub fn new(provider: P, entry_points: Vec<Address>) -> Self {
+        Self {
+            name: "entry_point_code".to_string(),
+            provider,
+            entry_points,
+        }
+    }
+
+    /// Report under `name` instead of "entry_point_code", e.g. when probing several chains
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> HealthProbe for EntryPointCodeProbe<P> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        for entry_point in &self.entry_points {
+            let code = self
+                .provider
+                .get_code(*entry_point, None)
+                .await
+                .map_err(|e| format!("node RPC unreachable: {}", e))?;
+            if code.is_empty() {
+                return Err(format!("no contract code at EntryPoint {}", entry_point));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Mempool task is running and answers requests
+pub struct PoolProbe {
+    pool: SupervisedPool,
+}
+
+impl PoolProbe {
+    /// Probe the given pool handle
+    pub fn new(pool: impl Into<SupervisedPool>) -> Self {
+        Self { pool: pool.into() }
+    }
+}
+
+#[async_trait]
+impl HealthProbe for PoolProbe {
+    fn name(&self) -> &str {
+        "pool"
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        self.pool
+            .current()
+            .map_err(|e| e.to_string())?
+            .get_supported_entry_points()
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("pool not responding: {}", e))
+    }
+
+    fn details(&self) -> Option<Value> {
+        serde_json::to_value(self.pool.status()).ok()
+    }
+
+    fn alert(&self, reason: &str) -> Option<Alert> {
+        // A restarting pool recovers on its own
+        let status = self.pool.status();
+        (status.state == PoolState::Stopped).then(|| {
+            Alert::new(AlertKind::PoolStopped, "pool", reason)
+                .with_details(serde_json::to_value(status).unwrap_or_default())
+        })
+    }
+}
+
+/// Bundle builder is attached and answers requests
+///
+/// Not critical: without a builder operations are still accepted into the
+/// mempool, only rundler_sendBundleNow and bundling are unavailable.
+pub struct BuilderProbe {
+    builder: Option<Arc<dyn Builder>>,
+}
+
+impl BuilderProbe {
+    /// Probe `builder`, reporting it unavailable when `None`
+    pub fn new(builder: Option<Arc<dyn Builder>>) -> Self {
+        Self { builder }
+    }
+}
+
+#[async_trait]
+impl HealthProbe for BuilderProbe {
+    fn name(&self) -> &str {
+        "builder"
+    }
+
+    fn critical(&self) -> bool {
+        false
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let Some(builder) = &self.builder else {
+            return Ok(());
+        };
+        builder
+            .get_supported_entry_points()
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("builder not responding: {}", e))
+    }
+
+    fn details(&self) -> Option<Value> {
+        Some(serde_json::json!({ "available": self.builder.is_some() }))
+    }
+}
+
+/// Paymaster signer is loaded with a usable address
+pub struct SignerProbe {
+    paymaster_service: Arc<PaymasterRelayService>,
+}
+
+impl SignerProbe {
+    /// Probe the signer of the given paymaster service
+    pub fn new(paymaster_service: Arc<PaymasterRelayService>) -> Self {
+        Self { paymaster_service }
+    }
+}
+
+#[async_trait]
+impl HealthProbe for SignerProbe {
+    fn name(&self) -> &str {
+        "paymaster_signer"
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        if self.paymaster_service.paymaster_address().await == Address::ZERO {
+            return Err("paymaster signer has no address".to_string());
+        }
+        Ok(())
+    }
+
+    fn alert(&self, reason: &str) -> Option<Alert> {
+        Some(Alert::new(AlertKind::SignerError, self.name(), reason))
+    }
+}
+
+/// Sponsorship policy file was parsed and has the policy sponsorship uses
+pub struct PolicyProbe {
+    paymaster_service: Arc<PaymasterRelayService>,
+}
+
+impl PolicyProbe {
+    /// Probe the policies of the given paymaster service
+    pub fn new(paymaster_service: Arc<PaymasterRelayService>) -> Self {
+        Self { paymaster_service }
+    }
+}
+
+#[async_trait]
+impl HealthProbe for PolicyProbe {
+    fn name(&self) -> &str {
+        "policy"
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        if !self.paymaster_service.policy_engine().has_policy("default") {
+            return Err("default sponsorship policy not loaded".to_string());
+        }
+        Ok(())
+    }
+
+    fn alert(&self, reason: &str) -> Option<Alert> {
+        Some(Alert::new(AlertKind::PolicyFailure, self.name(), reason))
+    }
+}
+
+/// Pool reputation of the paymaster on each sponsored EntryPoint is `ok`
+///
+/// Not critical: a throttled paymaster still gets operations in, only fewer
+/// of them. Alerts once per transition from `ok` to throttled or banned.
+pub struct ReputationProbe<P> {
+    pool: P,
+    paymaster_service: Arc<PaymasterRelayService>,
+    /// Last status seen per (EntryPoint, paymaster)
+    statuses: std::sync::Mutex<BTreeMap<(Address, Address), ReputationStatus>>,
+    /// Entities that left `ok` during the last check
+    transitions: std::sync::Mutex<Vec<EntityReputation>>,
+}
+
+impl<P> ReputationProbe<P> {
+    /// Probe the reputation `pool` keeps for the paymasters of `paymaster_service`
+    pub fn new(pool: P, paymaster_service: Arc<PaymasterRelayService>) -> Self {
+        Self {
+            pool,
+            paymaster_service,
+            statuses: Default::default(),
+            transitions: Default::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl<P: Pool> HealthProbe for ReputationProbe<P> {
+    fn name(&self) -> &str {
+        "paymaster_reputation"
+    }
+
+    fn critical(&self) -> bool {
+        false
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        // Without EntryPoint routing the paymaster contracts are not known
+        let Ok(supported) = self.paymaster_service.supported_entry_points() else {
+            return Ok(());
+        };
+        let mut reputations = Vec::with_capacity(supported.len());
+        for entry_point in supported {
+            let reputation =
+                entity_reputation(&self.pool, entry_point.entry_point, entry_point.paymaster)
+                    .await
+                    .map_err(|e| format!("failed to read reputation: {}", e))?;
+            reputations.push(reputation);
+        }
+
+        let mut statuses = self.statuses.lock().unwrap();
+        let transitions: Vec<EntityReputation> = reputations
+            .iter()
+            .filter(|reputation| {
+                let key = (reputation.entry_point, reputation.address);
+                let previous = statuses.insert(key, reputation.status);
+                reputation.status != ReputationStatus::Ok
+                    && previous.is_none_or(|previous| previous == ReputationStatus::Ok)
+            })
+            .cloned()
+            .collect();
+        *self.transitions.lock().unwrap() = transitions;
+
+        let failing: Vec<String> = reputations
+            .iter()
+            .filter(|reputation| reputation.status != ReputationStatus::Ok)
+            .map(|reputation| {
+                format!(
+                    "paymaster {} is {:?} on {}",
+                    reputation.address, reputation.status, reputation.entry_point
+                )
+            })
+            .collect();
+        if failing.is_empty() {
+            Ok(())
+        } else {
+            Err(failing.join("; "))
+        }
+    }
+
+    fn details(&self) -> Option<Value> {
+        let statuses = self.statuses.lock().unwrap();
+        let statuses: Vec<Value> = statuses
+            .iter()
+            .map(|((entry_point, address), status)| {
+                serde_json::json!({
+                    "entryPoint": entry_point,
+                    "address": address,
+                    "status": status,
+                })
+            })
+            .collect();
+        Some(Value::Array(statuses))
+    }
+
+    fn alert(&self, reason: &str) -> Option<Alert> {
+        let transitions = std::mem::take(&mut *self.transitions.lock().unwrap());
+        if transitions.is_empty() {
+            return None;
+        }
+        Some(
+            Alert::new(AlertKind::EntityThrottled, self.name(), reason)
+                .with_details(serde_json::to_value(transitions).unwrap_or_default()),
+        )
+    }
+}
+
+/// Local clock is close enough to block time for sponsorships to be signed
+///
+/// Skews above the warning threshold pass but show in the details.
+pub struct ClockSkewProbe {
+    clock_guard: Arc<ClockGuard>,
+}
+
+impl ClockSkewProbe {
+    /// Report the measurements of `clock_guard`
+    pub fn new(clock_guard: Arc<ClockGuard>) -> Self {
+        Self { clock_guard }
+    }
+}
+
+#[async_trait]
+impl HealthProbe for ClockSkewProbe {
+    fn name(&self) -> &str {
+        "clock_skew"
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let status = self.clock_guard.status();
+        if status.state == ClockSkewState::Refusing {
+            return Err(format!(
+                "local clock is {}s off the chain, sponsorship signing refused above {}s",
+                status.skew_seconds.unwrap_or_default(),
+                status.refuse_seconds
+            ));
+        }
+        Ok(())
+    }
+
+    fn details(&self) -> Option<Value> {
+        serde_json::to_value(self.clock_guard.status()).ok()
+    }
+
+    fn alert(&self, reason: &str) -> Option<Alert> {
+        Some(Alert::new(AlertKind::SignerError, self.name(), reason))
+    }
+}
+
+/// Node endpoints behind hedged reads, with their rolling latency and errors
+///
+/// Not critical: the node probe decides readiness, this one shows which
+/// endpoint serves reads and fails only while every endpoint is failing.
+pub struct UpstreamProbe<P> {
+    name: String,
+    provider: Arc<HedgedEvmProvider<P>>,
+}
+
+impl<P> UpstreamProbe<P> {
+    /// Report the endpoint counters of `provider`
+    pub fn new(provider: Arc<HedgedEvmProvider<P>>) -> Self {
+        Self {
+            name: "upstreams".to_string(),
+            provider,
+        }
+    }
+
+    /// Report under `name` instead of "upstreams", e.g. when probing several chains
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+}
+
+#[async_trait]
+impl<P: Send + Sync> HealthProbe for UpstreamProbe<P> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn critical(&self) -> bool {
+        false
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let status = self.provider.status();
+        let errors: Option<Vec<String>> = status
+            .endpoints
+            .iter()
+            .map(|endpoint| {
+                endpoint
+                    .last_error
+                    .as_ref()
+                    .map(|error| format!("{}: {}", endpoint.name, error))
+            })
+            .collect();
+        match errors {
+            Some(errors) => Err(format!(
+                "every node endpoint is failing ({})",
+                errors.join("; ")
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn details(&self) -> Option<Value> {
+        serde_json::to_value(self.provider.status()).ok()
+    }
+}
+
+/// Reads EntryPoint deposits; implemented for every EntryPoint provider
+#[async_trait]
+pub trait DepositReader: Send + Sync {
+    /// Deposit of `account` on the EntryPoint
+    async fn deposit_of(&self, account: Address) -> Result<U256, String>;
+}
+
+#[async_trait]
+impl<E: EntryPoint> DepositReader for E {
+    async fn deposit_of(&self, account: Address) -> Result<U256, String> {
+        self.balance_of(account, None)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Paymaster deposit on an EntryPoint is at least the configured minimum
+pub struct DepositProbe {
+    name: String,
+    entry_point: Arc<dyn DepositReader>,
+    paymaster_service: Arc<PaymasterRelayService>,
+    min_deposit: U256,
+}
+
+impl DepositProbe {
+    /// Probe the paymaster's deposit on `entry_point`, reported under `name`
+    pub fn new(
+        name: impl Into<String>,
+        entry_point: Arc<dyn DepositReader>,
+        paymaster_service: Arc<PaymasterRelayService>,
+        min_deposit: U256,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            entry_point,
+            paymaster_service,
+            min_deposit,
+        }
+    }
+}
+
+#[async_trait]
+impl HealthProbe for DepositProbe {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let paymaster = self.paymaster_service.paymaster_address().await;
+        let deposit = self
+            .entry_point
+            .deposit_of(paymaster)
+            .await
+            .map_err(|e| format!("{}: {}", DEPOSIT_READ_FAILED, e))?;
+        if deposit < self.min_deposit {
+            return Err(format!(
+                "paymaster {} deposit {} wei is below minimum {} wei",
+                paymaster, deposit, self.min_deposit
+            ));
+        }
+        Ok(())
+    }
+
+    fn alert(&self, reason: &str) -> Option<Alert> {
+        // Unreachable nodes are reported by the node probe
+        if reason.starts_with(DEPOSIT_READ_FAILED) {
+            return None;
+        }
+        Some(
+            Alert::new(AlertKind::DepositLow, self.name(), reason)
+                .with_details(serde_json::json!({ "minDepositWei": self.min_deposit.to_string() })),
+        )
+    }
+}
+
+/// Threat feed data is fresh; stale intel degrades the gateway without failing readiness
+pub struct ThreatIntelProbe {
+    store: Arc<ThreatIntelStore>,
+}
+
+impl ThreatIntelProbe {
+    /// Probe the freshness of `store`
+    pub fn new(store: Arc<ThreatIntelStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl HealthProbe for ThreatIntelProbe {
+    fn name(&self) -> &str {
+        "threat_intel"
+    }
+
+    fn critical(&self) -> bool {
+        false
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let status = self.store.status();
+        if !status.stale {
+            return Ok(());
+        }
+        let errors: Vec<String> = status
+            .feeds
+            .iter()
+            .filter_map(|feed| {
+                feed.last_error
+                    .as_ref()
+                    .map(|e| format!("{}: {}", feed.name, e))
+            })
+            .collect();
+        Err(match status.age_seconds {
+            Some(age) => format!(
+                "threat intelligence is {}s old ({})",
+                age,
+                errors.join("; ")
+            ),
+            None => format!("threat feeds not loaded ({})", errors.join("; ")),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
     use super::*;
+    use crate::{
+        chains::{ChainRegistry, ChainRoute},
+        connection_limit::ConnectionLimiter,
+        events::EventBus,
+        middleware::AuthMiddleware,
+        request_log::RequestLogger,
+        router::GatewayRouter,
+        shutdown::ShutdownController,
+        GatewayConfig,
+    };
+
+    /// Probe with a fixed outcome that counts its runs
+    struct StaticProbe {
+        name: &'static str,
+        critical: bool,
+        result: Result<(), String>,
+        delay: Duration,
+        runs: AtomicUsize,
+    }
+
+    impl StaticProbe {
+        fn new(name: &'static str, critical: bool, result: Result<(), String>) -> Self {
+            Self {
+                name,
+                critical,
+                result,
+                delay: Duration::ZERO,
+                runs: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl HealthProbe for StaticProbe {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn critical(&self) -> bool {
+            self.critical
+        }
+
+        async fn check(&self) -> Result<(), String> {
+            self.runs.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            self.result.clone()
+        }
+    }
+
+    fn create_test_state(shutdown: ShutdownController, health: HealthChecker) -> GatewayState {
+        GatewayState {
+            paymaster_service: None,
+            router: GatewayRouter::new(),
+            config: GatewayConfig::default(),
+            auth: AuthMiddleware::new(),
+            shutdown,
+            health,
+            chains: ChainRegistry::new(ChainRoute::new(GatewayRouter::new(), None)),
+            request_log: RequestLogger::default(),
+            prometheus: None,
+            access_control: None,
+            alerts: AlertBus::disabled(),
+            connection_limiter: Arc::new(ConnectionLimiter::unlimited()),
+            events: EventBus::new(Default::default()),
+            admin_methods: true,
+        }
+    }
+
+    fn report(status: ComponentStatus, critical: bool) -> ProbeReport {
+        ProbeReport {
+            name: "test".to_string(),
+            critical,
+            health: ComponentHealth {
+                status,
+                last_check: 0,
+                response_time_ms: Some(10),
+                error: None,
+                details: None,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_unavailable_while_draining() {
+        let shutdown = ShutdownController::new();
+        let state = create_test_state(shutdown.clone(), HealthChecker::new());
+
+        assert_eq!(
+            readiness_check(State(state.clone())).await.0,
+            StatusCode::OK
+        );
+        assert!(health_check(State(state.clone())).await.is_ok());
+
+        shutdown.trigger();
+
+        assert_eq!(
+            readiness_check(State(state.clone())).await.0,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            health_check(State(state)).await.unwrap_err(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failing_critical_probe_fails_readiness_only() {
+        let health = HealthChecker::new()
+            .with_probe(Arc::new(StaticProbe::new("pool", true, Ok(()))))
+            .with_probe(Arc::new(StaticProbe::new(
+                "node",
+                true,
+                Err("node RPC unreachable".to_string()),
+            )));
+        let state = create_test_state(ShutdownController::new(), health);
+
+        let (status, Json(ready)) = readiness_check(State(state.clone())).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!ready.ready);
+        assert_eq!(ready.components.len(), 2);
+        assert_eq!(ready.components[0].name, "pool");
+        assert_eq!(ready.components[1].name, "node");
+        assert_eq!(ready.components[1].health.status, ComponentStatus::Error);
+        assert_eq!(
+            ready.components[1].health.error.as_deref(),
+            Some("node RPC unreachable")
+        );
+        assert!(ready.components[1].health.response_time_ms.is_some());
+
+        // Liveness only depends on the process
+        let Json(health) = health_check(State(state)).await.unwrap();
+        assert_eq!(health.status, SystemStatus::Unhealthy);
+        assert_eq!(health.components.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_optional_probe_does_not_block_readiness() {
+        let health = HealthChecker::new().with_probe(Arc::new(StaticProbe::new(
+            "deposit",
+            false,
+            Err("deposit below minimum".to_string()),
+        )));
+        let state = create_test_state(ShutdownController::new(), health);
+
+        assert_eq!(readiness_check(State(state)).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_probe_timeout_and_cache() {
+        let mut slow = StaticProbe::new("slow", true, Ok(()));
+        slow.delay = Duration::from_secs(5);
+        let slow = Arc::new(slow);
+        let health = HealthChecker::with_config(HealthConfig {
+            probe_timeout_ms: 50,
+            cache_ttl_seconds: 60,
+            ..Default::default()
+        })
+        .with_probe(slow.clone());
+
+        let reports = health.probe_components().await;
+        assert_eq!(reports[0].health.status, ComponentStatus::Error);
+        assert!(reports[0]
+            .health
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("timed out"));
+
+        // Cached results are served without probing again
+        health.probe_components().await;
+        assert_eq!(slow.runs.load(Ordering::SeqCst), 1);
+    }
 
     #[tokio::test]
     async fn test_health_checker_creation() {
@@ -406,42 +1335,30 @@ mod tests {
     fn test_overall_status_determination() {
         let checker = HealthChecker::new();
 
-        let healthy_component = ComponentHealth {
-            status: ComponentStatus::Healthy,
-            last_check: 0,
-            response_time_ms: Some(10),
-            error: None,
-        };
-
-        let warning_component = ComponentHealth {
-            status: ComponentStatus::Warning,
-            last_check: 0,
-            response_time_ms: Some(20),
-            error: Some("Warning message".to_string()),
-        };
-
-        let error_component = ComponentHealth {
-            status: ComponentStatus::Error,
-            last_check: 0,
-            response_time_ms: Some(100),
-            error: Some("Error message".to_string()),
-        };
+        let healthy = report(ComponentStatus::Healthy, true);
+        let warning = report(ComponentStatus::Warning, true);
+        let optional_error = report(ComponentStatus::Error, false);
+        let critical_error = report(ComponentStatus::Error, true);
 
         // All healthy
         assert_eq!(
-            checker.determine_overall_status(&[&healthy_component, &healthy_component]),
+            checker.determine_overall_status(&[healthy.clone(), healthy.clone()]),
             SystemStatus::Healthy
         );
 
-        // Has warning
+        // Has warning or a failing optional component
+        assert_eq!(
+            checker.determine_overall_status(&[healthy.clone(), warning]),
+            SystemStatus::Degraded
+        );
         assert_eq!(
-            checker.determine_overall_status(&[&healthy_component, &warning_component]),
+            checker.determine_overall_status(&[healthy.clone(), optional_error]),
             SystemStatus::Degraded
         );
 
-        // Has error
+        // Has critical error
         assert_eq!(
-            checker.determine_overall_status(&[&healthy_component, &error_component]),
+            checker.determine_overall_status(&[healthy, critical_error]),
             SystemStatus::Unhealthy
         );
     }
@@ -0,0 +1,892 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use alloy_primitives::{Address, Bytes, TxHash, B256, U256};
+use async_trait::async_trait;
+use metrics::{counter, gauge, histogram};
+use rundler_provider::{
+    Block, BlockId, BlockNumberOrTag, EvmCall, EvmProvider, FeeHistory, Filter, GasUsedResult,
+    GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace, Log, ProviderError,
+    ProviderResult, RpcRecv, RpcSend, StateOverride, Transaction, TransactionReceipt,
+    TransactionRequest,
+};
+use rundler_types::ExpectedStorage;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+/// Weight of the newest sample in an endpoint's rolling latency
+const LATENCY_WEIGHT: f64 = 0.2;
+
+/// Hedged read settings (`[node.hedging]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HedgedReadConfig {
+    /// Time the preferred endpoint gets to answer a read before it is also
+    /// sent to the other endpoint
+    pub hedge_delay_ms: u64,
+    /// How much lower, in percent, the other endpoint's rolling latency must
+    /// be before reads prefer it, so close endpoints do not flap
+    pub switch_margin_percent: u64,
+}
+
+impl Default for HedgedReadConfig {
+    fn default() -> Self {
+        Self {
+            hedge_delay_ms: 150,
+            switch_margin_percent: 20,
+        }
+    }
+}
+
+/// Counters of one node endpoint, reported on /health
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamEndpointStatus {
+    /// "primary" or "secondary"
+    pub name: String,
+    /// Whether reads are sent here first
+    pub preferred: bool,
+    /// Rolling latency of answered requests, unset until one was answered
+    pub latency_ms: Option<f64>,
+    /// Requests sent, including hedges that lost the race
+    pub requests: u64,
+    /// Requests that failed
+    pub errors: u64,
+    /// Hedged reads this endpoint answered first
+    pub hedge_wins: u64,
+    /// Error of the last request, cleared by the next success
+    pub last_error: Option<String>,
+}
+
+/// Hedged read state of a chain's node endpoints
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamStatus {
+    /// Delay before a read is hedged
+    pub hedge_delay_ms: u64,
+    /// Reads that were sent to both endpoints
+    pub hedged_reads: u64,
+    /// Primary first, then the secondary when configured
+    pub endpoints: Vec<UpstreamEndpointStatus>,
+}
+
+#[derive(Debug, Default)]
+struct EndpointStats {
+    latency_ms: Option<f64>,
+    requests: u64,
+    errors: u64,
+    hedge_wins: u64,
+    last_error: Option<String>,
+}
+
+struct Endpoint<P> {
+    name: &'static str,
+    provider: P,
+    stats: Mutex<EndpointStats>,
+}
+
+impl<P> Endpoint<P> {
+    fn new(name: &'static str, provider: P) -> Self {
+        Self {
+            name,
+            provider,
+            stats: Mutex::new(EndpointStats::default()),
+        }
+    }
+
+    fn latency_ms(&self) -> Option<f64> {
+        self.stats.lock().unwrap().latency_ms
+    }
+}
+
+/// [`EvmProvider`] over a primary and an optional secondary node endpoint
+///
+/// Reads go to the preferred endpoint and, when it has not answered within
+/// the hedge delay, to the other one as well; the first success is returned
+/// and the slower request is dropped. An error fails over without waiting
+/// for the delay. Reads prefer whichever endpoint has the lower rolling
+/// latency. Transactions and arbitrary `request` calls, which may be writes,
+/// only go to the primary, and traces go to the preferred endpoint without
+/// hedging as they are too expensive to run twice.
+///
+/// Endpoints may be a block apart, so two reads of "latest" state can be
+/// answered by different nodes.
+pub struct HedgedEvmProvider<P> {
+    chain_id: String,
+    config: HedgedReadConfig,
+    /// The primary, then the secondary
+    endpoints: Vec<Endpoint<P>>,
+    preferred: AtomicUsize,
+    hedged_reads: AtomicU64,
+}
+
+impl<P> HedgedEvmProvider<P> {
+    /// Provider sending everything to `primary` until a secondary is added
+    pub fn new(primary: P, chain_id: u64) -> Self {
+        Self {
+            chain_id: chain_id.to_string(),
+            config: HedgedReadConfig::default(),
+            endpoints: vec![Endpoint::new("primary", primary)],
+            preferred: AtomicUsize::new(0),
+            hedged_reads: AtomicU64::new(0),
+        }
+    }
+
+    /// Hedge reads to `secondary` as configured by `config`
+    pub fn with_secondary(mut self, secondary: P, config: HedgedReadConfig) -> Self {
+        self.endpoints.truncate(1);
+        self.endpoints.push(Endpoint::new("secondary", secondary));
+        self.config = config;
+        self
+    }
+
+    /// Endpoint counters and rolling latencies
+    pub fn status(&self) -> UpstreamStatus {
+        let preferred = self.preferred.load(Ordering::Relaxed);
+        UpstreamStatus {
+            hedge_delay_ms: self.config.hedge_delay_ms,
+            hedged_reads: self.hedged_reads.load(Ordering::Relaxed),
+            endpoints: self
+                .endpoints
+                .iter()
+                .enumerate()
+                .map(|(index, endpoint)| {
+                    let stats = endpoint.stats.lock().unwrap();
+                    UpstreamEndpointStatus {
+                        name: endpoint.name.to_string(),
+                        preferred: index == preferred,
+                        latency_ms: stats.latency_ms,
+                        requests: stats.requests,
+                        errors: stats.errors,
+                        hedge_wins: stats.hedge_wins,
+                        last_error: stats.last_error.clone(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Send `call` to endpoint `index` alone
+    async fn through<'a, T, F, Fut>(&'a self, index: usize, call: F) -> ProviderResult<T>
+    where
+        F: FnOnce(&'a P) -> Fut,
+        Fut: Future<Output = ProviderResult<T>>,
+    {
+        let started = Instant::now();
+        let result = call(&self.endpoints[index].provider).await;
+        self.record(index, started, &result);
+        result
+    }
+
+    /// Send a read to the preferred endpoint, hedged to the other one
+    async fn read<'a, T, F, Fut>(&'a self, call: F) -> ProviderResult<T>
+    where
+        F: Fn(&'a P) -> Fut,
+        Fut: Future<Output = ProviderResult<T>>,
+    {
+        let lead = self.preferred.load(Ordering::Relaxed);
+        if self.endpoints.len() == 1 {
+            return self.through(lead, call).await;
+        }
+        let backup = 1 - lead;
+
+        let lead_started = Instant::now();
+        let lead_call = call(&self.endpoints[lead].provider);
+        tokio::pin!(lead_call);
+        let answered = tokio::select! {
+            result = &mut lead_call => Some(result),
+            _ = tokio::time::sleep(Duration::from_millis(self.config.hedge_delay_ms)) => None,
+        };
+        match answered {
+            Some(Ok(value)) => {
+                self.succeeded(lead, lead_started);
+                return Ok(value);
+            }
+            Some(Err(error)) => {
+                self.failed(lead, &error);
+                return self.through(backup, call).await.or(Err(error));
+            }
+            None => {}
+        }
+
+        self.hedged_reads.fetch_add(1, Ordering::Relaxed);
+        counter!("superrelay_upstream_hedged_reads_total", "chain_id" => self.chain_id.clone())
+            .increment(1);
+        let backup_started = Instant::now();
+        let backup_call = call(&self.endpoints[backup].provider);
+        tokio::pin!(backup_call);
+        let (first, result) = tokio::select! {
+            result = &mut lead_call => (lead, result),
+            result = &mut backup_call => (backup, result),
+        };
+        let (first_started, other, other_started, other_call) = if first == lead {
+            (lead_started, backup, backup_started, backup_call)
+        } else {
+            (backup_started, lead, lead_started, lead_call)
+        };
+        match result {
+            Ok(value) => {
+                self.endpoints[first].stats.lock().unwrap().hedge_wins += 1;
+                self.succeeded(first, first_started);
+                // Dropped unanswered, after at least this long
+                self.lost(other, other_started);
+                Ok(value)
+            }
+            Err(error) => {
+                self.failed(first, &error);
+                let result = other_call.await;
+                self.record(other, other_started, &result);
+                result.or(Err(error))
+            }
+        }
+    }
+
+    fn record<T>(&self, index: usize, started: Instant, result: &ProviderResult<T>) {
+        match result {
+            Ok(_) => self.succeeded(index, started),
+            Err(error) => self.failed(index, error),
+        }
+    }
+
+    fn succeeded(&self, index: usize, started: Instant) {
+        let elapsed = started.elapsed();
+        let endpoint = &self.endpoints[index];
+        {
+            let mut stats = endpoint.stats.lock().unwrap();
+            stats.requests += 1;
+            stats.last_error = None;
+            stats.latency_ms = Some(rolling(stats.latency_ms, elapsed));
+        }
+        counter!(
+            "superrelay_upstream_requests_total",
+            "chain_id" => self.chain_id.clone(),
+            "endpoint" => endpoint.name,
+            "outcome" => "ok"
+        )
+        .increment(1);
+        histogram!(
+            "superrelay_upstream_request_duration_seconds",
+            "chain_id" => self.chain_id.clone(),
+            "endpoint" => endpoint.name
+        )
+        .record(elapsed.as_secs_f64());
+        self.update_preference();
+    }
+
+    fn failed(&self, index: usize, error: &ProviderError) {
+        let endpoint = &self.endpoints[index];
+        {
+            let mut stats = endpoint.stats.lock().unwrap();
+            stats.requests += 1;
+            stats.errors += 1;
+            stats.last_error = Some(error.to_string());
+        }
+        warn!("Node endpoint {} failed: {}", endpoint.name, error);
+        counter!(
+            "superrelay_upstream_requests_total",
+            "chain_id" => self.chain_id.clone(),
+            "endpoint" => endpoint.name,
+            "outcome" => "error"
+        )
+        .increment(1);
+    }
+
+    /// The other endpoint answered a hedged read first
+    fn lost(&self, index: usize, started: Instant) {
+        let elapsed = started.elapsed();
+        let endpoint = &self.endpoints[index];
+        {
+            let mut stats = endpoint.stats.lock().unwrap();
+            stats.requests += 1;
+            // Only a lower bound of its latency, which says nothing when below the estimate
+            if stats
+                .latency_ms
+                .is_none_or(|latency| latency < elapsed.as_secs_f64() * 1000.0)
+            {
+                stats.latency_ms = Some(rolling(stats.latency_ms, elapsed));
+            }
+        }
+        counter!(
+            "superrelay_upstream_requests_total",
+            "chain_id" => self.chain_id.clone(),
+            "endpoint" => endpoint.name,
+            "outcome" => "lost"
+        )
+        .increment(1);
+        self.update_preference();
+    }
+
+    /// Prefer the other endpoint once its rolling latency is lower by the margin
+    fn update_preference(&self) {
+        if self.endpoints.len() == 1 {
+            return;
+        }
+        let current = self.preferred.load(Ordering::Relaxed);
+        let other = 1 - current;
+        let (Some(current_ms), Some(other_ms)) = (
+            self.endpoints[current].latency_ms(),
+            self.endpoints[other].latency_ms(),
+        ) else {
+            return;
+        };
+        let margin = 1.0 + self.config.switch_margin_percent as f64 / 100.0;
+        if other_ms * margin >= current_ms
+            || self
+                .preferred
+                .compare_exchange(current, other, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            return;
+        }
+        info!(
+            "🔀 Reads now prefer the {} node endpoint ({:.0}ms vs {:.0}ms)",
+            self.endpoints[other].name, other_ms, current_ms
+        );
+        for (index, endpoint) in self.endpoints.iter().enumerate() {
+            gauge!(
+                "superrelay_upstream_preferred",
+                "chain_id" => self.chain_id.clone(),
+                "endpoint" => endpoint.name
+            )
+            .set(if index == other { 1.0 } else { 0.0 });
+        }
+    }
+}
+
+fn rolling(latency_ms: Option<f64>, elapsed: Duration) -> f64 {
+    let sample = elapsed.as_secs_f64() * 1000.0;
+    match latency_ms {
+        Some(latency) => latency + LATENCY_WEIGHT * (sample - latency),
+        None => sample,
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> EvmProvider for HedgedEvmProvider<P> {
+    async fn request<Params, R>(&self, method: &'static str, params: Params) -> ProviderResult<R>
+    where
+        Params: RpcSend + 'static,
+        R: RpcRecv,
+    {
+        // Arbitrary methods may write
+        self.through(0, |node| node.request(method, params)).await
+    }
+
+    async fn fee_history(
+        &self,
+        block_count: u64,
+        block_number: BlockNumberOrTag,
+        reward_percentiles: &[f64],
+    ) -> ProviderResult<FeeHistory> {
+        self.read(|node| node.fee_history(block_count, block_number, reward_percentiles))
+            .await
+    }
+
+    async fn call(
+        &self,
+        tx: TransactionRequest,
+        block: Option<BlockId>,
+        state_overrides: Option<StateOverride>,
+    ) -> ProviderResult<Bytes> {
+        self.read(|node| node.call(tx.clone(), block, state_overrides.clone()))
+            .await
+    }
+
+    async fn send_raw_transaction(&self, tx: Bytes) -> ProviderResult<TxHash> {
+        self.through(0, |node| node.send_raw_transaction(tx)).await
+    }
+
+    async fn send_raw_transaction_conditional(
+        &self,
+        tx: Bytes,
+        expected_storage: &ExpectedStorage,
+    ) -> ProviderResult<TxHash> {
+        self.through(0, |node| {
+            node.send_raw_transaction_conditional(tx, expected_storage)
+        })
+        .await
+    }
+
+    async fn get_block_number(&self) -> ProviderResult<u64> {
+        self.read(|node| node.get_block_number()).await
+    }
+
+    async fn get_block(&self, block_id: BlockId) -> ProviderResult<Option<Block>> {
+        self.read(|node| node.get_block(block_id)).await
+    }
+
+    async fn get_full_block(&self, block_id: BlockId) -> ProviderResult<Option<Block>> {
+        self.read(|node| node.get_full_block(block_id)).await
+    }
+
+    async fn get_balance(&self, address: Address, block: Option<BlockId>) -> ProviderResult<U256> {
+        self.read(|node| node.get_balance(address, block)).await
+    }
+
+    async fn get_transaction_by_hash(&self, tx: TxHash) -> ProviderResult<Option<Transaction>> {
+        self.read(|node| node.get_transaction_by_hash(tx)).await
+    }
+
+    async fn get_transaction_receipt(
+        &self,
+        tx: TxHash,
+    ) -> ProviderResult<Option<TransactionReceipt>> {
+        self.read(|node| node.get_transaction_receipt(tx)).await
+    }
+
+    async fn debug_trace_transaction(
+        &self,
+        tx_hash: TxHash,
+        trace_options: GethDebugTracingOptions,
+    ) -> ProviderResult<GethTrace> {
+        let preferred = self.preferred.load(Ordering::Relaxed);
+        self.through(preferred, |node| {
+            node.debug_trace_transaction(tx_hash, trace_options)
+        })
+        .await
+    }
+
+    async fn debug_trace_call(
+        &self,
+        tx: TransactionRequest,
+        block_id: Option<BlockId>,
+        trace_options: GethDebugTracingCallOptions,
+    ) -> ProviderResult<GethTrace> {
+        let preferred = self.preferred.load(Ordering::Relaxed);
+        self.through(preferred, |node| {
+            node.debug_trace_call(tx, block_id, trace_options)
+        })
+        .await
+    }
+
+    async fn get_latest_block_hash_and_number(&self) -> ProviderResult<(B256, u64)> {
+        self.read(|node| node.get_latest_block_hash_and_number())
+            .await
+    }
+
+    async fn get_pending_base_fee(&self) -> ProviderResult<u128> {
+        self.read(|node| node.get_pending_base_fee()).await
+    }
+
+    async fn get_max_priority_fee(&self) -> ProviderResult<u128> {
+        self.read(|node| node.get_max_priority_fee()).await
+    }
+
+    async fn get_code(&self, address: Address, block: Option<BlockId>) -> ProviderResult<Bytes> {
+        self.read(|node| node.get_code(address, block)).await
+    }
+
+    async fn get_transaction_count(&self, address: Address) -> ProviderResult<u64> {
+        self.read(|node| node.get_transaction_count(address)).await
+    }
+
+    async fn get_logs(&self, filter: &Filter) -> ProviderResult<Vec<Log>> {
+        self.read(|node| node.get_logs(filter)).await
+    }
+
+    async fn get_gas_used(&self, call: EvmCall) -> ProviderResult<GasUsedResult> {
+        self.read(|node| node.get_gas_used(call.clone())).await
+    }
+
+    async fn batch_get_storage_at(
+        &self,
+        address: Address,
+        slots: Vec<B256>,
+    ) -> ProviderResult<Vec<B256>> {
+        self.read(|node| node.batch_get_storage_at(address, slots.clone()))
+            .await
+    }
+
+    async fn get_code_hash(
+        &self,
+        addresses: Vec<Address>,
+        block: Option<BlockId>,
+    ) -> ProviderResult<B256> {
+        self.read(|node| node.get_code_hash(addresses.clone(), block))
+            .await
+    }
+
+    async fn get_balances(&self, addresses: Vec<Address>) -> ProviderResult<Vec<(Address, U256)>> {
+        self.read(|node| node.get_balances(addresses.clone())).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+        time::{Duration, Instant},
+    };
+
+    use alloy_primitives::{address, Address, Bytes, TxHash, B256, U256};
+    use async_trait::async_trait;
+    use rundler_provider::{
+        Block, BlockId, BlockNumberOrTag, EvmCall, EvmProvider, FeeHistory, Filter, GasUsedResult,
+        GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace, Log, ProviderError,
+        ProviderResult, RpcRecv, RpcSend, StateOverride, Transaction, TransactionReceipt,
+        TransactionRequest,
+    };
+    use rundler_types::ExpectedStorage;
+
+    use super::*;
+    use crate::{HealthProbe, UpstreamProbe};
+
+    const ACCOUNT: Address = address!("00000000000000000000000000000000000000a1");
+
+    /// Node answering balance reads with `balance` after `delay`
+    struct FakeNode {
+        balance: u64,
+        delay: Mutex<Duration>,
+        failing: Mutex<bool>,
+        /// Reads started
+        reads: AtomicU64,
+        /// Reads that ran to completion rather than being dropped
+        answered: AtomicU64,
+        sent: Mutex<Vec<Bytes>>,
+    }
+
+    impl FakeNode {
+        fn new(balance: u64, delay_ms: u64) -> Arc<Self> {
+            Arc::new(Self {
+                balance,
+                delay: Mutex::new(Duration::from_millis(delay_ms)),
+                failing: Mutex::new(false),
+                reads: AtomicU64::new(0),
+                answered: AtomicU64::new(0),
+                sent: Mutex::new(Vec::new()),
+            })
+        }
+
+        fn set_delay(&self, delay_ms: u64) {
+            *self.delay.lock().unwrap() = Duration::from_millis(delay_ms);
+        }
+
+        fn reads(&self) -> u64 {
+            self.reads.load(Ordering::SeqCst)
+        }
+
+        fn answered(&self) -> u64 {
+            self.answered.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl EvmProvider for FakeNode {
+        async fn request<P, R>(&self, _method: &'static str, _params: P) -> ProviderResult<R>
+        where
+            P: RpcSend + 'static,
+            R: RpcRecv,
+        {
+            unimplemented!()
+        }
+
+        async fn fee_history(
+            &self,
+            _block_count: u64,
+            _block_number: BlockNumberOrTag,
+            _reward_percentiles: &[f64],
+        ) -> ProviderResult<FeeHistory> {
+            unimplemented!()
+        }
+
+        async fn call(
+            &self,
+            _tx: TransactionRequest,
+            _block: Option<BlockId>,
+            _state_overrides: Option<StateOverride>,
+        ) -> ProviderResult<Bytes> {
+            unimplemented!()
+        }
+
+        async fn send_raw_transaction(&self, tx: Bytes) -> ProviderResult<TxHash> {
+            self.sent.lock().unwrap().push(tx);
+            Ok(TxHash::ZERO)
+        }
+
+        async fn send_raw_transaction_conditional(
+            &self,
+            tx: Bytes,
+            _expected_storage: &ExpectedStorage,
+        ) -> ProviderResult<TxHash> {
+            self.sent.lock().unwrap().push(tx);
+            Ok(TxHash::ZERO)
+        }
+
+        async fn get_block_number(&self) -> ProviderResult<u64> {
+            unimplemented!()
+        }
+
+        async fn get_block(&self, _block_id: BlockId) -> ProviderResult<Option<Block>> {
+            unimplemented!()
+        }
+
+        async fn get_full_block(&self, _block_id: BlockId) -> ProviderResult<Option<Block>> {
+            unimplemented!()
+        }
+
+        async fn get_balance(
+            &self,
+            address: Address,
+            _block: Option<BlockId>,
+        ) -> ProviderResult<U256> {
+            assert_eq!(address, ACCOUNT);
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            let delay = *self.delay.lock().unwrap();
+            tokio::time::sleep(delay).await;
+            self.answered.fetch_add(1, Ordering::SeqCst);
+            if *self.failing.lock().unwrap() {
+                return Err(ProviderError::Other(anyhow::anyhow!("node unavailable")));
+            }
+            Ok(U256::from(self.balance))
+        }
+
+        async fn get_transaction_by_hash(
+            &self,
+            _tx: TxHash,
+        ) -> ProviderResult<Option<Transaction>> {
+            unimplemented!()
+        }
+
+        async fn get_transaction_receipt(
+            &self,
+            _tx: TxHash,
+        ) -> ProviderResult<Option<TransactionReceipt>> {
+            unimplemented!()
+        }
+
+        async fn debug_trace_transaction(
+            &self,
+            _tx_hash: TxHash,
+            _trace_options: GethDebugTracingOptions,
+        ) -> ProviderResult<GethTrace> {
+            unimplemented!()
+        }
+
+        async fn debug_trace_call(
+            &self,
+            _tx: TransactionRequest,
+            _block_id: Option<BlockId>,
+            _trace_options: GethDebugTracingCallOptions,
+        ) -> ProviderResult<GethTrace> {
+            unimplemented!()
+        }
+
+        async fn get_latest_block_hash_and_number(&self) -> ProviderResult<(B256, u64)> {
+            unimplemented!()
+        }
+
+        async fn get_pending_base_fee(&self) -> ProviderResult<u128> {
+            unimplemented!()
+        }
+
+        async fn get_max_priority_fee(&self) -> ProviderResult<u128> {
+            unimplemented!()
+        }
+
+        async fn get_code(
+            &self,
+            _address: Address,
+            _block: Option<BlockId>,
+        ) -> ProviderResult<Bytes> {
+            unimplemented!()
+        }
+
+        async fn get_transaction_count(&self, _address: Address) -> ProviderResult<u64> {
+            unimplemented!()
+        }
+
+        async fn get_logs(&self, _filter: &Filter) -> ProviderResult<Vec<Log>> {
+            unimplemented!()
+        }
+
+        async fn get_gas_used(&self, _call: EvmCall) -> ProviderResult<GasUsedResult> {
+            unimplemented!()
+        }
+
+        async fn batch_get_storage_at(
+            &self,
+            _address: Address,
+            _slots: Vec<B256>,
+        ) -> ProviderResult<Vec<B256>> {
+            unimplemented!()
+        }
+
+        async fn get_code_hash(
+            &self,
+            _addresses: Vec<Address>,
+            _block: Option<BlockId>,
+        ) -> ProviderResult<B256> {
+            unimplemented!()
+        }
+
+        async fn get_balances(
+            &self,
+            _addresses: Vec<Address>,
+        ) -> ProviderResult<Vec<(Address, U256)>> {
+            unimplemented!()
+        }
+    }
+
+    fn hedged(
+        primary: &Arc<FakeNode>,
+        secondary: &Arc<FakeNode>,
+        hedge_delay_ms: u64,
+    ) -> Arc<HedgedEvmProvider<Arc<FakeNode>>> {
+        let config = HedgedReadConfig {
+            hedge_delay_ms,
+            switch_margin_percent: 20,
+        };
+        Arc::new(
+            HedgedEvmProvider::new(primary.clone(), 1).with_secondary(secondary.clone(), config),
+        )
+    }
+
+    async fn balance(provider: &HedgedEvmProvider<Arc<FakeNode>>) -> ProviderResult<u64> {
+        provider
+            .get_balance(ACCOUNT, None)
+            .await
+            .map(|balance| balance.to())
+    }
+
+    #[tokio::test]
+    async fn test_fast_primary_is_not_hedged() {
+        let primary = FakeNode::new(1, 5);
+        let secondary = FakeNode::new(2, 5);
+        let provider = hedged(&primary, &secondary, 200);
+
+        assert_eq!(balance(&provider).await.unwrap(), 1);
+        assert_eq!(secondary.reads(), 0);
+        assert_eq!(provider.status().hedged_reads, 0);
+    }
+
+    #[tokio::test]
+    async fn test_slow_primary_is_hedged_and_first_answer_wins() {
+        let primary = FakeNode::new(1, 2_000);
+        let secondary = FakeNode::new(2, 10);
+        let provider = hedged(&primary, &secondary, 50);
+
+        let started = Instant::now();
+        assert_eq!(balance(&provider).await.unwrap(), 2);
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        // Each node was asked once and the slow request was dropped, not answered twice
+        assert_eq!((primary.reads(), secondary.reads()), (1, 1));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(primary.answered(), 0);
+
+        let status = provider.status();
+        assert_eq!(status.hedged_reads, 1);
+        assert_eq!(status.endpoints[1].hedge_wins, 1);
+        assert_eq!(status.endpoints[0].hedge_wins, 0);
+    }
+
+    #[tokio::test]
+    async fn test_faster_endpoint_becomes_preferred() {
+        let primary = FakeNode::new(1, 300);
+        let secondary = FakeNode::new(2, 10);
+        let provider = hedged(&primary, &secondary, 50);
+
+        assert_eq!(balance(&provider).await.unwrap(), 2);
+        let status = provider.status();
+        assert!(!status.endpoints[0].preferred);
+        assert!(status.endpoints[1].preferred);
+
+        // Reads now go to the secondary first, without hedging
+        assert_eq!(balance(&provider).await.unwrap(), 2);
+        assert_eq!((primary.reads(), secondary.reads()), (1, 2));
+        assert_eq!(provider.status().hedged_reads, 1);
+
+        // Until the primary turns out faster in turn
+        primary.set_delay(5);
+        secondary.set_delay(300);
+        for _ in 0..10 {
+            assert!(balance(&provider).await.is_ok());
+        }
+        assert!(provider.status().endpoints[0].preferred);
+    }
+
+    #[tokio::test]
+    async fn test_error_fails_over_without_waiting_for_hedge_delay() {
+        let primary = FakeNode::new(1, 0);
+        *primary.failing.lock().unwrap() = true;
+        let secondary = FakeNode::new(2, 10);
+        let provider = hedged(&primary, &secondary, 5_000);
+
+        let started = Instant::now();
+        assert_eq!(balance(&provider).await.unwrap(), 2);
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        let status = provider.status();
+        assert_eq!(status.hedged_reads, 0);
+        assert_eq!(status.endpoints[0].errors, 1);
+        assert!(status.endpoints[0].last_error.is_some());
+        assert_eq!(status.endpoints[1].last_error, None);
+
+        // Both failing returns the preferred endpoint's error
+        *secondary.failing.lock().unwrap() = true;
+        let err = balance(&provider).await.unwrap_err();
+        assert!(err.to_string().contains("node unavailable"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_transactions_only_go_to_primary() {
+        let primary = FakeNode::new(1, 300);
+        let secondary = FakeNode::new(2, 10);
+        let provider = hedged(&primary, &secondary, 50);
+
+        // Even once reads prefer the secondary
+        balance(&provider).await.unwrap();
+        assert!(provider.status().endpoints[1].preferred);
+
+        let tx = Bytes::from_static(&[0x02, 0x01]);
+        provider.send_raw_transaction(tx.clone()).await.unwrap();
+        provider
+            .send_raw_transaction_conditional(tx.clone(), &ExpectedStorage::default())
+            .await
+            .unwrap();
+
+        assert_eq!(*primary.sent.lock().unwrap(), vec![tx.clone(), tx]);
+        assert!(secondary.sent.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_upstream_probe_reports_endpoints() {
+        let primary = FakeNode::new(1, 0);
+        let secondary = FakeNode::new(2, 0);
+        let provider = hedged(&primary, &secondary, 50);
+        let probe = UpstreamProbe::new(provider.clone());
+        assert!(!probe.critical());
+
+        balance(&provider).await.unwrap();
+        probe.check().await.unwrap();
+        let details = probe.details().unwrap();
+        assert_eq!(details["hedgeDelayMs"], 50);
+        assert_eq!(details["endpoints"][0]["name"], "primary");
+        assert_eq!(details["endpoints"][0]["preferred"], true);
+        assert_eq!(details["endpoints"][1]["name"], "secondary");
+
+        // One failing endpoint is covered by the other
+        *primary.failing.lock().unwrap() = true;
+        balance(&provider).await.unwrap();
+        probe.check().await.unwrap();
+
+        *secondary.failing.lock().unwrap() = true;
+        balance(&provider).await.unwrap_err();
+        let reason = probe.check().await.unwrap_err();
+        assert!(
+            reason.contains("every node endpoint is failing"),
+            "{}",
+            reason
+        );
+    }
+}
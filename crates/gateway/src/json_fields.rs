@@ -0,0 +1,240 @@
+use alloy_primitives::{Address, Bytes, U256};
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::debug;
+
+use crate::error::{GatewayError, GatewayResult};
+
+/// Characters of an invalid value repeated in the error message
+const MAX_SHOWN_CHARS: usize = 24;
+
+const EXPECTED_QUANTITY: &str = "expected a 0x-prefixed hex or decimal integer";
+const EXPECTED_BYTES: &str = "expected 0x-prefixed hex bytes";
+const EXPECTED_ADDRESS: &str = "expected a 0x-prefixed 20-byte hex address";
+
+/// How UserOperation JSON fields are parsed (`[gateway.validation] field_parsing`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldParsing {
+    /// Every present field must parse and required fields must be present
+    #[default]
+    Strict,
+    /// Previous behavior, kept for one release: unparseable or missing gas
+    /// fields and bytes fall back to defaults, and strings without a 0x prefix
+    /// are read as hex
+    Lenient,
+}
+
+/// Typed fields of a JSON-RPC object, with errors naming the field, the
+/// offending value and the expected format
+///
+/// Quantities are 0x-prefixed hex (either case of prefix) or decimal, as a
+/// string or JSON number. Bytes and addresses are hex with or without the
+/// 0x prefix.
+#[derive(Debug, Clone)]
+pub struct JsonFields<'a> {
+    object: &'a Value,
+    /// Prefix of field names in errors, such as `eip7702Auth.`
+    path: String,
+    mode: FieldParsing,
+}
+
+impl<'a> JsonFields<'a> {
+    /// Fields of `object`
+    pub fn new(object: &'a Value, mode: FieldParsing) -> Self {
+        Self {
+            object,
+            path: String::new(),
+            mode,
+        }
+    }
+
+    /// Fields of the nested object `field`, `None` when absent or null
+    pub fn nested(&self, field: &str) -> GatewayResult<Option<JsonFields<'a>>> {
+        match self.present(field) {
+            None => Ok(None),
+            Some(value) if value.is_object() => Ok(Some(JsonFields {
+                object: value,
+                path: format!("{}{}.", self.path, field),
+                mode: self.mode,
+            })),
+            Some(value) => Err(self.invalid(field, value, "expected an object")),
+        }
+    }
+
+    /// `result`, or `default` when parsing is lenient and `result` failed
+    pub fn or_lenient<T>(&self, result: GatewayResult<T>, default: T) -> GatewayResult<T> {
+        match (result, self.mode) {
+            (Err(e), FieldParsing::Lenient) => {
+                debug!(
+                    "Lenient field parsing replaced a field with its default: {}",
+                    e
+                );
+                Ok(default)
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Required integer field
+    pub fn quantity(&self, field: &str) -> GatewayResult<U256> {
+        let value = self.required(field)?;
+        self.parse_quantity(field, value)
+    }
+
+    /// Optional integer field, `None` when absent or null
+    pub fn optional_quantity(&self, field: &str) -> GatewayResult<Option<U256>> {
+        self.present(field)
+            .map(|value| self.parse_quantity(field, value))
+            .transpose()
+    }
+
+    /// Required integer field that must fit in `T`
+    pub fn uint<T: TryFrom<U256>>(&self, field: &str) -> GatewayResult<T> {
+        let value = self.required(field)?;
+        self.parse_uint(field, value)
+    }
+
+    /// Optional integer field that must fit in `T`, `None` when absent or null
+    pub fn optional_uint<T: TryFrom<U256>>(&self, field: &str) -> GatewayResult<Option<T>> {
+        self.present(field)
+            .map(|value| self.parse_uint(field, value))
+            .transpose()
+    }
+
+    /// Bytes field, empty when absent or null
+    pub fn bytes(&self, field: &str) -> GatewayResult<Bytes> {
+        let Some(value) = self.present(field) else {
+            return Ok(Bytes::new());
+        };
+        let Value::String(s) = value else {
+            return Err(self.invalid(field, value, EXPECTED_BYTES));
+        };
+        let digits = self.strip_prefix(s);
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(self.invalid(field, value, EXPECTED_BYTES));
+        }
+        if digits.len() % 2 == 1 {
+            return Err(self.invalid(field, value, "odd number of hex digits"));
+        }
+        hex::decode(digits)
+            .map(Bytes::from)
+            .map_err(|_| self.invalid(field, value, EXPECTED_BYTES))
+    }
+
+    /// Required address field
+    pub fn address(&self, field: &str) -> GatewayResult<Address> {
+        let value = self.required(field)?;
+        self.parse_address(field, value)
+    }
+
+    /// Optional address field, `None` when absent, null, `""` or `"0x"`
+    pub fn optional_address(&self, field: &str) -> GatewayResult<Option<Address>> {
+        match self.present(field) {
+            None => Ok(None),
+            Some(Value::String(s)) if s.is_empty() || s == "0x" => Ok(None),
+            Some(value) => self.parse_address(field, value).map(Some),
+        }
+    }
+
+    fn present(&self, field: &str) -> Option<&'a Value> {
+        self.object.get(field).filter(|value| !value.is_null())
+    }
+
+    fn required(&self, field: &str) -> GatewayResult<&'a Value> {
+        self.present(field).ok_or_else(|| {
+            GatewayError::InvalidRequest(format!("Missing {}{} field", self.path, field))
+        })
+    }
+
+    fn strip_prefix<'s>(&self, s: &'s str) -> &'s str {
+        s.strip_prefix("0x")
+            .or_else(|| s.strip_prefix("0X"))
+            .unwrap_or(s)
+    }
+
+    fn parse_quantity(&self, field: &str, value: &Value) -> GatewayResult<U256> {
+        let s = match value {
+            Value::Number(n) => {
+                return n
+                    .as_u64()
+                    .map(U256::from)
+                    .ok_or_else(|| self.invalid(field, value, EXPECTED_QUANTITY));
+            }
+            Value::String(s) => s.as_str(),
+            _ => return Err(self.invalid(field, value, EXPECTED_QUANTITY)),
+        };
+
+        let hex = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            Some(digits) => Some(digits),
+            // Unprefixed strings were read as hex before strict parsing
+            None if self.mode == FieldParsing::Lenient => Some(s),
+            None => None,
+        };
+        let (digits, radix) = match hex {
+            Some(digits) => (digits, 16),
+            None => (s, 10),
+        };
+        let valid_digit = |b: u8| {
+            if radix == 16 {
+                b.is_ascii_hexdigit()
+            } else {
+                b.is_ascii_digit()
+            }
+        };
+        if digits.is_empty() || !digits.bytes().all(valid_digit) {
+            return Err(self.invalid(field, value, EXPECTED_QUANTITY));
+        }
+        U256::from_str_radix(digits, radix)
+            .map_err(|_| self.invalid(field, value, "value does not fit in 256 bits"))
+    }
+
+    fn parse_uint<T: TryFrom<U256>>(&self, field: &str, value: &Value) -> GatewayResult<T> {
+        let quantity = self.parse_quantity(field, value)?;
+        T::try_from(quantity).map_err(|_| {
+            let bits = std::mem::size_of::<T>() * 8;
+            self.invalid(
+                field,
+                value,
+                &format!("value does not fit in {} bits", bits),
+            )
+        })
+    }
+
+    fn parse_address(&self, field: &str, value: &Value) -> GatewayResult<Address> {
+        let Value::String(s) = value else {
+            return Err(self.invalid(field, value, EXPECTED_ADDRESS));
+        };
+        let digits = self.strip_prefix(s);
+        if digits.len() != 40 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(self.invalid(field, value, EXPECTED_ADDRESS));
+        }
+        digits
+            .parse()
+            .map_err(|_| self.invalid(field, value, EXPECTED_ADDRESS))
+    }
+
+    fn invalid(&self, field: &str, value: &Value, problem: &str) -> GatewayError {
+        GatewayError::InvalidRequest(format!(
+            "Invalid {}{} {}: {}",
+            self.path,
+            field,
+            shown(value),
+            problem
+        ))
+    }
+}
+
+/// `value` as JSON, cut to [`MAX_SHOWN_CHARS`] characters
+fn shown(value: &Value) -> String {
+    let (text, quote) = match value {
+        Value::String(s) => (s.clone(), "\""),
+        _ => (value.to_string(), ""),
+    };
+    if text.chars().count() > MAX_SHOWN_CHARS {
+        let cut: String = text.chars().take(MAX_SHOWN_CHARS).collect();
+        format!("{}{}...{}", quote, cut, quote)
+    } else {
+        format!("{}{}{}", quote, text, quote)
+    }
+}
@@ -9,35 +9,201 @@
 
 //! SuperRelay Gateway - API Gateway with enterprise features
 
+use std::{collections::HashMap, time::Duration};
+
+/// Separate listener for admin-scoped routes
+pub mod admin_listener;
+/// Operator alerts delivered to webhooks with per-kind cooldowns
+pub mod alerts;
 /// Complete API documentation with OpenAPI/Swagger support
 pub mod api_docs;
 /// Authorization and eligibility checking for UserOperations
 pub mod authorization;
+/// Batch sponsorship of pm_sponsorUserOperationBatch
+pub mod batch_sponsorship;
+/// handleOps simulation of pooled or raw operations, assembled like the builder
+pub mod bundle_simulation;
+/// Bundle attempts and per-operation inclusion tracked from builder events
+pub mod bundle_tracker;
+/// In-process caching of idempotent read responses
+pub mod cache;
+/// Per-chain routing for multi-chain deployments
+pub mod chains;
+/// Negotiated response compression and request decompression
+pub mod compression;
+/// Storage conditions of superRelay_sendUserOperationConditional
+pub mod conditional;
+/// Connection and expensive request limits with backpressure
+pub mod connection_limit;
+/// Per-request deadlines enforced across the sponsorship stages
+pub mod deadline;
 /// End-to-end transaction validation
 pub mod e2e_validator;
+/// Send, estimate and sponsor capability of the configured EntryPoints
+pub mod entry_point_status;
 /// Error types and result helpers
 pub mod error;
+/// Stable keys and descriptions of every JSON-RPC error, for client-side localization
+pub mod error_catalog;
+/// Gas estimation backend of eth_estimateUserOperationGas
+pub mod estimation;
+/// Live stream of request, pipeline, pool, bundle and alert events
+pub mod events;
+/// Fee suggestion tiers computed once per block
+pub mod gas_price;
 /// Main gateway implementation
 pub mod gateway;
 /// Health check and system monitoring
 pub mod health;
+/// Node reads hedged across a primary and a secondary endpoint
+pub mod hedged_provider;
+/// Typed UserOperation JSON fields with descriptive parse errors
+pub mod json_fields;
+/// Metadata and dispatch target of every served JSON-RPC method
+pub mod method_registry;
+/// Prometheus recorder and /metrics exporter shared by the whole process
+pub mod metrics;
 /// HTTP middleware for enterprise features
 pub mod middleware;
+/// Next usable nonce lookup for senders with pending operations
+pub mod nonce;
+/// Pool permissions of submitted operations by API key trust level
+pub mod op_permissions;
+/// Configurable ordering of the sponsorship checks
+pub mod pipeline;
+/// NDJSON export and summary of the pool contents
+pub mod pool_export;
+/// Pending pool operations written on shutdown and replayed on startup
+pub mod pool_snapshot;
+/// Pool task supervision and restart with backoff
+pub mod pool_supervisor;
+/// Dependency probes and cache warm-up before the public listener binds
+pub mod preflight;
+/// On-chain UserOperation receipt lookup
+pub mod receipt;
+/// Pool reputation of paymasters, factories and aggregators
+pub mod reputation;
+/// Correlation ids propagated through logs, errors and responses
+pub mod request_id;
+/// Sampled, redacted JSON-RPC request logging
+pub mod request_log;
 /// Request routing logic
 pub mod router;
+/// OpenAPI description of the JSON-RPC methods, served at /rpc-schema
+pub mod rpc_schema;
 /// Security analysis and threat detection for UserOperations
 pub mod security;
+/// Graceful shutdown and connection draining
+pub mod shutdown;
+/// UserOperation signature classification: ECDSA, ERC-1271 and WebAuthn
+pub mod signature;
+/// Fixtures shared by the unit tests
+#[cfg(test)]
+mod test_utils;
+/// Threat intelligence feeds with background refresh
+pub mod threat_feed;
+/// CSV export of per API key sponsorship usage
+pub mod usage_export;
 /// Data integrity validation for UserOperations
 pub mod validation;
+/// EntryPoint version selection from UserOperation shape and EntryPoint
+pub mod version_selector;
 
-pub use authorization::{AuthorizationChecker, AuthorizationConfig, AuthorizationResult};
+pub use admin_listener::AdminListenerConfig;
+pub use alerts::{
+    Alert, AlertBus, AlertConfig, AlertDelivery, AlertEventConfig, AlertKind, AlertSeverity,
+    AlertSink, WebhookConfig, WebhookSink,
+};
+pub use authorization::{
+    AccessControlConfig, AccessList, AuthorizationChecker, AuthorizationConfig,
+    AuthorizationResult, SenderAccessControl,
+};
+pub use batch_sponsorship::{BatchSponsorshipConfig, BatchSponsorshipMode};
+pub use bundle_simulation::{
+    BundleGasCaps, BundleSimulation, BundleSimulator, BundleSimulators, EntryPointBundleSimulator,
+    OpSimulation, OpSimulationStatus, MAX_BUNDLE_BLOCK_GAS_LIMIT_RATIO,
+    TARGET_BUNDLE_BLOCK_GAS_LIMIT_RATIO,
+};
+pub use bundle_tracker::{
+    BundleAttempt, BundleStats, BundleStatus, BundleTracker, BundleTrackerConfig, OpInclusion,
+    OpInclusionStatus,
+};
+pub use cache::{
+    BlockNumberSource, CacheCounters, EvmBlockNumberSource, ResponseCache, ResponseCacheConfig,
+    NO_CACHE_HEADER,
+};
+pub use chains::{ChainRegistry, ChainRoute, CHAIN_ID_HEADER};
+pub use compression::{negotiate, CompressionConfig, ContentCoding};
+pub use conditional::ConditionalSendConfig;
+pub use connection_limit::{ConnectionLimitConfig, ConnectionLimiter};
+pub use deadline::Deadline;
 pub use e2e_validator::{quick_e2e_health_check, E2EValidationResult, E2EValidator};
+pub use entry_point_status::{
+    ContractCodeReader, EntryPointCheckConfig, EntryPointIssue, EntryPointReport, EntryPointStatus,
+    EntryPointStatusBoard, EvmCodeReader,
+};
 pub use error::{GatewayError, GatewayResult};
+pub use error_catalog::{error_catalog, ErrorCatalogEntry, GATEWAY_ERROR_CATALOG};
+pub use estimation::{GasEstimator, SimGasEstimator};
+pub use events::{
+    EventBus, EventFilter, EventStreamConfig, EventSubscription, GatewayEvent, RequestOutcome,
+    StreamedEvent,
+};
+pub use gas_price::{
+    BlockHeadSource, EvmBlockHeadSource, GasPriceConfig, GasPriceOracle, GasPriceQuote,
+    GasPriceTier,
+};
 pub use gateway::PaymasterGateway;
-pub use health::{HealthChecker, HealthStatus, SystemStatus};
+pub use health::{
+    BuilderProbe, ChainIdProbe, ClockSkewProbe, DepositProbe, DepositReader, EntryPointCodeProbe,
+    HealthChecker, HealthConfig, HealthProbe, HealthStatus, NodeProbe, PolicyProbe, PoolProbe,
+    ProbeReport, ReputationProbe, SignerProbe, SystemStatus, ThreatIntelProbe, UpstreamProbe,
+};
+pub use hedged_provider::{
+    HedgedEvmProvider, HedgedReadConfig, UpstreamEndpointStatus, UpstreamStatus,
+};
+pub use json_fields::{FieldParsing, JsonFields};
+pub use method_registry::{MethodHandler, MethodSpec, TimeoutClass, METHODS};
+pub use metrics::{install_prometheus_recorder, serve_metrics, MetricsConfig};
+pub use metrics_exporter_prometheus::PrometheusHandle;
+pub use middleware::{AdminAuthConfig, ApiKeyConfig, ApiKeyEntry, ApiKeyScope, AuthMiddleware};
+pub use nonce::{EvmNonceReader, NonceReader};
+pub use op_permissions::{
+    BundlerSponsorshipConfig, OpPermissionsConfig, RequestedBundlerSponsorship,
+    RequestedPermissions, RequestedPermissionsLimits, TrustLevelPermissions,
+};
+pub use pipeline::{ModulePipeline, PipelineConfig, PipelineStats, SecurityModule};
+pub use pool_export::{PoolExportEntry, PoolExportQuery};
+pub use pool_snapshot::{PoolSnapshotConfig, PoolSnapshotter, ReplayReport};
+pub use pool_supervisor::{
+    LaunchedPool, PoolLauncher, PoolState, PoolStatus, PoolSupervisor, PoolSupervisorConfig,
+    SupervisedPool,
+};
+pub use preflight::{PreflightConfig, PreflightReport};
+pub use receipt::{EvmReceiptProvider, ReceiptEventSource, UserOperationReceiptProvider};
+pub use reputation::EntityReputation;
+pub use request_id::REQUEST_ID_HEADER;
+pub use request_log::{RequestLogConfig, RequestLogger};
 pub use router::GatewayRouter;
+pub use rpc_schema::{rpc_methods, rpc_schema, RpcMethod, RpcParam, RPC_SCHEMA_PATH};
+pub use rundler_paymaster_relay::CorsConfig;
 pub use security::{SecurityChecker, SecurityConfig, SecurityResult};
+pub use shutdown::{
+    serve_with_connection_limits, serve_with_graceful_shutdown, ShutdownController,
+};
+pub use signature::{
+    ContractSignatureReader, EvmContractSignatureReader, SignatureComponents, SignatureFormat,
+    SignatureValidationResult, SignatureValidator,
+};
+pub use threat_feed::{
+    FileThreatFeed, HttpThreatFeed, ThreatFeed, ThreatFeedConfig, ThreatIntelStore,
+    ThreatIntelligence,
+};
+pub use usage_export::UsageExportQuery;
 pub use validation::{DataIntegrityChecker, DataIntegrityResult, ValidationConfig};
+pub use version_selector::{
+    detect_entry_point_version, DetectionMethod, VersionSelection, VersionSelector,
+};
 
 /// Gateway configuration
 #[derive(Debug, Clone)]
@@ -46,14 +212,38 @@ pub struct GatewayConfig {
     pub host: String,
     /// Port to bind to
     pub port: u16,
-    /// Enable request logging
-    pub enable_logging: bool,
-    /// Enable CORS
-    pub enable_cors: bool,
-    /// Max concurrent connections
-    pub max_connections: u32,
-    /// Request timeout in seconds
+    /// Request logging, sampling and redaction
+    pub logging: RequestLogConfig,
+    /// CORS policy
+    pub cors: CorsConfig,
+    /// Response compression and request decompression
+    pub compression: CompressionConfig,
+    /// Connection and expensive request limits
+    pub connection_limits: ConnectionLimitConfig,
+    /// Request timeout in seconds, 0 for none
     pub request_timeout: u64,
+    /// Per-method request timeouts in seconds, overriding `request_timeout`
+    pub method_timeouts: HashMap<String, u64>,
+    /// Seconds to let in-flight requests finish on shutdown, defaults to `request_timeout`
+    pub drain_timeout: Option<u64>,
+    /// API key authentication settings
+    pub api_keys: ApiKeyConfig,
+    /// Readiness probe settings
+    pub health: HealthConfig,
+    /// Signed admin request settings
+    pub admin_auth: AdminAuthConfig,
+    /// Request body, batch and UserOperation field limits
+    pub validation: ValidationConfig,
+    /// Prometheus exporter settings
+    pub metrics: MetricsConfig,
+    /// Live event stream of GET /admin/events
+    pub events: EventStreamConfig,
+    /// Separate listener for admin-scoped routes
+    pub admin: AdminListenerConfig,
+    /// Startup checks before the public listener binds
+    pub preflight: PreflightConfig,
+    /// Capability checks deciding which EntryPoints are advertised
+    pub entry_point_checks: EntryPointCheckConfig,
 }
 
 impl Default for GatewayConfig {
@@ -61,10 +251,34 @@ impl Default for GatewayConfig {
         Self {
             host: "127.0.0.1".to_string(),
             port: 3000,
-            enable_logging: true,
-            enable_cors: true,
-            max_connections: 1000,
+            logging: RequestLogConfig::default(),
+            cors: CorsConfig::default(),
+            compression: CompressionConfig::default(),
+            connection_limits: ConnectionLimitConfig::default(),
             request_timeout: 30,
+            method_timeouts: HashMap::new(),
+            drain_timeout: None,
+            api_keys: ApiKeyConfig::default(),
+            health: HealthConfig::default(),
+            admin_auth: AdminAuthConfig::default(),
+            validation: ValidationConfig::default(),
+            metrics: MetricsConfig::default(),
+            events: EventStreamConfig::default(),
+            admin: AdminListenerConfig::default(),
+            preflight: PreflightConfig::default(),
+            entry_point_checks: EntryPointCheckConfig::default(),
         }
     }
 }
+
+impl GatewayConfig {
+    /// Deadline of a `method` call starting now
+    pub fn deadline_for(&self, method: &str) -> Deadline {
+        let seconds = self
+            .method_timeouts
+            .get(method)
+            .copied()
+            .unwrap_or(self.request_timeout);
+        Deadline::new((seconds > 0).then(|| Duration::from_secs(seconds)))
+    }
+}
@@ -0,0 +1,256 @@
+use crate::middleware::ApiKeyScope;
+
+/// Component answering a method once the gateway has authorized it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodHandler {
+    /// Sender access lists of the gateway, `SenderAccessControl::handle_admin_request`
+    AccessList,
+    /// Synthetic alert delivered through the alert bus
+    TestAlert,
+    /// Paymaster relay service, `GatewayRouter::route_to_paymaster`
+    Paymaster,
+    /// Pool, builder and node reads, `GatewayRouter::route_to_rundler`
+    Rundler,
+    /// SuperRelay extensions, `GatewayRouter::route_to_super_relay`
+    SuperRelay,
+}
+
+/// Default timeout and concurrency treatment of a method
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutClass {
+    /// Bounded by `request_timeout`
+    Standard,
+    /// Simulates against the node; bounded by `request_timeout` and, by
+    /// default, by `max_expensive_requests`
+    Expensive,
+}
+
+/// Metadata of a JSON-RPC method served by the gateway
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MethodSpec {
+    /// Method name, e.g. `pm_sponsorUserOperation`
+    pub name: &'static str,
+    /// Namespace the method is documented and tagged under
+    pub namespace: &'static str,
+    /// API key scope the method requires
+    pub scope: ApiKeyScope,
+    /// Whether the method leaves pool, paymaster and gateway state unchanged
+    pub read_only: bool,
+    /// Whether responses may come from the response cache (bypassed with `X-No-Cache`)
+    pub cacheable: bool,
+    /// Default timeout class
+    pub timeout: TimeoutClass,
+    /// Component the method is dispatched to
+    pub handler: MethodHandler,
+}
+
+const fn method(
+    name: &'static str,
+    namespace: &'static str,
+    scope: ApiKeyScope,
+    handler: MethodHandler,
+) -> MethodSpec {
+    MethodSpec {
+        name,
+        namespace,
+        scope,
+        read_only: true,
+        cacheable: false,
+        timeout: TimeoutClass::Standard,
+        handler,
+    }
+}
+
+const fn admin(name: &'static str) -> MethodSpec {
+    method(name, "admin", ApiKeyScope::Admin, MethodHandler::AccessList)
+}
+
+const fn debug_bundler(name: &'static str) -> MethodSpec {
+    method(
+        name,
+        "debug_bundler",
+        ApiKeyScope::Admin,
+        MethodHandler::Rundler,
+    )
+}
+
+const fn eth(name: &'static str) -> MethodSpec {
+    method(name, "eth", ApiKeyScope::Read, MethodHandler::Rundler)
+}
+
+const fn pm(name: &'static str, scope: ApiKeyScope) -> MethodSpec {
+    method(name, "pm", scope, MethodHandler::Paymaster)
+}
+
+const fn rundler(name: &'static str) -> MethodSpec {
+    method(name, "rundler", ApiKeyScope::Read, MethodHandler::Rundler)
+}
+
+const fn super_relay(name: &'static str) -> MethodSpec {
+    method(
+        name,
+        "superRelay",
+        ApiKeyScope::Read,
+        MethodHandler::SuperRelay,
+    )
+}
+
+impl MethodSpec {
+    /// `read` or `write`, as logged and used in metric labels
+    pub fn access(&self) -> &'static str {
+        if self.read_only {
+            "read"
+        } else {
+            "write"
+        }
+    }
+
+    const fn mutating(mut self) -> Self {
+        self.read_only = false;
+        self
+    }
+
+    const fn cacheable(mut self) -> Self {
+        self.cacheable = true;
+        self
+    }
+
+    const fn expensive(mut self) -> Self {
+        self.timeout = TimeoutClass::Expensive;
+        self
+    }
+
+    const fn scope(mut self, scope: ApiKeyScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    const fn handler(mut self, handler: MethodHandler) -> Self {
+        self.handler = handler;
+        self
+    }
+}
+
+/// Every JSON-RPC method the gateway serves
+///
+/// Requests are dispatched through this table only, so a method without an
+/// entry here is answered with `Method not found` even if a router arm
+/// matches it.
+pub const METHODS: &[MethodSpec] = &[
+    // Gateway administration
+    admin("admin_getAccessLists"),
+    admin("admin_addAccessListEntry").mutating(),
+    admin("admin_removeAccessListEntry").mutating(),
+    admin("admin_testAlert")
+        .mutating()
+        .handler(MethodHandler::TestAlert),
+    // Bundler spec test methods, answered with debug_api enabled
+    debug_bundler("debug_bundler_clearState").mutating(),
+    debug_bundler("debug_bundler_dumpMempool"),
+    debug_bundler("debug_bundler_sendBundleNow").mutating(),
+    debug_bundler("debug_bundler_setBundlingMode").mutating(),
+    debug_bundler("debug_bundler_setReputation").mutating(),
+    // ERC-4337 bundler methods
+    eth("eth_chainId").cacheable(),
+    eth("eth_supportedEntryPoints").cacheable(),
+    eth("eth_estimateUserOperationGas").cacheable().expensive(),
+    eth("eth_sendUserOperation")
+        .scope(ApiKeyScope::Send)
+        .mutating()
+        .expensive(),
+    eth("eth_getUserOperationByHash"),
+    eth("eth_getUserOperationReceipt"),
+    eth("eth_getUserOperationNonce"),
+    // Paymaster sponsorship
+    pm("pm_sponsorUserOperation", ApiKeyScope::Sponsor).mutating(),
+    pm("pm_sponsorUserOperationERC20", ApiKeyScope::Sponsor).mutating(),
+    pm("pm_sponsorUserOperationBatch", ApiKeyScope::Sponsor).mutating(),
+    pm("pm_simulateSponsorship", ApiKeyScope::Sponsor).expensive(),
+    pm("pm_getSupportedEntryPoints", ApiKeyScope::Read),
+    pm("pm_getSponsorshipRecord", ApiKeyScope::Read),
+    pm("pm_getAttestationKey", ApiKeyScope::Read),
+    // Paymaster deposit and signer
+    pm("pm_getDepositInfo", ApiKeyScope::Read),
+    pm("pm_getBalanceStatus", ApiKeyScope::Read),
+    pm("pm_getOutboundTx", ApiKeyScope::Read),
+    pm("pm_getSignerStatus", ApiKeyScope::Read),
+    pm("pm_depositTo", ApiKeyScope::Admin).mutating(),
+    pm("pm_withdrawTo", ApiKeyScope::Admin).mutating(),
+    pm("pm_rotatePaymasterKey", ApiKeyScope::Admin).mutating(),
+    pm("pm_drainSigner", ApiKeyScope::Admin).mutating(),
+    // Paymaster administration
+    pm("pm_invalidateSbtCache", ApiKeyScope::Admin).mutating(),
+    pm("pm_getUsageReport", ApiKeyScope::Admin),
+    pm("pm_listSponsorships", ApiKeyScope::Admin),
+    pm("pm_loadCandidatePolicy", ApiKeyScope::Admin).mutating(),
+    pm("pm_getPolicyShadowReport", ApiKeyScope::Admin),
+    pm("pm_promoteCandidatePolicy", ApiKeyScope::Admin).mutating(),
+    // Bundle tracking
+    rundler("rundler_getBundleStats"),
+    rundler("rundler_getBundleByHash"),
+    rundler("rundler_sendBundleNow")
+        .scope(ApiKeyScope::Admin)
+        .mutating(),
+    // Entity reputation
+    rundler("rundler_getEntityReputation"),
+    rundler("rundler_listThrottledEntities"),
+    rundler("rundler_setReputation")
+        .scope(ApiKeyScope::Admin)
+        .mutating(),
+    // SuperRelay extensions
+    super_relay("superRelay_getVerificationProof"),
+    super_relay("superRelay_getPipelineStats"),
+    super_relay("superRelay_getOpInclusionStatus"),
+    super_relay("superRelay_poolSummary"),
+    super_relay("superRelay_getUserOperationGasPrice"),
+    super_relay("superRelay_getErrorCatalog"),
+    super_relay("superRelay_getEntryPointStatus"),
+    super_relay("superRelay_getDummyPaymasterData"),
+    super_relay("superRelay_sendUserOperationConditional")
+        .scope(ApiKeyScope::Send)
+        .mutating()
+        .expensive(),
+    super_relay("superRelay_simulateBundle").scope(ApiKeyScope::Admin),
+];
+
+/// Metadata of `method`, `None` for a method the gateway does not serve
+pub fn lookup(method: &str) -> Option<&'static MethodSpec> {
+    METHODS.iter().find(|spec| spec.name == method)
+}
+
+/// Methods of the `Expensive` timeout class, the default `expensive_methods`
+pub fn expensive_methods() -> impl Iterator<Item = &'static str> {
+    METHODS
+        .iter()
+        .filter(|spec| spec.timeout == TimeoutClass::Expensive)
+        .map(|spec| spec.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    #[test]
+    fn test_names_unique_and_namespaced() {
+        let mut names = BTreeSet::new();
+        for spec in METHODS {
+            assert!(names.insert(spec.name), "{} registered twice", spec.name);
+            assert!(
+                spec.name.starts_with(&format!("{}_", spec.namespace)),
+                "{} is not in namespace {}",
+                spec.name,
+                spec.namespace
+            );
+        }
+    }
+
+    #[test]
+    fn test_mutating_methods_need_more_than_read() {
+        for spec in METHODS.iter().filter(|spec| !spec.read_only) {
+            assert_ne!(spec.scope, ApiKeyScope::Read, "{}", spec.name);
+            assert!(!spec.cacheable, "{}", spec.name);
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::{extract::State, routing::get, Router};
+use metrics::{counter, histogram};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::net::TcpListener;
+use tracing::info;
+
+use crate::{
+    error::{GatewayError, GatewayResult},
+    method_registry,
+    shutdown::{serve_with_graceful_shutdown, ShutdownController},
+};
+
+/// Prometheus exporter settings (`[gateway.metrics]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// Install the process-wide Prometheus recorder
+    pub enabled: bool,
+    /// Serve GET /metrics on the gateway port
+    pub serve_on_gateway: bool,
+    /// Also serve GET /metrics on this dedicated address, unset to disable
+    pub listen_address: Option<String>,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            serve_on_gateway: true,
+            listen_address: Some("0.0.0.0:8080".to_string()),
+        }
+    }
+}
+
+/// Handle of the recorder installed by [`install_prometheus_recorder`]
+static PROMETHEUS: Mutex<Option<PrometheusHandle>> = Mutex::new(None);
+
+/// Install the process-wide Prometheus recorder, or return the one installed
+///
+/// Every `metrics` macro in the process records into it, so gateway, paymaster
+/// relay and rundler component metrics all render from the returned handle.
+/// Only one global recorder can exist; a second one would stay silently inert,
+/// so all exporters share this handle.
+pub fn install_prometheus_recorder() -> GatewayResult<PrometheusHandle> {
+    let mut installed = PROMETHEUS.lock().unwrap();
+    if let Some(handle) = installed.as_ref() {
+        return Ok(handle.clone());
+    }
+
+    let handle = PrometheusBuilder::new().install_recorder().map_err(|e| {
+        GatewayError::ServerError(format!("Failed to install Prometheus recorder: {}", e))
+    })?;
+    info!("📈 Prometheus recorder installed");
+    *installed = Some(handle.clone());
+    Ok(handle)
+}
+
+/// Serve GET /metrics from `handle` on `addr` until `shutdown` triggers
+pub async fn serve_metrics(
+    addr: &str,
+    handle: PrometheusHandle,
+    shutdown: ShutdownController,
+) -> GatewayResult<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| GatewayError::ServerError(format!("Failed to bind to {}: {}", addr, e)))?;
+    info!("📈 Prometheus metrics on http://{}/metrics", addr);
+
+    let app = Router::new()
+        .route("/metrics", get(render))
+        .with_state(handle);
+    serve_with_graceful_shutdown(listener, app, shutdown, Duration::ZERO).await
+}
+
+async fn render(State(handle): State<PrometheusHandle>) -> String {
+    handle.render()
+}
+
+/// Count a JSON-RPC call and its latency by method, access and outcome
+///
+/// Calls to methods the gateway does not serve are counted as `unknown`, so
+/// arbitrary method names cannot add label values. `access` is `read` for
+/// read-only methods and `write` for the ones changing state.
+pub(crate) fn record_request(method: Option<&str>, response: &Value, started: Instant) {
+    let error_code = response
+        .get("error")
+        .and_then(|error| error.get("code"))
+        .and_then(Value::as_i64);
+    let (method, access) = match (method.map(method_registry::lookup), error_code) {
+        (None, _) => ("invalid", "none"),
+        (Some(None), _) | (Some(_), Some(-32601)) => ("unknown", "none"),
+        (Some(Some(spec)), _) => (spec.name, spec.access()),
+    };
+    let status = if response.get("error").is_some() {
+        "error"
+    } else {
+        "success"
+    };
+
+    counter!(
+        "superrelay_gateway_requests_total",
+        "method" => method,
+        "access" => access,
+        "status" => status
+    )
+    .increment(1);
+    histogram!(
+        "superrelay_gateway_request_duration_seconds",
+        "method" => method,
+        "access" => access
+    )
+    .record(started.elapsed().as_secs_f64());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::{GatewayConfig, PaymasterGateway};
+
+    async fn spawn_gateway(config: GatewayConfig) -> SocketAddr {
+        let handle = install_prometheus_recorder().unwrap();
+        let app = PaymasterGateway::new(config, None)
+            .with_prometheus(handle)
+            .app()
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    /// Send a raw HTTP request and return the status line and body
+    async fn send(addr: SocketAddr, request: String) -> (String, String) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.lines().next().unwrap().to_string(), body.to_string())
+    }
+
+    async fn post(addr: SocketAddr, body: &str) {
+        send(
+            addr,
+            format!(
+                "POST / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+        )
+        .await;
+    }
+
+    async fn get_metrics(addr: SocketAddr) -> (String, String) {
+        send(
+            addr,
+            "GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n".to_string(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_gateway_metrics_count_requests() {
+        let addr = spawn_gateway(GatewayConfig::default()).await;
+
+        post(
+            addr,
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId","params":[]}"#,
+        )
+        .await;
+        post(
+            addr,
+            r#"{"jsonrpc":"2.0","id":2,"method":"foo_bar","params":[]}"#,
+        )
+        .await;
+
+        let (status, body) = get_metrics(addr).await;
+        assert!(status.contains("200"), "{}", status);
+        assert!(
+            body.lines().any(
+                |line| line.starts_with("superrelay_gateway_requests_total{")
+                    && line.contains(r#"method="eth_chainId""#)
+                    && line.contains(r#"access="read""#)
+            ),
+            "{}",
+            body
+        );
+        assert!(body.contains("superrelay_gateway_request_duration_seconds"));
+
+        // Unserved method names are not used as label values
+        assert!(body.contains(r#"method="unknown""#), "{}", body);
+        assert!(!body.contains("foo_bar"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn test_dedicated_metrics_listener() {
+        let gateway = spawn_gateway(GatewayConfig {
+            metrics: MetricsConfig {
+                serve_on_gateway: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .await;
+        post(
+            gateway,
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId","params":[]}"#,
+        )
+        .await;
+
+        // Not served on the gateway port when disabled there
+        let (status, _) = get_metrics(gateway).await;
+        assert!(status.contains("404"), "{}", status);
+
+        // The same registry is rendered on the dedicated port
+        let addr = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap()
+        };
+        let shutdown = ShutdownController::new();
+        let handle = install_prometheus_recorder().unwrap();
+        let server_shutdown = shutdown.clone();
+        tokio::spawn(
+            async move { serve_metrics(&addr.to_string(), handle, server_shutdown).await },
+        );
+        let mut body = String::new();
+        for _ in 0..50 {
+            if TcpStream::connect(addr).await.is_ok() {
+                body = get_metrics(addr).await.1;
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert!(body.contains(r#"method="eth_chainId""#), "{}", body);
+        shutdown.trigger();
+    }
+}
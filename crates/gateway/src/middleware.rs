@@ -1,12 +1,23 @@
 use std::{
     collections::HashMap,
-    sync::Arc,
-    time::{Duration, Instant},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
+use alloy_primitives::{keccak256, Address};
+use axum::http::{header::AUTHORIZATION, HeaderMap};
+use ethers::types::Signature;
+use rundler_paymaster_relay::ClockGuard;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use tokio::sync::RwLock;
+use tracing::warn;
 
-use crate::error::{GatewayError, GatewayResult};
+use crate::{
+    error::{GatewayError, GatewayResult},
+    method_registry,
+};
 
 /// Rate limiting middleware
 #[derive(Clone)]
@@ -55,28 +66,388 @@ impl RateLimitMiddleware {
     }
 }
 
-/// Authentication middleware (placeholder)
-#[derive(Clone)]
+/// Permission scope granted to an API key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyScope {
+    /// pm_* sponsorship methods (except deposit management)
+    Sponsor,
+    /// eth_sendUserOperation and superRelay_sendUserOperationConditional
+    Send,
+    /// Read-only eth_* and rundler_* methods
+    Read,
+    /// admin_*, debug_* and pm_depositTo/pm_withdrawTo/pm_invalidateSbtCache/pm_rotatePaymasterKey/pm_drainSigner/pm_getUsageReport/pm_listSponsorships
+    /// and candidate policy methods
+    Admin,
+    /// Opt-in debugging output on the methods of the other scopes, such as
+    /// the `debugTimings` of sponsorships; no method requires it
+    Debug,
+    /// Pool permissions requested per operation in the third parameter of
+    /// eth_sendUserOperation; no method requires it
+    Permissions,
+}
+
+impl ApiKeyScope {
+    /// Scope a JSON-RPC method requires
+    ///
+    /// Served methods take the scope of their registry entry. Names the
+    /// registry does not know, such as the HTTP export routes, fall back on
+    /// their namespace.
+    pub fn required_for(method: &str) -> Self {
+        if let Some(spec) = method_registry::lookup(method) {
+            return spec.scope;
+        }
+        match method {
+            m if m.starts_with("pm_") => ApiKeyScope::Sponsor,
+            m if m.starts_with("admin_") || m.starts_with("debug_") => ApiKeyScope::Admin,
+            _ => ApiKeyScope::Read,
+        }
+    }
+}
+
+/// A configured API key, stored as a keccak256 hash of the raw key
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApiKeyEntry {
+    /// Key identifier used for attribution in logs
+    pub id: String,
+    /// Hex encoded keccak256 hash of the raw key
+    pub key_hash: String,
+    /// Scopes granted to this key
+    pub scopes: Vec<ApiKeyScope>,
+    /// Trust level in `[gateway.op_permissions.trust_levels]` of operations
+    /// this key submits, default permissions when unset
+    #[serde(default)]
+    pub trust_level: Option<String>,
+}
+
+/// API key authentication configuration
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ApiKeyConfig {
+    /// Require an API key on JSON-RPC requests
+    #[serde(default)]
+    pub enabled: bool,
+    /// Allow requests without a key for read-scoped methods
+    #[serde(default)]
+    pub allow_anonymous_reads: bool,
+    /// Configured keys
+    #[serde(default)]
+    pub keys: Vec<ApiKeyEntry>,
+}
+
+/// Hash a raw API key into the form stored in configuration
+pub fn hash_api_key(raw_key: &str) -> String {
+    hex::encode(keccak256(raw_key.as_bytes()))
+}
+
+/// Header carrying the admin's EIP-191 signature of a signed admin request
+pub const ADMIN_SIGNATURE_HEADER: &str = "x-admin-signature";
+/// Header carrying the unix time a signed admin request was signed at
+pub const ADMIN_TIMESTAMP_HEADER: &str = "x-admin-timestamp";
+/// Header carrying the single-use nonce of a signed admin request
+pub const ADMIN_NONCE_HEADER: &str = "x-admin-nonce";
+
+/// Longest nonce accepted on a signed admin request
+const MAX_ADMIN_NONCE_LEN: usize = 128;
+
+/// Signed admin request configuration (`[gateway.admin_auth]`)
+///
+/// Admin-scoped methods accept requests signed by one of `admin_addresses` as
+/// an alternative to an admin API key. Admin methods always need one of the
+/// two, even with API key authentication disabled.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AdminAuthConfig {
+    /// Accept signed admin requests
+    pub enabled: bool,
+    /// Addresses of the admin ECDSA keys
+    pub admin_addresses: Vec<Address>,
+    /// Seconds a signature stays valid, in either direction of the signing time
+    pub max_age_seconds: u64,
+}
+
+impl Default for AdminAuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            admin_addresses: Vec::new(),
+            max_age_seconds: 300,
+        }
+    }
+}
+
+/// Message an admin signs (EIP-191 personal_sign) for a JSON-RPC request body
+///
+/// The timestamp, nonce and canonical JSON of the body, newline separated.
+pub fn admin_signing_message(body: &Value, timestamp: u64, nonce: &str) -> String {
+    format!("{}\n{}\n{}", timestamp, nonce, canonical_json(body))
+}
+
+/// JSON serialization with object keys sorted and no whitespace
+pub fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let fields: Vec<String> = entries
+                .into_iter()
+                .map(|(key, value)| {
+                    format!("{}:{}", Value::from(key.as_str()), canonical_json(value))
+                })
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+/// Verifies signed admin requests and remembers their nonces
+#[derive(Debug, Default)]
+struct AdminVerifier {
+    config: AdminAuthConfig,
+    /// Nonces seen within the validity window, with their timestamps
+    seen_nonces: Mutex<HashMap<String, u64>>,
+}
+
+impl AdminVerifier {
+    fn is_signed(headers: &HeaderMap) -> bool {
+        headers.contains_key(ADMIN_SIGNATURE_HEADER)
+    }
+
+    /// Verify the signed admin request `body` at `now`, returning the signer
+    fn verify(&self, headers: &HeaderMap, body: &Value, now: u64) -> GatewayResult<Address> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::trim)
+                .ok_or_else(|| {
+                    GatewayError::AuthenticationFailed(format!(
+                        "Signed admin request is missing the {} header",
+                        name
+                    ))
+                })
+        };
+        let signature = Signature::from_str(header(ADMIN_SIGNATURE_HEADER)?).map_err(|e| {
+            GatewayError::AuthenticationFailed(format!("Invalid admin signature: {}", e))
+        })?;
+        let timestamp: u64 = header(ADMIN_TIMESTAMP_HEADER)?.parse().map_err(|_| {
+            GatewayError::AuthenticationFailed("Invalid admin request timestamp".to_string())
+        })?;
+        let nonce = header(ADMIN_NONCE_HEADER)?;
+        if nonce.is_empty() || nonce.len() > MAX_ADMIN_NONCE_LEN {
+            return Err(GatewayError::AuthenticationFailed(format!(
+                "Admin request nonce must be 1 to {} characters",
+                MAX_ADMIN_NONCE_LEN
+            )));
+        }
+
+        let message = admin_signing_message(body, timestamp, nonce);
+        let signer = signature
+            .recover(message)
+            .map(|address| Address::from_slice(address.as_bytes()))
+            .map_err(|e| {
+                GatewayError::AuthenticationFailed(format!("Invalid admin signature: {}", e))
+            })?;
+
+        if !self.config.admin_addresses.contains(&signer) {
+            warn!(
+                "Signed admin request rejected: recovered signer {} is not an admin key",
+                signer
+            );
+            return Err(GatewayError::AuthenticationFailed(
+                "Admin request not signed by an admin key".to_string(),
+            ));
+        }
+        if now.abs_diff(timestamp) > self.config.max_age_seconds {
+            warn!(
+                "Signed admin request from {} rejected: timestamp {} is outside the {}s window",
+                signer, timestamp, self.config.max_age_seconds
+            );
+            return Err(GatewayError::AuthenticationFailed(
+                "Admin request timestamp expired".to_string(),
+            ));
+        }
+
+        // Nonces older than the window can be forgotten, their requests are expired anyway
+        let mut seen_nonces = self.seen_nonces.lock().unwrap();
+        let max_age = self.config.max_age_seconds;
+        seen_nonces.retain(|_, seen| now.abs_diff(*seen) <= max_age);
+        if seen_nonces.contains_key(nonce) {
+            warn!(
+                "Signed admin request from {} rejected: nonce {:?} was already used",
+                signer, nonce
+            );
+            return Err(GatewayError::AuthenticationFailed(
+                "Admin request nonce already used".to_string(),
+            ));
+        }
+        seen_nonces.insert(nonce.to_string(), timestamp);
+
+        Ok(signer)
+    }
+}
+
+/// API key authentication middleware
+#[derive(Clone, Default)]
 pub struct AuthMiddleware {
-    // TODO: Add authentication fields
+    config: ApiKeyConfig,
+    keys_by_hash: Arc<HashMap<String, ApiKeyEntry>>,
+    admin: Arc<AdminVerifier>,
+    clock_guard: Option<Arc<ClockGuard>>,
 }
 
 impl AuthMiddleware {
-    /// Create a new auth middleware
+    /// Create a new auth middleware with authentication disabled
     pub fn new() -> Self {
-        Self {}
+        Self::default()
     }
 
-    /// Authenticate request
-    pub async fn authenticate(&self, _token: Option<&str>) -> GatewayResult<()> {
-        // TODO: Implement authentication logic
-        Ok(())
+    /// Create an auth middleware from API key configuration
+    pub fn with_config(config: ApiKeyConfig) -> Self {
+        let keys_by_hash = config
+            .keys
+            .iter()
+            .map(|entry| {
+                let hash = entry.key_hash.trim_start_matches("0x").to_lowercase();
+                (hash, entry.clone())
+            })
+            .collect();
+
+        Self {
+            config,
+            keys_by_hash: Arc::new(keys_by_hash),
+            admin: Arc::default(),
+            clock_guard: None,
+        }
     }
-}
 
-impl Default for AuthMiddleware {
-    fn default() -> Self {
-        Self::new()
+    /// Accept admin requests signed by the keys of `config`
+    pub fn with_admin_auth(mut self, config: AdminAuthConfig) -> Self {
+        self.admin = Arc::new(AdminVerifier {
+            config,
+            seen_nonces: Mutex::default(),
+        });
+        self
+    }
+
+    /// Check admin request timestamps against the skew-corrected clock of `clock_guard`
+    pub fn with_clock_guard(mut self, clock_guard: Arc<ClockGuard>) -> Self {
+        self.clock_guard = Some(clock_guard);
+        self
+    }
+
+    /// Extract the raw key from `x-api-key` or `Authorization: Bearer` headers
+    pub fn extract_key(headers: &HeaderMap) -> Option<&str> {
+        if let Some(key) = headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
+            return Some(key.trim());
+        }
+
+        headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+    }
+
+    /// Authorize a JSON-RPC method call, returning the resolved key id
+    ///
+    /// Returns `Ok(None)` when authentication is disabled or an anonymous read
+    /// is allowed. Admin-scoped methods are refused while authentication is
+    /// disabled; they always need an admin key or a signed admin request.
+    pub fn authorize(&self, headers: &HeaderMap, method: &str) -> GatewayResult<Option<String>> {
+        let required = ApiKeyScope::required_for(method);
+        if !self.config.enabled {
+            if required == ApiKeyScope::Admin {
+                return Err(GatewayError::AuthenticationFailed(format!(
+                    "{} requires an admin API key or a signed admin request",
+                    method
+                )));
+            }
+            return Ok(None);
+        }
+
+        let Some(raw_key) = Self::extract_key(headers) else {
+            if required == ApiKeyScope::Read && self.config.allow_anonymous_reads {
+                return Ok(None);
+            }
+            return Err(GatewayError::AuthenticationFailed(
+                "Missing API key".to_string(),
+            ));
+        };
+
+        let entry = self
+            .keys_by_hash
+            .get(&hash_api_key(raw_key))
+            .ok_or_else(|| GatewayError::AuthenticationFailed("Invalid API key".to_string()))?;
+
+        if !entry.scopes.contains(&required) {
+            return Err(GatewayError::AuthenticationFailed(format!(
+                "API key '{}' lacks the '{:?}' scope required by {}",
+                entry.id, required, method
+            )));
+        }
+
+        Ok(Some(entry.id.clone()))
+    }
+
+    /// Whether the API key `key_id` holds `scope`
+    ///
+    /// Admin signers and unknown ids hold none.
+    pub fn has_scope(&self, key_id: &str, scope: ApiKeyScope) -> bool {
+        self.config
+            .keys
+            .iter()
+            .any(|entry| entry.id == key_id && entry.scopes.contains(&scope))
+    }
+
+    /// Trust level of the API key `key_id`
+    ///
+    /// Admin signers and unknown ids have none.
+    pub fn trust_level(&self, key_id: &str) -> Option<&str> {
+        self.config
+            .keys
+            .iter()
+            .find(|entry| entry.id == key_id)
+            .and_then(|entry| entry.trust_level.as_deref())
+    }
+
+    /// Authorize a JSON-RPC request with body `body`, returning the resolved
+    /// key id or admin signer
+    ///
+    /// Admin-scoped methods take signed admin requests when enabled; every
+    /// other request goes through [`AuthMiddleware::authorize`].
+    pub fn authorize_request(
+        &self,
+        headers: &HeaderMap,
+        body: &Value,
+        method: &str,
+    ) -> GatewayResult<Option<String>> {
+        if !self.admin.config.enabled || ApiKeyScope::required_for(method) != ApiKeyScope::Admin {
+            return self.authorize(headers, method);
+        }
+
+        if AdminVerifier::is_signed(headers) {
+            let now = match &self.clock_guard {
+                Some(clock_guard) => clock_guard.now(),
+                None => SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default(),
+            };
+            let signer = self.admin.verify(headers, body, now)?;
+            return Ok(Some(format!("admin:{}", signer)));
+        }
+        if !self.config.enabled {
+            return Err(GatewayError::AuthenticationFailed(format!(
+                "{} requires a signed admin request",
+                method
+            )));
+        }
+        self.authorize(headers, method)
     }
 }
 
@@ -108,3 +479,176 @@ impl Default for PolicyMiddleware {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+    use serde_json::json;
+
+    use super::*;
+
+    const SPONSOR_KEY: &str = "sk-sponsor-test";
+    const READ_KEY: &str = "sk-read-test";
+
+    /// Create an auth middleware with one sponsor key and one read-only key
+    fn create_auth(allow_anonymous_reads: bool) -> AuthMiddleware {
+        AuthMiddleware::with_config(ApiKeyConfig {
+            enabled: true,
+            allow_anonymous_reads,
+            keys: vec![
+                ApiKeyEntry {
+                    id: "dapp-sponsor".to_string(),
+                    key_hash: hash_api_key(SPONSOR_KEY),
+                    scopes: vec![ApiKeyScope::Sponsor, ApiKeyScope::Read],
+                    trust_level: None,
+                },
+                ApiKeyEntry {
+                    id: "dapp-read".to_string(),
+                    key_hash: format!("0x{}", hash_api_key(READ_KEY)),
+                    scopes: vec![ApiKeyScope::Read],
+                    trust_level: None,
+                },
+            ],
+        })
+    }
+
+    fn headers_with(name: &'static str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_sponsorship_rejected_without_key() {
+        let auth = create_auth(true);
+
+        let result = auth.authorize(&HeaderMap::new(), "pm_sponsorUserOperation");
+        assert!(matches!(result, Err(GatewayError::AuthenticationFailed(_))));
+    }
+
+    #[test]
+    fn test_sponsorship_accepted_with_sponsor_key() {
+        let auth = create_auth(false);
+
+        let key_id = auth
+            .authorize(
+                &headers_with("x-api-key", SPONSOR_KEY),
+                "pm_sponsorUserOperation",
+            )
+            .unwrap();
+        assert_eq!(key_id.as_deref(), Some("dapp-sponsor"));
+
+        // Bearer tokens resolve to the same key
+        let key_id = auth
+            .authorize(
+                &headers_with("authorization", &format!("Bearer {}", SPONSOR_KEY)),
+                "pm_sponsorUserOperation",
+            )
+            .unwrap();
+        assert_eq!(key_id.as_deref(), Some("dapp-sponsor"));
+    }
+
+    #[test]
+    fn test_sponsorship_rejected_with_read_only_key() {
+        let auth = create_auth(false);
+        let headers = headers_with("x-api-key", READ_KEY);
+
+        let result = auth.authorize(&headers, "pm_sponsorUserOperation");
+        assert!(matches!(result, Err(GatewayError::AuthenticationFailed(_))));
+
+        // The same key may still read
+        let key_id = auth.authorize(&headers, "eth_chainId").unwrap();
+        assert_eq!(key_id.as_deref(), Some("dapp-read"));
+    }
+
+    #[test]
+    fn test_anonymous_reads() {
+        let allowed = create_auth(true);
+        assert_eq!(
+            allowed
+                .authorize(&HeaderMap::new(), "eth_supportedEntryPoints")
+                .unwrap(),
+            None
+        );
+        assert!(allowed
+            .authorize(&HeaderMap::new(), "eth_sendUserOperation")
+            .is_err());
+
+        let denied = create_auth(false);
+        assert!(denied.authorize(&HeaderMap::new(), "eth_chainId").is_err());
+    }
+
+    #[test]
+    fn test_unknown_key_rejected() {
+        let auth = create_auth(true);
+
+        let result = auth.authorize(&headers_with("x-api-key", "sk-unknown"), "eth_chainId");
+        assert!(matches!(result, Err(GatewayError::AuthenticationFailed(_))));
+    }
+
+    #[test]
+    fn test_auth_disabled_allows_everything() {
+        let auth = AuthMiddleware::new();
+
+        assert_eq!(
+            auth.authorize(&HeaderMap::new(), "pm_sponsorUserOperation")
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_auth_disabled_refuses_admin_methods() {
+        let auth = AuthMiddleware::new();
+
+        for method in [
+            "pm_depositTo",
+            "pm_withdrawTo",
+            "pm_rotatePaymasterKey",
+            "pm_drainSigner",
+            "rundler_setReputation",
+            "admin_getAccessLists",
+        ] {
+            assert!(
+                matches!(
+                    auth.authorize(&HeaderMap::new(), method),
+                    Err(GatewayError::AuthenticationFailed(_))
+                ),
+                "{}",
+                method
+            );
+            assert!(matches!(
+                auth.authorize_request(&HeaderMap::new(), &json!({ "method": method }), method),
+                Err(GatewayError::AuthenticationFailed(_))
+            ));
+        }
+        assert!(auth
+            .authorize(&HeaderMap::new(), "eth_sendUserOperation")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_deposit_management_requires_admin_key() {
+        let auth = create_auth(false);
+        let headers = headers_with("x-api-key", SPONSOR_KEY);
+
+        assert!(auth.authorize(&headers, "pm_depositTo").is_err());
+        assert!(auth.authorize(&headers, "pm_withdrawTo").is_err());
+        assert!(auth.authorize(&headers, "pm_invalidateSbtCache").is_err());
+        assert!(auth.authorize(&headers, "pm_rotatePaymasterKey").is_err());
+        assert!(auth.authorize(&headers, "pm_drainSigner").is_err());
+        assert!(auth.authorize(&headers, "pm_loadCandidatePolicy").is_err());
+        assert!(auth
+            .authorize(&headers, "pm_promoteCandidatePolicy")
+            .is_err());
+
+        // Reading the deposit only needs the read scope
+        let key_id = auth
+            .authorize(&headers_with("x-api-key", READ_KEY), "pm_getDepositInfo")
+            .unwrap();
+        assert_eq!(key_id.as_deref(), Some("dapp-read"));
+        assert!(auth
+            .authorize(&headers_with("x-api-key", READ_KEY), "pm_getSignerStatus")
+            .is_ok());
+    }
+}
@@ -0,0 +1,286 @@
+use alloy_primitives::{Address, Bytes, U256};
+use async_trait::async_trait;
+use rundler_provider::{EvmProvider, TransactionBuilder, TransactionRequest};
+use rundler_types::{pool::Pool, UserOperation, UserOperationId};
+
+use crate::error::{GatewayError, GatewayResult};
+
+/// `getNonce(address,uint192)` selector, identical on EntryPoint v0.6 and v0.7
+const GET_NONCE_SELECTOR: [u8; 4] = [0x35, 0x56, 0x7e, 0x1a];
+
+/// Bits of a nonce holding the sequence number, the key takes the upper 192
+const NONCE_SEQUENCE_BITS: usize = 64;
+
+/// Upper bound on pending operations walked for one sender and key
+pub const MAX_PENDING_NONCES: u64 = 256;
+
+/// Source of on-chain EntryPoint nonces
+#[async_trait]
+pub trait NonceReader: Send + Sync {
+    /// EntryPoint `getNonce(sender, key)`: the key in the upper 192 bits and the
+    /// next sequence number in the lower 64
+    async fn get_nonce(
+        &self,
+        entry_point: Address,
+        sender: Address,
+        key: U256,
+    ) -> GatewayResult<U256>;
+}
+
+/// [`NonceReader`] calling `getNonce` through a node provider
+pub struct EvmNonceReader<P> {
+    provider: P,
+}
+
+impl<P: EvmProvider> EvmNonceReader<P> {
+    /// Create a nonce reader using `provider`
+    pub fn new(provider: P) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> NonceReader for EvmNonceReader<P> {
+    async fn get_nonce(
+        &self,
+        entry_point: Address,
+        sender: Address,
+        key: U256,
+    ) -> GatewayResult<U256> {
+        let mut data = GET_NONCE_SELECTOR.to_vec();
+        data.extend_from_slice(&[0u8; 12]);
+        data.extend_from_slice(sender.as_slice());
+        data.extend_from_slice(&key.to_be_bytes::<32>());
+
+        let tx = TransactionRequest::default()
+            .to(entry_point)
+            .with_input(Bytes::from(data));
+        let result = self.provider.call(tx, None, None).await.map_err(|e| {
+            GatewayError::RundlerError(format!("getNonce on {} failed: {}", entry_point, e))
+        })?;
+
+        if result.len() < 32 {
+            return Err(GatewayError::RundlerError(format!(
+                "Invalid getNonce response from {}: {}",
+                entry_point, result
+            )));
+        }
+        Ok(U256::from_be_slice(&result[..32]))
+    }
+}
+
+/// Check that `key` fits the 192-bit nonce key
+pub fn validate_nonce_key(key: U256) -> GatewayResult<()> {
+    if key.bit_len() > 256 - NONCE_SEQUENCE_BITS {
+        return Err(GatewayError::InvalidRequest(format!(
+            "Nonce key {:#x} exceeds 192 bits",
+            key
+        )));
+    }
+    Ok(())
+}
+
+/// Next usable nonce after the operations of `sender` pending in `pool`
+///
+/// Starting from `on_chain_nonce`, consecutive nonces with a pending operation on
+/// `entry_point` are skipped. The first missing one is returned, so a gap left by
+/// a dropped operation is filled before any later nonce.
+pub async fn next_pending_nonce<P: Pool + ?Sized>(
+    pool: &P,
+    entry_point: Address,
+    sender: Address,
+    on_chain_nonce: U256,
+) -> GatewayResult<U256> {
+    let mut nonce = on_chain_nonce;
+    for _ in 0..MAX_PENDING_NONCES {
+        let pending = pool
+            .get_op_by_id(UserOperationId { sender, nonce })
+            .await
+            .map_err(|e| GatewayError::PoolError(format!("Failed to lookup operation: {}", e)))?;
+        match pending {
+            Some(op) if op.entry_point == entry_point && op.uo.nonce() == nonce => {
+                nonce += U256::from(1u64);
+            }
+            _ => return Ok(nonce),
+        }
+    }
+    Err(GatewayError::InvalidRequest(format!(
+        "Sender {:#x} has more than {} pending operations",
+        sender, MAX_PENDING_NONCES
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use alloy_primitives::{address, Address, Bytes, TxKind, B256, U256};
+    use rundler_provider::MockEvmProvider;
+    use rundler_types::{
+        chain::ChainSpec,
+        da::DAGasData,
+        pool::{MockPool, PoolOperation},
+        v0_6, v0_7, EntityInfos, UserOperationPermissions, UserOperationVariant, ValidTimeRange,
+    };
+
+    use super::*;
+
+    const SENDER: Address = address!("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+
+    fn entry_point_v0_6() -> Address {
+        ChainSpec::default().entry_point_address_v0_6
+    }
+
+    fn entry_point_v0_7() -> Address {
+        ChainSpec::default().entry_point_address_v0_7
+    }
+
+    /// Nonce with `key` in the upper 192 bits and `sequence` in the lower 64
+    fn nonce(key: u64, sequence: u64) -> U256 {
+        (U256::from(key) << 64) | U256::from(sequence)
+    }
+
+    fn pool_op(entry_point: Address, nonce: U256) -> PoolOperation {
+        let uo = if entry_point == entry_point_v0_6() {
+            UserOperationVariant::V0_6(
+                v0_6::UserOperationBuilder::new(
+                    &ChainSpec::default(),
+                    v0_6::UserOperationRequiredFields {
+                        sender: SENDER,
+                        nonce,
+                        ..Default::default()
+                    },
+                )
+                .build(),
+            )
+        } else {
+            UserOperationVariant::V0_7(
+                v0_7::UserOperationBuilder::new(
+                    &ChainSpec::default(),
+                    v0_7::UserOperationRequiredFields {
+                        sender: SENDER,
+                        nonce,
+                        call_data: Bytes::new(),
+                        call_gas_limit: 0,
+                        verification_gas_limit: 0,
+                        pre_verification_gas: 0,
+                        max_fee_per_gas: 0,
+                        max_priority_fee_per_gas: 0,
+                        signature: Bytes::new(),
+                    },
+                )
+                .build(),
+            )
+        };
+        PoolOperation {
+            uo,
+            entry_point,
+            aggregator: None,
+            valid_time_range: ValidTimeRange::all_time(),
+            expected_code_hash: B256::ZERO,
+            sim_block_hash: B256::ZERO,
+            sim_block_number: 0,
+            account_is_staked: false,
+            entity_infos: EntityInfos::default(),
+            da_gas_data: DAGasData::Empty,
+            filter_id: None,
+            perms: UserOperationPermissions::default(),
+        }
+    }
+
+    /// Pool holding operations of SENDER on `entry_point` with the given nonces
+    fn pool_with_pending(entry_point: Address, nonces: Vec<U256>) -> MockPool {
+        let pending: BTreeSet<U256> = nonces.into_iter().collect();
+        let mut pool = MockPool::new();
+        pool.expect_get_op_by_id().returning(move |id| {
+            Ok((id.sender == SENDER && pending.contains(&id.nonce))
+                .then(|| pool_op(entry_point, id.nonce)))
+        });
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_no_pending_ops_returns_on_chain_nonce() {
+        let pool = pool_with_pending(entry_point_v0_7(), vec![]);
+
+        let next = next_pending_nonce(&pool, entry_point_v0_7(), SENDER, nonce(0, 3))
+            .await
+            .unwrap();
+        assert_eq!(next, nonce(0, 3));
+    }
+
+    #[tokio::test]
+    async fn test_consecutive_pending_ops_are_skipped() {
+        let pool = pool_with_pending(
+            entry_point_v0_7(),
+            vec![nonce(0, 3), nonce(0, 4), nonce(0, 5)],
+        );
+
+        let next = next_pending_nonce(&pool, entry_point_v0_7(), SENDER, nonce(0, 3))
+            .await
+            .unwrap();
+        assert_eq!(next, nonce(0, 6));
+    }
+
+    #[tokio::test]
+    async fn test_gap_from_dropped_op_is_filled_first() {
+        // The op with sequence 4 was dropped, so 5 cannot execute until 4 is resent
+        let pool = pool_with_pending(entry_point_v0_6(), vec![nonce(0, 3), nonce(0, 5)]);
+
+        let next = next_pending_nonce(&pool, entry_point_v0_6(), SENDER, nonce(0, 3))
+            .await
+            .unwrap();
+        assert_eq!(next, nonce(0, 4));
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_independent() {
+        let pool = pool_with_pending(entry_point_v0_7(), vec![nonce(0, 0), nonce(0, 1)]);
+
+        let next = next_pending_nonce(&pool, entry_point_v0_7(), SENDER, nonce(7, 0))
+            .await
+            .unwrap();
+        assert_eq!(next, nonce(7, 0));
+    }
+
+    #[tokio::test]
+    async fn test_ops_on_other_entry_point_are_ignored() {
+        let pool = pool_with_pending(entry_point_v0_6(), vec![nonce(0, 0)]);
+
+        let next = next_pending_nonce(&pool, entry_point_v0_7(), SENDER, nonce(0, 0))
+            .await
+            .unwrap();
+        assert_eq!(next, nonce(0, 0));
+    }
+
+    #[test]
+    fn test_nonce_key_limited_to_192_bits() {
+        validate_nonce_key(U256::ZERO).unwrap();
+        validate_nonce_key((U256::from(1u64) << 192) - U256::from(1u64)).unwrap();
+        assert!(matches!(
+            validate_nonce_key(U256::from(1u64) << 192),
+            Err(GatewayError::InvalidRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reader_calls_get_nonce() {
+        let key = U256::from(7u64);
+        let mut provider = MockEvmProvider::new();
+        provider.expect_call().returning(move |tx, _, _| {
+            assert_eq!(tx.to, Some(TxKind::Call(entry_point_v0_7())));
+            let input = tx.input.input().unwrap();
+            assert_eq!(&input[..4], &[0x35, 0x56, 0x7e, 0x1a]);
+            assert_eq!(&input[16..36], SENDER.as_slice());
+            assert_eq!(U256::from_be_slice(&input[36..68]), key);
+            Ok(Bytes::from(nonce(7, 2).to_be_bytes::<32>()))
+        });
+
+        let reader = EvmNonceReader::new(provider);
+        let on_chain = reader
+            .get_nonce(entry_point_v0_7(), SENDER, key)
+            .await
+            .unwrap();
+        assert_eq!(on_chain, nonce(7, 2));
+    }
+}
@@ -0,0 +1,776 @@
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::{U256, U64};
+use rundler_types::{BundlerSponsorship, UserOperationPermissions};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::{
+    error::{GatewayError, GatewayResult},
+    gateway::JsonRpcRequest,
+};
+
+/// Bundler sponsorship granted with a trust level
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BundlerSponsorshipConfig {
+    /// Most the bundler pays for one operation, in wei
+    pub max_cost: U256,
+    /// Seconds from submission the sponsorship stays valid
+    pub valid_for_seconds: u64,
+}
+
+/// Pool permissions of operations submitted at one trust level
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct TrustLevelPermissions {
+    /// Skip the untrusted simulation checks of the pool
+    pub trusted: bool,
+    /// Operations of one sender allowed in the pool
+    pub max_allowed_in_pool_for_sender: Option<usize>,
+    /// Percentage under the required fees still accepted into the pool
+    pub underpriced_accept_pct: Option<u32>,
+    /// Percentage under the required fees still bundled
+    pub underpriced_bundle_pct: Option<u32>,
+    /// Have the bundler pay for the operations
+    pub bundler_sponsorship: Option<BundlerSponsorshipConfig>,
+}
+
+impl Default for TrustLevelPermissions {
+    fn default() -> Self {
+        Self {
+            trusted: false,
+            max_allowed_in_pool_for_sender: Some(10),
+            underpriced_accept_pct: Some(10),
+            underpriced_bundle_pct: Some(0),
+            bundler_sponsorship: None,
+        }
+    }
+}
+
+impl TrustLevelPermissions {
+    /// Permissions of an operation submitted at `now`
+    fn to_permissions(&self, now: u64) -> UserOperationPermissions {
+        UserOperationPermissions {
+            trusted: self.trusted,
+            max_allowed_in_pool_for_sender: self.max_allowed_in_pool_for_sender,
+            underpriced_accept_pct: self.underpriced_accept_pct,
+            underpriced_bundle_pct: self.underpriced_bundle_pct,
+            bundler_sponsorship: self.bundler_sponsorship.as_ref().map(|sponsorship| {
+                BundlerSponsorship {
+                    max_cost: sponsorship.max_cost,
+                    valid_until: now.saturating_add(sponsorship.valid_for_seconds),
+                }
+            }),
+            expected_storage: None,
+        }
+    }
+}
+
+/// Bounds of the permissions callers request per operation
+/// (`[gateway.op_permissions.requested]`)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct RequestedPermissionsLimits {
+    /// Highest `maxAllowedInPoolForSender` a caller may request
+    pub max_allowed_in_pool_for_sender: usize,
+    /// Allow callers to mark their operations trusted, skipping untrusted simulation
+    pub allow_trusted: bool,
+    /// Highest bundler sponsorship `maxCost` a caller may request, in wei; 0 refuses sponsorship
+    pub max_bundler_sponsorship_cost: U256,
+    /// Furthest a requested sponsorship's `validUntil` may lie ahead, in seconds
+    pub max_sponsorship_valid_seconds: u64,
+}
+
+impl Default for RequestedPermissionsLimits {
+    fn default() -> Self {
+        Self {
+            max_allowed_in_pool_for_sender: 100,
+            allow_trusted: false,
+            max_bundler_sponsorship_cost: U256::ZERO,
+            max_sponsorship_valid_seconds: 3600,
+        }
+    }
+}
+
+/// Pool permissions a caller requests for one operation: the `permissions`
+/// object in the third parameter of eth_sendUserOperation
+///
+/// The wire format is rundler's `RpcUserOperationPermissions`: camelCase
+/// fields and hex quantities. Fields left out keep the permissions of the
+/// caller's trust level.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RequestedPermissions {
+    /// Skip the untrusted simulation checks of the pool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trusted: Option<bool>,
+    /// Operations of the sender allowed in the pool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_allowed_in_pool_for_sender: Option<U64>,
+    /// Percentage under the required fees still accepted into the pool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub underpriced_accept_pct: Option<U64>,
+    /// Percentage under the required fees still bundled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub underpriced_bundle_pct: Option<U64>,
+    /// Have the bundler pay for the operation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundler_sponsorship: Option<RequestedBundlerSponsorship>,
+}
+
+/// Bundler sponsorship requested for one operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RequestedBundlerSponsorship {
+    /// Most the bundler pays for the operation, in wei
+    pub max_cost: U256,
+    /// Unix time the sponsorship expires at
+    pub valid_until: U64,
+}
+
+/// Third parameter of eth_sendUserOperation
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SendOptions {
+    #[serde(default)]
+    permissions: Option<RequestedPermissions>,
+}
+
+impl RequestedPermissions {
+    /// Parse the `permissions` of eth_sendUserOperation's third parameter,
+    /// `None` when the parameter or the field is absent or null
+    pub fn from_send_options(options: Option<&Value>) -> GatewayResult<Option<Self>> {
+        let Some(options) = options.filter(|options| !options.is_null()) else {
+            return Ok(None);
+        };
+        let options: SendOptions = serde_json::from_value(options.clone()).map_err(|e| {
+            GatewayError::InvalidParams(format!("Invalid eth_sendUserOperation options: {}", e))
+        })?;
+        Ok(options.permissions)
+    }
+
+    /// `base` with the requested fields replaced, checked against `limits`
+    fn merge(
+        &self,
+        mut base: UserOperationPermissions,
+        limits: &RequestedPermissionsLimits,
+        now: u64,
+    ) -> GatewayResult<UserOperationPermissions> {
+        let invalid = |reason: String| Err(GatewayError::InvalidParams(reason));
+
+        if let Some(trusted) = self.trusted {
+            if trusted && !limits.allow_trusted {
+                return invalid("Trusted operations cannot be requested".to_string());
+            }
+            base.trusted = trusted;
+        }
+        if let Some(count) = self.max_allowed_in_pool_for_sender {
+            let count: usize = count.to();
+            if count == 0 || count > limits.max_allowed_in_pool_for_sender {
+                return invalid(format!(
+                    "maxAllowedInPoolForSender must be within 1..={}, got {}",
+                    limits.max_allowed_in_pool_for_sender, count
+                ));
+            }
+            base.max_allowed_in_pool_for_sender = Some(count);
+        }
+        for (name, requested, field) in [
+            (
+                "underpricedAcceptPct",
+                self.underpriced_accept_pct,
+                &mut base.underpriced_accept_pct,
+            ),
+            (
+                "underpricedBundlePct",
+                self.underpriced_bundle_pct,
+                &mut base.underpriced_bundle_pct,
+            ),
+        ] {
+            if let Some(pct) = requested {
+                if pct > U64::from(100) {
+                    return invalid(format!("{} must be within 0..=100, got {}", name, pct));
+                }
+                *field = Some(pct.to());
+            }
+        }
+        if let Some(sponsorship) = &self.bundler_sponsorship {
+            if limits.max_bundler_sponsorship_cost.is_zero() {
+                return invalid("Bundler sponsorship cannot be requested".to_string());
+            }
+            if sponsorship.max_cost > limits.max_bundler_sponsorship_cost {
+                return invalid(format!(
+                    "Bundler sponsorship maxCost {} exceeds the limit of {}",
+                    sponsorship.max_cost, limits.max_bundler_sponsorship_cost
+                ));
+            }
+            let valid_until: u64 = sponsorship.valid_until.to();
+            let latest = now.saturating_add(limits.max_sponsorship_valid_seconds);
+            if valid_until <= now || valid_until > latest {
+                return invalid(format!(
+                    "Bundler sponsorship validUntil must be within {}..={}, got {}",
+                    now + 1,
+                    latest,
+                    valid_until
+                ));
+            }
+            base.bundler_sponsorship = Some(BundlerSponsorship {
+                max_cost: sponsorship.max_cost,
+                valid_until,
+            });
+        }
+        Ok(base)
+    }
+}
+
+/// Pool permissions of submitted operations by trust level (`[gateway.op_permissions]`)
+///
+/// An API key's `trust_level` names one of `trust_levels`. Anonymous requests
+/// and keys without a known trust level get `default`; the trust level is
+/// only ever taken from the authenticated key, never from the request.
+/// Keys holding the `permissions` scope may also request permissions per
+/// operation, within `requested`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct OpPermissionsConfig {
+    /// Permissions of anonymous requests and keys without a trust level
+    pub default: TrustLevelPermissions,
+    /// Permissions by trust level name
+    pub trust_levels: HashMap<String, TrustLevelPermissions>,
+    /// Bounds of the permissions requested per operation
+    pub requested: RequestedPermissionsLimits,
+}
+
+impl OpPermissionsConfig {
+    /// Trust level applied to `request`, `None` for the default permissions
+    pub fn trust_level_of<'a>(&self, request: &'a JsonRpcRequest) -> Option<&'a str> {
+        // Without an authenticated key any trust level is a client claim
+        request.api_key_id.as_ref()?;
+        let level = request.trust_level.as_deref()?;
+        if !self.trust_levels.contains_key(level) {
+            warn!(
+                "API key {:?} has unknown trust level {:?}, applying default permissions",
+                request.api_key_id, level
+            );
+            return None;
+        }
+        Some(level)
+    }
+
+    /// Pool permissions of an operation submitted by `request`
+    pub fn permissions_for(&self, request: &JsonRpcRequest) -> UserOperationPermissions {
+        self.trust_level_permissions(request, now())
+    }
+
+    fn trust_level_permissions(
+        &self,
+        request: &JsonRpcRequest,
+        now: u64,
+    ) -> UserOperationPermissions {
+        self.trust_level_of(request)
+            .and_then(|level| self.trust_levels.get(level))
+            .unwrap_or(&self.default)
+            .to_permissions(now)
+    }
+
+    /// Pool permissions of an operation submitted by `request`, with the
+    /// `requested` permissions merged over those of its trust level
+    ///
+    /// Requesting permissions takes an authenticated API key holding the
+    /// `permissions` scope; anyone else gets a permission error rather than
+    /// having the request silently dropped.
+    pub fn resolve(
+        &self,
+        request: &JsonRpcRequest,
+        requested: Option<&RequestedPermissions>,
+    ) -> GatewayResult<UserOperationPermissions> {
+        let now = now();
+        let base = self.trust_level_permissions(request, now);
+        let Some(requested) = requested else {
+            return Ok(base);
+        };
+        if request.api_key_id.is_none() || !request.permissions {
+            return Err(GatewayError::AuthenticationFailed(
+                "Requesting pool permissions requires an API key with the 'permissions' scope"
+                    .to_string(),
+            ));
+        }
+        requested.merge(base, &self.requested, now)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use alloy_primitives::{address, Address, B256, U256};
+    use axum::http::{HeaderMap, HeaderValue};
+    use rundler_pool::LocalPoolBuilder;
+    use rundler_types::{
+        chain::ChainSpec,
+        pool::{MempoolError, MockPool, PoolError},
+        v0_7, Entity, UserOperation, UserOperationVariant,
+    };
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        middleware::hash_api_key,
+        router::{add_op_to_pool, EthApiConfig},
+        test_utils, ApiKeyConfig, ApiKeyEntry, ApiKeyScope, AuthMiddleware, GatewayRouter,
+    };
+
+    const INTERNAL_KEY: &str = "sk-internal-batcher";
+    const DAPP_KEY: &str = "sk-dapp";
+    const OPERATOR_KEY: &str = "sk-operator";
+    const SENDER: Address = address!("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    const ENTRY_POINT: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+
+    fn config() -> OpPermissionsConfig {
+        serde_json::from_value(json!({
+            "trust_levels": {
+                "internal": {
+                    "trusted": true,
+                    "max_allowed_in_pool_for_sender": 100,
+                    "bundler_sponsorship": { "max_cost": "0x2386f26fc10000", "valid_for_seconds": 300 }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    fn auth() -> AuthMiddleware {
+        let key = |id: &str, raw: &str, trust_level: Option<&str>| ApiKeyEntry {
+            id: id.to_string(),
+            key_hash: hash_api_key(raw),
+            scopes: vec![ApiKeyScope::Send],
+            trust_level: trust_level.map(str::to_string),
+        };
+        let operator = ApiKeyEntry {
+            scopes: vec![ApiKeyScope::Send, ApiKeyScope::Permissions],
+            ..key("operator", OPERATOR_KEY, None)
+        };
+        AuthMiddleware::with_config(ApiKeyConfig {
+            enabled: true,
+            allow_anonymous_reads: false,
+            keys: vec![
+                key("internal-batcher", INTERNAL_KEY, Some("internal")),
+                key("dapp", DAPP_KEY, None),
+                operator,
+            ],
+        })
+    }
+
+    /// Request as the gateway resolves it: trust level and scopes from the authorized key only
+    fn authorized_request(
+        auth: &AuthMiddleware,
+        headers: &HeaderMap,
+        body: &Value,
+    ) -> JsonRpcRequest {
+        let api_key_id = auth
+            .authorize_request(headers, body, "eth_sendUserOperation")
+            .unwrap();
+        let permissions = api_key_id
+            .as_deref()
+            .is_some_and(|key_id| auth.has_scope(key_id, ApiKeyScope::Permissions));
+        JsonRpcRequest {
+            trust_level: api_key_id
+                .as_deref()
+                .and_then(|key_id| auth.trust_level(key_id))
+                .map(str::to_string),
+            api_key_id,
+            permissions,
+            ..test_utils::request(
+                "eth_sendUserOperation",
+                body["params"].as_array().cloned().unwrap_or_default(),
+            )
+        }
+    }
+
+    fn headers(key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", HeaderValue::from_str(key).unwrap());
+        headers
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[test]
+    fn test_anonymous_request_gets_default_permissions() {
+        let config = config();
+        let request = test_utils::request("eth_sendUserOperation", vec![]);
+
+        let perms = config.permissions_for(&request);
+        assert!(!perms.trusted);
+        assert_eq!(perms.max_allowed_in_pool_for_sender, Some(10));
+        assert_eq!(perms.underpriced_accept_pct, Some(10));
+        assert_eq!(perms.underpriced_bundle_pct, Some(0));
+        assert!(perms.bundler_sponsorship.is_none());
+
+        // A trust level without an authenticated key is never honored
+        let claimed = JsonRpcRequest {
+            trust_level: Some("internal".to_string()),
+            ..request
+        };
+        assert_eq!(config.trust_level_of(&claimed), None);
+        assert_eq!(config.permissions_for(&claimed), perms);
+    }
+
+    #[test]
+    fn test_keyed_trusted_request_gets_elevated_permissions() {
+        let config = config();
+        let auth = auth();
+        let request = authorized_request(&auth, &headers(INTERNAL_KEY), &json!({"params": []}));
+        assert_eq!(request.trust_level.as_deref(), Some("internal"));
+        assert_eq!(config.trust_level_of(&request), Some("internal"));
+
+        let perms = config.permissions_for(&request);
+        assert!(perms.trusted);
+        assert_eq!(perms.max_allowed_in_pool_for_sender, Some(100));
+        let sponsorship = perms.bundler_sponsorship.unwrap();
+        assert_eq!(sponsorship.max_cost, U256::from(10_000_000_000_000_000u64));
+        assert!(sponsorship.valid_until.abs_diff(now() + 300) <= 5);
+
+        // A key without a trust level keeps the defaults
+        let request = authorized_request(&auth, &headers(DAPP_KEY), &json!({"params": []}));
+        assert_eq!(
+            config.permissions_for(&request),
+            OpPermissionsConfig::default().permissions_for(&request)
+        );
+    }
+
+    #[test]
+    fn test_client_supplied_trust_is_ignored() {
+        let config = config();
+        let auth = auth();
+        let mut headers = headers(DAPP_KEY);
+        headers.insert("x-trust-level", HeaderValue::from_static("internal"));
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_sendUserOperation",
+            "trust_level": "internal",
+            "trustLevel": "internal",
+            "params": [{"trusted": true, "trustLevel": "internal"}]
+        });
+
+        let request = authorized_request(&auth, &headers, &body);
+        assert_eq!(request.api_key_id.as_deref(), Some("dapp"));
+        assert_eq!(request.trust_level, None);
+        let perms = config.permissions_for(&request);
+        assert!(!perms.trusted);
+        assert_eq!(perms.max_allowed_in_pool_for_sender, Some(10));
+
+        // Nor are trust levels no key may carry
+        assert_eq!(
+            auth.trust_level("admin:0x0000000000000000000000000000000000000001"),
+            None
+        );
+        let unknown = JsonRpcRequest {
+            trust_level: Some("root".to_string()),
+            ..authorized_request(&auth, &headers, &body)
+        };
+        assert!(!config.permissions_for(&unknown).trusted);
+    }
+
+    fn send_body(options: Value) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_sendUserOperation",
+            "params": [{}, ENTRY_POINT, options]
+        })
+    }
+
+    fn requested(options: &Value) -> Option<RequestedPermissions> {
+        RequestedPermissions::from_send_options(Some(options)).unwrap()
+    }
+
+    #[test]
+    fn test_requested_permissions_round_trip() {
+        let permissions = json!({
+            "trusted": false,
+            "maxAllowedInPoolForSender": "0x14",
+            "underpricedAcceptPct": "0x5",
+            "underpricedBundlePct": "0x0",
+            "bundlerSponsorship": { "maxCost": "0x2386f26fc10000", "validUntil": "0x6553f100" }
+        });
+        let parsed: RequestedPermissions = serde_json::from_value(permissions.clone()).unwrap();
+        assert_eq!(
+            parsed.max_allowed_in_pool_for_sender.unwrap().to::<u64>(),
+            20
+        );
+        assert_eq!(
+            parsed.bundler_sponsorship.as_ref().unwrap().max_cost,
+            U256::from(10_000_000_000_000_000u64)
+        );
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), permissions);
+
+        // Fields left out stay out, so they keep the trust level's values
+        let partial = json!({ "maxAllowedInPoolForSender": "0xb" });
+        let parsed: RequestedPermissions = serde_json::from_value(partial.clone()).unwrap();
+        assert_eq!(parsed.trusted, None);
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), partial);
+
+        // Misspelled fields are errors rather than silently ignored
+        assert!(serde_json::from_value::<RequestedPermissions>(
+            json!({ "maxAllowedInPoolForSenders": "0xb" })
+        )
+        .is_err());
+        assert!(matches!(
+            RequestedPermissions::from_send_options(Some(&json!({ "permission": {} }))),
+            Err(GatewayError::InvalidParams(_))
+        ));
+        assert!(matches!(
+            RequestedPermissions::from_send_options(Some(&json!("0x14"))),
+            Err(GatewayError::InvalidParams(_))
+        ));
+
+        // No third parameter, a null one or one without permissions request nothing
+        assert_eq!(RequestedPermissions::from_send_options(None).unwrap(), None);
+        assert_eq!(requested(&Value::Null), None);
+        assert_eq!(requested(&json!({})), None);
+    }
+
+    #[test]
+    fn test_requested_permissions_need_the_scope() {
+        let config = config();
+        let auth = auth();
+        let options = json!({ "permissions": { "maxAllowedInPoolForSender": "0x14" } });
+        let permissions = requested(&options);
+
+        // Anonymous callers and keys without the scope get a permission error
+        for key in [None, Some(DAPP_KEY), Some(INTERNAL_KEY)] {
+            let request = match key {
+                Some(key) => authorized_request(&auth, &headers(key), &send_body(options.clone())),
+                None => JsonRpcRequest {
+                    api_key_id: None,
+                    permissions: true,
+                    ..authorized_request(&auth, &headers(DAPP_KEY), &send_body(options.clone()))
+                },
+            };
+            assert!(
+                matches!(
+                    config.resolve(&request, permissions.as_ref()),
+                    Err(GatewayError::AuthenticationFailed(_))
+                ),
+                "{:?}",
+                key
+            );
+            // Without a request the trust level's permissions still apply
+            assert_eq!(
+                config.resolve(&request, None).unwrap(),
+                config.permissions_for(&request)
+            );
+        }
+
+        let request = authorized_request(&auth, &headers(OPERATOR_KEY), &send_body(options));
+        let perms = config.resolve(&request, permissions.as_ref()).unwrap();
+        assert_eq!(perms.max_allowed_in_pool_for_sender, Some(20));
+        // Merged over the defaults
+        assert_eq!(perms.underpriced_accept_pct, Some(10));
+        assert!(!perms.trusted);
+    }
+
+    #[test]
+    fn test_requested_permissions_are_bounded() {
+        let mut config = config();
+        config.requested.max_allowed_in_pool_for_sender = 50;
+        let auth = auth();
+        let resolve = |config: &OpPermissionsConfig, permissions: Value| {
+            let options = json!({ "permissions": permissions });
+            let request =
+                authorized_request(&auth, &headers(OPERATOR_KEY), &send_body(options.clone()));
+            config.resolve(&request, requested(&options).as_ref())
+        };
+        let rejected = |config: &OpPermissionsConfig, permissions: Value| match resolve(
+            config,
+            permissions.clone(),
+        ) {
+            Err(GatewayError::InvalidParams(reason)) => reason,
+            other => panic!("{} accepted: {:?}", permissions, other),
+        };
+
+        assert!(
+            rejected(&config, json!({ "maxAllowedInPoolForSender": "0x33" })).contains("1..=50")
+        );
+        assert!(
+            rejected(&config, json!({ "maxAllowedInPoolForSender": "0x0" })).contains("1..=50")
+        );
+        assert!(rejected(&config, json!({ "underpricedAcceptPct": "0x65" })).contains("0..=100"));
+        assert!(rejected(&config, json!({ "underpricedBundlePct": "0x65" })).contains("0..=100"));
+        assert!(rejected(&config, json!({ "trusted": true })).contains("Trusted"));
+        let sponsorship = |max_cost: u64, valid_until: u64| {
+            json!({ "bundlerSponsorship": {
+                "maxCost": format!("{:#x}", max_cost),
+                "validUntil": format!("{:#x}", valid_until)
+            }})
+        };
+        // Refused until a cost limit is configured
+        assert!(rejected(&config, sponsorship(1, now() + 60)).contains("cannot be requested"));
+
+        config.requested.allow_trusted = true;
+        config.requested.max_bundler_sponsorship_cost = U256::from(1_000);
+        assert!(rejected(&config, sponsorship(1_001, now() + 60)).contains("exceeds"));
+        assert!(rejected(&config, sponsorship(1_000, now() - 1)).contains("validUntil"));
+        assert!(rejected(&config, sponsorship(1_000, now() + 7_200)).contains("validUntil"));
+
+        let perms = resolve(
+            &config,
+            json!({
+                "trusted": true,
+                "maxAllowedInPoolForSender": "0x32",
+                "underpricedAcceptPct": "0x64",
+                "bundlerSponsorship": { "maxCost": "0x3e8", "validUntil": format!("{:#x}", now() + 60) }
+            }),
+        )
+        .unwrap();
+        assert!(perms.trusted);
+        assert_eq!(perms.max_allowed_in_pool_for_sender, Some(50));
+        assert_eq!(perms.underpriced_accept_pct, Some(100));
+        assert_eq!(perms.underpriced_bundle_pct, Some(0));
+        assert_eq!(
+            perms.bundler_sponsorship.unwrap().max_cost,
+            U256::from(1_000)
+        );
+    }
+
+    fn user_op(nonce: u64) -> UserOperationVariant {
+        UserOperationVariant::V0_7(
+            v0_7::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_7::UserOperationRequiredFields {
+                    sender: SENDER,
+                    nonce: U256::from(nonce),
+                    ..Default::default()
+                },
+            )
+            .build(),
+        )
+    }
+
+    /// Pool holding accepted operations and applying the mempool's per-sender
+    /// limit: the operation's `max_allowed_in_pool_for_sender`, unstaked senders
+    fn limiting_pool() -> MockPool {
+        let pending: Arc<Mutex<HashMap<Address, usize>>> = Default::default();
+        let mut pool = MockPool::new();
+        pool.expect_add_op().returning(move |op, perms| {
+            let allowed = perms.max_allowed_in_pool_for_sender.unwrap_or(4);
+            let mut pending = pending.lock().unwrap();
+            let count = pending.entry(op.sender()).or_default();
+            if *count >= allowed {
+                return Err(PoolError::MempoolError(MempoolError::MaxOperationsReached(
+                    allowed,
+                    Entity::account(op.sender()),
+                )));
+            }
+            *count += 1;
+            Ok(B256::from(U256::from(*count)))
+        });
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_elevated_sender_limit_admits_an_eleventh_operation() {
+        let config = config();
+        let auth = auth();
+
+        // The default of 10 pending operations per sender rejects the 11th
+        let pool = limiting_pool();
+        let request = authorized_request(&auth, &headers(DAPP_KEY), &send_body(Value::Null));
+        let perms = config.resolve(&request, None).unwrap();
+        for nonce in 0..10 {
+            add_op_to_pool(&pool, user_op(nonce), perms.clone())
+                .await
+                .unwrap();
+        }
+        let err = add_op_to_pool(&pool, user_op(10), perms).await.unwrap_err();
+        assert!(err.to_string().contains("Max operations (10)"), "{}", err);
+
+        // Requesting a limit of 11 lets it in
+        let pool = limiting_pool();
+        let options = json!({ "permissions": { "maxAllowedInPoolForSender": "0xb" } });
+        let request =
+            authorized_request(&auth, &headers(OPERATOR_KEY), &send_body(options.clone()));
+        let perms = config
+            .resolve(&request, requested(&options).as_ref())
+            .unwrap();
+        for nonce in 0..11 {
+            add_op_to_pool(&pool, user_op(nonce), perms.clone())
+                .await
+                .unwrap();
+        }
+        assert!(add_op_to_pool(&pool, user_op(11), perms).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_refuses_permissions_without_scope() {
+        // The pool never answers: a refused request must fail before reaching it
+        let router = GatewayRouter::with_rundler_components(
+            Arc::new(LocalPoolBuilder::new(10).get_handle()),
+            EthApiConfig {
+                chain_id: 31337,
+                entry_points: vec![ENTRY_POINT.parse().unwrap()],
+                ..Default::default()
+            },
+        )
+        .with_op_permissions(config());
+        let auth = auth();
+        let op = json!({
+            "sender": SENDER,
+            "nonce": "0x1",
+            "callData": "0x",
+            "callGasLimit": "0x186a0",
+            "verificationGasLimit": "0x186a0",
+            "preVerificationGas": "0x5208",
+            "maxFeePerGas": "0x3b9aca00",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "signature": "0x"
+        });
+        let body = json!({
+            "params": [op, ENTRY_POINT, { "permissions": { "maxAllowedInPoolForSender": "0xb" } }]
+        });
+
+        let request = authorized_request(&auth, &headers(DAPP_KEY), &body);
+        let err = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            router.route_to_rundler(&request),
+        )
+        .await
+        .unwrap()
+        .unwrap_err();
+        assert!(
+            matches!(err, GatewayError::AuthenticationFailed(_)),
+            "{}",
+            err
+        );
+        assert!(err.to_string().contains("'permissions' scope"));
+
+        // Malformed options are invalid params for every caller
+        let body = json!({ "params": [body["params"][0], ENTRY_POINT, { "permissions": [] }] });
+        let request = authorized_request(&auth, &headers(OPERATOR_KEY), &body);
+        assert!(matches!(
+            router.route_to_rundler(&request).await,
+            Err(GatewayError::InvalidParams(_))
+        ));
+    }
+}
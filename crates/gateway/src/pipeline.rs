@@ -0,0 +1,922 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use alloy_primitives::Address;
+use async_trait::async_trait;
+use futures_util::future::{join_all, AbortHandle, Abortable};
+use rundler_paymaster_relay::{
+    record_stage,
+    stats::{BoundedMap, LatencyHistogram},
+    SponsorStage, StageTimings,
+};
+use rundler_types::{UserOperation, UserOperationVariant};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, warn};
+
+use crate::{
+    authorization::AuthorizationChecker,
+    deadline::Deadline,
+    error::{GatewayError, GatewayResult},
+    events::{EventBus, GatewayEvent},
+    security::SecurityChecker,
+    threat_feed::ThreatIntelStore,
+    validation::DataIntegrityChecker,
+};
+
+/// Name of the data integrity stage
+pub const DATA_INTEGRITY_MODULE: &str = "data_integrity";
+/// Name of the authorization stage
+pub const AUTHORIZATION_MODULE: &str = "authorization";
+/// Name of the security analysis stage
+pub const SECURITY_MODULE: &str = "security";
+
+/// Operation a pipeline stage checks
+pub struct ModuleContext<'a> {
+    /// Operation to sponsor
+    pub user_op: &'a UserOperationVariant,
+    /// EntryPoint the operation targets
+    pub entry_point: Address,
+    /// Feed-backed threat intelligence, if configured
+    pub threat_intel: Option<&'a Arc<ThreatIntelStore>>,
+    /// Deadline of the request; each stage is abandoned when it passes
+    pub deadline: &'a Deadline,
+    /// Live events, where stage failures are published
+    pub events: Option<&'a EventBus>,
+    /// Stage durations collected for a debug response, if requested
+    pub timings: Option<&'a StageTimings>,
+}
+
+/// One named check of the sponsorship pipeline
+///
+/// A `ValidationError` rejects the operation; any other error means the
+/// module could not decide and is subject to the stage's `fail_open` setting.
+#[async_trait]
+pub trait SecurityModule: Send + Sync {
+    /// Name used in `[gateway.pipeline]` and in statistics
+    fn name(&self) -> &'static str;
+
+    /// Check the operation
+    async fn check(&self, ctx: &ModuleContext<'_>) -> GatewayResult<()>;
+}
+
+/// Data completeness and format checks
+pub struct DataIntegrityModule;
+
+#[async_trait]
+impl SecurityModule for DataIntegrityModule {
+    fn name(&self) -> &'static str {
+        DATA_INTEGRITY_MODULE
+    }
+
+    async fn check(&self, ctx: &ModuleContext<'_>) -> GatewayResult<()> {
+        let entry_point = format!("{:#x}", ctx.entry_point);
+        let result = DataIntegrityChecker::new()
+            .validate_user_operation(ctx.user_op, &entry_point)
+            .await
+            .map_err(|e| {
+                GatewayError::InternalError(format!(
+                    "Data integrity validation system error: {}",
+                    e
+                ))
+            })?;
+
+        if !result.is_valid {
+            error!("❌ Data integrity validation failed: {}", result.summary);
+            return Err(GatewayError::ValidationError(format!(
+                "Data integrity check failed: {} critical issues found: [{}]",
+                result.critical_issues.len(),
+                result.critical_issues.join(", ")
+            )));
+        }
+        if !result.warnings.is_empty() {
+            warn!(
+                "⚠️ Data integrity validation passed with warnings: {}",
+                result.warnings.join(", ")
+            );
+        }
+        debug!(
+            "✅ Data integrity validation passed (score: {}): {}",
+            result.validation_score, result.summary
+        );
+        Ok(())
+    }
+}
+
+/// Sender eligibility checks
+pub struct AuthorizationModule;
+
+#[async_trait]
+impl SecurityModule for AuthorizationModule {
+    fn name(&self) -> &'static str {
+        AUTHORIZATION_MODULE
+    }
+
+    async fn check(&self, ctx: &ModuleContext<'_>) -> GatewayResult<()> {
+        let mut checker = AuthorizationChecker::new();
+        if let Err(e) = checker.load_configuration().await {
+            warn!("Failed to load authorization configuration: {}", e);
+        }
+
+        let result = checker
+            .check_authorization(ctx.user_op, &ctx.entry_point, None)
+            .await
+            .map_err(|e| {
+                GatewayError::InternalError(format!("Authorization check system error: {}", e))
+            })?;
+
+        if !result.is_authorized {
+            error!("❌ Authorization check failed: {}", result.summary);
+            return Err(GatewayError::ValidationError(format!(
+                "Authorization check failed: {} blocking issues found: [{}]",
+                result.blocking_issues.len(),
+                result.blocking_issues.join(", ")
+            )));
+        }
+        if !result.warnings.is_empty() {
+            warn!(
+                "⚠️ Authorization check passed with warnings: {}",
+                result.warnings.join(", ")
+            );
+        }
+        debug!(
+            "✅ Authorization check passed (score: {}): {}",
+            result.authorization_score, result.summary
+        );
+        Ok(())
+    }
+}
+
+/// Threat intelligence, calldata and contract security analysis
+pub struct SecurityCheckModule;
+
+#[async_trait]
+impl SecurityModule for SecurityCheckModule {
+    fn name(&self) -> &'static str {
+        SECURITY_MODULE
+    }
+
+    async fn check(&self, ctx: &ModuleContext<'_>) -> GatewayResult<()> {
+        let mut checker = SecurityChecker::new();
+        if let Some(threat_intel) = ctx.threat_intel {
+            checker = checker.with_threat_intel(threat_intel.clone());
+        }
+        if let Err(e) = checker.load_threat_intelligence().await {
+            warn!("Failed to load threat intelligence: {}", e);
+        }
+
+        let result = checker
+            .check_security(ctx.user_op, &ctx.entry_point, None)
+            .await
+            .map_err(|e| {
+                GatewayError::InternalError(format!("Security check system error: {}", e))
+            })?;
+
+        if !result.is_secure {
+            error!("🚨 Security check failed: {}", result.summary);
+            return Err(GatewayError::ValidationError(format!(
+                "Security check failed: {} critical violations found: [{}]",
+                result.critical_violations.len(),
+                result.critical_violations.join(", ")
+            )));
+        }
+        if !result.warnings.is_empty() {
+            warn!(
+                "⚠️ Security check passed with warnings: {}",
+                result.warnings.join(", ")
+            );
+        }
+        debug!(
+            "✅ Security check passed (score: {}): {}",
+            result.security_score, result.summary
+        );
+        Ok(())
+    }
+}
+
+/// Stage the run time of the module `name` is reported as
+fn module_stage(name: &str) -> SponsorStage {
+    match name {
+        DATA_INTEGRITY_MODULE => SponsorStage::DataIntegrity,
+        AUTHORIZATION_MODULE => SponsorStage::Authorization,
+        SECURITY_MODULE => SponsorStage::Security,
+        _ => SponsorStage::CustomModule,
+    }
+}
+
+/// Built-in modules, in their default order
+pub fn builtin_modules() -> Vec<Arc<dyn SecurityModule>> {
+    vec![
+        Arc::new(DataIntegrityModule),
+        Arc::new(AuthorizationModule),
+        Arc::new(SecurityCheckModule),
+    ]
+}
+
+/// One stage of `[gateway.pipeline]`
+#[derive(Debug, Clone, Deserialize)]
+pub struct PipelineModuleConfig {
+    /// Module name
+    pub name: String,
+    /// Run this module; disabled modules are skipped
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Let operations through when the module errors instead of deciding
+    #[serde(default)]
+    pub fail_open: bool,
+    /// Start only once every earlier stage has passed, for a module relying
+    /// on their outcome; other stages run concurrently with the ones before
+    #[serde(default)]
+    pub after_previous: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl PipelineModuleConfig {
+    /// Enabled, fail-closed stage running `name`
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            enabled: true,
+            fail_open: false,
+            after_previous: false,
+        }
+    }
+}
+
+/// Order and settings of the sponsorship checks (`[gateway.pipeline]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PipelineConfig {
+    /// Stages in priority order; when several reject an operation, the
+    /// rejection of the first is reported
+    pub modules: Vec<PipelineModuleConfig>,
+    /// Run independent stages concurrently; `false` runs them one after the
+    /// other, e.g. to debug a module
+    pub parallel: bool,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            modules: [DATA_INTEGRITY_MODULE, AUTHORIZATION_MODULE, SECURITY_MODULE]
+                .into_iter()
+                .map(PipelineModuleConfig::new)
+                .collect(),
+            parallel: true,
+        }
+    }
+}
+
+/// Counters of one module
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleStats {
+    /// Module name
+    pub name: String,
+    /// Operations the module accepted
+    pub passed: u64,
+    /// Operations the module rejected
+    pub failed: u64,
+    /// Module errors, including those let through by `fail_open`
+    pub errors: u64,
+    /// Operations not checked because the module is disabled
+    pub skipped: u64,
+    /// Runs abandoned because an earlier stage rejected the operation
+    pub cancelled: u64,
+    /// Mean run time in milliseconds
+    pub avg_latency_ms: f64,
+    /// Longest run time in milliseconds
+    pub max_latency_ms: f64,
+    /// 99th percentile run time in milliseconds, estimated from histogram buckets
+    pub p99_latency_ms: f64,
+}
+
+/// Modules tracked before the least recently run ones are evicted
+const MAX_TRACKED_MODULES: usize = 64;
+
+#[derive(Default)]
+struct ModuleCounters {
+    passed: u64,
+    failed: u64,
+    errors: u64,
+    skipped: u64,
+    cancelled: u64,
+    latency: LatencyHistogram,
+}
+
+impl ModuleCounters {
+    fn stats(&self) -> ModuleStats {
+        let latency = self.latency.snapshot();
+        ModuleStats {
+            name: String::new(),
+            passed: self.passed,
+            failed: self.failed,
+            errors: self.errors,
+            skipped: self.skipped,
+            cancelled: self.cancelled,
+            avg_latency_ms: latency.avg_ms,
+            max_latency_ms: latency.max_ms,
+            p99_latency_ms: latency.p99_ms,
+        }
+    }
+}
+
+/// Per-module pass, fail and latency counters, served by superRelay_getPipelineStats
+///
+/// At most [`MAX_TRACKED_MODULES`] modules are tracked, so renamed or
+/// replaced modules cannot grow the counters without bound.
+#[derive(Debug)]
+pub struct PipelineStats {
+    modules: BoundedMap<ModuleCounters>,
+}
+
+impl Default for PipelineStats {
+    fn default() -> Self {
+        Self {
+            modules: BoundedMap::new(MAX_TRACKED_MODULES),
+        }
+    }
+}
+
+impl PipelineStats {
+    fn record_run(&self, name: &str, latency: Duration, result: &GatewayResult<()>) {
+        self.modules.update(name, |counters| {
+            match result {
+                Ok(()) => counters.passed += 1,
+                Err(GatewayError::ValidationError(_)) => counters.failed += 1,
+                Err(_) => counters.errors += 1,
+            }
+            counters.latency.record(latency);
+        });
+    }
+
+    fn record_skip(&self, name: &str) {
+        self.modules.update(name, |counters| counters.skipped += 1);
+    }
+
+    fn record_cancel(&self, name: &str) {
+        self.modules
+            .update(name, |counters| counters.cancelled += 1);
+    }
+
+    /// Counters of every tracked module that has seen an operation, sorted by name
+    pub fn snapshot(&self) -> Vec<ModuleStats> {
+        self.modules
+            .snapshot(ModuleCounters::stats)
+            .into_iter()
+            .map(|(name, stats)| ModuleStats { name, ..stats })
+            .collect()
+    }
+
+    /// Counters since the last reset, starting over from zero
+    pub fn snapshot_and_reset(&self) -> Vec<ModuleStats> {
+        self.modules
+            .drain()
+            .into_iter()
+            .map(|(name, counters)| ModuleStats {
+                name,
+                ..counters.stats()
+            })
+            .collect()
+    }
+
+    /// Modules evicted to stay within [`MAX_TRACKED_MODULES`]
+    pub fn evictions(&self) -> u64 {
+        self.modules.evictions()
+    }
+}
+
+struct PipelineStage {
+    module: Arc<dyn SecurityModule>,
+    config: PipelineModuleConfig,
+}
+
+/// Sponsorship checks run in the configured order
+///
+/// Consecutive stages run concurrently unless the pipeline is sequential or a
+/// stage is configured to run `after_previous`. Whichever finishes first, the
+/// outcome is that of running the stages one by one: the first stage to
+/// reject in configured order decides, and stages after it are cancelled.
+pub struct ModulePipeline {
+    stages: Vec<PipelineStage>,
+    parallel: bool,
+    stats: PipelineStats,
+}
+
+impl Default for ModulePipeline {
+    fn default() -> Self {
+        Self::new(&PipelineConfig::default(), builtin_modules())
+            .expect("default pipeline uses built-in modules")
+    }
+}
+
+impl ModulePipeline {
+    /// Arrange `modules` as `config` lists them
+    ///
+    /// Fails on module names that are unknown or listed twice. Modules not
+    /// listed are not run.
+    pub fn new(
+        config: &PipelineConfig,
+        modules: Vec<Arc<dyn SecurityModule>>,
+    ) -> GatewayResult<Self> {
+        let mut available: HashMap<&'static str, Arc<dyn SecurityModule>> =
+            modules.into_iter().map(|m| (m.name(), m)).collect();
+        let known: Vec<&str> = {
+            let mut known: Vec<&str> = available.keys().copied().collect();
+            known.sort_unstable();
+            known
+        };
+
+        let mut seen = HashSet::new();
+        let mut stages = Vec::with_capacity(config.modules.len());
+        for stage in &config.modules {
+            if !seen.insert(stage.name.as_str()) {
+                return Err(GatewayError::InvalidRequest(format!(
+                    "Pipeline module '{}' is listed more than once",
+                    stage.name
+                )));
+            }
+            let module = available.remove(stage.name.as_str()).ok_or_else(|| {
+                GatewayError::InvalidRequest(format!(
+                    "Unknown pipeline module '{}', expected one of: {}",
+                    stage.name,
+                    known.join(", ")
+                ))
+            })?;
+            stages.push(PipelineStage {
+                module,
+                config: stage.clone(),
+            });
+        }
+
+        Ok(Self {
+            stages,
+            parallel: config.parallel,
+            stats: PipelineStats::default(),
+        })
+    }
+
+    /// Pipeline of the built-in modules arranged by `config`
+    pub fn from_config(config: &PipelineConfig) -> GatewayResult<Self> {
+        Self::new(config, builtin_modules())
+    }
+
+    /// Run every enabled stage, stopping at the first rejection
+    pub async fn run(&self, ctx: &ModuleContext<'_>) -> GatewayResult<()> {
+        let mut group: Vec<&PipelineStage> = Vec::new();
+        for stage in &self.stages {
+            if !stage.config.enabled {
+                let name = stage.module.name();
+                debug!("⏭️ Pipeline module {} disabled, skipping", name);
+                self.stats.record_skip(name);
+                continue;
+            }
+            if !group.is_empty() && (!self.parallel || stage.config.after_previous) {
+                self.run_group(&group, ctx).await?;
+                group.clear();
+            }
+            group.push(stage);
+        }
+        self.run_group(&group, ctx).await
+    }
+
+    /// Run `stages` concurrently, a stage that fails cancelling those after it
+    ///
+    /// The stages before a failing one always finish, so the first failure in
+    /// stage order is returned however the runs interleave.
+    async fn run_group(
+        &self,
+        stages: &[&PipelineStage],
+        ctx: &ModuleContext<'_>,
+    ) -> GatewayResult<()> {
+        let (handles, registrations): (Vec<_>, Vec<_>) =
+            stages.iter().map(|_| AbortHandle::new_pair()).unzip();
+        let runs =
+            stages
+                .iter()
+                .zip(registrations)
+                .enumerate()
+                .map(|(index, (stage, registration))| {
+                    let later = &handles[index + 1..];
+                    Abortable::new(
+                        async move {
+                            let result = self.run_stage(stage, ctx).await;
+                            if result.is_err() {
+                                later.iter().for_each(AbortHandle::abort);
+                            }
+                            result
+                        },
+                        registration,
+                    )
+                });
+
+        let mut outcome = Ok(());
+        for (stage, result) in stages.iter().zip(join_all(runs).await) {
+            match result {
+                Ok(result) => outcome = outcome.and(result),
+                Err(_) => {
+                    let name = stage.module.name();
+                    debug!(
+                        "⏹️ Pipeline module {} cancelled by an earlier rejection",
+                        name
+                    );
+                    self.stats.record_cancel(name);
+                }
+            }
+        }
+        outcome
+    }
+
+    /// Run one stage, failing when it rejects the operation or errors
+    /// without `fail_open`
+    async fn run_stage(&self, stage: &PipelineStage, ctx: &ModuleContext<'_>) -> GatewayResult<()> {
+        let name = stage.module.name();
+        debug!("🔍 Running pipeline module {}", name);
+        let start = Instant::now();
+        let result = ctx.deadline.run(name, stage.module.check(ctx)).await;
+        let latency = start.elapsed();
+        self.stats.record_run(name, latency, &result);
+        record_stage(ctx.timings, module_stage(name), latency);
+        if let (Err(e), Some(events)) = (&result, ctx.events) {
+            events.publish(GatewayEvent::PipelineStageFailed {
+                stage: name.to_string(),
+                sender: ctx.user_op.sender(),
+                entry_point: ctx.entry_point,
+                error: e.to_string(),
+                failed_open: stage.config.fail_open
+                    && !matches!(
+                        e,
+                        GatewayError::ValidationError(_) | GatewayError::DeadlineExceeded { .. }
+                    ),
+            });
+        }
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(GatewayError::ValidationError(reason)) => {
+                Err(GatewayError::ValidationError(reason))
+            }
+            // Out of time is not the module failing to decide
+            Err(e @ GatewayError::DeadlineExceeded { .. }) => Err(e),
+            Err(e) if stage.config.fail_open => {
+                warn!("Pipeline module {} failed open: {}", name, e);
+                Ok(())
+            }
+            Err(e) => {
+                error!("💥 Pipeline module {} error: {}", name, e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Names of the stages in execution order, including disabled ones
+    pub fn module_names(&self) -> Vec<&'static str> {
+        self.stages
+            .iter()
+            .map(|stage| stage.module.name())
+            .collect()
+    }
+
+    /// Per-module statistics
+    pub fn stats(&self) -> &PipelineStats {
+        &self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use alloy_primitives::{address, Address, Bytes, U256};
+    use async_trait::async_trait;
+    use rundler_types::{chain::ChainSpec, v0_6, UserOperationVariant};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{test_utils, GatewayRouter};
+
+    const ENTRY_POINT: Address = address!("5FF137D4b0FDCD49DcA30c7CF57E578a026d2789");
+
+    fn user_op() -> UserOperationVariant {
+        UserOperationVariant::V0_6(
+            v0_6::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_6::UserOperationRequiredFields {
+                    sender: address!("f39fd6e51aad88f6f4ce6ab8827279cfffb92266"),
+                    nonce: U256::from(1),
+                    init_code: Bytes::new(),
+                    call_data: Bytes::new(),
+                    call_gas_limit: 100_000,
+                    verification_gas_limit: 100_000,
+                    pre_verification_gas: 21_000,
+                    max_fee_per_gas: 1_000_000_000,
+                    max_priority_fee_per_gas: 1_000_000_000,
+                    paymaster_and_data: Bytes::new(),
+                    signature: Bytes::new(),
+                },
+            )
+            .build(),
+        )
+    }
+
+    enum Outcome {
+        Pass,
+        Reject,
+        Error,
+        /// Pass after sleeping this many milliseconds
+        SlowPass(u64),
+        /// Reject after sleeping this many milliseconds
+        SlowReject(u64),
+    }
+
+    /// Appends its name to a shared log and returns a fixed outcome
+    struct RecordingModule {
+        name: &'static str,
+        outcome: Outcome,
+        log: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    #[async_trait]
+    impl SecurityModule for RecordingModule {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn check(&self, _ctx: &ModuleContext<'_>) -> GatewayResult<()> {
+            self.log.lock().unwrap().push(self.name);
+            match self.outcome {
+                Outcome::Pass => Ok(()),
+                Outcome::Reject => Err(GatewayError::ValidationError(self.name.to_string())),
+                Outcome::Error => Err(GatewayError::InternalError(self.name.to_string())),
+                Outcome::SlowPass(ms) => {
+                    tokio::time::sleep(Duration::from_millis(ms)).await;
+                    Ok(())
+                }
+                Outcome::SlowReject(ms) => {
+                    tokio::time::sleep(Duration::from_millis(ms)).await;
+                    Err(GatewayError::ValidationError(self.name.to_string()))
+                }
+            }
+        }
+    }
+
+    fn modules(
+        outcomes: Vec<(&'static str, Outcome)>,
+    ) -> (Vec<Arc<dyn SecurityModule>>, Arc<Mutex<Vec<&'static str>>>) {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let modules = outcomes
+            .into_iter()
+            .map(|(name, outcome)| {
+                Arc::new(RecordingModule {
+                    name,
+                    outcome,
+                    log: log.clone(),
+                }) as Arc<dyn SecurityModule>
+            })
+            .collect();
+        (modules, log)
+    }
+
+    fn config(names: &[&str]) -> PipelineConfig {
+        PipelineConfig {
+            modules: names
+                .iter()
+                .map(|n| PipelineModuleConfig::new(*n))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    async fn run(pipeline: &ModulePipeline) -> GatewayResult<()> {
+        let user_op = user_op();
+        pipeline
+            .run(&ModuleContext {
+                user_op: &user_op,
+                entry_point: ENTRY_POINT,
+                threat_intel: None,
+                deadline: &Deadline::default(),
+                events: None,
+                timings: None,
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_reordered_pipeline_runs_in_configured_order() {
+        let (available, log) = modules(vec![
+            ("a", Outcome::Pass),
+            ("b", Outcome::Pass),
+            ("c", Outcome::Pass),
+        ]);
+        let pipeline = ModulePipeline::new(&config(&["c", "a", "b"]), available).unwrap();
+
+        run(&pipeline).await.unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["c", "a", "b"]);
+        assert_eq!(pipeline.module_names(), vec!["c", "a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_module_skipped() {
+        let (available, log) = modules(vec![("a", Outcome::Pass), ("b", Outcome::Reject)]);
+        let mut config = config(&["a", "b"]);
+        config.modules[1].enabled = false;
+        let pipeline = ModulePipeline::new(&config, available).unwrap();
+
+        run(&pipeline).await.unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["a"]);
+
+        let stats = pipeline.stats().snapshot();
+        assert_eq!(stats[1].name, "b");
+        assert_eq!(stats[1].skipped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rejection_stops_pipeline() {
+        let (available, log) = modules(vec![("a", Outcome::Reject), ("b", Outcome::Pass)]);
+        let pipeline = ModulePipeline::new(&config(&["a", "b"]), available).unwrap();
+
+        let result = run(&pipeline).await;
+        assert!(matches!(result, Err(GatewayError::ValidationError(_))));
+        assert_eq!(*log.lock().unwrap(), vec!["a"]);
+    }
+
+    #[tokio::test]
+    async fn test_fail_open_continues_after_module_error() {
+        let (available, log) = modules(vec![("a", Outcome::Error), ("b", Outcome::Pass)]);
+        let mut fail_open = config(&["a", "b"]);
+        fail_open.modules[0].fail_open = true;
+        let pipeline = ModulePipeline::new(&fail_open, available).unwrap();
+
+        run(&pipeline).await.unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["a", "b"]);
+
+        let (available, _) = modules(vec![("a", Outcome::Error), ("b", Outcome::Pass)]);
+        let fail_closed = ModulePipeline::new(&config(&["a", "b"]), available).unwrap();
+        let result = run(&fail_closed).await;
+        assert!(matches!(result, Err(GatewayError::InternalError(_))));
+    }
+
+    /// Pipeline of two modules sleeping `ms` each, concurrent or not
+    fn slow_pipeline(ms: u64, parallel: bool) -> ModulePipeline {
+        let (available, _) = modules(vec![
+            ("a", Outcome::SlowPass(ms)),
+            ("b", Outcome::SlowPass(ms)),
+        ]);
+        let mut config = config(&["a", "b"]);
+        config.parallel = parallel;
+        ModulePipeline::new(&config, available).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_independent_stages_run_concurrently() {
+        let start = Instant::now();
+        run(&slow_pipeline(100, true)).await.unwrap();
+        let parallel = start.elapsed();
+
+        let start = Instant::now();
+        run(&slow_pipeline(100, false)).await.unwrap();
+        let sequential = start.elapsed();
+
+        assert!(parallel >= Duration::from_millis(100), "{:?}", parallel);
+        assert!(parallel < Duration::from_millis(180), "{:?}", parallel);
+        assert!(sequential >= Duration::from_millis(200), "{:?}", sequential);
+    }
+
+    #[tokio::test]
+    async fn test_stage_latency_attributed_per_module() {
+        let pipeline = slow_pipeline(100, true);
+        run(&pipeline).await.unwrap();
+
+        for stats in pipeline.stats().snapshot() {
+            assert!(stats.max_latency_ms >= 100.0, "{:?}", stats);
+            assert!(stats.max_latency_ms < 180.0, "{:?}", stats);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_first_rejection_in_order_reported() {
+        // b rejects at once, but a comes first and rejects later
+        let (available, log) = modules(vec![
+            ("a", Outcome::SlowReject(50)),
+            ("b", Outcome::Reject),
+            ("c", Outcome::SlowPass(1_000)),
+        ]);
+        let pipeline = ModulePipeline::new(&config(&["a", "b", "c"]), available).unwrap();
+
+        let start = Instant::now();
+        match run(&pipeline).await {
+            Err(GatewayError::ValidationError(reason)) => assert_eq!(reason, "a"),
+            other => panic!("expected a to reject: {:?}", other),
+        }
+        assert!(start.elapsed() < Duration::from_millis(500));
+        // b cancelled c before it started
+        assert_eq!(*log.lock().unwrap(), vec!["a", "b"]);
+
+        let stats = pipeline.stats().snapshot();
+        assert_eq!((stats[0].name.as_str(), stats[0].failed), ("a", 1));
+        assert_eq!((stats[1].name.as_str(), stats[1].failed), ("b", 1));
+        assert_eq!((stats[2].name.as_str(), stats[2].cancelled), ("c", 1));
+    }
+
+    #[tokio::test]
+    async fn test_later_rejection_does_not_cancel_earlier_stages() {
+        let (available, log) = modules(vec![("a", Outcome::SlowPass(50)), ("b", Outcome::Reject)]);
+        let pipeline = ModulePipeline::new(&config(&["a", "b"]), available).unwrap();
+
+        match run(&pipeline).await {
+            Err(GatewayError::ValidationError(reason)) => assert_eq!(reason, "b"),
+            other => panic!("expected b to reject: {:?}", other),
+        }
+        assert_eq!(*log.lock().unwrap(), vec!["a", "b"]);
+        assert_eq!(pipeline.stats().snapshot()[0].passed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_after_previous_waits_for_earlier_stages() {
+        let (available, log) = modules(vec![("a", Outcome::SlowReject(50)), ("b", Outcome::Pass)]);
+        let mut config = config(&["a", "b"]);
+        config.modules[1].after_previous = true;
+        let pipeline = ModulePipeline::new(&config, available).unwrap();
+
+        assert!(run(&pipeline).await.is_err());
+        assert_eq!(*log.lock().unwrap(), vec!["a"]);
+    }
+
+    #[test]
+    fn test_unknown_module_rejected() {
+        let error = ModulePipeline::from_config(&config(&["data_integrity", "bls_protection"]))
+            .err()
+            .unwrap();
+        let message = error.to_string();
+        assert!(message.contains("Unknown pipeline module 'bls_protection'"));
+        assert!(message.contains("authorization, data_integrity, security"));
+
+        let error = ModulePipeline::from_config(&config(&["security", "security"]))
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("listed more than once"));
+    }
+
+    #[test]
+    fn test_pipeline_config_defaults() {
+        let config: PipelineConfig = serde_json::from_value(json!({
+            "modules": [
+                { "name": "security", "fail_open": true },
+                { "name": "data_integrity", "enabled": false },
+            ]
+        }))
+        .unwrap();
+        assert!(config.modules[0].enabled);
+        assert!(!config.modules[1].fail_open);
+        assert!(!config.modules[0].after_previous);
+        assert!(config.parallel);
+
+        let pipeline = ModulePipeline::from_config(&config).unwrap();
+        assert_eq!(pipeline.module_names(), vec!["security", "data_integrity"]);
+    }
+
+    #[tokio::test]
+    async fn test_stats_aggregated_per_module() {
+        let (available, _) = modules(vec![("a", Outcome::Pass), ("b", Outcome::Reject)]);
+        let pipeline = ModulePipeline::new(&config(&["a", "b"]), available).unwrap();
+
+        for _ in 0..3 {
+            let _ = run(&pipeline).await;
+        }
+
+        let stats = pipeline.stats().snapshot();
+        assert_eq!(stats.len(), 2);
+        assert_eq!((stats[0].name.as_str(), stats[0].passed), ("a", 3));
+        assert_eq!((stats[1].name.as_str(), stats[1].failed), ("b", 3));
+        assert!(stats[0].max_latency_ms >= stats[0].avg_latency_ms);
+        assert!(stats[0].max_latency_ms >= stats[0].p99_latency_ms);
+
+        let interval = pipeline.stats().snapshot_and_reset();
+        assert_eq!(interval[0].passed, 3);
+        assert!(pipeline.stats().snapshot().is_empty());
+        let _ = run(&pipeline).await;
+        assert_eq!(pipeline.stats().snapshot()[0].passed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_stats_rpc() {
+        let router = GatewayRouter::new();
+        let request = test_utils::request("superRelay_getPipelineStats", vec![]);
+
+        let stats = router.route_to_super_relay(&request).await.unwrap();
+        assert_eq!(
+            stats["modules"],
+            json!(["data_integrity", "authorization", "security"])
+        );
+        assert_eq!(stats["stats"], json!([]));
+        assert_eq!(stats["evictedModules"], 0);
+    }
+}
@@ -0,0 +1,389 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use alloy_primitives::{Address, B256, U256};
+use axum::body::Bytes;
+use futures_util::{stream, Stream};
+use rundler_types::{
+    pool::{Pool, PoolOperation},
+    UserOperation,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::warn;
+
+use crate::{
+    bundle_tracker::BundleTracker,
+    error::{GatewayError, GatewayResult},
+};
+
+/// Method GET /admin/pool/export is authorized as, putting it in the admin scope
+pub const POOL_EXPORT_METHOD: &str = "admin_exportPool";
+
+/// Operations serialized into each chunk of a pool export
+pub const EXPORT_CHUNK_OPS: usize = 100;
+
+/// Upper bounds of the sender buckets of superRelay_poolSummary, by pending
+/// operation count; the last bucket is open ended
+const SENDER_BUCKETS: [(u64, &str); 4] = [(1, "1"), (4, "2-4"), (9, "5-9"), (u64::MAX, "10+")];
+
+/// Query of GET /admin/pool/export
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PoolExportQuery {
+    /// Only export operations of this EntryPoint
+    pub entry_point: Option<Address>,
+    /// Export at most this many operations
+    pub limit: Option<usize>,
+}
+
+/// One line of a pool export
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolExportEntry {
+    /// EntryPoint the operation targets
+    pub entry_point: Address,
+    /// Sender account
+    pub sender: Address,
+    /// Account nonce
+    pub nonce: U256,
+    /// Maximum fee per gas
+    pub max_fee_per_gas: U256,
+    /// Maximum priority fee per gas
+    pub max_priority_fee_per_gas: U256,
+    /// Signature aggregator, if any
+    pub aggregator: Option<Address>,
+    /// UserOperation hash
+    pub user_op_hash: B256,
+    /// Block the operation was simulated at when it entered the pool
+    pub sim_block_number: u64,
+    /// `pending`, or the latest builder outcome when bundle tracking is enabled
+    pub status: &'static str,
+}
+
+impl PoolExportEntry {
+    /// Export line of `op`, with its status from `tracker`
+    pub fn new(op: &PoolOperation, tracker: Option<&BundleTracker>) -> Self {
+        let user_op_hash = op.uo.hash();
+        let status = tracker
+            .and_then(|tracker| tracker.op_status(user_op_hash))
+            .map_or("pending", |status| status.latest.status());
+        Self {
+            entry_point: op.entry_point,
+            sender: op.uo.sender(),
+            nonce: op.uo.nonce(),
+            max_fee_per_gas: U256::from(op.uo.max_fee_per_gas()),
+            max_priority_fee_per_gas: U256::from(op.uo.max_priority_fee_per_gas()),
+            aggregator: op.aggregator,
+            user_op_hash,
+            sim_block_number: op.sim_block_number,
+            status,
+        }
+    }
+}
+
+/// EntryPoints an export of `pool` filtered by `entry_point` covers
+///
+/// Fails when the pool does not serve `entry_point`.
+pub async fn export_entry_points<P: Pool + ?Sized>(
+    pool: &P,
+    entry_point: Option<Address>,
+) -> GatewayResult<Vec<Address>> {
+    let supported = pool
+        .get_supported_entry_points()
+        .await
+        .map_err(|e| GatewayError::PoolError(e.to_string()))?;
+    match entry_point {
+        Some(entry_point) if !supported.contains(&entry_point) => Err(
+            GatewayError::InvalidRequest(format!("Unsupported entry point: {:#x}", entry_point)),
+        ),
+        Some(entry_point) => Ok(vec![entry_point]),
+        None => Ok(supported),
+    }
+}
+
+struct ExportState<P: ?Sized> {
+    pool: Arc<P>,
+    tracker: Option<Arc<BundleTracker>>,
+    entry_points: std::vec::IntoIter<Address>,
+    ops: std::vec::IntoIter<PoolOperation>,
+    remaining: usize,
+}
+
+/// NDJSON export of the operations of `pool` on `entry_points`, one
+/// [`PoolExportEntry`] per line
+///
+/// Each EntryPoint is read from the pool only once the previous one is
+/// written, and lines are serialized [`EXPORT_CHUNK_OPS`] at a time, so the
+/// response body is never held in memory as a whole.
+pub fn export_ndjson<P: Pool + ?Sized + 'static>(
+    pool: Arc<P>,
+    entry_points: Vec<Address>,
+    limit: Option<usize>,
+    tracker: Option<Arc<BundleTracker>>,
+) -> impl Stream<Item = GatewayResult<Bytes>> + Send {
+    let state = ExportState {
+        pool,
+        tracker,
+        entry_points: entry_points.into_iter(),
+        ops: Vec::new().into_iter(),
+        remaining: limit.unwrap_or(usize::MAX),
+    };
+
+    stream::unfold(Some(state), |state| async move {
+        let mut state = state?;
+        while state.ops.as_slice().is_empty() && state.remaining > 0 {
+            let entry_point = state.entry_points.next()?;
+            match state.pool.debug_dump_mempool(entry_point).await {
+                Ok(ops) => state.ops = ops.into_iter(),
+                Err(e) => {
+                    warn!("Pool export of {:#x} failed: {}", entry_point, e);
+                    return Some((Err(GatewayError::PoolError(e.to_string())), None));
+                }
+            }
+        }
+        if state.remaining == 0 {
+            return None;
+        }
+
+        let take = state.remaining.min(EXPORT_CHUNK_OPS);
+        let mut chunk = Vec::new();
+        for op in state.ops.by_ref().take(take) {
+            let entry = PoolExportEntry::new(&op, state.tracker.as_deref());
+            if let Err(e) = serde_json::to_writer(&mut chunk, &entry) {
+                return Some((Err(GatewayError::InternalError(e.to_string())), None));
+            }
+            chunk.push(b'\n');
+            state.remaining -= 1;
+        }
+        Some((Ok(Bytes::from(chunk)), Some(state)))
+    })
+}
+
+/// Operation and sender counts per EntryPoint of `pool`, served by
+/// superRelay_poolSummary
+///
+/// Senders are bucketed by how many operations they have pending.
+pub async fn pool_summary<P: Pool + ?Sized>(pool: &P) -> GatewayResult<Value> {
+    let mut entry_points = Vec::new();
+    let mut total_ops = 0;
+    for entry_point in export_entry_points(pool, None).await? {
+        let ops = pool
+            .debug_dump_mempool(entry_point)
+            .await
+            .map_err(|e| GatewayError::PoolError(e.to_string()))?;
+
+        let mut per_sender: HashMap<Address, u64> = HashMap::new();
+        for op in &ops {
+            *per_sender.entry(op.uo.sender()).or_default() += 1;
+        }
+        let mut buckets: BTreeMap<&str, u64> =
+            SENDER_BUCKETS.iter().map(|(_, name)| (*name, 0)).collect();
+        for count in per_sender.values() {
+            let (_, name) = SENDER_BUCKETS
+                .iter()
+                .find(|(max, _)| count <= max)
+                .expect("last bucket is open ended");
+            *buckets.entry(name).or_default() += 1;
+        }
+
+        total_ops += ops.len();
+        entry_points.push(json!({
+            "entryPoint": entry_point,
+            "ops": ops.len(),
+            "senders": per_sender.len(),
+            "senderBuckets": buckets,
+        }));
+    }
+
+    Ok(json!({
+        "totalOps": total_ops,
+        "entryPoints": entry_points,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy_primitives::{Address, B256, U256};
+    use futures_util::StreamExt;
+    use rundler_types::{
+        chain::ChainSpec,
+        da::DAGasData,
+        pool::{MockPool, PoolOperation},
+        v0_6, EntityInfos, UserOperationPermissions, UserOperationVariant, ValidTimeRange,
+    };
+    use serde_json::Value;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::{ApiKeyConfig, GatewayConfig, GatewayError, PaymasterGateway};
+
+    const ENTRY_POINT_A: Address = Address::repeat_byte(0xa0);
+    const ENTRY_POINT_B: Address = Address::repeat_byte(0xb0);
+
+    fn pool_op(entry_point: Address, sender: Address, nonce: u64) -> PoolOperation {
+        PoolOperation {
+            uo: UserOperationVariant::V0_6(
+                v0_6::UserOperationBuilder::new(
+                    &ChainSpec::default(),
+                    v0_6::UserOperationRequiredFields {
+                        sender,
+                        nonce: U256::from(nonce),
+                        max_fee_per_gas: 2_000_000_000,
+                        max_priority_fee_per_gas: 1_000_000_000,
+                        ..Default::default()
+                    },
+                )
+                .build(),
+            ),
+            entry_point,
+            aggregator: None,
+            valid_time_range: ValidTimeRange::all_time(),
+            expected_code_hash: B256::ZERO,
+            sim_block_hash: B256::ZERO,
+            sim_block_number: 7,
+            account_is_staked: false,
+            entity_infos: EntityInfos::default(),
+            da_gas_data: DAGasData::Empty,
+            filter_id: None,
+            perms: UserOperationPermissions::default(),
+        }
+    }
+
+    /// Pool holding `count_a` operations on ENTRY_POINT_A, from one sender per
+    /// operation, and 3 operations of one sender on ENTRY_POINT_B
+    fn populated_pool(count_a: u64) -> MockPool {
+        let mut pool = MockPool::new();
+        pool.expect_get_supported_entry_points()
+            .returning(|| Ok(vec![ENTRY_POINT_A, ENTRY_POINT_B]));
+        pool.expect_debug_dump_mempool()
+            .returning(move |entry_point| {
+                Ok(if entry_point == ENTRY_POINT_A {
+                    (0..count_a)
+                        .map(|i| pool_op(ENTRY_POINT_A, Address::with_last_byte(i as u8), i))
+                        .collect()
+                } else {
+                    (0..3)
+                        .map(|i| pool_op(ENTRY_POINT_B, Address::repeat_byte(0x55), i))
+                        .collect()
+                })
+            });
+        pool
+    }
+
+    /// Chunks of the export of `pool`, each split into its parsed lines
+    async fn export(
+        pool: MockPool,
+        entry_point: Option<Address>,
+        limit: Option<usize>,
+    ) -> Vec<Vec<Value>> {
+        let entry_points = export_entry_points(&pool, entry_point).await.unwrap();
+        export_ndjson(Arc::new(pool), entry_points, limit, None)
+            .map(|chunk| {
+                let chunk = chunk.unwrap();
+                assert!(chunk.ends_with(b"\n"));
+                std::str::from_utf8(&chunk)
+                    .unwrap()
+                    .lines()
+                    .map(|line| serde_json::from_str(line).unwrap())
+                    .collect()
+            })
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_export_streams_in_chunks() {
+        let chunks = export(populated_pool(250), None, None).await;
+
+        let sizes: Vec<_> = chunks.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![EXPORT_CHUNK_OPS, EXPORT_CHUNK_OPS, 50, 3]);
+
+        let first = &chunks[0][0];
+        assert_eq!(first["entryPoint"], serde_json::json!(ENTRY_POINT_A));
+        assert_eq!(
+            first["sender"],
+            serde_json::json!(Address::with_last_byte(0))
+        );
+        assert_eq!(first["nonce"], "0x0");
+        assert_eq!(first["maxFeePerGas"], "0x77359400");
+        assert_eq!(first["maxPriorityFeePerGas"], "0x3b9aca00");
+        assert!(first["aggregator"].is_null());
+        assert!(first["userOpHash"].as_str().unwrap().starts_with("0x"));
+        assert_eq!(first["simBlockNumber"], 7);
+        assert_eq!(first["status"], "pending");
+
+        let last = chunks.last().unwrap().last().unwrap();
+        assert_eq!(last["entryPoint"], serde_json::json!(ENTRY_POINT_B));
+    }
+
+    #[tokio::test]
+    async fn test_export_filters() {
+        let only_b = export(populated_pool(250), Some(ENTRY_POINT_B), None).await;
+        assert_eq!(only_b.concat().len(), 3);
+        assert!(only_b
+            .concat()
+            .iter()
+            .all(|op| op["entryPoint"] == serde_json::json!(ENTRY_POINT_B)));
+
+        // The limit spans EntryPoints
+        let limited = export(populated_pool(2), None, Some(4)).await;
+        assert_eq!(limited.concat().len(), 4);
+        let limited = export(populated_pool(250), None, Some(120)).await;
+        let sizes: Vec<_> = limited.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![EXPORT_CHUNK_OPS, 20]);
+
+        let err = export_entry_points(&populated_pool(1), Some(Address::repeat_byte(1)))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidRequest(_)));
+    }
+
+    #[tokio::test]
+    async fn test_pool_summary() {
+        let summary = pool_summary(&populated_pool(2)).await.unwrap();
+
+        assert_eq!(summary["totalOps"], 5);
+        let entry_points = summary["entryPoints"].as_array().unwrap();
+        assert_eq!(entry_points[0]["ops"], 2);
+        assert_eq!(entry_points[0]["senders"], 2);
+        assert_eq!(entry_points[0]["senderBuckets"]["1"], 2);
+        assert_eq!(entry_points[1]["ops"], 3);
+        assert_eq!(entry_points[1]["senders"], 1);
+        assert_eq!(entry_points[1]["senderBuckets"]["1"], 0);
+        assert_eq!(entry_points[1]["senderBuckets"]["2-4"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_export_requires_admin_scope() {
+        let config = GatewayConfig {
+            api_keys: ApiKeyConfig {
+                enabled: true,
+                allow_anonymous_reads: true,
+                keys: vec![],
+            },
+            ..Default::default()
+        };
+        let app = PaymasterGateway::new(config, None).app().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"GET /admin/pool/export?limit=10 HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 401"), "{}", response);
+    }
+}
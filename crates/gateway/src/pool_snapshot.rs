@@ -0,0 +1,750 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::{Address, B256, U256};
+use metrics::counter;
+use rundler_pool::PoolEvent;
+use rundler_types::{
+    pool::{Pool, PoolOperation},
+    BundlerSponsorship, ExpectedStorage, UserOperation, UserOperationPermissions,
+    UserOperationVariant,
+};
+use rundler_utils::emit::{self, WithEntryPoint};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    sync::{broadcast, watch},
+    task::JoinHandle,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::{GatewayError, GatewayResult},
+    nonce::NonceReader,
+    router::add_op_to_pool,
+};
+
+/// Snapshot file layout; files of another version are skipped
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Most receive times kept for operations the pool has not removed yet
+const MAX_TRACKED_RECEIVE_TIMES: usize = 100_000;
+
+/// Pool snapshot settings (`[pool]`)
+///
+/// On graceful shutdown the pending operations are written to
+/// `pool_snapshot_path`, and on the next start they are re-added to the pool
+/// so users do not have to resubmit them after a redeploy.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct PoolSnapshotConfig {
+    /// Snapshot file, unset to disable snapshots
+    pub pool_snapshot_path: Option<PathBuf>,
+    /// Most operations written per chain
+    pub max_snapshot_ops: usize,
+    /// Snapshots older than this are not replayed, in seconds
+    pub max_snapshot_age_seconds: u64,
+}
+
+impl Default for PoolSnapshotConfig {
+    fn default() -> Self {
+        Self {
+            pool_snapshot_path: None,
+            max_snapshot_ops: 10_000,
+            max_snapshot_age_seconds: 600,
+        }
+    }
+}
+
+impl PoolSnapshotConfig {
+    /// Settings of one of several chains, whose snapshot file has the chain id
+    /// added before the extension (`pool.json` becomes `pool.10.json`)
+    pub fn for_chain(&self, chain_id: u64) -> Self {
+        let pool_snapshot_path = self.pool_snapshot_path.as_ref().map(|path| {
+            let mut name = path.file_stem().unwrap_or_default().to_os_string();
+            name.push(format!(".{}", chain_id));
+            if let Some(extension) = path.extension() {
+                name.push(".");
+                name.push(extension);
+            }
+            path.with_file_name(name)
+        });
+        Self {
+            pool_snapshot_path,
+            ..self.clone()
+        }
+    }
+}
+
+/// Outcome of replaying a snapshot into the pool
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplayReport {
+    /// Operations back in the pool
+    pub replayed: usize,
+    /// Operations left out before reaching the pool: unreadable, on an
+    /// EntryPoint no longer served, expired or with a used nonce
+    pub dropped: usize,
+    /// Operations the pool refused
+    pub rejected: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PoolSnapshot {
+    version: u32,
+    chain_id: u64,
+    saved_at: u64,
+    ops: Vec<SnapshotOp>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotOp {
+    entry_point: Address,
+    user_op_hash: B256,
+    /// The operation as the pool stores it
+    user_operation: Value,
+    permissions: SnapshotPermissions,
+    /// When the pool first accepted the operation, unknown if that was before
+    /// this process started tracking
+    received_at: Option<u64>,
+    valid_until: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotPermissions {
+    trusted: bool,
+    max_allowed_in_pool_for_sender: Option<usize>,
+    underpriced_accept_pct: Option<u32>,
+    underpriced_bundle_pct: Option<u32>,
+    bundler_sponsorship: Option<SnapshotSponsorship>,
+    expected_storage: Option<ExpectedStorage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotSponsorship {
+    max_cost: U256,
+    valid_until: u64,
+}
+
+impl From<&UserOperationPermissions> for SnapshotPermissions {
+    fn from(perms: &UserOperationPermissions) -> Self {
+        Self {
+            trusted: perms.trusted,
+            max_allowed_in_pool_for_sender: perms.max_allowed_in_pool_for_sender,
+            underpriced_accept_pct: perms.underpriced_accept_pct,
+            underpriced_bundle_pct: perms.underpriced_bundle_pct,
+            bundler_sponsorship: perms.bundler_sponsorship.as_ref().map(|sponsorship| {
+                SnapshotSponsorship {
+                    max_cost: sponsorship.max_cost,
+                    valid_until: sponsorship.valid_until,
+                }
+            }),
+            expected_storage: perms.expected_storage.clone(),
+        }
+    }
+}
+
+impl From<SnapshotPermissions> for UserOperationPermissions {
+    fn from(perms: SnapshotPermissions) -> Self {
+        Self {
+            trusted: perms.trusted,
+            max_allowed_in_pool_for_sender: perms.max_allowed_in_pool_for_sender,
+            underpriced_accept_pct: perms.underpriced_accept_pct,
+            underpriced_bundle_pct: perms.underpriced_bundle_pct,
+            bundler_sponsorship: perms
+                .bundler_sponsorship
+                .map(|sponsorship| BundlerSponsorship {
+                    max_cost: sponsorship.max_cost,
+                    valid_until: sponsorship.valid_until,
+                }),
+            expected_storage: perms.expected_storage,
+        }
+    }
+}
+
+/// Serialized form of `user_op`, read back as a [`UserOperationVariant`]
+fn user_operation_value(user_op: &UserOperationVariant) -> serde_json::Result<Value> {
+    match user_op {
+        UserOperationVariant::V0_6(op) => serde_json::to_value(op),
+        UserOperationVariant::V0_7(op) => serde_json::to_value(op),
+        #[cfg(feature = "entrypoint-v0_8")]
+        UserOperationVariant::V0_8(op) => serde_json::to_value(op),
+    }
+}
+
+/// Writes one chain's pending pool operations on shutdown and re-adds them
+/// on the next start
+///
+/// Replayed operations skip the gateway's sponsorship checks they already
+/// passed; only cheap checks run before the pool validates them again.
+pub struct PoolSnapshotter {
+    chain_id: u64,
+    config: PoolSnapshotConfig,
+    nonce_reader: Option<Arc<dyn NonceReader>>,
+    received: Mutex<HashMap<B256, u64>>,
+    finished: watch::Sender<bool>,
+}
+
+impl PoolSnapshotter {
+    /// Snapshots of chain `chain_id` as configured by `config`
+    pub fn new(chain_id: u64, config: PoolSnapshotConfig) -> Self {
+        Self {
+            chain_id,
+            config,
+            nonce_reader: None,
+            received: Mutex::new(HashMap::new()),
+            finished: watch::channel(false).0,
+        }
+    }
+
+    /// Drop replayed operations whose nonce the EntryPoint already used
+    pub fn with_nonce_reader(mut self, nonce_reader: Arc<dyn NonceReader>) -> Self {
+        self.nonce_reader = Some(nonce_reader);
+        self
+    }
+
+    /// Whether a snapshot file is configured
+    pub fn is_enabled(&self) -> bool {
+        self.config.pool_snapshot_path.is_some()
+    }
+
+    /// Record receive times from the pool events broadcast on `events` until
+    /// the channel closes
+    pub fn subscribe(
+        self: &Arc<Self>,
+        events: broadcast::Receiver<WithEntryPoint<PoolEvent>>,
+    ) -> JoinHandle<()> {
+        let snapshotter = self.clone();
+        tokio::spawn(emit::receive_events(
+            "pool snapshot",
+            events,
+            move |event| snapshotter.record(&event),
+        ))
+    }
+
+    /// Apply one pool event
+    pub fn record(&self, event: &WithEntryPoint<PoolEvent>) {
+        let mut received = self.received.lock().unwrap();
+        match &event.event {
+            PoolEvent::ReceivedOp { op_hash, .. } => {
+                if received.len() < MAX_TRACKED_RECEIVE_TIMES {
+                    received.entry(*op_hash).or_insert_with(unix_now);
+                }
+            }
+            PoolEvent::RemovedOp { op_hash, .. } => {
+                received.remove(op_hash);
+            }
+            _ => {}
+        }
+    }
+
+    /// Write the pending operations of `pool`, if a snapshot file is
+    /// configured, then report the snapshot finished
+    ///
+    /// Without a pool, e.g. while it was restarting, nothing is written.
+    pub async fn shutdown<P: Pool + ?Sized>(&self, pool: Option<&P>) {
+        if let (Some(path), Some(pool)) = (&self.config.pool_snapshot_path, pool) {
+            match self.save(path, pool).await {
+                Ok(count) => info!(
+                    "💾 Pool snapshot of {} operation(s) written to {}",
+                    count,
+                    path.display()
+                ),
+                Err(e) => warn!("Failed to write pool snapshot to {}: {}", path.display(), e),
+            }
+        }
+        self.finished.send_replace(true);
+    }
+
+    /// Wait until [`Self::shutdown`] has finished
+    pub async fn wait_finished(&self) {
+        let mut receiver = self.finished.subscribe();
+        let _ = receiver.wait_for(|finished| *finished).await;
+    }
+
+    async fn save<P: Pool + ?Sized>(&self, path: &Path, pool: &P) -> GatewayResult<usize> {
+        let pool_error = |e: rundler_types::pool::PoolError| GatewayError::PoolError(e.to_string());
+        let mut ops = Vec::new();
+        for entry_point in pool
+            .get_supported_entry_points()
+            .await
+            .map_err(pool_error)?
+        {
+            let remaining = self.config.max_snapshot_ops.saturating_sub(ops.len());
+            let pooled = pool
+                .debug_dump_mempool(entry_point)
+                .await
+                .map_err(pool_error)?;
+            for op in pooled.iter().take(remaining) {
+                match self.snapshot_op(op) {
+                    Ok(op) => ops.push(op),
+                    Err(e) => warn!("Pool operation left out of the snapshot: {}", e),
+                }
+            }
+        }
+
+        let snapshot = PoolSnapshot {
+            version: SNAPSHOT_VERSION,
+            chain_id: self.chain_id,
+            saved_at: unix_now(),
+            ops,
+        };
+        let data = serde_json::to_vec(&snapshot).map_err(|e| {
+            GatewayError::InternalError(format!("Failed to serialize pool snapshot: {}", e))
+        })?;
+        // Written next to the target and renamed, so a crash mid-write never
+        // leaves a truncated snapshot behind
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, data)
+            .and_then(|_| std::fs::rename(&partial, path))
+            .map_err(|e| GatewayError::InternalError(e.to_string()))?;
+        Ok(snapshot.ops.len())
+    }
+
+    fn snapshot_op(&self, op: &PoolOperation) -> GatewayResult<SnapshotOp> {
+        let user_op_hash = op.uo.hash();
+        Ok(SnapshotOp {
+            entry_point: op.entry_point,
+            user_op_hash,
+            user_operation: user_operation_value(&op.uo)
+                .map_err(|e| GatewayError::InternalError(format!("{:#x}: {}", user_op_hash, e)))?,
+            permissions: SnapshotPermissions::from(&op.perms),
+            received_at: self.received.lock().unwrap().get(&user_op_hash).copied(),
+            valid_until: op.valid_time_range.valid_until.seconds_since_epoch(),
+        })
+    }
+
+    /// Re-add the operations of the snapshot file to `pool`
+    ///
+    /// A missing, corrupt, stale or foreign snapshot is skipped with a
+    /// warning. The file is removed once replayed so it is not replayed twice.
+    pub async fn replay<P: Pool + ?Sized>(&self, pool: &P) -> ReplayReport {
+        let mut report = ReplayReport::default();
+        let Some(path) = &self.config.pool_snapshot_path else {
+            return report;
+        };
+        let now = unix_now();
+        let Some(snapshot) = self.load(path, now) else {
+            return report;
+        };
+        let supported = match pool.get_supported_entry_points().await {
+            Ok(supported) => supported,
+            Err(e) => {
+                warn!("Pool snapshot not replayed, pool unavailable: {}", e);
+                return report;
+            }
+        };
+
+        for op in snapshot.ops {
+            let (hash, received_at) = (op.user_op_hash, op.received_at);
+            let (outcome, detail) = match self.check(op, &supported, now).await {
+                Err(reason) => {
+                    report.dropped += 1;
+                    ("dropped", reason)
+                }
+                Ok((user_op, perms)) => match add_op_to_pool(pool, user_op, perms).await {
+                    Ok(_) => {
+                        if let Some(received_at) = received_at {
+                            self.received.lock().unwrap().insert(hash, received_at);
+                        }
+                        report.replayed += 1;
+                        ("replayed", "back in the pool".to_string())
+                    }
+                    Err(e) => {
+                        report.rejected += 1;
+                        ("rejected", e.to_string())
+                    }
+                },
+            };
+            info!("♻️ Snapshot operation {:#x} {}: {}", hash, outcome, detail);
+            counter!(
+                "superrelay_pool_snapshot_ops_total",
+                "chain_id" => self.chain_id.to_string(),
+                "outcome" => outcome
+            )
+            .increment(1);
+        }
+
+        if let Err(e) = std::fs::remove_file(path) {
+            warn!(
+                "Failed to remove replayed pool snapshot {}: {}",
+                path.display(),
+                e
+            );
+        }
+        info!(
+            "♻️ Pool snapshot replayed: {} back in the pool, {} dropped, {} rejected",
+            report.replayed, report.dropped, report.rejected
+        );
+        report
+    }
+
+    /// Snapshot at `path` if it is readable, of this chain and recent enough
+    fn load(&self, path: &Path, now: u64) -> Option<PoolSnapshot> {
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!("No pool snapshot at {}", path.display());
+                return None;
+            }
+            Err(e) => {
+                warn!(
+                    "Pool snapshot {} skipped, unreadable: {}",
+                    path.display(),
+                    e
+                );
+                return None;
+            }
+        };
+        let snapshot: PoolSnapshot = match serde_json::from_slice(&data) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                warn!("Pool snapshot {} skipped, corrupt: {}", path.display(), e);
+                return None;
+            }
+        };
+        let age = now.saturating_sub(snapshot.saved_at);
+        let skip = if snapshot.version != SNAPSHOT_VERSION {
+            Some(format!("version {} is not supported", snapshot.version))
+        } else if snapshot.chain_id != self.chain_id {
+            Some(format!("written for chain {}", snapshot.chain_id))
+        } else if age > self.config.max_snapshot_age_seconds {
+            Some(format!(
+                "{}s old, older than {}s",
+                age, self.config.max_snapshot_age_seconds
+            ))
+        } else {
+            None
+        };
+        if let Some(reason) = skip {
+            warn!("Pool snapshot {} skipped, {}", path.display(), reason);
+            return None;
+        }
+        Some(snapshot)
+    }
+
+    /// The operation and permissions of `op` if it is still worth re-adding,
+    /// otherwise why not
+    async fn check(
+        &self,
+        op: SnapshotOp,
+        supported: &[Address],
+        now: u64,
+    ) -> Result<(UserOperationVariant, UserOperationPermissions), String> {
+        let user_op: UserOperationVariant = serde_json::from_value(op.user_operation)
+            .map_err(|e| format!("unreadable operation: {}", e))?;
+        if !supported.contains(&op.entry_point) {
+            return Err(format!(
+                "entry point {:#x} is no longer supported",
+                op.entry_point
+            ));
+        }
+        if op.valid_until < now {
+            return Err(format!("expired at {}", op.valid_until));
+        }
+        if let Some(reader) = &self.nonce_reader {
+            let key = user_op.nonce() >> 64;
+            match reader
+                .get_nonce(op.entry_point, user_op.sender(), key)
+                .await
+            {
+                Ok(next) if user_op.nonce() < next => {
+                    return Err(format!("nonce already used, next is {:#x}", next))
+                }
+                Ok(_) => {}
+                // Left to the pool's own validation
+                Err(e) => debug!("Nonce of snapshot operation not checked: {}", e),
+            }
+        }
+        Ok((user_op, op.permissions.into()))
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::Path,
+        sync::Arc,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use alloy_primitives::{Address, B256, U256};
+    use async_trait::async_trait;
+    use rundler_types::{
+        chain::ChainSpec,
+        da::DAGasData,
+        pool::{MockPool, PoolOperation},
+        v0_6, BundlerSponsorship, EntityInfos, Timestamp, UserOperation, UserOperationPermissions,
+        UserOperationVariant, ValidTimeRange,
+    };
+    use serde_json::Value;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    const CHAIN_ID: u64 = 31337;
+    const ENTRY_POINT_A: Address = Address::repeat_byte(0xa0);
+    const ENTRY_POINT_B: Address = Address::repeat_byte(0xb0);
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn pool_op(
+        entry_point: Address,
+        sender_byte: u8,
+        nonce: u64,
+        valid_until: u64,
+    ) -> PoolOperation {
+        PoolOperation {
+            uo: UserOperationVariant::V0_6(
+                v0_6::UserOperationBuilder::new(
+                    &ChainSpec::default(),
+                    v0_6::UserOperationRequiredFields {
+                        sender: Address::repeat_byte(sender_byte),
+                        nonce: U256::from(nonce),
+                        max_fee_per_gas: 2_000_000_000,
+                        max_priority_fee_per_gas: 1_000_000_000,
+                        ..Default::default()
+                    },
+                )
+                .build(),
+            ),
+            entry_point,
+            aggregator: None,
+            valid_time_range: ValidTimeRange::new(Timestamp::new(0), Timestamp::new(valid_until)),
+            expected_code_hash: B256::ZERO,
+            sim_block_hash: B256::ZERO,
+            sim_block_number: 7,
+            account_is_staked: false,
+            entity_infos: EntityInfos::default(),
+            da_gas_data: DAGasData::Empty,
+            filter_id: None,
+            perms: UserOperationPermissions::default(),
+        }
+    }
+
+    fn config(path: &Path) -> PoolSnapshotConfig {
+        PoolSnapshotConfig {
+            pool_snapshot_path: Some(path.to_path_buf()),
+            ..Default::default()
+        }
+    }
+
+    /// Every sender's next EntryPoint nonce is 1
+    struct NextNonceOne;
+
+    #[async_trait]
+    impl NonceReader for NextNonceOne {
+        async fn get_nonce(&self, _: Address, _: Address, _: U256) -> GatewayResult<U256> {
+            Ok(U256::from(1))
+        }
+    }
+
+    /// Pool serving only ENTRY_POINT_A that accepts exactly `expected`
+    fn replay_pool(expected: Vec<(UserOperationVariant, UserOperationPermissions)>) -> MockPool {
+        let mut pool = MockPool::new();
+        pool.expect_get_supported_entry_points()
+            .returning(|| Ok(vec![ENTRY_POINT_A]));
+        for (op, perms) in expected {
+            let hash = op.hash();
+            pool.expect_add_op()
+                .withf(move |added, added_perms| *added == op && *added_perms == perms)
+                .times(1)
+                .returning(move |_, _| Ok(hash));
+        }
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_pending_ops_replayed_after_restart() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pool-snapshot.json");
+        let in_an_hour = now() + 3_600;
+
+        let mut pending = pool_op(ENTRY_POINT_A, 0x11, 1, in_an_hour);
+        pending.perms = UserOperationPermissions {
+            trusted: true,
+            max_allowed_in_pool_for_sender: Some(20),
+            bundler_sponsorship: Some(BundlerSponsorship {
+                max_cost: U256::from(1_000_000),
+                valid_until: in_an_hour,
+            }),
+            ..Default::default()
+        };
+        let unbounded = pool_op(ENTRY_POINT_A, 0x12, 1, u64::MAX);
+        let expired = pool_op(ENTRY_POINT_A, 0x13, 1, now() - 10);
+        let nonce_used = pool_op(ENTRY_POINT_A, 0x14, 0, in_an_hour);
+        let other_entry_point = pool_op(ENTRY_POINT_B, 0x15, 1, in_an_hour);
+
+        let mut source = MockPool::new();
+        source
+            .expect_get_supported_entry_points()
+            .returning(|| Ok(vec![ENTRY_POINT_A, ENTRY_POINT_B]));
+        let (a_ops, b_ops) = (
+            vec![
+                pending.clone(),
+                unbounded.clone(),
+                expired.clone(),
+                nonce_used.clone(),
+            ],
+            vec![other_entry_point.clone()],
+        );
+        source
+            .expect_debug_dump_mempool()
+            .returning(move |entry_point| {
+                Ok(if entry_point == ENTRY_POINT_A {
+                    a_ops.clone()
+                } else {
+                    b_ops.clone()
+                })
+            });
+
+        // Shut down with operations pending
+        let before = PoolSnapshotter::new(CHAIN_ID, config(&path));
+        before.shutdown(Some(&source)).await;
+        before.wait_finished().await;
+        assert!(path.exists());
+
+        // Start again: only the operations still worth bundling reach the pool
+        let after =
+            PoolSnapshotter::new(CHAIN_ID, config(&path)).with_nonce_reader(Arc::new(NextNonceOne));
+        let pool = replay_pool(vec![
+            (pending.uo.clone(), pending.perms.clone()),
+            (unbounded.uo.clone(), unbounded.perms.clone()),
+        ]);
+        let report = after.replay(&pool).await;
+
+        assert_eq!(
+            report,
+            ReplayReport {
+                replayed: 2,
+                dropped: 3,
+                rejected: 0,
+            }
+        );
+        // Replayed once only
+        assert!(!path.exists());
+        assert_eq!(
+            after.replay(&MockPool::new()).await,
+            ReplayReport::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_capped_at_max_ops() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pool-snapshot.json");
+        let ops: Vec<_> = (0..5)
+            .map(|i| pool_op(ENTRY_POINT_A, 0x20 + i, 1, u64::MAX))
+            .collect();
+
+        let mut source = MockPool::new();
+        source
+            .expect_get_supported_entry_points()
+            .returning(|| Ok(vec![ENTRY_POINT_A]));
+        let dumped = ops.clone();
+        source
+            .expect_debug_dump_mempool()
+            .returning(move |_| Ok(dumped.clone()));
+
+        let snapshotter = PoolSnapshotter::new(
+            CHAIN_ID,
+            PoolSnapshotConfig {
+                max_snapshot_ops: 2,
+                ..config(&path)
+            },
+        );
+        snapshotter.shutdown(Some(&source)).await;
+
+        let snapshot: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        let hashes: Vec<B256> = snapshot["ops"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|op| serde_json::from_value(op["userOpHash"].clone()).unwrap())
+            .collect();
+        assert_eq!(hashes, vec![ops[0].uo.hash(), ops[1].uo.hash()]);
+    }
+
+    #[tokio::test]
+    async fn test_unusable_snapshots_skipped() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pool-snapshot.json");
+
+        // No snapshot yet, and a pool that must not be touched
+        let snapshotter = PoolSnapshotter::new(CHAIN_ID, config(&path));
+        assert_eq!(
+            snapshotter.replay(&MockPool::new()).await,
+            ReplayReport::default()
+        );
+
+        std::fs::write(&path, b"{\"version\":1,\"ops\":[").unwrap();
+        assert_eq!(
+            snapshotter.replay(&MockPool::new()).await,
+            ReplayReport::default()
+        );
+
+        // A valid snapshot written for another chain, then made stale
+        let mut source = MockPool::new();
+        source
+            .expect_get_supported_entry_points()
+            .returning(|| Ok(vec![ENTRY_POINT_A]));
+        source
+            .expect_debug_dump_mempool()
+            .returning(|_| Ok(vec![pool_op(ENTRY_POINT_A, 0x31, 1, u64::MAX)]));
+        PoolSnapshotter::new(1, config(&path))
+            .shutdown(Some(&source))
+            .await;
+        assert_eq!(
+            snapshotter.replay(&MockPool::new()).await,
+            ReplayReport::default()
+        );
+
+        let mut snapshot: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        snapshot["chainId"] = CHAIN_ID.into();
+        snapshot["savedAt"] = (now() - 601).into();
+        std::fs::write(&path, serde_json::to_vec(&snapshot).unwrap()).unwrap();
+        assert_eq!(
+            snapshotter.replay(&MockPool::new()).await,
+            ReplayReport::default()
+        );
+
+        // Skipped snapshots stay for inspection
+        assert!(path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_without_pool_writes_nothing() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pool-snapshot.json");
+
+        let snapshotter = PoolSnapshotter::new(CHAIN_ID, config(&path));
+        snapshotter.shutdown::<MockPool>(None).await;
+        snapshotter.wait_finished().await;
+        assert!(!path.exists());
+
+        let per_chain = config(&path).for_chain(10);
+        assert_eq!(
+            per_chain.pool_snapshot_path.unwrap(),
+            dir.path().join("pool-snapshot.10.json")
+        );
+    }
+}
@@ -0,0 +1,672 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use alloy_primitives::{Address, B256};
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+use futures_util::{future::BoxFuture, Stream};
+use metrics::{counter, gauge};
+use rundler_pool::LocalPoolHandle;
+use rundler_task::server::{HealthCheck, ServerStatus};
+use rundler_types::{
+    pool::{
+        NewHead, PaymasterMetadata, Pool, PoolError, PoolOperation, PoolOperationSummary,
+        PoolResult, Reputation, ReputationStatus, StakeStatus,
+    },
+    EntityUpdate, UserOperationId, UserOperationPermissions, UserOperationVariant,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::{
+    error::{GatewayError, GatewayResult},
+    pool_snapshot::PoolSnapshotter,
+    shutdown::ShutdownController,
+};
+
+/// Pool restart settings (`[pool.supervisor]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PoolSupervisorConfig {
+    /// Delay before the first restart after the pool task stops
+    pub initial_backoff_ms: u64,
+    /// Longest delay between restarts; a pool that stayed up this long starts
+    /// over at `initial_backoff_ms` when it next stops
+    pub max_backoff_ms: u64,
+    /// Consecutive failed restarts before giving up, unset to retry forever
+    pub max_restart_attempts: Option<u32>,
+}
+
+impl Default for PoolSupervisorConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            max_restart_attempts: None,
+        }
+    }
+}
+
+impl PoolSupervisorConfig {
+    /// Delay before restart number `attempt` (0-based)
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .initial_backoff_ms
+            .saturating_mul(1u64 << attempt.min(16))
+            .min(self.max_backoff_ms);
+        Duration::from_millis(delay)
+    }
+}
+
+/// Lifecycle of the supervised pool task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolState {
+    /// The pool task is running and serves requests
+    Running,
+    /// The pool task stopped and is being restarted
+    Restarting,
+    /// Restarts were given up, or the process is shutting down
+    Stopped,
+}
+
+/// Supervision state reported on /ready
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolStatus {
+    /// Current lifecycle state
+    pub state: PoolState,
+    /// Restarts attempted since the process started
+    pub restarts: u64,
+    /// Why the pool task last stopped
+    pub last_exit: Option<String>,
+}
+
+/// A freshly started pool
+pub struct LaunchedPool {
+    /// Handle of the new pool server
+    pub handle: LocalPoolHandle,
+    /// Resolves with the reason once the pool task stops
+    pub exited: BoxFuture<'static, String>,
+}
+
+/// Starts a pool task with its own request channel and chain subscription
+#[async_trait]
+pub trait PoolLauncher: Send + Sync {
+    /// Start a new pool, e.g. after the previous one stopped
+    async fn launch(&self) -> Result<LaunchedPool, String>;
+}
+
+/// Pool handle that follows restarts of the pool task
+///
+/// Requests read the current handle on every call and fail fast while the
+/// pool is restarting, instead of waiting on a channel nobody serves.
+#[derive(Clone)]
+pub struct SupervisedPool {
+    handle: Arc<ArcSwap<LocalPoolHandle>>,
+    status: Arc<Mutex<PoolStatus>>,
+}
+
+impl From<Arc<LocalPoolHandle>> for SupervisedPool {
+    /// Handle of a pool whose task is managed elsewhere, always reported as running
+    fn from(handle: Arc<LocalPoolHandle>) -> Self {
+        Self {
+            handle: Arc::new(ArcSwap::new(handle)),
+            status: Arc::new(Mutex::new(PoolStatus {
+                state: PoolState::Running,
+                restarts: 0,
+                last_exit: None,
+            })),
+        }
+    }
+}
+
+impl SupervisedPool {
+    /// Handle of the running pool
+    pub fn current(&self) -> GatewayResult<Arc<LocalPoolHandle>> {
+        match self.status.lock().unwrap().state {
+            PoolState::Running => Ok(self.handle.load_full()),
+            PoolState::Restarting => Err(GatewayError::PoolError(
+                "pool unavailable, restarting".to_string(),
+            )),
+            PoolState::Stopped => Err(GatewayError::PoolError(
+                "pool unavailable, restarts stopped".to_string(),
+            )),
+        }
+    }
+
+    /// Supervision state
+    pub fn status(&self) -> PoolStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    fn pool(&self) -> PoolResult<Arc<LocalPoolHandle>> {
+        self.current()
+            .map_err(|e| PoolError::Other(anyhow::anyhow!(e.to_string())))
+    }
+
+    fn set_state(&self, state: PoolState) {
+        self.status.lock().unwrap().state = state;
+    }
+}
+
+/// Restarts the pool task with backoff whenever it stops
+pub struct PoolSupervisor {
+    launcher: Arc<dyn PoolLauncher>,
+    config: PoolSupervisorConfig,
+    chain_id: u64,
+    snapshot: Option<Arc<PoolSnapshotter>>,
+}
+
+impl PoolSupervisor {
+    /// Supervise pools started by `launcher` for chain `chain_id`
+    pub fn new(
+        launcher: Arc<dyn PoolLauncher>,
+        config: PoolSupervisorConfig,
+        chain_id: u64,
+    ) -> Self {
+        Self {
+            launcher,
+            config,
+            chain_id,
+            snapshot: None,
+        }
+    }
+
+    /// Replay `snapshot` into the first pool, and write the running pool's
+    /// operations to it on shutdown before the pool task is dropped
+    pub fn with_snapshot(mut self, snapshot: Arc<PoolSnapshotter>) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
+    /// Let the snapshot write `pool`, the running pool at shutdown if any
+    async fn finish_snapshot(&self, pool: Option<&LocalPoolHandle>) {
+        if let Some(snapshot) = &self.snapshot {
+            snapshot.shutdown(pool).await;
+        }
+    }
+
+    /// Record `state` in the pool status and the `superrelay_pool_up` gauge
+    fn set_state(&self, pool: &SupervisedPool, state: PoolState) {
+        pool.set_state(state);
+        let up = if state == PoolState::Running {
+            1.0
+        } else {
+            0.0
+        };
+        gauge!("superrelay_pool_up", "chain_id" => self.chain_id.to_string()).set(up);
+    }
+
+    /// Launch the pool and supervise it until `shutdown` triggers
+    ///
+    /// Fails if the first launch fails; later failures are retried in the background.
+    pub async fn start(self, shutdown: ShutdownController) -> GatewayResult<SupervisedPool> {
+        let launched = self
+            .launcher
+            .launch()
+            .await
+            .map_err(|e| GatewayError::PoolError(format!("Failed to start pool: {}", e)))?;
+        let pool = SupervisedPool::from(Arc::new(launched.handle));
+        self.set_state(&pool, PoolState::Running);
+        info!("✅ Pool task started under supervision");
+
+        // Replayed in the background, a bad snapshot never holds up startup
+        if let Some(snapshot) = self.snapshot.clone() {
+            let pool = pool.clone();
+            tokio::spawn(async move { snapshot.replay(&pool).await });
+        }
+
+        tokio::spawn(self.supervise(pool.clone(), launched.exited, shutdown));
+        Ok(pool)
+    }
+
+    async fn supervise(
+        self,
+        pool: SupervisedPool,
+        mut exited: BoxFuture<'static, String>,
+        shutdown: ShutdownController,
+    ) {
+        let mut attempt = 0u32;
+        loop {
+            let started = Instant::now();
+            let reason = tokio::select! {
+                reason = &mut exited => reason,
+                _ = shutdown.wait() => {
+                    let running = pool.handle.load_full();
+                    self.finish_snapshot(Some(running.as_ref())).await;
+                    self.set_state(&pool, PoolState::Stopped);
+                    return;
+                }
+            };
+            error!("❌ Pool task stopped unexpectedly: {}", reason);
+            pool.status.lock().unwrap().last_exit = Some(reason);
+            self.set_state(&pool, PoolState::Restarting);
+
+            // A pool that stayed up is not crash looping
+            if started.elapsed() >= Duration::from_millis(self.config.max_backoff_ms) {
+                attempt = 0;
+            }
+
+            let mut failures = 0u32;
+            exited = loop {
+                if self
+                    .config
+                    .max_restart_attempts
+                    .is_some_and(|max| failures >= max)
+                {
+                    error!(
+                        "❌ Pool failed to restart {} times, giving up; requests fail until the process restarts",
+                        failures
+                    );
+                    self.set_state(&pool, PoolState::Stopped);
+                    self.finish_snapshot(None).await;
+                    return;
+                }
+
+                let delay = self.config.backoff(attempt);
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = shutdown.wait() => {
+                        self.set_state(&pool, PoolState::Stopped);
+                        self.finish_snapshot(None).await;
+                        return;
+                    }
+                }
+                attempt = attempt.saturating_add(1);
+                let restarts = {
+                    let mut status = pool.status.lock().unwrap();
+                    status.restarts += 1;
+                    status.restarts
+                };
+                counter!("superrelay_pool_restarts_total", "chain_id" => self.chain_id.to_string())
+                    .increment(1);
+
+                match self.launcher.launch().await {
+                    Ok(launched) => {
+                        pool.handle.store(Arc::new(launched.handle));
+                        self.set_state(&pool, PoolState::Running);
+                        info!("✅ Pool task restarted (restart {})", restarts);
+                        break launched.exited;
+                    }
+                    Err(e) => {
+                        failures += 1;
+                        warn!("Pool restart {} failed: {}", restarts, e);
+                        pool.status.lock().unwrap().last_exit = Some(e);
+                    }
+                }
+            };
+        }
+    }
+}
+
+#[async_trait]
+impl Pool for SupervisedPool {
+    async fn get_supported_entry_points(&self) -> PoolResult<Vec<Address>> {
+        self.pool()?.get_supported_entry_points().await
+    }
+
+    async fn add_op(
+        &self,
+        op: UserOperationVariant,
+        perms: UserOperationPermissions,
+    ) -> PoolResult<B256> {
+        self.pool()?.add_op(op, perms).await
+    }
+
+    async fn get_ops(
+        &self,
+        entry_point: Address,
+        max_ops: u64,
+        filter_id: Option<String>,
+    ) -> PoolResult<Vec<PoolOperation>> {
+        self.pool()?.get_ops(entry_point, max_ops, filter_id).await
+    }
+
+    async fn get_ops_summaries(
+        &self,
+        entry_point: Address,
+        max_ops: u64,
+        filter_id: Option<String>,
+    ) -> PoolResult<Vec<PoolOperationSummary>> {
+        self.pool()?
+            .get_ops_summaries(entry_point, max_ops, filter_id)
+            .await
+    }
+
+    async fn get_ops_by_hashes(
+        &self,
+        entry_point: Address,
+        hashes: Vec<B256>,
+    ) -> PoolResult<Vec<PoolOperation>> {
+        self.pool()?.get_ops_by_hashes(entry_point, hashes).await
+    }
+
+    async fn get_op_by_hash(&self, hash: B256) -> PoolResult<Option<PoolOperation>> {
+        self.pool()?.get_op_by_hash(hash).await
+    }
+
+    async fn get_op_by_id(&self, id: UserOperationId) -> PoolResult<Option<PoolOperation>> {
+        self.pool()?.get_op_by_id(id).await
+    }
+
+    async fn remove_ops(&self, entry_point: Address, ops: Vec<B256>) -> PoolResult<()> {
+        self.pool()?.remove_ops(entry_point, ops).await
+    }
+
+    async fn remove_op_by_id(
+        &self,
+        entry_point: Address,
+        id: UserOperationId,
+    ) -> PoolResult<Option<B256>> {
+        self.pool()?.remove_op_by_id(entry_point, id).await
+    }
+
+    async fn update_entities(
+        &self,
+        entry_point: Address,
+        entities: Vec<EntityUpdate>,
+    ) -> PoolResult<()> {
+        self.pool()?.update_entities(entry_point, entities).await
+    }
+
+    async fn subscribe_new_heads(
+        &self,
+        to_track: Vec<Address>,
+    ) -> PoolResult<Pin<Box<dyn Stream<Item = NewHead> + Send>>> {
+        self.pool()?.subscribe_new_heads(to_track).await
+    }
+
+    async fn get_reputation_status(
+        &self,
+        entry_point: Address,
+        address: Address,
+    ) -> PoolResult<ReputationStatus> {
+        self.pool()?
+            .get_reputation_status(entry_point, address)
+            .await
+    }
+
+    async fn get_stake_status(
+        &self,
+        entry_point: Address,
+        address: Address,
+    ) -> PoolResult<StakeStatus> {
+        self.pool()?.get_stake_status(entry_point, address).await
+    }
+
+    async fn debug_clear_state(
+        &self,
+        clear_mempool: bool,
+        clear_paymaster: bool,
+        clear_reputation: bool,
+    ) -> PoolResult<()> {
+        self.pool()?
+            .debug_clear_state(clear_mempool, clear_paymaster, clear_reputation)
+            .await
+    }
+
+    async fn debug_dump_mempool(&self, entry_point: Address) -> PoolResult<Vec<PoolOperation>> {
+        self.pool()?.debug_dump_mempool(entry_point).await
+    }
+
+    async fn debug_set_reputations(
+        &self,
+        entry_point: Address,
+        reputations: Vec<Reputation>,
+    ) -> PoolResult<()> {
+        self.pool()?
+            .debug_set_reputations(entry_point, reputations)
+            .await
+    }
+
+    async fn debug_dump_reputation(&self, entry_point: Address) -> PoolResult<Vec<Reputation>> {
+        self.pool()?.debug_dump_reputation(entry_point).await
+    }
+
+    async fn debug_dump_paymaster_balances(
+        &self,
+        entry_point: Address,
+    ) -> PoolResult<Vec<PaymasterMetadata>> {
+        self.pool()?
+            .debug_dump_paymaster_balances(entry_point)
+            .await
+    }
+
+    async fn admin_set_tracking(
+        &self,
+        entry_point: Address,
+        paymaster: bool,
+        reputation: bool,
+    ) -> PoolResult<()> {
+        self.pool()?
+            .admin_set_tracking(entry_point, paymaster, reputation)
+            .await
+    }
+}
+
+#[async_trait]
+impl HealthCheck for SupervisedPool {
+    fn name(&self) -> &'static str {
+        "SupervisedPool"
+    }
+
+    async fn status(&self) -> ServerStatus {
+        match self.current() {
+            Ok(pool) => pool.status().await,
+            Err(_) => ServerStatus::NotServing,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use futures_util::FutureExt;
+    use rundler_pool::LocalPoolBuilder;
+    use rundler_types::pool::Pool;
+    use tokio::sync::oneshot;
+
+    use super::*;
+    use crate::{HealthProbe, PoolProbe};
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let config = PoolSupervisorConfig {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1_000,
+            max_restart_attempts: None,
+        };
+        let delays: Vec<_> = (0..6).map(|attempt| config.backoff(attempt)).collect();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1_000, 1_000].map(Duration::from_millis)
+        );
+        assert_eq!(config.backoff(u32::MAX), Duration::from_millis(1_000));
+    }
+
+    /// Launcher whose pools stop when `kill` is called
+    ///
+    /// The handles come from dropped builders, so requests reach the pool channel
+    /// and fail immediately instead of hanging.
+    #[derive(Default)]
+    struct FakeLauncher {
+        launches: AtomicU32,
+        /// Launches after the first one that fail
+        failing_restarts: AtomicU32,
+        kill_switches: Mutex<Vec<oneshot::Sender<()>>>,
+    }
+
+    impl FakeLauncher {
+        fn kill(&self) {
+            let switch = self.kill_switches.lock().unwrap().pop().unwrap();
+            switch.send(()).unwrap();
+        }
+    }
+
+    #[async_trait]
+    impl PoolLauncher for FakeLauncher {
+        async fn launch(&self) -> Result<LaunchedPool, String> {
+            let launch = self.launches.fetch_add(1, Ordering::SeqCst);
+            if launch > 0
+                && self
+                    .failing_restarts
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                    .is_ok()
+            {
+                return Err("node unreachable".to_string());
+            }
+
+            let (kill, killed) = oneshot::channel::<()>();
+            self.kill_switches.lock().unwrap().push(kill);
+            Ok(LaunchedPool {
+                handle: LocalPoolBuilder::new(10).get_handle(),
+                exited: killed.map(|_| "pool task panicked".to_string()).boxed(),
+            })
+        }
+    }
+
+    fn config(max_restart_attempts: Option<u32>) -> PoolSupervisorConfig {
+        PoolSupervisorConfig {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1_000,
+            max_restart_attempts,
+        }
+    }
+
+    async fn start(
+        launcher: &Arc<FakeLauncher>,
+        max_restart_attempts: Option<u32>,
+        shutdown: ShutdownController,
+    ) -> SupervisedPool {
+        PoolSupervisor::new(launcher.clone(), config(max_restart_attempts), 31337)
+            .start(shutdown)
+            .await
+            .unwrap()
+    }
+
+    async fn wait_for_state(pool: &SupervisedPool, state: PoolState) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while pool.status().state != state {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("pool never reached {:?}", state));
+    }
+
+    /// Error of a request that reached a pool handle
+    async fn pool_request_error(pool: &SupervisedPool) -> String {
+        pool.get_supported_entry_points()
+            .await
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_pool_restarts_after_task_exit() {
+        let launcher = Arc::new(FakeLauncher::default());
+        let pool = start(&launcher, None, ShutdownController::new()).await;
+        assert_eq!(pool.status().state, PoolState::Running);
+        assert!(pool.current().is_ok());
+        assert!(!pool_request_error(&pool).await.contains("restarting"));
+
+        launcher.kill();
+        wait_for_state(&pool, PoolState::Restarting).await;
+
+        // Requests fail fast instead of waiting on the dead pool
+        assert_eq!(
+            pool.current().unwrap_err().to_string(),
+            "Pool error: pool unavailable, restarting"
+        );
+        assert!(pool_request_error(&pool)
+            .await
+            .contains("pool unavailable, restarting"));
+        let probe = PoolProbe::new(pool.clone());
+        assert!(probe
+            .check()
+            .await
+            .unwrap_err()
+            .contains("pool unavailable, restarting"));
+        assert_eq!(probe.details().unwrap()["state"], "restarting");
+        assert_eq!(probe.details().unwrap()["last_exit"], "pool task panicked");
+
+        wait_for_state(&pool, PoolState::Running).await;
+        let status = pool.status();
+        assert_eq!(status.restarts, 1);
+        assert_eq!(launcher.launches.load(Ordering::SeqCst), 2);
+        // Requests reach the new pool again
+        assert!(!pool_request_error(&pool).await.contains("restarting"));
+        assert_eq!(probe.details().unwrap()["state"], "running");
+    }
+
+    #[tokio::test]
+    async fn test_failed_restarts_are_retried() {
+        let launcher = Arc::new(FakeLauncher::default());
+        launcher.failing_restarts.store(2, Ordering::SeqCst);
+        let pool = start(&launcher, None, ShutdownController::new()).await;
+
+        launcher.kill();
+        wait_for_state(&pool, PoolState::Restarting).await;
+        wait_for_state(&pool, PoolState::Running).await;
+        assert_eq!(pool.status().restarts, 3);
+        assert_eq!(launcher.launches.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_gives_up_after_max_attempts() {
+        let launcher = Arc::new(FakeLauncher::default());
+        launcher.failing_restarts.store(u32::MAX, Ordering::SeqCst);
+        let pool = start(&launcher, Some(2), ShutdownController::new()).await;
+
+        launcher.kill();
+        wait_for_state(&pool, PoolState::Stopped).await;
+        let status = pool.status();
+        assert_eq!(status.restarts, 2);
+        assert_eq!(status.last_exit.as_deref(), Some("node unreachable"));
+        assert_eq!(
+            pool.current().unwrap_err().to_string(),
+            "Pool error: pool unavailable, restarts stopped"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_first_launch_failure_is_an_error() {
+        struct Unreachable;
+
+        #[async_trait]
+        impl PoolLauncher for Unreachable {
+            async fn launch(&self) -> Result<LaunchedPool, String> {
+                Err("node unreachable".to_string())
+            }
+        }
+
+        let result = PoolSupervisor::new(Arc::new(Unreachable), config(None), 31337)
+            .start(ShutdownController::new())
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_supervision() {
+        let launcher = Arc::new(FakeLauncher::default());
+        let shutdown = ShutdownController::new();
+        let pool = start(&launcher, None, shutdown.clone()).await;
+
+        shutdown.trigger();
+        wait_for_state(&pool, PoolState::Stopped).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(launcher.launches.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.status().restarts, 0);
+    }
+}
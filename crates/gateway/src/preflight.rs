@@ -0,0 +1,410 @@
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+use crate::{
+    chains::ChainRegistry,
+    gateway::JsonRpcRequest,
+    health::{ComponentStatus, HealthChecker, ProbeReport},
+};
+
+/// Methods answered once per chain so their caches are filled before traffic arrives
+const WARM_UP_METHODS: &[&str] = &[
+    "eth_chainId",
+    "eth_supportedEntryPoints",
+    "superRelay_getUserOperationGasPrice",
+];
+
+/// Startup checks run before the public listener binds (`[gateway.preflight]`)
+///
+/// Readiness probes are retried until every critical one passes or the
+/// timeout ends, so orchestrators never route to an instance whose node,
+/// policy or signer is not usable yet.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct PreflightConfig {
+    /// Probe dependencies before binding; `--skip-preflight` turns this off
+    pub enabled: bool,
+    /// Time every critical probe has to pass, in seconds
+    pub timeout_seconds: u64,
+    /// Delay between probe rounds while a critical probe fails, in milliseconds
+    pub retry_interval_ms: u64,
+    /// Answer chain id, supported EntryPoints and gas price once per chain
+    /// after the probes pass, filling their caches
+    pub warm_caches: bool,
+}
+
+impl Default for PreflightConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            timeout_seconds: 60,
+            retry_interval_ms: 1_000,
+            warm_caches: true,
+        }
+    }
+}
+
+/// Outcome of the startup checks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightReport {
+    /// Whether every critical probe passed within the timeout
+    pub passed: bool,
+    /// Probe rounds run
+    pub attempts: u32,
+    /// Time spent probing, in milliseconds
+    pub elapsed_ms: u64,
+    /// Results of the last probe round
+    pub checks: Vec<ProbeReport>,
+    /// Warm-up calls that failed, as `chain id/method: error`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warm_up_failures: Vec<String>,
+}
+
+impl PreflightReport {
+    /// Critical checks that failed in the last round
+    pub fn failed_checks(&self) -> impl Iterator<Item = &ProbeReport> {
+        self.checks.iter().filter(|check| blocks_readiness(check))
+    }
+}
+
+impl fmt::Display for PreflightReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failed: Vec<String> = self
+            .failed_checks()
+            .map(|check| {
+                format!(
+                    "{} ({})",
+                    check.name,
+                    check.health.error.as_deref().unwrap_or("failed")
+                )
+            })
+            .collect();
+        if failed.is_empty() && !self.passed {
+            return write!(f, "probes did not finish within {}ms", self.elapsed_ms);
+        }
+        write!(
+            f,
+            "{} of {} checks failed after {} attempts in {}ms: {}",
+            failed.len(),
+            self.checks.len(),
+            self.attempts,
+            self.elapsed_ms,
+            failed.join(", ")
+        )
+    }
+}
+
+/// Probe `health` until every critical probe passes or `config`'s timeout
+/// ends, then warm the caches of every chain in `chains`
+pub async fn run_preflight(
+    config: &PreflightConfig,
+    health: &HealthChecker,
+    chains: &ChainRegistry,
+) -> PreflightReport {
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(config.timeout_seconds);
+    let retry_interval = Duration::from_millis(config.retry_interval_ms);
+
+    let mut attempts = 0;
+    let mut last_checks = Vec::new();
+    let passed = loop {
+        attempts += 1;
+        // A round still running at the deadline fails the preflight
+        match tokio::time::timeout_at(deadline, health.probe_now()).await {
+            Ok(checks) => last_checks = checks,
+            Err(_) => break false,
+        }
+        let failed: Vec<&str> = last_checks
+            .iter()
+            .filter(|check| blocks_readiness(check))
+            .map(|check| check.name.as_str())
+            .collect();
+        if failed.is_empty() {
+            break true;
+        }
+        if Instant::now() + retry_interval >= deadline {
+            break false;
+        }
+        info!(
+            "⏳ Preflight attempt {} failed ({}), retrying in {:?}",
+            attempts,
+            failed.join(", "),
+            retry_interval
+        );
+        tokio::time::sleep(retry_interval).await;
+    };
+
+    let mut report = PreflightReport {
+        passed,
+        attempts,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        checks: last_checks,
+        warm_up_failures: Vec::new(),
+    };
+    if report.passed && config.warm_caches {
+        report.warm_up_failures = warm_caches(chains).await;
+    }
+    report
+}
+
+/// A failing critical probe, the same condition that fails /ready
+fn blocks_readiness(check: &ProbeReport) -> bool {
+    check.critical && check.health.status == ComponentStatus::Error
+}
+
+/// Answer the warm-up methods on every chain, returning the calls that failed
+///
+/// Failures do not fail the preflight: a chain without a gas price oracle
+/// simply has nothing to warm.
+async fn warm_caches(chains: &ChainRegistry) -> Vec<String> {
+    let mut failures = Vec::new();
+    for chain_id in chains.chain_ids() {
+        let Ok(route) = chains.select(Some(chain_id)) else {
+            continue;
+        };
+        for method in WARM_UP_METHODS {
+            let request = JsonRpcRequest {
+                id: json!(0),
+                method: method.to_string(),
+                params: Vec::new(),
+                api_key_id: None,
+                trust_level: None,
+                chain_id: Some(chain_id),
+                no_cache: false,
+                request_id: None,
+                deadline: Default::default(),
+                debug: false,
+                permissions: false,
+            };
+            let result = if method.starts_with("superRelay_") {
+                route.router.route_to_super_relay(&request).await
+            } else {
+                route.router.route_to_rundler(&request).await
+            };
+            match result {
+                Ok(_) => debug!("Warmed {} on chain {}", method, chain_id),
+                Err(e) => {
+                    warn!("Could not warm {} on chain {}: {}", method, chain_id, e);
+                    failures.push(format!("{}/{}: {}", chain_id, method, e));
+                }
+            }
+        }
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use alloy_primitives::{address, Address, Bytes};
+    use async_trait::async_trait;
+    use rundler_provider::{MockEvmProvider, ProviderError};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+    use crate::{
+        EntryPointCodeProbe, GatewayConfig, GatewayError, HealthProbe, NodeProbe, PaymasterGateway,
+    };
+
+    const ENTRY_POINT: Address = address!("0000000071727De22E5E9d8BAf0edAc6f37da032");
+
+    /// Port nothing listens on right now
+    async fn free_port() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    fn gateway(port: u16, preflight: PreflightConfig) -> PaymasterGateway {
+        PaymasterGateway::new(
+            GatewayConfig {
+                port,
+                preflight,
+                ..Default::default()
+            },
+            None,
+        )
+    }
+
+    fn quick_preflight(timeout_seconds: u64) -> PreflightConfig {
+        PreflightConfig {
+            timeout_seconds,
+            retry_interval_ms: 50,
+            ..Default::default()
+        }
+    }
+
+    /// Node whose RPC refuses every call
+    fn unreachable_node() -> MockEvmProvider {
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_block()
+            .returning(|_| Err(ProviderError::Other(anyhow::anyhow!("connection refused"))));
+        provider
+            .expect_get_code()
+            .returning(|_, _| Err(ProviderError::Other(anyhow::anyhow!("connection refused"))));
+        provider
+    }
+
+    /// Node with `code` deployed at every address
+    fn node_with_code(code: &'static [u8]) -> MockEvmProvider {
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_code()
+            .returning(move |_, _| Ok(Bytes::from_static(code)));
+        provider
+    }
+
+    /// Dependency that fails its first `failures` checks, counting every check
+    struct WarmingUpProbe {
+        failures: usize,
+        runs: AtomicUsize,
+    }
+
+    impl WarmingUpProbe {
+        fn new(failures: usize) -> Self {
+            Self {
+                failures,
+                runs: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl HealthProbe for WarmingUpProbe {
+        fn name(&self) -> &str {
+            "pool"
+        }
+
+        async fn check(&self) -> Result<(), String> {
+            let run = self.runs.fetch_add(1, Ordering::SeqCst);
+            if run < self.failures {
+                return Err("pool not responding".to_string());
+            }
+            Ok(())
+        }
+    }
+
+    /// Connect to `port` until something listens, up to `timeout`
+    async fn wait_for_listener(port: u16, timeout: Duration) -> bool {
+        tokio::time::timeout(timeout, async {
+            while TcpStream::connect(("127.0.0.1", port)).await.is_err() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_failing_provider_fails_startup() {
+        let port = free_port().await;
+        let gateway = gateway(port, quick_preflight(1))
+            .with_health_probe(Arc::new(NodeProbe::new(
+                unreachable_node(),
+                Duration::from_secs(120),
+            )))
+            .with_health_probe(Arc::new(EntryPointCodeProbe::new(
+                unreachable_node(),
+                vec![ENTRY_POINT],
+            )));
+
+        let err = gateway.start().await.unwrap_err();
+        let GatewayError::PreflightFailed(report) = &err else {
+            panic!("preflight failure expected, got {}", err);
+        };
+        assert!(!report.passed);
+        assert!(report.attempts > 1, "probes are retried until the timeout");
+        let failed: Vec<&str> = report
+            .failed_checks()
+            .map(|check| check.name.as_str())
+            .collect();
+        assert_eq!(failed, ["node", "entry_point_code"]);
+        assert!(report.failed_checks().all(|check| check
+            .health
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("node RPC unreachable")));
+
+        let message = err.to_string();
+        assert!(message.contains("2 of 2 checks failed"), "{}", message);
+        assert!(
+            message.contains("node (node RPC unreachable"),
+            "{}",
+            message
+        );
+
+        // Nothing was bound
+        TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_entry_point_code_fails_startup() {
+        let gateway = gateway(free_port().await, quick_preflight(1)).with_health_probe(Arc::new(
+            EntryPointCodeProbe::new(node_with_code(b""), vec![ENTRY_POINT]),
+        ));
+
+        let Err(GatewayError::PreflightFailed(report)) = gateway.start().await else {
+            panic!("preflight failure expected");
+        };
+        let check = report.failed_checks().next().unwrap();
+        assert_eq!(
+            check.health.error.as_deref(),
+            Some(format!("no contract code at EntryPoint {}", ENTRY_POINT).as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_listener_binds_only_after_probes_pass() {
+        let port = free_port().await;
+        let probe = Arc::new(WarmingUpProbe::new(3));
+        let gateway = gateway(port, quick_preflight(10))
+            .with_health_probe(probe.clone())
+            .with_health_probe(Arc::new(EntryPointCodeProbe::new(
+                node_with_code(&[0x60, 0x80]),
+                vec![ENTRY_POINT],
+            )));
+        let shutdown = gateway.shutdown_handle();
+        let server = tokio::spawn(gateway.start());
+
+        assert!(wait_for_listener(port, Duration::from_secs(5)).await);
+        assert_eq!(probe.runs.load(Ordering::SeqCst), 4);
+
+        shutdown.trigger();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_skipped_preflight_binds_immediately() {
+        let port = free_port().await;
+        let probe = Arc::new(WarmingUpProbe::new(usize::MAX));
+        let gateway = gateway(
+            port,
+            PreflightConfig {
+                enabled: false,
+                ..Default::default()
+            },
+        )
+        .with_health_probe(probe.clone());
+        let shutdown = gateway.shutdown_handle();
+        let server = tokio::spawn(gateway.start());
+
+        assert!(wait_for_listener(port, Duration::from_secs(5)).await);
+        assert_eq!(probe.runs.load(Ordering::SeqCst), 0);
+
+        shutdown.trigger();
+        server.await.unwrap().unwrap();
+    }
+}
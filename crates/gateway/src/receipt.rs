@@ -0,0 +1,388 @@
+use std::sync::Arc;
+
+use alloy_primitives::{Address, B256};
+use alloy_sol_types::SolEvent;
+use async_trait::async_trait;
+use rundler_contracts::v0_7::IEntryPoint::{
+    BeforeExecution, UserOperationEvent, UserOperationRevertReason,
+};
+use rundler_paymaster_relay::{MinedUserOperation, UserOperationEventSource};
+use rundler_provider::{EvmProvider, Filter, Log, TransactionReceipt};
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::error::{GatewayError, GatewayResult};
+
+/// Source of mined UserOperation receipts
+///
+/// Kept object safe so the router can hold any implementation behind an `Arc`.
+#[async_trait]
+pub trait UserOperationReceiptProvider: Send + Sync {
+    /// Look up the receipt of a mined UserOperation, returning `None` if no
+    /// `UserOperationEvent` exists for the hash
+    async fn get_user_operation_receipt(&self, hash: B256) -> GatewayResult<Option<Value>>;
+}
+
+/// Receipt provider that reconstructs receipts from EntryPoint event logs
+///
+/// `UserOperationEvent`, `UserOperationRevertReason` and `BeforeExecution` share the
+/// same signature across EntryPoint v0.6 and v0.7, so a single query covers every
+/// configured EntryPoint.
+pub struct EvmReceiptProvider<P> {
+    provider: P,
+    entry_points: Vec<Address>,
+    event_block_distance: Option<u64>,
+}
+
+impl<P: EvmProvider> EvmReceiptProvider<P> {
+    /// Create a new receipt provider searching the given EntryPoints
+    ///
+    /// `event_block_distance` limits how far back from the latest block logs are
+    /// searched; `None` searches from genesis.
+    pub fn new(provider: P, entry_points: Vec<Address>, event_block_distance: Option<u64>) -> Self {
+        Self {
+            provider,
+            entry_points,
+            event_block_distance,
+        }
+    }
+
+    async fn get_event_by_hash(&self, hash: B256) -> GatewayResult<Option<Log>> {
+        let to_block = self.provider.get_block_number().await.map_err(|e| {
+            GatewayError::RundlerError(format!("Failed to get block number: {}", e))
+        })?;
+        let from_block = match self.event_block_distance {
+            Some(distance) => to_block.saturating_sub(distance),
+            None => 0,
+        };
+
+        let filter = Filter::new()
+            .address(self.entry_points.clone())
+            .event_signature(UserOperationEvent::SIGNATURE_HASH)
+            .from_block(from_block)
+            .to_block(to_block)
+            .topic1(hash);
+
+        let logs = self
+            .provider
+            .get_logs(&filter)
+            .await
+            .map_err(|e| GatewayError::RundlerError(format!("Failed to query logs: {}", e)))?;
+
+        Ok(logs.into_iter().next())
+    }
+
+    fn construct_receipt(
+        &self,
+        event: Log,
+        tx_receipt: TransactionReceipt,
+    ) -> GatewayResult<Value> {
+        let entry_point = event.address();
+        let logs = filter_receipt_logs_matching_user_op(&event, &tx_receipt)?;
+
+        let uo_event = event
+            .log_decode::<UserOperationEvent>()
+            .map(|l| l.inner.data)
+            .map_err(|e| {
+                GatewayError::InternalError(format!("Failed to decode UserOperationEvent: {}", e))
+            })?;
+
+        // Pick up the revert reason emitted alongside a failed operation
+        let reason = if uo_event.success {
+            String::new()
+        } else {
+            logs.iter()
+                .filter(|l| l.topics().len() > 1 && l.topics()[1] == uo_event.userOpHash)
+                .find_map(|l| {
+                    l.log_decode::<UserOperationRevertReason>()
+                        .map(|l| l.inner.data.revertReason.to_string())
+                        .ok()
+                })
+                .unwrap_or_default()
+        };
+
+        let logs_json = serde_json::to_value(&logs).map_err(|e| {
+            GatewayError::InternalError(format!("Failed to serialize receipt logs: {}", e))
+        })?;
+        let receipt_json = serde_json::to_value(&tx_receipt).map_err(|e| {
+            GatewayError::InternalError(format!("Failed to serialize transaction receipt: {}", e))
+        })?;
+
+        Ok(json!({
+            "userOpHash": format!("{:#x}", uo_event.userOpHash),
+            "entryPoint": format!("{:#x}", entry_point),
+            "sender": format!("{:#x}", uo_event.sender),
+            "nonce": format!("0x{:x}", uo_event.nonce),
+            "paymaster": format!("{:#x}", uo_event.paymaster),
+            "actualGasCost": format!("0x{:x}", uo_event.actualGasCost),
+            "actualGasUsed": format!("0x{:x}", uo_event.actualGasUsed),
+            "success": uo_event.success,
+            "reason": reason,
+            "logs": logs_json,
+            "receipt": receipt_json
+        }))
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> UserOperationReceiptProvider for EvmReceiptProvider<P> {
+    async fn get_user_operation_receipt(&self, hash: B256) -> GatewayResult<Option<Value>> {
+        let Some(event) = self.get_event_by_hash(hash).await? else {
+            debug!("No UserOperationEvent found for hash: {:#x}", hash);
+            return Ok(None);
+        };
+
+        let tx_hash = event.transaction_hash.ok_or_else(|| {
+            GatewayError::InternalError("UserOperationEvent log missing tx hash".to_string())
+        })?;
+
+        let tx_receipt = self
+            .provider
+            .get_transaction_receipt(tx_hash)
+            .await
+            .map_err(|e| GatewayError::RundlerError(format!("Failed to get tx receipt: {}", e)))?
+            .ok_or_else(|| {
+                GatewayError::RundlerError(format!("Transaction receipt not found: {:#x}", tx_hash))
+            })?;
+
+        self.construct_receipt(event, tx_receipt).map(Some)
+    }
+}
+
+/// UserOperationEvent lookups of the sponsorship reconciler, answered from receipts
+pub struct ReceiptEventSource {
+    receipts: Arc<dyn UserOperationReceiptProvider>,
+}
+
+impl ReceiptEventSource {
+    /// Look events up through `receipts`
+    pub fn new(receipts: Arc<dyn UserOperationReceiptProvider>) -> Self {
+        Self { receipts }
+    }
+}
+
+#[async_trait]
+impl UserOperationEventSource for ReceiptEventSource {
+    async fn find_user_operation_event(
+        &self,
+        user_op_hash: B256,
+    ) -> Result<Option<MinedUserOperation>, String> {
+        let Some(receipt) = self
+            .receipts
+            .get_user_operation_receipt(user_op_hash)
+            .await
+            .map_err(|e| e.to_string())?
+        else {
+            return Ok(None);
+        };
+        MinedUserOperation::from_receipt(&receipt)
+            .map(Some)
+            .ok_or_else(|| "receipt has no transaction hash or block number".to_string())
+    }
+}
+
+/// Collect the logs belonging to one UserOperation within a bundle transaction
+///
+/// Mirrors the rundler RPC behaviour: the slice starts after the previous
+/// `UserOperationEvent` (or `BeforeExecution`) and ends with our own event.
+fn filter_receipt_logs_matching_user_op(
+    reference_log: &Log,
+    tx_receipt: &TransactionReceipt,
+) -> GatewayResult<Vec<Log>> {
+    let logs = tx_receipt.inner.inner.logs();
+
+    let is_ref_user_op = |log: &Log| {
+        log.topics().len() >= 2
+            && log.topics()[0] == reference_log.topics()[0]
+            && log.topics()[1] == reference_log.topics()[1]
+            && log.address() == reference_log.address()
+    };
+    let is_user_op_event =
+        |log: &Log| !log.topics().is_empty() && log.topics()[0] == reference_log.topics()[0];
+    let is_before_execution_log = |log: &Log| {
+        !log.topics().is_empty()
+            && log.topics()[0] == BeforeExecution::SIGNATURE_HASH
+            && log.address() == reference_log.address()
+    };
+
+    let mut start_idx = None;
+    for (i, log) in logs.iter().enumerate() {
+        if is_before_execution_log(log) || (is_user_op_event(log) && !is_ref_user_op(log)) {
+            start_idx = Some(i + 1);
+        } else if is_ref_user_op(log) {
+            let start_idx = start_idx.ok_or_else(|| {
+                GatewayError::InternalError(
+                    "Invalid log sequence: UserOperationEvent before BeforeExecution".to_string(),
+                )
+            })?;
+            return Ok(logs[start_idx..=i].to_vec());
+        }
+    }
+
+    Err(GatewayError::InternalError(
+        "No matching UserOperationEvent found in tx receipt".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, Bytes, Log as PrimitiveLog, LogData, U256};
+    use alloy_rpc_types_eth::TransactionReceipt as AlloyTransactionReceipt;
+    use rundler_provider::{
+        AnyReceiptEnvelope, MockEvmProvider, ReceiptWithBloom, WithOtherFields,
+    };
+
+    use super::*;
+
+    const ENTRY_POINT: Address = address!("0000000071727De22E5E9d8BAf0edAc6f37da032");
+    const SENDER: Address = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+    const PAYMASTER: Address = address!("63c0c19a282a1b52b07dd5a65b58948a07dae32b");
+
+    fn uo_hash() -> B256 {
+        B256::repeat_byte(0x11)
+    }
+
+    fn tx_hash() -> B256 {
+        B256::repeat_byte(0x22)
+    }
+
+    fn entry_point_log(data: LogData) -> Log {
+        Log {
+            inner: PrimitiveLog {
+                address: ENTRY_POINT,
+                data,
+            },
+            transaction_hash: Some(tx_hash()),
+            ..Default::default()
+        }
+    }
+
+    fn user_operation_event(success: bool) -> Log {
+        entry_point_log(
+            UserOperationEvent {
+                userOpHash: uo_hash(),
+                sender: SENDER,
+                paymaster: PAYMASTER,
+                nonce: U256::from(7),
+                success,
+                actualGasCost: U256::from(0x5208_u64 * 1_000),
+                actualGasUsed: U256::from(0x5208),
+            }
+            .encode_log_data(),
+        )
+    }
+
+    fn revert_reason_event() -> Log {
+        entry_point_log(
+            UserOperationRevertReason {
+                userOpHash: uo_hash(),
+                sender: SENDER,
+                nonce: U256::from(7),
+                revertReason: Bytes::from_static(&[0xde, 0xad]),
+            }
+            .encode_log_data(),
+        )
+    }
+
+    fn receipt(logs: Vec<Log>) -> TransactionReceipt {
+        let receipt = alloy_consensus::Receipt {
+            logs,
+            ..Default::default()
+        };
+
+        WithOtherFields::new(AlloyTransactionReceipt {
+            inner: AnyReceiptEnvelope {
+                inner: ReceiptWithBloom {
+                    receipt,
+                    ..Default::default()
+                },
+                r#type: 2,
+            },
+            transaction_hash: tx_hash(),
+            transaction_index: None,
+            block_hash: None,
+            block_number: Some(100),
+            gas_used: 0x5208,
+            effective_gas_price: 1_000,
+            blob_gas_used: None,
+            blob_gas_price: None,
+            from: Address::ZERO,
+            to: Some(ENTRY_POINT),
+            contract_address: None,
+        })
+    }
+
+    fn mock_provider(event: Option<Log>, tx_logs: Vec<Log>) -> MockEvmProvider {
+        let mut provider = MockEvmProvider::new();
+        provider.expect_get_block_number().returning(|| Ok(100));
+        provider
+            .expect_get_logs()
+            .returning(move |_| Ok(event.clone().into_iter().collect()));
+        let tx_receipt = receipt(tx_logs);
+        provider
+            .expect_get_transaction_receipt()
+            .returning(move |_| Ok(Some(tx_receipt.clone())));
+        provider
+    }
+
+    fn before_execution() -> Log {
+        entry_point_log(BeforeExecution {}.encode_log_data())
+    }
+
+    #[tokio::test]
+    async fn test_receipt_for_successful_op() {
+        let event = user_operation_event(true);
+        let provider = mock_provider(Some(event.clone()), vec![before_execution(), event.clone()]);
+        let receipts = EvmReceiptProvider::new(provider, vec![ENTRY_POINT], None);
+
+        let receipt = receipts
+            .get_user_operation_receipt(uo_hash())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(receipt["success"], json!(true));
+        assert_eq!(receipt["reason"], json!(""));
+        assert_eq!(receipt["sender"], json!(format!("{:#x}", SENDER)));
+        assert_eq!(receipt["paymaster"], json!(format!("{:#x}", PAYMASTER)));
+        assert_eq!(receipt["actualGasUsed"], json!("0x5208"));
+        assert_eq!(receipt["actualGasCost"], json!("0x1406f40"));
+        assert_eq!(receipt["logs"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            receipt["receipt"]["transactionHash"],
+            json!(format!("{:#x}", tx_hash()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_receipt_for_reverted_op() {
+        let event = user_operation_event(false);
+        let provider = mock_provider(
+            Some(event.clone()),
+            vec![before_execution(), revert_reason_event(), event.clone()],
+        );
+        let receipts = EvmReceiptProvider::new(provider, vec![ENTRY_POINT], Some(1_000));
+
+        let receipt = receipts
+            .get_user_operation_receipt(uo_hash())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(receipt["success"], json!(false));
+        assert_eq!(receipt["reason"], json!("0xdead"));
+        assert_eq!(receipt["logs"].as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_receipt_for_missing_op() {
+        let provider = mock_provider(None, vec![]);
+        let receipts = EvmReceiptProvider::new(provider, vec![ENTRY_POINT], None);
+
+        let receipt = receipts
+            .get_user_operation_receipt(uo_hash())
+            .await
+            .unwrap();
+        assert!(receipt.is_none());
+    }
+}
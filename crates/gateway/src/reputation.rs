@@ -0,0 +1,126 @@
+use alloy_primitives::{Address, U64};
+use rundler_types::pool::{Pool, ReputationStatus};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    error::{GatewayError, GatewayResult},
+    pool_export::export_entry_points,
+};
+
+/// Reputation of an entity on one EntryPoint, as the pool rates it
+///
+/// The fields of the bundler's `RpcReputationOutput`, with the EntryPoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityReputation {
+    /// EntryPoint the reputation is kept for
+    pub entry_point: Address,
+    /// Entity address
+    pub address: Address,
+    /// Operations seen in the current interval
+    pub ops_seen: U64,
+    /// Operations included in the current interval
+    pub ops_included: U64,
+    /// `ok`, `throttled` or `banned`
+    pub status: ReputationStatus,
+}
+
+fn pool_error(e: impl ToString) -> GatewayError {
+    GatewayError::PoolError(e.to_string())
+}
+
+/// Reputation of `address` on `entry_point`; an entity the pool has not seen
+/// has no operations counted
+pub async fn entity_reputation<P: Pool + ?Sized>(
+    pool: &P,
+    entry_point: Address,
+    address: Address,
+) -> GatewayResult<EntityReputation> {
+    let status = pool
+        .get_reputation_status(entry_point, address)
+        .await
+        .map_err(pool_error)?;
+    let counts = pool
+        .debug_dump_reputation(entry_point)
+        .await
+        .map_err(pool_error)?
+        .into_iter()
+        .find(|reputation| reputation.address == address);
+    Ok(EntityReputation {
+        entry_point,
+        address,
+        ops_seen: U64::from(counts.as_ref().map_or(0, |r| r.ops_seen)),
+        ops_included: U64::from(counts.as_ref().map_or(0, |r| r.ops_included)),
+        status,
+    })
+}
+
+/// Reputation of `address` on `entry_point`, or on every EntryPoint of the
+/// pool; served by rundler_getEntityReputation
+pub async fn entity_reputations<P: Pool + ?Sized>(
+    pool: &P,
+    address: Address,
+    entry_point: Option<Address>,
+) -> GatewayResult<Vec<EntityReputation>> {
+    let mut reputations = Vec::new();
+    for entry_point in export_entry_points(pool, entry_point).await? {
+        reputations.push(entity_reputation(pool, entry_point, address).await?);
+    }
+    Ok(reputations)
+}
+
+/// Throttled and banned entities on `entry_point`, or on every EntryPoint of
+/// the pool; served by rundler_listThrottledEntities
+pub async fn throttled_entities<P: Pool + ?Sized>(
+    pool: &P,
+    entry_point: Option<Address>,
+) -> GatewayResult<Vec<EntityReputation>> {
+    let mut throttled = Vec::new();
+    for entry_point in export_entry_points(pool, entry_point).await? {
+        let reputations = pool
+            .debug_dump_reputation(entry_point)
+            .await
+            .map_err(pool_error)?;
+        for reputation in reputations {
+            let status = pool
+                .get_reputation_status(entry_point, reputation.address)
+                .await
+                .map_err(pool_error)?;
+            if status != ReputationStatus::Ok {
+                throttled.push(EntityReputation {
+                    entry_point,
+                    address: reputation.address,
+                    ops_seen: U64::from(reputation.ops_seen),
+                    ops_included: U64::from(reputation.ops_included),
+                    status,
+                });
+            }
+        }
+    }
+    Ok(throttled)
+}
+
+/// `err` with `reputation` added to the entity in its error data
+///
+/// Errors other than pool rejections carrying the entity are returned as is.
+pub fn with_reputation(err: GatewayError, reputation: &EntityReputation) -> GatewayError {
+    match err {
+        GatewayError::PoolRejected {
+            code,
+            message,
+            data: Some(Value::Object(mut data)),
+        } => {
+            data.insert(
+                "reputation".to_string(),
+                serde_json::to_value(reputation).unwrap_or_default(),
+            );
+            GatewayError::PoolRejected {
+                code,
+                message,
+                data: Some(Value::Object(data)),
+            }
+        }
+        err => err,
+    }
+}
@@ -0,0 +1,147 @@
+use axum::http::HeaderMap;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+/// Header carrying a request's correlation id, accepted from callers and echoed back
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest caller-supplied id kept; longer ones are replaced by a generated id
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Correlation id of a request: the caller's x-request-id if usable, otherwise a new UUID
+///
+/// Caller ids are limited to printable ASCII so they can be logged and echoed
+/// in a response header verbatim.
+pub fn request_id(headers: &HeaderMap) -> String {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id.bytes().all(|b| b.is_ascii_graphic())
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+/// Add `requestId` to the `error.data` object of a JSON-RPC error response
+pub(crate) fn attach_to_error(response: &mut Value, request_id: &str) {
+    let Some(error) = response.get_mut("error").and_then(Value::as_object_mut) else {
+        return;
+    };
+    match error.get_mut("data") {
+        Some(Value::Object(data)) => {
+            data.insert("requestId".to_string(), json!(request_id));
+        }
+        // Keep non-object data instead of overwriting it
+        Some(data) if !data.is_null() => {}
+        _ => {
+            error.insert("data".to_string(), json!({ "requestId": request_id }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::{GatewayConfig, PaymasterGateway};
+
+    async fn spawn_gateway() -> std::net::SocketAddr {
+        let app = PaymasterGateway::new(GatewayConfig::default(), None)
+            .app()
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    /// POST a JSON-RPC body and return the response's x-request-id header and JSON body
+    async fn post(
+        addr: std::net::SocketAddr,
+        body: &str,
+        request_id: Option<&str>,
+    ) -> (Option<String>, Value) {
+        let mut request = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+         Content-Type: application/json\r\nContent-Length: {}\r\n",
+            body.len()
+        );
+        if let Some(id) = request_id {
+            request.push_str(&format!("{}: {}\r\n", REQUEST_ID_HEADER, id));
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let header = head.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case(REQUEST_ID_HEADER)
+                .then(|| value.trim().to_string())
+        });
+        (header, serde_json::from_str(body).unwrap())
+    }
+
+    const UNKNOWN_METHOD: &str = r#"{"jsonrpc":"2.0","id":1,"method":"foo_bar","params":[]}"#;
+
+    #[tokio::test]
+    async fn test_incoming_request_id_echoed() {
+        let addr = spawn_gateway().await;
+
+        let (header, body) = post(addr, UNKNOWN_METHOD, Some("trace-abc-123")).await;
+        assert_eq!(header.as_deref(), Some("trace-abc-123"));
+        assert_eq!(body["error"]["code"], -32601);
+        assert_eq!(body["error"]["data"]["requestId"], "trace-abc-123");
+    }
+
+    #[tokio::test]
+    async fn test_request_id_generated_when_missing() {
+        let addr = spawn_gateway().await;
+
+        let (header, body) = post(addr, UNKNOWN_METHOD, None).await;
+        let header = header.expect("x-request-id header");
+        assert!(uuid::Uuid::parse_str(&header).is_ok(), "{}", header);
+        assert_eq!(body["error"]["data"]["requestId"], header.as_str());
+
+        let (other, _) = post(addr, UNKNOWN_METHOD, None).await;
+        assert_ne!(other.as_deref(), Some(header.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_unusable_request_id_replaced() {
+        let addr = spawn_gateway().await;
+
+        let too_long = "a".repeat(200);
+        let (header, _) = post(addr, UNKNOWN_METHOD, Some(&too_long)).await;
+        let header = header.expect("x-request-id header");
+        assert!(uuid::Uuid::parse_str(&header).is_ok(), "{}", header);
+    }
+
+    #[tokio::test]
+    async fn test_request_id_on_success_and_routing_errors() {
+        let addr = spawn_gateway().await;
+
+        let chain_id = r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId","params":[]}"#;
+        let (header, body) = post(addr, chain_id, Some("ok-1")).await;
+        assert_eq!(header.as_deref(), Some("ok-1"));
+        assert!(body.get("result").is_some());
+        assert!(body.get("error").is_none());
+
+        // Failures inside the router carry the id too
+        let nonce = r#"{"jsonrpc":"2.0","id":2,"method":"eth_getUserOperationNonce","params":[]}"#;
+        let (_, body) = post(addr, nonce, Some("err-2")).await;
+        assert_eq!(body["error"]["data"]["requestId"], "err-2");
+    }
+}
@@ -0,0 +1,368 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use alloy_primitives::keccak256;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use tracing::{info, warn};
+
+use crate::method_registry;
+
+/// Request logging settings (`[gateway.logging]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RequestLogConfig {
+    /// Log JSON-RPC requests with their responses
+    pub enabled: bool,
+    /// Fraction of successful requests logged, from 0.0 (none) to 1.0 (all)
+    pub success_sample_rate: f64,
+    /// Fraction of successful calls of read-only methods logged, in place of
+    /// `success_sample_rate`; unset samples them like the other successes
+    pub read_success_sample_rate: Option<f64>,
+    /// Log every request answered with an error, regardless of sampling
+    pub always_log_errors: bool,
+    /// Replace payload fields with their keccak256 hash and length
+    pub redact: bool,
+    /// Fields redacted wherever they appear in a request or response
+    pub redacted_fields: Vec<String>,
+}
+
+impl Default for RequestLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            success_sample_rate: 1.0,
+            read_success_sample_rate: None,
+            always_log_errors: true,
+            redact: true,
+            redacted_fields: vec![
+                "signature".to_string(),
+                "callData".to_string(),
+                "paymasterAndData".to_string(),
+            ],
+        }
+    }
+}
+
+/// Sampled, redacted logging of JSON-RPC calls and batches
+#[derive(Debug, Clone)]
+pub struct RequestLogger {
+    config: Arc<RequestLogConfig>,
+    successes: Arc<AtomicU64>,
+    read_successes: Arc<AtomicU64>,
+}
+
+impl Default for RequestLogger {
+    fn default() -> Self {
+        Self::new(RequestLogConfig::default())
+    }
+}
+
+impl RequestLogger {
+    /// Create a logger with the given settings
+    pub fn new(config: RequestLogConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            successes: Arc::new(AtomicU64::new(0)),
+            read_successes: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Whether requests are logged at all
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Log `request` and its `response`, if sampled
+    pub fn log(&self, request_id: &str, request: &Value, response: &Value) {
+        let Some(line) = self.log_line(request, response) else {
+            return;
+        };
+        if is_error(response) {
+            warn!(request_id = %request_id, "{}", line);
+        } else {
+            info!(request_id = %request_id, "{}", line);
+        }
+    }
+
+    /// Line logged for `request` and its `response`, `None` when sampled out
+    ///
+    /// Successes are sampled evenly: with a rate of 0.01, exactly one in every
+    /// hundred is logged. Calls of read-only methods, and batches of only such
+    /// calls, are sampled separately when `read_success_sample_rate` is set.
+    pub fn log_line(&self, request: &Value, response: &Value) -> Option<String> {
+        if !self.config.enabled {
+            return None;
+        }
+        let error = is_error(response);
+        let access = access(request);
+        if !(error && self.config.always_log_errors) && !self.sample_success(access == "read") {
+            return None;
+        }
+
+        Some(format!(
+            "JSON-RPC {} {} request={} response={}",
+            if error { "error" } else { "ok" },
+            access,
+            self.redact(request),
+            self.redact(response)
+        ))
+    }
+
+    /// `value` with every configured field replaced by its hash and length
+    ///
+    /// Walks the parsed JSON, so fields nested in batch entries, user operations
+    /// and responses are all covered.
+    pub fn redact(&self, value: &Value) -> Value {
+        if !self.config.redact {
+            return value.clone();
+        }
+        match value {
+            Value::Object(object) => Value::Object(
+                object
+                    .iter()
+                    .map(|(key, value)| {
+                        let value = match value {
+                            Value::String(s) if self.config.redacted_fields.contains(key) => {
+                                redacted(s)
+                            }
+                            value => self.redact(value),
+                        };
+                        (key.clone(), value)
+                    })
+                    .collect::<Map<_, _>>(),
+            ),
+            Value::Array(values) => Value::Array(values.iter().map(|v| self.redact(v)).collect()),
+            value => value.clone(),
+        }
+    }
+
+    fn sample_success(&self, read_only: bool) -> bool {
+        let (rate, successes) = match self.config.read_success_sample_rate {
+            Some(rate) if read_only => (rate, &self.read_successes),
+            _ => (self.config.success_sample_rate, &self.successes),
+        };
+        let rate = rate.clamp(0.0, 1.0);
+        let n = successes.fetch_add(1, Ordering::Relaxed) as f64;
+        ((n + 1.0) * rate).floor() > (n * rate).floor()
+    }
+}
+
+/// keccak256 and byte length of a hex payload (or of the raw string)
+fn redacted(value: &str) -> Value {
+    let bytes = value
+        .strip_prefix("0x")
+        .and_then(|hex| hex::decode(hex).ok())
+        .unwrap_or_else(|| value.as_bytes().to_vec());
+    json!({
+        "keccak256": format!("{}", keccak256(&bytes)),
+        "length": bytes.len(),
+    })
+}
+
+/// `read` for a call of a read-only method or a batch of only such calls,
+/// `write` when one changes state, `unknown` for unserved or malformed calls
+fn access(request: &Value) -> &'static str {
+    match request {
+        Value::Array(calls) if !calls.is_empty() => {
+            let mut access = calls.iter().map(access);
+            if access.all(|access| access == "read") {
+                "read"
+            } else {
+                "write"
+            }
+        }
+        call => call
+            .get("method")
+            .and_then(Value::as_str)
+            .and_then(method_registry::lookup)
+            .map_or("unknown", |spec| spec.access()),
+    }
+}
+
+/// Whether a response, or any response of a batch, is an error
+fn is_error(response: &Value) -> bool {
+    match response {
+        Value::Array(responses) => responses.iter().any(is_error),
+        response => response.get("error").is_some(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::keccak256;
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    #[test]
+    fn test_success_sampling() {
+        let logger = RequestLogger::new(RequestLogConfig {
+            success_sample_rate: 0.01,
+            ..Default::default()
+        });
+        let ok = json!({"jsonrpc": "2.0", "id": 1, "result": "0x1"});
+        let logged = (0..1_000)
+            .filter(|_| logger.log_line(&json!({}), &ok).is_some())
+            .count();
+        assert_eq!(logged, 10);
+
+        // Errors bypass sampling
+        let error = json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -32602}});
+        assert!(logger.log_line(&json!({}), &error).is_some());
+        let batch = json!([ok, error]);
+        assert!(logger.log_line(&json!([]), &batch).is_some());
+    }
+
+    #[test]
+    fn test_read_only_sampled_separately() {
+        let logger = RequestLogger::new(RequestLogConfig {
+            read_success_sample_rate: Some(0.0),
+            ..Default::default()
+        });
+        let ok = json!({"jsonrpc": "2.0", "id": 1, "result": "0x1"});
+        let read = json!({"jsonrpc": "2.0", "id": 1, "method": "eth_chainId"});
+        let write = json!({"jsonrpc": "2.0", "id": 2, "method": "eth_sendUserOperation"});
+
+        assert!(logger.log_line(&read, &ok).is_none());
+        let batch = json!([read.clone(), read.clone()]);
+        assert!(logger
+            .log_line(&batch, &json!([ok.clone(), ok.clone()]))
+            .is_none());
+        let line = logger.log_line(&write, &ok).unwrap();
+        assert!(line.starts_with("JSON-RPC ok write "), "{}", line);
+        let batch = json!([read.clone(), write]);
+        let line = logger.log_line(&batch, &json!([ok.clone(), ok])).unwrap();
+        assert!(line.starts_with("JSON-RPC ok write "), "{}", line);
+
+        // Errors of read-only methods are still logged
+        let error = json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -32602}});
+        let line = logger.log_line(&read, &error).unwrap();
+        assert!(line.starts_with("JSON-RPC error read "), "{}", line);
+    }
+
+    #[test]
+    fn test_disabled_logs_nothing() {
+        let logger = RequestLogger::new(RequestLogConfig {
+            enabled: false,
+            ..Default::default()
+        });
+        let error = json!({"error": {"code": -32602}});
+        assert!(logger.log_line(&json!({}), &error).is_none());
+    }
+
+    const SIGNATURE: &str =
+        "0xdeadbeefcafebabe0102030405060708090a0b0c0d0e0f101112131415161718191a1b";
+    const CALL_DATA: &str =
+        "0xb61d27f6000000000000000000000000f39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+    const PAYMASTER_AND_DATA: &str =
+        "0x0000000000000000000000000000000000000000aa0000000000000000000000000000000000000000001122";
+
+    fn sponsor_request(id: u64) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "pm_sponsorUserOperation",
+            "params": [
+                {
+                    "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                    "nonce": "0x1",
+                    "initCode": "0x",
+                    "callData": CALL_DATA,
+                    "callGasLimit": "0x186a0",
+                    "verificationGasLimit": "0x186a0",
+                    "preVerificationGas": "0x5208",
+                    "maxFeePerGas": "0x3b9aca00",
+                    "maxPriorityFeePerGas": "0x3b9aca00",
+                    "paymasterAndData": "0x",
+                    "signature": SIGNATURE,
+                },
+                "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789"
+            ]
+        })
+    }
+
+    fn sponsor_response(id: u64) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": { "paymasterAndData": PAYMASTER_AND_DATA }
+        })
+    }
+
+    /// Redacted form of a hex payload
+    fn hashed(hex: &str) -> String {
+        format!("{}", keccak256(hex::decode(&hex[2..]).unwrap()))
+    }
+
+    fn assert_redacted(line: &str) {
+        for raw in [SIGNATURE, CALL_DATA, PAYMASTER_AND_DATA] {
+            assert!(!line.contains(&raw[2..]), "raw payload logged: {}", line);
+            assert!(line.contains(&hashed(raw)), "hash missing: {}", line);
+        }
+        assert!(line.contains("pm_sponsorUserOperation"));
+        assert!(line.contains("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"));
+    }
+
+    #[test]
+    fn test_sponsorship_request_logged_as_hashes() {
+        let logger = RequestLogger::default();
+        let line = logger
+            .log_line(&sponsor_request(1), &sponsor_response(1))
+            .unwrap();
+        assert_redacted(&line);
+
+        let redacted = logger.redact(&sponsor_request(1));
+        let signature = &redacted["params"][0]["signature"];
+        assert_eq!(signature["keccak256"], json!(hashed(SIGNATURE)));
+        assert_eq!(signature["length"], json!((SIGNATURE.len() - 2) / 2));
+        assert_eq!(redacted["params"][0]["nonce"], json!("0x1"));
+    }
+
+    #[test]
+    fn test_batch_entries_redacted() {
+        let logger = RequestLogger::default();
+        let line = logger
+            .log_line(
+                &json!([sponsor_request(1), sponsor_request(2)]),
+                &json!([sponsor_response(1), sponsor_response(2)]),
+            )
+            .unwrap();
+        assert_redacted(&line);
+    }
+
+    #[test]
+    fn test_redaction_can_be_disabled() {
+        let logger = RequestLogger::new(RequestLogConfig {
+            redact: false,
+            ..Default::default()
+        });
+        let line = logger
+            .log_line(&sponsor_request(1), &sponsor_response(1))
+            .unwrap();
+        assert!(line.contains(SIGNATURE));
+        assert!(line.contains(PAYMASTER_AND_DATA));
+    }
+
+    #[test]
+    fn test_errors_logged_when_successes_are_not() {
+        let logger = RequestLogger::new(RequestLogConfig {
+            success_sample_rate: 0.0,
+            ..Default::default()
+        });
+        assert!(logger
+            .log_line(&sponsor_request(1), &sponsor_response(1))
+            .is_none());
+
+        let error = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": { "code": -32602, "message": "Sender not allowed" }
+        });
+        let line = logger.log_line(&sponsor_request(1), &error).unwrap();
+        assert!(line.contains("Sender not allowed"));
+        assert!(!line.contains(&SIGNATURE[2..]));
+    }
+}
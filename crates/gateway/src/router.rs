@@ -1,42 +1,141 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use alloy_primitives::{Address, Bytes, U256};
+use alloy_primitives::{Address, Bytes, B256, U256};
 use ethers::types::H160;
-use rundler_paymaster_relay::PaymasterRelayService;
+use futures_util::{future, Stream};
+use rundler_paymaster_relay::{
+    canonical, idempotency::MAX_IDEMPOTENCY_KEY_LEN, record_stage, service::SponsorOptions,
+    simulation::SponsorshipSimulation, sponsorship_records::DEFAULT_PAGE_SIZE, DummyPaymasterData,
+    KeySource, MinedUserOperation, PaymasterError, PaymasterRelayService, SponsorStage,
+    SponsorshipQuery, SponsorshipStatus, StageTimings, UsageGranularity, VerificationProofStore,
+};
 use rundler_pool::LocalPoolHandle;
+#[cfg(feature = "entrypoint-v0_8")]
+use rundler_types::v0_8;
 use rundler_types::{
-    chain::ChainSpec, pool::Pool, v0_6, v0_7, UserOperation, UserOperationPermissions,
-    UserOperationVariant,
+    aggregator::SignatureAggregator,
+    authorization::Eip7702Auth,
+    builder::{Builder, BundlingMode},
+    chain::{ChainSpec, ContractRegistry},
+    pool::{MempoolError, Pool, PoolError, Reputation},
+    v0_6, v0_7, EntryPointVersion, ExpectedStorage, UserOperation, UserOperationOptionalGas,
+    UserOperationPermissions, UserOperationVariant,
 };
 use serde_json::{json, Value};
-use tracing::{debug, error, warn};
+use tracing::{debug, error, info, warn};
 
 use crate::{
-    authorization::AuthorizationChecker,
+    alerts::{Alert, AlertBus, AlertKind},
+    batch_sponsorship::{batch_result, BatchSponsorshipConfig, BatchSponsorshipMode},
+    bundle_simulation::{BundleSimulator, OpSimulation},
+    bundle_tracker::BundleTracker,
+    cache::{CacheCounters, ResponseCache},
+    conditional::ConditionalSendConfig,
+    deadline::Deadline,
+    entry_point_status::{
+        ContractCodeReader, EntryPointIssue, EntryPointStatus, EntryPointStatusBoard,
+    },
     error::{GatewayError, GatewayResult},
+    error_catalog::error_catalog,
+    estimation::{gas_estimate_to_json, parse_state_override, GasEstimator},
+    events::{EventBus, GatewayEvent},
+    gas_price::GasPriceOracle,
     gateway::JsonRpcRequest,
-    security::SecurityChecker,
-    validation::DataIntegrityChecker,
+    json_fields::JsonFields,
+    nonce::{next_pending_nonce, validate_nonce_key, NonceReader},
+    op_permissions::{OpPermissionsConfig, RequestedPermissions},
+    pipeline::{ModuleContext, ModulePipeline},
+    pool_export::{export_entry_points, export_ndjson, pool_summary, PoolExportQuery},
+    pool_supervisor::SupervisedPool,
+    receipt::UserOperationReceiptProvider,
+    reputation::{entity_reputation, entity_reputations, throttled_entities, with_reputation},
+    signature::SignatureValidator,
+    threat_feed::ThreatIntelStore,
+    usage_export::UsageExportQuery,
+    validation::ValidationConfig,
+    version_selector::VersionSelector,
 };
 
+/// Bundle attempts listed by rundler_getBundleStats
+const RECENT_BUNDLES: usize = 20;
+
+/// Operations one superRelay_simulateBundle call may name, the builder's bundle size
+const MAX_SIMULATED_OPS: usize = 128;
+
 /// Router that handles request routing to appropriate rundler components
 #[derive(Clone)]
 pub struct GatewayRouter {
     /// Supported EntryPoint addresses
     supported_entry_points: Vec<Address>,
-    /// Pool handle for mempool operations
-    pool_handle: Option<Arc<LocalPoolHandle>>,
+    /// Capabilities of the supported EntryPoints, deciding which are advertised
+    entry_point_status: Arc<EntryPointStatusBoard>,
+    /// Contract code lookup of the EntryPoint checks, skipped when unset
+    code_reader: Option<Arc<dyn ContractCodeReader>>,
+    /// Pool handle for mempool operations, following pool restarts
+    pool_handle: Option<SupervisedPool>,
     /// Chain ID for this network
     chain_id: u64,
+    /// On-chain receipt lookup for mined operations
+    receipt_provider: Option<Arc<dyn UserOperationReceiptProvider>>,
+    /// Paymaster whose signatures are verified before pool submission
+    paymaster_service: Option<Arc<PaymasterRelayService>>,
+    /// On-chain EntryPoint nonce lookup for eth_getUserOperationNonce
+    nonce_reader: Option<Arc<dyn NonceReader>>,
+    /// Feed-backed threat intelligence for the security check
+    threat_intel: Option<Arc<ThreatIntelStore>>,
+    /// Dual-signature verification proofs for superRelay_getVerificationProof
+    verification_proofs: Option<Arc<VerificationProofStore>>,
+    /// Ordered sponsorship checks
+    pipeline: Arc<ModulePipeline>,
+    /// Cached responses of idempotent read methods
+    response_cache: Arc<ResponseCache>,
+    /// Gas estimation backend, stub estimates when unset
+    gas_estimator: Option<Arc<dyn GasEstimator>>,
+    /// Fee suggestions of superRelay_getUserOperationGasPrice
+    gas_price_oracle: Option<Arc<GasPriceOracle>>,
+    /// Account signature checks; signatures are left to the EntryPoint when unset
+    signature_validator: Option<Arc<SignatureValidator>>,
+    /// Bundle builder driven by rundler_sendBundleNow and debug_bundler_* methods
+    builder: Option<Arc<dyn Builder>>,
+    /// Serve the debug_bundler_* methods used by the ERC-4337 spec tests, and
+    /// rundler_sendBundleNow/setReputation which change pool and builder state
+    debug_api_enabled: bool,
+    /// Enabled signature aggregators; operations naming any other are rejected
+    signature_aggregators: Arc<ContractRegistry<Arc<dyn SignatureAggregator>>>,
+    /// Chain spec UserOperations are built and hashed with
+    chain_spec: Arc<ChainSpec>,
+    /// EntryPoint version of incoming operations, from their shape and target EntryPoint
+    version_selector: Arc<VersionSelector>,
+    /// Recent bundles and per-operation inclusion, from builder events
+    bundle_tracker: Option<Arc<BundleTracker>>,
+    /// handleOps simulation of superRelay_simulateBundle
+    bundle_simulator: Option<Arc<dyn BundleSimulator>>,
+    /// Size and sanity limits every parsed operation must meet
+    limits: ValidationConfig,
+    /// Operator alerts for signer failures and repeatedly rejected senders
+    alerts: AlertBus,
+    /// Pool permissions of submitted operations by API key trust level
+    op_permissions: OpPermissionsConfig,
+    /// Whether and with how many slots conditional operations are accepted
+    conditional_send: ConditionalSendConfig,
+    /// Size cap and default mode of pm_sponsorUserOperationBatch
+    batch_sponsorship: BatchSponsorshipConfig,
+    /// Live events of pool submissions and sponsorship check failures
+    events: Option<EventBus>,
 }
 
 /// Configuration for the Gateway's ETH API
-#[derive(Default)]
+#[derive(Debug, Default)]
 pub struct EthApiConfig {
     /// Chain ID for the network
     pub chain_id: u64,
     /// Supported EntryPoint addresses
     pub entry_points: Vec<Address>,
+    /// Chain spec UserOperations are built and hashed with, its id replaced by `chain_id`
+    pub chain_spec: Arc<ChainSpec>,
 }
 
 impl GatewayRouter {
@@ -44,14 +143,39 @@ impl GatewayRouter {
     pub fn new() -> Self {
         Self {
             supported_entry_points: Self::default_entry_points(),
+            entry_point_status: Arc::new(EntryPointStatusBoard::default()),
+            code_reader: None,
             pool_handle: None,
             chain_id: 31337, // Anvil default
+            receipt_provider: None,
+            paymaster_service: None,
+            nonce_reader: None,
+            threat_intel: None,
+            verification_proofs: None,
+            pipeline: Arc::new(ModulePipeline::default()),
+            response_cache: Arc::new(ResponseCache::default()),
+            gas_estimator: None,
+            gas_price_oracle: None,
+            signature_validator: None,
+            builder: None,
+            debug_api_enabled: false,
+            signature_aggregators: Arc::new(ContractRegistry::default()),
+            chain_spec: Arc::new(ChainSpec::default()),
+            version_selector: Arc::new(VersionSelector::from_chain_spec(&ChainSpec::default())),
+            bundle_tracker: None,
+            bundle_simulator: None,
+            limits: ValidationConfig::default(),
+            alerts: AlertBus::disabled(),
+            op_permissions: OpPermissionsConfig::default(),
+            conditional_send: ConditionalSendConfig::default(),
+            batch_sponsorship: BatchSponsorshipConfig::default(),
+            events: None,
         }
     }
 
     /// Create a new router with rundler components
     pub fn with_rundler_components(
-        pool_handle: Arc<LocalPoolHandle>,
+        pool_handle: impl Into<SupervisedPool>,
         config: EthApiConfig,
     ) -> Self {
         let chain_id = if config.chain_id == 0 {
@@ -67,8 +191,33 @@ impl GatewayRouter {
 
         Self {
             supported_entry_points: entry_points,
-            pool_handle: Some(pool_handle),
+            entry_point_status: Arc::new(EntryPointStatusBoard::default()),
+            code_reader: None,
+            pool_handle: Some(pool_handle.into()),
             chain_id,
+            receipt_provider: None,
+            paymaster_service: None,
+            nonce_reader: None,
+            threat_intel: None,
+            verification_proofs: None,
+            pipeline: Arc::new(ModulePipeline::default()),
+            response_cache: Arc::new(ResponseCache::default()),
+            gas_estimator: None,
+            gas_price_oracle: None,
+            signature_validator: None,
+            builder: None,
+            debug_api_enabled: false,
+            signature_aggregators: Arc::new(ContractRegistry::default()),
+            version_selector: Arc::new(VersionSelector::from_chain_spec(&config.chain_spec)),
+            chain_spec: config.chain_spec,
+            bundle_tracker: None,
+            bundle_simulator: None,
+            limits: ValidationConfig::default(),
+            alerts: AlertBus::disabled(),
+            op_permissions: OpPermissionsConfig::default(),
+            conditional_send: ConditionalSendConfig::default(),
+            batch_sponsorship: BatchSponsorshipConfig::default(),
+            events: None,
         }
     }
 
@@ -80,13 +229,333 @@ impl GatewayRouter {
             } else {
                 config.entry_points
             },
+            entry_point_status: Arc::new(EntryPointStatusBoard::default()),
+            code_reader: None,
             pool_handle: None,
             chain_id: if config.chain_id == 0 {
                 31337
             } else {
                 config.chain_id
             },
+            receipt_provider: None,
+            paymaster_service: None,
+            nonce_reader: None,
+            threat_intel: None,
+            verification_proofs: None,
+            pipeline: Arc::new(ModulePipeline::default()),
+            response_cache: Arc::new(ResponseCache::default()),
+            gas_estimator: None,
+            gas_price_oracle: None,
+            signature_validator: None,
+            builder: None,
+            debug_api_enabled: false,
+            signature_aggregators: Arc::new(ContractRegistry::default()),
+            version_selector: Arc::new(VersionSelector::from_chain_spec(&config.chain_spec)),
+            chain_spec: config.chain_spec,
+            bundle_tracker: None,
+            bundle_simulator: None,
+            limits: ValidationConfig::default(),
+            alerts: AlertBus::disabled(),
+            op_permissions: OpPermissionsConfig::default(),
+            conditional_send: ConditionalSendConfig::default(),
+            batch_sponsorship: BatchSponsorshipConfig::default(),
+            events: None,
+        }
+    }
+
+    /// Attach an on-chain receipt provider for eth_getUserOperationReceipt
+    pub fn with_receipt_provider(
+        mut self,
+        receipt_provider: Arc<dyn UserOperationReceiptProvider>,
+    ) -> Self {
+        self.receipt_provider = Some(receipt_provider);
+        self
+    }
+
+    /// Attach an on-chain nonce reader for eth_getUserOperationNonce
+    pub fn with_nonce_reader(mut self, nonce_reader: Arc<dyn NonceReader>) -> Self {
+        self.nonce_reader = Some(nonce_reader);
+        self
+    }
+
+    /// Check operations against feed-backed threat intelligence
+    pub fn with_threat_intel(mut self, threat_intel: Arc<ThreatIntelStore>) -> Self {
+        self.threat_intel = Some(threat_intel);
+        self
+    }
+
+    /// Serve stored dual-signature verification proofs
+    pub fn with_verification_proofs(
+        mut self,
+        verification_proofs: Arc<VerificationProofStore>,
+    ) -> Self {
+        self.verification_proofs = Some(verification_proofs);
+        self
+    }
+
+    /// Run sponsorship checks through `pipeline` instead of the default order
+    pub fn with_pipeline(mut self, pipeline: Arc<ModulePipeline>) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    /// Serve idempotent read methods through `response_cache`
+    pub fn with_response_cache(mut self, response_cache: Arc<ResponseCache>) -> Self {
+        self.response_cache = response_cache;
+        self
+    }
+
+    /// Answer superRelay_simulateBundle with `simulator`
+    pub fn with_bundle_simulator(mut self, simulator: Arc<dyn BundleSimulator>) -> Self {
+        self.bundle_simulator = Some(simulator);
+        self
+    }
+
+    /// Estimate gas with `gas_estimator` instead of the stub estimates
+    pub fn with_gas_estimator(mut self, gas_estimator: Arc<dyn GasEstimator>) -> Self {
+        self.gas_estimator = Some(gas_estimator);
+        self
+    }
+
+    /// Answer superRelay_getUserOperationGasPrice from `oracle`
+    pub fn with_gas_price_oracle(mut self, oracle: Arc<GasPriceOracle>) -> Self {
+        self.gas_price_oracle = Some(oracle);
+        self
+    }
+
+    /// Check account signatures: strictly on eth_sendUserOperation, leniently on
+    /// pm_sponsorUserOperation where the final signature does not exist yet
+    pub fn with_signature_validator(
+        mut self,
+        signature_validator: Arc<SignatureValidator>,
+    ) -> Self {
+        self.signature_validator = Some(signature_validator);
+        self
+    }
+
+    /// Drive bundling through `builder` on rundler_sendBundleNow and
+    /// debug_bundler_sendBundleNow/setBundlingMode
+    pub fn with_builder(mut self, builder: Arc<dyn Builder>) -> Self {
+        self.builder = Some(builder);
+        self
+    }
+
+    /// Serve debug_bundler_* methods, rundler_sendBundleNow and
+    /// rundler_setReputation; unsupported like any unknown method when disabled
+    pub fn with_debug_api(mut self, enabled: bool) -> Self {
+        self.debug_api_enabled = enabled;
+        self
+    }
+
+    /// Accept UserOperations aggregated by one of `signature_aggregators`
+    pub fn with_signature_aggregators(
+        mut self,
+        signature_aggregators: Arc<ContractRegistry<Arc<dyn SignatureAggregator>>>,
+    ) -> Self {
+        self.signature_aggregators = signature_aggregators;
+        self
+    }
+
+    /// Select EntryPoint versions with `version_selector`, whose declared
+    /// versions replace the canonical EntryPoint addresses
+    pub fn with_version_selector(mut self, version_selector: Arc<VersionSelector>) -> Self {
+        self.version_selector = version_selector;
+        self
+    }
+
+    /// Serve bundle status and operation inclusion from `bundle_tracker`
+    pub fn with_bundle_tracker(mut self, bundle_tracker: Arc<BundleTracker>) -> Self {
+        self.bundle_tracker = Some(bundle_tracker);
+        self
+    }
+
+    /// Reject operations breaking the size and sanity limits of `limits`
+    pub fn with_limits(mut self, limits: ValidationConfig) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Publish signer failures and repeated sponsorship rejections onto `alerts`
+    pub fn with_alerts(mut self, alerts: AlertBus) -> Self {
+        self.alerts = alerts;
+        self
+    }
+
+    /// Grant submitted operations the pool permissions of their API key's trust level
+    pub fn with_op_permissions(mut self, op_permissions: OpPermissionsConfig) -> Self {
+        self.op_permissions = op_permissions;
+        self
+    }
+
+    /// Accept superRelay_sendUserOperationConditional as configured by `conditional_send`
+    pub fn with_conditional_send(mut self, conditional_send: ConditionalSendConfig) -> Self {
+        self.conditional_send = conditional_send;
+        self
+    }
+
+    /// Cap and default mode of pm_sponsorUserOperationBatch
+    pub fn with_batch_sponsorship(mut self, batch_sponsorship: BatchSponsorshipConfig) -> Self {
+        self.batch_sponsorship = batch_sponsorship;
+        self
+    }
+
+    /// Publish pool submissions and sponsorship check failures onto `events`
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Check that EntryPoints have contract code through `code_reader` before advertising them
+    pub fn with_code_reader(mut self, code_reader: Arc<dyn ContractCodeReader>) -> Self {
+        self.code_reader = Some(code_reader);
+        self
+    }
+
+    /// Hit and miss counts of the response cache
+    pub fn cache_counters(&self) -> Vec<CacheCounters> {
+        self.response_cache.counters()
+    }
+
+    /// Verify our own paymaster signatures on eth_sendUserOperation
+    pub fn with_paymaster_service(mut self, paymaster_service: Arc<PaymasterRelayService>) -> Self {
+        self.paymaster_service = Some(paymaster_service);
+        self
+    }
+
+    /// Chain this router serves
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// Check what each supported EntryPoint can be used for, updating the
+    /// advertised lists and alerting when they change
+    ///
+    /// A code or pool lookup that fails keeps the EntryPoint's previous outcome
+    /// for that check, so a flaky node does not churn the advertised lists.
+    pub async fn check_entry_points(&self) -> Vec<EntryPointStatus> {
+        let previous = self.entry_point_status.latest();
+        let pool_entry_points = match &self.pool_handle {
+            Some(pool) => Some(match pool.current() {
+                Ok(pool) => pool
+                    .get_supported_entry_points()
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            }),
+            None => None,
+        };
+
+        let mut statuses = Vec::with_capacity(self.supported_entry_points.len());
+        for &entry_point in &self.supported_entry_points {
+            let had = |issue: EntryPointIssue| {
+                previous.as_ref().is_some_and(|report| {
+                    report.entry_points.iter().any(|status| {
+                        status.entry_point == entry_point && status.issues.contains(&issue)
+                    })
+                })
+            };
+            let mut issues = Vec::new();
+
+            if let Some(code_reader) = &self.code_reader {
+                match code_reader.has_code(entry_point).await {
+                    Ok(true) => {}
+                    Ok(false) => issues.push(EntryPointIssue::NoCode),
+                    Err(e) => {
+                        warn!("EntryPoint {} code check failed: {}", entry_point, e);
+                        if had(EntryPointIssue::NoCode) {
+                            issues.push(EntryPointIssue::NoCode);
+                        }
+                    }
+                }
+            }
+
+            match &pool_entry_points {
+                Some(Ok(served)) if !served.contains(&entry_point) => {
+                    issues.push(EntryPointIssue::NotInPool)
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    warn!("EntryPoint {} pool check failed: {}", entry_point, e);
+                    if had(EntryPointIssue::NotInPool) {
+                        issues.push(EntryPointIssue::NotInPool);
+                    }
+                }
+                None => issues.push(EntryPointIssue::NoPool),
+            }
+
+            // Without an estimator stub estimates are served for any EntryPoint
+            if self
+                .gas_estimator
+                .as_ref()
+                .is_some_and(|estimator| !estimator.supports(entry_point))
+            {
+                issues.push(EntryPointIssue::NoEstimator);
+            }
+
+            match &self.paymaster_service {
+                // Without EntryPoint routing the paymaster signs for any EntryPoint
+                Some(service) => {
+                    if let Ok(supported) = service.supported_entry_points() {
+                        if !supported.iter().any(|ep| ep.entry_point == entry_point) {
+                            issues.push(EntryPointIssue::NotSponsored);
+                        }
+                    }
+                }
+                None => issues.push(EntryPointIssue::NoPaymaster),
+            }
+
+            statuses.push(issues.into_iter().fold(
+                EntryPointStatus::new(
+                    entry_point,
+                    self.version_selector.declared_version(entry_point),
+                ),
+                EntryPointStatus::with_issue,
+            ));
+        }
+
+        if self.entry_point_status.record(
+            self.chain_id,
+            &self.supported_entry_points,
+            statuses.clone(),
+            &self.alerts,
+        ) {
+            self.response_cache.invalidate("eth_supportedEntryPoints");
+        }
+        statuses
+    }
+
+    /// Re-check the EntryPoints every `interval`, the first time after one interval
+    pub fn spawn_entry_point_checks(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let router = self.clone();
+        tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            loop {
+                ticker.tick().await;
+                router.check_entry_points().await;
+            }
+        })
+    }
+
+    /// ChainSpec UserOperations are parsed with, so their hashes bind to this chain
+    fn chain_spec(&self) -> ChainSpec {
+        ChainSpec {
+            id: self.chain_id,
+            signature_aggregators: self.signature_aggregators.clone(),
+            ..(*self.chain_spec).clone()
+        }
+    }
+
+    /// Reject operations computed for a different chain than the one selected
+    fn ensure_routed_chain(&self, user_op: &UserOperationVariant) -> GatewayResult<()> {
+        if user_op.chain_id() != self.chain_id {
+            return Err(GatewayError::InvalidRequest(format!(
+                "UserOperation is for chain {} but was routed to chain {}",
+                user_op.chain_id(),
+                self.chain_id
+            )));
         }
+        Ok(())
     }
 
     /// Default EntryPoint addresses (commonly used ones)
@@ -113,9 +582,93 @@ impl GatewayRouter {
 
         match request.method.as_str() {
             "pm_sponsorUserOperation" => {
-                self.handle_sponsor_user_operation(paymaster_service, &request.params)
+                self.handle_sponsor_user_operation(paymaster_service, request)
+                    .await
+            }
+            "pm_sponsorUserOperationERC20" => {
+                self.handle_sponsor_user_operation_erc20(paymaster_service, request)
+                    .await
+            }
+            "pm_sponsorUserOperationBatch" => {
+                self.handle_sponsor_user_operation_batch(paymaster_service, request)
                     .await
             }
+            "pm_getDepositInfo" => {
+                self.handle_get_deposit_info(paymaster_service, request)
+                    .await
+            }
+            "pm_depositTo" => self.handle_deposit_to(paymaster_service, request).await,
+            "pm_withdrawTo" => self.handle_withdraw_to(paymaster_service, request).await,
+            "pm_getOutboundTx" => Self::handle_get_outbound_tx(paymaster_service, request),
+            "pm_simulateSponsorship" => {
+                self.handle_simulate_sponsorship(paymaster_service, request)
+                    .await
+            }
+            "pm_invalidateSbtCache" => {
+                Self::handle_invalidate_sbt_cache(paymaster_service, request)
+            }
+            "pm_rotatePaymasterKey" => {
+                Self::handle_rotate_paymaster_key(paymaster_service, request).await
+            }
+            "pm_getSupportedEntryPoints" => match paymaster_service.supported_entry_points() {
+                Ok(mut supported) => {
+                    supported.retain(|ep| {
+                        self.entry_point_status
+                            .status(ep.entry_point)
+                            .is_none_or(|status| status.sponsored())
+                    });
+                    serde_json::to_value(supported)
+                        .map_err(|e| GatewayError::InternalError(e.to_string()))
+                }
+                Err(PaymasterError::InvalidRequest(message)) => {
+                    Err(GatewayError::InvalidRequest(message))
+                }
+                Err(e) => Err(GatewayError::PaymasterError(e.to_string())),
+            },
+            "pm_getSignerStatus" => {
+                let status = paymaster_service.signer_status().await;
+                serde_json::to_value(status).map_err(|e| GatewayError::InternalError(e.to_string()))
+            }
+            "pm_drainSigner" => Self::handle_drain_signer(paymaster_service, request).await,
+            "pm_getUsageReport" => Self::handle_get_usage_report(paymaster_service, request),
+            "pm_getSponsorshipRecord" => {
+                Self::handle_get_sponsorship_record(paymaster_service, request)
+            }
+            "pm_listSponsorships" => Self::handle_list_sponsorships(paymaster_service, request),
+            "pm_getBalanceStatus" => {
+                let status = paymaster_service
+                    .balance_status()
+                    .map_err(|e| GatewayError::PaymasterError(e.to_string()))?;
+                serde_json::to_value(status).map_err(|e| GatewayError::InternalError(e.to_string()))
+            }
+            "pm_loadCandidatePolicy" => {
+                Self::handle_load_candidate_policy(paymaster_service, request)
+            }
+            "pm_getPolicyShadowReport" => {
+                serde_json::to_value(paymaster_service.policy_shadow_report())
+                    .map_err(|e| GatewayError::InternalError(e.to_string()))
+            }
+            "pm_promoteCandidatePolicy" => {
+                info!(
+                    "Promoting the candidate policy (requested by {:?})",
+                    request.api_key_id
+                );
+                let report = paymaster_service
+                    .promote_candidate_policy()
+                    .map_err(|e| match e {
+                        PaymasterError::InvalidRequest(message) => {
+                            GatewayError::InvalidRequest(message)
+                        }
+                        e => GatewayError::PaymasterError(e.to_string()),
+                    })?;
+                serde_json::to_value(report).map_err(|e| GatewayError::InternalError(e.to_string()))
+            }
+            "pm_getAttestationKey" => {
+                let address = paymaster_service
+                    .attestation_address()
+                    .map_err(|e| GatewayError::PaymasterError(e.to_string()))?;
+                Ok(json!(address.to_checksum(None)))
+            }
             _ => Err(GatewayError::InvalidRequest(format!(
                 "Unknown paymaster method: {}",
                 request.method
@@ -128,19 +681,26 @@ impl GatewayRouter {
         debug!("Routing to rundler: {}", request.method);
 
         match request.method.as_str() {
-            "eth_supportedEntryPoints" => self.get_supported_entry_points(),
-            "eth_chainId" => self.get_chain_id(),
-            "eth_estimateUserOperationGas" => {
-                if let Some(pool) = &self.pool_handle {
-                    self.estimate_user_operation_gas_with_pool(pool, request)
-                        .await
-                } else {
-                    self.estimate_user_operation_gas_fallback(request).await
-                }
+            "eth_supportedEntryPoints" => {
+                self.response_cache
+                    .permanent("eth_supportedEntryPoints", request.no_cache, || {
+                        self.get_supported_entry_points()
+                    })
             }
+            "eth_chainId" => self
+                .response_cache
+                .permanent("eth_chainId", request.no_cache, || self.get_chain_id()),
+            "eth_estimateUserOperationGas" => self.estimate_user_operation_gas(request).await,
             "eth_sendUserOperation" => {
                 if let Some(pool) = &self.pool_handle {
-                    self.send_user_operation_with_pool(pool, request).await
+                    let requested = RequestedPermissions::from_send_options(request.params.get(2))?;
+                    self.send_user_operation_with_pool(
+                        &pool.current()?,
+                        request,
+                        None,
+                        requested.as_ref(),
+                    )
+                    .await
                 } else {
                     warn!("Pool not available for eth_sendUserOperation");
                     Err(GatewayError::InvalidRequest(
@@ -150,20 +710,32 @@ impl GatewayRouter {
             }
             "eth_getUserOperationByHash" => {
                 if let Some(pool) = &self.pool_handle {
-                    self.get_user_operation_by_hash_with_pool(pool, request)
+                    self.get_user_operation_by_hash_with_pool(&pool.current()?, request)
                         .await
                 } else {
                     Ok(Value::Null) // Not found
                 }
             }
-            "eth_getUserOperationReceipt" => {
+            "eth_getUserOperationReceipt" => self.get_user_operation_receipt(request).await,
+            "rundler_getBundleStats" => self.get_bundle_stats(),
+            "rundler_getBundleByHash" => self.get_bundle_by_hash(request),
+            "rundler_sendBundleNow" if self.debug_api_enabled => self.send_bundle_now().await,
+            "rundler_getEntityReputation" => self.get_entity_reputation(request).await,
+            "rundler_listThrottledEntities" => self.list_throttled_entities(request).await,
+            "rundler_setReputation" if self.debug_api_enabled => self.set_reputation(request).await,
+            "eth_getUserOperationNonce" => {
                 if let Some(pool) = &self.pool_handle {
-                    self.get_user_operation_receipt_with_pool(pool, request)
+                    self.get_user_operation_nonce_with_pool(&pool.current()?, request)
                         .await
                 } else {
-                    Ok(Value::Null) // Not found
+                    Err(GatewayError::InvalidRequest(
+                        "Pool not available in gateway mode".to_string(),
+                    ))
                 }
             }
+            method if self.debug_api_enabled && method.starts_with("debug_bundler_") => {
+                self.route_debug_bundler(request).await
+            }
             _ => {
                 warn!("Unhandled rundler method: {}", request.method);
                 Err(GatewayError::UnsupportedMethod(request.method.clone()))
@@ -171,818 +743,3475 @@ impl GatewayRouter {
         }
     }
 
-    /// Handle pm_sponsorUserOperation method
-    async fn handle_sponsor_user_operation(
-        &self,
-        paymaster_service: &Arc<PaymasterRelayService>,
-        params: &[Value],
-    ) -> GatewayResult<Value> {
-        if params.len() != 2 {
-            return Err(GatewayError::InvalidRequest(
-                "pm_sponsorUserOperation requires exactly 2 parameters".to_string(),
-            ));
+    /// Route debug_bundler_* methods of the ERC-4337 bundler spec tests
+    async fn route_debug_bundler(&self, request: &JsonRpcRequest) -> GatewayResult<Value> {
+        match request.method.as_str() {
+            "debug_bundler_clearState" => {
+                self.debug_pool()?
+                    .debug_clear_state(true, true, true)
+                    .await
+                    .map_err(|e| GatewayError::PoolError(e.to_string()))?;
+                Ok(json!("ok"))
+            }
+            "debug_bundler_dumpMempool" => self.debug_dump_mempool(request).await,
+            "debug_bundler_sendBundleNow" => self.send_bundle_now().await,
+            "debug_bundler_setBundlingMode" => {
+                let mode: BundlingMode = request
+                    .params
+                    .first()
+                    .and_then(|mode| serde_json::from_value(mode.clone()).ok())
+                    .ok_or_else(|| {
+                        GatewayError::InvalidRequest(
+                            "Bundling mode must be \"auto\" or \"manual\"".to_string(),
+                        )
+                    })?;
+                self.debug_builder()?
+                    .debug_set_bundling_mode(mode)
+                    .await
+                    .map_err(|e| GatewayError::RundlerError(e.to_string()))?;
+                info!("Bundling mode set to {}", mode);
+                Ok(json!("ok"))
+            }
+            "debug_bundler_setReputation" => self.set_reputation(request).await,
+            _ => {
+                warn!("Unhandled debug method: {}", request.method);
+                Err(GatewayError::UnsupportedMethod(request.method.clone()))
+            }
         }
+    }
 
-        let _user_operation = &params[0];
-        let _entry_point = &params[1];
+    /// Pool handle for debug methods
+    fn debug_pool(&self) -> GatewayResult<Arc<LocalPoolHandle>> {
+        self.pool_handle
+            .as_ref()
+            .ok_or_else(|| {
+                GatewayError::InvalidRequest("Pool not available in gateway mode".to_string())
+            })?
+            .current()
+    }
 
-        debug!(
-            "Sponsoring UserOperation for entry point: {:?}",
-            _entry_point
-        );
+    /// Builder handle for bundling methods
+    fn debug_builder(&self) -> GatewayResult<&Arc<dyn Builder>> {
+        self.builder
+            .as_ref()
+            .ok_or_else(|| GatewayError::InvalidRequest("Builder not available".to_string()))
+    }
 
-        // 1. Parse EntryPoint address
-        let entry_point: Address = _entry_point
-            .as_str()
+    /// Bundle the pending operations now, returning the bundle transaction hash
+    async fn send_bundle_now(&self) -> GatewayResult<Value> {
+        let (tx_hash, _) = self
+            .debug_builder()?
+            .debug_send_bundle_now()
+            .await
+            .map_err(|e| GatewayError::RundlerError(e.to_string()))?;
+        Ok(json!(format!("{:#x}", tx_hash)))
+    }
+
+    /// Params: `[entryPoint]`; operations pending in the pool for that EntryPoint only
+    async fn debug_dump_mempool(&self, request: &JsonRpcRequest) -> GatewayResult<Value> {
+        let entry_point = match request.params.as_slice() {
+            [entry_point] => self.parse_sponsor_entry_point(entry_point)?,
+            _ => {
+                return Err(GatewayError::InvalidRequest(
+                    "debug_bundler_dumpMempool requires 1 parameter: entryPoint".to_string(),
+                ))
+            }
+        };
+
+        let ops = self
+            .debug_pool()?
+            .debug_dump_mempool(entry_point)
+            .await
+            .map_err(|e| GatewayError::PoolError(e.to_string()))?;
+        Ok(Value::Array(
+            ops.iter()
+                .filter(|op| op.entry_point == entry_point)
+                .map(|op| self.user_operation_to_json(&op.uo))
+                .collect(),
+        ))
+    }
+
+    /// Params: `[reputations, entryPoint]`, each reputation being
+    /// `{address, opsSeen, opsIncluded}` with counts as hex quantities or numbers
+    async fn set_reputation(&self, request: &JsonRpcRequest) -> GatewayResult<Value> {
+        let (reputations, entry_point) = match request.params.as_slice() {
+            [reputations, entry_point] => (reputations, entry_point),
+            _ => {
+                return Err(GatewayError::InvalidRequest(format!(
+                    "{} requires 2 parameters: reputations, entryPoint",
+                    request.method
+                )))
+            }
+        };
+        let entry_point = self.parse_sponsor_entry_point(entry_point)?;
+        let reputations = reputations
+            .as_array()
             .ok_or_else(|| {
-                GatewayError::InvalidRequest("Entry point must be a string".to_string())
+                GatewayError::InvalidRequest("Reputations must be an array".to_string())
             })?
-            .parse()
-            .map_err(|_| GatewayError::InvalidRequest("Invalid entry point address".to_string()))?;
+            .iter()
+            .map(Self::parse_reputation)
+            .collect::<GatewayResult<Vec<_>>>()?;
 
-        // 2. Validate entry point is supported
-        if !self.supported_entry_points.contains(&entry_point) {
-            return Err(GatewayError::InvalidRequest(format!(
-                "Unsupported entry point: {:#x}",
-                entry_point
-            )));
-        }
+        self.debug_pool()?
+            .debug_set_reputations(entry_point, reputations)
+            .await
+            .map_err(|e| GatewayError::PoolError(e.to_string()))?;
+        Ok(json!("ok"))
+    }
 
-        // 3. Parse UserOperation from JSON (simplified for now)
-        let user_op_variant = self.parse_user_operation_from_json(_user_operation, entry_point)?;
+    /// Parse one `{address, opsSeen, opsIncluded}` reputation entry
+    fn parse_reputation(value: &Value) -> GatewayResult<Reputation> {
+        let address = value
+            .get("address")
+            .and_then(Value::as_str)
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| {
+                GatewayError::InvalidRequest("Invalid reputation address".to_string())
+            })?;
+        let count = |field: &str| {
+            value
+                .get(field)
+                .and_then(|count| match count {
+                    Value::Number(n) => n.as_u64(),
+                    Value::String(s) => s
+                        .strip_prefix("0x")
+                        .map_or_else(|| s.parse().ok(), |hex| u64::from_str_radix(hex, 16).ok()),
+                    _ => None,
+                })
+                .ok_or_else(|| {
+                    GatewayError::InvalidRequest(format!("Invalid reputation {}", field))
+                })
+        };
+        Ok(Reputation {
+            address,
+            ops_seen: count("opsSeen")?,
+            ops_included: count("opsIncluded")?,
+        })
+    }
 
-        debug!(
-            "Parsed UserOperation variant: {:?}",
-            user_op_variant.entry_point()
-        );
+    /// Params: `[address, entryPoint?]`; the pool's reputation of the entity
+    /// on that EntryPoint, or on each EntryPoint of the pool
+    async fn get_entity_reputation(&self, request: &JsonRpcRequest) -> GatewayResult<Value> {
+        let (address, entry_point) =
+            match request.params.as_slice() {
+                [address] => (address, None),
+                [address, Value::Null] => (address, None),
+                [address, entry_point] => (address, Some(entry_point)),
+                _ => return Err(GatewayError::InvalidRequest(
+                    "rundler_getEntityReputation requires an address and an optional entryPoint"
+                        .to_string(),
+                )),
+            };
+        let address: Address = address
+            .as_str()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| GatewayError::InvalidRequest("Invalid entity address".to_string()))?;
+        let entry_point = entry_point
+            .map(|entry_point| self.parse_sponsor_entry_point(entry_point))
+            .transpose()?;
 
-        // 4. Data Integrity Check (第一个业务步骤: 数据的完备性检查)
-        debug!("🔍 Starting data integrity validation");
-        let data_integrity_checker = DataIntegrityChecker::new();
-        let entry_point_str = format!("{:#x}", entry_point);
+        let reputations =
+            entity_reputations(self.debug_pool()?.as_ref(), address, entry_point).await?;
+        serde_json::to_value(reputations).map_err(|e| GatewayError::InternalError(e.to_string()))
+    }
 
-        match data_integrity_checker
-            .validate_user_operation(&user_op_variant, &entry_point_str)
-            .await
-        {
-            Ok(validation_result) => {
-                if !validation_result.is_valid {
-                    error!(
-                        "❌ Data integrity validation failed: {}",
-                        validation_result.summary
-                    );
-                    return Err(GatewayError::ValidationError(format!(
-                        "Data integrity check failed: {} critical issues found: [{}]",
-                        validation_result.critical_issues.len(),
-                        validation_result.critical_issues.join(", ")
-                    )));
-                } else if !validation_result.warnings.is_empty() {
-                    warn!(
-                        "⚠️ Data integrity validation passed with warnings: {}",
-                        validation_result.warnings.join(", ")
-                    );
-                }
-                debug!(
-                    "✅ Data integrity validation passed (score: {}): {}",
-                    validation_result.validation_score, validation_result.summary
-                );
-            }
-            Err(e) => {
-                error!("💥 Data integrity validation error: {}", e);
-                return Err(GatewayError::InternalError(format!(
-                    "Data integrity validation system error: {}",
-                    e
-                )));
+    /// Params: `[entryPoint?]`; throttled and banned entities on that
+    /// EntryPoint, or on each EntryPoint of the pool
+    async fn list_throttled_entities(&self, request: &JsonRpcRequest) -> GatewayResult<Value> {
+        let entry_point = match request.params.as_slice() {
+            [] | [Value::Null] => None,
+            [entry_point] => Some(self.parse_sponsor_entry_point(entry_point)?),
+            _ => {
+                return Err(GatewayError::InvalidRequest(
+                    "rundler_listThrottledEntities takes an optional entryPoint".to_string(),
+                ))
             }
-        }
+        };
 
-        // 5. Authorization Check (第二个业务步骤: 资格检查)
-        debug!("🔐 Starting authorization and eligibility check");
-        let mut authorization_checker = AuthorizationChecker::new();
+        let throttled = throttled_entities(self.debug_pool()?.as_ref(), entry_point).await?;
+        serde_json::to_value(throttled).map_err(|e| GatewayError::InternalError(e.to_string()))
+    }
 
-        // Load authorization configuration
-        if let Err(e) = authorization_checker.load_configuration().await {
-            warn!("Failed to load authorization configuration: {}", e);
-            // Continue with default configuration
-        }
+    /// NDJSON export of the pool for GET /admin/pool/export
+    pub async fn export_pool(
+        &self,
+        query: &PoolExportQuery,
+    ) -> GatewayResult<impl Stream<Item = GatewayResult<axum::body::Bytes>> + Send> {
+        let pool = self.debug_pool()?;
+        let entry_points = export_entry_points(pool.as_ref(), query.entry_point).await?;
+        Ok(export_ndjson(
+            pool,
+            entry_points,
+            query.limit,
+            self.bundle_tracker.clone(),
+        ))
+    }
 
-        match authorization_checker
-            .check_authorization(&user_op_variant, &entry_point, None)
-            .await
-        {
-            Ok(authorization_result) => {
-                if !authorization_result.is_authorized {
-                    error!(
-                        "❌ Authorization check failed: {}",
-                        authorization_result.summary
-                    );
-                    return Err(GatewayError::ValidationError(format!(
-                        "Authorization check failed: {} blocking issues found: [{}]",
-                        authorization_result.blocking_issues.len(),
-                        authorization_result.blocking_issues.join(", ")
-                    )));
-                } else if !authorization_result.warnings.is_empty() {
-                    warn!(
-                        "⚠️ Authorization check passed with warnings: {}",
-                        authorization_result.warnings.join(", ")
-                    );
-                }
-                debug!(
-                    "✅ Authorization check passed (score: {}): {}",
-                    authorization_result.authorization_score, authorization_result.summary
-                );
+    /// CSV export of the sponsorship usage selected by `query`
+    pub fn export_usage(&self, query: &UsageExportQuery) -> GatewayResult<String> {
+        let paymaster_service = self.paymaster_service.as_ref().ok_or_else(|| {
+            GatewayError::InvalidRequest("Paymaster service not available".to_string())
+        })?;
+        let (from, to) = query.range();
+        paymaster_service
+            .usage_csv(
+                query.api_key_id.as_deref(),
+                from,
+                to,
+                query.granularity.unwrap_or(UsageGranularity::Hour),
+            )
+            .map_err(|e| GatewayError::InvalidRequest(e.to_string()))
+    }
+
+    /// Route SuperRelay-specific methods
+    pub async fn route_to_super_relay(&self, request: &JsonRpcRequest) -> GatewayResult<Value> {
+        debug!("Routing to super relay: {}", request.method);
+
+        match request.method.as_str() {
+            "superRelay_getVerificationProof" => self.get_verification_proof(request),
+            "superRelay_getPipelineStats" => self.get_pipeline_stats(),
+            "superRelay_getOpInclusionStatus" => self.get_op_inclusion_status(request),
+            "superRelay_poolSummary" => pool_summary(self.debug_pool()?.as_ref()).await,
+            "superRelay_getUserOperationGasPrice" => self.get_user_operation_gas_price().await,
+            "superRelay_getErrorCatalog" => Ok(error_catalog()),
+            "superRelay_getEntryPointStatus" => self.get_entry_point_status().await,
+            "superRelay_sendUserOperationConditional" => {
+                self.send_user_operation_conditional(request).await
             }
-            Err(e) => {
-                error!("💥 Authorization check error: {}", e);
-                return Err(GatewayError::InternalError(format!(
-                    "Authorization check system error: {}",
-                    e
-                )));
+            "superRelay_simulateBundle" => self.simulate_bundle(request).await,
+            "superRelay_getDummyPaymasterData" => self.get_dummy_paymaster_data(request).await,
+            _ => {
+                warn!("Unhandled super relay method: {}", request.method);
+                Err(GatewayError::UnsupportedMethod(request.method.clone()))
             }
         }
+    }
 
-        // 6. Security Check (第三个业务步骤: 安全性检查)
-        debug!("🔒 Starting security analysis");
-        let mut security_checker = SecurityChecker::new();
+    /// Handle superRelay_sendUserOperationConditional: eth_sendUserOperation
+    /// whose bundle is only included while `expectedStorage` holds
+    async fn send_user_operation_conditional(
+        &self,
+        request: &JsonRpcRequest,
+    ) -> GatewayResult<Value> {
+        let expected_storage = request.params.get(2).ok_or_else(|| {
+            GatewayError::InvalidParams(
+                "superRelay_sendUserOperationConditional requires 3 parameters: userOp, entryPoint, expectedStorage"
+                    .to_string(),
+            )
+        })?;
+        let expected_storage = self
+            .conditional_send
+            .parse_expected_storage(expected_storage)?;
+        let Some(pool) = &self.pool_handle else {
+            return Err(GatewayError::InvalidRequest(
+                "Pool not available in gateway mode".to_string(),
+            ));
+        };
+        self.send_user_operation_with_pool(&pool.current()?, request, Some(expected_storage), None)
+            .await
+    }
 
-        // Load threat intelligence data
-        if let Err(e) = security_checker.load_threat_intelligence().await {
-            warn!("Failed to load threat intelligence: {}", e);
-            // Continue with default security configuration
-        }
+    /// Handle superRelay_simulateBundle. Params: `[entryPoint, userOps, block?]`,
+    /// each of `userOps` a pooled operation's hash or a UserOperation
+    ///
+    /// Hashes no longer in the pool are reported skipped in their place.
+    async fn simulate_bundle(&self, request: &JsonRpcRequest) -> GatewayResult<Value> {
+        let (entry_point, user_ops, block) = match request.params.as_slice() {
+            [entry_point, user_ops] => (entry_point, user_ops, None),
+            [entry_point, user_ops, block] => (entry_point, user_ops, Some(block)),
+            _ => return Err(GatewayError::InvalidParams(
+                "superRelay_simulateBundle requires 2 or 3 parameters: entryPoint, userOps, block"
+                    .to_string(),
+            )),
+        };
+        let entry_point = self.parse_sponsor_entry_point(entry_point)?;
+        let simulator = self
+            .bundle_simulator
+            .as_ref()
+            .filter(|simulator| simulator.supports(entry_point))
+            .ok_or_else(|| {
+                GatewayError::InvalidRequest(format!(
+                    "Bundle simulation is not available for entry point {:#x}",
+                    entry_point
+                ))
+            })?;
+        let block = block
+            .map(|block| {
+                serde_json::from_value(block.clone())
+                    .map_err(|e| GatewayError::InvalidParams(format!("params[2]: {}", e)))
+            })
+            .transpose()?;
+        let user_ops = user_ops
+            .as_array()
+            .filter(|ops| !ops.is_empty() && ops.len() <= MAX_SIMULATED_OPS)
+            .ok_or_else(|| {
+                GatewayError::InvalidParams(format!(
+                    "params[1]: userOps must be 1 to {} userOpHashes or UserOperations",
+                    MAX_SIMULATED_OPS
+                ))
+            })?;
 
-        match security_checker
-            .check_security(&user_op_variant, &entry_point, None)
-            .await
-        {
-            Ok(security_result) => {
-                if !security_result.is_secure {
-                    error!("🚨 Security check failed: {}", security_result.summary);
-                    return Err(GatewayError::ValidationError(format!(
-                        "Security check failed: {} critical violations found: [{}]",
-                        security_result.critical_violations.len(),
-                        security_result.critical_violations.join(", ")
-                    )));
-                } else if !security_result.warnings.is_empty() {
-                    warn!(
-                        "⚠️ Security check passed with warnings: {}",
-                        security_result.warnings.join(", ")
-                    );
+        // Skipped hashes by position; the simulated operations fill the rest in order
+        let mut ops = Vec::new();
+        let mut skipped = Vec::new();
+        for user_op in user_ops {
+            let Some(hash) = user_op.as_str() else {
+                ops.push(self.parse_user_operation_from_json(user_op, entry_point)?);
+                skipped.push(None);
+                continue;
+            };
+            let hash: B256 = hash.parse().map_err(|_| {
+                GatewayError::InvalidParams(format!("params[1]: invalid userOpHash {}", hash))
+            })?;
+            let pooled = self
+                .debug_pool()?
+                .get_op_by_hash(hash)
+                .await
+                .map_err(|e| GatewayError::PoolError(e.to_string()))?;
+            match pooled {
+                Some(op) if op.entry_point == entry_point => {
+                    ops.push(op.uo.clone());
+                    skipped.push(None);
                 }
-                debug!(
-                    "✅ Security check passed (score: {}): {}",
-                    security_result.security_score, security_result.summary
-                );
-            }
-            Err(e) => {
-                error!("💥 Security check error: {}", e);
-                return Err(GatewayError::InternalError(format!(
-                    "Security check system error: {}",
-                    e
-                )));
+                Some(op) => skipped.push(Some(OpSimulation::skipped(
+                    hash,
+                    format!("pooled for entry point {:#x}", op.entry_point),
+                ))),
+                None => skipped.push(Some(OpSimulation::skipped(hash, "not in the pool"))),
             }
         }
 
-        // 7. Call paymaster service for sponsorship
-        // Convert alloy Address to ethers H160 for compatibility
-        let ethers_entry_point = H160::from_slice(entry_point.as_slice());
-        match paymaster_service
-            .sponsor_user_operation(user_op_variant, ethers_entry_point)
-            .await
-        {
-            Ok(sponsor_result) => {
-                debug!("Sponsorship successful");
+        let mut simulation = simulator.simulate_bundle(entry_point, ops, block).await?;
+        let mut simulated = std::mem::take(&mut simulation.ops).into_iter();
+        simulation.ops = skipped
+            .into_iter()
+            .filter_map(|skipped| skipped.or_else(|| simulated.next()))
+            .collect();
+        serde_json::to_value(simulation).map_err(|e| {
+            GatewayError::InternalError(format!("Failed to serialize bundle simulation: {}", e))
+        })
+    }
 
-                // Convert PaymasterSponsorResult to JSON response
-                let mut response = json!({
-                    "paymasterAndData": format!("0x{}", hex::encode(&sponsor_result.paymaster_and_data))
-                });
+    /// Handle superRelay_getVerificationProof: the stored proof, or null when unknown
+    fn get_verification_proof(&self, request: &JsonRpcRequest) -> GatewayResult<Value> {
+        let hash = request
+            .params
+            .first()
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                GatewayError::InvalidRequest(
+                    "superRelay_getVerificationProof requires 1 parameter: userOpHash".to_string(),
+                )
+            })?;
+        let user_op_hash: B256 = hash
+            .parse()
+            .map_err(|_| GatewayError::InvalidRequest(format!("Invalid userOpHash: {}", hash)))?;
 
-                // Add optional gas limits if present
-                if let Some(verification_gas) = sponsor_result.verification_gas_limit {
-                    response["paymasterVerificationGasLimit"] =
-                        json!(format!("0x{:x}", verification_gas));
-                }
-                if let Some(post_op_gas) = sponsor_result.post_op_gas_limit {
-                    response["paymasterPostOpGasLimit"] = json!(format!("0x{:x}", post_op_gas));
-                }
-                if let Some(pre_verification_gas) = sponsor_result.pre_verification_gas {
-                    response["preVerificationGas"] = json!(format!("0x{:x}", pre_verification_gas));
-                }
-                if let Some(verification_gas_limit_uo) = sponsor_result.verification_gas_limit_uo {
-                    response["verificationGasLimit"] =
-                        json!(format!("0x{:x}", verification_gas_limit_uo));
-                }
-                if let Some(call_gas_limit) = sponsor_result.call_gas_limit {
-                    response["callGasLimit"] = json!(format!("0x{:x}", call_gas_limit));
+        let verification_proofs = self.verification_proofs.as_ref().ok_or_else(|| {
+            GatewayError::InvalidRequest(
+                "Verification proofs are not recorded by this gateway".to_string(),
+            )
+        })?;
+
+        match verification_proofs.get(&user_op_hash) {
+            Some(proof) => serde_json::to_value(proof).map_err(|e| {
+                GatewayError::InternalError(format!("Failed to serialize proof: {}", e))
+            }),
+            None => Ok(Value::Null),
+        }
+    }
+
+    /// Handle superRelay_getDummyPaymasterData: paymaster fields with a stub
+    /// signature, sized like a real sponsorship on the EntryPoint
+    async fn get_dummy_paymaster_data(&self, request: &JsonRpcRequest) -> GatewayResult<Value> {
+        let entry_point = request
+            .params
+            .first()
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                GatewayError::InvalidRequest(
+                    "superRelay_getDummyPaymasterData requires 1 parameter: entryPoint".to_string(),
+                )
+            })?;
+        let entry_point: Address = entry_point.parse().map_err(|_| {
+            GatewayError::InvalidRequest(format!("Invalid entryPoint: {}", entry_point))
+        })?;
+        let dummy = self.dummy_paymaster_data(entry_point).await?;
+        serde_json::to_value(dummy).map_err(|e| GatewayError::InternalError(e.to_string()))
+    }
+
+    async fn dummy_paymaster_data(
+        &self,
+        entry_point: Address,
+    ) -> GatewayResult<DummyPaymasterData> {
+        let paymaster_service = self.paymaster_service.as_ref().ok_or_else(|| {
+            GatewayError::InvalidRequest("Paymaster service not available".to_string())
+        })?;
+        paymaster_service
+            .dummy_paymaster_data(entry_point)
+            .await
+            .map_err(|e| match e {
+                PaymasterError::InvalidRequest(message) => GatewayError::InvalidRequest(message),
+                e @ PaymasterError::UnsupportedEntryPoint { .. } => {
+                    GatewayError::InvalidRequest(e.to_string())
                 }
+                e => GatewayError::PaymasterError(e.to_string()),
+            })
+    }
 
-                Ok(response)
-            }
-            Err(e) => {
-                error!("Sponsorship failed: {:?}", e);
-                Err(GatewayError::PaymasterError(format!(
-                    "Sponsorship failed: {}",
-                    e
-                )))
-            }
+    /// `user_op` with the dummy paymaster fields of `entry_point` in place of
+    /// its own when it carries `"sponsorshipIntent": true`, so the estimate
+    /// covers the paymaster data and verification added by sponsorship
+    async fn with_sponsorship_intent(
+        &self,
+        user_op: &Value,
+        entry_point: Address,
+    ) -> GatewayResult<Option<Value>> {
+        let Some(fields) = user_op.as_object() else {
+            return Ok(None);
+        };
+        if fields.get("sponsorshipIntent").and_then(Value::as_bool) != Some(true) {
+            return Ok(None);
+        }
+
+        let dummy = self.dummy_paymaster_data(entry_point).await?;
+        let paymaster_fields = match &dummy.paymaster_and_data {
+            Some(paymaster_and_data) => json!({ "paymasterAndData": paymaster_and_data }),
+            None => json!({
+                "paymaster": dummy.paymaster,
+                "paymasterVerificationGasLimit": dummy.paymaster_verification_gas_limit,
+                "paymasterPostOpGasLimit": dummy.paymaster_post_op_gas_limit,
+                "paymasterData": dummy.paymaster_data,
+            }),
+        };
+        let mut fields = fields.clone();
+        fields.remove("sponsorshipIntent");
+        if let Value::Object(paymaster_fields) = paymaster_fields {
+            fields.extend(paymaster_fields);
         }
+        Ok(Some(Value::Object(fields)))
     }
 
-    // === Rundler component integration methods ===
+    /// Handle superRelay_getUserOperationGasPrice: slow, standard and fast fees
+    /// for the latest block
+    async fn get_user_operation_gas_price(&self) -> GatewayResult<Value> {
+        let oracle = self.gas_price_oracle.as_ref().ok_or_else(|| {
+            GatewayError::InvalidRequest(
+                "Gas price suggestions are not available on this gateway".to_string(),
+            )
+        })?;
+        serde_json::to_value(oracle.quote().await?).map_err(|e| {
+            GatewayError::InternalError(format!("Failed to serialize gas price: {}", e))
+        })
+    }
 
-    /// Get supported EntryPoint addresses
-    fn get_supported_entry_points(&self) -> GatewayResult<Value> {
-        let entry_points: Vec<String> = self
-            .supported_entry_points
-            .iter()
-            .map(|addr| format!("{:#x}", addr))
-            .collect();
+    /// Handle superRelay_getPipelineStats: per-module counters in pipeline order
+    fn get_pipeline_stats(&self) -> GatewayResult<Value> {
+        Ok(json!({
+            "modules": self.pipeline.module_names(),
+            "stats": self.pipeline.stats().snapshot(),
+            "evictedModules": self.pipeline.stats().evictions(),
+        }))
+    }
 
-        debug!("Returning supported entry points: {:?}", entry_points);
-        Ok(json!(entry_points))
+    fn bundle_tracker(&self) -> GatewayResult<&BundleTracker> {
+        self.bundle_tracker.as_deref().ok_or_else(|| {
+            GatewayError::InvalidRequest(
+                "Bundle tracking is not enabled on this gateway".to_string(),
+            )
+        })
     }
 
-    /// Get chain ID
-    fn get_chain_id(&self) -> GatewayResult<Value> {
-        let chain_id_hex = format!("0x{:x}", self.chain_id);
-        debug!("Returning chain ID: {}", chain_id_hex);
-        Ok(json!(chain_id_hex))
+    /// Handle rundler_getBundleStats: bundle counts over the tracking window and
+    /// the latest bundle attempts
+    fn get_bundle_stats(&self) -> GatewayResult<Value> {
+        let tracker = self.bundle_tracker()?;
+        let mut stats = serde_json::to_value(tracker.stats()).map_err(|e| {
+            GatewayError::InternalError(format!("Failed to serialize bundle stats: {}", e))
+        })?;
+        stats["recentBundles"] = json!(tracker.recent_bundles(RECENT_BUNDLES));
+        Ok(stats)
     }
 
-    /// Estimate user operation gas using real pool component
-    async fn estimate_user_operation_gas_with_pool(
+    /// Handle rundler_getBundleByHash: the tracked bundle sent as `txHash`, or
+    /// null when unknown
+    fn get_bundle_by_hash(&self, request: &JsonRpcRequest) -> GatewayResult<Value> {
+        let tx_hash = Self::hash_param(request, "rundler_getBundleByHash", "txHash")?;
+        Ok(json!(self.bundle_tracker()?.bundle_by_hash(tx_hash)))
+    }
+
+    /// Handle superRelay_getOpInclusionStatus: what the builders did with an
+    /// operation, or null when no builder has reported on it
+    fn get_op_inclusion_status(&self, request: &JsonRpcRequest) -> GatewayResult<Value> {
+        let user_op_hash =
+            Self::hash_param(request, "superRelay_getOpInclusionStatus", "userOpHash")?;
+        Ok(json!(self.bundle_tracker()?.op_status(user_op_hash)))
+    }
+
+    /// The first parameter of `request`, a 32 byte hash
+    fn hash_param(request: &JsonRpcRequest, method: &str, name: &str) -> GatewayResult<B256> {
+        let hash = request
+            .params
+            .first()
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                GatewayError::InvalidRequest(format!("{} requires 1 parameter: {}", method, name))
+            })?;
+        hash.parse()
+            .map_err(|_| GatewayError::InvalidRequest(format!("Invalid {}: {}", name, hash)))
+    }
+
+    /// Handle pm_getDepositInfo method
+    async fn handle_get_deposit_info(
         &self,
-        _pool: &Arc<LocalPoolHandle>,
+        paymaster_service: &Arc<PaymasterRelayService>,
         request: &JsonRpcRequest,
     ) -> GatewayResult<Value> {
-        if request.params.len() < 2 {
+        let params = &request.params;
+        if params.len() != 1 {
             return Err(GatewayError::InvalidRequest(
-                "Missing parameters".to_string(),
+                "pm_getDepositInfo requires 1 parameter: entryPoint".to_string(),
             ));
         }
+        let entry_point = self.parse_deposit_entry_point(&params[0])?;
 
-        let _user_op = &request.params[0];
-        let entry_point = &request.params[1];
-
-        debug!(
-            "Estimating gas with pool for entry point: {:?}",
-            entry_point
-        );
-
-        // TODO: Use actual pool.estimate_user_operation_gas() method
-        // For now, return reasonable estimates
-        Ok(json!({
-            "preVerificationGas": "0x5208",      // 21,000
-            "verificationGasLimit": "0x186A0",   // 100,000
-            "callGasLimit": "0x186A0",           // 100,000
-            "paymasterVerificationGasLimit": null,
-            "paymasterPostOpGasLimit": null
-        }))
+        let info = paymaster_service
+            .get_deposit_info(entry_point)
+            .await
+            .map_err(|e| GatewayError::PaymasterError(e.to_string()))?;
+        serde_json::to_value(info).map_err(|e| GatewayError::InternalError(e.to_string()))
     }
 
-    /// Fallback gas estimation without pool
-    async fn estimate_user_operation_gas_fallback(
+    /// Handle pm_depositTo method
+    async fn handle_deposit_to(
         &self,
+        paymaster_service: &Arc<PaymasterRelayService>,
         request: &JsonRpcRequest,
     ) -> GatewayResult<Value> {
-        if request.params.len() < 2 {
+        let params = &request.params;
+        if params.len() != 2 {
             return Err(GatewayError::InvalidRequest(
-                "Missing parameters".to_string(),
+                "pm_depositTo requires 2 parameters: entryPoint, amount".to_string(),
             ));
         }
+        let entry_point = self.parse_deposit_entry_point(&params[0])?;
+        let amount = Self::parse_wei_amount(&params[1])?;
 
-        debug!("Estimating gas with fallback method");
-
-        // Return conservative estimates
-        Ok(json!({
-            "preVerificationGas": "0x5208",      // 21,000
-            "verificationGasLimit": "0x186A0",   // 100,000
-            "callGasLimit": "0x186A0",           // 100,000
-        }))
+        info!(
+            "Depositing {} wei to entry point {:#x} (requested by {:?})",
+            amount, entry_point, request.api_key_id
+        );
+        let tx = paymaster_service
+            .deposit_to(entry_point, amount)
+            .await
+            .map_err(|e| GatewayError::PaymasterError(e.to_string()))?;
+        serde_json::to_value(tx).map_err(|e| GatewayError::InternalError(e.to_string()))
     }
 
-    /// Send user operation using real pool component
-    async fn send_user_operation_with_pool(
+    /// Handle pm_withdrawTo method
+    async fn handle_withdraw_to(
         &self,
-        _pool: &Arc<LocalPoolHandle>,
+        paymaster_service: &Arc<PaymasterRelayService>,
         request: &JsonRpcRequest,
     ) -> GatewayResult<Value> {
-        if request.params.len() < 2 {
+        let params = &request.params;
+        if params.len() != 3 {
             return Err(GatewayError::InvalidRequest(
-                "Missing parameters".to_string(),
+                "pm_withdrawTo requires 3 parameters: entryPoint, withdrawAddress, amount"
+                    .to_string(),
             ));
         }
+        let entry_point = self.parse_deposit_entry_point(&params[0])?;
+        let withdraw_address: Address = params[1]
+            .as_str()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| GatewayError::InvalidRequest("Invalid withdraw address".to_string()))?;
+        let amount = Self::parse_wei_amount(&params[2])?;
 
-        let user_op = &request.params[0];
-        let entry_point = &request.params[1];
-
-        debug!(
-            "Sending UserOperation with pool to entry point: {:?}",
-            entry_point
+        info!(
+            "Withdrawing {} wei from entry point {:#x} to {:#x} (requested by {:?})",
+            amount, entry_point, withdraw_address, request.api_key_id
         );
+        let tx = paymaster_service
+            .withdraw_to(entry_point, withdraw_address, amount)
+            .await
+            .map_err(|e| GatewayError::PaymasterError(e.to_string()))?;
+        serde_json::to_value(tx).map_err(|e| GatewayError::InternalError(e.to_string()))
+    }
 
-        // Validate entry point
-        let entry_point_addr: Address = entry_point
+    /// Handle pm_getOutboundTx method
+    fn handle_get_outbound_tx(
+        paymaster_service: &Arc<PaymasterRelayService>,
+        request: &JsonRpcRequest,
+    ) -> GatewayResult<Value> {
+        let params = &request.params;
+        if params.len() != 1 {
+            return Err(GatewayError::InvalidRequest(
+                "pm_getOutboundTx requires 1 parameter: txHash or id".to_string(),
+            ));
+        }
+        let key = params[0]
             .as_str()
-            .ok_or_else(|| GatewayError::InvalidRequest("Invalid entry point format".to_string()))?
-            .parse()
-            .map_err(|_| GatewayError::InvalidRequest("Invalid entry point address".to_string()))?;
+            .ok_or_else(|| GatewayError::InvalidRequest("Invalid txHash or id".to_string()))?;
 
-        if !self.supported_entry_points.contains(&entry_point_addr) {
-            return Err(GatewayError::InvalidRequest(format!(
-                "Unsupported entry point: {:#x}",
-                entry_point_addr
-            )));
+        match paymaster_service.outbound_tx(key) {
+            Ok(tx) => {
+                serde_json::to_value(tx).map_err(|e| GatewayError::InternalError(e.to_string()))
+            }
+            Err(PaymasterError::InvalidRequest(message)) => {
+                Err(GatewayError::InvalidRequest(message))
+            }
+            Err(e) => Err(GatewayError::PaymasterError(e.to_string())),
         }
+    }
 
-        // Parse UserOperation from JSON and call real pool.add_op()
-        let user_op_variant = self.parse_user_operation_from_json(user_op, entry_point_addr)?;
+    /// Handle pm_invalidateSbtCache method
+    fn handle_invalidate_sbt_cache(
+        paymaster_service: &Arc<PaymasterRelayService>,
+        request: &JsonRpcRequest,
+    ) -> GatewayResult<Value> {
+        let params = &request.params;
+        if params.len() != 1 {
+            return Err(GatewayError::InvalidRequest(
+                "pm_invalidateSbtCache requires 1 parameter: sender".to_string(),
+            ));
+        }
+        let sender: Address = params[0]
+            .as_str()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| GatewayError::InvalidRequest("Invalid sender address".to_string()))?;
 
-        debug!(
-            "Parsed UserOperation: sender={:?}, entry_point={:?}",
-            user_op_variant.sender(),
-            user_op_variant.entry_point()
+        info!(
+            "Invalidating cached SBT balances of {:#x} (requested by {:?})",
+            sender, request.api_key_id
         );
+        let invalidated = paymaster_service
+            .invalidate_sbt_cache(sender)
+            .map_err(|e| GatewayError::PaymasterError(e.to_string()))?;
+        Ok(json!(invalidated))
+    }
 
-        // Set appropriate permissions for user operations
-        let perms = UserOperationPermissions {
-            trusted: false,
-            max_allowed_in_pool_for_sender: Some(10),
-            underpriced_accept_pct: Some(10),
-            underpriced_bundle_pct: Some(0),
-            bundler_sponsorship: None,
+    /// Handle pm_getUsageReport method
+    fn handle_get_usage_report(
+        paymaster_service: &Arc<PaymasterRelayService>,
+        request: &JsonRpcRequest,
+    ) -> GatewayResult<Value> {
+        let params = &request.params;
+        if params.len() != 3 && params.len() != 4 {
+            return Err(GatewayError::InvalidRequest(
+                "pm_getUsageReport requires apiKeyId, fromTimestamp, toTimestamp and an optional granularity"
+                    .to_string(),
+            ));
+        }
+        let api_key_id = params[0]
+            .as_str()
+            .ok_or_else(|| GatewayError::InvalidRequest("Invalid apiKeyId".to_string()))?;
+        let from = Self::parse_timestamp(&params[1])?;
+        let to = Self::parse_timestamp(&params[2])?;
+        let granularity = match params.get(3) {
+            None | Some(Value::Null) => UsageGranularity::Hour,
+            Some(value) => value.as_str().and_then(|s| s.parse().ok()).ok_or_else(|| {
+                GatewayError::InvalidRequest("granularity must be \"hour\" or \"day\"".to_string())
+            })?,
         };
 
-        // Submit operation to pool and get hash
-        let user_op_hash = _pool.add_op(user_op_variant, perms).await.map_err(|e| {
-            GatewayError::PoolError(format!("Failed to add operation to pool: {:?}", e))
-        })?;
-
-        // Return the real operation hash from pool
-        let hash_hex = format!("0x{:x}", user_op_hash);
-        debug!(
-            "✅ Successfully submitted UserOperation to pool: hash={}",
-            hash_hex
-        );
-        Ok(json!(hash_hex))
+        match paymaster_service.usage_report(api_key_id, from, to, granularity) {
+            Ok(buckets) => Ok(json!({
+                "apiKeyId": api_key_id,
+                "granularity": granularity.to_string(),
+                "buckets": buckets,
+            })),
+            Err(PaymasterError::InvalidRequest(message)) => {
+                Err(GatewayError::InvalidRequest(message))
+            }
+            Err(e) => Err(GatewayError::PaymasterError(e.to_string())),
+        }
     }
 
-    /// Get user operation by hash using real pool component
-    async fn get_user_operation_by_hash_with_pool(
-        &self,
-        _pool: &Arc<LocalPoolHandle>,
+    /// Handle pm_getSponsorshipRecord method
+    fn handle_get_sponsorship_record(
+        paymaster_service: &Arc<PaymasterRelayService>,
         request: &JsonRpcRequest,
     ) -> GatewayResult<Value> {
-        if request.params.is_empty() {
+        let params = &request.params;
+        if params.len() != 1 {
             return Err(GatewayError::InvalidRequest(
-                "Missing hash parameter".to_string(),
+                "pm_getSponsorshipRecord requires 1 parameter: userOpHash".to_string(),
             ));
         }
-
-        let hash = &request.params[0];
-        debug!("Looking up UserOperation by hash with pool: {:?}", hash);
-
-        // Parse hash from hex string
-        let hash_str = hash
+        let user_op_hash: B256 = params[0]
             .as_str()
-            .ok_or_else(|| GatewayError::InvalidRequest("Hash must be a string".to_string()))?;
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| GatewayError::InvalidRequest("Invalid userOpHash".to_string()))?;
 
-        let hash_bytes = if let Some(stripped) = hash_str.strip_prefix("0x") {
-            hex::decode(stripped)
-        } else {
-            hex::decode(hash_str)
+        match paymaster_service.sponsorship_record(user_op_hash) {
+            Ok(record) => {
+                serde_json::to_value(record).map_err(|e| GatewayError::InternalError(e.to_string()))
+            }
+            Err(PaymasterError::InvalidRequest(message)) => {
+                Err(GatewayError::InvalidRequest(message))
+            }
+            Err(e) => Err(GatewayError::PaymasterError(e.to_string())),
         }
-        .map_err(|_| GatewayError::InvalidRequest("Invalid hash format".to_string()))?;
+    }
 
-        if hash_bytes.len() != 32 {
+    /// Handle pm_listSponsorships method
+    fn handle_list_sponsorships(
+        paymaster_service: &Arc<PaymasterRelayService>,
+        request: &JsonRpcRequest,
+    ) -> GatewayResult<Value> {
+        let params = &request.params;
+        if params.len() < 2 || params.len() > 5 {
             return Err(GatewayError::InvalidRequest(
-                "Hash must be 32 bytes".to_string(),
+                "pm_listSponsorships requires fromTimestamp, toTimestamp and an optional status, page and pageSize"
+                    .to_string(),
             ));
         }
+        let status = match params.get(2) {
+            None | Some(Value::Null) => None,
+            Some(value) => Some(
+                value
+                    .as_str()
+                    .ok_or_else(|| GatewayError::InvalidRequest("Invalid status".to_string()))?
+                    .parse::<SponsorshipStatus>()
+                    .map_err(GatewayError::InvalidRequest)?,
+            ),
+        };
+        let count = |index: usize, name: &str, default: usize| match params.get(index) {
+            None | Some(Value::Null) => Ok(default),
+            Some(value) => value
+                .as_u64()
+                .map(|n| n as usize)
+                .ok_or_else(|| GatewayError::InvalidRequest(format!("Invalid {}", name))),
+        };
+        let query = SponsorshipQuery {
+            from: Self::parse_timestamp(&params[0])?,
+            to: Self::parse_timestamp(&params[1])?,
+            status,
+            page: count(3, "page", 0)?,
+            page_size: count(4, "pageSize", DEFAULT_PAGE_SIZE)?,
+        };
 
-        let hash_b256 = alloy_primitives::B256::from_slice(&hash_bytes);
-
-        // Use real pool.get_op_by_hash() method
-        match _pool.get_op_by_hash(hash_b256).await {
-            Ok(Some(pool_op)) => {
-                debug!(
-                    "✅ Found UserOperation in pool: sender={:?}",
-                    pool_op.uo.sender()
-                );
-                // Convert PoolOperation back to JSON format
-                Ok(json!({
-                    "userOperation": self.user_operation_to_json(&pool_op.uo),
-                    "entryPoint": format!("{:#x}", pool_op.entry_point),
-                    "blockNumber": null,
-                    "blockHash": null,
-                    "transactionHash": null
-                }))
-            }
-            Ok(None) => {
-                debug!("UserOperation not found in pool for hash: {}", hash_str);
-                Ok(Value::Null)
+        match paymaster_service.list_sponsorships(&query) {
+            Ok(page) => {
+                serde_json::to_value(page).map_err(|e| GatewayError::InternalError(e.to_string()))
             }
-            Err(e) => {
-                error!("Pool lookup error: {:?}", e);
-                Err(GatewayError::PoolError(format!(
-                    "Failed to lookup operation: {:?}",
-                    e
-                )))
+            Err(PaymasterError::InvalidRequest(message)) => {
+                Err(GatewayError::InvalidRequest(message))
             }
+            Err(e) => Err(GatewayError::PaymasterError(e.to_string())),
         }
     }
 
-    /// Get user operation receipt using real pool component
-    async fn get_user_operation_receipt_with_pool(
-        &self,
-        _pool: &Arc<LocalPoolHandle>,
+    /// Parse a unix timestamp given as a number or a decimal or 0x-hex string
+    fn parse_timestamp(value: &Value) -> GatewayResult<u64> {
+        value
+            .as_u64()
+            .or_else(|| {
+                value
+                    .as_str()
+                    .and_then(|s| s.parse::<U256>().ok())
+                    .and_then(|n| u64::try_from(n).ok())
+            })
+            .ok_or_else(|| GatewayError::InvalidRequest("Invalid timestamp".to_string()))
+    }
+
+    /// Handle pm_rotatePaymasterKey method
+    async fn handle_rotate_paymaster_key(
+        paymaster_service: &Arc<PaymasterRelayService>,
         request: &JsonRpcRequest,
     ) -> GatewayResult<Value> {
-        if request.params.is_empty() {
+        // Never anonymous, whatever the API key configuration
+        if request.api_key_id.is_none() {
+            return Err(GatewayError::AuthenticationFailed(
+                "pm_rotatePaymasterKey requires an authenticated admin key".to_string(),
+            ));
+        }
+        let params = &request.params;
+        if params.is_empty() || params.len() > 2 {
             return Err(GatewayError::InvalidRequest(
-                "Missing hash parameter".to_string(),
+                "pm_rotatePaymasterKey requires 1 parameter plus an optional graceSeconds: source"
+                    .to_string(),
             ));
         }
+        let source: KeySource = serde_json::from_value(params[0].clone()).map_err(|e| {
+            GatewayError::InvalidRequest(format!(
+                "Invalid key source, expected {{\"env\": name}} or {{\"kms\": keyId}}: {}",
+                e
+            ))
+        })?;
+        let grace = match params.get(1) {
+            None | Some(Value::Null) => None,
+            Some(value) => Some(Duration::from_secs(value.as_u64().ok_or_else(|| {
+                GatewayError::InvalidRequest("graceSeconds must be a number".to_string())
+            })?)),
+        };
 
-        let hash = &request.params[0];
-        debug!(
-            "Looking up UserOperation receipt by hash with pool: {:?}",
-            hash
+        info!(
+            "Rotating paymaster signing key from {:?} (requested by {:?})",
+            source, request.api_key_id
         );
+        let rotation = paymaster_service
+            .rotate_paymaster_key(&source, grace)
+            .await
+            .map_err(|e| match e {
+                PaymasterError::InvalidRequest(message) => GatewayError::InvalidRequest(message),
+                e => GatewayError::PaymasterError(e.to_string()),
+            })?;
+        serde_json::to_value(rotation).map_err(|e| GatewayError::InternalError(e.to_string()))
+    }
+
+    /// Handle pm_loadCandidatePolicy method
+    fn handle_load_candidate_policy(
+        paymaster_service: &Arc<PaymasterRelayService>,
+        request: &JsonRpcRequest,
+    ) -> GatewayResult<Value> {
+        let file = match request.params.as_slice() {
+            [Value::String(file)] => file,
+            _ => {
+                return Err(GatewayError::InvalidRequest(
+                    "pm_loadCandidatePolicy requires 1 parameter: file".to_string(),
+                ))
+            }
+        };
+
+        info!(
+            "Loading candidate policy {} (requested by {:?})",
+            file, request.api_key_id
+        );
+        let report = paymaster_service
+            .load_named_candidate_policy(file)
+            .map_err(|e| GatewayError::InvalidRequest(e.to_string()))?;
+        serde_json::to_value(report).map_err(|e| GatewayError::InternalError(e.to_string()))
+    }
+
+    /// Handle pm_drainSigner method
+    async fn handle_drain_signer(
+        paymaster_service: &Arc<PaymasterRelayService>,
+        request: &JsonRpcRequest,
+    ) -> GatewayResult<Value> {
+        let params = &request.params;
+        if params.is_empty() || params.len() > 2 {
+            return Err(GatewayError::InvalidRequest(
+                "pm_drainSigner requires 1 parameter plus an optional draining flag: signer"
+                    .to_string(),
+            ));
+        }
+        let signer: Address = params[0]
+            .as_str()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| GatewayError::InvalidRequest("Invalid signer address".to_string()))?;
+        let draining = match params.get(1) {
+            None | Some(Value::Null) => true,
+            Some(value) => value.as_bool().ok_or_else(|| {
+                GatewayError::InvalidRequest("draining must be a boolean".to_string())
+            })?,
+        };
+
+        info!(
+            "Setting paymaster signer {:#x} draining={} (requested by {:?})",
+            signer, draining, request.api_key_id
+        );
+        let status = paymaster_service
+            .drain_signer(signer, draining)
+            .await
+            .map_err(|e| GatewayError::PaymasterError(e.to_string()))?;
+        serde_json::to_value(status).map_err(|e| GatewayError::InternalError(e.to_string()))
+    }
+
+    /// Parse a supported EntryPoint address parameter of a deposit method
+    fn parse_deposit_entry_point(&self, value: &Value) -> GatewayResult<Address> {
+        let entry_point: Address =
+            value.as_str().and_then(|s| s.parse().ok()).ok_or_else(|| {
+                GatewayError::InvalidRequest("Invalid entry point address".to_string())
+            })?;
+        if !self.supported_entry_points.contains(&entry_point) {
+            return Err(GatewayError::InvalidRequest(format!(
+                "Unsupported entry point: {:#x}",
+                entry_point
+            )));
+        }
+        Ok(entry_point)
+    }
+
+    /// Parse a wei amount given as a decimal or 0x-hex string
+    fn parse_wei_amount(value: &Value) -> GatewayResult<U256> {
+        value
+            .as_str()
+            .and_then(|s| s.parse::<U256>().ok())
+            .ok_or_else(|| GatewayError::InvalidRequest("Invalid amount".to_string()))
+    }
+
+    /// Handle pm_sponsorUserOperation method
+    async fn handle_sponsor_user_operation(
+        &self,
+        paymaster_service: &Arc<PaymasterRelayService>,
+        request: &JsonRpcRequest,
+    ) -> GatewayResult<Value> {
+        let params = &request.params;
+        if params.len() != 2 && params.len() != 3 {
+            return Err(GatewayError::InvalidRequest(
+                "pm_sponsorUserOperation requires 2 parameters plus an optional options object"
+                    .to_string(),
+            ));
+        }
+
+        let mut sponsor_options = Self::parse_sponsor_options(params.get(2), request.debug)?;
+        sponsor_options.requester = request.api_key_id.clone();
+        sponsor_options.request_id = request.request_id.clone();
+
+        self.sponsor(
+            paymaster_service,
+            &params[0],
+            &params[1],
+            sponsor_options,
+            &request.deadline,
+        )
+        .await
+    }
+
+    /// Handle pm_sponsorUserOperationERC20 method
+    ///
+    /// Like pm_sponsorUserOperation, but the operation pays its gas in `token`
+    /// and the response carries the signed token quote.
+    async fn handle_sponsor_user_operation_erc20(
+        &self,
+        paymaster_service: &Arc<PaymasterRelayService>,
+        request: &JsonRpcRequest,
+    ) -> GatewayResult<Value> {
+        let params = &request.params;
+        if params.len() != 3 && params.len() != 4 {
+            return Err(GatewayError::InvalidRequest(
+                "pm_sponsorUserOperationERC20 requires 3 parameters plus an optional options object: userOperation, entryPoint, token"
+                    .to_string(),
+            ));
+        }
+
+        let token: Address = params[2]
+            .as_str()
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| {
+                GatewayError::InvalidRequest("Token must be an address string".to_string())
+            })?;
+        let mut sponsor_options = Self::parse_sponsor_options(params.get(3), request.debug)?;
+        sponsor_options.requester = request.api_key_id.clone();
+        sponsor_options.request_id = request.request_id.clone();
+        sponsor_options.token = Some(token);
+
+        self.sponsor(
+            paymaster_service,
+            &params[0],
+            &params[1],
+            sponsor_options,
+            &request.deadline,
+        )
+        .await
+    }
+
+    /// Handle pm_sponsorUserOperationBatch method
+    ///
+    /// Every operation is parsed and checked before any is signed. The policy
+    /// is then checked, and the deployments counted against the quotas, for
+    /// the remaining operations together, rejecting the batch unless all of
+    /// them fit; the deployments stay reserved until their operations are
+    /// signed. Each operation is signed like a single pm_sponsorUserOperation,
+    /// under its sender's lock: operations of one sender in batch order, those
+    /// of different senders concurrently. A failure at any stage rejects the
+    /// batch with the operation's index in atomic mode, and takes the
+    /// operation's place in the results in best-effort mode.
+    async fn handle_sponsor_user_operation_batch(
+        &self,
+        paymaster_service: &Arc<PaymasterRelayService>,
+        request: &JsonRpcRequest,
+    ) -> GatewayResult<Value> {
+        let params = &request.params;
+        if params.len() != 2 && params.len() != 3 {
+            return Err(GatewayError::InvalidRequest(
+                "pm_sponsorUserOperationBatch requires 2 parameters plus an optional options object: userOperations, entryPoint"
+                    .to_string(),
+            ));
+        }
+
+        let user_operations = self.batch_sponsorship.parse_operations(&params[0])?;
+        let entry_point = self.parse_sponsor_entry_point(&params[1])?;
+        let mode = self.batch_sponsorship.parse_mode(params.get(2))?;
+        let mut sponsor_options = Self::parse_sponsor_options(params.get(2), request.debug)?;
+        sponsor_options.requester = request.api_key_id.clone();
+        sponsor_options.request_id = request.request_id.clone();
+        let started = Instant::now();
+
+        let mut prepared = Vec::with_capacity(user_operations.len());
+        for (index, user_operation) in user_operations.iter().enumerate() {
+            let timings = sponsor_options
+                .timings
+                .as_ref()
+                .map(|_| StageTimings::default());
+            let user_op = self
+                .prepare_sponsorship(
+                    user_operation,
+                    entry_point,
+                    &request.deadline,
+                    timings.as_ref(),
+                )
+                .await;
+            match user_op {
+                Err(e) if mode == BatchSponsorshipMode::Atomic => {
+                    return Err(GatewayError::BatchRejected {
+                        index,
+                        error: Box::new(e),
+                    });
+                }
+                user_op => prepared.push((user_op, timings)),
+            }
+        }
+
+        let (indices, user_ops): (Vec<usize>, Vec<UserOperationVariant>) = prepared
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (user_op, _))| Some((index, user_op.as_ref().ok()?.clone())))
+            .unzip();
+        let reserved = match paymaster_service.reserve_batch_budget(&user_ops) {
+            Ok(reserved) => reserved,
+            Err(rejection) => {
+                return Err(GatewayError::BatchRejected {
+                    index: indices[rejection.index],
+                    error: Box::new(self.sponsorship_error(rejection.error)),
+                })
+            }
+        };
+        let mut reservations = vec![None; prepared.len()];
+        for (index, reservation) in indices.into_iter().zip(reserved) {
+            reservations[index] = reservation;
+        }
+
+        // Operations of one sender are signed in order, senders concurrently
+        let mut results: Vec<Option<GatewayResult<Value>>> = Vec::with_capacity(prepared.len());
+        let mut senders: Vec<Vec<(usize, UserOperationVariant, SponsorOptions)>> = Vec::new();
+        for (index, (user_op, timings)) in prepared.into_iter().enumerate() {
+            let user_op = match user_op {
+                Ok(user_op) => user_op,
+                Err(e) => {
+                    results.push(Some(Err(e)));
+                    continue;
+                }
+            };
+            results.push(None);
+            // One key per operation, a retried batch replays each sponsorship
+            let options = SponsorOptions {
+                idempotency_key: sponsor_options
+                    .idempotency_key
+                    .as_ref()
+                    .map(|key| format!("{}:{}", key, index)),
+                timings,
+                deployment: reservations[index].take(),
+                ..sponsor_options.clone()
+            };
+            let sender = user_op.sender();
+            let operation = (index, user_op, options);
+            match senders
+                .iter_mut()
+                .find(|operations| operations[0].1.sender() == sender)
+            {
+                Some(operations) => operations.push(operation),
+                None => senders.push(vec![operation]),
+            }
+        }
+
+        let signed = senders.into_iter().map(|operations| async move {
+            let mut signed = Vec::with_capacity(operations.len());
+            for (index, user_op, options) in operations {
+                let result = self
+                    .sign_sponsorship(
+                        paymaster_service,
+                        user_op,
+                        entry_point,
+                        options,
+                        &request.deadline,
+                        started,
+                    )
+                    .await;
+                match result {
+                    Err(e) if mode == BatchSponsorshipMode::Atomic => return Err((index, e)),
+                    result => signed.push((index, result)),
+                }
+            }
+            Ok(signed)
+        });
+        // Atomic batches stop signing at the first failure: the reservations of
+        // operations left unsigned are released when dropped, while
+        // sponsorships other senders completed meanwhile stay accounted
+        let signed = match future::try_join_all(signed).await {
+            Ok(signed) => signed,
+            Err((index, e)) => {
+                return Err(GatewayError::BatchRejected {
+                    index,
+                    error: Box::new(e),
+                })
+            }
+        };
+        for (index, result) in signed.into_iter().flatten() {
+            results[index] = Some(result);
+        }
+
+        Ok(Value::Array(
+            results
+                .into_iter()
+                .enumerate()
+                .map(|(index, result)| {
+                    batch_result(index, result.expect("every operation has a result"))
+                })
+                .collect(),
+        ))
+    }
+
+    /// Check and sponsor the UserOperation and EntryPoint parameters of a sponsorship method
+    async fn sponsor(
+        &self,
+        paymaster_service: &Arc<PaymasterRelayService>,
+        _user_operation: &Value,
+        _entry_point: &Value,
+        sponsor_options: SponsorOptions,
+        deadline: &Deadline,
+    ) -> GatewayResult<Value> {
+        debug!(
+            "Sponsoring UserOperation for entry point: {:?}",
+            _entry_point
+        );
+        let started = Instant::now();
+
+        // 1-2. Parse the EntryPoint address and check it is supported
+        let entry_point = self.parse_sponsor_entry_point(_entry_point)?;
+
+        let user_op_variant = self
+            .prepare_sponsorship(
+                _user_operation,
+                entry_point,
+                deadline,
+                sponsor_options.timings.as_ref(),
+            )
+            .await?;
+        self.sign_sponsorship(
+            paymaster_service,
+            user_op_variant,
+            entry_point,
+            sponsor_options,
+            deadline,
+            started,
+        )
+        .await
+    }
+
+    /// Parse a UserOperation for `entry_point` and run the gateway's checks on
+    /// it, leaving it ready to be signed
+    async fn prepare_sponsorship(
+        &self,
+        _user_operation: &Value,
+        entry_point: Address,
+        deadline: &Deadline,
+        timings: Option<&StageTimings>,
+    ) -> GatewayResult<UserOperationVariant> {
+        // 3. Parse UserOperation from JSON (simplified for now)
+        let user_op_variant = self.parse_user_operation_from_json(_user_operation, entry_point)?;
+        self.ensure_routed_chain(&user_op_variant)?;
+
+        debug!(
+            "Parsed UserOperation variant: {:?}",
+            user_op_variant.entry_point()
+        );
+
+        // 4-6. Data integrity, authorization and security checks
+        if let Err(e) = self
+            .run_sponsorship_checks(&user_op_variant, entry_point, deadline, timings)
+            .await
+        {
+            if let GatewayError::ValidationError(reason) = &e {
+                self.alerts
+                    .record_rejection(user_op_variant.sender(), reason);
+            }
+            return Err(e);
+        }
+        let stage_start = Instant::now();
+        let signature_check = self
+            .check_account_signature(&user_op_variant, true, deadline)
+            .await;
+        record_stage(
+            timings,
+            SponsorStage::AccountSignature,
+            stage_start.elapsed(),
+        );
+        signature_check?;
+        let stage_start = Instant::now();
+        let user_op_variant = deadline
+            .run(
+                "aggregator",
+                self.prepare_aggregated_operation(user_op_variant),
+            )
+            .await;
+        record_stage(timings, SponsorStage::Aggregator, stage_start.elapsed());
+        user_op_variant
+    }
+
+    /// Sign and account the sponsorship of a checked UserOperation, returning
+    /// the sponsorship response
+    async fn sign_sponsorship(
+        &self,
+        paymaster_service: &Arc<PaymasterRelayService>,
+        user_op_variant: UserOperationVariant,
+        entry_point: Address,
+        sponsor_options: SponsorOptions,
+        deadline: &Deadline,
+        started: Instant,
+    ) -> GatewayResult<Value> {
+        let timings = sponsor_options.timings.clone();
+
+        // 7. Call paymaster service for sponsorship
+        // Convert alloy Address to ethers H160 for compatibility
+        let ethers_entry_point = H160::from_slice(entry_point.as_slice());
+        let attest = sponsor_options.attest;
+        // Dropped before it returns, the sponsorship is neither signed nor accounted
+        let sponsorship = deadline
+            .run("paymaster", async {
+                Ok(paymaster_service
+                    .sponsor_user_operation(user_op_variant, ethers_entry_point, sponsor_options)
+                    .await)
+            })
+            .await?;
+        if sponsorship.is_ok() {
+            // Signed and accounted: the caller gets the sponsorship even past the deadline
+            deadline.commit();
+        }
+        match sponsorship {
+            Ok(sponsor_result) => {
+                debug!("Sponsorship successful");
+
+                // Convert PaymasterSponsorResult to JSON response
+                // validUntil/validAfter are exactly the timestamps signed into the paymaster data
+                let mut response = json!({
+                    "paymasterAndData": format!("0x{}", hex::encode(&sponsor_result.paymaster_and_data)),
+                    "validUntil": sponsor_result.valid_until,
+                    "validAfter": sponsor_result.valid_after,
+                });
+
+                // Add optional gas limits if present
+                if let Some(verification_gas) = sponsor_result.verification_gas_limit {
+                    response["paymasterVerificationGasLimit"] =
+                        json!(format!("0x{:x}", verification_gas));
+                }
+                if let Some(post_op_gas) = sponsor_result.post_op_gas_limit {
+                    response["paymasterPostOpGasLimit"] = json!(format!("0x{:x}", post_op_gas));
+                }
+                if let Some(pre_verification_gas) = sponsor_result.pre_verification_gas {
+                    response["preVerificationGas"] = json!(format!("0x{:x}", pre_verification_gas));
+                }
+                if let Some(verification_gas_limit_uo) = sponsor_result.verification_gas_limit_uo {
+                    response["verificationGasLimit"] =
+                        json!(format!("0x{:x}", verification_gas_limit_uo));
+                }
+                if let Some(call_gas_limit) = sponsor_result.call_gas_limit {
+                    response["callGasLimit"] = json!(format!("0x{:x}", call_gas_limit));
+                }
+                if let Some(max_fee_per_gas) = sponsor_result.max_fee_per_gas {
+                    response["maxFeePerGas"] = json!(format!("0x{:x}", max_fee_per_gas));
+                }
+                if let Some(max_priority_fee_per_gas) = sponsor_result.max_priority_fee_per_gas {
+                    response["maxPriorityFeePerGas"] =
+                        json!(format!("0x{:x}", max_priority_fee_per_gas));
+                }
+                if let Some(sponsored_user_op) = &sponsor_result.sponsored_user_op {
+                    response["userOperation"] = self.user_operation_to_json(sponsored_user_op);
+                }
+                if let Some(token_quote) = &sponsor_result.token_quote {
+                    response["tokenQuote"] = serde_json::to_value(token_quote)
+                        .map_err(|e| GatewayError::InternalError(e.to_string()))?;
+                }
+                // Same signature and window as an identical request moments ago
+                if sponsor_result.cached {
+                    response["cached"] = json!(true);
+                }
+                // Only requested by keys with the debug scope
+                if let Some(timings) = &timings {
+                    response["timings"] = timings.to_json(started.elapsed());
+                }
+
+                // Signed last, over everything else in the response
+                if attest {
+                    let attestation = paymaster_service
+                        .attest_response(&response)
+                        .map_err(|e| GatewayError::PaymasterError(e.to_string()))?;
+                    response["attestation"] = serde_json::to_value(attestation)
+                        .map_err(|e| GatewayError::InternalError(e.to_string()))?;
+                }
+
+                Ok(response)
+            }
+            Err(e) => Err(self.sponsorship_error(e)),
+        }
+    }
+
+    /// Gateway error of a sponsorship the paymaster service refused or failed
+    fn sponsorship_error(&self, error: PaymasterError) -> GatewayError {
+        match error {
+            PaymasterError::IdempotencyConflict(message) => {
+                warn!("Idempotency key conflict: {}", message);
+                GatewayError::IdempotencyConflict(message)
+            }
+            PaymasterError::InvalidRequest(message) => {
+                warn!("Invalid sponsorship request: {}", message);
+                GatewayError::InvalidRequest(message)
+            }
+            e @ PaymasterError::UnsupportedEntryPoint { .. } => {
+                warn!("Sponsorship refused: {}", e);
+                GatewayError::InvalidRequest(e.to_string())
+            }
+            e @ (PaymasterError::DeploymentQuotaExceeded { .. }
+            | PaymasterError::ConcurrentSponsorship(_)) => {
+                warn!("Sponsorship refused: {}", e);
+                GatewayError::SponsorshipFailed(e)
+            }
+            e => {
+                error!("Sponsorship failed: {:?}", e);
+                if let PaymasterError::SignerError(report) = &e {
+                    self.alerts.publish(Alert::new(
+                        AlertKind::SignerError,
+                        "paymaster_signer",
+                        format!("signing a sponsorship failed: {}", report),
+                    ));
+                }
+                GatewayError::SponsorshipFailed(e)
+            }
+        }
+    }
+
+    /// Handle pm_simulateSponsorship method
+    ///
+    /// Failed gateway checks are reported as a rejected preview, like the
+    /// paymaster's own policy checks.
+    async fn handle_simulate_sponsorship(
+        &self,
+        paymaster_service: &Arc<PaymasterRelayService>,
+        request: &JsonRpcRequest,
+    ) -> GatewayResult<Value> {
+        let params = &request.params;
+        if params.len() != 2 {
+            return Err(GatewayError::InvalidRequest(
+                "pm_simulateSponsorship requires 2 parameters: userOperation, entryPoint"
+                    .to_string(),
+            ));
+        }
+
+        let entry_point = self.parse_sponsor_entry_point(&params[1])?;
+        let user_op_variant = self.parse_user_operation_from_json(&params[0], entry_point)?;
+        self.ensure_routed_chain(&user_op_variant)?;
+
+        let simulation = match self
+            .run_sponsorship_checks(&user_op_variant, entry_point, &request.deadline, None)
+            .await
+        {
+            Ok(()) => {
+                request
+                    .deadline
+                    .run("simulation", async {
+                        paymaster_service
+                            .simulate_sponsorship(
+                                user_op_variant,
+                                H160::from_slice(entry_point.as_slice()),
+                            )
+                            .await
+                            .map_err(|e| match e {
+                                PaymasterError::UnsupportedEntryPoint { .. } => {
+                                    GatewayError::InvalidRequest(e.to_string())
+                                }
+                                e => GatewayError::PaymasterError(e.to_string()),
+                            })
+                    })
+                    .await?
+            }
+            Err(GatewayError::ValidationError(reason)) => SponsorshipSimulation::rejected(reason),
+            Err(e) => return Err(e),
+        };
+        debug!("Sponsorship simulation: {:?}", simulation.status);
+        serde_json::to_value(simulation).map_err(|e| GatewayError::InternalError(e.to_string()))
+    }
+
+    /// Parse a supported EntryPoint address parameter of a sponsorship method
+    fn parse_sponsor_entry_point(&self, value: &Value) -> GatewayResult<Address> {
+        let entry_point: Address = value
+            .as_str()
+            .ok_or_else(|| {
+                GatewayError::InvalidRequest("Entry point must be a string".to_string())
+            })?
+            .parse()
+            .map_err(|_| GatewayError::InvalidRequest("Invalid entry point address".to_string()))?;
+
+        if !self.supported_entry_points.contains(&entry_point) {
+            return Err(GatewayError::InvalidRequest(format!(
+                "Unsupported entry point: {:#x}",
+                entry_point
+            )));
+        }
+        Ok(entry_point)
+    }
+
+    /// Validate the account signature of `user_op` if a validator is attached
+    async fn check_account_signature(
+        &self,
+        user_op: &UserOperationVariant,
+        lenient: bool,
+        deadline: &Deadline,
+    ) -> GatewayResult<()> {
+        let Some(validator) = &self.signature_validator else {
+            return Ok(());
+        };
+        // Aggregated signatures are checked by their aggregator instead
+        if user_op.aggregator().is_some() {
+            return Ok(());
+        }
+
+        let result = deadline
+            .run("signature", async {
+                Ok(validator.validate(user_op, lenient).await)
+            })
+            .await?;
+        if !result.is_valid {
+            return Err(GatewayError::ValidationError(format!(
+                "Invalid {:?} signature: {}",
+                result.format, result.reason
+            )));
+        }
+        debug!(
+            "{:?} signature accepted{}: {}",
+            result.format,
+            if result.lenient {
+                " in lenient mode"
+            } else {
+                ""
+            },
+            result.reason
+        );
+        Ok(())
+    }
+
+    /// Check an aggregated operation's signature with its aggregator and attach
+    /// the aggregator's gas costs, so the paymaster accounts for aggregation
+    ///
+    /// An empty or dummy signature is accepted, as the user signs after
+    /// sponsorship. The returned operation keeps its original signature, which
+    /// is what the paymaster hashes and hands back.
+    async fn prepare_aggregated_operation(
+        &self,
+        user_op: UserOperationVariant,
+    ) -> GatewayResult<UserOperationVariant> {
+        let Some(aggregator) = user_op.aggregator() else {
+            return Ok(user_op);
+        };
+        let chain_spec = self.chain_spec();
+        let agg = chain_spec
+            .get_signature_aggregator(&aggregator)
+            .ok_or(GatewayError::UnsupportedAggregator(aggregator))?;
+
+        let unsigned =
+            user_op.signature().is_empty() || user_op.signature() == agg.dummy_uo_signature();
+        let signature = match agg.validate_user_op_signature(&user_op).await {
+            Ok(signature) => signature,
+            Err(e) if unsigned => {
+                debug!(
+                    "Aggregator {:#x} skipped unsigned operation: {}",
+                    aggregator, e
+                );
+                agg.dummy_uo_signature().clone()
+            }
+            Err(e) => {
+                return Err(GatewayError::ValidationError(format!(
+                    "Aggregator {:#x} rejected the signature: {}",
+                    aggregator, e
+                )))
+            }
+        };
+
+        Ok(user_op
+            .transform_for_aggregator(&chain_spec, aggregator, agg.costs().clone(), signature)
+            .with_original_signature())
+    }
+
+    /// Sponsorship checks of the configured module pipeline
+    async fn run_sponsorship_checks(
+        &self,
+        user_op_variant: &UserOperationVariant,
+        entry_point: Address,
+        deadline: &Deadline,
+        timings: Option<&StageTimings>,
+    ) -> GatewayResult<()> {
+        let ctx = ModuleContext {
+            user_op: user_op_variant,
+            entry_point,
+            threat_intel: self.threat_intel.as_ref(),
+            deadline,
+            events: self.events.as_ref(),
+            timings,
+        };
+        self.pipeline.run(&ctx).await
+    }
+
+    /// Parse the optional pm_sponsorUserOperation options object
+    ///
+    /// `debugTimings` is honored for API keys with the debug scope only
+    /// (`debug`) and ignored otherwise.
+    fn parse_sponsor_options(
+        options: Option<&Value>,
+        debug: bool,
+    ) -> GatewayResult<SponsorOptions> {
+        let options = match options {
+            Some(value) if !value.is_null() => value,
+            _ => return Ok(SponsorOptions::default()),
+        };
+
+        let options = options.as_object().ok_or_else(|| {
+            GatewayError::InvalidRequest("Sponsor options must be an object".to_string())
+        })?;
+
+        let return_full_operation = match options.get("returnFullOperation") {
+            None | Some(Value::Null) => false,
+            Some(Value::Bool(flag)) => *flag,
+            Some(_) => {
+                return Err(GatewayError::InvalidRequest(
+                    "returnFullOperation must be a boolean".to_string(),
+                ))
+            }
+        };
+
+        let idempotency_key = match options.get("idempotencyKey") {
+            None | Some(Value::Null) => None,
+            Some(Value::String(key)) if !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN => {
+                Some(key.clone())
+            }
+            Some(_) => {
+                return Err(GatewayError::InvalidRequest(format!(
+                    "idempotencyKey must be a string of 1 to {} characters",
+                    MAX_IDEMPOTENCY_KEY_LEN
+                )))
+            }
+        };
+
+        let validity_seconds = match options.get("validitySeconds") {
+            None | Some(Value::Null) => None,
+            Some(value) => Some(value.as_u64().ok_or_else(|| {
+                GatewayError::InvalidRequest(
+                    "validitySeconds must be a non-negative integer".to_string(),
+                )
+            })?),
+        };
+
+        let attest = match options.get("attest") {
+            None | Some(Value::Null) => false,
+            Some(Value::Bool(flag)) => *flag,
+            Some(_) => {
+                return Err(GatewayError::InvalidRequest(
+                    "attest must be a boolean".to_string(),
+                ))
+            }
+        };
+
+        let debug_timings = match options.get("debugTimings") {
+            None | Some(Value::Null) => false,
+            Some(Value::Bool(flag)) => *flag,
+            Some(_) => {
+                return Err(GatewayError::InvalidRequest(
+                    "debugTimings must be a boolean".to_string(),
+                ))
+            }
+        };
+
+        Ok(SponsorOptions {
+            return_full_operation,
+            requester: None,
+            request_id: None,
+            idempotency_key,
+            validity_seconds,
+            token: None,
+            attest,
+            timings: (debug_timings && debug).then(StageTimings::default),
+            deployment: None,
+        })
+    }
+
+    // === Rundler component integration methods ===
+
+    /// Get supported EntryPoint addresses, checksummed like rundler's eth_supportedEntryPoints
+    ///
+    /// EntryPoints the latest capability check could not send to or estimate for are left out.
+    fn get_supported_entry_points(&self) -> GatewayResult<Value> {
+        let entry_points: Vec<String> = self
+            .supported_entry_points
+            .iter()
+            .filter(|addr| {
+                self.entry_point_status
+                    .status(**addr)
+                    .is_none_or(|status| status.advertised())
+            })
+            .map(|addr| addr.to_checksum(None))
+            .collect();
+
+        debug!("Returning supported entry points: {:?}", entry_points);
+        Ok(json!(entry_points))
+    }
+
+    /// Handle superRelay_getEntryPointStatus: send, estimate and sponsor
+    /// capability of every supported EntryPoint, checked now if never checked
+    async fn get_entry_point_status(&self) -> GatewayResult<Value> {
+        let report = match self.entry_point_status.latest() {
+            Some(report) => report,
+            None => {
+                self.check_entry_points().await;
+                self.entry_point_status
+                    .latest()
+                    .ok_or_else(|| GatewayError::InternalError("EntryPoints not checked".into()))?
+            }
+        };
+        serde_json::to_value(report).map_err(|e| GatewayError::InternalError(e.to_string()))
+    }
+
+    /// Get chain ID
+    fn get_chain_id(&self) -> GatewayResult<Value> {
+        let chain_id_hex = format!("0x{:x}", self.chain_id);
+        debug!("Returning chain ID: {}", chain_id_hex);
+        Ok(json!(chain_id_hex))
+    }
+
+    /// Handle eth_estimateUserOperationGas, cached unless state overrides are given
+    ///
+    /// params[2] is an optional state override map applied for this estimate
+    /// only. An operation carrying `eip7702Auth` is estimated as a delegated EOA,
+    /// one carrying `"sponsorshipIntent": true` with our dummy paymaster fields.
+    async fn estimate_user_operation_gas(&self, request: &JsonRpcRequest) -> GatewayResult<Value> {
+        if request.params.len() < 2 {
+            return Err(GatewayError::InvalidRequest(
+                "Missing parameters".to_string(),
+            ));
+        }
+
+        let entry_point: Address = request.params[1]
+            .as_str()
+            .ok_or_else(|| GatewayError::InvalidRequest("Invalid entry point format".to_string()))?
+            .parse()
+            .map_err(|_| GatewayError::InvalidRequest("Invalid entry point address".to_string()))?;
+        let sponsored = self
+            .with_sponsorship_intent(&request.params[0], entry_point)
+            .await?;
+        let user_op = sponsored.as_ref().unwrap_or(&request.params[0]);
+        let state_override = request
+            .params
+            .get(2)
+            .filter(|v| !v.is_null())
+            .map(|v| parse_state_override(v, 2))
+            .transpose()?;
+
+        let estimate = async {
+            match (&self.gas_estimator, &self.pool_handle) {
+                (Some(estimator), _) => {
+                    let op = self.parse_optional_gas_user_operation(user_op, entry_point)?;
+                    let estimate = estimator
+                        .estimate_user_operation_gas(
+                            op.clone(),
+                            entry_point,
+                            state_override.clone().unwrap_or_default(),
+                        )
+                        .await?;
+                    Ok(gas_estimate_to_json(&op, &estimate))
+                }
+                (None, Some(pool)) => {
+                    self.estimate_user_operation_gas_with_pool(&pool.current()?, request)
+                        .await
+                }
+                (None, None) => self.estimate_user_operation_gas_fallback(request).await,
+            }
+        };
+
+        let estimate = request.deadline.run("gas_estimation", estimate);
+        // Overridden state is not part of the cache key
+        if state_override.is_some() {
+            return estimate.await;
+        }
+        self.response_cache
+            .estimate(user_op, entry_point, request.no_cache, estimate)
+            .await
+    }
+
+    /// Estimate user operation gas using real pool component
+    async fn estimate_user_operation_gas_with_pool(
+        &self,
+        _pool: &Arc<LocalPoolHandle>,
+        request: &JsonRpcRequest,
+    ) -> GatewayResult<Value> {
+        if request.params.len() < 2 {
+            return Err(GatewayError::InvalidRequest(
+                "Missing parameters".to_string(),
+            ));
+        }
+
+        let _user_op = &request.params[0];
+        let entry_point = &request.params[1];
+
+        debug!(
+            "Estimating gas with pool for entry point: {:?}",
+            entry_point
+        );
+
+        // TODO: Use actual pool.estimate_user_operation_gas() method
+        // For now, return reasonable estimates
+        Ok(json!({
+            "preVerificationGas": "0x5208",      // 21,000
+            "verificationGasLimit": "0x186A0",   // 100,000
+            "callGasLimit": "0x186A0",           // 100,000
+            "paymasterVerificationGasLimit": null,
+            "paymasterPostOpGasLimit": null
+        }))
+    }
+
+    /// Fallback gas estimation without pool
+    async fn estimate_user_operation_gas_fallback(
+        &self,
+        request: &JsonRpcRequest,
+    ) -> GatewayResult<Value> {
+        if request.params.len() < 2 {
+            return Err(GatewayError::InvalidRequest(
+                "Missing parameters".to_string(),
+            ));
+        }
+
+        debug!("Estimating gas with fallback method");
+
+        // Return conservative estimates
+        Ok(json!({
+            "preVerificationGas": "0x5208",      // 21,000
+            "verificationGasLimit": "0x186A0",   // 100,000
+            "callGasLimit": "0x186A0",           // 100,000
+        }))
+    }
+
+    /// Send user operation using real pool component, bundled only while
+    /// `expected_storage` holds when given, with the `requested` pool
+    /// permissions of an eth_sendUserOperation caller
+    async fn send_user_operation_with_pool(
+        &self,
+        _pool: &Arc<LocalPoolHandle>,
+        request: &JsonRpcRequest,
+        expected_storage: Option<ExpectedStorage>,
+        requested: Option<&RequestedPermissions>,
+    ) -> GatewayResult<Value> {
+        if request.params.len() < 2 {
+            return Err(GatewayError::InvalidRequest(
+                "Missing parameters".to_string(),
+            ));
+        }
+
+        let user_op = &request.params[0];
+        let entry_point = &request.params[1];
+
+        debug!(
+            "Sending UserOperation with pool to entry point: {:?}",
+            entry_point
+        );
+
+        // Validate entry point
+        let entry_point_addr: Address = entry_point
+            .as_str()
+            .ok_or_else(|| GatewayError::InvalidRequest("Invalid entry point format".to_string()))?
+            .parse()
+            .map_err(|_| GatewayError::InvalidRequest("Invalid entry point address".to_string()))?;
+
+        if !self.supported_entry_points.contains(&entry_point_addr) {
+            return Err(GatewayError::InvalidRequest(format!(
+                "Unsupported entry point: {:#x}",
+                entry_point_addr
+            )));
+        }
+
+        // Permissions follow the trust level of the authenticated API key, overridden
+        // by those it requests when it may; refused requests fail before any validation
+        let trust_level = self.op_permissions.trust_level_of(request);
+        let perms = UserOperationPermissions {
+            expected_storage,
+            ..self.op_permissions.resolve(request, requested)?
+        };
+
+        // Parse UserOperation from JSON and call real pool.add_op()
+        let user_op_variant = self.parse_user_operation_from_json(user_op, entry_point_addr)?;
+        self.ensure_routed_chain(&user_op_variant)?;
+
+        debug!(
+            "Parsed UserOperation: sender={:?}, entry_point={:?}",
+            user_op_variant.sender(),
+            user_op_variant.entry_point()
+        );
+
+        self.check_account_signature(&user_op_variant, false, &request.deadline)
+            .await?;
+
+        // Reject operations carrying a bad signature from our own paymaster before
+        // they reach the pool; other paymasters are left to on-chain validation
+        if let Some(paymaster_service) = &self.paymaster_service {
+            request
+                .deadline
+                .run("sponsorship_verification", async {
+                    paymaster_service
+                        .verify_own_sponsorship(&user_op_variant)
+                        .await
+                        .map_err(|e| {
+                            if e.is_out_of_time_range() {
+                                GatewayError::SponsorshipOutOfTimeRange(e.to_string())
+                            } else {
+                                GatewayError::InvalidSponsorship(e.to_string())
+                            }
+                        })
+                })
+                .await?;
+        }
+
+        // Once handed to the pool the operation may be accepted, so the caller
+        // must get the outcome even past the deadline
+        request.deadline.check("pool_submission")?;
+        request.deadline.commit();
+        let sender = user_op_variant.sender();
+        let user_op_hash = add_op_to_pool(_pool.as_ref(), user_op_variant, perms.clone()).await?;
+        if let Some(events) = &self.events {
+            events.publish(GatewayEvent::PoolSubmission {
+                user_op_hash,
+                sender,
+                entry_point: entry_point_addr,
+                trust_level: trust_level.map(str::to_string),
+            });
+        }
+
+        // Return the real operation hash from pool
+        let hash_hex = format!("0x{:x}", user_op_hash);
+        match trust_level {
+            Some(level) => info!(
+                "✅ Submitted UserOperation {} for API key {:?} at trust level {}: {:?}",
+                hash_hex, request.api_key_id, level, perms
+            ),
+            None => debug!(
+                "✅ Successfully submitted UserOperation to pool: hash={}, permissions={:?}",
+                hash_hex, perms
+            ),
+        }
+        Ok(json!(hash_hex))
+    }
+
+    /// Get user operation by hash using real pool component
+    async fn get_user_operation_by_hash_with_pool(
+        &self,
+        _pool: &Arc<LocalPoolHandle>,
+        request: &JsonRpcRequest,
+    ) -> GatewayResult<Value> {
+        if request.params.is_empty() {
+            return Err(GatewayError::InvalidRequest(
+                "Missing hash parameter".to_string(),
+            ));
+        }
+
+        let hash = &request.params[0];
+        debug!("Looking up UserOperation by hash with pool: {:?}", hash);
+
+        // Parse hash from hex string
+        let hash_str = hash
+            .as_str()
+            .ok_or_else(|| GatewayError::InvalidRequest("Hash must be a string".to_string()))?;
+
+        let hash_bytes = if let Some(stripped) = hash_str.strip_prefix("0x") {
+            hex::decode(stripped)
+        } else {
+            hex::decode(hash_str)
+        }
+        .map_err(|_| GatewayError::InvalidRequest("Invalid hash format".to_string()))?;
+
+        if hash_bytes.len() != 32 {
+            return Err(GatewayError::InvalidRequest(
+                "Hash must be 32 bytes".to_string(),
+            ));
+        }
+
+        let hash_b256 = alloy_primitives::B256::from_slice(&hash_bytes);
+
+        // Use real pool.get_op_by_hash() method
+        match _pool.get_op_by_hash(hash_b256).await {
+            Ok(Some(pool_op)) => {
+                debug!(
+                    "✅ Found UserOperation in pool: sender={:?}",
+                    pool_op.uo.sender()
+                );
+                // Convert PoolOperation back to JSON format
+                Ok(json!({
+                    "userOperation": self.user_operation_to_json(&pool_op.uo),
+                    "entryPoint": format!("{:#x}", pool_op.entry_point),
+                    "blockNumber": null,
+                    "blockHash": null,
+                    "transactionHash": null
+                }))
+            }
+            Ok(None) => {
+                debug!("UserOperation not found in pool for hash: {}", hash_str);
+                Ok(Value::Null)
+            }
+            Err(e) => {
+                error!("Pool lookup error: {:?}", e);
+                Err(GatewayError::PoolError(format!(
+                    "Failed to lookup operation: {:?}",
+                    e
+                )))
+            }
+        }
+    }
+
+    /// Next usable nonce of a sender, counting its operations pending in the pool
+    ///
+    /// Params: `[sender, entryPoint, key?]`, where the optional key is the 192-bit
+    /// nonce key (default 0). v0.6 and v0.7 share the same nonce semantics.
+    async fn get_user_operation_nonce_with_pool(
+        &self,
+        pool: &Arc<LocalPoolHandle>,
+        request: &JsonRpcRequest,
+    ) -> GatewayResult<Value> {
+        let params = &request.params;
+        if params.len() != 2 && params.len() != 3 {
+            return Err(GatewayError::InvalidRequest(
+                "eth_getUserOperationNonce requires sender, entryPoint and an optional key"
+                    .to_string(),
+            ));
+        }
+
+        let sender: Address = params[0]
+            .as_str()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| GatewayError::InvalidRequest("Invalid sender address".to_string()))?;
+        let entry_point = self.parse_sponsor_entry_point(&params[1])?;
+        let key = match params.get(2) {
+            None | Some(Value::Null) => U256::ZERO,
+            Some(value) => value
+                .as_str()
+                .and_then(|s| s.parse::<U256>().ok())
+                .ok_or_else(|| GatewayError::InvalidRequest("Invalid nonce key".to_string()))?,
+        };
+        validate_nonce_key(key)?;
+
+        let nonce_reader = self.nonce_reader.as_ref().ok_or_else(|| {
+            GatewayError::InvalidRequest("Nonce lookup not available".to_string())
+        })?;
+        let on_chain_nonce = nonce_reader.get_nonce(entry_point, sender, key).await?;
+        let nonce = next_pending_nonce(pool.as_ref(), entry_point, sender, on_chain_nonce).await?;
+
+        debug!(
+            "Next nonce of {:#x} for key {:#x}: on-chain {:#x}, next {:#x}",
+            sender, key, on_chain_nonce, nonce
+        );
+        Ok(json!(format!("0x{:x}", nonce)))
+    }
+
+    /// Settle the usage accounted for a sponsored operation at the
+    /// actualGasCost of its `receipt`, and mark its sponsorship record mined
+    fn record_actual_gas_cost(&self, hash: B256, receipt: &Value) {
+        let Some(paymaster_service) = &self.paymaster_service else {
+            return;
+        };
+        if let Some(mined) = MinedUserOperation::from_receipt(receipt) {
+            paymaster_service.record_sponsorship_mined(hash, mined);
+        }
+        let Some(actual_gas_cost) = receipt["actualGasCost"]
+            .as_str()
+            .and_then(|s| s.parse::<U256>().ok())
+        else {
+            return;
+        };
+        if paymaster_service.record_actual_gas_cost(hash, actual_gas_cost) {
+            debug!(
+                "Recorded actual gas cost {} of {:#x}",
+                actual_gas_cost, hash
+            );
+        }
+    }
+
+    /// Get user operation receipt from on-chain UserOperationEvent logs
+    ///
+    /// Returns null until the operation is mined, as required by ERC-4337;
+    /// operations still waiting in the pool have no receipt.
+    async fn get_user_operation_receipt(&self, request: &JsonRpcRequest) -> GatewayResult<Value> {
+        if request.params.is_empty() {
+            return Err(GatewayError::InvalidRequest(
+                "Missing hash parameter".to_string(),
+            ));
+        }
+
+        let hash = &request.params[0];
+        debug!("Looking up UserOperation receipt by hash: {:?}", hash);
+
+        // Parse hash from hex string
+        let hash_str = hash
+            .as_str()
+            .ok_or_else(|| GatewayError::InvalidRequest("Hash must be a string".to_string()))?;
+
+        let hash_bytes = if let Some(stripped) = hash_str.strip_prefix("0x") {
+            hex::decode(stripped)
+        } else {
+            hex::decode(hash_str)
+        }
+        .map_err(|_| GatewayError::InvalidRequest("Invalid hash format".to_string()))?;
+
+        if hash_bytes.len() != 32 {
+            return Err(GatewayError::InvalidRequest(
+                "Hash must be 32 bytes".to_string(),
+            ));
+        }
+
+        let hash_b256 = alloy_primitives::B256::from_slice(&hash_bytes);
+
+        if let Some(receipt_provider) = &self.receipt_provider {
+            if let Some(receipt) = receipt_provider
+                .get_user_operation_receipt(hash_b256)
+                .await?
+            {
+                debug!("✅ Found mined UserOperation receipt: {}", hash_str);
+                self.record_actual_gas_cost(hash_b256, &receipt);
+                return Ok(receipt);
+            }
+        } else {
+            warn!("Receipt provider not available for eth_getUserOperationReceipt");
+        }
+
+        // Not mined yet: report pending operations in the logs only
+        if let Some(Ok(pool)) = self.pool_handle.as_ref().map(SupervisedPool::current) {
+            match pool.get_op_by_hash(hash_b256).await {
+                Ok(Some(_)) => debug!("UserOperation is pending in pool: {}", hash_str),
+                Ok(None) => debug!("UserOperation not found for receipt lookup: {}", hash_str),
+                Err(e) => error!("Pool lookup error for receipt: {:?}", e),
+            }
+        }
+
+        Ok(Value::Null)
+    }
+
+    // === UserOperation parsing methods ===
+
+    /// Parse UserOperation from JSON value based on entry point version
+    fn parse_user_operation_from_json(
+        &self,
+        json_value: &Value,
+        entry_point: Address,
+    ) -> GatewayResult<UserOperationVariant> {
+        let selection = self.version_selector.select(json_value, entry_point)?;
+        self.limits.check_raw_sizes(json_value)?;
+        debug!(
+            "Parsing UserOperation for EntryPoint {:#x} as {:?} (by {:?})",
+            entry_point, selection.version, selection.method
+        );
+        let user_op = match selection.version {
+            EntryPointVersion::V0_6 => self.parse_v06_user_operation(json_value)?,
+            EntryPointVersion::V0_7 => {
+                UserOperationVariant::V0_7(self.parse_v07_user_operation(json_value)?)
+            }
+            // v0.8 operations have the v0.7 fields and are hashed for their EntryPoint
+            #[cfg(feature = "entrypoint-v0_8")]
+            EntryPointVersion::V0_8 => {
+                let chain_spec = ChainSpec {
+                    entry_point_address_v0_8: entry_point,
+                    ..self.chain_spec()
+                };
+                UserOperationVariant::V0_8(v0_8::UserOperation::from_fields(
+                    self.parse_v07_user_operation(json_value)?,
+                    &chain_spec,
+                ))
+            }
+            EntryPointVersion::Unspecified => {
+                return Err(GatewayError::InvalidRequest(format!(
+                    "Unsupported EntryPoint version for {:#x}",
+                    entry_point
+                )))
+            }
+        };
+        self.limits.check_limits(&user_op)?;
+        Ok(user_op)
+    }
+
+    /// UserOperation fields of `json_value`, parsed as the limits configure
+    fn fields<'a>(&self, json_value: &'a Value) -> JsonFields<'a> {
+        JsonFields::new(json_value, self.limits.field_parsing)
+    }
+
+    /// Parse v0.6 UserOperation from JSON
+    ///
+    /// Gas fields are required. Lenient field parsing replaces missing or
+    /// malformed ones with defaults instead.
+    fn parse_v06_user_operation(&self, json_value: &Value) -> GatewayResult<UserOperationVariant> {
+        let fields = self.fields(json_value);
+        let sender = fields.address("sender")?;
+        let nonce = fields.quantity("nonce")?;
+        let authorization = self.parse_optional_eip7702_auth_field(&fields, "eip7702Auth")?;
+        let aggregator = self.parse_aggregator_field(&fields)?;
+
+        let mut builder = v0_6::UserOperationBuilder::new(
+            &self.chain_spec(),
+            v0_6::UserOperationRequiredFields {
+                sender,
+                nonce,
+                init_code: fields.or_lenient(fields.bytes("initCode"), Bytes::new())?,
+                call_data: fields.or_lenient(fields.bytes("callData"), Bytes::new())?,
+                call_gas_limit: fields.or_lenient(fields.uint("callGasLimit"), 100_000)?,
+                verification_gas_limit: fields
+                    .or_lenient(fields.uint("verificationGasLimit"), 100_000)?,
+                pre_verification_gas: fields
+                    .or_lenient(fields.uint("preVerificationGas"), 21_000)?,
+                max_fee_per_gas: fields.or_lenient(fields.uint("maxFeePerGas"), 1_000_000_000)?,
+                max_priority_fee_per_gas: fields
+                    .or_lenient(fields.uint("maxPriorityFeePerGas"), 1_000_000_000)?,
+                paymaster_and_data: fields
+                    .or_lenient(fields.bytes("paymasterAndData"), Bytes::new())?,
+                signature: fields.or_lenient(fields.bytes("signature"), Bytes::new())?,
+            },
+        );
+
+        // Attach EIP-7702 authorization so pool simulation sees the delegation
+        if let Some(auth) = authorization {
+            builder = builder.authorization_tuple(auth);
+        }
+        if let Some(aggregator) = aggregator {
+            builder = builder.aggregator(aggregator);
+        }
+
+        let user_op = builder.build();
+
+        Ok(UserOperationVariant::V0_6(user_op))
+    }
+
+    /// Parse v0.7 UserOperation from JSON
+    ///
+    /// Gas fields are required, paymaster gas limits default to 100000 when
+    /// left out. Lenient field parsing replaces missing or malformed fields
+    /// with defaults instead.
+    fn parse_v07_user_operation(&self, json_value: &Value) -> GatewayResult<v0_7::UserOperation> {
+        let fields = self.fields(json_value);
+        let sender = fields.address("sender")?;
+        let nonce = fields.quantity("nonce")?;
+        let authorization = self.parse_optional_eip7702_auth_field(&fields, "eip7702Auth")?;
+        let aggregator = self.parse_aggregator_field(&fields)?;
+
+        // Create a v0.7 UserOperation with required fields only
+        let chain_spec = self.chain_spec();
+        let mut builder = v0_7::UserOperationBuilder::new(
+            &chain_spec,
+            v0_7::UserOperationRequiredFields {
+                sender,
+                nonce,
+                call_data: fields.or_lenient(fields.bytes("callData"), Bytes::new())?,
+                call_gas_limit: fields.or_lenient(fields.uint("callGasLimit"), 100_000)?,
+                verification_gas_limit: fields
+                    .or_lenient(fields.uint("verificationGasLimit"), 100_000)?,
+                pre_verification_gas: fields
+                    .or_lenient(fields.uint("preVerificationGas"), 21_000)?,
+                max_fee_per_gas: fields.or_lenient(fields.uint("maxFeePerGas"), 1_000_000_000)?,
+                max_priority_fee_per_gas: fields
+                    .or_lenient(fields.uint("maxPriorityFeePerGas"), 1_000_000_000)?,
+                signature: fields.or_lenient(fields.bytes("signature"), Bytes::new())?,
+            },
+        );
+
+        // Add optional fields if present (v0.7 combines factory and factory_data)
+        if let Some(factory) = fields.or_lenient(fields.optional_address("factory"), None)? {
+            let factory_data = fields.or_lenient(fields.bytes("factoryData"), Bytes::new())?;
+            builder = builder.factory(factory, factory_data);
+        }
+
+        // Add paymaster fields if present (v0.7 combines all paymaster fields)
+        if let Some(paymaster) = fields.or_lenient(fields.optional_address("paymaster"), None)? {
+            let pv_gas_limit = fields
+                .or_lenient(fields.optional_uint("paymasterVerificationGasLimit"), None)?
+                .unwrap_or(100_000);
+            let po_gas_limit = fields
+                .or_lenient(fields.optional_uint("paymasterPostOpGasLimit"), None)?
+                .unwrap_or(100_000);
+            let paymaster_data = fields.or_lenient(fields.bytes("paymasterData"), Bytes::new())?;
+            builder = builder.paymaster(paymaster, pv_gas_limit, po_gas_limit, paymaster_data);
+        }
+
+        // Attach EIP-7702 authorization so pool simulation sees the delegation
+        if let Some(auth) = authorization {
+            builder = builder.authorization_tuple(auth);
+        }
+        if let Some(aggregator) = aggregator {
+            builder = builder.aggregator(aggregator);
+        }
+
+        Ok(builder.build())
+    }
+
+    /// Parse the UserOperation of eth_estimateUserOperationGas, whose gas
+    /// limits and fees may be left out
+    ///
+    /// Only the address of an `eip7702Auth` is kept: estimation fills in the
+    /// rest of the tuple so its calldata and authorization gas are covered.
+    fn parse_optional_gas_user_operation(
+        &self,
+        json_value: &Value,
+        entry_point: Address,
+    ) -> GatewayResult<UserOperationOptionalGas> {
+        let selection = self.version_selector.select(json_value, entry_point)?;
+        self.limits.check_raw_sizes(json_value)?;
+        let fields = self.fields(json_value);
+        let sender = fields.address("sender")?;
+        let nonce = fields.quantity("nonce")?;
+        let bytes = |field_name: &str| fields.bytes(field_name);
+        let gas = |field_name: &str| fields.optional_uint(field_name);
+        let eip7702_auth_address = self
+            .parse_optional_eip7702_auth_field(&fields, "eip7702Auth")?
+            .map(|auth| auth.address);
+        let aggregator = self.parse_aggregator_field(&fields)?;
+
+        match selection.version {
+            EntryPointVersion::V0_6 => Ok(UserOperationOptionalGas::V0_6(
+                v0_6::UserOperationOptionalGas {
+                    sender,
+                    nonce,
+                    init_code: bytes("initCode")?,
+                    call_data: bytes("callData")?,
+                    call_gas_limit: gas("callGasLimit")?,
+                    verification_gas_limit: gas("verificationGasLimit")?,
+                    pre_verification_gas: gas("preVerificationGas")?,
+                    max_fee_per_gas: gas("maxFeePerGas")?,
+                    max_priority_fee_per_gas: gas("maxPriorityFeePerGas")?,
+                    paymaster_and_data: bytes("paymasterAndData")?,
+                    signature: bytes("signature")?,
+                    eip7702_auth_address,
+                    aggregator,
+                },
+            )),
+            EntryPointVersion::V0_7 => Ok(UserOperationOptionalGas::V0_7(
+                v0_7::UserOperationOptionalGas {
+                    sender,
+                    nonce,
+                    call_data: bytes("callData")?,
+                    signature: bytes("signature")?,
+                    call_gas_limit: gas("callGasLimit")?,
+                    verification_gas_limit: gas("verificationGasLimit")?,
+                    pre_verification_gas: gas("preVerificationGas")?,
+                    max_priority_fee_per_gas: gas("maxPriorityFeePerGas")?,
+                    max_fee_per_gas: gas("maxFeePerGas")?,
+                    factory: fields.optional_address("factory")?,
+                    factory_data: bytes("factoryData")?,
+                    paymaster: fields.optional_address("paymaster")?,
+                    paymaster_verification_gas_limit: gas("paymasterVerificationGasLimit")?,
+                    paymaster_post_op_gas_limit: gas("paymasterPostOpGasLimit")?,
+                    paymaster_data: bytes("paymasterData")?,
+                    eip7702_auth_address,
+                    aggregator,
+                },
+            )),
+            #[cfg(feature = "entrypoint-v0_8")]
+            EntryPointVersion::V0_8 => Err(GatewayError::InvalidRequest(format!(
+                "Gas estimation is not available for v0.8 EntryPoint {:#x}",
+                entry_point
+            ))),
+            EntryPointVersion::Unspecified => Err(GatewayError::InvalidRequest(format!(
+                "Unsupported EntryPoint version for {:#x}",
+                entry_point
+            ))),
+        }
+    }
+
+    // === JSON conversion helper methods ===
+
+    /// Convert UserOperationVariant back to JSON format
+    fn user_operation_to_json(&self, user_op: &UserOperationVariant) -> Value {
+        canonical::user_operation_json(user_op)
+    }
+
+    // === JSON parsing helper methods ===
+
+    /// Parse an optional EIP-7702 authorization tuple from JSON
+    ///
+    /// The chain id must match the router's chain id; a chain id of 0 is accepted
+    /// because EIP-7702 defines it as valid on every chain.
+    fn parse_optional_eip7702_auth_field(
+        &self,
+        fields: &JsonFields<'_>,
+        field_name: &str,
+    ) -> GatewayResult<Option<Eip7702Auth>> {
+        let Some(auth) = fields.nested(field_name)? else {
+            return Ok(None);
+        };
+
+        let address = auth.address("address")?;
+        let chain_id: u64 = auth.uint("chainId")?;
+        if chain_id != 0 && chain_id != self.chain_id {
+            return Err(GatewayError::InvalidRequest(format!(
+                "{}.chainId {} does not match gateway chain id {}",
+                field_name, chain_id, self.chain_id
+            )));
+        }
+
+        Ok(Some(Eip7702Auth {
+            chain_id,
+            address,
+            nonce: auth.uint("nonce")?,
+            y_parity: auth.uint("yParity")?,
+            r: auth.quantity("r")?,
+            s: auth.quantity("s")?,
+        }))
+    }
+
+    /// Parse the optional `aggregator` field, which must name an enabled aggregator
+    fn parse_aggregator_field(&self, fields: &JsonFields<'_>) -> GatewayResult<Option<Address>> {
+        let aggregator = fields.optional_address("aggregator")?;
+        if let Some(aggregator) = aggregator {
+            if self.signature_aggregators.get(&aggregator).is_none() {
+                return Err(GatewayError::UnsupportedAggregator(aggregator));
+            }
+        }
+        Ok(aggregator)
+    }
+}
+
+impl Default for GatewayRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Add `op` to `pool`, mapping a mempool rejection to its ERC-4337 error code
+/// and data
+///
+/// A rejection for a throttled or banned entity also carries the entity's
+/// reputation, when the pool can report it.
+pub async fn add_op_to_pool<P: Pool + ?Sized>(
+    pool: &P,
+    op: UserOperationVariant,
+    perms: UserOperationPermissions,
+) -> GatewayResult<B256> {
+    let entry_point = op.entry_point();
+    let e = match pool.add_op(op, perms).await {
+        Ok(hash) => return Ok(hash),
+        Err(e) => e,
+    };
+    debug!("Pool rejected UserOperation: {}", e);
+    let throttled = match &e {
+        PoolError::MempoolError(MempoolError::EntityThrottled(entity)) => Some(entity.address),
+        _ => None,
+    };
+    let err = GatewayError::from(e);
+    let Some(address) = throttled else {
+        return Err(err);
+    };
+    match entity_reputation(pool, entry_point, address).await {
+        Ok(reputation) => Err(with_reputation(err, &reputation)),
+        Err(e) => {
+            debug!("No reputation of throttled entity {}: {}", address, e);
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy_primitives::B256;
+    use rundler_paymaster_relay::sponsorship::{self, SponsorshipData};
+    use rundler_types::builder::{BuilderError, BundlingMode, MockBuilder};
+    use serde_json::{json, Value};
 
-        // Parse hash from hex string
-        let hash_str = hash
-            .as_str()
-            .ok_or_else(|| GatewayError::InvalidRequest("Hash must be a string".to_string()))?;
+    use super::*;
+    use crate::{
+        gateway::JsonRpcRequest, health::ComponentStatus, json_fields::FieldParsing, test_utils,
+        BuilderProbe, GatewayError, HealthChecker, SystemStatus,
+    };
 
-        let hash_bytes = if let Some(stripped) = hash_str.strip_prefix("0x") {
-            hex::decode(stripped)
-        } else {
-            hex::decode(hash_str)
+    fn eth_config() -> EthApiConfig {
+        EthApiConfig {
+            chain_id: 11155111,
+            ..Default::default()
         }
-        .map_err(|_| GatewayError::InvalidRequest("Invalid hash format".to_string()))?;
+    }
 
-        if hash_bytes.len() != 32 {
-            return Err(GatewayError::InvalidRequest(
-                "Hash must be 32 bytes".to_string(),
-            ));
-        }
+    fn auth_json(chain_id: &str) -> Value {
+        json!({
+            "address": "0x63c0c19a282a1b52b07dd5a65b58948a07dae32b",
+            "chainId": chain_id,
+            "nonce": "0x5",
+            "yParity": "0x1",
+            "r": "0x1a2b3c",
+            "s": "0x4d5e6f"
+        })
+    }
 
-        let hash_b256 = alloy_primitives::B256::from_slice(&hash_bytes);
+    /// Router on Sepolia whose pool never answers
+    fn send_router() -> GatewayRouter {
+        GatewayRouter::with_rundler_components(
+            Arc::new(rundler_pool::LocalPoolBuilder::new(10).get_handle()),
+            eth_config(),
+        )
+    }
 
-        // Look up the operation first
-        match _pool.get_op_by_hash(hash_b256).await {
-            Ok(Some(pool_op)) => {
-                // For now, return basic receipt structure
-                // In a real implementation, this would come from blockchain data
-                debug!(
-                    "✅ Found UserOperation for receipt: sender={:?}",
-                    pool_op.uo.sender()
-                );
-                Ok(json!({
-                    "userOpHash": hash_str,
-                    "entryPoint": format!("{:#x}", pool_op.entry_point),
-                    "sender": format!("{:#x}", pool_op.uo.sender()),
-                    "nonce": format!("0x{:x}", pool_op.uo.nonce()),
-                    "paymaster": match &pool_op.uo {
-                        UserOperationVariant::V0_6(op) => {
-                            if op.paymaster_and_data().is_empty() {
-                                Value::Null
-                            } else {
-                                // Extract paymaster address from paymasterAndData
-                                json!("0x0000000000000000000000000000000000000000")
-                            }
-                        },
-                        UserOperationVariant::V0_7(op) => {
-                            op.paymaster().map(|p| json!(format!("{:#x}", p))).unwrap_or(Value::Null)
-                        }
-                    },
-                    "actualGasCost": "0x0", // Would be calculated from blockchain receipt
-                    "actualGasUsed": "0x0", // Would be calculated from blockchain receipt
-                    "success": true, // Would come from blockchain receipt
-                    "logs": [], // Would come from blockchain receipt
-                    "receipt": {
-                        "transactionHash": null, // Would be set when mined
-                        "blockNumber": null,
-                        "blockHash": null,
-                        "from": format!("{:#x}", pool_op.uo.sender()),
-                        "gasused": "0x0"
-                    }
-                }))
-            }
-            Ok(None) => {
-                debug!("UserOperation not found for receipt lookup: {}", hash_str);
-                Ok(Value::Null)
-            }
-            Err(e) => {
-                error!("Pool lookup error for receipt: {:?}", e);
-                Err(GatewayError::PoolError(format!(
-                    "Failed to lookup operation receipt: {:?}",
-                    e
-                )))
-            }
+    /// eth_sendUserOperation already past its deadline, so an accepted
+    /// operation stops right before the pool
+    fn expired_send_request(op: Value, entry_point: &str) -> JsonRpcRequest {
+        JsonRpcRequest {
+            id: json!(1),
+            method: "eth_sendUserOperation".to_string(),
+            params: vec![op, json!(entry_point)],
+            api_key_id: None,
+            trust_level: None,
+            chain_id: None,
+            no_cache: false,
+            request_id: None,
+            deadline: Deadline::after(Duration::ZERO),
+            debug: false,
+            permissions: false,
         }
     }
 
-    // === UserOperation parsing methods ===
+    #[tokio::test]
+    async fn test_eip7702_auth_accepted_on_send() {
+        let router = send_router();
+        let op_v06 = json!({
+            "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "nonce": "0x1",
+            "initCode": "0x",
+            "callData": "0x",
+            "callGasLimit": "0x186a0",
+            "verificationGasLimit": "0x186a0",
+            "preVerificationGas": "0x5208",
+            "maxFeePerGas": "0x3b9aca00",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "paymasterAndData": "0x",
+            "signature": "0x",
+            "eip7702Auth": auth_json("0xaa36a7")
+        });
+        let op_v07 = json!({
+            "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "nonce": "0x1",
+            "callData": "0x",
+            "callGasLimit": "0x186a0",
+            "verificationGasLimit": "0x186a0",
+            "preVerificationGas": "0x5208",
+            "maxFeePerGas": "0x3b9aca00",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "signature": "0x",
+            // Valid on every chain
+            "eip7702Auth": auth_json("0x0")
+        });
 
-    /// Parse UserOperation from JSON value based on entry point version
-    fn parse_user_operation_from_json(
-        &self,
-        json_value: &Value,
-        entry_point: Address,
-    ) -> GatewayResult<UserOperationVariant> {
-        // Determine version based on entry point address or JSON structure
-        if self.is_entry_point_v06(entry_point) {
-            self.parse_v06_user_operation(json_value)
-        } else {
-            self.parse_v07_user_operation(json_value)
+        for (op, entry_point) in [
+            (op_v06, "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789"),
+            (op_v07, "0x0000000071727De22E5E9d8BAf0edAc6f37da032"),
+        ] {
+            let result = router
+                .route_to_rundler(&expired_send_request(op, entry_point))
+                .await;
+            assert!(
+                matches!(
+                    result,
+                    Err(GatewayError::DeadlineExceeded {
+                        stage: "pool_submission",
+                        ..
+                    })
+                ),
+                "{}: {:?}",
+                entry_point,
+                result
+            );
         }
     }
 
-    /// Check if entry point is v0.6
-    fn is_entry_point_v06(&self, entry_point: Address) -> bool {
-        // Standard v0.6 EntryPoint address
-        entry_point
-            == "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789"
-                .parse::<Address>()
+    #[test]
+    fn test_parse_uses_selected_version() {
+        // v0.6 deployed at a non-canonical address
+        let entry_point = Address::repeat_byte(0x66);
+        let router = GatewayRouter::with_config(eth_config()).with_version_selector(Arc::new(
+            VersionSelector::from_chain_spec(&ChainSpec::default())
+                .with_entry_point(entry_point, EntryPointVersion::V0_6),
+        ));
+        let op_json = json!({
+            "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "nonce": "0x1",
+            "callData": "0x",
+            "signature": "0x"
+        });
+
+        let op = router
+            .parse_user_operation_from_json(&op_json, entry_point)
+            .unwrap();
+        assert!(matches!(op, UserOperationVariant::V0_6(_)));
+
+        // v0.7 fields are not silently parsed as a v0.6 operation
+        let mut v07_json = op_json.clone();
+        v07_json["paymaster"] = json!("0x63c0c19a282a1b52b07dd5a65b58948a07dae32b");
+        assert!(matches!(
+            router.parse_user_operation_from_json(&v07_json, entry_point),
+            Err(GatewayError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_v06_hashes_with_chain_spec_entry_point() {
+        // OP-stack testnet spec whose v0.6 EntryPoint is not the default one
+        let entry_point = Address::repeat_byte(0x66);
+        let chain_spec = ChainSpec {
+            id: 11155420,
+            entry_point_address_v0_6: entry_point,
+            ..Default::default()
+        };
+        let router = GatewayRouter::with_config(EthApiConfig {
+            chain_id: 11155420,
+            entry_points: vec![entry_point],
+            chain_spec: Arc::new(chain_spec.clone()),
+        });
+        let op_json = json!({
+            "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "nonce": "0x1",
+            "callData": "0x",
+            "callGasLimit": "0x186a0",
+            "verificationGasLimit": "0x186a0",
+            "preVerificationGas": "0x5208",
+            "maxFeePerGas": "0x3b9aca00",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "signature": "0x"
+        });
+
+        let op = router
+            .parse_user_operation_from_json(&op_json, entry_point)
+            .unwrap();
+        assert_eq!(op.entry_point(), entry_point);
+
+        let fields = v0_6::UserOperationRequiredFields {
+            sender: "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+                .parse()
+                .unwrap(),
+            nonce: U256::from(1),
+            call_gas_limit: 100_000,
+            verification_gas_limit: 100_000,
+            pre_verification_gas: 21_000,
+            max_fee_per_gas: 1_000_000_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+            ..Default::default()
+        };
+        let expected = v0_6::UserOperationBuilder::new(&chain_spec, fields.clone()).build();
+        assert_eq!(op.hash(), expected.hash());
+
+        let default_spec = ChainSpec {
+            id: 11155420,
+            ..Default::default()
+        };
+        let with_default = v0_6::UserOperationBuilder::new(&default_spec, fields).build();
+        assert_ne!(op.hash(), with_default.hash());
+    }
+
+    #[test]
+    fn test_parse_sponsor_options() {
+        assert!(
+            !GatewayRouter::parse_sponsor_options(None, false)
                 .unwrap()
+                .return_full_operation
+        );
+        assert!(
+            GatewayRouter::parse_sponsor_options(
+                Some(&json!({"returnFullOperation": true})),
+                false
+            )
+            .unwrap()
+            .return_full_operation
+        );
+        assert!(GatewayRouter::parse_sponsor_options(
+            Some(&json!({"returnFullOperation": "yes"})),
+            false
+        )
+        .is_err());
+        assert!(
+            GatewayRouter::parse_sponsor_options(Some(&json!({"attest": true})), false)
+                .unwrap()
+                .attest
+        );
+        assert!(
+            GatewayRouter::parse_sponsor_options(Some(&json!({"attest": "yes"})), false).is_err()
+        );
     }
 
-    /// Parse v0.6 UserOperation from JSON (simplified implementation)
-    fn parse_v06_user_operation(&self, json_value: &Value) -> GatewayResult<UserOperationVariant> {
-        // For now, use a simple approach that creates a minimal v0.6 UserOperation
-        // In production, this would need full field parsing
+    #[test]
+    fn test_parse_debug_timings_needs_debug_scope() {
+        let debug_timings = json!({"debugTimings": true});
+        assert!(
+            GatewayRouter::parse_sponsor_options(Some(&debug_timings), true)
+                .unwrap()
+                .timings
+                .is_some()
+        );
+        assert!(
+            GatewayRouter::parse_sponsor_options(Some(&debug_timings), false)
+                .unwrap()
+                .timings
+                .is_none()
+        );
+        assert!(GatewayRouter::parse_sponsor_options(None, true)
+            .unwrap()
+            .timings
+            .is_none());
+        assert!(
+            GatewayRouter::parse_sponsor_options(Some(&json!({"debugTimings": 1})), true).is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_idempotency_key() {
+        let options = GatewayRouter::parse_sponsor_options(
+            Some(&json!({"idempotencyKey": "order-42"})),
+            false,
+        )
+        .unwrap();
+        assert_eq!(options.idempotency_key.as_deref(), Some("order-42"));
+
+        for invalid in [json!(""), json!(42), json!("k".repeat(65))] {
+            assert!(GatewayRouter::parse_sponsor_options(
+                Some(&json!({ "idempotencyKey": invalid })),
+                false
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_validity_seconds() {
+        let options =
+            GatewayRouter::parse_sponsor_options(Some(&json!({"validitySeconds": 120})), false)
+                .unwrap();
+        assert_eq!(options.validity_seconds, Some(120));
+
+        for invalid in [json!(-1), json!("120"), json!(1.5)] {
+            assert!(GatewayRouter::parse_sponsor_options(
+                Some(&json!({ "validitySeconds": invalid })),
+                false
+            )
+            .is_err());
+        }
+    }
 
-        let sender: Address = json_value
-            .get("sender")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| GatewayError::InvalidRequest("Missing sender field".to_string()))?
+    #[test]
+    fn test_full_operation_round_trip_v06() {
+        let router = GatewayRouter::with_config(eth_config());
+        let entry_point: Address = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789"
             .parse()
-            .map_err(|_| GatewayError::InvalidRequest("Invalid sender address".to_string()))?;
+            .unwrap();
+        let op_json = json!({
+            "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "nonce": "0x1",
+            "initCode": "0x",
+            "callData": "0x",
+            "callGasLimit": "0x186a0",
+            "verificationGasLimit": "0x186a0",
+            "preVerificationGas": "0x5208",
+            "maxFeePerGas": "0x3b9aca00",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "paymasterAndData": "0x",
+            "signature": "0x"
+        });
+        let op = router
+            .parse_user_operation_from_json(&op_json, entry_point)
+            .unwrap();
+        let signed_hash = op.hash();
 
-        let nonce = self.parse_u256_field(json_value, "nonce")?;
+        let paymaster: Address = "0x63c0c19a282a1b52b07dd5a65b58948a07dae32b"
+            .parse()
+            .unwrap();
+        let sponsored = sponsored_op(op, paymaster, [paymaster.as_slice(), &[0xab; 65]].concat());
+        let returned = router.user_operation_to_json(&sponsored);
+        assert_eq!(returned["callGasLimit"], op_json["callGasLimit"]);
 
-        // Create a simplified UserOperation with required fields
-        let user_op = v0_6::UserOperationBuilder::new(
-            &ChainSpec::default(),
-            v0_6::UserOperationRequiredFields {
-                sender,
-                nonce,
-                init_code: self
-                    .parse_bytes_field(json_value, "initCode")
-                    .unwrap_or_default(),
-                call_data: self
-                    .parse_bytes_field(json_value, "callData")
-                    .unwrap_or_default(),
-                call_gas_limit: self
-                    .parse_u128_field(json_value, "callGasLimit")
-                    .unwrap_or(100_000),
-                verification_gas_limit: self
-                    .parse_u128_field(json_value, "verificationGasLimit")
-                    .unwrap_or(100_000),
-                pre_verification_gas: self
-                    .parse_u128_field(json_value, "preVerificationGas")
-                    .unwrap_or(21_000),
-                max_fee_per_gas: self
-                    .parse_u128_field(json_value, "maxFeePerGas")
-                    .unwrap_or(1_000_000_000),
-                max_priority_fee_per_gas: self
-                    .parse_u128_field(json_value, "maxPriorityFeePerGas")
-                    .unwrap_or(1_000_000_000),
-                paymaster_and_data: self
-                    .parse_bytes_field(json_value, "paymasterAndData")
-                    .unwrap_or_default(),
-                signature: self
-                    .parse_bytes_field(json_value, "signature")
-                    .unwrap_or_default(),
-            },
-        )
-        .build();
+        // The returned op parses to the same operation, and without the
+        // paymaster fields it hashes to what the paymaster signed over
+        let reparsed = router
+            .parse_user_operation_from_json(&returned, entry_point)
+            .unwrap();
+        assert_eq!(reparsed.hash(), sponsored.hash());
 
-        Ok(UserOperationVariant::V0_6(user_op))
+        let mut unsponsored = returned.clone();
+        unsponsored["paymasterAndData"] = json!("0x");
+        let unsponsored = router
+            .parse_user_operation_from_json(&unsponsored, entry_point)
+            .unwrap();
+        assert_eq!(unsponsored.hash(), signed_hash);
     }
 
-    /// Parse v0.7 UserOperation from JSON (simplified implementation)
-    fn parse_v07_user_operation(&self, json_value: &Value) -> GatewayResult<UserOperationVariant> {
-        let sender: Address = json_value
-            .get("sender")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| GatewayError::InvalidRequest("Missing sender field".to_string()))?
+    #[test]
+    fn test_full_operation_round_trip_v07() {
+        let router = GatewayRouter::with_config(eth_config());
+        let entry_point: Address = "0x0000000071727De22E5E9d8BAf0edAc6f37da032"
             .parse()
-            .map_err(|_| GatewayError::InvalidRequest("Invalid sender address".to_string()))?;
+            .unwrap();
+        let op_json = json!({
+            "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "nonce": "0x1",
+            "callData": "0x",
+            "callGasLimit": "0x186a0",
+            "verificationGasLimit": "0x186a0",
+            "preVerificationGas": "0x5208",
+            "maxFeePerGas": "0x3b9aca00",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "signature": "0x"
+        });
+        let op = router
+            .parse_user_operation_from_json(&op_json, entry_point)
+            .unwrap();
+        let signed_hash = op.hash();
 
-        let nonce = self.parse_u256_field(json_value, "nonce")?;
+        let paymaster: Address = "0x63c0c19a282a1b52b07dd5a65b58948a07dae32b"
+            .parse()
+            .unwrap();
+        let sponsored = sponsored_op(op, paymaster, vec![0xcd; 65]);
+        let returned = router.user_operation_to_json(&sponsored);
+        assert_eq!(returned["paymaster"], json!(format!("{:#x}", paymaster)));
+        assert_eq!(returned["paymasterVerificationGasLimit"], json!("0x186a0"));
+        assert_eq!(returned["paymasterPostOpGasLimit"], json!("0x4e20"));
 
-        // Create a v0.7 UserOperation with required fields only
-        let chain_spec = ChainSpec::default();
-        let mut builder = v0_7::UserOperationBuilder::new(
-            &chain_spec,
-            v0_7::UserOperationRequiredFields {
-                sender,
-                nonce,
-                call_data: self
-                    .parse_bytes_field(json_value, "callData")
-                    .unwrap_or_default(),
-                call_gas_limit: self
-                    .parse_u128_field(json_value, "callGasLimit")
-                    .unwrap_or(100_000),
-                verification_gas_limit: self
-                    .parse_u128_field(json_value, "verificationGasLimit")
-                    .unwrap_or(100_000),
-                pre_verification_gas: self
-                    .parse_u128_field(json_value, "preVerificationGas")
-                    .unwrap_or(21_000),
-                max_fee_per_gas: self
-                    .parse_u128_field(json_value, "maxFeePerGas")
-                    .unwrap_or(1_000_000_000),
-                max_priority_fee_per_gas: self
-                    .parse_u128_field(json_value, "maxPriorityFeePerGas")
-                    .unwrap_or(1_000_000_000),
-                signature: self
-                    .parse_bytes_field(json_value, "signature")
-                    .unwrap_or_default(),
-            },
-        );
+        let reparsed = router
+            .parse_user_operation_from_json(&returned, entry_point)
+            .unwrap();
+        assert_eq!(reparsed.hash(), sponsored.hash());
 
-        // Add optional fields if present (v0.7 combines factory and factory_data)
-        if let Some(factory) = self
-            .parse_optional_address_field(json_value, "factory")
-            .unwrap_or(None)
-        {
-            let factory_data = self
-                .parse_bytes_field(json_value, "factoryData")
-                .unwrap_or_default();
-            builder = builder.factory(factory, factory_data);
-        }
+        let mut unsponsored = returned.clone();
+        unsponsored["paymaster"] = Value::Null;
+        let unsponsored = router
+            .parse_user_operation_from_json(&unsponsored, entry_point)
+            .unwrap();
+        assert_eq!(unsponsored.hash(), signed_hash);
+    }
 
-        // Add paymaster fields if present (v0.7 combines all paymaster fields)
-        if let Some(paymaster) = self
-            .parse_optional_address_field(json_value, "paymaster")
-            .unwrap_or(None)
-        {
-            let pv_gas_limit = self
-                .parse_optional_u128_field(json_value, "paymasterVerificationGasLimit")
-                .unwrap_or(None)
-                .unwrap_or(100_000);
-            let po_gas_limit = self
-                .parse_optional_u128_field(json_value, "paymasterPostOpGasLimit")
-                .unwrap_or(None)
-                .unwrap_or(100_000);
-            let paymaster_data = self
-                .parse_bytes_field(json_value, "paymasterData")
-                .unwrap_or_default();
-            builder = builder.paymaster(paymaster, pv_gas_limit, po_gas_limit, paymaster_data);
+    fn sponsored_op(
+        op: UserOperationVariant,
+        paymaster: Address,
+        paymaster_and_data: Vec<u8>,
+    ) -> UserOperationVariant {
+        let result = rundler_paymaster_relay::service::PaymasterSponsorResult {
+            paymaster_and_data,
+            verification_gas_limit: Some(100_000),
+            post_op_gas_limit: Some(20_000),
+            pre_verification_gas: None,
+            verification_gas_limit_uo: None,
+            call_gas_limit: None,
+            sponsored_user_op: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            valid_until: 0,
+            valid_after: 0,
+            token_quote: None,
+            cached: false,
+        };
+        rundler_paymaster_relay::service::merge_sponsored_user_operation(op, paymaster, &result)
+    }
+
+    #[tokio::test]
+    async fn test_eip7702_auth_chain_id_mismatch() {
+        let op = json!({
+            "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "nonce": "0x1",
+            "callData": "0x",
+            "callGasLimit": "0x186a0",
+            "verificationGasLimit": "0x186a0",
+            "preVerificationGas": "0x5208",
+            "maxFeePerGas": "0x3b9aca00",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "signature": "0x",
+            "eip7702Auth": auth_json("0x1")
+        });
+
+        let result = send_router()
+            .route_to_rundler(&expired_send_request(
+                op,
+                "0x0000000071727De22E5E9d8BAf0edAc6f37da032",
+            ))
+            .await;
+        match result {
+            Err(GatewayError::InvalidRequest(message)) => assert_eq!(
+                message,
+                "eip7702Auth.chainId 1 does not match gateway chain id 11155111"
+            ),
+            other => panic!("expected a chain id mismatch, got {:?}", other),
         }
+    }
 
-        let user_op = builder.build();
+    const PAYMASTER_KEY: &str =
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
 
-        Ok(UserOperationVariant::V0_7(user_op))
+    fn paymaster_service(dir: &std::path::Path) -> Arc<PaymasterRelayService> {
+        use rundler_paymaster_relay::{policy::PolicyEngine, signer::SignerManager};
+
+        let policy_path = dir.join("policy.toml");
+        std::fs::write(
+            &policy_path,
+            "[default]\nsenders = [\"0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266\"]\n",
+        )
+        .unwrap();
+        let signer_manager =
+            SignerManager::new(secrecy::SecretString::new(PAYMASTER_KEY.to_string().into()))
+                .unwrap();
+        let pool = Arc::new(rundler_pool::LocalPoolBuilder::new(10).get_handle());
+        Arc::new(PaymasterRelayService::new(
+            signer_manager,
+            PolicyEngine::new(&policy_path).unwrap(),
+            pool,
+        ))
     }
 
-    // === JSON conversion helper methods ===
+    fn send_request(op: Value, entry_point: Address) -> JsonRpcRequest {
+        JsonRpcRequest {
+            id: json!(1),
+            method: "eth_sendUserOperation".to_string(),
+            params: vec![op, json!(format!("{:#x}", entry_point))],
+            api_key_id: None,
+            trust_level: None,
+            chain_id: None,
+            no_cache: false,
+            request_id: None,
+            deadline: Default::default(),
+            debug: false,
+            permissions: false,
+        }
+    }
 
-    /// Convert UserOperationVariant back to JSON format
-    fn user_operation_to_json(&self, user_op: &UserOperationVariant) -> Value {
-        match user_op {
-            UserOperationVariant::V0_6(op) => json!({
-                "sender": format!("{:#x}", op.sender()),
-                "nonce": format!("0x{:x}", op.nonce()),
-                "initCode": format!("0x{}", hex::encode(op.init_code())),
-                "callData": format!("0x{}", hex::encode(op.call_data())),
-                "callGasLimit": format!("0x{:x}", op.call_gas_limit()),
-                "verificationGasLimit": format!("0x{:x}", op.verification_gas_limit()),
-                "preVerificationGas": format!("0x{:x}", op.pre_verification_gas()),
-                "maxFeePerGas": format!("0x{:x}", op.max_fee_per_gas()),
-                "maxPriorityFeePerGas": format!("0x{:x}", op.max_priority_fee_per_gas()),
-                "paymasterAndData": format!("0x{}", hex::encode(op.paymaster_and_data())),
-                "signature": format!("0x{}", hex::encode(op.signature()))
-            }),
-            UserOperationVariant::V0_7(op) => json!({
-                "sender": format!("{:#x}", op.sender()),
-                "nonce": format!("0x{:x}", op.nonce()),
-                "factory": op.factory().map(|f| format!("{:#x}", f)),
-                "factoryData": format!("0x{}", hex::encode(op.factory_data())),
-                "callData": format!("0x{}", hex::encode(op.call_data())),
-                "callGasLimit": format!("0x{:x}", op.call_gas_limit()),
-                "verificationGasLimit": format!("0x{:x}", op.verification_gas_limit()),
-                "preVerificationGas": format!("0x{:x}", op.pre_verification_gas()),
-                "maxFeePerGas": format!("0x{:x}", op.max_fee_per_gas()),
-                "maxPriorityFeePerGas": format!("0x{:x}", op.max_priority_fee_per_gas()),
-                "paymaster": op.paymaster().map(|p| format!("{:#x}", p)),
-                "paymasterVerificationGasLimit": if op.paymaster_verification_gas_limit() > 0 {
-                    Some(format!("0x{:x}", op.paymaster_verification_gas_limit()))
-                } else {
-                    None
+    #[tokio::test]
+    async fn test_send_verifies_own_paymaster_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = paymaster_service(dir.path());
+        // The pool is never started, so accepted operations fail at submission
+        let pool = Arc::new(rundler_pool::LocalPoolBuilder::new(10).get_handle());
+        let router = GatewayRouter::with_rundler_components(pool, eth_config())
+            .with_paymaster_service(service.clone());
+        let entry_point: Address = "0x0000000071727De22E5E9d8BAf0edAc6f37da032"
+            .parse()
+            .unwrap();
+        let op = router
+            .parse_user_operation_from_json(
+                &json!({
+                    "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                    "nonce": "0x1",
+                    "callData": "0x",
+                    "callGasLimit": "0x186a0",
+                    "verificationGasLimit": "0x186a0",
+                    "preVerificationGas": "0x5208",
+                    "maxFeePerGas": "0x3b9aca00",
+                    "maxPriorityFeePerGas": "0x3b9aca00",
+                    "signature": "0x"
+                }),
+                entry_point,
+            )
+            .unwrap();
+
+        let sponsored = service
+            .sponsor_user_operation(
+                op.clone(),
+                H160::from_slice(entry_point.as_slice()),
+                SponsorOptions {
+                    return_full_operation: true,
+                    ..Default::default()
                 },
-                "paymasterPostOpGasLimit": if op.paymaster_post_op_gas_limit() > 0 {
-                    Some(format!("0x{:x}", op.paymaster_post_op_gas_limit()))
-                } else {
-                    None
+            )
+            .await
+            .unwrap()
+            .sponsored_user_op
+            .unwrap();
+        let sponsored_json = router.user_operation_to_json(&sponsored);
+
+        // Valid signature passes verification and reaches the pool
+        let result = router
+            .route_to_rundler(&send_request(sponsored_json.clone(), entry_point))
+            .await;
+        assert!(matches!(result, Err(GatewayError::PoolError(_))));
+
+        // A flipped signature byte is rejected before the pool
+        let mut tampered = sponsored_json.clone();
+        let mut paymaster_data =
+            hex::decode(&tampered["paymasterData"].as_str().unwrap()[2..]).unwrap();
+        paymaster_data[100] ^= 0xff;
+        tampered["paymasterData"] = json!(format!("0x{}", hex::encode(paymaster_data)));
+        let err = router
+            .route_to_rundler(&send_request(tampered, entry_point))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidSponsorship(_)));
+        assert_eq!(err.code(), -32501);
+
+        // A correctly signed but expired window is out of time range
+        let paymaster = sponsored.paymaster().unwrap();
+        let expired = sponsored_op(op.clone(), paymaster, vec![]);
+        let hash = sponsorship::sponsorship_hash(&expired, paymaster, 1_000, 0);
+        let wallet: ethers::signers::LocalWallet = PAYMASTER_KEY.parse().unwrap();
+        let signature = wallet
+            .sign_hash(ethers::types::H256::from(
+                sponsorship::signing_digest(hash).0,
+            ))
+            .unwrap();
+        let data = SponsorshipData {
+            valid_until: 1_000,
+            valid_after: 0,
+            signer: Address::from_slice(ethers::signers::Signer::address(&wallet).as_bytes()),
+            signature: signature.to_vec(),
+        };
+        let expired = sponsored_op(op.clone(), paymaster, data.encode());
+        let err = router
+            .route_to_rundler(&send_request(
+                router.user_operation_to_json(&expired),
+                entry_point,
+            ))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::SponsorshipOutOfTimeRange(_)));
+        assert_eq!(err.code(), -32503);
+
+        // Third-party paymasters are not checked here
+        let other: Address = "0x63c0c19a282a1b52b07dd5a65b58948a07dae32b"
+            .parse()
+            .unwrap();
+        let third_party = sponsored_op(op, other, vec![0xab; sponsorship::SPONSORSHIP_DATA_LEN]);
+        let result = router
+            .route_to_rundler(&send_request(
+                router.user_operation_to_json(&third_party),
+                entry_point,
+            ))
+            .await;
+        assert!(matches!(result, Err(GatewayError::PoolError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_sponsorship_for_other_chain_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = paymaster_service(dir.path());
+        let entry_point: Address = "0x0000000071727De22E5E9d8BAf0edAc6f37da032"
+            .parse()
+            .unwrap();
+        let router_for = |chain_id| {
+            let pool = Arc::new(rundler_pool::LocalPoolBuilder::new(10).get_handle());
+            GatewayRouter::with_rundler_components(
+                pool,
+                EthApiConfig {
+                    chain_id,
+                    ..Default::default()
                 },
-                "paymasterData": format!("0x{}", hex::encode(op.paymaster_data())),
-                "signature": format!("0x{}", hex::encode(op.signature()))
-            }),
-        }
+            )
+            .with_paymaster_service(service.clone())
+        };
+        let sepolia = router_for(11155111);
+        let base_sepolia = router_for(84532);
+
+        let op = sepolia
+            .parse_user_operation_from_json(
+                &json!({
+                    "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                    "nonce": "0x1",
+                    "callData": "0x",
+                    "callGasLimit": "0x186a0",
+                    "verificationGasLimit": "0x186a0",
+                    "preVerificationGas": "0x5208",
+                    "maxFeePerGas": "0x3b9aca00",
+                    "maxPriorityFeePerGas": "0x3b9aca00",
+                    "signature": "0x"
+                }),
+                entry_point,
+            )
+            .unwrap();
+        assert_eq!(op.chain_id(), 11155111);
+
+        let sponsored = service
+            .sponsor_user_operation(
+                op,
+                H160::from_slice(entry_point.as_slice()),
+                SponsorOptions {
+                    return_full_operation: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap()
+            .sponsored_user_op
+            .unwrap();
+        let sponsored_json = sepolia.user_operation_to_json(&sponsored);
+
+        // Sent to the chain it was sponsored on, it reaches the pool
+        let result = sepolia
+            .route_to_rundler(&send_request(sponsored_json.clone(), entry_point))
+            .await;
+        assert!(matches!(result, Err(GatewayError::PoolError(_))));
+
+        // Routed to another chain, the signature no longer matches the op hash
+        let err = base_sepolia
+            .route_to_rundler(&send_request(sponsored_json, entry_point))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidSponsorship(_)));
     }
 
-    // === JSON parsing helper methods ===
+    /// Operations of the integration tests, the seeds of the parsing property test
+    const CORPUS: &str = include_str!("../tests/corpus/user_operations.json");
+    const ENTRY_POINT_V06: &str = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
+    const ENTRY_POINT_V07: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+    const MUTATIONS_PER_SEED: usize = 2_000;
 
-    /// Parse a U256 field from JSON
-    fn parse_u256_field(&self, json: &Value, field_name: &str) -> GatewayResult<U256> {
-        let field_value = json
-            .get(field_name)
-            .ok_or_else(|| GatewayError::InvalidRequest(format!("Missing {} field", field_name)))?;
-
-        match field_value {
-            Value::String(s) => {
-                // Handle hex strings (with or without 0x prefix)
-                let cleaned = s.strip_prefix("0x").unwrap_or(s);
-                U256::from_str_radix(cleaned, 16).map_err(|_| {
-                    GatewayError::InvalidRequest(format!("Invalid {} format", field_name))
-                })
+    /// Fields the mutations pick from: both versions' fields and the optional extras
+    const FIELDS: &[&str] = &[
+        "sender",
+        "nonce",
+        "initCode",
+        "callData",
+        "callGasLimit",
+        "verificationGasLimit",
+        "preVerificationGas",
+        "maxFeePerGas",
+        "maxPriorityFeePerGas",
+        "paymasterAndData",
+        "signature",
+        "factory",
+        "factoryData",
+        "paymaster",
+        "paymasterVerificationGasLimit",
+        "paymasterPostOpGasLimit",
+        "paymasterData",
+        "eip7702Auth",
+        "aggregator",
+    ];
+
+    /// Seeded xorshift64* source of arbitrary JSON, so failures replay alike
+    struct Fuzzer(u64);
+
+    impl Fuzzer {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn digits(&mut self, count: usize, alphabet: &[u8]) -> String {
+            (0..count)
+                .map(|_| alphabet[self.below(alphabet.len())] as char)
+                .collect()
+        }
+
+        fn value(&mut self, depth: u32) -> Value {
+            match self.below(if depth == 0 { 12 } else { 14 }) {
+                0 => Value::Null,
+                1 => json!(self.below(2) == 0),
+                2 => json!(self.next()),
+                3 => json!(-(self.below(1_000) as i64) - 1),
+                4 => json!(self.below(1_000) as f64 / 7.0),
+                5 => {
+                    let count = 2 * self.below(80);
+                    json!(format!(
+                        "0x{}",
+                        self.digits(count, b"0123456789abcdefABCDEF")
+                    ))
+                }
+                6 => {
+                    let count = 2 * self.below(40) + 1;
+                    json!(format!("0x{}", self.digits(count, b"0f")))
+                }
+                7 => {
+                    let count = 1 + self.below(100);
+                    json!(self.digits(count, b"0123456789"))
+                }
+                8 => json!(format!("0x{}", "f".repeat(63 + self.below(3)))),
+                9 => {
+                    let count = self.below(20);
+                    json!(self.digits(count, b"0xgz-+. \\\"\xff"))
+                }
+                // Longer than any byte field may be
+                10 => json!(format!("0x{}", "ab".repeat(200_000))),
+                11 => json!(format!("0x{}", "00".repeat(self.below(3) * 10))),
+                12 => Value::Array((0..self.below(3)).map(|_| self.value(depth - 1)).collect()),
+                _ => {
+                    let mut object = serde_json::Map::new();
+                    for _ in 0..self.below(4) {
+                        let field = FIELDS[self.below(FIELDS.len())];
+                        object.insert(field.to_string(), self.value(depth - 1));
+                    }
+                    Value::Object(object)
+                }
             }
-            Value::Number(n) => {
-                if let Some(val) = n.as_u64() {
-                    Ok(U256::from(val))
-                } else {
-                    Err(GatewayError::InvalidRequest(format!(
-                        "Invalid {} number format",
-                        field_name
-                    )))
+        }
+
+        fn mutate(&mut self, seed: &Value) -> Value {
+            let mut op = seed.clone();
+            for _ in 0..1 + self.below(4) {
+                let field = FIELDS[self.below(FIELDS.len())];
+                let action = self.below(10);
+                if action == 0 {
+                    op = self.value(2);
+                    continue;
+                }
+                let Value::Object(fields) = &mut op else {
+                    continue;
+                };
+                match action {
+                    1..=2 => {
+                        fields.remove(field);
+                    }
+                    3 => {
+                        let auth = fields.entry("eip7702Auth").or_insert_with(|| json!({}));
+                        let part =
+                            ["address", "chainId", "nonce", "yParity", "r", "s"][self.below(6)];
+                        let value = self.value(1);
+                        if let Value::Object(auth) = auth {
+                            auth.insert(part.to_string(), value);
+                        }
+                    }
+                    _ => {
+                        fields.insert(field.to_string(), self.value(1));
+                    }
                 }
             }
-            _ => Err(GatewayError::InvalidRequest(format!(
-                "{} must be a string or number",
-                field_name
-            ))),
+            op
         }
     }
 
-    /// Parse a u128 field from JSON
-    fn parse_u128_field(&self, json: &Value, field_name: &str) -> GatewayResult<u128> {
-        let u256_val = self.parse_u256_field(json, field_name)?;
-        u256_val.try_into().map_err(|_| {
-            GatewayError::InvalidRequest(format!("{} value too large for u128", field_name))
-        })
+    /// Parsing `op_json` does not panic, and an operation it yields comes back
+    /// unchanged from its returned JSON
+    fn assert_parses_cleanly(router: &GatewayRouter, op_json: &Value, entry_point: Address) {
+        let _ = router.parse_optional_gas_user_operation(op_json, entry_point);
+        let Ok(op) = router.parse_user_operation_from_json(op_json, entry_point) else {
+            return;
+        };
+        let returned = router.user_operation_to_json(&op);
+        let reparsed = router
+            .parse_user_operation_from_json(&returned, entry_point)
+            .unwrap_or_else(|e| panic!("{} parsed from {} fails: {}", returned, op_json, e));
+        assert_eq!(reparsed.hash(), op.hash(), "{}", op_json);
+        assert_eq!(router.user_operation_to_json(&reparsed), returned);
     }
 
-    /// Parse an optional u128 field from JSON
-    fn parse_optional_u128_field(
-        &self,
-        json: &Value,
-        field_name: &str,
-    ) -> GatewayResult<Option<u128>> {
-        match json.get(field_name) {
-            Some(value) if !value.is_null() => Ok(Some(self.parse_u128_field(json, field_name)?)),
-            _ => Ok(None),
-        }
+    fn limits_router(field_parsing: FieldParsing) -> GatewayRouter {
+        GatewayRouter::with_config(eth_config()).with_limits(ValidationConfig {
+            field_parsing,
+            ..Default::default()
+        })
     }
 
-    /// Parse a bytes field from JSON
-    fn parse_bytes_field(&self, json: &Value, field_name: &str) -> GatewayResult<Bytes> {
-        let field_value = json
-            .get(field_name)
-            .ok_or_else(|| GatewayError::InvalidRequest(format!("Missing {} field", field_name)))?;
+    #[test]
+    fn test_parsing_arbitrary_json_never_panics() {
+        let corpus: Vec<Value> = serde_json::from_str(CORPUS).unwrap();
+        let entry_points = [
+            ENTRY_POINT_V06.parse().unwrap(),
+            ENTRY_POINT_V07.parse().unwrap(),
+        ];
+        let mut fuzzer = Fuzzer(0x5EED_F00D);
 
-        match field_value {
-            Value::String(s) => {
-                let cleaned = s.strip_prefix("0x").unwrap_or(s);
-                if cleaned.is_empty() {
-                    Ok(Bytes::new())
-                } else {
-                    hex::decode(cleaned).map(Bytes::from).map_err(|_| {
-                        GatewayError::InvalidRequest(format!("Invalid {} hex format", field_name))
-                    })
+        for field_parsing in [FieldParsing::Strict, FieldParsing::Lenient] {
+            let router = limits_router(field_parsing);
+            for seed in &corpus {
+                let entry_point: Address = seed["entryPoint"].as_str().unwrap().parse().unwrap();
+                let seed = &seed["userOperation"];
+                router
+                    .parse_user_operation_from_json(seed, entry_point)
+                    .unwrap_or_else(|e| panic!("corpus operation {} fails: {}", seed, e));
+                assert_parses_cleanly(&router, seed, entry_point);
+
+                for _ in 0..MUTATIONS_PER_SEED {
+                    let op_json = fuzzer.mutate(seed);
+                    // Mostly the seed's EntryPoint, sometimes the other version's
+                    let entry_point = if fuzzer.below(4) == 0 {
+                        entry_points[fuzzer.below(2)]
+                    } else {
+                        entry_point
+                    };
+                    assert_parses_cleanly(&router, &op_json, entry_point);
                 }
             }
-            _ => Err(GatewayError::InvalidRequest(format!(
-                "{} must be a hex string",
-                field_name
-            ))),
         }
     }
 
-    /// Parse an optional address field from JSON
-    fn parse_optional_address_field(
-        &self,
-        json: &Value,
-        field_name: &str,
-    ) -> GatewayResult<Option<Address>> {
-        match json.get(field_name) {
-            Some(Value::String(s)) if !s.is_empty() && s != "0x" => {
-                s.parse().map(Some).map_err(|_| {
-                    GatewayError::InvalidRequest(format!("Invalid {} address", field_name))
-                })
-            }
-            _ => Ok(None),
+    fn corpus_op(index: usize) -> (Value, Address) {
+        let corpus: Vec<Value> = serde_json::from_str(CORPUS).unwrap();
+        let entry_point = corpus[index]["entryPoint"]
+            .as_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        (corpus[index]["userOperation"].clone(), entry_point)
+    }
+
+    #[test]
+    fn test_overflowing_gas_limits_are_rejected() {
+        let router = limits_router(FieldParsing::Strict);
+        let max = format!("{:#x}", u128::MAX);
+
+        // Counted three times with a paymaster
+        let (mut v06, entry_point) = corpus_op(1);
+        v06["verificationGasLimit"] = json!(max);
+        let err = router
+            .parse_user_operation_from_json(&v06, entry_point)
+            .unwrap_err();
+        assert!(err.to_string().contains("overflow"), "{}", err);
+
+        let (mut v07, entry_point) = corpus_op(4);
+        v07["callGasLimit"] = json!(max);
+        v07["preVerificationGas"] = json!(max);
+        let err = router
+            .parse_user_operation_from_json(&v07, entry_point)
+            .unwrap_err();
+        assert!(err.to_string().contains("overflow"), "{}", err);
+    }
+
+    #[test]
+    fn test_oversized_hex_is_rejected_before_decoding() {
+        let router = limits_router(FieldParsing::Lenient);
+        let (mut op, entry_point) = corpus_op(3);
+        op["callData"] = json!(format!("0x{}", "ab".repeat(128 * 1024 + 1)));
+
+        // Lenient parsing does not drop the field for an empty one
+        let err = router
+            .parse_user_operation_from_json(&op, entry_point)
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("callData of 131073 bytes exceeds the maximum of 131072 bytes"),
+            "{}",
+            err
+        );
+        assert!(router
+            .parse_optional_gas_user_operation(&op, entry_point)
+            .is_err());
+    }
+
+    #[test]
+    fn test_non_object_user_operation_is_rejected() {
+        let router = limits_router(FieldParsing::Lenient);
+        let entry_point: Address = ENTRY_POINT_V07.parse().unwrap();
+        for op_json in [json!([]), json!("0x"), json!(1), Value::Null] {
+            assert!(router
+                .parse_user_operation_from_json(&op_json, entry_point)
+                .is_err());
+            assert!(router
+                .parse_optional_gas_user_operation(&op_json, entry_point)
+                .is_err());
         }
     }
-}
 
-impl Default for GatewayRouter {
-    fn default() -> Self {
-        Self::new()
+    #[test]
+    fn test_zero_paymaster_gas_limits_round_trip() {
+        let router = limits_router(FieldParsing::Strict);
+        let (mut op_json, entry_point) = corpus_op(4);
+        op_json["paymasterVerificationGasLimit"] = json!("0x0");
+        op_json["paymasterPostOpGasLimit"] = json!("0x0");
+
+        let op = router
+            .parse_user_operation_from_json(&op_json, entry_point)
+            .unwrap();
+        let returned = router.user_operation_to_json(&op);
+        // Left out, they would parse back as the 100000 default
+        assert_eq!(returned["paymasterVerificationGasLimit"], json!("0x0"));
+        assert_parses_cleanly(&router, &op_json, entry_point);
+    }
+
+    fn request(method: &str, params: Vec<Value>) -> JsonRpcRequest {
+        test_utils::request(method, params)
+    }
+
+    #[tokio::test]
+    async fn test_debug_methods_unsupported_when_disabled() {
+        let mut builder = MockBuilder::new();
+        builder.expect_debug_send_bundle_now().never();
+        let router = GatewayRouter::new().with_builder(Arc::new(builder));
+
+        for method in [
+            "debug_bundler_clearState",
+            "debug_bundler_dumpMempool",
+            "debug_bundler_sendBundleNow",
+            "debug_bundler_setBundlingMode",
+            "debug_bundler_setReputation",
+            "rundler_sendBundleNow",
+            "rundler_setReputation",
+        ] {
+            let err = router
+                .route_to_rundler(&request(method, vec![]))
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(&err, GatewayError::UnsupportedMethod(m) if m == method),
+                "{}: {:?}",
+                method,
+                err
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_bundle_now_through_builder() {
+        let tx_hash = B256::repeat_byte(0x11);
+        let mut builder = MockBuilder::new();
+        builder
+            .expect_debug_send_bundle_now()
+            .times(1)
+            .returning(move || Ok((tx_hash, 1)));
+        let router = GatewayRouter::new()
+            .with_builder(Arc::new(builder))
+            .with_debug_api(true);
+
+        let result = router
+            .route_to_rundler(&request("debug_bundler_sendBundleNow", vec![]))
+            .await
+            .unwrap();
+        assert_eq!(result, json!(format!("{:#x}", tx_hash)));
+    }
+
+    #[tokio::test]
+    async fn test_rundler_send_bundle_now_through_builder() {
+        let tx_hash = B256::repeat_byte(0x22);
+        let mut builder = MockBuilder::new();
+        builder
+            .expect_debug_send_bundle_now()
+            .times(1)
+            .returning(move || Ok((tx_hash, 7)));
+        let router = GatewayRouter::new()
+            .with_builder(Arc::new(builder))
+            .with_debug_api(true);
+
+        let result = router
+            .route_to_rundler(&request("rundler_sendBundleNow", vec![]))
+            .await
+            .unwrap();
+        assert_eq!(result, json!(format!("{:#x}", tx_hash)));
+
+        let err = GatewayRouter::new()
+            .with_debug_api(true)
+            .route_to_rundler(&request("rundler_sendBundleNow", vec![]))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, GatewayError::InvalidRequest(m) if m.contains("Builder not available"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_builder_availability() {
+        let health = HealthChecker::new().with_probe(Arc::new(BuilderProbe::new(None)));
+        let status = health.check_health().await;
+        assert_eq!(status.status, SystemStatus::Healthy);
+        assert_eq!(status.components[0].name, "builder");
+        assert_eq!(
+            status.components[0].health.details,
+            Some(json!({ "available": false }))
+        );
+
+        let mut builder = MockBuilder::new();
+        builder
+            .expect_get_supported_entry_points()
+            .returning(|| Err(BuilderError::Other(anyhow::anyhow!("task stopped"))));
+        // Attaching a builder replaces the detached probe of the same name
+        let health = HealthChecker::new()
+            .with_probe(Arc::new(BuilderProbe::new(None)))
+            .with_probe(Arc::new(BuilderProbe::new(Some(Arc::new(builder)))));
+        let status = health.check_health().await;
+        assert_eq!(status.components.len(), 1);
+        let report = &status.components[0];
+        assert_eq!(report.health.status, ComponentStatus::Error);
+        assert_eq!(report.health.details, Some(json!({ "available": true })));
+        assert!(!report.critical);
+        // Operations are still accepted without bundling
+        assert_eq!(status.status, SystemStatus::Degraded);
+    }
+
+    #[tokio::test]
+    async fn test_set_bundling_mode() {
+        let mut builder = MockBuilder::new();
+        builder
+            .expect_debug_set_bundling_mode()
+            .withf(|mode| *mode == BundlingMode::Manual)
+            .times(1)
+            .returning(|_| Ok(()));
+        let router = GatewayRouter::new()
+            .with_builder(Arc::new(builder))
+            .with_debug_api(true);
+
+        let result = router
+            .route_to_rundler(&request(
+                "debug_bundler_setBundlingMode",
+                vec![json!("manual")],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(result, json!("ok"));
+
+        let err = router
+            .route_to_rundler(&request(
+                "debug_bundler_setBundlingMode",
+                vec![json!("sometimes")],
+            ))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidRequest(_)));
+    }
+
+    #[tokio::test]
+    async fn test_builder_methods_require_builder() {
+        let router = GatewayRouter::new().with_debug_api(true);
+
+        let err = router
+            .route_to_rundler(&request("debug_bundler_sendBundleNow", vec![]))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, GatewayError::InvalidRequest(m) if m.contains("Builder not available"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pool_methods_validate_params() {
+        let router = GatewayRouter::new().with_debug_api(true);
+
+        let err = router
+            .route_to_rundler(&request(
+                "debug_bundler_dumpMempool",
+                vec![json!("0x1111111111111111111111111111111111111111")],
+            ))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, GatewayError::InvalidRequest(m) if m.contains("Unsupported entry point"))
+        );
+
+        let err = router
+            .route_to_rundler(&request(
+                "debug_bundler_setReputation",
+                vec![
+                    json!([{ "address": "0x1111111111111111111111111111111111111111", "opsSeen": "0x1" }]),
+                    json!(ENTRY_POINT_V07),
+                ],
+            ))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidRequest(m) if m.contains("opsIncluded")));
+
+        // Well-formed params still need a pool
+        let err = router
+            .route_to_rundler(&request(
+                "debug_bundler_setReputation",
+                vec![
+                    json!([{
+                        "address": "0x1111111111111111111111111111111111111111",
+                        "opsSeen": "0x14",
+                        "opsIncluded": 3
+                    }]),
+                    json!(ENTRY_POINT_V07),
+                ],
+            ))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidRequest(m) if m.contains("Pool not available")));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_debug_method_unsupported_when_enabled() {
+        let router = GatewayRouter::new().with_debug_api(true);
+
+        let err = router
+            .route_to_rundler(&request("debug_bundler_dumpPaymasterBalances", vec![]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::UnsupportedMethod(_)));
     }
 }
@@ -0,0 +1,1198 @@
+use serde_json::{json, Map, Value};
+use utoipa::ToSchema;
+
+use crate::{
+    api_docs::{JsonRpcError, UserOperation},
+    method_registry::{self, MethodSpec},
+    middleware::ApiKeyScope,
+};
+
+/// Path the JSON-RPC schema document is served at
+pub const RPC_SCHEMA_PATH: &str = "/rpc-schema";
+
+/// Positional parameter of a JSON-RPC method
+#[derive(Debug, Clone)]
+pub struct RpcParam {
+    /// Name the parameter is documented under
+    pub name: &'static str,
+    /// Whether the parameter has to be given
+    pub required: bool,
+    /// JSON Schema of the parameter
+    pub schema: Value,
+}
+
+/// Parameters and result of a JSON-RPC method answered by the gateway
+#[derive(Debug, Clone)]
+pub struct RpcMethod {
+    /// Method name, e.g. `pm_sponsorUserOperation`
+    pub name: &'static str,
+    /// One line description
+    pub summary: &'static str,
+    /// Parameters in `params` order; optional ones come last
+    pub params: Vec<RpcParam>,
+    /// JSON Schema of `result`
+    pub result: Value,
+}
+
+impl RpcMethod {
+    fn new(name: &'static str, summary: &'static str, result: Value) -> Self {
+        Self {
+            name,
+            summary,
+            params: Vec::new(),
+            result,
+        }
+    }
+
+    fn param(mut self, name: &'static str, schema: Value) -> Self {
+        self.params.push(RpcParam {
+            name,
+            required: true,
+            schema,
+        });
+        self
+    }
+
+    fn optional(mut self, name: &'static str, schema: Value) -> Self {
+        self.params.push(RpcParam {
+            name,
+            required: false,
+            schema,
+        });
+        self
+    }
+
+    /// Registry entry of the method
+    pub fn spec(&self) -> Option<&'static MethodSpec> {
+        method_registry::lookup(self.name)
+    }
+
+    /// Namespace the method is documented and tagged under
+    pub fn namespace(&self) -> &'static str {
+        match self.spec() {
+            Some(spec) => spec.namespace,
+            None => self.name.split('_').next().unwrap_or(self.name),
+        }
+    }
+
+    /// API key scope the method requires
+    pub fn scope(&self) -> ApiKeyScope {
+        ApiKeyScope::required_for(self.name)
+    }
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn nullable(schema: Value) -> Value {
+    json!({ "oneOf": [schema, { "type": "null" }] })
+}
+
+fn array_of(schema: Value) -> Value {
+    json!({ "type": "array", "items": schema })
+}
+
+fn object(description: &str) -> Value {
+    json!({ "type": "object", "description": description })
+}
+
+fn string(description: &str) -> Value {
+    json!({ "type": "string", "description": description })
+}
+
+/// Every JSON-RPC method the gateway routes, with its parameters and result
+///
+/// A method registered in `method_registry::METHODS` without an entry here
+/// fails the cross-check in this module's tests.
+pub fn rpc_methods() -> Vec<RpcMethod> {
+    let address = || schema_ref("Address");
+    let hash = || schema_ref("Hash");
+    let quantity = || schema_ref("Quantity");
+    let user_operation = || schema_ref("UserOperation");
+    let timestamp = || schema_ref("Timestamp");
+    let ok = || json!({ "const": "ok" });
+    let reputations = || {
+        array_of(json!({
+            "type": "object",
+            "required": ["address", "opsSeen", "opsIncluded"],
+            "properties": {
+                "address": address(),
+                "opsSeen": quantity(),
+                "opsIncluded": quantity(),
+            },
+        }))
+    };
+    let entity_reputation = || {
+        json!({
+            "type": "object",
+            "required": ["entryPoint", "address", "opsSeen", "opsIncluded", "status"],
+            "properties": {
+                "entryPoint": address(),
+                "address": address(),
+                "opsSeen": quantity(),
+                "opsIncluded": quantity(),
+                "status": { "enum": ["ok", "throttled", "banned"] },
+            },
+        })
+    };
+
+    vec![
+        // Paymaster
+        RpcMethod::new(
+            "pm_sponsorUserOperation",
+            "Sign paymaster data sponsoring the gas of a UserOperation",
+            schema_ref("SponsorshipResult"),
+        )
+        .param("userOperation", user_operation())
+        .param("entryPoint", address())
+        .optional("options", schema_ref("SponsorOptions")),
+        RpcMethod::new(
+            "pm_sponsorUserOperationERC20",
+            "Sponsor a UserOperation paying its gas in an ERC-20 token, with a signed token quote",
+            schema_ref("SponsorshipResult"),
+        )
+        .param("userOperation", user_operation())
+        .param("entryPoint", address())
+        .param("token", address())
+        .optional("options", schema_ref("SponsorOptions")),
+        RpcMethod::new(
+            "pm_sponsorUserOperationBatch",
+            "Sponsor several UserOperations, with one result or error per operation in order",
+            array_of(schema_ref("SponsorshipBatchEntry")),
+        )
+        .param("userOperations", array_of(user_operation()))
+        .param("entryPoint", address())
+        .optional("options", schema_ref("BatchSponsorOptions")),
+        RpcMethod::new(
+            "pm_simulateSponsorship",
+            "Preview whether a UserOperation would be sponsored, without paymaster data",
+            schema_ref("SponsorshipSimulation"),
+        )
+        .param("userOperation", user_operation())
+        .param("entryPoint", address()),
+        RpcMethod::new(
+            "pm_getSupportedEntryPoints",
+            "EntryPoints with a deployed paymaster contract, by version",
+            object("Supported EntryPoints and their paymaster contracts"),
+        ),
+        RpcMethod::new(
+            "pm_getDepositInfo",
+            "Deposit and stake of the paymaster at an EntryPoint",
+            object("Deposit, stake and withdrawal delay of the paymaster"),
+        )
+        .param("entryPoint", address()),
+        RpcMethod::new(
+            "pm_depositTo",
+            "Deposit wei to the paymaster's EntryPoint balance",
+            schema_ref("DepositTransaction"),
+        )
+        .param("entryPoint", address())
+        .param("amount", quantity()),
+        RpcMethod::new(
+            "pm_withdrawTo",
+            "Withdraw wei from the paymaster's EntryPoint balance",
+            schema_ref("DepositTransaction"),
+        )
+        .param("entryPoint", address())
+        .param("withdrawAddress", address())
+        .param("amount", quantity()),
+        RpcMethod::new(
+            "pm_getOutboundTx",
+            "Transaction sent from the paymaster account, across its fee replacements",
+            nullable(object("Outbound transaction")),
+        )
+        .param(
+            "txHashOrId",
+            string("Hash of any submission, or the outboundTxId of pm_depositTo"),
+        ),
+        RpcMethod::new(
+            "pm_getBalanceStatus",
+            "Paymaster balances against the configured thresholds",
+            object("Balance status of the paymaster"),
+        ),
+        RpcMethod::new(
+            "pm_invalidateSbtCache",
+            "Drop the cached SBT balances of a sender",
+            json!({ "type": "boolean", "description": "Whether balances were cached" }),
+        )
+        .param("sender", address()),
+        RpcMethod::new(
+            "pm_rotatePaymasterKey",
+            "Switch to a new paymaster signing key, accepting the old one for a grace period",
+            object("Old and new signer addresses and the end of the grace period"),
+        )
+        .param(
+            "source",
+            json!({
+                "type": "object",
+                "description": "{\"env\": name} or {\"kms\": keyId}",
+                "minProperties": 1,
+                "maxProperties": 1,
+                "properties": {
+                    "env": { "type": "string" },
+                    "kms": { "type": "string" },
+                },
+            }),
+        )
+        .optional("graceSeconds", json!({ "type": "integer", "minimum": 0 })),
+        RpcMethod::new(
+            "pm_getSignerStatus",
+            "Paymaster signers with their balances and draining state",
+            array_of(object("Status of one signer")),
+        ),
+        RpcMethod::new(
+            "pm_drainSigner",
+            "Stop or resume assigning sponsorships to a signer",
+            object("Status of the signer"),
+        )
+        .param("signer", address())
+        .optional("draining", json!({ "type": "boolean", "default": true })),
+        RpcMethod::new(
+            "pm_getUsageReport",
+            "Sponsorships and gas of an API key, bucketed by hour or day",
+            json!({
+                "type": "object",
+                "required": ["apiKeyId", "granularity", "buckets"],
+                "properties": {
+                    "apiKeyId": { "type": "string" },
+                    "granularity": { "enum": ["hour", "day"] },
+                    "buckets": array_of(object("Usage of one bucket")),
+                },
+            }),
+        )
+        .param("apiKeyId", json!({ "type": "string" }))
+        .param("fromTimestamp", timestamp())
+        .param("toTimestamp", timestamp())
+        .optional(
+            "granularity",
+            json!({ "enum": ["hour", "day"], "default": "hour" }),
+        ),
+        RpcMethod::new(
+            "pm_getSponsorshipRecord",
+            "Sponsorship of a UserOperation, from signing to inclusion",
+            nullable(object("Sponsorship record")),
+        )
+        .param("userOpHash", hash()),
+        RpcMethod::new(
+            "pm_listSponsorships",
+            "Page of sponsorship records signed within a time range",
+            object("Records of the page and the total count"),
+        )
+        .param("fromTimestamp", timestamp())
+        .param("toTimestamp", timestamp())
+        .optional(
+            "status",
+            json!({ "type": ["string", "null"], "description": "Only records in this status" }),
+        )
+        .optional(
+            "page",
+            json!({ "type": "integer", "minimum": 0, "default": 0 }),
+        )
+        .optional("pageSize", json!({ "type": "integer", "minimum": 1 })),
+        RpcMethod::new(
+            "pm_loadCandidatePolicy",
+            "Start shadow-evaluating a candidate sponsorship policy file",
+            schema_ref("PolicyShadowReport"),
+        )
+        .param(
+            "file",
+            string("Name of a policy file in the candidate_dir of [paymaster_relay.policy_shadow]"),
+        ),
+        RpcMethod::new(
+            "pm_getPolicyShadowReport",
+            "Divergences of the candidate policy from the active one",
+            schema_ref("PolicyShadowReport"),
+        ),
+        RpcMethod::new(
+            "pm_promoteCandidatePolicy",
+            "Enforce the candidate policy, returning its final shadow report",
+            schema_ref("PolicyShadowReport"),
+        ),
+        RpcMethod::new(
+            "pm_getAttestationKey",
+            "Address response attestations are signed with",
+            address(),
+        ),
+        // ERC-4337
+        RpcMethod::new(
+            "eth_sendUserOperation",
+            "Submit a UserOperation to the mempool",
+            hash(),
+        )
+        .param("userOperation", user_operation())
+        .param("entryPoint", address())
+        .optional(
+            "options",
+            object("Pool permissions requested as {permissions}, needs the permissions scope"),
+        ),
+        RpcMethod::new(
+            "eth_estimateUserOperationGas",
+            "Estimate the gas limits of a UserOperation; with sponsorshipIntent: true on the \
+             operation, as sponsored by this paymaster",
+            schema_ref("GasEstimate"),
+        )
+        .param("userOperation", user_operation())
+        .param("entryPoint", address())
+        .optional(
+            "stateOverride",
+            object("State overrides applied for this estimate only"),
+        ),
+        RpcMethod::new(
+            "eth_getUserOperationByHash",
+            "UserOperation by hash, pending or included",
+            nullable(object("UserOperation with its EntryPoint and inclusion")),
+        )
+        .param("userOpHash", hash()),
+        RpcMethod::new(
+            "eth_getUserOperationReceipt",
+            "Receipt of an included UserOperation",
+            nullable(object("UserOperation receipt")),
+        )
+        .param("userOpHash", hash()),
+        RpcMethod::new(
+            "eth_supportedEntryPoints",
+            "Checksummed EntryPoint addresses",
+            array_of(address()),
+        ),
+        RpcMethod::new("eth_chainId", "Chain id as a hex quantity", quantity()),
+        RpcMethod::new(
+            "eth_getUserOperationNonce",
+            "Next usable nonce of a sender, counting its operations pending in the pool",
+            quantity(),
+        )
+        .param("sender", address())
+        .param("entryPoint", address())
+        .optional("key", quantity()),
+        // Bundle tracking
+        RpcMethod::new(
+            "rundler_getBundleStats",
+            "Bundle counts over the tracking window and the latest bundle attempts",
+            object("Bundle statistics with recentBundles"),
+        ),
+        RpcMethod::new(
+            "rundler_getBundleByHash",
+            "Tracked bundle sent in a transaction",
+            nullable(object("Bundle attempt")),
+        )
+        .param("txHash", hash()),
+        RpcMethod::new(
+            "rundler_sendBundleNow",
+            "Bundle the pending UserOperations now, returning the bundle transaction hash; \
+             with debug_api enabled",
+            hash(),
+        ),
+        RpcMethod::new(
+            "rundler_getEntityReputation",
+            "Pool reputation of a paymaster, factory or aggregator, per EntryPoint",
+            array_of(entity_reputation()),
+        )
+        .param("address", address())
+        .optional("entryPoint", address()),
+        RpcMethod::new(
+            "rundler_listThrottledEntities",
+            "Entities the pool currently throttles or bans, per EntryPoint",
+            array_of(entity_reputation()),
+        )
+        .optional("entryPoint", address()),
+        RpcMethod::new(
+            "rundler_setReputation",
+            "Set the reputations of entities, e.g. to lift a throttle after an incident; \
+             with debug_api enabled",
+            ok(),
+        )
+        .param("reputations", reputations())
+        .param("entryPoint", address()),
+        // Bundler spec test methods, answered with [gateway] debug_api enabled
+        RpcMethod::new(
+            "debug_bundler_clearState",
+            "Clear the mempool and reputations",
+            ok(),
+        ),
+        RpcMethod::new(
+            "debug_bundler_dumpMempool",
+            "UserOperations pending for an EntryPoint",
+            array_of(user_operation()),
+        )
+        .param("entryPoint", address()),
+        RpcMethod::new(
+            "debug_bundler_sendBundleNow",
+            "Build and send a bundle now",
+            hash(),
+        ),
+        RpcMethod::new(
+            "debug_bundler_setBundlingMode",
+            "Switch between automatic and manual bundling",
+            ok(),
+        )
+        .param("mode", json!({ "enum": ["auto", "manual"] })),
+        RpcMethod::new(
+            "debug_bundler_setReputation",
+            "Set the reputations of entities",
+            ok(),
+        )
+        .param("reputations", reputations())
+        .param("entryPoint", address()),
+        // SuperRelay
+        RpcMethod::new(
+            "superRelay_sendUserOperationConditional",
+            "Submit a UserOperation bundled only while the expected storage holds",
+            hash(),
+        )
+        .param("userOperation", user_operation())
+        .param("entryPoint", address())
+        .param(
+            "expectedStorage",
+            object("Expected storage slot values by contract address"),
+        ),
+        RpcMethod::new(
+            "superRelay_getVerificationProof",
+            "Checks a UserOperation passed before it was admitted",
+            nullable(object("Verification proof")),
+        )
+        .param("userOpHash", hash()),
+        RpcMethod::new(
+            "superRelay_getOpInclusionStatus",
+            "What the builders did with a UserOperation",
+            nullable(object("Inclusion status")),
+        )
+        .param("userOpHash", hash()),
+        RpcMethod::new(
+            "superRelay_getPipelineStats",
+            "Counters of the security modules, in pipeline order",
+            json!({
+                "type": "object",
+                "required": ["modules", "stats"],
+                "properties": {
+                    "modules": array_of(json!({ "type": "string" })),
+                    "stats": object("Counters by module"),
+                },
+            }),
+        ),
+        RpcMethod::new(
+            "superRelay_getEntryPointStatus",
+            "Send, estimate and sponsor capability of every configured EntryPoint",
+            json!({
+                "type": "object",
+                "required": ["checkedAt", "entryPoints"],
+                "properties": {
+                    "checkedAt": { "type": "integer" },
+                    "entryPoints": array_of(json!({
+                        "type": "object",
+                        "required": ["entryPoint", "send", "estimate", "sponsor"],
+                        "properties": {
+                            "entryPoint": address(),
+                            "version": { "type": "string" },
+                            "send": { "type": "boolean" },
+                            "estimate": { "type": "boolean" },
+                            "sponsor": { "type": "boolean" },
+                            "issues": array_of(json!({ "type": "string" })),
+                        },
+                    })),
+                },
+            }),
+        ),
+        RpcMethod::new(
+            "superRelay_poolSummary",
+            "Pending UserOperations by EntryPoint and sender",
+            object("Mempool summary"),
+        ),
+        RpcMethod::new(
+            "superRelay_getUserOperationGasPrice",
+            "Slow, standard and fast fees for the latest block",
+            object("Fee suggestions"),
+        ),
+        RpcMethod::new(
+            "superRelay_simulateBundle",
+            "Run handleOps of pooled or raw UserOperations against a block without sending it",
+            json!({
+                "type": "object",
+                "required": ["ops", "gasLimit", "gasUsed"],
+                "properties": {
+                    "ops": array_of(json!({
+                        "type": "object",
+                        "required": ["userOpHash", "status"],
+                        "properties": {
+                            "userOpHash": hash(),
+                            "status": {
+                                "enum": ["success", "reverted", "included", "failed", "skipped"],
+                            },
+                            "reason": { "type": "string" },
+                            "actualGasUsed": quantity(),
+                        },
+                    })),
+                    "gasLimit": nullable(quantity()),
+                    "gasUsed": nullable(quantity()),
+                },
+            }),
+        )
+        .param("entryPoint", address())
+        .param(
+            "userOps",
+            array_of(json!({ "oneOf": [hash(), user_operation()] })),
+        )
+        .optional(
+            "block",
+            json!({ "oneOf": [quantity(), string("Block tag or hash")] }),
+        ),
+        RpcMethod::new(
+            "superRelay_getDummyPaymasterData",
+            "Paymaster fields with a stub signature, sized like a sponsorship, for gas estimation",
+            json!({
+                "type": "object",
+                "required": ["entryPoint", "version", "paymaster"],
+                "properties": {
+                    "entryPoint": address(),
+                    "version": { "type": "string" },
+                    "paymaster": address(),
+                    "paymasterAndData": schema_ref("Bytes"),
+                    "paymasterVerificationGasLimit": quantity(),
+                    "paymasterPostOpGasLimit": quantity(),
+                    "paymasterData": schema_ref("Bytes"),
+                },
+            }),
+        )
+        .param("entryPoint", address()),
+        RpcMethod::new(
+            "superRelay_getErrorCatalog",
+            "Key, code and params of every JSON-RPC error",
+            object("Error catalog"),
+        ),
+        // Admin
+        RpcMethod::new(
+            "admin_getAccessLists",
+            "Sender allowlist and denylist enforced at the gateway edge",
+            object("Access lists"),
+        ),
+        RpcMethod::new(
+            "admin_addAccessListEntry",
+            "Add a sender to an access list",
+            schema_ref("AccessListChange"),
+        )
+        .param("list", schema_ref("AccessList"))
+        .param("address", address()),
+        RpcMethod::new(
+            "admin_removeAccessListEntry",
+            "Remove a sender from an access list",
+            schema_ref("AccessListChange"),
+        )
+        .param("list", schema_ref("AccessList"))
+        .param("address", address()),
+        RpcMethod::new(
+            "admin_testAlert",
+            "Deliver a synthetic alert to every webhook",
+            json!({
+                "type": "object",
+                "required": ["alert", "deliveries"],
+                "properties": {
+                    "alert": object("Alert fired"),
+                    "deliveries": array_of(object("Delivery to one webhook")),
+                },
+            }),
+        )
+        .optional("message", json!({ "type": "string" })),
+    ]
+}
+
+/// Schemas shared by the methods, referenced as `#/components/schemas/<name>`
+fn component_schemas() -> Map<String, Value> {
+    let mut schemas = Map::new();
+    let mut add = |name: &str, schema: Value| {
+        schemas.insert(name.to_string(), schema);
+    };
+
+    add(
+        "Address",
+        json!({ "type": "string", "pattern": "^0x[0-9a-fA-F]{40}$" }),
+    );
+    add(
+        "Hash",
+        json!({ "type": "string", "pattern": "^0x[0-9a-fA-F]{64}$" }),
+    );
+    add(
+        "Bytes",
+        json!({ "type": "string", "pattern": "^0x([0-9a-fA-F]{2})*$" }),
+    );
+    add(
+        "Quantity",
+        json!({
+            "type": "string",
+            "pattern": "^0x[0-9a-fA-F]+$",
+            "description": "Hex encoded unsigned integer",
+        }),
+    );
+    add(
+        "Timestamp",
+        json!({
+            "oneOf": [
+                { "type": "integer", "minimum": 0 },
+                { "type": "string", "description": "Decimal or 0x-hex unix time" },
+            ],
+        }),
+    );
+
+    // The REST documentation's v0.6 operation, plus the v0.7 packed form
+    let (_, user_operation_v0_6) = <UserOperation as ToSchema<'_>>::schema();
+    add(
+        "UserOperationV06",
+        serde_json::to_value(user_operation_v0_6).unwrap_or_default(),
+    );
+    let address = schema_ref("Address");
+    let bytes = schema_ref("Bytes");
+    let quantity = schema_ref("Quantity");
+    add(
+        "UserOperationV07",
+        json!({
+            "type": "object",
+            "required": ["sender", "nonce"],
+            "properties": {
+                "sender": address,
+                "nonce": quantity,
+                "factory": address,
+                "factoryData": bytes,
+                "callData": bytes,
+                "callGasLimit": quantity,
+                "verificationGasLimit": quantity,
+                "preVerificationGas": quantity,
+                "maxFeePerGas": quantity,
+                "maxPriorityFeePerGas": quantity,
+                "paymaster": address,
+                "paymasterVerificationGasLimit": quantity,
+                "paymasterPostOpGasLimit": quantity,
+                "paymasterData": bytes,
+                "signature": bytes,
+                "eip7702Auth": {
+                    "type": "object",
+                    "description": "EIP-7702 authorization delegating the sender",
+                },
+                "aggregator": address,
+            },
+        }),
+    );
+    add(
+        "UserOperation",
+        json!({
+            "description": "v0.6 or v0.7 UserOperation, by the EntryPoint it is sent to",
+            "oneOf": [schema_ref("UserOperationV06"), schema_ref("UserOperationV07")],
+        }),
+    );
+
+    add(
+        "SponsorOptions",
+        json!({
+            "type": ["object", "null"],
+            "properties": {
+                "returnFullOperation": {
+                    "type": "boolean",
+                    "description": "Return the sponsored UserOperation as userOperation",
+                },
+                "idempotencyKey": {
+                    "type": "string",
+                    "minLength": 1,
+                    "description": "Replays the first response of the same key and operation",
+                },
+                "validitySeconds": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "Requested validity, clamped to the configured range",
+                },
+                "attest": {
+                    "type": "boolean",
+                    "description": "Sign the response with the attestation key",
+                },
+                "debugTimings": {
+                    "type": "boolean",
+                    "description": "Report per-stage timings; API keys with the debug scope only",
+                },
+            },
+        }),
+    );
+    add(
+        "BatchSponsorOptions",
+        json!({
+            "allOf": [
+                schema_ref("SponsorOptions"),
+                {
+                    "type": ["object", "null"],
+                    "properties": {
+                        "mode": {
+                            "enum": ["atomic", "bestEffort"],
+                            "description": "Reject the batch at a failing operation, or sign the others",
+                        },
+                    },
+                },
+            ],
+        }),
+    );
+    add(
+        "SponsorshipBatchEntry",
+        json!({
+            "type": "object",
+            "required": ["index"],
+            "properties": {
+                "index": { "type": "integer", "description": "Position of the operation in the batch" },
+                "result": schema_ref("SponsorshipResult"),
+                "error": {
+                    "type": "object",
+                    "description": "JSON-RPC error of an operation that was not sponsored",
+                },
+            },
+        }),
+    );
+    add(
+        "SponsorshipResult",
+        json!({
+            "type": "object",
+            "required": ["paymasterAndData", "validUntil", "validAfter"],
+            "properties": {
+                "paymasterAndData": bytes,
+                "validUntil": { "type": "integer", "description": "Unix time signed into the paymaster data" },
+                "validAfter": { "type": "integer", "description": "Unix time signed into the paymaster data" },
+                "paymasterVerificationGasLimit": quantity,
+                "paymasterPostOpGasLimit": quantity,
+                "preVerificationGas": quantity,
+                "verificationGasLimit": quantity,
+                "callGasLimit": quantity,
+                "maxFeePerGas": quantity,
+                "maxPriorityFeePerGas": quantity,
+                "userOperation": schema_ref("UserOperation"),
+                "tokenQuote": {
+                    "type": "object",
+                    "description": "Token rate and maximum charge, for ERC-20 sponsorships",
+                },
+                "cached": {
+                    "type": "boolean",
+                    "description": "Same signature as an identical request moments ago",
+                },
+                "timings": {
+                    "type": "object",
+                    "description": "Milliseconds per executed stage and totalMs, with debugTimings",
+                    "properties": {
+                        "stages": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "stage": { "type": "string" },
+                                    "ms": { "type": "number" },
+                                },
+                            },
+                        },
+                        "totalMs": { "type": "number" },
+                    },
+                },
+                "attestation": {
+                    "type": "object",
+                    "description": "Signature of the attestation key over the response",
+                },
+            },
+        }),
+    );
+    add(
+        "SponsorshipSimulation",
+        json!({
+            "type": "object",
+            "required": ["status"],
+            "properties": {
+                "status": { "type": "string" },
+                "policyId": { "type": "string" },
+                "reason": { "type": "string" },
+                "revertData": bytes,
+                "accountSigFailed": { "type": "boolean" },
+                "gas": { "type": "object" },
+            },
+        }),
+    );
+    add(
+        "GasEstimate",
+        json!({
+            "type": "object",
+            "required": ["preVerificationGas", "verificationGasLimit", "callGasLimit"],
+            "properties": {
+                "preVerificationGas": quantity,
+                "verificationGasLimit": quantity,
+                "callGasLimit": quantity,
+                "paymasterVerificationGasLimit": quantity,
+            },
+        }),
+    );
+    add(
+        "DepositTransaction",
+        json!({
+            "type": "object",
+            "description": "Transaction sent to the EntryPoint",
+            "required": ["txHash", "outboundTxId", "depositInfo"],
+            "properties": {
+                "txHash": schema_ref("Hash"),
+                "outboundTxId": { "type": "string" },
+                "depositInfo": { "type": "object" },
+            },
+        }),
+    );
+    add(
+        "PolicyShadowReport",
+        json!({
+            "type": "object",
+            "properties": {
+                "enabled": { "type": "boolean" },
+                "candidateFile": { "type": ["string", "null"] },
+                "since": { "type": ["integer", "null"] },
+                "evaluated": { "type": "integer" },
+                "agreed": { "type": "integer" },
+                "wouldReject": { "type": "integer" },
+                "wouldAllow": { "type": "integer" },
+                "differentRule": { "type": "integer" },
+                "rules": { "type": "object", "description": "Divergences by rule name" },
+                "sampleSenders": array_of(schema_ref("Hash")),
+            },
+        }),
+    );
+    add("AccessList", json!({ "enum": ["allowlist", "denylist"] }));
+    add(
+        "AccessListChange",
+        json!({
+            "type": "object",
+            "required": ["list", "address", "changed"],
+            "properties": {
+                "list": schema_ref("AccessList"),
+                "address": address,
+                "changed": { "type": "boolean" },
+            },
+        }),
+    );
+
+    let (name, error) = <JsonRpcError as ToSchema<'_>>::schema();
+    add(name, serde_json::to_value(error).unwrap_or_default());
+    schemas
+}
+
+/// JSON-RPC envelope of a call to `method`
+fn request_schema(method: &RpcMethod) -> Value {
+    let params: Vec<Value> = method.params.iter().map(|p| p.schema.clone()).collect();
+    let required = method.params.iter().filter(|p| p.required).count();
+    json!({
+        "type": "object",
+        "required": ["jsonrpc", "id", "method", "params"],
+        "properties": {
+            "jsonrpc": { "const": "2.0" },
+            "id": { "type": ["string", "integer", "null"] },
+            "method": { "const": method.name },
+            "params": {
+                "type": "array",
+                "prefixItems": params,
+                "minItems": required,
+                "maxItems": method.params.len(),
+            },
+        },
+    })
+}
+
+/// JSON-RPC envelope of the answer to `method`
+fn response_schema(method: &RpcMethod) -> Value {
+    json!({
+        "type": "object",
+        "required": ["jsonrpc", "id"],
+        "properties": {
+            "jsonrpc": { "const": "2.0" },
+            "id": { "type": ["string", "integer", "null"] },
+            "result": method.result,
+            "error": schema_ref("JsonRpcError"),
+        },
+    })
+}
+
+/// OpenAPI 3.1 document of the JSON-RPC surface
+///
+/// Every method is an operation on `/#<method>`: the fragment keeps the
+/// paths distinct while requests still go to `/`. Each operation carries
+/// `x-json-rpc-method`, its positional `x-json-rpc-params` and the
+/// `x-api-key-scope` it requires; the request body is the full JSON-RPC
+/// envelope with `params` as a tuple.
+pub fn rpc_schema() -> Value {
+    let methods = rpc_methods();
+
+    let mut paths = Map::new();
+    for method in &methods {
+        let params: Vec<Value> = method
+            .params
+            .iter()
+            .map(|param| {
+                json!({
+                    "name": param.name,
+                    "required": param.required,
+                    "schema": param.schema,
+                })
+            })
+            .collect();
+        paths.insert(
+            format!("/#{}", method.name),
+            json!({
+                "post": {
+                    "operationId": method.name,
+                    "summary": method.summary,
+                    "tags": [method.namespace()],
+                    "x-json-rpc-method": method.name,
+                    "x-json-rpc-params": params,
+                    "x-api-key-scope": method.scope(),
+                    "x-read-only": method.spec().is_some_and(|spec| spec.read_only),
+                    "x-cacheable": method.spec().is_some_and(|spec| spec.cacheable),
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": { "schema": request_schema(method) },
+                        },
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "JSON-RPC response carrying result or error",
+                            "content": {
+                                "application/json": { "schema": response_schema(method) },
+                            },
+                        },
+                    },
+                },
+            }),
+        );
+    }
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "SuperRelay JSON-RPC API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "JSON-RPC 2.0 methods answered by POST / on the gateway. \
+                Operations are keyed by method; x-json-rpc-params lists the positional params.",
+        },
+        "x-json-rpc": { "version": "2.0", "endpoint": "/" },
+        "tags": [
+            { "name": "pm", "description": "Paymaster sponsorship and administration" },
+            { "name": "eth", "description": "ERC-4337 bundler methods" },
+            { "name": "rundler", "description": "Bundle tracking" },
+            { "name": "debug_bundler", "description": "Bundler spec test methods, with debug_api enabled" },
+            { "name": "superRelay", "description": "SuperRelay extensions" },
+            { "name": "admin", "description": "Gateway administration" },
+        ],
+        "paths": paths,
+        "components": { "schemas": component_schemas() },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, net::SocketAddr};
+
+    use serde_json::Value;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::{GatewayConfig, PaymasterGateway};
+
+    #[test]
+    fn test_every_registered_method_is_documented() {
+        let registered: BTreeSet<&str> = method_registry::METHODS
+            .iter()
+            .map(|spec| spec.name)
+            .collect();
+        let documented: BTreeSet<&str> = rpc_methods().iter().map(|m| m.name).collect();
+
+        assert!(registered.contains("pm_sponsorUserOperation"));
+        assert!(registered.contains("admin_removeAccessListEntry"));
+        let undocumented: Vec<_> = registered.difference(&documented).collect();
+        assert!(
+            undocumented.is_empty(),
+            "registered without schema metadata in rpc_methods(): {:?}",
+            undocumented
+        );
+        let unregistered: Vec<_> = documented.difference(&registered).collect();
+        assert!(
+            unregistered.is_empty(),
+            "documented but not registered: {:?}",
+            unregistered
+        );
+    }
+
+    #[test]
+    fn test_optional_params_come_last() {
+        for method in rpc_methods() {
+            let first_optional = method.params.iter().position(|p| !p.required);
+            if let Some(first_optional) = first_optional {
+                assert!(
+                    method.params[first_optional..].iter().all(|p| !p.required),
+                    "{} has a required param after an optional one",
+                    method.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_references_resolve() {
+        let schema = rpc_schema();
+        let components = schema["components"]["schemas"].as_object().unwrap();
+        let text = schema.to_string();
+        for reference in text.split("\"#/components/schemas/").skip(1) {
+            let name = &reference[..reference.find('"').unwrap()];
+            assert!(components.contains_key(name), "unresolved $ref {}", name);
+        }
+    }
+
+    /// Reviewed signatures; a change here is a change of the public RPC surface
+    const SNAPSHOT: &str = include_str!("../tests/snapshots/rpc_methods.txt");
+
+    /// Short name of a schema: the component referenced, or its type
+    fn type_name(schema: &Value) -> String {
+        let join = |schemas: &Vec<Value>| {
+            schemas
+                .iter()
+                .map(type_name)
+                .collect::<Vec<_>>()
+                .join(" | ")
+        };
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            return reference.rsplit('/').next().unwrap().to_string();
+        }
+        if let Some(one_of) = schema.get("oneOf").and_then(Value::as_array) {
+            return join(one_of);
+        }
+        if let Some(constant) = schema.get("const") {
+            return constant.to_string();
+        }
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            return values
+                .iter()
+                .map(Value::to_string)
+                .collect::<Vec<_>>()
+                .join(" | ");
+        }
+        match schema.get("type") {
+            Some(Value::String(ty)) if ty == "array" => {
+                format!("{}[]", type_name(&schema["items"]))
+            }
+            Some(Value::String(ty)) => ty.clone(),
+            Some(Value::Array(types)) => types
+                .iter()
+                .map(|ty| ty.as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+                .join(" | "),
+            _ => "any".to_string(),
+        }
+    }
+
+    /// `method(param: Type, optional?: Type) -> Result [scope]` of every documented operation
+    fn signatures(schema: &Value) -> String {
+        let mut lines: Vec<String> = schema["paths"]
+            .as_object()
+            .unwrap()
+            .values()
+            .map(|path| {
+                let operation = &path["post"];
+                let params: Vec<String> = operation["x-json-rpc-params"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|param| {
+                        format!(
+                            "{}{}: {}",
+                            param["name"].as_str().unwrap(),
+                            if param["required"] == true { "" } else { "?" },
+                            type_name(&param["schema"])
+                        )
+                    })
+                    .collect();
+                let result = &operation["responses"]["200"]["content"]["application/json"]
+                    ["schema"]["properties"]["result"];
+                format!(
+                    "{}({}) -> {} [{}]",
+                    operation["x-json-rpc-method"].as_str().unwrap(),
+                    params.join(", "),
+                    type_name(result),
+                    operation["x-api-key-scope"].as_str().unwrap()
+                )
+            })
+            .collect();
+        lines.sort();
+        lines.join("\n") + "\n"
+    }
+
+    #[test]
+    fn test_method_signatures_match_snapshot() {
+        let rendered = signatures(&rpc_schema());
+        assert_eq!(
+            rendered, SNAPSHOT,
+            "the JSON-RPC surface changed; if intended, update tests/snapshots/rpc_methods.txt to:\n{}",
+            rendered
+        );
+    }
+
+    #[test]
+    fn test_document_is_openapi_with_json_rpc_extensions() {
+        let schema = rpc_schema();
+        assert_eq!(schema["openapi"], "3.1.0");
+        assert_eq!(schema["x-json-rpc"]["version"], "2.0");
+        assert_eq!(
+            schema["paths"].as_object().unwrap().len(),
+            rpc_methods().len()
+        );
+
+        let sponsor = &schema["paths"]["/#pm_sponsorUserOperation"]["post"];
+        assert_eq!(sponsor["operationId"], "pm_sponsorUserOperation");
+        assert_eq!(sponsor["tags"][0], "pm");
+        let params = &sponsor["requestBody"]["content"]["application/json"]["schema"]["properties"]
+            ["params"];
+        assert_eq!(params["minItems"], 2);
+        assert_eq!(params["maxItems"], 3);
+        assert_eq!(
+            params["prefixItems"][0]["$ref"],
+            "#/components/schemas/UserOperation"
+        );
+
+        // Component schemas reused from the REST documentation types
+        let components = &schema["components"]["schemas"];
+        assert!(components["JsonRpcError"]["properties"]["code"].is_object());
+        assert!(components["UserOperationV06"]["properties"]["callData"].is_object());
+    }
+
+    async fn serve() -> SocketAddr {
+        let app = PaymasterGateway::new(GatewayConfig::default(), None)
+            .app()
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    /// Body of the response to GET `path`
+    async fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            path
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200"), "{}", head);
+        body.to_string()
+    }
+
+    #[tokio::test]
+    async fn test_served_at_rpc_schema_and_in_swagger_ui() {
+        let addr = serve().await;
+
+        let served: Value = serde_json::from_str(&get(addr, "/rpc-schema").await).unwrap();
+        assert_eq!(served, rpc_schema());
+
+        let initializer = get(addr, "/swagger-ui/swagger-initializer.js").await;
+        assert!(initializer.contains("/rpc-schema"), "{}", initializer);
+        assert!(
+            initializer.contains("/api-docs/openapi.json"),
+            "{}",
+            initializer
+        );
+    }
+}
@@ -1,12 +1,15 @@
-use std::collections::{HashMap, HashSet};
+use std::{collections::HashMap, sync::Arc};
 
-use alloy_primitives::Address;
+use alloy_primitives::{Address, Selector};
 use num_traits::ToPrimitive;
 use rundler_types::{UserOperation, UserOperationVariant};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, warn};
 
-use crate::error::GatewayResult;
+use crate::{
+    error::GatewayResult,
+    threat_feed::{ThreatFeedConfig, ThreatIntelStore, ThreatIntelligence},
+};
 
 /// Security check result for UserOperation
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,6 +74,9 @@ pub struct SecurityMetadata {
     pub contract_risk_score: Option<u8>,
     /// Transaction pattern analysis
     pub pattern_analysis: Option<Vec<String>>,
+    /// Age of the threat feed data used, `None` without feeds or before they loaded
+    #[serde(default)]
+    pub threat_intel_age_seconds: Option<u64>,
 }
 
 /// Security configuration
@@ -98,6 +104,8 @@ pub struct SecurityConfig {
     pub max_init_code_size: usize,
     /// Minimum reputation score required
     pub min_contract_reputation: u8,
+    /// Threat intelligence feeds and their refresh schedule
+    pub threat_feeds: ThreatFeedConfig,
 }
 
 impl Default for SecurityConfig {
@@ -114,6 +122,7 @@ impl Default for SecurityConfig {
             max_calldata_size: 10_000,             // 10KB
             max_init_code_size: 50_000,            // 50KB
             min_contract_reputation: 50,
+            threat_feeds: ThreatFeedConfig::default(),
         }
     }
 }
@@ -122,12 +131,10 @@ impl Default for SecurityConfig {
 pub struct SecurityChecker {
     /// Security configuration
     config: SecurityConfig,
-    /// Known malicious addresses
-    malicious_addresses: HashSet<Address>,
-    /// Known phishing patterns
-    phishing_patterns: Vec<String>,
-    /// Contract reputation scores
-    contract_reputation: HashMap<Address, u8>,
+    /// Malicious addresses, selectors, phishing patterns and contract reputation
+    intel: Arc<ThreatIntelligence>,
+    /// Feed-backed threat intelligence, replacing the built-in indicators
+    threat_intel: Option<Arc<ThreatIntelStore>>,
     /// Suspicious transaction patterns
     suspicious_patterns: Vec<TransactionPattern>,
 }
@@ -150,9 +157,8 @@ impl SecurityChecker {
     pub fn new() -> Self {
         Self {
             config: SecurityConfig::default(),
-            malicious_addresses: HashSet::new(),
-            phishing_patterns: Vec::new(),
-            contract_reputation: HashMap::new(),
+            intel: Arc::default(),
+            threat_intel: None,
             suspicious_patterns: Self::default_suspicious_patterns(),
         }
     }
@@ -161,41 +167,33 @@ impl SecurityChecker {
     pub fn with_config(config: SecurityConfig) -> Self {
         Self {
             config,
-            malicious_addresses: HashSet::new(),
-            phishing_patterns: Vec::new(),
-            contract_reputation: HashMap::new(),
+            intel: Arc::default(),
+            threat_intel: None,
             suspicious_patterns: Self::default_suspicious_patterns(),
         }
     }
 
+    /// Use the threat intelligence of `store` instead of the built-in indicators
+    pub fn with_threat_intel(mut self, store: Arc<ThreatIntelStore>) -> Self {
+        self.threat_intel = Some(store);
+        self
+    }
+
     /// Load security configuration and threat intelligence
+    ///
+    /// Takes a snapshot of the feed store when one is set, so a refresh running
+    /// concurrently never affects this checker.
     pub async fn load_threat_intelligence(&mut self) -> GatewayResult<()> {
-        // TODO: In production, load from threat intelligence feeds
-        // For now, add some default threat indicators
-
-        // Add known malicious addresses (example addresses)
-        self.add_malicious_address(
-            "0x0000000000000000000000000000000000000bad"
-                .parse()
-                .unwrap(),
-        );
-        self.add_malicious_address(
-            "0x000000000000000000000000000000000000dead"
-                .parse()
-                .unwrap(),
-        );
-
-        // Add phishing patterns
-        self.add_phishing_pattern("transfer.*0x00000000".to_string());
-        self.add_phishing_pattern("approve.*999999999".to_string());
+        let loaded = match &self.threat_intel {
+            Some(store) => store.snapshot(),
+            None => Arc::new(ThreatIntelligence::builtin()),
+        };
 
-        // Set contract reputation scores
-        self.set_contract_reputation(
-            "0xA0b86a33E6441d6e42D2B6b63B3b66F2c7A77fD8"
-                .parse()
-                .unwrap(),
-            95, // High reputation contract
-        );
+        if self.intel.is_empty() {
+            self.intel = loaded;
+        } else {
+            Arc::make_mut(&mut self.intel).merge(&loaded);
+        }
 
         debug!("✅ Security threat intelligence loaded successfully");
         Ok(())
@@ -220,6 +218,8 @@ impl SecurityChecker {
         let sender = match user_op {
             UserOperationVariant::V0_6(op) => op.sender(),
             UserOperationVariant::V0_7(op) => op.sender(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.sender(),
         };
 
         debug!(
@@ -351,6 +351,11 @@ impl SecurityChecker {
             phishing_risk_level: Some(self.assess_phishing_risk_level(user_op)),
             contract_risk_score: Some(self.calculate_contract_risk_score(&sender)),
             pattern_analysis: Some(self.get_detected_patterns(user_op)),
+            threat_intel_age_seconds: self
+                .threat_intel
+                .as_ref()
+                .and_then(|store| store.age())
+                .map(|age| age.as_secs()),
         };
 
         debug!("Security analysis completed: {}", summary);
@@ -371,10 +376,12 @@ impl SecurityChecker {
         let sender = match user_op {
             UserOperationVariant::V0_6(op) => op.sender(),
             UserOperationVariant::V0_7(op) => op.sender(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.sender(),
         };
 
         // Check sender
-        if self.malicious_addresses.contains(&sender) {
+        if self.intel.malicious_addresses.contains(&sender) {
             return SecurityCheck {
                 check_name: "malicious_address".to_string(),
                 passed: false,
@@ -390,7 +397,7 @@ impl SecurityChecker {
         // Check paymaster (if present)
         let paymaster_address = self.extract_paymaster_address(user_op);
         if let Some(paymaster) = paymaster_address {
-            if self.malicious_addresses.contains(&paymaster) {
+            if self.intel.malicious_addresses.contains(&paymaster) {
                 return SecurityCheck {
                     check_name: "malicious_address".to_string(),
                     passed: false,
@@ -426,6 +433,11 @@ impl SecurityChecker {
                 op.call_gas_limit().to_u128().unwrap_or(u128::MAX),
                 op.verification_gas_limit().to_u128().unwrap_or(u128::MAX),
             ),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => (
+                op.call_gas_limit().to_u128().unwrap_or(u128::MAX),
+                op.verification_gas_limit().to_u128().unwrap_or(u128::MAX),
+            ),
         };
 
         // Check call gas limit
@@ -484,6 +496,8 @@ impl SecurityChecker {
         let call_data = match user_op {
             UserOperationVariant::V0_6(op) => op.call_data(),
             UserOperationVariant::V0_7(op) => op.call_data(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.call_data(),
         };
 
         // Check calldata size
@@ -505,9 +519,26 @@ impl SecurityChecker {
             };
         }
 
+        // Check for blacklisted function selectors
+        if call_data.len() >= 4 {
+            let selector = Selector::from_slice(&call_data[..4]);
+            if self.intel.blocked_selectors.contains(&selector) {
+                return SecurityCheck {
+                    check_name: "calldata_security".to_string(),
+                    passed: false,
+                    message: format!("Calldata calls blacklisted selector {}", selector),
+                    risk_level: SecurityRiskLevel::Critical,
+                    context: Some(serde_json::json!({
+                        "selector": selector.to_string(),
+                        "risk_type": "blacklisted_selector"
+                    })),
+                };
+            }
+        }
+
         // Check for suspicious patterns in calldata
         let calldata_hex = hex::encode(call_data);
-        for pattern in &self.phishing_patterns {
+        for pattern in &self.intel.phishing_patterns {
             if calldata_hex.contains(&pattern.replace(".*", "")) {
                 return SecurityCheck {
                     check_name: "calldata_security".to_string(),
@@ -542,6 +573,7 @@ impl SecurityChecker {
         // For now, use local reputation scores
 
         let reputation_score = self
+            .intel
             .contract_reputation
             .get(contract_address)
             .copied()
@@ -618,6 +650,8 @@ impl SecurityChecker {
         let call_data = match user_op {
             UserOperationVariant::V0_6(op) => op.call_data(),
             UserOperationVariant::V0_7(op) => op.call_data(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.call_data(),
         };
 
         let calldata_hex = hex::encode(call_data);
@@ -665,6 +699,11 @@ impl SecurityChecker {
                 op.max_fee_per_gas().to_u64().unwrap_or(0),
                 op.max_priority_fee_per_gas().to_u64().unwrap_or(0),
             ),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => (
+                op.max_fee_per_gas().to_u64().unwrap_or(0),
+                op.max_priority_fee_per_gas().to_u64().unwrap_or(0),
+            ),
         };
 
         // Check for unusually high priority fees (potential MEV extraction)
@@ -719,6 +758,21 @@ impl SecurityChecker {
                     };
                 }
             }
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => {
+                // v0.8 keeps the v0.7 factory + factory_data
+                if op.factory().is_some() {
+                    op.factory_data().clone()
+                } else {
+                    return SecurityCheck {
+                        check_name: "init_code_security".to_string(),
+                        passed: true,
+                        message: "No init code (account already deployed)".to_string(),
+                        risk_level: SecurityRiskLevel::Info,
+                        context: None,
+                    };
+                }
+            }
         };
 
         // Check init code size
@@ -753,7 +807,9 @@ impl SecurityChecker {
 
     /// Add malicious address to blacklist
     pub fn add_malicious_address(&mut self, address: Address) {
-        self.malicious_addresses.insert(address);
+        Arc::make_mut(&mut self.intel)
+            .malicious_addresses
+            .insert(address);
         warn!(
             "Added malicious address {:?} to security blacklist",
             address
@@ -762,13 +818,17 @@ impl SecurityChecker {
 
     /// Add phishing pattern
     pub fn add_phishing_pattern(&mut self, pattern: String) {
-        self.phishing_patterns.push(pattern.clone());
+        Arc::make_mut(&mut self.intel)
+            .phishing_patterns
+            .push(pattern.clone());
         debug!("Added phishing pattern: {}", pattern);
     }
 
     /// Set contract reputation score
     pub fn set_contract_reputation(&mut self, contract: Address, score: u8) {
-        self.contract_reputation.insert(contract, score);
+        Arc::make_mut(&mut self.intel)
+            .contract_reputation
+            .insert(contract, score);
         debug!("Set reputation score {} for contract {:?}", score, contract);
     }
 
@@ -786,6 +846,8 @@ impl SecurityChecker {
                 }
             }
             UserOperationVariant::V0_7(op) => op.paymaster(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.paymaster(),
         }
     }
 
@@ -805,6 +867,7 @@ impl SecurityChecker {
     /// Calculate contract risk score
     fn calculate_contract_risk_score(&self, contract: &Address) -> u8 {
         100 - self
+            .intel
             .contract_reputation
             .get(contract)
             .copied()
@@ -914,7 +977,7 @@ mod tests {
             .unwrap();
 
         checker.add_malicious_address(malicious_addr);
-        assert!(checker.malicious_addresses.contains(&malicious_addr));
+        assert!(checker.intel.malicious_addresses.contains(&malicious_addr));
     }
 
     #[test]
@@ -923,7 +986,7 @@ mod tests {
         let pattern = "transfer.*0x00000000".to_string();
 
         checker.add_phishing_pattern(pattern.clone());
-        assert!(checker.phishing_patterns.contains(&pattern));
+        assert!(checker.intel.phishing_patterns.contains(&pattern));
     }
 
     #[test]
@@ -934,7 +997,7 @@ mod tests {
             .unwrap();
 
         checker.set_contract_reputation(contract, 95);
-        assert_eq!(checker.contract_reputation.get(&contract), Some(&95));
+        assert_eq!(checker.intel.contract_reputation.get(&contract), Some(&95));
         assert_eq!(checker.calculate_contract_risk_score(&contract), 5);
     }
 
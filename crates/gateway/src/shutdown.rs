@@ -0,0 +1,254 @@
+use std::{future::IntoFuture, sync::Arc, time::Duration};
+
+use axum::Router;
+use tokio::{net::TcpListener, sync::watch, task::JoinHandle};
+use tracing::{info, warn};
+
+use crate::{
+    connection_limit::ConnectionLimiter,
+    error::{GatewayError, GatewayResult},
+};
+
+/// Coordinates graceful shutdown across server tasks
+///
+/// Once triggered the controller stays in the draining state; every clone
+/// observes the same signal.
+#[derive(Debug, Clone)]
+pub struct ShutdownController {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Default for ShutdownController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShutdownController {
+    /// Create a controller that has not been triggered
+    pub fn new() -> Self {
+        let (sender, _) = watch::channel(false);
+        Self {
+            sender: Arc::new(sender),
+        }
+    }
+
+    /// Begin draining; idempotent
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    /// Whether shutdown has been triggered
+    pub fn is_draining(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Wait until shutdown is triggered
+    pub async fn wait(&self) {
+        let mut receiver = self.sender.subscribe();
+        // The sender lives as long as self, so this only returns once triggered
+        let _ = receiver.wait_for(|draining| *draining).await;
+    }
+
+    /// Spawn a task that triggers shutdown on SIGTERM or SIGINT
+    pub fn listen_for_signals(&self) -> JoinHandle<()> {
+        let controller = self.clone();
+        tokio::spawn(async move {
+            wait_for_signal().await;
+            info!("🛑 Shutdown signal received, draining in-flight requests");
+            controller.trigger();
+        })
+    }
+}
+
+/// Wait for SIGTERM or SIGINT (Ctrl+C)
+pub async fn wait_for_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Serve `app` until shutdown is triggered, then stop accepting connections
+/// and let in-flight requests finish for up to `drain_timeout`
+pub async fn serve_with_graceful_shutdown(
+    listener: TcpListener,
+    app: Router,
+    shutdown: ShutdownController,
+    drain_timeout: Duration,
+) -> GatewayResult<()> {
+    serve_with_connection_limits(
+        listener,
+        app,
+        Arc::new(ConnectionLimiter::unlimited()),
+        shutdown,
+        drain_timeout,
+    )
+    .await
+}
+
+/// [`serve_with_graceful_shutdown`], admitting connections through `limiter`
+pub async fn serve_with_connection_limits(
+    listener: TcpListener,
+    app: Router,
+    limiter: Arc<ConnectionLimiter>,
+    shutdown: ShutdownController,
+    drain_timeout: Duration,
+) -> GatewayResult<()> {
+    let signal = shutdown.clone();
+    let server = axum::serve(listener, limiter.make_service(app))
+        .with_graceful_shutdown(async move { signal.wait().await })
+        .into_future();
+
+    let drain_deadline = async {
+        shutdown.wait().await;
+        info!(
+            "⏳ Gateway draining, waiting up to {:?} for in-flight requests",
+            drain_timeout
+        );
+        tokio::time::sleep(drain_timeout).await;
+    };
+
+    tokio::select! {
+        result = server => {
+            result.map_err(|e| GatewayError::ServerError(format!("Server error: {}", e)))?;
+            info!("✅ Gateway drained and stopped");
+        }
+        _ = drain_deadline => {
+            warn!(
+                "⚠️ Drain timeout of {:?} elapsed, no longer waiting for open connections",
+                drain_timeout
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::{routing::get, Router};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+
+    /// Handler that takes long enough for shutdown to start while it runs
+    async fn slow_handler() -> &'static str {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        "slow-done"
+    }
+
+    async fn spawn_server(
+        drain_timeout: Duration,
+    ) -> (
+        std::net::SocketAddr,
+        ShutdownController,
+        tokio::task::JoinHandle<crate::GatewayResult<()>>,
+    ) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/slow", get(slow_handler));
+
+        let shutdown = ShutdownController::new();
+        let server = tokio::spawn(serve_with_graceful_shutdown(
+            listener,
+            app,
+            shutdown.clone(),
+            drain_timeout,
+        ));
+
+        (addr, shutdown, server)
+    }
+
+    /// Send a GET request and return the raw HTTP response
+    async fn get_slow(addr: std::net::SocketAddr) -> std::io::Result<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+        Ok(response)
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_request_completes_during_drain() {
+        let (addr, shutdown, server) = spawn_server(Duration::from_secs(5)).await;
+
+        let request = tokio::spawn(get_slow(addr));
+
+        // Start draining while the slow handler is still running
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        shutdown.trigger();
+        assert!(shutdown.is_draining());
+
+        let response = request.await.unwrap().unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("slow-done"));
+
+        tokio::time::timeout(Duration::from_secs(2), server)
+            .await
+            .expect("server should stop after draining")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_new_connections_refused_after_drain() {
+        let (addr, shutdown, server) = spawn_server(Duration::from_secs(5)).await;
+
+        shutdown.trigger();
+        tokio::time::timeout(Duration::from_secs(2), server)
+            .await
+            .expect("idle server should stop immediately")
+            .unwrap()
+            .unwrap();
+
+        assert!(get_slow(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_drain_timeout_bounds_shutdown() {
+        let (addr, shutdown, server) = spawn_server(Duration::from_millis(50)).await;
+
+        let _request = tokio::spawn(get_slow(addr));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        shutdown.trigger();
+
+        // The server stops waiting for the slow request once the drain timeout elapses
+        tokio::time::timeout(Duration::from_millis(200), server)
+            .await
+            .expect("drain timeout should stop the server")
+            .unwrap()
+            .unwrap();
+    }
+}
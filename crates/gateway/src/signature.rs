@@ -0,0 +1,789 @@
+use std::sync::Arc;
+
+use alloy_primitives::{uint, Address, Bytes, B256, U256};
+use alloy_sol_types::{SolCall, SolValue};
+use async_trait::async_trait;
+use ethers::types::Signature as EcdsaSignature;
+use rundler_provider::{EvmProvider, TransactionBuilder, TransactionRequest};
+use rundler_types::{UserOperation, UserOperationVariant};
+use serde::Serialize;
+use tracing::debug;
+
+use crate::error::{GatewayError, GatewayResult};
+
+#[allow(unreachable_pub)]
+mod abi {
+    alloy_sol_types::sol! {
+        /// WebAuthn assertion as encoded by Solady / Coinbase Smart Wallet `WebAuthn.WebAuthnAuth`
+        struct WebAuthnAuth {
+            bytes authenticatorData;
+            string clientDataJSON;
+            uint256 challengeIndex;
+            uint256 typeIndex;
+            uint256 r;
+            uint256 s;
+        }
+
+        /// Coinbase Smart Wallet owner-indexed signature envelope
+        struct SignatureWrapper {
+            uint256 ownerIndex;
+            bytes signatureData;
+        }
+
+        function isValidSignature(bytes32 hash, bytes signature) external view returns (bytes4);
+    }
+}
+
+use abi::{isValidSignatureCall, SignatureWrapper, WebAuthnAuth};
+
+/// ERC-1271 `isValidSignature` success value
+pub const ERC1271_MAGIC_VALUE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+
+/// Half the secp256k1 group order; larger `s` values are malleable
+const SECP256K1_N_DIV_2: U256 =
+    uint!(0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF5D576E7357A4501DDFE92F46681B20A0_U256);
+
+/// Half the P-256 group order; passkey accounts reject larger `s` values
+const P256_N_DIV_2: U256 =
+    uint!(0x7FFFFFFF800000007FFFFFFFFFFFFFFFDE737D56D38BCF4279DCE5617E3192A8_U256);
+
+/// Authenticator data flag: user present
+const AUTH_DATA_FLAG_UP: u8 = 0x01;
+/// Authenticator data flag: user verified
+const AUTH_DATA_FLAG_UV: u8 = 0x04;
+/// rpIdHash (32 bytes), flags (1 byte) and signCount (4 bytes)
+const MIN_AUTH_DATA_LEN: usize = 37;
+
+/// Detected UserOperation signature format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureFormat {
+    /// No signature
+    Empty,
+    /// 65-byte secp256k1 `r || s || v`
+    Ecdsa,
+    /// Contract signature accepted or rejected by the account's `isValidSignature`
+    Erc1271,
+    /// ABI-encoded WebAuthn assertion of a passkey account
+    WebAuthn,
+    /// Not recognized
+    Unknown,
+}
+
+/// Parsed parts of a signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SignatureComponents {
+    /// secp256k1 signature
+    #[serde(rename_all = "camelCase")]
+    Ecdsa {
+        /// `r` value
+        r: B256,
+        /// `s` value
+        s: B256,
+        /// Recovery id as encoded (0, 1, 27 or 28)
+        v: u8,
+    },
+    /// WebAuthn assertion
+    #[serde(rename_all = "camelCase")]
+    WebAuthn {
+        /// Owner slot of wrapped signatures
+        owner_index: Option<U256>,
+        /// Raw authenticator data
+        authenticator_data: Bytes,
+        /// Client data JSON signed by the authenticator
+        client_data_json: String,
+        /// Challenge found in the client data
+        challenge: String,
+        /// User verification flag of the authenticator data
+        user_verified: bool,
+        /// P-256 `r` value
+        r: U256,
+        /// P-256 `s` value
+        s: U256,
+    },
+    /// Opaque contract signature
+    #[serde(rename_all = "camelCase")]
+    Contract {
+        /// Signature length in bytes
+        length: usize,
+    },
+}
+
+/// Outcome of validating a UserOperation signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureValidationResult {
+    /// Detected format
+    pub format: SignatureFormat,
+    /// Parsed signature, when the format could be parsed
+    pub components: Option<SignatureComponents>,
+    /// Whether the signature is accepted
+    pub is_valid: bool,
+    /// Why the signature was accepted or rejected
+    pub reason: String,
+    /// Signer recovered from an ECDSA signature
+    pub recovered_signer: Option<Address>,
+    /// Accepted only because validation ran in lenient mode
+    pub lenient: bool,
+}
+
+impl SignatureValidationResult {
+    fn new(format: SignatureFormat, is_valid: bool, reason: impl Into<String>) -> Self {
+        Self {
+            format,
+            components: None,
+            is_valid,
+            reason: reason.into(),
+            recovered_signer: None,
+            lenient: false,
+        }
+    }
+
+    fn with_components(mut self, components: SignatureComponents) -> Self {
+        self.components = Some(components);
+        self
+    }
+}
+
+/// On-chain access needed to validate contract signatures
+#[async_trait]
+pub trait ContractSignatureReader: Send + Sync {
+    /// Whether `account` has deployed code
+    async fn has_code(&self, account: Address) -> GatewayResult<bool>;
+
+    /// ERC-1271 `isValidSignature(hash, signature)` on `account`; an error
+    /// means the call reverted or could not be made
+    async fn is_valid_signature(
+        &self,
+        account: Address,
+        hash: B256,
+        signature: &Bytes,
+    ) -> GatewayResult<bool>;
+}
+
+/// [`ContractSignatureReader`] calling accounts through a node provider
+pub struct EvmContractSignatureReader<P> {
+    provider: P,
+}
+
+impl<P: EvmProvider> EvmContractSignatureReader<P> {
+    /// Create a reader using `provider`
+    pub fn new(provider: P) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> ContractSignatureReader for EvmContractSignatureReader<P> {
+    async fn has_code(&self, account: Address) -> GatewayResult<bool> {
+        let code = self.provider.get_code(account, None).await.map_err(|e| {
+            GatewayError::RundlerError(format!("Failed to get code of {}: {}", account, e))
+        })?;
+        Ok(!code.is_empty())
+    }
+
+    async fn is_valid_signature(
+        &self,
+        account: Address,
+        hash: B256,
+        signature: &Bytes,
+    ) -> GatewayResult<bool> {
+        let call = isValidSignatureCall {
+            hash,
+            signature: signature.clone(),
+        };
+        let tx = TransactionRequest::default()
+            .to(account)
+            .with_input(Bytes::from(call.abi_encode()));
+        let result = self.provider.call(tx, None, None).await.map_err(|e| {
+            GatewayError::RundlerError(format!("isValidSignature on {} failed: {}", account, e))
+        })?;
+        Ok(result.get(..4) == Some(&ERC1271_MAGIC_VALUE[..]))
+    }
+}
+
+/// Classifies and validates UserOperation signatures
+///
+/// WebAuthn envelopes are recognized by their encoding, contract accounts are
+/// asked through ERC-1271 when a [`ContractSignatureReader`] is attached, and
+/// 65-byte signatures are checked as ECDSA. In lenient mode, used where dummy
+/// signatures are expected, failures are reported but accepted.
+#[derive(Clone, Default)]
+pub struct SignatureValidator {
+    contract_reader: Option<Arc<dyn ContractSignatureReader>>,
+}
+
+impl SignatureValidator {
+    /// Validator without on-chain access; ERC-1271 is not checked
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check contract accounts through `contract_reader`
+    pub fn with_contract_reader(
+        mut self,
+        contract_reader: Arc<dyn ContractSignatureReader>,
+    ) -> Self {
+        self.contract_reader = Some(contract_reader);
+        self
+    }
+
+    /// Validate the signature of `user_op` against its userOpHash
+    pub async fn validate(
+        &self,
+        user_op: &UserOperationVariant,
+        lenient: bool,
+    ) -> SignatureValidationResult {
+        self.validate_signature(
+            user_op.sender(),
+            user_op.hash(),
+            user_op.signature(),
+            lenient,
+        )
+        .await
+    }
+
+    /// Validate `signature` of `sender` over `user_op_hash`
+    pub async fn validate_signature(
+        &self,
+        sender: Address,
+        user_op_hash: B256,
+        signature: &Bytes,
+        lenient: bool,
+    ) -> SignatureValidationResult {
+        let mut result = self.classify(sender, user_op_hash, signature).await;
+        if !result.is_valid && lenient {
+            debug!(
+                "Accepting {:?} signature in lenient mode: {}",
+                result.format, result.reason
+            );
+            result.is_valid = true;
+            result.lenient = true;
+        }
+        result
+    }
+
+    async fn classify(
+        &self,
+        sender: Address,
+        user_op_hash: B256,
+        signature: &Bytes,
+    ) -> SignatureValidationResult {
+        if signature.is_empty() {
+            return SignatureValidationResult::new(
+                SignatureFormat::Empty,
+                false,
+                "Signature is empty",
+            );
+        }
+
+        if let Some((owner_index, auth)) = decode_webauthn(signature) {
+            return check_webauthn(owner_index, auth, user_op_hash);
+        }
+
+        let mut contract_failure = None;
+        if let Some(reader) = &self.contract_reader {
+            match reader.has_code(sender).await {
+                Ok(true) => match reader
+                    .is_valid_signature(sender, user_op_hash, signature)
+                    .await
+                {
+                    Ok(true) => {
+                        return SignatureValidationResult::new(
+                            SignatureFormat::Erc1271,
+                            true,
+                            format!(
+                                "isValidSignature on {} returned the ERC-1271 magic value",
+                                sender
+                            ),
+                        )
+                        .with_components(SignatureComponents::Contract {
+                            length: signature.len(),
+                        });
+                    }
+                    Ok(false) => {
+                        contract_failure = Some(format!(
+                            "isValidSignature on {} rejected the signature",
+                            sender
+                        ))
+                    }
+                    Err(e) => contract_failure = Some(e.to_string()),
+                },
+                Ok(false) => {}
+                Err(e) => contract_failure = Some(e.to_string()),
+            }
+        }
+
+        if signature.len() == 65 {
+            return check_ecdsa(signature, user_op_hash);
+        }
+
+        match contract_failure {
+            Some(reason) => SignatureValidationResult::new(SignatureFormat::Erc1271, false, reason)
+                .with_components(SignatureComponents::Contract {
+                    length: signature.len(),
+                }),
+            None => SignatureValidationResult::new(
+                SignatureFormat::Unknown,
+                false,
+                format!("Unrecognized {}-byte signature", signature.len()),
+            ),
+        }
+    }
+}
+
+/// WebAuthn assertion, optionally inside an owner-indexed wrapper
+fn decode_webauthn(signature: &[u8]) -> Option<(Option<U256>, WebAuthnAuth)> {
+    if let Ok(wrapper) = <SignatureWrapper as SolValue>::abi_decode(signature) {
+        if let Ok(auth) = <WebAuthnAuth as SolValue>::abi_decode(&wrapper.signatureData) {
+            return Some((Some(wrapper.ownerIndex), auth));
+        }
+    }
+    <WebAuthnAuth as SolValue>::abi_decode(signature)
+        .ok()
+        .map(|auth| (None, auth))
+}
+
+fn check_webauthn(
+    owner_index: Option<U256>,
+    auth: WebAuthnAuth,
+    user_op_hash: B256,
+) -> SignatureValidationResult {
+    let expected_challenge = webauthn_challenge(user_op_hash);
+    let client_data = auth.clientDataJSON.as_bytes();
+    let challenge = client_data_string(client_data, "challenge").unwrap_or_default();
+    let flags = auth
+        .authenticatorData
+        .get(MIN_AUTH_DATA_LEN - 5)
+        .copied()
+        .unwrap_or_default();
+
+    let components = SignatureComponents::WebAuthn {
+        owner_index,
+        authenticator_data: auth.authenticatorData.clone(),
+        client_data_json: auth.clientDataJSON.clone(),
+        challenge: challenge.clone(),
+        user_verified: flags & AUTH_DATA_FLAG_UV != 0,
+        r: auth.r,
+        s: auth.s,
+    };
+    let fail = |reason: String| {
+        SignatureValidationResult::new(SignatureFormat::WebAuthn, false, reason)
+            .with_components(components.clone())
+    };
+
+    if auth.authenticatorData.len() < MIN_AUTH_DATA_LEN {
+        return fail(format!(
+            "Authenticator data is {} bytes, expected at least {}",
+            auth.authenticatorData.len(),
+            MIN_AUTH_DATA_LEN
+        ));
+    }
+    if flags & AUTH_DATA_FLAG_UP == 0 {
+        return fail("Authenticator data does not set the user presence flag".to_string());
+    }
+    if !slice_at(client_data, auth.typeIndex, br#""type":"webauthn.get""#) {
+        return fail("typeIndex does not point at \"type\":\"webauthn.get\"".to_string());
+    }
+    let expected = format!(r#""challenge":"{}""#, expected_challenge);
+    if !slice_at(client_data, auth.challengeIndex, expected.as_bytes()) {
+        return fail(format!(
+            "Challenge {:?} does not match userOpHash {}",
+            challenge, user_op_hash
+        ));
+    }
+    if auth.r.is_zero() || auth.s.is_zero() {
+        return fail("P-256 signature has a zero r or s".to_string());
+    }
+    if auth.s > P256_N_DIV_2 {
+        return fail("P-256 signature s value is not normalized".to_string());
+    }
+
+    SignatureValidationResult::new(
+        SignatureFormat::WebAuthn,
+        true,
+        "WebAuthn assertion challenges the userOpHash; the P-256 signature is verified by the account",
+    )
+    .with_components(components)
+}
+
+/// `r || s || v` over the EIP-191 digest of the userOpHash, as signed by ECDSA-owned accounts
+fn check_ecdsa(signature: &[u8], user_op_hash: B256) -> SignatureValidationResult {
+    let r = B256::from_slice(&signature[..32]);
+    let s = B256::from_slice(&signature[32..64]);
+    let v = signature[64];
+    let components = SignatureComponents::Ecdsa { r, s, v };
+    let fail = |reason: String| {
+        SignatureValidationResult::new(SignatureFormat::Ecdsa, false, reason)
+            .with_components(components.clone())
+    };
+
+    if !matches!(v, 0 | 1 | 27 | 28) {
+        return fail(format!("Invalid recovery id {}", v));
+    }
+    let s_value = U256::from_be_bytes(s.0);
+    if r.is_zero() || s_value.is_zero() {
+        return fail("ECDSA signature has a zero r or s".to_string());
+    }
+    if s_value > SECP256K1_N_DIV_2 {
+        return fail("ECDSA signature s value is not normalized".to_string());
+    }
+
+    let digest = ethers::utils::hash_message(user_op_hash.as_slice());
+    let recovered = EcdsaSignature::try_from(signature).and_then(|sig| sig.recover(digest));
+    match recovered {
+        Ok(signer) => {
+            let signer = Address::from_slice(signer.as_bytes());
+            let mut result = SignatureValidationResult::new(
+                SignatureFormat::Ecdsa,
+                true,
+                format!("ECDSA signature recovers {} from the userOpHash", signer),
+            )
+            .with_components(components);
+            result.recovered_signer = Some(signer);
+            result
+        }
+        Err(e) => fail(format!("ECDSA recovery failed: {}", e)),
+    }
+}
+
+/// Challenge a passkey signs for `user_op_hash`: its unpadded base64url encoding
+pub fn webauthn_challenge(user_op_hash: B256) -> String {
+    base64_url_encode(user_op_hash.as_slice())
+}
+
+/// Whether `data` holds `expected` at `index`
+fn slice_at(data: &[u8], index: U256, expected: &[u8]) -> bool {
+    let Ok(start) = usize::try_from(index) else {
+        return false;
+    };
+    start
+        .checked_add(expected.len())
+        .and_then(|end| data.get(start..end))
+        == Some(expected)
+}
+
+/// String value of `"key":"..."` in client data JSON
+fn client_data_string(client_data: &[u8], key: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_slice(client_data).ok()?;
+    value.get(key)?.as_str().map(str::to_string)
+}
+
+/// Unpadded base64url
+fn base64_url_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, b)| acc | ((*b as u32) << (16 - 8 * i)));
+        for i in 0..=chunk.len() {
+            out.push(ALPHABET[((bits >> (18 - 6 * i)) & 0x3f) as usize] as char);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy_primitives::{address, Address, Bytes, B256, U256};
+    use alloy_sol_types::SolValue;
+    use async_trait::async_trait;
+    use ethers::signers::{LocalWallet, Signer};
+    use rundler_types::{chain::ChainSpec, v0_6, UserOperation, UserOperationVariant};
+
+    use super::*;
+
+    const SENDER: Address = address!("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    const OWNER_KEY: &str = "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+    fn user_op_hash() -> B256 {
+        B256::repeat_byte(0x42)
+    }
+
+    fn user_op(signature: Bytes) -> UserOperationVariant {
+        UserOperationVariant::V0_6(
+            v0_6::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_6::UserOperationRequiredFields {
+                    sender: SENDER,
+                    nonce: U256::from(1),
+                    init_code: Bytes::new(),
+                    call_data: Bytes::new(),
+                    call_gas_limit: 100_000,
+                    verification_gas_limit: 100_000,
+                    pre_verification_gas: 21_000,
+                    max_fee_per_gas: 1_000_000_000,
+                    max_priority_fee_per_gas: 1_000_000_000,
+                    paymaster_and_data: Bytes::new(),
+                    signature,
+                },
+            )
+            .build(),
+        )
+    }
+
+    /// Contract account whose isValidSignature accepts exactly one signature
+    struct TestAccount {
+        has_code: bool,
+        accepted: Option<Bytes>,
+    }
+
+    #[async_trait]
+    impl ContractSignatureReader for TestAccount {
+        async fn has_code(&self, _account: Address) -> GatewayResult<bool> {
+            Ok(self.has_code)
+        }
+
+        async fn is_valid_signature(
+            &self,
+            _account: Address,
+            _hash: B256,
+            signature: &Bytes,
+        ) -> GatewayResult<bool> {
+            match &self.accepted {
+                Some(accepted) => Ok(accepted == signature),
+                None => Err(GatewayError::RundlerError("execution reverted".to_string())),
+            }
+        }
+    }
+
+    fn contract_validator(accepted: Option<Bytes>) -> SignatureValidator {
+        SignatureValidator::new().with_contract_reader(Arc::new(TestAccount {
+            has_code: true,
+            accepted,
+        }))
+    }
+
+    async fn ecdsa_signature(hash: B256) -> (Bytes, Address) {
+        let wallet: LocalWallet = OWNER_KEY.parse().unwrap();
+        let signature = wallet.sign_message(hash.as_slice()).await.unwrap();
+        (
+            Bytes::from(signature.to_vec()),
+            Address::from_slice(wallet.address().as_bytes()),
+        )
+    }
+
+    fn auth_data(flags: u8) -> Bytes {
+        let mut data = vec![0xaa; 32];
+        data.push(flags);
+        data.extend_from_slice(&[0, 0, 0, 1]);
+        Bytes::from(data)
+    }
+
+    fn webauthn(challenge: &str, flags: u8, s: U256) -> WebAuthnAuth {
+        let client_data = format!(
+            r#"{{"type":"webauthn.get","challenge":"{}","origin":"https://wallet.example","crossOrigin":false}}"#,
+            challenge
+        );
+        let challenge_index = client_data.find(r#""challenge""#).unwrap();
+        WebAuthnAuth {
+            authenticatorData: auth_data(flags),
+            clientDataJSON: client_data,
+            challengeIndex: U256::from(challenge_index),
+            typeIndex: U256::from(1),
+            r: U256::from(1),
+            s,
+        }
+    }
+
+    async fn validate(
+        validator: &SignatureValidator,
+        signature: Bytes,
+    ) -> (bool, SignatureFormat, String) {
+        let result = validator
+            .validate_signature(SENDER, user_op_hash(), &signature, false)
+            .await;
+        (result.is_valid, result.format, result.reason)
+    }
+
+    #[tokio::test]
+    async fn test_ecdsa_signature_recovers_signer() {
+        let (signature, owner) = ecdsa_signature(user_op_hash()).await;
+
+        let result = SignatureValidator::new()
+            .validate_signature(SENDER, user_op_hash(), &signature, false)
+            .await;
+        assert!(result.is_valid, "{}", result.reason);
+        assert_eq!(result.format, SignatureFormat::Ecdsa);
+        assert_eq!(result.recovered_signer, Some(owner));
+        assert!(matches!(
+            result.components,
+            Some(SignatureComponents::Ecdsa { v: 27 | 28, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_ecdsa_validated_against_user_op_hash() {
+        let (signature, owner) = ecdsa_signature(user_op(Bytes::new()).hash()).await;
+
+        let result = SignatureValidator::new()
+            .validate(&user_op(signature), false)
+            .await;
+        assert!(result.is_valid, "{}", result.reason);
+        assert_eq!(result.recovered_signer, Some(owner));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_ecdsa_rejected() {
+        let (signature, _) = ecdsa_signature(user_op_hash()).await;
+        let validator = SignatureValidator::new();
+
+        let mut bad_v = signature.to_vec();
+        bad_v[64] = 5;
+        let (is_valid, format, reason) = validate(&validator, bad_v.into()).await;
+        assert!(!is_valid);
+        assert_eq!(format, SignatureFormat::Ecdsa);
+        assert!(reason.contains("recovery id"));
+
+        let mut high_s = signature.to_vec();
+        high_s[32..64].copy_from_slice(&[0xff; 32]);
+        let (is_valid, _, reason) = validate(&validator, high_s.into()).await;
+        assert!(!is_valid);
+        assert!(reason.contains("not normalized"));
+    }
+
+    #[tokio::test]
+    async fn test_erc1271_contract_signature() {
+        let signature = Bytes::from(vec![0x11; 100]);
+
+        let (is_valid, format, _) = validate(
+            &contract_validator(Some(signature.clone())),
+            signature.clone(),
+        )
+        .await;
+        assert!(is_valid);
+        assert_eq!(format, SignatureFormat::Erc1271);
+
+        let (is_valid, format, reason) = validate(&contract_validator(None), signature).await;
+        assert!(!is_valid);
+        assert_eq!(format, SignatureFormat::Erc1271);
+        assert!(reason.contains("execution reverted"));
+    }
+
+    #[tokio::test]
+    async fn test_contract_rejection_falls_back_to_ecdsa() {
+        let (signature, owner) = ecdsa_signature(user_op_hash()).await;
+
+        let result = contract_validator(Some(Bytes::from_static(b"other")))
+            .validate_signature(SENDER, user_op_hash(), &signature, false)
+            .await;
+        assert!(result.is_valid);
+        assert_eq!(result.format, SignatureFormat::Ecdsa);
+        assert_eq!(result.recovered_signer, Some(owner));
+    }
+
+    #[tokio::test]
+    async fn test_webauthn_envelope_parsed() {
+        let auth = webauthn(&webauthn_challenge(user_op_hash()), 0x05, U256::from(2));
+        let validator = SignatureValidator::new();
+
+        let result = validator
+            .validate_signature(SENDER, user_op_hash(), &auth.abi_encode().into(), false)
+            .await;
+        assert!(result.is_valid, "{}", result.reason);
+        assert_eq!(result.format, SignatureFormat::WebAuthn);
+        let Some(SignatureComponents::WebAuthn {
+            owner_index,
+            challenge,
+            user_verified,
+            ..
+        }) = result.components
+        else {
+            panic!("expected WebAuthn components");
+        };
+        assert_eq!(owner_index, None);
+        assert_eq!(challenge, webauthn_challenge(user_op_hash()));
+        assert!(user_verified);
+
+        let wrapped = SignatureWrapper {
+            ownerIndex: U256::from(1),
+            signatureData: auth.abi_encode().into(),
+        };
+        let result = validator
+            .validate_signature(SENDER, user_op_hash(), &wrapped.abi_encode().into(), false)
+            .await;
+        assert!(result.is_valid, "{}", result.reason);
+        assert!(matches!(
+            result.components,
+            Some(SignatureComponents::WebAuthn {
+                owner_index: Some(_),
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_webauthn_challenge_must_match_user_op_hash() {
+        let validator = SignatureValidator::new();
+
+        let other = webauthn(
+            &webauthn_challenge(B256::repeat_byte(0x01)),
+            0x05,
+            U256::from(2),
+        );
+        let (is_valid, format, reason) = validate(&validator, other.abi_encode().into()).await;
+        assert!(!is_valid);
+        assert_eq!(format, SignatureFormat::WebAuthn);
+        assert!(reason.contains("does not match userOpHash"));
+
+        let no_presence = webauthn(&webauthn_challenge(user_op_hash()), 0x04, U256::from(2));
+        let (is_valid, _, reason) = validate(&validator, no_presence.abi_encode().into()).await;
+        assert!(!is_valid);
+        assert!(reason.contains("user presence"));
+
+        let high_s = webauthn(&webauthn_challenge(user_op_hash()), 0x05, U256::MAX);
+        let (is_valid, _, reason) = validate(&validator, high_s.abi_encode().into()).await;
+        assert!(!is_valid);
+        assert!(reason.contains("not normalized"));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_payloads() {
+        let validator = SignatureValidator::new();
+
+        let (is_valid, format, _) = validate(&validator, Bytes::new()).await;
+        assert!(!is_valid);
+        assert_eq!(format, SignatureFormat::Empty);
+
+        let auth = webauthn(&webauthn_challenge(user_op_hash()), 0x05, U256::from(2));
+        let mut truncated = auth.abi_encode();
+        truncated.truncate(truncated.len() - 40);
+        let (is_valid, format, reason) = validate(&validator, truncated.into()).await;
+        assert!(!is_valid);
+        assert_eq!(format, SignatureFormat::Unknown);
+        assert!(reason.contains("Unrecognized"));
+    }
+
+    #[tokio::test]
+    async fn test_lenient_mode_accepts_dummy_signatures() {
+        let validator = SignatureValidator::new();
+        let dummy = Bytes::from(vec![0xff; 65]);
+
+        let strict = validator
+            .validate_signature(SENDER, user_op_hash(), &dummy, false)
+            .await;
+        assert!(!strict.is_valid);
+
+        let lenient = validator
+            .validate_signature(SENDER, user_op_hash(), &dummy, true)
+            .await;
+        assert!(lenient.is_valid);
+        assert!(lenient.lenient);
+        assert_eq!(lenient.format, SignatureFormat::Ecdsa);
+        assert_eq!(lenient.reason, strict.reason);
+    }
+
+    #[test]
+    fn test_webauthn_challenge_is_unpadded_base64url() {
+        assert_eq!(webauthn_challenge(B256::ZERO), "A".repeat(43));
+        assert_eq!(
+            webauthn_challenge(B256::repeat_byte(0xff)),
+            format!("{}8", "_".repeat(42))
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Fixtures shared by the unit tests of the gateway
+
+use std::sync::Arc;
+
+use rundler_paymaster_relay::{
+    policy::PolicyEngine, service::PaymasterRelayService, signer::SignerManager,
+};
+use rundler_pool::LocalPoolBuilder;
+use secrecy::SecretString;
+use serde_json::{json, Value};
+use tempfile::tempdir;
+
+use crate::{
+    gateway::JsonRpcRequest, middleware::hash_api_key, ApiKeyConfig, ApiKeyEntry, ApiKeyScope,
+    GatewayConfig,
+};
+
+/// Paymaster signing key of [`service`]
+pub const SIGNER_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+/// Raw key of the API key configured by [`admin_api_keys`]
+pub const ADMIN_KEY: &str = "sk-admin-test";
+
+/// API key authentication with one [`ADMIN_KEY`] holding the admin, sponsor,
+/// send and read scopes
+///
+/// Admin methods are refused without a key, so tests calling them over HTTP
+/// configure this and send the key.
+pub fn admin_api_keys() -> ApiKeyConfig {
+    ApiKeyConfig {
+        enabled: true,
+        allow_anonymous_reads: true,
+        keys: vec![ApiKeyEntry {
+            id: "admin".to_string(),
+            key_hash: hash_api_key(ADMIN_KEY),
+            scopes: vec![
+                ApiKeyScope::Admin,
+                ApiKeyScope::Sponsor,
+                ApiKeyScope::Send,
+                ApiKeyScope::Read,
+            ],
+            trust_level: None,
+        }],
+    }
+}
+
+/// Default gateway configuration with [`admin_api_keys`]
+pub fn admin_gateway_config() -> GatewayConfig {
+    GatewayConfig {
+        api_keys: admin_api_keys(),
+        ..Default::default()
+    }
+}
+
+/// Paymaster service signing with [`SIGNER_KEY`] under the `[default]` policy
+/// `policy`, on an in-memory pool
+pub fn service(policy: &str) -> PaymasterRelayService {
+    let dir = tempdir().unwrap();
+    let policy_path = dir.path().join("policy.toml");
+    std::fs::write(&policy_path, format!("[default]\n{}", policy)).unwrap();
+
+    let signer_manager =
+        SignerManager::new(SecretString::new(SIGNER_KEY.to_string().into())).unwrap();
+    let policy_engine = PolicyEngine::new(&policy_path).unwrap();
+    let pool = Arc::new(LocalPoolBuilder::new(10).get_handle());
+    PaymasterRelayService::new(signer_manager, policy_engine, pool)
+}
+
+/// Anonymous JSON-RPC request for `method` with `params`
+pub fn request(method: &str, params: Vec<Value>) -> JsonRpcRequest {
+    JsonRpcRequest {
+        id: json!(1),
+        method: method.to_string(),
+        params,
+        api_key_id: None,
+        trust_level: None,
+        chain_id: None,
+        no_cache: false,
+        request_id: None,
+        deadline: Default::default(),
+        debug: false,
+        permissions: false,
+    }
+}
@@ -0,0 +1,682 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::{Address, Selector};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::error::{GatewayError, GatewayResult};
+
+/// Threat indicators operations are checked against
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThreatIntelligence {
+    /// Blacklisted senders and paymasters
+    pub malicious_addresses: HashSet<Address>,
+    /// Blacklisted callData function selectors
+    pub blocked_selectors: HashSet<Selector>,
+    /// Suspicious callData hex patterns
+    pub phishing_patterns: Vec<String>,
+    /// Contract reputation scores (0-100)
+    pub contract_reputation: HashMap<Address, u8>,
+}
+
+impl ThreatIntelligence {
+    /// Indicators shipped with the gateway, used before and alongside any feed
+    pub fn builtin() -> Self {
+        let mut intel = Self::default();
+        intel.malicious_addresses.insert(
+            "0x0000000000000000000000000000000000000bad"
+                .parse()
+                .unwrap(),
+        );
+        intel.malicious_addresses.insert(
+            "0x000000000000000000000000000000000000dead"
+                .parse()
+                .unwrap(),
+        );
+        intel
+            .phishing_patterns
+            .push("transfer.*0x00000000".to_string());
+        intel
+            .phishing_patterns
+            .push("approve.*999999999".to_string());
+        intel.contract_reputation.insert(
+            "0xA0b86a33E6441d6e42D2B6b63B3b66F2c7A77fD8"
+                .parse()
+                .unwrap(),
+            95,
+        );
+        intel
+    }
+
+    /// Whether no indicator is set
+    pub fn is_empty(&self) -> bool {
+        self.malicious_addresses.is_empty()
+            && self.blocked_selectors.is_empty()
+            && self.phishing_patterns.is_empty()
+            && self.contract_reputation.is_empty()
+    }
+
+    /// Add the indicators of `other`
+    pub fn merge(&mut self, other: &ThreatIntelligence) {
+        self.malicious_addresses
+            .extend(other.malicious_addresses.iter().copied());
+        self.blocked_selectors
+            .extend(other.blocked_selectors.iter().copied());
+        for pattern in &other.phishing_patterns {
+            if !self.phishing_patterns.contains(pattern) {
+                self.phishing_patterns.push(pattern.clone());
+            }
+        }
+        self.contract_reputation
+            .extend(other.contract_reputation.iter().map(|(k, v)| (*k, *v)));
+    }
+
+    /// Parse a JSON feed document:
+    /// `{"addresses": ["0x.."], "selectors": ["0xa9059cbb"], "phishingPatterns": [".."]}`
+    pub fn from_json(data: &str) -> GatewayResult<Self> {
+        let document: ThreatFeedDocument = serde_json::from_str(data)
+            .map_err(|e| GatewayError::InvalidRequest(format!("Invalid threat feed: {}", e)))?;
+        Ok(Self {
+            malicious_addresses: document.addresses.into_iter().collect(),
+            blocked_selectors: document.selectors.into_iter().collect(),
+            phishing_patterns: document.phishing_patterns,
+            contract_reputation: HashMap::new(),
+        })
+    }
+
+    /// Parse a CSV feed with `kind,value` rows, where kind is `address` or `selector`
+    ///
+    /// Empty lines, `#` comments and a `kind,value` header are skipped.
+    pub fn from_csv(data: &str) -> GatewayResult<Self> {
+        let mut intel = Self::default();
+        for (index, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut columns = line.split(',').map(str::trim);
+            let kind = columns.next().unwrap_or_default();
+            let value = columns.next().unwrap_or_default();
+            let invalid = || {
+                GatewayError::InvalidRequest(format!(
+                    "Invalid threat feed line {}: {}",
+                    index + 1,
+                    line
+                ))
+            };
+            match kind.to_ascii_lowercase().as_str() {
+                "kind" if index == 0 => {}
+                "address" => {
+                    intel
+                        .malicious_addresses
+                        .insert(value.parse().map_err(|_| invalid())?);
+                }
+                "selector" => {
+                    intel
+                        .blocked_selectors
+                        .insert(value.parse().map_err(|_| invalid())?);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(intel)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct ThreatFeedDocument {
+    addresses: Vec<Address>,
+    selectors: Vec<Selector>,
+    phishing_patterns: Vec<String>,
+}
+
+/// Source of threat intelligence
+#[async_trait]
+pub trait ThreatFeed: Send + Sync {
+    /// Feed name used in logs and status reports
+    fn name(&self) -> &str;
+
+    /// Load the feed's current indicators
+    async fn fetch(&self) -> GatewayResult<ThreatIntelligence>;
+}
+
+/// Local JSON or CSV file, chosen by the `.csv` extension
+pub struct FileThreatFeed {
+    path: PathBuf,
+    name: String,
+}
+
+impl FileThreatFeed {
+    /// Read indicators from `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let name = format!("file:{}", path.display());
+        Self { path, name }
+    }
+}
+
+#[async_trait]
+impl ThreatFeed for FileThreatFeed {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self) -> GatewayResult<ThreatIntelligence> {
+        let data = tokio::fs::read_to_string(&self.path).await.map_err(|e| {
+            GatewayError::InternalError(format!("Failed to read {}: {}", self.path.display(), e))
+        })?;
+        let is_csv = self
+            .path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        if is_csv {
+            ThreatIntelligence::from_csv(&data)
+        } else {
+            ThreatIntelligence::from_json(&data)
+        }
+    }
+}
+
+/// JSON feed served over HTTP
+pub struct HttpThreatFeed {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl HttpThreatFeed {
+    /// Poll `url`, giving up on a request after `timeout`
+    pub fn new(url: impl Into<String>, timeout: Duration) -> GatewayResult<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| GatewayError::InternalError(format!("HTTP client error: {}", e)))?;
+        Ok(Self {
+            url: url.into(),
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl ThreatFeed for HttpThreatFeed {
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    async fn fetch(&self) -> GatewayResult<ThreatIntelligence> {
+        let response = self
+            .client
+            .get(&self.url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| {
+                GatewayError::InternalError(format!("Threat feed request failed: {}", e))
+            })?;
+        let body = response.text().await.map_err(|e| {
+            GatewayError::InternalError(format!("Threat feed response unreadable: {}", e))
+        })?;
+        ThreatIntelligence::from_json(&body)
+    }
+}
+
+/// Threat feed sources (`[gateway.threat_feeds]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ThreatFeedConfig {
+    /// Local JSON or CSV feed files
+    pub files: Vec<PathBuf>,
+    /// HTTP(S) URLs serving JSON feeds
+    pub urls: Vec<String>,
+    /// Seconds between feed refreshes
+    pub refresh_interval_seconds: u64,
+    /// Up to this many seconds are added to each refresh interval at random
+    pub refresh_jitter_seconds: u64,
+    /// HTTP request timeout in seconds
+    pub request_timeout_seconds: u64,
+    /// Feed data older than this many seconds is reported as stale
+    pub max_age_seconds: u64,
+}
+
+impl Default for ThreatFeedConfig {
+    fn default() -> Self {
+        Self {
+            files: Vec::new(),
+            urls: Vec::new(),
+            refresh_interval_seconds: 300,
+            refresh_jitter_seconds: 30,
+            request_timeout_seconds: 10,
+            max_age_seconds: 3600,
+        }
+    }
+}
+
+impl ThreatFeedConfig {
+    /// Whether no feed is configured
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.urls.is_empty()
+    }
+
+    /// Feeds for the configured files and URLs
+    pub fn feeds(&self) -> GatewayResult<Vec<Arc<dyn ThreatFeed>>> {
+        let timeout = Duration::from_secs(self.request_timeout_seconds);
+        let mut feeds: Vec<Arc<dyn ThreatFeed>> = Vec::new();
+        for path in &self.files {
+            feeds.push(Arc::new(FileThreatFeed::new(path.clone())));
+        }
+        for url in &self.urls {
+            feeds.push(Arc::new(HttpThreatFeed::new(url.clone(), timeout)?));
+        }
+        Ok(feeds)
+    }
+}
+
+/// Last good data and outcome of one feed
+#[derive(Default)]
+struct FeedState {
+    intel: Option<ThreatIntelligence>,
+    last_success: Option<Instant>,
+    last_error: Option<String>,
+}
+
+/// Refresh state of one feed
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreatFeedStatus {
+    /// Feed name
+    pub name: String,
+    /// Seconds since the feed last loaded, `None` if it never did
+    pub age_seconds: Option<u64>,
+    /// Error of the last refresh, if it failed
+    pub last_error: Option<String>,
+}
+
+/// Threat intelligence freshness report
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreatIntelStatus {
+    /// Blacklisted addresses in the active set
+    pub addresses: usize,
+    /// Blacklisted selectors in the active set
+    pub selectors: usize,
+    /// Age of the oldest feed data in seconds, `None` if a feed never loaded
+    pub age_seconds: Option<u64>,
+    /// Whether any feed's data is older than the configured maximum age
+    pub stale: bool,
+    /// Per-feed state
+    pub feeds: Vec<ThreatFeedStatus>,
+}
+
+/// Active threat intelligence, rebuilt from the feeds on every refresh
+///
+/// Each refresh builds a complete new set and swaps it in at once, so checks
+/// holding a snapshot never see a partially loaded set. A failing feed keeps
+/// contributing its last good data.
+pub struct ThreatIntelStore {
+    base: ThreatIntelligence,
+    feeds: Vec<Arc<dyn ThreatFeed>>,
+    states: Mutex<HashMap<String, FeedState>>,
+    current: RwLock<Arc<ThreatIntelligence>>,
+    max_age: Duration,
+}
+
+impl ThreatIntelStore {
+    /// Store serving `base` until the feeds have loaded
+    pub fn new(
+        base: ThreatIntelligence,
+        feeds: Vec<Arc<dyn ThreatFeed>>,
+        max_age: Duration,
+    ) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(base.clone())),
+            base,
+            feeds,
+            states: Mutex::new(HashMap::new()),
+            max_age,
+        }
+    }
+
+    /// Store for the feeds of `config` on top of the built-in indicators
+    pub fn from_config(config: &ThreatFeedConfig) -> GatewayResult<Self> {
+        Ok(Self::new(
+            ThreatIntelligence::builtin(),
+            config.feeds()?,
+            Duration::from_secs(config.max_age_seconds),
+        ))
+    }
+
+    /// Current threat intelligence
+    pub fn snapshot(&self) -> Arc<ThreatIntelligence> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Reload every feed and swap in the combined result
+    ///
+    /// Returns an error naming the failed feeds; their previous data is kept.
+    pub async fn refresh(&self) -> GatewayResult<()> {
+        let mut failures = Vec::new();
+        for feed in &self.feeds {
+            let result = feed.fetch().await;
+            let mut states = self.states.lock().unwrap();
+            let state = states.entry(feed.name().to_string()).or_default();
+            match result {
+                Ok(intel) => {
+                    debug!(
+                        "Threat feed {} loaded {} addresses, {} selectors",
+                        feed.name(),
+                        intel.malicious_addresses.len(),
+                        intel.blocked_selectors.len()
+                    );
+                    state.intel = Some(intel);
+                    state.last_success = Some(Instant::now());
+                    state.last_error = None;
+                }
+                Err(e) => {
+                    warn!(
+                        "Threat feed {} failed, keeping previous data: {}",
+                        feed.name(),
+                        e
+                    );
+                    state.last_error = Some(e.to_string());
+                    failures.push(format!("{}: {}", feed.name(), e));
+                }
+            }
+        }
+
+        let mut combined = self.base.clone();
+        {
+            let states = self.states.lock().unwrap();
+            for intel in states.values().filter_map(|state| state.intel.as_ref()) {
+                combined.merge(intel);
+            }
+        }
+        *self.current.write().unwrap() = Arc::new(combined);
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(GatewayError::InternalError(format!(
+                "Threat feeds failed: {}",
+                failures.join("; ")
+            )))
+        }
+    }
+
+    /// Age of the oldest feed data, `None` if a feed never loaded
+    pub fn age(&self) -> Option<Duration> {
+        let states = self.states.lock().unwrap();
+        let mut oldest = Duration::ZERO;
+        for feed in &self.feeds {
+            let age = states.get(feed.name())?.last_success?.elapsed();
+            oldest = oldest.max(age);
+        }
+        Some(oldest)
+    }
+
+    /// Freshness of the active set and each feed
+    pub fn status(&self) -> ThreatIntelStatus {
+        let intel = self.snapshot();
+        let age = self.age();
+        let states = self.states.lock().unwrap();
+        let feeds = self
+            .feeds
+            .iter()
+            .map(|feed| {
+                let state = states.get(feed.name());
+                ThreatFeedStatus {
+                    name: feed.name().to_string(),
+                    age_seconds: state
+                        .and_then(|s| s.last_success)
+                        .map(|t| t.elapsed().as_secs()),
+                    last_error: state.and_then(|s| s.last_error.clone()),
+                }
+            })
+            .collect();
+
+        ThreatIntelStatus {
+            addresses: intel.malicious_addresses.len(),
+            selectors: intel.blocked_selectors.len(),
+            age_seconds: age.map(|age| age.as_secs()),
+            stale: !self.feeds.is_empty() && age.is_none_or(|age| age > self.max_age),
+            feeds,
+        }
+    }
+
+    /// Refresh now and then every `interval` plus up to `jitter`, until aborted
+    pub fn spawn_refresh(self: Arc<Self>, interval: Duration, jitter: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                match self.refresh().await {
+                    Ok(()) => info!("🛡️ Threat intelligence refreshed"),
+                    Err(e) => warn!("{}", e),
+                }
+                tokio::time::sleep(interval + random_jitter(jitter)).await;
+            }
+        })
+    }
+}
+
+/// Random delay below `max`, spreading refreshes of several gateways apart
+fn random_jitter(max: Duration) -> Duration {
+    let max_millis = max.as_millis() as u64;
+    if max_millis == 0 {
+        return Duration::ZERO;
+    }
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos() as u64;
+    Duration::from_millis(seed % max_millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use alloy_primitives::{address, Address, Bytes, Selector, U256};
+    use axum::{http::StatusCode, routing::get, Router};
+    use rundler_types::{chain::ChainSpec, v0_6, UserOperationVariant};
+    use tempfile::tempdir;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::SecurityChecker;
+
+    const FEED_SENDER: Address = address!("1111111111111111111111111111111111111111");
+    const CLEAN_SENDER: Address = address!("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+
+    fn user_op(sender: Address, call_data: Bytes) -> UserOperationVariant {
+        UserOperationVariant::V0_6(
+            v0_6::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_6::UserOperationRequiredFields {
+                    sender,
+                    nonce: U256::from(1),
+                    init_code: Bytes::new(),
+                    call_data,
+                    call_gas_limit: 100_000,
+                    verification_gas_limit: 100_000,
+                    pre_verification_gas: 21_000,
+                    max_fee_per_gas: 1_000_000_000,
+                    max_priority_fee_per_gas: 1_000_000_000,
+                    paymaster_and_data: Bytes::new(),
+                    signature: Bytes::new(),
+                },
+            )
+            .build(),
+        )
+    }
+
+    fn store(feeds: Vec<Arc<dyn ThreatFeed>>) -> Arc<ThreatIntelStore> {
+        Arc::new(ThreatIntelStore::new(
+            ThreatIntelligence::builtin(),
+            feeds,
+            Duration::from_secs(3600),
+        ))
+    }
+
+    /// Serve `body` at `/feed` until `healthy` is cleared, then answer 500
+    async fn serve_feed(body: String, healthy: Arc<AtomicBool>) -> String {
+        let app = Router::new().route(
+            "/feed",
+            get(move || {
+                let body = body.clone();
+                let healthy = healthy.clone();
+                async move {
+                    if healthy.load(Ordering::SeqCst) {
+                        (StatusCode::OK, body)
+                    } else {
+                        (StatusCode::INTERNAL_SERVER_ERROR, String::new())
+                    }
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/feed", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_load_json_and_csv_files() {
+        let dir = tempdir().unwrap();
+        let json_path = dir.path().join("feed.json");
+        std::fs::write(
+            &json_path,
+            format!(
+                r#"{{"addresses": ["{}"], "phishingPatterns": ["drain.*wallet"]}}"#,
+                FEED_SENDER
+            ),
+        )
+        .unwrap();
+        let csv_path = dir.path().join("feed.csv");
+        std::fs::write(
+            &csv_path,
+            "kind,value\n# known drainer\naddress,0x2222222222222222222222222222222222222222\nselector,0xa9059cbb\n",
+        )
+        .unwrap();
+
+        let store = store(vec![
+            Arc::new(FileThreatFeed::new(&json_path)),
+            Arc::new(FileThreatFeed::new(&csv_path)),
+        ]);
+        store.refresh().await.unwrap();
+
+        let intel = store.snapshot();
+        assert!(intel.malicious_addresses.contains(&FEED_SENDER));
+        assert!(intel
+            .malicious_addresses
+            .contains(&address!("2222222222222222222222222222222222222222")));
+        assert!(intel
+            .blocked_selectors
+            .contains(&Selector::from([0xa9, 0x05, 0x9c, 0xbb])));
+        assert!(intel
+            .phishing_patterns
+            .contains(&"drain.*wallet".to_string()));
+        // Built-in indicators stay active next to the feeds
+        assert!(intel
+            .malicious_addresses
+            .contains(&address!("0000000000000000000000000000000000000bad")));
+
+        let status = store.status();
+        assert!(!status.stale);
+        assert_eq!(status.age_seconds, Some(0));
+    }
+
+    #[test]
+    fn test_invalid_csv_row_fails_feed() {
+        assert!(ThreatIntelligence::from_csv("address,not-an-address\n").is_err());
+        assert!(ThreatIntelligence::from_csv("domain,evil.example\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failing_http_feed_keeps_previous_data() {
+        let healthy = Arc::new(AtomicBool::new(true));
+        let url = serve_feed(
+            format!(r#"{{"addresses": ["{}"]}}"#, FEED_SENDER),
+            healthy.clone(),
+        )
+        .await;
+
+        let store = store(vec![Arc::new(
+            HttpThreatFeed::new(url, Duration::from_secs(5)).unwrap(),
+        )]);
+        store.refresh().await.unwrap();
+        assert!(store.snapshot().malicious_addresses.contains(&FEED_SENDER));
+
+        healthy.store(false, Ordering::SeqCst);
+        assert!(store.refresh().await.is_err());
+
+        assert!(store.snapshot().malicious_addresses.contains(&FEED_SENDER));
+        let status = store.status();
+        assert!(status.age_seconds.is_some());
+        assert!(status.feeds[0].last_error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_unloaded_feed_reported_stale() {
+        let store = store(vec![Arc::new(
+            HttpThreatFeed::new("http://127.0.0.1:1/feed", Duration::from_secs(1)).unwrap(),
+        )]);
+        assert!(store.refresh().await.is_err());
+
+        let status = store.status();
+        assert!(status.stale);
+        assert_eq!(status.age_seconds, None);
+        // Built-in indicators remain in force
+        assert!(!store.snapshot().malicious_addresses.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_feed_blacklist_rejected_by_security_check() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("feed.json");
+        std::fs::write(
+            &path,
+            format!(
+                r#"{{"addresses": ["{}"], "selectors": ["0xdeadbeef"]}}"#,
+                FEED_SENDER
+            ),
+        )
+        .unwrap();
+        let store = store(vec![Arc::new(FileThreatFeed::new(&path))]);
+        store.refresh().await.unwrap();
+
+        let mut checker = SecurityChecker::new().with_threat_intel(store.clone());
+        checker.load_threat_intelligence().await.unwrap();
+        let entry_point = ChainSpec::default().entry_point_address_v0_6;
+
+        let result = checker
+            .check_security(&user_op(FEED_SENDER, Bytes::new()), &entry_point, None)
+            .await
+            .unwrap();
+        assert!(!result.is_secure);
+        assert!(!result.check_results["malicious_address"].passed);
+        assert_eq!(result.metadata.threat_intel_age_seconds, Some(0));
+
+        let result = checker
+            .check_security(
+                &user_op(CLEAN_SENDER, Bytes::from_static(&[0xde, 0xad, 0xbe, 0xef])),
+                &entry_point,
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(!result.is_secure);
+        assert!(!result.check_results["calldata_security"].passed);
+    }
+}
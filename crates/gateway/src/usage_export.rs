@@ -0,0 +1,31 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rundler_paymaster_relay::UsageGranularity;
+use serde::Deserialize;
+
+/// Method GET /admin/usage/export is authorized as, putting it in the admin scope
+pub const USAGE_EXPORT_METHOD: &str = "admin_exportUsage";
+
+/// Query of GET /admin/usage/export
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UsageExportQuery {
+    /// Only export the usage of this API key; every key when unset
+    pub api_key_id: Option<String>,
+    /// Unix time to export from, the start of time when unset
+    pub from: Option<u64>,
+    /// Unix time to export to, now when unset
+    pub to: Option<u64>,
+    /// Bucket length, hourly when unset
+    pub granularity: Option<UsageGranularity>,
+}
+
+impl UsageExportQuery {
+    /// Exported range in unix seconds
+    pub fn range(&self) -> (u64, u64) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        (self.from.unwrap_or(0), self.to.unwrap_or(now))
+    }
+}
@@ -4,9 +4,13 @@ use alloy_primitives::U256;
 use num_traits::ToPrimitive;
 use rundler_types::{v0_6, v0_7, UserOperation, UserOperationVariant};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use tracing::debug;
 
-use crate::error::GatewayResult;
+use crate::{
+    error::{GatewayError, GatewayResult},
+    json_fields::FieldParsing,
+};
 
 /// Data integrity validation result
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,8 +64,13 @@ pub struct DataIntegrityChecker {
     config: ValidationConfig,
 }
 
-/// Configuration for data integrity validation
-#[derive(Debug, Clone)]
+/// Configuration for data integrity validation and the structural request
+/// limits enforced before any check runs (`[gateway.validation]`)
+///
+/// Defaults follow rundler: fields are bounded by the 128 KiB maximum
+/// transaction size, request bodies by twice that, as rundler's RPC server does.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct ValidationConfig {
     /// Minimum gas limit allowed
     pub min_gas_limit: u64,
@@ -77,22 +86,179 @@ pub struct ValidationConfig {
     pub max_verification_gas: u64,
     /// Maximum number of pre-verification gas units
     pub max_pre_verification_gas: u64,
+    /// Maximum JSON-RPC request body size in bytes
+    pub max_request_body_bytes: usize,
+    /// Maximum number of requests in a JSON-RPC batch
+    pub max_batch_size: usize,
+    /// Maximum initCode (factory and factoryData) size in bytes
+    pub max_init_code_size: usize,
+    /// Maximum paymasterAndData (paymaster fields and paymasterData) size in bytes
+    pub max_paymaster_and_data_size: usize,
+    /// Maximum signature size in bytes
+    pub max_signature_size: usize,
+    /// Highest maxFeePerGas accepted, in wei
+    pub max_fee_per_gas: u128,
+    /// How strictly UserOperation fields are parsed
+    pub field_parsing: FieldParsing,
 }
 
 impl Default for ValidationConfig {
     fn default() -> Self {
         Self {
-            min_gas_limit: 21000,            // Minimum Ethereum transaction gas
-            max_gas_limit: 30_000_000,       // Maximum reasonable gas limit
-            max_call_data_size: 1024 * 1024, // 1MB max call data
+            min_gas_limit: 21000,           // Minimum Ethereum transaction gas
+            max_gas_limit: 30_000_000,      // Maximum reasonable gas limit
+            max_call_data_size: 128 * 1024, // rundler's max transaction size
             require_signature: true,
             strict_address_format: true,
             max_verification_gas: 5_000_000,
             max_pre_verification_gas: 1_000_000,
+            max_request_body_bytes: 2 * 128 * 1024,
+            max_batch_size: 20,
+            max_init_code_size: 128 * 1024,
+            max_paymaster_and_data_size: 16 * 1024,
+            max_signature_size: 16 * 1024,
+            max_fee_per_gas: 10_000_000_000_000, // 10,000 gwei
+            field_parsing: FieldParsing::Strict,
         }
     }
 }
 
+impl ValidationConfig {
+    /// Reject operations breaking the structural limits, naming the offending
+    /// field and its limit
+    ///
+    /// Runs on the parsed operation before any pipeline check, so oversized or
+    /// obviously invalid operations never reach simulation.
+    pub fn check_limits(&self, user_op: &UserOperationVariant) -> GatewayResult<()> {
+        let (init_code_size, paymaster_and_data_size, verification_gas_field) = match user_op {
+            UserOperationVariant::V0_6(op) => (
+                op.init_code().len(),
+                op.paymaster_and_data().len(),
+                if op.paymaster().is_some() {
+                    "verificationGasLimit (counted twice with a paymaster)"
+                } else {
+                    "verificationGasLimit"
+                },
+            ),
+            UserOperationVariant::V0_7(op) => (
+                op.packed().initCode.len(),
+                op.packed().paymasterAndData.len(),
+                "verificationGasLimit + paymasterVerificationGasLimit",
+            ),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => (
+                op.packed().initCode.len(),
+                op.packed().paymasterAndData.len(),
+                "verificationGasLimit + paymasterVerificationGasLimit",
+            ),
+        };
+
+        check_size(
+            "callData",
+            user_op.call_data().len(),
+            self.max_call_data_size,
+        )?;
+        check_size("initCode", init_code_size, self.max_init_code_size)?;
+        check_size(
+            "paymasterAndData",
+            paymaster_and_data_size,
+            self.max_paymaster_and_data_size,
+        )?;
+        check_size(
+            "signature",
+            user_op.signature().len(),
+            self.max_signature_size,
+        )?;
+
+        if user_op.sender().is_zero() {
+            return Err(GatewayError::InvalidRequest(
+                "sender must not be the zero address".to_string(),
+            ));
+        }
+        // Gas totals and costs are u128 sums further on, which must not overflow
+        if max_gas_cost(user_op).is_none() {
+            return Err(GatewayError::InvalidRequest(
+                "gas limits and maxFeePerGas overflow the maximum gas cost".to_string(),
+            ));
+        }
+        let verification_gas = user_op.total_verification_gas_limit();
+        if verification_gas > u128::from(self.max_verification_gas) {
+            return Err(GatewayError::InvalidRequest(format!(
+                "{} of {} exceeds the maximum of {}",
+                verification_gas_field, verification_gas, self.max_verification_gas
+            )));
+        }
+        if user_op.max_fee_per_gas() > self.max_fee_per_gas {
+            return Err(GatewayError::InvalidRequest(format!(
+                "maxFeePerGas of {} wei exceeds the maximum of {} wei",
+                user_op.max_fee_per_gas(),
+                self.max_fee_per_gas
+            )));
+        }
+        Ok(())
+    }
+
+    /// Reject byte fields of a UserOperation in JSON whose hex text is already
+    /// longer than their limit allows, before they are decoded
+    pub fn check_raw_sizes(&self, user_op: &Value) -> GatewayResult<()> {
+        let limits = [
+            ("initCode", self.max_init_code_size),
+            ("factoryData", self.max_init_code_size),
+            ("callData", self.max_call_data_size),
+            ("paymasterAndData", self.max_paymaster_and_data_size),
+            ("paymasterData", self.max_paymaster_and_data_size),
+            ("signature", self.max_signature_size),
+        ];
+        for (field, max) in limits {
+            if let Some(Value::String(s)) = user_op.get(field) {
+                let digits = s
+                    .strip_prefix("0x")
+                    .or_else(|| s.strip_prefix("0X"))
+                    .unwrap_or(s);
+                check_size(field, digits.len() / 2, max)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Most the operation can be charged, as the EntryPoint computes it, or `None`
+/// when that overflows
+fn max_gas_cost(user_op: &UserOperationVariant) -> Option<u128> {
+    let gas = match user_op {
+        UserOperationVariant::V0_6(op) => {
+            // The verification gas limit also bounds both paymaster calls
+            let multiplier = if op.paymaster().is_some() { 3 } else { 1 };
+            op.verification_gas_limit()
+                .checked_mul(multiplier)?
+                .checked_add(op.call_gas_limit())?
+                .checked_add(op.pre_verification_gas())?
+        }
+        UserOperationVariant::V0_7(op) => packed_gas(op)?,
+        #[cfg(feature = "entrypoint-v0_8")]
+        UserOperationVariant::V0_8(op) => packed_gas(op.fields())?,
+    };
+    gas.checked_mul(user_op.max_fee_per_gas())
+}
+
+fn packed_gas(op: &v0_7::UserOperation) -> Option<u128> {
+    op.verification_gas_limit()
+        .checked_add(op.paymaster_verification_gas_limit())?
+        .checked_add(op.paymaster_post_op_gas_limit())?
+        .checked_add(op.call_gas_limit())?
+        .checked_add(op.pre_verification_gas())
+}
+
+fn check_size(field: &str, size: usize, max: usize) -> GatewayResult<()> {
+    if size > max {
+        return Err(GatewayError::InvalidRequest(format!(
+            "{} of {} bytes exceeds the maximum of {} bytes",
+            field, size, max
+        )));
+    }
+    Ok(())
+}
+
 impl DataIntegrityChecker {
     /// Create a new data integrity checker with default configuration
     pub fn new() -> Self {
@@ -154,6 +320,17 @@ impl DataIntegrityChecker {
                 )
                 .await?;
             }
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => {
+                self.validate_v0_7_fields(
+                    op.fields(),
+                    &mut field_validations,
+                    &mut critical_issues,
+                    &mut warnings,
+                    &mut validation_score,
+                )
+                .await?;
+            }
         }
 
         // Cross-field validation
@@ -979,6 +1156,17 @@ impl DataIntegrityChecker {
                 let max_fee = op.max_fee_per_gas().to_u64().unwrap_or(u64::MAX);
                 let max_priority_fee = op.max_priority_fee_per_gas().to_u64().unwrap_or(u64::MAX);
 
+                if max_priority_fee > max_fee {
+                    critical_issues
+                        .push("Max priority fee per gas cannot exceed max fee per gas".to_string());
+                    *validation_score = validation_score.saturating_sub(15);
+                }
+            }
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => {
+                let max_fee = op.max_fee_per_gas().to_u64().unwrap_or(u64::MAX);
+                let max_priority_fee = op.max_priority_fee_per_gas().to_u64().unwrap_or(u64::MAX);
+
                 if max_priority_fee > max_fee {
                     critical_issues
                         .push("Max priority fee per gas cannot exceed max fee per gas".to_string());
@@ -989,7 +1177,13 @@ impl DataIntegrityChecker {
 
         // Additional consistency checks can be added here
         // For example: init_code consistency with factory fields in v0.7
-        if let UserOperationVariant::V0_7(op) = user_op {
+        let packed = match user_op {
+            UserOperationVariant::V0_6(_) => None,
+            UserOperationVariant::V0_7(op) => Some(op),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => Some(op.fields()),
+        };
+        if let Some(op) = packed {
             let has_factory = op.factory().is_some();
             let has_factory_data = !op.factory_data().is_empty();
 
@@ -1041,7 +1235,16 @@ impl Default for DataIntegrityChecker {
 
 #[cfg(test)]
 mod tests {
+    use alloy_primitives::{Address, Bytes, U256};
+    use rundler_types::{chain::ChainSpec, v0_6, v0_7, UserOperationVariant};
+    use serde_json::{json, Value};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
     use super::*;
+    use crate::{GatewayConfig, GatewayError, PaymasterGateway};
 
     #[test]
     fn test_validation_config_default() {
@@ -1110,4 +1313,265 @@ mod tests {
         assert!(high_result.is_valid); // Still valid but with warning
         assert_eq!(high_result.severity, ValidationSeverity::Warning);
     }
+
+    const SENDER: Address = Address::repeat_byte(0x11);
+
+    fn limits() -> ValidationConfig {
+        ValidationConfig {
+            max_request_body_bytes: 1024,
+            max_batch_size: 2,
+            max_call_data_size: 100,
+            max_init_code_size: 100,
+            max_paymaster_and_data_size: 100,
+            max_signature_size: 100,
+            max_verification_gas: 1_000_000,
+            max_fee_per_gas: 1_000_000_000_000,
+            ..Default::default()
+        }
+    }
+
+    fn v0_6_fields() -> v0_6::UserOperationRequiredFields {
+        v0_6::UserOperationRequiredFields {
+            sender: SENDER,
+            nonce: U256::ZERO,
+            verification_gas_limit: 100_000,
+            max_fee_per_gas: 1_000_000_000,
+            ..Default::default()
+        }
+    }
+
+    fn v0_6_op(fields: v0_6::UserOperationRequiredFields) -> UserOperationVariant {
+        UserOperationVariant::V0_6(
+            v0_6::UserOperationBuilder::new(&ChainSpec::default(), fields).build(),
+        )
+    }
+
+    fn v0_7_op(
+        build: impl FnOnce(v0_7::UserOperationBuilder<'_>) -> v0_7::UserOperationBuilder<'_>,
+    ) -> UserOperationVariant {
+        let chain_spec = ChainSpec::default();
+        let builder = v0_7::UserOperationBuilder::new(
+            &chain_spec,
+            v0_7::UserOperationRequiredFields {
+                sender: SENDER,
+                nonce: U256::ZERO,
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 1_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::new(),
+            },
+        );
+        UserOperationVariant::V0_7(build(builder).build())
+    }
+
+    fn bytes(len: usize) -> Bytes {
+        Bytes::from(vec![0xab; len])
+    }
+
+    /// Assert `at_limit` passes and `over_limit` fails naming `field` and the limit
+    fn assert_boundary(
+        at_limit: UserOperationVariant,
+        over_limit: UserOperationVariant,
+        field: &str,
+    ) {
+        let limits = limits();
+        limits.check_limits(&at_limit).unwrap();
+        let err = limits.check_limits(&over_limit).unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidRequest(_)));
+        let message = err.to_string();
+        assert!(message.contains(field), "{}", message);
+        assert!(message.contains("maximum"), "{}", message);
+    }
+
+    #[test]
+    fn test_byte_field_limits_v0_6() {
+        assert_boundary(
+            v0_6_op(v0_6::UserOperationRequiredFields {
+                call_data: bytes(100),
+                ..v0_6_fields()
+            }),
+            v0_6_op(v0_6::UserOperationRequiredFields {
+                call_data: bytes(101),
+                ..v0_6_fields()
+            }),
+            "callData of 101 bytes exceeds the maximum of 100 bytes",
+        );
+        assert_boundary(
+            v0_6_op(v0_6::UserOperationRequiredFields {
+                init_code: bytes(100),
+                ..v0_6_fields()
+            }),
+            v0_6_op(v0_6::UserOperationRequiredFields {
+                init_code: bytes(101),
+                ..v0_6_fields()
+            }),
+            "initCode",
+        );
+        assert_boundary(
+            v0_6_op(v0_6::UserOperationRequiredFields {
+                paymaster_and_data: bytes(100),
+                ..v0_6_fields()
+            }),
+            v0_6_op(v0_6::UserOperationRequiredFields {
+                paymaster_and_data: bytes(101),
+                ..v0_6_fields()
+            }),
+            "paymasterAndData",
+        );
+        assert_boundary(
+            v0_6_op(v0_6::UserOperationRequiredFields {
+                signature: bytes(100),
+                ..v0_6_fields()
+            }),
+            v0_6_op(v0_6::UserOperationRequiredFields {
+                signature: bytes(101),
+                ..v0_6_fields()
+            }),
+            "signature",
+        );
+    }
+
+    #[test]
+    fn test_packed_field_limits_v0_7() {
+        // initCode is the factory address followed by factoryData
+        assert_boundary(
+            v0_7_op(|op| op.factory(Address::repeat_byte(0xfa), bytes(80))),
+            v0_7_op(|op| op.factory(Address::repeat_byte(0xfa), bytes(81))),
+            "initCode of 101 bytes",
+        );
+        // paymasterAndData packs the address and two 16 byte gas limits before paymasterData
+        assert_boundary(
+            v0_7_op(|op| op.paymaster(Address::repeat_byte(0xbb), 0, 0, bytes(48))),
+            v0_7_op(|op| op.paymaster(Address::repeat_byte(0xbb), 0, 0, bytes(49))),
+            "paymasterAndData of 101 bytes",
+        );
+    }
+
+    #[test]
+    fn test_value_sanity_limits() {
+        let err = limits()
+            .check_limits(&v0_6_op(v0_6::UserOperationRequiredFields {
+                sender: Address::ZERO,
+                ..v0_6_fields()
+            }))
+            .unwrap_err();
+        assert!(err.to_string().contains("sender"), "{}", err);
+
+        assert_boundary(
+            v0_6_op(v0_6::UserOperationRequiredFields {
+                verification_gas_limit: 1_000_000,
+                ..v0_6_fields()
+            }),
+            v0_6_op(v0_6::UserOperationRequiredFields {
+                verification_gas_limit: 1_000_001,
+                ..v0_6_fields()
+            }),
+            "verificationGasLimit of 1000001 exceeds the maximum of 1000000",
+        );
+        // v0.7 counts the paymaster verification gas too
+        assert_boundary(
+            v0_7_op(|op| op.paymaster(Address::repeat_byte(0xbb), 900_000, 0, Bytes::new())),
+            v0_7_op(|op| op.paymaster(Address::repeat_byte(0xbb), 900_001, 0, Bytes::new())),
+            "paymasterVerificationGasLimit",
+        );
+        assert_boundary(
+            v0_6_op(v0_6::UserOperationRequiredFields {
+                max_fee_per_gas: 1_000_000_000_000,
+                ..v0_6_fields()
+            }),
+            v0_6_op(v0_6::UserOperationRequiredFields {
+                max_fee_per_gas: 1_000_000_000_001,
+                ..v0_6_fields()
+            }),
+            "maxFeePerGas",
+        );
+    }
+
+    async fn serve() -> std::net::SocketAddr {
+        let config = GatewayConfig {
+            validation: limits(),
+            ..Default::default()
+        };
+        let app = PaymasterGateway::new(config, None).app().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    /// POST `body` and return the status code and response body
+    async fn post(addr: std::net::SocketAddr, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response
+            .split_once("\r\n\r\n")
+            .map(|(_, body)| body.to_string())
+            .unwrap_or_default();
+        (status, body)
+    }
+
+    /// eth_chainId request padded to exactly `len` bytes
+    fn padded_request(len: usize) -> String {
+        let request =
+            json!({"jsonrpc": "2.0", "id": 1, "method": "eth_chainId", "pad": ""}).to_string();
+        let padded = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_chainId",
+            "pad": "x".repeat(len - request.len()),
+        })
+        .to_string();
+        assert_eq!(padded.len(), len);
+        padded
+    }
+
+    #[tokio::test]
+    async fn test_request_body_limit() {
+        let addr = serve().await;
+
+        let (status, _) = post(addr, &padded_request(1024)).await;
+        assert_eq!(status, 200);
+
+        let (status, body) = post(addr, &padded_request(1025)).await;
+        assert_eq!(status, 413);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error"]["code"], -32600);
+        assert_eq!(
+            body["error"]["message"],
+            "Request body exceeds the maximum of 1024 bytes"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_size_limit() {
+        let addr = serve().await;
+        let call = |id: u64| json!({"jsonrpc": "2.0", "id": id, "method": "eth_chainId"});
+
+        let (status, body) = post(addr, &json!([call(1), call(2)]).to_string()).await;
+        assert_eq!(status, 200);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        let responses = body.as_array().unwrap();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[1]["id"], 2);
+
+        let (_, body) = post(addr, &json!([call(1), call(2), call(3)]).to_string()).await;
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error"]["code"], -32600);
+        assert_eq!(
+            body["error"]["message"],
+            "Batch of 3 requests exceeds the maximum of 2"
+        );
+    }
 }
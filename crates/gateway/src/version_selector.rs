@@ -0,0 +1,454 @@
+use std::collections::HashMap;
+
+use alloy_primitives::{Address, Bytes};
+use rundler_provider::{EvmProvider, TransactionBuilder, TransactionRequest};
+use rundler_types::{chain::ChainSpec, EntryPointVersion};
+use serde_json::Value;
+
+use crate::error::{GatewayError, GatewayResult};
+
+/// `supportsInterface(bytes4)` selector of ERC-165
+const SUPPORTS_INTERFACE_SELECTOR: [u8; 4] = [0x01, 0xff, 0xc9, 0xa7];
+
+/// Interface id of ERC-165 itself, which every ERC-165 contract supports
+const ERC165_INTERFACE_ID: [u8; 4] = [0x01, 0xff, 0xc9, 0xa7];
+
+/// `eip712Domain()` selector of ERC-5267, implemented from EntryPoint v0.8 on
+#[cfg(feature = "entrypoint-v0_8")]
+const EIP712_DOMAIN_SELECTOR: [u8; 4] = [0x84, 0xb0, 0x19, 0x6e];
+
+/// Length of the head words of an encoded `eip712Domain()` result
+#[cfg(feature = "entrypoint-v0_8")]
+const EIP712_DOMAIN_HEAD_LEN: usize = 7 * 32;
+
+/// Fields only a v0.6 UserOperation has
+const V0_6_FIELDS: &[&str] = &["initCode", "paymasterAndData"];
+
+/// Fields only a v0.7 UserOperation has
+const V0_7_FIELDS: &[&str] = &[
+    "factory",
+    "factoryData",
+    "paymaster",
+    "paymasterVerificationGasLimit",
+    "paymasterPostOpGasLimit",
+    "paymasterData",
+];
+
+/// What determined the version of a [`VersionSelection`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectionMethod {
+    /// The EntryPoint's declared version; the operation had no version specific fields
+    Address,
+    /// The operation's fields; the EntryPoint has no declared version
+    Shape,
+    /// The operation's fields agreed with the EntryPoint's declared version
+    Both,
+}
+
+/// EntryPoint version an operation is parsed for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionSelection {
+    /// Version to parse the operation as
+    pub version: EntryPointVersion,
+    /// How the version was determined
+    pub method: DetectionMethod,
+}
+
+/// Picks the EntryPoint version of a JSON UserOperation
+///
+/// The operation's shape (`initCode`/`paymasterAndData` for v0.6, split
+/// factory and paymaster fields for v0.7) is cross-checked against the
+/// declared version of the target EntryPoint, so an operation is never
+/// parsed for a version its EntryPoint does not run.
+#[derive(Debug, Clone, Default)]
+pub struct VersionSelector {
+    entry_points: HashMap<Address, EntryPointVersion>,
+}
+
+impl VersionSelector {
+    /// Selector declaring the EntryPoints of `chain_spec`
+    pub fn from_chain_spec(chain_spec: &ChainSpec) -> Self {
+        let selector = Self::default()
+            .with_entry_point(chain_spec.entry_point_address_v0_6, EntryPointVersion::V0_6)
+            .with_entry_point(chain_spec.entry_point_address_v0_7, EntryPointVersion::V0_7);
+        #[cfg(feature = "entrypoint-v0_8")]
+        let selector =
+            selector.with_entry_point(chain_spec.entry_point_address_v0_8, EntryPointVersion::V0_8);
+        selector
+    }
+
+    /// Declare the version of the EntryPoint at `entry_point`
+    pub fn with_entry_point(mut self, entry_point: Address, version: EntryPointVersion) -> Self {
+        self.entry_points.insert(entry_point, version);
+        self
+    }
+
+    /// Declared version of `entry_point`, if known
+    pub fn declared_version(&self, entry_point: Address) -> Option<EntryPointVersion> {
+        self.entry_points.get(&entry_point).copied()
+    }
+
+    /// Version of `user_op` for `entry_point`
+    ///
+    /// Fails when the operation mixes v0.6 and v0.7 fields, when its shape
+    /// disagrees with the EntryPoint's declared version, or when neither
+    /// determines a version.
+    pub fn select(&self, user_op: &Value, entry_point: Address) -> GatewayResult<VersionSelection> {
+        let shape = Self::shape_version(user_op)?;
+        let declared = self.declared_version(entry_point);
+
+        let (version, method) = match (declared, shape) {
+            (Some(declared), Some(shape)) if declared == shape => (declared, DetectionMethod::Both),
+            // v0.8 operations have the v0.7 fields
+            #[cfg(feature = "entrypoint-v0_8")]
+            (Some(EntryPointVersion::V0_8), Some(EntryPointVersion::V0_7)) => {
+                (EntryPointVersion::V0_8, DetectionMethod::Both)
+            }
+            (Some(declared), Some(shape)) => {
+                return Err(GatewayError::InvalidRequest(format!(
+                    "UserOperation has {} fields but EntryPoint {:#x} is {}",
+                    version_name(shape),
+                    entry_point,
+                    version_name(declared)
+                )))
+            }
+            (Some(declared), None) => (declared, DetectionMethod::Address),
+            (None, Some(shape)) => (shape, DetectionMethod::Shape),
+            (None, None) => {
+                return Err(GatewayError::InvalidRequest(format!(
+                    "Cannot determine the version of EntryPoint {:#x} and the UserOperation \
+                     has no v0.6 or v0.7 specific fields",
+                    entry_point
+                )))
+            }
+        };
+        Ok(VersionSelection { version, method })
+    }
+
+    /// Version implied by the fields of `user_op`, `None` when it has no
+    /// version specific fields; `null` fields count as absent
+    pub fn shape_version(user_op: &Value) -> GatewayResult<Option<EntryPointVersion>> {
+        let user_op = user_op.as_object().ok_or_else(|| {
+            GatewayError::InvalidRequest("UserOperation must be an object".to_string())
+        })?;
+        let first_present = |fields: &[&'static str]| {
+            fields
+                .iter()
+                .copied()
+                .find(|field| user_op.get(*field).is_some_and(|value| !value.is_null()))
+        };
+
+        match (first_present(V0_6_FIELDS), first_present(V0_7_FIELDS)) {
+            (Some(v0_6), Some(v0_7)) => Err(GatewayError::InvalidRequest(format!(
+                "UserOperation mixes v0.6 ({}) and v0.7 ({}) fields",
+                v0_6, v0_7
+            ))),
+            (Some(_), None) => Ok(Some(EntryPointVersion::V0_6)),
+            (None, Some(_)) => Ok(Some(EntryPointVersion::V0_7)),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+fn version_name(version: EntryPointVersion) -> &'static str {
+    match version {
+        EntryPointVersion::V0_6 => "v0.6",
+        EntryPointVersion::V0_7 => "v0.7",
+        #[cfg(feature = "entrypoint-v0_8")]
+        EntryPointVersion::V0_8 => "v0.8",
+        EntryPointVersion::Unspecified => "unspecified",
+    }
+}
+
+/// Ask the contract at `entry_point` which version it runs
+///
+/// The v0.7 EntryPoint implements ERC-165 while v0.6 has no
+/// `supportsInterface`, so a deployed EntryPoint answering it is v0.7 and one
+/// rejecting the call is v0.6. With the `entrypoint-v0_8` feature, an ERC-165
+/// EntryPoint also answering ERC-5267 `eip712Domain()` is v0.8.
+pub async fn detect_entry_point_version<P: EvmProvider>(
+    provider: &P,
+    entry_point: Address,
+) -> GatewayResult<EntryPointVersion> {
+    let code = provider.get_code(entry_point, None).await.map_err(|e| {
+        GatewayError::RundlerError(format!("Failed to get code of {}: {}", entry_point, e))
+    })?;
+    if code.is_empty() {
+        return Err(GatewayError::RundlerError(format!(
+            "No EntryPoint deployed at {}",
+            entry_point
+        )));
+    }
+
+    let mut data = SUPPORTS_INTERFACE_SELECTOR.to_vec();
+    data.extend_from_slice(&ERC165_INTERFACE_ID);
+    data.extend_from_slice(&[0u8; 28]);
+
+    let tx = TransactionRequest::default()
+        .to(entry_point)
+        .with_input(Bytes::from(data));
+    match provider.call(tx, None, None).await {
+        Ok(result) if result.get(31) == Some(&1) => {}
+        _ => return Ok(EntryPointVersion::V0_6),
+    }
+
+    #[cfg(feature = "entrypoint-v0_8")]
+    {
+        let tx = TransactionRequest::default()
+            .to(entry_point)
+            .with_input(Bytes::copy_from_slice(&EIP712_DOMAIN_SELECTOR));
+        if provider
+            .call(tx, None, None)
+            .await
+            .is_ok_and(|result| result.len() >= EIP712_DOMAIN_HEAD_LEN)
+        {
+            return Ok(EntryPointVersion::V0_8);
+        }
+    }
+    Ok(EntryPointVersion::V0_7)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::Address;
+    use rundler_provider::{MockEvmProvider, ProviderError};
+    use rundler_types::{chain::ChainSpec, EntryPointVersion};
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn provider(code: &'static [u8], supports_erc165: Option<bool>) -> MockEvmProvider {
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_code()
+            .returning(move |_, _| Ok(Bytes::from_static(code)));
+        provider
+            .expect_call()
+            .returning(move |_, _, _| match supports_erc165 {
+                Some(supported) => {
+                    let mut result = [0u8; 32];
+                    result[31] = supported as u8;
+                    Ok(Bytes::from(result.to_vec()))
+                }
+                None => Err(ProviderError::Other(anyhow::anyhow!("execution reverted"))),
+            });
+        provider
+    }
+
+    #[tokio::test]
+    async fn test_detect_entry_point_version() {
+        let entry_point = Address::repeat_byte(0xe7);
+
+        let v0_7 = provider(&[0x60, 0x80], Some(true));
+        assert_eq!(
+            detect_entry_point_version(&v0_7, entry_point)
+                .await
+                .unwrap(),
+            EntryPointVersion::V0_7
+        );
+
+        let v0_6 = provider(&[0x60, 0x80], None);
+        assert_eq!(
+            detect_entry_point_version(&v0_6, entry_point)
+                .await
+                .unwrap(),
+            EntryPointVersion::V0_6
+        );
+
+        let undeployed = provider(&[], Some(true));
+        assert!(detect_entry_point_version(&undeployed, entry_point)
+            .await
+            .is_err());
+    }
+
+    #[cfg(feature = "entrypoint-v0_8")]
+    #[tokio::test]
+    async fn test_detect_entry_point_v0_8() {
+        let mut v0_8 = MockEvmProvider::new();
+        v0_8.expect_get_code()
+            .returning(|_, _| Ok(Bytes::from_static(&[0x60, 0x80])));
+        v0_8.expect_call().returning(|tx, _, _| {
+            if tx
+                .input
+                .input()
+                .is_some_and(|input| input[..] == EIP712_DOMAIN_SELECTOR)
+            {
+                return Ok(Bytes::from(vec![0u8; EIP712_DOMAIN_HEAD_LEN]));
+            }
+            let mut result = [0u8; 32];
+            result[31] = 1;
+            Ok(Bytes::from(result.to_vec()))
+        });
+        assert_eq!(
+            detect_entry_point_version(&v0_8, Address::repeat_byte(0xe8))
+                .await
+                .unwrap(),
+            EntryPointVersion::V0_8
+        );
+    }
+
+    fn selector() -> VersionSelector {
+        VersionSelector::from_chain_spec(&ChainSpec::default())
+    }
+
+    fn entry_point_v06() -> Address {
+        ChainSpec::default().entry_point_address_v0_6
+    }
+
+    fn entry_point_v07() -> Address {
+        ChainSpec::default().entry_point_address_v0_7
+    }
+
+    /// Fields shared by both versions
+    fn common_op() -> Value {
+        json!({
+            "sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "nonce": "0x1",
+            "callData": "0x",
+            "callGasLimit": "0x186a0",
+            "verificationGasLimit": "0x186a0",
+            "preVerificationGas": "0x5208",
+            "maxFeePerGas": "0x3b9aca00",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "signature": "0x"
+        })
+    }
+
+    fn v06_op() -> Value {
+        let mut op = common_op();
+        op["initCode"] = json!("0x");
+        op["paymasterAndData"] = json!("0x");
+        op
+    }
+
+    fn v07_op() -> Value {
+        let mut op = common_op();
+        op["factory"] = json!("0x9406Cc6185a346906296840746125a0E44976454");
+        op["factoryData"] = json!("0x1234");
+        op
+    }
+
+    fn assert_invalid(result: Result<impl std::fmt::Debug, GatewayError>, expected: &str) {
+        match result {
+            Err(GatewayError::InvalidRequest(message)) => {
+                assert!(message.contains(expected), "{}", message)
+            }
+            other => panic!("expected InvalidRequest, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_shape_agrees_with_entry_point() {
+        let selection = selector().select(&v06_op(), entry_point_v06()).unwrap();
+        assert_eq!(selection.version, EntryPointVersion::V0_6);
+        assert_eq!(selection.method, DetectionMethod::Both);
+
+        let selection = selector().select(&v07_op(), entry_point_v07()).unwrap();
+        assert_eq!(selection.version, EntryPointVersion::V0_7);
+        assert_eq!(selection.method, DetectionMethod::Both);
+    }
+
+    #[test]
+    fn test_ambiguous_op_uses_entry_point() {
+        // A v0.7 op without factory or paymaster has no version specific fields
+        for (entry_point, version) in [
+            (entry_point_v06(), EntryPointVersion::V0_6),
+            (entry_point_v07(), EntryPointVersion::V0_7),
+        ] {
+            let selection = selector().select(&common_op(), entry_point).unwrap();
+            assert_eq!(selection.version, version);
+            assert_eq!(selection.method, DetectionMethod::Address);
+        }
+
+        // Null fields count as absent
+        let mut op = common_op();
+        op["paymaster"] = Value::Null;
+        op["initCode"] = Value::Null;
+        let selection = selector().select(&op, entry_point_v07()).unwrap();
+        assert_eq!(selection.method, DetectionMethod::Address);
+    }
+
+    #[test]
+    fn test_ambiguous_op_for_unknown_entry_point_rejected() {
+        let unknown = Address::repeat_byte(0x42);
+        assert_invalid(
+            selector().select(&common_op(), unknown),
+            "Cannot determine the version",
+        );
+    }
+
+    #[test]
+    fn test_unknown_entry_point_uses_shape() {
+        let unknown = Address::repeat_byte(0x42);
+
+        let selection = selector().select(&v06_op(), unknown).unwrap();
+        assert_eq!(selection.version, EntryPointVersion::V0_6);
+        assert_eq!(selection.method, DetectionMethod::Shape);
+
+        let selection = selector().select(&v07_op(), unknown).unwrap();
+        assert_eq!(selection.version, EntryPointVersion::V0_7);
+        assert_eq!(selection.method, DetectionMethod::Shape);
+    }
+
+    #[test]
+    fn test_shape_and_entry_point_mismatch_rejected() {
+        assert_invalid(
+            selector().select(&v06_op(), entry_point_v07()),
+            "UserOperation has v0.6 fields but EntryPoint",
+        );
+        assert_invalid(
+            selector().select(&v07_op(), entry_point_v06()),
+            "UserOperation has v0.7 fields but EntryPoint",
+        );
+    }
+
+    #[test]
+    fn test_mixed_fields_rejected() {
+        let mut op = v06_op();
+        op["paymasterData"] = json!("0x");
+        assert_invalid(
+            selector().select(&op, entry_point_v06()),
+            "mixes v0.6 (initCode) and v0.7 (paymasterData) fields",
+        );
+
+        assert_invalid(
+            selector().select(&json!("0x1234"), entry_point_v06()),
+            "must be an object",
+        );
+    }
+
+    #[test]
+    fn test_non_canonical_entry_point_declared() {
+        // A chain with v0.7 deployed away from the canonical address
+        let custom = Address::repeat_byte(0x77);
+        let selector = selector().with_entry_point(custom, EntryPointVersion::V0_7);
+        assert_eq!(
+            selector.declared_version(custom),
+            Some(EntryPointVersion::V0_7)
+        );
+
+        let selection = selector.select(&common_op(), custom).unwrap();
+        assert_eq!(selection.version, EntryPointVersion::V0_7);
+        assert_eq!(selection.method, DetectionMethod::Address);
+        assert_invalid(selector.select(&v06_op(), custom), "is v0.7");
+    }
+
+    #[cfg(feature = "entrypoint-v0_8")]
+    #[test]
+    fn test_v0_8_entry_point_takes_v0_7_shape() {
+        let entry_point_v08 = ChainSpec::default().entry_point_address_v0_8;
+
+        let selection = selector().select(&v07_op(), entry_point_v08).unwrap();
+        assert_eq!(selection.version, EntryPointVersion::V0_8);
+        assert_eq!(selection.method, DetectionMethod::Both);
+
+        let selection = selector().select(&common_op(), entry_point_v08).unwrap();
+        assert_eq!(selection.version, EntryPointVersion::V0_8);
+        assert_eq!(selection.method, DetectionMethod::Address);
+
+        assert_invalid(
+            selector().select(&v06_op(), entry_point_v08),
+            "has v0.6 fields but EntryPoint",
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Signed admin requests as an alternative to admin API keys
+
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::Address;
+use async_trait::async_trait;
+use axum::http::{HeaderMap, HeaderValue};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    utils::hash_message,
+};
+use rundler_paymaster_relay::{BlockTimestampSource, ClockGuard, ClockSkewConfig, PaymasterError};
+use serde_json::{json, Value};
+use super_relay_gateway::{
+    middleware::{
+        admin_signing_message, canonical_json, ADMIN_NONCE_HEADER, ADMIN_SIGNATURE_HEADER,
+        ADMIN_TIMESTAMP_HEADER,
+    },
+    AdminAuthConfig, ApiKeyConfig, AuthMiddleware, GatewayError, GatewayRouter,
+};
+
+mod common;
+
+const ADMIN_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+const OTHER_KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+fn wallet(key: &str) -> LocalWallet {
+    key.parse().unwrap()
+}
+
+fn create_auth() -> AuthMiddleware {
+    AuthMiddleware::with_config(ApiKeyConfig::default()).with_admin_auth(AdminAuthConfig {
+        enabled: true,
+        admin_addresses: vec![Address::from_slice(wallet(ADMIN_KEY).address().as_bytes())],
+        max_age_seconds: 300,
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn body() -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "pm_rotatePaymasterKey",
+        "params": [{"source": "env", "name": "NEW_KEY"}],
+    })
+}
+
+/// Headers of `body` signed with `key` at `timestamp`
+fn signed_headers(key: &str, body: &Value, timestamp: u64, nonce: &str) -> HeaderMap {
+    let message = admin_signing_message(body, timestamp, nonce);
+    let signature = wallet(key).sign_hash(hash_message(message)).unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        ADMIN_SIGNATURE_HEADER,
+        HeaderValue::from_str(&format!("0x{}", signature)).unwrap(),
+    );
+    headers.insert(ADMIN_TIMESTAMP_HEADER, HeaderValue::from(timestamp));
+    headers.insert(ADMIN_NONCE_HEADER, HeaderValue::from_str(nonce).unwrap());
+    headers
+}
+
+#[test]
+fn test_valid_signature_accepted() {
+    let auth = create_auth();
+    let headers = signed_headers(ADMIN_KEY, &body(), now(), "nonce-1");
+
+    let identity = auth
+        .authorize_request(&headers, &body(), "pm_rotatePaymasterKey")
+        .unwrap();
+    assert_eq!(
+        identity,
+        Some(format!(
+            "admin:{}",
+            Address::from_slice(wallet(ADMIN_KEY).address().as_bytes())
+        ))
+    );
+}
+
+#[test]
+fn test_signature_covers_canonical_body() {
+    let auth = create_auth();
+    assert_eq!(
+        canonical_json(&json!({"b": [1, {"d": 2, "c": 3}], "a": "x"})),
+        r#"{"a":"x","b":[1,{"c":3,"d":2}]}"#
+    );
+
+    let mut tampered = body();
+    tampered["params"][0]["name"] = json!("ATTACKER_KEY");
+    let headers = signed_headers(ADMIN_KEY, &body(), now(), "nonce-1");
+    assert!(matches!(
+        auth.authorize_request(&headers, &tampered, "pm_rotatePaymasterKey"),
+        Err(GatewayError::AuthenticationFailed(_))
+    ));
+}
+
+#[test]
+fn test_expired_timestamp_rejected() {
+    let auth = create_auth();
+    let headers = signed_headers(ADMIN_KEY, &body(), now() - 301, "nonce-1");
+
+    let err = auth
+        .authorize_request(&headers, &body(), "pm_rotatePaymasterKey")
+        .unwrap_err();
+    assert!(err.to_string().contains("expired"), "{}", err);
+}
+
+#[test]
+fn test_reused_nonce_rejected() {
+    let auth = create_auth();
+    let headers = signed_headers(ADMIN_KEY, &body(), now(), "nonce-1");
+
+    assert!(auth
+        .authorize_request(&headers, &body(), "pm_rotatePaymasterKey")
+        .is_ok());
+    let err = auth
+        .authorize_request(&headers, &body(), "pm_rotatePaymasterKey")
+        .unwrap_err();
+    assert!(err.to_string().contains("nonce"), "{}", err);
+}
+
+#[test]
+fn test_wrong_key_rejected() {
+    let auth = create_auth();
+    let headers = signed_headers(OTHER_KEY, &body(), now(), "nonce-1");
+
+    let err = auth
+        .authorize_request(&headers, &body(), "pm_rotatePaymasterKey")
+        .unwrap_err();
+    assert!(err.to_string().contains("admin key"), "{}", err);
+}
+
+#[test]
+fn test_admin_methods_require_signature_when_enabled() {
+    let auth = create_auth();
+
+    // API keys are disabled, yet admin methods are no longer open
+    assert!(auth
+        .authorize_request(&HeaderMap::new(), &body(), "pm_rotatePaymasterKey")
+        .is_err());
+    // Other methods are unaffected
+    assert!(auth
+        .authorize_request(&HeaderMap::new(), &json!({}), "eth_chainId")
+        .is_ok());
+}
+
+/// Chain running `offset` seconds off the local clock
+struct SkewedChain {
+    offset: i64,
+}
+
+#[async_trait]
+impl BlockTimestampSource for SkewedChain {
+    async fn latest_block(&self) -> Result<(u64, u64), PaymasterError> {
+        Ok((1, (now() as i64 + self.offset) as u64))
+    }
+}
+
+#[tokio::test]
+async fn test_timestamp_checked_against_corrected_clock() {
+    // The admin's clock follows the chain, ours is 400s behind both
+    let guard = Arc::new(ClockGuard::new(
+        Arc::new(SkewedChain { offset: 400 }),
+        ClockSkewConfig {
+            enabled: true,
+            refuse_seconds: 600,
+            ..Default::default()
+        },
+    ));
+    guard.measure().await.unwrap();
+    let headers = signed_headers(ADMIN_KEY, &body(), now() + 400, "nonce-1");
+
+    let err = create_auth()
+        .authorize_request(&headers, &body(), "pm_rotatePaymasterKey")
+        .unwrap_err();
+    assert!(
+        matches!(err, GatewayError::AuthenticationFailed(_)),
+        "{}",
+        err
+    );
+    assert!(create_auth()
+        .with_clock_guard(guard)
+        .authorize_request(&headers, &body(), "pm_rotatePaymasterKey")
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_key_rotation_needs_authenticated_admin() {
+    std::env::set_var("ADMIN_AUTH_TEST_ROTATION_KEY", OTHER_KEY);
+    let service = Arc::new(
+        common::service("senders = []\n")
+            .with_key_rotation_env_vars(vec!["ADMIN_AUTH_TEST_ROTATION_KEY".to_string()]),
+    );
+    let router = GatewayRouter::new();
+    let mut request = common::request(
+        "pm_rotatePaymasterKey",
+        vec![json!({ "env": "ADMIN_AUTH_TEST_ROTATION_KEY" })],
+    );
+
+    let err = router
+        .route_to_paymaster(&service, &request)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, GatewayError::AuthenticationFailed(_)),
+        "{:?}",
+        err
+    );
+
+    // Only allowlisted environment variables
+    request.api_key_id = Some("admin".to_string());
+    request.params = vec![json!({ "env": "PATH" })];
+    let err = router
+        .route_to_paymaster(&service, &request)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, GatewayError::InvalidRequest(_)), "{:?}", err);
+    assert_eq!(
+        service.signer_status().await.active_signer,
+        Address::from_slice(wallet(common::SIGNER_KEY).address().as_bytes())
+    );
+
+    request.params = vec![json!({ "env": "ADMIN_AUTH_TEST_ROTATION_KEY" })];
+    let rotation = router.route_to_paymaster(&service, &request).await.unwrap();
+    assert_eq!(
+        rotation["activeSigner"].as_str().unwrap().to_lowercase(),
+        format!("{:?}", wallet(OTHER_KEY).address())
+    );
+}
@@ -0,0 +1,138 @@
+//! pm_sponsorUserOperation for UserOperations using a signature aggregator
+
+use std::sync::Arc;
+
+use alloy_primitives::{bytes, Address, Bytes};
+use rundler_paymaster_relay::service::PaymasterRelayService;
+use rundler_types::{
+    aggregator::{
+        AggregatorCosts, MockSignatureAggregator, SignatureAggregator, SignatureAggregatorError,
+    },
+    chain::ContractRegistry,
+};
+use serde_json::{json, Value};
+use super_relay_gateway::{gateway::JsonRpcRequest, GatewayError, GatewayRouter};
+
+mod common;
+
+const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+const ENTRY_POINT_V07: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+const AGGREGATOR: Address = Address::repeat_byte(0xa9);
+
+const COSTS: AggregatorCosts = AggregatorCosts {
+    execution_fixed_gas: 100_000,
+    execution_variable_gas: 5_000,
+    sig_fixed_length: 96,
+    sig_variable_length: 0,
+};
+
+fn create_service() -> Arc<PaymasterRelayService> {
+    Arc::new(common::service(&format!("senders = [\"{}\"]\n", SENDER)))
+}
+
+/// Aggregator accepting exactly `valid_signature`
+fn mock_aggregator(valid_signature: Bytes) -> MockSignatureAggregator {
+    let mut aggregator = MockSignatureAggregator::new();
+    aggregator.expect_address().return_const(AGGREGATOR);
+    aggregator.expect_costs().return_const(COSTS);
+    aggregator
+        .expect_dummy_uo_signature()
+        .return_const(Bytes::from(vec![0xde; 96]));
+    aggregator
+        .expect_validate_user_op_signature()
+        .returning(move |user_op| {
+            if *user_op.signature() == valid_signature {
+                Ok(Bytes::new())
+            } else {
+                Err(SignatureAggregatorError::ValidationReverted(Bytes::new()))
+            }
+        });
+    aggregator
+}
+
+fn router_with(aggregator: MockSignatureAggregator) -> GatewayRouter {
+    let mut registry: ContractRegistry<Arc<dyn SignatureAggregator>> = ContractRegistry::default();
+    registry.register(AGGREGATOR, Arc::new(aggregator));
+    GatewayRouter::new().with_signature_aggregators(Arc::new(registry))
+}
+
+fn sponsor_request(aggregator: Address, signature: &str) -> JsonRpcRequest {
+    common::request(
+        "pm_sponsorUserOperation",
+        vec![
+            json!({
+                "sender": SENDER,
+                "nonce": "0x1",
+                "callData": "0x",
+                "callGasLimit": "0x186a0",
+                "verificationGasLimit": "0x186a0",
+                "preVerificationGas": "0x5208",
+                "maxFeePerGas": "0x3b9aca00",
+                "maxPriorityFeePerGas": "0x3b9aca00",
+                "signature": signature,
+                "aggregator": format!("{:#x}", aggregator),
+            }),
+            json!(ENTRY_POINT_V07),
+            json!({ "returnFullOperation": true }),
+        ],
+    )
+}
+
+#[tokio::test]
+async fn test_enabled_aggregator_sponsored() {
+    let signature = bytes!("1234");
+    let router = router_with(mock_aggregator(signature.clone()));
+
+    let result: Value = router
+        .route_to_paymaster(&create_service(), &sponsor_request(AGGREGATOR, "0x1234"))
+        .await
+        .unwrap();
+
+    let user_op = &result["userOperation"];
+    assert_eq!(user_op["aggregator"], json!(format!("{:#x}", AGGREGATOR)));
+    // The returned operation keeps the user's signature, not the aggregator's
+    assert_eq!(user_op["signature"], json!("0x1234"));
+    assert!(result["paymasterAndData"].as_str().unwrap().len() > 2);
+}
+
+#[tokio::test]
+async fn test_unsigned_aggregated_operation_sponsored() {
+    let router = router_with(mock_aggregator(bytes!("1234")));
+
+    let result = router
+        .route_to_paymaster(&create_service(), &sponsor_request(AGGREGATOR, "0x"))
+        .await
+        .unwrap();
+    assert_eq!(
+        result["userOperation"]["aggregator"],
+        json!(format!("{:#x}", AGGREGATOR))
+    );
+}
+
+#[tokio::test]
+async fn test_unknown_aggregator_rejected() {
+    let router = router_with(mock_aggregator(bytes!("1234")));
+    let unknown = Address::repeat_byte(0x01);
+
+    let err = router
+        .route_to_paymaster(&create_service(), &sponsor_request(unknown, "0x1234"))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, GatewayError::UnsupportedAggregator(a) if a == unknown));
+    assert_eq!(err.code(), -32506);
+}
+
+#[tokio::test]
+async fn test_aggregator_rejected_signature() {
+    let router = router_with(mock_aggregator(bytes!("1234")));
+
+    let err = router
+        .route_to_paymaster(&create_service(), &sponsor_request(AGGREGATOR, "0xbeef"))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, GatewayError::ValidationError(m) if m.contains("rejected the signature")),
+        "{:?}",
+        err
+    );
+}
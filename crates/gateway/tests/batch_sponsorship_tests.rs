@@ -0,0 +1,398 @@
+//! pm_sponsorUserOperationBatch: results in request order, atomic and
+//! best-effort handling of failing operations, quotas checked and reserved
+//! for the batch as a whole, concurrent signing across senders, and the
+//! batch size cap
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use alloy_primitives::B256;
+use async_trait::async_trait;
+use rundler_paymaster_relay::{fees::FeeChecker, service::PaymasterRelayService, PaymasterError};
+use rundler_provider::FeeEstimator;
+use rundler_types::GasFees;
+use serde_json::{json, Value};
+use super_relay_gateway::{
+    gateway::JsonRpcRequest, BatchSponsorshipConfig, BatchSponsorshipMode, GatewayError,
+    GatewayRouter,
+};
+
+mod common;
+
+const ALICE: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+const BOB: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+const CAROL: &str = "0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC";
+const DAVE: &str = "0x90F79bf6EB2c4f870365E785982E1f101E93b906";
+const FACTORY: &str = "0x9406Cc6185a346906296840746125a0E44976454";
+const ENTRY_POINT_V07: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+
+/// Fee estimator requiring 1 gwei, answering after `delay`
+struct SlowFeeEstimator {
+    delay: Duration,
+}
+
+#[async_trait]
+impl FeeEstimator for SlowFeeEstimator {
+    async fn required_bundle_fees(
+        &self,
+        _block_hash: B256,
+        _min_fees: Option<GasFees>,
+    ) -> anyhow::Result<(GasFees, u128)> {
+        self.latest_bundle_fees().await
+    }
+
+    async fn latest_bundle_fees(&self) -> anyhow::Result<(GasFees, u128)> {
+        tokio::time::sleep(self.delay).await;
+        let fees = GasFees {
+            max_fee_per_gas: 1_000_000_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+        };
+        Ok((fees, fees.max_fee_per_gas))
+    }
+
+    fn required_op_fees(&self, bundle_fees: GasFees) -> GasFees {
+        bundle_fees
+    }
+}
+
+/// Service whose default policy allows every test sender with the extra `rules`
+fn create_service(rules: &str) -> Arc<PaymasterRelayService> {
+    Arc::new(create_service_with_fees(rules, Duration::ZERO))
+}
+
+/// [`create_service`] checking fees with a [`SlowFeeEstimator`] of `delay`
+fn create_service_with_fees(rules: &str, delay: Duration) -> PaymasterRelayService {
+    common::service(&format!(
+        "senders = [\"{}\", \"{}\", \"{}\", \"{}\"]\n{}",
+        ALICE, BOB, CAROL, DAVE, rules
+    ))
+    .with_fee_checker(FeeChecker::new(Arc::new(SlowFeeEstimator { delay }), false))
+}
+
+fn op(sender: &str, nonce: u64) -> Value {
+    json!({
+        "sender": sender,
+        "nonce": format!("{:#x}", nonce),
+        "callData": "0x",
+        "callGasLimit": "0x186a0",
+        "verificationGasLimit": "0x186a0",
+        "preVerificationGas": "0x5208",
+        "maxFeePerGas": "0x3b9aca00",
+        "maxPriorityFeePerGas": "0x3b9aca00",
+        "signature": "0x",
+    })
+}
+
+/// Operation deploying its account through FACTORY
+fn deploying_op(sender: &str, nonce: u64) -> Value {
+    let mut op = op(sender, nonce);
+    op["factory"] = json!(FACTORY);
+    op["factoryData"] = json!("0x5fbfb9cf");
+    op
+}
+
+/// Operation paying less than the required fees, failing when signed
+fn underpriced_op(sender: &str, nonce: u64) -> Value {
+    let mut op = op(sender, nonce);
+    op["maxFeePerGas"] = json!("0x3b9ac9ff");
+    op
+}
+
+fn unparseable_op() -> Value {
+    let mut op = op(ALICE, 9);
+    op["sender"] = json!("not-an-address");
+    op
+}
+
+fn batch_request(ops: Vec<Value>, options: Value) -> JsonRpcRequest {
+    common::request(
+        "pm_sponsorUserOperationBatch",
+        vec![json!(ops), json!(ENTRY_POINT_V07), options],
+    )
+}
+
+async fn sponsor_batch(
+    router: &GatewayRouter,
+    service: &Arc<PaymasterRelayService>,
+    ops: Vec<Value>,
+    options: Value,
+) -> Result<Vec<Value>, GatewayError> {
+    router
+        .route_to_paymaster(service, &batch_request(ops, options))
+        .await
+        .map(|result| result.as_array().unwrap().clone())
+}
+
+#[tokio::test]
+async fn test_results_follow_request_order() {
+    let service = create_service("");
+    let ops = vec![op(BOB, 3), op(ALICE, 1), op(ALICE, 2)];
+    let results = sponsor_batch(
+        &GatewayRouter::new(),
+        &service,
+        ops.clone(),
+        json!({ "returnFullOperation": true }),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(results.len(), ops.len());
+    for (index, (entry, op)) in results.iter().zip(&ops).enumerate() {
+        assert_eq!(entry["index"], index, "{}", entry);
+        assert!(entry.get("error").is_none(), "{}", entry);
+        let sponsored = &entry["result"]["userOperation"];
+        assert_eq!(
+            sponsored["sender"].as_str().unwrap().to_lowercase(),
+            op["sender"].as_str().unwrap().to_lowercase()
+        );
+        assert_eq!(sponsored["nonce"], op["nonce"]);
+        assert!(entry["result"]["paymasterAndData"]
+            .as_str()
+            .unwrap()
+            .starts_with("0x"));
+    }
+}
+
+#[tokio::test]
+async fn test_atomic_batch_rejected_at_failing_op() {
+    let service = create_service("");
+    let router = GatewayRouter::new();
+    let ops = vec![op(ALICE, 1), unparseable_op(), op(BOB, 1)];
+
+    let err = sponsor_batch(&router, &service, ops.clone(), Value::Null)
+        .await
+        .unwrap_err();
+    match &err {
+        GatewayError::BatchRejected { index, .. } => assert_eq!(*index, 1),
+        other => panic!("expected a batch rejection, got {:?}", other),
+    }
+    assert_eq!(err.rpc_data()["index"], 1);
+
+    // Requested explicitly, overriding a best-effort default
+    let router = router.with_batch_sponsorship(BatchSponsorshipConfig {
+        mode: BatchSponsorshipMode::BestEffort,
+        ..Default::default()
+    });
+    let err = sponsor_batch(&router, &service, ops, json!({ "mode": "atomic" }))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, GatewayError::BatchRejected { index: 1, .. }),
+        "{:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn test_best_effort_signs_the_other_ops() {
+    let service = create_service("");
+    let ops = vec![op(ALICE, 1), unparseable_op(), op(BOB, 1)];
+
+    let results = sponsor_batch(
+        &GatewayRouter::new(),
+        &service,
+        ops,
+        json!({ "mode": "bestEffort" }),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(results.len(), 3);
+    assert!(results[0]["result"]["paymasterAndData"].is_string());
+    assert_eq!(results[1]["index"], 1);
+    assert!(results[1].get("result").is_none());
+    assert!(results[1]["error"]["code"].is_i64(), "{}", results[1]);
+    assert!(results[1]["error"]["message"].is_string());
+    assert_eq!(results[2]["index"], 2);
+    assert!(results[2]["result"]["paymasterAndData"].is_string());
+}
+
+#[tokio::test]
+async fn test_quota_checked_for_whole_batch() {
+    let service = create_service("\n[default.deployments]\nper_day = 2\n");
+    let router = GatewayRouter::new();
+
+    // Each deployment fits the quota alone, the third does not fit after the first two
+    let ops = vec![
+        deploying_op(ALICE, 1),
+        op(BOB, 1),
+        deploying_op(ALICE, 2),
+        deploying_op(BOB, 2),
+    ];
+    for mode in ["atomic", "bestEffort"] {
+        let err = sponsor_batch(&router, &service, ops.clone(), json!({ "mode": mode }))
+            .await
+            .unwrap_err();
+        match &err {
+            GatewayError::BatchRejected { index, error } => {
+                assert_eq!(*index, 3, "{}", mode);
+                assert!(
+                    matches!(
+                        **error,
+                        GatewayError::SponsorshipFailed(
+                            PaymasterError::DeploymentQuotaExceeded { .. }
+                        )
+                    ),
+                    "{:?}",
+                    error
+                );
+            }
+            other => panic!("expected a batch rejection, got {:?}", other),
+        }
+    }
+
+    // The rejected batches used none of the quota
+    let results = sponsor_batch(&router, &service, ops[..3].to_vec(), Value::Null)
+        .await
+        .unwrap();
+    assert!(results.iter().all(|entry| entry.get("result").is_some()));
+    let err = sponsor_batch(&router, &service, vec![deploying_op(BOB, 3)], Value::Null)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, GatewayError::BatchRejected { index: 0, .. }),
+        "{:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn test_signing_failure_rejects_atomic_batch() {
+    let service = create_service("");
+    let router = GatewayRouter::new();
+    let ops = vec![op(ALICE, 1), underpriced_op(BOB, 1), op(ALICE, 2)];
+
+    let err = sponsor_batch(&router, &service, ops.clone(), Value::Null)
+        .await
+        .unwrap_err();
+    match &err {
+        GatewayError::BatchRejected { index, error } => {
+            assert_eq!(*index, 1);
+            assert!(
+                matches!(
+                    **error,
+                    GatewayError::SponsorshipFailed(PaymasterError::FeesTooLow { .. })
+                ),
+                "{:?}",
+                error
+            );
+        }
+        other => panic!("expected a batch rejection, got {:?}", other),
+    }
+
+    // Best effort signs the others around it
+    let results = sponsor_batch(&router, &service, ops, json!({ "mode": "bestEffort" }))
+        .await
+        .unwrap();
+    assert!(results[0]["result"]["paymasterAndData"].is_string());
+    assert!(results[1]["error"]["code"].is_i64(), "{}", results[1]);
+    assert!(results[2]["result"]["paymasterAndData"].is_string());
+}
+
+#[tokio::test]
+async fn test_quota_reserved_until_signed() {
+    let service = create_service("\n[default.deployments]\nper_day = 1\n");
+    let router = GatewayRouter::new();
+
+    // The deployment failing to sign gives its reservation back
+    let mut underpriced = underpriced_op(ALICE, 1);
+    underpriced["factory"] = json!(FACTORY);
+    underpriced["factoryData"] = json!("0x5fbfb9cf");
+    let err = sponsor_batch(
+        &router,
+        &service,
+        vec![op(BOB, 1), underpriced],
+        Value::Null,
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        matches!(err, GatewayError::BatchRejected { index: 1, .. }),
+        "{:?}",
+        err
+    );
+
+    let results = sponsor_batch(&router, &service, vec![deploying_op(ALICE, 1)], Value::Null)
+        .await
+        .unwrap();
+    assert!(results[0].get("result").is_some(), "{}", results[0]);
+
+    // The signed one keeps it
+    let err = sponsor_batch(&router, &service, vec![deploying_op(BOB, 2)], Value::Null)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, GatewayError::BatchRejected { index: 0, .. }),
+        "{:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn test_senders_signed_concurrently() {
+    let delay = Duration::from_millis(300);
+    let service = Arc::new(create_service_with_fees("", delay));
+    let router = GatewayRouter::new();
+    let ops = vec![op(ALICE, 1), op(BOB, 1), op(CAROL, 1), op(DAVE, 1)];
+
+    let started = Instant::now();
+    let results = sponsor_batch(&router, &service, ops, Value::Null)
+        .await
+        .unwrap();
+    let elapsed = started.elapsed();
+
+    assert!(results.iter().all(|entry| entry.get("result").is_some()));
+    // One after the other, the fee checks alone would take 4 * 300ms
+    assert!(elapsed < delay * 3, "{:?}", elapsed);
+}
+
+#[tokio::test]
+async fn test_batch_size_capped() {
+    let service = create_service("");
+    let router = GatewayRouter::new().with_batch_sponsorship(BatchSponsorshipConfig {
+        max_ops: 2,
+        ..Default::default()
+    });
+
+    let err = sponsor_batch(
+        &router,
+        &service,
+        vec![op(ALICE, 1), op(ALICE, 2), op(ALICE, 3)],
+        Value::Null,
+    )
+    .await
+    .unwrap_err();
+    match err {
+        GatewayError::InvalidRequest(message) => {
+            assert!(message.contains("limit of 2"), "{}", message)
+        }
+        other => panic!("expected an invalid request, got {:?}", other),
+    }
+
+    let err = sponsor_batch(&router, &service, vec![], Value::Null)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, GatewayError::InvalidRequest(_)), "{:?}", err);
+
+    let err = sponsor_batch(
+        &router,
+        &service,
+        vec![op(ALICE, 1)],
+        json!({ "mode": "sometimes" }),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, GatewayError::InvalidRequest(_)), "{:?}", err);
+
+    let results = sponsor_batch(
+        &router,
+        &service,
+        vec![op(ALICE, 1), op(ALICE, 2)],
+        Value::Null,
+    )
+    .await
+    .unwrap();
+    assert_eq!(results.len(), 2);
+}
@@ -0,0 +1,48 @@
+//! Fixtures shared by the gateway integration tests
+
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use rundler_paymaster_relay::{
+    policy::PolicyEngine, service::PaymasterRelayService, signer::SignerManager,
+};
+use rundler_pool::LocalPoolBuilder;
+use secrecy::SecretString;
+use serde_json::{json, Value};
+use super_relay_gateway::gateway::JsonRpcRequest;
+use tempfile::tempdir;
+
+/// Paymaster signing key of [`service`]
+pub const SIGNER_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+/// Paymaster service signing with [`SIGNER_KEY`] under the `[default]` policy
+/// `policy`, on an in-memory pool
+pub fn service(policy: &str) -> PaymasterRelayService {
+    let dir = tempdir().unwrap();
+    let policy_path = dir.path().join("policy.toml");
+    std::fs::write(&policy_path, format!("[default]\n{}", policy)).unwrap();
+
+    let signer_manager =
+        SignerManager::new(SecretString::new(SIGNER_KEY.to_string().into())).unwrap();
+    let policy_engine = PolicyEngine::new(&policy_path).unwrap();
+    let pool = Arc::new(LocalPoolBuilder::new(10).get_handle());
+    PaymasterRelayService::new(signer_manager, policy_engine, pool)
+}
+
+/// Anonymous JSON-RPC request for `method` with `params`
+pub fn request(method: &str, params: Vec<Value>) -> JsonRpcRequest {
+    JsonRpcRequest {
+        id: json!(1),
+        method: method.to_string(),
+        params,
+        api_key_id: None,
+        trust_level: None,
+        chain_id: None,
+        no_cache: false,
+        request_id: None,
+        deadline: Default::default(),
+        debug: false,
+        permissions: false,
+    }
+}
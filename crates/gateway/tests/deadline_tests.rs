@@ -0,0 +1,198 @@
+//! Request deadlines: stage timeouts, committed work and per-method overrides
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use rundler_paymaster_relay::usage::{InMemoryUsageStore, UsageStore};
+use serde_json::{json, Value};
+use super_relay_gateway::{
+    error::DEADLINE_EXCEEDED_CODE,
+    pipeline::{ModuleContext, PipelineModuleConfig},
+    Deadline, GatewayConfig, GatewayError, GatewayResult, ModulePipeline, PaymasterGateway,
+    PipelineConfig, SecurityModule,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+mod common;
+
+const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+const ENTRY_POINT_V07: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+
+#[tokio::test]
+async fn test_stage_aborted_at_deadline() {
+    let deadline = Deadline::after(Duration::from_millis(50));
+    let started = Instant::now();
+    let result: GatewayResult<()> = deadline
+        .run("threat_feed", async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok(())
+        })
+        .await;
+
+    assert!(started.elapsed() < Duration::from_secs(1));
+    let err = result.unwrap_err();
+    assert_eq!(err.code(), DEADLINE_EXCEEDED_CODE);
+    assert!(matches!(
+        err,
+        GatewayError::DeadlineExceeded {
+            stage: "threat_feed",
+            timeout_ms: 50
+        }
+    ));
+    assert!(deadline.is_expired());
+    // Later stages fail without starting
+    assert!(deadline.check("signing").is_err());
+}
+
+#[tokio::test]
+async fn test_committed_work_finishes_past_deadline() {
+    let deadline = Deadline::after(Duration::from_millis(20));
+    let work = async {
+        deadline.commit();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        7
+    };
+    assert_eq!(deadline.enforce(work).await.unwrap(), 7);
+
+    let deadline = Deadline::after(Duration::from_millis(20));
+    let result = deadline
+        .enforce(async {
+            deadline.enter("pool_submission");
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        })
+        .await;
+    assert!(matches!(
+        result,
+        Err(GatewayError::DeadlineExceeded {
+            stage: "pool_submission",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_method_timeout_overrides() {
+    let config = GatewayConfig {
+        request_timeout: 5,
+        method_timeouts: HashMap::from([
+            ("eth_estimateUserOperationGas".to_string(), 60),
+            ("debug_bundler_dumpMempool".to_string(), 0),
+        ]),
+        ..Default::default()
+    };
+
+    let remaining = |method: &str| config.deadline_for(method).remaining();
+    assert!(remaining("eth_chainId").unwrap() <= Duration::from_secs(5));
+    assert!(remaining("eth_estimateUserOperationGas").unwrap() > Duration::from_secs(5));
+    assert_eq!(remaining("debug_bundler_dumpMempool"), None);
+    assert!(!Deadline::default().is_expired());
+}
+
+/// Pipeline module stuck on a slow dependency
+struct SlowModule;
+
+#[async_trait]
+impl SecurityModule for SlowModule {
+    fn name(&self) -> &'static str {
+        "slow_check"
+    }
+
+    async fn check(&self, _ctx: &ModuleContext<'_>) -> GatewayResult<()> {
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        Ok(())
+    }
+}
+
+async fn serve(gateway: PaymasterGateway) -> std::net::SocketAddr {
+    let app = gateway.app().unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    addr
+}
+
+async fn sponsor(addr: std::net::SocketAddr) -> Value {
+    let body = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "pm_sponsorUserOperation",
+        "params": [
+            {
+                "sender": SENDER,
+                "nonce": "0x1",
+                "callData": "0x",
+                "callGasLimit": "0x186a0",
+                "verificationGasLimit": "0x186a0",
+                "preVerificationGas": "0x5208",
+                "maxFeePerGas": "0x3b9aca00",
+                "maxPriorityFeePerGas": "0x3b9aca00",
+                "signature": "0x",
+            },
+            ENTRY_POINT_V07,
+        ],
+    })
+    .to_string();
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!(
+        "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    serde_json::from_str(body).unwrap()
+}
+
+#[tokio::test]
+async fn test_slow_stage_times_out_without_charge() {
+    let usage = Arc::new(InMemoryUsageStore::default());
+    let service = Arc::new(
+        common::service(&format!("senders = [\"{}\"]\n", SENDER)).with_usage_store(usage.clone()),
+    );
+    let config = GatewayConfig {
+        request_timeout: 1,
+        ..Default::default()
+    };
+    let pipeline = ModulePipeline::new(
+        &PipelineConfig {
+            modules: vec![PipelineModuleConfig::new("slow_check")],
+            ..Default::default()
+        },
+        vec![Arc::new(SlowModule)],
+    )
+    .unwrap();
+    let addr = serve(
+        PaymasterGateway::new(config.clone(), Some(service.clone()))
+            .with_pipeline(Arc::new(pipeline)),
+    )
+    .await;
+
+    let started = Instant::now();
+    let response = sponsor(addr).await;
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_secs(1), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_millis(1500), "{:?}", elapsed);
+    assert_eq!(response["error"]["code"], json!(DEADLINE_EXCEEDED_CODE));
+    assert_eq!(response["error"]["data"]["stage"], json!("slow_check"));
+    assert_eq!(response["error"]["data"]["timeoutMs"], json!(1000));
+    assert!(usage.api_key_ids().is_empty());
+
+    // The same sponsorship within the deadline is accounted
+    let addr = serve(PaymasterGateway::new(config, Some(service))).await;
+    let response = sponsor(addr).await;
+    assert!(
+        response["result"]["paymasterAndData"].is_string(),
+        "{}",
+        response
+    );
+    assert_eq!(usage.api_key_ids().len(), 1);
+}
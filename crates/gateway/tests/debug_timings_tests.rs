@@ -0,0 +1,135 @@
+//! Opt-in per-stage timings of sponsorships, for API keys with the debug scope
+
+use std::sync::Arc;
+
+use rundler_paymaster_relay::{service::PaymasterRelayService, SponsorStage};
+use serde_json::{json, Value};
+use super_relay_gateway::{
+    gateway::JsonRpcRequest, middleware::hash_api_key, ApiKeyConfig, ApiKeyEntry, ApiKeyScope,
+    AuthMiddleware, GatewayRouter,
+};
+
+mod common;
+
+const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+const ENTRY_POINT_V07: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+
+fn create_service() -> Arc<PaymasterRelayService> {
+    Arc::new(common::service(&format!("senders = [\"{}\"]\n", SENDER)))
+}
+
+fn sponsor_request(options: Value, debug: bool) -> JsonRpcRequest {
+    JsonRpcRequest {
+        api_key_id: Some("dapp".to_string()),
+        debug,
+        ..common::request(
+            "pm_sponsorUserOperation",
+            vec![
+                json!({
+                    "sender": SENDER,
+                    "nonce": "0x1",
+                    "callData": "0x",
+                    "callGasLimit": "0x186a0",
+                    "verificationGasLimit": "0x186a0",
+                    "preVerificationGas": "0x5208",
+                    "maxFeePerGas": "0x3b9aca00",
+                    "maxPriorityFeePerGas": "0x3b9aca00",
+                    "signature": "0x",
+                }),
+                json!(ENTRY_POINT_V07),
+                options,
+            ],
+        )
+    }
+}
+
+#[tokio::test]
+async fn test_timings_list_every_executed_stage() {
+    let service = create_service();
+    let result = GatewayRouter::new()
+        .route_to_paymaster(
+            &service,
+            &sponsor_request(json!({ "debugTimings": true }), true),
+        )
+        .await
+        .unwrap();
+
+    let timings = &result["timings"];
+    let stages: Vec<&str> = timings["stages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|timing| timing["stage"].as_str().unwrap())
+        .collect();
+    // In execution order; the SBT, authorizer, fee and token stages are not configured
+    let executed = [
+        SponsorStage::DataIntegrity,
+        SponsorStage::Authorization,
+        SponsorStage::Security,
+        SponsorStage::AccountSignature,
+        SponsorStage::Aggregator,
+        SponsorStage::PolicyEvaluation,
+        SponsorStage::Signing,
+    ];
+    assert_eq!(
+        stages,
+        executed.map(SponsorStage::as_str).to_vec(),
+        "{}",
+        timings
+    );
+
+    let total = timings["totalMs"].as_f64().unwrap();
+    let sum: f64 = timings["stages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|timing| timing["ms"].as_f64().unwrap())
+        .sum();
+    assert!(sum <= total, "{}", timings);
+}
+
+#[tokio::test]
+async fn test_timings_need_flag_and_debug_scope() {
+    let service = create_service();
+    let router = GatewayRouter::new();
+
+    for (options, debug) in [
+        (json!({ "debugTimings": true }), false),
+        (json!({ "debugTimings": false }), true),
+        (json!({}), true),
+    ] {
+        let result = router
+            .route_to_paymaster(&service, &sponsor_request(options.clone(), debug))
+            .await
+            .unwrap();
+        assert!(result.get("timings").is_none(), "{} {}", options, debug);
+        assert!(result["paymasterAndData"].is_string());
+    }
+}
+
+#[test]
+fn test_debug_scope_is_held_per_key() {
+    let key = |id: &str, scopes: Vec<ApiKeyScope>| ApiKeyEntry {
+        id: id.to_string(),
+        key_hash: hash_api_key(id),
+        scopes,
+        trust_level: None,
+    };
+    let auth = AuthMiddleware::with_config(ApiKeyConfig {
+        enabled: true,
+        allow_anonymous_reads: false,
+        keys: vec![
+            key("partner", vec![ApiKeyScope::Sponsor, ApiKeyScope::Debug]),
+            key("dapp", vec![ApiKeyScope::Sponsor]),
+        ],
+    });
+
+    assert!(auth.has_scope("partner", ApiKeyScope::Debug));
+    assert!(!auth.has_scope("dapp", ApiKeyScope::Debug));
+    assert!(!auth.has_scope("unknown", ApiKeyScope::Debug));
+    // No method requires the debug scope
+    assert_eq!(
+        ApiKeyScope::required_for("pm_sponsorUserOperation"),
+        ApiKeyScope::Sponsor
+    );
+}
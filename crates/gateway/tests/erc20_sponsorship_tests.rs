@@ -0,0 +1,135 @@
+//! pm_sponsorUserOperationERC20 quoting and token paymaster data
+
+use std::sync::Arc;
+
+use alloy_primitives::{address, Address, U256};
+use async_trait::async_trait;
+use rundler_paymaster_relay::{
+    service::PaymasterRelayService,
+    token_paymaster::{OracleAnswer, TokenConfig, TOKEN_PAYMASTER_DATA_LEN},
+    PaymasterError, PriceFeed, PriceOracle, TokenPaymasterConfig, TokenPricing,
+};
+use serde_json::{json, Value};
+use super_relay_gateway::{gateway::JsonRpcRequest, GatewayError, GatewayRouter};
+
+mod common;
+
+const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+const ENTRY_POINT_V07: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+const PNT: Address = address!("00000000000000000000000000000000000000e2");
+
+/// Oracle of a chain without price feeds
+struct NoFeeds;
+
+#[async_trait]
+impl PriceOracle for NoFeeds {
+    async fn latest_answer(&self, oracle: Address) -> Result<OracleAnswer, PaymasterError> {
+        Err(PaymasterError::ChainError(format!("no feed at {}", oracle)))
+    }
+}
+
+fn create_service(token_pricing: Option<TokenPricing>) -> Arc<PaymasterRelayService> {
+    let service = common::service(&format!("senders = [\"{}\"]\n", SENDER));
+    Arc::new(match token_pricing {
+        Some(token_pricing) => service.with_token_pricing(token_pricing),
+        None => service,
+    })
+}
+
+/// Pricing of PNT at a static 1000 PNT per ETH with a 5% markup
+fn static_pricing() -> TokenPricing {
+    let config = TokenPaymasterConfig {
+        enabled: true,
+        markup_bps: 500,
+        tokens: vec![TokenConfig {
+            address: PNT,
+            symbol: "PNT".to_string(),
+            decimals: 18,
+            price_feed: PriceFeed::Static {
+                tokens_per_eth: U256::from(1_000u64) * U256::from(10u64).pow(U256::from(18)),
+            },
+        }],
+        ..Default::default()
+    };
+    TokenPricing::new(Arc::new(NoFeeds), config)
+}
+
+fn erc20_request(token: Value) -> JsonRpcRequest {
+    common::request(
+        "pm_sponsorUserOperationERC20",
+        vec![
+            json!({
+                "sender": SENDER,
+                "nonce": "0x1",
+                "callData": "0x",
+                "callGasLimit": "0x186a0",
+                "verificationGasLimit": "0x186a0",
+                "preVerificationGas": "0x5208",
+                "maxFeePerGas": "0x3b9aca00",
+                "maxPriorityFeePerGas": "0x3b9aca00",
+                "signature": "0x",
+            }),
+            json!(ENTRY_POINT_V07),
+            token,
+            json!({ "returnFullOperation": true }),
+        ],
+    )
+}
+
+#[tokio::test]
+async fn test_erc20_sponsorship_returns_quote() {
+    let service = create_service(Some(static_pricing()));
+    let result = GatewayRouter::new()
+        .route_to_paymaster(&service, &erc20_request(json!(format!("{:#x}", PNT))))
+        .await
+        .unwrap();
+
+    let quote = &result["tokenQuote"];
+    assert_eq!(quote["token"], json!(PNT));
+    // 1000 PNT per ETH plus the 5% markup
+    assert_eq!(
+        quote["rate"],
+        json!(U256::from(1_050u64) * U256::from(10u64).pow(U256::from(18)))
+    );
+    assert_eq!(quote["symbol"], "PNT");
+    assert_eq!(quote["markupBps"], 500);
+    assert_eq!(quote["validUntil"], result["validUntil"]);
+
+    // token || rate || validity window || signature
+    let paymaster_data = result["paymasterAndData"].as_str().unwrap();
+    assert_eq!(paymaster_data.len(), 2 + 2 * TOKEN_PAYMASTER_DATA_LEN);
+    assert!(paymaster_data[2..].starts_with(&hex::encode(PNT)));
+    assert_eq!(
+        result["userOperation"]["paymasterPostOpGasLimit"],
+        json!("0xc350")
+    );
+}
+
+#[tokio::test]
+async fn test_erc20_sponsorship_rejects_bad_tokens() {
+    let router = GatewayRouter::new();
+    let service = create_service(Some(static_pricing()));
+
+    let err = router
+        .route_to_paymaster(&service, &erc20_request(json!("not-an-address")))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, GatewayError::InvalidRequest(_)), "{}", err);
+
+    let unknown = Address::repeat_byte(0xee);
+    let err = router
+        .route_to_paymaster(&service, &erc20_request(json!(format!("{:#x}", unknown))))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, GatewayError::InvalidRequest(_)), "{}", err);
+
+    // Not enabled at all
+    let err = router
+        .route_to_paymaster(
+            &create_service(None),
+            &erc20_request(json!(format!("{:#x}", PNT))),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, GatewayError::InvalidRequest(_)), "{}", err);
+}
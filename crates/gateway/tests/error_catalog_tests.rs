@@ -0,0 +1,302 @@
+//! Error catalog: every error has a key and params, and the catalog is served over HTTP and RPC
+
+use std::{collections::HashSet, net::SocketAddr, sync::Arc};
+
+use alloy_primitives::{address, Address};
+use rundler_paymaster_relay::{PaymasterError, PAYMASTER_ERROR_CATALOG};
+use serde_json::{json, Value};
+use super_relay_gateway::{
+    error::{
+        CALL_EXECUTION_FAILED_CODE, ENTRYPOINT_VALIDATION_REJECTED_CODE, EXECUTION_REVERTED_CODE,
+        INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE, OPCODE_VIOLATION_CODE, OUT_OF_TIME_RANGE_CODE,
+        PAYMASTER_DEPOSIT_TOO_LOW_CODE, PAYMASTER_VALIDATION_REJECTED_CODE, SENDER_DENIED_CODE,
+        SIGNATURE_CHECK_FAILED_CODE, STAKE_TOO_LOW_CODE, THROTTLED_OR_BANNED_CODE,
+    },
+    AccessControlConfig, GatewayConfig, GatewayError, PaymasterGateway, PreflightReport,
+    SenderAccessControl, GATEWAY_ERROR_CATALOG,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+const DENIED: Address = address!("70997970c51812dc3a010c7d01b50e0d17dc79c8");
+
+/// Number of [`GatewayError`] variants
+const VARIANTS: usize = 27;
+
+/// Index of `error`'s variant; a new variant fails to compile here until it
+/// is given an index, and the coverage test until it has a sample
+fn variant(error: &GatewayError) -> usize {
+    match error {
+        GatewayError::InvalidRequest(_) => 0,
+        GatewayError::UnsupportedMethod(_) => 1,
+        GatewayError::AuthenticationFailed(_) => 2,
+        GatewayError::RateLimitExceeded => 3,
+        GatewayError::PolicyViolation(_) => 4,
+        GatewayError::RundlerError(_) => 5,
+        GatewayError::PaymasterError(_) => 6,
+        GatewayError::SponsorshipFailed(_) => 7,
+        GatewayError::PoolError(_) => 8,
+        GatewayError::ServerError(_) => 9,
+        GatewayError::PreflightFailed(_) => 10,
+        GatewayError::JsonRpcError(_) => 11,
+        GatewayError::Timeout => 12,
+        GatewayError::DeadlineExceeded { .. } => 13,
+        GatewayError::LimitExceeded { .. } => 14,
+        GatewayError::ValidationError(_) => 15,
+        GatewayError::InternalError(_) => 16,
+        GatewayError::InvalidSponsorship(_) => 17,
+        GatewayError::SponsorshipOutOfTimeRange(_) => 18,
+        GatewayError::UnsupportedAggregator(_) => 19,
+        GatewayError::IdempotencyConflict(_) => 20,
+        GatewayError::InvalidParams(_) => 21,
+        GatewayError::SenderDenied(_) => 22,
+        GatewayError::UnsupportedCapability(_) => 23,
+        GatewayError::EstimationFailed { .. } => 24,
+        GatewayError::PoolRejected { .. } => 25,
+        GatewayError::BatchRejected { .. } => 26,
+    }
+}
+
+fn reason() -> String {
+    "something went wrong".to_string()
+}
+
+/// One error of every variant, and of every code the conversions report
+fn samples() -> Vec<GatewayError> {
+    let mut samples = vec![
+        GatewayError::InvalidRequest(reason()),
+        GatewayError::UnsupportedMethod("eth_mine".to_string()),
+        GatewayError::AuthenticationFailed(reason()),
+        GatewayError::RateLimitExceeded,
+        GatewayError::PolicyViolation(reason()),
+        GatewayError::RundlerError(reason()),
+        GatewayError::PaymasterError(reason()),
+        GatewayError::SponsorshipFailed(PaymasterError::PolicyRejected(reason())),
+        GatewayError::PoolError(reason()),
+        GatewayError::ServerError(reason()),
+        GatewayError::PreflightFailed(Box::new(PreflightReport {
+            passed: false,
+            attempts: 3,
+            elapsed_ms: 1_000,
+            checks: Vec::new(),
+            warm_up_failures: Vec::new(),
+        })),
+        GatewayError::JsonRpcError(reason()),
+        GatewayError::Timeout,
+        GatewayError::DeadlineExceeded {
+            stage: "simulation",
+            timeout_ms: 5_000,
+        },
+        GatewayError::LimitExceeded {
+            message: reason(),
+            retry_after_seconds: 2,
+        },
+        GatewayError::ValidationError(reason()),
+        GatewayError::InternalError(reason()),
+        GatewayError::InvalidSponsorship(reason()),
+        GatewayError::SponsorshipOutOfTimeRange(reason()),
+        GatewayError::UnsupportedAggregator(Address::repeat_byte(0xaa)),
+        GatewayError::IdempotencyConflict(reason()),
+        GatewayError::InvalidParams(reason()),
+        GatewayError::SenderDenied(reason()),
+        GatewayError::UnsupportedCapability(reason()),
+        GatewayError::BatchRejected {
+            index: 1,
+            error: Box::new(GatewayError::ValidationError(reason())),
+        },
+    ];
+    for code in [ENTRYPOINT_VALIDATION_REJECTED_CODE, EXECUTION_REVERTED_CODE] {
+        samples.push(GatewayError::EstimationFailed {
+            code,
+            message: reason(),
+            data: None,
+        });
+    }
+    for code in [
+        INVALID_PARAMS_CODE,
+        CALL_EXECUTION_FAILED_CODE,
+        ENTRYPOINT_VALIDATION_REJECTED_CODE,
+        PAYMASTER_VALIDATION_REJECTED_CODE,
+        OPCODE_VIOLATION_CODE,
+        OUT_OF_TIME_RANGE_CODE,
+        THROTTLED_OR_BANNED_CODE,
+        STAKE_TOO_LOW_CODE,
+        SIGNATURE_CHECK_FAILED_CODE,
+        PAYMASTER_DEPOSIT_TOO_LOW_CODE,
+    ] {
+        samples.push(GatewayError::PoolRejected {
+            code,
+            message: reason(),
+            data: Some(json!({ "paymaster": Address::ZERO })),
+        });
+    }
+    samples
+}
+
+#[test]
+fn test_catalog_covers_every_variant() {
+    let samples = samples();
+    let covered: HashSet<usize> = samples.iter().map(variant).collect();
+    assert_eq!(covered, (0..VARIANTS).collect());
+
+    for error in &samples {
+        let entry = error.catalog_entry();
+        let catalog = match error {
+            GatewayError::SponsorshipFailed(_) => PAYMASTER_ERROR_CATALOG,
+            _ => GATEWAY_ERROR_CATALOG,
+        };
+        assert!(catalog.contains(entry), "{} not catalogued", entry.key);
+
+        // Failed sponsorships keep the code they were reported with before the catalog
+        let code = match error {
+            GatewayError::SponsorshipFailed(_) => INTERNAL_ERROR_CODE,
+            _ => entry.code,
+        };
+        assert_eq!(error.code(), code, "{}", entry.key);
+
+        let params = error.params();
+        let names: HashSet<&str> = params
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(
+            names,
+            entry.params.iter().copied().collect(),
+            "{}",
+            entry.key
+        );
+        assert!(!entry.describe(&params).contains('{'), "{}", entry.key);
+    }
+}
+
+#[test]
+fn test_catalog_keys_are_unique() {
+    let mut keys = HashSet::new();
+    for entry in GATEWAY_ERROR_CATALOG.iter().chain(PAYMASTER_ERROR_CATALOG) {
+        assert!(keys.insert(entry.key), "duplicate key {}", entry.key);
+        for name in entry.params {
+            assert!(
+                entry.description.contains(&format!("{{{}}}", name)),
+                "{} does not use {}",
+                entry.key,
+                name
+            );
+        }
+    }
+}
+
+#[test]
+fn test_rpc_data_keeps_existing_fields() {
+    let error = GatewayError::LimitExceeded {
+        message: "Too many connections".to_string(),
+        retry_after_seconds: 2,
+    };
+    assert_eq!(
+        error.rpc_data(),
+        json!({
+            "retryAfter": 2,
+            "key": "request.limit_exceeded",
+            "params": { "reason": "Too many connections", "retryAfter": 2 },
+        })
+    );
+
+    let error = GatewayError::PoolRejected {
+        code: STAKE_TOO_LOW_CODE,
+        message: reason(),
+        data: Some(json!({ "factory": Address::ZERO })),
+    };
+    let data = error.rpc_data();
+    assert_eq!(data["key"], "pool.stake_too_low");
+    assert!(data.get("factory").is_some());
+}
+
+async fn serve() -> SocketAddr {
+    let control = SenderAccessControl::from_config(&AccessControlConfig {
+        denylist: vec![DENIED.to_checksum(None)],
+        ..Default::default()
+    })
+    .unwrap();
+    let app = PaymasterGateway::new(GatewayConfig::default(), None)
+        .with_access_control(Arc::new(control))
+        .app()
+        .unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    addr
+}
+
+/// Send a raw HTTP request and return the response body as JSON
+async fn http(addr: SocketAddr, request: String) -> Value {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    serde_json::from_str(body).unwrap()
+}
+
+async fn call(addr: SocketAddr, method: &str, params: Value) -> Value {
+    let body = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params}).to_string();
+    http(
+        addr,
+        format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        ),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_rejection_carries_key_and_params() {
+    let addr = serve().await;
+
+    let op = json!({"sender": format!("{:#x}", DENIED), "nonce": "0x0"});
+    let response = call(addr, "eth_sendUserOperation", json!([op, Address::ZERO])).await;
+    let error = &response["error"];
+    assert_eq!(error["code"], SENDER_DENIED_CODE);
+    assert_eq!(error["data"]["key"], "policy.sender_not_allowed");
+    let reason = error["data"]["params"]["reason"].as_str().unwrap();
+    assert!(reason.contains("denylisted"), "{}", response);
+
+    let response = call(addr, "eth_mine", json!([])).await;
+    assert_eq!(response["error"]["code"], -32601);
+    assert_eq!(response["error"]["data"]["key"], "request.method_not_found");
+    assert_eq!(response["error"]["data"]["params"]["method"], "eth_mine");
+}
+
+#[tokio::test]
+async fn test_catalog_served_over_http_and_rpc() {
+    let addr = serve().await;
+
+    let catalog = http(
+        addr,
+        "GET /errors HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n".to_string(),
+    )
+    .await;
+    assert_eq!(
+        catalog["gateway"].as_array().unwrap().len(),
+        GATEWAY_ERROR_CATALOG.len()
+    );
+    assert_eq!(
+        catalog["paymaster"].as_array().unwrap().len(),
+        PAYMASTER_ERROR_CATALOG.len()
+    );
+    let sender_denied = catalog["gateway"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["key"] == "policy.sender_not_allowed")
+        .unwrap();
+    assert_eq!(sender_denied["code"], SENDER_DENIED_CODE);
+    assert_eq!(sender_denied["params"], json!(["reason"]));
+
+    let response = call(addr, "superRelay_getErrorCatalog", json!([])).await;
+    assert_eq!(response["result"], catalog);
+}
@@ -0,0 +1,239 @@
+//! UserOperation field parsing: strict errors naming the field, accepted forms and lenient defaults
+
+use std::sync::Arc;
+
+use alloy_primitives::{Address, Bytes, U256};
+use rundler_paymaster_relay::service::PaymasterRelayService;
+use serde_json::{json, Value};
+use super_relay_gateway::{
+    FieldParsing, GatewayError, GatewayRouter, JsonFields, ValidationConfig,
+};
+
+mod common;
+
+const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+const ENTRY_POINT_V07: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+
+fn user_op() -> Value {
+    json!({
+        "sender": SENDER,
+        "nonce": "0x1",
+        "callData": "0x",
+        "callGasLimit": "0x186a0",
+        "verificationGasLimit": "0x186a0",
+        "preVerificationGas": "0x5208",
+        "maxFeePerGas": "0x3b9aca00",
+        "maxPriorityFeePerGas": "0x3b9aca00",
+        "signature": "0x",
+    })
+}
+
+fn auth() -> Value {
+    json!({
+        "chainId": "0x0",
+        "address": "0x63c0c19a282a1B52b07dD5a65b58948A07DAE32B",
+        "nonce": "0x0",
+        "yParity": "0x0",
+        "r": "0x1",
+        "s": "0x1",
+    })
+}
+
+fn create_service() -> Arc<PaymasterRelayService> {
+    Arc::new(common::service(&format!("senders = [\"{}\"]\n", SENDER)))
+}
+
+fn router(field_parsing: FieldParsing) -> GatewayRouter {
+    GatewayRouter::new().with_limits(ValidationConfig {
+        field_parsing,
+        ..Default::default()
+    })
+}
+
+async fn sponsor(
+    router: &GatewayRouter,
+    service: &Arc<PaymasterRelayService>,
+    user_op: Value,
+) -> Result<Value, GatewayError> {
+    let request = common::request(
+        "pm_sponsorUserOperation",
+        vec![user_op, json!(ENTRY_POINT_V07)],
+    );
+    router.route_to_paymaster(service, &request).await
+}
+
+#[tokio::test]
+async fn test_malformed_fields_rejected_with_exact_errors() {
+    let long_quantity = format!("0x1{}", "0".repeat(32));
+    let long_bytes = format!("0x{}zz", "ab".repeat(20));
+    let auth_with = |field: &str, value: Value| {
+        let mut auth = auth();
+        auth[field] = value;
+        auth
+    };
+    let cases = [
+        ("sender", json!(null), "Missing sender field"),
+        (
+            "sender",
+            json!("0x1234"),
+            "Invalid sender \"0x1234\": expected a 0x-prefixed 20-byte hex address",
+        ),
+        (
+            "sender",
+            json!(42),
+            "Invalid sender 42: expected a 0x-prefixed 20-byte hex address",
+        ),
+        (
+            "nonce",
+            json!("0xzz"),
+            "Invalid nonce \"0xzz\": expected a 0x-prefixed hex or decimal integer",
+        ),
+        (
+            "nonce",
+            json!("abc"),
+            "Invalid nonce \"abc\": expected a 0x-prefixed hex or decimal integer",
+        ),
+        (
+            "nonce",
+            json!("0x"),
+            "Invalid nonce \"0x\": expected a 0x-prefixed hex or decimal integer",
+        ),
+        ("callGasLimit", json!(null), "Missing callGasLimit field"),
+        (
+            "callGasLimit",
+            json!(long_quantity),
+            "Invalid callGasLimit \"0x1000000000000000000000...\": value does not fit in 128 bits",
+        ),
+        (
+            "verificationGasLimit",
+            json!(1.5),
+            "Invalid verificationGasLimit 1.5: expected a 0x-prefixed hex or decimal integer",
+        ),
+        (
+            "preVerificationGas",
+            json!(true),
+            "Invalid preVerificationGas true: expected a 0x-prefixed hex or decimal integer",
+        ),
+        (
+            "maxFeePerGas",
+            json!(-1),
+            "Invalid maxFeePerGas -1: expected a 0x-prefixed hex or decimal integer",
+        ),
+        (
+            "callData",
+            json!("0x123"),
+            "Invalid callData \"0x123\": odd number of hex digits",
+        ),
+        (
+            "callData",
+            json!(long_bytes),
+            "Invalid callData \"0xababababababababababab...\": expected 0x-prefixed hex bytes",
+        ),
+        (
+            "signature",
+            json!(12),
+            "Invalid signature 12: expected 0x-prefixed hex bytes",
+        ),
+        (
+            "paymaster",
+            json!("0xabc"),
+            "Invalid paymaster \"0xabc\": expected a 0x-prefixed 20-byte hex address",
+        ),
+        (
+            "eip7702Auth",
+            json!("0x1"),
+            "Invalid eip7702Auth \"0x1\": expected an object",
+        ),
+        (
+            "eip7702Auth",
+            auth_with("address", json!(null)),
+            "Missing eip7702Auth.address field",
+        ),
+        (
+            "eip7702Auth",
+            auth_with("yParity", json!("0x100")),
+            "Invalid eip7702Auth.yParity \"0x100\": value does not fit in 8 bits",
+        ),
+    ];
+
+    let service = create_service();
+    let router = router(FieldParsing::Strict);
+    for (field, value, expected) in cases {
+        let mut user_op = user_op();
+        user_op[field] = value;
+        match sponsor(&router, &service, user_op).await {
+            Err(GatewayError::InvalidRequest(message)) => {
+                assert_eq!(message, expected, "{}", field)
+            }
+            other => panic!("{}: expected {:?}, got {:?}", field, expected, other),
+        }
+    }
+
+    // The same operation with well-formed fields is sponsored
+    let sponsored = sponsor(&router, &service, user_op()).await.unwrap();
+    assert!(sponsored["paymasterAndData"].is_string(), "{}", sponsored);
+}
+
+#[test]
+fn test_prefixed_and_canonical_forms_accepted() {
+    let object = json!({
+        "hexNonce": "0X1f",
+        "decimalNonce": "31",
+        "numberNonce": 31,
+        "upperData": "0XABcd",
+        "bareData": "abcd",
+        "emptyData": "0x",
+        "bareAddress": SENDER.trim_start_matches("0x"),
+        "emptyAddress": "0x",
+    });
+    let fields = JsonFields::new(&object, FieldParsing::Strict);
+
+    for field in ["hexNonce", "decimalNonce", "numberNonce"] {
+        assert_eq!(fields.quantity(field).unwrap(), U256::from(31));
+        assert_eq!(fields.uint::<u8>(field).unwrap(), 31);
+    }
+    let data = Bytes::from(vec![0xab, 0xcd]);
+    assert_eq!(fields.bytes("upperData").unwrap(), data);
+    assert_eq!(fields.bytes("bareData").unwrap(), data);
+    assert!(fields.bytes("emptyData").unwrap().is_empty());
+    assert!(fields.bytes("absent").unwrap().is_empty());
+    assert_eq!(
+        fields.address("bareAddress").unwrap(),
+        SENDER.parse::<Address>().unwrap()
+    );
+    assert_eq!(fields.optional_address("emptyAddress").unwrap(), None);
+    assert_eq!(fields.optional_uint::<u128>("absent").unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_lenient_parsing_keeps_previous_defaults() {
+    let object = json!({"nonce": "10", "callGasLimit": "0xzz"});
+    let strict = JsonFields::new(&object, FieldParsing::Strict);
+    let lenient = JsonFields::new(&object, FieldParsing::Lenient);
+    assert_eq!(strict.quantity("nonce").unwrap(), U256::from(10));
+    // Unprefixed quantities were read as hex
+    assert_eq!(lenient.quantity("nonce").unwrap(), U256::from(16));
+    assert!(strict
+        .or_lenient(strict.uint::<u128>("callGasLimit"), 100_000)
+        .is_err());
+    assert_eq!(
+        lenient
+            .or_lenient(lenient.uint::<u128>("callGasLimit"), 100_000)
+            .unwrap(),
+        100_000
+    );
+
+    let mut user_op = user_op();
+    user_op["callGasLimit"] = json!(null);
+    user_op["maxFeePerGas"] = json!("not a number");
+    let service = create_service();
+    assert!(
+        sponsor(&router(FieldParsing::Strict), &service, user_op.clone())
+            .await
+            .is_err()
+    );
+    let sponsored = sponsor(&router(FieldParsing::Lenient), &service, user_op)
+        .await
+        .unwrap();
+    assert!(sponsored["paymasterAndData"].is_string(), "{}", sponsored);
+}
@@ -0,0 +1,176 @@
+//! Method registry: every registered method is answered by its handler, and
+//! the metadata keeps the scopes and limits the gateway applied before
+
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
+
+use rundler_paymaster_relay::service::PaymasterRelayService;
+use serde_json::json;
+use super_relay_gateway::{
+    method_registry::{self, MethodHandler, METHODS},
+    AccessControlConfig, AlertBus, AlertConfig, ApiKeyScope, ConnectionLimitConfig, GatewayError,
+    GatewayResult, GatewayRouter, SenderAccessControl,
+};
+
+mod common;
+
+/// Whether the handler had no arm for the method
+fn unanswered(result: &GatewayResult<serde_json::Value>) -> bool {
+    match result {
+        Err(GatewayError::UnsupportedMethod(_)) => true,
+        Err(GatewayError::InvalidRequest(message)) => {
+            message.starts_with("Unknown paymaster method")
+        }
+        _ => false,
+    }
+}
+
+/// Enabled alert bus without webhooks
+fn alerts() -> AlertBus {
+    let config = AlertConfig {
+        enabled: true,
+        ..Default::default()
+    };
+    AlertBus::start(config, vec![]).unwrap()
+}
+
+/// Call `method` on its registered handler, `None` when the handler is
+/// still working on it after the timeout (so it did have an arm)
+async fn answer(
+    router: &GatewayRouter,
+    paymaster: &Arc<PaymasterRelayService>,
+    access_control: &SenderAccessControl,
+    alerts: &AlertBus,
+    handler: MethodHandler,
+    method: &str,
+) -> Option<GatewayResult<serde_json::Value>> {
+    let request = common::request(method, vec![]);
+    let call = async {
+        match handler {
+            MethodHandler::AccessList => access_control.handle_admin_request(&request),
+            MethodHandler::Paymaster => router.route_to_paymaster(paymaster, &request).await,
+            MethodHandler::Rundler => router.route_to_rundler(&request).await,
+            MethodHandler::SuperRelay => router.route_to_super_relay(&request).await,
+            MethodHandler::TestAlert => alerts
+                .fire_test(method)
+                .await
+                .map(|(alert, _)| json!(alert)),
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), call)
+        .await
+        .ok()
+}
+
+#[tokio::test]
+async fn test_router_answers_every_registered_method() {
+    let router = GatewayRouter::new().with_debug_api(true);
+    let paymaster = Arc::new(common::service("senders = []\n"));
+    let access_control = SenderAccessControl::from_config(&AccessControlConfig::default()).unwrap();
+    let alerts = alerts();
+
+    for spec in METHODS {
+        let answer = answer(
+            &router,
+            &paymaster,
+            &access_control,
+            &alerts,
+            spec.handler,
+            spec.name,
+        )
+        .await;
+        if let Some(result) = answer {
+            assert!(
+                !unanswered(&result),
+                "{} is registered but {:?} has no arm for it: {:?}",
+                spec.name,
+                spec.handler,
+                result
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_router_refuses_unregistered_methods() {
+    let router = GatewayRouter::new().with_debug_api(true);
+    let paymaster = Arc::new(common::service("senders = []\n"));
+    let access_control = SenderAccessControl::from_config(&AccessControlConfig::default()).unwrap();
+
+    for (handler, method) in [
+        (MethodHandler::AccessList, "admin_clearAccessLists"),
+        (MethodHandler::Paymaster, "pm_mine"),
+        (MethodHandler::Rundler, "eth_mine"),
+        (
+            MethodHandler::Rundler,
+            "debug_bundler_dumpPaymasterBalances",
+        ),
+        (MethodHandler::SuperRelay, "superRelay_mine"),
+    ] {
+        assert!(method_registry::lookup(method).is_none(), "{}", method);
+        let result = answer(
+            &router,
+            &paymaster,
+            &access_control,
+            &alerts(),
+            handler,
+            method,
+        )
+        .await
+        .unwrap();
+        assert!(unanswered(&result), "{}: {:?}", method, result);
+    }
+}
+
+#[test]
+fn test_scopes_preserved() {
+    for (method, scope) in [
+        ("pm_sponsorUserOperation", ApiKeyScope::Sponsor),
+        ("pm_simulateSponsorship", ApiKeyScope::Sponsor),
+        ("pm_getDepositInfo", ApiKeyScope::Read),
+        ("pm_depositTo", ApiKeyScope::Admin),
+        ("pm_getPolicyShadowReport", ApiKeyScope::Admin),
+        ("eth_sendUserOperation", ApiKeyScope::Send),
+        ("eth_estimateUserOperationGas", ApiKeyScope::Read),
+        ("rundler_sendBundleNow", ApiKeyScope::Admin),
+        ("superRelay_sendUserOperationConditional", ApiKeyScope::Send),
+        ("superRelay_simulateBundle", ApiKeyScope::Admin),
+        ("debug_bundler_dumpMempool", ApiKeyScope::Admin),
+        ("admin_getAccessLists", ApiKeyScope::Admin),
+        // Unregistered names fall back on their namespace
+        ("admin_exportPool", ApiKeyScope::Admin),
+        ("pm_mine", ApiKeyScope::Sponsor),
+        ("foo_bar", ApiKeyScope::Read),
+    ] {
+        assert_eq!(ApiKeyScope::required_for(method), scope, "{}", method);
+    }
+}
+
+#[test]
+fn test_default_expensive_methods() {
+    let expensive: BTreeSet<_> = ConnectionLimitConfig::default()
+        .expensive_methods
+        .into_iter()
+        .collect();
+    let expected: BTreeSet<_> = [
+        "eth_estimateUserOperationGas",
+        "eth_sendUserOperation",
+        "superRelay_sendUserOperationConditional",
+        "pm_simulateSponsorship",
+    ]
+    .into_iter()
+    .map(str::to_string)
+    .collect();
+    assert_eq!(expensive, expected);
+}
+
+#[test]
+fn test_read_only_flags() {
+    let read_only = |method| method_registry::lookup(method).unwrap().read_only;
+    assert!(read_only("eth_chainId"));
+    assert!(read_only("pm_getDepositInfo"));
+    assert!(read_only("superRelay_simulateBundle"));
+    assert!(!read_only("pm_sponsorUserOperation"));
+    assert!(!read_only("eth_sendUserOperation"));
+    assert!(!read_only("admin_addAccessListEntry"));
+    assert!(!read_only("debug_bundler_setReputation"));
+}
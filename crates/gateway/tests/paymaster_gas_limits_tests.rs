@@ -0,0 +1,115 @@
+//! v0.7 sponsorships report the paymaster gas limits they signed, including
+//! limits the client set itself
+
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::{Address, Bytes, U256};
+use ethers::signers::{LocalWallet, Signer};
+use rundler_paymaster_relay::sponsorship::verify_sponsorship;
+use rundler_types::{chain::ChainSpec, v0_7, UserOperationVariant};
+use serde_json::{json, Value};
+use super_relay_gateway::GatewayRouter;
+
+mod common;
+
+const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+const ENTRY_POINT_V07: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+
+fn paymaster() -> Address {
+    let wallet: LocalWallet = common::SIGNER_KEY.parse().unwrap();
+    Address::from_slice(wallet.address().as_bytes())
+}
+
+fn user_op() -> Value {
+    json!({
+        "sender": SENDER,
+        "nonce": "0x1",
+        "callData": "0x",
+        "callGasLimit": "0x186a0",
+        "verificationGasLimit": "0x186a0",
+        "preVerificationGas": "0x5208",
+        "maxFeePerGas": "0x3b9aca00",
+        "maxPriorityFeePerGas": "0x3b9aca00",
+        "signature": "0x",
+    })
+}
+
+/// `user_op` with the paymaster fields a client copies from `response`
+fn sponsored_op(response: &Value) -> UserOperationVariant {
+    let gas = |field: &str| {
+        let value = response[field].as_str().unwrap();
+        u128::from_str_radix(value.trim_start_matches("0x"), 16).unwrap()
+    };
+    let paymaster_data: Bytes = response["paymasterAndData"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let chain_spec = ChainSpec {
+        id: 31337,
+        ..Default::default()
+    };
+    let op = v0_7::UserOperationBuilder::new(
+        &chain_spec,
+        v0_7::UserOperationRequiredFields {
+            sender: SENDER.parse().unwrap(),
+            nonce: U256::from(1),
+            call_data: Bytes::new(),
+            call_gas_limit: 100_000,
+            verification_gas_limit: 100_000,
+            pre_verification_gas: 21_000,
+            max_fee_per_gas: 1_000_000_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+            signature: Bytes::new(),
+        },
+    )
+    .paymaster(
+        paymaster(),
+        gas("paymasterVerificationGasLimit"),
+        gas("paymasterPostOpGasLimit"),
+        paymaster_data,
+    )
+    .build();
+    UserOperationVariant::V0_7(op)
+}
+
+#[tokio::test]
+async fn test_reported_paymaster_gas_limits_are_signed() {
+    let service = Arc::new(common::service(&format!("senders = [\"{}\"]\n", SENDER)));
+    let router = GatewayRouter::new();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    // Limits from eth_estimateUserOperationGas, not the configured ones
+    let mut estimated = user_op();
+    estimated["paymaster"] = json!(paymaster());
+    estimated["paymasterVerificationGasLimit"] = json!("0x30d40");
+    estimated["paymasterPostOpGasLimit"] = json!("0x9c40");
+    estimated["paymasterData"] = json!("0x");
+
+    for (op, verification_gas_limit, post_op_gas_limit) in [
+        (estimated, "0x30d40", "0x9c40"),
+        (user_op(), "0x186a0", "0x4e20"),
+    ] {
+        let response = router
+            .route_to_paymaster(
+                &service,
+                &common::request("pm_sponsorUserOperation", vec![op, json!(ENTRY_POINT_V07)]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            response["paymasterVerificationGasLimit"], verification_gas_limit,
+            "{}",
+            response
+        );
+        assert_eq!(response["paymasterPostOpGasLimit"], post_op_gas_limit);
+
+        verify_sponsorship(&sponsored_op(&response), paymaster(), now).unwrap();
+    }
+}
@@ -0,0 +1,228 @@
+//! Pool reputation of entities: rundler_getEntityReputation and
+//! rundler_listThrottledEntities, rejections carrying the reputation, and the
+//! alert raised when a sponsoring paymaster gets throttled
+
+use std::sync::{Arc, Mutex};
+
+use alloy_primitives::{address, Address, U64};
+use rundler_paymaster_relay::{
+    entry_points::{EntryPointRouting, EntryPointVersion, PaymasterContractConfig},
+    service::PaymasterRelayService,
+};
+use rundler_types::{
+    chain::ChainSpec,
+    pool::{MempoolError, MockPool, PoolError, Reputation, ReputationStatus},
+    v0_6, Entity, UserOperationPermissions, UserOperationVariant,
+};
+use serde_json::json;
+use super_relay_gateway::{
+    error::THROTTLED_OR_BANNED_CODE,
+    reputation::{entity_reputations, throttled_entities},
+    router::add_op_to_pool,
+    AlertKind, EntityReputation, GatewayError, HealthProbe, ReputationProbe,
+};
+
+mod common;
+
+const SENDER: Address = address!("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+const PAYMASTER: Address = address!("00000000000000000000000000000000000000aa");
+const FACTORY: Address = address!("00000000000000000000000000000000000000fa");
+const UNSEEN: Address = address!("00000000000000000000000000000000000000ee");
+const OTHER_ENTRY_POINT: Address = Address::repeat_byte(0xb0);
+
+fn entry_point() -> Address {
+    ChainSpec::default().entry_point_address_v0_6
+}
+
+/// Pool on two EntryPoints that has seen PAYMASTER and FACTORY on the first,
+/// rating the paymaster as `status` and everything else as ok
+fn reputation_pool(status: Arc<Mutex<ReputationStatus>>) -> MockPool {
+    let mut pool = MockPool::new();
+    pool.expect_get_supported_entry_points()
+        .returning(|| Ok(vec![entry_point(), OTHER_ENTRY_POINT]));
+    pool.expect_debug_dump_reputation().returning(|ep| {
+        if ep != entry_point() {
+            return Ok(vec![]);
+        }
+        Ok(vec![
+            Reputation {
+                address: PAYMASTER,
+                ops_seen: 40,
+                ops_included: 2,
+            },
+            Reputation {
+                address: FACTORY,
+                ops_seen: 5,
+                ops_included: 5,
+            },
+        ])
+    });
+    pool.expect_get_reputation_status()
+        .returning(move |ep, address| {
+            Ok(if ep == entry_point() && address == PAYMASTER {
+                *status.lock().unwrap()
+            } else {
+                ReputationStatus::Ok
+            })
+        });
+    pool
+}
+
+fn throttled_pool() -> MockPool {
+    reputation_pool(Arc::new(Mutex::new(ReputationStatus::Throttled)))
+}
+
+fn throttled_paymaster() -> EntityReputation {
+    EntityReputation {
+        entry_point: entry_point(),
+        address: PAYMASTER,
+        ops_seen: U64::from(40),
+        ops_included: U64::from(2),
+        status: ReputationStatus::Throttled,
+    }
+}
+
+fn user_op() -> UserOperationVariant {
+    UserOperationVariant::V0_6(
+        v0_6::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_6::UserOperationRequiredFields {
+                sender: SENDER,
+                ..Default::default()
+            },
+        )
+        .build(),
+    )
+}
+
+fn create_service() -> Arc<PaymasterRelayService> {
+    let routing = EntryPointRouting::new(vec![PaymasterContractConfig {
+        version: EntryPointVersion::V0_6,
+        entry_point: entry_point(),
+        paymaster: PAYMASTER,
+        signer_view: "verifyingSigner()".to_string(),
+    }])
+    .unwrap();
+    Arc::new(common::service("senders = []\n").with_entry_point_routing(routing))
+}
+
+#[tokio::test]
+async fn test_entity_reputation_on_each_entry_point() {
+    let pool = throttled_pool();
+
+    let reputations = entity_reputations(&pool, PAYMASTER, None).await.unwrap();
+    assert_eq!(
+        reputations,
+        vec![
+            throttled_paymaster(),
+            EntityReputation {
+                entry_point: OTHER_ENTRY_POINT,
+                address: PAYMASTER,
+                ops_seen: U64::ZERO,
+                ops_included: U64::ZERO,
+                status: ReputationStatus::Ok,
+            },
+        ]
+    );
+    assert_eq!(
+        serde_json::to_value(&reputations[0]).unwrap(),
+        json!({
+            "entryPoint": entry_point(),
+            "address": PAYMASTER,
+            "opsSeen": "0x28",
+            "opsIncluded": "0x2",
+            "status": "throttled",
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_unseen_entity_has_no_ops() {
+    let pool = throttled_pool();
+
+    let reputations = entity_reputations(&pool, UNSEEN, Some(entry_point()))
+        .await
+        .unwrap();
+    assert_eq!(reputations.len(), 1);
+    assert_eq!(reputations[0].ops_seen, U64::ZERO);
+    assert_eq!(reputations[0].status, ReputationStatus::Ok);
+
+    let err = entity_reputations(&pool, UNSEEN, Some(Address::repeat_byte(0xcc)))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, GatewayError::InvalidRequest(_)), "{:?}", err);
+}
+
+#[tokio::test]
+async fn test_throttled_entities_listed() {
+    let pool = throttled_pool();
+    assert_eq!(
+        throttled_entities(&pool, None).await.unwrap(),
+        vec![throttled_paymaster()]
+    );
+    assert!(throttled_entities(&pool, Some(OTHER_ENTRY_POINT))
+        .await
+        .unwrap()
+        .is_empty());
+
+    let pool = reputation_pool(Arc::new(Mutex::new(ReputationStatus::Ok)));
+    assert!(throttled_entities(&pool, None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_throttled_rejection_carries_reputation() {
+    let mut pool = throttled_pool();
+    pool.expect_add_op().returning(|_, _| {
+        Err(PoolError::MempoolError(MempoolError::EntityThrottled(
+            Entity::paymaster(PAYMASTER),
+        )))
+    });
+
+    let err = add_op_to_pool(&pool, user_op(), UserOperationPermissions::default())
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), THROTTLED_OR_BANNED_CODE);
+    assert_eq!(
+        err.data(),
+        Some(&json!({
+            "paymaster": PAYMASTER.to_checksum(None),
+            "reputation": {
+                "entryPoint": entry_point(),
+                "address": PAYMASTER,
+                "opsSeen": "0x28",
+                "opsIncluded": "0x2",
+                "status": "throttled",
+            },
+        }))
+    );
+}
+
+#[tokio::test]
+async fn test_probe_alerts_once_per_transition() {
+    let status = Arc::new(Mutex::new(ReputationStatus::Ok));
+    let probe = ReputationProbe::new(reputation_pool(status.clone()), create_service());
+    assert!(!probe.critical());
+    probe.check().await.unwrap();
+
+    *status.lock().unwrap() = ReputationStatus::Throttled;
+    let reason = probe.check().await.unwrap_err();
+    assert!(reason.contains(&PAYMASTER.to_string()), "{}", reason);
+    let alert = probe.alert(&reason).unwrap();
+    assert_eq!(alert.kind, AlertKind::EntityThrottled);
+    assert_eq!(alert.details[0]["status"], "throttled");
+
+    // Still throttled: failing, but already alerted about
+    let reason = probe.check().await.unwrap_err();
+    assert!(probe.alert(&reason).is_none());
+
+    // Throttled to banned is not a transition out of ok
+    *status.lock().unwrap() = ReputationStatus::Banned;
+    let reason = probe.check().await.unwrap_err();
+    assert!(probe.alert(&reason).is_none());
+
+    *status.lock().unwrap() = ReputationStatus::Ok;
+    probe.check().await.unwrap();
+    *status.lock().unwrap() = ReputationStatus::Banned;
+    let reason = probe.check().await.unwrap_err();
+    assert_eq!(probe.alert(&reason).unwrap().details[0]["status"], "banned");
+}
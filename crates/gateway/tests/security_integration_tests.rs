@@ -250,6 +250,7 @@ async fn test_security_config_customization() {
         max_calldata_size: 1000,
         max_init_code_size: 10000,
         min_contract_reputation: 80,
+        threat_feeds: Default::default(),
     };
 
     let mut checker = SecurityChecker::with_config(custom_config);
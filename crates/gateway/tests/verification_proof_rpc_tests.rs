@@ -0,0 +1,79 @@
+//! superRelay_getVerificationProof over stored dual-signature proofs
+
+use std::sync::Arc;
+
+use alloy_primitives::B256;
+use rundler_paymaster_relay::verification_proof::{
+    unix_millis, KmsSigningSummary, StoredVerificationProof, ValidationSummary,
+    VerificationProofConfig, VerificationProofStore,
+};
+use serde_json::{json, Value};
+use super_relay_gateway::{gateway::JsonRpcRequest, GatewayError, GatewayRouter};
+
+mod common;
+
+fn request(params: Vec<Value>) -> JsonRpcRequest {
+    common::request("superRelay_getVerificationProof", params)
+}
+
+fn router_with_proof(user_op_hash: B256) -> GatewayRouter {
+    let store = VerificationProofStore::new(VerificationProofConfig::default()).unwrap();
+    let completed_at = unix_millis();
+    store.insert(StoredVerificationProof {
+        user_op_hash,
+        account_id: "passkey_user_alice".to_string(),
+        tee_device_id: "tee_device_001".to_string(),
+        signing: KmsSigningSummary {
+            signature: "0x1234".to_string(),
+            paymaster_address: "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".to_string(),
+            dual_signature_mode: true,
+        },
+        validation: ValidationSummary {
+            paymaster_verified: true,
+            user_passkey_verified: true,
+            local_verification: None,
+            balance: "0.05".to_string(),
+            membership_level: "premium".to_string(),
+            approved_at: completed_at / 1000,
+        },
+        proof_timestamp: "2026-10-01T00:00:00Z".to_string(),
+        requested_at: completed_at - 250,
+        completed_at,
+    });
+    GatewayRouter::new().with_verification_proofs(Arc::new(store))
+}
+
+#[tokio::test]
+async fn test_stored_proof_returned() {
+    let hash = B256::repeat_byte(0xab);
+    let router = router_with_proof(hash);
+
+    let proof = router
+        .route_to_super_relay(&request(vec![json!(hash.to_string())]))
+        .await
+        .unwrap();
+    assert_eq!(proof["teeDeviceId"], "tee_device_001");
+    assert_eq!(proof["signing"]["dualSignatureMode"], true);
+    assert_eq!(proof["validation"]["paymasterVerified"], true);
+}
+
+#[tokio::test]
+async fn test_unknown_hash_returns_null() {
+    let router = router_with_proof(B256::repeat_byte(0xab));
+
+    let proof = router
+        .route_to_super_relay(&request(vec![json!(B256::repeat_byte(0xcd).to_string())]))
+        .await
+        .unwrap();
+    assert_eq!(proof, Value::Null);
+}
+
+#[tokio::test]
+async fn test_invalid_hash_rejected() {
+    let router = router_with_proof(B256::repeat_byte(0xab));
+
+    let result = router
+        .route_to_super_relay(&request(vec![json!("0x1234")]))
+        .await;
+    assert!(matches!(result, Err(GatewayError::InvalidRequest(_))));
+}
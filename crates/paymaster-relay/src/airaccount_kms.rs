@@ -1,25 +1,176 @@
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
+use alloy_primitives::B256;
 use anyhow::{anyhow, Result};
 use ethers::{
+    abi::Token,
     signers::Signer,
     types::U256,
     utils::{keccak256, to_checksum},
 };
-use reqwest::Client;
+use reqwest::{Certificate, Client, Identity, Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tracing::{debug, info};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use zeroize::Zeroizing;
+
+use crate::{
+    key_manager::PaymasterKeyManager,
+    kms::KmsError,
+    user_signature::{LocalVerificationConfig, UserSignatureAlgorithm},
+    verification_proof::{
+        unix_millis, KmsSigningSummary, StoredVerificationProof, ValidationSummary,
+        VerificationProofStore,
+    },
+};
+
+/// 双重签名接口路径
+pub const SIGN_USER_OPERATION_PATH: &str = "/kms/sign-user-operation";
+/// 状态接口路径
+pub const STATUS_PATH: &str = "/kms/status";
+
+/// AirAccount KMS 连接配置
+///
+/// 默认只允许 https 并校验服务端证书；配置 ca_bundle 后只信任其中的 CA，
+/// 配置 client_cert 与 client_key 后使用双向 TLS。证书文件变化后无需重启即可重新加载。
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct AirAccountKmsConfig {
+    /// KMS 服务地址，如 https://kms.example.com
+    pub base_url: String,
+    /// 请求超时（秒）
+    pub timeout_seconds: u64,
+    /// 信任的 CA 证书包 (PEM)，不配置时使用内置根证书
+    pub ca_bundle: Option<PathBuf>,
+    /// 客户端证书 (PEM)，需与 client_key 同时配置
+    pub client_cert: Option<PathBuf>,
+    /// 客户端私钥 (PKCS#8 PEM)
+    pub client_key: Option<PathBuf>,
+    /// 允许明文 http 地址，仅用于本地开发
+    pub insecure_allow_http: bool,
+    /// KMS 端期望的 Paymaster 请求签名格式
+    pub signing_format: KmsSigningFormat,
+    /// 检查证书文件变化的间隔（秒）
+    pub tls_reload_interval_seconds: u64,
+    /// 调用 KMS 前在本地预验证用户签名，无效签名不再占用 KMS 请求
+    pub local_verification: LocalVerificationConfig,
+}
+
+impl Default for AirAccountKmsConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://localhost:3002".to_string(),
+            timeout_seconds: 30,
+            ca_bundle: None,
+            client_cert: None,
+            client_key: None,
+            insecure_allow_http: false,
+            signing_format: KmsSigningFormat::default(),
+            tls_reload_interval_seconds: 30,
+            local_verification: LocalVerificationConfig::default(),
+        }
+    }
+}
+
+impl AirAccountKmsConfig {
+    /// 检查地址协议与证书配置
+    pub fn validate(&self) -> Result<(), KmsError> {
+        let url =
+            reqwest::Url::parse(&self.base_url).map_err(|e| KmsError::InvalidConfiguration {
+                reason: format!("Invalid AirAccount KMS URL {}: {}", self.base_url, e),
+            })?;
+        match url.scheme() {
+            "https" => {}
+            "http" if self.insecure_allow_http => {}
+            "http" => {
+                return Err(KmsError::InvalidConfiguration {
+                    reason: format!(
+                        "AirAccount KMS URL {} is plaintext HTTP; use https or set insecure_allow_http for local development",
+                        self.base_url
+                    ),
+                })
+            }
+            scheme => {
+                return Err(KmsError::InvalidConfiguration {
+                    reason: format!("Unsupported AirAccount KMS URL scheme {}", scheme),
+                })
+            }
+        }
+        if self.client_cert.is_some() != self.client_key.is_some() {
+            return Err(KmsError::InvalidConfiguration {
+                reason: "client_cert and client_key must be configured together".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// 各证书文件的修改时间，用于发现变化
+    fn tls_modified(&self) -> Vec<Option<SystemTime>> {
+        [&self.ca_bundle, &self.client_cert, &self.client_key]
+            .into_iter()
+            .flatten()
+            .map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+            .collect()
+    }
+}
+
+/// Paymaster 请求签名格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KmsSigningFormat {
+    /// keccak256(abi.encode(userOpHash, accountId, keccak256(userSignature), nonce, timestamp))
+    Legacy,
+    /// 在 Legacy 字段后追加接口路径与递增计数器，防止跨接口重放
+    #[default]
+    PathAndCounter,
+}
 
-use crate::key_manager::PaymasterKeyManager;
+impl KmsSigningFormat {
+    /// Paymaster 对发往 path 的请求所签的消息
+    pub fn message(self, request: &KmsDualSignRequest, path: &str) -> Result<[u8; 32]> {
+        let user_op_hash = AirAccountKmsClient::get_user_operation_hash(&request.user_operation)?;
+        let user_signature_hash = keccak256(request.user_signature.as_bytes());
+
+        let mut tokens = vec![
+            Token::FixedBytes(user_op_hash.to_vec()),
+            Token::String(request.account_id.clone()),
+            Token::FixedBytes(user_signature_hash.to_vec()),
+            Token::Uint(U256::from(request.nonce)),
+            Token::Uint(U256::from(request.timestamp)),
+        ];
+        if self == KmsSigningFormat::PathAndCounter {
+            let counter = request
+                .request_counter
+                .ok_or_else(|| anyhow!("Request counter missing for path-and-counter signing"))?;
+            tokens.push(Token::String(path.to_string()));
+            tokens.push(Token::Uint(U256::from(counter)));
+        }
+        Ok(keccak256(ethers::abi::encode(&tokens)))
+    }
+}
 
 /// AirAccount KMS 客户端
 /// 实现双重签名验证机制，与 AirAccount TEE-KMS 服务通信
 pub struct AirAccountKmsClient {
-    base_url: String,
-    http_client: Client,
+    config: AirAccountKmsConfig,
+    /// 证书重新加载时整体替换
+    http_client: RwLock<Client>,
+    /// 上次加载时证书文件的修改时间
+    tls_modified: Mutex<Vec<Option<SystemTime>>>,
+    /// 请求计数器，从启动时的毫秒时间戳开始，重启后仍然递增
+    request_counter: AtomicU64,
     key_manager: PaymasterKeyManager,
     timeout: Duration,
+    proof_store: Option<Arc<VerificationProofStore>>,
 }
 
 /// KMS 双重签名请求
@@ -39,6 +190,9 @@ pub struct KmsDualSignRequest {
     pub business_validation: BusinessValidation,
     pub nonce: u64,
     pub timestamp: u64,
+    /// 递增请求计数器，仅 path-and-counter 签名格式发送
+    #[serde(rename = "requestCounter", skip_serializing_if = "Option::is_none")]
+    pub request_counter: Option<u64>,
 }
 
 /// 业务验证信息
@@ -99,27 +253,88 @@ pub struct KmsStatus {
 }
 
 impl AirAccountKmsClient {
-    /// 创建新的 KMS 客户端
-    pub fn new(base_url: String, key_manager: PaymasterKeyManager) -> Self {
-        let http_client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .user_agent("SuperRelay-Paymaster/1.0")
-            .build()
-            .unwrap();
+    /// 按配置创建 KMS 客户端
+    pub fn new(
+        config: AirAccountKmsConfig,
+        key_manager: PaymasterKeyManager,
+    ) -> Result<Self, KmsError> {
+        config.validate()?;
+        if config.base_url.starts_with("http://") {
+            warn!(
+                "⚠️ AirAccount KMS at {} uses plaintext HTTP (insecure_allow_http), for local development only",
+                config.base_url
+            );
+        }
+        let http_client = build_http_client(&config)?;
 
-        Self {
-            base_url,
-            http_client,
+        Ok(Self {
+            http_client: RwLock::new(http_client),
+            tls_modified: Mutex::new(config.tls_modified()),
+            request_counter: AtomicU64::new(unix_millis()),
             key_manager,
-            timeout: Duration::from_secs(30),
+            timeout: Duration::from_secs(config.timeout_seconds),
+            proof_store: None,
+            config,
+        })
+    }
+
+    /// 证书文件变化时重新加载 TLS 配置，返回是否已重新加载
+    ///
+    /// 新证书无法加载时保留原配置并返回错误。
+    pub fn reload_tls(&self) -> Result<bool, KmsError> {
+        let modified = self.config.tls_modified();
+        let mut loaded = self.tls_modified.lock().unwrap();
+        if *loaded == modified {
+            return Ok(false);
         }
+        let http_client = build_http_client(&self.config)?;
+        *self.http_client.write().unwrap() = http_client;
+        *loaded = modified;
+        Ok(true)
+    }
+
+    /// 定期检查证书文件，变化后无需重启即生效
+    pub fn spawn_tls_reload(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match self.reload_tls() {
+                    Ok(true) => info!("🔐 AirAccount KMS TLS certificates reloaded"),
+                    Ok(false) => {}
+                    Err(e) => warn!("Keeping previous AirAccount KMS TLS certificates: {}", e),
+                }
+            }
+        })
+    }
+
+    /// 当前的 HTTP 客户端
+    fn http_client(&self) -> Client {
+        self.http_client.read().unwrap().clone()
+    }
+
+    /// 下一个请求计数器值
+    fn next_request_counter(&self) -> u64 {
+        self.request_counter.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// 保存每次成功签名的验证证明，供 superRelay_getVerificationProof 查询
+    pub fn with_proof_store(mut self, proof_store: Arc<VerificationProofStore>) -> Self {
+        self.proof_store = Some(proof_store);
+        self
     }
 
     /// 使用双重签名机制签名 UserOperation
+    ///
+    /// signature_format 决定用户签名的本地预验证算法；本地验证失败时不调用 KMS，
+    /// 错误为 KmsError::InvalidUserSignature。KMS 请求失败时错误为 KmsError，
+    /// 可 downcast 区分连接、TLS 与签名被拒
     pub async fn sign_user_operation(
         &self,
         user_op: &Value,
         account_id: &str,
+        signature_format: &str,
         user_passkey_signature: &str,
         user_public_key: &str,
     ) -> Result<KmsSignResponse> {
@@ -127,6 +342,15 @@ impl AirAccountKmsClient {
             "🔐 Initiating dual-signature UserOperation signing for account: {}",
             account_id
         );
+        let requested_at = unix_millis();
+
+        // 0. 本地预验证用户签名（KMS 验证仍是最终结果）
+        let local_verification = self.verify_user_signature(
+            user_op,
+            signature_format,
+            user_passkey_signature,
+            user_public_key,
+        )?;
 
         // 1. 验证业务规则
         let business_validation = self.validate_business_rules(account_id).await?;
@@ -136,24 +360,113 @@ impl AirAccountKmsClient {
             .build_dual_sign_request(
                 user_op,
                 account_id,
+                signature_format,
                 user_passkey_signature,
                 user_public_key,
                 business_validation,
             )
             .await?;
 
-        // 3. 使用 Paymaster 私钥签名请求
-        let (paymaster_signature, paymaster_address) = self.sign_request(&request_data).await?;
+        // 3. 使用 Paymaster 私钥签名请求（含接口路径与请求计数器）
+        let (paymaster_signature, paymaster_address) = self
+            .sign_request(&request_data, SIGN_USER_OPERATION_PATH)
+            .await?;
 
         // 4. 发送双重签名请求
         let response = self
             .send_kms_request(&request_data, &paymaster_signature, &paymaster_address)
-            .await?;
+            .await
+            .inspect_err(|e| warn!("❌ AirAccount KMS request failed: {}", e))?;
+
+        // 5. 保存验证证明
+        if let Some(proof_store) = &self.proof_store {
+            self.record_proof(
+                proof_store,
+                &request_data,
+                &paymaster_address,
+                &response,
+                local_verification,
+                requested_at,
+            );
+        }
 
         info!("✅ Dual-signature UserOperation signed successfully");
         Ok(response)
     }
 
+    /// 按签名格式在本地验证用户签名，返回所用算法；不验证的格式返回 None
+    pub fn verify_user_signature(
+        &self,
+        user_op: &Value,
+        signature_format: &str,
+        user_signature: &str,
+        user_public_key: &str,
+    ) -> Result<Option<UserSignatureAlgorithm>> {
+        let Some(algorithm) = self
+            .config
+            .local_verification
+            .algorithm_for(signature_format)
+        else {
+            debug!(
+                "User signature format {} not verified locally, leaving it to the KMS",
+                signature_format
+            );
+            return Ok(None);
+        };
+        let user_op_hash = Self::get_user_operation_hash(user_op)?;
+        algorithm
+            .verify(user_op_hash, user_signature, user_public_key)
+            .inspect_err(|e| warn!("❌ Rejected before the KMS call: {}", e))?;
+        debug!("✅ User signature verified locally ({:?})", algorithm);
+        Ok(Some(algorithm))
+    }
+
+    /// 记录签名摘要与验证摘要
+    fn record_proof(
+        &self,
+        proof_store: &VerificationProofStore,
+        request_data: &KmsDualSignRequest,
+        paymaster_address: &str,
+        response: &KmsSignResponse,
+        local_verification: Option<UserSignatureAlgorithm>,
+        requested_at: u64,
+    ) {
+        let user_op_hash = match response.user_op_hash.parse::<B256>() {
+            Ok(hash) => hash,
+            Err(e) => {
+                warn!(
+                    "Not storing verification proof, invalid userOpHash {}: {}",
+                    response.user_op_hash, e
+                );
+                return;
+            }
+        };
+        let proof = &response.verification_proof;
+        let business = &request_data.business_validation;
+
+        proof_store.insert(StoredVerificationProof {
+            user_op_hash,
+            account_id: request_data.account_id.clone(),
+            tee_device_id: response.tee_device_id.clone(),
+            signing: KmsSigningSummary {
+                signature: response.signature.clone(),
+                paymaster_address: paymaster_address.to_string(),
+                dual_signature_mode: proof.dual_signature_mode,
+            },
+            validation: ValidationSummary {
+                paymaster_verified: proof.paymaster_verified,
+                user_passkey_verified: proof.user_passkey_verified,
+                local_verification,
+                balance: business.balance.clone(),
+                membership_level: business.membership_level.clone(),
+                approved_at: business.approved_at,
+            },
+            proof_timestamp: proof.timestamp.clone(),
+            requested_at,
+            completed_at: unix_millis(),
+        });
+    }
+
     /// 验证业务规则
     async fn validate_business_rules(&self, account_id: &str) -> Result<BusinessValidation> {
         debug!("📋 Validating business rules for account: {}", account_id);
@@ -184,6 +497,7 @@ impl AirAccountKmsClient {
         &self,
         user_op: &Value,
         account_id: &str,
+        signature_format: &str,
         user_signature: &str,
         user_public_key: &str,
         business_validation: BusinessValidation,
@@ -198,35 +512,36 @@ impl AirAccountKmsClient {
             .unwrap()
             .as_secs();
 
+        let request_counter = match self.config.signing_format {
+            KmsSigningFormat::Legacy => None,
+            KmsSigningFormat::PathAndCounter => Some(self.next_request_counter()),
+        };
+
         Ok(KmsDualSignRequest {
             user_operation: user_op.clone(),
             account_id: account_id.to_string(),
-            signature_format: "erc4337".to_string(),
+            signature_format: signature_format.to_string(),
             user_signature: user_signature.to_string(),
             user_public_key: user_public_key.to_string(),
             business_validation,
             nonce,
             timestamp,
+            request_counter,
         })
     }
 
-    /// 使用 Paymaster 私钥签名请求
-    async fn sign_request(&self, request_data: &KmsDualSignRequest) -> Result<(String, String)> {
+    /// 使用 Paymaster 私钥签名发往 path 的请求
+    async fn sign_request(
+        &self,
+        request_data: &KmsDualSignRequest,
+        path: &str,
+    ) -> Result<(String, String)> {
         debug!("🖋️ Signing request with Paymaster key");
 
         let signer = self.key_manager.get_signer().await;
 
         // 计算签名消息（与 AirAccount KMS 端的验证逻辑一致）
-        let user_op_hash = Self::get_user_operation_hash(&request_data.user_operation)?;
-        let user_signature_hash = keccak256(request_data.user_signature.as_bytes());
-
-        let message_to_sign = keccak256(ethers::abi::encode(&[
-            ethers::abi::Token::FixedBytes(user_op_hash.to_vec()),
-            ethers::abi::Token::String(request_data.account_id.clone()),
-            ethers::abi::Token::FixedBytes(user_signature_hash.to_vec()),
-            ethers::abi::Token::Uint(U256::from(request_data.nonce)),
-            ethers::abi::Token::Uint(U256::from(request_data.timestamp)),
-        ]));
+        let message_to_sign = self.config.signing_format.message(request_data, path)?;
 
         let signature = signer
             .sign_message(message_to_sign)
@@ -246,13 +561,13 @@ impl AirAccountKmsClient {
         request_data: &KmsDualSignRequest,
         paymaster_signature: &str,
         paymaster_address: &str,
-    ) -> Result<KmsSignResponse> {
-        let url = format!("{}/kms/sign-user-operation", self.base_url);
+    ) -> Result<KmsSignResponse, KmsError> {
+        let url = format!("{}{}", self.base_url(), SIGN_USER_OPERATION_PATH);
 
         debug!("📤 Sending KMS request to: {}", url);
 
         let response = self
-            .http_client
+            .http_client()
             .post(&url)
             .header("Content-Type", "application/json")
             .header("X-Paymaster-Signature", paymaster_signature)
@@ -261,21 +576,13 @@ impl AirAccountKmsClient {
             .timeout(self.timeout)
             .send()
             .await
-            .map_err(|e| anyhow!("Failed to send KMS request: {}", e))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("KMS request failed: {} - {}", status, error_text));
-        }
-
-        let kms_response: KmsSignResponse = response
-            .json()
-            .await
-            .map_err(|e| anyhow!("Failed to parse KMS response: {}", e))?;
+            .map_err(|e| transport_error(&url, e))?;
 
+        let kms_response: KmsSignResponse = read_json(&url, response).await?;
         if !kms_response.success {
-            return Err(anyhow!("KMS signing failed"));
+            return Err(KmsError::SignatureFailed {
+                reason: format!("{} reported a signing failure", url),
+            });
         }
 
         debug!("✅ KMS request successful");
@@ -283,40 +590,27 @@ impl AirAccountKmsClient {
     }
 
     /// 检查 KMS 服务状态
-    pub async fn check_status(&self) -> Result<KmsStatusResponse> {
-        let url = format!("{}/kms/status", self.base_url);
+    pub async fn check_status(&self) -> Result<KmsStatusResponse, KmsError> {
+        let url = format!("{}{}", self.base_url(), STATUS_PATH);
 
         debug!("📊 Checking KMS status: {}", url);
 
         let response = self
-            .http_client
+            .http_client()
             .get(&url)
             .timeout(Duration::from_secs(10))
             .send()
             .await
-            .map_err(|e| anyhow!("Failed to check KMS status: {}", e))?;
+            .map_err(|e| transport_error(&url, e))?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow!(
-                "KMS status check failed: {} - {}",
-                status,
-                error_text
-            ));
-        }
-
-        let status_response: KmsStatusResponse = response
-            .json()
-            .await
-            .map_err(|e| anyhow!("Failed to parse KMS status: {}", e))?;
+        let status_response: KmsStatusResponse = read_json(&url, response).await?;
 
         debug!("✅ KMS status retrieved");
         Ok(status_response)
     }
 
-    /// 计算 UserOperation 哈希（ERC-4337 标准）
-    fn get_user_operation_hash(user_op: &Value) -> Result<[u8; 32]> {
+    /// 计算 UserOperation 哈希（ERC-4337 标准），即用户签名与 WebAuthn challenge 的内容
+    pub fn get_user_operation_hash(user_op: &Value) -> Result<[u8; 32]> {
         // 提取 UserOperation 字段
         let sender = user_op["sender"]
             .as_str()
@@ -400,28 +694,222 @@ impl AirAccountKmsClient {
 
     /// 获取 KMS 基础 URL
     pub fn base_url(&self) -> &str {
-        &self.base_url
+        self.config.base_url.trim_end_matches('/')
+    }
+
+    /// 请求签名格式
+    pub fn signing_format(&self) -> KmsSigningFormat {
+        self.config.signing_format
+    }
+}
+
+/// 按配置构建 HTTP 客户端：rustls、可选的 CA 证书包与客户端证书
+fn build_http_client(config: &AirAccountKmsConfig) -> Result<Client, KmsError> {
+    let mut builder = Client::builder()
+        .use_rustls_tls()
+        .https_only(!config.insecure_allow_http)
+        .timeout(Duration::from_secs(config.timeout_seconds))
+        .user_agent("SuperRelay-Paymaster/1.0");
+
+    if let Some(path) = &config.ca_bundle {
+        let bundle = read_pem(path)?;
+        let certificates = pem_certificates(&bundle);
+        if certificates.is_empty() {
+            return Err(KmsError::InvalidConfiguration {
+                reason: format!("No certificates in CA bundle {}", path.display()),
+            });
+        }
+        builder = builder.tls_built_in_root_certs(false);
+        for pem in certificates {
+            let certificate = Certificate::from_pem(pem.as_bytes()).map_err(|e| {
+                KmsError::InvalidConfiguration {
+                    reason: format!("Invalid certificate in {}: {}", path.display(), e),
+                }
+            })?;
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    if let (Some(cert), Some(key)) = (&config.client_cert, &config.client_key) {
+        let mut pem = Zeroizing::new(read_pem(cert)?.into_bytes());
+        pem.push(b'\n');
+        pem.extend_from_slice(read_pem(key)?.as_bytes());
+        let identity = Identity::from_pem(&pem).map_err(|e| KmsError::InvalidConfiguration {
+            reason: format!(
+                "Invalid client certificate {} or key {}: {}",
+                cert.display(),
+                key.display(),
+                e
+            ),
+        })?;
+        builder = builder.identity(identity);
+    }
+
+    builder.build().map_err(|e| KmsError::InvalidConfiguration {
+        reason: format!("Failed to build AirAccount KMS HTTP client: {}", e),
+    })
+}
+
+fn read_pem(path: &Path) -> Result<String, KmsError> {
+    fs::read_to_string(path).map_err(|e| KmsError::InvalidConfiguration {
+        reason: format!("Failed to read {}: {}", path.display(), e),
+    })
+}
+
+/// CA 证书包中的各个 PEM 证书
+fn pem_certificates(bundle: &str) -> Vec<&str> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    bundle
+        .split_inclusive("-----END CERTIFICATE-----")
+        .filter_map(|block| block.find(BEGIN).map(|start| &block[start..]))
+        .collect()
+}
+
+/// 按失败层区分发送错误：TLS 握手与证书错误，或连接错误
+fn transport_error(url: &str, error: reqwest::Error) -> KmsError {
+    let reason = format!("{}: {}", url, error_chain(&error));
+    if is_tls_error(&error) {
+        KmsError::Tls { reason }
+    } else {
+        KmsError::Connection { reason }
+    }
+}
+
+fn is_tls_error(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(error);
+    while let Some(error) = source {
+        if error.is::<rustls::Error>() {
+            return true;
+        }
+        // hyper 将 TLS 错误包装在 io::Error 中
+        if let Some(io) = error.downcast_ref::<std::io::Error>() {
+            if io
+                .get_ref()
+                .is_some_and(|inner| inner.is::<rustls::Error>())
+            {
+                return true;
+            }
+        }
+        source = error.source();
     }
+    false
+}
+
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        let cause = error.to_string();
+        if !message.contains(&cause) {
+            message = format!("{}: {}", message, cause);
+        }
+        source = error.source();
+    }
+    message
+}
+
+/// 读取 JSON 响应，按 HTTP 状态区分签名被拒与服务错误
+async fn read_json<T: serde::de::DeserializeOwned>(
+    url: &str,
+    response: Response,
+) -> Result<T, KmsError> {
+    let status = response.status();
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| transport_error(url, e))?;
+
+    if !status.is_success() {
+        let reason = format!(
+            "{} returned {} - {}",
+            url,
+            status,
+            String::from_utf8_lossy(&body)
+        );
+        return Err(match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                KmsError::SignatureRejected { reason }
+            }
+            StatusCode::TOO_MANY_REQUESTS => KmsError::Throttled { reason },
+            status if status.is_server_error() => KmsError::ServiceUnavailable { reason },
+            _ => KmsError::SignatureFailed { reason },
+        });
+    }
+
+    serde_json::from_slice(&body).map_err(|e| KmsError::SignatureFailed {
+        reason: format!("Invalid response from {}: {}", url, e),
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use serde_json::json;
+    use std::{
+        collections::HashMap,
+        net::SocketAddr,
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use ethers::types::{Address, Signature};
+    use openssl::{
+        asn1::Asn1Time,
+        bn::BigNum,
+        ec::{EcGroup, EcKey},
+        hash::MessageDigest,
+        nid::Nid,
+        pkey::{PKey, Private},
+        x509::{
+            extension::{BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName},
+            X509NameBuilder, X509,
+        },
+    };
+    use rustls::server::AllowAnyAuthenticatedClient;
+    use serde_json::{json, Value};
+    use tempfile::{tempdir, TempDir};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+    use tokio_rustls::TlsAcceptor;
 
     use super::*;
 
+    fn local_client() -> AirAccountKmsClient {
+        let config = AirAccountKmsConfig {
+            base_url: "http://localhost:3002/".to_string(),
+            insecure_allow_http: true,
+            ..Default::default()
+        };
+        AirAccountKmsClient::new(config, PaymasterKeyManager::new()).unwrap()
+    }
+
+    fn user_op() -> Value {
+        json!({
+            "sender": "0x742D35Cc6634C0532925a3b8D6C18E3CB1EB98C1",
+            "nonce": "0x0",
+            "initCode": "0x",
+            "callData": "0x",
+            "callGasLimit": "0x186a0",
+            "verificationGasLimit": "0x186a0",
+            "preVerificationGas": "0x5208",
+            "maxFeePerGas": "0x59682f00",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "paymasterAndData": "0x"
+        })
+    }
+
     #[tokio::test]
     async fn test_kms_client_creation() {
-        let key_manager = PaymasterKeyManager::new();
-        let client = AirAccountKmsClient::new("http://localhost:3002".to_string(), key_manager);
+        let client = local_client();
 
         assert_eq!(client.base_url(), "http://localhost:3002");
+        assert_eq!(client.signing_format(), KmsSigningFormat::PathAndCounter);
     }
 
     #[tokio::test]
     async fn test_business_validation() {
-        let key_manager = PaymasterKeyManager::new();
-        let client = AirAccountKmsClient::new("http://localhost:3002".to_string(), key_manager);
+        let client = local_client();
 
         let validation = client
             .validate_business_rules("test-account")
@@ -433,30 +921,16 @@ mod tests {
 
     #[tokio::test]
     async fn test_user_operation_hash() {
-        let user_op = json!({
-            "sender": "0x742D35Cc6634C0532925a3b8D6C18E3CB1EB98C1",
-            "nonce": "0x0",
-            "initCode": "0x",
-            "callData": "0x",
-            "callGasLimit": "0x186a0",
-            "verificationGasLimit": "0x186a0",
-            "preVerificationGas": "0x5208",
-            "maxFeePerGas": "0x59682f00",
-            "maxPriorityFeePerGas": "0x3b9aca00",
-            "paymasterAndData": "0x"
-        });
-
-        let hash = AirAccountKmsClient::get_user_operation_hash(&user_op).unwrap();
+        let hash = AirAccountKmsClient::get_user_operation_hash(&user_op()).unwrap();
         assert_eq!(hash.len(), 32);
     }
 
     #[tokio::test]
     async fn test_request_signing() {
-        let key_manager = PaymasterKeyManager::new();
-        let client = AirAccountKmsClient::new("http://localhost:3002".to_string(), key_manager);
+        let client = local_client();
 
         let request = KmsDualSignRequest {
-            user_operation: json!({}),
+            user_operation: user_op(),
             account_id: "test-account".to_string(),
             signature_format: "erc4337".to_string(),
             user_signature: "0x1234567890".to_string(),
@@ -468,11 +942,613 @@ mod tests {
             },
             nonce: 123456789,
             timestamp: 1234567890,
+            request_counter: Some(7),
         };
 
-        let (signature, address) = client.sign_request(&request).await.unwrap();
+        let (signature, address) = client
+            .sign_request(&request, SIGN_USER_OPERATION_PATH)
+            .await
+            .unwrap();
         assert!(signature.starts_with("0x"));
         assert!(address.starts_with("0x"));
         assert_eq!(address.len(), 42); // Ethereum address length
+
+        // 签名消息绑定接口路径与计数器
+        let format = KmsSigningFormat::PathAndCounter;
+        let message = format.message(&request, SIGN_USER_OPERATION_PATH).unwrap();
+        assert_ne!(message, format.message(&request, STATUS_PATH).unwrap());
+        assert_ne!(
+            message,
+            KmsSigningFormat::Legacy
+                .message(&request, SIGN_USER_OPERATION_PATH)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let plaintext = AirAccountKmsConfig {
+            base_url: "http://localhost:3002".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            plaintext.validate(),
+            Err(KmsError::InvalidConfiguration { .. })
+        ));
+
+        let cert_only = AirAccountKmsConfig {
+            client_cert: Some(PathBuf::from("client.pem")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            cert_only.validate(),
+            Err(KmsError::InvalidConfiguration { .. })
+        ));
+        assert!(AirAccountKmsConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_pem_bundle_split() {
+        let bundle = "# root\n-----BEGIN CERTIFICATE-----\nAAA\n-----END CERTIFICATE-----\n\
+                      -----BEGIN CERTIFICATE-----\nBBB\n-----END CERTIFICATE-----\n";
+        assert_eq!(
+            pem_certificates(bundle),
+            [
+                "-----BEGIN CERTIFICATE-----\nAAA\n-----END CERTIFICATE-----",
+                "-----BEGIN CERTIFICATE-----\nBBB\n-----END CERTIFICATE-----"
+            ]
+        );
+    }
+
+    /// Certificate and PKCS#8 key
+    struct Issued {
+        cert: X509,
+        key: PKey<Private>,
+    }
+
+    fn ec_key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    fn unix_now() -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+    }
+
+    /// Certificate for `cn` signed by `issuer`, self-signed when `issuer` is None
+    fn issue(
+        cn: &str,
+        issuer: Option<&Issued>,
+        kind: &str,
+        valid_from: i64,
+        valid_to: i64,
+    ) -> Issued {
+        let key = ec_key();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", cn).unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        let serial = BigNum::from_u32(rand_serial()).unwrap();
+        builder
+            .set_serial_number(&serial.to_asn1_integer().unwrap())
+            .unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder
+            .set_issuer_name(issuer.map_or(&*name, |ca| ca.cert.subject_name()))
+            .unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::from_unix(valid_from).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::from_unix(valid_to).unwrap())
+            .unwrap();
+
+        match kind {
+            "ca" => {
+                builder
+                    .append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+                    .unwrap();
+                builder
+                    .append_extension(
+                        KeyUsage::new()
+                            .critical()
+                            .key_cert_sign()
+                            .crl_sign()
+                            .build()
+                            .unwrap(),
+                    )
+                    .unwrap();
+            }
+            "server" => {
+                let san = SubjectAlternativeName::new()
+                    .dns("localhost")
+                    .ip("127.0.0.1")
+                    .build(&builder.x509v3_context(issuer.map(|ca| &*ca.cert), None))
+                    .unwrap();
+                builder.append_extension(san).unwrap();
+                builder
+                    .append_extension(ExtendedKeyUsage::new().server_auth().build().unwrap())
+                    .unwrap();
+            }
+            _ => {
+                builder
+                    .append_extension(ExtendedKeyUsage::new().client_auth().build().unwrap())
+                    .unwrap();
+            }
+        }
+
+        let signing_key = issuer.map_or(&key, |ca| &ca.key);
+        builder.sign(signing_key, MessageDigest::sha256()).unwrap();
+        Issued {
+            cert: builder.build(),
+            key,
+        }
+    }
+
+    fn rand_serial() -> u32 {
+        rand::random::<u32>() >> 1
+    }
+
+    /// Test PKI: a CA with server and client certificates, and an unrelated CA
+    struct Pki {
+        dir: TempDir,
+        ca: Issued,
+        server: Issued,
+    }
+
+    impl Pki {
+        fn new() -> Self {
+            let now = unix_now();
+            let ca = issue("SuperRelay Test CA", None, "ca", now - 3600, now + 86_400);
+            let server = issue("localhost", Some(&ca), "server", now - 3600, now + 86_400);
+            let pki = Self {
+                dir: tempdir().unwrap(),
+                ca,
+                server,
+            };
+            std::fs::write(pki.path("ca.pem"), pki.ca.cert.to_pem().unwrap()).unwrap();
+
+            let wrong_ca = issue("Unrelated CA", None, "ca", now - 3600, now + 86_400);
+            std::fs::write(pki.path("wrong-ca.pem"), wrong_ca.cert.to_pem().unwrap()).unwrap();
+
+            pki.write_client("client", now - 3600, now + 86_400);
+            pki.write_client("expired", now - 2 * 86_400, now - 86_400);
+            pki
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.dir.path().join(name)
+        }
+
+        /// Client certificate signed by the CA, written to `<name>.pem` and `<name>.key`
+        fn write_client(&self, name: &str, valid_from: i64, valid_to: i64) {
+            let client = issue("paymaster", Some(&self.ca), "client", valid_from, valid_to);
+            std::fs::write(
+                self.path(&format!("{}.pem", name)),
+                client.cert.to_pem().unwrap(),
+            )
+            .unwrap();
+            std::fs::write(
+                self.path(&format!("{}.key", name)),
+                client.key.private_key_to_pem_pkcs8().unwrap(),
+            )
+            .unwrap();
+        }
+
+        /// Client config trusting `ca` and presenting `<client>.pem`
+        fn config(&self, addr: SocketAddr, ca: &str, client: &str) -> AirAccountKmsConfig {
+            AirAccountKmsConfig {
+                base_url: format!("https://localhost:{}", addr.port()),
+                timeout_seconds: 5,
+                ca_bundle: Some(self.path(ca)),
+                client_cert: Some(self.path(&format!("{}.pem", client))),
+                client_key: Some(self.path(&format!("{}.key", client))),
+                // The placeholder user signatures are for the KMS to judge
+                local_verification: LocalVerificationConfig {
+                    enabled: false,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        }
+    }
+
+    /// Request received by the test server
+    #[derive(Debug, Clone)]
+    struct Received {
+        path: String,
+        headers: HashMap<String, String>,
+        body: Value,
+    }
+
+    /// KMS stand-in requiring client certificates issued by the test CA
+    struct KmsServer {
+        addr: SocketAddr,
+        received: Arc<Mutex<Vec<Received>>>,
+    }
+
+    impl KmsServer {
+        /// Serve until the test ends; `status` is returned for signing requests
+        async fn start(pki: &Pki, status: u16) -> Self {
+            let mut roots = rustls::RootCertStore::empty();
+            roots
+                .add(&rustls::Certificate(pki.ca.cert.to_der().unwrap()))
+                .unwrap();
+            // TLS 1.2 so that a rejected client certificate fails the client's handshake
+            let config = rustls::ServerConfig::builder()
+                .with_safe_default_cipher_suites()
+                .with_safe_default_kx_groups()
+                .with_protocol_versions(&[&rustls::version::TLS12])
+                .unwrap()
+                .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+                .with_single_cert(
+                    vec![rustls::Certificate(pki.server.cert.to_der().unwrap())],
+                    rustls::PrivateKey(pki.server.key.private_key_to_pkcs8().unwrap()),
+                )
+                .unwrap();
+            let acceptor = TlsAcceptor::from(Arc::new(config));
+
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let log = received.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let (acceptor, log) = (acceptor.clone(), log.clone());
+                    tokio::spawn(async move {
+                        if let Ok(stream) = acceptor.accept(stream).await {
+                            serve(stream, status, log).await;
+                        }
+                    });
+                }
+            });
+            Self { addr, received }
+        }
+
+        fn received(&self) -> Vec<Received> {
+            self.received.lock().unwrap().clone()
+        }
+    }
+
+    /// Answer one HTTP request on `stream`
+    async fn serve<S>(mut stream: S, status: u16, log: Arc<Mutex<Vec<Received>>>)
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        let head_len = loop {
+            if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+            match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            }
+        };
+
+        let head = String::from_utf8_lossy(&buf[..head_len]).to_string();
+        let mut lines = head.lines();
+        let path = lines
+            .next()
+            .and_then(|line| line.split(' ').nth(1))
+            .unwrap_or_default()
+            .to_string();
+        let headers: HashMap<String, String> = lines
+            .filter_map(|line| line.split_once(": "))
+            .map(|(name, value)| (name.to_lowercase(), value.to_string()))
+            .collect();
+        let length: usize = headers
+            .get("content-length")
+            .and_then(|len| len.parse().ok())
+            .unwrap_or(0);
+        while buf.len() < head_len + length {
+            match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+        let body = serde_json::from_slice(&buf[head_len..head_len + length]).unwrap_or(Value::Null);
+        log.lock().unwrap().push(Received {
+            path: path.clone(),
+            headers,
+            body,
+        });
+
+        let (status, response) = match path.as_str() {
+            STATUS_PATH => (
+                200,
+                json!({
+                    "success": true,
+                    "status": {
+                        "service": "airaccount-kms",
+                        "mode": "tee",
+                        "teeConnection": "connected",
+                        "authorizedPaymastersCount": 1,
+                        "activeNoncesCount": 0,
+                        "features": ["dual-signature"]
+                    },
+                    "timestamp": "2026-10-15T00:00:00Z"
+                }),
+            ),
+            SIGN_USER_OPERATION_PATH if status == 200 => (
+                200,
+                json!({
+                    "success": true,
+                    "signature": format!("0x{}", "11".repeat(65)),
+                    "userOpHash": format!("0x{}", "22".repeat(32)),
+                    "teeDeviceId": "tee_device_001",
+                    "verificationProof": {
+                        "paymasterVerified": true,
+                        "userPasskeyVerified": true,
+                        "dualSignatureMode": true,
+                        "timestamp": "2026-10-15T00:00:00Z"
+                    }
+                }),
+            ),
+            _ => (
+                status,
+                json!({ "success": false, "error": "invalid paymaster signature" }),
+            ),
+        };
+        let response = response.to_string();
+        let reply = format!(
+            "HTTP/1.1 {} Test\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            response.len(),
+            response
+        );
+        let _ = stream.write_all(reply.as_bytes()).await;
+        let _ = stream.shutdown().await;
+    }
+
+    async fn sign(client: &AirAccountKmsClient) -> anyhow::Result<()> {
+        client
+            .sign_user_operation(
+                &user_op(),
+                "passkey_user_alice",
+                "erc4337",
+                "0x1234",
+                "0xabcdef",
+            )
+            .await
+            .map(|_| ())
+    }
+
+    fn kms_error(result: anyhow::Result<()>) -> KmsError {
+        result
+            .unwrap_err()
+            .downcast::<KmsError>()
+            .expect("KMS transport error")
+    }
+
+    /// The signed request the server received, rebuilt from its JSON body
+    fn signed_request(received: &Received) -> KmsDualSignRequest {
+        let body = &received.body;
+        KmsDualSignRequest {
+            user_operation: body["userOperation"].clone(),
+            account_id: body["accountId"].as_str().unwrap().to_string(),
+            signature_format: body["signatureFormat"].as_str().unwrap().to_string(),
+            user_signature: body["userSignature"].as_str().unwrap().to_string(),
+            user_public_key: body["userPublicKey"].as_str().unwrap().to_string(),
+            business_validation: BusinessValidation {
+                balance: body["businessValidation"]["balance"]
+                    .as_str()
+                    .unwrap()
+                    .to_string(),
+                membership_level: body["businessValidation"]["membershipLevel"]
+                    .as_str()
+                    .unwrap()
+                    .to_string(),
+                approved_at: body["businessValidation"]["approvedAt"].as_u64().unwrap(),
+            },
+            nonce: body["nonce"].as_u64().unwrap(),
+            timestamp: body["timestamp"].as_u64().unwrap(),
+            request_counter: body["requestCounter"].as_u64(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls_signing_succeeds() {
+        let pki = Pki::new();
+        let server = KmsServer::start(&pki, 200).await;
+        let client = AirAccountKmsClient::new(
+            pki.config(server.addr, "ca.pem", "client"),
+            PaymasterKeyManager::new(),
+        )
+        .unwrap();
+
+        let status = client.check_status().await.unwrap();
+        assert_eq!(status.status.tee_connection, "connected");
+        sign(&client).await.unwrap();
+        sign(&client).await.unwrap();
+
+        let received: Vec<_> = server
+            .received()
+            .into_iter()
+            .filter(|r| r.path == SIGN_USER_OPERATION_PATH)
+            .collect();
+        assert_eq!(received.len(), 2);
+
+        // The paymaster signature covers the endpoint path and the request counter
+        let mut counters = Vec::new();
+        for received in &received {
+            let request = signed_request(received);
+            counters.push(request.request_counter.unwrap());
+            let message = KmsSigningFormat::PathAndCounter
+                .message(&request, SIGN_USER_OPERATION_PATH)
+                .unwrap();
+            let signature: Signature = received.headers["x-paymaster-signature"].parse().unwrap();
+            let signer = signature.recover(message.to_vec()).unwrap();
+            assert_eq!(
+                signer,
+                received.headers["x-paymaster-address"]
+                    .parse::<Address>()
+                    .unwrap()
+            );
+
+            let other_path = KmsSigningFormat::PathAndCounter
+                .message(&request, STATUS_PATH)
+                .unwrap();
+            assert_ne!(signature.recover(other_path.to_vec()).unwrap(), signer);
+        }
+        assert!(counters[1] > counters[0]);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_signing_format_omits_counter() {
+        let pki = Pki::new();
+        let server = KmsServer::start(&pki, 200).await;
+        let config = AirAccountKmsConfig {
+            signing_format: KmsSigningFormat::Legacy,
+            ..pki.config(server.addr, "ca.pem", "client")
+        };
+        let client = AirAccountKmsClient::new(config, PaymasterKeyManager::new()).unwrap();
+
+        sign(&client).await.unwrap();
+        let received = server.received().pop().unwrap();
+        assert!(received.body.get("requestCounter").is_none());
+
+        let message = KmsSigningFormat::Legacy
+            .message(&signed_request(&received), SIGN_USER_OPERATION_PATH)
+            .unwrap();
+        let signature: Signature = received.headers["x-paymaster-signature"].parse().unwrap();
+        assert_eq!(
+            signature.recover(message.to_vec()).unwrap(),
+            received.headers["x-paymaster-address"]
+                .parse::<Address>()
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wrong_ca_is_tls_error() {
+        let pki = Pki::new();
+        let server = KmsServer::start(&pki, 200).await;
+        let client = AirAccountKmsClient::new(
+            pki.config(server.addr, "wrong-ca.pem", "client"),
+            PaymasterKeyManager::new(),
+        )
+        .unwrap();
+
+        let err = kms_error(sign(&client).await);
+        assert!(matches!(err, KmsError::Tls { .. }), "{}", err);
+        assert!(!err.is_retryable());
+        assert!(matches!(
+            client.check_status().await,
+            Err(KmsError::Tls { .. })
+        ));
+        assert!(server.received().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expired_client_certificate_is_tls_error() {
+        let pki = Pki::new();
+        let server = KmsServer::start(&pki, 200).await;
+        let client = AirAccountKmsClient::new(
+            pki.config(server.addr, "ca.pem", "expired"),
+            PaymasterKeyManager::new(),
+        )
+        .unwrap();
+
+        let err = kms_error(sign(&client).await);
+        assert!(matches!(err, KmsError::Tls { .. }), "{}", err);
+        assert!(server.received().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_signature_and_connection_errors() {
+        let pki = Pki::new();
+        let server = KmsServer::start(&pki, 401).await;
+        let client = AirAccountKmsClient::new(
+            pki.config(server.addr, "ca.pem", "client"),
+            PaymasterKeyManager::new(),
+        )
+        .unwrap();
+        let err = kms_error(sign(&client).await);
+        assert!(matches!(err, KmsError::SignatureRejected { .. }), "{}", err);
+        assert!(err.to_string().contains("invalid paymaster signature"));
+
+        // Nothing listens on the port any more
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed = listener.local_addr().unwrap();
+        drop(listener);
+        let client = AirAccountKmsClient::new(
+            pki.config(closed, "ca.pem", "client"),
+            PaymasterKeyManager::new(),
+        )
+        .unwrap();
+        let err = kms_error(sign(&client).await);
+        assert!(matches!(err, KmsError::Connection { .. }), "{}", err);
+        assert!(err.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_http_requires_opt_in() {
+        let config = AirAccountKmsConfig {
+            base_url: "http://localhost:3002".to_string(),
+            ..Default::default()
+        };
+        let err = AirAccountKmsClient::new(config.clone(), PaymasterKeyManager::new())
+            .err()
+            .unwrap();
+        assert!(matches!(err, KmsError::InvalidConfiguration { .. }));
+        assert!(err.to_string().contains("insecure_allow_http"));
+
+        let config = AirAccountKmsConfig {
+            insecure_allow_http: true,
+            ..config
+        };
+        assert!(AirAccountKmsClient::new(config, PaymasterKeyManager::new()).is_ok());
+
+        let missing = AirAccountKmsConfig {
+            ca_bundle: Some(Path::new("/nonexistent/ca.pem").to_path_buf()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            AirAccountKmsClient::new(missing, PaymasterKeyManager::new()),
+            Err(KmsError::InvalidConfiguration { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_certificates_reload_without_restart() {
+        let pki = Pki::new();
+        let server = KmsServer::start(&pki, 200).await;
+        let cert = pki.path("rotating.pem");
+        let key = pki.path("rotating.key");
+        std::fs::copy(pki.path("expired.pem"), &cert).unwrap();
+        std::fs::copy(pki.path("expired.key"), &key).unwrap();
+        let client = AirAccountKmsClient::new(
+            pki.config(server.addr, "ca.pem", "rotating"),
+            PaymasterKeyManager::new(),
+        )
+        .unwrap();
+        assert!(!client.reload_tls().unwrap());
+        assert!(matches!(
+            kms_error(sign(&client).await),
+            KmsError::Tls { .. }
+        ));
+
+        // Renewed certificate is picked up
+        std::fs::copy(pki.path("client.pem"), &cert).unwrap();
+        std::fs::copy(pki.path("client.key"), &key).unwrap();
+        assert!(client.reload_tls().unwrap());
+        assert!(!client.reload_tls().unwrap());
+        sign(&client).await.unwrap();
+
+        // A broken key keeps the loaded certificate
+        std::fs::write(&key, "not a key").unwrap();
+        assert!(matches!(
+            client.reload_tls(),
+            Err(KmsError::InvalidConfiguration { .. })
+        ));
+        sign(&client).await.unwrap();
     }
 }
@@ -12,6 +12,7 @@ use utoipa::{OpenApi, ToSchema};
     paths(
         crate::proxy_server::json_rpc_proxy_handler,
         crate::proxy_server::proxy_health_check,
+        crate::proxy_server::proxy_status,
         crate::proxy_server::readiness_check,
         crate::proxy_server::get_examples
     ),
@@ -5,18 +5,18 @@ use std::sync::Arc;
 
 use axum::{
     extract::State,
-    http::{Method, StatusCode},
+    http::StatusCode,
     response::Json,
     routing::{get, post},
     Router,
 };
-use tower_http::cors::{Any, CorsLayer};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
     api_handlers::health_check_handler,
     api_schemas::ApiDoc,
+    cors::{CorsConfig, CorsConfigError},
     rpc::{PaymasterRelayApiServer, PaymasterRelayApiServerImpl},
 };
 
@@ -163,30 +163,30 @@ async fn handle_health_check_rpc() -> serde_json::Value {
 }
 
 /// Create the Axum router with all API endpoints and Swagger UI
-pub fn create_api_router(app_state: AppState) -> Router {
-    Router::new()
+pub fn create_api_router(
+    app_state: AppState,
+    cors: &CorsConfig,
+) -> Result<Router, CorsConfigError> {
+    let router = Router::new()
         // JSON-RPC endpoint (root path for blockchain tools compatibility)
         .route("/", post(json_rpc_handler))
         .route("/health", get(health_check_handler))
         // Swagger UI - serve the auto-generated OpenAPI spec
         .merge(SwaggerUi::new("/swagger-ui").url("/api-doc/openapi.json", ApiDoc::openapi()))
-        // CORS configuration for web UI integration
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
-                .allow_headers(Any),
-        )
         // Shared state
-        .with_state(app_state)
+        .with_state(app_state);
+
+    // CORS configuration for web UI integration
+    cors.apply(router)
 }
 
 /// Start the HTTP API server with utoipa-generated documentation
 pub async fn start_api_server(
     bind_address: &str,
     rpc_impl: Arc<PaymasterRelayApiServerImpl>,
+    cors: &CorsConfig,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let app = create_api_router(rpc_impl);
+    let app = create_api_router(rpc_impl, cors)?;
 
     let listener = tokio::net::TcpListener::bind(bind_address).await?;
 
@@ -0,0 +1,348 @@
+// paymaster-relay/src/attestation.rs
+// Signed attestations of sponsorship responses, so clients that cache a response
+// can later prove it came from this relay. Attestations are signed by a dedicated
+// key, never the paymaster signing key, and the key's address is served by
+// pm_getAttestationKey for offline verification.
+//
+// An attestation covers the response payload (the JSON result without its
+// `attestation` field) in canonical form:
+//   - objects have their keys sorted by byte order, arrays keep their order
+//   - no whitespace between tokens
+//   - strings are JSON-escaped; 0x-prefixed hex strings are lowercased, so
+//     checksummed addresses hash like their lowercase form
+//   - numbers, booleans and null are written as serde_json writes them
+// The signed digest is keccak256(payloadHash ‖ uint256(timestamp)), i.e.
+// keccak256(abi.encode(bytes32, uint256)), signed without an EIP-191 prefix.
+
+use std::str::FromStr;
+
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{RecoveryMessage, Signature, H256},
+};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{error::PaymasterError, secrets};
+
+/// Response attestation settings (`[paymaster_relay.attestation]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AttestationConfig {
+    /// Sign responses of requests passing `"attest": true`
+    pub enabled: bool,
+    /// Environment variable holding the attestation private key
+    pub key_env: String,
+}
+
+impl Default for AttestationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            key_env: "ATTESTATION_PRIVATE_KEY".to_string(),
+        }
+    }
+}
+
+/// Proof that a response payload was produced by the relay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attestation {
+    /// keccak256 of the canonical payload
+    pub payload_hash: B256,
+    /// Unix time the attestation was signed
+    pub timestamp: u64,
+    /// Attestation key address
+    pub signer: Address,
+    /// 65 byte r ‖ s ‖ v signature of the attestation digest
+    pub signature: Bytes,
+}
+
+/// Signs attestations with the dedicated attestation key
+#[derive(Debug, Clone)]
+pub struct Attestor {
+    wallet: LocalWallet,
+}
+
+impl Attestor {
+    pub fn new(private_key: SecretString) -> Result<Self, PaymasterError> {
+        let wallet = LocalWallet::from_str(private_key.expose_secret().trim()).map_err(|e| {
+            PaymasterError::SignerError(eyre::eyre!("Invalid attestation key: {}", e))
+        })?;
+        Ok(Self { wallet })
+    }
+
+    /// Attestor with the key from the configured environment variable
+    pub fn from_config(config: &AttestationConfig) -> Result<Self, PaymasterError> {
+        let private_key = secrets::var(&config.key_env).ok_or_else(|| {
+            PaymasterError::SignerError(eyre::eyre!(
+                "Environment variable {} with the attestation key is not set",
+                config.key_env
+            ))
+        })?;
+        Self::new(private_key)
+    }
+
+    /// Address verifiers check attestation signatures against
+    pub fn address(&self) -> Address {
+        Address::from_slice(self.wallet.address().as_bytes())
+    }
+
+    /// Attest `payload` as of `timestamp`
+    pub fn attest(&self, payload: &Value, timestamp: u64) -> Result<Attestation, PaymasterError> {
+        let payload_hash = payload_hash(payload);
+        let digest = attestation_digest(payload_hash, timestamp);
+        let signature = self
+            .wallet
+            .sign_hash(H256::from(digest.0))
+            .map_err(|e| PaymasterError::SignerError(eyre::eyre!("Attestation failed: {}", e)))?;
+        Ok(Attestation {
+            payload_hash,
+            timestamp,
+            signer: self.address(),
+            signature: Bytes::from(signature.to_vec()),
+        })
+    }
+}
+
+/// Canonical form of `value`, see the module documentation
+pub fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let fields: Vec<String> = entries
+                .into_iter()
+                .map(|(key, value)| {
+                    format!("{}:{}", Value::from(key.as_str()), canonical_json(value))
+                })
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        Value::String(s) if is_hex(s) => Value::from(s.to_ascii_lowercase()).to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn is_hex(s: &str) -> bool {
+    s.strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .is_some_and(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+/// keccak256 of the canonical `payload`
+pub fn payload_hash(payload: &Value) -> B256 {
+    keccak256(canonical_json(payload).as_bytes())
+}
+
+/// Digest signed by the attestation key
+pub fn attestation_digest(payload_hash: B256, timestamp: u64) -> B256 {
+    keccak256(
+        [
+            payload_hash.as_slice(),
+            &U256::from(timestamp).to_be_bytes::<32>(),
+        ]
+        .concat(),
+    )
+}
+
+/// Check `attestation` covers `payload` and was signed by its signer
+///
+/// `payload` is the response without its `attestation` field. Callers still
+/// compare the returned signer with the address from pm_getAttestationKey.
+pub fn verify_attestation(
+    payload: &Value,
+    attestation: &Attestation,
+) -> Result<Address, PaymasterError> {
+    if payload_hash(payload) != attestation.payload_hash {
+        return Err(PaymasterError::InvalidRequest(
+            "Attestation does not cover this payload".to_string(),
+        ));
+    }
+    let signature = Signature::try_from(attestation.signature.as_ref()).map_err(|e| {
+        PaymasterError::InvalidRequest(format!("Invalid attestation signature: {}", e))
+    })?;
+    let digest = attestation_digest(attestation.payload_hash, attestation.timestamp);
+    let recovered = signature
+        .recover(RecoveryMessage::Hash(H256::from(digest.0)))
+        .map_err(|e| {
+            PaymasterError::InvalidRequest(format!("Invalid attestation signature: {}", e))
+        })?;
+    let recovered = Address::from_slice(recovered.as_bytes());
+    if recovered != attestation.signer {
+        return Err(PaymasterError::InvalidRequest(format!(
+            "Attestation signed by {}, not {}",
+            recovered, attestation.signer
+        )));
+    }
+    Ok(recovered)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use alloy_primitives::{Address, Bytes, U256};
+    use rundler_types::{chain::ChainSpec, v0_7, UserOperationVariant};
+    use secrecy::SecretString;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        service::{PaymasterRelayService, SponsorOptions},
+        test_utils, PaymasterError,
+    };
+
+    #[test]
+    fn test_canonical_json() {
+        assert_eq!(
+            canonical_json(&json!({
+                "validUntil": 10,
+                "paymasterAndData": "0xABcd",
+                "nested": {"b": [2, "0XFF", "Text"], "a": null},
+            })),
+            r#"{"nested":{"a":null,"b":[2,"0xff","Text"]},"paymasterAndData":"0xabcd","validUntil":10}"#
+        );
+        // Not hex, left as is
+        assert_eq!(canonical_json(&json!("0xZZ")), r#""0xZZ""#);
+    }
+
+    const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const ATTESTATION_KEY: &str =
+        "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+    fn attestor() -> Attestor {
+        Attestor::new(SecretString::new(ATTESTATION_KEY.to_string().into())).unwrap()
+    }
+
+    fn sponsorship() -> Value {
+        json!({
+            "paymasterAndData": "0x63C0C19a282a1B52b07dD5a65b58948A07DAE32B00000000000000000000000000000000",
+            "userOpHash": "0x7d6e0c1a4b8c5a3b2e9f0d1c2b3a4958677869a0b1c2d3e4f5061728394a5b6c",
+            "validUntil": 1_700_000_600u64,
+            "validAfter": 1_700_000_000u64,
+            "policyId": "default",
+        })
+    }
+
+    fn create_service() -> PaymasterRelayService {
+        test_utils::service(&format!("senders = [\"{}\"]\n", SENDER))
+    }
+
+    #[test]
+    fn test_attestation_round_trip() {
+        let attestor = attestor();
+        let mut response = sponsorship();
+        response["attestation"] =
+            serde_json::to_value(attestor.attest(&response, 1_700_000_000).unwrap()).unwrap();
+
+        // A client caches the serialized response and verifies it later
+        let cached = serde_json::to_string(&response).unwrap();
+        let mut payload: Value = serde_json::from_str(&cached).unwrap();
+        let attestation: Attestation = serde_json::from_value(
+            payload
+                .as_object_mut()
+                .unwrap()
+                .remove("attestation")
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(attestation.timestamp, 1_700_000_000);
+        assert_eq!(
+            verify_attestation(&payload, &attestation).unwrap(),
+            attestor.address()
+        );
+
+        // Hex case does not change the payload hash
+        payload["paymasterAndData"] = json!(payload["paymasterAndData"]
+            .as_str()
+            .unwrap()
+            .to_ascii_lowercase());
+        assert_eq!(
+            verify_attestation(&payload, &attestation).unwrap(),
+            attestor.address()
+        );
+
+        // Any other change does
+        payload["validUntil"] = json!(1_800_000_000u64);
+        assert!(matches!(
+            verify_attestation(&payload, &attestation),
+            Err(PaymasterError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_attestation_rejects_forged_fields() {
+        let attestor = attestor();
+        let payload = sponsorship();
+        let attestation = attestor.attest(&payload, 1_700_000_000).unwrap();
+
+        // The timestamp is part of the signed digest
+        let mut backdated = attestation.clone();
+        backdated.timestamp -= 3600;
+        assert!(verify_attestation(&payload, &backdated).is_err());
+
+        // Claiming another signer fails
+        let mut impersonated = attestation.clone();
+        impersonated.signer = Address::from_str(SENDER).unwrap();
+        assert!(verify_attestation(&payload, &impersonated).is_err());
+
+        let mut truncated = attestation;
+        truncated.signature = Bytes::from(vec![0u8; 10]);
+        assert!(verify_attestation(&payload, &truncated).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_service_attestation_configuration() {
+        let service = create_service();
+        assert!(matches!(
+            service.attestation_address(),
+            Err(PaymasterError::InvalidRequest(_))
+        ));
+
+        // Requests for an attestation fail before sponsoring when no key is loaded
+        let op = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender: Address::from_str(SENDER).unwrap(),
+                nonce: U256::ZERO,
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 1_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        let entry_point = ethers::types::Address::from_slice(
+            ChainSpec::default().entry_point_address_v0_7.as_slice(),
+        );
+        let result = service
+            .sponsor_user_operation(
+                UserOperationVariant::V0_7(op),
+                entry_point,
+                SponsorOptions {
+                    attest: true,
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(matches!(result, Err(PaymasterError::InvalidRequest(_))));
+
+        let service = service.with_attestor(attestor());
+        assert_eq!(service.attestation_address().unwrap(), attestor().address());
+        let attestation = service.attest_response(&sponsorship()).unwrap();
+        assert_eq!(
+            verify_attestation(&sponsorship(), &attestation).unwrap(),
+            attestor().address()
+        );
+    }
+}
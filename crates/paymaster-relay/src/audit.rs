@@ -0,0 +1,517 @@
+// paymaster-relay/src/audit.rs
+// Structured audit trail of sponsorship decisions for compliance.
+
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use alloy_primitives::{keccak256, Address, B256};
+use rundler_types::{UserOperation, UserOperationVariant};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{canonical, error::PaymasterError, service::PaymasterSponsorResult, sponsorship};
+
+/// Tracing target used when no audit log file is configured
+pub const AUDIT_LOG_TARGET: &str = "paymaster_audit";
+
+/// Bytes of callData kept when sensitive fields are not logged (the selector)
+const CALL_DATA_PREFIX_BYTES: usize = 4;
+
+/// Audit log configuration (`[paymaster_relay.audit_log]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AuditLogConfig {
+    /// JSON lines file; records go to the `paymaster_audit` tracing target when unset
+    pub path: Option<PathBuf>,
+    /// Rotate the file once it would grow beyond this many bytes
+    pub max_file_size_bytes: u64,
+    /// Number of rotated files to keep (`<path>.1` is the most recent)
+    pub max_files: usize,
+    /// Log full callData instead of its hash and selector
+    pub include_sensitive: bool,
+}
+
+impl Default for AuditLogConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            max_file_size_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+            include_sensitive: false,
+        }
+    }
+}
+
+/// Outcome of a sponsorship request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    Error,
+}
+
+/// One line of the audit log, written per pm_sponsorUserOperation call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// RFC 3339 time the request was received
+    pub timestamp: String,
+    /// Caller identity (API key id) when known
+    pub requester: Option<String>,
+    /// Correlation id of the gateway request, when known
+    #[serde(default)]
+    pub request_id: Option<String>,
+    pub chain_id: u64,
+    pub sender: String,
+    pub entry_point: String,
+    pub nonce: String,
+    pub user_op_hash: String,
+    /// keccak256 of the operation's canonical JSON, matching across field
+    /// order and hex casing
+    #[serde(default)]
+    pub user_op_digest: String,
+    /// Policy that allowed the operation; unset when rejected by policy
+    pub policy_id: Option<String>,
+    /// Maximum gas cost in wei, as a decimal string
+    pub max_gas_cost: String,
+    pub call_data_hash: String,
+    pub call_data_len: usize,
+    /// Full callData when sensitive logging is enabled, otherwise the selector
+    pub call_data: String,
+    pub call_data_truncated: bool,
+    /// Produced paymasterAndData, set on success
+    pub paymaster_and_data: Option<String>,
+    /// Key that signed the sponsorship, set on success
+    #[serde(default)]
+    pub signer: Option<String>,
+    /// Returned the earlier sponsorship of the request's idempotency key, or
+    /// of an identical request from the signature cache, instead of signing again
+    #[serde(default)]
+    pub replayed: bool,
+    pub outcome: AuditOutcome,
+    /// JSON-RPC error code, set on error
+    pub error_code: Option<i32>,
+    pub error_message: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl AuditRecord {
+    /// Start a record for `user_op` before it is processed
+    pub fn new(
+        user_op: &UserOperationVariant,
+        entry_point: Address,
+        requester: Option<String>,
+        include_sensitive: bool,
+    ) -> Self {
+        let call_data = user_op.call_data();
+        let call_data_truncated = !include_sensitive && call_data.len() > CALL_DATA_PREFIX_BYTES;
+        let logged_call_data = if call_data_truncated {
+            &call_data[..CALL_DATA_PREFIX_BYTES]
+        } else {
+            &call_data[..]
+        };
+
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            requester,
+            request_id: None,
+            chain_id: user_op.chain_id(),
+            sender: user_op.sender().to_checksum(None),
+            entry_point: entry_point.to_checksum(None),
+            nonce: format!("{:#x}", user_op.nonce()),
+            user_op_hash: format_b256(user_op.hash()),
+            user_op_digest: format_b256(canonical::user_operation_digest(user_op)),
+            policy_id: None,
+            max_gas_cost: sponsorship::sponsored_max_gas_cost(user_op).to_string(),
+            call_data_hash: format_b256(keccak256(call_data)),
+            call_data_len: call_data.len(),
+            call_data: format!("0x{}", hex::encode(logged_call_data)),
+            call_data_truncated,
+            paymaster_and_data: None,
+            signer: None,
+            replayed: false,
+            outcome: AuditOutcome::Error,
+            error_code: None,
+            error_message: None,
+            duration_ms: 0,
+        }
+    }
+
+    /// Fill in the outcome of the request
+    pub fn complete(
+        &mut self,
+        result: &Result<PaymasterSponsorResult, PaymasterError>,
+        duration: Duration,
+    ) {
+        self.duration_ms = duration.as_millis() as u64;
+        match result {
+            Ok(sponsor_result) => {
+                self.outcome = AuditOutcome::Success;
+                self.paymaster_and_data = Some(format!(
+                    "0x{}",
+                    hex::encode(&sponsor_result.paymaster_and_data)
+                ));
+            }
+            Err(error) => {
+                self.outcome = AuditOutcome::Error;
+                self.error_code = Some(error.code());
+                self.error_message = Some(error.to_string());
+            }
+        }
+    }
+}
+
+fn format_b256(value: B256) -> String {
+    format!("{:#x}", value)
+}
+
+/// Writes audit records as JSON lines to a size-rotated file or a tracing target
+#[derive(Debug, Clone, Default)]
+pub struct AuditLogger {
+    sink: Option<Arc<Mutex<RotatingFile>>>,
+    include_sensitive: bool,
+}
+
+impl AuditLogger {
+    pub fn new(config: &AuditLogConfig) -> Self {
+        Self {
+            sink: config.path.as_ref().map(|path| {
+                Arc::new(Mutex::new(RotatingFile::new(
+                    path.clone(),
+                    config.max_file_size_bytes,
+                    config.max_files,
+                )))
+            }),
+            include_sensitive: config.include_sensitive,
+        }
+    }
+
+    /// Whether full callData is logged
+    pub fn include_sensitive(&self) -> bool {
+        self.include_sensitive
+    }
+
+    /// Write one record; failures are logged and never fail the request
+    pub fn log(&self, record: &AuditRecord) {
+        self.log_event(record)
+    }
+
+    /// Write any other audited event, such as an admin change, as one line
+    pub fn log_event<T: Serialize>(&self, event: &T) {
+        let line = match serde_json::to_string(event) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to serialize audit record: {}", e);
+                return;
+            }
+        };
+
+        match &self.sink {
+            Some(sink) => {
+                let mut sink = sink.lock().unwrap_or_else(|e| e.into_inner());
+                if let Err(e) = sink.write_line(&line) {
+                    warn!("Failed to write audit log {}: {}", sink.path.display(), e);
+                }
+            }
+            None => info!(target: AUDIT_LOG_TARGET, "{}", line),
+        }
+    }
+}
+
+/// Append-only file that rotates to `<path>.1 .. <path>.N` by size
+#[derive(Debug)]
+struct RotatingFile {
+    path: PathBuf,
+    max_file_size_bytes: u64,
+    max_files: usize,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn new(path: PathBuf, max_file_size_bytes: u64, max_files: usize) -> Self {
+        Self {
+            path,
+            max_file_size_bytes,
+            max_files,
+            file: None,
+            size: 0,
+        }
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let line_len = line.len() as u64 + 1;
+        if self.file.is_none() {
+            self.open()?;
+        }
+        if self.size > 0 && self.size + line_len > self.max_file_size_bytes {
+            self.rotate()?;
+        }
+
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => return Err(io::Error::other("audit log file is not open")),
+        };
+        file.write_all(line.as_bytes())?;
+        file.write_all(b"\n")?;
+        file.flush()?;
+        self.size += line_len;
+        Ok(())
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = file.metadata()?.len();
+        self.file = Some(file);
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for index in (1..self.max_files).rev() {
+                let from = rotated_path(&self.path, index);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, index + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+
+        self.open()
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut rotated = OsString::from(path.as_os_str());
+    rotated.push(format!(".{}", index));
+    PathBuf::from(rotated)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, path::Path, str::FromStr};
+
+    use alloy_primitives::{Address, Bytes, U256};
+    use rundler_types::{chain::ChainSpec, v0_6, UserOperationVariant};
+    use serde_json::Value;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{
+        service::{PaymasterRelayService, SponsorOptions},
+        test_utils, user_operation_digest,
+    };
+
+    #[test]
+    fn test_rotating_file_rotates_by_size() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let mut file = RotatingFile::new(path.clone(), 16, 2);
+
+        for line in ["first-line", "second-line", "third-line", "fourth-line"] {
+            file.write_line(line).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth-line\n");
+        assert_eq!(
+            fs::read_to_string(rotated_path(&path, 1)).unwrap(),
+            "third-line\n"
+        );
+        assert_eq!(
+            fs::read_to_string(rotated_path(&path, 2)).unwrap(),
+            "second-line\n"
+        );
+        assert!(!rotated_path(&path, 3).exists());
+    }
+
+    const ALLOWED_SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const REJECTED_SENDER: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+
+    const AUDIT_FIELDS: &[&str] = &[
+        "timestamp",
+        "requester",
+        "request_id",
+        "chain_id",
+        "sender",
+        "entry_point",
+        "nonce",
+        "user_op_hash",
+        "user_op_digest",
+        "policy_id",
+        "max_gas_cost",
+        "call_data_hash",
+        "call_data_len",
+        "call_data",
+        "call_data_truncated",
+        "paymaster_and_data",
+        "signer",
+        "replayed",
+        "outcome",
+        "error_code",
+        "error_message",
+        "duration_ms",
+    ];
+
+    fn create_service(dir: &Path, audit_path: &Path) -> PaymasterRelayService {
+        let audit_config = AuditLogConfig {
+            path: Some(audit_path.to_path_buf()),
+            ..Default::default()
+        };
+
+        test_utils::service_in(dir, &format!("senders = [\"{}\"]\n", ALLOWED_SENDER))
+            .with_audit_logger(AuditLogger::new(&audit_config))
+    }
+
+    fn create_user_op(sender: &str) -> UserOperationVariant {
+        let chain_spec = ChainSpec::default();
+        let op = v0_6::UserOperationBuilder::new(
+            &chain_spec,
+            v0_6::UserOperationRequiredFields {
+                sender: Address::from_str(sender).unwrap(),
+                nonce: U256::ZERO,
+                init_code: Bytes::new(),
+                call_data: Bytes::from(vec![0xb6, 0x1d, 0x27, 0xf6, 0x01, 0x02, 0x03, 0x04]),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 1_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                paymaster_and_data: Bytes::new(),
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        UserOperationVariant::V0_6(op)
+    }
+
+    fn entry_point() -> ethers::types::Address {
+        ChainSpec::default()
+            .entry_point_address_v0_6
+            .to_string()
+            .parse()
+            .unwrap()
+    }
+
+    fn read_audit_lines(path: &Path) -> Vec<Value> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn assert_schema(line: &Value) {
+        let keys: BTreeSet<&str> = line
+            .as_object()
+            .expect("audit line must be a JSON object")
+            .keys()
+            .map(String::as_str)
+            .collect();
+        let expected: BTreeSet<&str> = AUDIT_FIELDS.iter().copied().collect();
+        assert_eq!(keys, expected);
+    }
+
+    #[tokio::test]
+    async fn test_audit_line_for_successful_sponsorship() {
+        let dir = tempdir().unwrap();
+        let audit_path = dir.path().join("audit.jsonl");
+        let service = create_service(dir.path(), &audit_path);
+
+        let options = SponsorOptions {
+            requester: Some("partner-a".to_string()),
+            request_id: Some("req-7f3a".to_string()),
+            ..Default::default()
+        };
+        let result = service
+            .sponsor_user_operation(create_user_op(ALLOWED_SENDER), entry_point(), options)
+            .await
+            .unwrap();
+
+        let lines = read_audit_lines(&audit_path);
+        assert_eq!(lines.len(), 1);
+        let line = &lines[0];
+        assert_schema(line);
+
+        assert_eq!(line["outcome"], "success");
+        assert_eq!(line["requester"], "partner-a");
+        assert_eq!(line["request_id"], "req-7f3a");
+        assert_eq!(line["policy_id"], "default");
+        assert_eq!(line["sender"], ALLOWED_SENDER);
+        assert_eq!(line["chain_id"], ChainSpec::default().id);
+        assert_eq!(
+            line["paymaster_and_data"],
+            format!("0x{}", hex::encode(&result.paymaster_and_data))
+        );
+        assert!(line["user_op_hash"].as_str().unwrap().starts_with("0x"));
+        assert_eq!(
+            line["user_op_digest"],
+            format!(
+                "{:#x}",
+                user_operation_digest(&create_user_op(ALLOWED_SENDER))
+            )
+        );
+        assert!(
+            line["max_gas_cost"]
+                .as_str()
+                .unwrap()
+                .parse::<u128>()
+                .unwrap()
+                > 0
+        );
+        assert!(line["error_code"].is_null());
+
+        // callData is reduced to its selector by default
+        assert_eq!(line["call_data"], "0xb61d27f6");
+        assert_eq!(line["call_data_truncated"], true);
+        assert_eq!(line["call_data_len"], 8);
+    }
+
+    #[tokio::test]
+    async fn test_audit_line_for_rejected_sponsorship() {
+        let dir = tempdir().unwrap();
+        let audit_path = dir.path().join("audit.jsonl");
+        let service = create_service(dir.path(), &audit_path);
+
+        let result = service
+            .sponsor_user_operation(
+                create_user_op(REJECTED_SENDER),
+                entry_point(),
+                SponsorOptions::default(),
+            )
+            .await;
+        assert!(result.is_err());
+
+        let lines = read_audit_lines(&audit_path);
+        assert_eq!(lines.len(), 1);
+        let line = &lines[0];
+        assert_schema(line);
+
+        assert_eq!(line["outcome"], "error");
+        assert_eq!(line["error_code"], -32604);
+        assert!(line["error_message"]
+            .as_str()
+            .unwrap()
+            .contains("not in the allowlist"));
+        assert!(line["policy_id"].is_null());
+        assert!(line["paymaster_and_data"].is_null());
+        assert!(line["requester"].is_null());
+        assert_eq!(line["sender"], REJECTED_SENDER);
+    }
+}
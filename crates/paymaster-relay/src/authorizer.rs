@@ -0,0 +1,474 @@
+// paymaster-relay/src/authorizer.rs
+// External authorizer webhooks of sponsorship policies. A policy naming a webhook
+// only sponsors the operations the customer's service approves; each call is
+// signed with an HMAC of the body so the customer can authenticate us.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::{Address, B256, U256};
+use ring::hmac;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::{call_data::Selector, error::PaymasterError};
+
+/// Header carrying `sha256=<hex HMAC>` of `<timestamp>.<body>`
+pub const SIGNATURE_HEADER: &str = "X-SuperRelay-Signature";
+/// Header carrying the unix time the request was signed at
+pub const TIMESTAMP_HEADER: &str = "X-SuperRelay-Timestamp";
+
+/// Handling of an authorizer that times out or does not answer 200
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthorizerFailure {
+    /// Refuse to sponsor the operation
+    #[default]
+    Deny,
+    /// Sponsor it as if the authorizer had approved
+    Allow,
+}
+
+/// External authorizer of a policy (`authorizer` of a policy in the policy file)
+#[derive(Clone, Debug, Deserialize)]
+pub struct AuthorizerConfig {
+    /// Webhook the operations are POSTed to
+    pub url: String,
+    /// Key of the HMAC signing the requests
+    pub secret: SecretString,
+    /// Milliseconds to wait for the decision
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Handling of timeouts and answers other than 200
+    #[serde(default)]
+    pub on_failure: AuthorizerFailure,
+    /// Seconds a decision is reused for retries of the same operation
+    #[serde(default = "default_cache_seconds")]
+    pub cache_seconds: u64,
+}
+
+fn default_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_cache_seconds() -> u64 {
+    30
+}
+
+/// Call of the operation, as decoded from its callData
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AuthorizedCall {
+    /// Called function; `None` for plain value transfers
+    pub selector: Option<Selector>,
+    pub target: Address,
+}
+
+/// Operation POSTed to the authorizer
+///
+/// Fields are declared in key order, so the body is canonical JSON: sorted
+/// keys and no whitespace.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizationRequest {
+    /// Calls the operation makes; `None` when callData does not decode
+    pub calls: Option<Vec<AuthorizedCall>>,
+    pub chain_id: u64,
+    pub entry_point: Address,
+    /// Most the operation can cost in gas, in wei
+    pub max_gas_cost: U256,
+    pub policy_id: String,
+    pub sender: Address,
+    pub user_op_hash: B256,
+}
+
+/// Answer of the authorizer
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizerDecision {
+    pub approved: bool,
+    /// Why the operation was denied, passed on to the client
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Validity window to sign instead of the requested one
+    #[serde(default)]
+    pub validity_seconds: Option<u64>,
+}
+
+/// Signature header value of `body` signed at `timestamp`
+pub fn sign_request(secret: &[u8], timestamp: u64, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+    let mut context = hmac::Context::with_key(&key);
+    context.update(timestamp.to_string().as_bytes());
+    context.update(b".");
+    context.update(body);
+    format!("sha256={}", hex::encode(context.sign().as_ref()))
+}
+
+/// Caller of the policies' authorizer webhooks, remembering recent decisions
+#[derive(Debug)]
+pub struct ExternalAuthorizer {
+    client: reqwest::Client,
+    /// Decisions by policy and operation hash, with their expiry
+    decisions: Mutex<HashMap<(String, B256), (Instant, AuthorizerDecision)>>,
+}
+
+impl Default for ExternalAuthorizer {
+    fn default() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            decisions: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl ExternalAuthorizer {
+    /// Ask the authorizer of `config` about `request`
+    ///
+    /// Returns the approving decision; denials, and failures under the deny
+    /// setting, are `PolicyRejected`. Approvals and denials are reused for
+    /// `cache_seconds`, failures are not.
+    pub async fn authorize(
+        &self,
+        config: &AuthorizerConfig,
+        request: &AuthorizationRequest,
+    ) -> Result<AuthorizerDecision, PaymasterError> {
+        let key = (request.policy_id.clone(), request.user_op_hash);
+        let cached = self
+            .decisions
+            .lock()
+            .unwrap()
+            .get(&key)
+            .filter(|(expires, _)| *expires > Instant::now())
+            .map(|(_, decision)| decision.clone());
+        let decision = match cached {
+            Some(decision) => {
+                debug!(
+                    "Reusing authorizer decision for {} under policy {}",
+                    request.user_op_hash, request.policy_id
+                );
+                decision
+            }
+            None => match self.call(config, request).await {
+                Ok(decision) => {
+                    let now = Instant::now();
+                    let mut decisions = self.decisions.lock().unwrap();
+                    decisions.retain(|_, (expires, _)| *expires > now);
+                    decisions.insert(
+                        key,
+                        (
+                            now + Duration::from_secs(config.cache_seconds),
+                            decision.clone(),
+                        ),
+                    );
+                    decision
+                }
+                Err(reason) => match config.on_failure {
+                    AuthorizerFailure::Deny => {
+                        return Err(PaymasterError::PolicyRejected(format!(
+                            "Authorizer of policy {} did not approve: {}",
+                            request.policy_id, reason
+                        )))
+                    }
+                    AuthorizerFailure::Allow => {
+                        warn!(
+                            "Authorizer of policy {} failed, sponsoring {} anyway: {}",
+                            request.policy_id, request.user_op_hash, reason
+                        );
+                        return Ok(AuthorizerDecision {
+                            approved: true,
+                            reason: Some(reason),
+                            validity_seconds: None,
+                        });
+                    }
+                },
+            },
+        };
+
+        if decision.approved {
+            Ok(decision)
+        } else {
+            Err(PaymasterError::PolicyRejected(format!(
+                "Authorizer of policy {} denied the operation{}",
+                request.policy_id,
+                decision
+                    .reason
+                    .as_deref()
+                    .map(|reason| format!(": {}", reason))
+                    .unwrap_or_default()
+            )))
+        }
+    }
+
+    /// POST `request` and read the decision, or why there is none
+    async fn call(
+        &self,
+        config: &AuthorizerConfig,
+        request: &AuthorizationRequest,
+    ) -> Result<AuthorizerDecision, String> {
+        let body = serde_json::to_vec(request).map_err(|e| e.to_string())?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let signature = sign_request(config.secret.expose_secret().as_bytes(), timestamp, &body);
+
+        let response = self
+            .client
+            .post(&config.url)
+            .header("Content-Type", "application/json")
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, signature)
+            .body(body)
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    format!("no answer within {} ms", config.timeout_ms)
+                } else {
+                    format!("request failed: {}", e)
+                }
+            })?;
+        let status = response.status();
+        if status != reqwest::StatusCode::OK {
+            return Err(format!("answered {}", status));
+        }
+        response
+            .json::<AuthorizerDecision>()
+            .await
+            .map_err(|e| format!("invalid answer: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    use alloy_primitives::{address, keccak256, Address, Bytes, U256};
+    use axum::{
+        body::Bytes as Body,
+        extract::State,
+        http::{HeaderMap, StatusCode},
+        response::{IntoResponse, Response},
+        routing::post,
+        Json, Router,
+    };
+    use ethers::abi::{self, Token};
+    use rundler_types::{chain::ChainSpec, v0_6, UserOperationVariant};
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        service::{PaymasterRelayService, SponsorOptions},
+        test_utils, PaymasterError,
+    };
+
+    const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const SECRET: &str = "whsec-test-secret";
+    const TARGET: Address = address!("5FbDB2315678afecb367f032d93F642f64180aa3");
+
+    /// What the mock webhook answers
+    enum Answer {
+        Decision(Value),
+        Status(StatusCode),
+        Hang,
+    }
+
+    struct Webhook {
+        answer: Answer,
+        received: Mutex<Vec<(HeaderMap, Body)>>,
+    }
+
+    async fn handle(
+        State(webhook): State<Arc<Webhook>>,
+        headers: HeaderMap,
+        body: Body,
+    ) -> Response {
+        webhook.received.lock().unwrap().push((headers, body));
+        match &webhook.answer {
+            Answer::Decision(decision) => Json(decision.clone()).into_response(),
+            Answer::Status(status) => status.into_response(),
+            Answer::Hang => {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                StatusCode::OK.into_response()
+            }
+        }
+    }
+
+    /// Mock webhook answering `answer`, and its URL
+    async fn start_webhook(answer: Answer) -> (Arc<Webhook>, String) {
+        let webhook = Arc::new(Webhook {
+            answer,
+            received: Mutex::new(vec![]),
+        });
+        let app = Router::new()
+            .route("/authorize", post(handle))
+            .with_state(webhook.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/authorize", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (webhook, url)
+    }
+
+    fn create_service(url: &str, on_failure: &str) -> PaymasterRelayService {
+        test_utils::service(&format!(
+            "senders = [\"{}\"]\n\
+         authorizer = {{ url = \"{}\", secret = \"{}\", timeout_ms = 300, on_failure = \"{}\" }}\n",
+            SENDER, url, SECRET, on_failure
+        ))
+    }
+
+    /// Operation calling transfer(address,uint256) on TARGET through execute
+    fn user_op() -> UserOperationVariant {
+        let transfer = [
+            &keccak256("transfer(address,uint256)")[..4],
+            &abi::encode(&[
+                Token::Address(ethers::types::Address::repeat_byte(0x11)),
+                Token::Uint(1000.into()),
+            ]),
+        ]
+        .concat();
+        let call_data = [
+            &keccak256("execute(address,uint256,bytes)")[..4],
+            &abi::encode(&[
+                Token::Address(ethers::types::Address::from_slice(TARGET.as_slice())),
+                Token::Uint(0.into()),
+                Token::Bytes(transfer),
+            ]),
+        ]
+        .concat();
+        UserOperationVariant::V0_6(
+            v0_6::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_6::UserOperationRequiredFields {
+                    sender: Address::from_str(SENDER).unwrap(),
+                    nonce: U256::ZERO,
+                    call_data: Bytes::from(call_data),
+                    call_gas_limit: 100_000,
+                    verification_gas_limit: 100_000,
+                    pre_verification_gas: 21_000,
+                    max_fee_per_gas: 1_000_000_000,
+                    max_priority_fee_per_gas: 1_000_000_000,
+                    ..Default::default()
+                },
+            )
+            .build(),
+        )
+    }
+
+    async fn sponsor(
+        service: &PaymasterRelayService,
+    ) -> Result<crate::service::PaymasterSponsorResult, PaymasterError> {
+        let entry_point = ethers::types::Address::from_slice(
+            ChainSpec::default().entry_point_address_v0_6.as_slice(),
+        );
+        service
+            .sponsor_user_operation(user_op(), entry_point, SponsorOptions::default())
+            .await
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[tokio::test]
+    async fn test_approved_with_validity_override() {
+        let (webhook, url) = start_webhook(Answer::Decision(
+            json!({"approved": true, "validitySeconds": 120}),
+        ))
+        .await;
+        let service = create_service(&url, "deny");
+
+        let result = sponsor(&service).await.unwrap();
+        assert!(result.valid_until.abs_diff(now() + 120) <= 5);
+
+        // A retry of the same operation reuses the decision
+        sponsor(&service).await.unwrap();
+        assert_eq!(webhook.received.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_denied() {
+        let (_, url) = start_webhook(Answer::Decision(
+            json!({"approved": false, "reason": "monthly budget exhausted"}),
+        ))
+        .await;
+        let service = create_service(&url, "deny");
+
+        let err = sponsor(&service).await.unwrap_err();
+        let PaymasterError::PolicyRejected(message) = &err else {
+            panic!("unexpected error {}", err);
+        };
+        assert!(message.contains("monthly budget exhausted"), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_and_failure_handling() {
+        let (_, url) = start_webhook(Answer::Hang).await;
+        let err = sponsor(&create_service(&url, "deny")).await.unwrap_err();
+        assert!(
+            matches!(&err, PaymasterError::PolicyRejected(m) if m.contains("no answer within 300 ms")),
+            "{}",
+            err
+        );
+        assert!(sponsor(&create_service(&url, "allow")).await.is_ok());
+
+        // Only 200 answers count
+        let (webhook, url) = start_webhook(Answer::Status(StatusCode::ACCEPTED)).await;
+        let service = create_service(&url, "deny");
+        assert!(sponsor(&service).await.is_err());
+        // Failures are not cached
+        assert!(sponsor(&service).await.is_err());
+        assert_eq!(webhook.received.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_request_body_and_hmac() {
+        let (webhook, url) = start_webhook(Answer::Decision(json!({"approved": true}))).await;
+        sponsor(&create_service(&url, "deny")).await.unwrap();
+
+        let (headers, body) = webhook.received.lock().unwrap().pop().unwrap();
+        let timestamp: u64 = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+        assert!(timestamp.abs_diff(now()) <= 5);
+        assert_eq!(
+            headers[SIGNATURE_HEADER].to_str().unwrap(),
+            sign_request(SECRET.as_bytes(), timestamp, &body)
+        );
+        assert_ne!(
+            headers[SIGNATURE_HEADER].to_str().unwrap(),
+            sign_request(b"other-secret", timestamp, &body)
+        );
+
+        // Canonical JSON: sorted keys, no whitespace
+        let text = std::str::from_utf8(&body).unwrap();
+        let request: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(text, serde_json::to_string(&request).unwrap());
+
+        let op = user_op();
+        assert_eq!(request["policyId"], "default");
+        assert_eq!(request["chainId"], ChainSpec::default().id);
+        assert_eq!(request["sender"], SENDER);
+        assert_eq!(request["calls"][0]["target"], TARGET.to_checksum(None));
+        assert_eq!(request["calls"][0]["selector"], "0xa9059cbb");
+        assert_eq!(
+            request["maxGasCost"],
+            json!(rundler_types::UserOperation::max_gas_cost(&op))
+        );
+        assert_eq!(
+            request["userOpHash"],
+            json!(rundler_types::UserOperation::hash(&op))
+        );
+    }
+}
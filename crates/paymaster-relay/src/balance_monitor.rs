@@ -0,0 +1,392 @@
+// paymaster-relay/src/balance_monitor.rs
+// Background watch of the paymaster account balance and its EntryPoint deposits
+// (pm_getBalanceStatus), optionally topping deposits up from the account within
+// a daily spend cap.
+
+use std::{
+    sync::RwLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::{Address, B256, U256};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::{deposit::DepositManager, metrics::PaymasterMetrics, signer::SignerManager};
+
+/// Seconds in one rebalance spend window
+const DAY_SECONDS: u64 = 24 * 60 * 60;
+
+/// Balance monitor settings (`[paymaster_relay.balance_monitor]`)
+///
+/// Wei amounts are decimal or 0x-hex strings.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BalanceMonitorConfig {
+    /// Run the background monitor
+    pub enabled: bool,
+    /// Seconds between balance checks
+    pub check_interval_seconds: u64,
+    /// Warn when the paymaster account balance drops below this
+    pub account_low_water_wei: U256,
+    /// Warn (and top up, with `auto_rebalance`) when a deposit drops below this
+    pub deposit_low_water_wei: U256,
+    /// Top low deposits up from the paymaster account
+    pub auto_rebalance: bool,
+    /// Deposit a top-up restores
+    pub deposit_target_wei: U256,
+    /// Most wei topped up in any 24 hours
+    pub max_rebalance_per_day_wei: U256,
+    /// Seconds after a top-up attempt before the next one
+    pub rebalance_cooldown_seconds: u64,
+}
+
+impl Default for BalanceMonitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            check_interval_seconds: 60,
+            // 0.1 ETH
+            account_low_water_wei: U256::from(100_000_000_000_000_000u64),
+            // 0.05 ETH
+            deposit_low_water_wei: U256::from(50_000_000_000_000_000u64),
+            auto_rebalance: false,
+            // 0.2 ETH
+            deposit_target_wei: U256::from(200_000_000_000_000_000u64),
+            // 1 ETH
+            max_rebalance_per_day_wei: U256::from(1_000_000_000_000_000_000u64),
+            rebalance_cooldown_seconds: 600,
+        }
+    }
+}
+
+/// Deposit of the paymaster on one EntryPoint as last checked
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryPointBalance {
+    pub entry_point: Address,
+    pub deposit: U256,
+    /// Below the deposit low-water mark
+    pub low: bool,
+}
+
+/// Outcome of the last automatic top-up
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RebalanceRecord {
+    pub entry_point: Address,
+    pub amount: U256,
+    /// Unix time of the attempt
+    pub timestamp: u64,
+    /// Mined transaction, unset when the top-up failed
+    pub tx_hash: Option<B256>,
+    pub error: Option<String>,
+}
+
+/// Balances, thresholds and rebalance state served by pm_getBalanceStatus
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceStatus {
+    pub paymaster: Option<Address>,
+    /// Account that sends top-ups, the active signing key
+    pub account: Option<Address>,
+    pub account_balance: Option<U256>,
+    pub account_low: bool,
+    pub deposits: Vec<EntryPointBalance>,
+    pub account_low_water: U256,
+    pub deposit_low_water: U256,
+    pub auto_rebalance: bool,
+    pub deposit_target: U256,
+    pub max_rebalance_per_day: U256,
+    /// Wei topped up in the current 24 hour window
+    pub rebalanced_today: U256,
+    pub last_rebalance: Option<RebalanceRecord>,
+    /// Unix time of the last check
+    pub last_checked: Option<u64>,
+    /// Unix time of the next scheduled check
+    pub next_check: Option<u64>,
+    /// Error of the last check, if it could not read every balance
+    pub last_error: Option<String>,
+}
+
+/// Spend of the current 24 hour window and the last top-up attempt
+#[derive(Debug, Default)]
+struct RebalanceBudget {
+    window_start: u64,
+    spent: U256,
+    last_attempt: Option<u64>,
+}
+
+impl RebalanceBudget {
+    /// Wei still allowed today under `cap`, starting a new window after 24 hours
+    fn remaining(&mut self, cap: U256, now: u64) -> U256 {
+        if now.saturating_sub(self.window_start) >= DAY_SECONDS {
+            self.window_start = now;
+            self.spent = U256::ZERO;
+        }
+        cap.saturating_sub(self.spent)
+    }
+}
+
+/// Polls the paymaster balances and tops up low deposits
+#[derive(Debug)]
+pub struct BalanceMonitor {
+    config: BalanceMonitorConfig,
+    entry_points: Vec<Address>,
+    status: RwLock<BalanceStatus>,
+    // Held for a whole check so concurrent checks cannot both top up
+    budget: Mutex<RebalanceBudget>,
+}
+
+impl BalanceMonitor {
+    /// Monitor the paymaster deposits on `entry_points`
+    pub fn new(config: BalanceMonitorConfig, entry_points: Vec<Address>) -> Self {
+        Self {
+            status: RwLock::new(unchecked_status(&config)),
+            config,
+            entry_points,
+            budget: Mutex::new(RebalanceBudget::default()),
+        }
+    }
+
+    /// Monitor settings
+    pub fn config(&self) -> &BalanceMonitorConfig {
+        &self.config
+    }
+
+    /// Status as of the last check
+    pub fn status(&self) -> BalanceStatus {
+        self.status.read().unwrap().clone()
+    }
+
+    /// Read the balances once, warn on low-water crossings and top up low deposits
+    pub async fn check(
+        &self,
+        deposits: &DepositManager,
+        signer: &Mutex<SignerManager>,
+        metrics: &PaymasterMetrics,
+    ) -> BalanceStatus {
+        let mut budget = self.budget.lock().await;
+        let previous = self.status();
+        let now = unix_now();
+        // Roll the spend window over even when nothing needs a top-up
+        budget.remaining(self.config.max_rebalance_per_day_wei, now);
+
+        let (paymaster, account) = {
+            let signer = signer.lock().await;
+            (
+                Address::from_slice(signer.address().as_bytes()),
+                Address::from_slice(signer.signer_address().as_bytes()),
+            )
+        };
+
+        let mut status = BalanceStatus {
+            paymaster: Some(paymaster),
+            account: Some(account),
+            last_rebalance: previous.last_rebalance.clone(),
+            last_checked: Some(now),
+            next_check: Some(now + self.config.check_interval_seconds),
+            ..unchecked_status(&self.config)
+        };
+        let mut errors = Vec::new();
+
+        match deposits.account_balance(account).await {
+            Ok(balance) => {
+                metrics.update_account_balance(wei_to_eth(balance));
+                status.account_balance = Some(balance);
+                status.account_low = balance < self.config.account_low_water_wei;
+                if status.account_low && !previous.account_low {
+                    warn!(
+                        "⚠️ Paymaster account {} balance {} wei is below {} wei",
+                        account, balance, self.config.account_low_water_wei
+                    );
+                }
+            }
+            Err(e) => errors.push(e.to_string()),
+        }
+
+        for &entry_point in &self.entry_points {
+            let deposit = match deposits.deposit_info(entry_point, paymaster).await {
+                Ok(info) => info.deposit,
+                Err(e) => {
+                    errors.push(e.to_string());
+                    continue;
+                }
+            };
+            metrics.update_deposit(&format!("{:#x}", entry_point), wei_to_eth(deposit));
+
+            let low = deposit < self.config.deposit_low_water_wei;
+            let was_low = previous
+                .deposits
+                .iter()
+                .any(|d| d.entry_point == entry_point && d.low);
+            if low && !was_low {
+                warn!(
+                    "⚠️ Paymaster deposit {} wei on {} is below {} wei",
+                    deposit, entry_point, self.config.deposit_low_water_wei
+                );
+            }
+
+            let mut balance = EntryPointBalance {
+                entry_point,
+                deposit,
+                low,
+            };
+            if low && self.config.auto_rebalance {
+                if let Some(record) = self
+                    .rebalance(
+                        &mut budget,
+                        deposits,
+                        signer,
+                        metrics,
+                        entry_point,
+                        deposit,
+                        now,
+                    )
+                    .await
+                {
+                    if let Some(tx_hash) = record.tx_hash {
+                        info!(
+                            "💰 Topped up paymaster deposit on {} by {} wei in {}",
+                            entry_point, record.amount, tx_hash
+                        );
+                        balance.deposit += record.amount;
+                        balance.low = balance.deposit < self.config.deposit_low_water_wei;
+                    }
+                    status.last_rebalance = Some(record);
+                }
+            }
+            status.deposits.push(balance);
+        }
+
+        status.rebalanced_today = budget.spent;
+        if !errors.is_empty() {
+            warn!("Paymaster balance check failed: {}", errors.join("; "));
+            status.last_error = Some(errors.join("; "));
+        }
+        *self.status.write().unwrap() = status.clone();
+        status
+    }
+
+    /// Top the deposit on `entry_point` up to the target, within the cooldown and
+    /// daily cap. Returns the attempt, if one was made.
+    #[allow(clippy::too_many_arguments)]
+    async fn rebalance(
+        &self,
+        budget: &mut RebalanceBudget,
+        deposits: &DepositManager,
+        signer: &Mutex<SignerManager>,
+        metrics: &PaymasterMetrics,
+        entry_point: Address,
+        deposit: U256,
+        now: u64,
+    ) -> Option<RebalanceRecord> {
+        if budget
+            .last_attempt
+            .is_some_and(|at| now < at + self.config.rebalance_cooldown_seconds)
+        {
+            return None;
+        }
+        // A top-up that timed out may still be mined; sending another could overshoot
+        if deposits.has_pending_transaction(entry_point) {
+            warn!(
+                "Paymaster deposit on {} not topped up: an earlier transaction to it is pending",
+                entry_point
+            );
+            return None;
+        }
+
+        let remaining = budget.remaining(self.config.max_rebalance_per_day_wei, now);
+        let amount = self
+            .config
+            .deposit_target_wei
+            .saturating_sub(deposit)
+            .min(deposits.config().max_deposit_wei)
+            .min(remaining);
+        if amount.is_zero() {
+            warn!(
+                "Paymaster deposit on {} not topped up: daily cap of {} wei reached",
+                entry_point, self.config.max_rebalance_per_day_wei
+            );
+            return None;
+        }
+
+        // The cooldown counts from attempts, so a failing top-up is not retried every check
+        budget.last_attempt = Some(now);
+        let result = deposits.deposit_to(signer, entry_point, amount).await;
+        metrics.record_rebalance(result.is_ok());
+        let mut record = RebalanceRecord {
+            entry_point,
+            amount,
+            timestamp: now,
+            tx_hash: None,
+            error: None,
+        };
+        match result {
+            Ok(tx) => {
+                budget.spent += amount;
+                record.tx_hash = Some(tx.tx_hash);
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to top up paymaster deposit on {}: {}",
+                    entry_point, e
+                );
+                record.error = Some(e.to_string());
+            }
+        }
+        Some(record)
+    }
+}
+
+/// Status carrying only the configured thresholds
+fn unchecked_status(config: &BalanceMonitorConfig) -> BalanceStatus {
+    BalanceStatus {
+        account_low_water: config.account_low_water_wei,
+        deposit_low_water: config.deposit_low_water_wei,
+        auto_rebalance: config.auto_rebalance,
+        deposit_target: config.deposit_target_wei,
+        max_rebalance_per_day: config.max_rebalance_per_day_wei,
+        ..Default::default()
+    }
+}
+
+/// Wei as (lossy) ether, for gauges
+pub(crate) fn wei_to_eth(wei: U256) -> f64 {
+    u128::try_from(wei).unwrap_or(u128::MAX) as f64 / 1e18
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_resets_daily() {
+        let cap = U256::from(100u64);
+        let mut budget = RebalanceBudget {
+            window_start: 1_000,
+            spent: U256::from(80u64),
+            last_attempt: None,
+        };
+        assert_eq!(
+            budget.remaining(cap, 1_000 + DAY_SECONDS - 1),
+            U256::from(20u64)
+        );
+        assert_eq!(budget.remaining(cap, 1_000 + DAY_SECONDS), cap);
+        assert_eq!(budget.window_start, 1_000 + DAY_SECONDS);
+    }
+
+    #[test]
+    fn test_wei_to_eth() {
+        assert_eq!(wei_to_eth(U256::from(1_500_000_000_000_000_000u64)), 1.5);
+        assert_eq!(wei_to_eth(U256::MAX), u128::MAX as f64 / 1e18);
+    }
+}
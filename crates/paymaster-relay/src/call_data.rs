@@ -0,0 +1,427 @@
+// paymaster-relay/src/call_data.rs
+// Decoding of account callData into the calls it makes, so policies can match
+// call targets and function selectors. Accounts expose their calls through
+// execute functions; the encodings understood are identified by their
+// parameter types, so accounts naming their functions differently only need
+// their signatures configured:
+//   (address,uint256,bytes)          one call: target, value, data
+//   (address[],bytes[])              batch without values
+//   (address[],uint256[],bytes[])    batch with values
+//   ((address,uint256,bytes)[])      batch of (target, value, data) tuples
+
+use std::fmt;
+
+use alloy_primitives::{keccak256, Address, Bytes, FixedBytes, U256};
+use ethers::abi::{self, ParamType, Token};
+use serde::Deserialize;
+
+/// 4-byte function selector
+pub type Selector = FixedBytes<4>;
+
+/// Execute functions decoded when a policy does not configure its own
+pub const DEFAULT_EXECUTE_FUNCTIONS: &[&str] = &[
+    "execute(address,uint256,bytes)",
+    "executeBatch(address[],bytes[])",
+    "executeBatch(address[],uint256[],bytes[])",
+    "executeBatch((address,uint256,bytes)[])",
+];
+
+/// Argument layout of an execute function
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecuteEncoding {
+    /// `(address,uint256,bytes)`
+    Single,
+    /// `(address[],bytes[])`
+    Batch,
+    /// `(address[],uint256[],bytes[])`
+    BatchWithValues,
+    /// `((address,uint256,bytes)[])`
+    BatchOfCalls,
+}
+
+impl ExecuteEncoding {
+    fn from_params(params: &str) -> Option<Self> {
+        match params {
+            "address,uint256,bytes" => Some(Self::Single),
+            "address[],bytes[]" => Some(Self::Batch),
+            "address[],uint256[],bytes[]" => Some(Self::BatchWithValues),
+            "(address,uint256,bytes)[]" => Some(Self::BatchOfCalls),
+            _ => None,
+        }
+    }
+
+    fn param_types(self) -> Vec<ParamType> {
+        let array = |kind| ParamType::Array(Box::new(kind));
+        match self {
+            Self::Single => vec![ParamType::Address, ParamType::Uint(256), ParamType::Bytes],
+            Self::Batch => vec![array(ParamType::Address), array(ParamType::Bytes)],
+            Self::BatchWithValues => vec![
+                array(ParamType::Address),
+                array(ParamType::Uint(256)),
+                array(ParamType::Bytes),
+            ],
+            Self::BatchOfCalls => vec![array(ParamType::Tuple(vec![
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Bytes,
+            ]))],
+        }
+    }
+}
+
+/// Account execute function, configured by its signature
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ExecuteFunction {
+    pub signature: String,
+    pub selector: Selector,
+    pub encoding: ExecuteEncoding,
+}
+
+impl TryFrom<String> for ExecuteFunction {
+    type Error = String;
+
+    fn try_from(signature: String) -> Result<Self, Self::Error> {
+        let signature: String = signature.chars().filter(|c| !c.is_whitespace()).collect();
+        let encoding = signature
+            .split_once('(')
+            .and_then(|(name, params)| {
+                let params = params.strip_suffix(')')?;
+                (!name.is_empty())
+                    .then_some(params)
+                    .and_then(ExecuteEncoding::from_params)
+            })
+            .ok_or_else(|| {
+                format!(
+                    "Unsupported execute function {}: expected parameters (address,uint256,bytes), \
+                     (address[],bytes[]), (address[],uint256[],bytes[]) or \
+                     ((address,uint256,bytes)[])",
+                    signature
+                )
+            })?;
+        Ok(Self {
+            selector: selector(&signature),
+            signature,
+            encoding,
+        })
+    }
+}
+
+impl ExecuteFunction {
+    /// The [`DEFAULT_EXECUTE_FUNCTIONS`]
+    pub fn defaults() -> Vec<Self> {
+        DEFAULT_EXECUTE_FUNCTIONS
+            .iter()
+            .map(|signature| Self::try_from(signature.to_string()).expect("valid default"))
+            .collect()
+    }
+}
+
+/// Function selector given as 0x-prefixed hex or as a signature
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct FunctionSelector(pub Selector);
+
+impl TryFrom<String> for FunctionSelector {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value.contains('(') {
+            let signature: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+            return Ok(Self(selector(&signature)));
+        }
+        value.parse::<Selector>().map(Self).map_err(|_| {
+            format!(
+                "Invalid selector {}: expected 4 hex bytes or a signature",
+                value
+            )
+        })
+    }
+}
+
+/// Call made by an account on behalf of a UserOperation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InnerCall {
+    pub target: Address,
+    pub value: U256,
+    pub data: Bytes,
+}
+
+impl InnerCall {
+    /// Selector of the called function; `None` for calls without one, such as
+    /// plain value transfers
+    pub fn selector(&self) -> Option<Selector> {
+        (self.data.len() >= 4).then(|| Selector::from_slice(&self.data[..4]))
+    }
+}
+
+impl fmt::Display for InnerCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.selector() {
+            Some(selector) => write!(f, "{}.{}", self.target, selector),
+            None => write!(f, "{}", self.target),
+        }
+    }
+}
+
+/// Calls made by `call_data` when it invokes one of `functions`, `None` when
+/// it matches none of them or does not decode
+pub fn decode_calls(functions: &[ExecuteFunction], call_data: &[u8]) -> Option<Vec<InnerCall>> {
+    if call_data.len() < 4 {
+        return None;
+    }
+    let (selector, args) = call_data.split_at(4);
+    let function = functions
+        .iter()
+        .find(|f| f.selector.as_slice() == selector)?;
+    let tokens = abi::decode(&function.encoding.param_types(), args).ok()?;
+
+    match function.encoding {
+        ExecuteEncoding::Single => Some(vec![call(&tokens)?]),
+        ExecuteEncoding::Batch => {
+            let [Token::Array(targets), Token::Array(datas)] = tokens.as_slice() else {
+                return None;
+            };
+            if targets.len() != datas.len() {
+                return None;
+            }
+            targets
+                .iter()
+                .zip(datas)
+                .map(|(target, data)| {
+                    call(&[
+                        target.clone(),
+                        Token::Uint(Default::default()),
+                        data.clone(),
+                    ])
+                })
+                .collect()
+        }
+        ExecuteEncoding::BatchWithValues => {
+            let [Token::Array(targets), Token::Array(values), Token::Array(datas)] =
+                tokens.as_slice()
+            else {
+                return None;
+            };
+            if targets.len() != values.len() || targets.len() != datas.len() {
+                return None;
+            }
+            targets
+                .iter()
+                .zip(values)
+                .zip(datas)
+                .map(|((target, value), data)| call(&[target.clone(), value.clone(), data.clone()]))
+                .collect()
+        }
+        ExecuteEncoding::BatchOfCalls => {
+            let [Token::Array(calls)] = tokens.as_slice() else {
+                return None;
+            };
+            calls
+                .iter()
+                .map(|tuple| match tuple {
+                    Token::Tuple(fields) => call(fields),
+                    _ => None,
+                })
+                .collect()
+        }
+    }
+}
+
+/// Call from its `(address, uint256, bytes)` tokens
+fn call(tokens: &[Token]) -> Option<InnerCall> {
+    match tokens {
+        [Token::Address(target), Token::Uint(value), Token::Bytes(data)] => Some(InnerCall {
+            target: Address::from(target.0),
+            value: U256::from_limbs(value.0),
+            data: Bytes::from(data.clone()),
+        }),
+        _ => None,
+    }
+}
+
+fn selector(signature: &str) -> Selector {
+    Selector::from_slice(&keccak256(signature.as_bytes())[..4])
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::H160;
+
+    use super::*;
+
+    const GAME: Address = Address::repeat_byte(0x11);
+    const TOKEN: Address = Address::repeat_byte(0x22);
+
+    fn transfer_data() -> Vec<u8> {
+        let mut data = selector("transfer(address,uint256)").to_vec();
+        data.extend(abi::encode(&[
+            Token::Address(H160::repeat_byte(0x33)),
+            uint(1000),
+        ]));
+        data
+    }
+
+    fn encode(signature: &str, args: &[Token]) -> Vec<u8> {
+        let mut data = selector(signature).to_vec();
+        data.extend(abi::encode(args));
+        data
+    }
+
+    fn uint(value: u64) -> Token {
+        Token::Uint(value.into())
+    }
+
+    fn address(address: Address) -> Token {
+        Token::Address(H160::from(address.0 .0))
+    }
+
+    #[test]
+    fn test_decode_single_execute() {
+        let call_data = encode(
+            "execute(address,uint256,bytes)",
+            &[address(TOKEN), uint(5), Token::Bytes(transfer_data())],
+        );
+        let calls = decode_calls(&ExecuteFunction::defaults(), &call_data).unwrap();
+        assert_eq!(
+            calls,
+            [InnerCall {
+                target: TOKEN,
+                value: U256::from(5),
+                data: transfer_data().into(),
+            }]
+        );
+        assert_eq!(
+            calls[0].selector(),
+            Some(Selector::from([0xa9, 0x05, 0x9c, 0xbb]))
+        );
+        assert_eq!(calls[0].to_string(), format!("{}.0xa9059cbb", TOKEN));
+    }
+
+    #[test]
+    fn test_decode_batch_encodings() {
+        let functions = ExecuteFunction::defaults();
+        let expected = |values: [u64; 2]| {
+            vec![
+                InnerCall {
+                    target: GAME,
+                    value: U256::from(values[0]),
+                    data: Bytes::new(),
+                },
+                InnerCall {
+                    target: TOKEN,
+                    value: U256::from(values[1]),
+                    data: transfer_data().into(),
+                },
+            ]
+        };
+
+        let batch = encode(
+            "executeBatch(address[],bytes[])",
+            &[
+                Token::Array(vec![address(GAME), address(TOKEN)]),
+                Token::Array(vec![Token::Bytes(vec![]), Token::Bytes(transfer_data())]),
+            ],
+        );
+        assert_eq!(decode_calls(&functions, &batch).unwrap(), expected([0, 0]));
+
+        let with_values = encode(
+            "executeBatch(address[],uint256[],bytes[])",
+            &[
+                Token::Array(vec![address(GAME), address(TOKEN)]),
+                Token::Array(vec![uint(7), uint(0)]),
+                Token::Array(vec![Token::Bytes(vec![]), Token::Bytes(transfer_data())]),
+            ],
+        );
+        assert_eq!(
+            decode_calls(&functions, &with_values).unwrap(),
+            expected([7, 0])
+        );
+        assert_eq!(expected([7, 0])[0].selector(), None);
+
+        let tuples = encode(
+            "executeBatch((address,uint256,bytes)[])",
+            &[Token::Array(vec![
+                Token::Tuple(vec![address(GAME), uint(7), Token::Bytes(vec![])]),
+                Token::Tuple(vec![address(TOKEN), uint(0), Token::Bytes(transfer_data())]),
+            ])],
+        );
+        assert_eq!(decode_calls(&functions, &tuples).unwrap(), expected([7, 0]));
+
+        // Arrays of different lengths do not describe calls
+        let mismatched = encode(
+            "executeBatch(address[],bytes[])",
+            &[
+                Token::Array(vec![address(GAME), address(TOKEN)]),
+                Token::Array(vec![Token::Bytes(vec![])]),
+            ],
+        );
+        assert_eq!(decode_calls(&functions, &mismatched), None);
+    }
+
+    #[test]
+    fn test_undecodable_call_data() {
+        let functions = ExecuteFunction::defaults();
+        assert_eq!(decode_calls(&functions, &[]), None);
+        assert_eq!(decode_calls(&functions, &[0xb6, 0x1d]), None);
+        // Unknown selector
+        assert_eq!(decode_calls(&functions, &transfer_data()), None);
+        // Known selector, truncated arguments
+        let call_data = encode(
+            "execute(address,uint256,bytes)",
+            &[address(TOKEN), uint(5), Token::Bytes(transfer_data())],
+        );
+        assert_eq!(decode_calls(&functions, &call_data[..40]), None);
+
+        // Only the configured functions are decoded
+        let custom =
+            vec![
+                ExecuteFunction::try_from("executeCall(address,uint256,bytes)".to_string())
+                    .unwrap(),
+            ];
+        assert_eq!(decode_calls(&custom, &call_data), None);
+        let custom_call = encode(
+            "executeCall(address,uint256,bytes)",
+            &[address(TOKEN), uint(5), Token::Bytes(vec![])],
+        );
+        assert_eq!(
+            decode_calls(&custom, &custom_call).unwrap()[0].target,
+            TOKEN
+        );
+    }
+
+    #[test]
+    fn test_parse_functions_and_selectors() {
+        let function =
+            ExecuteFunction::try_from("execute(address, uint256, bytes)".to_string()).unwrap();
+        assert_eq!(function.signature, "execute(address,uint256,bytes)");
+        assert_eq!(function.selector, Selector::from([0xb6, 0x1d, 0x27, 0xf6]));
+        assert_eq!(function.encoding, ExecuteEncoding::Single);
+        assert_eq!(
+            ExecuteFunction::defaults()
+                .iter()
+                .map(|f| f.selector.to_string())
+                .collect::<Vec<_>>(),
+            ["0xb61d27f6", "0x18dfb3c7", "0x47e1da2a", "0x34fcd5be"]
+        );
+        assert!(
+            ExecuteFunction::try_from("execute(bytes32,bytes)".to_string())
+                .unwrap_err()
+                .starts_with("Unsupported execute function execute(bytes32,bytes)")
+        );
+        assert!(ExecuteFunction::try_from("(address,uint256,bytes)".to_string()).is_err());
+
+        let transfer = Selector::from([0xa9, 0x05, 0x9c, 0xbb]);
+        for value in [
+            "0xa9059cbb",
+            "transfer(address,uint256)",
+            "transfer(address, uint256)",
+        ] {
+            assert_eq!(
+                FunctionSelector::try_from(value.to_string()).unwrap().0,
+                transfer
+            );
+        }
+        assert!(FunctionSelector::try_from("0xa9059c".to_string()).is_err());
+    }
+}
@@ -0,0 +1,507 @@
+// paymaster-relay/src/canonical.rs
+// Deterministic encoding of UserOperations, so equal operations hash alike in caches,
+// idempotency keys and audit records however the client ordered or cased them.
+//
+// Rules: object keys sorted, null fields dropped (an absent optional v0.7 field and a
+// null one are the same operation), quantities as minimal lowercase 0x hex whether sent
+// as hex, decimal string or number, other 0x strings lowercased, no whitespace.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use alloy_primitives::{keccak256, Address, B256, U256};
+use rundler_types::{authorization::Eip7702Auth, v0_7, UserOperation, UserOperationVariant};
+use serde_json::{json, Value};
+
+/// Fields holding quantities, including those of the EIP-7702 authorization
+const QUANTITY_FIELDS: &[&str] = &[
+    "nonce",
+    "callGasLimit",
+    "verificationGasLimit",
+    "preVerificationGas",
+    "maxFeePerGas",
+    "maxPriorityFeePerGas",
+    "paymasterVerificationGasLimit",
+    "paymasterPostOpGasLimit",
+    "chainId",
+    "yParity",
+    "r",
+    "s",
+];
+
+/// Fields filled in by or after sponsorship, left out of [`sponsorship_identity`]
+const SPONSORSHIP_FIELDS: &[&str] = &[
+    "paymaster",
+    "paymasterAndData",
+    "paymasterData",
+    "signature",
+];
+
+/// Canonical encoding of a UserOperation (or any JSON value) in its RPC shape
+pub fn canonical_json(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(value, None, &mut out);
+    out
+}
+
+/// keccak256 of [`canonical_json`]
+pub fn canonical_digest(value: &Value) -> B256 {
+    keccak256(canonical_json(value))
+}
+
+/// keccak256 of the canonical encoding of a parsed operation
+pub fn user_operation_digest(user_op: &UserOperationVariant) -> B256 {
+    canonical_digest(&user_operation_json(user_op))
+}
+
+/// Identity of a sponsorship request: the canonical operation without its
+/// paymaster fields and signature, bound to the chain and `entry_point`
+///
+/// A replay carrying the paymaster data and signature of its earlier
+/// sponsorship still has the identity of the original request.
+pub fn sponsorship_identity(user_op: &UserOperationVariant, entry_point: Address) -> B256 {
+    let mut op = user_operation_json(user_op);
+    if let Value::Object(fields) = &mut op {
+        for field in SPONSORSHIP_FIELDS {
+            fields.remove(*field);
+        }
+        fields.insert("chainId".to_string(), json!(user_op.chain_id()));
+        fields.insert("entryPoint".to_string(), json!(entry_point));
+    }
+    canonical_digest(&op)
+}
+
+/// RPC JSON shape of a parsed operation
+///
+/// Optional v0.7 fields that are unset are null, and paymaster gas limits
+/// are left out without a paymaster. Parsing the result gives back `user_op`.
+pub fn user_operation_json(user_op: &UserOperationVariant) -> Value {
+    let mut json_op = match user_op {
+        UserOperationVariant::V0_6(op) => json!({
+            "sender": format!("{:#x}", op.sender()),
+            "nonce": format!("0x{:x}", op.nonce()),
+            "initCode": format!("0x{}", hex::encode(op.init_code())),
+            "callData": format!("0x{}", hex::encode(op.call_data())),
+            "callGasLimit": format!("0x{:x}", op.call_gas_limit()),
+            "verificationGasLimit": format!("0x{:x}", op.verification_gas_limit()),
+            "preVerificationGas": format!("0x{:x}", op.pre_verification_gas()),
+            "maxFeePerGas": format!("0x{:x}", op.max_fee_per_gas()),
+            "maxPriorityFeePerGas": format!("0x{:x}", op.max_priority_fee_per_gas()),
+            "paymasterAndData": format!("0x{}", hex::encode(op.paymaster_and_data())),
+            "signature": format!("0x{}", hex::encode(op.signature()))
+        }),
+        UserOperationVariant::V0_7(op) => packed_json(op),
+        // v0.8 operations have the v0.7 fields
+        #[cfg(feature = "entrypoint-v0_8")]
+        UserOperationVariant::V0_8(op) => packed_json(op.fields()),
+    };
+
+    if let Some(auth) = user_op.authorization_tuple() {
+        json_op["eip7702Auth"] = eip7702_auth_json(auth);
+    }
+    if let Some(aggregator) = user_op.aggregator() {
+        json_op["aggregator"] = json!(format!("{:#x}", aggregator));
+    }
+
+    json_op
+}
+
+fn packed_json(op: &v0_7::UserOperation) -> Value {
+    json!({
+        "sender": format!("{:#x}", op.sender()),
+        "nonce": format!("0x{:x}", op.nonce()),
+        "factory": op.factory().map(|f| format!("{:#x}", f)),
+        "factoryData": format!("0x{}", hex::encode(op.factory_data())),
+        "callData": format!("0x{}", hex::encode(op.call_data())),
+        "callGasLimit": format!("0x{:x}", op.call_gas_limit()),
+        "verificationGasLimit": format!("0x{:x}", op.verification_gas_limit()),
+        "preVerificationGas": format!("0x{:x}", op.pre_verification_gas()),
+        "maxFeePerGas": format!("0x{:x}", op.max_fee_per_gas()),
+        "maxPriorityFeePerGas": format!("0x{:x}", op.max_priority_fee_per_gas()),
+        "paymaster": op.paymaster().map(|p| format!("{:#x}", p)),
+        "paymasterVerificationGasLimit": if op.paymaster().is_some() {
+            Some(format!("0x{:x}", op.paymaster_verification_gas_limit()))
+        } else {
+            None
+        },
+        "paymasterPostOpGasLimit": if op.paymaster().is_some() {
+            Some(format!("0x{:x}", op.paymaster_post_op_gas_limit()))
+        } else {
+            None
+        },
+        "paymasterData": format!("0x{}", hex::encode(op.paymaster_data())),
+        "signature": format!("0x{}", hex::encode(op.signature()))
+    })
+}
+
+/// RPC JSON shape of an EIP-7702 authorization tuple
+pub fn eip7702_auth_json(auth: &Eip7702Auth) -> Value {
+    json!({
+        "chainId": format!("0x{:x}", auth.chain_id),
+        "address": format!("{:#x}", auth.address),
+        "nonce": format!("0x{:x}", auth.nonce),
+        "yParity": format!("0x{:x}", auth.y_parity),
+        "r": format!("0x{:x}", auth.r),
+        "s": format!("0x{:x}", auth.s)
+    })
+}
+
+/// Append the canonical encoding of `value`, the value of `field` when it is
+/// an object member
+fn write_canonical(value: &Value, field: Option<&str>, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let sorted: BTreeMap<&str, &Value> = map
+                .iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.as_str(), v))
+                .collect();
+            out.push('{');
+            for (index, (key, v)) in sorted.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_canonical(v, Some(key), out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, None, out);
+            }
+            out.push(']');
+        }
+        Value::String(s) => match field.filter(|f| QUANTITY_FIELDS.contains(f)) {
+            Some(_) => match parse_quantity(s) {
+                Some(quantity) => write_quantity(quantity, out),
+                None => write_string(&lowercase_hex(s), out),
+            },
+            None => write_string(&lowercase_hex(s), out),
+        },
+        Value::Number(n) => match (field.filter(|f| QUANTITY_FIELDS.contains(f)), n.as_u64()) {
+            (Some(_), Some(quantity)) => write_quantity(U256::from(quantity), out),
+            _ => {
+                let _ = write!(out, "{}", n);
+            }
+        },
+        Value::Bool(b) => {
+            let _ = write!(out, "{}", b);
+        }
+        Value::Null => out.push_str("null"),
+    }
+}
+
+/// Quantity sent as 0x hex or as a decimal string
+fn parse_quantity(s: &str) -> Option<U256> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some("") => None,
+        Some(digits) => U256::from_str_radix(digits, 16).ok(),
+        None => U256::from_str_radix(s, 10).ok(),
+    }
+}
+
+fn write_quantity(quantity: U256, out: &mut String) {
+    let _ = write!(out, "\"{:#x}\"", quantity);
+}
+
+fn lowercase_hex(s: &str) -> String {
+    if s.starts_with("0x") || s.starts_with("0X") {
+        s.to_ascii_lowercase()
+    } else {
+        s.to_string()
+    }
+}
+
+fn write_string(s: &str, out: &mut String) {
+    // serde_json escapes strings the same way on every run
+    out.push_str(&Value::String(s.to_string()).to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use alloy_primitives::{Address, Bytes, U256};
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+    use rundler_types::{chain::ChainSpec, v0_7, UserOperationVariant};
+    use serde_json::Value;
+
+    use super::*;
+    use crate::idempotency::operation_hash;
+
+    #[test]
+    fn test_canonical_json_rules() {
+        let op = json!({
+            "sender": "0xF39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "nonce": 1,
+            "callGasLimit": "0x000100",
+            "verificationGasLimit": "65536",
+            "factory": null,
+            "eip7702Auth": { "yParity": "0x01", "chainId": 1 },
+            "callData": "0xABCD",
+        });
+
+        assert_eq!(
+            canonical_json(&op),
+            concat!(
+                r#"{"callData":"0xabcd","callGasLimit":"0x100","#,
+                r#""eip7702Auth":{"chainId":"0x1","yParity":"0x1"},"nonce":"0x1","#,
+                r#""sender":"0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266","#,
+                r#""verificationGasLimit":"0x10000"}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_unparseable_quantity_kept() {
+        assert_eq!(
+            canonical_json(&json!({ "nonce": "0xZZ", "callGasLimit": "0x" })),
+            r#"{"callGasLimit":"0x","nonce":"0xzz"}"#
+        );
+    }
+
+    const ROUNDS: usize = 200;
+
+    /// Value of one operation field, before it is written out
+    #[derive(Debug, Clone)]
+    enum Field {
+        Quantity(u128),
+        Data(Vec<u8>),
+        /// Optional v0.7 field that is unset
+        Unset,
+    }
+
+    const QUANTITIES: &[&str] = &[
+        "nonce",
+        "callGasLimit",
+        "verificationGasLimit",
+        "preVerificationGas",
+        "maxFeePerGas",
+        "maxPriorityFeePerGas",
+        "paymasterVerificationGasLimit",
+        "paymasterPostOpGasLimit",
+    ];
+    const DATA: &[&str] = &[
+        "sender",
+        "callData",
+        "factoryData",
+        "paymasterData",
+        "signature",
+    ];
+    const OPTIONAL: &[&str] = &["factory", "paymaster"];
+
+    fn random_op(rng: &mut StdRng) -> Vec<(&'static str, Field)> {
+        let mut fields = Vec::new();
+        for name in QUANTITIES {
+            let bits = rng.gen_range(0..128);
+            fields.push((*name, Field::Quantity(rng.gen::<u128>() >> bits)));
+        }
+        for name in DATA {
+            let len = if *name == "sender" {
+                20
+            } else {
+                rng.gen_range(0..12)
+            };
+            fields.push((*name, Field::Data((0..len).map(|_| rng.gen()).collect())));
+        }
+        for name in OPTIONAL {
+            fields.push((*name, Field::Unset));
+        }
+        fields
+    }
+
+    /// One of the many equivalent ways a client may write `fields`
+    fn write_op(fields: &[(&'static str, Field)], rng: &mut StdRng) -> Value {
+        let mut members: Vec<String> = fields
+            .iter()
+            .filter_map(|(name, field)| {
+                let value = match field {
+                    Field::Quantity(q) => write_quantity(*q, rng)?,
+                    Field::Data(bytes) => format!("\"{}\"", write_hex(bytes, rng)),
+                    Field::Unset if rng.gen_bool(0.5) => "null".to_string(),
+                    Field::Unset => return None,
+                };
+                let space = if rng.gen_bool(0.5) { " " } else { "" };
+                Some(format!("\"{}\":{}{}", name, space, value))
+            })
+            .collect();
+        members.shuffle(rng);
+        let text = format!("{{\n  {}\n}}", members.join(",\n  "));
+        serde_json::from_str(&text).unwrap()
+    }
+
+    fn write_quantity(q: u128, rng: &mut StdRng) -> Option<String> {
+        Some(match rng.gen_range(0..4) {
+            0 => format!("\"{:#x}\"", q),
+            1 => format!("\"0x{:0width$x}\"", q, width = rng.gen_range(1..40)),
+            2 => format!("\"{}\"", q),
+            _ if q <= u64::MAX as u128 => q.to_string(),
+            _ => format!("\"0X{:X}\"", q),
+        })
+    }
+
+    fn write_hex(bytes: &[u8], rng: &mut StdRng) -> String {
+        let prefix = if rng.gen_bool(0.8) { "0x" } else { "0X" };
+        let digits: String = hex::encode(bytes)
+            .chars()
+            .map(|c| {
+                if rng.gen_bool(0.5) {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                }
+            })
+            .collect();
+        format!("{}{}", prefix, digits)
+    }
+
+    /// `field` with a different value
+    fn mutate(field: &Field, rng: &mut StdRng) -> Field {
+        match field {
+            Field::Quantity(q) => Field::Quantity(q ^ (1 << rng.gen_range(0..128))),
+            Field::Data(bytes) if bytes.is_empty() => Field::Data(vec![rng.gen()]),
+            Field::Data(bytes) => {
+                let mut bytes = bytes.clone();
+                let index = rng.gen_range(0..bytes.len());
+                bytes[index] ^= 1 << rng.gen_range(0..8);
+                Field::Data(bytes)
+            }
+            Field::Unset => Field::Data((0..20).map(|_| rng.gen()).collect()),
+        }
+    }
+
+    #[test]
+    fn test_equivalent_encodings_share_digest() {
+        let mut rng = StdRng::seed_from_u64(1334);
+        for _ in 0..ROUNDS {
+            let op = random_op(&mut rng);
+            let first = write_op(&op, &mut rng);
+            let second = write_op(&op, &mut rng);
+            assert_eq!(
+                canonical_json(&first),
+                canonical_json(&second),
+                "{} vs {}",
+                first,
+                second
+            );
+            assert_eq!(canonical_digest(&first), canonical_digest(&second));
+        }
+    }
+
+    #[test]
+    fn test_any_field_change_changes_digest() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..ROUNDS {
+            let op = random_op(&mut rng);
+            let digest = canonical_digest(&write_op(&op, &mut rng));
+            for index in 0..op.len() {
+                let mut changed = op.clone();
+                changed[index].1 = mutate(&op[index].1, &mut rng);
+                assert_ne!(
+                    canonical_digest(&write_op(&changed, &mut rng)),
+                    digest,
+                    "changing {} kept the digest",
+                    op[index].0
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonical_form_is_compact_and_sorted() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..ROUNDS {
+            let canonical = canonical_json(&write_op(&random_op(&mut rng), &mut rng));
+            assert!(!canonical.contains(char::is_whitespace), "{}", canonical);
+            assert!(!canonical.contains("null"), "{}", canonical);
+
+            let parsed: serde_json::Map<String, Value> = serde_json::from_str(&canonical).unwrap();
+            assert!(parsed.values().all(|value| {
+                let s = value.as_str().unwrap();
+                s == s.to_ascii_lowercase()
+            }));
+
+            let mut keys: Vec<&String> = parsed.keys().collect();
+            keys.sort();
+            let positions: Vec<usize> = keys
+                .iter()
+                .map(|key| canonical.find(&format!("\"{}\":", key)).unwrap())
+                .collect();
+            assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+
+    fn user_op(nonce: u64, signature: &[u8], paymaster_data: &[u8]) -> UserOperationVariant {
+        let op = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender: Address::from_str("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266").unwrap(),
+                nonce: U256::from(nonce),
+                call_data: Bytes::from_static(&[0xb6, 0x1d, 0x27, 0xf6]),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 1_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::copy_from_slice(signature),
+            },
+        )
+        .paymaster(
+            Address::repeat_byte(0x11),
+            50_000,
+            10_000,
+            Bytes::copy_from_slice(paymaster_data),
+        )
+        .build();
+        UserOperationVariant::V0_7(op)
+    }
+
+    #[test]
+    fn test_typed_digest_matches_client_encodings() {
+        let op = user_op(3, &[0xAB; 65], &[0xCD; 77]);
+        let digest = user_operation_digest(&op);
+
+        // The same operation as a client would send it, keys shuffled and recased
+        let mut rng = StdRng::seed_from_u64(3);
+        let json = user_operation_json(&op);
+        for _ in 0..ROUNDS {
+            let mut members: Vec<String> = json
+                .as_object()
+                .unwrap()
+                .iter()
+                .map(|(key, value)| {
+                    let value = match value.as_str() {
+                        Some(s) if rng.gen_bool(0.5) => format!("\"{}\"", s.to_ascii_uppercase()),
+                        _ => value.to_string(),
+                    };
+                    format!("\"{}\": {}", key, value.replace("\"0X", "\"0x"))
+                })
+                .collect();
+            members.shuffle(&mut rng);
+            let written: Value =
+                serde_json::from_str(&format!("{{{}}}", members.join(", "))).unwrap();
+            assert_eq!(canonical_digest(&written), digest);
+        }
+    }
+
+    #[test]
+    fn test_operation_hash_ignores_sponsorship_fields() {
+        let entry_point = ChainSpec::default().entry_point_address_v0_7;
+        let unsigned = operation_hash(&user_op(1, &[], &[]), entry_point);
+
+        assert_eq!(
+            operation_hash(&user_op(1, &[0xAB; 65], &[0xCD; 77]), entry_point),
+            unsigned
+        );
+        assert_ne!(operation_hash(&user_op(2, &[], &[]), entry_point), unsigned);
+        assert_ne!(
+            operation_hash(&user_op(1, &[], &[]), Address::repeat_byte(0x22)),
+            unsigned
+        );
+    }
+}
@@ -0,0 +1,493 @@
+// paymaster-relay/src/clock_skew.rs
+// Local clock checked against the chain. Sponsorship windows are computed with local time
+// but enforced against block timestamps, so a drifting clock gets every signature expired
+// on chain. The measured skew corrects the clock windows and admin signatures are checked
+// with, is exported and warned about, and stops signing altogether once it is too large.
+
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use rundler_provider::{BlockId, EvmProvider};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::{error::PaymasterError, metrics::PaymasterMetrics};
+
+/// Clock skew check configuration (`[paymaster_relay.clock_skew]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ClockSkewConfig {
+    /// Measure the skew against the latest block and act on it
+    pub enabled: bool,
+    /// Seconds between measurements
+    pub check_interval_seconds: u64,
+    /// Skew, in either direction, logged as a warning and reported by /health
+    pub warn_seconds: u64,
+    /// Skew, in either direction, above which no sponsorship is signed
+    pub refuse_seconds: u64,
+    /// Seconds added to every validUntil so small skews leave signatures usable
+    pub safety_buffer_seconds: u64,
+}
+
+impl Default for ClockSkewConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            check_interval_seconds: 60,
+            warn_seconds: 15,
+            refuse_seconds: 60,
+            safety_buffer_seconds: 30,
+        }
+    }
+}
+
+/// Source of the chain's notion of time
+#[async_trait]
+pub trait BlockTimestampSource: Send + Sync {
+    /// Number and timestamp of the latest block
+    async fn latest_block(&self) -> Result<(u64, u64), PaymasterError>;
+}
+
+/// [`BlockTimestampSource`] reading the latest block through a node provider
+pub struct ProviderBlockTimestamps<P> {
+    provider: P,
+}
+
+impl<P> ProviderBlockTimestamps<P> {
+    pub fn new(provider: P) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> BlockTimestampSource for ProviderBlockTimestamps<P> {
+    async fn latest_block(&self) -> Result<(u64, u64), PaymasterError> {
+        let block = self
+            .provider
+            .get_block(BlockId::latest())
+            .await
+            .map_err(|e| PaymasterError::ChainError(format!("Failed to get latest block: {}", e)))?
+            .ok_or_else(|| {
+                PaymasterError::ChainError("Node returned no latest block".to_string())
+            })?;
+        Ok((block.header.number, block.header.timestamp))
+    }
+}
+
+/// How far the measured skew is from the thresholds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockSkewState {
+    /// Not measured yet; signing goes ahead on the local clock
+    Unknown,
+    /// Within the warning threshold
+    Ok,
+    /// Above the warning threshold
+    Warning,
+    /// Above the refusal threshold, sponsorships are not signed
+    Refusing,
+}
+
+/// Last clock skew measurement, reported by /health
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockSkewStatus {
+    pub state: ClockSkewState,
+    /// Local minus chain time in seconds, positive when the local clock is ahead
+    pub skew_seconds: Option<i64>,
+    /// Block the skew was measured against
+    pub block_number: Option<u64>,
+    /// Local unix time of the last measurement
+    pub measured_at: Option<u64>,
+    pub warn_seconds: u64,
+    pub refuse_seconds: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Measurement {
+    block_number: u64,
+    skew: i64,
+    measured_at: u64,
+}
+
+/// Measures the local clock against block timestamps and corrects it
+pub struct ClockGuard {
+    config: ClockSkewConfig,
+    source: Arc<dyn BlockTimestampSource>,
+    measurement: RwLock<Option<Measurement>>,
+    metrics: PaymasterMetrics,
+}
+
+impl ClockGuard {
+    pub fn new(source: Arc<dyn BlockTimestampSource>, config: ClockSkewConfig) -> Self {
+        Self {
+            config,
+            source,
+            measurement: RwLock::new(None),
+            metrics: PaymasterMetrics::new(),
+        }
+    }
+
+    pub fn config(&self) -> &ClockSkewConfig {
+        &self.config
+    }
+
+    /// Compare the local clock with the latest block now
+    ///
+    /// A block is measured when first seen: a head that has not moved since
+    /// the last measurement keeps its skew, so a slow or idle chain does not
+    /// count the age of its head as skew. The first measurement after start
+    /// can still be off by up to one block time.
+    pub async fn measure(&self) -> Result<ClockSkewStatus, PaymasterError> {
+        let (block_number, timestamp) = self.source.latest_block().await?;
+        let now = local_now();
+        let measured = {
+            let mut measurement = self.measurement.write().unwrap();
+            let skew = match *measurement {
+                Some(previous) if previous.block_number == block_number => previous.skew,
+                _ => now as i64 - timestamp as i64,
+            };
+            let measured = Measurement {
+                block_number,
+                skew,
+                measured_at: now,
+            };
+            *measurement = Some(measured);
+            measured
+        };
+
+        self.metrics.update_clock_skew(measured.skew);
+        let status = self.status();
+        match status.state {
+            ClockSkewState::Refusing => error!(
+                "🕰️ Local clock is {}s off block {}, refusing to sign sponsorships above {}s",
+                measured.skew, block_number, self.config.refuse_seconds
+            ),
+            ClockSkewState::Warning => warn!(
+                "🕰️ Local clock is {}s off block {} (warning above {}s)",
+                measured.skew, block_number, self.config.warn_seconds
+            ),
+            _ => {}
+        }
+        Ok(status)
+    }
+
+    /// Measure at startup and every `check_interval_seconds` after
+    pub fn spawn(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let guard = self.clone();
+        let interval = self.config.check_interval_seconds.max(1);
+        info!(
+            "🕰️ Clock skew check every {}s (warn {}s, refuse {}s, buffer {}s)",
+            interval,
+            self.config.warn_seconds,
+            self.config.refuse_seconds,
+            self.config.safety_buffer_seconds
+        );
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+            loop {
+                ticker.tick().await;
+                if let Err(e) = guard.measure().await {
+                    warn!("Clock skew check failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Last measurement against the thresholds
+    pub fn status(&self) -> ClockSkewStatus {
+        let measurement = *self.measurement.read().unwrap();
+        let state = match measurement {
+            None => ClockSkewState::Unknown,
+            Some(m) if m.skew.unsigned_abs() > self.config.refuse_seconds => {
+                ClockSkewState::Refusing
+            }
+            Some(m) if m.skew.unsigned_abs() > self.config.warn_seconds => ClockSkewState::Warning,
+            Some(_) => ClockSkewState::Ok,
+        };
+        ClockSkewStatus {
+            state,
+            skew_seconds: measurement.map(|m| m.skew),
+            block_number: measurement.map(|m| m.block_number),
+            measured_at: measurement.map(|m| m.measured_at),
+            warn_seconds: self.config.warn_seconds,
+            refuse_seconds: self.config.refuse_seconds,
+        }
+    }
+
+    /// Unix time corrected by the measured skew, the local time until measured
+    pub fn now(&self) -> u64 {
+        let skew = self
+            .measurement
+            .read()
+            .unwrap()
+            .map_or(0, |measurement| measurement.skew);
+        (local_now() as i64).saturating_sub(skew).max(0) as u64
+    }
+
+    /// Fail closed while the skew is above the refusal threshold, as the
+    /// validity window would be wrong on chain
+    pub fn check_signing(&self) -> Result<(), PaymasterError> {
+        let status = self.status();
+        if status.state == ClockSkewState::Refusing {
+            return Err(PaymasterError::SignerError(eyre::eyre!(
+                "Refusing to sign: local clock is {}s off the chain (limit {}s)",
+                status.skew_seconds.unwrap_or_default(),
+                self.config.refuse_seconds
+            )));
+        }
+        Ok(())
+    }
+
+    /// `(validAfter, validUntil)` of a sponsorship valid for `validity_seconds`
+    ///
+    /// Sponsorships are valid from signing on, so validAfter stays 0 and no
+    /// skew can put it in the future. validUntil counts from the corrected
+    /// clock and gets the safety buffer on top.
+    pub fn validity_window(&self, validity_seconds: u64) -> (u64, u64) {
+        (
+            0,
+            self.now() + validity_seconds + self.config.safety_buffer_seconds,
+        )
+    }
+}
+
+fn local_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::Path,
+        sync::{Arc, Mutex},
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use alloy_primitives::{address, Address, Bytes, U256};
+    use async_trait::async_trait;
+    use rundler_types::{chain::ChainSpec, v0_7, UserOperationVariant};
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{
+        service::{PaymasterRelayService, SponsorOptions},
+        test_utils, PaymasterError,
+    };
+
+    const ALICE: Address = address!("00000000000000000000000000000000000000a1");
+
+    /// Latest block set by the test
+    #[derive(Default)]
+    struct MockBlocks {
+        latest: Mutex<(u64, u64)>,
+    }
+
+    impl MockBlocks {
+        /// Produce block `number` with a timestamp `offset` seconds off the local clock
+        fn produce(&self, number: u64, offset: i64) {
+            *self.latest.lock().unwrap() = (number, (now() as i64 + offset) as u64);
+        }
+    }
+
+    #[async_trait]
+    impl BlockTimestampSource for MockBlocks {
+        async fn latest_block(&self) -> Result<(u64, u64), PaymasterError> {
+            Ok(*self.latest.lock().unwrap())
+        }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn config() -> ClockSkewConfig {
+        ClockSkewConfig {
+            enabled: true,
+            warn_seconds: 15,
+            refuse_seconds: 60,
+            safety_buffer_seconds: 30,
+            ..Default::default()
+        }
+    }
+
+    fn create_guard() -> (Arc<MockBlocks>, Arc<ClockGuard>) {
+        let blocks = Arc::new(MockBlocks::default());
+        let guard = Arc::new(ClockGuard::new(blocks.clone(), config()));
+        (blocks, guard)
+    }
+
+    fn create_service(dir: &Path, guard: Arc<ClockGuard>) -> PaymasterRelayService {
+        test_utils::service_in(dir, &format!("senders = [\"{}\"]\n", ALICE)).with_clock_guard(guard)
+    }
+
+    fn op() -> (UserOperationVariant, ethers::types::Address) {
+        let op = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender: ALICE,
+                nonce: U256::ZERO,
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 2_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        let entry_point = ChainSpec::default().entry_point_address_v0_7;
+        (
+            UserOperationVariant::V0_7(op),
+            ethers::types::Address::from_slice(entry_point.as_slice()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_unknown_until_measured() {
+        let (_, guard) = create_guard();
+
+        let status = guard.status();
+        assert_eq!(status.state, ClockSkewState::Unknown);
+        assert_eq!(status.skew_seconds, None);
+        assert!(guard.check_signing().is_ok());
+        assert!(guard.now().abs_diff(now()) <= 1);
+    }
+
+    #[tokio::test]
+    async fn test_skew_above_warning_threshold() {
+        let (blocks, guard) = create_guard();
+
+        blocks.produce(1, -5);
+        assert_eq!(guard.measure().await.unwrap().state, ClockSkewState::Ok);
+
+        // Local clock 20s ahead of the chain
+        blocks.produce(2, -20);
+        let status = guard.measure().await.unwrap();
+        assert_eq!(status.state, ClockSkewState::Warning);
+        assert_eq!(status.block_number, Some(2));
+        assert!((19..=21).contains(&status.skew_seconds.unwrap()));
+        // Still signing, on the corrected clock
+        assert!(guard.check_signing().is_ok());
+        assert!(now().abs_diff(guard.now() + 20) <= 1);
+    }
+
+    #[tokio::test]
+    async fn test_head_that_did_not_move_keeps_its_skew() {
+        let (blocks, guard) = create_guard();
+
+        blocks.produce(7, 0);
+        let first = guard.measure().await.unwrap();
+        // An idle chain whose head aged past the refusal threshold is not skew
+        *blocks.latest.lock().unwrap() = (7, now() - 120);
+        let again = guard.measure().await.unwrap();
+
+        assert_eq!(again.skew_seconds, first.skew_seconds);
+        assert_eq!(again.state, ClockSkewState::Ok);
+    }
+
+    #[tokio::test]
+    async fn test_refuses_to_sign_above_refusal_threshold() {
+        let dir = tempdir().unwrap();
+        let (blocks, guard) = create_guard();
+        let service = create_service(dir.path(), guard.clone());
+
+        // Local clock 90s behind the chain
+        blocks.produce(1, 90);
+        assert_eq!(
+            guard.measure().await.unwrap().state,
+            ClockSkewState::Refusing
+        );
+        let (user_op, entry_point) = op();
+        let err = service
+            .sponsor_user_operation(user_op, entry_point, SponsorOptions::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PaymasterError::SignerError(_)), "{}", err);
+        assert!(err.to_string().contains("clock"), "{}", err);
+
+        // Signing resumes once the clock agrees with a new block
+        blocks.produce(2, 0);
+        assert_eq!(guard.measure().await.unwrap().state, ClockSkewState::Ok);
+        let (user_op, entry_point) = op();
+        service
+            .sponsor_user_operation(user_op, entry_point, SponsorOptions::default())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_validity_window_uses_corrected_clock_and_buffer() {
+        let dir = tempdir().unwrap();
+        let (blocks, guard) = create_guard();
+        let service = create_service(dir.path(), guard.clone());
+
+        // Chain 30s ahead: the window counts from chain time, plus the buffer
+        blocks.produce(1, 30);
+        guard.measure().await.unwrap();
+        let before = now();
+        let (valid_after, valid_until) = guard.validity_window(120);
+        let after = now();
+        assert_eq!(valid_after, 0);
+        assert!(valid_until >= before + 30 + 120 + 30 - 1);
+        assert!(valid_until <= after + 30 + 120 + 30 + 1);
+
+        let (user_op, entry_point) = op();
+        let options = SponsorOptions {
+            validity_seconds: Some(120),
+            ..Default::default()
+        };
+        let before = now();
+        let result = service
+            .sponsor_user_operation(user_op, entry_point, options)
+            .await
+            .unwrap();
+        let after = now();
+        assert_eq!(result.valid_after, 0);
+        assert!(result.valid_until >= before + 30 + 120 + 30 - 1);
+        assert!(result.valid_until <= after + 30 + 120 + 30 + 1);
+    }
+
+    #[tokio::test]
+    async fn test_own_sponsorship_verified_on_corrected_clock() {
+        let dir = tempdir().unwrap();
+        let blocks = Arc::new(MockBlocks::default());
+        let guard = Arc::new(ClockGuard::new(
+            blocks.clone(),
+            ClockSkewConfig {
+                refuse_seconds: 300,
+                ..config()
+            },
+        ));
+        let service = create_service(dir.path(), guard.clone());
+
+        // Local clock 100s ahead of the chain: the shortest window ends before
+        // local time, but not before chain time
+        blocks.produce(1, -100);
+        guard.measure().await.unwrap();
+        let (user_op, entry_point) = op();
+        let options = SponsorOptions {
+            validity_seconds: Some(30),
+            return_full_operation: true,
+            ..Default::default()
+        };
+        let result = service
+            .sponsor_user_operation(user_op, entry_point, options)
+            .await
+            .unwrap();
+        assert!(result.valid_until < now());
+
+        let sponsored = result.sponsored_user_op.unwrap();
+        assert!(service.verify_own_sponsorship(&sponsored).await.unwrap());
+    }
+}
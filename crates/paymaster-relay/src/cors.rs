@@ -0,0 +1,313 @@
+// paymaster-relay/src/cors.rs
+// CORS policy shared by the gateway, Swagger UI and proxy API servers.
+
+use std::time::Duration;
+
+use axum::{
+    http::{HeaderName, HeaderValue, Method},
+    Router,
+};
+use serde::Deserialize;
+use thiserror::Error;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer};
+use tracing::warn;
+
+/// CORS configuration (`[cors]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Add CORS headers at all
+    pub enabled: bool,
+    /// Origins allowed to call the API, e.g. "https://dash.example.com" or
+    /// "https://*.example.com". "*" allows any origin; an empty list keeps the
+    /// permissive allow-all policy.
+    pub allowed_origins: Vec<String>,
+    /// Allowed request methods, any when empty
+    pub allowed_methods: Vec<String>,
+    /// Allowed request headers, any when empty
+    pub allowed_headers: Vec<String>,
+    /// Seconds browsers may cache a preflight response
+    pub max_age_seconds: Option<u64>,
+    /// Allow cookies and other credentials on cross-origin requests
+    pub allow_credentials: bool,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            allowed_origins: Vec::new(),
+            allowed_methods: vec!["GET".to_string(), "POST".to_string(), "OPTIONS".to_string()],
+            allowed_headers: Vec::new(),
+            max_age_seconds: None,
+            allow_credentials: false,
+        }
+    }
+}
+
+/// Invalid entry in a [`CorsConfig`]
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CorsConfigError {
+    #[error("Invalid CORS method: {0}")]
+    InvalidMethod(String),
+
+    #[error("Invalid CORS header: {0}")]
+    InvalidHeader(String),
+
+    #[error("Invalid CORS origin: {0}")]
+    InvalidOrigin(String),
+}
+
+impl CorsConfig {
+    /// CORS layer for this policy, `None` when CORS is disabled
+    pub fn layer(&self) -> Result<Option<CorsLayer>, CorsConfigError> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        if self.allowed_origins.is_empty() {
+            warn!("CORS allows any origin; set allowed_origins to restrict it");
+            return Ok(Some(CorsLayer::permissive()));
+        }
+
+        let mut layer = CorsLayer::new()
+            .allow_origin(self.allow_origin()?)
+            .allow_methods(self.allow_methods()?)
+            .allow_headers(self.allow_headers()?)
+            .allow_credentials(self.allow_credentials);
+        if let Some(max_age) = self.max_age_seconds {
+            layer = layer.max_age(Duration::from_secs(max_age));
+        }
+        Ok(Some(layer))
+    }
+
+    /// Add this policy's CORS layer to `router`
+    pub fn apply<S>(&self, router: Router<S>) -> Result<Router<S>, CorsConfigError>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Ok(match self.layer()? {
+            Some(layer) => router.layer(layer),
+            None => router,
+        })
+    }
+
+    fn allow_origin(&self) -> Result<AllowOrigin, CorsConfigError> {
+        if self.allowed_origins.iter().any(|origin| origin == "*") {
+            // Credentials cannot be combined with a literal "*"
+            return Ok(if self.allow_credentials {
+                AllowOrigin::mirror_request()
+            } else {
+                Any.into()
+            });
+        }
+
+        if !self
+            .allowed_origins
+            .iter()
+            .any(|origin| origin.contains('*'))
+        {
+            let origins = self
+                .allowed_origins
+                .iter()
+                .map(|origin| {
+                    HeaderValue::from_str(origin)
+                        .map_err(|_| CorsConfigError::InvalidOrigin(origin.clone()))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(AllowOrigin::list(origins));
+        }
+
+        let patterns = self.allowed_origins.clone();
+        Ok(AllowOrigin::predicate(move |origin, _| {
+            origin
+                .to_str()
+                .map(|origin| patterns.iter().any(|p| origin_matches(p, origin)))
+                .unwrap_or(false)
+        }))
+    }
+
+    fn allow_methods(&self) -> Result<AllowMethods, CorsConfigError> {
+        if self.allowed_methods.is_empty() {
+            return Ok(if self.allow_credentials {
+                AllowMethods::mirror_request()
+            } else {
+                Any.into()
+            });
+        }
+        let methods = self
+            .allowed_methods
+            .iter()
+            .map(|method| {
+                Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                    .map_err(|_| CorsConfigError::InvalidMethod(method.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(methods.into())
+    }
+
+    fn allow_headers(&self) -> Result<AllowHeaders, CorsConfigError> {
+        if self.allowed_headers.is_empty() {
+            return Ok(if self.allow_credentials {
+                AllowHeaders::mirror_request()
+            } else {
+                Any.into()
+            });
+        }
+        let headers = self
+            .allowed_headers
+            .iter()
+            .map(|header| {
+                HeaderName::from_bytes(header.as_bytes())
+                    .map_err(|_| CorsConfigError::InvalidHeader(header.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(headers.into())
+    }
+}
+
+/// Whether `origin` matches `pattern`, where a `*` in the pattern matches any
+/// non-empty run of characters (e.g. "https://*.example.com")
+fn origin_matches(pattern: &str, origin: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            origin.len() > prefix.len() + suffix.len()
+                && origin.starts_with(prefix)
+                && origin.ends_with(suffix)
+        }
+        None => pattern.eq_ignore_ascii_case(origin),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Method, Request, Response, StatusCode},
+        routing::post,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_origin_wildcard_patterns() {
+        assert!(origin_matches(
+            "https://*.example.com",
+            "https://dash.example.com"
+        ));
+        assert!(!origin_matches(
+            "https://*.example.com",
+            "https://example.com"
+        ));
+        assert!(!origin_matches(
+            "https://*.example.com",
+            "https://dash.example.com.evil.io"
+        ));
+        assert!(origin_matches(
+            "https://Dash.example.com",
+            "https://dash.example.com"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_method_rejected() {
+        let config = CorsConfig {
+            allowed_origins: vec!["https://dash.example.com".to_string()],
+            allowed_methods: vec!["GET POST".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.layer(),
+            Err(CorsConfigError::InvalidMethod(_))
+        ));
+    }
+
+    const DASHBOARD: &str = "https://dash.example.com";
+
+    fn create_router(cors: CorsConfig) -> Router {
+        let router = Router::new().route("/", post(|| async { "ok" }));
+        cors.apply(router).unwrap()
+    }
+
+    fn restricted(origins: &[&str]) -> CorsConfig {
+        CorsConfig {
+            allowed_origins: origins.iter().map(|origin| origin.to_string()).collect(),
+            allowed_headers: vec!["content-type".to_string(), "x-api-key".to_string()],
+            max_age_seconds: Some(600),
+            ..Default::default()
+        }
+    }
+
+    async fn preflight(router: Router, origin: &str) -> Response<Body> {
+        let request = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "x-api-key")
+            .body(Body::empty())
+            .unwrap();
+        router.oneshot(request).await.unwrap()
+    }
+
+    fn allowed_origin(response: &Response<Body>) -> Option<&str> {
+        response
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .map(|value| value.to_str().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_preflight_from_allowed_origin() {
+        let response = preflight(create_router(restricted(&[DASHBOARD])), DASHBOARD).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(allowed_origin(&response), Some(DASHBOARD));
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_MAX_AGE], "600");
+        let methods = response.headers()[header::ACCESS_CONTROL_ALLOW_METHODS]
+            .to_str()
+            .unwrap();
+        assert!(methods.contains("POST"));
+    }
+
+    #[tokio::test]
+    async fn test_preflight_from_denied_origin() {
+        let response = preflight(
+            create_router(restricted(&[DASHBOARD])),
+            "https://evil.example.org",
+        )
+        .await;
+
+        assert_eq!(allowed_origin(&response), None);
+    }
+
+    #[tokio::test]
+    async fn test_preflight_with_wildcards() {
+        // Subdomain pattern
+        let router = create_router(restricted(&["https://*.example.com"]));
+        let response = preflight(router.clone(), DASHBOARD).await;
+        assert_eq!(allowed_origin(&response), Some(DASHBOARD));
+        let response = preflight(router, "https://example.com.evil.io").await;
+        assert_eq!(allowed_origin(&response), None);
+
+        // "*" allows any origin
+        let response = preflight(create_router(restricted(&["*"])), "https://any.site").await;
+        assert_eq!(allowed_origin(&response), Some("*"));
+
+        // No origins configured keeps the permissive policy
+        let response = preflight(create_router(CorsConfig::default()), "https://any.site").await;
+        assert!(allowed_origin(&response).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_cors_adds_no_headers() {
+        let cors = CorsConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let response = preflight(create_router(cors), DASHBOARD).await;
+
+        assert_eq!(allowed_origin(&response), None);
+    }
+}
@@ -0,0 +1,636 @@
+// paymaster-relay/src/dashboard.rs
+// Data behind the admin dashboard tables: paymaster balances, loaded policies
+// and a bounded history of recent sponsorships. Every report carries an
+// `errors` list so a failing dependency leaves the rest of the dashboard usable.
+
+use std::{collections::VecDeque, sync::Mutex};
+
+use alloy_primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audit::{AuditOutcome, AuditRecord},
+    balance_monitor::{wei_to_eth, BalanceMonitorConfig},
+    deposit::DepositManager,
+    policy::{PolicyEngine, PolicySummary},
+};
+
+/// Sponsorships kept for the dashboard transaction table
+pub const DEFAULT_HISTORY_CAPACITY: usize = 200;
+
+/// Health of the paymaster balances
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceHealth {
+    /// Every balance was read and none is below its low-water mark
+    Healthy,
+    /// A balance is below its low-water mark
+    Warning,
+    /// Some balances could not be read, the others are healthy
+    Degraded,
+    /// No balance could be read
+    Unknown,
+}
+
+/// Paymaster deposit on one EntryPoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DashboardDeposit {
+    pub entry_point: Address,
+    pub deposit_wei: U256,
+    pub deposit_eth: f64,
+    pub staked: bool,
+    pub stake_wei: U256,
+    pub unstake_delay_sec: u32,
+    /// Below the deposit low-water mark
+    pub low: bool,
+}
+
+/// GET /dashboard/api/balance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DashboardBalance {
+    pub paymaster: Option<Address>,
+    /// Paymaster signer account that pays for top-ups
+    pub account: Option<Address>,
+    pub paymaster_balance_wei: Option<U256>,
+    pub paymaster_balance_eth: Option<f64>,
+    /// Sum of the deposits that could be read
+    pub entrypoint_deposit_eth: f64,
+    pub deposits: Vec<DashboardDeposit>,
+    pub status: BalanceHealth,
+    pub errors: Vec<String>,
+}
+
+impl DashboardBalance {
+    /// Report with no data, when the paymaster service cannot be reached
+    pub fn unavailable(error: String) -> Self {
+        Self {
+            paymaster: None,
+            account: None,
+            paymaster_balance_wei: None,
+            paymaster_balance_eth: None,
+            entrypoint_deposit_eth: 0.0,
+            deposits: Vec::new(),
+            status: BalanceHealth::Unknown,
+            errors: vec![error],
+        }
+    }
+
+    /// Read the balance of `account` and the deposits of `paymaster` on the
+    /// EntryPoints of `deposits`, reporting each failed read in `errors`
+    pub async fn read(
+        deposits: Option<&DepositManager>,
+        paymaster: Address,
+        account: Address,
+        thresholds: &BalanceMonitorConfig,
+    ) -> Self {
+        let mut report = Self {
+            paymaster: Some(paymaster),
+            account: Some(account),
+            paymaster_balance_wei: None,
+            paymaster_balance_eth: None,
+            entrypoint_deposit_eth: 0.0,
+            deposits: Vec::new(),
+            status: BalanceHealth::Unknown,
+            errors: Vec::new(),
+        };
+
+        let Some(deposits) = deposits else {
+            report
+                .errors
+                .push("Deposit management is not configured".to_string());
+            return report;
+        };
+
+        let mut low = false;
+        match deposits.account_balance(account).await {
+            Ok(balance) => {
+                low |= balance < thresholds.account_low_water_wei;
+                report.paymaster_balance_wei = Some(balance);
+                report.paymaster_balance_eth = Some(wei_to_eth(balance));
+            }
+            Err(e) => report.errors.push(format!("account balance: {}", e)),
+        }
+        if deposits.entry_points().is_empty() {
+            report.errors.push("No EntryPoints configured".to_string());
+        }
+        for &entry_point in deposits.entry_points() {
+            match deposits.deposit_info(entry_point, paymaster).await {
+                Ok(info) => {
+                    let deposit = DashboardDeposit {
+                        entry_point,
+                        deposit_wei: info.deposit,
+                        deposit_eth: wei_to_eth(info.deposit),
+                        staked: info.staked,
+                        stake_wei: info.stake,
+                        unstake_delay_sec: info.unstake_delay_sec,
+                        low: info.deposit < thresholds.deposit_low_water_wei,
+                    };
+                    low |= deposit.low;
+                    report.entrypoint_deposit_eth += deposit.deposit_eth;
+                    report.deposits.push(deposit);
+                }
+                Err(e) => report
+                    .errors
+                    .push(format!("deposit on {}: {}", entry_point, e)),
+            }
+        }
+
+        let read_any = report.paymaster_balance_wei.is_some() || !report.deposits.is_empty();
+        report.status = match (read_any, low, report.errors.is_empty()) {
+            (false, _, _) => BalanceHealth::Unknown,
+            (true, true, _) => BalanceHealth::Warning,
+            (true, false, false) => BalanceHealth::Degraded,
+            (true, false, true) => BalanceHealth::Healthy,
+        };
+        report
+    }
+}
+
+/// GET /dashboard/api/policies
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DashboardPolicies {
+    pub policies: Vec<DashboardPolicy>,
+    pub total_policies: usize,
+    pub errors: Vec<String>,
+}
+
+/// One row of the policy table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DashboardPolicy {
+    pub id: String,
+    pub sender_count: usize,
+    pub tokens: Option<Vec<Address>>,
+    pub max_token_amounts: Vec<TokenCap>,
+    /// Sponsorships this policy allowed among the recent history
+    pub recent_sponsorships: usize,
+}
+
+/// Per-operation cap of one token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenCap {
+    pub token: Address,
+    pub max_amount: U256,
+}
+
+impl DashboardPolicies {
+    /// Report with no data, when the paymaster service cannot be reached
+    pub fn unavailable(error: String) -> Self {
+        Self {
+            policies: Vec::new(),
+            total_policies: 0,
+            errors: vec![error],
+        }
+    }
+
+    /// Policies loaded in `engine`, with their sponsorships in `history`
+    pub fn read(engine: &PolicyEngine, history: &SponsorshipHistory) -> Self {
+        let policies: Vec<_> = engine
+            .summaries()
+            .into_iter()
+            .map(|summary| {
+                let PolicySummary {
+                    id,
+                    sender_count,
+                    tokens,
+                    max_token_amounts,
+                } = summary;
+                DashboardPolicy {
+                    recent_sponsorships: history.sponsored_by(&id),
+                    id,
+                    sender_count,
+                    tokens,
+                    max_token_amounts: max_token_amounts
+                        .into_iter()
+                        .map(|(token, max_amount)| TokenCap { token, max_amount })
+                        .collect(),
+                }
+            })
+            .collect();
+        Self {
+            total_policies: policies.len(),
+            policies,
+            errors: Vec::new(),
+        }
+    }
+}
+
+/// One sponsorship request and its outcome
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SponsorshipEntry {
+    /// RFC 3339 time the request was received
+    pub timestamp: String,
+    pub user_op_hash: String,
+    pub sender: String,
+    pub entry_point: String,
+    pub nonce: String,
+    pub policy_id: Option<String>,
+    pub requester: Option<String>,
+    pub outcome: AuditOutcome,
+    pub replayed: bool,
+    pub error_code: Option<i32>,
+    pub error_message: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl From<&AuditRecord> for SponsorshipEntry {
+    fn from(record: &AuditRecord) -> Self {
+        Self {
+            timestamp: record.timestamp.clone(),
+            user_op_hash: record.user_op_hash.clone(),
+            sender: record.sender.clone(),
+            entry_point: record.entry_point.clone(),
+            nonce: record.nonce.clone(),
+            policy_id: record.policy_id.clone(),
+            requester: record.requester.clone(),
+            outcome: record.outcome,
+            replayed: record.replayed,
+            error_code: record.error_code,
+            error_message: record.error_message.clone(),
+            duration_ms: record.duration_ms,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct HistoryState {
+    entries: VecDeque<SponsorshipEntry>,
+    total: u64,
+    successful: u64,
+}
+
+/// The most recent sponsorship requests, oldest dropped first
+#[derive(Debug)]
+pub struct SponsorshipHistory {
+    capacity: usize,
+    state: Mutex<HistoryState>,
+}
+
+impl Default for SponsorshipHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_CAPACITY)
+    }
+}
+
+impl SponsorshipHistory {
+    /// Keep the last `capacity` requests
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(HistoryState::default()),
+        }
+    }
+
+    /// Record a completed request
+    pub fn record(&self, entry: SponsorshipEntry) {
+        let mut state = self.state.lock().unwrap();
+        state.total += 1;
+        if entry.outcome == AuditOutcome::Success {
+            state.successful += 1;
+        }
+        if self.capacity == 0 {
+            return;
+        }
+        if state.entries.len() == self.capacity {
+            state.entries.pop_front();
+        }
+        state.entries.push_back(entry);
+    }
+
+    /// Successful sponsorships allowed by `policy_id` still in the history
+    fn sponsored_by(&self, policy_id: &str) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .filter(|entry| {
+                entry.outcome == AuditOutcome::Success
+                    && entry.policy_id.as_deref() == Some(policy_id)
+            })
+            .count()
+    }
+
+    /// Up to `limit` most recent requests, newest first, and the totals
+    pub fn report(&self, limit: usize) -> DashboardTransactions {
+        let state = self.state.lock().unwrap();
+        DashboardTransactions {
+            recent_transactions: state.entries.iter().rev().take(limit).cloned().collect(),
+            total_count: state.total,
+            successful_count: state.successful,
+            failed_count: state.total - state.successful,
+            capacity: self.capacity,
+            errors: Vec::new(),
+        }
+    }
+}
+
+/// GET /dashboard/api/transactions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DashboardTransactions {
+    /// Newest first
+    pub recent_transactions: Vec<SponsorshipEntry>,
+    /// Requests since the service started, including those dropped from the history
+    pub total_count: u64,
+    pub successful_count: u64,
+    pub failed_count: u64,
+    /// Most requests kept in `recent_transactions`
+    pub capacity: usize,
+    pub errors: Vec<String>,
+}
+
+impl DashboardTransactions {
+    /// Report with no data, when the paymaster service cannot be reached
+    pub fn unavailable(error: String) -> Self {
+        Self {
+            recent_transactions: Vec::new(),
+            total_count: 0,
+            successful_count: 0,
+            failed_count: 0,
+            capacity: 0,
+            errors: vec![error],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use alloy_primitives::{Address, Bytes, U256};
+    use async_trait::async_trait;
+    use rundler_provider::DepositInfo;
+    use rundler_types::{chain::ChainSpec, v0_7, UserOperationVariant};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        deposit::{DepositChain, DepositConfig, DepositManager},
+        service::{PaymasterRelayService, SponsorOptions},
+        test_utils, PaymasterError,
+    };
+
+    fn entry(nonce: u64, outcome: AuditOutcome) -> SponsorshipEntry {
+        SponsorshipEntry {
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            user_op_hash: format!("0x{:064x}", nonce),
+            sender: Address::ZERO.to_checksum(None),
+            entry_point: Address::ZERO.to_checksum(None),
+            nonce: format!("{:#x}", nonce),
+            policy_id: Some("default".to_string()),
+            requester: None,
+            outcome,
+            replayed: false,
+            error_code: None,
+            error_message: None,
+            duration_ms: 1,
+        }
+    }
+
+    #[test]
+    fn test_history_bounded_newest_first() {
+        let history = SponsorshipHistory::new(2);
+        history.record(entry(0, AuditOutcome::Success));
+        history.record(entry(1, AuditOutcome::Error));
+        history.record(entry(2, AuditOutcome::Success));
+
+        let report = history.report(10);
+        let nonces: Vec<_> = report
+            .recent_transactions
+            .iter()
+            .map(|entry| entry.nonce.as_str())
+            .collect();
+        assert_eq!(nonces, ["0x2", "0x1"]);
+        assert_eq!(report.total_count, 3);
+        assert_eq!(report.successful_count, 2);
+        assert_eq!(report.failed_count, 1);
+        assert_eq!(history.report(1).recent_transactions.len(), 1);
+        assert_eq!(history.sponsored_by("default"), 1);
+    }
+
+    const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const UNLISTED_SENDER: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+    const TOKEN: &str = "0x5FbDB2315678afecb367f032d93F642f64180aa3";
+
+    /// Chain whose balance reads succeed or fail as configured
+    struct StaticChain {
+        balance: Option<U256>,
+        deposit: Option<U256>,
+    }
+
+    #[async_trait]
+    impl DepositChain for StaticChain {
+        async fn get_deposit_info(
+            &self,
+            _entry_point: Address,
+            _account: Address,
+        ) -> Result<DepositInfo, PaymasterError> {
+            let deposit = self
+                .deposit
+                .ok_or_else(|| PaymasterError::ChainError("connection refused".to_string()))?;
+            Ok(DepositInfo {
+                deposit,
+                ..Default::default()
+            })
+        }
+
+        async fn get_balance(&self, _account: Address) -> Result<U256, PaymasterError> {
+            self.balance
+                .ok_or_else(|| PaymasterError::ChainError("connection refused".to_string()))
+        }
+    }
+
+    fn create_service(chain: StaticChain) -> PaymasterRelayService {
+        let deposits = DepositManager::new(Arc::new(chain), DepositConfig::default())
+            .with_entry_points(vec![ChainSpec::default().entry_point_address_v0_7]);
+
+        test_utils::service(&format!(
+            "senders = [\"{}\"]\ntokens = [\"{}\"]\n\n\
+         [default.max_token_amounts]\n\"{}\" = \"0x3e8\"\n\n\
+         [partner]\nsenders = [\"{}\", \"{}\"]\n",
+            SENDER, TOKEN, TOKEN, SENDER, UNLISTED_SENDER
+        ))
+        .with_deposit_manager(deposits)
+    }
+
+    fn ether(amount: u64) -> U256 {
+        U256::from(amount) * U256::from(10u64).pow(U256::from(18))
+    }
+
+    fn keys(value: &Value) -> Vec<&str> {
+        let mut keys: Vec<_> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    async fn sponsor(service: &PaymasterRelayService, sender: &str, nonce: u64) -> bool {
+        let op = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender: Address::from_str(sender).unwrap(),
+                nonce: U256::from(nonce),
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 1_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        let entry_point = ethers::types::Address::from_slice(
+            ChainSpec::default().entry_point_address_v0_7.as_slice(),
+        );
+        service
+            .sponsor_user_operation(
+                UserOperationVariant::V0_7(op),
+                entry_point,
+                SponsorOptions::default(),
+            )
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_balance_report_schema() {
+        let service = create_service(StaticChain {
+            balance: Some(ether(2)),
+            deposit: Some(ether(1)),
+        });
+
+        let balance = service.dashboard_balance().await;
+        assert_eq!(balance.status, BalanceHealth::Healthy);
+        assert_eq!(balance.paymaster_balance_eth, Some(2.0));
+        assert_eq!(balance.entrypoint_deposit_eth, 1.0);
+
+        let value = serde_json::to_value(&balance).unwrap();
+        assert_eq!(
+            keys(&value),
+            [
+                "account",
+                "deposits",
+                "entrypoint_deposit_eth",
+                "errors",
+                "paymaster",
+                "paymaster_balance_eth",
+                "paymaster_balance_wei",
+                "status"
+            ]
+        );
+        assert_eq!(value["status"], json!("healthy"));
+        assert_eq!(value["errors"], json!([]));
+        assert_eq!(
+            keys(&value["deposits"][0]),
+            [
+                "deposit_eth",
+                "deposit_wei",
+                "entry_point",
+                "low",
+                "stake_wei",
+                "staked",
+                "unstake_delay_sec"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_balance_report_degrades_when_chain_fails() {
+        // Deposits unreadable, account balance still served
+        let service = create_service(StaticChain {
+            balance: Some(ether(2)),
+            deposit: None,
+        });
+        let balance = service.dashboard_balance().await;
+        assert_eq!(balance.status, BalanceHealth::Degraded);
+        assert_eq!(balance.paymaster_balance_eth, Some(2.0));
+        assert!(balance.deposits.is_empty());
+        assert_eq!(balance.errors.len(), 1);
+        assert!(
+            balance.errors[0].contains("connection refused"),
+            "{:?}",
+            balance.errors
+        );
+
+        // Nothing readable
+        let service = create_service(StaticChain {
+            balance: None,
+            deposit: None,
+        });
+        let balance = service.dashboard_balance().await;
+        assert_eq!(balance.status, BalanceHealth::Unknown);
+        assert!(balance.paymaster.is_some());
+        assert_eq!(balance.errors.len(), 2);
+        let value = serde_json::to_value(&balance).unwrap();
+        assert_eq!(value["paymaster_balance_eth"], Value::Null);
+        assert_eq!(value["status"], json!("unknown"));
+
+        // Other reports do not depend on the chain
+        assert_eq!(service.dashboard_policies().total_policies, 2);
+        assert!(service.dashboard_transactions(10).errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_policies_and_transactions_reports() {
+        let service = create_service(StaticChain {
+            balance: Some(ether(2)),
+            deposit: Some(ether(1)),
+        });
+        assert!(sponsor(&service, SENDER, 0).await);
+        assert!(!sponsor(&service, UNLISTED_SENDER, 0).await);
+
+        let policies = serde_json::to_value(service.dashboard_policies()).unwrap();
+        assert_eq!(keys(&policies), ["errors", "policies", "total_policies"]);
+        assert_eq!(policies["total_policies"], json!(2));
+        let default = &policies["policies"][0];
+        assert_eq!(
+            keys(default),
+            [
+                "id",
+                "max_token_amounts",
+                "recent_sponsorships",
+                "sender_count",
+                "tokens"
+            ]
+        );
+        assert_eq!(default["id"], json!("default"));
+        assert_eq!(default["sender_count"], json!(1));
+        assert_eq!(default["recent_sponsorships"], json!(1));
+        assert_eq!(
+            default["max_token_amounts"][0]["max_amount"],
+            json!("0x3e8")
+        );
+        assert_eq!(policies["policies"][1]["sender_count"], json!(2));
+
+        let transactions = serde_json::to_value(service.dashboard_transactions(10)).unwrap();
+        assert_eq!(
+            keys(&transactions),
+            [
+                "capacity",
+                "errors",
+                "failed_count",
+                "recent_transactions",
+                "successful_count",
+                "total_count"
+            ]
+        );
+        assert_eq!(transactions["total_count"], json!(2));
+        assert_eq!(transactions["successful_count"], json!(1));
+        assert_eq!(transactions["failed_count"], json!(1));
+
+        // Newest first
+        let rows = transactions["recent_transactions"].as_array().unwrap();
+        assert_eq!(rows[0]["outcome"], json!("error"));
+        assert!(rows[0]["policy_id"].is_null());
+        assert_eq!(rows[1]["outcome"], json!("success"));
+        assert_eq!(rows[1]["policy_id"], json!("default"));
+        assert_eq!(
+            service.dashboard_transactions(1).recent_transactions.len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,564 @@
+// paymaster-relay/src/deposit.rs
+// EntryPoint deposit management for the paymaster account (pm_getDepositInfo,
+// pm_depositTo, pm_withdrawTo). Transactions go through the outbound transaction
+// manager and are signed with the paymaster signer, so the deposit belongs to the
+// same address that signs sponsorships.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use alloy_primitives::{Address, Bytes, B256, U256};
+use async_trait::async_trait;
+use ethers::{
+    abi::{self, ParamType, Token},
+    types::H160,
+};
+use rundler_provider::{DepositInfo, EntryPoint, EvmProvider};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::info;
+
+use crate::{
+    error::PaymasterError,
+    outbound_tx::{
+        OutboundChain, OutboundReceipt, OutboundRequest, OutboundTxManager, OutboundTxStatus,
+    },
+    signer::SignerManager,
+};
+
+/// Deposit management configuration (`[paymaster_relay.deposit]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DepositConfig {
+    /// Largest amount a single pm_depositTo may move, in wei (decimal or 0x-hex string)
+    pub max_deposit_wei: U256,
+    /// Enable pm_withdrawTo
+    pub allow_withdraw: bool,
+    /// Gas limit of deposit and withdraw transactions
+    pub gas_limit: u64,
+    /// Seconds to wait for a submitted transaction to be mined
+    pub receipt_timeout_seconds: u64,
+}
+
+impl Default for DepositConfig {
+    fn default() -> Self {
+        Self {
+            // 1 ETH
+            max_deposit_wei: U256::from(1_000_000_000_000_000_000u64),
+            allow_withdraw: false,
+            gas_limit: 100_000,
+            receipt_timeout_seconds: 60,
+        }
+    }
+}
+
+/// Deposit of the paymaster on one EntryPoint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymasterDepositInfo {
+    pub entry_point: Address,
+    pub paymaster: Address,
+    pub deposit: U256,
+    pub staked: bool,
+    pub stake: U256,
+    pub unstake_delay_sec: u32,
+    pub withdraw_time: u64,
+}
+
+impl PaymasterDepositInfo {
+    fn new(entry_point: Address, paymaster: Address, info: DepositInfo) -> Self {
+        Self {
+            entry_point,
+            paymaster,
+            deposit: info.deposit,
+            staked: info.staked,
+            stake: info.stake,
+            unstake_delay_sec: info.unstake_delay_sec,
+            withdraw_time: info.withdraw_time,
+        }
+    }
+}
+
+/// Mined deposit or withdraw transaction and the deposit after it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DepositTransaction {
+    pub tx_hash: B256,
+    /// Id of the transaction for pm_getOutboundTx
+    pub outbound_tx_id: String,
+    pub deposit_info: PaymasterDepositInfo,
+}
+
+/// Chain access needed to manage EntryPoint deposits
+#[async_trait]
+pub trait DepositChain: Send + Sync {
+    /// Deposit info of `account` on `entry_point`
+    async fn get_deposit_info(
+        &self,
+        entry_point: Address,
+        account: Address,
+    ) -> Result<DepositInfo, PaymasterError>;
+
+    /// Ether balance of `account`
+    async fn get_balance(&self, account: Address) -> Result<U256, PaymasterError>;
+}
+
+/// [`DepositChain`] backed by a node provider and the enabled EntryPoints
+pub struct ProviderDepositChain<P> {
+    provider: P,
+    chain_id: u64,
+    entry_points: HashMap<Address, Arc<dyn EntryPoint>>,
+}
+
+impl<P> ProviderDepositChain<P> {
+    pub fn new(provider: P, chain_id: u64, entry_points: Vec<Arc<dyn EntryPoint>>) -> Self {
+        Self {
+            provider,
+            chain_id,
+            entry_points: entry_points
+                .into_iter()
+                .map(|ep| (*ep.address(), ep))
+                .collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> DepositChain for ProviderDepositChain<P> {
+    async fn get_deposit_info(
+        &self,
+        entry_point: Address,
+        account: Address,
+    ) -> Result<DepositInfo, PaymasterError> {
+        let entry_point = self.entry_points.get(&entry_point).ok_or_else(|| {
+            PaymasterError::InvalidRequest(format!("Unsupported entry point: {}", entry_point))
+        })?;
+        entry_point
+            .get_deposit_info(account)
+            .await
+            .map_err(|e| PaymasterError::ChainError(format!("Failed to get deposit info: {}", e)))
+    }
+
+    async fn get_balance(&self, account: Address) -> Result<U256, PaymasterError> {
+        self.provider
+            .get_balance(account, None)
+            .await
+            .map_err(|e| PaymasterError::ChainError(format!("Failed to get balance: {}", e)))
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> OutboundChain for ProviderDepositChain<P> {
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    async fn latest_block(&self) -> Result<(B256, u64), PaymasterError> {
+        self.provider
+            .get_latest_block_hash_and_number()
+            .await
+            .map_err(|e| PaymasterError::ChainError(format!("Failed to get latest block: {}", e)))
+    }
+
+    async fn transaction_count(&self, account: Address) -> Result<u64, PaymasterError> {
+        self.provider
+            .get_transaction_count(account)
+            .await
+            .map_err(|e| PaymasterError::ChainError(format!("Failed to get nonce: {}", e)))
+    }
+
+    async fn send_raw_transaction(&self, tx: Bytes) -> Result<B256, PaymasterError> {
+        self.provider
+            .send_raw_transaction(tx)
+            .await
+            .map_err(|e| PaymasterError::ChainError(format!("Failed to send transaction: {}", e)))
+    }
+
+    async fn receipt(&self, tx_hash: B256) -> Result<Option<OutboundReceipt>, PaymasterError> {
+        let receipt = self
+            .provider
+            .get_transaction_receipt(tx_hash)
+            .await
+            .map_err(|e| {
+                PaymasterError::ChainError(format!("Failed to get receipt for {}: {}", tx_hash, e))
+            })?;
+        Ok(receipt.and_then(|receipt| {
+            Some(OutboundReceipt {
+                block_number: receipt.block_number?,
+                success: receipt.status(),
+            })
+        }))
+    }
+}
+
+/// Reads and moves the paymaster's EntryPoint deposit
+#[derive(Clone)]
+pub struct DepositManager {
+    chain: Arc<dyn DepositChain>,
+    outbound: Option<Arc<OutboundTxManager>>,
+    config: DepositConfig,
+    entry_points: Vec<Address>,
+}
+
+impl std::fmt::Debug for DepositManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DepositManager")
+            .field("config", &self.config)
+            .field("entry_points", &self.entry_points)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DepositManager {
+    pub fn new(chain: Arc<dyn DepositChain>, config: DepositConfig) -> Self {
+        Self {
+            chain,
+            outbound: None,
+            config,
+            entry_points: Vec::new(),
+        }
+    }
+
+    /// Send deposit and withdraw transactions through `outbound`, which
+    /// pm_depositTo and pm_withdrawTo need
+    pub fn with_outbound_txs(mut self, outbound: Arc<OutboundTxManager>) -> Self {
+        self.outbound = Some(outbound);
+        self
+    }
+
+    /// Report the paymaster deposits on `entry_points` (dashboard balances)
+    pub fn with_entry_points(mut self, entry_points: Vec<Address>) -> Self {
+        self.entry_points = entry_points;
+        self
+    }
+
+    /// EntryPoints the paymaster deposits are reported on
+    pub fn entry_points(&self) -> &[Address] {
+        &self.entry_points
+    }
+
+    /// Deposit info of `paymaster` on `entry_point`
+    pub async fn deposit_info(
+        &self,
+        entry_point: Address,
+        paymaster: Address,
+    ) -> Result<PaymasterDepositInfo, PaymasterError> {
+        let info = self.chain.get_deposit_info(entry_point, paymaster).await?;
+        Ok(PaymasterDepositInfo::new(entry_point, paymaster, info))
+    }
+
+    /// Deposit management settings
+    pub fn config(&self) -> &DepositConfig {
+        &self.config
+    }
+
+    /// Whether a transaction to `entry_point` is sent but not mined yet
+    pub fn has_pending_transaction(&self, entry_point: Address) -> bool {
+        self.outbound
+            .as_ref()
+            .is_some_and(|outbound| outbound.pending().iter().any(|tx| tx.to == entry_point))
+    }
+
+    /// Ether balance of `account`, e.g. the paymaster signer account
+    pub async fn account_balance(&self, account: Address) -> Result<U256, PaymasterError> {
+        self.chain.get_balance(account).await
+    }
+
+    /// Send `amount` wei from the paymaster account to its deposit on `entry_point`
+    pub async fn deposit_to(
+        &self,
+        signer: &Mutex<SignerManager>,
+        entry_point: Address,
+        amount: U256,
+    ) -> Result<DepositTransaction, PaymasterError> {
+        if amount.is_zero() || amount > self.config.max_deposit_wei {
+            return Err(PaymasterError::InvalidRequest(format!(
+                "Deposit amount must be between 1 and {} wei, got {}",
+                self.config.max_deposit_wei, amount
+            )));
+        }
+
+        let paymaster = signer_address(signer).await;
+        let call_data = encode_call(
+            "depositTo",
+            &[ParamType::Address],
+            &[Token::Address(to_h160(paymaster))],
+        );
+        self.send_and_confirm("deposit", signer, entry_point, call_data, amount)
+            .await
+    }
+
+    /// Withdraw `amount` wei of the paymaster deposit on `entry_point` to `to`
+    pub async fn withdraw_to(
+        &self,
+        signer: &Mutex<SignerManager>,
+        entry_point: Address,
+        to: Address,
+        amount: U256,
+    ) -> Result<DepositTransaction, PaymasterError> {
+        if !self.config.allow_withdraw {
+            return Err(PaymasterError::InvalidRequest(
+                "pm_withdrawTo is disabled; set paymaster_relay.deposit.allow_withdraw".to_string(),
+            ));
+        }
+        if amount.is_zero() {
+            return Err(PaymasterError::InvalidRequest(
+                "Withdraw amount must be positive".to_string(),
+            ));
+        }
+
+        let call_data = encode_call(
+            "withdrawTo",
+            &[ParamType::Address, ParamType::Uint(256)],
+            &[
+                Token::Address(to_h160(to)),
+                Token::Uint(ethers::types::U256::from_big_endian(
+                    &amount.to_be_bytes::<32>(),
+                )),
+            ],
+        );
+        self.send_and_confirm("withdraw", signer, entry_point, call_data, U256::ZERO)
+            .await
+    }
+
+    /// Send a call to `entry_point` and wait for it to be mined, then read back the deposit
+    async fn send_and_confirm(
+        &self,
+        kind: &str,
+        signer: &Mutex<SignerManager>,
+        entry_point: Address,
+        call_data: Vec<u8>,
+        value: U256,
+    ) -> Result<DepositTransaction, PaymasterError> {
+        let outbound = self.outbound.as_ref().ok_or_else(|| {
+            PaymasterError::InvalidRequest("Outbound transactions are not configured".to_string())
+        })?;
+        // Reject unknown EntryPoints before spending gas
+        let paymaster = signer_address(signer).await;
+        self.chain.get_deposit_info(entry_point, paymaster).await?;
+
+        let request = OutboundRequest {
+            kind: kind.to_string(),
+            to: entry_point,
+            value,
+            data: Bytes::from(call_data),
+            gas_limit: self.config.gas_limit,
+        };
+        let tx = outbound.send(signer, request).await?;
+        info!(
+            "💰 Submitted EntryPoint {} transaction {} to {}",
+            kind, tx.tx_hash, entry_point
+        );
+
+        let timeout = Duration::from_secs(self.config.receipt_timeout_seconds);
+        let tx = outbound.wait(signer, &tx.id, timeout).await?;
+        match tx.status {
+            OutboundTxStatus::Mined => {}
+            OutboundTxStatus::Dropped => {
+                return Err(PaymasterError::ChainError(format!(
+                    "Transaction {} was dropped: its nonce was used by another transaction",
+                    tx.tx_hash
+                )))
+            }
+            _ => {
+                return Err(PaymasterError::ChainError(format!(
+                    "Transaction {} reverted",
+                    tx.tx_hash
+                )))
+            }
+        }
+
+        Ok(DepositTransaction {
+            tx_hash: tx.tx_hash,
+            outbound_tx_id: tx.id,
+            deposit_info: self.deposit_info(entry_point, paymaster).await?,
+        })
+    }
+}
+
+async fn signer_address(signer: &Mutex<SignerManager>) -> Address {
+    Address::from_slice(signer.lock().await.address().as_bytes())
+}
+
+fn to_h160(address: Address) -> H160 {
+    H160::from_slice(address.as_slice())
+}
+
+fn encode_call(name: &str, params: &[ParamType], args: &[Token]) -> Vec<u8> {
+    [&abi::short_signature(name, params)[..], &abi::encode(args)].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex as StdMutex;
+
+    use alloy_primitives::address;
+    use ethers::{types::transaction::eip2718::TypedTransaction, utils::rlp::Rlp};
+    use rundler_provider::FeeEstimator;
+    use rundler_types::GasFees;
+    use secrecy::SecretString;
+
+    use super::*;
+    use crate::outbound_tx::OutboundTxConfig;
+
+    const ENTRY_POINT: Address = address!("0000000071727De22E5E9d8BAf0edAc6f37da032");
+
+    /// In-memory chain that applies deposits when transactions are sent
+    #[derive(Default)]
+    struct MockChain {
+        deposit: StdMutex<U256>,
+        sent: StdMutex<Vec<Bytes>>,
+    }
+
+    #[async_trait]
+    impl DepositChain for MockChain {
+        async fn get_deposit_info(
+            &self,
+            entry_point: Address,
+            _account: Address,
+        ) -> Result<DepositInfo, PaymasterError> {
+            if entry_point != ENTRY_POINT {
+                return Err(PaymasterError::InvalidRequest(
+                    "Unsupported entry point".to_string(),
+                ));
+            }
+            Ok(DepositInfo {
+                deposit: *self.deposit.lock().unwrap(),
+                ..Default::default()
+            })
+        }
+
+        async fn get_balance(&self, _account: Address) -> Result<U256, PaymasterError> {
+            Ok(U256::from(10u64).pow(U256::from(18)))
+        }
+    }
+
+    #[async_trait]
+    impl OutboundChain for MockChain {
+        fn chain_id(&self) -> u64 {
+            31337
+        }
+
+        async fn latest_block(&self) -> Result<(B256, u64), PaymasterError> {
+            Ok((B256::ZERO, 1))
+        }
+
+        async fn transaction_count(&self, _account: Address) -> Result<u64, PaymasterError> {
+            Ok(3)
+        }
+
+        async fn send_raw_transaction(&self, tx: Bytes) -> Result<B256, PaymasterError> {
+            let (decoded, _) = TypedTransaction::decode_signed(&Rlp::new(&tx)).unwrap();
+            let value = decoded.value().copied().unwrap_or_default();
+            let mut deposit = self.deposit.lock().unwrap();
+            *deposit += U256::from_limbs(value.0);
+            self.sent.lock().unwrap().push(tx);
+            Ok(B256::repeat_byte(0x11))
+        }
+
+        async fn receipt(&self, _tx_hash: B256) -> Result<Option<OutboundReceipt>, PaymasterError> {
+            Ok(Some(OutboundReceipt {
+                block_number: 2,
+                success: true,
+            }))
+        }
+    }
+
+    struct FixedFees;
+
+    #[async_trait]
+    impl FeeEstimator for FixedFees {
+        async fn required_bundle_fees(
+            &self,
+            _block_hash: B256,
+            _min_fees: Option<GasFees>,
+        ) -> anyhow::Result<(GasFees, u128)> {
+            self.latest_bundle_fees().await
+        }
+
+        async fn latest_bundle_fees(&self) -> anyhow::Result<(GasFees, u128)> {
+            Ok((
+                GasFees {
+                    max_fee_per_gas: 2_000_000_000,
+                    max_priority_fee_per_gas: 1_000_000_000,
+                },
+                500_000_000,
+            ))
+        }
+
+        fn required_op_fees(&self, bundle_fees: GasFees) -> GasFees {
+            bundle_fees
+        }
+    }
+
+    fn manager(chain: &Arc<MockChain>) -> DepositManager {
+        let outbound = OutboundTxManager::new(
+            chain.clone(),
+            Arc::new(FixedFees),
+            OutboundTxConfig::default(),
+        )
+        .unwrap();
+        DepositManager::new(chain.clone(), DepositConfig::default())
+            .with_outbound_txs(Arc::new(outbound))
+    }
+
+    fn signer() -> Mutex<SignerManager> {
+        Mutex::new(
+            SignerManager::new(SecretString::new(
+                "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+                    .to_string()
+                    .into(),
+            ))
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_deposit_to_signs_with_paymaster_key() {
+        let chain = Arc::new(MockChain::default());
+        let manager = manager(&chain);
+        let signer = signer();
+        let paymaster = signer_address(&signer).await;
+
+        let amount = U256::from(500_000_000_000_000_000u64);
+        let result = manager
+            .deposit_to(&signer, ENTRY_POINT, amount)
+            .await
+            .unwrap();
+        assert_eq!(result.deposit_info.deposit, amount);
+        assert_eq!(result.deposit_info.paymaster, paymaster);
+        assert_eq!(result.tx_hash, B256::repeat_byte(0x11));
+
+        let sent = chain.sent.lock().unwrap();
+        let (tx, signature) = TypedTransaction::decode_signed(&Rlp::new(&sent[0])).unwrap();
+        assert_eq!(
+            signature.recover(tx.sighash()).unwrap().as_bytes(),
+            paymaster.as_slice()
+        );
+        assert_eq!(tx.nonce().unwrap().as_u64(), 3);
+        assert_eq!(tx.to_addr().unwrap().as_bytes(), ENTRY_POINT.as_slice());
+        assert_eq!(
+            &tx.data().unwrap()[..4],
+            &abi::short_signature("depositTo", &[ParamType::Address])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deposit_cap_and_withdraw_flag() {
+        let chain = Arc::new(MockChain::default());
+        let manager = manager(&chain);
+        let signer = signer();
+
+        let over_cap = DepositConfig::default().max_deposit_wei + U256::from(1);
+        assert!(matches!(
+            manager.deposit_to(&signer, ENTRY_POINT, over_cap).await,
+            Err(PaymasterError::InvalidRequest(_))
+        ));
+        assert!(matches!(
+            manager
+                .withdraw_to(&signer, ENTRY_POINT, Address::ZERO, U256::from(1))
+                .await,
+            Err(PaymasterError::InvalidRequest(_))
+        ));
+        assert!(chain.sent.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,683 @@
+// paymaster-relay/src/entry_points.rs
+// EntryPoints the paymaster sponsors for. Each configured EntryPoint version has
+// its own paymaster contract; requests for other EntryPoints are rejected before
+// signing, and the contracts are checked on chain at startup.
+
+use std::fmt;
+
+use alloy_primitives::{keccak256, Address, Bytes};
+use async_trait::async_trait;
+use rundler_provider::{EvmProvider, TransactionBuilder, TransactionRequest};
+use rundler_types::UserOperationVariant;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::error::PaymasterError;
+
+/// Version of an EntryPoint contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EntryPointVersion {
+    #[serde(rename = "v0.6")]
+    V0_6,
+    #[serde(rename = "v0.7")]
+    V0_7,
+    #[cfg(feature = "entrypoint-v0_8")]
+    #[serde(rename = "v0.8")]
+    V0_8,
+}
+
+impl EntryPointVersion {
+    /// Version whose operation format `user_op` has
+    pub fn of(user_op: &UserOperationVariant) -> Self {
+        match user_op {
+            UserOperationVariant::V0_6(_) => EntryPointVersion::V0_6,
+            UserOperationVariant::V0_7(_) => EntryPointVersion::V0_7,
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(_) => EntryPointVersion::V0_8,
+        }
+    }
+}
+
+impl fmt::Display for EntryPointVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntryPointVersion::V0_6 => write!(f, "v0.6"),
+            EntryPointVersion::V0_7 => write!(f, "v0.7"),
+            #[cfg(feature = "entrypoint-v0_8")]
+            EntryPointVersion::V0_8 => write!(f, "v0.8"),
+        }
+    }
+}
+
+/// Paymaster contract deployed for one EntryPoint (`[[paymaster_relay.paymasters]]`)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PaymasterContractConfig {
+    pub version: EntryPointVersion,
+    pub entry_point: Address,
+    /// Paymaster contract named in the sponsored operations
+    pub paymaster: Address,
+    /// View checking the signer at startup: `name()` returning the signer
+    /// address, or `name(address)` returning whether the address may sign
+    #[serde(default = "default_signer_view")]
+    pub signer_view: String,
+}
+
+fn default_signer_view() -> String {
+    "verifyingSigner()".to_string()
+}
+
+/// EntryPoint the paymaster sponsors for, served by pm_getSupportedEntryPoints
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupportedEntryPoint {
+    pub entry_point: Address,
+    pub version: EntryPointVersion,
+    pub paymaster: Address,
+}
+
+impl fmt::Display for SupportedEntryPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}, paymaster {})",
+            self.entry_point, self.version, self.paymaster
+        )
+    }
+}
+
+/// Paymaster contracts by EntryPoint
+#[derive(Debug, Clone)]
+pub struct EntryPointRouting {
+    contracts: Vec<PaymasterContractConfig>,
+}
+
+impl EntryPointRouting {
+    /// Routing to `contracts`; an EntryPoint may appear only once
+    pub fn new(contracts: Vec<PaymasterContractConfig>) -> Result<Self, PaymasterError> {
+        for (i, contract) in contracts.iter().enumerate() {
+            if contracts[..i]
+                .iter()
+                .any(|other| other.entry_point == contract.entry_point)
+            {
+                return Err(PaymasterError::InvalidRequest(format!(
+                    "Paymaster configured twice for entry point {}",
+                    contract.entry_point
+                )));
+            }
+            SignerView::parse(&contract.signer_view)?;
+        }
+        Ok(Self { contracts })
+    }
+
+    /// Configured paymaster contracts
+    pub fn contracts(&self) -> &[PaymasterContractConfig] {
+        &self.contracts
+    }
+
+    /// Supported EntryPoints in configuration order
+    pub fn supported(&self) -> Vec<SupportedEntryPoint> {
+        self.contracts
+            .iter()
+            .map(|contract| SupportedEntryPoint {
+                entry_point: contract.entry_point,
+                version: contract.version,
+                paymaster: contract.paymaster,
+            })
+            .collect()
+    }
+
+    /// Version of `entry_point`, if a paymaster is deployed for it
+    pub fn version(&self, entry_point: Address) -> Option<EntryPointVersion> {
+        self.contracts
+            .iter()
+            .find(|contract| contract.entry_point == entry_point)
+            .map(|contract| contract.version)
+    }
+
+    /// Whether `paymaster` is one of the configured contracts
+    pub fn is_paymaster(&self, paymaster: Address) -> bool {
+        self.contracts
+            .iter()
+            .any(|contract| contract.paymaster == paymaster)
+    }
+
+    /// Paymaster contract sponsoring `user_op` on `entry_point`
+    ///
+    /// Fails when no paymaster is deployed for the EntryPoint, or the operation
+    /// is in the format of another EntryPoint version.
+    pub fn resolve(
+        &self,
+        entry_point: Address,
+        user_op: &UserOperationVariant,
+    ) -> Result<&PaymasterContractConfig, PaymasterError> {
+        let version = EntryPointVersion::of(user_op);
+        let contract = self
+            .contracts
+            .iter()
+            .find(|contract| contract.entry_point == entry_point);
+        let message = match contract {
+            Some(contract) if contract.version == version => return Ok(contract),
+            Some(contract) => format!(
+                "Entry point {} is {} but the UserOperation is in the {} format",
+                entry_point, contract.version, version
+            ),
+            None => format!(
+                "No paymaster is deployed for entry point {} ({} UserOperation)",
+                entry_point, version
+            ),
+        };
+        Err(PaymasterError::UnsupportedEntryPoint {
+            message,
+            supported: self.supported(),
+        })
+    }
+}
+
+/// Signer view call of a paymaster contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SignerView {
+    /// `name()` returning the signer address
+    Signer([u8; 4]),
+    /// `name(address)` returning whether the address may sign
+    IsSigner([u8; 4]),
+}
+
+impl SignerView {
+    fn parse(signature: &str) -> Result<Self, PaymasterError> {
+        let signature: String = signature.split_whitespace().collect();
+        let selector = |s: &str| {
+            let mut selector = [0u8; 4];
+            selector.copy_from_slice(&keccak256(s.as_bytes())[..4]);
+            selector
+        };
+        let name = signature
+            .strip_suffix("(address)")
+            .map(|name| (name, true))
+            .or_else(|| signature.strip_suffix("()").map(|name| (name, false)));
+        match name {
+            Some((name, takes_address)) if !name.is_empty() && !name.contains('(') => {
+                Ok(if takes_address {
+                    SignerView::IsSigner(selector(&signature))
+                } else {
+                    SignerView::Signer(selector(&signature))
+                })
+            }
+            _ => Err(PaymasterError::InvalidRequest(format!(
+                "Invalid signer view {:?}, expected name() or name(address)",
+                signature
+            ))),
+        }
+    }
+
+    fn calldata(&self, signer: Address) -> Bytes {
+        match self {
+            SignerView::Signer(selector) => Bytes::copy_from_slice(selector),
+            SignerView::IsSigner(selector) => {
+                let mut data = selector.to_vec();
+                data.extend_from_slice(&[0u8; 12]);
+                data.extend_from_slice(signer.as_slice());
+                data.into()
+            }
+        }
+    }
+}
+
+/// Chain access needed to check the paymaster contracts
+#[async_trait]
+pub trait PaymasterContractReader: Send + Sync {
+    /// Deployed code at `address`
+    async fn code(&self, address: Address) -> Result<Bytes, PaymasterError>;
+
+    /// Result of a view call of `to` with `data`
+    async fn call(&self, to: Address, data: Bytes) -> Result<Bytes, PaymasterError>;
+}
+
+/// [`PaymasterContractReader`] backed by a node provider
+pub struct ProviderContractReader<P> {
+    provider: P,
+}
+
+impl<P> ProviderContractReader<P> {
+    pub fn new(provider: P) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> PaymasterContractReader for ProviderContractReader<P> {
+    async fn code(&self, address: Address) -> Result<Bytes, PaymasterError> {
+        self.provider.get_code(address, None).await.map_err(|e| {
+            PaymasterError::ChainError(format!("Failed to get code of {}: {}", address, e))
+        })
+    }
+
+    async fn call(&self, to: Address, data: Bytes) -> Result<Bytes, PaymasterError> {
+        let tx = TransactionRequest::default().to(to).with_input(data);
+        self.provider
+            .call(tx, None, None)
+            .await
+            .map_err(|e| PaymasterError::ChainError(format!("Call to {} failed: {}", to, e)))
+    }
+}
+
+/// Outcome of the startup check of one paymaster contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContractCheck {
+    /// Deployed, and every signer is authorized
+    Ready,
+    /// No code at the paymaster address
+    NoCode,
+    /// Signers the contract does not accept
+    UnauthorizedSigners(Vec<Address>),
+    /// The contract could not be read
+    Failed(String),
+}
+
+/// Check that each paymaster contract is deployed and accepts `signers`,
+/// logging what is wrong with those that are not
+///
+/// Returns the outcome per contract in configuration order.
+pub async fn check_paymaster_contracts(
+    reader: &dyn PaymasterContractReader,
+    routing: &EntryPointRouting,
+    signers: &[Address],
+) -> Vec<ContractCheck> {
+    let mut checks = Vec::with_capacity(routing.contracts.len());
+    for contract in &routing.contracts {
+        let check = check_contract(reader, contract, signers).await;
+        match &check {
+            ContractCheck::Ready => info!(
+                "✅ Paymaster {} for entry point {} ({}) is deployed and accepts our signers",
+                contract.paymaster, contract.entry_point, contract.version
+            ),
+            ContractCheck::NoCode => warn!(
+                "⚠️ No contract deployed at paymaster {} for entry point {} ({}); its sponsorships will revert on chain",
+                contract.paymaster, contract.entry_point, contract.version
+            ),
+            ContractCheck::UnauthorizedSigners(unauthorized) => warn!(
+                "⚠️ Paymaster {} for entry point {} ({}) does not accept signers {:?} ({}); their sponsorships will fail validation",
+                contract.paymaster, contract.entry_point, contract.version, unauthorized, contract.signer_view
+            ),
+            ContractCheck::Failed(reason) => warn!(
+                "⚠️ Could not check paymaster {} for entry point {} ({}): {}",
+                contract.paymaster, contract.entry_point, contract.version, reason
+            ),
+        }
+        checks.push(check);
+    }
+    checks
+}
+
+async fn check_contract(
+    reader: &dyn PaymasterContractReader,
+    contract: &PaymasterContractConfig,
+    signers: &[Address],
+) -> ContractCheck {
+    match reader.code(contract.paymaster).await {
+        Ok(code) if code.is_empty() => return ContractCheck::NoCode,
+        Ok(_) => {}
+        Err(e) => return ContractCheck::Failed(e.to_string()),
+    }
+    let view = match SignerView::parse(&contract.signer_view) {
+        Ok(view) => view,
+        Err(e) => return ContractCheck::Failed(e.to_string()),
+    };
+
+    let mut unauthorized = Vec::new();
+    for &signer in signers {
+        let result = match reader.call(contract.paymaster, view.calldata(signer)).await {
+            Ok(result) if result.len() >= 32 => result,
+            Ok(result) => {
+                return ContractCheck::Failed(format!(
+                    "{} returned {}, expected a 32 byte word",
+                    contract.signer_view, result
+                ))
+            }
+            Err(e) => return ContractCheck::Failed(e.to_string()),
+        };
+        let word = &result[..32];
+        let authorized = match view {
+            SignerView::Signer(_) => Address::from_slice(&word[12..]) == signer,
+            SignerView::IsSigner(_) => word.iter().any(|b| *b != 0),
+        };
+        if !authorized {
+            unauthorized.push(signer);
+        }
+    }
+    if unauthorized.is_empty() {
+        ContractCheck::Ready
+    } else {
+        ContractCheck::UnauthorizedSigners(unauthorized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        str::FromStr,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use alloy_primitives::{address, Address, Bytes, U256};
+    use async_trait::async_trait;
+    use rundler_types::{chain::ChainSpec, v0_6, v0_7, UserOperationVariant};
+
+    use super::*;
+    use crate::{
+        service::{PaymasterRelayService, SponsorOptions},
+        sponsorship::verify_sponsorship_by,
+        test_utils, PaymasterError,
+    };
+
+    #[test]
+    fn test_signer_view_parsing() {
+        assert_eq!(
+            SignerView::parse("verifyingSigner()").unwrap(),
+            // keccak256("verifyingSigner()")[..4]
+            SignerView::Signer([0x23, 0xd9, 0xac, 0x9b])
+        );
+        assert!(matches!(
+            SignerView::parse("signers(address)").unwrap(),
+            SignerView::IsSigner(_)
+        ));
+        for invalid in [
+            "verifyingSigner",
+            "()",
+            "isSigner(address,uint256)",
+            "f(uint256)",
+        ] {
+            assert!(SignerView::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    /// Address of the signing key below
+    const SIGNER: Address = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+    const PAYMASTER_V0_6: Address = address!("00000000000000000000000000000000000a0006");
+
+    fn v0_6_only() -> EntryPointRouting {
+        EntryPointRouting::new(vec![PaymasterContractConfig {
+            version: EntryPointVersion::V0_6,
+            entry_point: ChainSpec::default().entry_point_address_v0_6,
+            paymaster: PAYMASTER_V0_6,
+            signer_view: "verifyingSigner()".to_string(),
+        }])
+        .unwrap()
+    }
+
+    fn create_service(routing: Option<EntryPointRouting>) -> PaymasterRelayService {
+        let service = test_utils::service(&format!("senders = [\"{}\"]\n", SENDER));
+        match routing {
+            Some(routing) => service.with_entry_point_routing(routing),
+            None => service,
+        }
+    }
+
+    fn v0_6_op() -> UserOperationVariant {
+        UserOperationVariant::V0_6(
+            v0_6::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_6::UserOperationRequiredFields {
+                    sender: Address::from_str(SENDER).unwrap(),
+                    nonce: U256::ZERO,
+                    call_gas_limit: 100_000,
+                    verification_gas_limit: 100_000,
+                    pre_verification_gas: 21_000,
+                    ..Default::default()
+                },
+            )
+            .build(),
+        )
+    }
+
+    fn v0_7_op() -> UserOperationVariant {
+        UserOperationVariant::V0_7(
+            v0_7::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_7::UserOperationRequiredFields {
+                    sender: Address::from_str(SENDER).unwrap(),
+                    nonce: U256::ZERO,
+                    call_data: Bytes::new(),
+                    call_gas_limit: 100_000,
+                    verification_gas_limit: 100_000,
+                    pre_verification_gas: 21_000,
+                    max_fee_per_gas: 1_000_000_000,
+                    max_priority_fee_per_gas: 1_000_000_000,
+                    signature: Bytes::new(),
+                },
+            )
+            .build(),
+        )
+    }
+
+    fn ethers_address(address: Address) -> ethers::types::Address {
+        ethers::types::Address::from_slice(address.as_slice())
+    }
+
+    fn options() -> SponsorOptions {
+        SponsorOptions {
+            return_full_operation: true,
+            ..Default::default()
+        }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[tokio::test]
+    async fn test_v0_6_sponsored_with_its_paymaster_contract() {
+        let service = create_service(Some(v0_6_only()));
+        let entry_point = ethers_address(ChainSpec::default().entry_point_address_v0_6);
+
+        let result = service
+            .sponsor_user_operation(v0_6_op(), entry_point, options())
+            .await
+            .unwrap();
+        assert_eq!(&result.paymaster_and_data[..20], PAYMASTER_V0_6.as_slice());
+
+        let sponsored = result.sponsored_user_op.unwrap();
+        verify_sponsorship_by(&sponsored, PAYMASTER_V0_6, &[SIGNER], now()).unwrap();
+        assert!(service.verify_own_sponsorship(&sponsored).await.unwrap());
+
+        assert_eq!(
+            service.supported_entry_points().unwrap(),
+            [SupportedEntryPoint {
+                entry_point: ChainSpec::default().entry_point_address_v0_6,
+                version: EntryPointVersion::V0_6,
+                paymaster: PAYMASTER_V0_6,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_v0_7_refused_when_unconfigured() {
+        let service = create_service(Some(v0_6_only()));
+        let v0_7 = ethers_address(ChainSpec::default().entry_point_address_v0_7);
+        let v0_6 = ethers_address(ChainSpec::default().entry_point_address_v0_6);
+
+        let err = service
+            .sponsor_user_operation(v0_7_op(), v0_7, options())
+            .await
+            .unwrap_err();
+        let PaymasterError::UnsupportedEntryPoint { supported, .. } = &err else {
+            panic!("unexpected error {}", err);
+        };
+        assert_eq!(supported.len(), 1);
+        let message = err.to_string();
+        assert!(message.contains("No paymaster is deployed"), "{}", message);
+        assert!(message.contains(&PAYMASTER_V0_6.to_string()), "{}", message);
+        assert_eq!(err.code(), -32602);
+
+        let error: jsonrpsee::types::ErrorObjectOwned = err.into();
+        let data: serde_json::Value = serde_json::from_str(error.data().unwrap().get()).unwrap();
+        assert_eq!(data["supportedEntryPoints"][0]["version"], "v0.6");
+        assert_eq!(
+            data["supportedEntryPoints"][0]["paymaster"],
+            PAYMASTER_V0_6.to_checksum(None)
+        );
+
+        // A v0.7 operation sent to the v0.6 EntryPoint is refused as well
+        let err = service
+            .sponsor_user_operation(v0_7_op(), v0_6, options())
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("is v0.6 but the UserOperation is in the v0.7 format"),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_without_routing_every_entry_point_is_sponsored() {
+        let service = create_service(None);
+        let v0_7 = ethers_address(ChainSpec::default().entry_point_address_v0_7);
+        assert!(service
+            .sponsor_user_operation(v0_7_op(), v0_7, options())
+            .await
+            .is_ok());
+        assert!(matches!(
+            service.supported_entry_points(),
+            Err(PaymasterError::InvalidRequest(_))
+        ));
+    }
+
+    #[cfg(feature = "entrypoint-v0_8")]
+    #[tokio::test]
+    async fn test_v0_8_sponsored_in_the_v0_7_layout() {
+        use rundler_types::UserOperation;
+
+        const PAYMASTER_V0_8: Address = address!("00000000000000000000000000000000000a0008");
+        let entry_point = ChainSpec::default().entry_point_address_v0_8;
+        let service = create_service(Some(
+            EntryPointRouting::new(vec![PaymasterContractConfig {
+                version: EntryPointVersion::V0_8,
+                entry_point,
+                paymaster: PAYMASTER_V0_8,
+                signer_view: "verifyingSigner()".to_string(),
+            }])
+            .unwrap(),
+        ));
+
+        // Without conversion the v0.7 format is refused for a v0.8 EntryPoint
+        let refused = service
+            .sponsor_user_operation(v0_7_op(), ethers_address(entry_point), options())
+            .await;
+        assert!(matches!(
+            refused,
+            Err(PaymasterError::UnsupportedEntryPoint { .. })
+        ));
+
+        let user_op = service.operation_for_entry_point(v0_7_op(), ethers_address(entry_point));
+        assert_eq!(EntryPointVersion::of(&user_op), EntryPointVersion::V0_8);
+        let result = service
+            .sponsor_user_operation(user_op, ethers_address(entry_point), options())
+            .await
+            .unwrap();
+        assert_eq!(result.verification_gas_limit, Some(100_000));
+
+        let sponsored = result.sponsored_user_op.unwrap();
+        assert!(sponsored.is_v0_8());
+        assert_eq!(sponsored.entry_point(), entry_point);
+        assert_eq!(sponsored.paymaster(), Some(PAYMASTER_V0_8));
+        verify_sponsorship_by(&sponsored, PAYMASTER_V0_8, &[SIGNER], now()).unwrap();
+    }
+
+    #[test]
+    fn test_routing_config() {
+        let config: Vec<PaymasterContractConfig> = toml::from_str::<toml::Value>(
+            r#"
+        [[paymasters]]
+        version = "v0.7"
+        entry_point = "0x0000000071727De22E5E9d8BAf0edAc6f37da032"
+        paymaster = "0x00000000000000000000000000000000000a0007"
+        "#,
+        )
+        .unwrap()["paymasters"]
+            .clone()
+            .try_into()
+            .unwrap();
+        assert_eq!(config[0].version, EntryPointVersion::V0_7);
+        assert_eq!(config[0].signer_view, "verifyingSigner()");
+
+        let twice = vec![config[0].clone(), config[0].clone()];
+        assert!(EntryPointRouting::new(twice).is_err());
+        let invalid_view = PaymasterContractConfig {
+            signer_view: "signer(uint256)".to_string(),
+            ..config[0].clone()
+        };
+        assert!(EntryPointRouting::new(vec![invalid_view]).is_err());
+    }
+
+    /// Contract code and view call results of a fake chain
+    #[derive(Default)]
+    struct MockContracts {
+        code: HashMap<Address, Bytes>,
+        calls: HashMap<(Address, Bytes), Bytes>,
+    }
+
+    #[async_trait]
+    impl PaymasterContractReader for MockContracts {
+        async fn code(&self, address: Address) -> Result<Bytes, PaymasterError> {
+            Ok(self.code.get(&address).cloned().unwrap_or_default())
+        }
+
+        async fn call(&self, to: Address, data: Bytes) -> Result<Bytes, PaymasterError> {
+            self.calls
+                .get(&(to, data.clone()))
+                .cloned()
+                .ok_or_else(|| PaymasterError::ChainError(format!("call to {} reverted", to)))
+        }
+    }
+
+    fn word(address: Address) -> Bytes {
+        let mut word = [0u8; 32];
+        word[12..].copy_from_slice(address.as_slice());
+        Bytes::copy_from_slice(&word)
+    }
+
+    /// keccak256("verifyingSigner()")[..4]
+    const VERIFYING_SIGNER: [u8; 4] = [0x23, 0xd9, 0xac, 0x9b];
+
+    #[tokio::test]
+    async fn test_startup_check_without_code() {
+        let service = create_service(Some(v0_6_only()));
+        let checks = service
+            .check_paymaster_contracts(&MockContracts::default())
+            .await
+            .unwrap();
+        assert_eq!(checks, [ContractCheck::NoCode]);
+    }
+
+    #[tokio::test]
+    async fn test_startup_check_of_signer() {
+        let service = create_service(Some(v0_6_only()));
+        let selector = Bytes::copy_from_slice(&VERIFYING_SIGNER);
+        let mut chain = MockContracts::default();
+        chain
+            .code
+            .insert(PAYMASTER_V0_6, Bytes::from_static(&[0x60, 0x80]));
+
+        chain
+            .calls
+            .insert((PAYMASTER_V0_6, selector.clone()), word(SIGNER));
+        let checks = service.check_paymaster_contracts(&chain).await.unwrap();
+        assert_eq!(checks, [ContractCheck::Ready]);
+
+        let other = address!("70997970C51812dc3A010C7d01b50e0d17dc79C8");
+        chain.calls.insert((PAYMASTER_V0_6, selector), word(other));
+        let checks = service.check_paymaster_contracts(&chain).await.unwrap();
+        assert_eq!(checks, [ContractCheck::UnauthorizedSigners(vec![SIGNER])]);
+
+        // A contract without the view cannot be checked
+        chain.calls.clear();
+        let checks = service.check_paymaster_contracts(&chain).await.unwrap();
+        assert!(matches!(&checks[0], ContractCheck::Failed(reason) if reason.contains("reverted")));
+    }
+}
@@ -1,9 +1,17 @@
 // paymaster-relay/src/error.rs
 // This file will define custom error types for the paymaster-relay crate.
 
-use rundler_types::pool::PoolError;
+use alloy_primitives::Address;
+use rundler_types::{pool::PoolError, GasFees};
+use serde_json::{json, Value};
 use thiserror::Error;
 
+use crate::{
+    entry_points::SupportedEntryPoint,
+    error_catalog::{self, ErrorCatalogEntry},
+    usage::DeploymentLimit,
+};
+
 /// Main error type for paymaster operations
 #[derive(Debug, Error)]
 pub enum PaymasterError {
@@ -18,6 +26,68 @@ pub enum PaymasterError {
 
     #[error("Mempool submission error: {0}")]
     PoolError(#[from] PoolError),
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Chain interaction failed: {0}")]
+    ChainError(String),
+
+    #[error(
+        "Gas fees too low: maxFeePerGas {} (minimum {}), maxPriorityFeePerGas {} (minimum {})",
+        current.max_fee_per_gas,
+        required.max_fee_per_gas,
+        current.max_priority_fee_per_gas,
+        required.max_priority_fee_per_gas
+    )]
+    FeesTooLow { current: GasFees, required: GasFees },
+
+    #[error("Idempotency key conflict: {0}")]
+    IdempotencyConflict(String),
+
+    #[error("Concurrent sponsorship: {0}")]
+    ConcurrentSponsorship(String),
+
+    #[error(
+        "Unsupported entry point: {message}; supported: {}",
+        supported_list(supported)
+    )]
+    UnsupportedEntryPoint {
+        message: String,
+        supported: Vec<SupportedEntryPoint>,
+    },
+
+    #[error(
+        "Deployment quota exceeded for factory {factory}: at most {} account deployments per {} {}; resets at {resets_at}",
+        limit.max,
+        limit.window,
+        quota_scope(limit)
+    )]
+    DeploymentQuotaExceeded {
+        factory: Address,
+        limit: DeploymentLimit,
+        /// Unix time the exceeded window ends
+        resets_at: u64,
+    },
+}
+
+fn quota_scope(limit: &DeploymentLimit) -> &'static str {
+    if limit.factory.is_some() {
+        "for this factory"
+    } else {
+        "across all factories"
+    }
+}
+
+fn supported_list(supported: &[SupportedEntryPoint]) -> String {
+    if supported.is_empty() {
+        return "none".to_string();
+    }
+    supported
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 impl PaymasterError {
@@ -28,31 +98,132 @@ impl PaymasterError {
             PaymasterError::SignerError(_) => "signer_error",
             PaymasterError::PolicyRejected(_) => "policy_rejection",
             PaymasterError::PoolError(_) => "pool_error",
+            PaymasterError::InvalidRequest(_) => "validation_error",
+            PaymasterError::ChainError(_) => "chain_error",
+            PaymasterError::FeesTooLow { .. } => "validation_error",
+            PaymasterError::IdempotencyConflict(_) => "idempotency_conflict",
+            PaymasterError::ConcurrentSponsorship(_) => "concurrent_sponsorship",
+            PaymasterError::UnsupportedEntryPoint { .. } => "validation_error",
+            PaymasterError::DeploymentQuotaExceeded { .. } => "policy_rejection",
         }
     }
+
+    /// JSON-RPC error code reported to clients
+    pub fn code(&self) -> i32 {
+        match self {
+            PaymasterError::InvalidUserOperation(_) => -32602,
+            PaymasterError::SignerError(_) => -32603,
+            PaymasterError::PolicyRejected(_) => -32604,
+            PaymasterError::PoolError(_) => -32605,
+            PaymasterError::InvalidRequest(_) => -32602,
+            PaymasterError::ChainError(_) => -32606,
+            PaymasterError::FeesTooLow { .. } => -32602,
+            PaymasterError::IdempotencyConflict(_) => -32507,
+            PaymasterError::ConcurrentSponsorship(_) => -32607,
+            PaymasterError::UnsupportedEntryPoint { .. } => -32602,
+            PaymasterError::DeploymentQuotaExceeded { .. } => -32604,
+        }
+    }
+
+    /// Catalog entry of the error; every variant must have one
+    pub fn catalog_entry(&self) -> &'static ErrorCatalogEntry {
+        match self {
+            PaymasterError::InvalidUserOperation(_) => &error_catalog::INVALID_USER_OPERATION,
+            PaymasterError::SignerError(_) => &error_catalog::SIGNER_ERROR,
+            PaymasterError::PolicyRejected(_) => &error_catalog::POLICY_REJECTED,
+            PaymasterError::PoolError(_) => &error_catalog::POOL_ERROR,
+            PaymasterError::InvalidRequest(_) => &error_catalog::INVALID_REQUEST,
+            PaymasterError::ChainError(_) => &error_catalog::CHAIN_ERROR,
+            PaymasterError::FeesTooLow { .. } => &error_catalog::FEES_TOO_LOW,
+            PaymasterError::IdempotencyConflict(_) => &error_catalog::IDEMPOTENCY_CONFLICT,
+            PaymasterError::ConcurrentSponsorship(_) => &error_catalog::CONCURRENT_SPONSORSHIP,
+            PaymasterError::UnsupportedEntryPoint { .. } => &error_catalog::UNSUPPORTED_ENTRY_POINT,
+            PaymasterError::DeploymentQuotaExceeded { .. } => {
+                &error_catalog::DEPLOYMENT_QUOTA_EXCEEDED
+            }
+        }
+    }
+
+    /// Values of the catalog entry's placeholders
+    pub fn params(&self) -> Value {
+        match self {
+            PaymasterError::InvalidUserOperation(reason)
+            | PaymasterError::PolicyRejected(reason)
+            | PaymasterError::InvalidRequest(reason)
+            | PaymasterError::ChainError(reason)
+            | PaymasterError::IdempotencyConflict(reason)
+            | PaymasterError::ConcurrentSponsorship(reason) => json!({ "reason": reason }),
+            PaymasterError::SignerError(err) => json!({ "reason": err.to_string() }),
+            PaymasterError::PoolError(err) => json!({ "reason": err.to_string() }),
+            PaymasterError::FeesTooLow { current, required } => json!({
+                "maxFeePerGas": current.max_fee_per_gas.to_string(),
+                "minMaxFeePerGas": required.max_fee_per_gas.to_string(),
+                "maxPriorityFeePerGas": current.max_priority_fee_per_gas.to_string(),
+                "minMaxPriorityFeePerGas": required.max_priority_fee_per_gas.to_string(),
+            }),
+            PaymasterError::UnsupportedEntryPoint { message, supported } => json!({
+                "reason": message,
+                "supported": supported_list(supported),
+            }),
+            PaymasterError::DeploymentQuotaExceeded {
+                factory,
+                limit,
+                resets_at,
+            } => json!({
+                "factory": factory.to_string(),
+                "limit": limit.max,
+                "window": limit.window,
+                "scope": quota_scope(limit),
+                "resetsAt": resets_at,
+            }),
+        }
+    }
+
+    /// JSON-RPC error data: the catalog key and params, plus `extra` fields
+    fn data_with(&self, extra: Value) -> Value {
+        let mut data = self.catalog_entry().data(self.params());
+        if let (Some(data), Value::Object(extra)) = (data.as_object_mut(), extra) {
+            data.extend(extra);
+        }
+        data
+    }
 }
 
 impl From<PaymasterError> for jsonrpsee::types::ErrorObjectOwned {
     fn from(error: PaymasterError) -> Self {
-        match error {
-            PaymasterError::InvalidUserOperation(msg) => jsonrpsee::types::ErrorObjectOwned::owned(
-                -32602,
-                "Invalid UserOperation",
-                Some(msg),
-            ),
-            PaymasterError::SignerError(err) => jsonrpsee::types::ErrorObjectOwned::owned(
-                -32603,
-                "Signer error",
-                Some(err.to_string()),
-            ),
-            PaymasterError::PolicyRejected(msg) => {
-                jsonrpsee::types::ErrorObjectOwned::owned(-32604, "Policy rejected", Some(msg))
+        let code = error.code();
+        let data = match &error {
+            PaymasterError::UnsupportedEntryPoint { supported, .. } => {
+                error.data_with(json!({ "supportedEntryPoints": supported }))
             }
-            PaymasterError::PoolError(err) => jsonrpsee::types::ErrorObjectOwned::owned(
-                -32605,
-                "Pool error",
-                Some(err.to_string()),
-            ),
-        }
+            PaymasterError::FeesTooLow { required, .. } => error.data_with(json!({
+                "maxFeePerGas": format!("0x{:x}", required.max_fee_per_gas),
+                "maxPriorityFeePerGas": format!("0x{:x}", required.max_priority_fee_per_gas),
+            })),
+            PaymasterError::DeploymentQuotaExceeded {
+                factory, resets_at, ..
+            } => error.data_with(json!({
+                "factory": factory,
+                "resetsAt": resets_at,
+            })),
+            _ => error.data_with(Value::Null),
+        };
+        let message = match &error {
+            PaymasterError::InvalidUserOperation(_) => "Invalid UserOperation".to_string(),
+            PaymasterError::SignerError(_) => "Signer error".to_string(),
+            PaymasterError::PolicyRejected(_) => "Policy rejected".to_string(),
+            PaymasterError::PoolError(_) => "Pool error".to_string(),
+            PaymasterError::InvalidRequest(_) => "Invalid request".to_string(),
+            PaymasterError::ChainError(_) => "Chain error".to_string(),
+            PaymasterError::IdempotencyConflict(_) => "Idempotency key conflict".to_string(),
+            PaymasterError::ConcurrentSponsorship(_) => "Concurrent sponsorship".to_string(),
+            PaymasterError::DeploymentQuotaExceeded { .. } => {
+                "Deployment quota exceeded".to_string()
+            }
+            PaymasterError::UnsupportedEntryPoint { .. } | PaymasterError::FeesTooLow { .. } => {
+                error.to_string()
+            }
+        };
+        jsonrpsee::types::ErrorObjectOwned::owned(code, message, Some(data))
     }
 }
@@ -0,0 +1,294 @@
+// paymaster-relay/src/error_catalog.rs
+// Stable keys and descriptions of the JSON-RPC errors, so clients can localize messages.
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Documented JSON-RPC error
+///
+/// Error responses carry the entry's `key` and the values of its `params`
+/// in their data, so clients can show their own translation of
+/// `description` instead of the English message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ErrorCatalogEntry {
+    /// JSON-RPC error code the error is reported with
+    pub code: i32,
+    /// Stable machine key, such as `policy.sender_not_allowed`
+    pub key: &'static str,
+    /// English description with `{param}` placeholders
+    pub description: &'static str,
+    /// Names of the placeholders in `description`
+    pub params: &'static [&'static str],
+}
+
+impl ErrorCatalogEntry {
+    /// `description` with its placeholders filled from `params`
+    ///
+    /// Placeholders missing from `params` are left as they are.
+    pub fn describe(&self, params: &Value) -> String {
+        self.params
+            .iter()
+            .fold(self.description.to_string(), |description, name| {
+                let value = match params.get(*name) {
+                    Some(Value::String(s)) => s.clone(),
+                    Some(value) => value.to_string(),
+                    None => return description,
+                };
+                description.replace(&format!("{{{}}}", name), &value)
+            })
+    }
+
+    /// JSON-RPC error data naming this entry, with the values of its placeholders
+    pub fn data(&self, params: Value) -> Value {
+        serde_json::json!({ "key": self.key, "params": params })
+    }
+}
+
+const fn entry(
+    code: i32,
+    key: &'static str,
+    description: &'static str,
+    params: &'static [&'static str],
+) -> ErrorCatalogEntry {
+    ErrorCatalogEntry {
+        code,
+        key,
+        description,
+        params,
+    }
+}
+
+pub const INVALID_USER_OPERATION: ErrorCatalogEntry = entry(
+    -32602,
+    "paymaster.invalid_user_operation",
+    "The UserOperation is malformed: {reason}",
+    &["reason"],
+);
+pub const SIGNER_ERROR: ErrorCatalogEntry = entry(
+    -32603,
+    "paymaster.signer_error",
+    "The paymaster could not sign the sponsorship: {reason}",
+    &["reason"],
+);
+pub const POLICY_REJECTED: ErrorCatalogEntry = entry(
+    -32604,
+    "paymaster.policy_rejected",
+    "The sponsorship policy does not allow this operation: {reason}",
+    &["reason"],
+);
+pub const POOL_ERROR: ErrorCatalogEntry = entry(
+    -32605,
+    "paymaster.pool_error",
+    "The mempool refused or could not be reached: {reason}",
+    &["reason"],
+);
+pub const INVALID_REQUEST: ErrorCatalogEntry = entry(
+    -32602,
+    "paymaster.invalid_request",
+    "The sponsorship request is invalid: {reason}",
+    &["reason"],
+);
+pub const CHAIN_ERROR: ErrorCatalogEntry = entry(
+    -32606,
+    "paymaster.chain_error",
+    "Reading from or sending to the chain failed: {reason}",
+    &["reason"],
+);
+pub const FEES_TOO_LOW: ErrorCatalogEntry = entry(
+    -32602,
+    "paymaster.fees_too_low",
+    "Gas fees are below the sponsored minimum: maxFeePerGas {maxFeePerGas} \
+     (minimum {minMaxFeePerGas}), maxPriorityFeePerGas {maxPriorityFeePerGas} \
+     (minimum {minMaxPriorityFeePerGas})",
+    &[
+        "maxFeePerGas",
+        "minMaxFeePerGas",
+        "maxPriorityFeePerGas",
+        "minMaxPriorityFeePerGas",
+    ],
+);
+pub const IDEMPOTENCY_CONFLICT: ErrorCatalogEntry = entry(
+    -32507,
+    "paymaster.idempotency_conflict",
+    "The idempotency key was already used for a different UserOperation: {reason}",
+    &["reason"],
+);
+pub const CONCURRENT_SPONSORSHIP: ErrorCatalogEntry = entry(
+    -32607,
+    "paymaster.concurrent_sponsorship",
+    "Another sponsorship of this sender and nonce is in progress: {reason}",
+    &["reason"],
+);
+pub const UNSUPPORTED_ENTRY_POINT: ErrorCatalogEntry = entry(
+    -32602,
+    "paymaster.unsupported_entry_point",
+    "No paymaster is configured for this EntryPoint: {reason}; supported: {supported}",
+    &["reason", "supported"],
+);
+pub const DEPLOYMENT_QUOTA_EXCEEDED: ErrorCatalogEntry = entry(
+    -32604,
+    "paymaster.deployment_quota_exceeded",
+    "Factory {factory} reached its quota of {limit} sponsored account deployments \
+     per {window} {scope}; the quota resets at {resetsAt}",
+    &["factory", "limit", "window", "scope", "resetsAt"],
+);
+
+/// Every error a [`PaymasterError`](crate::PaymasterError) is reported as
+pub const PAYMASTER_ERROR_CATALOG: &[ErrorCatalogEntry] = &[
+    INVALID_USER_OPERATION,
+    SIGNER_ERROR,
+    POLICY_REJECTED,
+    POOL_ERROR,
+    INVALID_REQUEST,
+    CHAIN_ERROR,
+    FEES_TOO_LOW,
+    IDEMPOTENCY_CONFLICT,
+    CONCURRENT_SPONSORSHIP,
+    UNSUPPORTED_ENTRY_POINT,
+    DEPLOYMENT_QUOTA_EXCEEDED,
+];
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use alloy_primitives::Address;
+    use rundler_types::{pool::PoolError, GasFees};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        entry_points::{EntryPointVersion, SupportedEntryPoint},
+        DeploymentLimit, PaymasterError, UsageGranularity,
+    };
+
+    #[test]
+    fn test_describe_fills_placeholders() {
+        assert_eq!(
+            POLICY_REJECTED.describe(&json!({ "reason": "sender not allowed" })),
+            "The sponsorship policy does not allow this operation: sender not allowed"
+        );
+        assert_eq!(
+            UNSUPPORTED_ENTRY_POINT.describe(&json!({ "supported": ["v0.6"] })),
+            "No paymaster is configured for this EntryPoint: {reason}; supported: [\"v0.6\"]"
+        );
+    }
+
+    /// Number of [`PaymasterError`] variants
+    const VARIANTS: usize = 11;
+
+    /// Index of `error`'s variant; a new variant fails to compile here until it
+    /// is given an index, and the coverage test until it has a sample
+    fn variant(error: &PaymasterError) -> usize {
+        match error {
+            PaymasterError::InvalidUserOperation(_) => 0,
+            PaymasterError::SignerError(_) => 1,
+            PaymasterError::PolicyRejected(_) => 2,
+            PaymasterError::PoolError(_) => 3,
+            PaymasterError::InvalidRequest(_) => 4,
+            PaymasterError::ChainError(_) => 5,
+            PaymasterError::FeesTooLow { .. } => 6,
+            PaymasterError::IdempotencyConflict(_) => 7,
+            PaymasterError::UnsupportedEntryPoint { .. } => 8,
+            PaymasterError::DeploymentQuotaExceeded { .. } => 9,
+            PaymasterError::ConcurrentSponsorship(_) => 10,
+        }
+    }
+
+    fn fees(max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> GasFees {
+        GasFees {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        }
+    }
+
+    fn samples() -> Vec<PaymasterError> {
+        vec![
+            PaymasterError::InvalidUserOperation("missing sender".to_string()),
+            PaymasterError::SignerError(eyre::eyre!("key unavailable")),
+            PaymasterError::PolicyRejected("sender not allowed".to_string()),
+            PaymasterError::PoolError(PoolError::UnexpectedResponse),
+            PaymasterError::InvalidRequest("bad context".to_string()),
+            PaymasterError::ChainError("connection refused".to_string()),
+            PaymasterError::FeesTooLow {
+                current: fees(1_000, 10),
+                required: fees(2_000, 20),
+            },
+            PaymasterError::IdempotencyConflict("key reused".to_string()),
+            PaymasterError::UnsupportedEntryPoint {
+                message: "No paymaster is deployed".to_string(),
+                supported: vec![SupportedEntryPoint {
+                    entry_point: Address::repeat_byte(0x01),
+                    version: EntryPointVersion::V0_6,
+                    paymaster: Address::repeat_byte(0x02),
+                }],
+            },
+            PaymasterError::DeploymentQuotaExceeded {
+                factory: Address::repeat_byte(0x03),
+                limit: DeploymentLimit {
+                    factory: Some(Address::repeat_byte(0x03)),
+                    window: UsageGranularity::Day,
+                    max: 100,
+                },
+                resets_at: 1_700_092_800,
+            },
+            PaymasterError::ConcurrentSponsorship("nonce 1 in progress".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_catalog_covers_every_variant() {
+        let samples = samples();
+        let covered: HashSet<usize> = samples.iter().map(variant).collect();
+        assert_eq!(covered, (0..VARIANTS).collect());
+
+        for error in samples {
+            let entry = error.catalog_entry();
+            assert!(PAYMASTER_ERROR_CATALOG.contains(entry), "{}", entry.key);
+            assert_eq!(error.code(), entry.code, "{}", entry.key);
+
+            let params = error.params();
+            let names: HashSet<&str> = params
+                .as_object()
+                .unwrap()
+                .keys()
+                .map(String::as_str)
+                .collect();
+            assert_eq!(
+                names,
+                entry.params.iter().copied().collect(),
+                "{}",
+                entry.key
+            );
+            assert!(!entry.describe(&params).contains('{'), "{}", entry.key);
+
+            let key = entry.key;
+            let error: jsonrpsee::types::ErrorObjectOwned = error.into();
+            let data: Value = serde_json::from_str(error.data().unwrap().get()).unwrap();
+            assert_eq!(data["key"], key);
+            assert_eq!(data["params"], params);
+        }
+    }
+
+    #[test]
+    fn test_error_data_keeps_existing_fields() {
+        let error: jsonrpsee::types::ErrorObjectOwned = PaymasterError::FeesTooLow {
+            current: fees(1_000, 10),
+            required: fees(2_000, 20),
+        }
+        .into();
+        let data: Value = serde_json::from_str(error.data().unwrap().get()).unwrap();
+        assert_eq!(data["maxFeePerGas"], "0x7d0");
+        assert_eq!(data["maxPriorityFeePerGas"], "0x14");
+        assert_eq!(
+            data["params"],
+            json!({
+                "maxFeePerGas": "1000",
+                "minMaxFeePerGas": "2000",
+                "maxPriorityFeePerGas": "10",
+                "minMaxPriorityFeePerGas": "20",
+            })
+        );
+        assert_eq!(data["key"], "paymaster.fees_too_low");
+    }
+}
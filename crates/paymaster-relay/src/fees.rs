@@ -0,0 +1,263 @@
+// paymaster-relay/src/fees.rs
+// Gas fee sanity checks applied before an operation is sponsored.
+
+use std::{fmt, sync::Arc};
+
+use rundler_provider::FeeEstimator;
+use rundler_types::{GasFees, UserOperation, UserOperationVariant};
+use tracing::debug;
+
+use crate::error::PaymasterError;
+
+/// Compares operation fees against what the bundler needs for the latest block
+#[derive(Clone)]
+pub struct FeeChecker {
+    estimator: Arc<dyn FeeEstimator>,
+    auto_bump_fees: bool,
+}
+
+impl fmt::Debug for FeeChecker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FeeChecker")
+            .field("auto_bump_fees", &self.auto_bump_fees)
+            .finish()
+    }
+}
+
+impl FeeChecker {
+    /// Reject underpriced operations, or raise their fees when `auto_bump_fees` is set
+    pub fn new(estimator: Arc<dyn FeeEstimator>, auto_bump_fees: bool) -> Self {
+        Self {
+            estimator,
+            auto_bump_fees,
+        }
+    }
+
+    /// Minimum fees an operation needs to be bundled at the latest block
+    pub async fn required_fees(&self) -> Result<GasFees, PaymasterError> {
+        let (bundle_fees, _base_fee) =
+            self.estimator.latest_bundle_fees().await.map_err(|e| {
+                PaymasterError::ChainError(format!("Failed to estimate fees: {}", e))
+            })?;
+        Ok(self.estimator.required_op_fees(bundle_fees))
+    }
+
+    /// Check `user_op` fees, returning the bumped fees if they had to be raised
+    pub async fn check(
+        &self,
+        user_op: &UserOperationVariant,
+    ) -> Result<Option<GasFees>, PaymasterError> {
+        let current = GasFees {
+            max_fee_per_gas: user_op.max_fee_per_gas(),
+            max_priority_fee_per_gas: user_op.max_priority_fee_per_gas(),
+        };
+        let required = self.required_fees().await?;
+        debug!("Operation fees {:?}, required {:?}", current, required);
+        check_fees(current, required, self.auto_bump_fees)
+    }
+}
+
+/// Compare `current` fees with `required`, bumping them or failing when too low
+pub fn check_fees(
+    current: GasFees,
+    required: GasFees,
+    auto_bump_fees: bool,
+) -> Result<Option<GasFees>, PaymasterError> {
+    if current.max_fee_per_gas >= required.max_fee_per_gas
+        && current.max_priority_fee_per_gas >= required.max_priority_fee_per_gas
+    {
+        return Ok(None);
+    }
+
+    if !auto_bump_fees {
+        return Err(PaymasterError::FeesTooLow { current, required });
+    }
+
+    let max_priority_fee_per_gas = current
+        .max_priority_fee_per_gas
+        .max(required.max_priority_fee_per_gas);
+    Ok(Some(GasFees {
+        max_fee_per_gas: current
+            .max_fee_per_gas
+            .max(required.max_fee_per_gas)
+            .max(max_priority_fee_per_gas),
+        max_priority_fee_per_gas,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use alloy_primitives::{Address, Bytes, B256, U256};
+    use async_trait::async_trait;
+    use rundler_provider::FeeEstimator;
+    use rundler_types::{chain::ChainSpec, v0_7, GasFees, UserOperation, UserOperationVariant};
+
+    use super::*;
+    use crate::{
+        service::{PaymasterRelayService, SponsorOptions},
+        sponsorship::verify_sponsorship,
+        test_utils, PaymasterError,
+    };
+
+    #[test]
+    fn test_bump_keeps_max_fee_above_priority_fee() {
+        let current = GasFees {
+            max_fee_per_gas: 10,
+            max_priority_fee_per_gas: 1,
+        };
+        let required = GasFees {
+            max_fee_per_gas: 5,
+            max_priority_fee_per_gas: 20,
+        };
+
+        let bumped = check_fees(current, required, true).unwrap().unwrap();
+        assert_eq!(bumped.max_priority_fee_per_gas, 20);
+        assert_eq!(bumped.max_fee_per_gas, 20);
+    }
+
+    const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const GWEI: u128 = 1_000_000_000;
+
+    /// Fee estimator that always requires the same operation fees
+    struct FixedFeeEstimator(GasFees);
+
+    #[async_trait]
+    impl FeeEstimator for FixedFeeEstimator {
+        async fn required_bundle_fees(
+            &self,
+            _block_hash: B256,
+            _min_fees: Option<GasFees>,
+        ) -> anyhow::Result<(GasFees, u128)> {
+            Ok((self.0, self.0.max_fee_per_gas))
+        }
+
+        async fn latest_bundle_fees(&self) -> anyhow::Result<(GasFees, u128)> {
+            Ok((self.0, self.0.max_fee_per_gas))
+        }
+
+        fn required_op_fees(&self, bundle_fees: GasFees) -> GasFees {
+            bundle_fees
+        }
+    }
+
+    fn required_fees() -> GasFees {
+        GasFees {
+            max_fee_per_gas: 30 * GWEI,
+            max_priority_fee_per_gas: 2 * GWEI,
+        }
+    }
+
+    fn create_service(auto_bump_fees: bool) -> PaymasterRelayService {
+        test_utils::service(&format!("senders = [\"{}\"]\n", SENDER)).with_fee_checker(
+            FeeChecker::new(Arc::new(FixedFeeEstimator(required_fees())), auto_bump_fees),
+        )
+    }
+
+    fn create_user_op(
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+    ) -> UserOperationVariant {
+        let op = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender: Address::from_str(SENDER).unwrap(),
+                nonce: U256::ZERO,
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        UserOperationVariant::V0_7(op)
+    }
+
+    fn entry_point() -> ethers::types::Address {
+        ethers::types::Address::from_slice(ChainSpec::default().entry_point_address_v0_7.as_slice())
+    }
+
+    fn full_operation() -> SponsorOptions {
+        SponsorOptions {
+            return_full_operation: true,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_underpriced_operation_rejected_with_minimum_fees() {
+        let service = create_service(false);
+
+        let err = service
+            .sponsor_user_operation(
+                create_user_op(10 * GWEI, GWEI),
+                entry_point(),
+                SponsorOptions::default(),
+            )
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            PaymasterError::FeesTooLow { required, .. } if required == required_fees()
+        ));
+        assert_eq!(err.code(), -32602);
+        let message = err.to_string();
+        assert!(message.contains(&(30 * GWEI).to_string()));
+        assert!(message.contains(&(2 * GWEI).to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_underpriced_operation_bumped_and_signed() {
+        let service = create_service(true);
+
+        let result = service
+            .sponsor_user_operation(
+                create_user_op(10 * GWEI, 3 * GWEI),
+                entry_point(),
+                full_operation(),
+            )
+            .await
+            .unwrap();
+
+        // Only the fee that was too low is raised
+        assert_eq!(result.max_fee_per_gas, Some(30 * GWEI));
+        assert_eq!(result.max_priority_fee_per_gas, Some(3 * GWEI));
+
+        let sponsored = result.sponsored_user_op.unwrap();
+        assert_eq!(sponsored.max_fee_per_gas(), 30 * GWEI);
+        assert_eq!(sponsored.max_priority_fee_per_gas(), 3 * GWEI);
+
+        // The signature covers the bumped fees
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let paymaster = service.paymaster_address().await;
+        verify_sponsorship(&sponsored, paymaster, now).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sufficient_fees_left_unchanged() {
+        let service = create_service(true);
+
+        let result = service
+            .sponsor_user_operation(
+                create_user_op(40 * GWEI, 2 * GWEI),
+                entry_point(),
+                full_operation(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result.max_fee_per_gas, None);
+        assert_eq!(result.max_priority_fee_per_gas, None);
+        let sponsored = result.sponsored_user_op.unwrap();
+        assert_eq!(sponsored.max_fee_per_gas(), 40 * GWEI);
+        assert_eq!(sponsored.max_priority_fee_per_gas(), 2 * GWEI);
+    }
+}
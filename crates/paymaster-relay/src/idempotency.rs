@@ -0,0 +1,418 @@
+// paymaster-relay/src/idempotency.rs
+// Idempotency keys for pm_sponsorUserOperation, so a retried request never signs twice.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use alloy_primitives::{Address, B256};
+use rundler_types::UserOperationVariant;
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+
+use crate::{canonical, error::PaymasterError, service::PaymasterSponsorResult};
+
+/// Longest idempotency key accepted
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 64;
+
+/// Settings of the idempotency key cache
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdempotencyConfig {
+    /// Seconds a sponsorship is replayed for its key
+    #[serde(default = "default_ttl_seconds")]
+    pub ttl_seconds: u64,
+    /// Keys remembered at once; the oldest are evicted first
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_ttl_seconds() -> u64 {
+    300
+}
+
+fn default_max_entries() -> usize {
+    10_000
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            ttl_seconds: default_ttl_seconds(),
+            max_entries: default_max_entries(),
+        }
+    }
+}
+
+/// Sponsorship of a key, shared by every request replaying it
+pub type IdempotencySlot = Arc<OnceCell<PaymasterSponsorResult>>;
+
+type SlotKey = (String, Address);
+
+#[derive(Debug)]
+struct Entry {
+    operation: B256,
+    created: Instant,
+    slot: IdempotencySlot,
+}
+
+#[derive(Debug, Default)]
+struct Slots {
+    entries: HashMap<SlotKey, Entry>,
+    /// Keys in insertion order, with the creation time of the entry they were
+    /// inserted for
+    order: VecDeque<(SlotKey, Instant)>,
+}
+
+/// Bounded TTL cache of sponsorships by idempotency key and sender
+///
+/// Requests replaying a key share one [`IdempotencySlot`], so concurrent
+/// replays wait for a single signature instead of each producing one. Failed
+/// sponsorships leave the slot empty and the next replay tries again.
+#[derive(Debug)]
+pub struct IdempotencyCache {
+    ttl: Duration,
+    max_entries: usize,
+    slots: Mutex<Slots>,
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        Self::new(&IdempotencyConfig::default())
+    }
+}
+
+impl IdempotencyCache {
+    /// Create an empty cache
+    pub fn new(config: &IdempotencyConfig) -> Self {
+        Self {
+            ttl: Duration::from_secs(config.ttl_seconds),
+            max_entries: config.max_entries.max(1),
+            slots: Mutex::new(Slots::default()),
+        }
+    }
+
+    /// Slot of `key` for `sender`, creating it for `operation` when unknown or
+    /// expired
+    ///
+    /// Fails when the key is still held for a different operation.
+    pub fn slot(
+        &self,
+        key: &str,
+        sender: Address,
+        operation: B256,
+    ) -> Result<IdempotencySlot, PaymasterError> {
+        let now = Instant::now();
+        let mut slots = self.slots.lock().unwrap();
+        self.purge_expired(&mut slots, now);
+
+        let slot_key = (key.to_string(), sender);
+        if let Some(entry) = slots.entries.get(&slot_key) {
+            if entry.operation != operation {
+                return Err(PaymasterError::IdempotencyConflict(format!(
+                    "key {:?} was already used for a different UserOperation of {}",
+                    key, sender
+                )));
+            }
+            return Ok(entry.slot.clone());
+        }
+
+        while slots.entries.len() >= self.max_entries {
+            let Some((oldest, created)) = slots.order.pop_front() else {
+                break;
+            };
+            Self::remove_if_created(&mut slots, &oldest, created);
+        }
+
+        let slot = IdempotencySlot::default();
+        slots.entries.insert(
+            slot_key.clone(),
+            Entry {
+                operation,
+                created: now,
+                slot: slot.clone(),
+            },
+        );
+        slots.order.push_back((slot_key, now));
+        Ok(slot)
+    }
+
+    /// Keys currently remembered
+    pub fn len(&self) -> usize {
+        let mut slots = self.slots.lock().unwrap();
+        self.purge_expired(&mut slots, Instant::now());
+        slots.entries.len()
+    }
+
+    /// Whether no key is remembered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn purge_expired(&self, slots: &mut Slots, now: Instant) {
+        while let Some((_, created)) = slots.order.front() {
+            if now.duration_since(*created) < self.ttl {
+                break;
+            }
+            let (key, created) = slots.order.pop_front().unwrap();
+            Self::remove_if_created(slots, &key, created);
+        }
+    }
+
+    /// Remove `key` unless it was re-inserted after `created`
+    fn remove_if_created(slots: &mut Slots, key: &SlotKey, created: Instant) {
+        if slots
+            .entries
+            .get(key)
+            .is_some_and(|entry| entry.created == created)
+        {
+            slots.entries.remove(key);
+        }
+    }
+}
+
+/// Identity of `user_op` for idempotency checks
+///
+/// The canonical operation bound to the chain and `entry_point`, without
+/// the paymaster data or signature, so a replay of a sponsored request still
+/// matches its original.
+pub fn operation_hash(user_op: &UserOperationVariant, entry_point: Address) -> B256 {
+    canonical::sponsorship_identity(user_op, entry_point)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, str::FromStr, sync::Arc};
+
+    use alloy_primitives::{Address, Bytes, U256};
+    use rundler_types::{chain::ChainSpec, v0_7, UserOperationVariant};
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{
+        audit::{AuditLogConfig, AuditLogger, AuditRecord},
+        service::{PaymasterRelayService, SponsorOptions},
+        test_utils, PaymasterError,
+    };
+
+    fn cache(ttl_seconds: u64, max_entries: usize) -> IdempotencyCache {
+        IdempotencyCache::new(&IdempotencyConfig {
+            ttl_seconds,
+            max_entries,
+        })
+    }
+
+    #[test]
+    fn test_replay_shares_slot() {
+        let cache = cache(60, 10);
+        let sender = Address::repeat_byte(1);
+        let op = B256::repeat_byte(1);
+
+        let first = cache.slot("key", sender, op).unwrap();
+        let second = cache.slot("key", sender, op).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // Keys are scoped to the sender
+        let other = cache.slot("key", Address::repeat_byte(2), op).unwrap();
+        assert!(!Arc::ptr_eq(&first, &other));
+    }
+
+    #[test]
+    fn test_conflicting_operation_rejected() {
+        let cache = cache(60, 10);
+        let sender = Address::repeat_byte(1);
+        cache.slot("key", sender, B256::repeat_byte(1)).unwrap();
+
+        let err = cache.slot("key", sender, B256::repeat_byte(2)).unwrap_err();
+        assert!(matches!(err, PaymasterError::IdempotencyConflict(_)));
+    }
+
+    #[test]
+    fn test_expired_key_reusable() {
+        let cache = cache(0, 10);
+        let sender = Address::repeat_byte(1);
+        cache.slot("key", sender, B256::repeat_byte(1)).unwrap();
+
+        assert!(cache.slot("key", sender, B256::repeat_byte(2)).is_ok());
+    }
+
+    #[test]
+    fn test_oldest_key_evicted_when_full() {
+        let cache = cache(60, 2);
+        let sender = Address::repeat_byte(1);
+        cache.slot("a", sender, B256::repeat_byte(1)).unwrap();
+        cache.slot("b", sender, B256::repeat_byte(2)).unwrap();
+        cache.slot("c", sender, B256::repeat_byte(3)).unwrap();
+
+        assert_eq!(cache.len(), 2);
+        // "a" was evicted, so it no longer conflicts
+        assert!(cache.slot("a", sender, B256::repeat_byte(4)).is_ok());
+        assert!(cache.slot("c", sender, B256::repeat_byte(4)).is_err());
+    }
+
+    const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    fn create_service(dir: &Path, config: IdempotencyConfig) -> PaymasterRelayService {
+        let audit_config = AuditLogConfig {
+            path: Some(dir.join("audit.jsonl")),
+            ..Default::default()
+        };
+
+        test_utils::service_in(dir, &format!("senders = [\"{}\"]\n", SENDER))
+            .with_audit_logger(AuditLogger::new(&audit_config))
+            .with_idempotency(config)
+    }
+
+    fn audit_records(dir: &Path) -> Vec<AuditRecord> {
+        std::fs::read_to_string(dir.join("audit.jsonl"))
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn create_user_op(nonce: u64) -> UserOperationVariant {
+        let op = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender: Address::from_str(SENDER).unwrap(),
+                nonce: U256::from(nonce),
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 1_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        UserOperationVariant::V0_7(op)
+    }
+
+    fn entry_point() -> ethers::types::Address {
+        ethers::types::Address::from_slice(ChainSpec::default().entry_point_address_v0_7.as_slice())
+    }
+
+    fn with_key(key: &str) -> SponsorOptions {
+        SponsorOptions {
+            idempotency_key: Some(key.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_returns_cached_sponsorship() {
+        let dir = tempdir().unwrap();
+        let service = create_service(dir.path(), IdempotencyConfig::default());
+
+        let first = service
+            .sponsor_user_operation(create_user_op(1), entry_point(), with_key("order-1"))
+            .await
+            .unwrap();
+        let replay = service
+            .sponsor_user_operation(create_user_op(1), entry_point(), with_key("order-1"))
+            .await
+            .unwrap();
+        assert_eq!(first.paymaster_and_data, replay.paymaster_and_data);
+
+        let records = audit_records(dir.path());
+        assert_eq!(records.len(), 2);
+        assert!(!records[0].replayed);
+        assert!(records[1].replayed);
+    }
+
+    #[tokio::test]
+    async fn test_conflicting_replay_rejected() {
+        let dir = tempdir().unwrap();
+        let service = create_service(dir.path(), IdempotencyConfig::default());
+
+        service
+            .sponsor_user_operation(create_user_op(1), entry_point(), with_key("order-1"))
+            .await
+            .unwrap();
+        let err = service
+            .sponsor_user_operation(create_user_op(2), entry_point(), with_key("order-1"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PaymasterError::IdempotencyConflict(_)));
+        assert_eq!(err.code(), -32507);
+
+        // A fresh key sponsors the other operation
+        assert!(service
+            .sponsor_user_operation(create_user_op(2), entry_point(), with_key("order-2"))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_replays_sign_once() {
+        let dir = tempdir().unwrap();
+        let service = Arc::new(create_service(dir.path(), IdempotencyConfig::default()));
+
+        let requests: Vec<_> = (0..2)
+            .map(|_| {
+                let service = service.clone();
+                tokio::spawn(async move {
+                    service
+                        .sponsor_user_operation(create_user_op(1), entry_point(), with_key("race"))
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+        let mut results = Vec::new();
+        for request in requests {
+            results.push(request.await.unwrap());
+        }
+        assert_eq!(results[0].paymaster_and_data, results[1].paymaster_and_data);
+
+        let records = audit_records(dir.path());
+        assert_eq!(records.len(), 2);
+        let signed: Vec<_> = records.iter().filter(|record| !record.replayed).collect();
+        assert_eq!(signed.len(), 1);
+        assert!(signed[0].signer.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_requests_without_key_not_cached() {
+        let dir = tempdir().unwrap();
+        let service = create_service(dir.path(), IdempotencyConfig::default());
+
+        for _ in 0..2 {
+            service
+                .sponsor_user_operation(create_user_op(1), entry_point(), SponsorOptions::default())
+                .await
+                .unwrap();
+        }
+        assert!(audit_records(dir.path())
+            .iter()
+            .all(|record| !record.replayed));
+    }
+
+    #[tokio::test]
+    async fn test_expired_key_sponsors_again() {
+        let dir = tempdir().unwrap();
+        let service = create_service(
+            dir.path(),
+            IdempotencyConfig {
+                ttl_seconds: 0,
+                ..Default::default()
+            },
+        );
+
+        service
+            .sponsor_user_operation(create_user_op(1), entry_point(), with_key("order-1"))
+            .await
+            .unwrap();
+        // The key expired, so it no longer conflicts
+        service
+            .sponsor_user_operation(create_user_op(2), entry_point(), with_key("order-1"))
+            .await
+            .unwrap();
+        assert!(audit_records(dir.path())
+            .iter()
+            .all(|record| !record.replayed));
+    }
+}
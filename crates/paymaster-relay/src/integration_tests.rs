@@ -14,7 +14,9 @@ use tokio::time::timeout;
 use tracing::{info, warn};
 
 use crate::{
-    airaccount_kms::{AirAccountKmsClient, BusinessValidation, KmsDualSignRequest},
+    airaccount_kms::{
+        AirAccountKmsClient, AirAccountKmsConfig, BusinessValidation, KmsDualSignRequest,
+    },
     key_manager::PaymasterKeyManager,
 };
 
@@ -42,10 +44,12 @@ impl DualSignatureIntegrationTest {
     pub async fn initialize_kms_client(&mut self) -> Result<()> {
         info!("🚀 Initializing AirAccount KMS client for integration test");
 
-        let kms_client = AirAccountKmsClient::new(
-            "http://localhost:3000".to_string(),
-            self.key_manager.clone(),
-        );
+        let config = AirAccountKmsConfig {
+            base_url: "http://localhost:3000".to_string(),
+            insecure_allow_http: true,
+            ..Default::default()
+        };
+        let kms_client = AirAccountKmsClient::new(config, self.key_manager.clone())?;
 
         self.kms_client = Some(kms_client);
         info!("✅ KMS client initialized successfully");
@@ -106,6 +110,7 @@ impl DualSignatureIntegrationTest {
             business_validation,
             nonce: 12345,
             timestamp: chrono::Utc::now().timestamp() as u64,
+            request_counter: None,
         };
 
         // 验证请求结构
@@ -215,6 +220,7 @@ impl DualSignatureIntegrationTest {
             business_validation,
             nonce: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0) as u64,
             timestamp: chrono::Utc::now().timestamp() as u64,
+            request_counter: None,
         };
 
         // 4. 模拟 AirAccount KMS 端的验证流程
@@ -1,8 +1,23 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
 
+use async_trait::async_trait;
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_kms::{
+    error::{DisplayErrorContext, ProvideErrorMetadata, SdkError},
+    primitives::Blob,
+    types::{MessageType, SigningAlgorithmSpec},
+};
 use ethers::{
+    core::k256::{elliptic_curve::sec1::ToEncodedPoint, AffinePoint},
     signers::Signer,
-    types::{Address, Signature, H256},
+    types::{Address, Signature, H256, U256},
+    utils::keccak256,
 };
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -23,6 +38,28 @@ pub enum KmsError {
     HardwareWalletError { reason: String },
     #[error("Invalid KMS configuration: {reason}")]
     InvalidConfiguration { reason: String },
+    #[error("KMS request throttled: {reason}")]
+    Throttled { reason: String },
+    #[error("KMS connection failed: {reason}")]
+    Connection { reason: String },
+    #[error("KMS TLS error: {reason}")]
+    Tls { reason: String },
+    #[error("KMS rejected the paymaster request signature: {reason}")]
+    SignatureRejected { reason: String },
+    #[error("User signature failed local verification: {reason}")]
+    InvalidUserSignature { reason: String },
+}
+
+impl KmsError {
+    /// Whether the failed request may succeed if retried after a backoff
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            KmsError::Throttled { .. }
+                | KmsError::ServiceUnavailable { .. }
+                | KmsError::Connection { .. }
+        )
+    }
 }
 
 /// KMS key types supported
@@ -142,6 +179,8 @@ pub struct KmsConfig {
     pub enable_audit_logging: bool,
     /// Maximum signing requests per minute
     pub rate_limit_per_minute: u32,
+    /// AWS KMS key ARN used by the aws-kms signer backend
+    pub aws_key_arn: Option<String>,
 }
 
 /// KMS authentication credentials
@@ -168,6 +207,10 @@ pub struct MockKmsProvider {
     config: KmsConfig,
     /// Audit log
     audit_log: Vec<SigningAuditInfo>,
+    /// Number of upcoming asymmetric requests to reject as throttled
+    throttled_requests: Arc<AtomicU32>,
+    /// Return high-s DER signatures, as AWS KMS may do
+    high_s_signatures: bool,
 }
 
 impl MockKmsProvider {
@@ -178,6 +221,8 @@ impl MockKmsProvider {
             signing_keys: HashMap::new(),
             config,
             audit_log: Vec::new(),
+            throttled_requests: Arc::new(AtomicU32::new(0)),
+            high_s_signatures: false,
         };
 
         // Initialize with default test keys
@@ -273,6 +318,31 @@ impl MockKmsProvider {
         Ok(())
     }
 
+    /// Reject the next `count` asymmetric KMS requests with a throttling error
+    pub fn with_throttled_requests(self, count: u32) -> Self {
+        self.throttled_requests.store(count, Ordering::SeqCst);
+        self
+    }
+
+    /// Return DER signatures with the high-s form of the signature
+    pub fn with_high_s_signatures(mut self) -> Self {
+        self.high_s_signatures = true;
+        self
+    }
+
+    fn check_throttled(&self) -> Result<(), KmsError> {
+        let throttled = self
+            .throttled_requests
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if throttled {
+            return Err(KmsError::Throttled {
+                reason: "Mock KMS rate exceeded".to_string(),
+            });
+        }
+        Ok(())
+    }
+
     /// List available keys
     pub fn list_keys(&self) -> Vec<&KmsKeyInfo> {
         self.keys.values().collect()
@@ -478,6 +548,262 @@ impl MockKmsProvider {
     }
 }
 
+/// Asymmetric KMS operations returning DER encoded keys and signatures,
+/// matching the AWS KMS `GetPublicKey` and `Sign` APIs
+#[async_trait]
+pub trait AsymmetricKmsClient: Send + Sync {
+    /// Get the DER encoded SubjectPublicKeyInfo of a key
+    async fn get_public_key(&self, key_id: &str) -> Result<Vec<u8>, KmsError>;
+
+    /// Sign a 32 byte digest, returning a DER encoded ECDSA signature
+    async fn sign_digest(&self, key_id: &str, digest: H256) -> Result<Vec<u8>, KmsError>;
+}
+
+#[async_trait]
+impl AsymmetricKmsClient for MockKmsProvider {
+    async fn get_public_key(&self, key_id: &str) -> Result<Vec<u8>, KmsError> {
+        self.check_throttled()?;
+
+        let wallet = self
+            .signing_keys
+            .get(key_id)
+            .ok_or_else(|| KmsError::KeyNotFound {
+                key_id: key_id.to_string(),
+            })?;
+        let affine: &AffinePoint = wallet.signer().verifying_key().as_ref();
+
+        let mut der = SECP256K1_SPKI_PREFIX.to_vec();
+        der.extend_from_slice(affine.to_encoded_point(false).as_bytes());
+        Ok(der)
+    }
+
+    async fn sign_digest(&self, key_id: &str, digest: H256) -> Result<Vec<u8>, KmsError> {
+        self.check_throttled()?;
+
+        let wallet = self
+            .signing_keys
+            .get(key_id)
+            .ok_or_else(|| KmsError::KeyNotFound {
+                key_id: key_id.to_string(),
+            })?;
+        let signature = wallet
+            .sign_hash(digest)
+            .map_err(|e| KmsError::SignatureFailed {
+                reason: format!("Signature generation failed: {}", e),
+            })?;
+
+        let s = if self.high_s_signatures {
+            secp256k1_order() - signature.s
+        } else {
+            signature.s
+        };
+
+        Ok(encode_der_signature(signature.r, s))
+    }
+}
+
+/// AWS KMS client for secp256k1 asymmetric keys
+#[derive(Debug, Clone)]
+pub struct AwsKmsClient {
+    client: aws_sdk_kms::Client,
+}
+
+impl AwsKmsClient {
+    /// Create a client from the default AWS credential chain, optionally
+    /// overriding the region
+    pub async fn new(region: Option<String>) -> Self {
+        let mut loader = aws_config::defaults(BehaviorVersion::v2025_01_17());
+        if let Some(region) = region {
+            loader = loader.region(Region::new(region));
+        }
+        let config = loader.load().await;
+
+        Self {
+            client: aws_sdk_kms::Client::new(&config),
+        }
+    }
+}
+
+#[async_trait]
+impl AsymmetricKmsClient for AwsKmsClient {
+    async fn get_public_key(&self, key_id: &str) -> Result<Vec<u8>, KmsError> {
+        let response = self
+            .client
+            .get_public_key()
+            .key_id(key_id)
+            .send()
+            .await
+            .map_err(map_aws_error)?;
+
+        response
+            .public_key
+            .map(Blob::into_inner)
+            .ok_or_else(|| KmsError::ServiceUnavailable {
+                reason: format!("No public key returned for {}", key_id),
+            })
+    }
+
+    async fn sign_digest(&self, key_id: &str, digest: H256) -> Result<Vec<u8>, KmsError> {
+        let response = self
+            .client
+            .sign()
+            .key_id(key_id)
+            .message(Blob::new(digest.as_bytes()))
+            .message_type(MessageType::Digest)
+            .signing_algorithm(SigningAlgorithmSpec::EcdsaSha256)
+            .send()
+            .await
+            .map_err(map_aws_error)?;
+
+        response
+            .signature
+            .map(Blob::into_inner)
+            .ok_or_else(|| KmsError::SignatureFailed {
+                reason: format!("No signature returned for {}", key_id),
+            })
+    }
+}
+
+fn map_aws_error<E, R>(error: SdkError<E, R>) -> KmsError
+where
+    E: ProvideErrorMetadata + std::error::Error + 'static,
+    R: std::fmt::Debug,
+{
+    let reason = DisplayErrorContext(&error).to_string();
+
+    match error.code() {
+        Some("ThrottlingException" | "LimitExceededException") => KmsError::Throttled { reason },
+        Some("NotFoundException") => KmsError::KeyNotFound { key_id: reason },
+        Some("AccessDeniedException" | "UnrecognizedClientException") => {
+            KmsError::AuthenticationFailed { reason }
+        }
+        Some("DisabledException" | "KMSInvalidStateException" | "InvalidKeyUsageException") => {
+            KmsError::SignatureFailed { reason }
+        }
+        _ => KmsError::ServiceUnavailable { reason },
+    }
+}
+
+/// DER prefix of a secp256k1 SubjectPublicKeyInfo holding an uncompressed point
+const SECP256K1_SPKI_PREFIX: [u8; 23] = [
+    0x30, 0x56, 0x30, 0x10, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x05, 0x2b,
+    0x81, 0x04, 0x00, 0x0a, 0x03, 0x42, 0x00,
+];
+
+/// DER encoded secp256k1 curve OID (1.3.132.0.10)
+const SECP256K1_OID: [u8; 7] = [0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x0a];
+
+/// Order of the secp256k1 curve
+pub fn secp256k1_order() -> U256 {
+    U256::from_big_endian(&[
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xfe, 0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36,
+        0x41, 0x41,
+    ])
+}
+
+/// Derive the Ethereum address from a DER encoded secp256k1 SubjectPublicKeyInfo
+pub fn address_from_public_key_der(der: &[u8]) -> Result<Address, KmsError> {
+    let is_secp256k1 = der
+        .windows(SECP256K1_OID.len())
+        .any(|window| window == SECP256K1_OID);
+    if !is_secp256k1 {
+        return Err(KmsError::InvalidConfiguration {
+            reason: "KMS key is not a secp256k1 key".to_string(),
+        });
+    }
+
+    // The key ends with the 65 byte uncompressed point 0x04 || x || y
+    if der.len() < 65 || der[der.len() - 65] != 0x04 {
+        return Err(KmsError::InvalidConfiguration {
+            reason: "KMS public key is not an uncompressed point".to_string(),
+        });
+    }
+
+    let hash = keccak256(&der[der.len() - 64..]);
+    Ok(Address::from_slice(&hash[12..]))
+}
+
+/// Decode a DER encoded ECDSA signature into its `(r, s)` scalars
+pub fn decode_der_signature(der: &[u8]) -> Result<(U256, U256), KmsError> {
+    let invalid = |reason: &str| KmsError::SignatureFailed {
+        reason: format!("Invalid DER signature: {}", reason),
+    };
+
+    let (tag, body, rest) = read_der_element(der).ok_or_else(|| invalid("truncated"))?;
+    if tag != 0x30 || !rest.is_empty() {
+        return Err(invalid("expected a single SEQUENCE"));
+    }
+
+    let (r_tag, r, body) = read_der_element(body).ok_or_else(|| invalid("truncated r"))?;
+    let (s_tag, s, body) = read_der_element(body).ok_or_else(|| invalid("truncated s"))?;
+    if r_tag != 0x02 || s_tag != 0x02 || !body.is_empty() {
+        return Err(invalid("expected two INTEGERs"));
+    }
+
+    Ok((
+        der_integer_to_u256(r).ok_or_else(|| invalid("r out of range"))?,
+        der_integer_to_u256(s).ok_or_else(|| invalid("s out of range"))?,
+    ))
+}
+
+/// Encode `(r, s)` scalars as a DER ECDSA signature
+pub fn encode_der_signature(r: U256, s: U256) -> Vec<u8> {
+    let r = u256_to_der_integer(r);
+    let s = u256_to_der_integer(s);
+
+    let mut der = vec![0x30, (r.len() + s.len()) as u8];
+    der.extend(r);
+    der.extend(s);
+    der
+}
+
+/// Split one DER element into `(tag, contents, remaining)`
+fn read_der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&len, input) = input.split_first()?;
+
+    let (len, input) = match len {
+        len if len < 0x80 => (len as usize, input),
+        0x81 => {
+            let (&len, input) = input.split_first()?;
+            (len as usize, input)
+        }
+        _ => return None,
+    };
+
+    if input.len() < len {
+        return None;
+    }
+    let (contents, rest) = input.split_at(len);
+    Some((tag, contents, rest))
+}
+
+fn der_integer_to_u256(bytes: &[u8]) -> Option<U256> {
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    let bytes = &bytes[start..];
+    if bytes.len() > 32 {
+        return None;
+    }
+    Some(U256::from_big_endian(bytes))
+}
+
+fn u256_to_der_integer(value: U256) -> Vec<u8> {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(31);
+    let mut contents = bytes[start..].to_vec();
+    // Keep the integer positive when the high bit is set
+    if contents[0] & 0x80 != 0 {
+        contents.insert(0, 0);
+    }
+
+    let mut der = vec![0x02, contents.len() as u8];
+    der.extend(contents);
+    der
+}
+
 impl Default for KmsConfig {
     fn default() -> Self {
         Self {
@@ -496,6 +822,7 @@ impl Default for KmsConfig {
             signing_timeout_seconds: 30,
             enable_audit_logging: true,
             rate_limit_per_minute: 1000,
+            aws_key_arn: None,
         }
     }
 }
@@ -542,6 +869,36 @@ mod tests {
         assert!(response.audit_info.duration_ms > 0);
     }
 
+    #[test]
+    fn test_der_signature_round_trip() {
+        let r = U256::from(0x80u64) << 248;
+        let s = U256::from(0x1234u64);
+
+        let der = encode_der_signature(r, s);
+        // r has its high bit set and needs a leading zero byte
+        assert_eq!(der[2..5], [0x02, 33, 0x00]);
+        assert_eq!(decode_der_signature(&der).unwrap(), (r, s));
+
+        assert!(decode_der_signature(&der[..der.len() - 1]).is_err());
+        assert!(decode_der_signature(&[0x31, 0x00]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_public_key_der() {
+        let kms = MockKmsProvider::new(KmsConfig::default()).unwrap();
+
+        let der = AsymmetricKmsClient::get_public_key(&kms, "paymaster-primary-key")
+            .await
+            .unwrap();
+        assert_eq!(der.len(), 88);
+        assert_eq!(
+            address_from_public_key_der(&der).unwrap(),
+            kms.get_key_info("paymaster-primary-key").unwrap().address
+        );
+
+        assert!(address_from_public_key_der(&der[23..]).is_err());
+    }
+
     #[tokio::test]
     async fn test_kms_connectivity() {
         let config = KmsConfig::default();
@@ -3,7 +3,21 @@ pub mod api_docs;
 pub mod api_handlers;
 pub mod api_schemas;
 pub mod api_server;
+pub mod attestation;
+pub mod audit;
+pub mod authorizer;
+pub mod balance_monitor;
+pub mod call_data;
+pub mod canonical;
+pub mod clock_skew;
+pub mod cors;
+pub mod dashboard;
+pub mod deposit;
+pub mod entry_points;
 pub mod error;
+pub mod error_catalog;
+pub mod fees;
+pub mod idempotency;
 #[cfg(feature = "integration-tests")]
 pub mod integration_tests;
 pub mod key_manager;
@@ -12,27 +26,111 @@ pub mod metrics;
 // TODO: Fix KmsProvider trait dependencies before enabling
 // #[cfg(feature = "optee-kms")]
 // pub mod optee_kms;
+pub mod outbound_tx;
 pub mod policy;
+pub mod policy_shadow;
 pub mod proxy_client;
 pub mod proxy_server;
 pub mod rpc;
+pub mod sbt;
 pub mod schemas;
+pub mod secrets;
+pub mod sender_lock;
 pub mod service;
+pub mod signature_cache;
 pub mod signer;
+pub mod simulation;
+pub mod sponsorship;
+pub mod sponsorship_records;
+pub mod stats;
 pub mod swagger;
+#[cfg(test)]
+mod test_utils;
+pub mod timings;
+pub mod token_paymaster;
+pub mod usage;
+pub mod user_signature;
 pub mod validation;
+pub mod verification_proof;
 
 // Re-export commonly used types
-pub use airaccount_kms::{AirAccountKmsClient, KmsDualSignRequest, KmsSignResponse};
+pub use airaccount_kms::{
+    AirAccountKmsClient, AirAccountKmsConfig, KmsDualSignRequest, KmsSignResponse, KmsSigningFormat,
+};
 pub use api_server::{create_api_router, start_api_server, AppState};
+pub use attestation::{verify_attestation, Attestation, AttestationConfig, Attestor};
+pub use audit::{AuditLogConfig, AuditLogger, AuditRecord};
+pub use authorizer::{
+    AuthorizationRequest, AuthorizerConfig, AuthorizerDecision, AuthorizerFailure,
+    ExternalAuthorizer,
+};
+pub use balance_monitor::{BalanceMonitor, BalanceMonitorConfig, BalanceStatus};
+pub use call_data::{ExecuteFunction, FunctionSelector, InnerCall, Selector};
+pub use canonical::{canonical_digest, canonical_json, user_operation_digest};
+pub use clock_skew::{
+    BlockTimestampSource, ClockGuard, ClockSkewConfig, ClockSkewState, ClockSkewStatus,
+    ProviderBlockTimestamps,
+};
+pub use cors::{CorsConfig, CorsConfigError};
+pub use dashboard::{
+    BalanceHealth, DashboardBalance, DashboardPolicies, DashboardTransactions, SponsorshipHistory,
+};
+pub use deposit::{
+    DepositChain, DepositConfig, DepositManager, DepositTransaction, PaymasterDepositInfo,
+    ProviderDepositChain,
+};
+pub use entry_points::{
+    check_paymaster_contracts, ContractCheck, EntryPointRouting, EntryPointVersion,
+    PaymasterContractConfig, PaymasterContractReader, ProviderContractReader, SupportedEntryPoint,
+};
 pub use error::PaymasterError;
+pub use error_catalog::{ErrorCatalogEntry, PAYMASTER_ERROR_CATALOG};
+pub use fees::FeeChecker;
+pub use idempotency::{IdempotencyCache, IdempotencyConfig};
 pub use key_manager::{PaymasterKeyError, PaymasterKeyManager, PaymasterKeyStatus};
-pub use kms::{KmsConfig, KmsError, MockKmsProvider, SigningContext};
+pub use kms::{
+    AsymmetricKmsClient, AwsKmsClient, KmsConfig, KmsError, MockKmsProvider, SigningContext,
+};
 // TODO: Re-enable when optee_kms module is fixed
 // #[cfg(feature = "optee-kms")]
 // pub use optee_kms::{OpteKmsProvider, OpteeKmsConfig};
+pub use outbound_tx::{
+    OutboundChain, OutboundReceipt, OutboundRequest, OutboundTx, OutboundTxConfig,
+    OutboundTxManager, OutboundTxStatus,
+};
+pub use policy::PolicyRejection;
+pub use policy_shadow::{PolicyShadow, PolicyShadowConfig, PolicyShadowReport, RuleDivergence};
 pub use proxy_server::start_proxy_api_server;
 pub use rpc::{PaymasterRelayApiServer, PaymasterRelayApiServerImpl};
-pub use service::PaymasterRelayService;
-pub use signer::{SignerBackend, SignerManager};
+pub use sbt::{ProviderTokenBalances, SBTValidator, SBTValidatorConfig, TokenBalanceReader};
+pub use secrets::{KdfParams, Secrets, SecretsError};
+pub use sender_lock::{SenderLockConfig, SenderLocks};
+pub use service::{DummyPaymasterData, PaymasterGasConfig, PaymasterRelayService, SignerStatus};
+pub use signature_cache::{SignatureCache, SignatureCacheConfig};
+pub use signer::{
+    AwsKmsSignerBackend, KeyRotation, KeySource, RetiringSigner, SignerBackend, SignerBackendKind,
+    SignerKeyStatus, SignerLease, SignerManager,
+};
+pub use simulation::{
+    EntryPointSimulator, SimulatedGas, SimulationStatus, SponsorshipSimulation, ValidationSimulator,
+};
+pub use sponsorship::{SponsorshipData, SponsorshipError};
+pub use sponsorship_records::{
+    FileSponsorshipStore, MinedUserOperation, SponsorshipPage, SponsorshipQuery, SponsorshipRecord,
+    SponsorshipRecordConfig, SponsorshipRecords, SponsorshipStatus, SponsorshipStore, StatusUpdate,
+    UserOperationEventSource,
+};
 pub use swagger::{serve_swagger_ui, SwaggerState};
+pub use timings::{record_stage, SponsorStage, StageTiming, StageTimings};
+pub use token_paymaster::{
+    PriceFeed, PriceOracle, ProviderPriceOracle, TokenPaymasterConfig, TokenPricing, TokenQuote,
+};
+pub use usage::{
+    DeploymentLimit, DeploymentReservation, InMemoryUsageStore, UsageBucket, UsageConfig,
+    UsageGranularity, UsageStore,
+};
+pub use user_signature::{LocalVerificationConfig, UserSignatureAlgorithm};
+pub use verification_proof::{
+    KmsSigningSummary, StoredVerificationProof, ValidationSummary, VerificationProofConfig,
+    VerificationProofStore,
+};
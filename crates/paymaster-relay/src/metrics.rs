@@ -5,10 +5,76 @@
 
 use std::time::{Duration, Instant};
 
+use alloy_primitives::Address;
 use anyhow::Result;
-use metrics::{counter, gauge, histogram};
+use metrics::{counter, gauge, histogram, Label};
+
+use crate::entry_points::EntryPointVersion;
+
+/// `entry_point` label of an EntryPoint the paymaster is not configured for
+pub const OTHER_ENTRY_POINT: &str = "other";
+
+/// Chain and EntryPoint of a sponsorship, labelling its metrics
+///
+/// Label values stay bounded: an EntryPoint outside the configured set is
+/// labelled [`OTHER_ENTRY_POINT`], and senders are never labels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SponsorshipLabels {
+    /// Chain id
+    pub chain_id: u64,
+    /// Checksummed EntryPoint address, or [`OTHER_ENTRY_POINT`]
+    pub entry_point: String,
+    /// `v0.6`, `v0.7` or `v0.8`, `unknown` for other EntryPoints
+    pub entry_point_version: String,
+}
+
+impl SponsorshipLabels {
+    /// Labels of `entry_point` on `chain_id`, with the version it is configured
+    /// with; `None` when the EntryPoint is not configured
+    pub fn new(chain_id: u64, entry_point: Address, version: Option<EntryPointVersion>) -> Self {
+        match version {
+            Some(version) => Self {
+                chain_id,
+                entry_point: entry_point.to_checksum(None),
+                entry_point_version: version.to_string(),
+            },
+            None => Self {
+                chain_id,
+                entry_point: OTHER_ENTRY_POINT.to_string(),
+                entry_point_version: "unknown".to_string(),
+            },
+        }
+    }
+
+    /// The labels with `outcome` and `extra`
+    fn with_outcome(&self, outcome: &'static str, extra: &[(&'static str, &str)]) -> Vec<Label> {
+        let mut labels = vec![
+            Label::new("chain_id", self.chain_id.to_string()),
+            Label::new("entry_point", self.entry_point.clone()),
+            Label::new("entry_point_version", self.entry_point_version.clone()),
+            Label::new("outcome", outcome),
+        ];
+        labels.extend(
+            extra
+                .iter()
+                .map(|(key, value)| Label::new(*key, value.to_string())),
+        );
+        labels
+    }
+}
+
+fn outcome(success: bool) -> &'static str {
+    if success {
+        "success"
+    } else {
+        "failure"
+    }
+}
 
 /// PaymasterRelay metrics collection and reporting
+///
+/// Sponsorship, latency, signer and quota metrics carry the
+/// [`SponsorshipLabels`] of the request and an `outcome` label.
 #[derive(Debug, Clone)]
 pub struct PaymasterMetrics {
     /// Service start time for uptime calculation
@@ -24,46 +90,173 @@ impl PaymasterMetrics {
     }
 
     /// Record a successful paymaster request
-    pub fn record_request_success(&self, duration: Duration) {
-        counter!("paymaster_requests_total", "status" => "success").increment(1);
-        histogram!("paymaster_request_duration_seconds").record(duration.as_secs_f64());
+    pub fn record_request_success(&self, labels: &SponsorshipLabels, duration: Duration) {
+        counter!(
+            "paymaster_requests_total",
+            labels.with_outcome("success", &[])
+        )
+        .increment(1);
+        histogram!(
+            "paymaster_request_duration_seconds",
+            labels.with_outcome("success", &[])
+        )
+        .record(duration.as_secs_f64());
     }
 
-    /// Record a failed paymaster request
-    pub fn record_request_failure(&self, error_type: &str, duration: Duration) {
-        counter!("paymaster_requests_total", "status" => "error", "error_type" => error_type.to_string()).increment(1);
-        histogram!("paymaster_request_duration_seconds").record(duration.as_secs_f64());
+    /// Record a failed paymaster request, `error_type` being one of a fixed set
+    pub fn record_request_failure(
+        &self,
+        labels: &SponsorshipLabels,
+        error_type: &str,
+        duration: Duration,
+    ) {
+        counter!(
+            "paymaster_requests_total",
+            labels.with_outcome("error", &[("error_type", error_type)])
+        )
+        .increment(1);
+        histogram!(
+            "paymaster_request_duration_seconds",
+            labels.with_outcome("error", &[])
+        )
+        .record(duration.as_secs_f64());
     }
 
     /// Record gas sponsored amount
-    pub fn record_gas_sponsored(&self, amount: u64) {
-        counter!("paymaster_gas_sponsored_total").increment(amount);
-        gauge!("paymaster_gas_sponsored_latest").set(amount as f64);
+    pub fn record_gas_sponsored(&self, labels: &SponsorshipLabels, amount: u64) {
+        counter!(
+            "paymaster_gas_sponsored_total",
+            labels.with_outcome("success", &[])
+        )
+        .increment(amount);
+        gauge!(
+            "paymaster_gas_sponsored_latest",
+            labels.with_outcome("success", &[])
+        )
+        .set(amount as f64);
+    }
+
+    /// Record a sponsorship rejected by policy, a budget or a quota
+    pub fn record_policy_violation(&self, labels: &SponsorshipLabels, policy_type: &str) {
+        counter!(
+            "paymaster_policy_violations_total",
+            labels.with_outcome("rejected", &[("policy_type", policy_type)])
+        )
+        .increment(1);
     }
 
-    /// Record policy violation
-    pub fn record_policy_violation(&self, policy_type: &str) {
-        counter!("paymaster_policy_violations_total", "policy_type" => policy_type.to_string())
-            .increment(1);
+    /// Record a request the candidate policy decided differently, see [`crate::policy_shadow`]
+    pub fn record_policy_shadow_divergence(&self, divergence: &str, rule: &str) {
+        counter!(
+            "paymaster_policy_shadow_divergences_total",
+            "divergence" => divergence.to_string(),
+            "rule" => rule.to_string()
+        )
+        .increment(1);
     }
 
     /// Record signature operation
-    pub fn record_signature_operation(&self, success: bool, duration: Duration) {
-        let status = if success { "success" } else { "failure" };
-        counter!("paymaster_signature_operations_total", "status" => status.to_string())
-            .increment(1);
-        histogram!("paymaster_signature_duration_seconds").record(duration.as_secs_f64());
+    pub fn record_signature_operation(
+        &self,
+        labels: &SponsorshipLabels,
+        success: bool,
+        duration: Duration,
+    ) {
+        let outcome = outcome(success);
+        counter!(
+            "paymaster_signature_operations_total",
+            labels.with_outcome(outcome, &[])
+        )
+        .increment(1);
+        histogram!(
+            "paymaster_signature_duration_seconds",
+            labels.with_outcome(outcome, &[])
+        )
+        .record(duration.as_secs_f64());
+    }
+
+    /// Record a sponsorship signed by the signer pool key `signer`
+    pub fn record_signer_signature(&self, labels: &SponsorshipLabels, signer: &str) {
+        counter!(
+            "paymaster_signer_signatures_total",
+            labels.with_outcome("success", &[("signer", signer)])
+        )
+        .increment(1);
+    }
+
+    /// Update whether the signer pool key `signer` is drained
+    pub fn update_signer_draining(&self, signer: &str, draining: bool) {
+        let value = if draining { 1.0 } else { 0.0 };
+        gauge!("paymaster_signer_draining", "signer" => signer.to_string()).set(value);
     }
 
     /// Record pool submission
     pub fn record_pool_submission(&self, success: bool) {
-        let status = if success { "success" } else { "failure" };
-        counter!("paymaster_pool_submissions_total", "status" => status.to_string()).increment(1);
+        counter!("paymaster_pool_submissions_total", "outcome" => outcome(success)).increment(1);
+    }
+
+    /// Record a sponsorship replayed for its idempotency key
+    pub fn record_idempotency_hit(&self, labels: &SponsorshipLabels) {
+        counter!(
+            "paymaster_idempotency_hits_total",
+            labels.with_outcome("replayed", &[])
+        )
+        .increment(1);
+    }
+
+    /// Record whether a request without idempotency key reused a cached signature
+    pub fn record_signature_cache(&self, labels: &SponsorshipLabels, hit: bool) {
+        let outcome = if hit { "hit" } else { "miss" };
+        counter!(
+            "paymaster_signature_cache_requests_total",
+            labels.with_outcome(outcome, &[])
+        )
+        .increment(1);
     }
 
-    /// Update success rate gauge
+    /// Record an idempotency key reused for a different UserOperation
+    pub fn record_idempotency_conflict(&self, labels: &SponsorshipLabels) {
+        counter!(
+            "paymaster_idempotency_conflicts_total",
+            labels.with_outcome("conflict", &[])
+        )
+        .increment(1);
+    }
+
+    /// Record whether a request found its sender and nonce already being
+    /// sponsored, sharing that sponsorship or conflicting with it
+    pub fn record_concurrent_sponsorship(&self, labels: &SponsorshipLabels, shared: bool) {
+        let outcome = if shared { "shared" } else { "conflict" };
+        counter!(
+            "paymaster_concurrent_sponsorships_total",
+            labels.with_outcome(outcome, &[])
+        )
+        .increment(1);
+    }
+
+    /// Update the local minus chain clock skew gauge, in seconds
+    pub fn update_clock_skew(&self, skew_seconds: i64) {
+        gauge!("paymaster_clock_skew_seconds").set(skew_seconds as f64);
+    }
+
+    /// Update the paymaster account balance gauge, in ether
+    pub fn update_account_balance(&self, balance_eth: f64) {
+        gauge!("paymaster_account_balance_eth").set(balance_eth);
+    }
+
+    /// Update the EntryPoint deposit gauge, in ether
+    pub fn update_deposit(&self, entry_point: &str, deposit_eth: f64) {
+        gauge!("paymaster_deposit_eth", "entry_point" => entry_point.to_string()).set(deposit_eth);
+    }
+
+    /// Record an automatic deposit top-up
+    pub fn record_rebalance(&self, success: bool) {
+        counter!("paymaster_rebalances_total", "outcome" => outcome(success)).increment(1);
+    }
+
+    /// Update the success ratio gauge, from 0 to 1
     pub fn update_success_rate(&self, success_rate: f64) {
-        gauge!("paymaster_success_rate").set(success_rate);
+        gauge!("paymaster_success_ratio").set(success_rate);
     }
 
     /// Update active connections gauge
@@ -73,7 +266,7 @@ impl PaymasterMetrics {
 
     /// Update memory usage gauge
     pub fn update_memory_usage(&self, usage_mb: u64) {
-        gauge!("paymaster_memory_usage_mb").set(usage_mb as f64);
+        gauge!("paymaster_memory_usage_bytes").set((usage_mb * 1024 * 1024) as f64);
     }
 
     /// Update request queue depth
@@ -82,10 +275,18 @@ impl PaymasterMetrics {
     }
 
     /// Record UserOperation validation
-    pub fn record_validation(&self, success: bool, duration: Duration) {
-        let status = if success { "success" } else { "failure" };
-        counter!("paymaster_validations_total", "status" => status.to_string()).increment(1);
-        histogram!("paymaster_validation_duration_seconds").record(duration.as_secs_f64());
+    pub fn record_validation(&self, labels: &SponsorshipLabels, success: bool, duration: Duration) {
+        let outcome = outcome(success);
+        counter!(
+            "paymaster_validations_total",
+            labels.with_outcome(outcome, &[])
+        )
+        .increment(1);
+        histogram!(
+            "paymaster_validation_duration_seconds",
+            labels.with_outcome(outcome, &[])
+        )
+        .record(duration.as_secs_f64());
     }
 
     /// Get service uptime
@@ -100,7 +301,7 @@ impl PaymasterMetrics {
 
     /// Record response time histogram
     pub fn record_response_time(&self, endpoint: &str, duration: Duration) {
-        histogram!("paymaster_response_time_seconds", "endpoint" => endpoint.to_string())
+        histogram!("paymaster_response_duration_seconds", "endpoint" => endpoint.to_string())
             .record(duration.as_secs_f64());
     }
 
@@ -145,7 +346,17 @@ pub fn init_metrics() -> Result<()> {
 
 #[cfg(test)]
 mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use alloy_primitives::{Address, Bytes, U256};
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use rundler_types::{chain::ChainSpec, v0_6, v0_7, UserOperationVariant};
+
     use super::*;
+    use crate::{
+        service::{PaymasterRelayService, SponsorOptions},
+        test_utils,
+    };
 
     #[test]
     fn test_format_duration() {
@@ -155,6 +366,24 @@ mod tests {
         assert_eq!(format_duration(Duration::from_secs(90061)), "1d 1h 1m 1s");
     }
 
+    #[test]
+    fn test_unconfigured_entry_point_labelled_other() {
+        let labels = SponsorshipLabels::new(10, Address::repeat_byte(0x11), None);
+        assert_eq!(labels.entry_point, OTHER_ENTRY_POINT);
+        assert_eq!(labels.entry_point_version, "unknown");
+
+        let labels = SponsorshipLabels::new(
+            10,
+            Address::repeat_byte(0x11),
+            Some(EntryPointVersion::V0_7),
+        );
+        assert_eq!(
+            labels.entry_point,
+            Address::repeat_byte(0x11).to_checksum(None)
+        );
+        assert_eq!(labels.entry_point_version, "v0.7");
+    }
+
     #[test]
     fn test_metrics_creation() {
         let metrics = PaymasterMetrics::new();
@@ -164,13 +393,14 @@ mod tests {
     #[test]
     fn test_record_operations() {
         let metrics = PaymasterMetrics::new();
+        let labels = SponsorshipLabels::new(1, Address::ZERO, Some(EntryPointVersion::V0_6));
 
         // Test recording various operations
-        metrics.record_request_success(Duration::from_millis(100));
-        metrics.record_request_failure("validation_error", Duration::from_millis(50));
-        metrics.record_gas_sponsored(1000000);
-        metrics.record_policy_violation("rate_limit");
-        metrics.record_signature_operation(true, Duration::from_millis(10));
+        metrics.record_request_success(&labels, Duration::from_millis(100));
+        metrics.record_request_failure(&labels, "validation_error", Duration::from_millis(50));
+        metrics.record_gas_sponsored(&labels, 1000000);
+        metrics.record_policy_violation(&labels, "rate_limit");
+        metrics.record_signature_operation(&labels, true, Duration::from_millis(10));
         metrics.record_pool_submission(true);
 
         // Test gauge updates
@@ -180,4 +410,133 @@ mod tests {
         metrics.update_queue_depth(5);
         metrics.update_health_status(true);
     }
+
+    const SENDER: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+    const CHAIN_ID: u64 = 8453;
+
+    fn create_service() -> PaymasterRelayService {
+        test_utils::service(&format!("senders = [\"{}\"]\n", SENDER)).with_chain_spec(Arc::new(
+            ChainSpec {
+                id: CHAIN_ID,
+                ..Default::default()
+            },
+        ))
+    }
+
+    fn v0_6_op() -> UserOperationVariant {
+        UserOperationVariant::V0_6(
+            v0_6::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_6::UserOperationRequiredFields {
+                    sender: Address::from_str(SENDER).unwrap(),
+                    nonce: U256::ZERO,
+                    call_gas_limit: 100_000,
+                    verification_gas_limit: 100_000,
+                    pre_verification_gas: 21_000,
+                    max_fee_per_gas: 1_000_000_000,
+                    max_priority_fee_per_gas: 1_000_000_000,
+                    ..Default::default()
+                },
+            )
+            .build(),
+        )
+    }
+
+    fn v0_7_op() -> UserOperationVariant {
+        UserOperationVariant::V0_7(
+            v0_7::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_7::UserOperationRequiredFields {
+                    sender: Address::from_str(SENDER).unwrap(),
+                    nonce: U256::from(1),
+                    call_data: Bytes::new(),
+                    call_gas_limit: 100_000,
+                    verification_gas_limit: 100_000,
+                    pre_verification_gas: 21_000,
+                    max_fee_per_gas: 1_000_000_000,
+                    max_priority_fee_per_gas: 1_000_000_000,
+                    signature: Bytes::new(),
+                },
+            )
+            .build(),
+        )
+    }
+
+    fn ethers_address(address: Address) -> ethers::types::Address {
+        ethers::types::Address::from_slice(address.as_slice())
+    }
+
+    /// Scraped lines of `metric` carrying every one of `labels`
+    fn series<'a>(scrape: &'a str, metric: &str, labels: &[String]) -> Vec<&'a str> {
+        scrape
+            .lines()
+            .filter(|line| line.starts_with(&format!("{}{{", metric)))
+            .filter(|line| labels.iter().all(|label| line.contains(label.as_str())))
+            .collect()
+    }
+
+    fn labels(entry_point: &str, version: &str) -> Vec<String> {
+        vec![
+            format!("chain_id=\"{}\"", CHAIN_ID),
+            format!("entry_point=\"{}\"", entry_point),
+            format!("entry_point_version=\"{}\"", version),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_sponsorship_metrics_labelled_per_entry_point() {
+        let handle = PrometheusBuilder::new().install_recorder().unwrap();
+        let service = create_service();
+        let v0_6 = ChainSpec::default().entry_point_address_v0_6;
+        let v0_7 = ChainSpec::default().entry_point_address_v0_7;
+
+        service
+            .sponsor_user_operation(v0_6_op(), ethers_address(v0_6), SponsorOptions::default())
+            .await
+            .unwrap();
+        service
+            .sponsor_user_operation(v0_7_op(), ethers_address(v0_7), SponsorOptions::default())
+            .await
+            .unwrap();
+        service
+            .sponsor_user_operation(
+                v0_6_op(),
+                ethers_address(Address::repeat_byte(0x42)),
+                SponsorOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        let scrape = handle.render();
+        for (entry_point, version) in [(v0_6, "v0.6"), (v0_7, "v0.7")] {
+            let mut success = labels(&entry_point.to_checksum(None), version);
+            success.push("outcome=\"success\"".to_string());
+            for metric in [
+                "paymaster_requests_total",
+                "paymaster_request_duration_seconds_count",
+                "paymaster_signature_operations_total",
+                "paymaster_signer_signatures_total",
+                "paymaster_validations_total",
+            ] {
+                assert_eq!(
+                    series(&scrape, metric, &success).len(),
+                    1,
+                    "{} for {}:\n{}",
+                    metric,
+                    version,
+                    scrape
+                );
+            }
+        }
+
+        // Unconfigured EntryPoints share one series, senders are never labels
+        let other = labels(OTHER_ENTRY_POINT, "unknown");
+        assert_eq!(
+            series(&scrape, "paymaster_requests_total", &other).len(),
+            1,
+            "{}",
+            scrape
+        );
+        assert!(!scrape.to_lowercase().contains(&SENDER.to_lowercase()));
+    }
 }
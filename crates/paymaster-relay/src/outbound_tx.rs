@@ -0,0 +1,890 @@
+// paymaster-relay/src/outbound_tx.rs
+// Transactions sent from the paymaster account (deposits, withdrawals, top-ups):
+// serialized nonce assignment, fees from the shared fee estimator, replacement of
+// stuck transactions, and a ledger that survives restarts (pm_getOutboundTx).
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex as StdMutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::{Address, Bytes, B256, U256};
+use async_trait::async_trait;
+use ethers::types::{transaction::eip2718::TypedTransaction, Eip1559TransactionRequest, H160};
+use rundler_provider::FeeEstimator;
+use rundler_types::GasFees;
+use serde::{Deserialize, Serialize};
+use tokio::{sync::Mutex, task::JoinHandle};
+use tracing::{info, warn};
+
+use crate::{error::PaymasterError, signer::SignerManager};
+
+/// Outbound transaction settings (`[paymaster_relay.outbound_tx]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OutboundTxConfig {
+    /// Blocks a transaction may stay unmined before it is replaced with higher fees
+    pub stuck_after_blocks: u64,
+    /// Percent a replacement raises both fees by; nodes require at least 10
+    pub fee_bump_percent: u32,
+    /// Replacements of one transaction, after which it is left as last sent
+    pub max_replacements: u32,
+    /// Milliseconds between checks of pending transactions
+    pub poll_interval_ms: u64,
+    /// Finished transactions kept for pm_getOutboundTx
+    pub history_size: usize,
+    /// JSON file of the tracked transactions; kept in memory only when unset
+    pub path: Option<PathBuf>,
+}
+
+impl Default for OutboundTxConfig {
+    fn default() -> Self {
+        Self {
+            stuck_after_blocks: 5,
+            fee_bump_percent: 15,
+            max_replacements: 5,
+            poll_interval_ms: 2_000,
+            history_size: 1_000,
+            path: None,
+        }
+    }
+}
+
+/// Where an outbound transaction stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutboundTxStatus {
+    /// Sent, not mined yet
+    Pending,
+    /// Mined and succeeded
+    Mined,
+    /// Mined and reverted
+    Reverted,
+    /// Its nonce was used by a transaction not sent through the manager
+    Dropped,
+}
+
+/// Transaction to send from the paymaster account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboundRequest {
+    /// What the transaction does, e.g. `deposit`
+    pub kind: String,
+    pub to: Address,
+    pub value: U256,
+    pub data: Bytes,
+    pub gas_limit: u64,
+}
+
+/// Transaction sent by the manager, across its replacements
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboundTx {
+    /// Internal id, the same for every replacement
+    pub id: String,
+    pub kind: String,
+    pub chain_id: u64,
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    pub data: Bytes,
+    pub nonce: u64,
+    pub gas_limit: u64,
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+    /// Hash of the latest submission, or of the one that was mined
+    pub tx_hash: B256,
+    /// Hashes of earlier submissions, oldest first
+    pub replaced: Vec<B256>,
+    pub status: OutboundTxStatus,
+    /// Unix time of the first submission
+    pub submitted_at: u64,
+    /// Block number when the latest submission was sent
+    pub sent_at_block: u64,
+    /// Block the transaction was mined in
+    pub block_number: Option<u64>,
+}
+
+impl OutboundTx {
+    fn fees(&self) -> GasFees {
+        GasFees {
+            max_fee_per_gas: self.max_fee_per_gas,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+        }
+    }
+
+    /// Whether `key` is the id or the hash of any submission
+    fn matches(&self, key: &str) -> bool {
+        if self.id == key {
+            return true;
+        }
+        key.parse::<B256>()
+            .is_ok_and(|hash| self.tx_hash == hash || self.replaced.contains(&hash))
+    }
+}
+
+/// Receipt of a mined transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutboundReceipt {
+    pub block_number: u64,
+    pub success: bool,
+}
+
+/// Chain access needed to send and track outbound transactions
+#[async_trait]
+pub trait OutboundChain: Send + Sync {
+    /// Chain id transactions are signed for
+    fn chain_id(&self) -> u64;
+
+    /// Hash and number of the latest block
+    async fn latest_block(&self) -> Result<(B256, u64), PaymasterError>;
+
+    /// Number of mined transactions of `account`, its next nonce on chain
+    async fn transaction_count(&self, account: Address) -> Result<u64, PaymasterError>;
+
+    /// Submit a signed transaction, returning its hash
+    async fn send_raw_transaction(&self, tx: Bytes) -> Result<B256, PaymasterError>;
+
+    /// Receipt of `tx_hash`, unset while it is not mined
+    async fn receipt(&self, tx_hash: B256) -> Result<Option<OutboundReceipt>, PaymasterError>;
+}
+
+/// Sends transactions from the paymaster account and sees them mined
+///
+/// Nonces are assigned one transaction at a time, after the last nonce the
+/// chain or the ledger knows of, so a restart with transactions still pending
+/// does not reuse their nonces.
+pub struct OutboundTxManager {
+    chain: Arc<dyn OutboundChain>,
+    fee_estimator: Arc<dyn FeeEstimator>,
+    config: OutboundTxConfig,
+    // Next nonce of each sending account, held while a transaction is signed and sent
+    nonces: Mutex<HashMap<Address, u64>>,
+    // Oldest first
+    ledger: StdMutex<Vec<OutboundTx>>,
+}
+
+impl std::fmt::Debug for OutboundTxManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutboundTxManager")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OutboundTxManager {
+    /// Create the manager, restoring the ledger at the configured path
+    pub fn new(
+        chain: Arc<dyn OutboundChain>,
+        fee_estimator: Arc<dyn FeeEstimator>,
+        config: OutboundTxConfig,
+    ) -> io::Result<Self> {
+        let mut ledger: Vec<OutboundTx> = Vec::new();
+        if let Some(path) = &config.path {
+            match fs::read(path) {
+                Ok(data) => {
+                    ledger = serde_json::from_slice(&data)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    let pending = ledger
+                        .iter()
+                        .filter(|tx| tx.status == OutboundTxStatus::Pending)
+                        .count();
+                    info!(
+                        "📤 Restored {} outbound transactions ({} pending) from {}",
+                        ledger.len(),
+                        pending,
+                        path.display()
+                    );
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(Self {
+            chain,
+            fee_estimator,
+            config,
+            nonces: Mutex::new(HashMap::new()),
+            ledger: StdMutex::new(ledger),
+        })
+    }
+
+    /// Outbound transaction settings
+    pub fn config(&self) -> &OutboundTxConfig {
+        &self.config
+    }
+
+    /// Transaction with the internal id or any submission hash `key`
+    pub fn get(&self, key: &str) -> Option<OutboundTx> {
+        let ledger = self.ledger.lock().unwrap();
+        ledger.iter().find(|tx| tx.matches(key)).cloned()
+    }
+
+    /// Transactions not mined yet, oldest first
+    pub fn pending(&self) -> Vec<OutboundTx> {
+        let ledger = self.ledger.lock().unwrap();
+        ledger
+            .iter()
+            .filter(|tx| tx.status == OutboundTxStatus::Pending)
+            .cloned()
+            .collect()
+    }
+
+    /// Sign `request` with the active paymaster key and send it at the next nonce
+    pub async fn send(
+        &self,
+        signer: &Mutex<SignerManager>,
+        request: OutboundRequest,
+    ) -> Result<OutboundTx, PaymasterError> {
+        let mut nonces = self.nonces.lock().await;
+        let mut signer = signer.lock().await;
+        let from = Address::from_slice(signer.signer_address().as_bytes());
+
+        let on_chain = self.chain.transaction_count(from).await?;
+        let tracked = self
+            .pending()
+            .iter()
+            .filter(|tx| tx.from == from)
+            .map(|tx| tx.nonce + 1)
+            .max()
+            .unwrap_or_default();
+        let nonce = on_chain
+            .max(tracked)
+            .max(nonces.get(&from).copied().unwrap_or_default());
+
+        let (_, block) = self.chain.latest_block().await?;
+        let (fees, _base_fee) =
+            self.fee_estimator.latest_bundle_fees().await.map_err(|e| {
+                PaymasterError::ChainError(format!("Failed to estimate fees: {}", e))
+            })?;
+
+        let mut tx = OutboundTx {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind: request.kind,
+            chain_id: self.chain.chain_id(),
+            from,
+            to: request.to,
+            value: request.value,
+            data: request.data,
+            nonce,
+            gas_limit: request.gas_limit,
+            max_fee_per_gas: fees.max_fee_per_gas,
+            max_priority_fee_per_gas: fees.max_priority_fee_per_gas,
+            tx_hash: B256::ZERO,
+            replaced: Vec::new(),
+            status: OutboundTxStatus::Pending,
+            submitted_at: unix_now(),
+            sent_at_block: block,
+            block_number: None,
+        };
+        tx.tx_hash = self.submit(&mut signer, &tx).await?;
+        nonces.insert(from, nonce + 1);
+        drop(signer);
+
+        info!(
+            "📤 Sent {} transaction {} ({}) with nonce {}",
+            tx.kind, tx.tx_hash, tx.id, tx.nonce
+        );
+        self.update(tx.clone());
+        Ok(tx)
+    }
+
+    /// Settle mined or dropped transactions, and replace the ones unmined for
+    /// `stuck_after_blocks` with higher fees
+    pub async fn poll(&self, signer: &Mutex<SignerManager>) -> Result<(), PaymasterError> {
+        // No new transaction takes a nonce while replacements are signed
+        let _nonces = self.nonces.lock().await;
+        let pending = self.pending();
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let (block_hash, block) = self.chain.latest_block().await?;
+        for mut tx in pending {
+            if self.settle(&mut tx).await? {
+                info!(
+                    "📤 Outbound transaction {} ({}) is {:?}",
+                    tx.tx_hash, tx.id, tx.status
+                );
+                self.update(tx);
+                continue;
+            }
+            if block.saturating_sub(tx.sent_at_block) < self.config.stuck_after_blocks
+                || tx.replaced.len() >= self.config.max_replacements as usize
+            {
+                continue;
+            }
+            if let Some(replacement) = self.replace(signer, &tx, block_hash, block).await {
+                self.update(replacement);
+            }
+        }
+        Ok(())
+    }
+
+    /// Poll until the transaction `id` is no longer pending or `timeout` passes
+    pub async fn wait(
+        &self,
+        signer: &Mutex<SignerManager>,
+        id: &str,
+        timeout: Duration,
+    ) -> Result<OutboundTx, PaymasterError> {
+        let deadline = Instant::now() + timeout;
+        let interval = Duration::from_millis(self.config.poll_interval_ms.max(1));
+        loop {
+            self.poll(signer).await?;
+            let tx = self.get(id).ok_or_else(|| {
+                PaymasterError::InvalidRequest(format!("Unknown outbound transaction {}", id))
+            })?;
+            if tx.status != OutboundTxStatus::Pending {
+                return Ok(tx);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(PaymasterError::ChainError(format!(
+                    "Transaction {} not mined within {}s; pm_getOutboundTx {} keeps tracking it",
+                    tx.tx_hash,
+                    timeout.as_secs(),
+                    tx.id
+                )));
+            }
+            tokio::time::sleep(interval.min(deadline - now)).await;
+        }
+    }
+
+    /// Poll pending transactions every `poll_interval_ms` in the background
+    pub fn spawn(self: &Arc<Self>, signer: Arc<Mutex<SignerManager>>) -> JoinHandle<()> {
+        let manager = self.clone();
+        let interval = Duration::from_millis(self.config.poll_interval_ms.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = manager.poll(&signer).await {
+                    warn!("Failed to check outbound transactions: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Mark `tx` mined, reverted or dropped; returns whether it settled
+    async fn settle(&self, tx: &mut OutboundTx) -> Result<bool, PaymasterError> {
+        // Read the nonce first, so a transaction mined in between still has a receipt
+        let on_chain = self.chain.transaction_count(tx.from).await?;
+        for hash in std::iter::once(tx.tx_hash).chain(tx.replaced.clone()) {
+            if let Some(receipt) = self.chain.receipt(hash).await? {
+                tx.tx_hash = hash;
+                tx.block_number = Some(receipt.block_number);
+                tx.status = if receipt.success {
+                    OutboundTxStatus::Mined
+                } else {
+                    OutboundTxStatus::Reverted
+                };
+                return Ok(true);
+            }
+        }
+        if on_chain > tx.nonce {
+            warn!(
+                "Nonce {} of outbound transaction {} was used by another transaction",
+                tx.nonce, tx.id
+            );
+            tx.status = OutboundTxStatus::Dropped;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Re-send `tx` at the same nonce with fees raised by `fee_bump_percent`
+    /// and at least the current ones
+    async fn replace(
+        &self,
+        signer: &Mutex<SignerManager>,
+        tx: &OutboundTx,
+        block_hash: B256,
+        block: u64,
+    ) -> Option<OutboundTx> {
+        let mut signer = signer.lock().await;
+        if signer.signer_address().as_bytes() != tx.from.as_slice() {
+            warn!(
+                "Outbound transaction {} is stuck but {} is no longer the active key",
+                tx.id, tx.from
+            );
+            return None;
+        }
+
+        let min_fees = tx.fees().increase_by_percent(self.config.fee_bump_percent);
+        let fees = match self
+            .fee_estimator
+            .required_bundle_fees(block_hash, Some(min_fees))
+            .await
+        {
+            Ok((fees, _base_fee)) => fees,
+            Err(e) => {
+                warn!("Failed to estimate fees to replace {}: {}", tx.id, e);
+                return None;
+            }
+        };
+
+        let mut replacement = OutboundTx {
+            max_fee_per_gas: fees.max_fee_per_gas,
+            max_priority_fee_per_gas: fees.max_priority_fee_per_gas,
+            sent_at_block: block,
+            ..tx.clone()
+        };
+        match self.submit(&mut signer, &replacement).await {
+            Ok(tx_hash) => {
+                info!(
+                    "📤 Replaced stuck transaction {} with {} ({}, max fee {} wei)",
+                    tx.tx_hash, tx_hash, tx.id, fees.max_fee_per_gas
+                );
+                replacement.replaced.push(tx.tx_hash);
+                replacement.tx_hash = tx_hash;
+                Some(replacement)
+            }
+            Err(e) => {
+                warn!("Failed to replace stuck transaction {}: {}", tx.id, e);
+                None
+            }
+        }
+    }
+
+    /// Sign `tx` with the active key and submit it
+    async fn submit(
+        &self,
+        signer: &mut SignerManager,
+        tx: &OutboundTx,
+    ) -> Result<B256, PaymasterError> {
+        let request: TypedTransaction = Eip1559TransactionRequest::new()
+            .from(H160::from_slice(tx.from.as_slice()))
+            .to(H160::from_slice(tx.to.as_slice()))
+            .value(ethers::types::U256::from_big_endian(
+                &tx.value.to_be_bytes::<32>(),
+            ))
+            .data(tx.data.to_vec())
+            .nonce(tx.nonce)
+            .gas(tx.gas_limit)
+            .max_fee_per_gas(tx.max_fee_per_gas)
+            .max_priority_fee_per_gas(tx.max_priority_fee_per_gas)
+            .chain_id(tx.chain_id)
+            .into();
+
+        let signature = signer.sign_hash(request.sighash().0).await?;
+        let raw_tx = request.rlp_signed(&signature);
+        self.chain
+            .send_raw_transaction(Bytes::from(raw_tx.to_vec()))
+            .await
+    }
+
+    /// Store `tx` over its earlier version, dropping the oldest finished
+    /// transactions beyond `history_size`
+    fn update(&self, tx: OutboundTx) {
+        let mut ledger = self.ledger.lock().unwrap();
+        match ledger.iter_mut().find(|entry| entry.id == tx.id) {
+            Some(entry) => *entry = tx,
+            None => ledger.push(tx),
+        }
+
+        let finished = ledger
+            .iter()
+            .filter(|tx| tx.status != OutboundTxStatus::Pending)
+            .count();
+        let mut excess = finished.saturating_sub(self.config.history_size);
+        ledger.retain(|tx| {
+            if excess > 0 && tx.status != OutboundTxStatus::Pending {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+
+        if let Err(e) = self.persist(&ledger) {
+            warn!("Failed to save outbound transactions: {}", e);
+        }
+    }
+
+    /// Write the ledger to a temporary file and rename it over the ledger file
+    fn persist(&self, ledger: &[OutboundTx]) -> io::Result<()> {
+        let Some(path) = &self.config.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let data = serde_json::to_vec(ledger)?;
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex as StdMutex},
+        time::Duration,
+    };
+
+    use alloy_primitives::{address, keccak256, Address, Bytes, B256, U256};
+    use async_trait::async_trait;
+    use ethers::{types::transaction::eip2718::TypedTransaction, utils::rlp::Rlp};
+    use rundler_provider::FeeEstimator;
+    use rundler_types::GasFees;
+    use secrecy::SecretString;
+    use tempfile::tempdir;
+    use tokio::sync::Mutex;
+
+    use super::*;
+
+    const SIGNER_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const ENTRY_POINT: Address = address!("0000000071727De22E5E9d8BAf0edAc6f37da032");
+
+    /// Chain that mines only what the test tells it to
+    #[derive(Default)]
+    struct FakeChain {
+        block: StdMutex<u64>,
+        /// Mined transactions of the paymaster account
+        mined_nonces: StdMutex<u64>,
+        receipts: StdMutex<HashMap<B256, OutboundReceipt>>,
+        sent: StdMutex<Vec<(B256, TypedTransaction)>>,
+    }
+
+    impl FakeChain {
+        fn new(block: u64, nonce: u64) -> Arc<Self> {
+            Arc::new(Self {
+                block: StdMutex::new(block),
+                mined_nonces: StdMutex::new(nonce),
+                ..Default::default()
+            })
+        }
+
+        fn advance(&self, blocks: u64) {
+            *self.block.lock().unwrap() += blocks;
+        }
+
+        fn mine(&self, tx_hash: B256) {
+            let block = *self.block.lock().unwrap();
+            self.receipts.lock().unwrap().insert(
+                tx_hash,
+                OutboundReceipt {
+                    block_number: block,
+                    success: true,
+                },
+            );
+            *self.mined_nonces.lock().unwrap() += 1;
+        }
+
+        fn sent(&self) -> Vec<(B256, TypedTransaction)> {
+            self.sent.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl OutboundChain for FakeChain {
+        fn chain_id(&self) -> u64 {
+            31337
+        }
+
+        async fn latest_block(&self) -> Result<(B256, u64), PaymasterError> {
+            let block = *self.block.lock().unwrap();
+            Ok((B256::left_padding_from(&block.to_be_bytes()), block))
+        }
+
+        async fn transaction_count(&self, _account: Address) -> Result<u64, PaymasterError> {
+            Ok(*self.mined_nonces.lock().unwrap())
+        }
+
+        async fn send_raw_transaction(&self, tx: Bytes) -> Result<B256, PaymasterError> {
+            // Let other senders run between the nonce read and the submission
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            let (decoded, _) = TypedTransaction::decode_signed(&Rlp::new(&tx)).unwrap();
+            let tx_hash = keccak256(&tx);
+            self.sent.lock().unwrap().push((tx_hash, decoded));
+            Ok(tx_hash)
+        }
+
+        async fn receipt(&self, tx_hash: B256) -> Result<Option<OutboundReceipt>, PaymasterError> {
+            Ok(self.receipts.lock().unwrap().get(&tx_hash).copied())
+        }
+    }
+
+    /// Estimator quoting whatever fees the test sets
+    struct FakeFees(StdMutex<GasFees>);
+
+    impl FakeFees {
+        fn new(max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> Arc<Self> {
+            Arc::new(Self(StdMutex::new(GasFees {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            })))
+        }
+    }
+
+    #[async_trait]
+    impl FeeEstimator for FakeFees {
+        async fn required_bundle_fees(
+            &self,
+            _block_hash: B256,
+            min_fees: Option<GasFees>,
+        ) -> anyhow::Result<(GasFees, u128)> {
+            let current = *self.0.lock().unwrap();
+            let min_fees = min_fees.unwrap_or_default();
+            Ok((
+                GasFees {
+                    max_fee_per_gas: current.max_fee_per_gas.max(min_fees.max_fee_per_gas),
+                    max_priority_fee_per_gas: current
+                        .max_priority_fee_per_gas
+                        .max(min_fees.max_priority_fee_per_gas),
+                },
+                0,
+            ))
+        }
+
+        async fn latest_bundle_fees(&self) -> anyhow::Result<(GasFees, u128)> {
+            Ok((*self.0.lock().unwrap(), 0))
+        }
+
+        fn required_op_fees(&self, bundle_fees: GasFees) -> GasFees {
+            bundle_fees
+        }
+    }
+
+    fn signer() -> Arc<Mutex<SignerManager>> {
+        Arc::new(Mutex::new(
+            SignerManager::new(SecretString::new(SIGNER_KEY.to_string().into())).unwrap(),
+        ))
+    }
+
+    fn manager(
+        chain: &Arc<FakeChain>,
+        fees: &Arc<FakeFees>,
+        config: OutboundTxConfig,
+    ) -> OutboundTxManager {
+        OutboundTxManager::new(chain.clone(), fees.clone(), config).unwrap()
+    }
+
+    fn deposit(amount: u64) -> OutboundRequest {
+        OutboundRequest {
+            kind: "deposit".to_string(),
+            to: ENTRY_POINT,
+            value: U256::from(amount),
+            data: Bytes::from_static(&[0xb7, 0x60, 0xfa, 0xf9]),
+            gas_limit: 100_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stuck_transaction_replaced_with_higher_fees() {
+        let chain = FakeChain::new(100, 7);
+        let fees = FakeFees::new(2_000_000_000, 1_000_000_000);
+        let config = OutboundTxConfig {
+            stuck_after_blocks: 3,
+            fee_bump_percent: 20,
+            ..Default::default()
+        };
+        let manager = manager(&chain, &fees, config);
+        let signer = signer();
+
+        let original = manager.send(&signer, deposit(1)).await.unwrap();
+        assert_eq!(original.nonce, 7);
+        assert_eq!(original.sent_at_block, 100);
+
+        // Not stuck yet
+        chain.advance(2);
+        manager.poll(&signer).await.unwrap();
+        assert_eq!(chain.sent().len(), 1);
+
+        chain.advance(1);
+        manager.poll(&signer).await.unwrap();
+        let sent = chain.sent();
+        assert_eq!(sent.len(), 2);
+        let (replacement_hash, replacement) = &sent[1];
+        assert_eq!(replacement.nonce().unwrap().as_u64(), 7);
+        let TypedTransaction::Eip1559(replacement) = replacement else {
+            panic!("unexpected transaction type");
+        };
+        assert_eq!(
+            replacement.max_fee_per_gas.unwrap().as_u128(),
+            2_400_000_000
+        );
+        assert_eq!(
+            replacement.max_priority_fee_per_gas.unwrap().as_u128(),
+            1_200_000_000
+        );
+
+        let tx = manager.get(&original.id).unwrap();
+        assert_eq!(tx.tx_hash, *replacement_hash);
+        assert_eq!(tx.replaced, vec![original.tx_hash]);
+        assert_eq!(tx.sent_at_block, 103);
+        assert_eq!(tx.status, OutboundTxStatus::Pending);
+        // The first hash still finds the transaction
+        assert_eq!(
+            manager.get(&original.tx_hash.to_string()).unwrap().id,
+            tx.id
+        );
+
+        // Network fees rose beyond the bump: the next replacement follows them
+        *fees.0.lock().unwrap() = GasFees {
+            max_fee_per_gas: 5_000_000_000,
+            max_priority_fee_per_gas: 2_000_000_000,
+        };
+        chain.advance(3);
+        manager.poll(&signer).await.unwrap();
+        let (second_hash, second) = chain.sent().pop().unwrap();
+        let TypedTransaction::Eip1559(second) = second else {
+            panic!("unexpected transaction type");
+        };
+        assert_eq!(second.max_fee_per_gas.unwrap().as_u128(), 5_000_000_000);
+        assert_eq!(
+            second.max_priority_fee_per_gas.unwrap().as_u128(),
+            2_000_000_000
+        );
+
+        chain.mine(second_hash);
+        manager.poll(&signer).await.unwrap();
+        let tx = manager.get(&tx.id).unwrap();
+        assert_eq!(tx.status, OutboundTxStatus::Mined);
+        assert_eq!(tx.tx_hash, second_hash);
+        assert_eq!(tx.block_number, Some(106));
+        assert_eq!(chain.sent().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_replacement_stops_at_max_replacements() {
+        let chain = FakeChain::new(1, 0);
+        let fees = FakeFees::new(2_000_000_000, 1_000_000_000);
+        let config = OutboundTxConfig {
+            stuck_after_blocks: 1,
+            max_replacements: 2,
+            ..Default::default()
+        };
+        let manager = manager(&chain, &fees, config);
+        let signer = signer();
+
+        manager.send(&signer, deposit(1)).await.unwrap();
+        for _ in 0..5 {
+            chain.advance(1);
+            manager.poll(&signer).await.unwrap();
+        }
+        assert_eq!(chain.sent().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_restart_resumes_tracking_without_resending() {
+        let dir = tempdir().unwrap();
+        let config = OutboundTxConfig {
+            stuck_after_blocks: 5,
+            path: Some(dir.path().join("outbound-txs.json")),
+            ..Default::default()
+        };
+        let chain = FakeChain::new(50, 3);
+        let fees = FakeFees::new(2_000_000_000, 1_000_000_000);
+        let signer = signer();
+
+        let before = manager(&chain, &fees, config.clone());
+        let pending = before.send(&signer, deposit(1)).await.unwrap();
+        drop(before);
+
+        let after = manager(&chain, &fees, config);
+        assert_eq!(after.get(&pending.id), Some(pending.clone()));
+        assert_eq!(after.pending(), vec![pending.clone()]);
+
+        // Still waiting to be mined: tracked, not sent again
+        chain.advance(1);
+        after.poll(&signer).await.unwrap();
+        assert_eq!(chain.sent().len(), 1);
+
+        // The chain does not count the pending nonce yet; the ledger does
+        let next = after.send(&signer, deposit(2)).await.unwrap();
+        assert_eq!(next.nonce, pending.nonce + 1);
+
+        chain.mine(pending.tx_hash);
+        after.poll(&signer).await.unwrap();
+        assert_eq!(
+            after.get(&pending.id).unwrap().status,
+            OutboundTxStatus::Mined
+        );
+        assert_eq!(after.pending(), vec![next]);
+        assert_eq!(chain.sent().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_senders_get_distinct_nonces() {
+        let chain = FakeChain::new(1, 11);
+        let fees = FakeFees::new(2_000_000_000, 1_000_000_000);
+        let manager = Arc::new(manager(&chain, &fees, OutboundTxConfig::default()));
+        let signer = signer();
+
+        let sends: Vec<_> = (0..8)
+            .map(|i| {
+                let manager = manager.clone();
+                let signer = signer.clone();
+                tokio::spawn(async move { manager.send(&signer, deposit(i)).await.unwrap() })
+            })
+            .collect();
+        let mut nonces = Vec::new();
+        for send in sends {
+            nonces.push(send.await.unwrap().nonce);
+        }
+
+        nonces.sort_unstable();
+        assert_eq!(nonces, (11..19).collect::<Vec<_>>());
+        let mut sent: Vec<u64> = chain
+            .sent()
+            .iter()
+            .map(|(_, tx)| tx.nonce().unwrap().as_u64())
+            .collect();
+        sent.sort_unstable();
+        assert_eq!(sent, nonces);
+    }
+
+    #[tokio::test]
+    async fn test_wait_reports_mined_and_dropped_transactions() {
+        let chain = FakeChain::new(1, 0);
+        let fees = FakeFees::new(2_000_000_000, 1_000_000_000);
+        let config = OutboundTxConfig {
+            poll_interval_ms: 10,
+            ..Default::default()
+        };
+        let manager = manager(&chain, &fees, config);
+        let signer = signer();
+
+        let tx = manager.send(&signer, deposit(1)).await.unwrap();
+        let err = manager
+            .wait(&signer, &tx.id, Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(&tx.id), "{}", err);
+
+        chain.mine(tx.tx_hash);
+        let mined = manager
+            .wait(&signer, &tx.id, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(mined.status, OutboundTxStatus::Mined);
+
+        // Another transaction took the nonce
+        let tx = manager.send(&signer, deposit(2)).await.unwrap();
+        *chain.mined_nonces.lock().unwrap() += 1;
+        let dropped = manager
+            .wait(&signer, &tx.id, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(dropped.status, OutboundTxStatus::Dropped);
+    }
+}
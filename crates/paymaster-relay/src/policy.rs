@@ -1,28 +1,207 @@
 // paymaster-relay/src/policy.rs
 // This file will implement the PolicyEngine for sponsorship rules.
 
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
 
-use alloy_primitives::Address;
+use alloy_primitives::{Address, U256};
 use rundler_types::{UserOperation, UserOperationVariant};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::error::PaymasterError;
+use crate::{
+    authorizer::{AuthorizationRequest, AuthorizedCall, AuthorizerConfig},
+    call_data::{decode_calls, ExecuteFunction, FunctionSelector},
+    error::PaymasterError,
+    usage::{DeploymentLimit, UsageGranularity},
+};
+
+/// Handling of callData no configured execute function decodes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UndecodableCallData {
+    /// Refuse to sponsor the operation
+    #[default]
+    Reject,
+    /// Sponsor it on the sender rules alone
+    SenderOnly,
+}
+
+/// A policy rule an operation broke, named after its policy field
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PolicyRejection {
+    /// Rule that rejected the operation, e.g. `senders` or `max_token_amounts`
+    pub rule: &'static str,
+    /// Reason returned to the client
+    pub message: String,
+}
+
+impl PolicyRejection {
+    fn new(rule: &'static str, message: String) -> Self {
+        Self { rule, message }
+    }
+}
+
+impl From<PolicyRejection> for PaymasterError {
+    fn from(rejection: PolicyRejection) -> Self {
+        PaymasterError::PolicyRejected(rejection.message)
+    }
+}
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct Policy {
     pub senders: Vec<Address>,
+    /// ERC-20 tokens gas may be paid in; every configured token when unset
+    #[serde(default)]
+    pub tokens: Option<Vec<Address>>,
+    /// Most a single operation may be charged per token, in token base units
+    #[serde(default)]
+    pub max_token_amounts: HashMap<Address, U256>,
+    /// Contracts the operation's calls may target; any target when unset
+    #[serde(default)]
+    pub allowed_targets: Option<Vec<Address>>,
+    /// Functions the operation's calls may invoke, as selectors or signatures;
+    /// any function when unset
+    #[serde(default)]
+    pub allowed_selectors: Option<Vec<FunctionSelector>>,
+    /// Account execute functions the callData is decoded with
+    #[serde(default = "ExecuteFunction::defaults")]
+    pub execute_functions: Vec<ExecuteFunction>,
+    /// Handling of callData none of `execute_functions` decodes
+    #[serde(default)]
+    pub undecodable_call_data: UndecodableCallData,
+    /// Webhook with the final say on sponsoring the operations this policy allows
+    #[serde(default)]
+    pub authorizer: Option<AuthorizerConfig>,
+    /// Factories an operation deploying its account may use; any factory when unset
+    #[serde(default)]
+    pub allowed_factories: Option<Vec<Address>>,
+    /// Quotas on sponsoring operations that deploy their account
+    #[serde(default)]
+    pub deployments: DeploymentQuotas,
     // We can add more policy rules here later, e.g.,
-    // target_contracts: Vec<Address>,
     // max_gas_limit: u64,
 }
 
+impl Policy {
+    /// Check the calls `call_data` makes against the allowed targets and
+    /// selectors; every call of a batch must pass
+    fn check_calls(&self, policy_id: &str, call_data: &[u8]) -> Result<(), PolicyRejection> {
+        if self.allowed_targets.is_none() && self.allowed_selectors.is_none() {
+            return Ok(());
+        }
+        let Some(calls) = decode_calls(&self.execute_functions, call_data) else {
+            return match self.undecodable_call_data {
+                UndecodableCallData::SenderOnly => Ok(()),
+                UndecodableCallData::Reject => Err(PolicyRejection::new(
+                    "undecodable_call_data",
+                    format!(
+                        "CallData does not decode as a call of an account execute function; \
+                         policy {} only sponsors allowed calls.",
+                        policy_id
+                    ),
+                )),
+            };
+        };
+
+        let count = calls.len();
+        for (index, call) in calls.iter().enumerate() {
+            let position = index + 1;
+            if let Some(targets) = &self.allowed_targets {
+                if !targets.contains(&call.target) {
+                    return Err(PolicyRejection::new(
+                        "allowed_targets",
+                        format!(
+                            "Call {} of {} targets {}, which policy {} does not allow.",
+                            position, count, call.target, policy_id
+                        ),
+                    ));
+                }
+            }
+            if let Some(selectors) = &self.allowed_selectors {
+                match call.selector() {
+                    Some(selector) if selectors.iter().any(|allowed| allowed.0 == selector) => {}
+                    Some(selector) => {
+                        return Err(PolicyRejection::new(
+                            "allowed_selectors",
+                            format!(
+                                "Call {} of {} invokes {} on {}, which policy {} does not allow.",
+                                position, count, selector, call.target, policy_id
+                            ),
+                        ))
+                    }
+                    None => {
+                        return Err(PolicyRejection::new(
+                            "allowed_selectors",
+                            format!(
+                                "Call {} of {} to {} invokes no function, which policy {} does not allow.",
+                                position, count, call.target, policy_id
+                            ),
+                        ))
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Most account deployments sponsored per UTC hour and per UTC day
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct DeploymentQuota {
+    #[serde(default)]
+    pub per_hour: Option<u64>,
+    #[serde(default)]
+    pub per_day: Option<u64>,
+}
+
+impl DeploymentQuota {
+    fn limits(&self, factory: Option<Address>) -> impl Iterator<Item = DeploymentLimit> {
+        [
+            (UsageGranularity::Hour, self.per_hour),
+            (UsageGranularity::Day, self.per_day),
+        ]
+        .into_iter()
+        .filter_map(move |(window, max)| {
+            max.map(|max| DeploymentLimit {
+                factory,
+                window,
+                max,
+            })
+        })
+    }
+}
+
+/// Deployment quotas of a policy: `per_hour`/`per_day` apply to each factory
+/// unless `factories` overrides them, `total` to all factories together
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct DeploymentQuotas {
+    #[serde(flatten)]
+    pub per_factory: DeploymentQuota,
+    #[serde(default)]
+    pub factories: HashMap<Address, DeploymentQuota>,
+    #[serde(default)]
+    pub total: DeploymentQuota,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct PolicyConfig {
     #[serde(flatten)]
     pub policies: HashMap<String, Policy>,
 }
 
+/// Allowlist size and token caps of one loaded policy, for the dashboard
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PolicySummary {
+    pub id: String,
+    pub sender_count: usize,
+    /// Tokens gas may be paid in; every configured token when unset
+    pub tokens: Option<Vec<Address>>,
+    /// Most a single operation may be charged per token
+    pub max_token_amounts: BTreeMap<Address, U256>,
+}
+
 #[derive(Clone, Debug)]
 pub struct PolicyEngine {
     config: PolicyConfig,
@@ -39,24 +218,165 @@ impl PolicyEngine {
         Ok(Self { config })
     }
 
-    pub fn check_policy(&self, user_op: &UserOperationVariant) -> Result<(), PaymasterError> {
+    /// Number of loaded policies
+    pub fn policy_count(&self) -> usize {
+        self.config.policies.len()
+    }
+
+    /// Whether a policy with the given id was loaded
+    pub fn has_policy(&self, policy_id: &str) -> bool {
+        self.config.policies.contains_key(policy_id)
+    }
+
+    /// Summaries of the loaded policies, ordered by id
+    pub fn summaries(&self) -> Vec<PolicySummary> {
+        let mut summaries: Vec<_> = self
+            .config
+            .policies
+            .iter()
+            .map(|(id, policy)| PolicySummary {
+                id: id.clone(),
+                sender_count: policy.senders.len(),
+                tokens: policy.tokens.clone(),
+                max_token_amounts: policy.max_token_amounts.clone().into_iter().collect(),
+            })
+            .collect();
+        summaries.sort_by(|a, b| a.id.cmp(&b.id));
+        summaries
+    }
+
+    /// Check `user_op` against the policies, returning the id of the policy that allowed it
+    pub fn check_policy(&self, user_op: &UserOperationVariant) -> Result<String, PaymasterError> {
+        Ok(self.evaluate(user_op)?)
+    }
+
+    /// [`Self::check_policy`], naming the rule that rejected `user_op`
+    pub fn evaluate(&self, user_op: &UserOperationVariant) -> Result<String, PolicyRejection> {
         // For now, we use a single, hardcoded "default" policy.
         // This can be extended to select a policy based on the RPC input.
-        if let Some(policy) = self.config.policies.get("default") {
+        let policy_id = "default";
+        if let Some(policy) = self.config.policies.get(policy_id) {
             if !policy.senders.contains(&user_op.sender()) {
-                return Err(PaymasterError::PolicyRejected(format!(
-                    "Sender {} is not in the allowlist.",
-                    user_op.sender()
-                )));
+                return Err(PolicyRejection::new(
+                    "senders",
+                    format!("Sender {} is not in the allowlist.", user_op.sender()),
+                ));
+            }
+            if let (Some(factory), Some(factories)) = (user_op.factory(), &policy.allowed_factories)
+            {
+                if !factories.contains(&factory) {
+                    return Err(PolicyRejection::new(
+                        "allowed_factories",
+                        format!(
+                            "Factory {} is not allowed by policy {}.",
+                            factory, policy_id
+                        ),
+                    ));
+                }
             }
+            policy.check_calls(policy_id, user_op.call_data())?;
         } else {
-            return Err(PaymasterError::PolicyRejected(
+            return Err(PolicyRejection::new(
+                "policy",
                 "Default policy not found.".to_string(),
             ));
         }
 
         // Add more policy checks here as needed.
 
+        Ok(policy_id.to_string())
+    }
+
+    /// Authorizer of the policy `policy_id` and the request describing
+    /// `user_op` to it; `None` when the policy has no authorizer
+    pub fn authorization_request(
+        &self,
+        policy_id: &str,
+        user_op: &UserOperationVariant,
+        entry_point: Address,
+    ) -> Option<(&AuthorizerConfig, AuthorizationRequest)> {
+        let policy = self.config.policies.get(policy_id)?;
+        let authorizer = policy.authorizer.as_ref()?;
+        let calls = decode_calls(&policy.execute_functions, user_op.call_data()).map(|calls| {
+            calls
+                .iter()
+                .map(|call| AuthorizedCall {
+                    selector: call.selector(),
+                    target: call.target,
+                })
+                .collect()
+        });
+        Some((
+            authorizer,
+            AuthorizationRequest {
+                calls,
+                chain_id: user_op.chain_id(),
+                entry_point,
+                max_gas_cost: user_op.max_gas_cost(),
+                policy_id: policy_id.to_string(),
+                sender: user_op.sender(),
+                user_op_hash: user_op.hash(),
+            },
+        ))
+    }
+
+    /// Quotas on deployments through `factory` under the policy `policy_id`,
+    /// the factory's own before the ones across all factories
+    pub fn deployment_limits(&self, policy_id: &str, factory: Address) -> Vec<DeploymentLimit> {
+        let Some(policy) = self.config.policies.get(policy_id) else {
+            return Vec::new();
+        };
+        let quotas = &policy.deployments;
+        let per_factory = quotas
+            .factories
+            .get(&factory)
+            .unwrap_or(&quotas.per_factory);
+        per_factory
+            .limits(Some(factory))
+            .chain(quotas.total.limits(None))
+            .collect()
+    }
+
+    /// Check paying `amount` of `token` for gas under the policy `policy_id`
+    pub fn check_token_payment(
+        &self,
+        policy_id: &str,
+        token: Address,
+        amount: U256,
+    ) -> Result<(), PaymasterError> {
+        Ok(self.evaluate_token_payment(policy_id, token, amount)?)
+    }
+
+    /// [`Self::check_token_payment`], naming the rule that rejected the payment
+    pub fn evaluate_token_payment(
+        &self,
+        policy_id: &str,
+        token: Address,
+        amount: U256,
+    ) -> Result<(), PolicyRejection> {
+        let policy = self.config.policies.get(policy_id).ok_or_else(|| {
+            PolicyRejection::new("policy", format!("Policy {} not found.", policy_id))
+        })?;
+
+        if let Some(tokens) = &policy.tokens {
+            if !tokens.contains(&token) {
+                return Err(PolicyRejection::new(
+                    "tokens",
+                    format!("Token {} is not allowed by policy {}.", token, policy_id),
+                ));
+            }
+        }
+        if let Some(max_amount) = policy.max_token_amounts.get(&token) {
+            if amount > *max_amount {
+                return Err(PolicyRejection::new(
+                    "max_token_amounts",
+                    format!(
+                        "Token amount {} exceeds the maximum of {} for token {}.",
+                        amount, max_amount, token
+                    ),
+                ));
+            }
+        }
         Ok(())
     }
 }
@@ -70,6 +390,13 @@ mod tests {
     use super::*;
 
     fn create_test_user_op(sender: Address) -> UserOperationVariant {
+        create_test_user_op_with_call_data(sender, Vec::new())
+    }
+
+    fn create_test_user_op_with_call_data(
+        sender: Address,
+        call_data: Vec<u8>,
+    ) -> UserOperationVariant {
         use alloy_primitives::{Bytes, U256};
         use rundler_types::{chain::ChainSpec, v0_6};
 
@@ -80,7 +407,7 @@ mod tests {
                 sender,
                 nonce: U256::ZERO,
                 init_code: Bytes::new(),
-                call_data: Bytes::new(),
+                call_data: call_data.into(),
                 call_gas_limit: 100_000,
                 verification_gas_limit: 100_000,
                 pre_verification_gas: 21_000,
@@ -147,11 +474,15 @@ senders = ["{}"]"#,
 
         // 1. Test with allowed sender
         let user_op_allowed = create_test_user_op(allowed_sender);
-        assert!(engine.check_policy(&user_op_allowed).is_ok());
+        assert_eq!(engine.check_policy(&user_op_allowed).unwrap(), "default");
 
         // 2. Test with disallowed sender
         let user_op_disallowed = create_test_user_op(disallowed_sender);
         assert!(engine.check_policy(&user_op_disallowed).is_err());
+        assert_eq!(
+            engine.evaluate(&user_op_disallowed).unwrap_err().rule,
+            "senders"
+        );
 
         // 3. Test with no "default" policy in file
         let no_default_path = dir.path().join("no_default.toml");
@@ -166,4 +497,248 @@ senders = ["{}"]"#,
         let engine_no_default = PolicyEngine::new(&no_default_path).unwrap();
         assert!(engine_no_default.check_policy(&user_op_allowed).is_err());
     }
+
+    #[test]
+    fn test_check_token_payment() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("policy.toml");
+        let usdc = Address::from_str("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48").unwrap();
+        let dai = Address::from_str("0x6b175474e89094c44da98b954eedeac495271d0f").unwrap();
+        std::fs::write(
+            &file_path,
+            format!(
+                r#"[default]
+senders = []
+tokens = ["{usdc}"]
+max_token_amounts = {{ "{usdc}" = "1000000" }}
+
+[open]
+senders = []
+"#
+            ),
+        )
+        .unwrap();
+        let engine = PolicyEngine::new(&file_path).unwrap();
+
+        engine
+            .check_token_payment("default", usdc, U256::from(1_000_000))
+            .unwrap();
+        assert!(engine
+            .check_token_payment("default", usdc, U256::from(1_000_001))
+            .is_err());
+        assert!(engine
+            .check_token_payment("default", dai, U256::from(1))
+            .is_err());
+
+        // Without a token list every token is allowed, without a maximum any amount
+        engine.check_token_payment("open", dai, U256::MAX).unwrap();
+
+        // Rejections name the rule that failed
+        let rule = |token, amount| {
+            engine
+                .evaluate_token_payment("default", token, amount)
+                .unwrap_err()
+                .rule
+        };
+        assert_eq!(rule(usdc, U256::from(1_000_001)), "max_token_amounts");
+        assert_eq!(rule(dai, U256::from(1)), "tokens");
+        assert_eq!(
+            engine
+                .evaluate_token_payment("missing", dai, U256::from(1))
+                .unwrap_err()
+                .rule,
+            "policy"
+        );
+    }
+
+    /// Calldata of `signature` called with `args`
+    fn encode_call(signature: &str, args: &[ethers::abi::Token]) -> Vec<u8> {
+        let mut data = alloy_primitives::keccak256(signature)[..4].to_vec();
+        data.extend(ethers::abi::encode(args));
+        data
+    }
+
+    fn token_address(address: Address) -> ethers::abi::Token {
+        ethers::abi::Token::Address(address.0 .0.into())
+    }
+
+    #[test]
+    fn test_call_rules_parsing() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("policy.toml");
+        std::fs::write(
+            &file_path,
+            r#"[default]
+senders = []
+allowed_targets = ["0x1111111111111111111111111111111111111111"]
+allowed_selectors = ["0xa9059cbb", "mint(address, uint256)"]
+execute_functions = ["executeCall(address,uint256,bytes)"]
+undecodable_call_data = "sender-only"
+
+[open]
+senders = []
+"#,
+        )
+        .unwrap();
+        let engine = PolicyEngine::new(&file_path).unwrap();
+
+        let policy = &engine.config.policies["default"];
+        assert_eq!(
+            policy.allowed_targets,
+            Some(vec![Address::repeat_byte(0x11)])
+        );
+        let selectors: Vec<String> = policy
+            .allowed_selectors
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|selector| selector.0.to_string())
+            .collect();
+        assert_eq!(selectors, ["0xa9059cbb", "0x40c10f19"]);
+        assert_eq!(policy.execute_functions.len(), 1);
+        assert_eq!(
+            policy.execute_functions[0].signature,
+            "executeCall(address,uint256,bytes)"
+        );
+        assert_eq!(
+            policy.undecodable_call_data,
+            UndecodableCallData::SenderOnly
+        );
+
+        // Without call rules every call is allowed, decoded with the standard functions
+        let open = &engine.config.policies["open"];
+        assert_eq!(open.allowed_targets, None);
+        assert_eq!(open.allowed_selectors, None);
+        assert_eq!(open.execute_functions, ExecuteFunction::defaults());
+        assert_eq!(open.undecodable_call_data, UndecodableCallData::Reject);
+
+        for (rule, expected) in [
+            (
+                "allowed_selectors = [\"0xa9059c\"]",
+                "Invalid selector 0xa9059c",
+            ),
+            (
+                "execute_functions = [\"execute(bytes32,bytes)\"]",
+                "Unsupported execute function execute(bytes32,bytes)",
+            ),
+            (
+                "undecodable_call_data = \"allow\"",
+                "unknown variant `allow`",
+            ),
+        ] {
+            std::fs::write(&file_path, format!("[default]\nsenders = []\n{}\n", rule)).unwrap();
+            let err = PolicyEngine::new(&file_path).unwrap_err().to_string();
+            assert!(err.contains(expected), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_check_call_rules() {
+        use ethers::abi::Token;
+
+        let sender = Address::repeat_byte(0x01);
+        let game = Address::repeat_byte(0x11);
+        let other = Address::repeat_byte(0x22);
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("policy.toml");
+        std::fs::write(
+            &file_path,
+            format!(
+                r#"[default]
+senders = ["{sender}"]
+allowed_targets = ["{game}"]
+allowed_selectors = ["transfer(address,uint256)", "mint(address,uint256)"]
+"#
+            ),
+        )
+        .unwrap();
+        let engine = PolicyEngine::new(&file_path).unwrap();
+        let check = |call_data: Vec<u8>| {
+            engine
+                .check_policy(&create_test_user_op_with_call_data(sender, call_data))
+                .map_err(|e| e.to_string())
+        };
+
+        let inner = |signature: &str| {
+            encode_call(signature, &[token_address(other), Token::Uint(1u64.into())])
+        };
+        let execute = |target: Address, data: Vec<u8>| {
+            encode_call(
+                "execute(address,uint256,bytes)",
+                &[
+                    token_address(target),
+                    Token::Uint(0u64.into()),
+                    Token::Bytes(data),
+                ],
+            )
+        };
+        let batch = |calls: Vec<(Address, Vec<u8>)>| {
+            let (targets, datas): (Vec<_>, Vec<_>) = calls
+                .into_iter()
+                .map(|(target, data)| (token_address(target), Token::Bytes(data)))
+                .unzip();
+            encode_call(
+                "executeBatch(address[],bytes[])",
+                &[Token::Array(targets), Token::Array(datas)],
+            )
+        };
+
+        // Allowed target and selector, alone or batched
+        assert_eq!(
+            check(execute(game, inner("transfer(address,uint256)"))).unwrap(),
+            "default"
+        );
+        check(batch(vec![
+            (game, inner("transfer(address,uint256)")),
+            (game, inner("mint(address,uint256)")),
+        ]))
+        .unwrap();
+
+        // Other targets and functions are refused
+        let err = check(execute(other, inner("transfer(address,uint256)"))).unwrap_err();
+        assert!(
+            err.contains(&format!("Call 1 of 1 targets {}", other)),
+            "{}",
+            err
+        );
+        let err = check(execute(game, inner("approve(address,uint256)"))).unwrap_err();
+        assert!(err.contains("Call 1 of 1 invokes 0x095ea7b3"), "{}", err);
+        let err = check(execute(game, Vec::new())).unwrap_err();
+        assert!(err.contains("invokes no function"), "{}", err);
+
+        // Every call of a batch must pass
+        let err = check(batch(vec![
+            (game, inner("mint(address,uint256)")),
+            (other, inner("transfer(address,uint256)")),
+        ]))
+        .unwrap_err();
+        assert!(err.contains("Call 2 of 2 targets"), "{}", err);
+
+        // CallData that is not an execute call is refused by default...
+        let direct = inner("transfer(address,uint256)");
+        let err = check(direct.clone()).unwrap_err();
+        assert!(err.contains("does not decode"), "{}", err);
+        assert!(check(Vec::new()).is_err());
+
+        // ...or left to the sender rules
+        std::fs::write(
+            &file_path,
+            format!(
+                r#"[default]
+senders = ["{sender}"]
+allowed_targets = ["{game}"]
+undecodable_call_data = "sender-only"
+"#
+            ),
+        )
+        .unwrap();
+        let engine = PolicyEngine::new(&file_path).unwrap();
+        let op = create_test_user_op_with_call_data(sender, direct);
+        assert_eq!(engine.check_policy(&op).unwrap(), "default");
+        let op = create_test_user_op_with_call_data(
+            sender,
+            execute(other, inner("transfer(address,uint256)")),
+        );
+        assert!(engine.check_policy(&op).is_err());
+    }
 }
@@ -0,0 +1,659 @@
+// paymaster-relay/src/policy_shadow.rs
+// Dry run of a policy change: while a candidate policy is loaded, sponsorships are evaluated
+// against it as well. Only the active policy is enforced; where the candidate decides
+// differently is counted per rule, with a few hashed senders as samples.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::{keccak256, Address};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    error::PaymasterError,
+    metrics::PaymasterMetrics,
+    policy::{PolicyEngine, PolicyRejection},
+};
+
+/// Settings of shadow policy evaluation ([paymaster_relay.policy_shadow])
+#[derive(Debug, Clone, Deserialize)]
+pub struct PolicyShadowConfig {
+    /// Candidate policy file loaded at startup; shadow evaluation is off until
+    /// a candidate is loaded here or with pm_loadCandidatePolicy
+    #[serde(default)]
+    pub candidate_file: Option<PathBuf>,
+    /// Directory pm_loadCandidatePolicy loads candidate files from, by file
+    /// name; the method is refused while unset
+    #[serde(default)]
+    pub candidate_dir: Option<PathBuf>,
+    /// Hashed senders of divergent requests kept in the report
+    #[serde(default = "default_max_samples")]
+    pub max_samples: usize,
+}
+
+fn default_max_samples() -> usize {
+    20
+}
+
+impl Default for PolicyShadowConfig {
+    fn default() -> Self {
+        Self {
+            candidate_file: None,
+            candidate_dir: None,
+            max_samples: default_max_samples(),
+        }
+    }
+}
+
+/// How the candidate policy decided differently from the active one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Divergence {
+    /// The active policy allowed the request, the candidate would reject it
+    WouldReject,
+    /// The active policy rejected the request, the candidate would allow it
+    WouldAllow,
+    /// Both reject the request, for different rules
+    DifferentRule,
+}
+
+impl Divergence {
+    /// Compare the decisions of the active and the candidate policy; `None` when they agree
+    pub fn between(
+        active: Result<(), &PolicyRejection>,
+        candidate: Result<(), &PolicyRejection>,
+    ) -> Option<(Self, &'static str)> {
+        match (active, candidate) {
+            (Ok(()), Ok(())) => None,
+            (Ok(()), Err(candidate)) => Some((Self::WouldReject, candidate.rule)),
+            (Err(active), Ok(())) => Some((Self::WouldAllow, active.rule)),
+            (Err(active), Err(candidate)) if active.rule == candidate.rule => None,
+            (Err(_), Err(candidate)) => Some((Self::DifferentRule, candidate.rule)),
+        }
+    }
+
+    /// Metric label of the divergence
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::WouldReject => "would_reject",
+            Self::WouldAllow => "would_allow",
+            Self::DifferentRule => "different_rule",
+        }
+    }
+}
+
+/// Divergences attributed to one rule: the rule the candidate rejected with,
+/// or for requests it would allow, the rule the active policy rejected with
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleDivergence {
+    pub would_reject: u64,
+    pub would_allow: u64,
+    pub different_rule: u64,
+}
+
+/// Shadow evaluation results served by pm_getPolicyShadowReport
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyShadowReport {
+    /// A candidate policy is loaded and evaluated
+    pub enabled: bool,
+    /// File the candidate policy was loaded from
+    pub candidate_file: Option<PathBuf>,
+    /// Unix time the candidate was loaded, the start of the counts
+    pub since: Option<u64>,
+    /// Decisions compared
+    pub evaluated: u64,
+    /// Decisions the candidate made the same way
+    pub agreed: u64,
+    pub would_reject: u64,
+    pub would_allow: u64,
+    pub different_rule: u64,
+    /// Divergences by rule name
+    pub rules: BTreeMap<String, RuleDivergence>,
+    /// keccak256 of the first distinct senders of divergent requests
+    pub sample_senders: Vec<String>,
+}
+
+#[derive(Debug)]
+struct Candidate {
+    engine: Arc<PolicyEngine>,
+    file: PathBuf,
+    loaded_at: u64,
+}
+
+#[derive(Debug)]
+struct Policies {
+    active: Arc<PolicyEngine>,
+    candidate: Option<Candidate>,
+    /// Bumped on every load and promotion, so evaluations of a replaced
+    /// candidate finishing late are not counted against the new one
+    generation: u64,
+}
+
+/// Policies of a sponsorship evaluation, taken together with [`PolicyShadow::snapshot`]
+#[derive(Debug, Clone)]
+pub struct PolicySnapshot {
+    /// Policy enforced
+    pub active: Arc<PolicyEngine>,
+    /// Policy evaluated in the shadow of the active one
+    pub candidate: Option<Arc<PolicyEngine>>,
+    generation: u64,
+}
+
+/// Active sponsorship policy, and the candidate evaluated alongside it
+#[derive(Debug)]
+pub struct PolicyShadow {
+    max_samples: usize,
+    candidate_dir: Option<PathBuf>,
+    policies: RwLock<Policies>,
+    report: Mutex<PolicyShadowReport>,
+}
+
+impl PolicyShadow {
+    /// Enforce `active`, with no candidate loaded
+    pub fn new(active: Arc<PolicyEngine>, config: &PolicyShadowConfig) -> Self {
+        Self {
+            max_samples: config.max_samples,
+            candidate_dir: config.candidate_dir.clone(),
+            policies: RwLock::new(Policies {
+                active,
+                candidate: None,
+                generation: 0,
+            }),
+            report: Mutex::new(PolicyShadowReport::default()),
+        }
+    }
+
+    /// Policy enforced
+    pub fn active(&self) -> Arc<PolicyEngine> {
+        self.policies.read().unwrap().active.clone()
+    }
+
+    /// Active and candidate policy for evaluating one request
+    pub fn snapshot(&self) -> PolicySnapshot {
+        let policies = self.policies.read().unwrap();
+        PolicySnapshot {
+            active: policies.active.clone(),
+            candidate: policies
+                .candidate
+                .as_ref()
+                .map(|candidate| candidate.engine.clone()),
+            generation: policies.generation,
+        }
+    }
+
+    /// Start evaluating the policy in `file` as candidate, replacing any
+    /// candidate loaded before and resetting the report
+    pub fn load_candidate(&self, file: &Path) -> Result<PolicyShadowReport, PaymasterError> {
+        let engine = PolicyEngine::new(file)?;
+        let mut policies = self.policies.write().unwrap();
+        policies.candidate = Some(Candidate {
+            engine: Arc::new(engine),
+            file: file.to_path_buf(),
+            loaded_at: unix_now(),
+        });
+        policies.generation += 1;
+        Ok(self.reset(&policies))
+    }
+
+    /// [`Self::load_candidate`] of the file `name` in the candidate directory,
+    /// as requested over RPC
+    ///
+    /// Only plain file names resolving inside the directory are accepted. Why
+    /// a file failed to load is logged; the caller only learns that it did, so
+    /// parse errors never quote the file back.
+    pub fn load_named_candidate(&self, name: &str) -> Result<PolicyShadowReport, PaymasterError> {
+        let path = self.candidate_path(name)?;
+        self.load_candidate(&path).map_err(|e| {
+            warn!("Candidate policy {:?} failed to load: {}", path, e);
+            PaymasterError::InvalidRequest("Invalid candidate policy".to_string())
+        })
+    }
+
+    /// Canonical path of the file `name` in the candidate directory
+    fn candidate_path(&self, name: &str) -> Result<PathBuf, PaymasterError> {
+        let dir = self.candidate_dir.as_ref().ok_or_else(|| {
+            PaymasterError::InvalidRequest(
+                "Loading candidate policies needs a candidate_dir".to_string(),
+            )
+        })?;
+        let invalid = || {
+            PaymasterError::InvalidRequest(
+                "Candidate policy must name a file of the candidate directory".to_string(),
+            )
+        };
+        if Path::new(name).file_name() != Some(OsStr::new(name)) {
+            return Err(invalid());
+        }
+        let dir = dir.canonicalize().map_err(|e| {
+            warn!("Candidate policy directory {:?} is unusable: {}", dir, e);
+            invalid()
+        })?;
+        // Canonical, so a symlink out of the directory is caught too
+        let path = dir.join(name).canonicalize().map_err(|_| invalid())?;
+        if !path.starts_with(&dir) || !path.is_file() {
+            return Err(invalid());
+        }
+        Ok(path)
+    }
+
+    /// Enforce the candidate from now on, returning its final report; `None`
+    /// when no candidate is loaded
+    pub fn promote_candidate(&self) -> Option<PolicyShadowReport> {
+        let mut policies = self.policies.write().unwrap();
+        let candidate = policies.candidate.take()?;
+        policies.active = candidate.engine;
+        policies.generation += 1;
+        let promoted = self.report();
+        self.reset(&policies);
+        Some(promoted)
+    }
+
+    /// Counts since the candidate was loaded
+    pub fn report(&self) -> PolicyShadowReport {
+        self.report.lock().unwrap().clone()
+    }
+
+    fn reset(&self, policies: &Policies) -> PolicyShadowReport {
+        let mut report = self.report.lock().unwrap();
+        *report = PolicyShadowReport {
+            enabled: policies.candidate.is_some(),
+            candidate_file: policies
+                .candidate
+                .as_ref()
+                .map(|candidate| candidate.file.clone()),
+            since: policies
+                .candidate
+                .as_ref()
+                .map(|candidate| candidate.loaded_at),
+            ..Default::default()
+        };
+        report.clone()
+    }
+
+    /// Count the decisions of the policies of `snapshot` on a request of `sender`
+    pub fn record(
+        &self,
+        snapshot: &PolicySnapshot,
+        sender: Address,
+        active: Result<(), &PolicyRejection>,
+        candidate: Result<(), &PolicyRejection>,
+        metrics: &PaymasterMetrics,
+    ) {
+        let divergence = Divergence::between(active, candidate);
+        // Locked in the order of load_candidate so a reset cannot slip in between
+        let policies = self.policies.read().unwrap();
+        if snapshot.generation != policies.generation {
+            return;
+        }
+        let mut report = self.report.lock().unwrap();
+        report.evaluated += 1;
+        let Some((divergence, rule)) = divergence else {
+            report.agreed += 1;
+            return;
+        };
+
+        metrics.record_policy_shadow_divergence(divergence.as_str(), rule);
+        let counts = report.rules.entry(rule.to_string()).or_default();
+        match divergence {
+            Divergence::WouldReject => counts.would_reject += 1,
+            Divergence::WouldAllow => counts.would_allow += 1,
+            Divergence::DifferentRule => counts.different_rule += 1,
+        }
+        match divergence {
+            Divergence::WouldReject => report.would_reject += 1,
+            Divergence::WouldAllow => report.would_allow += 1,
+            Divergence::DifferentRule => report.different_rule += 1,
+        }
+        let sample = format!("{:#x}", keccak256(sender));
+        if report.sample_senders.len() < self.max_samples
+            && !report.sample_senders.contains(&sample)
+        {
+            report.sample_senders.push(sample);
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, sync::Arc, time::Duration};
+
+    use alloy_primitives::{address, keccak256, Address, Bytes, U256};
+    use async_trait::async_trait;
+    use rundler_types::{chain::ChainSpec, v0_7, UserOperationVariant};
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{
+        service::{PaymasterRelayService, SponsorOptions},
+        test_utils,
+        token_paymaster::{OracleAnswer, TokenConfig},
+        PaymasterError, PriceFeed, PriceOracle, TokenPaymasterConfig, TokenPricing,
+    };
+
+    const ALICE: Address = address!("00000000000000000000000000000000000000a1");
+    const BOB: Address = address!("00000000000000000000000000000000000000b0");
+    const CAROL: Address = address!("00000000000000000000000000000000000000c0");
+    const DAVE: Address = address!("00000000000000000000000000000000000000d0");
+
+    const PNT: Address = address!("00000000000000000000000000000000000000e2");
+
+    /// Statically priced tokens never ask the oracle
+    struct NoOracle;
+
+    #[async_trait]
+    impl PriceOracle for NoOracle {
+        async fn latest_answer(&self, oracle: Address) -> Result<OracleAnswer, PaymasterError> {
+            Err(PaymasterError::ChainError(format!(
+                "no answer for {}",
+                oracle
+            )))
+        }
+    }
+
+    fn senders(senders: &[Address]) -> String {
+        let senders: Vec<_> = senders.iter().map(|s| format!("\"{}\"", s)).collect();
+        format!("senders = [{}]\n", senders.join(", "))
+    }
+
+    fn create_service(dir: &Path, policy: &str) -> PaymasterRelayService {
+        test_utils::service_in(dir, policy).with_policy_shadow(PolicyShadowConfig::default())
+    }
+
+    fn op(sender: Address) -> (UserOperationVariant, ethers::types::Address) {
+        let op = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender,
+                nonce: U256::ZERO,
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 2_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        let entry_point = ChainSpec::default().entry_point_address_v0_7;
+        (
+            UserOperationVariant::V0_7(op),
+            ethers::types::Address::from_slice(entry_point.as_slice()),
+        )
+    }
+
+    async fn sponsor(
+        service: &PaymasterRelayService,
+        sender: Address,
+        options: SponsorOptions,
+    ) -> Result<(), PaymasterError> {
+        let (op, entry_point) = op(sender);
+        service
+            .sponsor_user_operation(op, entry_point, options)
+            .await
+            .map(|_| ())
+    }
+
+    /// Report once `evaluated` decisions were compared; the candidate is
+    /// evaluated on its own task
+    async fn report_after(service: &PaymasterRelayService, evaluated: u64) -> PolicyShadowReport {
+        for _ in 0..100 {
+            let report = service.policy_shadow_report();
+            if report.evaluated >= evaluated {
+                return report;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("candidate policy evaluated fewer than {} times", evaluated);
+    }
+
+    fn hashed(sender: Address) -> String {
+        format!("{:#x}", keccak256(sender))
+    }
+
+    #[tokio::test]
+    async fn test_shadow_evaluation_is_off_by_default() {
+        let dir = tempdir().unwrap();
+        let service = create_service(dir.path(), &senders(&[ALICE]));
+
+        sponsor(&service, ALICE, SponsorOptions::default())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert_eq!(
+            service.policy_shadow_report(),
+            PolicyShadowReport::default()
+        );
+        assert!(matches!(
+            service.promote_candidate_policy(),
+            Err(PaymasterError::InvalidRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_divergence_is_counted_per_rule() {
+        let dir = tempdir().unwrap();
+        let service = create_service(dir.path(), &senders(&[ALICE, BOB]));
+        let candidate =
+            test_utils::write_policy(dir.path(), "candidate.toml", &senders(&[ALICE, CAROL]));
+        let loaded = service.load_candidate_policy(&candidate).unwrap();
+        assert!(loaded.enabled);
+        assert_eq!(loaded.candidate_file.as_deref(), Some(candidate.as_path()));
+
+        // Only the active policy is enforced
+        sponsor(&service, ALICE, SponsorOptions::default())
+            .await
+            .unwrap();
+        sponsor(&service, BOB, SponsorOptions::default())
+            .await
+            .unwrap();
+        assert!(sponsor(&service, CAROL, SponsorOptions::default())
+            .await
+            .is_err());
+        assert!(sponsor(&service, DAVE, SponsorOptions::default())
+            .await
+            .is_err());
+
+        let report = report_after(&service, 4).await;
+        assert_eq!(report.evaluated, 4);
+        // Alice is allowed by both, Dave rejected by both for the same rule
+        assert_eq!(report.agreed, 2);
+        assert_eq!(report.would_reject, 1);
+        assert_eq!(report.would_allow, 1);
+        assert_eq!(report.different_rule, 0);
+        assert_eq!(
+            report.rules.get("senders"),
+            Some(&RuleDivergence {
+                would_reject: 1,
+                would_allow: 1,
+                different_rule: 0,
+            })
+        );
+        let mut samples = report.sample_senders.clone();
+        samples.sort();
+        let mut expected = vec![hashed(BOB), hashed(CAROL)];
+        expected.sort();
+        assert_eq!(samples, expected);
+    }
+
+    #[tokio::test]
+    async fn test_different_token_rule_is_counted() {
+        let dir = tempdir().unwrap();
+        let policy = format!("{}tokens = []\n", senders(&[ALICE]));
+        let token_pricing = TokenPricing::new(
+            Arc::new(NoOracle),
+            TokenPaymasterConfig {
+                enabled: true,
+                tokens: vec![TokenConfig {
+                    address: PNT,
+                    symbol: "PNT".to_string(),
+                    decimals: 18,
+                    price_feed: PriceFeed::Static {
+                        tokens_per_eth: U256::from(1_000u64)
+                            * U256::from(10u64).pow(U256::from(18)),
+                    },
+                }],
+                ..Default::default()
+            },
+        );
+        let service = create_service(dir.path(), &policy).with_token_pricing(token_pricing);
+        // The candidate allows the token but caps it below any gas cost
+        let candidate = test_utils::write_policy(
+            dir.path(),
+            "candidate.toml",
+            &format!(
+                "{}max_token_amounts = {{ \"{}\" = \"1\" }}\n",
+                senders(&[ALICE]),
+                PNT
+            ),
+        );
+        service.load_candidate_policy(&candidate).unwrap();
+
+        let pay_in_pnt = SponsorOptions {
+            token: Some(PNT),
+            ..Default::default()
+        };
+        assert!(sponsor(&service, ALICE, pay_in_pnt).await.is_err());
+
+        // The operation itself agreed, the token payment did not
+        let report = report_after(&service, 2).await;
+        assert_eq!(report.agreed, 1);
+        assert_eq!(report.different_rule, 1);
+        assert_eq!(
+            report.rules.get("max_token_amounts"),
+            Some(&RuleDivergence {
+                would_reject: 0,
+                would_allow: 0,
+                different_rule: 1,
+            })
+        );
+        assert_eq!(report.sample_senders, vec![hashed(ALICE)]);
+    }
+
+    #[tokio::test]
+    async fn test_promotion_swaps_enforced_policy() {
+        let dir = tempdir().unwrap();
+        let service = create_service(dir.path(), &senders(&[ALICE, BOB]));
+        let candidate =
+            test_utils::write_policy(dir.path(), "candidate.toml", &senders(&[ALICE, CAROL]));
+        service.load_candidate_policy(&candidate).unwrap();
+
+        sponsor(&service, BOB, SponsorOptions::default())
+            .await
+            .unwrap();
+        report_after(&service, 1).await;
+
+        let promoted = service.promote_candidate_policy().unwrap();
+        assert_eq!(promoted.would_reject, 1);
+        assert_eq!(
+            promoted.candidate_file.as_deref(),
+            Some(candidate.as_path())
+        );
+
+        // The candidate is enforced now, without a restart, and no longer shadowed
+        assert!(sponsor(&service, BOB, SponsorOptions::default())
+            .await
+            .is_err());
+        sponsor(&service, CAROL, SponsorOptions::default())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let report = service.policy_shadow_report();
+        assert!(!report.enabled);
+        assert_eq!(report.evaluated, 0);
+        assert!(service.promote_candidate_policy().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_candidate_keeps_shadow_off() {
+        let dir = tempdir().unwrap();
+        let service = create_service(dir.path(), &senders(&[ALICE]));
+        let candidate = test_utils::write_policy(dir.path(), "candidate.toml", "senders = 123\n");
+
+        assert!(service.load_candidate_policy(&candidate).is_err());
+        assert!(!service.policy_shadow_report().enabled);
+    }
+
+    #[tokio::test]
+    async fn test_requested_candidate_confined_to_directory() {
+        let dir = tempdir().unwrap();
+        let candidates = dir.path().join("candidates");
+        std::fs::create_dir(&candidates).unwrap();
+        test_utils::write_policy(&candidates, "candidate.toml", &senders(&[ALICE]));
+        test_utils::write_policy(
+            &candidates,
+            "broken.toml",
+            "secret = \"hunter2\"\nsenders = 123\n",
+        );
+        let outside = test_utils::write_policy(dir.path(), "outside.toml", &senders(&[BOB]));
+
+        // Refused until a directory is configured
+        let service = create_service(dir.path(), &senders(&[ALICE]));
+        assert!(service
+            .load_named_candidate_policy("candidate.toml")
+            .is_err());
+
+        let service = service.with_policy_shadow(PolicyShadowConfig {
+            candidate_dir: Some(candidates.clone()),
+            ..Default::default()
+        });
+        for name in [
+            "../outside.toml",
+            outside.to_str().unwrap(),
+            "",
+            ".",
+            "..",
+            "missing.toml",
+        ] {
+            assert!(
+                matches!(
+                    service.load_named_candidate_policy(name),
+                    Err(PaymasterError::InvalidRequest(_))
+                ),
+                "{}",
+                name
+            );
+        }
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&outside, candidates.join("link.toml")).unwrap();
+            assert!(service.load_named_candidate_policy("link.toml").is_err());
+        }
+        assert!(!service.policy_shadow_report().enabled);
+
+        // Parse errors stay in the log
+        let err = service
+            .load_named_candidate_policy("broken.toml")
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Invalid candidate policy"),
+            "{}",
+            err
+        );
+        assert!(!err.to_string().contains("hunter2"), "{}", err);
+
+        let loaded = service
+            .load_named_candidate_policy("candidate.toml")
+            .unwrap();
+        assert!(loaded.enabled);
+        assert_eq!(
+            loaded.candidate_file,
+            Some(candidates.canonicalize().unwrap().join("candidate.toml"))
+        );
+    }
+}
@@ -1,47 +1,243 @@
 // proxy_client.rs
 // HTTP client for proxying requests to external SuperRelay service
 
-use std::time::Duration;
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use tokio::task::JoinHandle;
+
+/// Connection pooling, timeout and retry settings of the proxy client (`[proxy_client]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ProxyClientConfig {
+    /// Seconds a whole upstream request may take
+    pub request_timeout_seconds: u64,
+    /// Seconds to establish an upstream connection
+    pub connect_timeout_seconds: u64,
+    /// Idle pooled connections kept per upstream host
+    pub max_idle_connections: usize,
+    /// Seconds an idle pooled connection is kept
+    pub idle_timeout_seconds: u64,
+    /// Retries of idempotent requests while the upstream is down
+    pub max_retries: u32,
+    /// Backoff before the first retry, doubled for each further retry
+    pub initial_backoff_ms: u64,
+    /// Upper bound of the backoff
+    pub max_backoff_ms: u64,
+    /// Seconds between background health checks
+    pub health_interval_seconds: u64,
+}
+
+impl Default for ProxyClientConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout_seconds: 30,
+            connect_timeout_seconds: 5,
+            max_idle_connections: 16,
+            idle_timeout_seconds: 90,
+            max_retries: 3,
+            initial_backoff_ms: 200,
+            max_backoff_ms: 5_000,
+            health_interval_seconds: 10,
+        }
+    }
+}
+
+impl ProxyClientConfig {
+    /// Backoff before retry number `retry` (starting at 0)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self
+            .initial_backoff_ms
+            .saturating_mul(2u64.saturating_pow(retry));
+        Duration::from_millis(backoff.min(self.max_backoff_ms))
+    }
+}
+
+/// Failure of a proxied request
+#[derive(Debug, thiserror::Error)]
+pub enum ProxyError {
+    /// The upstream could not be reached or is unavailable (connection
+    /// failure, timeout, or a 502/503/504 in front of it)
+    #[error("SuperRelay upstream is down: {0}")]
+    UpstreamDown(String),
+    /// The upstream answered with an error, passed through to the client
+    #[error("SuperRelay upstream returned HTTP {status}")]
+    UpstreamError {
+        /// HTTP status of the upstream response
+        status: u16,
+        /// Upstream response body
+        body: String,
+    },
+}
+
+impl ProxyError {
+    fn from_transport(error: reqwest::Error) -> Self {
+        ProxyError::UpstreamDown(error.to_string())
+    }
+}
+
+/// JSON-RPC methods safe to send again when the upstream may not have seen
+/// them: health and read-only methods
+///
+/// Anything that sponsors, sends or changes state, pm_sponsorUserOperation
+/// and eth_sendUserOperation among them, is never retried.
+pub fn is_idempotent_method(method: &str) -> bool {
+    matches!(
+        method,
+        "health"
+            | "eth_chainId"
+            | "eth_supportedEntryPoints"
+            | "eth_estimateUserOperationGas"
+            | "net_version"
+            | "web3_clientVersion"
+            | "superRelay_poolSummary"
+    ) || ["eth_get", "pm_get", "rundler_get", "superRelay_get"]
+        .iter()
+        .any(|prefix| method.starts_with(prefix))
+}
+
+/// Whether every call of a JSON-RPC request or batch is idempotent
+fn is_idempotent_request(request: &Value) -> bool {
+    let method_is_idempotent = |call: &Value| {
+        call.get("method")
+            .and_then(Value::as_str)
+            .is_some_and(is_idempotent_method)
+    };
+    match request {
+        Value::Array(calls) => !calls.is_empty() && calls.iter().all(method_is_idempotent),
+        call => method_is_idempotent(call),
+    }
+}
+
+/// Upstream state kept by the background health watcher, served on /proxy/status
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UpstreamHealth {
+    /// Whether the last health check succeeded
+    pub healthy: bool,
+    /// Unix time of the last health check
+    pub last_checked: Option<u64>,
+    /// Unix time of the last successful health check
+    pub last_healthy: Option<u64>,
+    /// Error of the last failed health check
+    pub last_error: Option<String>,
+    /// Health checks failed in a row
+    pub consecutive_failures: u32,
+}
 
 /// HTTP client for connecting to external SuperRelay service
+///
+/// Clones share one connection pool and one health state.
 #[derive(Clone)]
 pub struct SuperRelayProxyClient {
     client: Client,
     base_url: String,
+    config: ProxyClientConfig,
+    health: Arc<RwLock<UpstreamHealth>>,
 }
 
 impl SuperRelayProxyClient {
     /// Create a new proxy client
     pub fn new(super_relay_url: &str) -> Self {
+        Self::with_config(super_relay_url, ProxyClientConfig::default())
+    }
+
+    /// Create a proxy client with the given pooling, timeout and retry settings
+    pub fn with_config(super_relay_url: &str, config: ProxyClientConfig) -> Self {
         let client = Client::builder()
-            .timeout(Duration::from_secs(30))
+            .timeout(Duration::from_secs(config.request_timeout_seconds))
+            .connect_timeout(Duration::from_secs(config.connect_timeout_seconds))
+            .pool_max_idle_per_host(config.max_idle_connections)
+            .pool_idle_timeout(Duration::from_secs(config.idle_timeout_seconds))
+            .tcp_keepalive(Duration::from_secs(60))
             .build()
             .expect("Failed to create HTTP client");
 
         Self {
             client,
             base_url: super_relay_url.trim_end_matches('/').to_string(),
+            config,
+            health: Arc::new(RwLock::new(UpstreamHealth::default())),
         }
     }
 
+    /// Upstream SuperRelay URL
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Pooling, timeout and retry settings
+    pub fn config(&self) -> &ProxyClientConfig {
+        &self.config
+    }
+
     /// Forward JSON-RPC request to SuperRelay service
-    pub async fn forward_json_rpc(&self, request: Value) -> Result<Value, reqwest::Error> {
+    ///
+    /// Idempotent requests are retried with exponential backoff while the
+    /// upstream is down; all others are sent exactly once.
+    pub async fn forward_json_rpc(&self, request: Value) -> Result<Value, ProxyError> {
+        let max_retries = if is_idempotent_request(&request) {
+            self.config.max_retries
+        } else {
+            0
+        };
+
+        let mut retry = 0;
+        loop {
+            match self.send_once(&request).await {
+                Err(ProxyError::UpstreamDown(e)) if retry < max_retries => {
+                    let backoff = self.config.backoff(retry);
+                    tracing::warn!(
+                        "SuperRelay upstream down ({}), retry {}/{} in {:?}",
+                        e,
+                        retry + 1,
+                        max_retries,
+                        backoff
+                    );
+                    tokio::time::sleep(backoff).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send_once(&self, request: &Value) -> Result<Value, ProxyError> {
         let response = self
             .client
             .post(&self.base_url)
             .header("Content-Type", "application/json")
-            .json(&request)
+            .json(request)
             .send()
-            .await?;
+            .await
+            .map_err(ProxyError::from_transport)?;
 
-        response.json().await
+        let status = response.status();
+        let body = response.text().await.map_err(ProxyError::from_transport)?;
+        if matches!(
+            status,
+            StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+        ) {
+            return Err(ProxyError::UpstreamDown(format!("HTTP {}", status)));
+        }
+        if !status.is_success() {
+            return Err(ProxyError::UpstreamError {
+                status: status.as_u16(),
+                body,
+            });
+        }
+        serde_json::from_str(&body).map_err(|_| ProxyError::UpstreamError {
+            status: status.as_u16(),
+            body,
+        })
     }
 
     /// Test connection to SuperRelay service
-    pub async fn health_check(&self) -> Result<bool, reqwest::Error> {
+    pub async fn health_check(&self) -> Result<bool, ProxyError> {
         let health_request = serde_json::json!({
             "jsonrpc": "2.0",
             "method": "health",
@@ -57,4 +253,331 @@ impl SuperRelayProxyClient {
             }
         }
     }
+
+    /// Upstream state as last seen by the health watcher
+    pub fn upstream_health(&self) -> UpstreamHealth {
+        self.health.read().unwrap().clone()
+    }
+
+    /// Check the upstream once, without retries, and record the outcome
+    pub async fn check_upstream(&self) -> UpstreamHealth {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "health",
+            "params": [],
+            "id": 1
+        });
+        let result = self.send_once(&request).await;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let mut health = self.health.write().unwrap();
+        health.last_checked = Some(now);
+        match result {
+            // An upstream answering with an error is up
+            Ok(_) | Err(ProxyError::UpstreamError { .. }) => {
+                if !health.healthy {
+                    tracing::info!("SuperRelay upstream {} is up", self.base_url);
+                }
+                health.healthy = true;
+                health.last_healthy = Some(now);
+                health.last_error = None;
+                health.consecutive_failures = 0;
+            }
+            Err(e) => {
+                if health.healthy {
+                    tracing::warn!("SuperRelay upstream {} went down: {}", self.base_url, e);
+                }
+                health.healthy = false;
+                health.last_error = Some(e.to_string());
+                health.consecutive_failures += 1;
+            }
+        }
+        health.clone()
+    }
+
+    /// Check the upstream every `health_interval_seconds` in the background
+    pub fn spawn_health_watcher(&self) -> JoinHandle<()> {
+        let client = self.clone();
+        let interval = Duration::from_secs(self.config.health_interval_seconds.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                client.check_upstream().await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{proxy_server::create_proxy_api_router, CorsConfig};
+
+    #[test]
+    fn test_idempotent_methods() {
+        for method in [
+            "health",
+            "eth_chainId",
+            "eth_getUserOperationReceipt",
+            "pm_getDepositInfo",
+            "rundler_getBundleStats",
+        ] {
+            assert!(is_idempotent_method(method), "{}", method);
+        }
+        for method in [
+            "pm_sponsorUserOperation",
+            "eth_sendUserOperation",
+            "pm_depositTo",
+            "admin_setTracking",
+        ] {
+            assert!(!is_idempotent_method(method), "{}", method);
+        }
+
+        let call = |method: &str| serde_json::json!({"method": method});
+        assert!(is_idempotent_request(&Value::Array(vec![
+            call("eth_chainId"),
+            call("health")
+        ])));
+        assert!(!is_idempotent_request(&Value::Array(vec![
+            call("eth_chainId"),
+            call("pm_sponsorUserOperation")
+        ])));
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let config = ProxyClientConfig {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 500,
+            ..Default::default()
+        };
+        let backoffs: Vec<_> = (0..5)
+            .map(|retry| config.backoff(retry).as_millis())
+            .collect();
+        assert_eq!(backoffs, vec![100, 200, 400, 500, 500]);
+    }
+
+    /// Upstream answering `failures` requests with `status` before succeeding
+    #[derive(Clone)]
+    struct FlakyUpstream {
+        requests: Arc<AtomicUsize>,
+        failures: usize,
+        status: StatusCode,
+    }
+
+    async fn flaky_handler(
+        State(upstream): State<FlakyUpstream>,
+        Json(request): Json<Value>,
+    ) -> (StatusCode, Json<Value>) {
+        let seen = upstream.requests.fetch_add(1, Ordering::SeqCst);
+        if seen < upstream.failures {
+            return (
+                upstream.status,
+                Json(json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": {"code": -32602, "message": "rejected"},
+                })),
+            );
+        }
+        (
+            StatusCode::OK,
+            Json(json!({"jsonrpc": "2.0", "id": request["id"], "result": "0x1"})),
+        )
+    }
+
+    async fn serve(app: Router) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    /// Start an upstream failing `failures` times with `status`, returning its URL and request counter
+    async fn flaky_upstream(failures: usize, status: StatusCode) -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let upstream = FlakyUpstream {
+            requests: requests.clone(),
+            failures,
+            status,
+        };
+        let app = Router::new()
+            .route("/", post(flaky_handler))
+            .with_state(upstream);
+        (format!("http://{}", serve(app).await), requests)
+    }
+
+    fn fast_retries() -> ProxyClientConfig {
+        ProxyClientConfig {
+            request_timeout_seconds: 5,
+            connect_timeout_seconds: 1,
+            max_retries: 3,
+            initial_backoff_ms: 10,
+            max_backoff_ms: 40,
+            ..Default::default()
+        }
+    }
+
+    fn call(method: &str) -> Value {
+        json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": []})
+    }
+
+    /// URL of a port nothing listens on
+    async fn closed_port() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_request_retried_until_upstream_recovers() {
+        let (url, requests) = flaky_upstream(2, StatusCode::SERVICE_UNAVAILABLE).await;
+        let client = SuperRelayProxyClient::with_config(&url, fast_retries());
+
+        let response = client.forward_json_rpc(call("eth_chainId")).await.unwrap();
+        assert_eq!(response["result"], "0x1");
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retries_exhausted() {
+        let (url, requests) = flaky_upstream(usize::MAX, StatusCode::BAD_GATEWAY).await;
+        let client = SuperRelayProxyClient::with_config(&url, fast_retries());
+
+        let err = client
+            .forward_json_rpc(call("eth_getUserOperationReceipt"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProxyError::UpstreamDown(_)), "{}", err);
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_sponsorship_never_retried() {
+        let (url, requests) = flaky_upstream(1, StatusCode::SERVICE_UNAVAILABLE).await;
+        let client = SuperRelayProxyClient::with_config(&url, fast_retries());
+
+        let err = client
+            .forward_json_rpc(call("pm_sponsorUserOperation"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProxyError::UpstreamDown(_)), "{}", err);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // A batch with one non-idempotent call is not retried either
+        let (url, requests) = flaky_upstream(1, StatusCode::SERVICE_UNAVAILABLE).await;
+        let client = SuperRelayProxyClient::with_config(&url, fast_retries());
+        let batch = json!([call("eth_chainId"), call("eth_sendUserOperation")]);
+        assert!(client.forward_json_rpc(batch).await.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_upstream_errors_not_retried() {
+        let (url, requests) = flaky_upstream(1, StatusCode::BAD_REQUEST).await;
+        let client = SuperRelayProxyClient::with_config(&url, fast_retries());
+
+        let err = client
+            .forward_json_rpc(call("eth_chainId"))
+            .await
+            .unwrap_err();
+        match err {
+            ProxyError::UpstreamError { status, body } => {
+                assert_eq!(status, 400);
+                assert!(body.contains("rejected"), "{}", body);
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_upstream_is_down() {
+        let client = SuperRelayProxyClient::with_config(
+            &closed_port().await,
+            ProxyClientConfig {
+                max_retries: 1,
+                ..fast_retries()
+            },
+        );
+
+        let err = client.health_check().await.unwrap_err();
+        assert!(matches!(err, ProxyError::UpstreamDown(_)), "{}", err);
+
+        let health = client.check_upstream().await;
+        assert!(!health.healthy);
+        assert_eq!(health.consecutive_failures, 1);
+        assert!(health.last_error.is_some());
+    }
+
+    async fn api_server(upstream_url: &str) -> (String, Arc<SuperRelayProxyClient>) {
+        let client = Arc::new(SuperRelayProxyClient::with_config(
+            upstream_url,
+            fast_retries(),
+        ));
+        let app = create_proxy_api_router(client.clone(), &CorsConfig::default()).unwrap();
+        (format!("http://{}", serve(app).await), client)
+    }
+
+    #[tokio::test]
+    async fn test_api_server_passes_upstream_errors_through() {
+        let (url, _) = flaky_upstream(1, StatusCode::BAD_REQUEST).await;
+        let (api, _) = api_server(&url).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/jsonrpc", api))
+            .json(&call("pm_sponsorUserOperation"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 400);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["error"]["message"], "rejected");
+    }
+
+    #[tokio::test]
+    async fn test_api_server_reports_down_upstream() {
+        let (api, client) = api_server(&closed_port().await).await;
+        let http = reqwest::Client::new();
+
+        let response = http
+            .post(format!("{}/jsonrpc", api))
+            .json(&call("pm_sponsorUserOperation"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 502);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["code"], -32000);
+
+        client.check_upstream().await;
+        let status: Value = http
+            .get(format!("{}/proxy/status", api))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(status["health"]["healthy"], false);
+        assert_eq!(status["health"]["consecutive_failures"], 1);
+        assert_eq!(status["maxRetries"], 3);
+    }
 }
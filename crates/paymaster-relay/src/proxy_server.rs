@@ -5,19 +5,19 @@ use std::sync::Arc;
 
 use axum::{
     extract::{Path, State},
-    http::{Method, StatusCode},
-    response::{Html, IntoResponse, Json},
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::{Html, IntoResponse, Json, Response},
     routing::{get, post},
     Router,
 };
 use serde_json::{json, Value};
-use tower_http::cors::{Any, CorsLayer};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
     api_schemas::{examples, ApiDoc, ErrorResponse},
-    proxy_client::SuperRelayProxyClient,
+    cors::{CorsConfig, CorsConfigError},
+    proxy_client::{ProxyError, SuperRelayProxyClient},
 };
 
 pub type ProxyAppState = Arc<SuperRelayProxyClient>;
@@ -55,13 +55,31 @@ async fn proxy_health_check() -> Json<serde_json::Value> {
 async fn json_rpc_proxy_handler(
     State(proxy_client): State<ProxyAppState>,
     Json(request): Json<Value>,
-) -> Result<Json<Value>, (StatusCode, Json<ErrorResponse>)> {
+) -> Response {
     // Forward JSON-RPC request to SuperRelay service
     match proxy_client.forward_json_rpc(request).await {
-        Ok(response) => Ok(Json(response)),
-        Err(e) => {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => proxy_error_response(e),
+    }
+}
+
+/// 502 when the upstream is down, its own status and body when it answered
+/// with an error
+fn proxy_error_response(error: ProxyError) -> Response {
+    match error {
+        ProxyError::UpstreamError { status, body } => {
+            tracing::warn!("SuperRelay returned HTTP {}", status);
+            let status = StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY);
+            let content_type = if serde_json::from_str::<Value>(&body).is_ok() {
+                "application/json"
+            } else {
+                "text/plain; charset=utf-8"
+            };
+            (status, [(CONTENT_TYPE, content_type)], body).into_response()
+        }
+        e @ ProxyError::UpstreamDown(_) => {
             tracing::error!("Proxy request failed: {}", e);
-            Err((
+            (
                 StatusCode::BAD_GATEWAY,
                 Json(ErrorResponse {
                     code: -32000,
@@ -71,11 +89,33 @@ async fn json_rpc_proxy_handler(
                         "suggestion": "Please check if SuperRelay service is running"
                     })),
                 }),
-            ))
+            )
+                .into_response()
         }
     }
 }
 
+/// Upstream health, as last checked by the background watcher, and the
+/// proxy client settings
+#[utoipa::path(
+    get,
+    path = "/proxy/status",
+    responses(
+        (status = 200, description = "Upstream SuperRelay status")
+    ),
+    tag = "monitoring"
+)]
+async fn proxy_status(State(proxy_client): State<ProxyAppState>) -> Json<Value> {
+    let config = proxy_client.config();
+    Json(json!({
+        "upstream": proxy_client.base_url(),
+        "health": proxy_client.upstream_health(),
+        "maxRetries": config.max_retries,
+        "requestTimeoutSeconds": config.request_timeout_seconds,
+        "healthIntervalSeconds": config.health_interval_seconds,
+    }))
+}
+
 /// Dashboard home redirect handler
 async fn dashboard_home() -> impl IntoResponse {
     axum::response::Redirect::permanent("/dashboard")
@@ -197,14 +237,15 @@ async fn get_transaction_history() -> Json<Value> {
     ),
     tag = "monitoring"
 )]
-async fn readiness_check() -> Json<Value> {
+async fn readiness_check(State(proxy_client): State<ProxyAppState>) -> Json<Value> {
+    let upstream_healthy = proxy_client.upstream_health().healthy;
     Json(json!({
-        "status": "ready",
+        "status": if upstream_healthy { "ready" } else { "degraded" },
         "service": "paymaster-proxy",
         "mode": "proxy",
         "checks": {
             "uptime": "pass",
-            "proxy_connection": "pass"
+            "proxy_connection": if upstream_healthy { "pass" } else { "fail" }
         }
     }))
 }
@@ -311,13 +352,17 @@ async fn generate_python_example(Path(endpoint): Path<String>) -> Result<Json<Va
 }
 
 /// Create the proxy API router
-pub fn create_proxy_api_router(proxy_client: ProxyAppState) -> Router {
-    Router::new()
+pub fn create_proxy_api_router(
+    proxy_client: ProxyAppState,
+    cors: &CorsConfig,
+) -> Result<Router, CorsConfigError> {
+    let router = Router::new()
         // 根路径：GET 重定向到 dashboard
         .route("/", get(dashboard_home))
         // JSON-RPC 端点 - 纯 JSON-RPC 协议
         .route("/jsonrpc", post(json_rpc_proxy_handler))
         .route("/health", get(proxy_health_check))
+        .route("/proxy/status", get(proxy_status))
         .route("/ready", get(readiness_check))
         .route("/metrics", get(get_metrics))
         // Dashboard routes
@@ -338,23 +383,21 @@ pub fn create_proxy_api_router(proxy_client: ProxyAppState) -> Router {
         .route("/codegen/python/:endpoint", get(generate_python_example))
         // Swagger UI with comprehensive documentation
         .merge(SwaggerUi::new("/swagger-ui").url("/api-doc/openapi.json", ApiDoc::openapi()))
-        // CORS configuration
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
-                .allow_headers(Any),
-        )
         // Shared proxy client state
-        .with_state(proxy_client)
+        .with_state(proxy_client);
+
+    // CORS configuration
+    cors.apply(router)
 }
 
 /// Start the proxy API server
 pub async fn start_proxy_api_server(
     bind_address: &str,
     proxy_client: SuperRelayProxyClient,
+    cors: &CorsConfig,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let app = create_proxy_api_router(Arc::new(proxy_client));
+    proxy_client.spawn_health_watcher();
+    let app = create_proxy_api_router(Arc::new(proxy_client), cors)?;
 
     let listener = tokio::net::TcpListener::bind(bind_address).await?;
 
@@ -371,6 +414,7 @@ pub async fn start_proxy_api_server(
         bind_address
     );
     tracing::info!("🏥 Health Check: http://{}/health", bind_address);
+    tracing::info!("🔗 Upstream Status: http://{}/proxy/status", bind_address);
     tracing::info!("💡 在 Swagger UI 中选择服务器即可切换协议测试");
 
     axum::serve(listener, app)
@@ -3,14 +3,24 @@
 
 use std::str::FromStr;
 
-use alloy_primitives::{Address as AlloyAddress, Bytes, U256};
+use alloy_primitives::{Address as AlloyAddress, Bytes, B256, U256};
 use async_trait::async_trait;
 use jsonrpsee::{proc_macros::rpc, types::ErrorObjectOwned};
 use rundler_types::{chain::ChainSpec, v0_6, v0_7, UserOperationVariant};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    service::PaymasterRelayService,
+    balance_monitor::BalanceStatus,
+    deposit::{DepositTransaction, PaymasterDepositInfo},
+    entry_points::SupportedEntryPoint,
+    outbound_tx::OutboundTx,
+    policy_shadow::PolicyShadowReport,
+    service::{PaymasterRelayService, PaymasterSponsorResult, SignerStatus, SponsorOptions},
+    signer::{KeyRotation, KeySource},
+    simulation::SponsorshipSimulation,
+    sponsorship_records::{
+        SponsorshipPage, SponsorshipQuery, SponsorshipRecord, SponsorshipStatus, DEFAULT_PAGE_SIZE,
+    },
     validation::{InputValidator, ValidationLimits},
 };
 
@@ -53,8 +63,16 @@ impl TryInto<UserOperationVariant> for JsonUserOperation {
     type Error = String;
 
     fn try_into(self) -> Result<UserOperationVariant, Self::Error> {
-        let chain_spec = ChainSpec::default(); // Use default for now
+        self.into_user_operation(&ChainSpec::default())
+    }
+}
 
+impl JsonUserOperation {
+    /// Build the UserOperation, hashed with the chain id and EntryPoints of `chain_spec`
+    pub fn into_user_operation(
+        self,
+        chain_spec: &ChainSpec,
+    ) -> Result<UserOperationVariant, String> {
         // Parse common fields
         let sender = AlloyAddress::from_str(&self.sender)
             .map_err(|e| format!("Invalid sender address: {}", e))?;
@@ -89,7 +107,7 @@ impl TryInto<UserOperationVariant> for JsonUserOperation {
             };
 
             let uo = v0_6::UserOperationBuilder::new(
-                &chain_spec,
+                chain_spec,
                 v0_6::UserOperationRequiredFields {
                     sender,
                     nonce,
@@ -110,7 +128,7 @@ impl TryInto<UserOperationVariant> for JsonUserOperation {
         } else {
             // v0.7 format
             let mut builder = v0_7::UserOperationBuilder::new(
-                &chain_spec,
+                chain_spec,
                 v0_7::UserOperationRequiredFields {
                     sender,
                     nonce,
@@ -198,6 +216,183 @@ pub trait PaymasterRelayApi {
         user_op: serde_json::Value,
         entry_point: String,
     ) -> Result<String, ErrorObjectOwned>;
+
+    /// Have a user operation pay its gas in an ERC-20 token
+    ///
+    /// Returns paymaster data in the token paymaster format together with the
+    /// quote it was signed for: the rate, markup and most tokens the operation
+    /// can be charged.
+    #[method(name = "sponsorUserOperationERC20")]
+    async fn sponsor_user_operation_erc20(
+        &self,
+        user_op: serde_json::Value,
+        entry_point: String,
+        token: String,
+    ) -> Result<String, ErrorObjectOwned>;
+
+    /// EntryPoints sponsored for, with their version and paymaster contract
+    ///
+    /// Sponsorship requests for any other EntryPoint are rejected.
+    #[method(name = "getSupportedEntryPoints")]
+    async fn get_supported_entry_points(
+        &self,
+    ) -> Result<Vec<SupportedEntryPoint>, ErrorObjectOwned>;
+
+    /// Deposit and stake of the paymaster on an EntryPoint (v0.6 or v0.7)
+    #[method(name = "getDepositInfo")]
+    async fn get_deposit_info(
+        &self,
+        entry_point: String,
+    ) -> Result<PaymasterDepositInfo, ErrorObjectOwned>;
+
+    /// Deposit `amount` wei from the paymaster signer account into its EntryPoint deposit
+    ///
+    /// Amounts above the configured cap are rejected. Returns the mined transaction hash
+    /// and the new deposit.
+    #[method(name = "depositTo")]
+    async fn deposit_to(
+        &self,
+        entry_point: String,
+        amount: String,
+    ) -> Result<DepositTransaction, ErrorObjectOwned>;
+
+    /// Withdraw `amount` wei of the paymaster deposit to `withdraw_address`
+    ///
+    /// Only available when withdrawals are enabled in the config.
+    #[method(name = "withdrawTo")]
+    async fn withdraw_to(
+        &self,
+        entry_point: String,
+        withdraw_address: String,
+        amount: String,
+    ) -> Result<DepositTransaction, ErrorObjectOwned>;
+
+    /// Transaction sent from the paymaster account, by the hash of any of its
+    /// submissions or its internal id; null when unknown
+    #[method(name = "getOutboundTx")]
+    async fn get_outbound_tx(&self, key: String) -> Result<Option<OutboundTx>, ErrorObjectOwned>;
+
+    /// Drop the cached SBT and PNTs balances of `sender` so the next sponsorship
+    /// reads them from chain again. Returns whether any were cached.
+    #[method(name = "invalidateSbtCache")]
+    async fn invalidate_sbt_cache(&self, sender: String) -> Result<bool, ErrorObjectOwned>;
+
+    /// Switch sponsorship signing to a new key without restarting
+    ///
+    /// `source` is `{"env": "VAR_NAME"}` or `{"kms": "<key id or ARN>"}`. The old key
+    /// keeps verifying for `grace_seconds`, or the configured grace period.
+    #[method(name = "rotatePaymasterKey")]
+    async fn rotate_paymaster_key(
+        &self,
+        source: KeySource,
+        grace_seconds: Option<u64>,
+    ) -> Result<KeyRotation, ErrorObjectOwned>;
+
+    /// Paymaster address with the active and retiring signing keys
+    #[method(name = "getSignerStatus")]
+    async fn get_signer_status(&self) -> Result<SignerStatus, ErrorObjectOwned>;
+
+    /// Stop assigning sponsorships to a signer pool key, or resume with
+    /// `draining` false. Sponsorships already signing with it finish normally.
+    #[method(name = "drainSigner")]
+    async fn drain_signer(
+        &self,
+        signer: String,
+        draining: Option<bool>,
+    ) -> Result<SignerStatus, ErrorObjectOwned>;
+
+    /// Paymaster account balance and EntryPoint deposits as last checked by the
+    /// balance monitor, with the low-water marks, the last automatic top-up and
+    /// the time of the next check
+    #[method(name = "getBalanceStatus")]
+    async fn get_balance_status(&self) -> Result<BalanceStatus, ErrorObjectOwned>;
+
+    /// Address of the key signing response attestations, for verifying them offline
+    #[method(name = "getAttestationKey")]
+    async fn get_attestation_key(&self) -> Result<AlloyAddress, ErrorObjectOwned>;
+
+    /// Sponsorship of an operation with its validity window and whether it was
+    /// mined, dropped or expired; null for operations not sponsored here
+    #[method(name = "getSponsorshipRecord")]
+    async fn get_sponsorship_record(
+        &self,
+        user_op_hash: String,
+    ) -> Result<Option<SponsorshipRecord>, ErrorObjectOwned>;
+
+    /// Sponsorships made between two unix timestamps, oldest first, optionally
+    /// only those with `status` (pending, mined, dropped or expired)
+    #[method(name = "listSponsorships")]
+    async fn list_sponsorships(
+        &self,
+        from_timestamp: u64,
+        to_timestamp: u64,
+        status: Option<String>,
+        page: Option<usize>,
+        page_size: Option<usize>,
+    ) -> Result<SponsorshipPage, ErrorObjectOwned>;
+
+    /// Preview sponsorship of a user operation without receiving paymaster data
+    ///
+    /// Runs the sponsorship checks and EntryPoint validation simulation, and reports
+    /// whether the policy would reject the operation, validation reverted (with the
+    /// revert reason), or it validates, with the gas used.
+    #[method(name = "simulateSponsorship")]
+    async fn simulate_sponsorship(
+        &self,
+        user_op: serde_json::Value,
+        entry_point: String,
+    ) -> Result<SponsorshipSimulation, ErrorObjectOwned>;
+
+    /// Evaluate every sponsorship against the policy file `file` of the
+    /// candidate directory as well, without enforcing it; replaces the
+    /// candidate loaded before
+    #[method(name = "loadCandidatePolicy")]
+    async fn load_candidate_policy(
+        &self,
+        file: String,
+    ) -> Result<PolicyShadowReport, ErrorObjectOwned>;
+
+    /// Requests the candidate policy would have rejected or allowed where the
+    /// active policy did not, per rule, with hashed samples of their senders
+    #[method(name = "getPolicyShadowReport")]
+    async fn get_policy_shadow_report(&self) -> Result<PolicyShadowReport, ErrorObjectOwned>;
+
+    /// Enforce the candidate policy instead of the active one, returning the
+    /// candidate's final shadow report
+    #[method(name = "promoteCandidatePolicy")]
+    async fn promote_candidate_policy(&self) -> Result<PolicyShadowReport, ErrorObjectOwned>;
+}
+
+/// Parse an address parameter of a deposit or admin method
+fn parse_address_param(name: &str, value: &str) -> Result<AlloyAddress, ErrorObjectOwned> {
+    AlloyAddress::from_str(value).map_err(|e| {
+        ErrorObjectOwned::owned(-32602, format!("Invalid {}", name), Some(e.to_string()))
+    })
+}
+
+/// JSON response of the sponsorship methods
+fn sponsor_response(sponsor_result: &PaymasterSponsorResult) -> String {
+    let mut response = serde_json::json!({
+        "paymasterAndData": format!("0x{}", hex::encode(&sponsor_result.paymaster_and_data)),
+        "validUntil": sponsor_result.valid_until,
+        "validAfter": sponsor_result.valid_after,
+        "preVerificationGas": sponsor_result.pre_verification_gas.map(|g| format!("0x{:x}", g)),
+        "verificationGasLimit": sponsor_result.verification_gas_limit_uo.map(|g| format!("0x{:x}", g)),
+        "callGasLimit": sponsor_result.call_gas_limit.map(|g| format!("0x{:x}", g)),
+        "maxFeePerGas": sponsor_result.max_fee_per_gas.map(|g| format!("0x{:x}", g)),
+        "maxPriorityFeePerGas": sponsor_result.max_priority_fee_per_gas.map(|g| format!("0x{:x}", g)),
+        "tokenQuote": sponsor_result.token_quote,
+    });
+    if sponsor_result.cached {
+        response["cached"] = serde_json::json!(true);
+    }
+    response.to_string()
+}
+
+/// Parse a wei amount given as a decimal or 0x-hex string
+fn parse_amount_param(value: &str) -> Result<U256, ErrorObjectOwned> {
+    U256::from_str(value)
+        .map_err(|e| ErrorObjectOwned::owned(-32602, "Invalid amount", Some(e.to_string())))
 }
 
 pub struct PaymasterRelayApiServerImpl {
@@ -222,15 +417,13 @@ impl PaymasterRelayApiServerImpl {
             validator: InputValidator::new(limits),
         }
     }
-}
 
-#[async_trait]
-impl PaymasterRelayApiServer for PaymasterRelayApiServerImpl {
-    async fn sponsor_user_operation(
+    /// Validate and convert the UserOperation and EntryPoint parameters
+    fn parse_user_operation_params(
         &self,
         user_op: serde_json::Value,
-        entry_point: String,
-    ) -> Result<String, ErrorObjectOwned> {
+        entry_point: &str,
+    ) -> Result<(UserOperationVariant, ethers::types::Address), ErrorObjectOwned> {
         // Enhanced input validation
         self.validator
             .validate_user_operation_json(&user_op)
@@ -244,7 +437,7 @@ impl PaymasterRelayApiServer for PaymasterRelayApiServerImpl {
 
         let entry_point_addr = self
             .validator
-            .validate_entry_point(&entry_point)
+            .validate_entry_point(entry_point)
             .map_err(|e| {
                 ErrorObjectOwned::owned(
                     -32602,
@@ -262,10 +455,15 @@ impl PaymasterRelayApiServer for PaymasterRelayApiServerImpl {
             )
         })?;
 
-        let user_op_variant: UserOperationVariant = json_user_op
-            .try_into()
+        let user_op_variant = json_user_op
+            .into_user_operation(self.service.chain_spec())
             .map_err(|e| ErrorObjectOwned::owned(-32602, "Invalid user operation data", Some(e)))?;
 
+        #[cfg(feature = "entrypoint-v0_8")]
+        let user_op_variant = self
+            .service
+            .operation_for_entry_point(user_op_variant, entry_point_addr);
+
         // Additional validation on converted UserOperation
         self.validator
             .validate_user_operation(&user_op_variant)
@@ -277,25 +475,194 @@ impl PaymasterRelayApiServer for PaymasterRelayApiServerImpl {
                 )
             })?;
 
+        Ok((user_op_variant, entry_point_addr))
+    }
+}
+
+#[async_trait]
+impl PaymasterRelayApiServer for PaymasterRelayApiServerImpl {
+    async fn sponsor_user_operation(
+        &self,
+        user_op: serde_json::Value,
+        entry_point: String,
+    ) -> Result<String, ErrorObjectOwned> {
+        let (user_op_variant, entry_point_addr) =
+            self.parse_user_operation_params(user_op, &entry_point)?;
+
         // Call the service
         match self
             .service
-            .sponsor_user_operation(user_op_variant, entry_point_addr)
+            .sponsor_user_operation(user_op_variant, entry_point_addr, SponsorOptions::default())
             .await
         {
-            Ok(sponsor_result) => {
-                // Return JSON response with paymaster data
-                let response = serde_json::json!({
-                    "paymasterAndData": format!("0x{}", hex::encode(&sponsor_result.paymaster_and_data)),
-                    "preVerificationGas": sponsor_result.pre_verification_gas.map(|g| format!("0x{:x}", g)),
-                    "verificationGasLimit": sponsor_result.verification_gas_limit_uo.map(|g| format!("0x{:x}", g)),
-                    "callGasLimit": sponsor_result.call_gas_limit.map(|g| format!("0x{:x}", g)),
-                });
-                Ok(response.to_string())
-            }
+            // Return JSON response with paymaster data
+            Ok(sponsor_result) => Ok(sponsor_response(&sponsor_result)),
             Err(e) => Err(e.into()),
         }
     }
+
+    async fn sponsor_user_operation_erc20(
+        &self,
+        user_op: serde_json::Value,
+        entry_point: String,
+        token: String,
+    ) -> Result<String, ErrorObjectOwned> {
+        let (user_op_variant, entry_point_addr) =
+            self.parse_user_operation_params(user_op, &entry_point)?;
+        let options = SponsorOptions {
+            token: Some(parse_address_param("token", &token)?),
+            ..Default::default()
+        };
+
+        let sponsor_result = self
+            .service
+            .sponsor_user_operation(user_op_variant, entry_point_addr, options)
+            .await?;
+        Ok(sponsor_response(&sponsor_result))
+    }
+
+    async fn get_supported_entry_points(
+        &self,
+    ) -> Result<Vec<SupportedEntryPoint>, ErrorObjectOwned> {
+        Ok(self.service.supported_entry_points()?)
+    }
+
+    async fn get_deposit_info(
+        &self,
+        entry_point: String,
+    ) -> Result<PaymasterDepositInfo, ErrorObjectOwned> {
+        let entry_point = parse_address_param("entry point", &entry_point)?;
+        Ok(self.service.get_deposit_info(entry_point).await?)
+    }
+
+    async fn deposit_to(
+        &self,
+        entry_point: String,
+        amount: String,
+    ) -> Result<DepositTransaction, ErrorObjectOwned> {
+        let entry_point = parse_address_param("entry point", &entry_point)?;
+        let amount = parse_amount_param(&amount)?;
+        Ok(self.service.deposit_to(entry_point, amount).await?)
+    }
+
+    async fn withdraw_to(
+        &self,
+        entry_point: String,
+        withdraw_address: String,
+        amount: String,
+    ) -> Result<DepositTransaction, ErrorObjectOwned> {
+        let entry_point = parse_address_param("entry point", &entry_point)?;
+        let withdraw_address = parse_address_param("withdraw address", &withdraw_address)?;
+        let amount = parse_amount_param(&amount)?;
+        Ok(self
+            .service
+            .withdraw_to(entry_point, withdraw_address, amount)
+            .await?)
+    }
+
+    async fn get_outbound_tx(&self, key: String) -> Result<Option<OutboundTx>, ErrorObjectOwned> {
+        Ok(self.service.outbound_tx(&key)?)
+    }
+
+    async fn invalidate_sbt_cache(&self, sender: String) -> Result<bool, ErrorObjectOwned> {
+        let sender = parse_address_param("sender", &sender)?;
+        Ok(self.service.invalidate_sbt_cache(sender)?)
+    }
+
+    async fn rotate_paymaster_key(
+        &self,
+        source: KeySource,
+        grace_seconds: Option<u64>,
+    ) -> Result<KeyRotation, ErrorObjectOwned> {
+        Ok(self
+            .service
+            .rotate_paymaster_key(&source, grace_seconds.map(std::time::Duration::from_secs))
+            .await?)
+    }
+
+    async fn get_signer_status(&self) -> Result<SignerStatus, ErrorObjectOwned> {
+        Ok(self.service.signer_status().await)
+    }
+
+    async fn drain_signer(
+        &self,
+        signer: String,
+        draining: Option<bool>,
+    ) -> Result<SignerStatus, ErrorObjectOwned> {
+        let signer = parse_address_param("signer", &signer)?;
+        Ok(self
+            .service
+            .drain_signer(signer, draining.unwrap_or(true))
+            .await?)
+    }
+
+    async fn get_balance_status(&self) -> Result<BalanceStatus, ErrorObjectOwned> {
+        Ok(self.service.balance_status()?)
+    }
+
+    async fn get_attestation_key(&self) -> Result<AlloyAddress, ErrorObjectOwned> {
+        Ok(self.service.attestation_address()?)
+    }
+
+    async fn get_sponsorship_record(
+        &self,
+        user_op_hash: String,
+    ) -> Result<Option<SponsorshipRecord>, ErrorObjectOwned> {
+        let user_op_hash = B256::from_str(&user_op_hash).map_err(|e| {
+            ErrorObjectOwned::owned(-32602, "Invalid userOpHash", Some(e.to_string()))
+        })?;
+        Ok(self.service.sponsorship_record(user_op_hash)?)
+    }
+
+    async fn list_sponsorships(
+        &self,
+        from_timestamp: u64,
+        to_timestamp: u64,
+        status: Option<String>,
+        page: Option<usize>,
+        page_size: Option<usize>,
+    ) -> Result<SponsorshipPage, ErrorObjectOwned> {
+        let status = status
+            .map(|status| SponsorshipStatus::from_str(&status))
+            .transpose()
+            .map_err(|e| ErrorObjectOwned::owned(-32602, "Invalid status", Some(e)))?;
+        let query = SponsorshipQuery {
+            from: from_timestamp,
+            to: to_timestamp,
+            status,
+            page: page.unwrap_or_default(),
+            page_size: page_size.unwrap_or(DEFAULT_PAGE_SIZE),
+        };
+        Ok(self.service.list_sponsorships(&query)?)
+    }
+
+    async fn simulate_sponsorship(
+        &self,
+        user_op: serde_json::Value,
+        entry_point: String,
+    ) -> Result<SponsorshipSimulation, ErrorObjectOwned> {
+        let (user_op_variant, entry_point_addr) =
+            self.parse_user_operation_params(user_op, &entry_point)?;
+        Ok(self
+            .service
+            .simulate_sponsorship(user_op_variant, entry_point_addr)
+            .await?)
+    }
+
+    async fn load_candidate_policy(
+        &self,
+        file: String,
+    ) -> Result<PolicyShadowReport, ErrorObjectOwned> {
+        Ok(self.service.load_named_candidate_policy(&file)?)
+    }
+
+    async fn get_policy_shadow_report(&self) -> Result<PolicyShadowReport, ErrorObjectOwned> {
+        Ok(self.service.policy_shadow_report())
+    }
+
+    async fn promote_candidate_policy(&self) -> Result<PolicyShadowReport, ErrorObjectOwned> {
+        Ok(self.service.promote_candidate_policy()?)
+    }
 }
 
 /// Helper function to convert JsonUserOperation to UserOperationVariant
@@ -0,0 +1,318 @@
+// paymaster-relay/src/sbt.rs
+// SBT and PNTs holder gating for sponsorship. Eligibility is read on chain with
+// balanceOf on the configured SBT (ERC-721) and PNTs (ERC-20) contracts and cached
+// per sender.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use alloy_primitives::{Address, Bytes, U256};
+use async_trait::async_trait;
+use rundler_provider::{EvmProvider, TransactionBuilder, TransactionRequest};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::error::PaymasterError;
+
+/// `balanceOf(address)` selector, shared by ERC-20 and ERC-721
+const BALANCE_OF_SELECTOR: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+
+/// SBT gating configuration (`[paymaster_relay.sbt]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SBTValidatorConfig {
+    /// Require senders to hold the configured tokens before sponsoring
+    pub enabled: bool,
+    /// SBT (ERC-721) contract, not checked when unset
+    pub sbt_contract: Option<Address>,
+    /// PNTs (ERC-20) contract, not checked when unset
+    pub pnts_contract: Option<Address>,
+    /// Minimum number of SBTs the sender must hold
+    pub min_sbt_balance: U256,
+    /// Minimum PNTs balance of the sender, in token base units
+    pub min_pnts_balance: U256,
+    /// Seconds a sender's balances are reused before being read again
+    pub cache_ttl_seconds: u64,
+    /// Sponsor when balances cannot be read instead of rejecting
+    pub fail_open: bool,
+}
+
+impl Default for SBTValidatorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sbt_contract: None,
+            pnts_contract: None,
+            min_sbt_balance: U256::from(1u64),
+            min_pnts_balance: U256::ZERO,
+            cache_ttl_seconds: 300,
+            fail_open: false,
+        }
+    }
+}
+
+/// Token balances of one sender
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HolderBalances {
+    /// SBTs held, zero when no SBT contract is configured
+    pub sbt: U256,
+    /// PNTs held, zero when no PNTs contract is configured
+    pub pnts: U256,
+}
+
+/// Chain access needed to read token balances
+#[async_trait]
+pub trait TokenBalanceReader: Send + Sync {
+    /// `balanceOf(owner)` on `token`
+    async fn balance_of(&self, token: Address, owner: Address) -> Result<U256, PaymasterError>;
+}
+
+/// [`TokenBalanceReader`] calling `balanceOf` through a node provider
+pub struct ProviderTokenBalances<P> {
+    provider: P,
+}
+
+impl<P> ProviderTokenBalances<P> {
+    pub fn new(provider: P) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> TokenBalanceReader for ProviderTokenBalances<P> {
+    async fn balance_of(&self, token: Address, owner: Address) -> Result<U256, PaymasterError> {
+        let mut data = BALANCE_OF_SELECTOR.to_vec();
+        data.extend_from_slice(&[0u8; 12]);
+        data.extend_from_slice(owner.as_slice());
+
+        let tx = TransactionRequest::default()
+            .to(token)
+            .with_input(Bytes::from(data));
+        let result = self.provider.call(tx, None, None).await.map_err(|e| {
+            PaymasterError::ChainError(format!("balanceOf on {} failed: {}", token, e))
+        })?;
+
+        if result.len() < 32 {
+            return Err(PaymasterError::ChainError(format!(
+                "Invalid balanceOf response from {}: {}",
+                token, result
+            )));
+        }
+        Ok(U256::from_be_slice(&result[..32]))
+    }
+}
+
+/// Checks that senders hold enough SBTs and PNTs to be sponsored
+///
+/// Clones share the balance cache.
+#[derive(Clone)]
+pub struct SBTValidator {
+    reader: Arc<dyn TokenBalanceReader>,
+    config: SBTValidatorConfig,
+    cache: Arc<Mutex<HashMap<Address, (HolderBalances, Instant)>>>,
+}
+
+impl fmt::Debug for SBTValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SBTValidator")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl SBTValidator {
+    pub fn new(reader: Arc<dyn TokenBalanceReader>, config: SBTValidatorConfig) -> Self {
+        Self {
+            reader,
+            config,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn config(&self) -> &SBTValidatorConfig {
+        &self.config
+    }
+
+    /// Balances of `sender`, from the cache while they are fresh
+    pub async fn balances(&self, sender: Address) -> Result<HolderBalances, PaymasterError> {
+        let ttl = Duration::from_secs(self.config.cache_ttl_seconds);
+        let cached = self.cache.lock().unwrap().get(&sender).copied();
+        if let Some((balances, fetched_at)) = cached {
+            if fetched_at.elapsed() < ttl {
+                return Ok(balances);
+            }
+        }
+
+        let sbt = match self.config.sbt_contract {
+            Some(token) => self.reader.balance_of(token, sender).await?,
+            None => U256::ZERO,
+        };
+        let pnts = match self.config.pnts_contract {
+            Some(token) => self.reader.balance_of(token, sender).await?,
+            None => U256::ZERO,
+        };
+        let balances = HolderBalances { sbt, pnts };
+        debug!("Sender {} balances: {:?}", sender, balances);
+
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(sender, (balances, Instant::now()));
+        Ok(balances)
+    }
+
+    /// Reject `sender` unless it meets the SBT and PNTs thresholds
+    pub async fn check(&self, sender: Address) -> Result<(), PaymasterError> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        let balances = match self.balances(sender).await {
+            Ok(balances) => balances,
+            Err(e) if self.config.fail_open => {
+                warn!("Sponsoring {} without SBT check: {}", sender, e);
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+
+        if self.config.sbt_contract.is_some() && balances.sbt < self.config.min_sbt_balance {
+            return Err(PaymasterError::PolicyRejected(format!(
+                "Sender {} holds {} SBT, {} required",
+                sender, balances.sbt, self.config.min_sbt_balance
+            )));
+        }
+        if self.config.pnts_contract.is_some() && balances.pnts < self.config.min_pnts_balance {
+            return Err(PaymasterError::PolicyRejected(format!(
+                "Sender {} holds {} PNTs, {} required",
+                sender, balances.pnts, self.config.min_pnts_balance
+            )));
+        }
+        Ok(())
+    }
+
+    /// Drop the cached balances of `sender`, returning whether any were cached
+    pub fn invalidate(&self, sender: Address) -> bool {
+        self.cache.lock().unwrap().remove(&sender).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, TxKind};
+    use rundler_provider::{MockEvmProvider, ProviderError};
+
+    use super::*;
+
+    const SBT: Address = address!("1000000000000000000000000000000000000001");
+    const PNTS: Address = address!("2000000000000000000000000000000000000002");
+    const SENDER: Address = address!("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+
+    fn config() -> SBTValidatorConfig {
+        SBTValidatorConfig {
+            enabled: true,
+            sbt_contract: Some(SBT),
+            pnts_contract: Some(PNTS),
+            min_sbt_balance: U256::from(1u64),
+            min_pnts_balance: U256::from(100u64),
+            ..Default::default()
+        }
+    }
+
+    /// Provider answering balanceOf with fixed SBT and PNTs balances
+    fn provider(sbt: u64, pnts: u64) -> MockEvmProvider {
+        let mut provider = MockEvmProvider::new();
+        provider.expect_call().returning(move |tx, _, _| {
+            let balance = match tx.to {
+                Some(TxKind::Call(to)) if to == SBT => sbt,
+                Some(TxKind::Call(to)) if to == PNTS => pnts,
+                other => panic!("unexpected call to {:?}", other),
+            };
+            Ok(Bytes::from(U256::from(balance).to_be_bytes::<32>()))
+        });
+        provider
+    }
+
+    fn failing_provider() -> MockEvmProvider {
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_call()
+            .returning(|_, _, _| Err(ProviderError::Other(anyhow::anyhow!("connection refused"))));
+        provider
+    }
+
+    fn validator(provider: MockEvmProvider, config: SBTValidatorConfig) -> SBTValidator {
+        SBTValidator::new(Arc::new(ProviderTokenBalances::new(provider)), config)
+    }
+
+    #[tokio::test]
+    async fn test_holder_accepted() {
+        let validator = validator(provider(1, 500), config());
+
+        validator.check(SENDER).await.unwrap();
+        assert_eq!(
+            validator.balances(SENDER).await.unwrap(),
+            HolderBalances {
+                sbt: U256::from(1u64),
+                pnts: U256::from(500u64)
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_non_holder_rejected() {
+        let validator = validator(provider(0, 500), config());
+
+        let err = validator.check(SENDER).await.unwrap_err();
+        assert!(matches!(err, PaymasterError::PolicyRejected(ref m) if m.contains("SBT")));
+    }
+
+    #[tokio::test]
+    async fn test_pnts_below_threshold_rejected() {
+        let validator = validator(provider(1, 99), config());
+
+        let err = validator.check(SENDER).await.unwrap_err();
+        assert!(matches!(err, PaymasterError::PolicyRejected(ref m) if m.contains("PNTs")));
+    }
+
+    #[tokio::test]
+    async fn test_rpc_error_fail_closed_and_open() {
+        let closed = validator(failing_provider(), config());
+        assert!(matches!(
+            closed.check(SENDER).await,
+            Err(PaymasterError::ChainError(_))
+        ));
+
+        let open = validator(
+            failing_provider(),
+            SBTValidatorConfig {
+                fail_open: true,
+                ..config()
+            },
+        );
+        open.check(SENDER).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_balances_cached_until_invalidated() {
+        let mut provider = MockEvmProvider::new();
+        // One call per contract for the first lookup and again after invalidation
+        provider
+            .expect_call()
+            .times(4)
+            .returning(|_, _, _| Ok(Bytes::from(U256::from(1_000u64).to_be_bytes::<32>())));
+        let validator = validator(provider, config());
+
+        validator.check(SENDER).await.unwrap();
+        validator.check(SENDER).await.unwrap();
+
+        assert!(validator.invalidate(SENDER));
+        assert!(!validator.invalidate(SENDER));
+        validator.check(SENDER).await.unwrap();
+    }
+}
@@ -0,0 +1,649 @@
+// paymaster-relay/src/secrets.rs
+// Secrets file encrypted at rest: named secrets (paymaster, signer and pool keys)
+// sealed with a passphrase, so no private key has to sit in a plaintext .env.
+//
+// The file is a JSON envelope. The key is derived from the passphrase with
+// argon2id (memory-hard, parameters stored in the file) and the secrets, a JSON
+// object of name to value, are sealed with AES-256-GCM. The envelope names its
+// KDF, so files written with scrypt before argon2id stay readable. The format
+// version, KDF and its parameters are authenticated as associated data, so a
+// wrong passphrase and any modified byte both fail with
+// `SecretsError::Authentication`.
+//
+// Decrypted values only live in `SecretString`s, which are zeroized on drop;
+// the decrypted buffer is zeroized as well.
+
+use std::{collections::BTreeMap, fs, path::Path, sync::OnceLock};
+
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::warn;
+use zeroize::Zeroizing;
+
+/// Environment variable holding the secrets file passphrase
+pub const PASSPHRASE_VAR: &str = "SUPER_RELAY_SECRETS_PASSPHRASE";
+
+const FORMAT_VERSION: u32 = 1;
+const ARGON2ID: &str = "argon2id";
+const SCRYPT: &str = "scrypt";
+const CIPHER: &str = "aes-256-gcm";
+const SALT_LEN: usize = 16;
+/// Largest accepted argon2id memory cost, 1 GiB
+const MAX_MEMORY_KIB: u32 = 1 << 20;
+/// Largest accepted scrypt cost, 1 GiB of memory with r = 8
+const MAX_LOG_N: u8 = 20;
+
+/// Secrets installed for the process by [`install`]
+static INSTALLED: OnceLock<Secrets> = OnceLock::new();
+
+/// Key derivation function and cost parameters of a secrets file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KdfParams {
+    /// argon2id, written by default
+    Argon2id {
+        /// Memory cost in KiB
+        memory_kib: u32,
+        /// Number of passes over the memory
+        iterations: u32,
+        /// Number of lanes
+        parallelism: u32,
+    },
+    /// scrypt, read from files written before argon2id
+    Scrypt {
+        /// log2 of the CPU/memory cost N
+        log_n: u8,
+        /// Block size
+        r: u32,
+        /// Parallelism
+        p: u32,
+    },
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        // RFC 9106's second recommended option, 64 MiB
+        KdfParams::Argon2id {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 4,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Name of the KDF in the envelope
+    fn kdf(&self) -> &'static str {
+        match self {
+            KdfParams::Argon2id { .. } => ARGON2ID,
+            KdfParams::Scrypt { .. } => SCRYPT,
+        }
+    }
+
+    /// Cost parameters as authenticated in the associated data
+    fn costs(&self) -> [u32; 3] {
+        match *self {
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => [memory_kib, iterations, parallelism],
+            KdfParams::Scrypt { log_n, r, p } => [log_n.into(), r, p],
+        }
+    }
+}
+
+/// Failure to read, decrypt or write a secrets file
+#[derive(Debug, Error)]
+pub enum SecretsError {
+    #[error("Failed to read secrets file {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Not a SuperRelay secrets file: {0}")]
+    Format(String),
+
+    #[error("Unsupported secrets file version {0}")]
+    Version(u32),
+
+    #[error("Invalid key derivation parameters: {0}")]
+    Kdf(String),
+
+    #[error("Secrets passphrase is empty")]
+    EmptyPassphrase,
+
+    #[error("Secrets file authentication failed: wrong passphrase or corrupted file")]
+    Authentication,
+}
+
+/// On-disk form of a secrets file, holding the parameters of its KDF
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    version: u32,
+    kdf: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    memory_kib: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    iterations: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parallelism: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    log_n: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    r: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    p: Option<u32>,
+    salt: String,
+    cipher: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Named secrets decrypted from a secrets file
+#[derive(Debug, Default)]
+pub struct Secrets {
+    entries: BTreeMap<String, SecretString>,
+}
+
+impl Secrets {
+    /// Secrets holding `entries`, by name
+    pub fn new(entries: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self {
+            entries: entries
+                .into_iter()
+                .map(|(name, value)| (name, SecretString::new(value.into())))
+                .collect(),
+        }
+    }
+
+    /// Secret stored under `name`
+    pub fn get(&self, name: &str) -> Option<&SecretString> {
+        self.entries.get(name)
+    }
+
+    /// Names of the stored secrets, sorted
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// Number of stored secrets
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no secret is stored
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Secrets file content sealing these secrets with `passphrase`
+    pub fn encrypt(
+        &self,
+        passphrase: &SecretString,
+        params: KdfParams,
+    ) -> Result<String, SecretsError> {
+        let rng = SystemRandom::new();
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill(&mut salt)
+            .and_then(|_| rng.fill(&mut nonce))
+            .map_err(|_| SecretsError::Kdf("system randomness unavailable".to_string()))?;
+
+        let key = derive_key(passphrase, &salt, params)?;
+        let plaintext: BTreeMap<&str, &str> = self
+            .entries
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.expose_secret()))
+            .collect();
+        let mut in_out =
+            Zeroizing::new(serde_json::to_vec(&plaintext).expect("string map serializes to JSON"));
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(associated_data(params)),
+            &mut *in_out,
+        )
+        .map_err(|_| SecretsError::Authentication)?;
+
+        let (memory_kib, iterations, parallelism, log_n, r, p) = match params {
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => (
+                Some(memory_kib),
+                Some(iterations),
+                Some(parallelism),
+                None,
+                None,
+                None,
+            ),
+            KdfParams::Scrypt { log_n, r, p } => (None, None, None, Some(log_n), Some(r), Some(p)),
+        };
+        let envelope = Envelope {
+            version: FORMAT_VERSION,
+            kdf: params.kdf().to_string(),
+            memory_kib,
+            iterations,
+            parallelism,
+            log_n,
+            r,
+            p,
+            salt: hex::encode(salt),
+            cipher: CIPHER.to_string(),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(&*in_out),
+        };
+        Ok(serde_json::to_string_pretty(&envelope).expect("envelope serializes to JSON") + "\n")
+    }
+
+    /// Open secrets file `content` with `passphrase`
+    pub fn decrypt(content: &str, passphrase: &SecretString) -> Result<Self, SecretsError> {
+        let envelope: Envelope =
+            serde_json::from_str(content).map_err(|e| SecretsError::Format(e.to_string()))?;
+        if envelope.version != FORMAT_VERSION {
+            return Err(SecretsError::Version(envelope.version));
+        }
+        let params = envelope.kdf_params()?;
+        let salt = decode_hex("salt", &envelope.salt)?;
+        let nonce = Nonce::try_assume_unique_for_key(&decode_hex("nonce", &envelope.nonce)?)
+            .map_err(|_| SecretsError::Format(format!("nonce must be {} bytes", NONCE_LEN)))?;
+        let mut in_out = Zeroizing::new(decode_hex("ciphertext", &envelope.ciphertext)?);
+
+        let key = derive_key(passphrase, &salt, params)?;
+        let plaintext = key
+            .open_in_place(nonce, Aad::from(associated_data(params)), &mut in_out[..])
+            .map_err(|_| SecretsError::Authentication)?;
+        let entries: BTreeMap<String, String> = serde_json::from_slice(plaintext)
+            .map_err(|e| SecretsError::Format(format!("invalid secrets: {}", e)))?;
+        Ok(Self::new(entries))
+    }
+
+    /// Read and open the secrets file at `path`
+    pub fn load(path: &Path, passphrase: &SecretString) -> Result<Self, SecretsError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| SecretsError::Io(path.display().to_string(), e))?;
+        Self::decrypt(&content, passphrase)
+    }
+
+    /// The secrets as dotenv `NAME="value"` lines
+    pub fn to_dotenv(&self) -> SecretString {
+        let mut out = String::new();
+        for (name, value) in &self.entries {
+            let escaped = value
+                .expose_secret()
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            out.push_str(&format!("{}=\"{}\"\n", name, escaped));
+        }
+        SecretString::new(out.into())
+    }
+}
+
+impl Envelope {
+    /// KDF named by the envelope, with its parameters
+    fn kdf_params(&self) -> Result<KdfParams, SecretsError> {
+        match (self.kdf.as_str(), self.cipher.as_str()) {
+            (ARGON2ID, CIPHER) => Ok(KdfParams::Argon2id {
+                memory_kib: self.param("memoryKib", self.memory_kib)?,
+                iterations: self.param("iterations", self.iterations)?,
+                parallelism: self.param("parallelism", self.parallelism)?,
+            }),
+            (SCRYPT, CIPHER) => Ok(KdfParams::Scrypt {
+                log_n: self.param("logN", self.log_n)?,
+                r: self.param("r", self.r)?,
+                p: self.param("p", self.p)?,
+            }),
+            _ => Err(SecretsError::Format(format!(
+                "unsupported algorithms {} / {}",
+                self.kdf, self.cipher
+            ))),
+        }
+    }
+
+    fn param<T>(&self, name: &str, value: Option<T>) -> Result<T, SecretsError> {
+        value
+            .ok_or_else(|| SecretsError::Format(format!("missing {} parameter {}", self.kdf, name)))
+    }
+}
+
+/// Make `secrets` the process-wide source of [`var`]; returns false when
+/// secrets were already installed
+pub fn install(secrets: Secrets) -> bool {
+    INSTALLED.set(secrets).is_ok()
+}
+
+/// Secrets installed with [`install`]
+pub fn installed() -> Option<&'static Secrets> {
+    INSTALLED.get()
+}
+
+/// Secret `name` from the installed secrets file, falling back to the plaintext
+/// environment with a warning
+pub fn var(name: &str) -> Option<SecretString> {
+    if let Some(value) = installed().and_then(|secrets| secrets.get(name)) {
+        return Some(SecretString::new(value.expose_secret().into()));
+    }
+    let value = std::env::var(name).ok()?;
+    warn!(
+        "⚠️  {} read from the plaintext environment; store it in an encrypted secrets file (super-relay secrets encrypt)",
+        name
+    );
+    Some(SecretString::new(value.into()))
+}
+
+fn derive_key(
+    passphrase: &SecretString,
+    salt: &[u8],
+    params: KdfParams,
+) -> Result<LessSafeKey, SecretsError> {
+    if passphrase.expose_secret().is_empty() {
+        return Err(SecretsError::EmptyPassphrase);
+    }
+    let password = passphrase.expose_secret().as_bytes();
+    let mut key = Zeroizing::new([0u8; 32]);
+    match params {
+        KdfParams::Argon2id {
+            memory_kib,
+            iterations,
+            parallelism,
+        } => {
+            if memory_kib > MAX_MEMORY_KIB {
+                return Err(SecretsError::Kdf(format!(
+                    "argon2id memory {} KiB exceeds {}",
+                    memory_kib, MAX_MEMORY_KIB
+                )));
+            }
+            let argon2_params =
+                argon2::Params::new(memory_kib, iterations, parallelism, Some(key.len()))
+                    .map_err(|e| SecretsError::Kdf(format!("argon2id {}", e)))?;
+            argon2::Argon2::new(
+                argon2::Algorithm::Argon2id,
+                argon2::Version::V0x13,
+                argon2_params,
+            )
+            .hash_password_into(password, salt, &mut *key)
+            .map_err(|e| SecretsError::Kdf(format!("argon2id {}", e)))?;
+        }
+        KdfParams::Scrypt { log_n, r, p } => {
+            if log_n > MAX_LOG_N {
+                return Err(SecretsError::Kdf(format!(
+                    "scrypt log_n {} exceeds {}",
+                    log_n, MAX_LOG_N
+                )));
+            }
+            let scrypt_params = scrypt::Params::new(log_n, r, p)
+                .map_err(|e| SecretsError::Kdf(format!("scrypt {}", e)))?;
+            scrypt::scrypt(password, salt, &scrypt_params, &mut *key)
+                .map_err(|e| SecretsError::Kdf(format!("scrypt {}", e)))?;
+        }
+    }
+    let key = UnboundKey::new(&AES_256_GCM, &*key).map_err(|_| SecretsError::Authentication)?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn associated_data(params: KdfParams) -> Vec<u8> {
+    let costs = params.costs().map(|cost| cost.to_string()).join(":");
+    format!(
+        "superrelay-secrets:v{}:{}:{}:{}",
+        FORMAT_VERSION,
+        params.kdf(),
+        costs,
+        CIPHER
+    )
+    .into_bytes()
+}
+
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>, SecretsError> {
+    hex::decode(value).map_err(|e| SecretsError::Format(format!("invalid {}: {}", field, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    const PAYMASTER_KEY: &str =
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const POOL_KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+    /// Cheap argon2id cost, so tests stay fast in debug builds
+    const TEST_PARAMS: KdfParams = KdfParams::Argon2id {
+        memory_kib: 1024,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    /// Cheap scrypt cost
+    const SCRYPT_TEST_PARAMS: KdfParams = KdfParams::Scrypt {
+        log_n: 10,
+        r: 8,
+        p: 1,
+    };
+
+    /// PAYMASTER_PRIVATE_KEY sealed with "correct horse" and SCRYPT_TEST_PARAMS,
+    /// as written before argon2id
+    const SCRYPT_FILE: &str = r#"{
+  "version": 1,
+  "kdf": "scrypt",
+  "logN": 10,
+  "r": 8,
+  "p": 1,
+  "salt": "000102030405060708090a0b0c0d0e0f",
+  "cipher": "aes-256-gcm",
+  "nonce": "6465666768696a6b6c6d6e6f",
+  "ciphertext": "d0ca17a5c43c0b1352e8d3c672ea7ea830449d27d2435913aa714403a88baf9c68f6a1febd959ea6d8de850ff0bb5ccd7a3b3ee679736e30e8904774eeec0b3568901fcf3b3890587827ad28c3e33a19af5ca2ba7a0a2d16b6b2f847adab9168edbd049b51e8730bbce9c889d722"
+}
+"#;
+
+    fn passphrase(value: &str) -> SecretString {
+        SecretString::new(value.into())
+    }
+
+    fn secrets() -> Secrets {
+        Secrets::new([
+            (
+                "PAYMASTER_PRIVATE_KEY".to_string(),
+                PAYMASTER_KEY.to_string(),
+            ),
+            ("PAYMASTER_POOL_KEY_1".to_string(), POOL_KEY.to_string()),
+            (
+                "QUOTED".to_string(),
+                "line one\nsays \"hi\" \\ bye".to_string(),
+            ),
+        ])
+    }
+
+    fn encrypted() -> String {
+        secrets()
+            .encrypt(&passphrase("correct horse"), TEST_PARAMS)
+            .unwrap()
+    }
+
+    /// `content` with one field of the JSON envelope replaced
+    fn with_field(content: &str, field: &str, value: Value) -> String {
+        let mut envelope: Value = serde_json::from_str(content).unwrap();
+        envelope[field] = value;
+        envelope.to_string()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("superrelay.secrets");
+        let content = encrypted();
+        std::fs::write(&path, &content).unwrap();
+
+        // No secret appears in the file
+        assert!(!content.contains(&PAYMASTER_KEY[2..]));
+        assert!(!content.contains("PAYMASTER_PRIVATE_KEY"));
+
+        let secrets = Secrets::load(&path, &passphrase("correct horse")).unwrap();
+        assert_eq!(
+            secrets.names().collect::<Vec<_>>(),
+            ["PAYMASTER_POOL_KEY_1", "PAYMASTER_PRIVATE_KEY", "QUOTED"]
+        );
+        assert_eq!(
+            secrets
+                .get("PAYMASTER_PRIVATE_KEY")
+                .unwrap()
+                .expose_secret(),
+            PAYMASTER_KEY
+        );
+        assert_eq!(
+            secrets.get("PAYMASTER_POOL_KEY_1").unwrap().expose_secret(),
+            POOL_KEY
+        );
+        assert!(secrets.get("SIGNER_PRIVATE_KEYS").is_none());
+
+        // Each encryption uses a fresh salt and nonce
+        assert_ne!(encrypted(), content);
+    }
+
+    #[test]
+    fn test_argon2id_by_default() {
+        let content = secrets()
+            .encrypt(&passphrase("correct horse"), KdfParams::default())
+            .unwrap();
+        let envelope: Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(envelope["kdf"], "argon2id");
+        assert_eq!(envelope["memoryKib"], 64 * 1024);
+        assert_eq!(envelope["iterations"], 3);
+        assert_eq!(envelope["parallelism"], 4);
+        assert!(envelope.get("logN").is_none());
+        assert_eq!(envelope["cipher"], "aes-256-gcm");
+    }
+
+    #[test]
+    fn test_scrypt_files_stay_readable() {
+        let secrets = Secrets::decrypt(SCRYPT_FILE, &passphrase("correct horse")).unwrap();
+        assert_eq!(
+            secrets
+                .get("PAYMASTER_PRIVATE_KEY")
+                .unwrap()
+                .expose_secret(),
+            PAYMASTER_KEY
+        );
+        assert!(matches!(
+            Secrets::decrypt(SCRYPT_FILE, &passphrase("wrong horse")),
+            Err(SecretsError::Authentication)
+        ));
+
+        // Still written on request
+        let content = secrets()
+            .encrypt(&passphrase("correct horse"), SCRYPT_TEST_PARAMS)
+            .unwrap();
+        let envelope: Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(envelope["kdf"], "scrypt");
+        assert!(envelope.get("memoryKib").is_none());
+        let secrets = Secrets::decrypt(&content, &passphrase("correct horse")).unwrap();
+        assert_eq!(secrets.len(), 3);
+    }
+
+    #[test]
+    fn test_dotenv_output_escapes_values() {
+        let dotenv = secrets().to_dotenv();
+        assert_eq!(
+            dotenv.expose_secret(),
+            format!(
+                "PAYMASTER_POOL_KEY_1=\"{}\"\nPAYMASTER_PRIVATE_KEY=\"{}\"\nQUOTED=\"line one\\nsays \\\"hi\\\" \\\\ bye\"\n",
+                POOL_KEY, PAYMASTER_KEY
+            )
+        );
+    }
+
+    #[test]
+    fn test_wrong_passphrase_fails_authentication() {
+        let content = encrypted();
+        assert!(matches!(
+            Secrets::decrypt(&content, &passphrase("wrong horse")),
+            Err(SecretsError::Authentication)
+        ));
+        assert!(matches!(
+            Secrets::decrypt(&content, &passphrase("")),
+            Err(SecretsError::EmptyPassphrase)
+        ));
+    }
+
+    #[test]
+    fn test_tampered_file_fails_authentication() {
+        let content = encrypted();
+        let envelope: Value = serde_json::from_str(&content).unwrap();
+        let ciphertext = envelope["ciphertext"].as_str().unwrap();
+
+        // Flip one bit at the start and middle of the ciphertext and in the tag
+        let len = ciphertext.len() / 2;
+        for index in [0, len / 2, len - 1] {
+            let mut bytes = hex::decode(ciphertext).unwrap();
+            bytes[index] ^= 1;
+            let tampered = with_field(&content, "ciphertext", hex::encode(&bytes).into());
+            let err = Secrets::decrypt(&tampered, &passphrase("correct horse")).unwrap_err();
+            assert!(matches!(err, SecretsError::Authentication), "{}", index);
+            assert_eq!(
+                err.to_string(),
+                "Secrets file authentication failed: wrong passphrase or corrupted file"
+            );
+        }
+
+        // Truncated ciphertext, other salt, nonce or KDF cost
+        let truncated = with_field(&content, "ciphertext", ciphertext[..40].into());
+        let salt = with_field(&content, "salt", "00".repeat(16).into());
+        let nonce = with_field(&content, "nonce", "00".repeat(12).into());
+        let cost = with_field(&content, "iterations", 2.into());
+        let scrypt_cost = with_field(SCRYPT_FILE, "r", 4.into());
+        for tampered in [truncated, salt, nonce, cost, scrypt_cost] {
+            assert!(matches!(
+                Secrets::decrypt(&tampered, &passphrase("correct horse")),
+                Err(SecretsError::Authentication)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_malformed_files_rejected() {
+        let content = encrypted();
+        let mut missing_iterations: Value = serde_json::from_str(&content).unwrap();
+        missing_iterations
+            .as_object_mut()
+            .unwrap()
+            .remove("iterations");
+        let cases = [
+            "PAYMASTER_PRIVATE_KEY=0x1234".to_string(),
+            with_field(&content, "version", 2.into()),
+            with_field(&content, "cipher", "chacha20-poly1305".into()),
+            with_field(&content, "kdf", "bcrypt".into()),
+            missing_iterations.to_string(),
+            with_field(&content, "nonce", "zz".into()),
+            with_field(&content, "nonce", "00".into()),
+            with_field(&content, "memoryKib", (2 << 20).into()),
+            with_field(SCRYPT_FILE, "logN", 40.into()),
+        ];
+        let expected = [
+            "Not a SuperRelay secrets file: expected value at line 1 column 1",
+            "Unsupported secrets file version 2",
+            "Not a SuperRelay secrets file: unsupported algorithms argon2id / chacha20-poly1305",
+            "Not a SuperRelay secrets file: unsupported algorithms bcrypt / aes-256-gcm",
+            "Not a SuperRelay secrets file: missing argon2id parameter iterations",
+            "Not a SuperRelay secrets file: invalid nonce: Invalid character 'z' at position 0",
+            "Not a SuperRelay secrets file: nonce must be 12 bytes",
+            "Invalid key derivation parameters: argon2id memory 2097152 KiB exceeds 1048576",
+            "Invalid key derivation parameters: scrypt log_n 40 exceeds 20",
+        ];
+        for (content, expected) in cases.iter().zip(expected) {
+            let err = Secrets::decrypt(content, &passphrase("correct horse")).unwrap_err();
+            assert_eq!(err.to_string(), expected);
+        }
+
+        let missing = tempdir().unwrap().path().join("missing.secrets");
+        assert!(matches!(
+            Secrets::load(&missing, &passphrase("correct horse")),
+            Err(SecretsError::Io(..))
+        ));
+    }
+}
@@ -0,0 +1,473 @@
+// paymaster-relay/src/sender_lock.rs
+// Per sender and nonce serialization of sponsorships, so concurrent requests for one
+// operation sign and account it once.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use alloy_primitives::{Address, B256, U256};
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+
+use crate::{error::PaymasterError, service::PaymasterSponsorResult};
+
+/// Settings of the per sender and nonce sponsorship locks
+#[derive(Debug, Clone, Deserialize)]
+pub struct SenderLockConfig {
+    /// Sender and nonce pairs sponsored at once; requests for further pairs
+    /// are rejected until one completes
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_max_entries() -> usize {
+    10_000
+}
+
+impl Default for SenderLockConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: default_max_entries(),
+        }
+    }
+}
+
+/// Sponsorship in progress for a sender and nonce, shared by every request
+/// for the same operation
+pub type SenderSlot = Arc<OnceCell<PaymasterSponsorResult>>;
+
+type LockKey = (Address, U256);
+
+#[derive(Debug)]
+struct Entry {
+    operation: B256,
+    holders: usize,
+    slot: SenderSlot,
+}
+
+/// Sponsorships in progress by sender and nonce
+///
+/// Requests for an operation already being sponsored share its
+/// [`SenderSlot`] and wait for the single signature, while a different
+/// operation for the same sender and nonce is rejected. Entries only live
+/// while a request holds them, so idle pairs take no space.
+#[derive(Debug)]
+pub struct SenderLocks {
+    max_entries: usize,
+    entries: Mutex<HashMap<LockKey, Entry>>,
+}
+
+impl Default for SenderLocks {
+    fn default() -> Self {
+        Self::new(&SenderLockConfig::default())
+    }
+}
+
+impl SenderLocks {
+    /// Create locks with no sponsorship in progress
+    pub fn new(config: &SenderLockConfig) -> Self {
+        Self {
+            max_entries: config.max_entries.max(1),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Hold the slot of `sender` and `nonce` for `operation` until the
+    /// returned lease is dropped
+    ///
+    /// Fails when a different sponsorship is in progress for the pair, or
+    /// when too many pairs are in progress.
+    pub fn acquire(
+        self: &Arc<Self>,
+        sender: Address,
+        nonce: U256,
+        operation: B256,
+    ) -> Result<SenderLease, PaymasterError> {
+        let mut entries = self.entries.lock().unwrap();
+        let key = (sender, nonce);
+        if let Some(entry) = entries.get_mut(&key) {
+            if entry.operation != operation {
+                return Err(PaymasterError::ConcurrentSponsorship(format!(
+                    "a different sponsorship of {} with nonce {} is in progress",
+                    sender, nonce
+                )));
+            }
+            entry.holders += 1;
+            return Ok(SenderLease {
+                locks: self.clone(),
+                key,
+                slot: entry.slot.clone(),
+            });
+        }
+
+        if entries.len() >= self.max_entries {
+            return Err(PaymasterError::ConcurrentSponsorship(format!(
+                "{} sponsorships are already in progress",
+                entries.len()
+            )));
+        }
+        let slot = SenderSlot::default();
+        entries.insert(
+            key,
+            Entry {
+                operation,
+                holders: 1,
+                slot: slot.clone(),
+            },
+        );
+        Ok(SenderLease {
+            locks: self.clone(),
+            key,
+            slot,
+        })
+    }
+
+    /// Sender and nonce pairs being sponsored
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Whether no sponsorship is in progress
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn release(&self, key: &LockKey) {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.get_mut(key) {
+            entry.holders -= 1;
+            if entry.holders == 0 {
+                entries.remove(key);
+            }
+        }
+    }
+}
+
+/// Hold on a sender and nonce pair, released on drop
+#[derive(Debug)]
+pub struct SenderLease {
+    locks: Arc<SenderLocks>,
+    key: LockKey,
+    slot: SenderSlot,
+}
+
+impl SenderLease {
+    /// Sponsorship shared by the holders of the pair
+    pub fn slot(&self) -> &SenderSlot {
+        &self.slot
+    }
+}
+
+impl Drop for SenderLease {
+    fn drop(&mut self) {
+        self.locks.release(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use alloy_primitives::{Address, Bytes, B256, U256};
+    use futures::future::join_all;
+    use rundler_types::{chain::ChainSpec, v0_7, UserOperation, UserOperationVariant};
+
+    use super::*;
+    use crate::{
+        service::{PaymasterRelayService, SponsorOptions},
+        test_utils,
+        usage::SponsorshipUsage,
+        DeploymentLimit, InMemoryUsageStore, PaymasterError, UsageBucket, UsageGranularity,
+        UsageStore,
+    };
+
+    fn locks(max_entries: usize) -> Arc<SenderLocks> {
+        Arc::new(SenderLocks::new(&SenderLockConfig { max_entries }))
+    }
+
+    #[test]
+    fn test_same_operation_shares_slot() {
+        let locks = locks(10);
+        let sender = Address::repeat_byte(1);
+        let op = B256::repeat_byte(1);
+
+        let first = locks.acquire(sender, U256::from(1), op).unwrap();
+        let second = locks.acquire(sender, U256::from(1), op).unwrap();
+        assert!(Arc::ptr_eq(first.slot(), second.slot()));
+
+        // Another nonce is sponsored on its own
+        let other = locks.acquire(sender, U256::from(2), op).unwrap();
+        assert!(!Arc::ptr_eq(first.slot(), other.slot()));
+    }
+
+    #[test]
+    fn test_different_operation_rejected_while_held() {
+        let locks = locks(10);
+        let sender = Address::repeat_byte(1);
+        let lease = locks
+            .acquire(sender, U256::ZERO, B256::repeat_byte(1))
+            .unwrap();
+
+        let err = locks
+            .acquire(sender, U256::ZERO, B256::repeat_byte(2))
+            .unwrap_err();
+        assert!(matches!(err, PaymasterError::ConcurrentSponsorship(_)));
+
+        drop(lease);
+        assert!(locks
+            .acquire(sender, U256::ZERO, B256::repeat_byte(2))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_released_entries_removed() {
+        let locks = locks(1);
+        let sender = Address::repeat_byte(1);
+        let op = B256::repeat_byte(1);
+
+        let first = locks.acquire(sender, U256::ZERO, op).unwrap();
+        let second = locks.acquire(sender, U256::ZERO, op).unwrap();
+        assert!(locks.acquire(sender, U256::from(1), op).is_err());
+
+        drop(first);
+        assert_eq!(locks.len(), 1);
+        drop(second);
+        assert!(locks.is_empty());
+        assert!(locks.acquire(sender, U256::from(1), op).is_ok());
+    }
+
+    const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const REQUESTS_PER_OP: usize = 16;
+
+    /// Usage store keeping the charged operations, slow to charge so that
+    /// concurrent requests overlap the sponsorship holding the lock
+    #[derive(Debug, Default)]
+    struct SlowUsageStore {
+        inner: InMemoryUsageStore,
+        charged: Mutex<Vec<B256>>,
+    }
+
+    impl UsageStore for SlowUsageStore {
+        fn record_sponsorship(&self, usage: SponsorshipUsage) {
+            std::thread::sleep(Duration::from_millis(200));
+            self.charged.lock().unwrap().push(usage.user_op_hash);
+            self.inner.record_sponsorship(usage);
+        }
+
+        fn record_actual_gas_cost(&self, user_op_hash: B256, actual_gas_cost: U256) -> bool {
+            self.inner
+                .record_actual_gas_cost(user_op_hash, actual_gas_cost)
+        }
+
+        fn report(
+            &self,
+            api_key_id: &str,
+            from: u64,
+            to: u64,
+            granularity: UsageGranularity,
+        ) -> Vec<UsageBucket> {
+            self.inner.report(api_key_id, from, to, granularity)
+        }
+
+        fn api_key_ids(&self) -> Vec<String> {
+            self.inner.api_key_ids()
+        }
+
+        fn record_deployment(
+            &self,
+            factory: Address,
+            timestamp: u64,
+            limits: &[DeploymentLimit],
+        ) -> Result<(), DeploymentLimit> {
+            self.inner.record_deployment(factory, timestamp, limits)
+        }
+
+        fn release_deployment(&self, factory: Address, timestamp: u64) {
+            self.inner.release_deployment(factory, timestamp)
+        }
+
+        fn deployment_count(
+            &self,
+            factory: Option<Address>,
+            window: UsageGranularity,
+            timestamp: u64,
+        ) -> u64 {
+            self.inner.deployment_count(factory, window, timestamp)
+        }
+    }
+
+    fn create_service(usage: Arc<SlowUsageStore>) -> PaymasterRelayService {
+        test_utils::service(&format!("senders = [\"{}\"]\n", SENDER))
+            .with_usage_store(usage)
+            .with_sender_locks(SenderLockConfig::default())
+    }
+
+    /// Operation of SENDER at `nonce`, told apart from others by `call_gas_limit`
+    fn user_op(nonce: u64, call_gas_limit: u128) -> UserOperationVariant {
+        UserOperationVariant::V0_7(
+            v0_7::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_7::UserOperationRequiredFields {
+                    sender: Address::from_str(SENDER).unwrap(),
+                    nonce: U256::from(nonce),
+                    call_data: Bytes::new(),
+                    call_gas_limit,
+                    verification_gas_limit: 100_000,
+                    pre_verification_gas: 21_000,
+                    max_fee_per_gas: 1_000_000_000,
+                    max_priority_fee_per_gas: 1_000_000_000,
+                    signature: Bytes::new(),
+                },
+            )
+            .build(),
+        )
+    }
+
+    fn entry_point() -> ethers::types::Address {
+        ethers::types::Address::from_slice(ChainSpec::default().entry_point_address_v0_7.as_slice())
+    }
+
+    fn options() -> SponsorOptions {
+        SponsorOptions {
+            return_full_operation: true,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_concurrent_requests_signed_and_charged_once() {
+        let usage = Arc::new(SlowUsageStore::default());
+        let service = Arc::new(create_service(usage.clone()));
+
+        // Identical and conflicting requests for nonce 1, interleaved
+        let requests = (0..REQUESTS_PER_OP * 2).map(|i| {
+            let service = service.clone();
+            let op = user_op(1, 100_000 + (i % 2) as u128);
+            tokio::spawn(async move {
+                let result = service
+                    .sponsor_user_operation(op, entry_point(), options())
+                    .await;
+                (i % 2, result)
+            })
+        });
+        let results: Vec<_> = join_all(requests)
+            .await
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+
+        // Whichever operation took the lock first was sponsored for all of its
+        // requests with one signature; every request for the other was rejected
+        let (winner, _) = results
+            .iter()
+            .find(|(_, result)| result.is_ok())
+            .expect("one operation sponsored");
+        let mut signatures = Vec::new();
+        for (op, result) in &results {
+            if op == winner {
+                signatures.push(result.as_ref().unwrap().paymaster_and_data.clone());
+            } else {
+                let err = result.as_ref().unwrap_err();
+                assert!(matches!(err, PaymasterError::ConcurrentSponsorship(_)));
+                assert_eq!(err.code(), -32607);
+            }
+        }
+        assert_eq!(signatures.len(), REQUESTS_PER_OP);
+        signatures.dedup();
+        assert_eq!(signatures.len(), 1);
+
+        // Charged once
+        let sponsored = results
+            .iter()
+            .find_map(|(_, result)| result.as_ref().ok())
+            .and_then(|sponsorship| sponsorship.sponsored_user_op.as_ref())
+            .unwrap();
+        assert_eq!(*usage.charged.lock().unwrap(), vec![sponsored.hash()]);
+
+        // The lock was released, so the other operation is sponsored now
+        service
+            .sponsor_user_operation(
+                user_op(1, 100_000 + (1 - winner) as u128),
+                entry_point(),
+                options(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(usage.charged.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_other_nonces_sponsored_concurrently() {
+        let usage = Arc::new(SlowUsageStore::default());
+        let service = Arc::new(create_service(usage.clone()));
+
+        let requests = (0..4).map(|nonce| {
+            let service = service.clone();
+            tokio::spawn(async move {
+                service
+                    .sponsor_user_operation(user_op(nonce, 100_000), entry_point(), options())
+                    .await
+            })
+        });
+        for result in join_all(requests).await {
+            result.unwrap().unwrap();
+        }
+        assert_eq!(usage.charged.lock().unwrap().len(), 4);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_same_operation_of_two_api_keys_not_shared() {
+        let usage = Arc::new(SlowUsageStore::default());
+        let service = Arc::new(create_service(usage.clone()));
+        let keys = ["key-a", "key-b"];
+
+        // The same operation asked for with two API keys, interleaved
+        let requests = (0..REQUESTS_PER_OP * 2).map(|i| {
+            let service = service.clone();
+            let options = SponsorOptions {
+                requester: Some(keys[i % 2].to_string()),
+                ..options()
+            };
+            tokio::spawn(async move {
+                let result = service
+                    .sponsor_user_operation(user_op(1, 100_000), entry_point(), options)
+                    .await;
+                (i % 2, result)
+            })
+        });
+        let results: Vec<_> = join_all(requests)
+            .await
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+
+        // Only the key that took the lock first shares its sponsorship; the
+        // other key is not handed a sponsorship accounted to someone else
+        let (winner, _) = results
+            .iter()
+            .find(|(_, result)| result.is_ok())
+            .expect("one API key sponsored");
+        let mut signatures = Vec::new();
+        for (key, result) in &results {
+            if key == winner {
+                signatures.push(result.as_ref().unwrap().paymaster_and_data.clone());
+            } else {
+                let err = result.as_ref().unwrap_err();
+                assert!(matches!(err, PaymasterError::ConcurrentSponsorship(_)));
+                assert_eq!(err.code(), -32607);
+            }
+        }
+        assert_eq!(signatures.len(), REQUESTS_PER_OP);
+        signatures.dedup();
+        assert_eq!(signatures.len(), 1);
+
+        assert_eq!(usage.charged.lock().unwrap().len(), 1);
+        assert_eq!(usage.api_key_ids(), vec![keys[*winner].to_string()]);
+    }
+}
@@ -1,22 +1,175 @@
 // paymaster-relay/src/service.rs
 // This file will contain the core business logic of the PaymasterRelayService.
 
-use std::{collections::HashMap, sync::Arc, time::Instant};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use ethers::types::{Address, H256};
 use rundler_pool::LocalPoolHandle;
-use rundler_types::{UserOperation, UserOperationVariant};
-use tokio::sync::Mutex;
-use tracing::{debug, info, warn};
+#[cfg(feature = "entrypoint-v0_8")]
+use rundler_types::v0_8;
+use rundler_types::{chain::ChainSpec, v0_6, v0_7, UserOperation, UserOperationVariant};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::Mutex, task::JoinHandle};
+use tracing::{debug, info, info_span, warn, Instrument};
 
 use crate::{
+    attestation::{Attestation, Attestor},
+    audit::{AuditLogger, AuditRecord},
+    authorizer::ExternalAuthorizer,
+    balance_monitor::{BalanceMonitor, BalanceStatus},
+    canonical,
+    clock_skew::ClockGuard,
+    dashboard::{DashboardBalance, DashboardPolicies, DashboardTransactions, SponsorshipHistory},
+    deposit::{DepositManager, DepositTransaction, PaymasterDepositInfo},
+    entry_points::{
+        check_paymaster_contracts, ContractCheck, EntryPointRouting, EntryPointVersion,
+        PaymasterContractReader, SupportedEntryPoint,
+    },
     error::PaymasterError,
+    fees::FeeChecker,
+    idempotency::{self, IdempotencyCache, IdempotencyConfig},
     kms::{GasEstimates, SigningContext},
-    metrics::PaymasterMetrics,
-    policy::PolicyEngine,
-    signer::SignerManager,
+    metrics::{PaymasterMetrics, SponsorshipLabels},
+    outbound_tx::{OutboundTx, OutboundTxManager},
+    policy::{PolicyEngine, PolicyRejection},
+    policy_shadow::{PolicyShadow, PolicyShadowConfig, PolicyShadowReport, PolicySnapshot},
+    sbt::SBTValidator,
+    sender_lock::{SenderLockConfig, SenderLocks},
+    signature_cache::{self, SignatureCache, SignatureCacheConfig},
+    signer::{KeyRotation, KeySource, SignerKeyStatus, SignerManager},
+    simulation::{SponsorshipSimulation, ValidationSimulator},
+    sponsorship::{self, SponsorshipData, SponsorshipError},
+    sponsorship_records::{
+        MinedUserOperation, SponsorshipPage, SponsorshipQuery, SponsorshipRecord,
+        SponsorshipRecords, SponsorshipStatus, StatusUpdate,
+    },
+    timings::{record_stage, SponsorStage, StageTimings},
+    token_paymaster::{self, TokenPaymasterData, TokenPricing, TokenQuote},
+    usage::{
+        self, DeploymentReservation, InMemoryUsageStore, SponsorshipUsage, UsageBucket,
+        UsageGranularity, UsageStore, ANONYMOUS_KEY_ID,
+    },
 };
 
+/// Seconds a signed sponsorship stays valid (the `validUntil` offset) by default
+pub const SPONSORSHIP_VALIDITY_SECS: u64 = 600;
+
+/// Validity windows clients may request for their sponsorships
+#[derive(Debug, Clone, Deserialize)]
+pub struct ValidityConfig {
+    /// Window of requests that do not ask for one
+    #[serde(default = "default_validity_seconds")]
+    pub default_seconds: u64,
+    /// Shortest window a request may ask for
+    #[serde(default = "default_min_validity_seconds")]
+    pub min_seconds: u64,
+    /// Longest window a request may ask for
+    #[serde(default = "default_max_validity_seconds")]
+    pub max_seconds: u64,
+}
+
+fn default_validity_seconds() -> u64 {
+    SPONSORSHIP_VALIDITY_SECS
+}
+
+fn default_min_validity_seconds() -> u64 {
+    30
+}
+
+fn default_max_validity_seconds() -> u64 {
+    3600
+}
+
+impl Default for ValidityConfig {
+    fn default() -> Self {
+        Self {
+            default_seconds: default_validity_seconds(),
+            min_seconds: default_min_validity_seconds(),
+            max_seconds: default_max_validity_seconds(),
+        }
+    }
+}
+
+impl ValidityConfig {
+    /// Window of a request asking for `requested` seconds
+    ///
+    /// Windows outside the configured bounds are rejected, never clamped, so
+    /// the returned `validUntil` is always the one the client asked for.
+    pub fn window_seconds(&self, requested: Option<u64>) -> Result<u64, PaymasterError> {
+        let Some(seconds) = requested else {
+            return Ok(self.default_seconds);
+        };
+        if seconds < self.min_seconds || seconds > self.max_seconds {
+            return Err(PaymasterError::InvalidRequest(format!(
+                "validitySeconds {} is outside the allowed range of {} to {} seconds",
+                seconds, self.min_seconds, self.max_seconds
+            )));
+        }
+        Ok(seconds)
+    }
+
+    /// `seconds` brought within the configured bounds
+    pub fn clamp_seconds(&self, seconds: u64) -> u64 {
+        seconds.clamp(self.min_seconds, self.max_seconds.max(self.min_seconds))
+    }
+}
+
+/// Paymaster gas limits of v0.7 sponsorships, also handed out with the dummy
+/// paymaster data for gas estimation
+#[derive(Debug, Clone, Deserialize)]
+pub struct PaymasterGasConfig {
+    /// paymasterVerificationGasLimit of sponsored operations
+    #[serde(default = "default_paymaster_verification_gas_limit")]
+    pub verification_gas_limit: u64,
+    /// paymasterPostOpGasLimit of sponsored operations paying no token
+    #[serde(default = "default_paymaster_post_op_gas_limit")]
+    pub post_op_gas_limit: u64,
+}
+
+fn default_paymaster_verification_gas_limit() -> u64 {
+    100_000
+}
+
+fn default_paymaster_post_op_gas_limit() -> u64 {
+    20_000
+}
+
+impl Default for PaymasterGasConfig {
+    fn default() -> Self {
+        Self {
+            verification_gas_limit: default_paymaster_verification_gas_limit(),
+            post_op_gas_limit: default_paymaster_post_op_gas_limit(),
+        }
+    }
+}
+
+/// Paymaster fields with a stub signature, served by
+/// superRelay_getDummyPaymasterData for estimating gas before sponsorship
+///
+/// Sized exactly like the fields of a real sponsorship on the EntryPoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DummyPaymasterData {
+    pub entry_point: alloy_primitives::Address,
+    pub version: EntryPointVersion,
+    pub paymaster: alloy_primitives::Address,
+    /// v0.6: the paymaster address followed by the paymaster data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paymaster_and_data: Option<alloy_primitives::Bytes>,
+    /// v0.7 and later: paymaster gas limits and data, next to `paymaster`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paymaster_verification_gas_limit: Option<alloy_primitives::U64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paymaster_post_op_gas_limit: Option<alloy_primitives::U64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paymaster_data: Option<alloy_primitives::Bytes>,
+}
+
 /// Result of paymaster sponsorship operation
 #[derive(Debug, Clone)]
 pub struct PaymasterSponsorResult {
@@ -30,13 +183,102 @@ pub struct PaymasterSponsorResult {
     pub pre_verification_gas: Option<u64>,
     pub verification_gas_limit_uo: Option<u64>,
     pub call_gas_limit: Option<u64>,
+    /// Complete sponsored UserOperation, set when requested via `SponsorOptions`
+    pub sponsored_user_op: Option<UserOperationVariant>,
+    /// Raised fees the signature covers, set when the client's fees were bumped
+    pub max_fee_per_gas: Option<u128>,
+    pub max_priority_fee_per_gas: Option<u128>,
+    /// Unix time the sponsorship expires, as signed into the paymaster data
+    pub valid_until: u64,
+    /// Unix time the sponsorship becomes valid, as signed into the paymaster data
+    pub valid_after: u64,
+    /// Token amount quoted when gas is paid in an ERC-20 token
+    pub token_quote: Option<TokenQuote>,
+    /// Signed for an earlier identical request and taken from the signature cache
+    pub cached: bool,
+}
+
+/// Paymaster signing keys reported by pm_getSignerStatus
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignerStatus {
+    /// Paymaster address, unchanged by key rotation
+    pub paymaster: alloy_primitives::Address,
+    /// Key signing new sponsorships
+    pub active_signer: alloy_primitives::Address,
+    /// Previous key still accepted during its grace period
+    pub retiring_signer: Option<alloy_primitives::Address>,
+    /// Unix time the grace period of the retiring key ends
+    pub retire_at: Option<u64>,
+    /// Keys of the signer pool, the active key first
+    pub signers: Vec<SignerKeyStatus>,
+}
+
+/// Per-request options for sponsorship
+#[derive(Debug, Clone, Default)]
+pub struct SponsorOptions {
+    /// Return the UserOperation merged with the paymaster fields
+    pub return_full_operation: bool,
+    /// Caller identity (e.g. API key id) recorded in the audit log
+    pub requester: Option<String>,
+    /// Correlation id of the gateway request, attached to logs and the audit log
+    pub request_id: Option<String>,
+    /// Client key under which replays of this request return the same sponsorship
+    pub idempotency_key: Option<String>,
+    /// Requested validity window, the configured default when unset
+    pub validity_seconds: Option<u64>,
+    /// Pay gas in this ERC-20 token instead of sponsoring it
+    pub token: Option<alloy_primitives::Address>,
+    /// The caller attests the response with [`PaymasterRelayService::attest_response`];
+    /// rejected up front when no attestation key is configured
+    pub attest: bool,
+    /// Collect the durations of the stages run for this request
+    pub timings: Option<StageTimings>,
+    /// Deployment of the operation already counted against the quotas, by
+    /// [`PaymasterRelayService::reserve_batch_budget`]
+    pub deployment: Option<Arc<DeploymentReservation>>,
+}
+
+/// First operation of a batch the policy or deployment quotas reject
+#[derive(Debug)]
+pub struct BatchRejection {
+    /// Position of the operation in the batch
+    pub index: usize,
+    /// Error sponsoring the operation would fail with
+    pub error: PaymasterError,
 }
 
 #[derive(Clone, Debug)]
 pub struct PaymasterRelayService {
     signer_manager: Arc<Mutex<SignerManager>>,
-    policy_engine: PolicyEngine,
+    policy_shadow: Arc<PolicyShadow>,
     metrics: PaymasterMetrics,
+    audit_logger: AuditLogger,
+    deposit_manager: Option<DepositManager>,
+    outbound_txs: Option<Arc<OutboundTxManager>>,
+    fee_checker: Option<FeeChecker>,
+    sbt_validator: Option<SBTValidator>,
+    simulator: Option<Arc<dyn ValidationSimulator>>,
+    key_rotation_grace: Duration,
+    key_rotation_env_vars: Vec<String>,
+    idempotency: Arc<IdempotencyCache>,
+    signature_cache: Arc<SignatureCache>,
+    sender_locks: Arc<SenderLocks>,
+    validity: ValidityConfig,
+    paymaster_gas: PaymasterGasConfig,
+    usage: Option<Arc<dyn UsageStore>>,
+    /// Sponsored account deployments per factory, the usage store when configured
+    deployments: Arc<dyn UsageStore>,
+    token_pricing: Option<TokenPricing>,
+    balance_monitor: Option<Arc<BalanceMonitor>>,
+    history: Arc<SponsorshipHistory>,
+    attestor: Option<Attestor>,
+    sponsorship_records: Option<Arc<SponsorshipRecords>>,
+    entry_point_routing: Option<Arc<EntryPointRouting>>,
+    authorizer: Arc<ExternalAuthorizer>,
+    clock_guard: Option<Arc<ClockGuard>>,
+    /// Chain spec operations are built and hashed with
+    chain_spec: Arc<ChainSpec>,
 }
 
 impl PaymasterRelayService {
@@ -45,141 +287,1583 @@ impl PaymasterRelayService {
         policy_engine: PolicyEngine,
         _pool: Arc<LocalPoolHandle>,
     ) -> Self {
+        let metrics = PaymasterMetrics::new();
+        for key in signer_manager.signer_keys() {
+            metrics.update_signer_draining(&format!("{:?}", key.address), key.draining);
+        }
+
         Self {
             signer_manager: Arc::new(Mutex::new(signer_manager)),
-            policy_engine,
-            metrics: PaymasterMetrics::new(),
+            policy_shadow: Arc::new(PolicyShadow::new(
+                Arc::new(policy_engine),
+                &PolicyShadowConfig::default(),
+            )),
+            metrics,
+            audit_logger: AuditLogger::default(),
+            deposit_manager: None,
+            outbound_txs: None,
+            fee_checker: None,
+            sbt_validator: None,
+            simulator: None,
+            key_rotation_grace: Duration::from_secs(default_max_validity_seconds()),
+            key_rotation_env_vars: Vec::new(),
+            idempotency: Arc::new(IdempotencyCache::default()),
+            signature_cache: Arc::new(SignatureCache::default()),
+            sender_locks: Arc::new(SenderLocks::default()),
+            validity: ValidityConfig::default(),
+            paymaster_gas: PaymasterGasConfig::default(),
+            usage: None,
+            deployments: Arc::new(InMemoryUsageStore::default()),
+            token_pricing: None,
+            balance_monitor: None,
+            history: Arc::new(SponsorshipHistory::default()),
+            attestor: None,
+            sponsorship_records: None,
+            entry_point_routing: None,
+            authorizer: Arc::new(ExternalAuthorizer::default()),
+            clock_guard: None,
+            chain_spec: Arc::new(ChainSpec::default()),
+        }
+    }
+
+    /// Write sponsorship decisions to the given audit logger
+    pub fn with_audit_logger(mut self, audit_logger: AuditLogger) -> Self {
+        self.audit_logger = audit_logger;
+        self
+    }
+
+    /// Enable the EntryPoint deposit management methods
+    pub fn with_deposit_manager(mut self, deposit_manager: DepositManager) -> Self {
+        self.deposit_manager = Some(deposit_manager);
+        self
+    }
+
+    /// Track transactions sent from the paymaster account, see [`Self::spawn_outbound_txs`]
+    ///
+    /// The deposit manager sends through the same manager.
+    pub fn with_outbound_txs(mut self, outbound_txs: Arc<OutboundTxManager>) -> Self {
+        self.outbound_txs = Some(outbound_txs);
+        self
+    }
+
+    /// Watch the paymaster balances, see [`Self::spawn_balance_monitor`]
+    ///
+    /// Needs the deposit manager to read deposits and send top-ups.
+    pub fn with_balance_monitor(mut self, balance_monitor: BalanceMonitor) -> Self {
+        self.balance_monitor = Some(Arc::new(balance_monitor));
+        self
+    }
+
+    /// Check operation fees against the latest block before signing
+    pub fn with_fee_checker(mut self, fee_checker: FeeChecker) -> Self {
+        self.fee_checker = Some(fee_checker);
+        self
+    }
+
+    /// Only sponsor senders holding the configured SBT and PNTs balances
+    pub fn with_sbt_validator(mut self, sbt_validator: SBTValidator) -> Self {
+        self.sbt_validator = Some(sbt_validator);
+        self
+    }
+
+    /// Enable sponsorship previews simulated on the EntryPoint
+    pub fn with_simulator(mut self, simulator: Arc<dyn ValidationSimulator>) -> Self {
+        self.simulator = Some(simulator);
+        self
+    }
+
+    /// Keep accepting signatures of a rotated-out key for `grace`
+    ///
+    /// Defaults to the longest sponsorship validity, so sponsorships signed
+    /// just before a rotation stay usable until they expire.
+    pub fn with_key_rotation_grace(mut self, grace: Duration) -> Self {
+        self.key_rotation_grace = grace;
+        self
+    }
+
+    /// Environment variables pm_rotatePaymasterKey may load a new key from
+    ///
+    /// Rotation to an environment variable not listed here is refused; none
+    /// are allowed by default.
+    pub fn with_key_rotation_env_vars(mut self, names: Vec<String>) -> Self {
+        self.key_rotation_env_vars = names;
+        self
+    }
+
+    /// Accept requested validity windows within `config`
+    ///
+    /// Also sets the key rotation grace period to the longest window; call
+    /// [`Self::with_key_rotation_grace`] afterwards to override it.
+    pub fn with_validity(mut self, config: ValidityConfig) -> Self {
+        self.key_rotation_grace = Duration::from_secs(config.max_seconds);
+        self.validity = config;
+        self
+    }
+
+    /// Give v0.7 sponsorships the paymaster gas limits of `config`
+    pub fn with_paymaster_gas(mut self, config: PaymasterGasConfig) -> Self {
+        self.paymaster_gas = config;
+        self
+    }
+
+    /// Replay sponsorships by idempotency key with the given cache settings
+    pub fn with_idempotency(mut self, config: IdempotencyConfig) -> Self {
+        self.idempotency = Arc::new(IdempotencyCache::new(&config));
+        self
+    }
+
+    /// Reuse signatures of repeated requests without idempotency key with
+    /// the given cache settings
+    pub fn with_signature_cache(mut self, config: SignatureCacheConfig) -> Self {
+        self.signature_cache = Arc::new(SignatureCache::new(&config));
+        self
+    }
+
+    /// Serialize sponsorships per sender and nonce with the given settings
+    pub fn with_sender_locks(mut self, config: SenderLockConfig) -> Self {
+        self.sender_locks = Arc::new(SenderLocks::new(&config));
+        self
+    }
+
+    /// Evaluate candidate policies with the given settings
+    ///
+    /// Only keeps the active policy; load the configured candidate with
+    /// [`Self::load_candidate_policy`] afterwards.
+    pub fn with_policy_shadow(mut self, config: PolicyShadowConfig) -> Self {
+        self.policy_shadow = Arc::new(PolicyShadow::new(self.policy_shadow.active(), &config));
+        self
+    }
+
+    /// Compute validity windows with the clock of `clock_guard`, and refuse
+    /// to sign while it is too far off the chain
+    pub fn with_clock_guard(mut self, clock_guard: Arc<ClockGuard>) -> Self {
+        self.clock_guard = Some(clock_guard);
+        self
+    }
+
+    /// Clock skew check, when enabled
+    pub fn clock_guard(&self) -> Option<&Arc<ClockGuard>> {
+        self.clock_guard.as_ref()
+    }
+
+    /// Unix time windows are computed with, corrected for clock skew when checked
+    fn now(&self) -> u64 {
+        match &self.clock_guard {
+            Some(clock_guard) => clock_guard.now(),
+            None => unix_now(),
+        }
+    }
+
+    /// Account sponsorships per API key, and deployments per factory, in `usage`
+    pub fn with_usage_store(mut self, usage: Arc<dyn UsageStore>) -> Self {
+        self.deployments = usage.clone();
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Let operations pay gas in the tokens priced by `token_pricing`
+    pub fn with_token_pricing(mut self, token_pricing: TokenPricing) -> Self {
+        self.token_pricing = Some(token_pricing);
+        self
+    }
+
+    /// Sign attestations of sponsorship responses with `attestor`
+    pub fn with_attestor(mut self, attestor: Attestor) -> Self {
+        self.attestor = Some(attestor);
+        self
+    }
+
+    /// Keep the outcome of every sponsorship in `records`
+    pub fn with_sponsorship_records(mut self, records: Arc<SponsorshipRecords>) -> Self {
+        self.sponsorship_records = Some(records);
+        self
+    }
+
+    /// Only sponsor operations for the EntryPoints of `routing`, naming the
+    /// paymaster contract deployed for each
+    pub fn with_entry_point_routing(mut self, routing: EntryPointRouting) -> Self {
+        self.entry_point_routing = Some(Arc::new(routing));
+        self
+    }
+
+    /// Build and hash incoming operations with the served chain's spec
+    pub fn with_chain_spec(mut self, chain_spec: Arc<ChainSpec>) -> Self {
+        self.chain_spec = chain_spec;
+        self
+    }
+
+    /// Chain spec operations are built and hashed with
+    pub fn chain_spec(&self) -> &ChainSpec {
+        &self.chain_spec
+    }
+
+    fn entry_point_routing(&self) -> Result<&Arc<EntryPointRouting>, PaymasterError> {
+        self.entry_point_routing.as_ref().ok_or_else(|| {
+            PaymasterError::InvalidRequest("Entry point routing is not configured".to_string())
+        })
+    }
+
+    /// EntryPoints sponsored for, served by pm_getSupportedEntryPoints
+    pub fn supported_entry_points(&self) -> Result<Vec<SupportedEntryPoint>, PaymasterError> {
+        Ok(self.entry_point_routing()?.supported())
+    }
+
+    /// Version of `entry_point`: the configured one when EntryPoints are
+    /// routed, otherwise the chain spec's
+    fn entry_point_version(
+        &self,
+        entry_point: alloy_primitives::Address,
+    ) -> Result<EntryPointVersion, PaymasterError> {
+        if let Some(routing) = &self.entry_point_routing {
+            return routing.version(entry_point).ok_or_else(|| {
+                PaymasterError::UnsupportedEntryPoint {
+                    message: format!("No paymaster is deployed for entry point {}", entry_point),
+                    supported: routing.supported(),
+                }
+            });
+        }
+        #[cfg(feature = "entrypoint-v0_8")]
+        if entry_point == self.chain_spec.entry_point_address_v0_8 {
+            return Ok(EntryPointVersion::V0_8);
         }
+        if entry_point == self.chain_spec.entry_point_address_v0_6 {
+            Ok(EntryPointVersion::V0_6)
+        } else if entry_point == self.chain_spec.entry_point_address_v0_7 {
+            Ok(EntryPointVersion::V0_7)
+        } else {
+            Err(PaymasterError::InvalidRequest(format!(
+                "Unknown entry point {}",
+                entry_point
+            )))
+        }
+    }
+
+    /// Metric labels of a sponsorship on `entry_point`
+    fn metric_labels(&self, entry_point: Address) -> SponsorshipLabels {
+        let entry_point = alloy_primitives::Address::from_slice(entry_point.as_bytes());
+        SponsorshipLabels::new(
+            self.chain_spec.id,
+            entry_point,
+            self.entry_point_version(entry_point).ok(),
+        )
+    }
+
+    /// Paymaster fields of a sponsorship on `entry_point`, with a stub
+    /// signature, for clients estimating gas before requesting sponsorship
+    pub async fn dummy_paymaster_data(
+        &self,
+        entry_point: alloy_primitives::Address,
+    ) -> Result<DummyPaymasterData, PaymasterError> {
+        let version = self.entry_point_version(entry_point)?;
+        let routed_paymaster = self.entry_point_routing.as_ref().and_then(|routing| {
+            routing
+                .contracts()
+                .iter()
+                .find(|contract| contract.entry_point == entry_point)
+                .map(|contract| contract.paymaster)
+        });
+        let (paymaster, signer) = {
+            let signer_manager = self.signer_manager.lock().await;
+            (
+                routed_paymaster.unwrap_or_else(|| {
+                    alloy_primitives::Address::from_slice(signer_manager.address().as_bytes())
+                }),
+                alloy_primitives::Address::from_slice(signer_manager.signer_address().as_bytes()),
+            )
+        };
+
+        let paymaster_data = sponsorship::dummy_paymaster_data(signer);
+        let dummy = DummyPaymasterData {
+            entry_point,
+            version,
+            paymaster,
+            paymaster_and_data: None,
+            paymaster_verification_gas_limit: None,
+            paymaster_post_op_gas_limit: None,
+            paymaster_data: None,
+        };
+        Ok(match version {
+            EntryPointVersion::V0_6 => DummyPaymasterData {
+                paymaster_and_data: Some([paymaster.as_slice(), &paymaster_data].concat().into()),
+                ..dummy
+            },
+            _ => DummyPaymasterData {
+                paymaster_verification_gas_limit: Some(alloy_primitives::U64::from(
+                    self.paymaster_gas.verification_gas_limit,
+                )),
+                paymaster_post_op_gas_limit: Some(alloy_primitives::U64::from(
+                    self.paymaster_gas.post_op_gas_limit,
+                )),
+                paymaster_data: Some(paymaster_data.into()),
+                ..dummy
+            },
+        })
     }
 
-    /// Get reference to metrics for health endpoints
-    pub fn metrics(&self) -> &PaymasterMetrics {
-        &self.metrics
+    /// `user_op` as an operation of `entry_point`
+    ///
+    /// v0.8 operations have the v0.7 format, so they are parsed as v0.7 and
+    /// converted here when `entry_point` is configured as v0.8.
+    #[cfg(feature = "entrypoint-v0_8")]
+    pub fn operation_for_entry_point(
+        &self,
+        user_op: UserOperationVariant,
+        entry_point: Address,
+    ) -> UserOperationVariant {
+        let entry_point = alloy_primitives::Address::from_slice(entry_point.as_bytes());
+        let is_v0_8 = self
+            .entry_point_routing
+            .as_ref()
+            .is_some_and(|routing| routing.version(entry_point) == Some(EntryPointVersion::V0_8));
+        match user_op {
+            UserOperationVariant::V0_7(op) if is_v0_8 => {
+                let chain_spec = ChainSpec {
+                    id: op.chain_id(),
+                    entry_point_address_v0_8: entry_point,
+                    ..(*self.chain_spec).clone()
+                };
+                UserOperationVariant::V0_8(v0_8::UserOperation::from_fields(op, &chain_spec))
+            }
+            user_op => user_op,
+        }
+    }
+
+    /// Check that the configured paymaster contracts are deployed and accept
+    /// every signer of the pool, logging diagnostics for those that do not
+    pub async fn check_paymaster_contracts(
+        &self,
+        reader: &dyn PaymasterContractReader,
+    ) -> Result<Vec<ContractCheck>, PaymasterError> {
+        let routing = self.entry_point_routing()?;
+        let signers: Vec<alloy_primitives::Address> = self
+            .signer_manager
+            .lock()
+            .await
+            .signer_keys()
+            .iter()
+            .map(|key| alloy_primitives::Address::from_slice(key.address.as_bytes()))
+            .collect();
+        Ok(check_paymaster_contracts(reader, routing, &signers).await)
+    }
+
+    /// Sponsorship history, when enabled
+    pub fn sponsorship_records(&self) -> Option<&Arc<SponsorshipRecords>> {
+        self.sponsorship_records.as_ref()
+    }
+
+    fn attestor(&self) -> Result<&Attestor, PaymasterError> {
+        self.attestor.as_ref().ok_or_else(|| {
+            PaymasterError::InvalidRequest("Response attestation is not configured".to_string())
+        })
+    }
+
+    /// Address of the attestation key, served by pm_getAttestationKey
+    pub fn attestation_address(&self) -> Result<alloy_primitives::Address, PaymasterError> {
+        Ok(self.attestor()?.address())
+    }
+
+    /// Attest a response payload (the result without its `attestation` field)
+    pub fn attest_response(
+        &self,
+        payload: &serde_json::Value,
+    ) -> Result<Attestation, PaymasterError> {
+        self.attestor()?.attest(payload, unix_now())
+    }
+
+    fn token_pricing(&self) -> Result<&TokenPricing, PaymasterError> {
+        self.token_pricing.as_ref().ok_or_else(|| {
+            PaymasterError::InvalidRequest("ERC-20 gas payment is not enabled".to_string())
+        })
+    }
+
+    fn usage_store(&self) -> Result<&Arc<dyn UsageStore>, PaymasterError> {
+        self.usage.as_ref().ok_or_else(|| {
+            PaymasterError::InvalidRequest("Usage accounting is not enabled".to_string())
+        })
+    }
+
+    /// Usage of `api_key_id` between `from` and `to` (unix seconds), served by
+    /// pm_getUsageReport
+    pub fn usage_report(
+        &self,
+        api_key_id: &str,
+        from: u64,
+        to: u64,
+        granularity: UsageGranularity,
+    ) -> Result<Vec<UsageBucket>, PaymasterError> {
+        Ok(self
+            .usage_store()?
+            .report(api_key_id, from, to, granularity))
+    }
+
+    /// CSV export of the usage of `api_key_id`, or of every API key when unset
+    pub fn usage_csv(
+        &self,
+        api_key_id: Option<&str>,
+        from: u64,
+        to: u64,
+        granularity: UsageGranularity,
+    ) -> Result<String, PaymasterError> {
+        let store = self.usage_store()?;
+        let api_key_ids = match api_key_id {
+            Some(id) => vec![id.to_string()],
+            None => store.api_key_ids(),
+        };
+        Ok(usage::usage_csv(
+            store.as_ref(),
+            &api_key_ids,
+            from,
+            to,
+            granularity,
+        ))
+    }
+
+    /// Settle the usage of a sponsored operation at its mined `actual_gas_cost`
+    ///
+    /// Returns whether the operation was awaiting its cost.
+    pub fn record_actual_gas_cost(
+        &self,
+        user_op_hash: alloy_primitives::B256,
+        actual_gas_cost: alloy_primitives::U256,
+    ) -> bool {
+        self.usage
+            .as_ref()
+            .is_some_and(|usage| usage.record_actual_gas_cost(user_op_hash, actual_gas_cost))
+    }
+
+    /// Mark a recorded sponsorship mined, as seen in its receipt
+    pub fn record_sponsorship_mined(
+        &self,
+        user_op_hash: alloy_primitives::B256,
+        mined: MinedUserOperation,
+    ) {
+        if let Some(records) = &self.sponsorship_records {
+            records.update_status(user_op_hash, StatusUpdate::Mined(mined));
+        }
+    }
+
+    fn records(&self) -> Result<&Arc<SponsorshipRecords>, PaymasterError> {
+        self.sponsorship_records.as_ref().ok_or_else(|| {
+            PaymasterError::InvalidRequest("Sponsorship records are not enabled".to_string())
+        })
+    }
+
+    /// Record of one sponsored operation, served by pm_getSponsorshipRecord
+    pub fn sponsorship_record(
+        &self,
+        user_op_hash: alloy_primitives::B256,
+    ) -> Result<Option<SponsorshipRecord>, PaymasterError> {
+        Ok(self.records()?.get(user_op_hash))
+    }
+
+    /// Sponsorships matching `query`, served by pm_listSponsorships
+    pub fn list_sponsorships(
+        &self,
+        query: &SponsorshipQuery,
+    ) -> Result<SponsorshipPage, PaymasterError> {
+        Ok(self.records()?.list(query))
+    }
+
+    /// Forget the cached SBT and PNTs balances of `sender`
+    ///
+    /// Returns whether any were cached.
+    pub fn invalidate_sbt_cache(
+        &self,
+        sender: alloy_primitives::Address,
+    ) -> Result<bool, PaymasterError> {
+        let sbt_validator = self.sbt_validator.as_ref().ok_or_else(|| {
+            PaymasterError::InvalidRequest("SBT validation is not configured".to_string())
+        })?;
+        Ok(sbt_validator.invalidate(sender))
+    }
+
+    fn deposit_manager(&self) -> Result<&DepositManager, PaymasterError> {
+        self.deposit_manager.as_ref().ok_or_else(|| {
+            PaymasterError::InvalidRequest("Deposit management is not configured".to_string())
+        })
+    }
+
+    /// Paymaster deposit and stake on `entry_point`
+    pub async fn get_deposit_info(
+        &self,
+        entry_point: alloy_primitives::Address,
+    ) -> Result<PaymasterDepositInfo, PaymasterError> {
+        let paymaster = self.paymaster_address().await;
+        self.deposit_manager()?
+            .deposit_info(entry_point, paymaster)
+            .await
+    }
+
+    /// Top up the paymaster deposit on `entry_point` from the signer account
+    pub async fn deposit_to(
+        &self,
+        entry_point: alloy_primitives::Address,
+        amount: alloy_primitives::U256,
+    ) -> Result<DepositTransaction, PaymasterError> {
+        self.deposit_manager()?
+            .deposit_to(&self.signer_manager, entry_point, amount)
+            .await
+    }
+
+    /// Withdraw from the paymaster deposit on `entry_point`
+    pub async fn withdraw_to(
+        &self,
+        entry_point: alloy_primitives::Address,
+        withdraw_address: alloy_primitives::Address,
+        amount: alloy_primitives::U256,
+    ) -> Result<DepositTransaction, PaymasterError> {
+        self.deposit_manager()?
+            .withdraw_to(&self.signer_manager, entry_point, withdraw_address, amount)
+            .await
+    }
+
+    fn outbound_txs(&self) -> Result<&Arc<OutboundTxManager>, PaymasterError> {
+        self.outbound_txs.as_ref().ok_or_else(|| {
+            PaymasterError::InvalidRequest("Outbound transactions are not configured".to_string())
+        })
+    }
+
+    /// Transaction sent from the paymaster account with the internal id or
+    /// any submission hash `key`, served by pm_getOutboundTx
+    pub fn outbound_tx(&self, key: &str) -> Result<Option<OutboundTx>, PaymasterError> {
+        Ok(self.outbound_txs()?.get(key))
+    }
+
+    /// Settle and replace stuck outbound transactions in the background,
+    /// including those restored from before a restart
+    pub fn spawn_outbound_txs(&self) -> Result<JoinHandle<()>, PaymasterError> {
+        Ok(self.outbound_txs()?.spawn(self.signer_manager.clone()))
+    }
+
+    fn balance_monitor(&self) -> Result<&Arc<BalanceMonitor>, PaymasterError> {
+        self.balance_monitor.as_ref().ok_or_else(|| {
+            PaymasterError::InvalidRequest("Balance monitoring is not configured".to_string())
+        })
+    }
+
+    /// Paymaster balances, thresholds and last top-up as of the last check
+    pub fn balance_status(&self) -> Result<BalanceStatus, PaymasterError> {
+        Ok(self.balance_monitor()?.status())
+    }
+
+    /// Check the paymaster balances now, topping up low deposits if enabled
+    pub async fn check_balances(&self) -> Result<BalanceStatus, PaymasterError> {
+        let balance_monitor = self.balance_monitor()?;
+        Ok(balance_monitor
+            .check(self.deposit_manager()?, &self.signer_manager, &self.metrics)
+            .await)
+    }
+
+    /// Check the paymaster balances every `check_interval_seconds` in the background
+    pub fn spawn_balance_monitor(
+        self: &Arc<Self>,
+    ) -> Result<tokio::task::JoinHandle<()>, PaymasterError> {
+        let interval = self
+            .balance_monitor()?
+            .config()
+            .check_interval_seconds
+            .max(1);
+        self.deposit_manager()?;
+        let service = self.clone();
+        Ok(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+            loop {
+                ticker.tick().await;
+                if let Err(e) = service.check_balances().await {
+                    warn!("Paymaster balance check failed: {}", e);
+                }
+            }
+        }))
+    }
+
+    /// Address of the paymaster the signer signs for
+    pub async fn paymaster_address(&self) -> alloy_primitives::Address {
+        let signer_manager = self.signer_manager.lock().await;
+        alloy_primitives::Address::from_slice(signer_manager.address().as_bytes())
+    }
+
+    /// Active and retiring paymaster signing keys
+    pub async fn signer_status(&self) -> SignerStatus {
+        let signer_manager = self.signer_manager.lock().await;
+        let retiring = signer_manager.retiring_signer(unix_now());
+        SignerStatus {
+            paymaster: alloy_primitives::Address::from_slice(signer_manager.address().as_bytes()),
+            active_signer: alloy_primitives::Address::from_slice(
+                signer_manager.signer_address().as_bytes(),
+            ),
+            retiring_signer: retiring
+                .map(|retiring| alloy_primitives::Address::from_slice(retiring.address.as_bytes())),
+            retire_at: retiring.map(|retiring| retiring.retire_at),
+            signers: signer_manager.signer_keys(),
+        }
+    }
+
+    /// Stop or resume assigning sponsorships to the signer pool key `signer`
+    ///
+    /// A drained key can then be rotated out; sponsorships already signing
+    /// with it finish normally.
+    pub async fn drain_signer(
+        &self,
+        signer: alloy_primitives::Address,
+        draining: bool,
+    ) -> Result<SignerStatus, PaymasterError> {
+        let signer = Address::from_slice(signer.as_slice());
+        self.signer_manager
+            .lock()
+            .await
+            .set_draining(signer, draining)
+            .map_err(PaymasterError::SignerError)?;
+        self.metrics
+            .update_signer_draining(&format!("{:?}", signer), draining);
+        Ok(self.signer_status().await)
+    }
+
+    /// Switch sponsorship signing to the key from `source`
+    ///
+    /// The previous key keeps verifying for `grace`, or the configured grace
+    /// period. Sponsorships in flight finish with the key they started with.
+    /// Environment sources must be in the configured allowlist.
+    pub async fn rotate_paymaster_key(
+        &self,
+        source: &KeySource,
+        grace: Option<Duration>,
+    ) -> Result<KeyRotation, PaymasterError> {
+        if let KeySource::Env(name) = source {
+            if !self.key_rotation_env_vars.contains(name) {
+                return Err(PaymasterError::InvalidRequest(format!(
+                    "Environment variable {} is not in key_rotation_env_vars",
+                    name
+                )));
+            }
+        }
+        let grace = grace.unwrap_or(self.key_rotation_grace);
+        let rotation = self
+            .signer_manager
+            .lock()
+            .await
+            .rotate_signer(source, grace)
+            .await
+            .map_err(PaymasterError::SignerError)?;
+
+        // Cached sponsorships carry the retiring key's signature
+        self.signature_cache.clear();
+        info!(
+            "✅ Paymaster signing key rotated to {:?}, {:?} retires at {}",
+            rotation.active_signer,
+            rotation.retiring_signer.address,
+            rotation.retiring_signer.retire_at
+        );
+        self.metrics
+            .update_signer_draining(&format!("{:?}", rotation.active_signer), false);
+        Ok(rotation)
+    }
+
+    /// Paymaster balance and deposits read now, for the dashboard
+    ///
+    /// Reads that fail are listed in `errors` instead of failing the report.
+    pub async fn dashboard_balance(&self) -> DashboardBalance {
+        let (paymaster, account) = {
+            let signer_manager = self.signer_manager.lock().await;
+            (
+                alloy_primitives::Address::from_slice(signer_manager.address().as_bytes()),
+                alloy_primitives::Address::from_slice(signer_manager.signer_address().as_bytes()),
+            )
+        };
+        let thresholds = self
+            .balance_monitor
+            .as_ref()
+            .map(|monitor| monitor.config().clone())
+            .unwrap_or_default();
+        DashboardBalance::read(
+            self.deposit_manager.as_ref(),
+            paymaster,
+            account,
+            &thresholds,
+        )
+        .await
+    }
+
+    /// Loaded policies and their recent sponsorships, for the dashboard
+    pub fn dashboard_policies(&self) -> DashboardPolicies {
+        DashboardPolicies::read(&self.policy_engine(), &self.history)
+    }
+
+    /// Up to `limit` most recent sponsorship requests, for the dashboard
+    pub fn dashboard_transactions(&self, limit: usize) -> DashboardTransactions {
+        self.history.report(limit)
+    }
+
+    /// Loaded sponsorship policies
+    pub fn policy_engine(&self) -> Arc<PolicyEngine> {
+        self.policy_shadow.active()
+    }
+
+    /// Evaluate every sponsorship against the policy in `file` as well,
+    /// without enforcing it; replaces the candidate loaded before
+    ///
+    /// `file` is trusted, as configured by the operator; requests name a file
+    /// with [`Self::load_named_candidate_policy`] instead.
+    pub fn load_candidate_policy(&self, file: &Path) -> Result<PolicyShadowReport, PaymasterError> {
+        let report = self.policy_shadow.load_candidate(file)?;
+        info!("🪞 Shadow evaluating candidate policy {:?}", file);
+        Ok(report)
+    }
+
+    /// Load the file `name` of the configured candidate directory as candidate
+    /// policy, served by pm_loadCandidatePolicy
+    pub fn load_named_candidate_policy(
+        &self,
+        name: &str,
+    ) -> Result<PolicyShadowReport, PaymasterError> {
+        let report = self.policy_shadow.load_named_candidate(name)?;
+        info!(
+            "🪞 Shadow evaluating candidate policy {:?}",
+            report.candidate_file
+        );
+        Ok(report)
+    }
+
+    /// Divergences of the candidate policy from the active one, served by
+    /// pm_getPolicyShadowReport
+    pub fn policy_shadow_report(&self) -> PolicyShadowReport {
+        self.policy_shadow.report()
+    }
+
+    /// Enforce the candidate policy instead of the active one, returning the
+    /// candidate's final shadow report
+    pub fn promote_candidate_policy(&self) -> Result<PolicyShadowReport, PaymasterError> {
+        let report = self.policy_shadow.promote_candidate().ok_or_else(|| {
+            PaymasterError::InvalidRequest("No candidate policy is loaded".to_string())
+        })?;
+        // Cached sponsorships were allowed by the replaced policy
+        self.signature_cache.clear();
+        info!(
+            "✅ Candidate policy {:?} promoted after {} evaluations ({} would reject, {} would allow)",
+            report.candidate_file, report.evaluated, report.would_reject, report.would_allow
+        );
+        Ok(report)
+    }
+
+    /// Get reference to metrics for health endpoints
+    pub fn metrics(&self) -> &PaymasterMetrics {
+        &self.metrics
+    }
+
+    pub async fn sponsor_user_operation(
+        &self,
+        user_op: UserOperationVariant,
+        entry_point: Address,
+        options: SponsorOptions,
+    ) -> Result<PaymasterSponsorResult, PaymasterError> {
+        let start_time = Instant::now();
+
+        // Update active connections count
+        self.metrics.update_active_connections(1); // Simplified - would track actual count
+
+        let entry_point_address = alloy_primitives::Address::from_slice(entry_point.as_bytes());
+        let mut audit_record = AuditRecord::new(
+            &user_op,
+            entry_point_address,
+            options.requester.clone(),
+            self.audit_logger.include_sensitive(),
+        );
+        audit_record.request_id = options.request_id.clone();
+
+        let span = info_span!(
+            "sponsor_user_operation",
+            request_id = options.request_id.as_deref()
+        );
+        let return_full_operation = options.return_full_operation;
+        let mut result = self
+            .sponsor_user_operation_serialized(user_op, entry_point, options, &mut audit_record)
+            .instrument(span)
+            .await;
+        let duration = start_time.elapsed();
+
+        if let Ok(sponsorship) = &mut result {
+            if !return_full_operation {
+                sponsorship.sponsored_user_op = None;
+            }
+        }
+
+        audit_record.complete(&result, duration);
+        self.audit_logger.log(&audit_record);
+        self.history.record((&audit_record).into());
+
+        // Record metrics based on result
+        let labels = self.metric_labels(entry_point);
+        match &result {
+            Ok(_user_op_hash) => {
+                self.metrics.record_request_success(&labels, duration);
+                // Extract gas amount from the operation for monitoring
+                if let Ok(gas_amount) = self.estimate_gas_amount() {
+                    self.metrics.record_gas_sponsored(&labels, gas_amount);
+                }
+            }
+            Err(error) => {
+                let error_type = Self::categorize_error(error);
+                self.metrics
+                    .record_request_failure(&labels, error_type, duration);
+            }
+        }
+
+        // Reset active connections
+        self.metrics.update_active_connections(0);
+
+        result
+    }
+
+    /// Sponsor and account `user_op` while holding its sender and nonce
+    ///
+    /// Concurrent requests for the same operation wait for and share one
+    /// sponsorship, so it is signed and charged once; a different operation
+    /// for the same sender and nonce, or the same one asked for by another
+    /// requester or with another validity window, is rejected meanwhile.
+    async fn sponsor_user_operation_serialized(
+        &self,
+        user_op: UserOperationVariant,
+        entry_point: Address,
+        options: SponsorOptions,
+        audit_record: &mut AuditRecord,
+    ) -> Result<PaymasterSponsorResult, PaymasterError> {
+        let entry_point_address = alloy_primitives::Address::from_slice(entry_point.as_bytes());
+        let operation = sponsorship_lease(&user_op, entry_point_address, &options);
+        let lease = match self
+            .sender_locks
+            .acquire(user_op.sender(), user_op.nonce(), operation)
+        {
+            Ok(lease) => lease,
+            Err(e) => {
+                self.metrics
+                    .record_concurrent_sponsorship(&self.metric_labels(entry_point), false);
+                return Err(e);
+            }
+        };
+
+        let requester = options.requester.clone();
+        let deployment = options.deployment.clone();
+        let mut sponsored = false;
+        let result = lease
+            .slot()
+            .get_or_try_init(|| {
+                sponsored = true;
+                async move {
+                    let sponsorship = self
+                        .sponsor_user_operation_once(user_op, entry_point, options, audit_record)
+                        .await?;
+                    // Replays were accounted when first sponsored
+                    if !audit_record.replayed {
+                        if let Some(deployment) = &deployment {
+                            deployment.consume();
+                        }
+                        self.record_usage(requester, &sponsorship);
+                        self.record_sponsorship(
+                            entry_point_address,
+                            audit_record.policy_id.clone(),
+                            &sponsorship,
+                        );
+                    }
+                    Ok(sponsorship)
+                }
+            })
+            .await
+            .cloned();
+        if result.is_ok() && !sponsored {
+            audit_record.replayed = true;
+            debug!("Sharing the sponsorship of a concurrent request for the same operation");
+            self.metrics
+                .record_concurrent_sponsorship(&self.metric_labels(entry_point), true);
+        }
+        result
+    }
+
+    /// Sponsor `user_op`, or return the sponsorship already made for its
+    /// idempotency key
+    async fn sponsor_user_operation_once(
+        &self,
+        user_op: UserOperationVariant,
+        entry_point: Address,
+        options: SponsorOptions,
+        audit_record: &mut AuditRecord,
+    ) -> Result<PaymasterSponsorResult, PaymasterError> {
+        let Some(key) = options.idempotency_key.clone() else {
+            return self
+                .sponsor_user_operation_cached(user_op, entry_point, options, audit_record)
+                .await;
+        };
+
+        let operation = sponsorship_operation(
+            &user_op,
+            alloy_primitives::Address::from_slice(entry_point.as_bytes()),
+            options.token,
+        );
+        let slot = match self.idempotency.slot(&key, user_op.sender(), operation) {
+            Ok(slot) => slot,
+            Err(e) => {
+                self.metrics
+                    .record_idempotency_conflict(&self.metric_labels(entry_point));
+                return Err(e);
+            }
+        };
+
+        let mut sponsored = false;
+        let result = slot
+            .get_or_try_init(|| {
+                sponsored = true;
+                self.sponsor_user_operation_internal(
+                    user_op,
+                    entry_point,
+                    options,
+                    audit_record,
+                    false,
+                )
+            })
+            .await
+            .cloned();
+        if result.is_ok() && !sponsored {
+            audit_record.replayed = true;
+            debug!("Replaying sponsorship for idempotency key {:?}", key);
+            self.metrics
+                .record_idempotency_hit(&self.metric_labels(entry_point));
+        }
+        result
     }
 
-    pub async fn sponsor_user_operation(
+    /// Sponsor `user_op`, or return the signature cached for an identical
+    /// request made moments ago
+    ///
+    /// Covers wallets that sponsor, re-estimate gas and sponsor again with
+    /// unchanged fields, so they get a single signature and validity window.
+    async fn sponsor_user_operation_cached(
         &self,
         user_op: UserOperationVariant,
         entry_point: Address,
+        options: SponsorOptions,
+        audit_record: &mut AuditRecord,
     ) -> Result<PaymasterSponsorResult, PaymasterError> {
-        let start_time = Instant::now();
-
-        // Update active connections count
-        self.metrics.update_active_connections(1); // Simplified - would track actual count
-
-        let result = self
-            .sponsor_user_operation_internal(user_op, entry_point)
-            .await;
-        let duration = start_time.elapsed();
+        if !self.signature_cache.is_enabled() {
+            return self
+                .sponsor_user_operation_internal(user_op, entry_point, options, audit_record, false)
+                .await;
+        }
 
-        // Record metrics based on result
-        match &result {
-            Ok(_user_op_hash) => {
-                self.metrics.record_request_success(duration);
-                // Extract gas amount from the operation for monitoring
-                if let Ok(gas_amount) = self.estimate_gas_amount() {
-                    self.metrics.record_gas_sponsored(gas_amount);
-                }
-            }
-            Err(error) => {
-                let error_type = Self::categorize_error(error);
-                self.metrics.record_request_failure(error_type, duration);
+        let key = signature_cache::cache_key(
+            &user_op,
+            alloy_primitives::Address::from_slice(entry_point.as_bytes()),
+            &options,
+        );
+        if let Some(mut cached) = self.signature_cache.get(key, self.now()) {
+            self.metrics
+                .record_signature_cache(&self.metric_labels(entry_point), true);
+            audit_record.replayed = true;
+            debug!("Reusing the cached signature of an identical sponsorship request");
+            // The client's own signature is not part of the key, merge this request's
+            if let Some(paymaster) = cached
+                .sponsored_user_op
+                .as_ref()
+                .and_then(|sponsored| sponsored.paymaster())
+            {
+                cached.sponsored_user_op =
+                    Some(merge_sponsored_user_operation(user_op, paymaster, &cached));
             }
+            return Ok(cached);
         }
 
-        // Reset active connections
-        self.metrics.update_active_connections(0);
+        self.metrics
+            .record_signature_cache(&self.metric_labels(entry_point), false);
+        let result = self
+            .sponsor_user_operation_internal(user_op, entry_point, options, audit_record, false)
+            .await?;
+        self.signature_cache.insert(key, &result);
+        Ok(result)
+    }
 
-        result
+    /// Evaluate `user_op` against the candidate policy of `policies` on its own
+    /// task, recording where it disagrees with the active policy's `active`
+    ///
+    /// Resolves to the candidate when it allows the operation, for comparing
+    /// the token caps later on.
+    fn spawn_shadow_evaluation(
+        &self,
+        policies: &PolicySnapshot,
+        user_op: &UserOperationVariant,
+        active: &Result<String, PolicyRejection>,
+    ) -> Option<JoinHandle<Option<Arc<PolicyEngine>>>> {
+        let candidate = policies.candidate.clone()?;
+        let policies = policies.clone();
+        let policy_shadow = self.policy_shadow.clone();
+        let metrics = self.metrics.clone();
+        let user_op = user_op.clone();
+        let active = active.as_ref().map(|_| ()).map_err(Clone::clone);
+        Some(tokio::spawn(async move {
+            let result = candidate.evaluate(&user_op);
+            policy_shadow.record(
+                &policies,
+                user_op.sender(),
+                active.as_ref().copied(),
+                result.as_ref().map(|_| ()),
+                &metrics,
+            );
+            result.is_ok().then_some(candidate)
+        }))
     }
 
     async fn sponsor_user_operation_internal(
         &self,
         user_op: UserOperationVariant,
         entry_point: Address,
+        options: SponsorOptions,
+        audit_record: &mut AuditRecord,
+        preview: bool,
     ) -> Result<PaymasterSponsorResult, PaymasterError> {
-        // 1. Check policy
+        let timings = options.timings.clone();
+        let timings = timings.as_ref();
+        let labels = self.metric_labels(entry_point);
+        let mut validity_seconds = self.validity.window_seconds(options.validity_seconds)?;
+        if options.attest {
+            self.attestor()?;
+        }
+        // The chain would read the validity window differently from the local clock
+        if let Some(clock_guard) = &self.clock_guard {
+            clock_guard.check_signing()?;
+        }
+
+        // 0. Only sponsor for EntryPoints with a deployed paymaster contract
+        let routed_paymaster = match &self.entry_point_routing {
+            Some(routing) => {
+                let entry_point = alloy_primitives::Address::from_slice(entry_point.as_bytes());
+                Some(routing.resolve(entry_point, &user_op)?.paymaster)
+            }
+            None => None,
+        };
+
+        // 1. Check policy, and the candidate policy off the request path
+        let policies = self.policy_shadow.snapshot();
         let policy_start = Instant::now();
-        let policy_result = self.policy_engine.check_policy(&user_op);
+        let policy_result = policies.active.evaluate(&user_op);
         let policy_duration = policy_start.elapsed();
         self.metrics
-            .record_validation(policy_result.is_ok(), policy_duration);
+            .record_validation(&labels, policy_result.is_ok(), policy_duration);
+        record_stage(timings, SponsorStage::PolicyEvaluation, policy_duration);
+        let shadow = self.spawn_shadow_evaluation(&policies, &user_op, &policy_result);
+
+        let policy_id = match policy_result {
+            Ok(policy_id) => {
+                audit_record.policy_id = Some(policy_id.clone());
+                policy_id
+            }
+            Err(e) => {
+                self.metrics
+                    .record_policy_violation(&labels, "validation_failed");
+                return Err(e.into());
+            }
+        };
+
+        // 2. Require the sender to hold the configured SBT and PNTs
+        if let Some(sbt_validator) = &self.sbt_validator {
+            let sbt_start = Instant::now();
+            let sbt_result = sbt_validator.check(user_op.sender()).await;
+            record_stage(timings, SponsorStage::SbtCheck, sbt_start.elapsed());
+            if let Err(e) = sbt_result {
+                if matches!(e, PaymasterError::PolicyRejected(_)) {
+                    self.metrics
+                        .record_policy_violation(&labels, "sbt_requirement");
+                }
+                return Err(e);
+            }
+        }
+
+        // 3. Let the policy's external authorizer have the final say
+        if let Some((authorizer, request)) = policies.active.authorization_request(
+            &policy_id,
+            &user_op,
+            alloy_primitives::Address::from_slice(entry_point.as_bytes()),
+        ) {
+            let authorizer_start = Instant::now();
+            let decision = self.authorizer.authorize(authorizer, &request).await;
+            record_stage(
+                timings,
+                SponsorStage::ExternalAuthorizer,
+                authorizer_start.elapsed(),
+            );
+            let decision = match decision {
+                Ok(decision) => decision,
+                Err(e) => {
+                    self.metrics
+                        .record_policy_violation(&labels, "external_authorizer");
+                    return Err(e);
+                }
+            };
+            // The customer's window is trusted but kept within the configured bounds
+            if let Some(seconds) = decision.validity_seconds {
+                validity_seconds = self.validity.clamp_seconds(seconds);
+                debug!(
+                    "Authorizer of policy {} set the validity window to {}s",
+                    policy_id, validity_seconds
+                );
+            }
+        }
+
+        // 4. Make sure the operation pays enough to be bundled before the signature expires
+        let bumped_fees = match &self.fee_checker {
+            Some(fee_checker) => {
+                let fee_start = Instant::now();
+                let bumped_fees = fee_checker.check(&user_op).await;
+                record_stage(timings, SponsorStage::FeeCheck, fee_start.elapsed());
+                bumped_fees?
+            }
+            None => None,
+        };
+        if let Some(fees) = bumped_fees {
+            info!(
+                "⛽ Bumping UserOperation fees to maxFeePerGas={} maxPriorityFeePerGas={}",
+                fees.max_fee_per_gas, fees.max_priority_fee_per_gas
+            );
+        }
+
+        // 5. Price the token before taking the signer, oracles are read on chain
+        let token_payment = match options.token {
+            Some(token) => {
+                let token_pricing = self.token_pricing()?;
+                let pricing_start = Instant::now();
+                let rate = token_pricing.rate(token, self.now()).await;
+                record_stage(timings, SponsorStage::TokenPricing, pricing_start.elapsed());
+                let rate = rate?;
+                Some((token_pricing, token, rate))
+            }
+            None => None,
+        };
+
+        // 6. Count an account deployment against the policy's factory quotas,
+        // unless reserved with its batch; previews only check them
+        let reserved = options
+            .deployment
+            .as_ref()
+            .map(|deployment| deployment.factory());
+        if let Some(factory) = user_op
+            .factory()
+            .filter(|factory| Some(*factory) != reserved)
+        {
+            let limits = policies.active.deployment_limits(&policy_id, factory);
+            let now = self.now();
+            let exceeded = if preview {
+                self.deployments.exceeded_deployment_limit(now, &limits)
+            } else {
+                self.deployments
+                    .record_deployment(factory, now, &limits)
+                    .err()
+            };
+            if let Some(limit) = exceeded {
+                self.metrics
+                    .record_policy_violation(&labels, "deployment_quota");
+                return Err(PaymasterError::DeploymentQuotaExceeded {
+                    factory,
+                    limit,
+                    resets_at: limit.resets_at(now),
+                });
+            }
+        }
+
+        // 7. Build the paymaster fields the signature commits to, taking the
+        // next pool key so concurrent sponsorships sign with different keys
+        let (sponsor_address, signer) = {
+            let mut signer_manager = self.signer_manager.lock().await;
+            let signer = signer_manager
+                .next_signer()
+                .map_err(PaymasterError::SignerError)?;
+            (
+                routed_paymaster.unwrap_or_else(|| {
+                    alloy_primitives::Address::from_slice(signer_manager.address().as_bytes())
+                }),
+                signer,
+            )
+        };
+        let paymaster_address = match &token_payment {
+            Some((token_pricing, _, _)) => {
+                token_pricing.config().paymaster.unwrap_or(sponsor_address)
+            }
+            None => sponsor_address,
+        };
+        let (valid_after, valid_until) = match &self.clock_guard {
+            Some(clock_guard) => clock_guard.validity_window(validity_seconds),
+            None => (0, unix_now() + validity_seconds),
+        };
+
+        let mut result = match &user_op {
+            UserOperationVariant::V0_6(_op) => PaymasterSponsorResult {
+                paymaster_and_data: vec![],
+                verification_gas_limit: None,
+                post_op_gas_limit: None,
+                pre_verification_gas: None,
+                verification_gas_limit_uo: None,
+                call_gas_limit: None,
+                sponsored_user_op: None,
+                max_fee_per_gas: None,
+                max_priority_fee_per_gas: None,
+                valid_until,
+                valid_after,
+                token_quote: None,
+                cached: false,
+            },
+            _ => {
+                // For v0.7 and v0.8, return separate paymaster fields
+                let paymaster_verification_gas_limit = self.paymaster_gas.verification_gas_limit;
+                // Token payments transfer the token in postOp
+                let paymaster_post_op_gas_limit = match &token_payment {
+                    Some((token_pricing, _, _)) => token_pricing.config().post_op_gas_limit,
+                    None => self.paymaster_gas.post_op_gas_limit,
+                };
+
+                PaymasterSponsorResult {
+                    paymaster_and_data: vec![],
+                    verification_gas_limit: Some(paymaster_verification_gas_limit),
+                    post_op_gas_limit: Some(paymaster_post_op_gas_limit),
+                    pre_verification_gas: None,
+                    verification_gas_limit_uo: None,
+                    call_gas_limit: None,
+                    sponsored_user_op: None,
+                    max_fee_per_gas: None,
+                    max_priority_fee_per_gas: None,
+                    valid_until,
+                    valid_after,
+                    token_quote: None,
+                    cached: false,
+                }
+            }
+        };
+        if let Some(fees) = bumped_fees {
+            result.max_fee_per_gas = Some(fees.max_fee_per_gas);
+            result.max_priority_fee_per_gas = Some(fees.max_priority_fee_per_gas);
+        }
 
-        if let Err(e) = policy_result {
-            self.metrics.record_policy_violation("validation_failed");
-            return Err(e);
+        // The hash covers the operation as it will be submitted, including paymaster gas
+        let merged = merge_sponsored_user_operation(user_op.clone(), paymaster_address, &result);
+        // Paymaster gas limits the client set are kept and signed, so report those
+        if let Some((verification_gas_limit, post_op_gas_limit)) = paymaster_gas_limits(&merged)? {
+            result.verification_gas_limit = Some(verification_gas_limit);
+            result.post_op_gas_limit = Some(post_op_gas_limit);
         }
+        let sponsorship_hash = match &token_payment {
+            Some((token_pricing, token, rate)) => {
+                // The quote is for the most the operation can cost, at the exact rate signed
+                let quote = token_pricing.quote(
+                    *token,
+                    *rate,
+                    sponsorship::sponsored_max_gas_cost(&merged),
+                    valid_until,
+                    valid_after,
+                )?;
+                let payment_result = policies.active.evaluate_token_payment(
+                    &policy_id,
+                    *token,
+                    quote.max_token_amount,
+                );
+                // The candidate's cap is compared if it allowed the operation itself
+                let candidate = match shadow {
+                    Some(handle) => handle.await.ok().flatten(),
+                    None => None,
+                };
+                if let Some(candidate) = candidate {
+                    let candidate_result = candidate.evaluate_token_payment(
+                        &policy_id,
+                        *token,
+                        quote.max_token_amount,
+                    );
+                    self.policy_shadow.record(
+                        &policies,
+                        user_op.sender(),
+                        payment_result.as_ref().map(|_| ()),
+                        candidate_result.as_ref().map(|_| ()),
+                        &self.metrics,
+                    );
+                }
+                if let Err(e) = payment_result {
+                    self.metrics
+                        .record_policy_violation(&labels, "token_payment");
+                    return Err(e.into());
+                }
+                result.token_quote = Some(quote);
+                token_paymaster::token_payment_hash(
+                    &merged,
+                    paymaster_address,
+                    *token,
+                    *rate,
+                    valid_until,
+                    valid_after,
+                )
+            }
+            None => {
+                sponsorship::sponsorship_hash(&merged, paymaster_address, valid_until, valid_after)
+            }
+        };
 
-        // 2. Sign the hash using KMS/hardware wallet integration
+        // 8. Sign the hash using KMS/hardware wallet integration
         let signing_start = Instant::now();
-        let user_op_hash = user_op.hash();
 
         debug!(
             "🔐 Paymaster signing UserOperation: hash={:?}, entry_point={:?}",
-            user_op_hash, entry_point
+            sponsorship_hash, entry_point
         );
 
         // Create comprehensive signing context for KMS audit logging
         let signing_context =
-            self.create_signing_context(&user_op, entry_point, H256::from_slice(&user_op_hash.0));
+            self.create_signing_context(&user_op, entry_point, H256::from_slice(&user_op.hash().0));
 
-        let mut signer_manager = self.signer_manager.lock().await;
         info!(
-            "🔑 Using {} backend for UserOperation signing",
-            signer_manager.backend_type()
+            "🔑 Using {} backend of signer {:?} for UserOperation signing",
+            signer.backend_type(),
+            signer.address()
         );
 
-        let signature = signer_manager
-            .sign_hash_with_context(user_op_hash.into(), Some(signing_context))
+        let signature = signer
+            .sign_hash_with_context(
+                sponsorship::signing_digest(sponsorship_hash).0,
+                Some(signing_context),
+            )
             .await;
         let signing_duration = signing_start.elapsed();
+        record_stage(timings, SponsorStage::Signing, signing_duration);
 
         let signature = match signature {
             Ok(sig) => {
                 self.metrics
-                    .record_signature_operation(true, signing_duration);
+                    .record_signature_operation(&labels, true, signing_duration);
+                self.metrics
+                    .record_signer_signature(&labels, &format!("{:?}", signer.address()));
+                audit_record.signer = Some(
+                    alloy_primitives::Address::from_slice(signer.address().as_bytes())
+                        .to_checksum(None),
+                );
                 sig
             }
             Err(e) => {
                 self.metrics
-                    .record_signature_operation(false, signing_duration);
+                    .record_signature_operation(&labels, false, signing_duration);
                 return Err(PaymasterError::SignerError(e));
             }
         };
 
-        // 3. Generate paymaster and data for the client to use
-        let paymaster_address = signer_manager.address();
-
-        match user_op {
+        // Name the leased key, which the signature recovers to
+        let signer = alloy_primitives::Address::from_slice(signer.address().as_bytes());
+        let paymaster_data = match &token_payment {
+            Some((_, token, rate)) => TokenPaymasterData {
+                token: *token,
+                rate: *rate,
+                valid_until,
+                valid_after,
+                signer,
+                signature: signature.to_vec(),
+            }
+            .encode(),
+            None => SponsorshipData {
+                valid_until,
+                valid_after,
+                signer,
+                signature: signature.to_vec(),
+            }
+            .encode(),
+        };
+        result.paymaster_and_data = match &user_op {
+            // For v0.6, prefix the paymaster address to form paymasterAndData
             UserOperationVariant::V0_6(_op) => {
-                // For v0.6, combine paymaster address and signature into paymasterAndData
-                let paymaster_and_data =
-                    [paymaster_address.as_bytes(), &signature.to_vec()].concat();
-
-                Ok(PaymasterSponsorResult {
-                    paymaster_and_data,
-                    verification_gas_limit: None,
-                    post_op_gas_limit: None,
-                    pre_verification_gas: None,
-                    verification_gas_limit_uo: None,
-                    call_gas_limit: None,
-                })
+                [paymaster_address.as_slice(), &paymaster_data].concat()
             }
-            UserOperationVariant::V0_7(_op) => {
-                // For v0.7, return separate paymaster fields
-                let paymaster_verification_gas_limit = 100_000;
-                let paymaster_post_op_gas_limit = 20_000;
+            _ => paymaster_data,
+        };
 
-                Ok(PaymasterSponsorResult {
-                    paymaster_and_data: signature.to_vec(),
-                    verification_gas_limit: Some(paymaster_verification_gas_limit),
-                    post_op_gas_limit: Some(paymaster_post_op_gas_limit),
-                    pre_verification_gas: None,
-                    verification_gas_limit_uo: None,
-                    call_gas_limit: None,
-                })
+        // 9. Keep the complete sponsored operation for usage accounting and
+        // callers asking for it
+        result.sponsored_user_op = Some(merge_sponsored_user_operation(
+            user_op,
+            paymaster_address,
+            &result,
+        ));
+
+        Ok(result)
+    }
+
+    /// Account `sponsorship` to the API key of `requester`
+    fn record_usage(&self, requester: Option<String>, sponsorship: &PaymasterSponsorResult) {
+        let (Some(usage), Some(sponsored)) = (&self.usage, &sponsorship.sponsored_user_op) else {
+            return;
+        };
+        usage.record_sponsorship(SponsorshipUsage {
+            api_key_id: requester.unwrap_or_else(|| ANONYMOUS_KEY_ID.to_string()),
+            sender: sponsored.sender(),
+            user_op_hash: sponsored.hash(),
+            max_gas_cost: sponsorship::sponsored_max_gas_cost(sponsored),
+            timestamp: unix_now(),
+        });
+    }
+
+    fn record_sponsorship(
+        &self,
+        entry_point: alloy_primitives::Address,
+        policy_id: Option<String>,
+        sponsorship: &PaymasterSponsorResult,
+    ) {
+        let (Some(records), Some(sponsored)) =
+            (&self.sponsorship_records, &sponsorship.sponsored_user_op)
+        else {
+            return;
+        };
+        let now = unix_now();
+        records.record_sponsorship(SponsorshipRecord {
+            user_op_hash: sponsored.hash(),
+            sender: sponsored.sender(),
+            policy_id,
+            chain_id: sponsored.chain_id(),
+            entry_point,
+            valid_after: sponsorship.valid_after,
+            valid_until: sponsorship.valid_until,
+            sponsored_at: now,
+            updated_at: now,
+            status: SponsorshipStatus::Pending,
+            reason: None,
+            transaction_hash: None,
+            block_number: None,
+            success: None,
+        });
+    }
+
+    /// Preview sponsorship of `user_op` without handing out a signature
+    ///
+    /// Runs the sponsorship checks, then simulates EntryPoint validation of the
+    /// operation as it would be sponsored. Failed checks are reported as a
+    /// rejected preview rather than an error.
+    pub async fn simulate_sponsorship(
+        &self,
+        user_op: UserOperationVariant,
+        entry_point: Address,
+    ) -> Result<SponsorshipSimulation, PaymasterError> {
+        let simulator = self.simulator.as_ref().ok_or_else(|| {
+            PaymasterError::InvalidRequest("Sponsorship simulation is not configured".to_string())
+        })?;
+        let entry_point_address = alloy_primitives::Address::from_slice(entry_point.as_bytes());
+
+        // The record is never logged, previews do not sponsor anything
+        let mut audit_record = AuditRecord::new(&user_op, entry_point_address, None, false);
+        let options = SponsorOptions {
+            return_full_operation: true,
+            ..Default::default()
+        };
+        let sponsored = match self
+            .sponsor_user_operation_internal(user_op, entry_point, options, &mut audit_record, true)
+            .await
+        {
+            Ok(result) => result
+                .sponsored_user_op
+                .expect("full operation is requested"),
+            Err(
+                e @ (PaymasterError::PolicyRejected(_)
+                | PaymasterError::FeesTooLow { .. }
+                | PaymasterError::DeploymentQuotaExceeded { .. }),
+            ) => {
+                return Ok(SponsorshipSimulation::rejected(e.to_string()));
+            }
+            Err(e) => return Err(e),
+        };
+
+        let validation = simulator
+            .simulate_validation(entry_point_address, sponsored.clone())
+            .await?;
+        Ok(SponsorshipSimulation::from_validation(
+            audit_record.policy_id.unwrap_or_default(),
+            &sponsored,
+            validation,
+        ))
+    }
+
+    /// Check `user_ops` against the active policy and count their account
+    /// deployments against the quotas as one batch, before sponsoring any
+    ///
+    /// Deployments earlier in the batch count against the quotas of later
+    /// ones. The deployments stay counted while the returned reservations,
+    /// one per operation, are held: passed in [`SponsorOptions::deployment`],
+    /// a sponsored operation keeps its own, and dropping the others gives
+    /// them back.
+    pub fn reserve_batch_budget(
+        &self,
+        user_ops: &[UserOperationVariant],
+    ) -> Result<Vec<Option<Arc<DeploymentReservation>>>, BatchRejection> {
+        let policies = self.policy_shadow.snapshot();
+        let now = self.now();
+        let mut reservations = Vec::with_capacity(user_ops.len());
+        for (index, user_op) in user_ops.iter().enumerate() {
+            let policy_id = policies
+                .active
+                .evaluate(user_op)
+                .map_err(|e| BatchRejection {
+                    index,
+                    error: e.into(),
+                })?;
+            let Some(factory) = user_op.factory() else {
+                reservations.push(None);
+                continue;
+            };
+            let limits = policies.active.deployment_limits(&policy_id, factory);
+            let reservation =
+                DeploymentReservation::reserve(self.deployments.clone(), factory, now, &limits)
+                    .map_err(|limit| BatchRejection {
+                        index,
+                        error: PaymasterError::DeploymentQuotaExceeded {
+                            factory,
+                            limit,
+                            resets_at: limit.resets_at(now),
+                        },
+                    })?;
+            reservations.push(Some(Arc::new(reservation)));
+        }
+        Ok(reservations)
+    }
+
+    /// Verify a UserOperation's paymaster signature if it names our paymaster
+    /// or token paymaster
+    ///
+    /// Returns `Ok(false)` when the operation uses another (or no) paymaster.
+    pub async fn verify_own_sponsorship(
+        &self,
+        user_op: &UserOperationVariant,
+    ) -> Result<bool, SponsorshipError> {
+        let now = self.now();
+        let (paymaster_address, signers) = {
+            let signer_manager = self.signer_manager.lock().await;
+            let signers: Vec<alloy_primitives::Address> = signer_manager
+                .verifying_addresses(now)
+                .iter()
+                .map(|signer| alloy_primitives::Address::from_slice(signer.as_bytes()))
+                .collect();
+            (
+                alloy_primitives::Address::from_slice(signer_manager.address().as_bytes()),
+                signers,
+            )
+        };
+        let token_paymaster = self.token_pricing.as_ref().map(|token_pricing| {
+            token_pricing
+                .config()
+                .paymaster
+                .unwrap_or(paymaster_address)
+        });
+
+        match user_op.paymaster() {
+            Some(paymaster)
+                if Some(paymaster) == token_paymaster
+                    && token_paymaster::is_token_payment(user_op) =>
+            {
+                token_paymaster::verify_token_payment_by(user_op, paymaster, &signers, now)?;
             }
+            Some(paymaster)
+                if paymaster == paymaster_address
+                    || self
+                        .entry_point_routing
+                        .as_ref()
+                        .is_some_and(|routing| routing.is_paymaster(paymaster)) =>
+            {
+                sponsorship::verify_sponsorship_by(user_op, paymaster, &signers, now)?;
+            }
+            _ => return Ok(false),
         }
+        Ok(true)
     }
 
     /// Categorize errors for metrics
@@ -206,38 +1890,20 @@ impl PaymasterRelayService {
         entry_point: Address,
         user_op_hash: H256,
     ) -> SigningContext {
-        let (sender_address, gas_estimates) = match user_op {
-            UserOperationVariant::V0_6(op) => {
-                let gas_est = GasEstimates {
-                    call_gas_limit: op.call_gas_limit(),
-                    verification_gas_limit: op.verification_gas_limit(),
-                    pre_verification_gas: op.pre_verification_gas(),
-                    max_fee_per_gas: op.max_fee_per_gas(),
-                    max_priority_fee_per_gas: op.max_priority_fee_per_gas(),
-                };
-                (op.sender(), gas_est)
-            }
-            UserOperationVariant::V0_7(op) => {
-                let gas_est = GasEstimates {
-                    call_gas_limit: op.call_gas_limit(),
-                    verification_gas_limit: op.verification_gas_limit(),
-                    pre_verification_gas: op.pre_verification_gas(),
-                    max_fee_per_gas: op.max_fee_per_gas(),
-                    max_priority_fee_per_gas: op.max_priority_fee_per_gas(),
-                };
-                (op.sender(), gas_est)
-            }
+        let sender_address = user_op.sender();
+        let gas_estimates = GasEstimates {
+            call_gas_limit: user_op.call_gas_limit(),
+            verification_gas_limit: user_op.verification_gas_limit(),
+            pre_verification_gas: user_op.pre_verification_gas(),
+            max_fee_per_gas: user_op.max_fee_per_gas(),
+            max_priority_fee_per_gas: user_op.max_priority_fee_per_gas(),
         };
 
         let mut metadata = HashMap::new();
         metadata.insert("paymaster_service".to_string(), "SuperRelay".to_string());
         metadata.insert(
             "entry_point_version".to_string(),
-            match user_op {
-                UserOperationVariant::V0_6(_) => "v0.6",
-                UserOperationVariant::V0_7(_) => "v0.7",
-            }
-            .to_string(),
+            EntryPointVersion::of(user_op).to_string(),
         );
 
         // Get backend type from locked signer manager
@@ -271,7 +1937,7 @@ impl PaymasterRelayService {
     /// Get KMS audit information for compliance
     pub async fn get_kms_audit_info(&self) -> Option<Vec<crate::kms::SigningAuditInfo>> {
         let signer_manager = self.signer_manager.lock().await;
-        signer_manager.get_kms_audit_log()
+        signer_manager.get_kms_audit_log().await
     }
 
     /// Get signer configuration details
@@ -282,6 +1948,20 @@ impl PaymasterRelayService {
             "current_address".to_string(),
             format!("{:?}", signer_manager.address()),
         );
+        info.insert(
+            "signer_address".to_string(),
+            format!("{:?}", signer_manager.signer_address()),
+        );
+        if let Some(retiring) = signer_manager.retiring_signer(unix_now()) {
+            info.insert(
+                "retiring_signer_address".to_string(),
+                format!("{:?}", retiring.address),
+            );
+            info.insert(
+                "retiring_signer_until".to_string(),
+                retiring.retire_at.to_string(),
+            );
+        }
         info.insert(
             "service_name".to_string(),
             "PaymasterRelayService".to_string(),
@@ -334,9 +2014,602 @@ impl PaymasterRelayService {
     }
 }
 
+/// Merge sponsorship output into the UserOperation it was produced for
+///
+/// v0.6 takes `paymasterAndData` as is; v0.7 and v0.8 split it into paymaster
+/// address, verification gas, post-op gas and data. Gas adjustments from the sponsor are
+/// only applied to limits the client left at zero, so explicit values survive.
+/// Bumped fees always replace the client's, as the signature covers them.
+pub fn merge_sponsored_user_operation(
+    user_op: UserOperationVariant,
+    paymaster: alloy_primitives::Address,
+    result: &PaymasterSponsorResult,
+) -> UserOperationVariant {
+    fn adjust(current: u128, adjusted: Option<u64>) -> u128 {
+        match adjusted {
+            Some(value) if current == 0 => value.into(),
+            _ => current,
+        }
+    }
+
+    fn merge_packed(
+        op: v0_7::UserOperation,
+        paymaster: alloy_primitives::Address,
+        result: &PaymasterSponsorResult,
+        chain_spec: &ChainSpec,
+    ) -> v0_7::UserOperation {
+        let pre_verification_gas = adjust(op.pre_verification_gas(), result.pre_verification_gas);
+        let verification_gas_limit = adjust(
+            op.verification_gas_limit(),
+            result.verification_gas_limit_uo,
+        );
+        let call_gas_limit = adjust(op.call_gas_limit(), result.call_gas_limit);
+        let paymaster_verification_gas_limit = adjust(
+            op.paymaster_verification_gas_limit(),
+            result.verification_gas_limit,
+        );
+        let paymaster_post_op_gas_limit =
+            adjust(op.paymaster_post_op_gas_limit(), result.post_op_gas_limit);
+        let max_fee_per_gas = result.max_fee_per_gas.unwrap_or(op.max_fee_per_gas());
+        let max_priority_fee_per_gas = result
+            .max_priority_fee_per_gas
+            .unwrap_or(op.max_priority_fee_per_gas());
+
+        v0_7::UserOperationBuilder::from_uo(op, chain_spec)
+            .max_fee_per_gas(max_fee_per_gas)
+            .max_priority_fee_per_gas(max_priority_fee_per_gas)
+            .pre_verification_gas(pre_verification_gas)
+            .verification_gas_limit(verification_gas_limit)
+            .call_gas_limit(call_gas_limit)
+            .paymaster(
+                paymaster,
+                paymaster_verification_gas_limit,
+                paymaster_post_op_gas_limit,
+                result.paymaster_and_data.clone().into(),
+            )
+            .build()
+    }
+
+    let chain_spec = ChainSpec {
+        id: user_op.chain_id(),
+        entry_point_address_v0_6: user_op.entry_point(),
+        entry_point_address_v0_7: user_op.entry_point(),
+        #[cfg(feature = "entrypoint-v0_8")]
+        entry_point_address_v0_8: user_op.entry_point(),
+        ..Default::default()
+    };
+
+    match user_op {
+        UserOperationVariant::V0_6(op) => {
+            let pre_verification_gas =
+                adjust(op.pre_verification_gas(), result.pre_verification_gas);
+            let verification_gas_limit = adjust(
+                op.verification_gas_limit(),
+                result.verification_gas_limit_uo,
+            );
+            let call_gas_limit = adjust(op.call_gas_limit(), result.call_gas_limit);
+            let max_fee_per_gas = result.max_fee_per_gas.unwrap_or(op.max_fee_per_gas());
+            let max_priority_fee_per_gas = result
+                .max_priority_fee_per_gas
+                .unwrap_or(op.max_priority_fee_per_gas());
+
+            let op = v0_6::UserOperationBuilder::from_uo(op, &chain_spec)
+                .max_fee_per_gas(max_fee_per_gas)
+                .max_priority_fee_per_gas(max_priority_fee_per_gas)
+                .pre_verification_gas(pre_verification_gas)
+                .verification_gas_limit(verification_gas_limit)
+                .call_gas_limit(call_gas_limit)
+                .paymaster_and_data(result.paymaster_and_data.clone().into())
+                .build();
+            UserOperationVariant::V0_6(op)
+        }
+        UserOperationVariant::V0_7(op) => {
+            UserOperationVariant::V0_7(merge_packed(op, paymaster, result, &chain_spec))
+        }
+        #[cfg(feature = "entrypoint-v0_8")]
+        UserOperationVariant::V0_8(op) => {
+            let merged = merge_packed(op.into_fields(), paymaster, result, &chain_spec);
+            UserOperationVariant::V0_8(v0_8::UserOperation::from_fields(merged, &chain_spec))
+        }
+    }
+}
+
+/// Paymaster verification and post-op gas limits of the merged `user_op`,
+/// `None` for v0.6 where they are not separate fields
+fn paymaster_gas_limits(
+    user_op: &UserOperationVariant,
+) -> Result<Option<(u64, u64)>, PaymasterError> {
+    let (verification_gas_limit, post_op_gas_limit) = match user_op {
+        UserOperationVariant::V0_6(_) => return Ok(None),
+        UserOperationVariant::V0_7(op) => (
+            op.paymaster_verification_gas_limit(),
+            op.paymaster_post_op_gas_limit(),
+        ),
+        #[cfg(feature = "entrypoint-v0_8")]
+        UserOperationVariant::V0_8(op) => (
+            op.paymaster_verification_gas_limit(),
+            op.paymaster_post_op_gas_limit(),
+        ),
+    };
+    let limit = |value: u128, field: &str| {
+        u64::try_from(value).map_err(|_| {
+            PaymasterError::InvalidRequest(format!("{} does not fit in 64 bits", field))
+        })
+    };
+    Ok(Some((
+        limit(verification_gas_limit, "paymasterVerificationGasLimit")?,
+        limit(post_op_gas_limit, "paymasterPostOpGasLimit")?,
+    )))
+}
+
+/// Identity of a sponsorship request for `user_op`
+///
+/// Paying in a token is a different request than sponsoring the same
+/// operation.
+fn sponsorship_operation(
+    user_op: &UserOperationVariant,
+    entry_point: alloy_primitives::Address,
+    token: Option<alloy_primitives::Address>,
+) -> alloy_primitives::B256 {
+    let operation = idempotency::operation_hash(user_op, entry_point);
+    match token {
+        Some(token) => {
+            alloy_primitives::keccak256([operation.as_slice(), token.as_slice()].concat())
+        }
+        None => operation,
+    }
+}
+
+/// Identity of the sponsorship a request holds its sender and nonce for
+///
+/// As with [`signature_cache::cache_key`], the requester and validity window
+/// are part of it: sharing the sponsorship of another API key would account
+/// it to that key only.
+fn sponsorship_lease(
+    user_op: &UserOperationVariant,
+    entry_point: alloy_primitives::Address,
+    options: &SponsorOptions,
+) -> alloy_primitives::B256 {
+    canonical::canonical_digest(&serde_json::json!({
+        "operation": sponsorship_operation(user_op, entry_point, options.token),
+        "validitySeconds": options.validity_seconds,
+        "requester": options.requester,
+    }))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
 /// Get memory usage in MB (placeholder implementation)
 fn get_memory_usage_mb() -> u64 {
     // In a real implementation, you'd use system metrics
     // For now, return a placeholder value
     78 // Matches our test results
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        str::FromStr,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use alloy_primitives::{address, Address, Bytes, U256, U64};
+    use rundler_types::{chain::ChainSpec, v0_6, v0_7, UserOperationVariant};
+
+    use super::*;
+    use crate::{
+        sponsorship::{verify_sponsorship, SponsorshipData, DUMMY_SIGNATURE},
+        test_utils,
+    };
+
+    const PAYMASTER: alloy_primitives::Address =
+        address!("63c0c19a282a1b52b07dd5a65b58948a07dae32b");
+
+    fn sponsor_result(paymaster_and_data: Vec<u8>) -> PaymasterSponsorResult {
+        PaymasterSponsorResult {
+            paymaster_and_data,
+            verification_gas_limit: Some(100_000),
+            post_op_gas_limit: Some(20_000),
+            pre_verification_gas: Some(50_000),
+            verification_gas_limit_uo: None,
+            call_gas_limit: Some(300_000),
+            sponsored_user_op: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            valid_until: 0,
+            valid_after: 0,
+            token_quote: None,
+            cached: false,
+        }
+    }
+
+    #[test]
+    fn test_merge_v06_keeps_client_fields() {
+        let chain_spec = ChainSpec::default();
+        let op = v0_6::UserOperationBuilder::new(
+            &chain_spec,
+            v0_6::UserOperationRequiredFields {
+                sender: address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266"),
+                nonce: U256::from(1),
+                call_gas_limit: 0,
+                verification_gas_limit: 150_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 1_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                init_code: Bytes::new(),
+                call_data: Bytes::new(),
+                paymaster_and_data: Bytes::new(),
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        let signed_hash = op.hash();
+
+        let paymaster_and_data = [PAYMASTER.as_slice(), &[0xab; 65]].concat();
+        let merged = merge_sponsored_user_operation(
+            UserOperationVariant::V0_6(op),
+            PAYMASTER,
+            &sponsor_result(paymaster_and_data.clone()),
+        );
+
+        let UserOperationVariant::V0_6(merged) = merged else {
+            panic!("expected v0.6 operation");
+        };
+        assert_eq!(
+            merged.paymaster_and_data(),
+            &Bytes::from(paymaster_and_data)
+        );
+        // Explicit values are untouched, unset ones are filled in
+        assert_eq!(merged.pre_verification_gas(), 21_000);
+        assert_eq!(merged.verification_gas_limit(), 150_000);
+        assert_eq!(merged.call_gas_limit(), 300_000);
+
+        // Stripping the paymaster fields gives back the hash that was signed
+        // (gas limits the sponsor filled in are restored to their originals)
+        let stripped = v0_6::UserOperationBuilder::from_uo(merged, &chain_spec)
+            .call_gas_limit(0)
+            .clear_paymaster()
+            .build();
+        assert_eq!(stripped.hash(), signed_hash);
+    }
+
+    #[test]
+    fn test_merge_v07_splits_paymaster_fields() {
+        let chain_spec = ChainSpec::default();
+        let op = v0_7::UserOperationBuilder::new(
+            &chain_spec,
+            v0_7::UserOperationRequiredFields {
+                sender: address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266"),
+                nonce: U256::from(1),
+                call_gas_limit: 200_000,
+                verification_gas_limit: 150_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 1_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                call_data: Bytes::new(),
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        let signed_hash = op.hash();
+
+        let signature = vec![0xcd; 65];
+        let merged = merge_sponsored_user_operation(
+            UserOperationVariant::V0_7(op),
+            PAYMASTER,
+            &sponsor_result(signature.clone()),
+        );
+
+        let UserOperationVariant::V0_7(merged) = merged else {
+            panic!("expected v0.7 operation");
+        };
+        assert_eq!(merged.paymaster(), Some(PAYMASTER));
+        assert_eq!(merged.paymaster_verification_gas_limit(), 100_000);
+        assert_eq!(merged.paymaster_post_op_gas_limit(), 20_000);
+        assert_eq!(merged.paymaster_data(), &Bytes::from(signature));
+        assert_eq!(merged.call_gas_limit(), 200_000);
+
+        let stripped = v0_7::UserOperationBuilder::from_uo(merged, &chain_spec)
+            .clear_paymaster()
+            .build();
+        assert_eq!(stripped.hash(), signed_hash);
+    }
+
+    const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    fn create_service() -> PaymasterRelayService {
+        test_utils::service(&format!("senders = [\"{}\"]\n", SENDER)).with_validity(
+            ValidityConfig {
+                default_seconds: 600,
+                min_seconds: 60,
+                max_seconds: 1800,
+            },
+        )
+    }
+
+    fn v0_6_op() -> (UserOperationVariant, ethers::types::Address) {
+        let op = v0_6::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_6::UserOperationRequiredFields {
+                sender: Address::from_str(SENDER).unwrap(),
+                nonce: U256::ZERO,
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                ..Default::default()
+            },
+        )
+        .build();
+        let entry_point = ChainSpec::default().entry_point_address_v0_6;
+        (
+            UserOperationVariant::V0_6(op),
+            ethers::types::Address::from_slice(entry_point.as_slice()),
+        )
+    }
+
+    fn v0_7_op() -> (UserOperationVariant, ethers::types::Address) {
+        let op = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender: Address::from_str(SENDER).unwrap(),
+                nonce: U256::ZERO,
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 1_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        let entry_point = ChainSpec::default().entry_point_address_v0_7;
+        (
+            UserOperationVariant::V0_7(op),
+            ethers::types::Address::from_slice(entry_point.as_slice()),
+        )
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn options(validity_seconds: Option<u64>) -> SponsorOptions {
+        SponsorOptions {
+            return_full_operation: true,
+            validity_seconds,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_returned_timestamps_match_signed_data() {
+        let service = create_service();
+        let paymaster = service.paymaster_address().await;
+
+        for (op, entry_point) in [v0_6_op(), v0_7_op()] {
+            let is_v0_6 = matches!(op, UserOperationVariant::V0_6(_));
+            let before = now();
+            let result = service
+                .sponsor_user_operation(op, entry_point, options(Some(120)))
+                .await
+                .unwrap();
+
+            assert_eq!(result.valid_after, 0);
+            assert!(result.valid_until >= before + 120 && result.valid_until <= now() + 120);
+
+            // v0.6 paymasterAndData starts with the paymaster address
+            let paymaster_data = if is_v0_6 {
+                assert_eq!(&result.paymaster_and_data[..20], paymaster.as_slice());
+                &result.paymaster_and_data[20..]
+            } else {
+                &result.paymaster_and_data[..]
+            };
+            let decoded = SponsorshipData::decode(paymaster_data).unwrap();
+            assert_eq!(decoded.valid_until, result.valid_until);
+            assert_eq!(decoded.valid_after, result.valid_after);
+
+            // The signature covers exactly the returned timestamps
+            let sponsored = result.sponsored_user_op.unwrap();
+            let verified = verify_sponsorship(&sponsored, paymaster, now()).unwrap();
+            assert_eq!(verified.valid_until, result.valid_until);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_window_from_config() {
+        let service = create_service();
+        let (op, entry_point) = v0_7_op();
+
+        let before = now();
+        let result = service
+            .sponsor_user_operation(op, entry_point, options(None))
+            .await
+            .unwrap();
+        assert!(result.valid_until >= before + 600 && result.valid_until <= now() + 600);
+    }
+
+    #[tokio::test]
+    async fn test_window_outside_bounds_rejected() {
+        let service = create_service();
+
+        for seconds in [0, 59, 1801] {
+            let (op, entry_point) = v0_7_op();
+            let err = service
+                .sponsor_user_operation(op, entry_point, options(Some(seconds)))
+                .await
+                .unwrap_err();
+            assert!(matches!(err, PaymasterError::InvalidRequest(_)));
+            assert!(err.to_string().contains("60 to 1800"), "{}", err);
+        }
+
+        for seconds in [60, 1800] {
+            let (op, entry_point) = v0_7_op();
+            assert!(service
+                .sponsor_user_operation(op, entry_point, options(Some(seconds)))
+                .await
+                .is_ok());
+        }
+    }
+
+    fn create_default_service() -> PaymasterRelayService {
+        test_utils::service(&format!("senders = [\"{}\"]\n", SENDER))
+    }
+
+    fn v0_6_user_op() -> UserOperationVariant {
+        UserOperationVariant::V0_6(
+            v0_6::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_6::UserOperationRequiredFields {
+                    sender: Address::from_str(SENDER).unwrap(),
+                    nonce: U256::ZERO,
+                    call_gas_limit: 100_000,
+                    verification_gas_limit: 100_000,
+                    pre_verification_gas: 21_000,
+                    ..Default::default()
+                },
+            )
+            .build(),
+        )
+    }
+
+    fn v0_7_user_op() -> UserOperationVariant {
+        UserOperationVariant::V0_7(
+            v0_7::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_7::UserOperationRequiredFields {
+                    sender: Address::from_str(SENDER).unwrap(),
+                    nonce: U256::ZERO,
+                    call_data: Bytes::new(),
+                    call_gas_limit: 100_000,
+                    verification_gas_limit: 100_000,
+                    pre_verification_gas: 21_000,
+                    max_fee_per_gas: 1_000_000_000,
+                    max_priority_fee_per_gas: 1_000_000_000,
+                    signature: Bytes::new(),
+                },
+            )
+            .build(),
+        )
+    }
+
+    fn ethers_address(address: Address) -> ethers::types::Address {
+        ethers::types::Address::from_slice(address.as_slice())
+    }
+
+    #[tokio::test]
+    async fn test_v0_6_dummy_matches_sponsorship_length() {
+        let service = create_default_service();
+        let entry_point = ChainSpec::default().entry_point_address_v0_6;
+
+        let dummy = service.dummy_paymaster_data(entry_point).await.unwrap();
+        let sponsored = service
+            .sponsor_user_operation(
+                v0_6_user_op(),
+                ethers_address(entry_point),
+                SponsorOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(dummy.version, EntryPointVersion::V0_6);
+        let paymaster_and_data = dummy.paymaster_and_data.unwrap();
+        assert_eq!(paymaster_and_data.len(), sponsored.paymaster_and_data.len());
+        assert_eq!(
+            &paymaster_and_data[..20],
+            &sponsored.paymaster_and_data[..20]
+        );
+        assert_eq!(&paymaster_and_data[..20], dummy.paymaster.as_slice());
+        assert!(dummy.paymaster_data.is_none());
+        assert!(dummy.paymaster_verification_gas_limit.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_v0_7_dummy_matches_sponsorship_length() {
+        let service = create_default_service();
+        let entry_point = ChainSpec::default().entry_point_address_v0_7;
+
+        let dummy = service.dummy_paymaster_data(entry_point).await.unwrap();
+        let sponsored = service
+            .sponsor_user_operation(
+                v0_7_user_op(),
+                ethers_address(entry_point),
+                SponsorOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(dummy.version, EntryPointVersion::V0_7);
+        let paymaster_data = dummy.paymaster_data.unwrap();
+        assert_eq!(paymaster_data.len(), sponsored.paymaster_and_data.len());
+        assert_eq!(
+            dummy.paymaster_verification_gas_limit,
+            sponsored.verification_gas_limit.map(U64::from)
+        );
+        assert_eq!(
+            dummy.paymaster_post_op_gas_limit,
+            sponsored.post_op_gas_limit.map(U64::from)
+        );
+        assert!(dummy.paymaster_and_data.is_none());
+
+        // The stub decodes like a real sponsorship, without a zero signature byte
+        let decoded = SponsorshipData::decode(&paymaster_data).unwrap();
+        assert_eq!(decoded.valid_after, 0);
+        assert_eq!(decoded.signature, DUMMY_SIGNATURE);
+        // naming the key real sponsorships are signed with
+        assert_eq!(
+            decoded.signer,
+            SponsorshipData::decode(&sponsored.paymaster_and_data)
+                .unwrap()
+                .signer
+        );
+        assert!(DUMMY_SIGNATURE.iter().all(|byte| *byte != 0));
+    }
+
+    #[tokio::test]
+    async fn test_gas_limits_from_config() {
+        let gas = PaymasterGasConfig {
+            verification_gas_limit: 150_000,
+            post_op_gas_limit: 45_000,
+        };
+        let service = create_default_service().with_paymaster_gas(gas);
+        let entry_point = ChainSpec::default().entry_point_address_v0_7;
+
+        let dummy = service.dummy_paymaster_data(entry_point).await.unwrap();
+        assert_eq!(
+            dummy.paymaster_verification_gas_limit,
+            Some(U64::from(150_000))
+        );
+        assert_eq!(dummy.paymaster_post_op_gas_limit, Some(U64::from(45_000)));
+
+        // Sponsorships sign the same limits
+        let sponsored = service
+            .sponsor_user_operation(
+                v0_7_user_op(),
+                ethers_address(entry_point),
+                SponsorOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(sponsored.verification_gas_limit, Some(150_000));
+        assert_eq!(sponsored.post_op_gas_limit, Some(45_000));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_entry_point_rejected() {
+        let service = create_default_service();
+        let err = service
+            .dummy_paymaster_data(Address::repeat_byte(0x42))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PaymasterError::InvalidRequest(_)));
+    }
+}
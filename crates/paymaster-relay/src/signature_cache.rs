@@ -0,0 +1,496 @@
+// paymaster-relay/src/signature_cache.rs
+// Short-lived cache of signed sponsorships, so a wallet asking again for the same operation
+// (sponsor, re-estimate, sponsor) gets the signature and validity window it was already given.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use alloy_primitives::{Address, B256};
+use rundler_types::UserOperationVariant;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    canonical,
+    service::{PaymasterSponsorResult, SponsorOptions},
+};
+
+/// Settings of the automatic signature cache
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignatureCacheConfig {
+    /// Reuse signatures of repeated requests without an idempotency key
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Seconds a signature is reused for
+    #[serde(default = "default_ttl_seconds")]
+    pub ttl_seconds: u64,
+    /// Signatures expiring within this many seconds are never reused
+    #[serde(default = "default_expiry_margin_seconds")]
+    pub expiry_margin_seconds: u64,
+    /// Signatures remembered at once; the oldest are evicted first
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_ttl_seconds() -> u64 {
+    30
+}
+
+fn default_expiry_margin_seconds() -> u64 {
+    60
+}
+
+fn default_max_entries() -> usize {
+    10_000
+}
+
+impl Default for SignatureCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            ttl_seconds: default_ttl_seconds(),
+            expiry_margin_seconds: default_expiry_margin_seconds(),
+            max_entries: default_max_entries(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    signed: HashMap<B256, (Instant, PaymasterSponsorResult)>,
+    /// Keys in insertion order, with the time the entry was inserted
+    order: VecDeque<(B256, Instant)>,
+}
+
+/// Bounded TTL cache of sponsorships by [`cache_key`]
+///
+/// Unlike [`IdempotencyCache`](crate::IdempotencyCache) nothing is shared
+/// between concurrent requests: a request arriving while the first is
+/// still signing signs on its own.
+#[derive(Debug)]
+pub struct SignatureCache {
+    enabled: bool,
+    ttl: Duration,
+    expiry_margin_seconds: u64,
+    max_entries: usize,
+    entries: Mutex<Entries>,
+}
+
+impl Default for SignatureCache {
+    fn default() -> Self {
+        Self::new(&SignatureCacheConfig::default())
+    }
+}
+
+impl SignatureCache {
+    /// Create an empty cache
+    pub fn new(config: &SignatureCacheConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            ttl: Duration::from_secs(config.ttl_seconds),
+            expiry_margin_seconds: config.expiry_margin_seconds,
+            max_entries: config.max_entries.max(1),
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Whether signatures are reused at all
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Sponsorship cached for `key`, marked `cached`, unless it expires
+    /// within the safety margin of unix time `now`
+    pub fn get(&self, key: B256, now: u64) -> Option<PaymasterSponsorResult> {
+        if !self.enabled {
+            return None;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        self.purge_expired(&mut entries, Instant::now());
+
+        let (_, result) = entries.signed.get(&key)?;
+        if result.valid_until <= now.saturating_add(self.expiry_margin_seconds) {
+            entries.signed.remove(&key);
+            return None;
+        }
+        let mut result = result.clone();
+        result.cached = true;
+        Some(result)
+    }
+
+    /// Remember the sponsorship signed for `key`
+    pub fn insert(&self, key: B256, result: &PaymasterSponsorResult) {
+        if !self.enabled {
+            return;
+        }
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        self.purge_expired(&mut entries, now);
+
+        while entries.signed.len() >= self.max_entries {
+            let Some((oldest, inserted)) = entries.order.pop_front() else {
+                break;
+            };
+            Self::remove_if_inserted(&mut entries, &oldest, inserted);
+        }
+        entries.signed.insert(key, (now, result.clone()));
+        entries.order.push_back((key, now));
+    }
+
+    /// Forget every cached signature, such as after a key rotation
+    pub fn clear(&self) {
+        *self.entries.lock().unwrap() = Entries::default();
+    }
+
+    /// Signatures currently cached
+    pub fn len(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        self.purge_expired(&mut entries, Instant::now());
+        entries.signed.len()
+    }
+
+    /// Whether no signature is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn purge_expired(&self, entries: &mut Entries, now: Instant) {
+        while let Some((_, inserted)) = entries.order.front() {
+            if now.duration_since(*inserted) < self.ttl {
+                break;
+            }
+            let (key, inserted) = entries.order.pop_front().unwrap();
+            Self::remove_if_inserted(entries, &key, inserted);
+        }
+    }
+
+    /// Remove `key` unless it was re-inserted after `inserted`
+    fn remove_if_inserted(entries: &mut Entries, key: &B256, inserted: Instant) {
+        if entries
+            .signed
+            .get(key)
+            .is_some_and(|(at, _)| *at == inserted)
+        {
+            entries.signed.remove(key);
+        }
+    }
+}
+
+/// Key of a sponsorship request in the [`SignatureCache`]
+///
+/// The operation's [`sponsorship identity`](canonical::sponsorship_identity),
+/// so any change to its gas or fee fields is a different request, bound to
+/// the options that change what is signed or who it is accounted to.
+pub fn cache_key(
+    user_op: &UserOperationVariant,
+    entry_point: Address,
+    options: &SponsorOptions,
+) -> B256 {
+    canonical::canonical_digest(&json!({
+        "operation": canonical::sponsorship_identity(user_op, entry_point),
+        "token": options.token,
+        "validitySeconds": options.validity_seconds,
+        "requester": options.requester,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, str::FromStr};
+
+    use alloy_primitives::{Address, Bytes, U256};
+    use rundler_types::{chain::ChainSpec, v0_7, UserOperation, UserOperationVariant};
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{
+        audit::{AuditLogConfig, AuditLogger, AuditRecord},
+        service::{PaymasterRelayService, SponsorOptions},
+        test_utils,
+    };
+
+    fn sponsorship(valid_until: u64) -> PaymasterSponsorResult {
+        PaymasterSponsorResult {
+            paymaster_and_data: vec![1, 2, 3],
+            verification_gas_limit: None,
+            post_op_gas_limit: None,
+            pre_verification_gas: None,
+            verification_gas_limit_uo: None,
+            call_gas_limit: None,
+            sponsored_user_op: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            valid_until,
+            valid_after: 0,
+            token_quote: None,
+            cached: false,
+        }
+    }
+
+    fn cache(ttl_seconds: u64, max_entries: usize) -> SignatureCache {
+        SignatureCache::new(&SignatureCacheConfig {
+            ttl_seconds,
+            max_entries,
+            expiry_margin_seconds: 60,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_hit_marked_cached() {
+        let cache = cache(30, 10);
+        cache.insert(B256::repeat_byte(1), &sponsorship(1_000));
+
+        let hit = cache.get(B256::repeat_byte(1), 900).unwrap();
+        assert!(hit.cached);
+        assert_eq!(hit.paymaster_and_data, vec![1, 2, 3]);
+        assert!(cache.get(B256::repeat_byte(2), 900).is_none());
+    }
+
+    #[test]
+    fn test_near_expiry_refused() {
+        let cache = cache(30, 10);
+        cache.insert(B256::repeat_byte(1), &sponsorship(1_000));
+
+        assert!(cache.get(B256::repeat_byte(1), 940).is_none());
+        // Refused entries are dropped
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_expired_and_evicted() {
+        let expired = cache(0, 10);
+        expired.insert(B256::repeat_byte(1), &sponsorship(1_000));
+        assert!(expired.get(B256::repeat_byte(1), 0).is_none());
+
+        let full = cache(30, 2);
+        for byte in 1..=3 {
+            full.insert(B256::repeat_byte(byte), &sponsorship(1_000));
+        }
+        assert_eq!(full.len(), 2);
+        assert!(full.get(B256::repeat_byte(1), 0).is_none());
+        assert!(full.get(B256::repeat_byte(3), 0).is_some());
+    }
+
+    #[test]
+    fn test_disabled() {
+        let cache = SignatureCache::new(&SignatureCacheConfig {
+            enabled: false,
+            ..Default::default()
+        });
+        cache.insert(B256::repeat_byte(1), &sponsorship(u64::MAX));
+        assert!(cache.get(B256::repeat_byte(1), 0).is_none());
+    }
+
+    const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    fn create_service(dir: &Path, config: SignatureCacheConfig) -> PaymasterRelayService {
+        let audit_config = AuditLogConfig {
+            path: Some(dir.join("audit.jsonl")),
+            ..Default::default()
+        };
+
+        test_utils::service_in(dir, &format!("senders = [\"{}\"]\n", SENDER))
+            .with_audit_logger(AuditLogger::new(&audit_config))
+            .with_signature_cache(config)
+    }
+
+    fn audit_records(dir: &Path) -> Vec<AuditRecord> {
+        std::fs::read_to_string(dir.join("audit.jsonl"))
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn create_user_op(call_gas_limit: u128, signature: &[u8]) -> UserOperationVariant {
+        let op = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender: Address::from_str(SENDER).unwrap(),
+                nonce: U256::from(1),
+                call_data: Bytes::new(),
+                call_gas_limit,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 1_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::copy_from_slice(signature),
+            },
+        )
+        .build();
+        UserOperationVariant::V0_7(op)
+    }
+
+    fn entry_point() -> ethers::types::Address {
+        ethers::types::Address::from_slice(ChainSpec::default().entry_point_address_v0_7.as_slice())
+    }
+
+    fn full_operation() -> SponsorOptions {
+        SponsorOptions {
+            return_full_operation: true,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repeated_request_reuses_signature() {
+        let dir = tempdir().unwrap();
+        let service = create_service(dir.path(), SignatureCacheConfig::default());
+
+        let first = service
+            .sponsor_user_operation(
+                create_user_op(100_000, &[]),
+                entry_point(),
+                full_operation(),
+            )
+            .await
+            .unwrap();
+        assert!(!first.cached);
+
+        // Re-requested after estimation, now carrying the account's signature
+        let repeat = service
+            .sponsor_user_operation(
+                create_user_op(100_000, &[0xAB; 65]),
+                entry_point(),
+                full_operation(),
+            )
+            .await
+            .unwrap();
+        assert!(repeat.cached);
+        assert_eq!(repeat.paymaster_and_data, first.paymaster_and_data);
+        assert_eq!(repeat.valid_until, first.valid_until);
+        // The returned operation is the one of this request
+        let sponsored = repeat.sponsored_user_op.unwrap();
+        assert_eq!(
+            sponsored.signature().as_ref(),
+            &[0xAB; 65][..],
+            "cached sponsorship kept the earlier signature"
+        );
+        assert!(service.verify_own_sponsorship(&sponsored).await.unwrap());
+
+        let records = audit_records(dir.path());
+        assert_eq!(records.len(), 2);
+        assert!(!records[0].replayed);
+        assert!(records[1].replayed);
+    }
+
+    #[tokio::test]
+    async fn test_gas_field_change_signs_again() {
+        let dir = tempdir().unwrap();
+        let service = create_service(dir.path(), SignatureCacheConfig::default());
+
+        let first = service
+            .sponsor_user_operation(
+                create_user_op(100_000, &[]),
+                entry_point(),
+                SponsorOptions::default(),
+            )
+            .await
+            .unwrap();
+        let re_estimated = service
+            .sponsor_user_operation(
+                create_user_op(120_000, &[]),
+                entry_point(),
+                SponsorOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert!(!re_estimated.cached);
+        assert_ne!(re_estimated.paymaster_and_data, first.paymaster_and_data);
+
+        let entry_point = ChainSpec::default().entry_point_address_v0_7;
+        let options = SponsorOptions::default();
+        let key = cache_key(&create_user_op(100_000, &[]), entry_point, &options);
+        assert_eq!(
+            cache_key(&create_user_op(100_000, &[0xCD; 65]), entry_point, &options),
+            key
+        );
+        assert_ne!(
+            cache_key(&create_user_op(100_001, &[]), entry_point, &options),
+            key
+        );
+        let other_requester = SponsorOptions {
+            requester: Some("dapp-b".to_string()),
+            ..Default::default()
+        };
+        assert_ne!(
+            cache_key(&create_user_op(100_000, &[]), entry_point, &other_requester),
+            key
+        );
+    }
+
+    #[tokio::test]
+    async fn test_near_expiry_signature_not_reused() {
+        let dir = tempdir().unwrap();
+        let service = create_service(
+            dir.path(),
+            SignatureCacheConfig {
+                expiry_margin_seconds: 120,
+                ..Default::default()
+            },
+        );
+        let short_window = SponsorOptions {
+            validity_seconds: Some(60),
+            ..Default::default()
+        };
+
+        for _ in 0..2 {
+            let result = service
+                .sponsor_user_operation(
+                    create_user_op(100_000, &[]),
+                    entry_point(),
+                    short_window.clone(),
+                )
+                .await
+                .unwrap();
+            assert!(!result.cached);
+        }
+        assert!(audit_records(dir.path()).iter().all(|r| !r.replayed));
+    }
+
+    #[tokio::test]
+    async fn test_disabled_or_idempotent_requests_not_marked_cached() {
+        let dir = tempdir().unwrap();
+        let service = create_service(
+            dir.path(),
+            SignatureCacheConfig {
+                enabled: false,
+                ..Default::default()
+            },
+        );
+        for _ in 0..2 {
+            let result = service
+                .sponsor_user_operation(
+                    create_user_op(100_000, &[]),
+                    entry_point(),
+                    SponsorOptions::default(),
+                )
+                .await
+                .unwrap();
+            assert!(!result.cached);
+        }
+
+        // Idempotency keys replay through their own cache
+        let dir = tempdir().unwrap();
+        let service = create_service(dir.path(), SignatureCacheConfig::default());
+        let keyed = SponsorOptions {
+            idempotency_key: Some("order-1".to_string()),
+            ..Default::default()
+        };
+        for _ in 0..2 {
+            let result = service
+                .sponsor_user_operation(create_user_op(100_000, &[]), entry_point(), keyed.clone())
+                .await
+                .unwrap();
+            assert!(!result.cached);
+        }
+    }
+}
@@ -1,7 +1,17 @@
 // paymaster-relay/src/signer.rs
 // This file will implement the SignerManager for handling private keys.
 
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use ethers::{
     signers::{LocalWallet, Signer},
@@ -9,9 +19,17 @@ use ethers::{
 };
 use eyre::Result;
 use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
 use tracing::{debug, info, warn};
 
-use crate::kms::{KmsConfig, KmsError, KmsSigningRequest, MockKmsProvider, SigningContext};
+use crate::{
+    kms::{
+        address_from_public_key_der, decode_der_signature, secp256k1_order, AsymmetricKmsClient,
+        AwsKmsClient, KmsConfig, KmsError, KmsSigningRequest, MockKmsProvider, SigningContext,
+    },
+    secrets,
+};
 
 /// Signer backend type
 #[derive(Debug, Clone)]
@@ -20,13 +38,474 @@ pub enum SignerBackend {
     DirectKey(LocalWallet),
     /// KMS-based signing (enterprise mode)
     Kms(MockKmsProvider),
+    /// AWS KMS asymmetric secp256k1 key
+    AwsKms(AwsKmsSignerBackend),
+}
+
+/// Signer backend selected by configuration (`signer.backend`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignerBackendKind {
+    /// Raw private key from PAYMASTER_PRIVATE_KEY
+    #[default]
+    PrivateKey,
+    /// AWS KMS key referenced by ARN
+    AwsKms,
+}
+
+impl FromStr for SignerBackendKind {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "private-key" => Ok(SignerBackendKind::PrivateKey),
+            "aws-kms" => Ok(SignerBackendKind::AwsKms),
+            other => Err(eyre::eyre!(
+                "Unknown signer backend '{}', expected 'private-key' or 'aws-kms'",
+                other
+            )),
+        }
+    }
+}
+
+/// Retry policy for throttled or unavailable KMS requests
+#[derive(Debug, Clone)]
+pub struct KmsRetryConfig {
+    /// Total attempts per request, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after each attempt
+    pub initial_backoff: Duration,
+    /// Upper bound for the retry delay
+    pub max_backoff: Duration,
+}
+
+impl Default for KmsRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Signer backend using an AWS KMS asymmetric secp256k1 key
+///
+/// KMS returns DER encoded signatures without a recovery id, so signatures
+/// are normalized to low-s and the recovery id is found by recovering
+/// against the address derived from the key at startup.
+#[derive(Clone)]
+pub struct AwsKmsSignerBackend {
+    client: Arc<dyn AsymmetricKmsClient>,
+    key_id: String,
+    address: Address,
+    retry: KmsRetryConfig,
+}
+
+impl fmt::Debug for AwsKmsSignerBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AwsKmsSignerBackend")
+            .field("key_id", &self.key_id)
+            .field("address", &self.address)
+            .field("retry", &self.retry)
+            .finish()
+    }
+}
+
+impl AwsKmsSignerBackend {
+    /// Connect to AWS KMS using the key ARN and region from `KmsConfig`
+    pub async fn new(kms_config: &KmsConfig) -> Result<Self, KmsError> {
+        let key_arn =
+            kms_config
+                .aws_key_arn
+                .clone()
+                .ok_or_else(|| KmsError::InvalidConfiguration {
+                    reason: "aws_key_arn is required for the aws-kms signer backend".to_string(),
+                })?;
+
+        let region =
+            Some(kms_config.credentials.region_or_tenant.clone()).filter(|r| !r.is_empty());
+        let client = AwsKmsClient::new(region).await;
+
+        Self::with_client(Arc::new(client), key_arn, KmsRetryConfig::default()).await
+    }
+
+    /// Create the backend from any asymmetric KMS client, deriving the signer
+    /// address from the key's public key
+    pub async fn with_client(
+        client: Arc<dyn AsymmetricKmsClient>,
+        key_id: String,
+        retry: KmsRetryConfig,
+    ) -> Result<Self, KmsError> {
+        let mut backend = Self {
+            client,
+            key_id,
+            address: Address::zero(),
+            retry,
+        };
+
+        let public_key = backend
+            .with_retry("GetPublicKey", || {
+                backend.client.get_public_key(&backend.key_id)
+            })
+            .await?;
+        backend.address = address_from_public_key_der(&public_key)?;
+
+        info!(
+            "🔑 AWS KMS signer ready: key_id={}, address={:?}",
+            backend.key_id, backend.address
+        );
+
+        Ok(backend)
+    }
+
+    /// Address derived from the KMS public key
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// KMS key identifier
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Sign a 32 byte hash, returning an RSV signature that recovers to `address()`
+    pub async fn sign_hash(&self, hash: H256) -> Result<Signature, KmsError> {
+        let der = self
+            .with_retry("Sign", || self.client.sign_digest(&self.key_id, hash))
+            .await?;
+
+        der_signature_to_rsv(&der, hash, self.address)
+    }
+
+    /// Backend for another key reached through the same KMS client
+    pub async fn with_key(&self, key_id: String) -> Result<Self, KmsError> {
+        Self::with_client(self.client.clone(), key_id, self.retry.clone()).await
+    }
+
+    /// Check the key is reachable and still maps to the startup address
+    pub async fn test_connectivity(&self) -> Result<(), KmsError> {
+        let public_key = self
+            .with_retry("GetPublicKey", || self.client.get_public_key(&self.key_id))
+            .await?;
+
+        let address = address_from_public_key_der(&public_key)?;
+        if address != self.address {
+            return Err(KmsError::InvalidConfiguration {
+                reason: format!(
+                    "KMS key {} now maps to {:?}, expected {:?}",
+                    self.key_id, address, self.address
+                ),
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn with_retry<T, F, Fut>(&self, operation: &str, mut request: F) -> Result<T, KmsError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, KmsError>>,
+    {
+        let mut backoff = self.retry.initial_backoff;
+        let mut attempt = 1;
+
+        loop {
+            match request().await {
+                Err(e) if e.is_retryable() && attempt < self.retry.max_attempts => {
+                    warn!(
+                        "⏳ KMS {} failed (attempt {}/{}), retrying in {:?}: {}",
+                        operation, attempt, self.retry.max_attempts, backoff, e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(self.retry.max_backoff);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Convert a DER encoded ECDSA signature into an RSV signature for `signer`
+///
+/// High-s values are normalized to low-s as required by EIP-2, and `v` is
+/// chosen as the recovery id (27 or 28) that recovers `signer`.
+pub fn der_signature_to_rsv(
+    der: &[u8],
+    hash: H256,
+    signer: Address,
+) -> Result<Signature, KmsError> {
+    let (r, mut s) = decode_der_signature(der)?;
+
+    let order = secp256k1_order();
+    if s > order >> 1 {
+        s = order - s;
+    }
+
+    for v in [27, 28] {
+        let signature = Signature { r, s, v };
+        if signature.recover(hash).ok() == Some(signer) {
+            return Ok(signature);
+        }
+    }
+
+    Err(KmsError::SignatureFailed {
+        reason: format!("KMS signature does not recover to {:?}", signer),
+    })
+}
+
+/// Where pm_rotatePaymasterKey loads the new signing key from
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum KeySource {
+    /// Private key held in the named environment variable
+    Env(String),
+    /// KMS key id or ARN, reached through the current AWS KMS client
+    Kms(String),
+}
+
+/// Previous signing key, still accepted when verifying sponsorships
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetiringSigner {
+    /// Address of the previous key
+    pub address: Address,
+    /// Unix time after which its signatures are no longer accepted
+    pub retire_at: u64,
+}
+
+/// Signing keys after a rotation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyRotation {
+    /// Paymaster address, unchanged by rotation
+    pub paymaster: Address,
+    /// Key that signs from now on
+    pub active_signer: Address,
+    /// Key being retired
+    pub retiring_signer: RetiringSigner,
+}
+
+impl SignerBackend {
+    /// Name reported as the signer backend type
+    fn kind(&self) -> &'static str {
+        match self {
+            SignerBackend::DirectKey(_) => "direct_key",
+            SignerBackend::Kms(_) => "kms",
+            SignerBackend::AwsKms(_) => "aws_kms",
+        }
+    }
+
+    async fn sign_hash(
+        &mut self,
+        hash: [u8; 32],
+        context: Option<SigningContext>,
+    ) -> Result<Signature> {
+        match self {
+            SignerBackend::DirectKey(signer) => {
+                debug!("🔐 Signing with direct key: address={:?}", signer.address());
+                let signature = signer.sign_hash(hash.into())?;
+                Ok(signature)
+            }
+            SignerBackend::Kms(kms_provider) => {
+                debug!("🔐 Signing with KMS provider");
+
+                let signing_context = context.unwrap_or_else(|| SigningContext {
+                    operation_type: "paymaster_operation".to_string(),
+                    user_operation_hash: None,
+                    sender_address: None,
+                    entry_point: None,
+                    gas_estimates: None,
+                    metadata: HashMap::new(),
+                });
+
+                // Use the primary key ID from KMS provider's keys
+                let primary_key_id = kms_provider
+                    .list_keys()
+                    .into_iter()
+                    .find(|key| key.permissions.contains(&"sign".to_string()))
+                    .ok_or_else(|| eyre::eyre!("No signing key found in KMS provider"))?
+                    .key_id
+                    .clone();
+
+                let kms_request = KmsSigningRequest {
+                    key_id: primary_key_id,
+                    message_hash: H256::from_slice(&hash),
+                    context: signing_context,
+                };
+
+                let kms_response = kms_provider
+                    .sign(kms_request)
+                    .await
+                    .map_err(|e| eyre::eyre!("KMS signing failed: {}", e))?;
+
+                debug!(
+                    "✅ KMS signing completed: key_id={}, audit_id={}",
+                    kms_response.key_id, kms_response.audit_info.request_id
+                );
+
+                Ok(kms_response.signature)
+            }
+            SignerBackend::AwsKms(backend) => {
+                debug!("🔐 Signing with AWS KMS: key_id={}", backend.key_id());
+                let signature = backend
+                    .sign_hash(H256::from(hash))
+                    .await
+                    .map_err(|e| eyre::eyre!("AWS KMS signing failed: {}", e))?;
+                Ok(signature)
+            }
+        }
+    }
+
+    async fn test_connectivity(&self) -> Result<()> {
+        match self {
+            SignerBackend::DirectKey(_) => {
+                debug!("📡 Connectivity test: Direct key backend (always available)");
+                Ok(())
+            }
+            SignerBackend::Kms(kms_provider) => {
+                debug!("📡 Testing KMS connectivity...");
+                kms_provider
+                    .test_connectivity()
+                    .await
+                    .map_err(|e| eyre::eyre!("KMS connectivity test failed: {}", e))?;
+                info!("✅ KMS connectivity test passed");
+                Ok(())
+            }
+            SignerBackend::AwsKms(backend) => {
+                debug!("📡 Testing AWS KMS connectivity...");
+                backend
+                    .test_connectivity()
+                    .await
+                    .map_err(|e| eyre::eyre!("AWS KMS connectivity test failed: {}", e))?;
+                info!("✅ AWS KMS connectivity test passed");
+                Ok(())
+            }
+        }
+    }
 }
 
+/// Signing key of the paymaster signer pool
+#[derive(Clone, Debug)]
+struct PoolKey {
+    address: Address,
+    backend_type: &'static str,
+    backend: Arc<Mutex<SignerBackend>>,
+    stats: Arc<PoolKeyStats>,
+    /// No new sponsorships are assigned while set
+    draining: bool,
+}
+
+#[derive(Debug, Default)]
+struct PoolKeyStats {
+    in_flight: AtomicUsize,
+    signatures: AtomicU64,
+}
+
+impl PoolKey {
+    fn new(backend: SignerBackend, address: Address) -> Self {
+        Self {
+            address,
+            backend_type: backend.kind(),
+            backend: Arc::new(Mutex::new(backend)),
+            stats: Arc::new(PoolKeyStats::default()),
+            draining: false,
+        }
+    }
+
+    fn lease(&self) -> SignerLease {
+        self.stats.in_flight.fetch_add(1, Ordering::Relaxed);
+        SignerLease {
+            address: self.address,
+            backend_type: self.backend_type,
+            backend: self.backend.clone(),
+            stats: self.stats.clone(),
+        }
+    }
+
+    fn status(&self) -> SignerKeyStatus {
+        SignerKeyStatus {
+            address: self.address,
+            draining: self.draining,
+            in_flight: self.stats.in_flight.load(Ordering::Relaxed),
+            signatures: self.stats.signatures.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Pool key assigned to one signing request by [`SignerManager::next_signer`]
+///
+/// Signing through a lease only waits for other requests using the same key.
+/// The lease keeps its key usable after the key is drained or rotated out,
+/// so a sponsorship finishes with the key it started with.
+#[derive(Debug)]
+pub struct SignerLease {
+    address: Address,
+    backend_type: &'static str,
+    backend: Arc<Mutex<SignerBackend>>,
+    stats: Arc<PoolKeyStats>,
+}
+
+impl SignerLease {
+    /// Address of the leased key, which the signature recovers to
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Signer backend type of the leased key
+    pub fn backend_type(&self) -> &str {
+        self.backend_type
+    }
+
+    /// Sign a hash with the leased key
+    pub async fn sign_hash_with_context(
+        &self,
+        hash: [u8; 32],
+        context: Option<SigningContext>,
+    ) -> Result<Signature> {
+        let signature = self.backend.lock().await.sign_hash(hash, context).await?;
+        self.stats.signatures.fetch_add(1, Ordering::Relaxed);
+        Ok(signature)
+    }
+}
+
+impl Drop for SignerLease {
+    fn drop(&mut self) {
+        self.stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Signing key of the pool as reported by pm_getSignerStatus
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignerKeyStatus {
+    /// Key address, registered as a signer of the paymaster contract
+    pub address: Address,
+    /// Whether the key is drained and receives no new sponsorships
+    pub draining: bool,
+    /// Signing requests holding the key right now
+    pub in_flight: usize,
+    /// Signatures made with the key since startup
+    pub signatures: u64,
+}
+
+/// Paymaster signing keys
+///
+/// Sponsorships are signed round-robin by the keys of the pool, all of which
+/// must be registered as signers of the paymaster contract. The first key is
+/// the active key: it signs paymaster transactions and is the one replaced by
+/// [`SignerManager::rotate_signer`].
 #[derive(Clone, Debug)]
 pub struct SignerManager {
-    backend: SignerBackend,
+    /// Signing keys, the active key first
+    keys: Vec<PoolKey>,
+    /// Pool position the next sponsorship starts searching from
+    next_key: usize,
     /// Primary paymaster address
     primary_address: Address,
+    /// Previous key still accepted for verification
+    retiring: Option<RetiringSigner>,
     /// Configuration metadata
     config_metadata: HashMap<String, String>,
 }
@@ -41,11 +520,11 @@ impl SignerManager {
         config_metadata.insert("backend_type".to_string(), "direct_key".to_string());
         config_metadata.insert("created_at".to_string(), chrono::Utc::now().to_rfc3339());
 
-        Ok(Self {
-            backend: SignerBackend::DirectKey(signer),
+        Ok(Self::with_backend(
+            SignerBackend::DirectKey(signer),
             primary_address,
             config_metadata,
-        })
+        ))
     }
 
     /// Create SignerManager with KMS backend (enterprise mode)
@@ -85,11 +564,94 @@ impl SignerManager {
             kms_config.backup_key_ids.len()
         );
 
-        Ok(Self {
-            backend: SignerBackend::Kms(kms_provider),
+        Ok(Self::with_backend(
+            SignerBackend::Kms(kms_provider),
             primary_address,
             config_metadata,
-        })
+        ))
+    }
+
+    /// Create SignerManager backed by an AWS KMS secp256k1 key
+    pub async fn new_with_aws_kms(kms_config: KmsConfig) -> Result<Self, KmsError> {
+        debug!("🔑 Initializing SignerManager with AWS KMS backend");
+
+        let backend = AwsKmsSignerBackend::new(&kms_config).await?;
+        let mut manager = Self::from_aws_kms_backend(backend);
+        manager.config_metadata.insert(
+            "aws_region".to_string(),
+            kms_config.credentials.region_or_tenant.clone(),
+        );
+
+        Ok(manager)
+    }
+
+    /// Create SignerManager from an initialized AWS KMS backend
+    pub fn from_aws_kms_backend(backend: AwsKmsSignerBackend) -> Self {
+        let primary_address = backend.address();
+
+        let mut config_metadata = HashMap::new();
+        config_metadata.insert("backend_type".to_string(), "aws_kms".to_string());
+        config_metadata.insert("primary_key_id".to_string(), backend.key_id().to_string());
+        config_metadata.insert("created_at".to_string(), chrono::Utc::now().to_rfc3339());
+
+        info!(
+            "✅ SignerManager initialized with AWS KMS: primary_address={:?}",
+            primary_address
+        );
+
+        Self::with_backend(
+            SignerBackend::AwsKms(backend),
+            primary_address,
+            config_metadata,
+        )
+    }
+
+    fn with_backend(
+        backend: SignerBackend,
+        primary_address: Address,
+        config_metadata: HashMap<String, String>,
+    ) -> Self {
+        Self {
+            keys: vec![PoolKey::new(backend, primary_address)],
+            next_key: 0,
+            primary_address,
+            retiring: None,
+            config_metadata,
+        }
+    }
+
+    /// Add the keys from `sources` to the signing pool
+    ///
+    /// Keys that fail to load or are already in the pool are skipped with a
+    /// warning; an error is returned only when none of `sources` loads.
+    pub async fn with_pool_keys(mut self, sources: &[KeySource]) -> Result<Self> {
+        let mut loaded = 0;
+        for source in sources {
+            match self.load_key(source).await {
+                Ok((_, address)) if self.key_index(address).is_some() => {
+                    warn!(
+                        "⚠️ Signer pool key {:?} from {:?} is already in the pool",
+                        address, source
+                    );
+                }
+                Ok((backend, address)) => {
+                    info!("🔑 Added signer pool key {:?} from {:?}", address, source);
+                    self.keys.push(PoolKey::new(backend, address));
+                    loaded += 1;
+                }
+                Err(e) => warn!("⚠️ Failed to load signer pool key {:?}: {}", source, e),
+            }
+        }
+        if loaded == 0 && !sources.is_empty() {
+            return Err(eyre::eyre!(
+                "None of the {} configured signer pool keys could be loaded",
+                sources.len()
+            ));
+        }
+
+        self.config_metadata
+            .insert("pool_size".to_string(), self.keys.len().to_string());
+        Ok(self)
     }
 
     /// Sign a hash using the configured backend
@@ -97,58 +659,67 @@ impl SignerManager {
         self.sign_hash_with_context(hash, None).await
     }
 
-    /// Sign a hash with additional context for audit logging
+    /// Sign a hash with the active key, for audit logging with `context`
     pub async fn sign_hash_with_context(
         &mut self,
         hash: [u8; 32],
         context: Option<SigningContext>,
     ) -> Result<Signature> {
-        match &mut self.backend {
-            SignerBackend::DirectKey(signer) => {
-                debug!("🔐 Signing with direct key: address={:?}", signer.address());
-                let signature = signer.sign_hash(hash.into())?;
-                Ok(signature)
-            }
-            SignerBackend::Kms(kms_provider) => {
-                debug!("🔐 Signing with KMS provider");
-
-                let signing_context = context.unwrap_or_else(|| SigningContext {
-                    operation_type: "paymaster_operation".to_string(),
-                    user_operation_hash: None,
-                    sender_address: None,
-                    entry_point: None,
-                    gas_estimates: None,
-                    metadata: HashMap::new(),
-                });
+        self.keys[0]
+            .lease()
+            .sign_hash_with_context(hash, context)
+            .await
+    }
 
-                // Use the primary key ID from KMS provider's keys
-                let primary_key_id = kms_provider
-                    .list_keys()
-                    .into_iter()
-                    .find(|key| key.permissions.contains(&"sign".to_string()))
-                    .ok_or_else(|| eyre::eyre!("No signing key found in KMS provider"))?
-                    .key_id
-                    .clone();
+    /// Lease the next pool key that is not draining, round-robin
+    pub fn next_signer(&mut self) -> Result<SignerLease> {
+        let len = self.keys.len();
+        let index = (0..len)
+            .map(|offset| (self.next_key + offset) % len)
+            .find(|&index| !self.keys[index].draining)
+            .ok_or_else(|| eyre::eyre!("All paymaster signing keys are draining"))?;
+        self.next_key = (index + 1) % len;
+        Ok(self.keys[index].lease())
+    }
 
-                let kms_request = KmsSigningRequest {
-                    key_id: primary_key_id,
-                    message_hash: H256::from_slice(&hash),
-                    context: signing_context,
-                };
+    /// Stop or resume assigning new sponsorships to the pool key `address`
+    ///
+    /// Requests already holding the key finish with it. The last key taking
+    /// work cannot be drained.
+    pub fn set_draining(&mut self, address: Address, draining: bool) -> Result<()> {
+        let index = self
+            .key_index(address)
+            .ok_or_else(|| eyre::eyre!("{:?} is not a key of the signer pool", address))?;
+        let serving = self.keys.iter().filter(|key| !key.draining).count();
+        if draining && !self.keys[index].draining && serving == 1 {
+            return Err(eyre::eyre!(
+                "{:?} is the last signing key taking work and cannot be drained",
+                address
+            ));
+        }
 
-                let kms_response = kms_provider
-                    .sign(kms_request)
-                    .await
-                    .map_err(|e| eyre::eyre!("KMS signing failed: {}", e))?;
+        if self.keys[index].draining != draining {
+            info!(
+                "{} paymaster signer {:?}",
+                if draining {
+                    "🚰 Draining"
+                } else {
+                    "▶️ Resuming"
+                },
+                address
+            );
+        }
+        self.keys[index].draining = draining;
+        Ok(())
+    }
 
-                debug!(
-                    "✅ KMS signing completed: key_id={}, audit_id={}",
-                    kms_response.key_id, kms_response.audit_info.request_id
-                );
+    /// Addresses, drain state and counters of the pool keys, the active key first
+    pub fn signer_keys(&self) -> Vec<SignerKeyStatus> {
+        self.keys.iter().map(PoolKey::status).collect()
+    }
 
-                Ok(kms_response.signature)
-            }
-        }
+    fn key_index(&self, address: Address) -> Option<usize> {
+        self.keys.iter().position(|key| key.address == address)
     }
 
     /// Get the primary paymaster address
@@ -156,12 +727,106 @@ impl SignerManager {
         self.primary_address
     }
 
-    /// Get signer backend type
-    pub fn backend_type(&self) -> &str {
-        match self.backend {
-            SignerBackend::DirectKey(_) => "direct_key",
-            SignerBackend::Kms(_) => "kms",
+    /// Address of the active key
+    pub fn signer_address(&self) -> Address {
+        self.keys[0].address
+    }
+
+    /// Previous key, while its grace period at unix time `now` lasts
+    pub fn retiring_signer(&self, now: u64) -> Option<RetiringSigner> {
+        self.retiring.filter(|retiring| now < retiring.retire_at)
+    }
+
+    /// Keys whose sponsorship signatures are accepted at unix time `now`
+    ///
+    /// Drained pool keys are included, they are still registered on chain.
+    pub fn verifying_addresses(&self, now: u64) -> Vec<Address> {
+        let mut addresses: Vec<Address> = self.keys.iter().map(|key| key.address).collect();
+        addresses.extend(self.retiring_signer(now).map(|retiring| retiring.address));
+        addresses
+    }
+
+    /// Switch the active key to the key from `source`
+    ///
+    /// The new key is loaded before anything changes, so a failed load leaves
+    /// the current key signing. The previous key is kept for verification for
+    /// `grace_period`. Requests that leased the previous key finish with it.
+    pub async fn rotate_signer(
+        &mut self,
+        source: &KeySource,
+        grace_period: Duration,
+    ) -> Result<KeyRotation> {
+        let (backend, new_address) = self.load_key(source).await?;
+        if new_address == self.signer_address() {
+            return Err(eyre::eyre!(
+                "{:?} is already the active signer",
+                new_address
+            ));
+        }
+        if self.key_index(new_address).is_some() {
+            return Err(eyre::eyre!(
+                "{:?} is already a key of the signer pool",
+                new_address
+            ));
         }
+
+        let retiring_signer = RetiringSigner {
+            address: self.signer_address(),
+            retire_at: unix_now() + grace_period.as_secs(),
+        };
+        info!(
+            "🔄 Rotating paymaster signer {:?} -> {:?}, old key accepted until {}",
+            retiring_signer.address, new_address, retiring_signer.retire_at
+        );
+
+        let mut key = PoolKey::new(backend, new_address);
+        key.draining = self.keys[0].draining;
+        self.keys[0] = key;
+        self.retiring = Some(retiring_signer);
+        self.config_metadata
+            .insert("backend_type".to_string(), self.backend_type().to_string());
+        self.config_metadata
+            .insert("rotated_at".to_string(), chrono::Utc::now().to_rfc3339());
+
+        Ok(KeyRotation {
+            paymaster: self.primary_address,
+            active_signer: new_address,
+            retiring_signer,
+        })
+    }
+
+    /// Load the signing key referenced by `source`
+    async fn load_key(&self, source: &KeySource) -> Result<(SignerBackend, Address)> {
+        match source {
+            KeySource::Env(name) => {
+                let private_key = secrets::var(name).ok_or_else(|| {
+                    eyre::eyre!("Environment variable {} with the new key is not set", name)
+                })?;
+                let wallet = LocalWallet::from_str(private_key.expose_secret().trim())
+                    .map_err(|e| eyre::eyre!("Invalid private key in {}: {}", name, e))?;
+                let address = wallet.address();
+                Ok((SignerBackend::DirectKey(wallet), address))
+            }
+            KeySource::Kms(key_id) => {
+                let SignerBackend::AwsKms(current) = self.keys[0].backend.lock().await.clone()
+                else {
+                    return Err(eyre::eyre!(
+                        "KMS key references require the aws-kms signer backend"
+                    ));
+                };
+                let backend = current
+                    .with_key(key_id.clone())
+                    .await
+                    .map_err(|e| eyre::eyre!("Failed to load KMS key {}: {}", key_id, e))?;
+                let address = backend.address();
+                Ok((SignerBackend::AwsKms(backend), address))
+            }
+        }
+    }
+
+    /// Get signer backend type of the active key
+    pub fn backend_type(&self) -> &str {
+        self.keys[0].backend_type
     }
 
     /// Get configuration metadata
@@ -169,36 +834,25 @@ impl SignerManager {
         &self.config_metadata
     }
 
-    /// Test KMS connectivity (only for KMS backend)
+    /// Test KMS connectivity of every pool key (always passes for direct keys)
     pub async fn test_kms_connectivity(&self) -> Result<()> {
-        match &self.backend {
-            SignerBackend::DirectKey(_) => {
-                debug!("📡 Connectivity test: Direct key backend (always available)");
-                Ok(())
-            }
-            SignerBackend::Kms(kms_provider) => {
-                debug!("📡 Testing KMS connectivity...");
-                kms_provider
-                    .test_connectivity()
-                    .await
-                    .map_err(|e| eyre::eyre!("KMS connectivity test failed: {}", e))?;
-                info!("✅ KMS connectivity test passed");
-                Ok(())
-            }
+        for key in &self.keys {
+            key.backend.lock().await.test_connectivity().await?;
         }
+        Ok(())
     }
 
-    /// Get KMS audit log (only for KMS backend)
-    pub fn get_kms_audit_log(&self) -> Option<Vec<crate::kms::SigningAuditInfo>> {
-        match &self.backend {
-            SignerBackend::DirectKey(_) => None,
+    /// Get KMS audit log of the active key (only for KMS backend)
+    pub async fn get_kms_audit_log(&self) -> Option<Vec<crate::kms::SigningAuditInfo>> {
+        match &*self.keys[0].backend.lock().await {
+            SignerBackend::DirectKey(_) | SignerBackend::AwsKms(_) => None,
             SignerBackend::Kms(kms_provider) => Some(kms_provider.get_audit_log().to_vec()),
         }
     }
 
     /// Rotate KMS key (only for KMS backend)
     pub async fn rotate_kms_key(&mut self, key_id: &str) -> Result<()> {
-        match &mut self.backend {
+        match &mut *self.keys[0].backend.lock().await {
             SignerBackend::DirectKey(_) => {
                 warn!("🔄 Key rotation requested for direct key backend (not supported)");
                 Err(eyre::eyre!(
@@ -214,18 +868,55 @@ impl SignerManager {
                 info!("✅ KMS key rotation completed: key_id={}", key_id);
                 Ok(())
             }
+            SignerBackend::AwsKms(_) => {
+                warn!("🔄 Key rotation requested for AWS KMS backend (managed by AWS)");
+                Err(eyre::eyre!(
+                    "AWS KMS keys are rotated in AWS; configure a new aws_key_arn instead"
+                ))
+            }
         }
     }
 }
 
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
+    use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
 
+    use alloy_primitives::{Address, Bytes, U256};
     use ethers::types::H256;
+    use rundler_types::{chain::ChainSpec, v0_6, v0_7, UserOperation, UserOperationVariant};
+    use secrecy::SecretString;
+    use tempfile::tempdir;
 
     use super::*;
-    use crate::kms::{GasEstimates, KmsConfig};
+    use crate::{
+        kms::{encode_der_signature, GasEstimates, KmsConfig},
+        service::{PaymasterRelayService, SponsorOptions},
+        sponsorship::{self, verify_sponsorship_by, SponsorshipData},
+        test_utils, PaymasterError,
+    };
+
+    const PRIMARY_KEY_ID: &str = "paymaster-primary-key";
+
+    fn fast_retry() -> KmsRetryConfig {
+        KmsRetryConfig {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+        }
+    }
+
+    async fn mock_aws_backend(kms: MockKmsProvider) -> Result<AwsKmsSignerBackend, KmsError> {
+        AwsKmsSignerBackend::with_client(Arc::new(kms), PRIMARY_KEY_ID.to_string(), fast_retry())
+            .await
+    }
 
     #[tokio::test]
     async fn test_signer_manager_direct_key() {
@@ -310,7 +1001,7 @@ mod tests {
         assert!(signature.s != ethers::types::U256::zero());
 
         // 7. Check audit log
-        let audit_log = signer_manager.get_kms_audit_log().unwrap();
+        let audit_log = signer_manager.get_kms_audit_log().await.unwrap();
         assert!(
             !audit_log.is_empty(),
             "Audit log should contain signing entry"
@@ -344,4 +1035,647 @@ mod tests {
             Some(&"2".to_string())
         );
     }
+
+    #[tokio::test]
+    async fn test_aws_kms_backend_der_to_rsv() {
+        let kms = MockKmsProvider::new(KmsConfig::default()).unwrap();
+        let expected_address = kms.get_key_info(PRIMARY_KEY_ID).unwrap().address;
+        let backend = mock_aws_backend(kms).await.unwrap();
+        assert_eq!(backend.address(), expected_address);
+
+        let hash = H256::random();
+        let signature = backend.sign_hash(hash).await.unwrap();
+        assert!(signature.v == 27 || signature.v == 28);
+        signature
+            .verify(hash, expected_address)
+            .expect("Signature verification failed");
+    }
+
+    #[tokio::test]
+    async fn test_aws_kms_backend_normalizes_high_s() {
+        let kms = MockKmsProvider::new(KmsConfig::default())
+            .unwrap()
+            .with_high_s_signatures();
+        let wallet = LocalWallet::from_str(
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        )
+        .unwrap();
+        let backend = mock_aws_backend(kms).await.unwrap();
+
+        let hash = H256::random();
+        let signature = backend.sign_hash(hash).await.unwrap();
+
+        // Deterministic signing means the normalized signature matches a local signer
+        assert!(signature.s <= secp256k1_order() >> 1);
+        assert_eq!(signature, wallet.sign_hash(hash).unwrap());
+    }
+
+    #[test]
+    fn test_der_signature_to_rsv_rejects_wrong_signer() {
+        let wallet = LocalWallet::from_str(
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        )
+        .unwrap();
+        let hash = H256::random();
+        let signature = wallet.sign_hash(hash).unwrap();
+        let der = encode_der_signature(signature.r, signature.s);
+
+        assert_eq!(
+            der_signature_to_rsv(&der, hash, wallet.address()).unwrap(),
+            signature
+        );
+        assert!(der_signature_to_rsv(&der, hash, Address::random()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_aws_kms_backend_retries_throttling() {
+        // Two throttled requests fit within three attempts
+        let kms = MockKmsProvider::new(KmsConfig::default())
+            .unwrap()
+            .with_throttled_requests(2);
+        assert!(mock_aws_backend(kms).await.is_ok());
+
+        let kms = MockKmsProvider::new(KmsConfig::default())
+            .unwrap()
+            .with_throttled_requests(3);
+        assert!(matches!(
+            mock_aws_backend(kms).await,
+            Err(KmsError::Throttled { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_signer_manager_aws_kms() {
+        let kms = MockKmsProvider::new(KmsConfig::default()).unwrap();
+        let backend = mock_aws_backend(kms).await.unwrap();
+        let mut signer_manager = SignerManager::from_aws_kms_backend(backend);
+
+        assert_eq!(signer_manager.backend_type(), "aws_kms");
+        assert_eq!(
+            signer_manager.address(),
+            Address::from_str("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266").unwrap()
+        );
+        assert!(signer_manager.get_kms_audit_log().await.is_none());
+
+        let hash = H256::random().to_fixed_bytes();
+        let signature = signer_manager.sign_hash(hash).await.unwrap();
+        signature.verify(hash, signer_manager.address()).unwrap();
+
+        signer_manager.test_kms_connectivity().await.unwrap();
+        assert!(signer_manager.rotate_kms_key(PRIMARY_KEY_ID).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rotate_signer_to_kms_key() {
+        let kms = MockKmsProvider::new(KmsConfig::default()).unwrap();
+        let backup_address = kms.get_key_info("paymaster-backup-key-1").unwrap().address;
+        let backend = mock_aws_backend(kms).await.unwrap();
+        let mut signer_manager = SignerManager::from_aws_kms_backend(backend);
+        let paymaster = signer_manager.address();
+
+        let source = KeySource::Kms("paymaster-backup-key-1".to_string());
+        let rotation = signer_manager
+            .rotate_signer(&source, Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(rotation.active_signer, backup_address);
+        assert_eq!(rotation.retiring_signer.address, paymaster);
+        assert_eq!(signer_manager.address(), paymaster);
+        assert_eq!(signer_manager.signer_address(), backup_address);
+
+        let hash = H256::random().to_fixed_bytes();
+        let signature = signer_manager.sign_hash(hash).await.unwrap();
+        signature.verify(hash, backup_address).unwrap();
+
+        let retire_at = rotation.retiring_signer.retire_at;
+        assert_eq!(
+            signer_manager.verifying_addresses(retire_at - 1),
+            vec![backup_address, paymaster]
+        );
+        assert_eq!(
+            signer_manager.verifying_addresses(retire_at),
+            vec![backup_address]
+        );
+
+        let unknown = KeySource::Kms("missing-key".to_string());
+        assert!(signer_manager
+            .rotate_signer(&unknown, Duration::ZERO)
+            .await
+            .is_err());
+        assert_eq!(signer_manager.signer_address(), backup_address);
+    }
+
+    #[tokio::test]
+    async fn test_new_with_aws_kms_requires_key_arn() {
+        let result = SignerManager::new_with_aws_kms(KmsConfig::default()).await;
+        assert!(matches!(result, Err(KmsError::InvalidConfiguration { .. })));
+    }
+
+    #[test]
+    fn test_signer_backend_kind_from_str() {
+        assert_eq!(
+            "private-key".parse::<SignerBackendKind>().unwrap(),
+            SignerBackendKind::PrivateKey
+        );
+        assert_eq!(
+            "aws-kms".parse::<SignerBackendKind>().unwrap(),
+            SignerBackendKind::AwsKms
+        );
+        assert!("vault".parse::<SignerBackendKind>().is_err());
+    }
+
+    const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const OLD_KEY: &str = test_utils::SIGNER_KEY;
+    const NEW_KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+    fn create_service() -> PaymasterRelayService {
+        let env_vars = ["switch", "status", "grace", "unset", "same", "concurrent"]
+            .into_iter()
+            .map(env_var)
+            .collect();
+        test_utils::service(&format!("senders = [\"{}\"]\n", SENDER))
+            .with_key_rotation_env_vars(env_vars)
+    }
+
+    /// Environment variable unique to `test`
+    fn env_var(test: &str) -> String {
+        format!("KEY_ROTATION_TEST_{}", test.to_uppercase())
+    }
+
+    /// Key source for `key`, through the environment variable of `test`
+    fn env_source(test: &str, key: &str) -> KeySource {
+        let name = env_var(test);
+        std::env::set_var(&name, key);
+        KeySource::Env(name)
+    }
+
+    fn address_of(key: &str) -> Address {
+        let wallet = ethers::signers::LocalWallet::from_str(key).unwrap();
+        to_alloy(ethers::signers::Signer::address(&wallet))
+    }
+
+    fn to_alloy(address: ethers::types::Address) -> Address {
+        Address::from_slice(address.as_bytes())
+    }
+
+    fn create_user_op(nonce: u64) -> UserOperationVariant {
+        let op = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender: Address::from_str(SENDER).unwrap(),
+                nonce: U256::from(nonce),
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 1_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        UserOperationVariant::V0_7(op)
+    }
+
+    async fn sponsor(service: &PaymasterRelayService, nonce: u64) -> UserOperationVariant {
+        let entry_point = ethers::types::Address::from_slice(
+            ChainSpec::default().entry_point_address_v0_7.as_slice(),
+        );
+        service
+            .sponsor_user_operation(
+                create_user_op(nonce),
+                entry_point,
+                SponsorOptions {
+                    return_full_operation: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap()
+            .sponsored_user_op
+            .unwrap()
+    }
+
+    fn signed_by(user_op: &UserOperationVariant, paymaster: Address, signer: Address) -> bool {
+        verify_sponsorship_by(user_op, paymaster, &[signer], 0).is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_rotation_switches_signing_key() {
+        let service = create_service();
+        let paymaster = service.paymaster_address().await;
+        let (old_signer, new_signer) = (address_of(OLD_KEY), address_of(NEW_KEY));
+
+        let before = sponsor(&service, 1).await;
+        assert!(signed_by(&before, paymaster, old_signer));
+        assert!(service.verify_own_sponsorship(&before).await.unwrap());
+
+        let rotation = service
+            .rotate_paymaster_key(&env_source("switch", NEW_KEY), None)
+            .await
+            .unwrap();
+        assert_eq!(to_alloy(rotation.paymaster), paymaster);
+        assert_eq!(to_alloy(rotation.active_signer), new_signer);
+        assert_eq!(to_alloy(rotation.retiring_signer.address), old_signer);
+
+        let after = sponsor(&service, 2).await;
+        assert_eq!(after.paymaster(), Some(paymaster));
+        assert!(signed_by(&after, paymaster, new_signer));
+        assert!(!signed_by(&after, paymaster, old_signer));
+        assert!(service.verify_own_sponsorship(&after).await.unwrap());
+
+        // Sponsorships handed out before the rotation stay valid during the grace period
+        assert!(service.verify_own_sponsorship(&before).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_active_and_retiring_keys() {
+        let service = create_service();
+        let paymaster = service.paymaster_address().await;
+
+        let status = service.signer_status().await;
+        assert_eq!(status.active_signer, address_of(OLD_KEY));
+        assert_eq!(status.retiring_signer, None);
+
+        service
+            .rotate_paymaster_key(&env_source("status", NEW_KEY), None)
+            .await
+            .unwrap();
+
+        let status = service.signer_status().await;
+        assert_eq!(status.paymaster, paymaster);
+        assert_eq!(status.active_signer, address_of(NEW_KEY));
+        assert_eq!(status.retiring_signer, Some(address_of(OLD_KEY)));
+        assert!(status.retire_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_old_key_rejected_after_grace_period() {
+        let service = create_service();
+        let before = sponsor(&service, 1).await;
+
+        service
+            .rotate_paymaster_key(&env_source("grace", NEW_KEY), Some(Duration::ZERO))
+            .await
+            .unwrap();
+
+        assert!(service.verify_own_sponsorship(&before).await.is_err());
+        assert_eq!(service.signer_status().await.retiring_signer, None);
+    }
+
+    #[tokio::test]
+    async fn test_failed_rotation_keeps_current_key() {
+        let service = create_service();
+
+        let missing = KeySource::Env(env_var("unset"));
+        assert!(service.rotate_paymaster_key(&missing, None).await.is_err());
+        let unlisted = env_source("unlisted", NEW_KEY);
+        assert!(matches!(
+            service.rotate_paymaster_key(&unlisted, None).await,
+            Err(PaymasterError::InvalidRequest(_))
+        ));
+        let kms = KeySource::Kms("paymaster-backup-key-1".to_string());
+        assert!(service.rotate_paymaster_key(&kms, None).await.is_err());
+        let same = env_source("same", OLD_KEY);
+        assert!(service.rotate_paymaster_key(&same, None).await.is_err());
+
+        let status = service.signer_status().await;
+        assert_eq!(status.active_signer, address_of(OLD_KEY));
+        assert_eq!(status.retiring_signer, None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_sponsorships_during_rotation_are_valid() {
+        let service = Arc::new(create_service());
+        let paymaster = service.paymaster_address().await;
+        let signers = [address_of(OLD_KEY), address_of(NEW_KEY)];
+
+        let mut handles = Vec::new();
+        for nonce in 0..20 {
+            let service = service.clone();
+            handles.push(tokio::spawn(async move { sponsor(&service, nonce).await }));
+        }
+        service
+            .rotate_paymaster_key(&env_source("concurrent", NEW_KEY), None)
+            .await
+            .unwrap();
+        for nonce in 20..40 {
+            let service = service.clone();
+            handles.push(tokio::spawn(async move { sponsor(&service, nonce).await }));
+        }
+
+        for handle in handles {
+            let user_op = handle.await.unwrap();
+            // Each signature comes entirely from one key, never a mix
+            assert!(
+                signers
+                    .iter()
+                    .any(|signer| signed_by(&user_op, paymaster, *signer)),
+                "sponsorship signed by neither key"
+            );
+            assert!(service.verify_own_sponsorship(&user_op).await.unwrap());
+        }
+    }
+
+    const PRIMARY_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const POOL_KEY_1: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+    const POOL_KEY_2: &str = "0x5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdab365a";
+
+    /// Key source for `key`, through an environment variable unique to `name`
+    fn pool_key_source(name: &str, key: &str) -> KeySource {
+        let name = format!("SIGNER_POOL_TEST_{}", name.to_uppercase());
+        std::env::set_var(&name, key);
+        KeySource::Env(name)
+    }
+
+    fn h160(address: Address) -> ethers::types::Address {
+        ethers::types::Address::from_slice(address.as_slice())
+    }
+
+    async fn signer_manager(test: &str, pool_keys: &[&str]) -> SignerManager {
+        let sources: Vec<KeySource> = pool_keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| pool_key_source(&format!("{}_{}", test, i), key))
+            .collect();
+        SignerManager::new(SecretString::new(PRIMARY_KEY.to_string().into()))
+            .unwrap()
+            .with_pool_keys(&sources)
+            .await
+            .unwrap()
+    }
+
+    async fn create_pool_service(test: &str, pool_keys: &[&str]) -> Arc<PaymasterRelayService> {
+        let dir = tempdir().unwrap();
+        Arc::new(test_utils::service_signed_by(
+            dir.path(),
+            &format!("senders = [\"{}\"]\n", SENDER),
+            signer_manager(test, pool_keys).await,
+        ))
+    }
+
+    fn create_v0_6_user_op(nonce: u64) -> UserOperationVariant {
+        let op = v0_6::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_6::UserOperationRequiredFields {
+                sender: Address::from_str(SENDER).unwrap(),
+                nonce: U256::from(nonce),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                ..Default::default()
+            },
+        )
+        .build();
+        UserOperationVariant::V0_6(op)
+    }
+
+    async fn sponsor_on(
+        service: &PaymasterRelayService,
+        user_op: UserOperationVariant,
+        entry_point: Address,
+    ) -> UserOperationVariant {
+        service
+            .sponsor_user_operation(
+                user_op,
+                h160(entry_point),
+                SponsorOptions {
+                    return_full_operation: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap()
+            .sponsored_user_op
+            .unwrap()
+    }
+
+    /// Pool key whose signature the sponsored operation carries, which its
+    /// paymaster data names
+    fn pool_signer(user_op: &UserOperationVariant, paymaster: Address, keys: &[&str]) -> Address {
+        let signer = keys
+            .iter()
+            .map(|key| address_of(key))
+            .find(|signer| verify_sponsorship_by(user_op, paymaster, &[*signer], 0).is_ok())
+            .expect("sponsorship signed by no pool key");
+        assert_eq!(named_signer(user_op), signer);
+        signer
+    }
+
+    /// Signer embedded in the paymaster data of `user_op`
+    fn named_signer(user_op: &UserOperationVariant) -> Address {
+        SponsorshipData::decode(sponsorship::paymaster_data(user_op))
+            .unwrap()
+            .signer
+    }
+
+    #[tokio::test]
+    async fn test_sponsorship_names_leased_key() {
+        let keys = [PRIMARY_KEY, POOL_KEY_1, POOL_KEY_2];
+        let service = create_pool_service("named", &keys[1..]).await;
+        let paymaster = service.paymaster_address().await;
+        let chain_spec = ChainSpec::default();
+
+        // Keys are leased in turn, for either paymaster data layout
+        let sponsored = [
+            sponsor(&service, 0).await,
+            sponsor_on(
+                &service,
+                create_v0_6_user_op(1),
+                chain_spec.entry_point_address_v0_6,
+            )
+            .await,
+            sponsor(&service, 2).await,
+        ];
+        assert!(matches!(sponsored[1], UserOperationVariant::V0_6(_)));
+        for (user_op, key) in sponsored.iter().zip(keys) {
+            assert_eq!(named_signer(user_op), address_of(key));
+            assert_eq!(pool_signer(user_op, paymaster, &keys), address_of(key));
+        }
+
+        // The signature has to come from the key the data names
+        let UserOperationVariant::V0_7(op) = &sponsored[0] else {
+            unreachable!()
+        };
+        let renamed = SponsorshipData {
+            signer: address_of(POOL_KEY_1),
+            ..SponsorshipData::decode(op.paymaster_data()).unwrap()
+        };
+        let renamed = UserOperationVariant::V0_7(
+            v0_7::UserOperationBuilder::from_uo(op.clone(), &chain_spec)
+                .paymaster(
+                    paymaster,
+                    op.paymaster_verification_gas_limit(),
+                    op.paymaster_post_op_gas_limit(),
+                    renamed.encode().into(),
+                )
+                .build(),
+        );
+        assert!(service.verify_own_sponsorship(&renamed).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_sponsorships_round_robin_across_keys() {
+        let keys = [PRIMARY_KEY, POOL_KEY_1, POOL_KEY_2];
+        let service = create_pool_service("round_robin", &keys[1..]).await;
+        let paymaster = service.paymaster_address().await;
+        assert_eq!(paymaster, address_of(PRIMARY_KEY));
+
+        let handles: Vec<_> = (0..30)
+            .map(|nonce| {
+                let service = service.clone();
+                tokio::spawn(async move { sponsor(&service, nonce).await })
+            })
+            .collect();
+
+        let mut used: HashMap<Address, usize> = HashMap::new();
+        for handle in handles {
+            let user_op = handle.await.unwrap();
+            // Every key signs for the same paymaster contract
+            assert_eq!(user_op.paymaster(), Some(paymaster));
+            *used
+                .entry(pool_signer(&user_op, paymaster, &keys))
+                .or_default() += 1;
+            assert!(service.verify_own_sponsorship(&user_op).await.unwrap());
+        }
+        for key in keys {
+            assert_eq!(used.get(&address_of(key)), Some(&10), "{:?}", used);
+        }
+
+        let status = service.signer_status().await;
+        assert_eq!(status.active_signer, address_of(PRIMARY_KEY));
+        assert_eq!(status.signers.len(), 3);
+        for signer in &status.signers {
+            assert_eq!(signer.signatures, 10);
+            assert_eq!(signer.in_flight, 0);
+            assert!(!signer.draining);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drained_key_finishes_in_flight_signing() {
+        let mut signer_manager = signer_manager("in_flight", &[POOL_KEY_1]).await;
+        let primary = h160(address_of(PRIMARY_KEY));
+        let pool_key = h160(address_of(POOL_KEY_1));
+
+        let first = signer_manager.next_signer().unwrap();
+        let in_flight = signer_manager.next_signer().unwrap();
+        assert_eq!(first.address(), primary);
+        assert_eq!(in_flight.address(), pool_key);
+        drop(first);
+
+        signer_manager.set_draining(pool_key, true).unwrap();
+        let status = signer_manager.signer_keys();
+        assert!(status[1].draining);
+        assert_eq!(status[1].in_flight, 1);
+
+        // The request holding the drained key still signs with it
+        let hash = H256::random();
+        let signature = in_flight
+            .sign_hash_with_context(hash.to_fixed_bytes(), None)
+            .await
+            .unwrap();
+        signature.verify(hash, pool_key).unwrap();
+        drop(in_flight);
+        assert_eq!(signer_manager.signer_keys()[1].in_flight, 0);
+
+        // New work only goes to the keys still serving
+        for _ in 0..4 {
+            assert_eq!(signer_manager.next_signer().unwrap().address(), primary);
+        }
+        assert!(signer_manager.set_draining(primary, true).is_err());
+
+        signer_manager.set_draining(pool_key, false).unwrap();
+        let next: Vec<_> = (0..2)
+            .map(|_| signer_manager.next_signer().unwrap().address())
+            .collect();
+        assert!(next.contains(&primary) && next.contains(&pool_key));
+    }
+
+    #[tokio::test]
+    async fn test_draining_during_concurrent_sponsorships() {
+        let keys = [PRIMARY_KEY, POOL_KEY_1];
+        let service = create_pool_service("drain", &keys[1..]).await;
+        let paymaster = service.paymaster_address().await;
+
+        let mut handles = Vec::new();
+        for nonce in 0..20 {
+            let service = service.clone();
+            handles.push(tokio::spawn(async move { sponsor(&service, nonce).await }));
+        }
+        let status = service
+            .drain_signer(address_of(POOL_KEY_1), true)
+            .await
+            .unwrap();
+        assert!(status.signers[1].draining);
+        for nonce in 20..40 {
+            let service = service.clone();
+            handles.push(tokio::spawn(async move { sponsor(&service, nonce).await }));
+        }
+
+        // Sponsorships started before the drain complete, and stay valid
+        for handle in handles {
+            let user_op = handle.await.unwrap();
+            pool_signer(&user_op, paymaster, &keys);
+            assert!(service.verify_own_sponsorship(&user_op).await.unwrap());
+        }
+
+        let drained_signatures = service.signer_status().await.signers[1].signatures;
+        for nonce in 40..46 {
+            let user_op = sponsor(&service, nonce).await;
+            assert_eq!(
+                pool_signer(&user_op, paymaster, &keys),
+                address_of(PRIMARY_KEY)
+            );
+        }
+        assert_eq!(
+            service.signer_status().await.signers[1].signatures,
+            drained_signatures
+        );
+
+        assert!(service
+            .drain_signer(address_of(PRIMARY_KEY), true)
+            .await
+            .is_err());
+        assert!(service
+            .drain_signer(Address::repeat_byte(0x42), true)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pool_requires_one_loadable_key() {
+        let missing = KeySource::Env("SIGNER_POOL_TEST_UNSET".to_string());
+        let primary =
+            || SignerManager::new(SecretString::new(PRIMARY_KEY.to_string().into())).unwrap();
+
+        // Unloadable and duplicate keys are skipped
+        let sources = [
+            missing.clone(),
+            pool_key_source("loading", POOL_KEY_1),
+            pool_key_source("loading_duplicate", POOL_KEY_1),
+            pool_key_source("loading_primary", PRIMARY_KEY),
+        ];
+        let signer_manager = primary().with_pool_keys(&sources).await.unwrap();
+        let addresses: Vec<_> = signer_manager
+            .signer_keys()
+            .iter()
+            .map(|key| key.address)
+            .collect();
+        assert_eq!(
+            addresses,
+            vec![h160(address_of(PRIMARY_KEY)), h160(address_of(POOL_KEY_1))]
+        );
+
+        assert!(primary()
+            .with_pool_keys(std::slice::from_ref(&missing))
+            .await
+            .is_err());
+        // KMS references need the aws-kms backend
+        let kms = KeySource::Kms("paymaster-backup-key-1".to_string());
+        assert!(primary().with_pool_keys(&[kms]).await.is_err());
+
+        let single = primary().with_pool_keys(&[]).await.unwrap();
+        assert_eq!(single.signer_keys().len(), 1);
+    }
 }
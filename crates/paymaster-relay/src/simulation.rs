@@ -0,0 +1,422 @@
+// paymaster-relay/src/simulation.rs
+// Sponsorship previews: EntryPoint validation simulation of an operation as it
+// would be sponsored, without handing out the paymaster signature.
+
+use std::fmt;
+
+use alloy_primitives::{Address, Bytes, U256};
+use async_trait::async_trait;
+use rundler_provider::{EntryPoint, SimulationProvider};
+use rundler_types::{
+    v0_6, v0_7, UserOperation, UserOperationVariant, ValidationOutput, ValidationRevert,
+};
+use serde::Serialize;
+
+use crate::error::PaymasterError;
+
+/// Runs EntryPoint `simulateValidation` for sponsorship previews
+#[async_trait]
+pub trait ValidationSimulator: Send + Sync {
+    /// Simulate validation of `user_op` on `entry_point`
+    ///
+    /// The outer error covers failures to reach the node, the inner one a
+    /// validation revert.
+    async fn simulate_validation(
+        &self,
+        entry_point: Address,
+        user_op: UserOperationVariant,
+    ) -> Result<Result<ValidationOutput, ValidationRevert>, PaymasterError>;
+}
+
+impl fmt::Debug for dyn ValidationSimulator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ValidationSimulator")
+    }
+}
+
+/// [`ValidationSimulator`] backed by the node's v0.6 and v0.7 EntryPoint providers
+pub struct EntryPointSimulator<E06, E07> {
+    v0_6: Option<E06>,
+    v0_7: Option<E07>,
+}
+
+impl<E06, E07> EntryPointSimulator<E06, E07> {
+    pub fn new(v0_6: Option<E06>, v0_7: Option<E07>) -> Self {
+        Self { v0_6, v0_7 }
+    }
+}
+
+#[async_trait]
+impl<E06, E07> ValidationSimulator for EntryPointSimulator<E06, E07>
+where
+    E06: EntryPoint + SimulationProvider<UO = v0_6::UserOperation>,
+    E07: EntryPoint + SimulationProvider<UO = v0_7::UserOperation>,
+{
+    async fn simulate_validation(
+        &self,
+        entry_point: Address,
+        user_op: UserOperationVariant,
+    ) -> Result<Result<ValidationOutput, ValidationRevert>, PaymasterError> {
+        let result = match (user_op, &self.v0_6, &self.v0_7) {
+            (UserOperationVariant::V0_6(op), Some(ep), _) if *ep.address() == entry_point => {
+                ep.simulate_validation(op, None).await
+            }
+            (UserOperationVariant::V0_7(op), _, Some(ep)) if *ep.address() == entry_point => {
+                ep.simulate_validation(op, None).await
+            }
+            _ => {
+                return Err(PaymasterError::InvalidRequest(format!(
+                    "Simulation is not available for entry point {}",
+                    entry_point
+                )))
+            }
+        };
+        result.map_err(|e| PaymasterError::ChainError(format!("Simulation failed: {}", e)))
+    }
+}
+
+/// Outcome of a sponsorship preview
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SimulationStatus {
+    /// The operation would be sponsored and validates on chain
+    Ok,
+    /// Sponsorship policy, SBT or fee checks would refuse the operation
+    PolicyRejected,
+    /// EntryPoint validation reverted
+    SimulationReverted,
+}
+
+/// Gas figures of a successful validation simulation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedGas {
+    /// Pre-verification gas of the sponsored operation
+    pub pre_verification_gas: U256,
+    /// Gas used by account and paymaster validation
+    pub verification_gas_used: U256,
+    /// Pre-verification plus validation gas, as reported by the EntryPoint
+    pub pre_op_gas: U256,
+    /// Call gas limit of the sponsored operation
+    pub call_gas_limit: U256,
+    /// Paymaster validation gas limit (v0.7)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paymaster_verification_gas_limit: Option<U256>,
+    /// Paymaster postOp gas limit (v0.7)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paymaster_post_op_gas_limit: Option<U256>,
+}
+
+/// Result of `pm_simulateSponsorship`
+///
+/// Never carries paymaster data, so a preview cannot be submitted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SponsorshipSimulation {
+    pub status: SimulationStatus,
+    /// Policy that matched the operation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy_id: Option<String>,
+    /// Rejection or revert reason
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Raw revert data returned by the reverting entity
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_data: Option<Bytes>,
+    /// Whether the account signature failed, expected for dummy signatures
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_sig_failed: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas: Option<SimulatedGas>,
+}
+
+impl SponsorshipSimulation {
+    /// Preview refused before simulation
+    pub fn rejected(reason: impl Into<String>) -> Self {
+        Self {
+            status: SimulationStatus::PolicyRejected,
+            policy_id: None,
+            reason: Some(reason.into()),
+            revert_data: None,
+            account_sig_failed: None,
+            gas: None,
+        }
+    }
+
+    /// Preview of `sponsored`, the operation merged with the paymaster fields,
+    /// from its validation simulation
+    pub fn from_validation(
+        policy_id: String,
+        sponsored: &UserOperationVariant,
+        validation: Result<ValidationOutput, ValidationRevert>,
+    ) -> Self {
+        let output = match validation {
+            Ok(output) => output,
+            Err(revert) => {
+                let revert_data = match &revert {
+                    ValidationRevert::Operation {
+                        inner_revert_data, ..
+                    } if !inner_revert_data.is_empty() => Some(inner_revert_data.clone()),
+                    ValidationRevert::Unknown(data) => Some(data.clone()),
+                    _ => None,
+                };
+                return Self {
+                    status: SimulationStatus::SimulationReverted,
+                    policy_id: Some(policy_id),
+                    reason: Some(revert.to_string()),
+                    revert_data,
+                    account_sig_failed: None,
+                    gas: None,
+                };
+            }
+        };
+
+        let return_info = output.return_info;
+        if return_info.paymaster_sig_failed {
+            return Self {
+                status: SimulationStatus::SimulationReverted,
+                policy_id: Some(policy_id),
+                reason: Some("Paymaster signature rejected by the paymaster contract".to_string()),
+                revert_data: None,
+                account_sig_failed: Some(return_info.account_sig_failed),
+                gas: None,
+            };
+        }
+
+        let pre_verification_gas = sponsored.pre_verification_gas();
+        let (paymaster_verification_gas_limit, paymaster_post_op_gas_limit) = match sponsored {
+            UserOperationVariant::V0_6(_) => (None, None),
+            UserOperationVariant::V0_7(op) => (
+                Some(U256::from(op.paymaster_verification_gas_limit())),
+                Some(U256::from(op.paymaster_post_op_gas_limit())),
+            ),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => (
+                Some(U256::from(op.paymaster_verification_gas_limit())),
+                Some(U256::from(op.paymaster_post_op_gas_limit())),
+            ),
+        };
+        Self {
+            status: SimulationStatus::Ok,
+            policy_id: Some(policy_id),
+            reason: None,
+            revert_data: None,
+            account_sig_failed: Some(return_info.account_sig_failed),
+            gas: Some(SimulatedGas {
+                pre_verification_gas: U256::from(pre_verification_gas),
+                verification_gas_used: U256::from(
+                    return_info.pre_op_gas.saturating_sub(pre_verification_gas),
+                ),
+                pre_op_gas: U256::from(return_info.pre_op_gas),
+                call_gas_limit: U256::from(sponsored.call_gas_limit()),
+                paymaster_verification_gas_limit,
+                paymaster_post_op_gas_limit,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use alloy_primitives::{Address, Bytes, U256};
+    use rundler_provider::{MockEntryPointV0_6, MockEntryPointV0_7};
+    use rundler_types::{
+        chain::ChainSpec, v0_7, StakeInfo, UserOperation, UserOperationVariant, ValidationOutput,
+        ValidationReturnInfo, ValidationRevert,
+    };
+
+    use super::*;
+    use crate::{service::PaymasterRelayService, test_utils};
+
+    fn sponsored_op() -> UserOperationVariant {
+        let op = v0_6::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_6::UserOperationRequiredFields {
+                pre_verification_gas: 21_000,
+                call_gas_limit: 50_000,
+                ..Default::default()
+            },
+        )
+        .build();
+        UserOperationVariant::V0_6(op)
+    }
+
+    fn output(return_info: ValidationReturnInfo) -> ValidationOutput {
+        ValidationOutput {
+            return_info,
+            sender_info: StakeInfo::default(),
+            factory_info: StakeInfo::default(),
+            paymaster_info: StakeInfo::default(),
+            aggregator_info: None,
+        }
+    }
+
+    #[test]
+    fn test_v0_6_gas_breakdown() {
+        let simulation = SponsorshipSimulation::from_validation(
+            "default".to_string(),
+            &sponsored_op(),
+            Ok(output(ValidationReturnInfo {
+                pre_op_gas: 70_000,
+                ..Default::default()
+            })),
+        );
+
+        assert_eq!(simulation.status, SimulationStatus::Ok);
+        let gas = simulation.gas.unwrap();
+        assert_eq!(gas.verification_gas_used, U256::from(49_000u64));
+        assert_eq!(gas.call_gas_limit, U256::from(50_000u64));
+        assert_eq!(gas.paymaster_verification_gas_limit, None);
+    }
+
+    #[test]
+    fn test_paymaster_signature_failure_is_a_revert() {
+        let simulation = SponsorshipSimulation::from_validation(
+            "default".to_string(),
+            &sponsored_op(),
+            Ok(output(ValidationReturnInfo {
+                pre_op_gas: 70_000,
+                paymaster_sig_failed: true,
+                ..Default::default()
+            })),
+        );
+
+        assert_eq!(simulation.status, SimulationStatus::SimulationReverted);
+        assert!(simulation.gas.is_none());
+    }
+
+    const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    /// Address of the test signer key
+    const PAYMASTER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const OTHER_SENDER: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+
+    fn entry_point_v0_7() -> Address {
+        ChainSpec::default().entry_point_address_v0_7
+    }
+
+    fn create_service(entry_point: MockEntryPointV0_7) -> PaymasterRelayService {
+        test_utils::service(&format!("senders = [\"{}\"]\n", SENDER)).with_simulator(Arc::new(
+            EntryPointSimulator::<MockEntryPointV0_6, _>::new(None, Some(entry_point)),
+        ))
+    }
+
+    /// v0.7 EntryPoint answering simulateValidation with `result`
+    fn mock_entry_point(result: Result<ValidationOutput, ValidationRevert>) -> MockEntryPointV0_7 {
+        let mut entry_point = MockEntryPointV0_7::new();
+        entry_point
+            .expect_address()
+            .return_const(entry_point_v0_7());
+        entry_point
+            .expect_simulate_validation()
+            .withf(|op, _| op.paymaster() == Some(Address::from_str(PAYMASTER).unwrap()))
+            .returning(move |_, _| Ok(result.clone()));
+        entry_point
+    }
+
+    fn validation_output(pre_op_gas: u128) -> ValidationOutput {
+        ValidationOutput {
+            return_info: ValidationReturnInfo {
+                pre_op_gas,
+                account_sig_failed: true,
+                ..Default::default()
+            },
+            sender_info: StakeInfo::default(),
+            factory_info: StakeInfo::default(),
+            paymaster_info: StakeInfo::default(),
+            aggregator_info: None,
+        }
+    }
+
+    fn create_user_op(sender: &str) -> UserOperationVariant {
+        let op = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender: Address::from_str(sender).unwrap(),
+                nonce: U256::ZERO,
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 30_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        UserOperationVariant::V0_7(op)
+    }
+
+    fn ethers_entry_point() -> ethers::types::Address {
+        ethers::types::Address::from_slice(entry_point_v0_7().as_slice())
+    }
+
+    #[tokio::test]
+    async fn test_simulation_ok_reports_gas_without_paymaster_data() {
+        let service = create_service(mock_entry_point(Ok(validation_output(81_000))));
+
+        let simulation = service
+            .simulate_sponsorship(create_user_op(SENDER), ethers_entry_point())
+            .await
+            .unwrap();
+
+        assert_eq!(simulation.status, SimulationStatus::Ok);
+        assert_eq!(simulation.policy_id.as_deref(), Some("default"));
+        assert_eq!(simulation.account_sig_failed, Some(true));
+        let gas = simulation.gas.as_ref().unwrap();
+        assert_eq!(gas.pre_verification_gas, U256::from(21_000u64));
+        assert_eq!(gas.verification_gas_used, U256::from(60_000u64));
+        assert_eq!(gas.call_gas_limit, U256::from(100_000u64));
+        assert!(gas.paymaster_verification_gas_limit.is_some());
+
+        let json = serde_json::to_value(&simulation).unwrap();
+        assert_eq!(json["status"], "ok");
+        assert!(json.get("paymasterAndData").is_none());
+        assert!(json.get("paymasterData").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_simulation_revert_reported_with_reason() {
+        let service = create_service(mock_entry_point(Err(ValidationRevert::Operation {
+            entry_point_reason: "AA23 reverted".to_string(),
+            inner_revert_data: Bytes::from_static(&[0xde, 0xad]),
+            inner_revert_reason: Some("not owner".to_string()),
+        })));
+
+        let simulation = service
+            .simulate_sponsorship(create_user_op(SENDER), ethers_entry_point())
+            .await
+            .unwrap();
+
+        assert_eq!(simulation.status, SimulationStatus::SimulationReverted);
+        assert_eq!(
+            simulation.reason.as_deref(),
+            Some("AA23 reverted : not owner")
+        );
+        assert_eq!(
+            simulation.revert_data,
+            Some(Bytes::from_static(&[0xde, 0xad]))
+        );
+        assert!(simulation.gas.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_policy_rejection_skips_simulation() {
+        let mut entry_point = MockEntryPointV0_7::new();
+        entry_point
+            .expect_address()
+            .return_const(entry_point_v0_7());
+        entry_point.expect_simulate_validation().never();
+        let service = create_service(entry_point);
+
+        let simulation = service
+            .simulate_sponsorship(create_user_op(OTHER_SENDER), ethers_entry_point())
+            .await
+            .unwrap();
+
+        assert_eq!(simulation.status, SimulationStatus::PolicyRejected);
+        assert!(simulation.reason.unwrap().contains("allowlist"));
+        assert!(simulation.gas.is_none());
+    }
+}
@@ -0,0 +1,542 @@
+// paymaster-relay/src/sponsorship.rs
+// Encoding and verification of the paymaster data emitted by pm_sponsorUserOperation.
+//
+// Layout follows the eth-infinitism VerifyingPaymaster, with the signing key named
+// before the signature so a paymaster trusting several keys knows which one to check:
+//   paymasterData = abi.encode(uint48 validUntil, uint48 validAfter) || signer (20 bytes)
+//                   || signature (65 bytes)
+// For v0.6 paymasterAndData is the paymaster address followed by paymasterData; for v0.7
+// paymasterData is carried separately next to the paymaster gas limits.
+
+use alloy_primitives::{hex, keccak256, Address, Bytes, B256, U256};
+use ethers::types::{Signature, H256};
+use rundler_types::{chain::ChainSpec, v0_7, UserOperation, UserOperationVariant};
+use thiserror::Error;
+
+/// Length of the abi encoded (validUntil, validAfter) pair
+pub const VALIDITY_WINDOW_LEN: usize = 64;
+/// Length of the signer address following the validity window
+pub const SIGNER_LEN: usize = 20;
+/// Length of an RSV ECDSA signature
+pub const SIGNATURE_LEN: usize = 65;
+
+const MAX_UINT48: u64 = (1 << 48) - 1;
+
+/// Stub signature as long as a real one, for estimating gas before sponsorship
+///
+/// No byte is zero, so it costs as much calldata as any real signature. It
+/// still recovers to some address (r is the x coordinate of the secp256k1
+/// generator, s is in the lower half of the curve order), so the paymaster's
+/// signature check fails without reverting.
+pub const DUMMY_SIGNATURE: [u8; SIGNATURE_LEN] = hex!(
+    "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+    "7aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+    "1c"
+);
+
+/// Reasons a sponsorship embedded in a UserOperation is rejected
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SponsorshipError {
+    #[error("Malformed paymaster data: {0}")]
+    Malformed(String),
+
+    #[error("Invalid paymaster signature: {0}")]
+    InvalidSignature(String),
+
+    #[error("Sponsorship expired at {valid_until} (now {now})")]
+    Expired { valid_until: u64, now: u64 },
+
+    #[error("Sponsorship not valid before {valid_after} (now {now})")]
+    NotYetValid { valid_after: u64, now: u64 },
+}
+
+impl SponsorshipError {
+    /// Whether the signature is valid but the validity window excludes `now`
+    pub fn is_out_of_time_range(&self) -> bool {
+        matches!(
+            self,
+            SponsorshipError::Expired { .. } | SponsorshipError::NotYetValid { .. }
+        )
+    }
+}
+
+/// Length of the complete paymasterData of a sponsorship
+pub const SPONSORSHIP_DATA_LEN: usize = VALIDITY_WINDOW_LEN + SIGNER_LEN + SIGNATURE_LEN;
+
+/// Validity window, signer and signature carried in paymasterData
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SponsorshipData {
+    /// Last timestamp the sponsorship is valid; 0 means no expiry
+    pub valid_until: u64,
+    /// First timestamp the sponsorship is valid
+    pub valid_after: u64,
+    /// Key the signature was made with, one of the paymaster's signers
+    pub signer: Address,
+    pub signature: Vec<u8>,
+}
+
+impl SponsorshipData {
+    /// Encode as `abi.encode(validUntil, validAfter) || signer || signature`
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoded =
+            Vec::with_capacity(VALIDITY_WINDOW_LEN + SIGNER_LEN + self.signature.len());
+        encoded.extend_from_slice(&uint_word(self.valid_until));
+        encoded.extend_from_slice(&uint_word(self.valid_after));
+        encoded.extend_from_slice(self.signer.as_slice());
+        encoded.extend_from_slice(&self.signature);
+        encoded
+    }
+
+    /// Decode paymasterData produced by [`SponsorshipData::encode`]
+    pub fn decode(paymaster_data: &[u8]) -> Result<Self, SponsorshipError> {
+        if paymaster_data.len() != SPONSORSHIP_DATA_LEN {
+            return Err(SponsorshipError::Malformed(format!(
+                "expected {} bytes, got {}",
+                SPONSORSHIP_DATA_LEN,
+                paymaster_data.len()
+            )));
+        }
+
+        let signature_start = VALIDITY_WINDOW_LEN + SIGNER_LEN;
+        Ok(Self {
+            valid_until: decode_uint48(&paymaster_data[..32])?,
+            valid_after: decode_uint48(&paymaster_data[32..VALIDITY_WINDOW_LEN])?,
+            signer: Address::from_slice(&paymaster_data[VALIDITY_WINDOW_LEN..signature_start]),
+            signature: paymaster_data[signature_start..].to_vec(),
+        })
+    }
+}
+
+/// Paymaster data naming `signer` with [`DUMMY_SIGNATURE`], sized exactly like
+/// a real sponsorship
+///
+/// Real sponsorships are valid from 0, the expiry is the latest encodable
+/// time so it takes at least as many non-zero bytes as a real one. `signer`
+/// should be a key the paymaster trusts, so that validation takes the path
+/// of a real sponsorship up to the signature check.
+pub fn dummy_paymaster_data(signer: Address) -> Vec<u8> {
+    SponsorshipData {
+        valid_until: MAX_UINT48,
+        valid_after: 0,
+        signer,
+        signature: DUMMY_SIGNATURE.to_vec(),
+    }
+    .encode()
+}
+
+/// Hash the paymaster signs for `user_op`, matching VerifyingPaymaster.getHash
+///
+/// Paymaster data itself is excluded so the signature can be embedded afterwards.
+pub fn sponsorship_hash(
+    user_op: &UserOperationVariant,
+    paymaster: Address,
+    valid_until: u64,
+    valid_after: u64,
+) -> B256 {
+    let mut encoded = Vec::with_capacity(32 * 13);
+    encoded.extend_from_slice(&address_word(user_op.sender()));
+    encoded.extend_from_slice(&user_op.nonce().to_be_bytes::<32>());
+
+    match user_op {
+        UserOperationVariant::V0_6(op) => {
+            encoded.extend_from_slice(keccak256(op.init_code()).as_slice());
+            encoded.extend_from_slice(keccak256(op.call_data()).as_slice());
+            encoded.extend_from_slice(&u128_word(op.call_gas_limit()));
+            encoded.extend_from_slice(&u128_word(op.verification_gas_limit()));
+            encoded.extend_from_slice(&u128_word(op.pre_verification_gas()));
+            encoded.extend_from_slice(&u128_word(op.max_fee_per_gas()));
+            encoded.extend_from_slice(&u128_word(op.max_priority_fee_per_gas()));
+        }
+        UserOperationVariant::V0_7(op) => encode_packed_fields(&mut encoded, op),
+        // The v0.8 VerifyingPaymaster hashes the same fields as v0.7
+        #[cfg(feature = "entrypoint-v0_8")]
+        UserOperationVariant::V0_8(op) => encode_packed_fields(&mut encoded, op.fields()),
+    }
+
+    encoded.extend_from_slice(&uint_word(user_op.chain_id()));
+    encoded.extend_from_slice(&address_word(paymaster));
+    encoded.extend_from_slice(&uint_word(valid_until));
+    encoded.extend_from_slice(&uint_word(valid_after));
+
+    keccak256(&encoded)
+}
+
+/// Encode the gas and code fields of a packed (v0.7) operation
+fn encode_packed_fields(encoded: &mut Vec<u8>, op: &v0_7::UserOperation) {
+    let init_code: Bytes = match op.factory() {
+        Some(factory) => [factory.as_slice(), &op.factory_data()[..]].concat().into(),
+        None => Bytes::new(),
+    };
+    encoded.extend_from_slice(keccak256(&init_code).as_slice());
+    encoded.extend_from_slice(keccak256(op.call_data()).as_slice());
+    encoded.extend_from_slice(&packed_u128_word(
+        op.verification_gas_limit(),
+        op.call_gas_limit(),
+    ));
+    encoded.extend_from_slice(&packed_u128_word(
+        op.paymaster_verification_gas_limit(),
+        op.paymaster_post_op_gas_limit(),
+    ));
+    encoded.extend_from_slice(&u128_word(op.pre_verification_gas()));
+    encoded.extend_from_slice(&packed_u128_word(
+        op.max_priority_fee_per_gas(),
+        op.max_fee_per_gas(),
+    ));
+}
+
+/// Most the paymaster can be charged for `user_op`, including its share of a
+/// signature aggregator's validation gas
+///
+/// Aggregation costs are only known once the operation has been transformed
+/// for its aggregator; untransformed operations count no aggregation gas.
+pub fn sponsored_max_gas_cost(user_op: &UserOperationVariant) -> U256 {
+    let chain_spec = ChainSpec {
+        id: user_op.chain_id(),
+        ..Default::default()
+    };
+    let aggregator_gas = user_op.aggregator_gas_limit(&chain_spec, None);
+    user_op.max_gas_cost() + U256::from(user_op.max_fee_per_gas()) * U256::from(aggregator_gas)
+}
+
+/// EIP-191 digest of a sponsorship hash, as recovered by `toEthSignedMessageHash`
+pub fn signing_digest(hash: B256) -> B256 {
+    B256::from(ethers::utils::hash_message(hash.as_slice()).0)
+}
+
+/// Paymaster data embedded in `user_op` (after the address for v0.6)
+pub fn paymaster_data(user_op: &UserOperationVariant) -> &[u8] {
+    match user_op {
+        UserOperationVariant::V0_6(op) => op.paymaster_and_data().get(20..).unwrap_or_default(),
+        UserOperationVariant::V0_7(op) => &op.paymaster_data()[..],
+        #[cfg(feature = "entrypoint-v0_8")]
+        UserOperationVariant::V0_8(op) => &op.paymaster_data()[..],
+    }
+}
+
+/// Verify the sponsorship signed by `paymaster` in `user_op` at time `now`
+pub fn verify_sponsorship(
+    user_op: &UserOperationVariant,
+    paymaster: Address,
+    now: u64,
+) -> Result<SponsorshipData, SponsorshipError> {
+    verify_sponsorship_by(user_op, paymaster, &[paymaster], now)
+}
+
+/// Verify a sponsorship for `paymaster` signed by any of `signers` at time `now`
+///
+/// The signature must recover to the signer named in the paymaster data.
+pub fn verify_sponsorship_by(
+    user_op: &UserOperationVariant,
+    paymaster: Address,
+    signers: &[Address],
+    now: u64,
+) -> Result<SponsorshipData, SponsorshipError> {
+    let data = SponsorshipData::decode(paymaster_data(user_op))?;
+
+    let hash = sponsorship_hash(user_op, paymaster, data.valid_until, data.valid_after);
+    verify_signer(hash, data.signer, &data.signature, signers)?;
+
+    if now < data.valid_after {
+        return Err(SponsorshipError::NotYetValid {
+            valid_after: data.valid_after,
+            now,
+        });
+    }
+    if data.valid_until != 0 && now >= data.valid_until {
+        return Err(SponsorshipError::Expired {
+            valid_until: data.valid_until,
+            now,
+        });
+    }
+
+    Ok(data)
+}
+
+/// Check that `signature` of `hash` was made by `signer`, one of `signers`
+pub(crate) fn verify_signer(
+    hash: B256,
+    signer: Address,
+    signature: &[u8],
+    signers: &[Address],
+) -> Result<(), SponsorshipError> {
+    if !signers.contains(&signer) {
+        return Err(SponsorshipError::InvalidSignature(format!(
+            "signer {} is not one of {:?}",
+            signer, signers
+        )));
+    }
+    let signature =
+        Signature::try_from(signature).map_err(|e| SponsorshipError::Malformed(e.to_string()))?;
+    let recovered = signature
+        .recover(H256::from(signing_digest(hash).0))
+        .map_err(|e| SponsorshipError::InvalidSignature(e.to_string()))?;
+    if recovered.as_bytes() != signer.as_slice() {
+        return Err(SponsorshipError::InvalidSignature(format!(
+            "recovered {:?}, expected signer {}",
+            recovered, signer
+        )));
+    }
+    Ok(())
+}
+
+fn uint_word(value: u64) -> [u8; 32] {
+    U256::from(value).to_be_bytes::<32>()
+}
+
+fn u128_word(value: u128) -> [u8; 32] {
+    U256::from(value).to_be_bytes::<32>()
+}
+
+fn packed_u128_word(high: u128, low: u128) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[..16].copy_from_slice(&high.to_be_bytes());
+    word[16..].copy_from_slice(&low.to_be_bytes());
+    word
+}
+
+fn address_word(address: Address) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address.as_slice());
+    word
+}
+
+fn decode_uint48(word: &[u8]) -> Result<u64, SponsorshipError> {
+    let value = U256::from_be_slice(word);
+    if value > U256::from(MAX_UINT48) {
+        return Err(SponsorshipError::Malformed(format!(
+            "validity timestamp {} exceeds uint48",
+            value
+        )));
+    }
+    Ok(value.to::<u64>())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use alloy_primitives::address;
+    use ethers::signers::LocalWallet;
+    use rundler_types::{aggregator::AggregatorCosts, v0_6, v0_7};
+
+    use super::*;
+
+    const PAYMASTER_KEY: &str =
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    fn wallet() -> (LocalWallet, Address) {
+        let wallet = LocalWallet::from_str(PAYMASTER_KEY).unwrap();
+        let address = Address::from_slice(ethers::signers::Signer::address(&wallet).as_bytes());
+        (wallet, address)
+    }
+
+    fn sign(
+        user_op: &UserOperationVariant,
+        paymaster: Address,
+        valid_until: u64,
+        valid_after: u64,
+    ) -> SponsorshipData {
+        let (wallet, signer) = wallet();
+        let hash = sponsorship_hash(user_op, paymaster, valid_until, valid_after);
+        let signature = wallet
+            .sign_hash(H256::from(signing_digest(hash).0))
+            .unwrap();
+        SponsorshipData {
+            valid_until,
+            valid_after,
+            signer,
+            signature: signature.to_vec(),
+        }
+    }
+
+    fn v06_op(paymaster_and_data: Vec<u8>) -> UserOperationVariant {
+        let op = v0_6::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_6::UserOperationRequiredFields {
+                sender: address!("0000000000000000000000000000000000000001"),
+                nonce: U256::from(7),
+                init_code: Bytes::new(),
+                call_data: Bytes::from(vec![0xde, 0xad, 0xbe, 0xef]),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 200_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 2_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                paymaster_and_data: paymaster_and_data.into(),
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        UserOperationVariant::V0_6(op)
+    }
+
+    fn v07_op(
+        paymaster: Address,
+        paymaster_verification_gas_limit: u128,
+        paymaster_data: Vec<u8>,
+    ) -> UserOperationVariant {
+        let op = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender: address!("0000000000000000000000000000000000000001"),
+                nonce: U256::from(7),
+                call_data: Bytes::from(vec![0xde, 0xad, 0xbe, 0xef]),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 200_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 2_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::new(),
+            },
+        )
+        .paymaster(
+            paymaster,
+            paymaster_verification_gas_limit,
+            20_000,
+            paymaster_data.into(),
+        )
+        .build();
+        UserOperationVariant::V0_7(op)
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let data = SponsorshipData {
+            valid_until: 1_700_000_600,
+            valid_after: 1_700_000_000,
+            signer: address!("00000000000000000000000000000000000000a1"),
+            signature: vec![0x11; SIGNATURE_LEN],
+        };
+        let encoded = data.encode();
+        assert_eq!(encoded.len(), SPONSORSHIP_DATA_LEN);
+        assert_eq!(
+            &encoded[VALIDITY_WINDOW_LEN..VALIDITY_WINDOW_LEN + SIGNER_LEN],
+            data.signer.as_slice()
+        );
+        assert_eq!(SponsorshipData::decode(&encoded).unwrap(), data);
+
+        assert!(matches!(
+            SponsorshipData::decode(&encoded[..100]),
+            Err(SponsorshipError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_v06_sponsorship() {
+        let (_, paymaster) = wallet();
+        let unsigned = v06_op(vec![]);
+        let data = sign(&unsigned, paymaster, 2_000, 1_000);
+        let op = v06_op([paymaster.as_slice(), &data.encode()].concat());
+
+        assert_eq!(verify_sponsorship(&op, paymaster, 1_500).unwrap(), data);
+    }
+
+    #[test]
+    fn test_verify_v07_sponsorship() {
+        let (_, paymaster) = wallet();
+        let unsigned = v07_op(paymaster, 100_000, vec![]);
+        let data = sign(&unsigned, paymaster, 2_000, 1_000);
+        let op = v07_op(paymaster, 100_000, data.encode());
+
+        assert_eq!(verify_sponsorship(&op, paymaster, 1_500).unwrap(), data);
+    }
+
+    #[test]
+    fn test_tampered_signature_rejected() {
+        let (_, paymaster) = wallet();
+        let data = sign(&v06_op(vec![]), paymaster, 2_000, 1_000);
+        let mut encoded = data.encode();
+        encoded[VALIDITY_WINDOW_LEN + SIGNER_LEN + 10] ^= 0xff;
+        let op = v06_op([paymaster.as_slice(), &encoded].concat());
+
+        assert!(matches!(
+            verify_sponsorship(&op, paymaster, 1_500),
+            Err(SponsorshipError::InvalidSignature(_)) | Err(SponsorshipError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_signer_must_be_trusted_and_match_signature() {
+        let (_, signer) = wallet();
+        let paymaster = address!("00000000000000000000000000000000000000b2");
+        let other = address!("00000000000000000000000000000000000000c3");
+        let data = sign(&v06_op(vec![]), paymaster, 2_000, 1_000);
+        let op = v06_op([paymaster.as_slice(), &data.encode()].concat());
+        assert_eq!(
+            verify_sponsorship_by(&op, paymaster, &[other, signer], 1_500).unwrap(),
+            data
+        );
+
+        // Signed by a key the paymaster does not trust
+        let err = verify_sponsorship_by(&op, paymaster, &[other], 1_500).unwrap_err();
+        assert!(
+            matches!(err, SponsorshipError::InvalidSignature(_)),
+            "{}",
+            err
+        );
+
+        // Naming another trusted key than the one that signed
+        let renamed = SponsorshipData {
+            signer: other,
+            ..data
+        };
+        let op = v06_op([paymaster.as_slice(), &renamed.encode()].concat());
+        let err = verify_sponsorship_by(&op, paymaster, &[other, signer], 1_500).unwrap_err();
+        assert!(
+            matches!(err, SponsorshipError::InvalidSignature(_)),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_changed_operation_rejected() {
+        let (_, paymaster) = wallet();
+        let data = sign(&v07_op(paymaster, 100_000, vec![]), paymaster, 2_000, 1_000);
+
+        // Signature was produced for different paymaster gas limits
+        let op = v07_op(paymaster, 500_000, data.encode());
+
+        assert!(matches!(
+            verify_sponsorship(&op, paymaster, 1_500),
+            Err(SponsorshipError::InvalidSignature(_))
+        ));
+    }
+
+    #[test]
+    fn test_validity_window() {
+        let (_, paymaster) = wallet();
+        let data = sign(&v06_op(vec![]), paymaster, 2_000, 1_000);
+        let op = v06_op([paymaster.as_slice(), &data.encode()].concat());
+
+        let expired = verify_sponsorship(&op, paymaster, 2_000).unwrap_err();
+        assert!(matches!(expired, SponsorshipError::Expired { .. }));
+        assert!(expired.is_out_of_time_range());
+
+        let early = verify_sponsorship(&op, paymaster, 999).unwrap_err();
+        assert!(matches!(early, SponsorshipError::NotYetValid { .. }));
+    }
+
+    #[test]
+    fn test_max_gas_cost_includes_aggregator_gas() {
+        let op = v06_op(vec![]);
+        assert_eq!(sponsored_max_gas_cost(&op), op.max_gas_cost());
+
+        let costs = AggregatorCosts {
+            execution_fixed_gas: 100_000,
+            execution_variable_gas: 5_000,
+            sig_fixed_length: 64,
+            sig_variable_length: 0,
+        };
+        let aggregated = op.clone().transform_for_aggregator(
+            &ChainSpec::default(),
+            address!("00000000000000000000000000000000000000a9"),
+            costs,
+            Bytes::new(),
+        );
+        assert_eq!(
+            sponsored_max_gas_cost(&aggregated),
+            op.max_gas_cost() + U256::from(2_000_000_000u128 * 5_000)
+        );
+    }
+}
@@ -0,0 +1,1039 @@
+// paymaster-relay/src/sponsorship_records.rs
+// Durable history of sponsorships and what became of them on chain. Writes
+// go through a bounded queue to a writer thread, off the sponsorship path.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::{Address, B256};
+use async_trait::async_trait;
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+use tracing::{debug, info, warn};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Appended lines tolerated beyond twice the live records before the file is compacted
+const COMPACT_SLACK_LINES: usize = 1024;
+
+/// Records per pm_listSponsorships page when the caller does not ask
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Largest page pm_listSponsorships returns
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// Sponsorship record configuration (`[paymaster_relay.sponsorship_records]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SponsorshipRecordConfig {
+    /// Record sponsorships and track their outcome
+    pub enabled: bool,
+    /// JSON lines file records are appended to and replayed from; memory only when unset
+    pub path: Option<PathBuf>,
+    /// Writes waiting for the writer before new ones are dropped
+    pub queue_capacity: usize,
+    /// Seconds between reconciliations of unresolved records against EntryPoint logs
+    pub reconcile_interval_seconds: u64,
+    /// Seconds past validUntil before an operation that was not mined is expired
+    pub expiry_grace_seconds: u64,
+    /// Records sponsored longer ago than this many days are dropped on compaction
+    pub retention_days: u64,
+}
+
+impl Default for SponsorshipRecordConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+            queue_capacity: 4096,
+            reconcile_interval_seconds: 30,
+            expiry_grace_seconds: 120,
+            retention_days: 30,
+        }
+    }
+}
+
+/// What became of a sponsored operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SponsorshipStatus {
+    /// Signed, not seen on chain yet
+    Pending,
+    /// Included by a UserOperationEvent
+    Mined,
+    /// Removed from the mempool without being mined; still mined if resubmitted
+    /// before it expires
+    Dropped,
+    /// Not mined before validUntil
+    Expired,
+}
+
+impl SponsorshipStatus {
+    /// Name used in RPC params and results
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SponsorshipStatus::Pending => "pending",
+            SponsorshipStatus::Mined => "mined",
+            SponsorshipStatus::Dropped => "dropped",
+            SponsorshipStatus::Expired => "expired",
+        }
+    }
+}
+
+impl fmt::Display for SponsorshipStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SponsorshipStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(SponsorshipStatus::Pending),
+            "mined" => Ok(SponsorshipStatus::Mined),
+            "dropped" => Ok(SponsorshipStatus::Dropped),
+            "expired" => Ok(SponsorshipStatus::Expired),
+            _ => Err(format!(
+                "Unknown status {:?}, expected pending, mined, dropped or expired",
+                s
+            )),
+        }
+    }
+}
+
+/// Where a sponsored operation was mined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinedUserOperation {
+    /// Bundle transaction carrying the operation
+    pub transaction_hash: B256,
+    /// Block containing the transaction
+    pub block_number: u64,
+    /// Whether the operation executed successfully, unknown from builder events
+    pub success: Option<bool>,
+}
+
+impl MinedUserOperation {
+    /// Read from an eth_getUserOperationReceipt result
+    pub fn from_receipt(receipt: &Value) -> Option<Self> {
+        let transaction = &receipt["receipt"];
+        let transaction_hash = transaction["transactionHash"].as_str()?.parse().ok()?;
+        let block_number = match &transaction["blockNumber"] {
+            Value::String(s) => u64::from_str_radix(s.strip_prefix("0x")?, 16).ok()?,
+            value => value.as_u64()?,
+        };
+        Some(Self {
+            transaction_hash,
+            block_number,
+            success: receipt["success"].as_bool(),
+        })
+    }
+}
+
+/// One sponsorship, served by pm_getSponsorshipRecord and pm_listSponsorships
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SponsorshipRecord {
+    /// Hash of the operation as sponsored
+    pub user_op_hash: B256,
+    pub sender: Address,
+    /// Policy that allowed the operation
+    pub policy_id: Option<String>,
+    pub chain_id: u64,
+    pub entry_point: Address,
+    /// Validity window signed into the paymaster data
+    pub valid_after: u64,
+    pub valid_until: u64,
+    /// Unix time of the sponsorship
+    pub sponsored_at: u64,
+    /// Unix time of the latest status change
+    pub updated_at: u64,
+    pub status: SponsorshipStatus,
+    /// Why the operation was dropped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Bundle transaction, set once mined
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction_hash: Option<B256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    /// Whether the mined operation executed successfully, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub success: Option<bool>,
+}
+
+impl SponsorshipRecord {
+    /// Whether the reconciler still looks for the operation on chain at `now`
+    ///
+    /// Dropped operations are looked for until they expire, since the sender
+    /// may resubmit them.
+    pub fn is_unresolved(&self, now: u64, expiry_grace_seconds: u64) -> bool {
+        match self.status {
+            SponsorshipStatus::Pending => true,
+            SponsorshipStatus::Dropped => {
+                now <= self.valid_until.saturating_add(expiry_grace_seconds)
+            }
+            SponsorshipStatus::Mined | SponsorshipStatus::Expired => false,
+        }
+    }
+
+    /// Apply `update` at `now`, returning whether the record changed
+    ///
+    /// Mined is final; dropped and expired only replace pending, so updates
+    /// arriving late or more than once are harmless.
+    pub fn apply(&mut self, update: &StatusUpdate, now: u64) -> bool {
+        match (self.status, update) {
+            // A receipt tells whether an operation seen in a landed bundle succeeded
+            (SponsorshipStatus::Mined, StatusUpdate::Mined(mined))
+                if self.success.is_none() && mined.success.is_some() =>
+            {
+                self.success = mined.success;
+            }
+            (SponsorshipStatus::Mined, _) => return false,
+            (_, StatusUpdate::Mined(mined)) => {
+                self.status = SponsorshipStatus::Mined;
+                self.reason = None;
+                self.transaction_hash = Some(mined.transaction_hash);
+                self.block_number = Some(mined.block_number);
+                self.success = mined.success;
+            }
+            (SponsorshipStatus::Pending, StatusUpdate::Dropped { reason }) => {
+                self.status = SponsorshipStatus::Dropped;
+                self.reason = Some(reason.clone());
+            }
+            (SponsorshipStatus::Pending, StatusUpdate::Expired) => {
+                self.status = SponsorshipStatus::Expired;
+            }
+            _ => return false,
+        }
+        self.updated_at = now;
+        true
+    }
+}
+
+/// Status change of a recorded sponsorship
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusUpdate {
+    /// A UserOperationEvent or a landed bundle included the operation
+    Mined(MinedUserOperation),
+    /// The builder removed the operation from the mempool
+    Dropped { reason: String },
+    /// validUntil passed without the operation being mined
+    Expired,
+}
+
+/// Filter and page of pm_listSponsorships
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SponsorshipQuery {
+    /// Earliest sponsorship time, unix seconds
+    pub from: u64,
+    /// Latest sponsorship time, unix seconds
+    pub to: u64,
+    pub status: Option<SponsorshipStatus>,
+    /// Zero-based page number
+    pub page: usize,
+    pub page_size: usize,
+}
+
+/// One page of records, oldest first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SponsorshipPage {
+    pub records: Vec<SponsorshipRecord>,
+    /// Records matching the filter across all pages
+    pub total: usize,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+/// Storage behind sponsorship records
+///
+/// [`FileSponsorshipStore`] is the default; an embedded database only has to
+/// implement these methods. Writes come from a single writer thread.
+pub trait SponsorshipStore: Send + Sync + fmt::Debug {
+    /// Insert or replace the record of `record.user_op_hash`
+    ///
+    /// The record is readable even when persisting it fails.
+    fn put(&self, record: SponsorshipRecord) -> io::Result<()>;
+
+    /// Record of one operation
+    fn get(&self, user_op_hash: B256) -> Option<SponsorshipRecord>;
+
+    /// Records matching `query`, oldest first
+    fn list(&self, query: &SponsorshipQuery) -> SponsorshipPage;
+
+    /// Pending and dropped records, which may still be mined
+    fn unresolved(&self) -> Vec<SponsorshipRecord>;
+}
+
+#[derive(Debug, Default)]
+struct FileState {
+    records: HashMap<B256, SponsorshipRecord>,
+    file: Option<File>,
+    /// Lines in the file, including superseded ones
+    lines: usize,
+    /// A write failed, the file no longer holds every record
+    dirty: bool,
+}
+
+/// [`SponsorshipStore`] indexing records in memory over an append-only JSON
+/// lines file
+///
+/// Each write appends the full record; the latest line of a hash wins when
+/// the file is replayed on startup. The file is rewritten without superseded
+/// lines and expired records once it grows, and after a failed write, so a
+/// write that failed is persisted again by the next one.
+#[derive(Debug)]
+pub struct FileSponsorshipStore {
+    path: Option<PathBuf>,
+    retention_seconds: u64,
+    state: Mutex<FileState>,
+}
+
+impl FileSponsorshipStore {
+    /// Open the store, replaying the file at the configured path
+    ///
+    /// A torn last line, as left by a crash mid-write, is skipped.
+    pub fn open(config: &SponsorshipRecordConfig) -> io::Result<Self> {
+        let store = Self {
+            path: config.path.clone(),
+            retention_seconds: config.retention_days.saturating_mul(SECONDS_PER_DAY),
+            state: Mutex::new(FileState::default()),
+        };
+        let Some(path) = &store.path else {
+            return Ok(store);
+        };
+
+        let mut state = store.state.lock().unwrap();
+        match File::open(path) {
+            Ok(file) => {
+                let mut skipped = 0;
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    state.lines += 1;
+                    match serde_json::from_str::<SponsorshipRecord>(&line) {
+                        Ok(record) => {
+                            state.records.insert(record.user_op_hash, record);
+                        }
+                        Err(_) => skipped += 1,
+                    }
+                }
+                if skipped > 0 {
+                    warn!(
+                        "Skipped {} unreadable sponsorship records in {}",
+                        skipped,
+                        path.display()
+                    );
+                    state.dirty = true;
+                }
+                info!(
+                    "🧾 Restored {} sponsorship records from {}",
+                    state.records.len(),
+                    path.display()
+                );
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        store.compact(&mut state, unix_now())?;
+        drop(state);
+        Ok(store)
+    }
+
+    fn needs_compaction(state: &FileState) -> bool {
+        state.dirty || state.lines > 2 * state.records.len() + COMPACT_SLACK_LINES
+    }
+
+    /// Rewrite the file with one line per live record and reopen it for appending
+    fn compact(&self, state: &mut FileState, now: u64) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let cutoff = now.saturating_sub(self.retention_seconds);
+        state
+            .records
+            .retain(|_, record| record.sponsored_at >= cutoff);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let mut data = Vec::new();
+        for record in state.records.values() {
+            serde_json::to_writer(&mut data, record)?;
+            data.push(b'\n');
+        }
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+
+        state.file = Some(OpenOptions::new().append(true).open(path)?);
+        state.lines = state.records.len();
+        state.dirty = false;
+        Ok(())
+    }
+}
+
+impl SponsorshipStore for FileSponsorshipStore {
+    fn put(&self, record: SponsorshipRecord) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        state.records.insert(record.user_op_hash, record);
+        if self.path.is_none() {
+            return Ok(());
+        }
+
+        if Self::needs_compaction(&state) {
+            let result = self.compact(&mut state, unix_now());
+            state.dirty = result.is_err();
+            return result;
+        }
+        let result = match state.file.as_mut() {
+            Some(file) => file.write_all(&line),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "sponsorship record file is not open",
+            )),
+        };
+        match result {
+            Ok(()) => state.lines += 1,
+            Err(_) => state.dirty = true,
+        }
+        result
+    }
+
+    fn get(&self, user_op_hash: B256) -> Option<SponsorshipRecord> {
+        self.state
+            .lock()
+            .unwrap()
+            .records
+            .get(&user_op_hash)
+            .cloned()
+    }
+
+    fn list(&self, query: &SponsorshipQuery) -> SponsorshipPage {
+        let state = self.state.lock().unwrap();
+        let mut matching: Vec<&SponsorshipRecord> = state
+            .records
+            .values()
+            .filter(|record| (query.from..=query.to).contains(&record.sponsored_at))
+            .filter(|record| query.status.map_or(true, |status| record.status == status))
+            .collect();
+        matching.sort_by_key(|record| (record.sponsored_at, record.user_op_hash));
+
+        let page_size = query.page_size.clamp(1, MAX_PAGE_SIZE);
+        SponsorshipPage {
+            total: matching.len(),
+            records: matching
+                .into_iter()
+                .skip(query.page.saturating_mul(page_size))
+                .take(page_size)
+                .cloned()
+                .collect(),
+            page: query.page,
+            page_size,
+        }
+    }
+
+    fn unresolved(&self) -> Vec<SponsorshipRecord> {
+        self.state
+            .lock()
+            .unwrap()
+            .records
+            .values()
+            .filter(|record| {
+                matches!(
+                    record.status,
+                    SponsorshipStatus::Pending | SponsorshipStatus::Dropped
+                )
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Source of the UserOperationEvent logs the reconciler looks for
+#[async_trait]
+pub trait UserOperationEventSource: Send + Sync {
+    /// Where the operation was mined, `None` if no UserOperationEvent exists
+    async fn find_user_operation_event(
+        &self,
+        user_op_hash: B256,
+    ) -> Result<Option<MinedUserOperation>, String>;
+}
+
+enum RecordWrite {
+    Sponsored(SponsorshipRecord),
+    Update {
+        user_op_hash: B256,
+        update: StatusUpdate,
+    },
+    Flush(oneshot::Sender<()>),
+}
+
+/// Sponsorship history: writes are queued for a writer thread, reads go
+/// straight to the store
+///
+/// A full queue drops the write and counts it in
+/// `paymaster_sponsorship_record_writes_dropped_total` rather than slowing
+/// sponsorship down.
+#[derive(Debug)]
+pub struct SponsorshipRecords {
+    config: SponsorshipRecordConfig,
+    store: Arc<dyn SponsorshipStore>,
+    writes: mpsc::Sender<RecordWrite>,
+    dropped_writes: AtomicU64,
+}
+
+impl SponsorshipRecords {
+    /// Open the file store at the configured path and start the writer
+    pub fn open(config: SponsorshipRecordConfig) -> io::Result<Self> {
+        let store = Arc::new(FileSponsorshipStore::open(&config)?);
+        Self::new(config, store)
+    }
+
+    /// Start the writer of `store`
+    pub fn new(
+        config: SponsorshipRecordConfig,
+        store: Arc<dyn SponsorshipStore>,
+    ) -> io::Result<Self> {
+        let (writes, queue) = mpsc::channel(config.queue_capacity.max(1));
+        let writer_store = store.clone();
+        std::thread::Builder::new()
+            .name("sponsorship-records".to_string())
+            .spawn(move || run_writer(writer_store, queue))?;
+        Ok(Self {
+            config,
+            store,
+            writes,
+            dropped_writes: AtomicU64::new(0),
+        })
+    }
+
+    fn enqueue(&self, write: RecordWrite) {
+        if let Err(e) = self.writes.try_send(write) {
+            self.dropped_writes.fetch_add(1, Ordering::Relaxed);
+            counter!("paymaster_sponsorship_record_writes_dropped_total").increment(1);
+            debug!("Dropped sponsorship record write: {}", e);
+        }
+    }
+
+    /// Record a new sponsorship; a record already kept for the hash is left as is
+    pub fn record_sponsorship(&self, record: SponsorshipRecord) {
+        self.enqueue(RecordWrite::Sponsored(record));
+    }
+
+    /// Apply `update` to the record of `user_op_hash`, if one is kept
+    pub fn update_status(&self, user_op_hash: B256, update: StatusUpdate) {
+        // Builder events and receipts also cover operations sponsored elsewhere
+        if self.store.get(user_op_hash).is_none() {
+            return;
+        }
+        self.enqueue(RecordWrite::Update {
+            user_op_hash,
+            update,
+        });
+    }
+
+    /// Record of one operation
+    pub fn get(&self, user_op_hash: B256) -> Option<SponsorshipRecord> {
+        self.store.get(user_op_hash)
+    }
+
+    /// Records matching `query`, oldest first
+    pub fn list(&self, query: &SponsorshipQuery) -> SponsorshipPage {
+        self.store.list(query)
+    }
+
+    /// Writes dropped because the queue was full
+    pub fn dropped_writes(&self) -> u64 {
+        self.dropped_writes.load(Ordering::Relaxed)
+    }
+
+    /// Wait until every write queued so far is applied
+    pub async fn flush(&self) {
+        let (done, applied) = oneshot::channel();
+        if self.writes.send(RecordWrite::Flush(done)).await.is_ok() {
+            let _ = applied.await;
+        }
+    }
+
+    /// Look for the UserOperationEvent of each unresolved record of `chain_id`,
+    /// marking found operations mined and pending ones past validUntil expired
+    ///
+    /// Returns the number of updates queued.
+    pub async fn reconcile(&self, chain_id: u64, source: &dyn UserOperationEventSource) -> usize {
+        let now = unix_now();
+        let grace = self.config.expiry_grace_seconds;
+        let mut updates = 0;
+        for record in self.store.unresolved() {
+            if record.chain_id != chain_id || !record.is_unresolved(now, grace) {
+                continue;
+            }
+            let update = match source.find_user_operation_event(record.user_op_hash).await {
+                Ok(Some(mined)) => StatusUpdate::Mined(mined),
+                Ok(None)
+                    if record.status == SponsorshipStatus::Pending
+                        && now > record.valid_until.saturating_add(grace) =>
+                {
+                    StatusUpdate::Expired
+                }
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!(
+                        "Failed to look up UserOperationEvent of {:#x}: {}",
+                        record.user_op_hash, e
+                    );
+                    continue;
+                }
+            };
+            self.enqueue(RecordWrite::Update {
+                user_op_hash: record.user_op_hash,
+                update,
+            });
+            updates += 1;
+        }
+        updates
+    }
+
+    /// Reconcile the records of `chain_id` every configured interval
+    pub fn spawn_reconciler(
+        self: &Arc<Self>,
+        chain_id: u64,
+        source: Arc<dyn UserOperationEventSource>,
+    ) -> JoinHandle<()> {
+        let records = self.clone();
+        let interval = Duration::from_secs(self.config.reconcile_interval_seconds.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let updates = records.reconcile(chain_id, source.as_ref()).await;
+                if updates > 0 {
+                    debug!(
+                        "Reconciled {} sponsorship records of chain {}",
+                        updates, chain_id
+                    );
+                }
+            }
+        })
+    }
+}
+
+/// Apply queued writes until every sender is dropped
+fn run_writer(store: Arc<dyn SponsorshipStore>, mut queue: mpsc::Receiver<RecordWrite>) {
+    while let Some(write) = queue.blocking_recv() {
+        let record = match write {
+            RecordWrite::Sponsored(record) => {
+                if store.get(record.user_op_hash).is_some() {
+                    continue;
+                }
+                record
+            }
+            RecordWrite::Update {
+                user_op_hash,
+                update,
+            } => {
+                let Some(mut record) = store.get(user_op_hash) else {
+                    continue;
+                };
+                if !record.apply(&update, unix_now()) {
+                    continue;
+                }
+                record
+            }
+            RecordWrite::Flush(done) => {
+                let _ = done.send(());
+                continue;
+            }
+        };
+        let user_op_hash = record.user_op_hash;
+        if let Err(e) = store.put(record) {
+            counter!("paymaster_sponsorship_record_write_errors_total").increment(1);
+            warn!(
+                "Failed to persist sponsorship record of {:#x}: {}",
+                user_op_hash, e
+            );
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{b256, Bytes, U256};
+    use rundler_types::{chain::ChainSpec, v0_7, UserOperation, UserOperationVariant};
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{
+        service::{PaymasterRelayService, SponsorOptions},
+        test_utils,
+    };
+
+    const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const BUNDLE_TX: B256 =
+        b256!("00000000000000000000000000000000000000000000000000000000000000aa");
+
+    fn create_service(records: Arc<SponsorshipRecords>) -> PaymasterRelayService {
+        test_utils::service(&format!("senders = [\"{}\"]\n", SENDER))
+            .with_sponsorship_records(records)
+    }
+
+    async fn sponsor(service: &PaymasterRelayService, nonce: u64) -> B256 {
+        let op = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender: Address::from_str(SENDER).unwrap(),
+                nonce: U256::from(nonce),
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 1_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        let entry_point = ChainSpec::default().entry_point_address_v0_7;
+        let result = service
+            .sponsor_user_operation(
+                UserOperationVariant::V0_7(op),
+                ethers::types::Address::from_slice(entry_point.as_slice()),
+                SponsorOptions {
+                    return_full_operation: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        result.sponsored_user_op.unwrap().hash()
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn config(path: Option<std::path::PathBuf>) -> SponsorshipRecordConfig {
+        SponsorshipRecordConfig {
+            enabled: true,
+            path,
+            ..Default::default()
+        }
+    }
+
+    fn record(user_op_hash: B256, valid_until: u64) -> SponsorshipRecord {
+        SponsorshipRecord {
+            user_op_hash,
+            sender: Address::from_str(SENDER).unwrap(),
+            policy_id: Some("default".to_string()),
+            chain_id: ChainSpec::default().id,
+            entry_point: ChainSpec::default().entry_point_address_v0_7,
+            valid_after: 0,
+            valid_until,
+            sponsored_at: now(),
+            updated_at: now(),
+            status: SponsorshipStatus::Pending,
+            reason: None,
+            transaction_hash: None,
+            block_number: None,
+            success: None,
+        }
+    }
+
+    fn list_all(service: &PaymasterRelayService) -> SponsorshipPage {
+        service
+            .list_sponsorships(&SponsorshipQuery {
+                from: 0,
+                to: now(),
+                status: None,
+                page: 0,
+                page_size: 100,
+            })
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_records_survive_restart() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("sponsorships.jsonl");
+
+        let records = Arc::new(SponsorshipRecords::open(config(Some(path.clone()))).unwrap());
+        let service = create_service(records.clone());
+        let mined = sponsor(&service, 0).await;
+        let pending = sponsor(&service, 1).await;
+        records.flush().await;
+        service.record_sponsorship_mined(
+            mined,
+            MinedUserOperation {
+                transaction_hash: BUNDLE_TX,
+                block_number: 42,
+                success: Some(true),
+            },
+        );
+        records.flush().await;
+        drop(service);
+        drop(records);
+
+        // A new service on the same file sees the records as they were last written
+        let records = Arc::new(SponsorshipRecords::open(config(Some(path))).unwrap());
+        let service = create_service(records);
+        let record = service.sponsorship_record(mined).unwrap().unwrap();
+        assert_eq!(record.status, SponsorshipStatus::Mined);
+        assert_eq!(record.transaction_hash, Some(BUNDLE_TX));
+        assert_eq!(record.block_number, Some(42));
+        assert_eq!(record.policy_id.as_deref(), Some("default"));
+        assert!(record.valid_until > record.valid_after);
+        let record = service.sponsorship_record(pending).unwrap().unwrap();
+        assert_eq!(record.status, SponsorshipStatus::Pending);
+
+        let page = list_all(&service);
+        assert_eq!(page.total, 2);
+        let page = service
+            .list_sponsorships(&SponsorshipQuery {
+                from: 0,
+                to: now(),
+                status: Some(SponsorshipStatus::Pending),
+                page: 0,
+                page_size: 100,
+            })
+            .unwrap();
+        assert_eq!(page.records.len(), 1);
+        assert_eq!(page.records[0].user_op_hash, pending);
+    }
+
+    #[tokio::test]
+    async fn test_torn_last_line_is_skipped() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("sponsorships.jsonl");
+        let store = FileSponsorshipStore::open(&config(Some(path.clone()))).unwrap();
+        let hash = B256::repeat_byte(1);
+        store.put(record(hash, now() + 600)).unwrap();
+        drop(store);
+
+        // A crash mid-write leaves half a line behind
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"{\"userOpHash\":\"0x02").unwrap();
+        drop(file);
+
+        let store = FileSponsorshipStore::open(&config(Some(path.clone()))).unwrap();
+        assert!(store.get(hash).is_some());
+        // The file is rewritten without the torn line
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 1);
+    }
+
+    /// Event source answering from a fixed set of mined operations
+    #[derive(Default)]
+    struct MockEvents {
+        mined: HashMap<B256, MinedUserOperation>,
+    }
+
+    #[async_trait]
+    impl UserOperationEventSource for MockEvents {
+        async fn find_user_operation_event(
+            &self,
+            user_op_hash: B256,
+        ) -> Result<Option<MinedUserOperation>, String> {
+            Ok(self.mined.get(&user_op_hash).cloned())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconciler_marks_mined_and_expired() {
+        let store = Arc::new(FileSponsorshipStore::open(&config(None)).unwrap());
+        let records = SponsorshipRecords::new(config(None), store.clone()).unwrap();
+        let chain_id = ChainSpec::default().id;
+
+        let mined = B256::repeat_byte(1);
+        let waiting = B256::repeat_byte(2);
+        let expired = B256::repeat_byte(3);
+        let other_chain = B256::repeat_byte(4);
+        for (hash, valid_until) in [(mined, now() + 600), (waiting, now() + 600)] {
+            store.put(record(hash, valid_until)).unwrap();
+        }
+        store.put(record(expired, now() - 3600)).unwrap();
+        store
+            .put(SponsorshipRecord {
+                chain_id: chain_id + 1,
+                ..record(other_chain, now() - 3600)
+            })
+            .unwrap();
+
+        let mut events = MockEvents::default();
+        events.mined.insert(
+            mined,
+            MinedUserOperation {
+                transaction_hash: BUNDLE_TX,
+                block_number: 7,
+                success: Some(false),
+            },
+        );
+        assert_eq!(records.reconcile(chain_id, &events).await, 2);
+        records.flush().await;
+
+        let record = records.get(mined).unwrap();
+        assert_eq!(record.status, SponsorshipStatus::Mined);
+        assert_eq!(record.transaction_hash, Some(BUNDLE_TX));
+        assert_eq!(record.success, Some(false));
+        assert_eq!(
+            records.get(waiting).unwrap().status,
+            SponsorshipStatus::Pending
+        );
+        assert_eq!(
+            records.get(expired).unwrap().status,
+            SponsorshipStatus::Expired
+        );
+        // Other chains are reconciled against their own logs
+        assert_eq!(
+            records.get(other_chain).unwrap().status,
+            SponsorshipStatus::Pending
+        );
+
+        // Nothing left to do for this chain until the waiting operation shows up
+        assert_eq!(records.reconcile(chain_id, &events).await, 0);
+    }
+
+    #[test]
+    fn test_status_transitions() {
+        let mut pending = record(B256::repeat_byte(1), now() + 600);
+        let mined = StatusUpdate::Mined(MinedUserOperation {
+            transaction_hash: BUNDLE_TX,
+            block_number: 7,
+            success: None,
+        });
+
+        assert!(pending.apply(
+            &StatusUpdate::Dropped {
+                reason: "rejected".to_string()
+            },
+            now()
+        ));
+        assert_eq!(pending.status, SponsorshipStatus::Dropped);
+        assert!(pending.is_unresolved(now(), 0));
+        // A dropped operation resubmitted by the sender can still be mined
+        assert!(pending.apply(&mined, now()));
+        assert_eq!(pending.status, SponsorshipStatus::Mined);
+        assert_eq!(pending.reason, None);
+        // Mined is final, repeated updates change nothing
+        assert!(!pending.apply(&mined, now()));
+        assert!(!pending.apply(&StatusUpdate::Expired, now()));
+        assert!(!pending.is_unresolved(now(), 0));
+    }
+
+    #[test]
+    fn test_mined_from_receipt() {
+        let receipt = serde_json::json!({
+            "success": true,
+            "receipt": {
+                "transactionHash": format!("{:#x}", BUNDLE_TX),
+                "blockNumber": "0x2a",
+            }
+        });
+        assert_eq!(
+            MinedUserOperation::from_receipt(&receipt),
+            Some(MinedUserOperation {
+                transaction_hash: BUNDLE_TX,
+                block_number: 42,
+                success: Some(true),
+            })
+        );
+        assert_eq!(
+            MinedUserOperation::from_receipt(&serde_json::json!({"success": true})),
+            None
+        );
+    }
+
+    /// Store whose writes wait until the gate is released
+    #[derive(Debug)]
+    struct GatedStore {
+        inner: FileSponsorshipStore,
+        gate: Mutex<()>,
+    }
+
+    impl SponsorshipStore for GatedStore {
+        fn put(&self, record: SponsorshipRecord) -> std::io::Result<()> {
+            let _open = self.gate.lock().unwrap();
+            self.inner.put(record)
+        }
+
+        fn get(&self, user_op_hash: B256) -> Option<SponsorshipRecord> {
+            self.inner.get(user_op_hash)
+        }
+
+        fn list(&self, query: &SponsorshipQuery) -> SponsorshipPage {
+            self.inner.list(query)
+        }
+
+        fn unresolved(&self) -> Vec<SponsorshipRecord> {
+            self.inner.unresolved()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_drops_writes() {
+        let store = Arc::new(GatedStore {
+            inner: FileSponsorshipStore::open(&config(None)).unwrap(),
+            gate: Mutex::new(()),
+        });
+        let records = SponsorshipRecords::new(
+            SponsorshipRecordConfig {
+                queue_capacity: 1,
+                ..config(None)
+            },
+            store.clone(),
+        )
+        .unwrap();
+
+        let gate = store.gate.lock().unwrap();
+        for byte in 1..=4 {
+            records.record_sponsorship(record(B256::repeat_byte(byte), now() + 600));
+        }
+        // The writer holds at most one write and the queue one more
+        assert!(records.dropped_writes() >= 2);
+        drop(gate);
+
+        records.flush().await;
+        let written = (1..=4)
+            .filter(|&byte| records.get(B256::repeat_byte(byte)).is_some())
+            .count() as u64;
+        assert_eq!(written + records.dropped_writes(), 4);
+    }
+}
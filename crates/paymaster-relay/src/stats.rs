@@ -0,0 +1,669 @@
+// paymaster-relay/src/stats.rs
+// Request statistics with bounded memory: fixed-bucket latency histograms, per-key maps capped
+// by recency, and lifetime totals reported next to the numbers of the last interval.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+/// Upper bounds of the latency buckets in milliseconds; slower requests land in an overflow bucket
+pub const LATENCY_BUCKETS_MS: [u64; 13] = [
+    1, 2, 5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000,
+];
+
+const BUCKETS: usize = LATENCY_BUCKETS_MS.len() + 1;
+
+/// Lock shards of a [`BoundedMap`]
+const SHARDS: usize = 16;
+
+/// Fewest keys per shard, so that small maps evict by recency across all their keys
+const MIN_SHARD_CAPACITY: usize = 4;
+
+/// Latency distribution in fixed buckets, recorded without locks
+#[derive(Debug, Default)]
+pub struct LatencyHistogram {
+    buckets: [AtomicU64; BUCKETS],
+    sum_micros: AtomicU64,
+    max_micros: AtomicU64,
+}
+
+impl LatencyHistogram {
+    /// Count one request taking `latency`
+    pub fn record(&self, latency: Duration) {
+        let micros = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| micros <= bound * 1_000)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+        self.max_micros.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    /// Distribution recorded so far
+    pub fn snapshot(&self) -> LatencySnapshot {
+        LatencySnapshot::from_parts(
+            self.buckets
+                .iter()
+                .map(|bucket| bucket.load(Ordering::Relaxed))
+                .collect(),
+            self.sum_micros.load(Ordering::Relaxed),
+            self.max_micros.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Distribution recorded so far, starting over from zero
+    ///
+    /// A request recorded concurrently is counted in this snapshot or in the
+    /// next one, never in both and never in neither.
+    pub fn take(&self) -> LatencySnapshot {
+        LatencySnapshot::from_parts(
+            self.buckets
+                .iter()
+                .map(|bucket| bucket.swap(0, Ordering::Relaxed))
+                .collect(),
+            self.sum_micros.swap(0, Ordering::Relaxed),
+            self.max_micros.swap(0, Ordering::Relaxed),
+        )
+    }
+}
+
+/// Latency distribution at one point in time
+///
+/// Percentiles are estimated as the upper bound of the bucket holding them,
+/// capped at the slowest request seen.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencySnapshot {
+    /// Requests recorded
+    pub count: u64,
+    /// Mean latency in milliseconds
+    pub avg_ms: f64,
+    /// Slowest request in milliseconds
+    pub max_ms: f64,
+    /// Median latency in milliseconds
+    pub p50_ms: f64,
+    /// 90th percentile latency in milliseconds
+    pub p90_ms: f64,
+    /// 99th percentile latency in milliseconds
+    pub p99_ms: f64,
+    /// Requests per bucket, aligned with [`LATENCY_BUCKETS_MS`] plus the overflow bucket
+    pub buckets: Vec<u64>,
+    #[serde(skip)]
+    sum_micros: u64,
+    #[serde(skip)]
+    max_micros: u64,
+}
+
+impl Default for LatencySnapshot {
+    fn default() -> Self {
+        Self::from_parts(vec![0; BUCKETS], 0, 0)
+    }
+}
+
+impl LatencySnapshot {
+    fn from_parts(buckets: Vec<u64>, sum_micros: u64, max_micros: u64) -> Self {
+        let count: u64 = buckets.iter().sum();
+        let max_ms = max_micros as f64 / 1_000.0;
+        let quantile = |q: f64| -> f64 {
+            let rank = (q * count as f64).ceil().max(1.0) as u64;
+            let mut seen = 0;
+            for (bucket, requests) in buckets.iter().enumerate() {
+                seen += requests;
+                if seen >= rank {
+                    return LATENCY_BUCKETS_MS
+                        .get(bucket)
+                        .map_or(max_ms, |bound| (*bound as f64).min(max_ms));
+                }
+            }
+            0.0
+        };
+        Self {
+            count,
+            avg_ms: if count == 0 {
+                0.0
+            } else {
+                sum_micros as f64 / 1_000.0 / count as f64
+            },
+            max_ms,
+            p50_ms: quantile(0.5),
+            p90_ms: quantile(0.9),
+            p99_ms: quantile(0.99),
+            buckets,
+            sum_micros,
+            max_micros,
+        }
+    }
+
+    /// Distribution of the requests of both snapshots
+    pub fn merge(&self, other: &LatencySnapshot) -> LatencySnapshot {
+        LatencySnapshot::from_parts(
+            self.buckets
+                .iter()
+                .zip(&other.buckets)
+                .map(|(a, b)| a + b)
+                .collect(),
+            self.sum_micros + other.sum_micros,
+            self.max_micros.max(other.max_micros),
+        )
+    }
+}
+
+/// Outcome counts and latencies of requests
+#[derive(Debug, Default)]
+pub struct RequestCounters {
+    successful: AtomicU64,
+    failed: AtomicU64,
+    latency: LatencyHistogram,
+}
+
+impl RequestCounters {
+    /// Count one request
+    pub fn record(&self, success: bool, latency: Duration) {
+        if success {
+            self.successful.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.failed.fetch_add(1, Ordering::Relaxed);
+        }
+        self.latency.record(latency);
+    }
+
+    /// Counts recorded so far
+    pub fn snapshot(&self) -> RequestSnapshot {
+        RequestSnapshot::new(
+            self.successful.load(Ordering::Relaxed),
+            self.failed.load(Ordering::Relaxed),
+            self.latency.snapshot(),
+        )
+    }
+
+    /// Counts recorded so far, starting over from zero
+    pub fn take(&self) -> RequestSnapshot {
+        RequestSnapshot::new(
+            self.successful.swap(0, Ordering::Relaxed),
+            self.failed.swap(0, Ordering::Relaxed),
+            self.latency.take(),
+        )
+    }
+}
+
+/// Request counts at one point in time
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RequestSnapshot {
+    /// Requests recorded
+    pub total_requests: u64,
+    /// Requests that succeeded
+    pub successful_requests: u64,
+    /// Requests that failed
+    pub failed_requests: u64,
+    /// Latency distribution of all requests
+    pub latency: LatencySnapshot,
+}
+
+impl RequestSnapshot {
+    fn new(successful: u64, failed: u64, latency: LatencySnapshot) -> Self {
+        Self {
+            total_requests: successful + failed,
+            successful_requests: successful,
+            failed_requests: failed,
+            latency,
+        }
+    }
+
+    /// Counts of the requests of both snapshots
+    pub fn merge(&self, other: &RequestSnapshot) -> RequestSnapshot {
+        RequestSnapshot::new(
+            self.successful_requests + other.successful_requests,
+            self.failed_requests + other.failed_requests,
+            self.latency.merge(&other.latency),
+        )
+    }
+}
+
+struct Entry<V> {
+    last_used: u64,
+    value: V,
+}
+
+/// Per-key values holding at most `capacity` keys
+///
+/// Keys are spread over lock shards so recorders of different keys rarely
+/// contend. When a new key arrives at a full shard, the least recently updated
+/// key of that shard is evicted and counted, so churning keys cannot grow the
+/// map.
+pub struct BoundedMap<V> {
+    shards: Vec<Mutex<HashMap<String, Entry<V>>>>,
+    shard_capacity: usize,
+    tick: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl<V: Default> BoundedMap<V> {
+    /// Create a map holding at most `capacity` keys, at least one
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        let shards = SHARDS.min(capacity / MIN_SHARD_CAPACITY).max(1);
+        Self {
+            shards: (0..shards).map(|_| Mutex::new(HashMap::new())).collect(),
+            shard_capacity: capacity / shards,
+            tick: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    fn shard(&self, key: &str) -> &Mutex<HashMap<String, Entry<V>>> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+
+    /// Apply `update` to the value of `key`, inserting a default value first
+    /// when the key is new
+    pub fn update<R>(&self, key: &str, update: impl FnOnce(&mut V) -> R) -> R {
+        let tick = self.tick.fetch_add(1, Ordering::Relaxed);
+        let mut shard = self.shard(key).lock().unwrap();
+        if !shard.contains_key(key) && shard.len() >= self.shard_capacity {
+            let oldest = shard
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(oldest, _)| oldest.clone());
+            if let Some(oldest) = oldest {
+                shard.remove(&oldest);
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        let entry = shard.entry(key.to_string()).or_insert_with(|| Entry {
+            last_used: tick,
+            value: V::default(),
+        });
+        entry.last_used = tick;
+        update(&mut entry.value)
+    }
+
+    /// `read` of every value, sorted by key
+    pub fn snapshot<S>(&self, read: impl Fn(&V) -> S) -> Vec<(String, S)> {
+        let mut snapshot: Vec<(String, S)> = self
+            .shards
+            .iter()
+            .flat_map(|shard| {
+                let shard = shard.lock().unwrap();
+                shard
+                    .iter()
+                    .map(|(key, entry)| (key.clone(), read(&entry.value)))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        snapshot.sort_by(|a, b| a.0.cmp(&b.0));
+        snapshot
+    }
+
+    /// Remove every key, returning the values sorted by key
+    ///
+    /// Each shard is emptied under its lock, so an update lands either in the
+    /// returned values or in the emptied map.
+    pub fn drain(&self) -> Vec<(String, V)> {
+        let mut drained: Vec<(String, V)> = self
+            .shards
+            .iter()
+            .flat_map(|shard| {
+                let mut shard = shard.lock().unwrap();
+                shard
+                    .drain()
+                    .map(|(key, entry)| (key, entry.value))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        drained.sort_by(|a, b| a.0.cmp(&b.0));
+        drained
+    }
+
+    /// Keys held
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+
+    /// Whether no key is held
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Keys evicted to stay within capacity since the map was created
+    pub fn evictions(&self) -> u64 {
+        self.evictions.load(Ordering::Relaxed)
+    }
+}
+
+impl<V: Default> std::fmt::Debug for BoundedMap<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoundedMap")
+            .field("len", &self.len())
+            .field("evictions", &self.evictions())
+            .finish()
+    }
+}
+
+/// Counts of the requests of one closed interval
+#[derive(Debug, Clone, Serialize)]
+pub struct IntervalSnapshot {
+    /// Length of the interval in seconds
+    pub seconds: f64,
+    /// Requests recorded during the interval
+    pub requests: RequestSnapshot,
+}
+
+/// Lifetime request counts with per-interval counts next to them
+///
+/// Every request is counted in the lifetime totals and in the current
+/// interval. [`IntervalRequestStats::snapshot_and_reset`] closes the current
+/// interval and starts the next one; [`IntervalRequestStats::rotate_if_due`]
+/// does so once the interval has lasted `interval`, so that readers see the
+/// last complete interval without resetting it for each other.
+pub struct IntervalRequestStats {
+    lifetime: RequestCounters,
+    current: RequestCounters,
+    interval: Duration,
+    started: Mutex<Instant>,
+    last: Mutex<Option<IntervalSnapshot>>,
+}
+
+impl IntervalRequestStats {
+    /// Create stats closing an interval every `interval` when rotated
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            lifetime: RequestCounters::default(),
+            current: RequestCounters::default(),
+            interval,
+            started: Mutex::new(Instant::now()),
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Count one request
+    pub fn record(&self, success: bool, latency: Duration) {
+        self.lifetime.record(success, latency);
+        self.current.record(success, latency);
+    }
+
+    /// Counts since the stats were created
+    pub fn lifetime(&self) -> RequestSnapshot {
+        self.lifetime.snapshot()
+    }
+
+    /// Counts of the interval in progress
+    pub fn current(&self) -> RequestSnapshot {
+        self.current.snapshot()
+    }
+
+    /// Last interval closed, `None` before the first one
+    pub fn last_interval(&self) -> Option<IntervalSnapshot> {
+        self.last.lock().unwrap().clone()
+    }
+
+    /// Close the interval in progress, returning its counts; lifetime totals are kept
+    pub fn snapshot_and_reset(&self) -> IntervalSnapshot {
+        let mut started = self.started.lock().unwrap();
+        self.close(&mut started)
+    }
+
+    /// Close the interval in progress when it has lasted the configured
+    /// length, returning the last closed interval
+    pub fn rotate_if_due(&self) -> Option<IntervalSnapshot> {
+        let mut started = self.started.lock().unwrap();
+        if started.elapsed() >= self.interval {
+            Some(self.close(&mut started))
+        } else {
+            self.last_interval()
+        }
+    }
+
+    fn close(&self, started: &mut Instant) -> IntervalSnapshot {
+        let snapshot = IntervalSnapshot {
+            seconds: started.elapsed().as_secs_f64(),
+            requests: self.current.take(),
+        };
+        *started = Instant::now();
+        *self.last.lock().unwrap() = Some(snapshot.clone());
+        snapshot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::*;
+    use crate::swagger::SwaggerMetrics;
+
+    const TASKS: u64 = 16;
+    const PER_TASK: u64 = 1_000;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_histogram_counts_every_request_across_takes() {
+        let histogram = Arc::new(LatencyHistogram::default());
+        let recorders: Vec<_> = (0..TASKS)
+            .map(|task| {
+                let histogram = histogram.clone();
+                tokio::spawn(async move {
+                    for i in 0..PER_TASK {
+                        histogram.record(Duration::from_micros((task * PER_TASK + i) * 7));
+                        if i % 100 == 0 {
+                            tokio::task::yield_now().await;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        // Take snapshots while the recorders run; no request may be lost or double counted
+        let mut taken = 0;
+        while !recorders.iter().all(|recorder| recorder.is_finished()) {
+            let snapshot = histogram.take();
+            assert_eq!(snapshot.buckets.iter().sum::<u64>(), snapshot.count);
+            taken += snapshot.count;
+            tokio::task::yield_now().await;
+        }
+        for recorder in recorders {
+            recorder.await.unwrap();
+        }
+        taken += histogram.take().count;
+
+        assert_eq!(taken, TASKS * PER_TASK);
+        assert_eq!(histogram.snapshot().count, 0);
+    }
+
+    #[test]
+    fn test_histogram_percentiles_and_average() {
+        let histogram = LatencyHistogram::default();
+        for _ in 0..98 {
+            histogram.record(Duration::from_millis(3));
+        }
+        histogram.record(Duration::from_millis(400));
+        histogram.record(Duration::from_secs(30));
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count, 100);
+        assert_eq!(snapshot.buckets.len(), LATENCY_BUCKETS_MS.len() + 1);
+        assert_eq!(snapshot.buckets[LATENCY_BUCKETS_MS.len()], 1);
+        assert_eq!(snapshot.p50_ms, 5.0);
+        assert_eq!(snapshot.p99_ms, 500.0);
+        assert_eq!(snapshot.max_ms, 30_000.0);
+        assert!((snapshot.avg_ms - (98.0 * 3.0 + 400.0 + 30_000.0) / 100.0).abs() < 1e-9);
+        assert_eq!(snapshot.merge(&snapshot).count, 200);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_bounded_map_stays_bounded_when_keys_churn() {
+        let map = Arc::new(BoundedMap::<u64>::new(64));
+        let recorders: Vec<_> = (0..TASKS)
+            .map(|task| {
+                let map = map.clone();
+                tokio::spawn(async move {
+                    for i in 0..PER_TASK {
+                        map.update(&format!("key-{}-{}", task, i), |count| *count += 1);
+                        assert!(map.len() <= 64);
+                    }
+                })
+            })
+            .collect();
+        for recorder in recorders {
+            recorder.await.unwrap();
+        }
+
+        // Every key was new once: it is either still held or was evicted
+        assert!(map.len() <= 64);
+        assert_eq!(map.len() as u64 + map.evictions(), TASKS * PER_TASK);
+        let held: u64 = map.snapshot(|count| *count).iter().map(|(_, c)| c).sum();
+        assert_eq!(held, map.len() as u64);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_bounded_map_counts_shared_keys_exactly() {
+        let map = Arc::new(BoundedMap::<u64>::new(64));
+        let recorders: Vec<_> = (0..TASKS)
+            .map(|_| {
+                let map = map.clone();
+                tokio::spawn(async move {
+                    for i in 0..PER_TASK {
+                        map.update(&format!("method-{}", i % 8), |count| *count += 1);
+                    }
+                })
+            })
+            .collect();
+
+        let mut drained = 0;
+        for _ in 0..10 {
+            drained += map.drain().iter().map(|(_, count)| count).sum::<u64>();
+            tokio::task::yield_now().await;
+        }
+        for recorder in recorders {
+            recorder.await.unwrap();
+        }
+        drained += map.drain().iter().map(|(_, count)| count).sum::<u64>();
+
+        assert_eq!(drained, TASKS * PER_TASK);
+        assert_eq!(map.evictions(), 0);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_bounded_map_evicts_least_recently_updated() {
+        let map = BoundedMap::<u64>::new(3);
+        for key in ["a", "b", "c"] {
+            map.update(key, |count| *count += 1);
+        }
+        map.update("a", |count| *count += 1);
+        map.update("d", |count| *count += 1);
+
+        let keys: Vec<String> = map.snapshot(|_| ()).into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec!["a", "c", "d"]);
+        assert_eq!(map.evictions(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_intervals_add_up_to_lifetime() {
+        let stats = Arc::new(IntervalRequestStats::new(Duration::from_secs(60)));
+        let recorders: Vec<_> = (0..TASKS)
+            .map(|task| {
+                let stats = stats.clone();
+                tokio::spawn(async move {
+                    for i in 0..PER_TASK {
+                        stats.record((task + i) % 4 != 0, Duration::from_millis(i % 20));
+                    }
+                })
+            })
+            .collect();
+
+        let mut intervals = Vec::new();
+        while !recorders.iter().all(|recorder| recorder.is_finished()) {
+            intervals.push(stats.snapshot_and_reset().requests);
+            tokio::task::yield_now().await;
+        }
+        for recorder in recorders {
+            recorder.await.unwrap();
+        }
+        intervals.push(stats.snapshot_and_reset().requests);
+
+        let summed = intervals
+            .iter()
+            .fold(RequestSnapshot::default(), |sum, interval| {
+                sum.merge(interval)
+            });
+        let lifetime = stats.lifetime();
+        assert_eq!(lifetime.total_requests, TASKS * PER_TASK);
+        assert_eq!(summed.total_requests, lifetime.total_requests);
+        assert_eq!(summed.failed_requests, lifetime.failed_requests);
+        assert_eq!(summed.latency.count, lifetime.latency.count);
+        assert_eq!(stats.current().total_requests, 0);
+        assert_eq!(
+            stats.last_interval().unwrap().requests,
+            *intervals.last().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_interval_rotates_once_due() {
+        let stats = IntervalRequestStats::new(Duration::from_secs(3600));
+        stats.record(true, Duration::from_millis(1));
+        assert!(stats.rotate_if_due().is_none());
+        assert_eq!(stats.current().total_requests, 1);
+
+        let stats = IntervalRequestStats::new(Duration::ZERO);
+        stats.record(true, Duration::from_millis(1));
+        assert_eq!(stats.rotate_if_due().unwrap().requests.total_requests, 1);
+        assert_eq!(stats.current().total_requests, 0);
+        assert_eq!(stats.lifetime().total_requests, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_swagger_metrics_report_per_endpoint_histograms() {
+        let metrics = SwaggerMetrics::with_limits(8, Duration::from_secs(60));
+        let recorders: Vec<_> = (0..TASKS)
+            .map(|task| {
+                let metrics = metrics.clone();
+                tokio::spawn(async move {
+                    for i in 0..PER_TASK {
+                        metrics.record_request(
+                            &format!("/endpoint/{}", (task * PER_TASK + i) % 32),
+                            true,
+                            Duration::from_millis(10 + 20 * (i % 2)),
+                        );
+                    }
+                })
+            })
+            .collect();
+        for recorder in recorders {
+            recorder.await.unwrap();
+        }
+
+        let stats = metrics.get_stats();
+        assert_eq!(stats["total_requests"], TASKS * PER_TASK);
+        assert_eq!(stats["avg_response_time_ms"], 20.0);
+
+        let statistics = metrics.statistics();
+        assert_eq!(statistics["lifetime"]["total_requests"], TASKS * PER_TASK);
+        assert_eq!(
+            statistics["current_interval"]["total_requests"],
+            TASKS * PER_TASK
+        );
+        assert!(statistics["last_interval"].is_null());
+        assert!(statistics["endpoints"].as_object().unwrap().len() <= 8);
+        assert!(statistics["evicted_endpoints"].as_u64().unwrap() > 0);
+
+        let interval = metrics.snapshot_and_reset();
+        assert_eq!(interval.requests.total_requests, TASKS * PER_TASK);
+        assert_eq!(
+            metrics.statistics()["current_interval"]["total_requests"],
+            0
+        );
+    }
+}
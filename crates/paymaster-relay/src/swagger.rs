@@ -3,25 +3,21 @@
 
 use std::{
     net::SocketAddr,
-    sync::{
-        atomic::{AtomicU64, Ordering},
-        Arc,
-    },
+    sync::Arc,
     time::{Duration, Instant},
 };
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::{Html, IntoResponse, Json, Response},
     routing::{get, post},
     Router,
 };
-// Prometheus monitoring removed - using Rundler's existing metrics system
+use metrics_exporter_prometheus::PrometheusHandle;
 use reqwest;
+use serde::Deserialize;
 use serde_json::json;
-use tower::ServiceBuilder;
-use tower_http::cors::{Any, CorsLayer};
 use tracing::info;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
@@ -29,60 +25,99 @@ use utoipa_swagger_ui::SwaggerUi;
 use crate::{
     api_docs::{ApiDoc, ErrorResponse, SponsorUserOperationRequest, SponsorUserOperationResponse},
     api_schemas::examples,
+    cors::{CorsConfig, CorsConfigError},
+    dashboard::{DashboardBalance, DashboardPolicies, DashboardTransactions},
+    stats::{BoundedMap, IntervalRequestStats, IntervalSnapshot, RequestCounters},
     PaymasterRelayService,
 };
 
+/// Path of the sponsorship endpoint, its key in the per-endpoint metrics
+const SPONSOR_ENDPOINT: &str = "/api/v1/sponsor";
+
+/// Rows of the dashboard transaction table when the request does not ask
+const DEFAULT_TRANSACTION_ROWS: usize = 50;
+
 /// Swagger server state including metrics
 #[derive(Clone)]
 pub struct SwaggerState {
     pub paymaster_service: Arc<PaymasterRelayService>,
     pub metrics: SwaggerMetrics,
     pub start_time: Instant,
-    // Prometheus handle removed - using Rundler's metrics
+    /// Process-wide recorder, when the hosting process installed one
+    pub prometheus: Option<PrometheusHandle>,
 }
 
+/// Endpoints tracked separately before the least recently used ones are evicted
+const MAX_TRACKED_ENDPOINTS: usize = 64;
+
+/// Length of the interval reported by /statistics
+const STATISTICS_INTERVAL: Duration = Duration::from_secs(60);
+
 /// Additional metrics for Swagger UI itself
+///
+/// Clones share the same counters. Requests are counted in lifetime totals, in
+/// the current interval and per endpoint, each with a latency histogram.
 #[derive(Clone)]
 pub struct SwaggerMetrics {
-    total_requests: Arc<AtomicU64>,
-    successful_requests: Arc<AtomicU64>,
-    failed_requests: Arc<AtomicU64>,
-    avg_response_time_ms: Arc<AtomicU64>,
+    requests: Arc<IntervalRequestStats>,
+    endpoints: Arc<BoundedMap<RequestCounters>>,
 }
 
 impl SwaggerMetrics {
     pub fn new() -> Self {
-        Self {
-            total_requests: Arc::new(AtomicU64::new(0)),
-            successful_requests: Arc::new(AtomicU64::new(0)),
-            failed_requests: Arc::new(AtomicU64::new(0)),
-            avg_response_time_ms: Arc::new(AtomicU64::new(0)),
-        }
+        Self::with_limits(MAX_TRACKED_ENDPOINTS, STATISTICS_INTERVAL)
     }
 
-    pub fn record_request(&self, success: bool, duration: Duration) {
-        self.total_requests.fetch_add(1, Ordering::Relaxed);
-
-        if success {
-            self.successful_requests.fetch_add(1, Ordering::Relaxed);
-        } else {
-            self.failed_requests.fetch_add(1, Ordering::Relaxed);
+    /// Metrics tracking at most `max_endpoints` endpoints and closing an interval every `interval`
+    pub fn with_limits(max_endpoints: usize, interval: Duration) -> Self {
+        Self {
+            requests: Arc::new(IntervalRequestStats::new(interval)),
+            endpoints: Arc::new(BoundedMap::new(max_endpoints)),
         }
+    }
 
-        // Update average response time (simplified)
-        let duration_ms = duration.as_millis() as u64;
-        self.avg_response_time_ms
-            .store(duration_ms, Ordering::Relaxed);
+    pub fn record_request(&self, endpoint: &str, success: bool, duration: Duration) {
+        self.requests.record(success, duration);
+        self.endpoints
+            .update(endpoint, |counters| counters.record(success, duration));
     }
 
     pub fn get_stats(&self) -> serde_json::Value {
+        let lifetime = self.requests.lifetime();
+        json!({
+            "total_requests": lifetime.total_requests,
+            "successful_requests": lifetime.successful_requests,
+            "failed_requests": lifetime.failed_requests,
+            "avg_response_time_ms": lifetime.latency.avg_ms,
+            "p50_response_time_ms": lifetime.latency.p50_ms,
+            "p99_response_time_ms": lifetime.latency.p99_ms
+        })
+    }
+
+    /// Lifetime totals, the last complete interval and per-endpoint counts
+    ///
+    /// Closes the current interval first when it has lasted its full length.
+    pub fn statistics(&self) -> serde_json::Value {
+        let last_interval = self.requests.rotate_if_due();
+        let endpoints: serde_json::Map<String, serde_json::Value> = self
+            .endpoints
+            .snapshot(RequestCounters::snapshot)
+            .into_iter()
+            .map(|(endpoint, stats)| (endpoint, json!(stats)))
+            .collect();
         json!({
-            "total_requests": self.total_requests.load(Ordering::Relaxed),
-            "successful_requests": self.successful_requests.load(Ordering::Relaxed),
-            "failed_requests": self.failed_requests.load(Ordering::Relaxed),
-            "avg_response_time_ms": self.avg_response_time_ms.load(Ordering::Relaxed)
+            "lifetime": self.requests.lifetime(),
+            "last_interval": last_interval,
+            "current_interval": self.requests.current(),
+            "endpoints": endpoints,
+            "evicted_endpoints": self.endpoints.evictions()
         })
     }
+
+    /// Close the current interval now, returning its counts
+    pub fn snapshot_and_reset(&self) -> IntervalSnapshot {
+        self.requests.snapshot_and_reset()
+    }
 }
 
 impl Default for SwaggerMetrics {
@@ -92,22 +127,25 @@ impl Default for SwaggerMetrics {
 }
 
 /// Start the Swagger UI server with Prometheus metrics
+///
+/// `prometheus` is the recorder already installed by the hosting process; the
+/// server never installs its own, as only one global recorder can exist.
 pub async fn serve_swagger_ui(
     paymaster_service: Arc<PaymasterRelayService>,
     addr: SocketAddr,
+    cors: &CorsConfig,
+    prometheus: Option<PrometheusHandle>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // Using Rundler's existing metrics system instead of separate Prometheus
-
     let state = SwaggerState {
         paymaster_service,
         metrics: SwaggerMetrics::new(),
         start_time: Instant::now(),
+        prometheus,
     };
 
-    let app = create_router().with_state(state);
+    let app = create_router(cors)?.with_state(state);
 
     info!("Starting Swagger UI server on {}", addr);
-    // Metrics are available through Rundler's main metrics endpoint
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
     axum::serve(listener, app).await?;
@@ -116,14 +154,14 @@ pub async fn serve_swagger_ui(
 }
 
 /// Create the Swagger router with all endpoints
-fn create_router() -> Router<SwaggerState> {
+fn create_router(cors: &CorsConfig) -> Result<Router<SwaggerState>, CorsConfigError> {
     // Modify OpenAPI spec to set correct server URL
     let mut openapi = ApiDoc::openapi();
 
     // Update servers to point to the actual service
     openapi.servers = Some(vec![utoipa::openapi::Server::new("http://localhost:3000")]);
 
-    Router::new()
+    let router = Router::new()
         // Swagger UI with integrated dashboard
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", openapi))
         // Dashboard integration
@@ -134,28 +172,21 @@ fn create_router() -> Router<SwaggerState> {
         .route("/dashboard/api/metrics", get(get_metrics_dashboard))
         .route("/dashboard/api/transactions", get(get_transaction_history))
         // API endpoints
-        .route("/api/v1/sponsor", post(sponsor_user_operation_endpoint))
+        .route(SPONSOR_ENDPOINT, post(sponsor_user_operation_endpoint))
         // Health and monitoring endpoints
         .route("/health", get(health_check))
         .route("/ready", get(readiness_check))
         .route("/metrics", get(get_metrics))
+        .route("/statistics", get(get_statistics))
         .route("/prometheus", get(get_prometheus_metrics))
         .route("/examples/:version", get(get_examples))
         // Code generation endpoints
         .route("/codegen/curl/:endpoint", get(generate_curl_example))
         .route("/codegen/javascript/:endpoint", get(generate_js_example))
-        .route("/codegen/python/:endpoint", get(generate_python_example))
-        // State and middleware
-        .layer(
-            ServiceBuilder::new()
-                .layer(
-                    CorsLayer::new()
-                        .allow_origin(Any)
-                        .allow_methods(Any)
-                        .allow_headers(Any),
-                )
-                .into_inner(),
-        )
+        .route("/codegen/python/:endpoint", get(generate_python_example));
+
+    // State and middleware
+    cors.apply(router)
 }
 
 /// Sponsor user operation endpoint with metrics
@@ -179,7 +210,9 @@ async fn sponsor_user_operation_endpoint(
 
     // 验证UserOperation格式
     if let Err(e) = serde_json::from_value::<serde_json::Value>(json!(request.user_operation)) {
-        state.metrics.record_request(false, start_time.elapsed());
+        state
+            .metrics
+            .record_request(SPONSOR_ENDPOINT, false, start_time.elapsed());
         return Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
@@ -194,7 +227,9 @@ async fn sponsor_user_operation_endpoint(
 
     // 验证EntryPoint地址
     if !request.entry_point.starts_with("0x") {
-        state.metrics.record_request(false, start_time.elapsed());
+        state
+            .metrics
+            .record_request(SPONSOR_ENDPOINT, false, start_time.elapsed());
         return Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
@@ -232,7 +267,9 @@ async fn sponsor_user_operation_endpoint(
                 Ok(json_response) => {
                     // Check if it's an error response
                     if let Some(error) = json_response.get("error") {
-                        state.metrics.record_request(false, start_time.elapsed());
+                        state
+                            .metrics
+                            .record_request(SPONSOR_ENDPOINT, false, start_time.elapsed());
                         return Err((
                             StatusCode::BAD_REQUEST,
                             Json(ErrorResponse {
@@ -250,14 +287,18 @@ async fn sponsor_user_operation_endpoint(
 
                     // Extract result
                     if let Some(result) = json_response.get("result") {
-                        state.metrics.record_request(true, start_time.elapsed());
+                        state
+                            .metrics
+                            .record_request(SPONSOR_ENDPOINT, true, start_time.elapsed());
 
                         // Extract paymasterAndData from result
                         let paymaster_and_data = result.as_str().unwrap_or("0x").to_string();
 
                         Ok(Json(SponsorUserOperationResponse { paymaster_and_data }))
                     } else {
-                        state.metrics.record_request(false, start_time.elapsed());
+                        state
+                            .metrics
+                            .record_request(SPONSOR_ENDPOINT, false, start_time.elapsed());
                         Err((
                             StatusCode::INTERNAL_SERVER_ERROR,
                             Json(ErrorResponse {
@@ -269,7 +310,9 @@ async fn sponsor_user_operation_endpoint(
                     }
                 }
                 Err(e) => {
-                    state.metrics.record_request(false, start_time.elapsed());
+                    state
+                        .metrics
+                        .record_request(SPONSOR_ENDPOINT, false, start_time.elapsed());
                     Err((
                         StatusCode::INTERNAL_SERVER_ERROR,
                         Json(ErrorResponse {
@@ -285,7 +328,9 @@ async fn sponsor_user_operation_endpoint(
             }
         }
         Err(e) => {
-            state.metrics.record_request(false, start_time.elapsed());
+            state
+                .metrics
+                .record_request(SPONSOR_ENDPOINT, false, start_time.elapsed());
 
             // If service is not running, provide helpful message
             if e.to_string().contains("Connection refused") {
@@ -321,7 +366,7 @@ async fn sponsor_user_operation_endpoint(
     let user_op = match serde_json::from_value(json!(request.user_operation)) {
         Ok(op) => op,
         Err(e) => {
-            state.metrics.record_request(false, start_time.elapsed());
+            state.metrics.record_request(SPONSOR_ENDPOINT, false, start_time.elapsed());
             return Err((
                 StatusCode::BAD_REQUEST,
                 Json(ErrorResponse {
@@ -338,7 +383,7 @@ async fn sponsor_user_operation_endpoint(
     let entry_point = match request.entry_point.parse::<Address>() {
         Ok(addr) => addr,
         Err(e) => {
-            state.metrics.record_request(false, start_time.elapsed());
+            state.metrics.record_request(SPONSOR_ENDPOINT, false, start_time.elapsed());
             return Err((
                 StatusCode::BAD_REQUEST,
                 Json(ErrorResponse {
@@ -357,11 +402,12 @@ async fn sponsor_user_operation_endpoint(
         .sponsor_user_operation(
             user_op,
             ethers::types::Address::from_slice(entry_point.as_slice()),
+            crate::service::SponsorOptions::default(),
         )
         .await
     {
         Ok(sponsor_result) => {
-            state.metrics.record_request(true, start_time.elapsed());
+            state.metrics.record_request(SPONSOR_ENDPOINT, true, start_time.elapsed());
             Ok(Json(SponsorUserOperationResponse {
                 paymaster_and_data: format!(
                     "0x{}",
@@ -370,7 +416,7 @@ async fn sponsor_user_operation_endpoint(
             }))
         }
         Err(e) => {
-            state.metrics.record_request(false, start_time.elapsed());
+            state.metrics.record_request(SPONSOR_ENDPOINT, false, start_time.elapsed());
             Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ErrorResponse {
@@ -408,6 +454,7 @@ async fn health_check(State(state): State<SwaggerState>) -> Json<serde_json::Val
                 "swagger_ui": "/swagger-ui/",
                 "health": "/health",
                 "metrics": "/metrics",
+                "statistics": "/statistics",
                 "prometheus": "/prometheus",
                 "ready": "/ready"
             }
@@ -461,14 +508,26 @@ async fn get_metrics(State(state): State<SwaggerState>) -> Json<serde_json::Valu
     }))
 }
 
-/// Get Prometheus metrics - redirects to Rundler's metrics endpoint
-async fn get_prometheus_metrics() -> impl IntoResponse {
+/// Request statistics: lifetime totals, the last complete interval and per-endpoint latencies
+async fn get_statistics(State(state): State<SwaggerState>) -> Json<serde_json::Value> {
+    let mut statistics = state.metrics.statistics();
+    statistics["uptime_seconds"] = json!(state.start_time.elapsed().as_secs());
+    Json(statistics)
+}
+
+/// Get Prometheus metrics from the shared recorder, or redirect to Rundler's endpoint
+async fn get_prometheus_metrics(State(state): State<SwaggerState>) -> Response {
+    if let Some(handle) = &state.prometheus {
+        return handle.render().into_response();
+    }
+
     // Rundler exposes metrics on its own endpoint
     Response::builder()
         .status(303)
         .header("Location", "http://localhost:8080/metrics")
         .body("Metrics available at Rundler's metrics endpoint".to_string())
         .unwrap()
+        .into_response()
 }
 
 /// Get examples for different versions
@@ -976,7 +1035,7 @@ async fn dashboard_page() -> impl IntoResponse {
             resultDiv.className = 'test-result';
             
             try {
-                const response = await fetch('/api/balance');
+                const response = await fetch('/dashboard/api/balance');
                 const data = await response.json();
                 resultDiv.textContent = JSON.stringify(data, null, 2);
                 resultDiv.classList.add(response.ok ? 'result-success' : 'result-error');
@@ -1033,7 +1092,7 @@ async fn dashboard_page() -> impl IntoResponse {
                 if (response.ok) {
                     const metrics = await response.json();
                     document.getElementById('total-requests').textContent = metrics.total_requests || 0;
-                    document.getElementById('avg-response').textContent = `${metrics.average_response_time_ms?.toFixed(1) || 0}ms`;
+                    document.getElementById('avg-response').textContent = `${metrics.avg_response_time_ms?.toFixed(1) || 0}ms`;
                     document.getElementById('uptime').textContent = `${metrics.uptime_seconds || 0}s`;
                     document.getElementById('requests-per-min').textContent = metrics.requests_per_minute?.toFixed(1) || 0;
                     document.getElementById('memory-usage').textContent = `${metrics.memory_usage_mb?.toFixed(1) || 0} MB`;
@@ -1050,17 +1109,17 @@ async fn dashboard_page() -> impl IntoResponse {
 
         async function loadBalanceStatus() {
             try {
-                const response = await fetch('/api/balance');
+                const response = await fetch('/dashboard/api/balance');
                 if (response.ok) {
                     const balance = await response.json();
-                    document.getElementById('paymaster-balance').textContent = `${balance.paymaster_balance} ETH`;
-                    document.getElementById('entrypoint-deposit').textContent = `${balance.entrypoint_deposit} ETH`;
+                    document.getElementById('paymaster-balance').textContent = balance.paymaster_balance_eth === null ? 'Unavailable' : `${balance.paymaster_balance_eth.toFixed(4)} ETH`;
+                    document.getElementById('entrypoint-deposit').textContent = `${balance.entrypoint_deposit_eth.toFixed(4)} ETH`;
                     
                     // Update system status based on balance health
                     const statusElement = document.getElementById('system-status');
                     if (balance.status === 'healthy') {
                         statusElement.innerHTML = '<span class="status-indicator status-healthy"></span>Healthy';
-                    } else if (balance.status === 'warning') {
+                    } else if (balance.status === 'warning' || balance.status === 'degraded') {
                         statusElement.innerHTML = '<span class="status-indicator status-warning"></span>Warning';
                     } else {
                         statusElement.innerHTML = '<span class="status-indicator status-error"></span>Critical';
@@ -1093,56 +1152,56 @@ async fn dashboard_page() -> impl IntoResponse {
     Html(html)
 }
 
-/// Get balance status for dashboard
-async fn get_balance_status() -> Json<serde_json::Value> {
-    // TODO: Implement actual balance checking
-    Json(json!({
-        "paymaster_balance_eth": 0.0,
-        "entrypoint_deposit_eth": 0.0,
-        "network": "dev",
-        "status": "unknown"
-    }))
+/// Paymaster balance and EntryPoint deposits for dashboard
+async fn get_balance_status(State(state): State<SwaggerState>) -> Json<DashboardBalance> {
+    Json(state.paymaster_service.dashboard_balance().await)
 }
 
-/// Get policies status for dashboard
-async fn get_policies_status() -> Json<serde_json::Value> {
-    // TODO: Implement actual policy reading
-    Json(json!({
-        "active_policies": [],
-        "total_policies": 0
-    }))
+/// Loaded policies for dashboard
+async fn get_policies_status(State(state): State<SwaggerState>) -> Json<DashboardPolicies> {
+    Json(state.paymaster_service.dashboard_policies())
 }
 
 /// Get metrics for dashboard (different from Prometheus)
 async fn get_metrics_dashboard(State(state): State<SwaggerState>) -> Json<serde_json::Value> {
     let uptime = state.start_time.elapsed().as_secs();
-    let total_requests = state.metrics.total_requests.load(Ordering::Relaxed);
-    let successful_requests = state.metrics.successful_requests.load(Ordering::Relaxed);
-    let success_rate = if total_requests > 0 {
-        (successful_requests as f64 / total_requests as f64 * 100.0) as u64
+    let requests = state.metrics.requests.lifetime();
+    let success_rate = if requests.total_requests > 0 {
+        (requests.successful_requests as f64 / requests.total_requests as f64 * 100.0) as u64
     } else {
         100
     };
 
     Json(json!({
-        "total_requests": total_requests,
-        "successful_requests": successful_requests,
-        "failed_requests": state.metrics.failed_requests.load(Ordering::Relaxed),
+        "total_requests": requests.total_requests,
+        "successful_requests": requests.successful_requests,
+        "failed_requests": requests.failed_requests,
         "success_rate": success_rate,
-        "avg_response_time_ms": state.metrics.avg_response_time_ms.load(Ordering::Relaxed),
+        "avg_response_time_ms": requests.latency.avg_ms,
+        "p99_response_time_ms": requests.latency.p99_ms,
         "uptime_seconds": uptime,
         "memory_usage_mb": get_memory_usage_mb(),
         "cpu_usage_percent": get_cpu_usage_percent()
     }))
 }
 
-/// Get transaction history for dashboard
-async fn get_transaction_history() -> Json<serde_json::Value> {
-    // TODO: Implement actual transaction history
-    Json(json!({
-        "recent_transactions": [],
-        "total_count": 0
-    }))
+/// Query of the dashboard transaction table
+#[derive(Debug, Deserialize)]
+struct TransactionHistoryQuery {
+    /// Rows to return, newest first
+    limit: Option<usize>,
+}
+
+/// Recent sponsorships for dashboard
+async fn get_transaction_history(
+    State(state): State<SwaggerState>,
+    Query(query): Query<TransactionHistoryQuery>,
+) -> Json<DashboardTransactions> {
+    Json(
+        state
+            .paymaster_service
+            .dashboard_transactions(query.limit.unwrap_or(DEFAULT_TRANSACTION_ROWS)),
+    )
 }
 
 /// 返回OpenAPI规范
@@ -0,0 +1,113 @@
+// paymaster-relay/src/timings.rs
+// Durations of the stages of a sponsorship, for the stage histogram and opt-in debug responses.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use metrics::histogram;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Timed stage of a sponsorship request
+///
+/// The names are stable: they label `superrelay_sponsor_stage_duration_seconds`
+/// and the `timings` of debug responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SponsorStage {
+    /// Gateway data completeness and format checks
+    DataIntegrity,
+    /// Gateway sender eligibility checks
+    Authorization,
+    /// Gateway threat intelligence and calldata analysis
+    Security,
+    /// Gateway pipeline modules other than the built-in ones
+    CustomModule,
+    /// Account signature validation
+    AccountSignature,
+    /// Aggregated signature preparation
+    Aggregator,
+    /// Sponsorship policy evaluation
+    PolicyEvaluation,
+    /// SBT and PNTs balance reads
+    SbtCheck,
+    /// The policy's external authorizer webhook
+    ExternalAuthorizer,
+    /// Fee check against the current network fees
+    FeeCheck,
+    /// Token price oracle reads
+    TokenPricing,
+    /// Paymaster signature by the KMS or local key
+    Signing,
+}
+
+impl SponsorStage {
+    /// Name in metrics labels and responses
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            SponsorStage::DataIntegrity => "data_integrity",
+            SponsorStage::Authorization => "authorization",
+            SponsorStage::Security => "security",
+            SponsorStage::CustomModule => "custom_module",
+            SponsorStage::AccountSignature => "account_signature",
+            SponsorStage::Aggregator => "aggregator",
+            SponsorStage::PolicyEvaluation => "policy_evaluation",
+            SponsorStage::SbtCheck => "sbt_check",
+            SponsorStage::ExternalAuthorizer => "external_authorizer",
+            SponsorStage::FeeCheck => "fee_check",
+            SponsorStage::TokenPricing => "token_pricing",
+            SponsorStage::Signing => "signing",
+        }
+    }
+}
+
+/// Duration of one stage run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StageTiming {
+    pub stage: SponsorStage,
+    pub ms: f64,
+}
+
+/// Stage durations of one request, in the order the stages finished
+///
+/// Clones share the same list, so the gateway and the paymaster service
+/// record into one.
+#[derive(Debug, Clone, Default)]
+pub struct StageTimings {
+    stages: Arc<Mutex<Vec<StageTiming>>>,
+}
+
+impl StageTimings {
+    /// Add a run of `stage` that took `duration`
+    pub fn record(&self, stage: SponsorStage, duration: Duration) {
+        self.stages.lock().unwrap().push(StageTiming {
+            stage,
+            ms: duration.as_secs_f64() * 1000.0,
+        });
+    }
+
+    /// Stages recorded so far
+    pub fn stages(&self) -> Vec<StageTiming> {
+        self.stages.lock().unwrap().clone()
+    }
+
+    /// `timings` object of a debug response, with the request's `total` time
+    pub fn to_json(&self, total: Duration) -> Value {
+        json!({
+            "stages": self.stages(),
+            "totalMs": total.as_secs_f64() * 1000.0,
+        })
+    }
+}
+
+/// Record `duration` of `stage` in the stage histogram, and in `timings`
+/// when the request collects them
+pub fn record_stage(timings: Option<&StageTimings>, stage: SponsorStage, duration: Duration) {
+    histogram!("superrelay_sponsor_stage_duration_seconds", "stage" => stage.as_str())
+        .record(duration.as_secs_f64());
+    if let Some(timings) = timings {
+        timings.record(stage, duration);
+    }
+}
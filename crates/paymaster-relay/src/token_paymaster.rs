@@ -0,0 +1,811 @@
+// paymaster-relay/src/token_paymaster.rs
+// ERC-20 gas payment: token price feeds, quotes and the token paymaster data format.
+//
+// Layout of the paymaster data emitted by pm_sponsorUserOperationERC20:
+//   paymasterData = token (20 bytes) || uint256 rate || abi.encode(uint48 validUntil, uint48 validAfter)
+//                   || signer (20 bytes) || signature (65 bytes)
+// `rate` is the number of token base units charged per 1 ETH (1e18 wei) of gas, markup
+// included. The signature covers keccak256(abi.encode(sponsorshipHash, token, rate)), with
+// sponsorshipHash computed as for verifying-paymaster sponsorships.
+
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use async_trait::async_trait;
+use rundler_provider::{EvmProvider, TransactionBuilder, TransactionRequest};
+use rundler_types::UserOperationVariant;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::PaymasterError,
+    sponsorship::{self, SponsorshipData, SponsorshipError, SPONSORSHIP_DATA_LEN},
+};
+
+/// Length of the token address and rate preceding the validity window
+pub const TOKEN_PREFIX_LEN: usize = 20 + 32;
+/// Length of the complete token paymaster data
+pub const TOKEN_PAYMASTER_DATA_LEN: usize = TOKEN_PREFIX_LEN + SPONSORSHIP_DATA_LEN;
+
+/// Markup denominator, markups are given in basis points
+const BPS: u64 = 10_000;
+
+/// `latestRoundData()` selector of Chainlink aggregators
+const LATEST_ROUND_DATA_SELECTOR: [u8; 4] = [0xfe, 0xaf, 0x96, 0x8c];
+/// `decimals()` selector of Chainlink aggregators
+const DECIMALS_SELECTOR: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+
+/// Where the exchange rate of an accepted token comes from
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PriceFeed {
+    /// Fixed rate in token base units per 1 ETH
+    Static { tokens_per_eth: U256 },
+    /// Chainlink aggregator pricing 1 token in ETH (e.g. USDC / ETH)
+    ///
+    /// Set `inverted` for aggregators pricing 1 ETH in the token instead
+    /// (e.g. ETH / USD for a USD stablecoin).
+    Chainlink {
+        oracle: Address,
+        #[serde(default)]
+        inverted: bool,
+    },
+}
+
+/// Token accepted for gas payment (`[[paymaster_relay.token_paymaster.tokens]]`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenConfig {
+    /// ERC-20 contract
+    pub address: Address,
+    /// Display symbol returned in quotes
+    #[serde(default)]
+    pub symbol: String,
+    /// Token decimals
+    pub decimals: u8,
+    /// Source of the exchange rate
+    pub price_feed: PriceFeed,
+}
+
+/// ERC-20 gas payment configuration (`[paymaster_relay.token_paymaster]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TokenPaymasterConfig {
+    /// Serve pm_sponsorUserOperationERC20
+    pub enabled: bool,
+    /// Token paymaster contract, the sponsoring paymaster when unset
+    pub paymaster: Option<Address>,
+    /// Markup added to the oracle rate, in basis points
+    pub markup_bps: u32,
+    /// Seconds an oracle answer may be old before quotes are refused
+    pub max_oracle_age_seconds: u64,
+    /// v0.7 postOp gas limit, covering the token transfer
+    pub post_op_gas_limit: u64,
+    /// Accepted tokens
+    pub tokens: Vec<TokenConfig>,
+}
+
+impl Default for TokenPaymasterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            paymaster: None,
+            markup_bps: 1_000,
+            max_oracle_age_seconds: 3_600,
+            post_op_gas_limit: 50_000,
+            tokens: Vec::new(),
+        }
+    }
+}
+
+/// Latest answer of a price oracle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OracleAnswer {
+    /// Price, scaled by `10^decimals`
+    pub answer: U256,
+    /// Decimals of `answer`
+    pub decimals: u8,
+    /// Unix time the answer was last updated
+    pub updated_at: u64,
+}
+
+/// Chain access needed to read price oracles
+#[async_trait]
+pub trait PriceOracle: Send + Sync {
+    /// Latest answer of the aggregator at `oracle`
+    async fn latest_answer(&self, oracle: Address) -> Result<OracleAnswer, PaymasterError>;
+}
+
+/// [`PriceOracle`] reading Chainlink aggregators through a node provider
+pub struct ProviderPriceOracle<P> {
+    provider: P,
+}
+
+impl<P> ProviderPriceOracle<P> {
+    pub fn new(provider: P) -> Self {
+        Self { provider }
+    }
+}
+
+impl<P: EvmProvider> ProviderPriceOracle<P> {
+    async fn call(&self, oracle: Address, selector: [u8; 4]) -> Result<Bytes, PaymasterError> {
+        let tx = TransactionRequest::default()
+            .to(oracle)
+            .with_input(Bytes::from(selector.to_vec()));
+        self.provider
+            .call(tx, None, None)
+            .await
+            .map_err(|e| PaymasterError::ChainError(format!("Price feed {} failed: {}", oracle, e)))
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> PriceOracle for ProviderPriceOracle<P> {
+    async fn latest_answer(&self, oracle: Address) -> Result<OracleAnswer, PaymasterError> {
+        // (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound)
+        let round = self.call(oracle, LATEST_ROUND_DATA_SELECTOR).await?;
+        let decimals = self.call(oracle, DECIMALS_SELECTOR).await?;
+        if round.len() < 32 * 5 || decimals.len() < 32 {
+            return Err(PaymasterError::ChainError(format!(
+                "Invalid price feed response from {}",
+                oracle
+            )));
+        }
+
+        // A set sign bit is a negative int256 answer
+        if round[32] & 0x80 != 0 {
+            return Err(PaymasterError::ChainError(format!(
+                "Price feed {} returned a negative answer",
+                oracle
+            )));
+        }
+        let updated_at = U256::from_be_slice(&round[96..128]);
+        let decimals = U256::from_be_slice(&decimals[..32]);
+        Ok(OracleAnswer {
+            answer: U256::from_be_slice(&round[32..64]),
+            decimals: u8::try_from(decimals).map_err(|_| {
+                PaymasterError::ChainError(format!("Price feed {} decimals out of range", oracle))
+            })?,
+            updated_at: u64::try_from(updated_at).unwrap_or(u64::MAX),
+        })
+    }
+}
+
+/// Token amount quoted for an operation, as signed into its paymaster data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenQuote {
+    /// ERC-20 the operation pays in
+    pub token: Address,
+    /// Display symbol of the token
+    pub symbol: String,
+    /// Token base units per 1 ETH of gas, markup included
+    pub rate: U256,
+    /// Markup included in `rate`, in basis points
+    pub markup_bps: u32,
+    /// Most gas the operation can cost, in wei
+    pub max_gas_cost: U256,
+    /// Most tokens the operation can be charged, in base units
+    pub max_token_amount: U256,
+    /// Unix time the quote expires, as signed into the paymaster data
+    pub valid_until: u64,
+    /// Unix time the quote becomes valid, as signed into the paymaster data
+    pub valid_after: u64,
+}
+
+/// Token amount charged for `gas_cost` wei at `rate`, rounded up
+pub fn token_amount(gas_cost: U256, rate: U256) -> U256 {
+    let one_eth = U256::from(10u64).pow(U256::from(18));
+    let (amount, remainder) = (gas_cost * rate).div_rem(one_eth);
+    if remainder.is_zero() {
+        amount
+    } else {
+        amount + U256::from(1)
+    }
+}
+
+/// Token address, rate, validity window, signer and signature carried in token paymaster data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenPaymasterData {
+    pub token: Address,
+    /// Token base units per 1 ETH of gas
+    pub rate: U256,
+    /// Last timestamp the quote is valid; 0 means no expiry
+    pub valid_until: u64,
+    /// First timestamp the quote is valid
+    pub valid_after: u64,
+    /// Key the signature was made with, one of the paymaster's signers
+    pub signer: Address,
+    pub signature: Vec<u8>,
+}
+
+impl TokenPaymasterData {
+    /// Encode as `token || rate || abi.encode(validUntil, validAfter) || signer || signature`
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(TOKEN_PAYMASTER_DATA_LEN);
+        encoded.extend_from_slice(self.token.as_slice());
+        encoded.extend_from_slice(&self.rate.to_be_bytes::<32>());
+        encoded.extend_from_slice(
+            &SponsorshipData {
+                valid_until: self.valid_until,
+                valid_after: self.valid_after,
+                signer: self.signer,
+                signature: self.signature.clone(),
+            }
+            .encode(),
+        );
+        encoded
+    }
+
+    /// Decode paymasterData produced by [`TokenPaymasterData::encode`]
+    pub fn decode(paymaster_data: &[u8]) -> Result<Self, SponsorshipError> {
+        if paymaster_data.len() != TOKEN_PAYMASTER_DATA_LEN {
+            return Err(SponsorshipError::Malformed(format!(
+                "expected {} bytes, got {}",
+                TOKEN_PAYMASTER_DATA_LEN,
+                paymaster_data.len()
+            )));
+        }
+
+        let window = SponsorshipData::decode(&paymaster_data[TOKEN_PREFIX_LEN..])?;
+        Ok(Self {
+            token: Address::from_slice(&paymaster_data[..20]),
+            rate: U256::from_be_slice(&paymaster_data[20..TOKEN_PREFIX_LEN]),
+            valid_until: window.valid_until,
+            valid_after: window.valid_after,
+            signer: window.signer,
+            signature: window.signature,
+        })
+    }
+}
+
+/// Whether `user_op` carries paymaster data in the token paymaster format
+pub fn is_token_payment(user_op: &UserOperationVariant) -> bool {
+    sponsorship::paymaster_data(user_op).len() == TOKEN_PAYMASTER_DATA_LEN
+}
+
+/// Hash the paymaster signs for paying `user_op` in `token` at `rate`
+pub fn token_payment_hash(
+    user_op: &UserOperationVariant,
+    paymaster: Address,
+    token: Address,
+    rate: U256,
+    valid_until: u64,
+    valid_after: u64,
+) -> B256 {
+    let mut encoded = Vec::with_capacity(32 * 3);
+    encoded.extend_from_slice(
+        sponsorship::sponsorship_hash(user_op, paymaster, valid_until, valid_after).as_slice(),
+    );
+    encoded.extend_from_slice(&[0u8; 12]);
+    encoded.extend_from_slice(token.as_slice());
+    encoded.extend_from_slice(&rate.to_be_bytes::<32>());
+    keccak256(&encoded)
+}
+
+/// Verify a token payment for `paymaster` signed by any of `signers` at time `now`
+pub fn verify_token_payment_by(
+    user_op: &UserOperationVariant,
+    paymaster: Address,
+    signers: &[Address],
+    now: u64,
+) -> Result<TokenPaymasterData, SponsorshipError> {
+    let data = TokenPaymasterData::decode(sponsorship::paymaster_data(user_op))?;
+
+    let hash = token_payment_hash(
+        user_op,
+        paymaster,
+        data.token,
+        data.rate,
+        data.valid_until,
+        data.valid_after,
+    );
+    sponsorship::verify_signer(hash, data.signer, &data.signature, signers)?;
+
+    if now < data.valid_after {
+        return Err(SponsorshipError::NotYetValid {
+            valid_after: data.valid_after,
+            now,
+        });
+    }
+    if data.valid_until != 0 && now >= data.valid_until {
+        return Err(SponsorshipError::Expired {
+            valid_until: data.valid_until,
+            now,
+        });
+    }
+
+    Ok(data)
+}
+
+/// Prices accepted tokens and quotes the token amount of operations
+#[derive(Clone)]
+pub struct TokenPricing {
+    oracle: Arc<dyn PriceOracle>,
+    config: TokenPaymasterConfig,
+    tokens: HashMap<Address, TokenConfig>,
+}
+
+impl fmt::Debug for TokenPricing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenPricing")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl TokenPricing {
+    pub fn new(oracle: Arc<dyn PriceOracle>, config: TokenPaymasterConfig) -> Self {
+        let tokens = config
+            .tokens
+            .iter()
+            .map(|token| (token.address, token.clone()))
+            .collect();
+        Self {
+            oracle,
+            config,
+            tokens,
+        }
+    }
+
+    pub fn config(&self) -> &TokenPaymasterConfig {
+        &self.config
+    }
+
+    /// Configuration of `token`, rejecting tokens that are not accepted
+    pub fn token(&self, token: Address) -> Result<&TokenConfig, PaymasterError> {
+        self.tokens.get(&token).ok_or_else(|| {
+            PaymasterError::InvalidRequest(format!(
+                "Token {} is not accepted for gas payment",
+                token
+            ))
+        })
+    }
+
+    /// Rate of `token` in base units per 1 ETH, markup included, at time `now`
+    pub async fn rate(&self, token: Address, now: u64) -> Result<U256, PaymasterError> {
+        let config = self.token(token)?;
+        let base_rate = match &config.price_feed {
+            PriceFeed::Static { tokens_per_eth } => *tokens_per_eth,
+            PriceFeed::Chainlink { oracle, inverted } => {
+                let answer = self.oracle.latest_answer(*oracle).await?;
+                if now.saturating_sub(answer.updated_at) > self.config.max_oracle_age_seconds {
+                    return Err(PaymasterError::ChainError(format!(
+                        "Price feed {} is stale, last updated at {}",
+                        oracle, answer.updated_at
+                    )));
+                }
+                if answer.answer.is_zero() {
+                    return Err(PaymasterError::ChainError(format!(
+                        "Price feed {} returned a zero answer",
+                        oracle
+                    )));
+                }
+                let token_unit = U256::from(10u64).pow(U256::from(config.decimals));
+                let feed_unit = U256::from(10u64).pow(U256::from(answer.decimals));
+                if *inverted {
+                    answer.answer * token_unit / feed_unit
+                } else {
+                    token_unit * feed_unit / answer.answer
+                }
+            }
+        };
+        if base_rate.is_zero() {
+            return Err(PaymasterError::ChainError(format!(
+                "Token {} has a zero exchange rate",
+                token
+            )));
+        }
+        Ok(base_rate * U256::from(BPS + u64::from(self.config.markup_bps)) / U256::from(BPS))
+    }
+
+    /// Quote paying `max_gas_cost` wei in `token` at `rate`
+    pub fn quote(
+        &self,
+        token: Address,
+        rate: U256,
+        max_gas_cost: U256,
+        valid_until: u64,
+        valid_after: u64,
+    ) -> Result<TokenQuote, PaymasterError> {
+        let config = self.token(token)?;
+        Ok(TokenQuote {
+            token,
+            symbol: config.symbol.clone(),
+            rate,
+            markup_bps: self.config.markup_bps,
+            max_gas_cost,
+            max_token_amount: token_amount(max_gas_cost, rate),
+            valid_until,
+            valid_after,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use alloy_primitives::{address, Address, Bytes, U256};
+    use async_trait::async_trait;
+    use rundler_types::{chain::ChainSpec, v0_7, UserOperation, UserOperationVariant};
+
+    use super::*;
+    use crate::{
+        service::{PaymasterRelayService, SponsorOptions},
+        sponsorship::{sponsored_max_gas_cost, SponsorshipError},
+        test_utils, PaymasterError,
+    };
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let data = TokenPaymasterData {
+            token: address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            rate: U256::from(3_300_000_000u64),
+            valid_until: 1_700_000_600,
+            valid_after: 1_700_000_000,
+            signer: address!("f39fd6e51aad88f6f4ce6ab8827279cfffb92266"),
+            signature: vec![0x11; sponsorship::SIGNATURE_LEN],
+        };
+        let encoded = data.encode();
+        assert_eq!(encoded.len(), TOKEN_PAYMASTER_DATA_LEN);
+        assert_eq!(&encoded[..20], data.token.as_slice());
+        assert_eq!(TokenPaymasterData::decode(&encoded).unwrap(), data);
+
+        assert!(matches!(
+            TokenPaymasterData::decode(&encoded[..SPONSORSHIP_DATA_LEN]),
+            Err(SponsorshipError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_token_amount_rounds_up() {
+        // 1 gwei of gas at 3000 USDC (6 decimals) per ETH is 0.000003 USDC
+        assert_eq!(
+            token_amount(U256::from(1_000_000_000u64), U256::from(3_000_000_000u64)),
+            U256::from(3)
+        );
+        assert_eq!(
+            token_amount(U256::from(1u64), U256::from(3_000_000_000u64)),
+            U256::from(1)
+        );
+        assert_eq!(
+            token_amount(U256::ZERO, U256::from(3_000_000_000u64)),
+            U256::ZERO
+        );
+    }
+
+    const SENDER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    /// Address of `test_utils::SIGNER_KEY`
+    const SIGNER: Address = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+
+    const USDC: Address = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+    const PNT: Address = address!("00000000000000000000000000000000000000e2");
+    const DAI: Address = address!("6b175474e89094c44da98b954eedeac495271d0f");
+    const USD_FEED: Address = address!("5f4ec3df9cbd43714fe2740f5e3616155c5b8419");
+
+    /// Price oracle answering with whatever the test last set
+    #[derive(Default)]
+    struct MockOracle {
+        answers: Mutex<Vec<(Address, OracleAnswer)>>,
+    }
+
+    impl MockOracle {
+        fn set(&self, oracle: Address, answer: OracleAnswer) {
+            let mut answers = self.answers.lock().unwrap();
+            answers.retain(|(feed, _)| *feed != oracle);
+            answers.push((oracle, answer));
+        }
+    }
+
+    #[async_trait]
+    impl PriceOracle for MockOracle {
+        async fn latest_answer(&self, oracle: Address) -> Result<OracleAnswer, PaymasterError> {
+            self.answers
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|(feed, _)| *feed == oracle)
+                .map(|(_, answer)| *answer)
+                .ok_or_else(|| PaymasterError::ChainError(format!("no answer for {}", oracle)))
+        }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn config(markup_bps: u32) -> TokenPaymasterConfig {
+        TokenPaymasterConfig {
+            enabled: true,
+            markup_bps,
+            max_oracle_age_seconds: 600,
+            tokens: vec![
+                // 3000 USDC per ETH from an ETH / USD feed
+                TokenConfig {
+                    address: USDC,
+                    symbol: "USDC".to_string(),
+                    decimals: 6,
+                    price_feed: PriceFeed::Chainlink {
+                        oracle: USD_FEED,
+                        inverted: true,
+                    },
+                },
+                // 1000 PNT per ETH
+                TokenConfig {
+                    address: PNT,
+                    symbol: "PNT".to_string(),
+                    decimals: 18,
+                    price_feed: PriceFeed::Static {
+                        tokens_per_eth: U256::from(1_000u64)
+                            * U256::from(10u64).pow(U256::from(18)),
+                    },
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    /// Mock oracle pricing ETH at 3000 USD with 8 decimals, updated `age` seconds ago
+    fn usd_oracle(age: u64) -> Arc<MockOracle> {
+        let oracle = Arc::new(MockOracle::default());
+        oracle.set(
+            USD_FEED,
+            OracleAnswer {
+                answer: U256::from(3_000u64 * 100_000_000),
+                decimals: 8,
+                updated_at: now() - age,
+            },
+        );
+        oracle
+    }
+
+    fn create_service(policy: &str, token_pricing: Option<TokenPricing>) -> PaymasterRelayService {
+        let service = test_utils::service(&format!("senders = [\"{}\"]\n{}", SENDER, policy));
+        match token_pricing {
+            Some(token_pricing) => service.with_token_pricing(token_pricing),
+            None => service,
+        }
+    }
+
+    fn op() -> (UserOperationVariant, ethers::types::Address) {
+        let op = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender: Address::from_str(SENDER).unwrap(),
+                nonce: U256::ZERO,
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 2_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        let entry_point = ChainSpec::default().entry_point_address_v0_7;
+        (
+            UserOperationVariant::V0_7(op),
+            ethers::types::Address::from_slice(entry_point.as_slice()),
+        )
+    }
+
+    fn pay_in(token: Address) -> SponsorOptions {
+        SponsorOptions {
+            return_full_operation: true,
+            token: Some(token),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quote_matches_signed_rate() {
+        let pricing = TokenPricing::new(usd_oracle(0), config(1_000));
+        let service = create_service("", Some(pricing));
+        let (op, entry_point) = op();
+
+        let result = service
+            .sponsor_user_operation(op, entry_point, pay_in(USDC))
+            .await
+            .unwrap();
+        let quote = result.token_quote.unwrap();
+        let sponsored = result.sponsored_user_op.unwrap();
+
+        // 3000 USDC per ETH plus the 10% markup
+        assert_eq!(quote.token, USDC);
+        assert_eq!(quote.symbol, "USDC");
+        assert_eq!(quote.markup_bps, 1_000);
+        assert_eq!(quote.rate, U256::from(3_300_000_000u64));
+        assert_eq!(quote.max_gas_cost, sponsored_max_gas_cost(&sponsored));
+        assert_eq!(
+            quote.max_token_amount,
+            token_amount(quote.max_gas_cost, quote.rate)
+        );
+        assert_eq!(quote.valid_until, result.valid_until);
+
+        // Without a token paymaster contract the sponsoring paymaster is used
+        assert_eq!(sponsored.paymaster(), Some(SIGNER));
+
+        // The paymaster data carries exactly the quoted token, rate and window
+        let UserOperationVariant::V0_7(ref v0_7_op) = sponsored else {
+            panic!("expected v0.7 operation");
+        };
+        let data = TokenPaymasterData::decode(v0_7_op.paymaster_data()).unwrap();
+        assert_eq!(data.token, quote.token);
+        assert_eq!(data.rate, quote.rate);
+        assert_eq!(data.valid_until, quote.valid_until);
+        assert_eq!(v0_7_op.paymaster_post_op_gas_limit(), 50_000);
+
+        verify_token_payment_by(&sponsored, SIGNER, &[SIGNER], now()).unwrap();
+        assert!(service.verify_own_sponsorship(&sponsored).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_markup_applied_to_rates() {
+        let oracle = usd_oracle(0);
+        // 1 DAI costs 0.0005 ETH on an 18 decimal DAI / ETH feed
+        let token_feed = address!("00000000000000000000000000000000000000f1");
+        oracle.set(
+            token_feed,
+            OracleAnswer {
+                answer: U256::from(500_000_000_000_000u64),
+                decimals: 18,
+                updated_at: now(),
+            },
+        );
+        let mut config = config(0);
+        config.tokens.push(TokenConfig {
+            address: DAI,
+            symbol: "DAI".to_string(),
+            decimals: 18,
+            price_feed: PriceFeed::Chainlink {
+                oracle: token_feed,
+                inverted: false,
+            },
+        });
+        let eth = U256::from(10u64).pow(U256::from(18));
+
+        let pricing = TokenPricing::new(oracle.clone(), config.clone());
+        assert_eq!(
+            pricing.rate(USDC, now()).await.unwrap(),
+            U256::from(3_000_000_000u64)
+        );
+        assert_eq!(
+            pricing.rate(PNT, now()).await.unwrap(),
+            U256::from(1_000u64) * eth
+        );
+        assert_eq!(
+            pricing.rate(DAI, now()).await.unwrap(),
+            U256::from(2_000u64) * eth
+        );
+
+        // 2.5% markup on every feed
+        config.markup_bps = 250;
+        let pricing = TokenPricing::new(oracle, config);
+        assert_eq!(
+            pricing.rate(USDC, now()).await.unwrap(),
+            U256::from(3_075_000_000u64)
+        );
+        assert_eq!(
+            pricing.rate(PNT, now()).await.unwrap(),
+            U256::from(1_025u64) * eth
+        );
+
+        // Charged amounts round up to the next base unit
+        let quote = pricing
+            .quote(USDC, U256::from(3_075_000_000u64), U256::from(1u64), 0, 0)
+            .unwrap();
+        assert_eq!(quote.max_token_amount, U256::from(1u64));
+    }
+
+    #[tokio::test]
+    async fn test_quote_expiry() {
+        let pricing = TokenPricing::new(usd_oracle(0), config(1_000));
+        let service = create_service("", Some(pricing));
+        let (op, entry_point) = op();
+
+        let options = SponsorOptions {
+            validity_seconds: Some(60),
+            ..pay_in(USDC)
+        };
+        let result = service
+            .sponsor_user_operation(op, entry_point, options)
+            .await
+            .unwrap();
+        let sponsored = result.sponsored_user_op.unwrap();
+        let valid_until = result.token_quote.unwrap().valid_until;
+        assert!(valid_until >= now() + 59 && valid_until <= now() + 60);
+
+        verify_token_payment_by(&sponsored, SIGNER, &[SIGNER], valid_until - 1).unwrap();
+        let expired =
+            verify_token_payment_by(&sponsored, SIGNER, &[SIGNER], valid_until).unwrap_err();
+        assert!(matches!(expired, SponsorshipError::Expired { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_stale_oracle_refused() {
+        let pricing = TokenPricing::new(usd_oracle(601), config(1_000));
+        let err = pricing.rate(USDC, now()).await.unwrap_err();
+        assert!(matches!(err, PaymasterError::ChainError(_)), "{}", err);
+        assert!(err.to_string().contains("stale"), "{}", err);
+
+        // Static rates do not age
+        pricing.rate(PNT, now()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_token_policy_guards() {
+        let policy = format!("tokens = [\"{}\"]\n", USDC);
+        let service = create_service(
+            &policy,
+            Some(TokenPricing::new(usd_oracle(0), config(1_000))),
+        );
+
+        let (op, entry_point) = op();
+        service
+            .sponsor_user_operation(op.clone(), entry_point, pay_in(USDC))
+            .await
+            .unwrap();
+
+        // PNT is configured but not allowed by the policy
+        let err = service
+            .sponsor_user_operation(op.clone(), entry_point, pay_in(PNT))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PaymasterError::PolicyRejected(_)), "{}", err);
+
+        // Tokens nobody configured are refused before pricing
+        let unknown = address!("00000000000000000000000000000000000000ee");
+        let err = service
+            .sponsor_user_operation(op, entry_point, pay_in(unknown))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PaymasterError::InvalidRequest(_)), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_max_token_amount_guard() {
+        let policy = format!("max_token_amounts = {{ \"{}\" = \"10\" }}\n", USDC);
+        let service = create_service(
+            &policy,
+            Some(TokenPricing::new(usd_oracle(0), config(1_000))),
+        );
+
+        let (op, entry_point) = op();
+        let err = service
+            .sponsor_user_operation(op, entry_point, pay_in(USDC))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PaymasterError::PolicyRejected(_)), "{}", err);
+        assert!(
+            err.to_string().contains("exceeds the maximum of 10"),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_erc20_payment_requires_pricing() {
+        let service = create_service("", None);
+        let (op, entry_point) = op();
+
+        let err = service
+            .sponsor_user_operation(op.clone(), entry_point, pay_in(USDC))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PaymasterError::InvalidRequest(_)), "{}", err);
+
+        // Plain sponsorship is unaffected
+        let result = service
+            .sponsor_user_operation(op, entry_point, SponsorOptions::default())
+            .await
+            .unwrap();
+        assert!(result.token_quote.is_none());
+    }
+}
@@ -0,0 +1,1065 @@
+// paymaster-relay/src/usage.rs
+// Per API key usage accounting of sponsored operations, for billing dApps.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, fs,
+    io::{self, Write},
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::{Address, B256, U256};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+const SECONDS_PER_HOUR: u64 = 60 * 60;
+const SECONDS_PER_DAY: u64 = 24 * SECONDS_PER_HOUR;
+
+/// Key id usage of requests without an API key is accounted under
+pub const ANONYMOUS_KEY_ID: &str = "anonymous";
+
+/// Usage accounting configuration (`[paymaster_relay.usage]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct UsageConfig {
+    /// Account sponsorships per API key
+    pub enabled: bool,
+    /// JSON file usage is snapshotted to and restored from; memory only when unset
+    pub snapshot_path: Option<PathBuf>,
+    /// Seconds between snapshots
+    pub snapshot_interval_seconds: u64,
+    /// Hourly buckets older than this many days are dropped
+    pub retention_days: u64,
+    /// Hours a sponsorship waits for its actual gas cost before the estimate is final
+    pub pending_cost_hours: u64,
+}
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            snapshot_path: None,
+            snapshot_interval_seconds: 300,
+            retention_days: 90,
+            pending_cost_hours: 24,
+        }
+    }
+}
+
+/// Length of the buckets of a usage report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageGranularity {
+    /// One bucket per UTC hour
+    Hour,
+    /// One bucket per UTC day
+    Day,
+}
+
+impl UsageGranularity {
+    /// Bucket length in seconds
+    pub fn seconds(self) -> u64 {
+        match self {
+            UsageGranularity::Hour => SECONDS_PER_HOUR,
+            UsageGranularity::Day => SECONDS_PER_DAY,
+        }
+    }
+}
+
+impl FromStr for UsageGranularity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hour" => Ok(UsageGranularity::Hour),
+            "day" => Ok(UsageGranularity::Day),
+            _ => Err(format!(
+                "Unknown granularity {:?}, expected \"hour\" or \"day\"",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for UsageGranularity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UsageGranularity::Hour => f.write_str("hour"),
+            UsageGranularity::Day => f.write_str("day"),
+        }
+    }
+}
+
+/// One sponsorship handed out under an API key
+#[derive(Debug, Clone)]
+pub struct SponsorshipUsage {
+    /// API key the sponsorship was requested with
+    pub api_key_id: String,
+    /// Sender of the sponsored operation
+    pub sender: Address,
+    /// Hash of the operation as sponsored, matched against receipts
+    pub user_op_hash: B256,
+    /// Most the operation can cost the paymaster, used until the actual cost is known
+    pub max_gas_cost: U256,
+    /// Unix time of the sponsorship
+    pub timestamp: u64,
+}
+
+/// Usage of one API key over one bucket of a report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBucket {
+    /// Unix time the bucket starts
+    pub start: u64,
+    /// Operations sponsored
+    pub sponsored_ops: u64,
+    /// Gas cost in wei: actual for mined operations, the maximum for the others
+    pub gas_cost: U256,
+    /// Operations whose cost is still the maximum, not yet seen mined
+    pub estimated_ops: u64,
+    /// Distinct senders sponsored
+    pub unique_senders: u64,
+}
+
+/// Cap on sponsored account deployments over one UTC hour or day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeploymentLimit {
+    /// Factory the cap applies to, every factory together when unset
+    pub factory: Option<Address>,
+    /// Window the deployments are counted over
+    pub window: UsageGranularity,
+    /// Deployments sponsored per window
+    pub max: u64,
+}
+
+impl DeploymentLimit {
+    /// Unix time the window containing `timestamp` ends and its count resets
+    pub fn resets_at(&self, timestamp: u64) -> u64 {
+        let step = self.window.seconds();
+        timestamp - timestamp % step + step
+    }
+}
+
+/// Storage behind usage accounting
+///
+/// The in-memory [`InMemoryUsageStore`] is the default; a database backed
+/// store only has to implement these methods.
+pub trait UsageStore: Send + Sync + fmt::Debug {
+    /// Account `usage` at its maximum gas cost
+    fn record_sponsorship(&self, usage: SponsorshipUsage);
+
+    /// Replace the maximum gas cost of a sponsored operation with its
+    /// `actual_gas_cost`, returning whether the operation was pending
+    fn record_actual_gas_cost(&self, user_op_hash: B256, actual_gas_cost: U256) -> bool;
+
+    /// Usage of `api_key_id` in buckets starting from `from` up to `to`, both unix
+    /// seconds; buckets without sponsorships are left out
+    fn report(
+        &self,
+        api_key_id: &str,
+        from: u64,
+        to: u64,
+        granularity: UsageGranularity,
+    ) -> Vec<UsageBucket>;
+
+    /// API keys with any usage recorded
+    fn api_key_ids(&self) -> Vec<String>;
+
+    /// Count a sponsored deployment through `factory` at `timestamp`, unless
+    /// it would exceed one of `limits`; the first such limit is returned and
+    /// nothing is counted
+    fn record_deployment(
+        &self,
+        factory: Address,
+        timestamp: u64,
+        limits: &[DeploymentLimit],
+    ) -> Result<(), DeploymentLimit>;
+
+    /// Take back a deployment counted by [`UsageStore::record_deployment`]
+    /// through `factory` at `timestamp` that was not sponsored after all
+    fn release_deployment(&self, factory: Address, timestamp: u64);
+
+    /// Deployments sponsored through `factory`, or through every factory when
+    /// unset, in the `window` containing `timestamp`
+    fn deployment_count(
+        &self,
+        factory: Option<Address>,
+        window: UsageGranularity,
+        timestamp: u64,
+    ) -> u64;
+
+    /// First of `limits` a deployment at `timestamp` would exceed, without
+    /// counting one
+    fn exceeded_deployment_limit(
+        &self,
+        timestamp: u64,
+        limits: &[DeploymentLimit],
+    ) -> Option<DeploymentLimit> {
+        limits
+            .iter()
+            .find(|limit| {
+                self.deployment_count(limit.factory, limit.window, timestamp) >= limit.max
+            })
+            .copied()
+    }
+}
+
+/// Deployment counted against the quotas ahead of its sponsorship
+///
+/// Dropped without [`DeploymentReservation::consume`], the deployment is
+/// released again, so an operation that ends up not sponsored gives its place
+/// in the quota back.
+#[derive(Debug)]
+pub struct DeploymentReservation {
+    store: Arc<dyn UsageStore>,
+    factory: Address,
+    timestamp: u64,
+    consumed: AtomicBool,
+}
+
+impl DeploymentReservation {
+    /// Count a deployment through `factory` at `timestamp` in `store`, unless
+    /// it would exceed one of `limits`
+    pub fn reserve(
+        store: Arc<dyn UsageStore>,
+        factory: Address,
+        timestamp: u64,
+        limits: &[DeploymentLimit],
+    ) -> Result<Self, DeploymentLimit> {
+        store.record_deployment(factory, timestamp, limits)?;
+        Ok(Self {
+            store,
+            factory,
+            timestamp,
+            consumed: AtomicBool::new(false),
+        })
+    }
+
+    /// Factory the deployment goes through
+    pub fn factory(&self) -> Address {
+        self.factory
+    }
+
+    /// Keep the deployment counted, the operation was sponsored
+    pub fn consume(&self) {
+        self.consumed.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for DeploymentReservation {
+    fn drop(&mut self) {
+        if !self.consumed.load(Ordering::Relaxed) {
+            self.store.release_deployment(self.factory, self.timestamp);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct HourUsage {
+    sponsored_ops: u64,
+    gas_cost: U256,
+    estimated_ops: u64,
+    senders: HashSet<Address>,
+}
+
+/// Sponsorship still accounted at its maximum gas cost
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingCost {
+    api_key_id: String,
+    hour: u64,
+    max_gas_cost: U256,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UsageState {
+    /// Hourly usage per API key, keyed by hour start
+    hours: HashMap<String, BTreeMap<u64, HourUsage>>,
+    pending: HashMap<B256, PendingCost>,
+    /// Sponsored deployments per factory, keyed by hour start
+    #[serde(default)]
+    deployments: HashMap<Address, BTreeMap<u64, u64>>,
+}
+
+impl UsageState {
+    /// Drop hours before `retention_cutoff` and stop waiting for costs of
+    /// sponsorships before `pending_cutoff`
+    fn prune(&mut self, retention_cutoff: u64, pending_cutoff: u64) {
+        for hours in self.hours.values_mut() {
+            *hours = hours.split_off(&retention_cutoff);
+        }
+        self.hours.retain(|_, hours| !hours.is_empty());
+        self.pending
+            .retain(|_, pending| pending.hour >= pending_cutoff);
+        for hours in self.deployments.values_mut() {
+            *hours = hours.split_off(&retention_cutoff);
+        }
+        self.deployments.retain(|_, hours| !hours.is_empty());
+    }
+
+    fn deployment_count(
+        &self,
+        factory: Option<Address>,
+        window: UsageGranularity,
+        timestamp: u64,
+    ) -> u64 {
+        let start = timestamp - timestamp % window.seconds();
+        let end = start + window.seconds();
+        let count =
+            |hours: &BTreeMap<u64, u64>| -> u64 { hours.range(start..end).map(|(_, n)| n).sum() };
+        match factory {
+            Some(factory) => self.deployments.get(&factory).map_or(0, count),
+            None => self.deployments.values().map(count).sum(),
+        }
+    }
+}
+
+/// [`UsageStore`] holding hourly aggregates in memory
+///
+/// Day buckets are built from the hours they contain, so senders are counted
+/// once per day. With a snapshot path configured, the state is restored on
+/// startup and written periodically by [`InMemoryUsageStore::spawn_snapshots`].
+#[derive(Debug)]
+pub struct InMemoryUsageStore {
+    config: UsageConfig,
+    state: Mutex<UsageState>,
+}
+
+impl Default for InMemoryUsageStore {
+    fn default() -> Self {
+        Self {
+            config: UsageConfig::default(),
+            state: Mutex::new(UsageState::default()),
+        }
+    }
+}
+
+impl InMemoryUsageStore {
+    /// Create the store, restoring the snapshot at the configured path
+    pub fn new(config: UsageConfig) -> io::Result<Self> {
+        let mut state = UsageState::default();
+        if let Some(path) = &config.snapshot_path {
+            match fs::read(path) {
+                Ok(data) => {
+                    state = serde_json::from_slice(&data)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    info!(
+                        "📊 Restored usage of {} API keys from {}",
+                        state.hours.len(),
+                        path.display()
+                    );
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(Self {
+            config,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Write the state to a temporary file and rename it over the snapshot file
+    pub fn snapshot(&self) -> io::Result<()> {
+        let Some(path) = &self.config.snapshot_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let data = serde_json::to_vec(&*self.state.lock().unwrap())?;
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    }
+
+    /// Snapshot the store every configured interval, if a snapshot path is set
+    pub fn spawn_snapshots(self: &Arc<Self>) -> Option<JoinHandle<()>> {
+        self.config.snapshot_path.as_ref()?;
+        let store = self.clone();
+        let interval = Duration::from_secs(self.config.snapshot_interval_seconds.max(1));
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = store.snapshot() {
+                    warn!("Failed to snapshot usage: {}", e);
+                }
+            }
+        }))
+    }
+
+    fn prune(&self, state: &mut UsageState, now: u64) {
+        let retention_cutoff =
+            hour_start(now).saturating_sub(self.config.retention_days * SECONDS_PER_DAY);
+        let pending_cutoff =
+            hour_start(now).saturating_sub(self.config.pending_cost_hours * SECONDS_PER_HOUR);
+        state.prune(retention_cutoff, pending_cutoff);
+    }
+}
+
+impl UsageStore for InMemoryUsageStore {
+    fn record_sponsorship(&self, usage: SponsorshipUsage) {
+        let hour = hour_start(usage.timestamp);
+        let mut state = self.state.lock().unwrap();
+        self.prune(&mut state, unix_now().max(usage.timestamp));
+
+        let bucket = state
+            .hours
+            .entry(usage.api_key_id.clone())
+            .or_default()
+            .entry(hour)
+            .or_default();
+        bucket.sponsored_ops += 1;
+        bucket.gas_cost = bucket.gas_cost.saturating_add(usage.max_gas_cost);
+        bucket.estimated_ops += 1;
+        bucket.senders.insert(usage.sender);
+
+        state.pending.insert(
+            usage.user_op_hash,
+            PendingCost {
+                api_key_id: usage.api_key_id,
+                hour,
+                max_gas_cost: usage.max_gas_cost,
+            },
+        );
+    }
+
+    fn record_actual_gas_cost(&self, user_op_hash: B256, actual_gas_cost: U256) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(pending) = state.pending.remove(&user_op_hash) else {
+            return false;
+        };
+        let Some(bucket) = state
+            .hours
+            .get_mut(&pending.api_key_id)
+            .and_then(|hours| hours.get_mut(&pending.hour))
+        else {
+            return false;
+        };
+        bucket.gas_cost = bucket
+            .gas_cost
+            .saturating_sub(pending.max_gas_cost)
+            .saturating_add(actual_gas_cost);
+        bucket.estimated_ops = bucket.estimated_ops.saturating_sub(1);
+        true
+    }
+
+    fn report(
+        &self,
+        api_key_id: &str,
+        from: u64,
+        to: u64,
+        granularity: UsageGranularity,
+    ) -> Vec<UsageBucket> {
+        let state = self.state.lock().unwrap();
+        let Some(hours) = state.hours.get(api_key_id) else {
+            return Vec::new();
+        };
+        if from > to {
+            return Vec::new();
+        }
+
+        let step = granularity.seconds();
+        let first = from - from % step;
+        let mut buckets: BTreeMap<u64, (HourUsage, HashSet<Address>)> = BTreeMap::new();
+        for (hour, usage) in hours.range(first..=to) {
+            let (total, senders) = buckets.entry(hour - hour % step).or_default();
+            total.sponsored_ops += usage.sponsored_ops;
+            total.gas_cost = total.gas_cost.saturating_add(usage.gas_cost);
+            total.estimated_ops += usage.estimated_ops;
+            senders.extend(&usage.senders);
+        }
+
+        buckets
+            .into_iter()
+            .map(|(start, (total, senders))| UsageBucket {
+                start,
+                sponsored_ops: total.sponsored_ops,
+                gas_cost: total.gas_cost,
+                estimated_ops: total.estimated_ops,
+                unique_senders: senders.len() as u64,
+            })
+            .collect()
+    }
+
+    fn api_key_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.state.lock().unwrap().hours.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    fn record_deployment(
+        &self,
+        factory: Address,
+        timestamp: u64,
+        limits: &[DeploymentLimit],
+    ) -> Result<(), DeploymentLimit> {
+        let mut state = self.state.lock().unwrap();
+        self.prune(&mut state, unix_now().max(timestamp));
+
+        if let Some(limit) = limits.iter().find(|limit| {
+            state.deployment_count(limit.factory, limit.window, timestamp) >= limit.max
+        }) {
+            return Err(*limit);
+        }
+        *state
+            .deployments
+            .entry(factory)
+            .or_default()
+            .entry(hour_start(timestamp))
+            .or_default() += 1;
+        Ok(())
+    }
+
+    fn release_deployment(&self, factory: Address, timestamp: u64) {
+        let mut state = self.state.lock().unwrap();
+        let Some(hours) = state.deployments.get_mut(&factory) else {
+            return;
+        };
+        let hour = hour_start(timestamp);
+        if let Some(count) = hours.get_mut(&hour) {
+            *count -= 1;
+            if *count == 0 {
+                hours.remove(&hour);
+            }
+        }
+        if hours.is_empty() {
+            state.deployments.remove(&factory);
+        }
+    }
+
+    fn deployment_count(
+        &self,
+        factory: Option<Address>,
+        window: UsageGranularity,
+        timestamp: u64,
+    ) -> u64 {
+        self.state
+            .lock()
+            .unwrap()
+            .deployment_count(factory, window, timestamp)
+    }
+}
+
+/// CSV export of usage of `api_key_ids`, one row per key and bucket
+pub fn usage_csv(
+    store: &dyn UsageStore,
+    api_key_ids: &[String],
+    from: u64,
+    to: u64,
+    granularity: UsageGranularity,
+) -> String {
+    let mut csv = String::from(
+        "api_key_id,granularity,bucket_start,sponsored_ops,gas_cost_wei,estimated_ops,unique_senders\n",
+    );
+    for api_key_id in api_key_ids {
+        for bucket in store.report(api_key_id, from, to, granularity) {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                csv_field(api_key_id),
+                granularity,
+                bucket.start,
+                bucket.sponsored_ops,
+                bucket.gas_cost,
+                bucket.estimated_ops,
+                bucket.unique_senders
+            ));
+        }
+    }
+    csv
+}
+
+/// Quote `value` if it would break the CSV row
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn hour_start(timestamp: u64) -> u64 {
+    timestamp - timestamp % SECONDS_PER_HOUR
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        str::FromStr,
+        sync::Arc,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use alloy_primitives::{address, Address, Bytes, U256};
+    use rundler_types::{chain::ChainSpec, v0_7, UserOperation, UserOperationVariant};
+
+    use super::*;
+    use crate::{
+        service::{PaymasterRelayService, SponsorOptions},
+        sponsorship::sponsored_max_gas_cost,
+        test_utils, PaymasterError,
+    };
+
+    const DAY: u64 = 1_700_006_400; // 2023-11-15 00:00 UTC
+
+    fn usage(key: &str, sender: u8, hash: u8, timestamp: u64) -> SponsorshipUsage {
+        SponsorshipUsage {
+            api_key_id: key.to_string(),
+            sender: Address::repeat_byte(sender),
+            user_op_hash: B256::repeat_byte(hash),
+            max_gas_cost: U256::from(1_000),
+            timestamp,
+        }
+    }
+
+    fn store() -> InMemoryUsageStore {
+        InMemoryUsageStore::new(UsageConfig {
+            retention_days: 100_000,
+            pending_cost_hours: 1_000_000,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_day_buckets_count_senders_once() {
+        let store = store();
+        store.record_sponsorship(usage("dapp", 1, 1, DAY + 10));
+        store.record_sponsorship(usage("dapp", 1, 2, DAY + 2 * SECONDS_PER_HOUR));
+        store.record_sponsorship(usage("dapp", 2, 3, DAY + 2 * SECONDS_PER_HOUR + 5));
+
+        let hours = store.report("dapp", DAY, DAY + SECONDS_PER_DAY, UsageGranularity::Hour);
+        assert_eq!(hours.len(), 2);
+        assert_eq!(hours[1].start, DAY + 2 * SECONDS_PER_HOUR);
+        assert_eq!(hours[1].unique_senders, 2);
+
+        let days = store.report("dapp", DAY, DAY + SECONDS_PER_DAY, UsageGranularity::Day);
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].start, DAY);
+        assert_eq!(days[0].sponsored_ops, 3);
+        assert_eq!(days[0].unique_senders, 2);
+    }
+
+    #[test]
+    fn test_actual_cost_replaces_estimate_once() {
+        let store = store();
+        store.record_sponsorship(usage("dapp", 1, 1, DAY));
+
+        assert!(store.record_actual_gas_cost(B256::repeat_byte(1), U256::from(400)));
+        assert!(!store.record_actual_gas_cost(B256::repeat_byte(1), U256::from(400)));
+
+        let report = store.report("dapp", DAY, DAY, UsageGranularity::Hour);
+        assert_eq!(report[0].gas_cost, U256::from(400));
+        assert_eq!(report[0].estimated_ops, 0);
+    }
+
+    #[test]
+    fn test_deployment_counts_reset_and_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = UsageConfig {
+            snapshot_path: Some(dir.path().join("usage.json")),
+            retention_days: 100_000,
+            ..Default::default()
+        };
+        let factory = Address::repeat_byte(0xfa);
+        let limits = [DeploymentLimit {
+            factory: Some(factory),
+            window: UsageGranularity::Day,
+            max: 2,
+        }];
+
+        let store = InMemoryUsageStore::new(config.clone()).unwrap();
+        assert!(store.record_deployment(factory, DAY + 10, &limits).is_ok());
+        assert!(store
+            .record_deployment(factory, DAY + 5 * SECONDS_PER_HOUR, &limits)
+            .is_ok());
+        assert_eq!(
+            store.record_deployment(factory, DAY + 6 * SECONDS_PER_HOUR, &limits),
+            Err(limits[0])
+        );
+        assert_eq!(limits[0].resets_at(DAY + 10), DAY + SECONDS_PER_DAY);
+        store.snapshot().unwrap();
+
+        let restored = InMemoryUsageStore::new(config).unwrap();
+        assert_eq!(
+            restored.deployment_count(Some(factory), UsageGranularity::Day, DAY + 10),
+            2
+        );
+        assert_eq!(
+            restored.deployment_count(None, UsageGranularity::Hour, DAY + 5 * SECONDS_PER_HOUR),
+            1
+        );
+        assert_eq!(
+            restored.exceeded_deployment_limit(DAY + SECONDS_PER_HOUR, &limits),
+            Some(limits[0])
+        );
+        // The next UTC day starts from zero
+        assert!(restored
+            .record_deployment(factory, DAY + SECONDS_PER_DAY, &limits)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_unconsumed_reservation_released() {
+        let store: Arc<dyn UsageStore> = Arc::new(store());
+        let factory = Address::repeat_byte(0xfa);
+        let limits = [DeploymentLimit {
+            factory: None,
+            window: UsageGranularity::Day,
+            max: 1,
+        }];
+        let count = || store.deployment_count(None, UsageGranularity::Day, DAY);
+
+        let reservation =
+            DeploymentReservation::reserve(store.clone(), factory, DAY, &limits).unwrap();
+        assert_eq!(count(), 1);
+        // Held until dropped
+        assert!(DeploymentReservation::reserve(store.clone(), factory, DAY, &limits).is_err());
+        drop(reservation);
+        assert_eq!(count(), 0);
+
+        let reservation =
+            DeploymentReservation::reserve(store.clone(), factory, DAY, &limits).unwrap();
+        reservation.consume();
+        drop(reservation);
+        assert_eq!(count(), 1);
+    }
+
+    #[test]
+    fn test_csv_quotes_key_ids() {
+        let store = store();
+        store.record_sponsorship(usage("a,b", 1, 1, DAY));
+
+        let csv = usage_csv(
+            &store,
+            &["a,b".to_string()],
+            DAY,
+            DAY,
+            UsageGranularity::Day,
+        );
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1], format!("\"a,b\",day,{},1,1000,1,1", DAY));
+    }
+
+    const SENDER_A: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const SENDER_B: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+
+    fn create_service() -> PaymasterRelayService {
+        test_utils::service(&format!("senders = [\"{}\", \"{}\"]\n", SENDER_A, SENDER_B))
+    }
+
+    fn op(sender: &str, nonce: u64) -> (UserOperationVariant, ethers::types::Address) {
+        let op = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender: Address::from_str(sender).unwrap(),
+                nonce: U256::from(nonce),
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 1_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        let entry_point = ChainSpec::default().entry_point_address_v0_7;
+        (
+            UserOperationVariant::V0_7(op),
+            ethers::types::Address::from_slice(entry_point.as_slice()),
+        )
+    }
+
+    fn options(requester: Option<&str>) -> SponsorOptions {
+        SponsorOptions {
+            return_full_operation: true,
+            requester: requester.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[tokio::test]
+    async fn test_usage_accounted_per_api_key() {
+        let store = Arc::new(InMemoryUsageStore::new(UsageConfig::default()).unwrap());
+        let service = create_service().with_usage_store(store.clone());
+
+        let mut dapp_a = Vec::new();
+        for (sender, nonce) in [(SENDER_A, 0), (SENDER_A, 1), (SENDER_B, 0)] {
+            let (op, entry_point) = op(sender, nonce);
+            let result = service
+                .sponsor_user_operation(op, entry_point, options(Some("dapp-a")))
+                .await
+                .unwrap();
+            dapp_a.push(result.sponsored_user_op.unwrap());
+        }
+        let (op_b, entry_point) = op(SENDER_B, 1);
+        let result = service
+            .sponsor_user_operation(op_b, entry_point, options(Some("dapp-b")))
+            .await
+            .unwrap();
+        let dapp_b_max_cost = sponsored_max_gas_cost(&result.sponsored_user_op.unwrap());
+        let (anonymous, entry_point) = op(SENDER_A, 2);
+        service
+            .sponsor_user_operation(anonymous, entry_point, options(None))
+            .await
+            .unwrap();
+
+        // Costs are the maximum until the operations are seen mined
+        let max_cost = dapp_a
+            .iter()
+            .fold(U256::ZERO, |total, op| total + sponsored_max_gas_cost(op));
+        let report = service
+            .usage_report("dapp-a", 0, now(), UsageGranularity::Day)
+            .unwrap();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].sponsored_ops, 3);
+        assert_eq!(report[0].estimated_ops, 3);
+        assert_eq!(report[0].unique_senders, 2);
+        assert_eq!(report[0].gas_cost, max_cost);
+
+        // Receipts settle the cost of the mined operations only
+        assert!(service.record_actual_gas_cost(dapp_a[0].hash(), U256::from(1_000)));
+        assert!(service.record_actual_gas_cost(dapp_a[1].hash(), U256::from(2_000)));
+        assert!(!service.record_actual_gas_cost(dapp_a[1].hash(), U256::from(2_000)));
+        let report = service
+            .usage_report("dapp-a", 0, now(), UsageGranularity::Day)
+            .unwrap();
+        assert_eq!(report[0].estimated_ops, 1);
+        assert_eq!(
+            report[0].gas_cost,
+            U256::from(3_000) + sponsored_max_gas_cost(&dapp_a[2])
+        );
+
+        let report = service
+            .usage_report("dapp-b", 0, now(), UsageGranularity::Hour)
+            .unwrap();
+        assert_eq!(report[0].sponsored_ops, 1);
+        assert_eq!(report[0].gas_cost, dapp_b_max_cost);
+        assert_eq!(
+            store.report(ANONYMOUS_KEY_ID, 0, now(), UsageGranularity::Hour)[0].sponsored_ops,
+            1
+        );
+
+        let csv = usage_csv(
+            store.as_ref(),
+            &store.api_key_ids(),
+            0,
+            now(),
+            UsageGranularity::Day,
+        );
+        let rows: Vec<_> = csv.lines().collect();
+        assert_eq!(rows.len(), 4);
+        assert!(rows[0].starts_with("api_key_id,granularity,bucket_start"));
+        assert!(rows[2].starts_with("dapp-a,day,"));
+        assert!(rows[2].ends_with(",1,2"));
+    }
+
+    #[tokio::test]
+    async fn test_replays_and_rejections_not_accounted() {
+        let store = Arc::new(InMemoryUsageStore::new(UsageConfig::default()).unwrap());
+        let service = create_service().with_usage_store(store.clone());
+
+        let replayed = SponsorOptions {
+            idempotency_key: Some("retry-1".to_string()),
+            ..options(Some("dapp-a"))
+        };
+        for _ in 0..3 {
+            let (op, entry_point) = op(SENDER_A, 0);
+            service
+                .sponsor_user_operation(op, entry_point, replayed.clone())
+                .await
+                .unwrap();
+        }
+        let (denied, entry_point) = op("0x0000000000000000000000000000000000000001", 0);
+        assert!(service
+            .sponsor_user_operation(denied, entry_point, options(Some("dapp-a")))
+            .await
+            .is_err());
+
+        let report = store.report("dapp-a", 0, now(), UsageGranularity::Hour);
+        assert_eq!(report[0].sponsored_ops, 1);
+    }
+
+    #[tokio::test]
+    async fn test_usage_report_requires_accounting() {
+        let service = create_service();
+        let err = service
+            .usage_report("dapp-a", 0, now(), UsageGranularity::Hour)
+            .unwrap_err();
+        assert!(matches!(err, PaymasterError::InvalidRequest(_)));
+        assert!(!service.record_actual_gas_cost(Default::default(), U256::from(1)));
+    }
+
+    const ALICE: Address = address!("00000000000000000000000000000000000000a1");
+    const FACTORY_A: Address = address!("00000000000000000000000000000000000000fa");
+    const FACTORY_B: Address = address!("00000000000000000000000000000000000000fb");
+    const FACTORY_C: Address = address!("00000000000000000000000000000000000000fc");
+
+    const SECONDS_PER_DAY: u64 = 86_400;
+
+    /// Service whose default policy allows ALICE with the extra `rules`
+    fn create_quota_service(rules: &str) -> PaymasterRelayService {
+        test_utils::service(&format!("senders = [\"{}\"]\n{}", ALICE, rules))
+    }
+
+    /// Operation of ALICE, deploying the account through `factory` when set
+    fn deployment_op(
+        factory: Option<Address>,
+        nonce: u64,
+    ) -> (UserOperationVariant, ethers::types::Address) {
+        let mut builder = v0_7::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_7::UserOperationRequiredFields {
+                sender: ALICE,
+                nonce: U256::from(nonce),
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 21_000,
+                max_fee_per_gas: 2_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                signature: Bytes::new(),
+            },
+        );
+        if let Some(factory) = factory {
+            builder = builder.factory(factory, Bytes::from_static(&[0x5f, 0xbf, 0xb9, 0xcf]));
+        }
+        let entry_point = ChainSpec::default().entry_point_address_v0_7;
+        (
+            UserOperationVariant::V0_7(builder.build()),
+            ethers::types::Address::from_slice(entry_point.as_slice()),
+        )
+    }
+
+    async fn sponsor(
+        service: &PaymasterRelayService,
+        factory: Option<Address>,
+        nonce: u64,
+    ) -> Result<(), PaymasterError> {
+        let (op, entry_point) = deployment_op(factory, nonce);
+        service
+            .sponsor_user_operation(op, entry_point, SponsorOptions::default())
+            .await
+            .map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn test_factory_capped_without_affecting_others() {
+        let service = create_quota_service("[default.deployments]\nper_day = 2\n");
+
+        sponsor(&service, Some(FACTORY_A), 0).await.unwrap();
+        sponsor(&service, Some(FACTORY_A), 1).await.unwrap();
+        let err = sponsor(&service, Some(FACTORY_A), 2).await.unwrap_err();
+        match &err {
+            PaymasterError::DeploymentQuotaExceeded {
+                factory,
+                limit,
+                resets_at,
+            } => {
+                assert_eq!(*factory, FACTORY_A);
+                assert_eq!(limit.factory, Some(FACTORY_A));
+                assert_eq!(limit.window, UsageGranularity::Day);
+                assert_eq!(limit.max, 2);
+                // At the start of the next UTC day
+                assert_eq!(resets_at % SECONDS_PER_DAY, 0);
+                assert!(*resets_at > now() && *resets_at <= now() + SECONDS_PER_DAY);
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+        assert_eq!(err.category(), "policy_rejection");
+        assert!(err.to_string().contains(&FACTORY_A.to_string()), "{}", err);
+
+        // Another factory has a quota of its own
+        sponsor(&service, Some(FACTORY_B), 3).await.unwrap();
+        sponsor(&service, Some(FACTORY_B), 4).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_operations_deploying_nothing_bypass_quotas() {
+        let service = create_quota_service(
+            "[default.deployments]\nper_hour = 1\n\n[default.deployments.total]\nper_day = 1\n",
+        );
+
+        sponsor(&service, Some(FACTORY_A), 0).await.unwrap();
+        for nonce in 1..5 {
+            sponsor(&service, None, nonce).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_total_quota_across_factories() {
+        let service = create_quota_service(&format!(
+            "[default.deployments.total]\nper_hour = 2\n\n[default.deployments.factories.\"{}\"]\nper_day = 1\n",
+            FACTORY_B
+        ));
+
+        sponsor(&service, Some(FACTORY_A), 0).await.unwrap();
+        // FACTORY_B's own quota overrides the default of no per-factory limit
+        sponsor(&service, Some(FACTORY_B), 1).await.unwrap();
+        let err = sponsor(&service, Some(FACTORY_B), 2).await.unwrap_err();
+        let PaymasterError::DeploymentQuotaExceeded { limit, .. } = err else {
+            panic!("unexpected error {:?}", err);
+        };
+        assert_eq!(limit.factory, Some(FACTORY_B));
+
+        let err = sponsor(&service, Some(FACTORY_C), 3).await.unwrap_err();
+        match err {
+            PaymasterError::DeploymentQuotaExceeded {
+                factory,
+                limit,
+                resets_at,
+            } => {
+                assert_eq!(factory, FACTORY_C);
+                assert_eq!(limit.factory, None);
+                assert_eq!(limit.window, UsageGranularity::Hour);
+                assert_eq!(resets_at % 3_600, 0);
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_factory_allowlist() {
+        let service = create_quota_service(&format!("allowed_factories = [\"{}\"]\n", FACTORY_A));
+
+        sponsor(&service, Some(FACTORY_A), 0).await.unwrap();
+        sponsor(&service, None, 1).await.unwrap();
+        let err = sponsor(&service, Some(FACTORY_B), 2).await.unwrap_err();
+        match err {
+            PaymasterError::PolicyRejected(reason) => {
+                assert!(reason.contains("not allowed"), "{}", reason);
+                assert!(reason.contains(&FACTORY_B.to_string()), "{}", reason);
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+}
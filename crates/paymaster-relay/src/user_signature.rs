@@ -0,0 +1,513 @@
+// paymaster-relay/src/user_signature.rs
+// Local pre-verification of the user signature of a dual-signature request. Only a
+// cheap filter against obviously invalid signatures: the KMS stays authoritative.
+
+use ethers::{
+    core::k256::ecdsa::VerifyingKey,
+    types::{Address, RecoveryMessage, Signature},
+    utils::public_key_to_address,
+};
+use ring::{
+    digest::{digest, SHA256},
+    signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1, ECDSA_P256_SHA256_FIXED},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::kms::KmsError;
+
+/// Algorithm a user signature is verified with before the KMS call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UserSignatureAlgorithm {
+    /// ECDSA over secp256k1, recovered to the account address
+    Secp256k1,
+    /// WebAuthn assertion of a P-256 passkey
+    WebauthnP256,
+}
+
+impl UserSignatureAlgorithm {
+    /// Algorithm of `signature_format`, `None` for formats without a local verifier
+    pub fn of_format(signature_format: &str) -> Option<Self> {
+        match signature_format.to_ascii_lowercase().as_str() {
+            "erc4337" | "secp256k1" => Some(UserSignatureAlgorithm::Secp256k1),
+            "webauthn" | "passkey" => Some(UserSignatureAlgorithm::WebauthnP256),
+            _ => None,
+        }
+    }
+
+    /// Check that `signature` by the holder of `public_key` covers `user_op_hash`
+    pub fn verify(
+        self,
+        user_op_hash: [u8; 32],
+        signature: &str,
+        public_key: &str,
+    ) -> Result<(), KmsError> {
+        let result = match self {
+            UserSignatureAlgorithm::Secp256k1 => {
+                verify_secp256k1(user_op_hash, signature, public_key)
+            }
+            UserSignatureAlgorithm::WebauthnP256 => {
+                verify_webauthn(user_op_hash, signature, public_key)
+            }
+        };
+        result.map_err(|reason| KmsError::InvalidUserSignature { reason })
+    }
+}
+
+/// Local verification of user signatures (`local_verification` of the AirAccount KMS config)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct LocalVerificationConfig {
+    /// Verify user signatures before calling the KMS
+    pub enabled: bool,
+    /// Signature formats forwarded to the KMS unverified, e.g. passkeys the
+    /// gateway has no public key for
+    pub skip_formats: Vec<String>,
+}
+
+impl Default for LocalVerificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            skip_formats: vec![],
+        }
+    }
+}
+
+impl LocalVerificationConfig {
+    /// Algorithm verifying `signature_format` locally, `None` when it is not verified
+    pub fn algorithm_for(&self, signature_format: &str) -> Option<UserSignatureAlgorithm> {
+        if !self.enabled
+            || self
+                .skip_formats
+                .iter()
+                .any(|skipped| skipped.eq_ignore_ascii_case(signature_format))
+        {
+            return None;
+        }
+        UserSignatureAlgorithm::of_format(signature_format)
+    }
+}
+
+/// Recover the signer, accepting both the raw hash and its EIP-191 message as
+/// wallets differ in what they sign
+fn verify_secp256k1(
+    user_op_hash: [u8; 32],
+    signature: &str,
+    public_key: &str,
+) -> Result<(), String> {
+    let signature = Signature::try_from(decode_hex(signature, "signature")?.as_slice())
+        .map_err(|e| format!("invalid secp256k1 signature: {}", e))?;
+    let expected = secp256k1_address(public_key)?;
+    let recovered: Vec<Address> = [
+        RecoveryMessage::Hash(user_op_hash.into()),
+        RecoveryMessage::Data(user_op_hash.to_vec()),
+    ]
+    .into_iter()
+    .filter_map(|message| signature.recover(message).ok())
+    .collect();
+    if recovered.contains(&expected) {
+        Ok(())
+    } else {
+        Err(format!(
+            "secp256k1 signature was not made by {:?}, it recovers to {:?}",
+            expected, recovered
+        ))
+    }
+}
+
+/// Account address of `public_key`: an address, or a SEC1 encoded public key
+fn secp256k1_address(public_key: &str) -> Result<Address, String> {
+    let bytes = decode_hex(public_key, "public key")?;
+    if bytes.len() == 20 {
+        return Ok(Address::from_slice(&bytes));
+    }
+    VerifyingKey::from_sec1_bytes(&bytes)
+        .map(|key| public_key_to_address(&key))
+        .map_err(|_| {
+            format!(
+                "public key is neither an address nor a secp256k1 key ({} bytes)",
+                bytes.len()
+            )
+        })
+}
+
+/// WebAuthn assertion carried as the user signature of the `webauthn` format
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WebauthnAssertion {
+    /// Hex authenticator data
+    authenticator_data: String,
+    /// Client data exactly as the authenticator produced it
+    #[serde(rename = "clientDataJSON")]
+    client_data_json: String,
+    /// Hex ECDSA signature, DER or 64 byte r || s
+    signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+}
+
+/// Verify a WebAuthn assertion whose challenge is the base64url userOpHash
+/// against the stored uncompressed P-256 public key
+fn verify_webauthn(
+    user_op_hash: [u8; 32],
+    signature: &str,
+    public_key: &str,
+) -> Result<(), String> {
+    let assertion: WebauthnAssertion = serde_json::from_str(signature)
+        .map_err(|e| format!("malformed WebAuthn assertion: {}", e))?;
+    let authenticator_data = decode_hex(&assertion.authenticator_data, "authenticatorData")?;
+    // rpIdHash (32) || flags (1) || signCount (4)
+    if authenticator_data.len() < 37 {
+        return Err(format!(
+            "authenticatorData is {} bytes, expected at least 37",
+            authenticator_data.len()
+        ));
+    }
+    if authenticator_data[32] & 0x01 == 0 {
+        return Err("authenticatorData lacks the user presence flag".to_string());
+    }
+
+    let client_data: ClientData = serde_json::from_str(&assertion.client_data_json)
+        .map_err(|e| format!("malformed clientDataJSON: {}", e))?;
+    if client_data.kind != "webauthn.get" {
+        return Err(format!(
+            "clientDataJSON type is {:?}, expected \"webauthn.get\"",
+            client_data.kind
+        ));
+    }
+    let expected = webauthn_challenge(user_op_hash);
+    if client_data.challenge != expected {
+        return Err(format!(
+            "WebAuthn challenge {} does not bind userOpHash 0x{} ({})",
+            client_data.challenge,
+            hex::encode(user_op_hash),
+            expected
+        ));
+    }
+
+    let public_key = decode_hex(public_key, "public key")?;
+    if public_key.len() != 65 || public_key[0] != 0x04 {
+        return Err(format!(
+            "public key is {} bytes, expected an uncompressed P-256 point",
+            public_key.len()
+        ));
+    }
+    let signature = decode_hex(&assertion.signature, "WebAuthn signature")?;
+    let algorithm = if signature.len() == 64 {
+        &ECDSA_P256_SHA256_FIXED
+    } else {
+        &ECDSA_P256_SHA256_ASN1
+    };
+    let mut message = authenticator_data;
+    message.extend_from_slice(digest(&SHA256, assertion.client_data_json.as_bytes()).as_ref());
+    UnparsedPublicKey::new(algorithm, &public_key)
+        .verify(&message, &signature)
+        .map_err(|_| "P-256 signature does not match the passkey public key".to_string())
+}
+
+/// Challenge a WebAuthn assertion over `user_op_hash` carries in its client data
+pub fn webauthn_challenge(user_op_hash: [u8; 32]) -> String {
+    base64url(&user_op_hash)
+}
+
+fn decode_hex(value: &str, what: &str) -> Result<Vec<u8>, String> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    hex::decode(digits).map_err(|e| format!("{} is not hex: {}", what, e))
+}
+
+/// Unpadded base64url, the encoding of WebAuthn challenges
+fn base64url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| {
+            bits | ((*byte as u32) << (16 - 8 * i))
+        });
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[((bits >> (18 - 6 * i)) & 0x3f) as usize] as char);
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        signers::{LocalWallet, Signer},
+        types::H256,
+    };
+    use ring::{
+        rand::SystemRandom,
+        signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING},
+    };
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{kms::KmsError, AirAccountKmsClient, AirAccountKmsConfig, PaymasterKeyManager};
+
+    #[test]
+    fn test_base64url() {
+        assert_eq!(base64url(b""), "");
+        assert_eq!(base64url(b"f"), "Zg");
+        assert_eq!(base64url(b"fo"), "Zm8");
+        assert_eq!(base64url(b"foo"), "Zm9v");
+        assert_eq!(base64url(&[0xfb, 0xff]), "-_8");
+    }
+
+    #[test]
+    fn test_format_selection() {
+        let config = LocalVerificationConfig {
+            skip_formats: vec!["WebAuthn".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            config.algorithm_for("erc4337"),
+            Some(UserSignatureAlgorithm::Secp256k1)
+        );
+        assert_eq!(config.algorithm_for("webauthn"), None);
+        assert_eq!(config.algorithm_for("bls"), None);
+        let disabled = LocalVerificationConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        assert_eq!(disabled.algorithm_for("erc4337"), None);
+    }
+
+    const USER_KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+    const OTHER_KEY: &str = "0x5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdab365a";
+
+    fn user_op() -> Value {
+        json!({
+            "sender": "0x742D35Cc6634C0532925a3b8D6C18E3CB1EB98C1",
+            "nonce": "0x0",
+            "initCode": "0x",
+            "callData": "0x",
+            "callGasLimit": "0x186a0",
+            "verificationGasLimit": "0x186a0",
+            "preVerificationGas": "0x5208",
+            "maxFeePerGas": "0x59682f00",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "paymasterAndData": "0x"
+        })
+    }
+
+    fn user_op_hash() -> [u8; 32] {
+        AirAccountKmsClient::get_user_operation_hash(&user_op()).unwrap()
+    }
+
+    /// Client of a KMS nobody listens at, so reaching the KMS is a connection error
+    async fn unreachable_client(
+        local_verification: LocalVerificationConfig,
+    ) -> AirAccountKmsClient {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed = listener.local_addr().unwrap();
+        drop(listener);
+        let config = AirAccountKmsConfig {
+            base_url: format!("http://{}", closed),
+            insecure_allow_http: true,
+            timeout_seconds: 5,
+            local_verification,
+            ..Default::default()
+        };
+        AirAccountKmsClient::new(config, PaymasterKeyManager::new()).unwrap()
+    }
+
+    async fn sign(
+        client: &AirAccountKmsClient,
+        format: &str,
+        signature: &str,
+        public_key: &str,
+    ) -> KmsError {
+        client
+            .sign_user_operation(
+                &user_op(),
+                "passkey_user_alice",
+                format,
+                signature,
+                public_key,
+            )
+            .await
+            .unwrap_err()
+            .downcast::<KmsError>()
+            .expect("KMS error")
+    }
+
+    async fn secp256k1_signature(key: &str) -> String {
+        let wallet: LocalWallet = key.parse().unwrap();
+        let signature = wallet.sign_message(user_op_hash()).await.unwrap();
+        format!("0x{}", hex::encode(signature.to_vec()))
+    }
+
+    fn user_address() -> String {
+        format!("{:?}", USER_KEY.parse::<LocalWallet>().unwrap().address())
+    }
+
+    #[tokio::test]
+    async fn test_valid_secp256k1_signature_goes_to_kms() {
+        let client = unreachable_client(LocalVerificationConfig::default()).await;
+        let signature = secp256k1_signature(USER_KEY).await;
+
+        assert_eq!(
+            client
+                .verify_user_signature(&user_op(), "erc4337", &signature, &user_address())
+                .unwrap(),
+            Some(UserSignatureAlgorithm::Secp256k1)
+        );
+        // A signature over the raw hash passes as well
+        let wallet: LocalWallet = USER_KEY.parse().unwrap();
+        let raw = wallet.sign_hash(H256(user_op_hash())).unwrap();
+        let raw = format!("0x{}", hex::encode(raw.to_vec()));
+        assert!(client
+            .verify_user_signature(&user_op(), "erc4337", &raw, &user_address())
+            .is_ok());
+
+        let err = sign(&client, "erc4337", &signature, &user_address()).await;
+        assert!(matches!(err, KmsError::Connection { .. }), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_wrong_key_rejected_before_kms() {
+        let client = unreachable_client(LocalVerificationConfig::default()).await;
+        let signature = secp256k1_signature(OTHER_KEY).await;
+
+        let err = sign(&client, "erc4337", &signature, &user_address()).await;
+        assert!(
+            matches!(err, KmsError::InvalidUserSignature { .. }),
+            "{}",
+            err
+        );
+        assert!(err.to_string().contains("was not made by"), "{}", err);
+        assert!(!err.is_retryable());
+
+        let err = sign(&client, "erc4337", "0x1234", &user_address()).await;
+        assert!(
+            matches!(err, KmsError::InvalidUserSignature { .. }),
+            "{}",
+            err
+        );
+
+        // With local verification off the KMS judges the signature
+        let client = unreachable_client(LocalVerificationConfig {
+            enabled: false,
+            ..Default::default()
+        })
+        .await;
+        let err = sign(&client, "erc4337", &signature, &user_address()).await;
+        assert!(matches!(err, KmsError::Connection { .. }), "{}", err);
+    }
+
+    /// Passkey and a WebAuthn assertion of it over the operation
+    fn passkey_assertion(challenge: &str, kind: &str) -> (String, Value) {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key_pair =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+                .unwrap();
+
+        // rpIdHash, flags with user presence and verification, signCount
+        let mut authenticator_data = vec![0x11; 32];
+        authenticator_data.extend_from_slice(&[0x05, 0, 0, 0, 1]);
+        let client_data = format!(
+            r#"{{"type":"{}","challenge":"{}","origin":"https://wallet.example"}}"#,
+            kind, challenge
+        );
+        let mut message = authenticator_data.clone();
+        message.extend_from_slice(
+            ring::digest::digest(&ring::digest::SHA256, client_data.as_bytes()).as_ref(),
+        );
+        let signature = key_pair.sign(&rng, &message).unwrap();
+
+        (
+            format!("0x{}", hex::encode(key_pair.public_key().as_ref())),
+            json!({
+                "authenticatorData": format!("0x{}", hex::encode(&authenticator_data)),
+                "clientDataJSON": client_data,
+                "signature": format!("0x{}", hex::encode(signature.as_ref())),
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_webauthn_assertion_verified() {
+        let client = unreachable_client(LocalVerificationConfig::default()).await;
+        let (public_key, assertion) =
+            passkey_assertion(&webauthn_challenge(user_op_hash()), "webauthn.get");
+
+        assert_eq!(
+            client
+                .verify_user_signature(&user_op(), "webauthn", &assertion.to_string(), &public_key)
+                .unwrap(),
+            Some(UserSignatureAlgorithm::WebauthnP256)
+        );
+        let err = sign(&client, "webauthn", &assertion.to_string(), &public_key).await;
+        assert!(matches!(err, KmsError::Connection { .. }), "{}", err);
+
+        // Another passkey's public key
+        let (other_key, _) = passkey_assertion("", "webauthn.get");
+        let err = sign(&client, "webauthn", &assertion.to_string(), &other_key).await;
+        assert!(err.to_string().contains("does not match"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_webauthn_envelope_rejected() {
+        let client = unreachable_client(LocalVerificationConfig::default()).await;
+        let challenge = webauthn_challenge(user_op_hash());
+        let (public_key, assertion) = passkey_assertion(&challenge, "webauthn.get");
+
+        let mut short = assertion.clone();
+        short["authenticatorData"] = json!("0x1122");
+        let mut missing = assertion.clone();
+        missing.as_object_mut().unwrap().remove("clientDataJSON");
+        let (other_op_key, other_op) =
+            passkey_assertion(&webauthn_challenge([0xab; 32]), "webauthn.get");
+        let (create_key, create) = passkey_assertion(&challenge, "webauthn.create");
+
+        for (envelope, public_key, reason) in [
+            (
+                "0x1234".to_string(),
+                public_key.as_str(),
+                "malformed WebAuthn",
+            ),
+            (missing.to_string(), public_key.as_str(), "clientDataJSON"),
+            (
+                short.to_string(),
+                public_key.as_str(),
+                "expected at least 37",
+            ),
+            (
+                other_op.to_string(),
+                other_op_key.as_str(),
+                "does not bind userOpHash",
+            ),
+            (create.to_string(), create_key.as_str(), "webauthn.get"),
+        ] {
+            let err = sign(&client, "webauthn", &envelope, public_key).await;
+            assert!(
+                matches!(err, KmsError::InvalidUserSignature { .. }),
+                "{}",
+                err
+            );
+            assert!(err.to_string().contains(reason), "{}", err);
+        }
+
+        // Formats configured as unverifiable go straight to the KMS
+        let client = unreachable_client(LocalVerificationConfig {
+            skip_formats: vec!["webauthn".to_string()],
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(
+            client
+                .verify_user_signature(&user_op(), "webauthn", "0x1234", &public_key)
+                .unwrap(),
+            None
+        );
+    }
+}
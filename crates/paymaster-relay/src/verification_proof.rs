@@ -0,0 +1,353 @@
+// paymaster-relay/src/verification_proof.rs
+// Bounded store of dual-signature verification evidence, queryable by userOpHash.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::B256;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::user_signature::UserSignatureAlgorithm;
+
+const MILLIS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// Verification proof store configuration (`[paymaster_relay.verification_proofs]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct VerificationProofConfig {
+    /// JSON file the store is saved to and restored from; memory only when unset
+    pub path: Option<PathBuf>,
+    /// Records kept before the least recently completed one is evicted
+    pub max_entries: usize,
+    /// Records older than this many days are dropped
+    pub retention_days: u64,
+}
+
+impl Default for VerificationProofConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            max_entries: 10_000,
+            retention_days: 30,
+        }
+    }
+}
+
+/// What the TEE KMS signed and with which key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KmsSigningSummary {
+    /// Signature returned by the KMS
+    pub signature: String,
+    /// Paymaster that authorized the signing request
+    pub paymaster_address: String,
+    /// Whether the KMS ran in dual-signature mode
+    pub dual_signature_mode: bool,
+}
+
+/// Checks that passed before the KMS signed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationSummary {
+    /// Paymaster signature verified by the KMS
+    pub paymaster_verified: bool,
+    /// User passkey signature verified by the KMS
+    pub user_passkey_verified: bool,
+    /// Algorithm the user signature was verified with before the KMS call,
+    /// `None` when it was not verified locally
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_verification: Option<UserSignatureAlgorithm>,
+    /// Account balance seen by the business rules
+    pub balance: String,
+    /// Membership level seen by the business rules
+    pub membership_level: String,
+    /// Unix time the business rules approved the request
+    pub approved_at: u64,
+}
+
+/// Evidence of one dual-signature signing, returned by `superRelay_getVerificationProof`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredVerificationProof {
+    pub user_op_hash: B256,
+    pub account_id: String,
+    /// TEE device that produced the signature
+    pub tee_device_id: String,
+    pub signing: KmsSigningSummary,
+    pub validation: ValidationSummary,
+    /// Proof timestamp reported by the KMS
+    pub proof_timestamp: String,
+    /// Unix milliseconds the signing request was started
+    pub requested_at: u64,
+    /// Unix milliseconds the signed response was received
+    pub completed_at: u64,
+}
+
+#[derive(Default)]
+struct ProofEntries {
+    by_hash: HashMap<B256, StoredVerificationProof>,
+    /// (completed_at, hash), oldest first
+    by_completion: BTreeSet<(u64, B256)>,
+}
+
+impl ProofEntries {
+    fn insert(&mut self, proof: StoredVerificationProof) {
+        self.remove(&proof.user_op_hash);
+        self.by_completion
+            .insert((proof.completed_at, proof.user_op_hash));
+        self.by_hash.insert(proof.user_op_hash, proof);
+    }
+
+    fn remove(&mut self, hash: &B256) {
+        if let Some(old) = self.by_hash.remove(hash) {
+            self.by_completion.remove(&(old.completed_at, *hash));
+        }
+    }
+
+    /// Drop records completed before `cutoff`, then the oldest beyond `max_entries`
+    fn evict(&mut self, cutoff: u64, max_entries: usize) -> usize {
+        let mut evicted = 0;
+        while let Some(&(completed_at, hash)) = self.by_completion.first() {
+            if completed_at >= cutoff && self.by_hash.len() <= max_entries {
+                break;
+            }
+            self.remove(&hash);
+            evicted += 1;
+        }
+        evicted
+    }
+}
+
+/// Verification proofs of completed dual-signature signings
+///
+/// Bounded by entry count and age; the least recently completed record is
+/// evicted first. With a path configured, the store is rewritten after every
+/// insert and reloaded on startup.
+pub struct VerificationProofStore {
+    config: VerificationProofConfig,
+    entries: Mutex<ProofEntries>,
+}
+
+impl VerificationProofStore {
+    /// Create the store, restoring records saved at the configured path
+    pub fn new(config: VerificationProofConfig) -> io::Result<Self> {
+        let mut entries = ProofEntries::default();
+        if let Some(path) = &config.path {
+            match fs::read(path) {
+                Ok(data) => {
+                    let proofs: Vec<StoredVerificationProof> = serde_json::from_slice(&data)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    for proof in proofs {
+                        entries.insert(proof);
+                    }
+                    info!(
+                        "📜 Restored {} verification proofs from {}",
+                        entries.by_hash.len(),
+                        path.display()
+                    );
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let store = Self {
+            config,
+            entries: Mutex::new(entries),
+        };
+        store.entries.lock().unwrap().evict(
+            store.retention_cutoff(unix_millis()),
+            store.config.max_entries,
+        );
+        Ok(store)
+    }
+
+    /// Record `proof`, replacing an earlier one for the same operation
+    pub fn insert(&self, proof: StoredVerificationProof) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(proof);
+        entries.evict(
+            self.retention_cutoff(unix_millis()),
+            self.config.max_entries,
+        );
+
+        if let Err(e) = self.persist(&entries) {
+            warn!("Failed to save verification proofs: {}", e);
+        }
+    }
+
+    /// Proof recorded for `user_op_hash`, unless evicted or past retention
+    pub fn get(&self, user_op_hash: &B256) -> Option<StoredVerificationProof> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .by_hash
+            .get(user_op_hash)
+            .filter(|proof| proof.completed_at >= self.retention_cutoff(unix_millis()))
+            .cloned()
+    }
+
+    /// Number of stored proofs
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().by_hash.len()
+    }
+
+    /// Whether no proof is stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn retention_cutoff(&self, now: u64) -> u64 {
+        now.saturating_sub(self.config.retention_days.saturating_mul(MILLIS_PER_DAY))
+    }
+
+    /// Write all records to a temporary file and rename it over the store file
+    fn persist(&self, entries: &ProofEntries) -> io::Result<()> {
+        let Some(path) = &self.config.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let proofs: Vec<&StoredVerificationProof> = entries
+            .by_completion
+            .iter()
+            .filter_map(|(_, hash)| entries.by_hash.get(hash))
+            .collect();
+        let data = serde_json::to_vec(&proofs)?;
+
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    }
+}
+
+/// Current time in Unix milliseconds
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn proof(hash_byte: u8, completed_at: u64) -> StoredVerificationProof {
+        StoredVerificationProof {
+            user_op_hash: B256::repeat_byte(hash_byte),
+            account_id: "passkey_user_alice".to_string(),
+            tee_device_id: "tee_device_001".to_string(),
+            signing: KmsSigningSummary {
+                signature: "0x1234".to_string(),
+                paymaster_address: "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".to_string(),
+                dual_signature_mode: true,
+            },
+            validation: ValidationSummary {
+                paymaster_verified: true,
+                user_passkey_verified: true,
+                local_verification: Some(UserSignatureAlgorithm::WebauthnP256),
+                balance: "0.05".to_string(),
+                membership_level: "premium".to_string(),
+                approved_at: completed_at / 1000,
+            },
+            proof_timestamp: "2026-10-01T00:00:00Z".to_string(),
+            requested_at: completed_at - 250,
+            completed_at,
+        }
+    }
+
+    fn memory_store(max_entries: usize) -> VerificationProofStore {
+        VerificationProofStore::new(VerificationProofConfig {
+            path: None,
+            max_entries,
+            retention_days: 30,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_store_and_retrieve() {
+        let store = memory_store(10);
+        let now = unix_millis();
+        store.insert(proof(1, now));
+
+        let stored = store.get(&B256::repeat_byte(1)).unwrap();
+        assert_eq!(stored, proof(1, now));
+
+        let json = serde_json::to_value(&stored).unwrap();
+        assert_eq!(json["teeDeviceId"], "tee_device_001");
+        assert_eq!(json["signing"]["signature"], "0x1234");
+        assert_eq!(json["validation"]["userPasskeyVerified"], true);
+        assert_eq!(json["validation"]["localVerification"], "webauthn-p256");
+        assert_eq!(json["completedAt"], now);
+    }
+
+    #[test]
+    fn test_unknown_hash_not_found() {
+        let store = memory_store(10);
+        store.insert(proof(1, unix_millis()));
+
+        assert!(store.get(&B256::repeat_byte(2)).is_none());
+    }
+
+    #[test]
+    fn test_least_recently_completed_evicted() {
+        let store = memory_store(2);
+        let now = unix_millis();
+        // Inserted out of completion order
+        store.insert(proof(1, now - 2_000));
+        store.insert(proof(2, now - 3_000));
+        store.insert(proof(3, now - 1_000));
+
+        assert_eq!(store.len(), 2);
+        assert!(store.get(&B256::repeat_byte(2)).is_none());
+        assert!(store.get(&B256::repeat_byte(1)).is_some());
+        assert!(store.get(&B256::repeat_byte(3)).is_some());
+    }
+
+    #[test]
+    fn test_proofs_past_retention_dropped() {
+        let store = memory_store(10);
+        let day = 24 * 60 * 60 * 1000;
+        store.insert(proof(1, unix_millis() - 31 * day));
+        store.insert(proof(2, unix_millis() - 29 * day));
+
+        assert!(store.get(&B256::repeat_byte(1)).is_none());
+        assert!(store.get(&B256::repeat_byte(2)).is_some());
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_store_survives_restart() {
+        let dir = tempdir().unwrap();
+        let config = VerificationProofConfig {
+            path: Some(dir.path().join("proofs").join("verification-proofs.json")),
+            max_entries: 10,
+            retention_days: 30,
+        };
+        let now = unix_millis();
+
+        let store = VerificationProofStore::new(config.clone()).unwrap();
+        store.insert(proof(1, now - 1_000));
+        store.insert(proof(2, now));
+        drop(store);
+
+        let restored = VerificationProofStore::new(config).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored.get(&B256::repeat_byte(2)).unwrap(), proof(2, now));
+    }
+}
@@ -0,0 +1,192 @@
+//! Balance monitor top-ups of a drained EntryPoint deposit against a local Anvil node
+
+use std::sync::Arc;
+
+use alloy_primitives::{Address, U256};
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::Address as H160,
+    utils::Anvil,
+};
+use rundler_paymaster_relay::{
+    balance_monitor::{BalanceMonitor, BalanceMonitorConfig},
+    deposit::{DepositConfig, DepositManager, ProviderDepositChain},
+    outbound_tx::{OutboundTxConfig, OutboundTxManager},
+    service::PaymasterRelayService,
+};
+use rundler_provider::{AlloyEntryPointV0_7, AlloyEvmProvider, EntryPoint};
+use rundler_types::{chain::ChainSpec, PriorityFeeMode};
+
+mod common;
+
+/// `milli` thousandths of an ether, in wei
+fn milli_ether(milli: u64) -> U256 {
+    U256::from(milli) * U256::from(1_000_000_000_000_000u64)
+}
+
+async fn create_service(node_url: &str, monitor: BalanceMonitorConfig) -> PaymasterRelayService {
+    let chain_spec = ChainSpec {
+        id: 31337,
+        ..Default::default()
+    };
+
+    // Install the v0.7 EntryPoint at its canonical address
+    let node = Provider::<Http>::try_from(node_url).unwrap();
+    let entry_point = H160::from_slice(chain_spec.entry_point_address_v0_7.as_slice());
+    let code = format!(
+        "0x{}",
+        hex::encode(&rundler_contracts::v0_7::ENTRY_POINT_SIMULATIONS_V0_7_DEPLOYED_BYTECODE[..])
+    );
+    node.request::<_, ()>("anvil_setCode", (entry_point, code))
+        .await
+        .unwrap();
+
+    let provider = rundler_provider::new_alloy_provider(node_url, 30).unwrap();
+    let (da_gas_oracle, _) =
+        rundler_provider::new_alloy_da_gas_oracle(&chain_spec, provider.clone());
+    let ep_v0_7: Arc<dyn EntryPoint> = Arc::new(AlloyEntryPointV0_7::new(
+        chain_spec.clone(),
+        5_000_000,
+        25_000_000,
+        25_000_000,
+        25_000_000,
+        provider.clone(),
+        da_gas_oracle,
+    ));
+    let evm_provider = AlloyEvmProvider::new(provider);
+    let chain = Arc::new(ProviderDepositChain::new(
+        evm_provider.clone(),
+        chain_spec.id,
+        vec![ep_v0_7],
+    ));
+    let fee_estimator = rundler_provider::new_fee_estimator(
+        &chain_spec,
+        evm_provider,
+        PriorityFeeMode::BaseFeePercent(50),
+        0,
+        0,
+    );
+    let outbound = Arc::new(
+        OutboundTxManager::new(
+            chain.clone(),
+            Arc::new(fee_estimator),
+            OutboundTxConfig {
+                poll_interval_ms: 100,
+                ..Default::default()
+            },
+        )
+        .unwrap(),
+    );
+
+    let deposit_config = DepositConfig {
+        allow_withdraw: true,
+        ..Default::default()
+    };
+    common::service("senders = []\n")
+        .with_deposit_manager(
+            DepositManager::new(chain, deposit_config).with_outbound_txs(outbound.clone()),
+        )
+        .with_outbound_txs(outbound)
+        .with_balance_monitor(BalanceMonitor::new(
+            monitor,
+            vec![chain_spec.entry_point_address_v0_7],
+        ))
+}
+
+fn rebalancing() -> BalanceMonitorConfig {
+    BalanceMonitorConfig {
+        enabled: true,
+        deposit_low_water_wei: milli_ether(50),
+        auto_rebalance: true,
+        deposit_target_wei: milli_ether(200),
+        ..Default::default()
+    }
+}
+
+/// Deposit 0.1 ETH and withdraw all but 0.01 ETH, below the 0.05 ETH low-water mark
+async fn drain_deposit(service: &PaymasterRelayService, entry_point: Address) {
+    service
+        .deposit_to(entry_point, milli_ether(100))
+        .await
+        .unwrap();
+    service
+        .withdraw_to(entry_point, Address::repeat_byte(0x77), milli_ether(90))
+        .await
+        .unwrap();
+    let info = service.get_deposit_info(entry_point).await.unwrap();
+    assert_eq!(info.deposit, milli_ether(10));
+}
+
+#[tokio::test]
+#[ignore] // requires anvil
+async fn test_drained_deposit_topped_up() {
+    let anvil = Anvil::new().spawn();
+    let service = create_service(&anvil.endpoint(), rebalancing()).await;
+    let entry_point = ChainSpec::default().entry_point_address_v0_7;
+    drain_deposit(&service, entry_point).await;
+
+    let status = service.check_balances().await.unwrap();
+    assert!(status.last_error.is_none(), "{:?}", status.last_error);
+    let rebalance = status.last_rebalance.clone().unwrap();
+    assert!(rebalance.tx_hash.is_some(), "{:?}", rebalance.error);
+    assert_eq!(rebalance.amount, milli_ether(190));
+    assert_eq!(status.rebalanced_today, milli_ether(190));
+    assert_eq!(status.deposits[0].deposit, milli_ether(200));
+    assert!(!status.deposits[0].low);
+    assert!(status.next_check > status.last_checked);
+
+    // The top-up landed on chain
+    let info = service.get_deposit_info(entry_point).await.unwrap();
+    assert_eq!(info.deposit, milli_ether(200));
+    assert_eq!(service.balance_status().unwrap(), status);
+}
+
+#[tokio::test]
+#[ignore] // requires anvil
+async fn test_daily_cap_and_cooldown_limit_top_ups() {
+    let anvil = Anvil::new().spawn();
+    let config = BalanceMonitorConfig {
+        max_rebalance_per_day_wei: milli_ether(30),
+        ..rebalancing()
+    };
+    let service = create_service(&anvil.endpoint(), config).await;
+    let entry_point = ChainSpec::default().entry_point_address_v0_7;
+    drain_deposit(&service, entry_point).await;
+
+    // Only the daily cap is topped up, leaving the deposit low
+    let status = service.check_balances().await.unwrap();
+    assert_eq!(
+        status.last_rebalance.as_ref().unwrap().amount,
+        milli_ether(30)
+    );
+    assert_eq!(status.deposits[0].deposit, milli_ether(40));
+    assert!(status.deposits[0].low);
+
+    // Within the cooldown nothing more is sent
+    let status = service.check_balances().await.unwrap();
+    assert_eq!(status.rebalanced_today, milli_ether(30));
+    let info = service.get_deposit_info(entry_point).await.unwrap();
+    assert_eq!(info.deposit, milli_ether(40));
+}
+
+#[tokio::test]
+#[ignore] // requires anvil
+async fn test_monitor_only_reports() {
+    let anvil = Anvil::new().spawn();
+    let config = BalanceMonitorConfig {
+        auto_rebalance: false,
+        ..rebalancing()
+    };
+    let service = create_service(&anvil.endpoint(), config).await;
+    let entry_point = ChainSpec::default().entry_point_address_v0_7;
+
+    let status = service.check_balances().await.unwrap();
+    assert!(status.deposits[0].low);
+    assert!(status.last_rebalance.is_none());
+    assert!(!status.account_low);
+    assert!(status.account_balance.unwrap() > milli_ether(1_000));
+    assert_eq!(
+        service.get_deposit_info(entry_point).await.unwrap().deposit,
+        U256::ZERO
+    );
+}
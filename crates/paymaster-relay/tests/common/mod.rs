@@ -0,0 +1,52 @@
+//! Fixtures shared by the paymaster relay integration tests
+
+#![allow(dead_code)]
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rundler_paymaster_relay::{
+    policy::PolicyEngine, service::PaymasterRelayService, signer::SignerManager,
+};
+use rundler_pool::LocalPoolBuilder;
+use secrecy::SecretString;
+use tempfile::tempdir;
+
+/// Paymaster signing key of [`service`] and [`service_in`], the first Anvil
+/// development account
+pub const SIGNER_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+/// Writes `policy` as the `[default]` policy file `name` in `dir`
+pub fn write_policy(dir: &Path, name: &str, policy: &str) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, format!("[default]\n{}", policy)).unwrap();
+    path
+}
+
+/// Paymaster service signing with [`SIGNER_KEY`] under the `[default]` policy
+/// `policy`, on an in-memory pool
+pub fn service(policy: &str) -> PaymasterRelayService {
+    let dir = tempdir().unwrap();
+    service_in(dir.path(), policy)
+}
+
+/// [`service`] with its `policy.toml` kept in `dir`
+pub fn service_in(dir: &Path, policy: &str) -> PaymasterRelayService {
+    let signer_manager =
+        SignerManager::new(SecretString::new(SIGNER_KEY.to_string().into())).unwrap();
+    service_signed_by(dir, policy, signer_manager)
+}
+
+/// [`service_in`] signing with `signer_manager`
+pub fn service_signed_by(
+    dir: &Path,
+    policy: &str,
+    signer_manager: SignerManager,
+) -> PaymasterRelayService {
+    let policy_path = write_policy(dir, "policy.toml", policy);
+    let policy_engine = PolicyEngine::new(&policy_path).unwrap();
+    let pool = Arc::new(LocalPoolBuilder::new(10).get_handle());
+    PaymasterRelayService::new(signer_manager, policy_engine, pool)
+}
@@ -0,0 +1,131 @@
+//! EntryPoint deposit management against a local Anvil node
+
+use std::{str::FromStr, sync::Arc};
+
+use alloy_primitives::U256;
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::Address as H160,
+    utils::Anvil,
+};
+use rundler_paymaster_relay::{
+    deposit::{DepositConfig, DepositManager, ProviderDepositChain},
+    outbound_tx::{OutboundTxConfig, OutboundTxManager},
+    service::PaymasterRelayService,
+};
+use rundler_provider::{AlloyEntryPointV0_7, AlloyEvmProvider, EntryPoint};
+use rundler_types::{chain::ChainSpec, PriorityFeeMode};
+
+mod common;
+
+async fn create_service(node_url: &str, config: DepositConfig) -> PaymasterRelayService {
+    let chain_spec = ChainSpec {
+        id: 31337,
+        ..Default::default()
+    };
+
+    // Install the v0.7 EntryPoint at its canonical address
+    let node = Provider::<Http>::try_from(node_url).unwrap();
+    let entry_point = H160::from_slice(chain_spec.entry_point_address_v0_7.as_slice());
+    let code = format!(
+        "0x{}",
+        hex::encode(&rundler_contracts::v0_7::ENTRY_POINT_SIMULATIONS_V0_7_DEPLOYED_BYTECODE[..])
+    );
+    node.request::<_, ()>("anvil_setCode", (entry_point, code))
+        .await
+        .unwrap();
+    assert!(!node.get_code(entry_point, None).await.unwrap().is_empty());
+
+    let provider = rundler_provider::new_alloy_provider(node_url, 30).unwrap();
+    let (da_gas_oracle, _) =
+        rundler_provider::new_alloy_da_gas_oracle(&chain_spec, provider.clone());
+    let ep_v0_7: Arc<dyn EntryPoint> = Arc::new(AlloyEntryPointV0_7::new(
+        chain_spec.clone(),
+        5_000_000,
+        25_000_000,
+        25_000_000,
+        25_000_000,
+        provider.clone(),
+        da_gas_oracle,
+    ));
+    let evm_provider = AlloyEvmProvider::new(provider);
+    let chain = Arc::new(ProviderDepositChain::new(
+        evm_provider.clone(),
+        chain_spec.id,
+        vec![ep_v0_7],
+    ));
+    let fee_estimator = rundler_provider::new_fee_estimator(
+        &chain_spec,
+        evm_provider,
+        PriorityFeeMode::BaseFeePercent(50),
+        0,
+        0,
+    );
+    let outbound = Arc::new(
+        OutboundTxManager::new(
+            chain.clone(),
+            Arc::new(fee_estimator),
+            OutboundTxConfig {
+                poll_interval_ms: 100,
+                ..Default::default()
+            },
+        )
+        .unwrap(),
+    );
+
+    common::service("senders = []\n")
+        .with_deposit_manager(
+            DepositManager::new(chain, config).with_outbound_txs(outbound.clone()),
+        )
+        .with_outbound_txs(outbound)
+}
+
+#[tokio::test]
+#[ignore] // requires anvil
+async fn test_deposit_to_increases_deposit() {
+    let anvil = Anvil::new().spawn();
+    let service = create_service(&anvil.endpoint(), DepositConfig::default()).await;
+    let entry_point = ChainSpec::default().entry_point_address_v0_7;
+
+    let before = service.get_deposit_info(entry_point).await.unwrap();
+    assert_eq!(before.deposit, U256::ZERO);
+
+    let amount = U256::from_str("100000000000000000").unwrap(); // 0.1 ETH
+    let tx = service.deposit_to(entry_point, amount).await.unwrap();
+    assert_eq!(tx.deposit_info.deposit, before.deposit + amount);
+
+    let after = service.get_deposit_info(entry_point).await.unwrap();
+    assert_eq!(after.deposit, before.deposit + amount);
+}
+
+#[tokio::test]
+#[ignore] // requires anvil
+async fn test_deposit_above_cap_rejected() {
+    let anvil = Anvil::new().spawn();
+    let config = DepositConfig {
+        max_deposit_wei: U256::from(1_000u64),
+        ..Default::default()
+    };
+    let service = create_service(&anvil.endpoint(), config).await;
+    let entry_point = ChainSpec::default().entry_point_address_v0_7;
+
+    assert!(service
+        .deposit_to(entry_point, U256::from(1_001u64))
+        .await
+        .is_err());
+    let info = service.get_deposit_info(entry_point).await.unwrap();
+    assert_eq!(info.deposit, U256::ZERO);
+}
+
+#[tokio::test]
+#[ignore] // requires anvil
+async fn test_withdraw_disabled_by_default() {
+    let anvil = Anvil::new().spawn();
+    let service = create_service(&anvil.endpoint(), DepositConfig::default()).await;
+    let entry_point = ChainSpec::default().entry_point_address_v0_7;
+
+    let result = service
+        .withdraw_to(entry_point, entry_point, U256::from(1u64))
+        .await;
+    assert!(result.unwrap_err().to_string().contains("disabled"));
+}
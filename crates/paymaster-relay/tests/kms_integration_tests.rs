@@ -77,7 +77,7 @@ async fn test_kms_audit_logging() {
         SignerManager::new_with_kms(kms_config).expect("Failed to create KMS signer manager");
 
     // Initial audit log should be empty
-    let initial_audit = signer_manager.get_kms_audit_log().unwrap();
+    let initial_audit = signer_manager.get_kms_audit_log().await.unwrap();
     assert!(initial_audit.is_empty());
 
     // Perform signing operation
@@ -116,7 +116,7 @@ async fn test_kms_audit_logging() {
         .expect("KMS signing should succeed");
 
     // Check audit log
-    let audit_log = signer_manager.get_kms_audit_log().unwrap();
+    let audit_log = signer_manager.get_kms_audit_log().await.unwrap();
     assert_eq!(audit_log.len(), 1);
 
     let audit_entry = &audit_log[0];
@@ -264,6 +264,7 @@ async fn test_kms_full_integration() {
         signing_timeout_seconds: 30,
         enable_audit_logging: true,
         rate_limit_per_minute: 1000,
+        aws_key_arn: None,
     };
 
     let mut signer_manager = SignerManager::new_with_kms(kms_config)
@@ -321,7 +322,7 @@ async fn test_kms_full_integration() {
     assert!(signature.s != ethers::types::U256::zero());
 
     // 4. Check audit trail
-    let audit_log = signer_manager.get_kms_audit_log().unwrap();
+    let audit_log = signer_manager.get_kms_audit_log().await.unwrap();
     assert!(!audit_log.is_empty());
     assert_eq!(
         audit_log[0].service_metadata.get("operation_type"),
@@ -662,7 +662,7 @@ impl<P: EvmProvider> Chain<P> {
                 Some(EntryPointVersion::V0_7) => {
                     Self::load_v0_7(log, &mut mined_ops, &mut entity_balance_updates)
                 }
-                Some(EntryPointVersion::Unspecified) | None => {
+                _ => {
                     warn!(
                         "Log with unknown entry point address: {:?}. Ignoring.",
                         log.address()
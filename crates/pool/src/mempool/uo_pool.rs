@@ -181,6 +181,8 @@ where
                 // whether or not the UO is using a post op. Can cause the efficiency check to fail.
                 UserOperationVariant::V0_6(op) => op.call_gas_limit(),
                 UserOperationVariant::V0_7(op) => op.execution_gas_limit(),
+                #[cfg(feature = "entrypoint-v0_8")]
+                UserOperationVariant::V0_8(op) => op.execution_gas_limit(),
             };
             if execution_gas_limit == 0 {
                 return Ok(()); // No call gas limit, not useful, but not a failure here.
@@ -605,8 +607,13 @@ where
             self.check_execution_gas_limit_efficiency(op.clone(), block_hash);
         let (sim_result, _) = tokio::try_join!(sim_fut, execution_gas_check_future)?;
 
-        // Check if op has more than the maximum allowed expected storage slots
-        let expected_slots = sim_result.expected_storage.num_slots();
+        // Check if op has more than the maximum allowed expected storage slots,
+        // counting the storage its submitter requires at inclusion
+        let expected_slots = sim_result.expected_storage.num_slots()
+            + perms
+                .expected_storage
+                .as_ref()
+                .map_or(0, |storage| storage.num_slots());
         if expected_slots > self.config.max_expected_storage_slots {
             return Err(MempoolError::TooManyExpectedStorageSlots(
                 self.config.max_expected_storage_slots,
@@ -699,6 +699,12 @@ impl LocalPoolServerRunner {
                                                 break 'resp Err(anyhow::anyhow!("Invalid user operation version for mempool v0.7 {:?}", op.uo_type()).into());
                                             }
                                         }
+                                        #[cfg(feature = "entrypoint-v0_8")]
+                                        EntryPointVersion::V0_8 => {
+                                            if !matches!(&op, UserOperationVariant::V0_8(_)){
+                                                break 'resp Err(anyhow::anyhow!("Invalid user operation version for mempool v0.8 {:?}", op.uo_type()).into());
+                                            }
+                                        }
                                         EntryPointVersion::Unspecified => {
                                             panic!("Found mempool with unspecified entry point version")
                                         }
@@ -29,7 +29,8 @@ use rundler_types::{
     },
     v0_6, v0_7, BundlerSponsorship as RundlerBundlerSponsorship, Entity as RundlerEntity,
     EntityInfos, EntityType as RundlerEntityType, EntityUpdate as RundlerEntityUpdate,
-    EntityUpdateType as RundlerEntityUpdateType, StakeInfo as RundlerStakeInfo, UserOperation as _,
+    EntityUpdateType as RundlerEntityUpdateType, ExpectedStorage as RundlerExpectedStorage,
+    StakeInfo as RundlerStakeInfo, UserOperation as _,
     UserOperationPermissions as RundlerUserOperationPermissions, UserOperationVariant,
     ValidTimeRange,
 };
@@ -44,6 +45,9 @@ impl From<&UserOperationVariant> for UserOperation {
         match op {
             UserOperationVariant::V0_6(op) => op.into(),
             UserOperationVariant::V0_7(op) => op.into(),
+            // v0.8 has no mempool, its operations are sent in the v0.7 layout
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.fields().into(),
         }
     }
 }
@@ -633,6 +637,10 @@ impl TryFrom<UserOperationPermissions> for RundlerUserOperationPermissions {
                 .bundler_sponsorship
                 .map(|s| s.try_into())
                 .transpose()?,
+            expected_storage: permissions
+                .expected_storage
+                .map(|s| s.try_into())
+                .transpose()?,
         })
     }
 }
@@ -647,6 +655,43 @@ impl From<RundlerUserOperationPermissions> for UserOperationPermissions {
             underpriced_accept_pct: permissions.underpriced_accept_pct,
             underpriced_bundle_pct: permissions.underpriced_bundle_pct,
             bundler_sponsorship: permissions.bundler_sponsorship.map(|s| s.into()),
+            expected_storage: permissions.expected_storage.map(|s| s.into()),
+        }
+    }
+}
+
+impl TryFrom<ExpectedStorage> for RundlerExpectedStorage {
+    type Error = ConversionError;
+
+    fn try_from(expected_storage: ExpectedStorage) -> Result<Self, Self::Error> {
+        let mut storage = RundlerExpectedStorage::default();
+        for slot in expected_storage.slots {
+            storage
+                .0
+                .entry(from_bytes(&slot.address)?)
+                .or_default()
+                .insert(from_bytes(&slot.slot)?, from_bytes(&slot.value)?);
+        }
+        Ok(storage)
+    }
+}
+
+impl From<RundlerExpectedStorage> for ExpectedStorage {
+    fn from(expected_storage: RundlerExpectedStorage) -> Self {
+        Self {
+            slots: expected_storage
+                .0
+                .into_iter()
+                .flat_map(|(address, slots)| {
+                    slots
+                        .into_iter()
+                        .map(move |(slot, value)| ExpectedStorageSlot {
+                            address: address.to_proto_bytes(),
+                            slot: slot.to_proto_bytes(),
+                            value: value.to_proto_bytes(),
+                        })
+                })
+                .collect(),
         }
     }
 }
@@ -147,6 +147,10 @@ where
 
                     mempools.insert(pool_config.entry_point, pool);
                 }
+                #[cfg(feature = "entrypoint-v0_8")]
+                EntryPointVersion::V0_8 => {
+                    bail!("Entry point v0.8 has no mempool");
+                }
                 EntryPointVersion::Unspecified => {
                     bail!("Unsupported entry point version");
                 }
@@ -126,6 +126,11 @@ impl EntryPointRouter {
                 }
                 Ok(&self.v0_7.as_ref().unwrap().1)
             }
+            #[cfg(feature = "entrypoint-v0_8")]
+            EntryPointVersion::V0_8 => Err(EthRpcError::InvalidParams(format!(
+                "Entry point v0.8 is not served by the bundler: {:?}",
+                entry_point
+            ))),
             EntryPointVersion::Unspecified => unreachable!("unspecified entry point version"),
         }
     }
@@ -219,6 +224,11 @@ impl EntryPointRouter {
 
                 Ok(RpcGasEstimateV0_7::from(e).into())
             }
+            #[cfg(feature = "entrypoint-v0_8")]
+            EntryPointVersion::V0_8 => Err(EthRpcError::InvalidParams(format!(
+                "Entry point v0.8 is not served by the bundler: {:?}",
+                entry_point
+            ))),
             EntryPointVersion::Unspecified => unreachable!("unspecified entry point version"),
         }
     }
@@ -258,6 +268,11 @@ impl EntryPointRouter {
         match ep {
             EntryPointVersion::V0_6 => Ok(&self.v0_6.as_ref().unwrap().1),
             EntryPointVersion::V0_7 => Ok(&self.v0_7.as_ref().unwrap().1),
+            #[cfg(feature = "entrypoint-v0_8")]
+            EntryPointVersion::V0_8 => Err(EthRpcError::InvalidParams(format!(
+                "Entry point v0.8 is not served by the bundler: {:?}",
+                entry_point
+            ))),
             EntryPointVersion::Unspecified => unreachable!("unspecified entry point version"),
         }
     }
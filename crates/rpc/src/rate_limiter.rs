@@ -1,15 +1,24 @@
 // Rate limiting middleware for RPC requests
 use std::{
     collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
     net::IpAddr,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
 use jsonrpsee::types::ErrorObjectOwned;
-use parking_lot::RwLock;
+use metrics::{Counter, Gauge};
+use metrics_derive::Metrics;
+use parking_lot::Mutex;
 use tokio::time::{interval, MissedTickBehavior};
 
+/// Number of lock stripes of the bucket map
+const SHARDS: usize = 64;
+
 /// Token bucket for rate limiting
 #[derive(Debug, Clone)]
 pub(crate) struct TokenBucket {
@@ -20,17 +29,17 @@ pub(crate) struct TokenBucket {
 }
 
 impl TokenBucket {
-    pub(crate) fn new(capacity: u32, refill_rate: u32) -> Self {
+    pub(crate) fn new(capacity: u32, refill_rate: u32, now: Instant) -> Self {
         Self {
             tokens: capacity,
             capacity,
             refill_rate,
-            last_refill: Instant::now(),
+            last_refill: now,
         }
     }
 
-    pub(crate) fn try_consume(&mut self, tokens: u32) -> bool {
-        self.refill();
+    pub(crate) fn try_consume(&mut self, tokens: u32, now: Instant) -> bool {
+        self.refill(now);
 
         if self.tokens >= tokens {
             self.tokens -= tokens;
@@ -40,9 +49,8 @@ impl TokenBucket {
         }
     }
 
-    fn refill(&mut self) {
-        let now = Instant::now();
-        let elapsed = now.duration_since(self.last_refill);
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
         let tokens_to_add = (elapsed.as_secs_f64() * self.refill_rate as f64) as u32;
 
         if tokens_to_add > 0 {
@@ -63,6 +71,8 @@ pub struct RateLimiterConfig {
     pub cleanup_interval: Duration,
     /// Entry expiry time for inactive IPs
     pub entry_expiry: Duration,
+    /// Maximum number of tracked IPs; new IPs past the cap evict the longest idle ones
+    pub max_tracked_keys: usize,
 }
 
 impl Default for RateLimiterConfig {
@@ -72,24 +82,91 @@ impl Default for RateLimiterConfig {
             burst_capacity: 200,                       // Allow bursts up to 200
             cleanup_interval: Duration::from_secs(60), // Cleanup every minute
             entry_expiry: Duration::from_secs(300),    // Remove after 5 minutes
+            max_tracked_keys: 100_000,                 // ~10MB of buckets
         }
     }
 }
 
+#[derive(Metrics)]
+#[metrics(scope = "rpc_rate_limiter")]
+struct RateLimiterMetrics {
+    #[metric(describe = "the number of IPs with a tracked token bucket.")]
+    tracked_keys: Gauge,
+    #[metric(describe = "the count of idle IPs evicted because the tracked IP cap was hit.")]
+    cap_evictions: Counter,
+    #[metric(describe = "the count of idle IPs removed by the periodic sweep.")]
+    expired_keys: Counter,
+}
+
+/// Token bucket of an IP and the last time the IP made a request
+type Shard = HashMap<IpAddr, (TokenBucket, Instant)>;
+
+#[derive(Debug)]
+struct Buckets {
+    shards: Vec<Mutex<Shard>>,
+    tracked: AtomicUsize,
+    metrics: RateLimiterMetrics,
+}
+
+impl Buckets {
+    fn new() -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+            tracked: AtomicUsize::new(0),
+            metrics: RateLimiterMetrics::default(),
+        }
+    }
+
+    fn shard(&self, ip: &IpAddr) -> &Mutex<Shard> {
+        let mut hasher = DefaultHasher::new();
+        ip.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    fn update_tracked(&self, added: usize, removed: usize) {
+        if added > 0 {
+            self.tracked.fetch_add(added, Ordering::Relaxed);
+        }
+        if removed > 0 {
+            self.tracked.fetch_sub(removed, Ordering::Relaxed);
+        }
+        self.metrics
+            .tracked_keys
+            .set(self.tracked.load(Ordering::Relaxed) as f64);
+    }
+
+    /// Remove entries idle for `expiry` or longer, one shard at a time so
+    /// requests hashed to the other shards are never blocked by the sweep
+    fn sweep(&self, now: Instant, expiry: Duration) -> usize {
+        let mut removed = 0;
+        for shard in &self.shards {
+            let mut shard = shard.lock();
+            let before = shard.len();
+            shard.retain(|_ip, (_, last_access)| {
+                now.saturating_duration_since(*last_access) < expiry
+            });
+            removed += before - shard.len();
+        }
+        self.metrics.expired_keys.increment(removed as u64);
+        self.update_tracked(0, removed);
+        removed
+    }
+}
+
 /// IP-based rate limiter
+///
+/// Buckets live in a lock-striped map: a request locks only the stripe of its
+/// IP, and the background sweep of idle IPs locks one stripe at a time.
 #[derive(Debug)]
 pub struct RateLimiter {
-    buckets: Arc<RwLock<HashMap<IpAddr, (TokenBucket, Instant)>>>,
+    buckets: Arc<Buckets>,
     config: RateLimiterConfig,
 }
 
 impl RateLimiter {
     /// Create a new rate limiter with the given configuration
     pub fn new(config: RateLimiterConfig) -> Self {
-        let limiter = Self {
-            buckets: Arc::new(RwLock::new(HashMap::new())),
-            config,
-        };
+        let limiter = Self::new_without_cleanup(config);
 
         // Start cleanup task
         limiter.start_cleanup_task();
@@ -97,34 +174,92 @@ impl RateLimiter {
         limiter
     }
 
+    fn new_without_cleanup(config: RateLimiterConfig) -> Self {
+        Self {
+            buckets: Arc::new(Buckets::new()),
+            config,
+        }
+    }
+
     /// Check if request is allowed for given IP
     pub fn check_rate_limit(&self, ip: IpAddr) -> bool {
-        let mut buckets = self.buckets.write();
-        let now = Instant::now();
+        self.check_rate_limit_at(ip, Instant::now())
+    }
 
-        let entry = buckets.entry(ip).or_insert_with(|| {
-            (
-                TokenBucket::new(self.config.burst_capacity, self.config.requests_per_second),
-                now,
-            )
-        });
+    fn check_rate_limit_at(&self, ip: IpAddr, now: Instant) -> bool {
+        let mut shard = self.buckets.shard(&ip).lock();
+
+        let mut evicted = 0;
+        let added = if shard.contains_key(&ip) {
+            0
+        } else {
+            // Each stripe holds its share of the cap; make room by evicting
+            // the stripe's longest idle IP
+            let stripe_cap = self.config.max_tracked_keys.div_ceil(SHARDS).max(1);
+            while shard.len() >= stripe_cap {
+                let Some(oldest) = shard
+                    .iter()
+                    .min_by_key(|(_, (_, last_access))| *last_access)
+                    .map(|(ip, _)| *ip)
+                else {
+                    break;
+                };
+                shard.remove(&oldest);
+                evicted += 1;
+            }
+            shard.insert(
+                ip,
+                (
+                    TokenBucket::new(
+                        self.config.burst_capacity,
+                        self.config.requests_per_second,
+                        now,
+                    ),
+                    now,
+                ),
+            );
+            1
+        };
+        let entry = shard.get_mut(&ip).expect("bucket was just inserted");
 
         // Update last access time and check rate limit
         entry.1 = now;
-        entry.0.try_consume(1)
+        let allowed = entry.0.try_consume(1, now);
+        // Counted under the stripe lock so a concurrent sweep never removes
+        // an entry before it was counted
+        if added > 0 || evicted > 0 {
+            self.buckets.update_tracked(added, evicted);
+        }
+        drop(shard);
+
+        if evicted > 0 {
+            tracing::debug!(
+                "Rate limiter at its cap of {} IPs, evicted {} idle entries",
+                self.config.max_tracked_keys,
+                evicted
+            );
+            self.buckets.metrics.cap_evictions.increment(evicted as u64);
+        }
+        allowed
+    }
+
+    /// Remove IPs idle for longer than the configured expiry
+    fn sweep_at(&self, now: Instant) -> usize {
+        self.buckets.sweep(now, self.config.entry_expiry)
     }
 
     /// Get current statistics
     pub fn get_stats(&self) -> RateLimiterStats {
-        let buckets = self.buckets.read();
+        let tracked = self.buckets.tracked.load(Ordering::Relaxed);
         RateLimiterStats {
-            active_ips: buckets.len(),
-            total_buckets: buckets.len(),
+            active_ips: tracked,
+            total_buckets: tracked,
         }
     }
 
     fn start_cleanup_task(&self) {
-        let buckets = Arc::clone(&self.buckets);
+        // The task ends once the limiter is dropped
+        let buckets = Arc::downgrade(&self.buckets);
         let expiry_duration = self.config.entry_expiry;
         let cleanup_interval = self.config.cleanup_interval;
 
@@ -135,16 +270,10 @@ impl RateLimiter {
             loop {
                 interval.tick().await;
 
-                let now = Instant::now();
-                let mut buckets = buckets.write();
-
-                // Remove expired entries
-                let expired_count = buckets.len();
-                buckets.retain(|_ip, (_, last_access)| {
-                    now.duration_since(*last_access) < expiry_duration
-                });
-
-                let removed = expired_count - buckets.len();
+                let Some(buckets) = buckets.upgrade() else {
+                    break;
+                };
+                let removed = buckets.sweep(Instant::now(), expiry_duration);
                 if removed > 0 {
                     tracing::debug!("Rate limiter cleanup: removed {} expired entries", removed);
                 }
@@ -210,15 +339,16 @@ mod tests {
 
     #[test]
     fn test_token_bucket() {
-        let mut bucket = TokenBucket::new(10, 5);
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(10, 5, now);
 
         // Should allow initial requests up to capacity
         for _ in 0..10 {
-            assert!(bucket.try_consume(1));
+            assert!(bucket.try_consume(1, now));
         }
 
         // Should reject when empty
-        assert!(!bucket.try_consume(1));
+        assert!(!bucket.try_consume(1, now));
     }
 
     #[tokio::test]
@@ -228,6 +358,7 @@ mod tests {
             burst_capacity: 5,
             cleanup_interval: Duration::from_secs(60),
             entry_expiry: Duration::from_secs(300),
+            max_tracked_keys: 100,
         };
 
         let limiter = RateLimiter::new(config);
@@ -241,4 +372,61 @@ mod tests {
         // Should reject after burst exhausted
         assert!(!limiter.check_rate_limit(test_ip));
     }
+
+    #[test]
+    fn test_sweep_removes_idle_ips() {
+        let config = RateLimiterConfig {
+            requests_per_second: 1,
+            burst_capacity: 3,
+            cleanup_interval: Duration::from_secs(60),
+            entry_expiry: Duration::from_secs(300),
+            max_tracked_keys: 10_000,
+        };
+        let limiter = RateLimiter::new_without_cleanup(config);
+        let start = Instant::now();
+
+        // A rotating-IP scan leaves 1000 idle entries behind
+        for i in 0..1000u32 {
+            assert!(limiter.check_rate_limit_at(IpAddr::V4(Ipv4Addr::from(0x0a00_0000 + i)), start));
+        }
+        // An active client exhausts its burst right before the sweep
+        let active = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1));
+        let later = start + Duration::from_secs(299);
+        for _ in 0..3 {
+            assert!(limiter.check_rate_limit_at(active, later));
+        }
+        assert!(!limiter.check_rate_limit_at(active, later));
+        assert_eq!(limiter.get_stats().active_ips, 1001);
+
+        assert_eq!(limiter.sweep_at(start + Duration::from_secs(300)), 1000);
+        assert_eq!(limiter.get_stats().active_ips, 1);
+        // The active client's bucket survived, still empty
+        assert!(!limiter.check_rate_limit_at(active, later));
+    }
+
+    #[test]
+    fn test_cap_evicts_longest_idle_ips() {
+        let config = RateLimiterConfig {
+            // Buckets never refill, so a recreated bucket would be full again
+            requests_per_second: 0,
+            burst_capacity: 1,
+            max_tracked_keys: SHARDS * 2,
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new_without_cleanup(config);
+        let start = Instant::now();
+
+        let active = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1));
+        assert!(limiter.check_rate_limit_at(active, start));
+        for i in 0..10_000u32 {
+            let now = start + Duration::from_millis(i as u64 + 1);
+            // The active client keeps making requests while the scan rotates IPs
+            assert!(!limiter.check_rate_limit_at(active, now));
+            assert!(limiter.check_rate_limit_at(IpAddr::V4(Ipv4Addr::from(0x0a00_0000 + i)), now));
+        }
+
+        assert_eq!(limiter.get_stats().active_ips, SHARDS * 2);
+        // The recently seen client kept its empty bucket instead of a fresh one
+        assert!(!limiter.check_rate_limit_at(active, start + Duration::from_secs(11)));
+    }
 }
@@ -13,6 +13,8 @@
 
 use alloy_primitives::{Address, B256, U128, U256, U64};
 use rundler_provider::{Log, TransactionReceipt};
+#[cfg(feature = "entrypoint-v0_8")]
+use rundler_types::v0_8;
 use rundler_types::{
     chain::{ChainSpec, FromWithSpec, IntoWithSpec},
     pool::{Reputation, ReputationStatus},
@@ -102,6 +104,9 @@ pub(crate) struct RpcStakeInfo {
 pub(crate) enum RpcUserOperation {
     V0_6(RpcUserOperationV0_6),
     V0_7(RpcUserOperationV0_7),
+    /// Same fields as v0.7, told apart by the entry point it is sent to
+    #[cfg(feature = "entrypoint-v0_8")]
+    V0_8(RpcUserOperationV0_7),
 }
 
 impl From<UserOperationVariant> for RpcUserOperation {
@@ -109,6 +114,8 @@ impl From<UserOperationVariant> for RpcUserOperation {
         match op {
             UserOperationVariant::V0_6(op) => RpcUserOperation::V0_6(op.into()),
             UserOperationVariant::V0_7(op) => RpcUserOperation::V0_7(op.into()),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => RpcUserOperation::V0_8(op.into_fields().into()),
         }
     }
 }
@@ -118,6 +125,10 @@ impl FromWithSpec<RpcUserOperation> for UserOperationVariant {
         match op {
             RpcUserOperation::V0_6(op) => UserOperationVariant::V0_6(op.into_with_spec(chain_spec)),
             RpcUserOperation::V0_7(op) => UserOperationVariant::V0_7(op.into_with_spec(chain_spec)),
+            #[cfg(feature = "entrypoint-v0_8")]
+            RpcUserOperation::V0_8(op) => UserOperationVariant::V0_8(
+                v0_8::UserOperation::from_fields(op.into_with_spec(chain_spec), chain_spec),
+            ),
         }
     }
 }
@@ -58,6 +58,7 @@ impl FromWithSpec<RpcUserOperationPermissions> for UserOperationPermissions {
             bundler_sponsorship: rpc
                 .bundler_sponsorship
                 .map(|c| c.into_with_spec(chain_spec)),
+            expected_storage: None,
         }
     }
 }
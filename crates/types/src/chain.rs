@@ -22,6 +22,8 @@ use crate::{aggregator::SignatureAggregator, da::DAGasOracleType, proxy::Submiss
 
 const ENTRY_POINT_ADDRESS_V0_6: &str = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
 const ENTRY_POINT_ADDRESS_V0_7: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+#[cfg(feature = "entrypoint-v0_8")]
+const ENTRY_POINT_ADDRESS_V0_8: &str = "0x4337084D9E255Ff0702461CF8895CE9E3b5Ff108";
 const MULTICALL3_ADDRESS: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
 
 /// Chain specification for Rundler
@@ -38,6 +40,10 @@ pub struct ChainSpec {
     pub entry_point_address_v0_6: Address,
     /// entry point address for v0_7
     pub entry_point_address_v0_7: Address,
+    /// entry point address for v0_8
+    #[cfg(feature = "entrypoint-v0_8")]
+    #[serde(default = "default_entry_point_address_v0_8")]
+    pub entry_point_address_v0_8: Address,
     /// address of the multicall3 contract
     pub multicall3_address: Address,
 
@@ -161,6 +167,11 @@ pub enum PriorityFeeOracleType {
     UsageBased,
 }
 
+#[cfg(feature = "entrypoint-v0_8")]
+fn default_entry_point_address_v0_8() -> Address {
+    Address::from_str(ENTRY_POINT_ADDRESS_V0_8).unwrap()
+}
+
 impl Default for ChainSpec {
     fn default() -> Self {
         Self {
@@ -169,6 +180,8 @@ impl Default for ChainSpec {
             block_gas_limit: 30_000_000,
             entry_point_address_v0_6: Address::from_str(ENTRY_POINT_ADDRESS_V0_6).unwrap(),
             entry_point_address_v0_7: Address::from_str(ENTRY_POINT_ADDRESS_V0_7).unwrap(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            entry_point_address_v0_8: default_entry_point_address_v0_8(),
             multicall3_address: Address::from_str(MULTICALL3_ADDRESS).unwrap(),
             deposit_transfer_overhead: 30_000,
             transaction_intrinsic_gas: 21_000,
@@ -313,7 +326,11 @@ impl ChainSpec {
 
     /// Check if the chain supports EIP-7702
     pub fn supports_eip7702(&self, entry_point: Address) -> bool {
-        self.eip7702_enabled || entry_point == self.entry_point_address_v0_7
+        let entry_point_supported = entry_point == self.entry_point_address_v0_7;
+        #[cfg(feature = "entrypoint-v0_8")]
+        let entry_point_supported =
+            entry_point_supported || entry_point == self.entry_point_address_v0_8;
+        self.eip7702_enabled || entry_point_supported
     }
 }
 
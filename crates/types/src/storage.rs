@@ -29,7 +29,7 @@ pub struct StorageSlot {
 
 /// The expected storage values for a user operation that must
 /// be checked to determine if this operation is valid.
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ExpectedStorage(pub BTreeMap<Address, BTreeMap<B256, B256>>);
 
 impl ExpectedStorage {
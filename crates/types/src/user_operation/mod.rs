@@ -25,6 +25,9 @@ pub use permissions::{BundlerSponsorship, UserOperationPermissions};
 pub mod v0_6;
 /// User Operation types for Entry Point v0.7
 pub mod v0_7;
+/// User Operation types for Entry Point v0.8
+#[cfg(feature = "entrypoint-v0_8")]
+pub mod v0_8;
 
 use crate::{aggregator::AggregatorCosts, authorization::Eip7702Auth, chain::ChainSpec, Entity};
 
@@ -53,6 +56,9 @@ pub enum EntryPointVersion {
     V0_6,
     /// Version 0.7
     V0_7,
+    /// Version 0.8
+    #[cfg(feature = "entrypoint-v0_8")]
+    V0_8,
 }
 
 /// Unique identifier for a user operation from a given sender
@@ -478,6 +484,9 @@ pub enum UserOperationVariant {
     V0_6(v0_6::UserOperation),
     /// User operation version 0.7
     V0_7(v0_7::UserOperation),
+    /// User operation version 0.8
+    #[cfg(feature = "entrypoint-v0_8")]
+    V0_8(v0_8::UserOperation),
 }
 
 impl UserOperation for UserOperationVariant {
@@ -491,6 +500,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.entry_point(),
             UserOperationVariant::V0_7(op) => op.entry_point(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.entry_point(),
         }
     }
 
@@ -498,6 +509,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.chain_id(),
             UserOperationVariant::V0_7(op) => op.chain_id(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.chain_id(),
         }
     }
 
@@ -505,6 +518,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.hash(),
             UserOperationVariant::V0_7(op) => op.hash(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.hash(),
         }
     }
 
@@ -512,6 +527,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.id(),
             UserOperationVariant::V0_7(op) => op.id(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.id(),
         }
     }
 
@@ -519,6 +536,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.sender(),
             UserOperationVariant::V0_7(op) => op.sender(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.sender(),
         }
     }
 
@@ -526,6 +545,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.nonce(),
             UserOperationVariant::V0_7(op) => op.nonce(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.nonce(),
         }
     }
 
@@ -533,6 +554,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.paymaster(),
             UserOperationVariant::V0_7(op) => op.paymaster(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.paymaster(),
         }
     }
 
@@ -540,6 +563,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.factory(),
             UserOperationVariant::V0_7(op) => op.factory(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.factory(),
         }
     }
 
@@ -547,6 +572,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.aggregator(),
             UserOperationVariant::V0_7(op) => op.aggregator(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.aggregator(),
         }
     }
 
@@ -554,6 +581,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.call_data(),
             UserOperationVariant::V0_7(op) => op.call_data(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.call_data(),
         }
     }
 
@@ -561,6 +590,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.max_gas_cost(),
             UserOperationVariant::V0_7(op) => op.max_gas_cost(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.max_gas_cost(),
         }
     }
 
@@ -568,6 +599,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.entities(),
             UserOperationVariant::V0_7(op) => op.entities(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.entities(),
         }
     }
 
@@ -575,6 +608,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.heap_size(),
             UserOperationVariant::V0_7(op) => op.heap_size(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.heap_size(),
         }
     }
 
@@ -582,6 +617,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.call_gas_limit(),
             UserOperationVariant::V0_7(op) => op.call_gas_limit(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.call_gas_limit(),
         }
     }
 
@@ -589,6 +626,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.verification_gas_limit(),
             UserOperationVariant::V0_7(op) => op.verification_gas_limit(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.verification_gas_limit(),
         }
     }
 
@@ -596,6 +635,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.total_verification_gas_limit(),
             UserOperationVariant::V0_7(op) => op.total_verification_gas_limit(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.total_verification_gas_limit(),
         }
     }
 
@@ -603,6 +644,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.paymaster_post_op_gas_limit(),
             UserOperationVariant::V0_7(op) => op.paymaster_post_op_gas_limit(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.paymaster_post_op_gas_limit(),
         }
     }
 
@@ -610,6 +653,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.required_pre_execution_buffer(),
             UserOperationVariant::V0_7(op) => op.required_pre_execution_buffer(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.required_pre_execution_buffer(),
         }
     }
 
@@ -617,6 +662,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.pre_verification_gas(),
             UserOperationVariant::V0_7(op) => op.pre_verification_gas(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.pre_verification_gas(),
         }
     }
 
@@ -624,6 +671,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.static_pre_verification_gas(chain_spec),
             UserOperationVariant::V0_7(op) => op.static_pre_verification_gas(chain_spec),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.static_pre_verification_gas(chain_spec),
         }
     }
 
@@ -631,6 +680,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.calldata_floor_gas_limit(),
             UserOperationVariant::V0_7(op) => op.calldata_floor_gas_limit(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.calldata_floor_gas_limit(),
         }
     }
 
@@ -638,6 +689,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.max_fee_per_gas(),
             UserOperationVariant::V0_7(op) => op.max_fee_per_gas(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.max_fee_per_gas(),
         }
     }
 
@@ -645,6 +698,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.max_priority_fee_per_gas(),
             UserOperationVariant::V0_7(op) => op.max_priority_fee_per_gas(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.max_priority_fee_per_gas(),
         }
     }
 
@@ -652,6 +707,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.signature(),
             UserOperationVariant::V0_7(op) => op.signature(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.signature(),
         }
     }
 
@@ -659,6 +716,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.aggregator_gas_limit(chain_spec, bundle_size),
             UserOperationVariant::V0_7(op) => op.aggregator_gas_limit(chain_spec, bundle_size),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.aggregator_gas_limit(chain_spec, bundle_size),
         }
     }
 
@@ -686,6 +745,15 @@ impl UserOperation for UserOperationVariant {
                     new_signature,
                 ))
             }
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => {
+                UserOperationVariant::V0_8(op.transform_for_aggregator(
+                    chain_spec,
+                    aggregator,
+                    aggregator_costs,
+                    new_signature,
+                ))
+            }
         }
     }
 
@@ -693,6 +761,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.original_signature(),
             UserOperationVariant::V0_7(op) => op.original_signature(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.original_signature(),
         }
     }
 
@@ -704,6 +774,10 @@ impl UserOperation for UserOperationVariant {
             UserOperationVariant::V0_7(op) => {
                 UserOperationVariant::V0_7(op.with_original_signature())
             }
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => {
+                UserOperationVariant::V0_8(op.with_original_signature())
+            }
         }
     }
 
@@ -711,6 +785,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.extra_data_len(bundle_size),
             UserOperationVariant::V0_7(op) => op.extra_data_len(bundle_size),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.extra_data_len(bundle_size),
         }
     }
 
@@ -718,6 +794,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.abi_encoded_size(),
             UserOperationVariant::V0_7(op) => op.abi_encoded_size(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.abi_encoded_size(),
         }
     }
 
@@ -725,6 +803,8 @@ impl UserOperation for UserOperationVariant {
         match self {
             UserOperationVariant::V0_6(op) => op.authorization_tuple(),
             UserOperationVariant::V0_7(op) => op.authorization_tuple(),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op.authorization_tuple(),
         }
     }
 
@@ -741,6 +821,11 @@ impl UserOperation for UserOperationVariant {
                 .effective_verification_gas_limit_efficiency_reject_threshold(
                     verification_gas_limit_efficiency_reject_threshold,
                 ),
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(op) => op
+                .effective_verification_gas_limit_efficiency_reject_threshold(
+                    verification_gas_limit_efficiency_reject_threshold,
+                ),
         }
     }
 }
@@ -760,11 +845,21 @@ impl UserOperationVariant {
         }
     }
 
+    #[cfg(feature = "entrypoint-v0_8")]
+    fn into_v0_8(self) -> Option<v0_8::UserOperation> {
+        match self {
+            UserOperationVariant::V0_8(op) => Some(op),
+            _ => None,
+        }
+    }
+
     /// Returns the user operation type
     pub fn uo_type(&self) -> EntryPointVersion {
         match self {
             UserOperationVariant::V0_6(_) => EntryPointVersion::V0_6,
             UserOperationVariant::V0_7(_) => EntryPointVersion::V0_7,
+            #[cfg(feature = "entrypoint-v0_8")]
+            UserOperationVariant::V0_8(_) => EntryPointVersion::V0_8,
         }
     }
 
@@ -777,6 +872,12 @@ impl UserOperationVariant {
     pub fn is_v0_6(&self) -> bool {
         matches!(self, UserOperationVariant::V0_6(_))
     }
+
+    /// True if the UO is v0.8 type
+    #[cfg(feature = "entrypoint-v0_8")]
+    pub fn is_v0_8(&self) -> bool {
+        matches!(self, UserOperationVariant::V0_8(_))
+    }
 }
 
 /// User operation optional gas enum
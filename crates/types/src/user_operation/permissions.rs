@@ -13,6 +13,8 @@
 
 use alloy_primitives::U256;
 
+use crate::ExpectedStorage;
+
 /// User operation permissions
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct UserOperationPermissions {
@@ -26,6 +28,9 @@ pub struct UserOperationPermissions {
     pub underpriced_bundle_pct: Option<u32>,
     /// Bundler sponsorship settings
     pub bundler_sponsorship: Option<BundlerSponsorship>,
+    /// Storage values the submitter requires at inclusion, sent along with the
+    /// bundle's conditional transaction
+    pub expected_storage: Option<ExpectedStorage>,
 }
 
 /// Bundler sponsorship settings
@@ -425,6 +425,14 @@ impl UserOperation {
     pub fn paymaster_post_op_gas_limit(&self) -> u128 {
         self.paymaster_post_op_gas_limit
     }
+
+    /// Sets the entry point and hash of an operation sharing the v0.7 layout
+    #[cfg(feature = "entrypoint-v0_8")]
+    pub(super) fn with_entry_point(mut self, entry_point: Address, hash: B256) -> Self {
+        self.entry_point = entry_point;
+        self.hash = hash;
+        self
+    }
 }
 
 #[cfg(feature = "test-utils")]
@@ -0,0 +1,544 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::{address, keccak256, ruint::FromUintError, Address, Bytes, B256, U256};
+use alloy_sol_types::{sol, SolValue};
+use rundler_contracts::v0_7::PackedUserOperation;
+use serde::{Deserialize, Serialize};
+
+use super::{v0_7, UserOperation as UserOperationTrait, UserOperationId, UserOperationVariant};
+use crate::{
+    aggregator::AggregatorCosts, authorization::Eip7702Auth, chain::ChainSpec, Entity,
+    EntryPointVersion,
+};
+
+/// Factory address marking the init code of an EIP-7702 account: `0x7702` padded to 20 bytes
+///
+/// The EntryPoint hashes such an init code with the marker replaced by the
+/// account's delegate.
+pub const INIT_CODE_EIP7702_MARKER: Address = address!("7702000000000000000000000000000000000000");
+
+/// Name of the EntryPoint's EIP-712 domain
+const DOMAIN_NAME: &str = "ERC4337";
+
+/// Version of the EntryPoint's EIP-712 domain
+const DOMAIN_VERSION: &str = "1";
+
+/// EIP-712 type of the EntryPoint's domain
+const DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+
+/// EIP-712 type of the hashed operation
+const PACKED_USER_OPERATION_TYPE: &str = "PackedUserOperation(address sender,uint256 nonce,bytes initCode,bytes callData,bytes32 accountGasLimits,uint256 preVerificationGas,bytes32 gasFees,bytes paymasterAndData)";
+
+/// User Operation for Entry Point v0.8
+///
+/// v0.8 keeps the fields and packed layout of v0.7, and is built from a
+/// [`v0_7::UserOperation`] with [`UserOperation::from_fields`]. Its hash is the
+/// EIP-712 typed data hash of the packed operation under the EntryPoint's domain.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct UserOperation {
+    /// Fields of the operation, with the v0.8 entry point and hash
+    inner: v0_7::UserOperation,
+}
+
+impl UserOperationTrait for UserOperation {
+    type OptionalGas = v0_7::UserOperationOptionalGas;
+
+    fn entry_point_version() -> EntryPointVersion {
+        EntryPointVersion::V0_8
+    }
+
+    fn entry_point(&self) -> Address {
+        self.inner.entry_point()
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.inner.chain_id()
+    }
+
+    fn hash(&self) -> B256 {
+        self.inner.hash()
+    }
+
+    fn id(&self) -> UserOperationId {
+        self.inner.id()
+    }
+
+    fn sender(&self) -> Address {
+        self.inner.sender()
+    }
+
+    fn nonce(&self) -> U256 {
+        self.inner.nonce()
+    }
+
+    fn paymaster(&self) -> Option<Address> {
+        self.inner.paymaster()
+    }
+
+    fn factory(&self) -> Option<Address> {
+        self.inner.factory()
+    }
+
+    fn aggregator(&self) -> Option<Address> {
+        self.inner.aggregator()
+    }
+
+    fn call_data(&self) -> &Bytes {
+        self.inner.call_data()
+    }
+
+    fn max_gas_cost(&self) -> U256 {
+        self.inner.max_gas_cost()
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.inner.entities()
+    }
+
+    fn heap_size(&self) -> usize {
+        self.inner.heap_size()
+    }
+
+    fn max_fee_per_gas(&self) -> u128 {
+        self.inner.max_fee_per_gas()
+    }
+
+    fn max_priority_fee_per_gas(&self) -> u128 {
+        self.inner.max_priority_fee_per_gas()
+    }
+
+    fn signature(&self) -> &Bytes {
+        self.inner.signature()
+    }
+
+    fn pre_verification_gas(&self) -> u128 {
+        self.inner.pre_verification_gas()
+    }
+
+    fn call_gas_limit(&self) -> u128 {
+        self.inner.call_gas_limit()
+    }
+
+    fn verification_gas_limit(&self) -> u128 {
+        self.inner.verification_gas_limit()
+    }
+
+    fn total_verification_gas_limit(&self) -> u128 {
+        self.inner.total_verification_gas_limit()
+    }
+
+    fn paymaster_post_op_gas_limit(&self) -> u128 {
+        UserOperationTrait::paymaster_post_op_gas_limit(&self.inner)
+    }
+
+    fn static_pre_verification_gas(&self, chain_spec: &ChainSpec) -> u128 {
+        self.inner.static_pre_verification_gas(chain_spec)
+    }
+
+    fn calldata_floor_gas_limit(&self) -> u128 {
+        self.inner.calldata_floor_gas_limit()
+    }
+
+    fn required_pre_execution_buffer(&self) -> u128 {
+        self.inner.required_pre_execution_buffer()
+    }
+
+    fn aggregator_gas_limit(&self, chain_spec: &ChainSpec, bundle_size: Option<usize>) -> u128 {
+        self.inner.aggregator_gas_limit(chain_spec, bundle_size)
+    }
+
+    fn transform_for_aggregator(
+        self,
+        chain_spec: &ChainSpec,
+        aggregator: Address,
+        aggregator_costs: AggregatorCosts,
+        new_signature: Bytes,
+    ) -> Self {
+        // hash stays the same as only signature changed
+        Self {
+            inner: self.inner.transform_for_aggregator(
+                chain_spec,
+                aggregator,
+                aggregator_costs,
+                new_signature,
+            ),
+        }
+    }
+
+    fn original_signature(&self) -> &Bytes {
+        self.inner.original_signature()
+    }
+
+    fn with_original_signature(self) -> Self {
+        Self {
+            inner: self.inner.with_original_signature(),
+        }
+    }
+
+    fn extra_data_len(&self, bundle_size: usize) -> usize {
+        self.inner.extra_data_len(bundle_size)
+    }
+
+    fn abi_encoded_size(&self) -> usize {
+        self.inner.abi_encoded_size()
+    }
+
+    fn authorization_tuple(&self) -> Option<&Eip7702Auth> {
+        self.inner.authorization_tuple()
+    }
+
+    fn effective_verification_gas_limit_efficiency_reject_threshold(
+        &self,
+        verification_gas_limit_efficiency_reject_threshold: f64,
+    ) -> f64 {
+        self.inner
+            .effective_verification_gas_limit_efficiency_reject_threshold(
+                verification_gas_limit_efficiency_reject_threshold,
+            )
+    }
+}
+
+impl UserOperation {
+    /// Creates the v0.8 user operation with the fields of `uo`, sent to the
+    /// v0.8 entry point of `chain_spec`
+    pub fn from_fields(uo: v0_7::UserOperation, chain_spec: &ChainSpec) -> Self {
+        let entry_point = chain_spec.entry_point_address_v0_8;
+        let hash = hash_packed_user_operation(
+            uo.packed(),
+            uo.authorization_tuple().map(|auth| auth.address),
+            entry_point,
+            chain_spec.id,
+        );
+        Self {
+            inner: uo.with_entry_point(entry_point, hash),
+        }
+    }
+
+    /// Creates a user operation from its packed onchain representation
+    pub fn from_packed(
+        puo: PackedUserOperation,
+        chain_spec: &ChainSpec,
+    ) -> Result<Self, FromUintError<u128>> {
+        let uo = v0_7::UserOperationBuilder::from_packed(puo, chain_spec)?.build();
+        Ok(Self::from_fields(uo, chain_spec))
+    }
+
+    /// Returns the fields of the user operation
+    ///
+    /// They carry the v0.8 entry point and hash, to be modified with a
+    /// [`v0_7::UserOperationBuilder`] and rebuilt with [`UserOperation::from_fields`].
+    pub fn fields(&self) -> &v0_7::UserOperation {
+        &self.inner
+    }
+
+    /// Converts the user operation into its fields
+    pub fn into_fields(self) -> v0_7::UserOperation {
+        self.inner
+    }
+
+    /// Packs the user operation to its offchain representation
+    pub fn pack(self) -> PackedUserOperation {
+        self.inner.pack()
+    }
+
+    /// Returns a reference to the packed user operation
+    pub fn packed(&self) -> &PackedUserOperation {
+        self.inner.packed()
+    }
+
+    /// Get the paymaster data
+    pub fn paymaster_data(&self) -> &Bytes {
+        self.inner.paymaster_data()
+    }
+
+    /// Get the factory data
+    pub fn factory_data(&self) -> &Bytes {
+        self.inner.factory_data()
+    }
+
+    /// Get the paymaster verification gas limit
+    pub fn paymaster_verification_gas_limit(&self) -> u128 {
+        self.inner.paymaster_verification_gas_limit()
+    }
+
+    /// Get the paymaster post-op gas limit
+    pub fn paymaster_post_op_gas_limit(&self) -> u128 {
+        self.inner.paymaster_post_op_gas_limit()
+    }
+}
+
+#[cfg(feature = "test-utils")]
+impl Default for UserOperation {
+    fn default() -> Self {
+        Self::from_fields(v0_7::UserOperation::default(), &ChainSpec::default())
+    }
+}
+
+impl From<UserOperationVariant> for UserOperation {
+    /// Converts a UserOperationVariant to a UserOperation 0.8
+    ///
+    /// # Panics
+    ///
+    /// Panics if the variant is not v0.8. This is for use in contexts
+    /// where the variant is known to be v0.8.
+    fn from(value: UserOperationVariant) -> Self {
+        value.into_v0_8().expect("Expected UserOperationV0_8")
+    }
+}
+
+impl From<UserOperation> for super::UserOperationVariant {
+    fn from(op: UserOperation) -> Self {
+        super::UserOperationVariant::V0_8(op)
+    }
+}
+
+impl AsRef<UserOperation> for super::UserOperationVariant {
+    /// # Panics
+    ///
+    /// Panics if the variant is not v0.8. This is for use in contexts
+    /// where the variant is known to be v0.8.
+    fn as_ref(&self) -> &UserOperation {
+        match self {
+            super::UserOperationVariant::V0_8(op) => op,
+            _ => panic!("Expected UserOperationV0_8"),
+        }
+    }
+}
+
+impl AsMut<UserOperation> for super::UserOperationVariant {
+    /// # Panics
+    ///
+    /// Panics if the variant is not v0.8. This is for use in contexts
+    /// where the variant is known to be v0.8.
+    fn as_mut(&mut self) -> &mut UserOperation {
+        match self {
+            super::UserOperationVariant::V0_8(op) => op,
+            _ => panic!("Expected UserOperationV0_8"),
+        }
+    }
+}
+
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Default, Debug, PartialEq, Eq)]
+    struct DomainEncoded {
+        bytes32 typeHash;
+        bytes32 nameHash;
+        bytes32 versionHash;
+        uint256 chainId;
+        address verifyingContract;
+    }
+
+    #[allow(missing_docs)]
+    #[derive(Default, Debug, PartialEq, Eq)]
+    struct UserOperationTypedForHash {
+        bytes32 typeHash;
+        address sender;
+        uint256 nonce;
+        bytes32 hashInitCode;
+        bytes32 hashCallData;
+        bytes32 accountGasLimits;
+        uint256 preVerificationGas;
+        bytes32 gasFees;
+        bytes32 hashPaymasterAndData;
+    }
+}
+
+/// EntryPoint v0.8 `getUserOpHash`
+///
+/// An init code starting with [`INIT_CODE_EIP7702_MARKER`] is hashed with the
+/// marker replaced by `eip7702_delegate`, the address the sender delegates to.
+fn hash_packed_user_operation(
+    puo: &PackedUserOperation,
+    eip7702_delegate: Option<Address>,
+    entry_point: Address,
+    chain_id: u64,
+) -> B256 {
+    let marker = INIT_CODE_EIP7702_MARKER.as_slice();
+    let hash_init_code = match eip7702_delegate {
+        Some(delegate) if puo.initCode.starts_with(marker) => {
+            let mut init_code = delegate.to_vec();
+            init_code.extend_from_slice(&puo.initCode[marker.len()..]);
+            keccak256(init_code)
+        }
+        _ => keccak256(&puo.initCode),
+    };
+
+    let struct_hash = keccak256(
+        UserOperationTypedForHash {
+            typeHash: keccak256(PACKED_USER_OPERATION_TYPE),
+            sender: puo.sender,
+            nonce: puo.nonce,
+            hashInitCode: hash_init_code,
+            hashCallData: keccak256(&puo.callData),
+            accountGasLimits: puo.accountGasLimits,
+            preVerificationGas: puo.preVerificationGas,
+            gasFees: puo.gasFees,
+            hashPaymasterAndData: keccak256(&puo.paymasterAndData),
+        }
+        .abi_encode(),
+    );
+
+    let domain_separator = keccak256(
+        DomainEncoded {
+            typeHash: keccak256(DOMAIN_TYPE),
+            nameHash: keccak256(DOMAIN_NAME),
+            versionHash: keccak256(DOMAIN_VERSION),
+            chainId: U256::from(chain_id),
+            verifyingContract: entry_point,
+        }
+        .abi_encode(),
+    );
+
+    let mut typed_data = Vec::with_capacity(66);
+    typed_data.extend_from_slice(&[0x19, 0x01]);
+    typed_data.extend_from_slice(domain_separator.as_slice());
+    typed_data.extend_from_slice(struct_hash.as_slice());
+    keccak256(typed_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{b256, bytes, uint};
+
+    use super::*;
+
+    fn required_fields() -> v0_7::UserOperationRequiredFields {
+        v0_7::UserOperationRequiredFields {
+            sender: address!("b292Cf4a8E1fF21Ac27C4f94071Cd02C022C414b"),
+            nonce: U256::from(1),
+            call_data: Bytes::new(),
+            call_gas_limit: 100_000,
+            verification_gas_limit: 200_000,
+            pre_verification_gas: 50_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+            max_fee_per_gas: 2_000_000_000,
+            signature: Bytes::new(),
+        }
+    }
+
+    fn chain_spec(id: u64) -> ChainSpec {
+        ChainSpec {
+            id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_hash() {
+        // The v0.7 sepolia operation of the v0.7 hash test, sent to the v0.8 entry point
+        let cs = chain_spec(11155111);
+        let puo = PackedUserOperation {
+            sender: address!("b292Cf4a8E1fF21Ac27C4f94071Cd02C022C414b"),
+            nonce: uint!(0xF83D07238A7C8814A48535035602123AD6DBFA63000000000000000000000001_U256),
+            initCode: Bytes::default(),
+            callData: bytes!("e9ae5c530000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001d8b292cf4a8e1ff21ac27c4f94071cd02c022c414b00000000000000000000000000000000000000000000000000000000000000009517e29f0000000000000000000000000000000000000000000000000000000000000002000000000000000000000000ad6330089d9a1fe89f4020292e1afe9969a5a2fc00000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000120000000000000000000000000000000000000000000000000000000000001518000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000018e2fbe8980000000000000000000000000000000000000000000000000000000000000008000000000000000000000000000000000000000000000000000000000000000800000000000000000000000002372912728f93ab3daaaebea4f87e6e28476d987000000000000000000000000000000000000000000000000002386f26fc10000000000000000000000000000000000000000000000000000000000000000006000000000000000000000000000000000000000000000000000000000000000000000000000000000"),
+            accountGasLimits: b256!("000000000000000000000000000114fc0000000000000000000000000012c9b5"),
+            preVerificationGas: U256::from(48916),
+            gasFees: b256!("000000000000000000000000524121000000000000000000000000109a4a441a"),
+            paymasterAndData: Bytes::default(),
+            signature: bytes!("3c7bfe22c9c2ef8994a9637bcc4df1741c5dc0c25b209545a7aeb20f7770f351479b683bd17c4d55bc32e2a649c8d2dff49dcfcc1f3fd837bcd88d1e69a434cf1c"),
+        };
+
+        let uo = UserOperation::from_packed(puo, &cs).unwrap();
+        assert_eq!(
+            uo.hash(),
+            b256!("b2fbe81199f6292d3f88bacec9c5587681da4e632f5b3dead0f1741a7b65cb94")
+        );
+        assert_eq!(uo.entry_point(), cs.entry_point_address_v0_8);
+    }
+
+    #[test]
+    fn test_hash_with_paymaster() {
+        let cs = chain_spec(1);
+        let fields = v0_7::UserOperationBuilder::new(
+            &cs,
+            v0_7::UserOperationRequiredFields {
+                call_data: bytes!("deadbeef"),
+                ..required_fields()
+            },
+        )
+        .paymaster(
+            address!("1111111111111111111111111111111111111111"),
+            100_000,
+            50_000,
+            bytes!("1234"),
+        )
+        .build();
+        let v0_7_hash = fields.hash();
+
+        let uo = UserOperation::from_fields(fields, &cs);
+        assert_eq!(
+            uo.hash(),
+            b256!("f522fb733ff354717e7817c5cfd6870e9306ac52fba3a3f2897187b2c2f9f2ae")
+        );
+        assert_ne!(uo.hash(), v0_7_hash);
+    }
+
+    #[test]
+    fn test_hash_eip7702_init_code() {
+        let cs = chain_spec(1);
+        let delegate = address!("4Cd241E8d1510e30b2076397afc7508Ae59C66c9");
+        let build = |factory_data: Bytes| {
+            let fields = v0_7::UserOperationBuilder::new(&cs, required_fields())
+                .factory(INIT_CODE_EIP7702_MARKER, factory_data)
+                .authorization_tuple(Eip7702Auth {
+                    chain_id: 1,
+                    address: delegate,
+                    ..Default::default()
+                })
+                .build();
+            UserOperation::from_fields(fields, &cs)
+        };
+
+        assert_eq!(
+            build(Bytes::new()).hash(),
+            b256!("c034db0a00e47fad36a84ffb13a5e5fc1a54432b69943ce60be53bc97ae92f79")
+        );
+        assert_eq!(
+            build(bytes!("abcd")).hash(),
+            b256!("d37f9fff99f13a0643e04914c710cfa5396670899fbe056b88d69ce574052a03")
+        );
+    }
+
+    #[test]
+    fn test_pack_unpack() {
+        let cs = ChainSpec::default();
+        let fields = v0_7::UserOperationBuilder::new(&cs, required_fields())
+            .factory(Address::random(), bytes!("deadbeef"))
+            .paymaster(Address::random(), 10, 20, bytes!("1234"))
+            .build();
+
+        let uo = UserOperation::from_fields(fields, &cs);
+        let unpacked = UserOperation::from_packed(uo.clone().pack(), &cs).unwrap();
+        assert_eq!(uo, unpacked);
+    }
+
+    #[test]
+    fn test_variant() {
+        let cs = ChainSpec::default();
+        let fields = v0_7::UserOperationBuilder::new(&cs, required_fields()).build();
+        let uo: UserOperationVariant = UserOperation::from_fields(fields, &cs).into();
+
+        assert!(uo.is_v0_8());
+        assert_eq!(uo.uo_type(), EntryPointVersion::V0_8);
+        assert_eq!(uo.entry_point(), cs.entry_point_address_v0_8);
+
+        // The stored form tells v0.8 apart from v0.7, which has the same fields
+        let json = serde_json::to_value(AsRef::<UserOperation>::as_ref(&uo)).unwrap();
+        let parsed: UserOperationVariant = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, uo);
+    }
+}
@@ -288,7 +288,7 @@ create_default_config() {
 # SuperRelay i.MX 93 Production Configuration
 [node]
 http_api = "0.0.0.0:3000"
-network = "mainnet"
+network = "ethereum"
 node_http = "${ETH_NODE_URL}"
 
 [paymaster_relay]
@@ -189,7 +189,7 @@ create_sepolia_env() {
 # Network Configuration
 RPC_URL=$SEPOLIA_RPC_URL
 CHAIN_ID=$SEPOLIA_CHAIN_ID
-NETWORK=sepolia
+NETWORK=ethereum_sepolia
 
 # EntryPoint Addresses
 ENTRY_POINT_V06_ADDRESS=$ENTRYPOINT_V06_ADDRESS
@@ -35,7 +35,7 @@ check_required_env() {
             echo "   export SIGNER_PRIVATE_KEYS=0x..."
             echo "   export PAYMASTER_PRIVATE_KEY=0x..."
             echo "   export RPC_URL=https://..."
-            echo "   export NETWORK=mainnet|sepolia|polygon"
+            echo "   export NETWORK=ethereum|ethereum_sepolia|polygon"
             echo ""
             echo "🔒 安全提醒:"
             echo "   • 使用环境变量而非配置文件存储私钥"
@@ -167,7 +167,7 @@ PAYMASTER_PRIVATE_KEY=0x...
 
 # 🌐 网络配置 (必需)
 RPC_URL=https://eth-mainnet.alchemyapi.io/v2/YOUR_KEY
-NETWORK=mainnet
+NETWORK=ethereum
 CHAIN_ID=1
 
 # 🏭 服务配置